@@ -69,6 +69,8 @@ async fn demo_standalone_agent() -> Result<(), Box<dyn std::error::Error>> {
         security_mode: SecurityMode::AuthCrypt,
         sender_kid: Some(alice_kid),
         recipient_kid: Some(bob_kid),
+        content_encryption: None,
+        compression: None,
     };
     
     println!("🔐 Alice encrypting message for Bob...");
@@ -142,6 +144,8 @@ async fn demo_node_integration() -> Result<(), Box<dyn std::error::Error>> {
         security_mode: SecurityMode::AuthCrypt,
         sender_kid: Some(sender_kid),
         recipient_kid: Some(bob_kid),
+        content_encryption: None,
+        compression: None,
     };
     
     let encrypted_message = message.pack(&*sender_agent.key_manager(), pack_options).await?;
@@ -228,6 +232,8 @@ async fn demo_message_routing() -> Result<(), Box<dyn std::error::Error>> {
         security_mode: SecurityMode::AuthCrypt,
         sender_kid: Some(signer_kid),
         recipient_kid: Some(agent2_kid),
+        content_encryption: None,
+        compression: None,
     };
     
     let encrypted = secret_message.pack(&*signer_agent.key_manager(), pack_options).await?;
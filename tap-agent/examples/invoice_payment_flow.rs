@@ -189,6 +189,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             transaction_id: transaction_id.clone(),
             settlement_id: Some(settlement_id.to_string()),
             amount: Some(payment.amount.clone()),
+            settled_asset: None,
         };
 
         let (packed_settle, _delivery_results) = customer_agent
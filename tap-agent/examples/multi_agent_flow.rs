@@ -232,12 +232,14 @@ fn main() -> Result<()> {
             received_add_agents.agents[0].id,
             received_add_agents.agents[0]
                 .role
-                .as_deref()
+                .as_ref()
+                .map(|r| r.as_str())
                 .unwrap_or("unknown"),
             received_add_agents.agents[1].id,
             received_add_agents.agents[1]
                 .role
-                .as_deref()
+                .as_ref()
+                .map(|r| r.as_str())
                 .unwrap_or("unknown")
         );
 
@@ -248,6 +250,7 @@ fn main() -> Result<()> {
 
         let reject = Reject {
         transaction_id: transfer_id.to_string(),
+        code: None,
         reason: Some("compliance.policy: Additional beneficiary information required. Please provide additional beneficiary information to comply with regulations".to_string()),
     };
 
@@ -352,6 +355,7 @@ fn main() -> Result<()> {
             transaction_id: transfer_id.to_string(),
             settlement_id: Some(settlement_id.to_string()),
             amount: Some(transfer.amount.clone()),
+            settled_asset: None,
         };
 
         // Send settlement to all relevant parties
@@ -375,6 +379,7 @@ fn main() -> Result<()> {
             transaction_id: transfer_id.to_string(),
             settlement_id: Some(api_settlement_id.to_string()),
             amount: Some(transfer.amount.clone()),
+            settled_asset: None,
         };
 
         let (packed_api_settle, _delivery_results) = originator_wallet_api
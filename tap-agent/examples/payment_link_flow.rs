@@ -252,6 +252,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         settlement_id: Some(
             "0x1234567890abcdef1234567890abcdef12345678901234567890abcdef123456".to_string(),
         ),
+        settled_asset: None,
     };
 
     let (packed_settle, _delivery_results) = customer_agent
@@ -125,6 +125,7 @@ fn main() -> Result<()> {
                 if let Error::Validation(_) = e {
                     let reject = Reject {
                     transaction_id: transfer_id.clone(),
+                    code: None,
                     reason: Some(format!("validation.failed: Transfer validation failed: {}. Please correct the validation issues and try again", e)),
                 };
 
@@ -161,6 +162,7 @@ fn main() -> Result<()> {
 
             let reject = Reject {
             transaction_id: transfer_id.clone(),
+            code: None,
             reason: Some(format!("risk.threshold.exceeded: Risk score ({}) exceeds threshold ({}). Please contact support for further assistance", risk_score, risk_threshold)),
         };
 
@@ -255,6 +257,7 @@ fn main() -> Result<()> {
             transaction_id: transfer_id.clone(),
             settlement_id: Some(settlement_id.to_string()),
             amount: Some(transfer.amount.clone()),
+            settled_asset: None,
         };
 
         let (packed_settle, _delivery_results) = match originator_agent
@@ -113,6 +113,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             transaction_id: transfer_id.clone(),
             settlement_id: Some(settlement_id.to_string()),
             amount: Some(transfer.amount.clone()),
+            settled_asset: None,
         };
 
         let (packed_settle, _delivery_results) = originator_agent
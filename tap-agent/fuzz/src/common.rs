@@ -0,0 +1,46 @@
+//! Shared setup for the envelope fuzz targets
+
+#![allow(dead_code)] // not every target uses every helper
+
+use std::sync::{Arc, OnceLock};
+use tap_agent::{AgentKeyManager, AgentKeyManagerBuilder, DIDGenerationOptions, KeyManager, KeyType};
+use tokio::runtime::Runtime;
+
+/// Key manager holding the recipient key, and that key's ID
+pub struct Recipient {
+    pub key_manager: Arc<AgentKeyManager>,
+    pub kid: String,
+}
+
+/// Recipient shared by all iterations, generated on first use
+pub fn recipient() -> &'static Recipient {
+    static RECIPIENT: OnceLock<Recipient> = OnceLock::new();
+    RECIPIENT.get_or_init(|| {
+        let key_manager = Arc::new(AgentKeyManagerBuilder::new().build().unwrap());
+        let key = key_manager
+            .generate_key(DIDGenerationOptions {
+                key_type: KeyType::Ed25519,
+            })
+            .unwrap();
+        Recipient {
+            key_manager,
+            kid: key.did_doc.verification_method[0].id.clone(),
+        }
+    })
+}
+
+/// Single-threaded runtime for driving the async unpack API
+pub fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+    })
+}
+
+/// Base64url without padding, as used in JOSE
+pub fn base64url(bytes: &[u8]) -> String {
+    use base64::Engine;
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
@@ -0,0 +1,101 @@
+#![no_main]
+
+//! Builds a JWE addressed to a key the fuzzer's key manager holds, so fuzzed
+//! protected headers, ephemeral keys, wrapped keys, IVs, tags and ciphertexts
+//! reach key agreement, key unwrapping and content decryption rather than
+//! failing recipient lookup.
+
+mod common;
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use serde_json::json;
+use tap_agent::{Jwe, JweHeader, JweRecipient, PlainMessage, UnpackOptions, Unpackable};
+
+const ALGORITHMS: &[&str] = &["ECDH-ES+A256KW", "ECDH-1PU+A256KW", "ECDH-ES", "RSA-OAEP"];
+const ENCRYPTIONS: &[&str] = &["A256GCM", "A256CBC-HS512", "A128GCM"];
+const CURVES: &[(&str, &str)] = &[
+    ("OKP", "X25519"),
+    ("OKP", "Ed25519"),
+    ("EC", "P-256"),
+    ("EC", "secp256k1"),
+];
+
+#[derive(Arbitrary, Debug)]
+struct FuzzJwe {
+    alg_index: u8,
+    enc_index: u8,
+    curve_index: u8,
+    epk_x: Vec<u8>,
+    epk_y: Vec<u8>,
+    apu: Vec<u8>,
+    apv: Vec<u8>,
+    skid: Option<String>,
+    zip: Option<String>,
+    raw_protected: Option<Vec<u8>>,
+    use_recipient_kid: bool,
+    kid: String,
+    encrypted_key: Vec<u8>,
+    iv: Vec<u8>,
+    tag: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+fuzz_target!(|data: FuzzJwe| {
+    let recipient = common::recipient();
+    let (kty, crv) = CURVES[data.curve_index as usize % CURVES.len()];
+
+    let protected = match data.raw_protected {
+        Some(raw) => raw,
+        None => {
+            let mut epk = json!({
+                "kty": kty,
+                "crv": crv,
+                "x": common::base64url(&data.epk_x),
+            });
+            if kty == "EC" {
+                epk["y"] = common::base64url(&data.epk_y).into();
+            }
+            json!({
+                "epk": epk,
+                "apu": common::base64url(&data.apu),
+                "apv": common::base64url(&data.apv),
+                "skid": data.skid,
+                "zip": data.zip,
+                "typ": "application/didcomm-encrypted+json",
+                "enc": ENCRYPTIONS[data.enc_index as usize % ENCRYPTIONS.len()],
+                "alg": ALGORITHMS[data.alg_index as usize % ALGORITHMS.len()],
+            })
+            .to_string()
+            .into_bytes()
+        }
+    };
+
+    let kid = if data.use_recipient_kid {
+        recipient.kid.clone()
+    } else {
+        data.kid
+    };
+
+    let jwe = Jwe {
+        ciphertext: common::base64url(&data.ciphertext),
+        protected: common::base64url(&protected),
+        recipients: vec![JweRecipient {
+            encrypted_key: common::base64url(&data.encrypted_key),
+            header: JweHeader {
+                kid,
+                sender_kid: None,
+            },
+        }],
+        tag: common::base64url(&data.tag),
+        iv: common::base64url(&data.iv),
+    };
+
+    let _ = jwe.get_protected_header();
+    let _ = jwe.authcrypt_sender_kid();
+
+    common::runtime().block_on(async {
+        let _: Result<PlainMessage, _> =
+            Jwe::unpack(&jwe, &*recipient.key_manager, UnpackOptions::new()).await;
+    });
+});
@@ -0,0 +1,62 @@
+#![no_main]
+
+//! Builds a JWS whose protected header names a key the verifier can resolve,
+//! so fuzzed headers, payloads and signatures reach signature verification
+//! and payload decompression rather than failing key lookup.
+
+mod common;
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use serde_json::json;
+use tap_agent::{Jws, JwsSignature, PlainMessage, UnpackOptions, Unpackable};
+
+#[derive(Arbitrary, Debug)]
+struct FuzzJws {
+    alg: String,
+    zip: Option<String>,
+    use_recipient_kid: bool,
+    kid: String,
+    raw_protected: Option<Vec<u8>>,
+    payload: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+fuzz_target!(|data: FuzzJws| {
+    let recipient = common::recipient();
+    let kid = if data.use_recipient_kid {
+        recipient.kid.clone()
+    } else {
+        data.kid
+    };
+
+    let protected = match data.raw_protected {
+        Some(raw) => raw,
+        None => json!({
+            "typ": "application/didcomm-signed+json",
+            "alg": data.alg,
+            "kid": kid,
+            "zip": data.zip,
+        })
+        .to_string()
+        .into_bytes(),
+    };
+
+    let jws = Jws {
+        payload: common::base64url(&data.payload),
+        signatures: vec![JwsSignature {
+            protected: common::base64url(&protected),
+            signature: common::base64url(&data.signature),
+        }],
+    };
+
+    common::runtime().block_on(async {
+        let _: Result<PlainMessage, _> =
+            Jws::unpack(&jws, &*recipient.key_manager, UnpackOptions::new()).await;
+    });
+
+    // Whatever was built must survive a serialization round trip
+    if let Ok(serialized) = serde_json::to_string(&jws) {
+        let _ = serde_json::from_str::<Jws>(&serialized);
+    }
+});
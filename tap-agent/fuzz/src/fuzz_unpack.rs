@@ -0,0 +1,31 @@
+#![no_main]
+
+//! Feeds raw bytes to the generic unpack entry point, which detects whether
+//! the input is a JWS, JWE or PlainMessage. Any panic is a bug; malformed
+//! input must surface as a `tap_agent::Error`.
+//!
+//! Seed with the negative corpus:
+//!
+//! ```sh
+//! cargo fuzz run fuzz_unpack corpus/fuzz_unpack ../tests/corpus/envelopes
+//! ```
+
+mod common;
+
+use libfuzzer_sys::fuzz_target;
+use tap_agent::{PlainMessage, UnpackOptions, Unpackable, UnpackedMessage};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    let input = input.to_string();
+    let recipient = common::recipient();
+
+    common::runtime().block_on(async {
+        let _: Result<PlainMessage, _> =
+            String::unpack(&input, &*recipient.key_manager, UnpackOptions::new()).await;
+        let _: Result<UnpackedMessage, _> =
+            String::unpack(&input, &*recipient.key_manager, UnpackOptions::new()).await;
+    });
+});
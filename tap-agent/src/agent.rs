@@ -1,5 +1,8 @@
 use crate::agent_key_manager::{AgentKeyManager, AgentKeyManagerBuilder};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::compression::CompressionCodec;
 use crate::config::AgentConfig;
+use crate::did::KeyPurpose;
 #[cfg(all(not(target_arch = "wasm32"), test))]
 use crate::did::SyncDIDResolver; // Import SyncDIDResolver trait
 use crate::error::{Error, Result};
@@ -463,19 +466,19 @@ impl TapAgent {
             // Create a default resolver
             let resolver = Arc::new(crate::did::MultiResolver::default());
             let agent = Self::new_with_resolver(config, Arc::new(key_manager), resolver);
-            Ok((agent, key.did))
+            Ok((agent, key.did.clone()))
         }
 
         #[cfg(all(not(target_arch = "wasm32"), not(test)))]
         {
             let agent = Self::new(config, Arc::new(key_manager));
-            Ok((agent, key.did))
+            Ok((agent, key.did.clone()))
         }
 
         #[cfg(target_arch = "wasm32")]
         {
             let agent = Self::new(config, Arc::new(key_manager));
-            Ok((agent, key.did))
+            Ok((agent, key.did.clone()))
         }
     }
 
@@ -492,10 +495,25 @@ impl TapAgent {
     ///
     /// A Result containing either the created agent or an error if no keys are available
     pub async fn from_stored_keys(did: Option<String>, debug: bool) -> Result<Self> {
+        Self::from_stored_keys_with_path(did, None, debug).await
+    }
+
+    /// Creates a new TapAgent from keys stored at a custom path
+    ///
+    /// Like [`TapAgent::from_stored_keys`], but reads the key storage file at
+    /// `storage_path` (None uses the default location).
+    pub async fn from_stored_keys_with_path(
+        did: Option<String>,
+        storage_path: Option<PathBuf>,
+        debug: bool,
+    ) -> Result<Self> {
         use crate::storage::KeyStorage;
 
         // Load keys from storage
-        let key_manager_builder = AgentKeyManagerBuilder::new().load_from_default_storage();
+        let key_manager_builder = match &storage_path {
+            Some(path) => AgentKeyManagerBuilder::new().load_from_path(path.clone()),
+            None => AgentKeyManagerBuilder::new().load_from_default_storage(),
+        };
         let key_manager = key_manager_builder.build()?;
 
         // Get the DIDs available in the key manager
@@ -517,7 +535,10 @@ impl TapAgent {
             specified_did
         } else {
             // Try to get the default DID from storage
-            let storage = KeyStorage::load_default()?;
+            let storage = match &storage_path {
+                Some(path) => KeyStorage::load_from_path(path)?,
+                None => KeyStorage::load_default()?,
+            };
             storage.default_did.unwrap_or_else(|| dids[0].clone())
         };
 
@@ -883,19 +904,19 @@ impl TapAgent {
             // Create a default resolver
             let resolver = Arc::new(crate::did::MultiResolver::default());
             let agent = Self::new_with_resolver(config, Arc::new(key_manager), resolver);
-            Ok((agent, generated_key.did))
+            Ok((agent, generated_key.did.clone()))
         }
 
         #[cfg(all(not(target_arch = "wasm32"), not(test)))]
         {
             let agent = Self::new(config, Arc::new(key_manager));
-            Ok((agent, generated_key.did))
+            Ok((agent, generated_key.did.clone()))
         }
 
         #[cfg(target_arch = "wasm32")]
         {
             let agent = Self::new(config, Arc::new(key_manager));
-            Ok((agent, generated_key.did))
+            Ok((agent, generated_key.did.clone()))
         }
     }
 
@@ -937,6 +958,69 @@ impl TapAgent {
     ///
     /// Resolves the DID document and returns the first authentication verification method ID
     pub async fn get_signing_kid(&self) -> Result<String> {
+        self.get_kid_for_purpose(KeyPurpose::Authentication).await
+    }
+
+    /// Issue a key attestation binding an agent to this agent's organization
+    ///
+    /// This agent signs as the organization, with its attestation key. The
+    /// returned JWS is what the attested agent is configured with through
+    /// [`AgentConfig::with_key_attestation`]; see [`crate::attestation`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn attest_key(
+        &self,
+        agent_did: &str,
+        kid: Option<&str>,
+        expires_at: Option<i64>,
+    ) -> Result<Value> {
+        let signing_kid = self.get_kid_for_purpose(KeyPurpose::Attestation).await?;
+        let claims = crate::attestation::key_attestation_claims(
+            self.get_agent_did(),
+            agent_did,
+            kid,
+            expires_at,
+        );
+        let protected = crate::message::JwsProtected {
+            typ: "JWT".to_string(),
+            alg: String::new(),
+            kid: signing_kid.clone(),
+            zip: None,
+        };
+        let payload = serde_json::to_vec(&claims)
+            .map_err(|e| Error::Serialization(format!("Failed to serialize claims: {}", e)))?;
+        let jws = self
+            .key_manager
+            .sign_jws(&signing_kid, &payload, Some(protected))
+            .await?;
+        serde_json::from_str(&jws)
+            .map_err(|e| Error::Serialization(format!("Failed to parse signed attestation: {}", e)))
+    }
+
+    /// Get the ID of the verification method this agent uses for a purpose
+    ///
+    /// A key ID configured for the purpose in [`AgentConfig::key_ids`] comes
+    /// first, then the first method the agent's DID document lists for it.
+    /// Without either, attestations are signed and messages encrypted with
+    /// the authentication key.
+    pub async fn get_kid_for_purpose(&self, purpose: KeyPurpose) -> Result<String> {
+        if let Some(kid) = self.config.key_ids.get(&purpose) {
+            return Ok(kid.clone());
+        }
+        if purpose == KeyPurpose::Attestation {
+            if let Ok(agent_key) = self.key_manager.get_generated_key(&self.config.agent_did) {
+                if let Some(kid) = agent_key.did_doc.verification_methods_for(purpose).first() {
+                    return Ok(kid.clone());
+                }
+            }
+        }
+        self.default_signing_kid()
+    }
+
+    /// The authentication key ID from the DID document, or guessed from the DID
+    fn default_signing_kid(&self) -> Result<String> {
+        if let Some(kid) = self.config.key_ids.get(&KeyPurpose::Authentication) {
+            return Ok(kid.clone());
+        }
         let did = &self.config.agent_did;
 
         // Try to get the DID document from our key manager first
@@ -1022,6 +1106,172 @@ impl TapAgent {
         }
     }
 
+    /// Negotiate payload compression with a recipient
+    ///
+    /// Picks the first codec from the agent configuration that the recipient
+    /// advertises in its DID document. Returns `None` when compression is not
+    /// configured or the recipient's DID document cannot be resolved.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn negotiate_compression(&self, recipient_did: &str) -> Option<CompressionCodec> {
+        if self.config.compression.is_empty() || !recipient_did.starts_with("did:") {
+            return None;
+        }
+
+        #[cfg(test)]
+        if let Some(resolver) = &self.resolver {
+            let did_doc = resolver.resolve(recipient_did).await.ok()??;
+            return crate::compression::negotiate(
+                &self.config.compression,
+                &crate::compression::advertised_codecs(&did_doc),
+            );
+        }
+
+        let resolver = crate::did::MultiResolver::default();
+        let did_doc = crate::did::SyncDIDResolver::resolve(&resolver, recipient_did)
+            .await
+            .ok()??;
+        crate::compression::negotiate(
+            &self.config.compression,
+            &crate::compression::advertised_codecs(&did_doc),
+        )
+    }
+
+    /// Preferred locales of a counterparty, most preferred first
+    ///
+    /// A locale override in the agent configuration wins; otherwise the
+    /// locales advertised in the counterparty's DID document are used. Returns
+    /// an empty list when neither is available.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn counterparty_locales(&self, recipient_did: &str) -> Vec<String> {
+        if let Some(locales) = self.config.counterparty_locales.get(recipient_did) {
+            return locales.clone();
+        }
+        if !recipient_did.starts_with("did:") {
+            return Vec::new();
+        }
+
+        #[cfg(test)]
+        if let Some(resolver) = &self.resolver {
+            return match resolver.resolve(recipient_did).await {
+                Ok(Some(did_doc)) => crate::localization::advertised_locales(&did_doc),
+                _ => Vec::new(),
+            };
+        }
+
+        let resolver = crate::did::MultiResolver::default();
+        match crate::did::SyncDIDResolver::resolve(&resolver, recipient_did).await {
+            Ok(Some(did_doc)) => crate::localization::advertised_locales(&did_doc),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Localize the human-readable fields of an outgoing message for a counterparty
+    ///
+    /// Applies the configured message catalog in the counterparty's preferred
+    /// locale and sets the `lang` header when any field was translated. Does
+    /// nothing when no catalog is configured.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn localize_message(&self, message: &mut PlainMessage, recipient_did: &str) {
+        let catalog = match &self.config.message_catalog {
+            Some(catalog) if !catalog.is_empty() => catalog,
+            _ => return,
+        };
+
+        let locales = self.counterparty_locales(recipient_did).await;
+        if let Some(locale) = catalog.localize_body(&mut message.body, &locales) {
+            debug!(
+                "Localized message {} for {} into {}",
+                message.id, recipient_did, locale
+            );
+            message.extra_headers.insert(
+                crate::localization::LANG_HEADER.to_string(),
+                Value::String(locale),
+            );
+        }
+    }
+
+    /// Advertise our key agreement key for the thread of an outgoing message
+    ///
+    /// Sets the `thread_key` header when thread keys are enabled. A thread
+    /// key that cannot be generated is logged and left out.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn advertise_thread_key(&self, message: &mut PlainMessage) {
+        if !self.config.thread_keys {
+            return;
+        }
+        let thread_id = crate::thread_keys::thread_id(message).to_string();
+        match self
+            .key_manager
+            .thread_key(self.get_agent_did(), &thread_id)
+        {
+            Ok(public_jwk) => {
+                message.extra_headers.insert(
+                    crate::thread_keys::THREAD_KEY_HEADER.to_string(),
+                    public_jwk,
+                );
+            }
+            Err(e) => warn!("Sending message {} without a thread key: {}", message.id, e),
+        }
+    }
+
+    /// Attach the configured key attestation to an outgoing message
+    #[cfg(not(target_arch = "wasm32"))]
+    fn attach_key_attestation(&self, message: &mut PlainMessage) {
+        if let Some(attestation) = &self.config.key_attestation {
+            message.extra_headers.insert(
+                crate::attestation::KEY_ATTESTATION_HEADER.to_string(),
+                attestation.clone(),
+            );
+        }
+    }
+
+    /// Record the thread key a counterparty advertised in a received message
+    ///
+    /// The key is only taken from messages authenticated by the sender, as
+    /// `authenticated_kids` (the key IDs that signed or authcrypted the
+    /// message) shows. An invalid key is logged and ignored.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn accept_thread_key(&self, message: &PlainMessage, authenticated_kids: &[String]) {
+        if !self.config.thread_keys
+            || !authenticated_kids
+                .iter()
+                .any(|kid| kid.split('#').next() == Some(message.from.as_str()))
+        {
+            return;
+        }
+        let key = match crate::thread_keys::advertised_thread_key(message) {
+            Ok(Some(key)) => key,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("Ignoring thread key of message {}: {}", message.id, e);
+                return;
+            }
+        };
+        let thread_id = crate::thread_keys::thread_id(message);
+        if let Err(e) = self
+            .key_manager
+            .add_peer_thread_key(thread_id, &message.from, key)
+        {
+            warn!("Failed to record thread key of {}: {}", message.from, e);
+        }
+    }
+
+    /// Key ID to decrypt a JWE with: one of our thread keys it is addressed
+    /// to, or else our key agreement key
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn decryption_kid(&self, jwe: &crate::message::Jwe) -> Option<String> {
+        if let Some(recipient) = jwe
+            .recipients
+            .iter()
+            .find(|recipient| self.key_manager.is_own_thread_key(&recipient.header.kid))
+        {
+            return Some(recipient.header.kid.clone());
+        }
+        self.get_kid_for_purpose(KeyPurpose::KeyAgreement)
+            .await
+            .ok()
+    }
+
     /// Send a message to a specific endpoint
     ///
     /// # Parameters
@@ -1311,9 +1561,16 @@ impl crate::agent::Agent for TapAgent {
         debug!("Recipients: {:?}", to);
 
         // Convert the TapMessageBody to a PlainMessage with explicit routing
-        let plain_message =
+        let mut plain_message =
             message.to_didcomm_with_route(self.get_agent_did(), to.iter().copied())?;
 
+        // Human-readable fields are localized per recipient, so only a single recipient qualifies
+        if to.len() == 1 {
+            self.localize_message(&mut plain_message, to[0]).await;
+        }
+        self.advertise_thread_key(&mut plain_message);
+        self.attach_key_attestation(&mut plain_message);
+
         // Determine the appropriate security mode
         let security_mode = self.determine_security_mode::<T>();
         debug!("Security Mode: {:?}", security_mode);
@@ -1326,12 +1583,35 @@ impl crate::agent::Agent for TapAgent {
         }
 
         // Get the appropriate key IDs
-        let sender_kid = self.get_signing_kid().await?;
+        let sender_kid = self
+            .get_kid_for_purpose(
+                security_mode
+                    .sender_key_purpose()
+                    .unwrap_or(KeyPurpose::Authentication),
+            )
+            .await?;
         let recipient_kid = if to.len() == 1
             && (security_mode == SecurityMode::AuthCrypt
                 || security_mode == SecurityMode::AnonCrypt)
         {
-            Some(self.get_encryption_kid(to[0]).await?)
+            // Within a thread, encrypt to the key the recipient advertised for it
+            let thread_kid = if self.config.thread_keys {
+                self.key_manager
+                    .peer_thread_kid(crate::thread_keys::thread_id(&plain_message), to[0])
+            } else {
+                None
+            };
+            match thread_kid {
+                Some(kid) => Some(kid),
+                None => Some(self.get_encryption_kid(to[0]).await?),
+            }
+        } else {
+            None
+        };
+
+        // Compression is negotiated per recipient, so only a single recipient qualifies
+        let compression = if to.len() == 1 {
+            self.negotiate_compression(to[0]).await
         } else {
             None
         };
@@ -1345,6 +1625,8 @@ impl crate::agent::Agent for TapAgent {
                 Some(sender_kid)
             },
             recipient_kid,
+            content_encryption: None,
+            compression,
         };
 
         // Pack the plain message using the Packable trait
@@ -1445,7 +1727,7 @@ impl crate::agent::Agent for TapAgent {
             .map_err(|e| Error::Serialization(format!("Failed to parse JWE: {}", e)))?;
 
         // Get our encryption key ID
-        let our_kid = self.get_signing_kid().await.ok();
+        let our_kid = self.decryption_kid(&jwe).await;
 
         // Create unpack options (accept both AuthCrypt and AnonCrypt)
         let unpack_options = UnpackOptions {
@@ -1457,6 +1739,8 @@ impl crate::agent::Agent for TapAgent {
         // Decrypt the message
         let plain_message: PlainMessage<Value> =
             crate::message::Jwe::unpack(&jwe, &*self.key_manager, unpack_options).await?;
+        let sender_kids: Vec<String> = jwe.authcrypt_sender_kid().into_iter().collect();
+        self.accept_thread_key(&plain_message, &sender_kids);
 
         debug!(
             "Processed encrypted message: {} of type {}",
@@ -1479,9 +1763,8 @@ impl crate::agent::Agent for TapAgent {
         debug!("\n==== RECEIVING RAW MESSAGE ====");
         debug!("Agent DID: {}", self.get_agent_did());
 
-        // First try to parse as JSON to determine message type
-        let json_value: Value = serde_json::from_str(raw_message)
-            .map_err(|e| Error::Serialization(format!("Failed to parse message as JSON: {}", e)))?;
+        // Parse to JSON, converting compact serializations, to determine message type
+        let json_value: Value = crate::message::parse_envelope(raw_message)?;
 
         // Check if it's an encrypted message (JWE) or signed message (JWS)
         let is_encrypted =
@@ -1531,6 +1814,12 @@ impl crate::agent::Agent for TapAgent {
                 };
                 crate::message::Jws::unpack(&jws, &*self.key_manager, unpack_options).await?
             };
+            let signer_kids: Vec<String> = jws
+                .signatures
+                .iter()
+                .filter_map(|signature| signature.get_kid())
+                .collect();
+            self.accept_thread_key(&plain_message, &signer_kids);
 
             // Log the unpacked message
             debug!("--- UNPACKED CONTENT ---");
@@ -1551,8 +1840,12 @@ impl crate::agent::Agent for TapAgent {
             );
             debug!("---------------------");
 
+            // Parse as JWE
+            let jwe: crate::message::Jwe = serde_json::from_value(json_value.clone())
+                .map_err(|e| Error::Serialization(format!("Failed to parse JWE: {}", e)))?;
+
             // Get our encryption key ID
-            let our_kid = self.get_signing_kid().await.ok();
+            let our_kid = self.decryption_kid(&jwe).await;
 
             // Create unpack options (accept both AuthCrypt and AnonCrypt for encrypted messages)
             let unpack_options = UnpackOptions {
@@ -1574,6 +1867,8 @@ impl crate::agent::Agent for TapAgent {
                         return Err(e);
                     }
                 };
+            let sender_kids: Vec<String> = jwe.authcrypt_sender_kid().into_iter().collect();
+            self.accept_thread_key(&plain_message, &sender_kids);
 
             // Log the unpacked message
             debug!("--- UNPACKED CONTENT ---");
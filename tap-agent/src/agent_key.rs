@@ -4,8 +4,9 @@
 //! It defines traits for signing, verification, encryption, and decryption operations, allowing
 //! for a unified interface that can support both local keys and remote keys (e.g., HSM-backed).
 
+use crate::compression::CompressionCodec;
 use crate::error::{Error, Result};
-use crate::message::{Jwe, JweProtected, JwsProtected};
+use crate::message::{Jwe, JwsProtected};
 use async_trait::async_trait;
 use serde_json::Value;
 use std::fmt::Debug;
@@ -56,8 +57,10 @@ impl JwsAlgorithm {
 /// JWE algorithm identifier (for key encryption)
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum JweAlgorithm {
-    /// ECDH-ES + AES key wrap with 256-bit key
+    /// ECDH-ES + AES key wrap with 256-bit key (anoncrypt)
     EcdhEsA256kw,
+    /// ECDH-1PU + AES key wrap with 256-bit key (authcrypt)
+    Ecdh1puA256kw,
     /// ECDH-ES (direct key agreement)
     EcdhEs,
 }
@@ -67,16 +70,35 @@ impl JweAlgorithm {
     pub fn as_str(&self) -> &'static str {
         match self {
             JweAlgorithm::EcdhEsA256kw => "ECDH-ES+A256KW",
+            JweAlgorithm::Ecdh1puA256kw => "ECDH-1PU+A256KW",
             JweAlgorithm::EcdhEs => "ECDH-ES",
         }
     }
 }
 
+impl std::str::FromStr for JweAlgorithm {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "ECDH-ES+A256KW" => Ok(JweAlgorithm::EcdhEsA256kw),
+            "ECDH-1PU+A256KW" => Ok(JweAlgorithm::Ecdh1puA256kw),
+            "ECDH-ES" => Ok(JweAlgorithm::EcdhEs),
+            _ => Err(Error::Cryptography(format!(
+                "Unsupported JWE algorithm: {}",
+                s
+            ))),
+        }
+    }
+}
+
 /// JWE encryption algorithm identifier (for content encryption)
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum JweEncryption {
     /// AES-GCM with 256-bit key
     A256GCM,
+    /// AES-256-CBC with HMAC-SHA-512 (512-bit composite key)
+    A256CBCHS512,
 }
 
 impl JweEncryption {
@@ -84,10 +106,66 @@ impl JweEncryption {
     pub fn as_str(&self) -> &'static str {
         match self {
             JweEncryption::A256GCM => "A256GCM",
+            JweEncryption::A256CBCHS512 => "A256CBC-HS512",
+        }
+    }
+}
+
+impl std::str::FromStr for JweEncryption {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "A256GCM" => Ok(JweEncryption::A256GCM),
+            "A256CBC-HS512" => Ok(JweEncryption::A256CBCHS512),
+            _ => Err(Error::Cryptography(format!(
+                "Unsupported JWE content encryption: {}",
+                s
+            ))),
         }
     }
 }
 
+/// Key agreement and content encryption used to create a JWE
+///
+/// The key agreement curve is not part of the cipher: it is negotiated
+/// from the recipients' key types (Ed25519 keys are converted to X25519).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JweCipher {
+    /// Key agreement and key wrapping algorithm
+    pub alg: JweAlgorithm,
+    /// Content encryption algorithm
+    pub enc: JweEncryption,
+    /// Compression applied to the plaintext before encryption (JWE `zip`)
+    pub zip: Option<CompressionCodec>,
+}
+
+impl JweCipher {
+    /// Anonymous encryption (ECDH-ES+A256KW) with the given content encryption
+    pub fn anoncrypt(enc: JweEncryption) -> Self {
+        Self {
+            alg: JweAlgorithm::EcdhEsA256kw,
+            enc,
+            zip: None,
+        }
+    }
+
+    /// Authenticated sender encryption (ECDH-1PU+A256KW) with the given content encryption
+    pub fn authcrypt(enc: JweEncryption) -> Self {
+        Self {
+            alg: JweAlgorithm::Ecdh1puA256kw,
+            enc,
+            zip: None,
+        }
+    }
+
+    /// Compress the plaintext with `codec` before encrypting it
+    pub fn with_compression(mut self, codec: Option<CompressionCodec>) -> Self {
+        self.zip = codec;
+        self
+    }
+}
+
 /// Agent key capable of signing data for JWS creation.
 ///
 /// Implementations of this trait can sign data to create JWS signatures.
@@ -146,11 +224,14 @@ pub trait EncryptionKey: AgentKey {
     fn recommended_jwe_alg_enc(&self) -> (JweAlgorithm, JweEncryption);
 
     /// Creates a JWE for multiple recipients
+    ///
+    /// Uses [`recommended_jwe_alg_enc`](Self::recommended_jwe_alg_enc) when no
+    /// cipher is given. With ECDH-1PU this key authenticates as the sender.
     async fn create_jwe(
         &self,
         plaintext: &[u8],
         recipients: &[Arc<dyn VerificationKey>],
-        protected_header: Option<JweProtected>,
+        cipher: Option<JweCipher>,
     ) -> Result<Jwe>;
 }
 
@@ -171,8 +252,29 @@ pub trait DecryptionKey: AgentKey {
         sender_key: Option<&dyn VerificationKey>,
     ) -> Result<Vec<u8>>;
 
-    /// Unwraps a JWE to retrieve the plaintext
+    /// Unwraps an anoncrypt (ECDH-ES) JWE to retrieve the plaintext
     async fn unwrap_jwe(&self, jwe: &Jwe) -> Result<Vec<u8>>;
+
+    /// Unwraps an authcrypt (ECDH-1PU) JWE, authenticating the sender's key
+    async fn unwrap_authcrypt_jwe(
+        &self,
+        jwe: &Jwe,
+        sender_key: &dyn VerificationKey,
+    ) -> Result<Vec<u8>>;
+}
+
+/// Unwraps a JWE, authenticating the sender when the JWE is authcrypt
+///
+/// `sender_key` is the key resolved from [`Jwe::authcrypt_sender_kid`].
+pub(crate) async fn unwrap_jwe_from_sender(
+    decryption_key: &dyn DecryptionKey,
+    jwe: &Jwe,
+    sender_key: Option<&dyn VerificationKey>,
+) -> Result<Vec<u8>> {
+    match sender_key {
+        Some(sender_key) => decryption_key.unwrap_authcrypt_jwe(jwe, sender_key).await,
+        None => decryption_key.unwrap_jwe(jwe).await,
+    }
 }
 
 /// Error type specific to agent key operations
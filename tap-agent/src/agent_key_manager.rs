@@ -4,20 +4,28 @@
 //! It manages keys for signing, verification, encryption, and decryption operations, with support
 //! for different key types (Ed25519, P-256, secp256k1).
 
-use crate::agent_key::{AgentKey, DecryptionKey, EncryptionKey, SigningKey, VerificationKey};
+use crate::agent_key::{
+    unwrap_jwe_from_sender, AgentKey, DecryptionKey, EncryptionKey, JweCipher, SigningKey,
+    VerificationKey,
+};
 use crate::did::{DIDGenerationOptions, DIDKeyGenerator, GeneratedKey, KeyType};
 use crate::error::{Error, Result};
+use crate::key_lock::KeyLock;
 use crate::key_manager::{KeyManager, Secret, SecretMaterial};
 use crate::local_agent_key::{LocalAgentKey, PublicVerificationKey};
-use crate::message::{JweProtected, JwsProtected};
+use crate::message::JwsProtected;
 use crate::message_packing::{KeyManagerPacking, MessageError};
 use crate::storage::{KeyStorage, StoredKey};
+use crate::thread_keys::{generate_thread_key, ThreadKeys};
 
 use async_trait::async_trait;
 use base64::Engine;
+use serde_json::Value;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use zeroize::Zeroizing;
 
 /// Agent Key Manager implements the KeyManager trait using the agent key abstraction
 #[derive(Debug, Clone)]
@@ -36,8 +44,12 @@ pub struct AgentKeyManager {
     verification_keys: Arc<RwLock<HashMap<String, Arc<dyn VerificationKey + Send + Sync>>>>,
     /// Generated keys with DID documents (for key ID resolution)
     generated_keys: Arc<RwLock<HashMap<String, GeneratedKey>>>,
+    /// Thread keys by thread ID, kept in memory only
+    thread_keys: Arc<RwLock<HashMap<String, ThreadKeys>>>,
     /// Storage path
     storage_path: Option<PathBuf>,
+    /// Passphrase lock on the private keys, shared by clones
+    lock: Arc<KeyLock>,
 }
 
 impl AgentKeyManager {
@@ -51,10 +63,46 @@ impl AgentKeyManager {
             decryption_keys: Arc::new(RwLock::new(HashMap::new())),
             verification_keys: Arc::new(RwLock::new(HashMap::new())),
             generated_keys: Arc::new(RwLock::new(HashMap::new())),
+            thread_keys: Arc::new(RwLock::new(HashMap::new())),
             storage_path: None,
+            lock: Arc::new(KeyLock::default()),
         }
     }
 
+    /// Set or change the passphrase that unlocks the private keys
+    ///
+    /// The key manager must be unlocked. Setting a passphrase does not lock
+    /// it; see [`lock`](Self::lock) and [`key_lock`](crate::key_lock).
+    pub fn set_passphrase(&self, passphrase: &str) -> Result<()> {
+        self.lock.set_passphrase(passphrase)
+    }
+
+    /// Lock the private keys when they have not been used for `timeout`
+    ///
+    /// Needs a passphrase. `None` turns the idle timeout off.
+    pub fn set_idle_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        self.lock.set_idle_timeout(timeout)
+    }
+
+    /// Lock the private keys until [`unlock`](Self::unlock) is called
+    ///
+    /// Needs a passphrase. Signing, decryption, encryption from our keys and
+    /// key derivation fail with [`Error::Locked`] while locked; verifying
+    /// signatures and resolving public keys keep working.
+    pub fn lock(&self) -> Result<()> {
+        self.lock.lock()
+    }
+
+    /// Unlock the private keys with the passphrase
+    pub fn unlock(&self, passphrase: &str) -> Result<()> {
+        self.lock.unlock(passphrase)
+    }
+
+    /// Whether the private keys are locked, explicitly or by the idle timeout
+    pub fn is_locked(&self) -> bool {
+        self.lock.is_locked()
+    }
+
     /// Get a generated key (with DID document) by DID
     pub fn get_generated_key(&self, did: &str) -> Result<GeneratedKey> {
         if let Ok(generated_keys) = self.generated_keys.read() {
@@ -147,8 +195,11 @@ impl AgentKeyManager {
     /// Get the raw private key bytes and key type for a DID
     ///
     /// Checks generated_keys first (raw bytes), falls back to extracting
-    /// from the secrets JWK "d" parameter.
+    /// from the secrets JWK "d" parameter. Fails while the key manager is
+    /// locked. The caller owns the returned copy and should zeroize it.
     pub fn get_private_key(&self, did: &str) -> Result<(Vec<u8>, KeyType)> {
+        self.lock.ensure_unlocked()?;
+
         // Check generated_keys first (has raw bytes)
         if let Ok(generated_keys) = self.generated_keys.read() {
             if let Some(key) = generated_keys.get(did) {
@@ -173,6 +224,143 @@ impl AgentKeyManager {
         )))
     }
 
+    /// Derive a 256-bit symmetric key from the private key of a DID
+    ///
+    /// The key is the HMAC-SHA256 of `context` under the private key, so
+    /// each context gets an independent key, the same one every time, and
+    /// the private key itself does not leave the key manager. Use it for
+    /// data the agent encrypts for itself, such as records at rest.
+    pub fn derive_symmetric_key(&self, did: &str, context: &str) -> Result<[u8; 32]> {
+        use hmac::{Hmac, Mac};
+
+        let private_key = Zeroizing::new(self.get_private_key(did)?.0);
+        let mut mac = <Hmac<sha2::Sha256> as Mac>::new_from_slice(&private_key)
+            .map_err(|e| Error::Cryptography(e.to_string()))?;
+        mac.update(context.as_bytes());
+        Ok(mac.finalize().into_bytes().into())
+    }
+
+    /// Public key of our thread key for a thread, generating it on first use
+    ///
+    /// The key has the key type of `did`'s DID key. Its private key is
+    /// added to the decryption keys, kept in memory only, and dropped by
+    /// [`retire_thread_keys`](Self::retire_thread_keys). See
+    /// [`thread_keys`](crate::thread_keys).
+    pub fn thread_key(&self, did: &str, thread_id: &str) -> Result<Value> {
+        let mut threads = self
+            .thread_keys
+            .write()
+            .map_err(|_| Error::FailedToAcquireResolverWriteLock)?;
+        if let Some(public_jwk) = threads.get(thread_id).and_then(|keys| keys.own.get(did)) {
+            return Ok(public_jwk.clone());
+        }
+
+        let (_, key_type) = self.get_private_key(did)?;
+        let key = generate_thread_key(did, key_type)?;
+        let public_jwk = AgentKey::public_key_jwk(&key)?;
+        self.decryption_keys
+            .write()
+            .map_err(|_| Error::FailedToAcquireResolverWriteLock)?
+            .insert(AgentKey::key_id(&key).to_string(), Arc::new(key));
+        threads
+            .entry(thread_id.to_string())
+            .or_default()
+            .own
+            .insert(did.to_string(), public_jwk.clone());
+        Ok(public_jwk)
+    }
+
+    /// Record the thread key a counterparty advertised for a thread
+    ///
+    /// A key the counterparty advertised for the thread before is replaced.
+    pub fn add_peer_thread_key(
+        &self,
+        thread_id: &str,
+        peer_did: &str,
+        key: PublicVerificationKey,
+    ) -> Result<()> {
+        let kid = VerificationKey::key_id(&key).to_string();
+        let mut threads = self
+            .thread_keys
+            .write()
+            .map_err(|_| Error::FailedToAcquireResolverWriteLock)?;
+        let mut verification_keys = self
+            .verification_keys
+            .write()
+            .map_err(|_| Error::FailedToAcquireResolverWriteLock)?;
+        let keys = threads.entry(thread_id.to_string()).or_default();
+        if let Some(previous) = keys.peers.insert(peer_did.to_string(), kid.clone()) {
+            if previous != kid {
+                verification_keys.remove(&previous);
+            }
+        }
+        verification_keys.insert(kid, Arc::new(key));
+        Ok(())
+    }
+
+    /// Key ID of the thread key a counterparty advertised for a thread
+    pub fn peer_thread_kid(&self, thread_id: &str, peer_did: &str) -> Option<String> {
+        self.thread_keys
+            .read()
+            .ok()?
+            .get(thread_id)?
+            .peers
+            .get(peer_did)
+            .cloned()
+    }
+
+    /// Whether a key ID is one of our thread keys
+    pub fn is_own_thread_key(&self, kid: &str) -> bool {
+        self.thread_keys.read().is_ok_and(|threads| {
+            threads
+                .values()
+                .flat_map(|keys| keys.own.values())
+                .any(|public_jwk| public_jwk.get("kid").and_then(Value::as_str) == Some(kid))
+        })
+    }
+
+    /// IDs of the threads with thread keys
+    pub fn thread_ids(&self) -> Vec<String> {
+        self.thread_keys
+            .read()
+            .map(|threads| threads.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Drop our thread keys and the counterparties' thread keys of a thread
+    ///
+    /// Messages encrypted to our thread keys cannot be decrypted afterwards,
+    /// and messages to the counterparties are encrypted to their DID keys
+    /// again. Returns the number of keys dropped.
+    pub fn retire_thread_keys(&self, thread_id: &str) -> Result<usize> {
+        let Some(keys) = self
+            .thread_keys
+            .write()
+            .map_err(|_| Error::FailedToAcquireResolverWriteLock)?
+            .remove(thread_id)
+        else {
+            return Ok(0);
+        };
+
+        let mut decryption_keys = self
+            .decryption_keys
+            .write()
+            .map_err(|_| Error::FailedToAcquireResolverWriteLock)?;
+        for public_jwk in keys.own.values() {
+            if let Some(kid) = public_jwk.get("kid").and_then(Value::as_str) {
+                decryption_keys.remove(kid);
+            }
+        }
+        let mut verification_keys = self
+            .verification_keys
+            .write()
+            .map_err(|_| Error::FailedToAcquireResolverWriteLock)?;
+        for kid in keys.peers.values() {
+            verification_keys.remove(kid);
+        }
+        Ok(keys.own.len() + keys.peers.len())
+    }
+
     /// Save keys to storage if a storage path is configured
     pub fn save_to_storage(&self) -> Result<()> {
         // Skip if no storage path is configured
@@ -304,6 +492,13 @@ impl AgentKeyManager {
             return Err(Error::FailedToAcquireResolverWriteLock);
         }
 
+        // Store the generated key for DID document access
+        if let Ok(mut generated_keys) = self.generated_keys.write() {
+            generated_keys.insert(key.did.clone(), key.clone());
+        } else {
+            return Err(Error::FailedToAcquireResolverWriteLock);
+        }
+
         // Store in all collections
         self.store_agent_key(&agent_key, &key_id)?;
 
@@ -576,6 +771,8 @@ impl KeyManager for AgentKeyManager {
 
     /// Get a signing key by ID
     async fn get_signing_key(&self, kid: &str) -> Result<Arc<dyn SigningKey + Send + Sync>> {
+        self.lock.ensure_unlocked()?;
+
         // Check if we have a signing key with this ID
         if let Ok(signing_keys) = self.signing_keys.read() {
             if let Some(key) = signing_keys.get(kid) {
@@ -626,6 +823,8 @@ impl KeyManager for AgentKeyManager {
 
     /// Get an encryption key by ID
     async fn get_encryption_key(&self, kid: &str) -> Result<Arc<dyn EncryptionKey + Send + Sync>> {
+        self.lock.ensure_unlocked()?;
+
         // Check if we have an encryption key with this ID
         if let Ok(encryption_keys) = self.encryption_keys.read() {
             if let Some(key) = encryption_keys.get(kid) {
@@ -678,6 +877,8 @@ impl KeyManager for AgentKeyManager {
 
     /// Get a decryption key by ID
     async fn get_decryption_key(&self, kid: &str) -> Result<Arc<dyn DecryptionKey + Send + Sync>> {
+        self.lock.ensure_unlocked()?;
+
         // Check if we have a decryption key with this ID
         if let Ok(decryption_keys) = self.decryption_keys.read() {
             if let Some(key) = decryption_keys.get(kid) {
@@ -886,7 +1087,7 @@ impl KeyManager for AgentKeyManager {
         sender_kid: &str,
         recipient_kid: &str,
         plaintext: &[u8],
-        protected_header: Option<JweProtected>,
+        cipher: Option<JweCipher>,
     ) -> Result<String> {
         // Get the encryption key
         let encryption_key = KeyManager::get_encryption_key(self, sender_kid).await?;
@@ -896,7 +1097,7 @@ impl KeyManager for AgentKeyManager {
 
         // Encrypt the plaintext
         let jwe = encryption_key
-            .create_jwe(plaintext, &[recipient_key], protected_header)
+            .create_jwe(plaintext, &[recipient_key], cipher)
             .await
             .map_err(|e| Error::Cryptography(e.to_string()))?;
 
@@ -910,6 +1111,13 @@ impl KeyManager for AgentKeyManager {
         let jwe: crate::message::Jwe = serde_json::from_str(jwe)
             .map_err(|e| Error::Serialization(format!("Failed to parse JWE: {}", e)))?;
 
+        // Authcrypt JWEs are decrypted against the sender's public key
+        let sender_key = match jwe.authcrypt_sender_kid() {
+            Some(skid) => Some(KeyManager::resolve_verification_key(self, &skid).await?),
+            None => None,
+        };
+        let sender_key = sender_key.as_deref().map(|k| k as &dyn VerificationKey);
+
         // Find the recipient if expected_kid is provided
         if let Some(kid) = expected_kid {
             // Just verify recipient exists, we don't need the actual instance
@@ -924,8 +1132,7 @@ impl KeyManager for AgentKeyManager {
             let decryption_key = KeyManager::get_decryption_key(self, kid).await?;
 
             // Decrypt the JWE
-            decryption_key
-                .unwrap_jwe(&jwe)
+            unwrap_jwe_from_sender(decryption_key.as_ref(), &jwe, sender_key)
                 .await
                 .map_err(|e| Error::Cryptography(e.to_string()))
         } else {
@@ -936,7 +1143,9 @@ impl KeyManager for AgentKeyManager {
                     KeyManager::get_decryption_key(self, &recipient.header.kid).await
                 {
                     // Try to decrypt
-                    if let Ok(plaintext) = decryption_key.unwrap_jwe(&jwe).await {
+                    if let Ok(plaintext) =
+                        unwrap_jwe_from_sender(decryption_key.as_ref(), &jwe, sender_key).await
+                    {
                         return Ok(plaintext);
                     }
                 }
@@ -1042,7 +1251,9 @@ impl AgentKeyManagerBuilder {
             decryption_keys: Arc::new(RwLock::new(self.decryption_keys)),
             verification_keys: Arc::new(RwLock::new(self.verification_keys)),
             generated_keys: Arc::new(RwLock::new(HashMap::new())),
+            thread_keys: Arc::new(RwLock::new(HashMap::new())),
             storage_path: self.storage_path.clone(),
+            lock: Arc::new(KeyLock::default()),
         };
 
         // Load keys from storage if requested
@@ -1174,6 +1385,92 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_derive_symmetric_key() {
+        let km = AgentKeyManager::new();
+        let key = km
+            .generate_key(DIDGenerationOptions {
+                key_type: KeyType::Ed25519,
+            })
+            .unwrap();
+
+        let storage_key = km.derive_symmetric_key(&key.did, "storage").unwrap();
+        assert_eq!(
+            storage_key,
+            km.derive_symmetric_key(&key.did, "storage").unwrap()
+        );
+        assert_ne!(
+            storage_key,
+            km.derive_symmetric_key(&key.did, "backup").unwrap()
+        );
+        assert!(km
+            .derive_symmetric_key("did:key:nonexistent", "storage")
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_lock_blocks_private_key_operations() {
+        let km = AgentKeyManager::new();
+        let key = km
+            .generate_key(DIDGenerationOptions {
+                key_type: KeyType::Ed25519,
+            })
+            .unwrap();
+        let kid = AgentKey::key_id(&km.agent_key_from_generated(&key).unwrap()).to_string();
+        KeyManager::sign_jws(&km, &kid, b"payload", None)
+            .await
+            .unwrap();
+
+        km.set_passphrase("correct horse").unwrap();
+        km.lock().unwrap();
+        assert!(km.is_locked());
+        assert!(km.clone().is_locked());
+        assert!(matches!(
+            KeyManager::sign_jws(&km, &kid, b"payload", None).await,
+            Err(Error::Locked(_))
+        ));
+        assert!(matches!(
+            km.get_private_key(&key.did),
+            Err(Error::Locked(_))
+        ));
+        assert!(km.derive_symmetric_key(&key.did, "storage").is_err());
+        assert!(KeyManager::resolve_verification_key(&km, &kid)
+            .await
+            .is_ok());
+
+        assert!(km.unlock("wrong horse").is_err());
+        assert!(km.is_locked());
+        km.unlock("correct horse").unwrap();
+        KeyManager::sign_jws(&km, &kid, b"payload", None)
+            .await
+            .unwrap();
+
+        km.set_idle_timeout(Some(Duration::ZERO)).unwrap();
+        assert!(km.is_locked());
+    }
+
+    #[test]
+    fn test_private_keys_are_redacted_from_debug_output() {
+        let km = AgentKeyManager::new();
+        let key = km
+            .generate_key(DIDGenerationOptions {
+                key_type: KeyType::Ed25519,
+            })
+            .unwrap();
+        let secret = km.secrets().read().unwrap().get(&key.did).cloned().unwrap();
+        let d = match &secret.secret_material {
+            SecretMaterial::JWK { private_key_jwk } => {
+                private_key_jwk["d"].as_str().unwrap().to_string()
+            }
+        };
+
+        assert!(!format!("{:?}", secret).contains(&d));
+        assert!(!format!("{:?}", key).contains(&format!("{:?}", key.private_key)));
+        assert!(format!("{:?}", key).contains("[redacted]"));
+        let stored = KeyStorage::from_generated_key(&key);
+        assert!(!format!("{:?}", stored).contains(&stored.private_key));
+    }
+
     #[tokio::test]
     async fn test_get_private_key_roundtrip() {
         let km = AgentKeyManager::new();
@@ -0,0 +1,271 @@
+//! Key attestations binding agent keys to an organization
+//!
+//! An agent's DID on its own says nothing about who operates it. An
+//! organization, typically identified by a `did:web`, can vouch for the
+//! agents acting for it by issuing each one a key attestation: a verifiable
+//! credential, encoded as a JWT, of type `KeyAttestation` about the agent's
+//! DID, optionally naming the key it binds:
+//!
+//! ```json
+//! {
+//!   "iss": "did:web:vasp.example",
+//!   "sub": "did:key:z6Mk...",
+//!   "exp": 1767225600,
+//!   "vc": {
+//!     "type": ["VerifiableCredential", "KeyAttestation"],
+//!     "credentialSubject": { "id": "did:key:z6Mk...", "kid": "did:key:z6Mk...#z6Mk..." }
+//!   }
+//! }
+//! ```
+//!
+//! The organization issues attestations with
+//! [`TapAgent::attest_key`](crate::agent::TapAgent::attest_key). An agent
+//! configured with one through
+//! [`AgentConfig::with_key_attestation`](crate::config::AgentConfig::with_key_attestation)
+//! sends it in the `key_attestation` header of its messages, and receivers
+//! check it with [`verify_key_attestation`]. Whether the organization is one
+//! the receiver deals with is left to the receiver.
+
+use crate::did::SyncDIDResolver;
+use crate::error::{Error, Result};
+use crate::verification::{verify_credential, JwsVerification};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tap_msg::didcomm::PlainMessage;
+
+/// DIDComm header carrying the sender's key attestation
+pub const KEY_ATTESTATION_HEADER: &str = "key_attestation";
+
+/// Credential type of key attestations
+pub const KEY_ATTESTATION_TYPE: &str = "KeyAttestation";
+
+/// A key attestation whose organization signature has been verified
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyAttestation {
+    /// DID of the organization, which issued and signed the attestation
+    pub organization: String,
+    /// DID of the attested agent
+    pub agent_did: String,
+    /// Verification method of the agent the attestation is limited to
+    pub kid: Option<String>,
+    /// Unix time in seconds after which the attestation is no longer valid
+    pub expires_at: Option<i64>,
+    /// Details of the verified organization signature
+    pub verification: JwsVerification,
+}
+
+/// Claims of a key attestation issued by `organization` for `agent_did`
+///
+/// `kid` limits the attestation to one of the agent's keys.
+pub fn key_attestation_claims(
+    organization: &str,
+    agent_did: &str,
+    kid: Option<&str>,
+    expires_at: Option<i64>,
+) -> Value {
+    let mut subject = json!({ "id": agent_did });
+    if let Some(kid) = kid {
+        subject["kid"] = json!(kid);
+    }
+    let mut claims = json!({
+        "iss": organization,
+        "sub": agent_did,
+        "vc": {
+            "type": ["VerifiableCredential", KEY_ATTESTATION_TYPE],
+            "credentialSubject": subject,
+        }
+    });
+    if let Some(exp) = expires_at {
+        claims["exp"] = json!(exp);
+    }
+    claims
+}
+
+/// Verify the key attestation a message was sent with
+///
+/// Returns `None` when the message carries no attestation. Otherwise the
+/// attestation must be a `KeyAttestation` credential whose organization
+/// signature verifies, that is valid at `now` (Unix time in seconds) and
+/// that is about the sender of the message.
+///
+/// `signer_kid` is the verification method that verified the message's
+/// signature, or `None` if the message was not signed. An attestation
+/// limited to a key only holds for messages signed with that key, and a
+/// signed message must have been signed by a key of the sender.
+pub async fn verify_key_attestation(
+    message: &PlainMessage,
+    signer_kid: Option<&str>,
+    resolver: &dyn SyncDIDResolver,
+    now: i64,
+) -> Result<Option<KeyAttestation>> {
+    let Some(attestation) = message.extra_headers.get(KEY_ATTESTATION_HEADER) else {
+        return Ok(None);
+    };
+
+    let credential = verify_credential(attestation, resolver, now).await?;
+    if !credential.has_type(KEY_ATTESTATION_TYPE) {
+        return Err(Error::Validation(format!(
+            "Credential is not a {}",
+            KEY_ATTESTATION_TYPE
+        )));
+    }
+    if credential.subject.as_deref() != Some(message.from.as_str()) {
+        return Err(Error::Validation(format!(
+            "Key attestation is about {}, not the sender {}",
+            credential.subject.as_deref().unwrap_or("nobody"),
+            message.from
+        )));
+    }
+    if let Some(signer_kid) = signer_kid {
+        if signer_kid.split('#').next() != Some(message.from.as_str()) {
+            return Err(Error::Validation(format!(
+                "Message was signed with {}, which is not a key of the sender {}",
+                signer_kid, message.from
+            )));
+        }
+    }
+    let kid = credential.claims["kid"].as_str().map(String::from);
+    if let Some(kid) = &kid {
+        if kid.split('#').next() != Some(message.from.as_str()) {
+            return Err(Error::Validation(format!(
+                "Attested key {} does not belong to the sender {}",
+                kid, message.from
+            )));
+        }
+        match signer_kid {
+            Some(signer_kid) if signer_kid == kid => {}
+            Some(signer_kid) => {
+                return Err(Error::Validation(format!(
+                    "Attestation is limited to key {}, but the message was signed with {}",
+                    kid, signer_kid
+                )));
+            }
+            None => {
+                return Err(Error::Validation(format!(
+                    "Attestation is limited to key {}, but the message was not signed",
+                    kid
+                )));
+            }
+        }
+    }
+
+    Ok(Some(KeyAttestation {
+        organization: credential.issuer,
+        agent_did: message.from.clone(),
+        kid,
+        expires_at: credential.expires_at,
+        verification: credential.verification,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::TapAgent;
+    use crate::did::MultiResolver;
+
+    fn message_from(from: &str, attestation: Option<Value>) -> PlainMessage {
+        let mut message = PlainMessage::new(
+            "msg-1".to_string(),
+            "https://tap.rsvp/schema/1.0#Authorize".to_string(),
+            json!({}),
+            from.to_string(),
+        );
+        if let Some(attestation) = attestation {
+            message
+                .extra_headers
+                .insert(KEY_ATTESTATION_HEADER.to_string(), attestation);
+        }
+        message
+    }
+
+    #[tokio::test]
+    async fn test_verify_key_attestation() {
+        let (organization, organization_did) = TapAgent::from_ephemeral_key().await.unwrap();
+        let (_, agent_did) = TapAgent::from_ephemeral_key().await.unwrap();
+        let resolver = MultiResolver::default();
+        let now = chrono::Utc::now().timestamp();
+        let kid = format!("{}#key-1", agent_did);
+
+        let attestation = organization
+            .attest_key(&agent_did, Some(&kid), Some(now + 3600))
+            .await
+            .unwrap();
+        let message = message_from(&agent_did, Some(attestation.clone()));
+        let verified = verify_key_attestation(&message, Some(&kid), &resolver, now)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(verified.organization, organization_did);
+        assert_eq!(verified.agent_did, agent_did);
+        assert_eq!(verified.kid.as_deref(), Some(kid.as_str()));
+        assert_eq!(verified.verification.signer_did, organization_did);
+
+        // Messages without an attestation have nothing to verify
+        let unattested = message_from(&agent_did, None);
+        assert!(verify_key_attestation(&unattested, None, &resolver, now)
+            .await
+            .unwrap()
+            .is_none());
+
+        // An attestation is only good for the agent it was issued to
+        let (_, other_did) = TapAgent::from_ephemeral_key().await.unwrap();
+        let forwarded = message_from(&other_did, Some(attestation));
+        assert!(verify_key_attestation(&forwarded, None, &resolver, now)
+            .await
+            .is_err());
+
+        // ... and not after it expires
+        let expired = organization
+            .attest_key(&agent_did, None, Some(now - 1))
+            .await
+            .unwrap();
+        assert!(verify_key_attestation(
+            &message_from(&agent_did, Some(expired)),
+            None,
+            &resolver,
+            now
+        )
+        .await
+        .is_err());
+
+        // The attested key must be one of the agent's
+        let foreign_key = organization
+            .attest_key(&agent_did, Some(&format!("{}#key-1", other_did)), None)
+            .await
+            .unwrap();
+        assert!(verify_key_attestation(
+            &message_from(&agent_did, Some(foreign_key)),
+            Some(&format!("{}#key-1", other_did)),
+            &resolver,
+            now
+        )
+        .await
+        .is_err());
+
+        // An attestation limited to a key only holds for messages signed with it
+        let other_key = format!("{}#key-2", agent_did);
+        assert!(
+            verify_key_attestation(&message, Some(&other_key), &resolver, now)
+                .await
+                .is_err()
+        );
+        assert!(verify_key_attestation(&message, None, &resolver, now)
+            .await
+            .is_err());
+
+        // ... and a message signed by someone else is never attested
+        let unlimited = organization
+            .attest_key(&agent_did, None, None)
+            .await
+            .unwrap();
+        assert!(verify_key_attestation(
+            &message_from(&agent_did, Some(unlimited)),
+            Some(&format!("{}#key-1", other_did)),
+            &resolver,
+            now
+        )
+        .await
+        .is_err());
+    }
+}
@@ -0,0 +1,442 @@
+//! Encrypted key backups split into Shamir shares
+//!
+//! A backup encrypts a [`KeyStorage`] with a random AES-256-GCM key and
+//! splits that key into `N` shares, any `K` of which recover it
+//! ([Shamir's secret sharing] over GF(2^8)). The encrypted backup can be
+//! stored anywhere; the shares go to separate custodians, so no single secret
+//! or custodian can restore the keys alone, and losing up to `N - K` shares
+//! does not lose them.
+//!
+//! ```rust,no_run
+//! use tap_agent::backup::KeyBackup;
+//! use tap_agent::KeyStorage;
+//!
+//! # fn main() -> tap_agent::Result<()> {
+//! let storage = KeyStorage::load_default()?;
+//! let (backup, shares) = KeyBackup::create(&storage, 5, 3)?;
+//!
+//! // Later, with any three of the five shares
+//! let restored = backup.restore(&shares[1..4])?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [Shamir's secret sharing]: https://en.wikipedia.org/wiki/Shamir%27s_secret_sharing
+
+use crate::error::{Error, Result};
+use crate::storage::{set_secure_file_permissions, KeyStorage};
+use aes_gcm::aead::{rand_core::RngCore, Aead, OsRng};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use zeroize::Zeroizing;
+
+/// Version of the backup format
+pub const KEY_BACKUP_VERSION: u8 = 1;
+
+/// A key storage encrypted with a key that is split into shares
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyBackup {
+    /// Version of the backup format
+    pub version: u8,
+    /// Identifies the backup its shares belong to
+    pub id: String,
+    /// Number of shares needed to restore the backup
+    pub threshold: u8,
+    /// Number of shares the key was split into
+    pub share_count: u8,
+    /// When the backup was created
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Base64-encoded AES-GCM nonce
+    pub nonce: String,
+    /// Base64-encoded encrypted key storage
+    pub ciphertext: String,
+}
+
+/// One share of the key of a [`KeyBackup`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyShare {
+    /// ID of the backup this share belongs to
+    pub backup_id: String,
+    /// Number of shares needed to restore the backup
+    pub threshold: u8,
+    /// Position of the share, from 1 to the number of shares
+    pub index: u8,
+    /// Base64-encoded share of the backup key
+    pub value: String,
+}
+
+impl KeyBackup {
+    /// Encrypt a key storage and split its key into `share_count` shares,
+    /// any `threshold` of which restore it
+    pub fn create(
+        storage: &KeyStorage,
+        share_count: u8,
+        threshold: u8,
+    ) -> Result<(Self, Vec<KeyShare>)> {
+        let plaintext = Zeroizing::new(serde_json::to_vec(storage).map_err(|e| {
+            Error::Serialization(format!("Failed to serialize key storage: {}", e))
+        })?);
+
+        let mut key = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(key.as_mut_slice());
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce);
+
+        let mut backup = Self {
+            version: KEY_BACKUP_VERSION,
+            id: uuid::Uuid::new_v4().to_string(),
+            threshold,
+            share_count,
+            created_at: chrono::Utc::now(),
+            nonce: base64::engine::general_purpose::STANDARD.encode(nonce),
+            ciphertext: String::new(),
+        };
+        let shares = split_secret(key.as_slice(), share_count, threshold)?
+            .into_iter()
+            .map(|(index, value)| KeyShare {
+                backup_id: backup.id.clone(),
+                threshold,
+                index,
+                value: base64::engine::general_purpose::STANDARD.encode(value),
+            })
+            .collect();
+
+        let ciphertext = Aes256Gcm::new(key.as_slice().into())
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                aes_gcm::aead::Payload {
+                    msg: &plaintext,
+                    aad: backup.aad().as_bytes(),
+                },
+            )
+            .map_err(|_| Error::Cryptography("Failed to encrypt key backup".to_string()))?;
+        backup.ciphertext = base64::engine::general_purpose::STANDARD.encode(ciphertext);
+
+        Ok((backup, shares))
+    }
+
+    /// Recover the key storage from at least `threshold` shares
+    pub fn restore(&self, shares: &[KeyShare]) -> Result<KeyStorage> {
+        if self.version != KEY_BACKUP_VERSION {
+            return Err(Error::Validation(format!(
+                "Unsupported key backup version: {}",
+                self.version
+            )));
+        }
+        if let Some(share) = shares.iter().find(|s| s.backup_id != self.id) {
+            return Err(Error::Validation(format!(
+                "Share {} belongs to backup {}, not {}",
+                share.index, share.backup_id, self.id
+            )));
+        }
+        if shares.len() < self.threshold as usize {
+            return Err(Error::Validation(format!(
+                "Restoring this backup needs {} shares, got {}",
+                self.threshold,
+                shares.len()
+            )));
+        }
+
+        let points = shares
+            .iter()
+            .map(|share| {
+                base64::engine::general_purpose::STANDARD
+                    .decode(&share.value)
+                    .map(|value| (share.index, value))
+                    .map_err(|e| Error::Validation(format!("Invalid share {}: {}", share.index, e)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let key = Zeroizing::new(combine_shares(&points)?);
+        if key.len() != 32 {
+            return Err(Error::Validation(
+                "Shares do not hold a backup key".to_string(),
+            ));
+        }
+
+        let nonce = base64::engine::general_purpose::STANDARD
+            .decode(&self.nonce)
+            .ok()
+            .filter(|nonce| nonce.len() == 12)
+            .ok_or_else(|| Error::Validation("Invalid key backup nonce".to_string()))?;
+        let ciphertext = base64::engine::general_purpose::STANDARD
+            .decode(&self.ciphertext)
+            .map_err(|e| Error::Validation(format!("Invalid key backup ciphertext: {}", e)))?;
+
+        let plaintext = Zeroizing::new(
+            Aes256Gcm::new(key.as_slice().into())
+                .decrypt(
+                    Nonce::from_slice(&nonce),
+                    aes_gcm::aead::Payload {
+                        msg: &ciphertext,
+                        aad: self.aad().as_bytes(),
+                    },
+                )
+                .map_err(|_| {
+                    Error::Cryptography(
+                        "Failed to decrypt key backup; the shares do not match it".to_string(),
+                    )
+                })?,
+        );
+
+        serde_json::from_slice(&plaintext)
+            .map_err(|e| Error::Serialization(format!("Failed to parse restored keys: {}", e)))
+    }
+
+    /// Load a backup from a file
+    pub fn load_from_path(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| Error::Storage(format!("Failed to read key backup: {}", e)))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| Error::Storage(format!("Failed to parse key backup: {}", e)))
+    }
+
+    /// Save the backup to a file readable only by its owner
+    pub fn save_to_path(&self, path: &Path) -> Result<()> {
+        write_json(self, path)
+    }
+
+    /// Binds the ciphertext to the backup parameters
+    fn aad(&self) -> String {
+        format!(
+            "tap-key-backup:{}:{}:{}:{}",
+            self.version, self.id, self.threshold, self.share_count
+        )
+    }
+}
+
+impl KeyShare {
+    /// Load a share from a file
+    pub fn load_from_path(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| Error::Storage(format!("Failed to read key share: {}", e)))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| Error::Storage(format!("Failed to parse key share: {}", e)))
+    }
+
+    /// Save the share to a file readable only by its owner
+    pub fn save_to_path(&self, path: &Path) -> Result<()> {
+        write_json(self, path)
+    }
+}
+
+fn write_json<T: Serialize>(value: &T, path: &Path) -> Result<()> {
+    let contents = serde_json::to_string_pretty(value)
+        .map_err(|e| Error::Serialization(format!("Failed to serialize backup: {}", e)))?;
+    fs::write(path, contents)
+        .map_err(|e| Error::Storage(format!("Failed to write {}: {}", path.display(), e)))?;
+    set_secure_file_permissions(path)
+}
+
+/// Split a secret into `share_count` shares, any `threshold` of which
+/// recover it
+///
+/// Returns `(index, share)` pairs with indexes from 1 to `share_count`.
+pub fn split_secret(secret: &[u8], share_count: u8, threshold: u8) -> Result<Vec<(u8, Vec<u8>)>> {
+    if threshold == 0 || threshold > share_count {
+        return Err(Error::Validation(format!(
+            "Threshold must be between 1 and the number of shares ({}), got {}",
+            share_count, threshold
+        )));
+    }
+
+    let mut shares: Vec<(u8, Vec<u8>)> = (1..=share_count)
+        .map(|index| (index, Vec::with_capacity(secret.len())))
+        .collect();
+    let mut coefficients = Zeroizing::new(vec![0u8; threshold as usize]);
+    for &byte in secret {
+        // A random polynomial of degree threshold - 1 through (0, byte)
+        coefficients[0] = byte;
+        OsRng.fill_bytes(&mut coefficients[1..]);
+
+        for (x, share) in shares.iter_mut() {
+            let y = coefficients
+                .iter()
+                .rev()
+                .fold(0u8, |acc, &c| gf_mul(acc, *x) ^ c);
+            share.push(y);
+        }
+    }
+    Ok(shares)
+}
+
+/// Recover a secret from `(index, share)` pairs made by [`split_secret`]
+///
+/// Combining fewer shares than the threshold yields a wrong secret rather
+/// than an error.
+pub fn combine_shares(shares: &[(u8, Vec<u8>)]) -> Result<Vec<u8>> {
+    let Some((_, first)) = shares.first() else {
+        return Err(Error::Validation("No shares to combine".to_string()));
+    };
+    let len = first.len();
+    for (i, (index, share)) in shares.iter().enumerate() {
+        if *index == 0 {
+            return Err(Error::Validation("Share index 0 is invalid".to_string()));
+        }
+        if share.len() != len {
+            return Err(Error::Validation(format!(
+                "Share {} has a different length than the others",
+                index
+            )));
+        }
+        if shares[..i].iter().any(|(other, _)| other == index) {
+            return Err(Error::Validation(format!("Share {} is given twice", index)));
+        }
+    }
+
+    // Lagrange interpolation at x = 0
+    let mut secret = vec![0u8; len];
+    for (i, (xi, share)) in shares.iter().enumerate() {
+        let mut basis = 1u8;
+        for (j, (xj, _)) in shares.iter().enumerate() {
+            if i != j {
+                basis = gf_mul(basis, gf_div(*xj, xj ^ xi));
+            }
+        }
+        for (byte, &y) in secret.iter_mut().zip(share) {
+            *byte ^= gf_mul(y, basis);
+        }
+    }
+    Ok(secret)
+}
+
+/// Multiplication in GF(2^8) with the AES polynomial
+///
+/// Runs in constant time: it takes part in splitting and combining secrets,
+/// so it must not branch on their bytes.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    for _ in 0..8 {
+        product ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    product
+}
+
+/// Division in GF(2^8); `b` must not be zero
+fn gf_div(a: u8, b: u8) -> u8 {
+    // b^254 is the inverse of b; 254 = 0b11111110
+    let mut inverse = 1u8;
+    let mut power = b;
+    for _ in 0..7 {
+        power = gf_mul(power, power);
+        inverse = gf_mul(inverse, power);
+    }
+    gf_mul(a, inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StoredKey;
+    use std::collections::HashMap;
+
+    fn test_storage() -> KeyStorage {
+        let mut storage = KeyStorage::new();
+        storage.add_key(StoredKey {
+            did: "did:key:z6MkTest".to_string(),
+            label: "treasury".to_string(),
+            key_type: crate::did::KeyType::Ed25519,
+            private_key: "cHJpdmF0ZQ==".to_string(),
+            public_key: "cHVibGlj".to_string(),
+            metadata: HashMap::new(),
+        });
+        storage
+    }
+
+    #[test]
+    fn test_gf_arithmetic() {
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(gf_div(1, a), a), 1);
+        }
+        assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+    }
+
+    #[test]
+    fn test_any_threshold_subset_recovers_the_secret() {
+        let secret = b"correct horse battery staple".to_vec();
+        let shares = split_secret(&secret, 5, 3).unwrap();
+        assert_eq!(shares.len(), 5);
+
+        for subset in [[0, 1, 2], [0, 2, 4], [4, 3, 1], [1, 2, 3]] {
+            let chosen: Vec<_> = subset.iter().map(|&i| shares[i].clone()).collect();
+            assert_eq!(combine_shares(&chosen).unwrap(), secret);
+        }
+        assert_eq!(combine_shares(&shares).unwrap(), secret);
+        assert_ne!(combine_shares(&shares[..2]).unwrap(), secret);
+
+        let duplicated = vec![shares[0].clone(), shares[0].clone(), shares[1].clone()];
+        assert!(combine_shares(&duplicated).is_err());
+        assert!(split_secret(&secret, 3, 4).is_err());
+        assert!(split_secret(&secret, 3, 0).is_err());
+    }
+
+    #[test]
+    fn test_backup_restores_with_threshold_shares() {
+        let storage = test_storage();
+        let (backup, shares) = KeyBackup::create(&storage, 5, 3).unwrap();
+        assert_eq!(shares.len(), 5);
+        assert!(shares.iter().all(|s| s.backup_id == backup.id));
+
+        let restored = backup.restore(&shares[2..]).unwrap();
+        assert_eq!(restored.keys.len(), 1);
+        assert_eq!(restored.keys["did:key:z6MkTest"].label, "treasury");
+        assert_eq!(restored.default_did.as_deref(), Some("did:key:z6MkTest"));
+
+        assert!(matches!(
+            backup.restore(&shares[..2]),
+            Err(Error::Validation(_))
+        ));
+
+        // Shares of another backup are rejected
+        let (other, other_shares) = KeyBackup::create(&storage, 5, 3).unwrap();
+        let mixed = vec![
+            shares[0].clone(),
+            shares[1].clone(),
+            other_shares[2].clone(),
+        ];
+        assert!(matches!(backup.restore(&mixed), Err(Error::Validation(_))));
+
+        // Shares from the same custodians but another backup don't decrypt it
+        let forged: Vec<_> = other_shares[..3]
+            .iter()
+            .map(|s| KeyShare {
+                backup_id: backup.id.clone(),
+                ..s.clone()
+            })
+            .collect();
+        assert!(matches!(
+            backup.restore(&forged),
+            Err(Error::Cryptography(_))
+        ));
+        assert!(other.restore(&other_shares[..3]).is_ok());
+    }
+
+    #[test]
+    fn test_backup_files_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let (backup, shares) = KeyBackup::create(&test_storage(), 3, 2).unwrap();
+
+        let backup_path = dir.path().join("backup.json");
+        backup.save_to_path(&backup_path).unwrap();
+        let share_paths: Vec<_> = shares
+            .iter()
+            .map(|share| {
+                let path = dir.path().join(format!("share-{}.json", share.index));
+                share.save_to_path(&path).unwrap();
+                path
+            })
+            .collect();
+
+        let loaded = KeyBackup::load_from_path(&backup_path).unwrap();
+        let loaded_shares: Vec<_> = share_paths[1..]
+            .iter()
+            .map(|path| KeyShare::load_from_path(path).unwrap())
+            .collect();
+        assert_eq!(loaded.restore(&loaded_shares).unwrap().keys.len(), 1);
+    }
+}
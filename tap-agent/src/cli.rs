@@ -6,6 +6,7 @@
 //! This module is only available when the `native` feature is enabled.
 #![cfg(feature = "native")]
 
+use crate::backup::{KeyBackup, KeyShare};
 use crate::did::{
     DIDGenerationOptions, DIDKeyGenerator, GeneratedKey, KeyType, MultiResolver, SyncDIDResolver,
     VerificationMaterial,
@@ -17,7 +18,7 @@ use crate::storage::{KeyStorage, StoredKey};
 use base64::Engine;
 use clap::{Parser, Subcommand};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tap_msg::didcomm::PlainMessage;
 
@@ -104,6 +105,16 @@ pub enum Commands {
         label: Option<String>,
     },
 
+    /// Back up stored keys to encrypted files restorable from Shamir shares
+    #[command(
+        name = "backup",
+        about = "Back up stored keys as an encrypted file and Shamir shares"
+    )]
+    Backup {
+        #[command(subcommand)]
+        subcommand: BackupCommands,
+    },
+
     /// Pack a plaintext DIDComm message
     #[command(name = "pack", about = "Pack a plaintext DIDComm message")]
     Pack {
@@ -196,6 +207,46 @@ pub enum KeysCommands {
     },
 }
 
+/// Subcommands for key backups
+#[derive(Subcommand, Debug)]
+pub enum BackupCommands {
+    /// Encrypt the stored keys and split the backup key into shares
+    #[command(name = "create")]
+    Create {
+        /// Number of shares to create
+        #[arg(short = 'n', long, default_value_t = 5)]
+        shares: u8,
+
+        /// Number of shares needed to restore the keys
+        #[arg(short = 'k', long, default_value_t = 3)]
+        threshold: u8,
+
+        /// Output file for the encrypted backup
+        #[arg(short, long, default_value = "tap-keys-backup.json")]
+        output: PathBuf,
+
+        /// Directory for the share files (defaults to the backup's directory)
+        #[arg(short = 'd', long)]
+        share_dir: Option<PathBuf>,
+    },
+
+    /// Restore the stored keys from a backup and enough of its shares
+    #[command(name = "restore")]
+    Restore {
+        /// The encrypted backup file
+        #[arg(short, long, required = true)]
+        backup: PathBuf,
+
+        /// A share file (repeat for each share)
+        #[arg(short, long = "share", required = true)]
+        shares: Vec<PathBuf>,
+
+        /// Replace existing stored keys
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
 /// Run the CLI with the given arguments
 pub fn run() -> Result<()> {
     let cli = Cli::parse();
@@ -234,6 +285,23 @@ pub fn run() -> Result<()> {
         } => {
             import_key(&key_file, default, label.as_deref())?;
         }
+        Commands::Backup { subcommand } => match subcommand {
+            BackupCommands::Create {
+                shares,
+                threshold,
+                output,
+                share_dir,
+            } => {
+                create_backup(shares, threshold, &output, share_dir)?;
+            }
+            BackupCommands::Restore {
+                backup,
+                shares,
+                force,
+            } => {
+                restore_backup(&backup, &shares, force)?;
+            }
+        },
         Commands::Pack {
             input,
             output,
@@ -721,6 +789,84 @@ fn delete_key(storage: &mut KeyStorage, did_or_label: &str, force: bool) -> Resu
     Ok(())
 }
 
+/// Back up the stored keys as an encrypted file and share files
+fn create_backup(
+    share_count: u8,
+    threshold: u8,
+    output: &Path,
+    share_dir: Option<PathBuf>,
+) -> Result<()> {
+    let storage = KeyStorage::load_default()?;
+    if storage.keys.is_empty() {
+        return Err(Error::Storage(
+            "No keys found in storage to back up".to_string(),
+        ));
+    }
+
+    let (backup, shares) = KeyBackup::create(&storage, share_count, threshold)?;
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .map_err(|e| Error::Storage(format!("Failed to create backup directory: {}", e)))?;
+    }
+    backup.save_to_path(output)?;
+
+    let share_dir =
+        share_dir.unwrap_or_else(|| output.parent().map(Path::to_path_buf).unwrap_or_default());
+    fs::create_dir_all(&share_dir)
+        .map_err(|e| Error::Storage(format!("Failed to create share directory: {}", e)))?;
+    let stem = output
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("tap-keys-backup");
+
+    println!(
+        "Backed up {} keys to {}",
+        storage.keys.len(),
+        output.display()
+    );
+    for share in &shares {
+        let path = share_dir.join(format!("{}-share-{}.json", stem, share.index));
+        share.save_to_path(&path)?;
+        println!("  Share {}: {}", share.index, path.display());
+    }
+    println!();
+    println!(
+        "Any {} of the {} shares restore the keys. Move each share to a different custodian",
+        threshold, share_count
+    );
+    println!("and do not store them with the backup file.");
+
+    Ok(())
+}
+
+/// Restore the stored keys from a backup and its shares
+fn restore_backup(backup_path: &Path, share_paths: &[PathBuf], force: bool) -> Result<()> {
+    let backup = KeyBackup::load_from_path(backup_path)?;
+    let shares = share_paths
+        .iter()
+        .map(|path| KeyShare::load_from_path(path))
+        .collect::<Result<Vec<_>>>()?;
+    let restored = backup.restore(&shares)?;
+
+    let existing = KeyStorage::load_default()?;
+    if !existing.keys.is_empty() && !force {
+        return Err(Error::Storage(format!(
+            "Key storage already holds {} keys; use --force to replace them",
+            existing.keys.len()
+        )));
+    }
+
+    restored.save_default()?;
+    println!(
+        "Restored {} keys from backup {} created {}",
+        restored.keys.len(),
+        backup.id,
+        backup.created_at.to_rfc3339()
+    );
+
+    Ok(())
+}
+
 /// Lookup and resolve a DID to its corresponding DID document
 fn lookup_did(did: &str, output: Option<PathBuf>) -> Result<()> {
     println!("Looking up DID: {}", did);
@@ -949,6 +1095,8 @@ async fn pack_message_async(
         security_mode,
         sender_kid,
         recipient_kid,
+        content_encryption: None,
+        compression: None,
     };
 
     // Pack the message directly using the PlainMessage's Packable implementation
@@ -0,0 +1,259 @@
+//! Payload compression for signed and encrypted envelopes
+//!
+//! Large message bodies (IVMS101 records, invoices) are compressed before
+//! signing or encryption. The codec is recorded in the `zip` parameter of
+//! the JWS or JWE protected header so the recipient can reverse it after
+//! verification or decryption.
+//!
+//! Codecs are only used when the counterparty advertises support for them
+//! through the `compression` property of its `DIDCommMessaging` service:
+//!
+//! ```json
+//! {
+//!   "id": "did:web:example.com#didcomm",
+//!   "type": "DIDCommMessaging",
+//!   "serviceEndpoint": "https://example.com/didcomm",
+//!   "compression": ["zstd", "DEF"]
+//! }
+//! ```
+
+use crate::did::DIDDoc;
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
+
+/// Payloads smaller than this are sent uncompressed
+pub const MIN_COMPRESSION_SIZE: usize = 1024;
+
+/// Upper bound on decompressed payload size, guarding against compression bombs
+pub const MAX_DECOMPRESSED_SIZE: usize = 16 * 1024 * 1024;
+
+/// DID document service property listing the compression codecs an agent accepts
+pub const COMPRESSION_SERVICE_PROPERTY: &str = "compression";
+
+/// Compression codec applied to an envelope payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CompressionCodec {
+    /// Raw DEFLATE (RFC 1951), the JOSE `"zip": "DEF"` codec
+    #[serde(rename = "DEF")]
+    Deflate,
+    /// Zstandard (RFC 8878)
+    #[serde(rename = "zstd")]
+    Zstd,
+}
+
+impl CompressionCodec {
+    /// The `zip` header value for this codec
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CompressionCodec::Deflate => "DEF",
+            CompressionCodec::Zstd => "zstd",
+        }
+    }
+
+    /// Compress `data` with this codec
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            CompressionCodec::Deflate => {
+                let mut encoder =
+                    flate2::read::DeflateEncoder::new(data, flate2::Compression::default());
+                let mut compressed = Vec::new();
+                encoder.read_to_end(&mut compressed).map_err(|e| {
+                    Error::Serialization(format!("DEFLATE compression failed: {}", e))
+                })?;
+                Ok(compressed)
+            }
+            CompressionCodec::Zstd => Ok(ruzstd::encoding::compress_to_vec(
+                data,
+                ruzstd::encoding::CompressionLevel::Fastest,
+            )),
+        }
+    }
+
+    /// Decompress `data`, failing if the output exceeds [`MAX_DECOMPRESSED_SIZE`]
+    pub fn decompress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            CompressionCodec::Deflate => {
+                read_bounded(flate2::read::DeflateDecoder::new(data), *self)
+            }
+            CompressionCodec::Zstd => {
+                let decoder = ruzstd::decoding::StreamingDecoder::new(data)
+                    .map_err(|e| Error::Serialization(format!("Invalid zstd frame: {}", e)))?;
+                read_bounded(decoder, *self)
+            }
+        }
+    }
+}
+
+impl fmt::Display for CompressionCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for CompressionCodec {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "DEF" => Ok(CompressionCodec::Deflate),
+            "zstd" => Ok(CompressionCodec::Zstd),
+            other => Err(Error::Validation(format!(
+                "Unsupported compression codec: {}",
+                other
+            ))),
+        }
+    }
+}
+
+fn read_bounded(reader: impl Read, codec: CompressionCodec) -> Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    reader
+        .take(MAX_DECOMPRESSED_SIZE as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| Error::Serialization(format!("{} decompression failed: {}", codec, e)))?;
+
+    if decompressed.len() > MAX_DECOMPRESSED_SIZE {
+        return Err(Error::Validation(format!(
+            "Decompressed payload exceeds {} bytes",
+            MAX_DECOMPRESSED_SIZE
+        )));
+    }
+    Ok(decompressed)
+}
+
+/// Compress `payload` if a codec is given and the payload is large enough
+///
+/// Returns the bytes to sign or encrypt together with the codec actually
+/// applied, which belongs in the `zip` header.
+pub fn maybe_compress(
+    codec: Option<CompressionCodec>,
+    payload: &[u8],
+) -> Result<(Vec<u8>, Option<CompressionCodec>)> {
+    match codec {
+        Some(codec) if payload.len() >= MIN_COMPRESSION_SIZE => {
+            let compressed = codec.compress(payload)?;
+            if compressed.len() < payload.len() {
+                Ok((compressed, Some(codec)))
+            } else {
+                Ok((payload.to_vec(), None))
+            }
+        }
+        _ => Ok((payload.to_vec(), None)),
+    }
+}
+
+/// Reverse [`maybe_compress`] given the `zip` header value, if any
+pub fn decompress_payload(zip: Option<&str>, payload: Vec<u8>) -> Result<Vec<u8>> {
+    match zip {
+        Some(zip) => zip.parse::<CompressionCodec>()?.decompress(&payload),
+        None => Ok(payload),
+    }
+}
+
+/// Codecs advertised by a DID document's `DIDCommMessaging` services
+///
+/// Unknown codec names are ignored.
+pub fn advertised_codecs(did_doc: &DIDDoc) -> Vec<CompressionCodec> {
+    did_doc
+        .service
+        .iter()
+        .filter(|service| service.type_ == "DIDCommMessaging")
+        .filter_map(|service| service.properties.get(COMPRESSION_SERVICE_PROPERTY))
+        .filter_map(|value| value.as_array())
+        .flatten()
+        .filter_map(|value| value.as_str()?.parse().ok())
+        .collect()
+}
+
+/// Pick the first of our `preferred` codecs that the counterparty supports
+pub fn negotiate(
+    preferred: &[CompressionCodec],
+    supported_by_peer: &[CompressionCodec],
+) -> Option<CompressionCodec> {
+    preferred
+        .iter()
+        .find(|codec| supported_by_peer.contains(codec))
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::did::Service;
+    use std::collections::HashMap;
+
+    fn large_payload() -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "originator": vec!["Alice Example, 1 Main Street, Springfield"; 100]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_codec_roundtrip() {
+        let payload = large_payload();
+        for codec in [CompressionCodec::Deflate, CompressionCodec::Zstd] {
+            let compressed = codec.compress(&payload).unwrap();
+            assert!(compressed.len() < payload.len());
+            assert_eq!(codec.decompress(&compressed).unwrap(), payload);
+            assert_eq!(codec.as_str().parse::<CompressionCodec>().unwrap(), codec);
+        }
+    }
+
+    #[test]
+    fn test_maybe_compress_skips_small_payloads() {
+        let (bytes, zip) = maybe_compress(Some(CompressionCodec::Zstd), b"small").unwrap();
+        assert_eq!(bytes, b"small");
+        assert_eq!(zip, None);
+
+        let payload = large_payload();
+        let (bytes, zip) = maybe_compress(Some(CompressionCodec::Deflate), &payload).unwrap();
+        assert_eq!(zip, Some(CompressionCodec::Deflate));
+        assert_eq!(decompress_payload(Some("DEF"), bytes).unwrap(), payload);
+    }
+
+    #[test]
+    fn test_decompression_is_bounded() {
+        let bomb = vec![0u8; MAX_DECOMPRESSED_SIZE + 1];
+        for codec in [CompressionCodec::Deflate, CompressionCodec::Zstd] {
+            let compressed = codec.compress(&bomb).unwrap();
+            assert!(codec.decompress(&compressed).is_err());
+        }
+        assert!(decompress_payload(Some("br"), vec![]).is_err());
+    }
+
+    #[test]
+    fn test_negotiation_from_did_doc() {
+        let mut properties = HashMap::new();
+        properties.insert(
+            COMPRESSION_SERVICE_PROPERTY.to_string(),
+            serde_json::json!(["br", "DEF"]),
+        );
+        let did_doc = DIDDoc {
+            id: "did:web:example.com".to_string(),
+            verification_method: vec![],
+            authentication: vec![],
+            key_agreement: vec![],
+            assertion_method: vec![],
+            capability_invocation: vec![],
+            capability_delegation: vec![],
+            service: vec![Service {
+                id: "did:web:example.com#didcomm".to_string(),
+                type_: "DIDCommMessaging".to_string(),
+                service_endpoint: "https://example.com/didcomm".to_string(),
+                properties,
+            }],
+        };
+
+        let peer = advertised_codecs(&did_doc);
+        assert_eq!(peer, vec![CompressionCodec::Deflate]);
+        assert_eq!(
+            negotiate(&[CompressionCodec::Zstd, CompressionCodec::Deflate], &peer),
+            Some(CompressionCodec::Deflate)
+        );
+        assert_eq!(negotiate(&[CompressionCodec::Zstd], &peer), None);
+    }
+}
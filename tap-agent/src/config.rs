@@ -1,6 +1,9 @@
 //! Configuration for the TAP Agent
 
+use crate::compression::CompressionCodec;
+use crate::did::KeyPurpose;
 use crate::error::Result;
+use crate::localization::MessageCatalog;
 use std::collections::HashMap;
 
 /// Configuration options for a TAP Agent
@@ -18,6 +21,29 @@ pub struct AgentConfig {
     /// Timeout in seconds for network operations
     pub timeout_seconds: Option<u64>,
 
+    /// Compression codecs this agent may apply to outgoing payloads, in
+    /// order of preference; empty disables compression
+    pub compression: Vec<CompressionCodec>,
+
+    /// Translations applied to human-readable fields of outgoing messages
+    pub message_catalog: Option<MessageCatalog>,
+
+    /// Preferred locales per counterparty DID, overriding the locales the
+    /// counterparty advertises in its DID document
+    pub counterparty_locales: HashMap<String, Vec<String>>,
+
+    /// Verification method IDs of the agent's keys per purpose, overriding
+    /// those listed in its DID document
+    pub key_ids: HashMap<KeyPurpose, String>,
+
+    /// Advertise an ephemeral key agreement key per thread and encrypt to
+    /// the counterparty's once it is known (see [`crate::thread_keys`])
+    pub thread_keys: bool,
+
+    /// Key attestation issued by the agent's organization, sent in the
+    /// `key_attestation` header of outgoing messages (see [`crate::attestation`])
+    pub key_attestation: Option<serde_json::Value>,
+
     /// Additional configuration parameters
     pub parameters: HashMap<String, String>,
 }
@@ -31,6 +57,12 @@ impl AgentConfig {
             security_mode: Some("SIGNED".to_string()),
             debug: false,
             timeout_seconds: Some(30),
+            compression: Vec::new(),
+            message_catalog: None,
+            counterparty_locales: HashMap::new(),
+            key_ids: HashMap::new(),
+            thread_keys: false,
+            key_attestation: None,
             parameters: HashMap::new(),
         }
     }
@@ -51,6 +83,42 @@ impl AgentConfig {
         self
     }
 
+    /// Sets the compression codecs offered to recipients, in order of preference
+    pub fn with_compression(mut self, codecs: Vec<CompressionCodec>) -> Self {
+        self.compression = codecs;
+        self
+    }
+
+    /// Sets the catalog used to localize outgoing messages
+    pub fn with_message_catalog(mut self, catalog: MessageCatalog) -> Self {
+        self.message_catalog = Some(catalog);
+        self
+    }
+
+    /// Sets the preferred locales of a counterparty, most preferred first
+    pub fn with_counterparty_locales(mut self, did: &str, locales: Vec<String>) -> Self {
+        self.counterparty_locales.insert(did.to_string(), locales);
+        self
+    }
+
+    /// Sets the verification method ID of the key used for a purpose
+    pub fn with_key_id(mut self, purpose: KeyPurpose, kid: &str) -> Self {
+        self.key_ids.insert(purpose, kid.to_string());
+        self
+    }
+
+    /// Enables or disables ephemeral key agreement keys per thread
+    pub fn with_thread_keys(mut self, enabled: bool) -> Self {
+        self.thread_keys = enabled;
+        self
+    }
+
+    /// Sets the key attestation sent with outgoing messages
+    pub fn with_key_attestation(mut self, attestation: serde_json::Value) -> Self {
+        self.key_attestation = Some(attestation);
+        self
+    }
+
     /// Sets the debug mode
     pub fn with_debug(mut self, debug: bool) -> Self {
         self.debug = debug;
@@ -0,0 +1,321 @@
+//! JWE Content Encryption
+//!
+//! Implements the content encryption algorithms used by DIDComm v2:
+//! - A256GCM (RFC 7518 Section 5.3)
+//! - A256CBC-HS512 (RFC 7518 Section 5.2.5)
+//!
+//! The additional authenticated data (AAD) is the encoded JWE protected
+//! header, binding the header to the ciphertext.
+
+use crate::agent_key::JweEncryption;
+use crate::error::{Error, Result};
+use aes::cipher::{BlockDecrypt, BlockEncrypt};
+use aes::Aes256;
+use aes_gcm::aead::{rand_core::RngCore, OsRng};
+use aes_gcm::{AeadInPlace, Aes256Gcm, KeyInit, Nonce};
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+
+const AES_BLOCK_SIZE: usize = 16;
+
+/// Output of content encryption
+#[derive(Debug, Clone)]
+pub struct EncryptedContent {
+    /// The ciphertext
+    pub ciphertext: Vec<u8>,
+    /// The initialization vector
+    pub iv: Vec<u8>,
+    /// The authentication tag
+    pub tag: Vec<u8>,
+}
+
+/// Length in bytes of the content encryption key for `enc`
+pub fn cek_len(enc: JweEncryption) -> usize {
+    match enc {
+        JweEncryption::A256GCM => 32,
+        // MAC_KEY || ENC_KEY
+        JweEncryption::A256CBCHS512 => 64,
+    }
+}
+
+/// Generate a random content encryption key for `enc`
+pub fn generate_cek(enc: JweEncryption) -> Vec<u8> {
+    let mut cek = vec![0u8; cek_len(enc)];
+    OsRng.fill_bytes(&mut cek);
+    cek
+}
+
+/// Encrypt `plaintext` with a fresh random IV
+pub fn encrypt_content(
+    enc: JweEncryption,
+    cek: &[u8],
+    plaintext: &[u8],
+    aad: &[u8],
+) -> Result<EncryptedContent> {
+    check_cek_len(enc, cek)?;
+
+    match enc {
+        JweEncryption::A256GCM => {
+            let mut iv = [0u8; 12];
+            OsRng.fill_bytes(&mut iv);
+
+            let cipher = Aes256Gcm::new_from_slice(cek).map_err(|e| {
+                Error::Cryptography(format!("Failed to create AES-GCM cipher: {}", e))
+            })?;
+            let mut buffer = plaintext.to_vec();
+            let tag = cipher
+                .encrypt_in_place_detached(Nonce::from_slice(&iv), aad, &mut buffer)
+                .map_err(|e| Error::Cryptography(format!("AES-GCM encryption failed: {}", e)))?;
+
+            Ok(EncryptedContent {
+                ciphertext: buffer,
+                iv: iv.to_vec(),
+                tag: tag.to_vec(),
+            })
+        }
+        JweEncryption::A256CBCHS512 => {
+            let mut iv = [0u8; AES_BLOCK_SIZE];
+            OsRng.fill_bytes(&mut iv);
+
+            let (mac_key, enc_key) = cek.split_at(32);
+            let ciphertext = aes_256_cbc_encrypt(enc_key, &iv, plaintext)?;
+            let tag = cbc_hmac_tag(mac_key, aad, &iv, &ciphertext)?;
+
+            Ok(EncryptedContent {
+                ciphertext,
+                iv: iv.to_vec(),
+                tag,
+            })
+        }
+    }
+}
+
+/// Decrypt and authenticate content encrypted with [`encrypt_content`]
+pub fn decrypt_content(
+    enc: JweEncryption,
+    cek: &[u8],
+    iv: &[u8],
+    ciphertext: &[u8],
+    tag: &[u8],
+    aad: &[u8],
+) -> Result<Vec<u8>> {
+    check_cek_len(enc, cek)?;
+
+    match enc {
+        JweEncryption::A256GCM => {
+            if iv.len() != 12 || tag.len() != 16 {
+                return Err(Error::Cryptography(
+                    "Invalid IV or tag length for A256GCM".to_string(),
+                ));
+            }
+
+            let cipher = Aes256Gcm::new_from_slice(cek).map_err(|e| {
+                Error::Cryptography(format!("Failed to create AES-GCM cipher: {}", e))
+            })?;
+            let mut buffer = ciphertext.to_vec();
+            cipher
+                .decrypt_in_place_detached(
+                    Nonce::from_slice(iv),
+                    aad,
+                    &mut buffer,
+                    aes_gcm::Tag::from_slice(tag),
+                )
+                .map_err(|e| Error::Cryptography(format!("AES-GCM decryption failed: {:?}", e)))?;
+
+            Ok(buffer)
+        }
+        JweEncryption::A256CBCHS512 => {
+            if iv.len() != AES_BLOCK_SIZE || tag.len() != 32 {
+                return Err(Error::Cryptography(
+                    "Invalid IV or tag length for A256CBC-HS512".to_string(),
+                ));
+            }
+
+            let (mac_key, enc_key) = cek.split_at(32);
+
+            // Authenticate before decrypting
+            let mut mac = cbc_hmac(mac_key, aad, iv, ciphertext)?;
+            mac.update(&(aad.len() as u64 * 8).to_be_bytes());
+            mac.verify_truncated_left(tag).map_err(|_| {
+                Error::Cryptography("A256CBC-HS512 authentication failed".to_string())
+            })?;
+
+            aes_256_cbc_decrypt(enc_key, iv, ciphertext)
+        }
+    }
+}
+
+fn check_cek_len(enc: JweEncryption, cek: &[u8]) -> Result<()> {
+    if cek.len() != cek_len(enc) {
+        return Err(Error::Cryptography(format!(
+            "Invalid content encryption key length for {}: {}",
+            enc.as_str(),
+            cek.len()
+        )));
+    }
+    Ok(())
+}
+
+/// HMAC-SHA-512 over AAD || IV || ciphertext, without the AAD length
+fn cbc_hmac(mac_key: &[u8], aad: &[u8], iv: &[u8], ciphertext: &[u8]) -> Result<Hmac<Sha512>> {
+    let mut mac = <Hmac<Sha512> as Mac>::new_from_slice(mac_key)
+        .map_err(|e| Error::Cryptography(format!("Failed to create HMAC: {}", e)))?;
+    mac.update(aad);
+    mac.update(iv);
+    mac.update(ciphertext);
+    Ok(mac)
+}
+
+/// Authentication tag per RFC 7518 Section 5.2.2.1: the first half of
+/// HMAC(MAC_KEY, AAD || IV || ciphertext || AL)
+fn cbc_hmac_tag(mac_key: &[u8], aad: &[u8], iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+    let mut mac = cbc_hmac(mac_key, aad, iv, ciphertext)?;
+    mac.update(&(aad.len() as u64 * 8).to_be_bytes());
+    Ok(mac.finalize().into_bytes()[..32].to_vec())
+}
+
+/// AES-256-CBC with PKCS#7 padding
+fn aes_256_cbc_encrypt(key: &[u8], iv: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+    let cipher = Aes256::new_from_slice(key)
+        .map_err(|e| Error::Cryptography(format!("Failed to create AES cipher: {}", e)))?;
+
+    let pad_len = AES_BLOCK_SIZE - plaintext.len() % AES_BLOCK_SIZE;
+    let mut buffer = plaintext.to_vec();
+    buffer.resize(plaintext.len() + pad_len, pad_len as u8);
+
+    let mut previous = [0u8; AES_BLOCK_SIZE];
+    previous.copy_from_slice(iv);
+    for chunk in buffer.chunks_exact_mut(AES_BLOCK_SIZE) {
+        for (byte, prev) in chunk.iter_mut().zip(previous.iter()) {
+            *byte ^= prev;
+        }
+        cipher.encrypt_block(chunk.into());
+        previous.copy_from_slice(chunk);
+    }
+
+    Ok(buffer)
+}
+
+fn aes_256_cbc_decrypt(key: &[u8], iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+    if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(AES_BLOCK_SIZE) {
+        return Err(Error::Cryptography(
+            "Invalid A256CBC-HS512 ciphertext length".to_string(),
+        ));
+    }
+
+    let cipher = Aes256::new_from_slice(key)
+        .map_err(|e| Error::Cryptography(format!("Failed to create AES cipher: {}", e)))?;
+
+    let mut buffer = ciphertext.to_vec();
+    let mut previous = [0u8; AES_BLOCK_SIZE];
+    previous.copy_from_slice(iv);
+    for chunk in buffer.chunks_exact_mut(AES_BLOCK_SIZE) {
+        let mut current = [0u8; AES_BLOCK_SIZE];
+        current.copy_from_slice(chunk);
+        cipher.decrypt_block(chunk.into());
+        for (byte, prev) in chunk.iter_mut().zip(previous.iter()) {
+            *byte ^= prev;
+        }
+        previous = current;
+    }
+
+    let pad_len = *buffer.last().unwrap_or(&0) as usize;
+    if pad_len == 0
+        || pad_len > AES_BLOCK_SIZE
+        || !buffer[buffer.len() - pad_len..]
+            .iter()
+            .all(|&b| b as usize == pad_len)
+    {
+        return Err(Error::Cryptography("Invalid PKCS#7 padding".to_string()));
+    }
+    buffer.truncate(buffer.len() - pad_len);
+
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_roundtrip() {
+        for enc in [JweEncryption::A256GCM, JweEncryption::A256CBCHS512] {
+            let cek = generate_cek(enc);
+            for plaintext in [&b""[..], b"0123456789abcdef", b"hello DIDComm"] {
+                let encrypted = encrypt_content(enc, &cek, plaintext, b"header").unwrap();
+                let decrypted = decrypt_content(
+                    enc,
+                    &cek,
+                    &encrypted.iv,
+                    &encrypted.ciphertext,
+                    &encrypted.tag,
+                    b"header",
+                )
+                .unwrap();
+                assert_eq!(decrypted, plaintext);
+
+                // Different AAD fails authentication
+                assert!(decrypt_content(
+                    enc,
+                    &cek,
+                    &encrypted.iv,
+                    &encrypted.ciphertext,
+                    &encrypted.tag,
+                    b"other",
+                )
+                .is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_a256cbc_hs512_rfc7518_vector() {
+        // RFC 7518 Appendix B.3
+        let cek = hex::decode(
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f\
+             202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f",
+        )
+        .unwrap();
+        let plaintext = hex::decode(
+            "41206369706865722073797374656d206d757374206e6f742062652072657175\
+             6972656420746f206265207365637265742c20616e64206974206d7573742062\
+             652061626c6520746f2066616c6c20696e746f207468652068616e6473206f66\
+             2074686520656e656d7920776974686f757420696e636f6e76656e69656e6365",
+        )
+        .unwrap();
+        let iv = hex::decode("1af38c2dc2b96ffdd86694092341bc04").unwrap();
+        let aad = hex::decode(
+            "546865207365636f6e64207072696e6369706c65206f66204175677573746520\
+             4b6572636b686f666673",
+        )
+        .unwrap();
+
+        let (mac_key, enc_key) = cek.split_at(32);
+        let ciphertext = aes_256_cbc_encrypt(enc_key, &iv, &plaintext).unwrap();
+        assert_eq!(
+            hex::encode(&ciphertext),
+            "4affaaadb78c31c5da4b1b590d10ffbd3dd8d5d302423526912da037ecbcc7bd\
+             822c301dd67c373bccb584ad3e9279c2e6d12a1374b77f077553df829410446b\
+             36ebd97066296ae6427ea75c2e0846a11a09ccf5370dc80bfecbad28c73f09b3\
+             a3b75e662a2594410ae496b2e2e6609e31e6e02cc837f053d21f37ff4f51950b\
+             be2638d09dd7a4930930806d0703b1f6"
+        );
+
+        let tag = cbc_hmac_tag(mac_key, &aad, &iv, &ciphertext).unwrap();
+        assert_eq!(
+            hex::encode(&tag),
+            "4dd3b4c088a7f45c216839645b2012bf2e6269a8c56a816dbc1b267761955bc5"
+        );
+
+        let decrypted = decrypt_content(
+            JweEncryption::A256CBCHS512,
+            &cek,
+            &iv,
+            &ciphertext,
+            &tag,
+            &aad,
+        )
+        .unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+}
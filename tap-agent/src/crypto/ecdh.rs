@@ -0,0 +1,339 @@
+//! ECDH Key Agreement
+//!
+//! Key agreement over the curves supported for DIDComm v2 encryption:
+//! - X25519, including Ed25519 keys converted to their X25519 form
+//! - P-256
+//! - secp256k1
+//!
+//! Keys are parsed from JWKs and JWE ephemeral public keys. Each curve is
+//! only available when the corresponding `crypto-*` feature is enabled.
+
+use crate::error::{Error, Result};
+use crate::message::EphemeralPublicKey;
+#[cfg(any(
+    feature = "crypto-ed25519",
+    feature = "crypto-p256",
+    feature = "crypto-secp256k1"
+))]
+use aes_gcm::aead::OsRng;
+#[cfg(any(
+    feature = "crypto-ed25519",
+    feature = "crypto-p256",
+    feature = "crypto-secp256k1"
+))]
+use base64::Engine;
+#[cfg(all(feature = "crypto-secp256k1", not(feature = "crypto-p256")))]
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+#[cfg(feature = "crypto-p256")]
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use serde_json::Value;
+use std::fmt;
+#[cfg(feature = "crypto-ed25519")]
+use zeroize::Zeroize;
+#[cfg(any(
+    feature = "crypto-ed25519",
+    feature = "crypto-p256",
+    feature = "crypto-secp256k1"
+))]
+use zeroize::Zeroizing;
+
+/// Curve used for ECDH key agreement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EcdhCurve {
+    /// Curve25519 (also used for Ed25519 keys)
+    X25519,
+    /// NIST P-256
+    P256,
+    /// secp256k1
+    Secp256k1,
+}
+
+impl EcdhCurve {
+    /// Returns the JWK curve name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EcdhCurve::X25519 => "X25519",
+            EcdhCurve::P256 => "P-256",
+            EcdhCurve::Secp256k1 => "secp256k1",
+        }
+    }
+}
+
+impl fmt::Display for EcdhCurve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A public key usable for ECDH key agreement
+#[derive(Debug, Clone)]
+pub enum EcdhPublicKey {
+    #[cfg(feature = "crypto-ed25519")]
+    X25519(x25519_dalek::PublicKey),
+    #[cfg(feature = "crypto-p256")]
+    P256(p256::PublicKey),
+    #[cfg(feature = "crypto-secp256k1")]
+    Secp256k1(k256::PublicKey),
+}
+
+impl EcdhPublicKey {
+    /// Parse a public JWK, converting Ed25519 keys to X25519
+    pub fn from_jwk(jwk: &Value) -> Result<Self> {
+        let kty = jwk.get("kty").and_then(|v| v.as_str());
+        let crv = jwk.get("crv").and_then(|v| v.as_str());
+
+        match (kty, crv) {
+            #[cfg(feature = "crypto-ed25519")]
+            (Some("OKP"), Some("Ed25519")) => {
+                let x = jwk_bytes::<32>(jwk, "x")?;
+                let montgomery = curve25519_dalek::edwards::CompressedEdwardsY(x)
+                    .decompress()
+                    .ok_or_else(|| Error::Cryptography("Invalid Ed25519 public key".to_string()))?
+                    .to_montgomery();
+                Ok(Self::X25519(x25519_dalek::PublicKey::from(
+                    montgomery.to_bytes(),
+                )))
+            }
+            #[cfg(feature = "crypto-ed25519")]
+            (Some("OKP"), Some("X25519")) => Ok(Self::X25519(x25519_dalek::PublicKey::from(
+                jwk_bytes::<32>(jwk, "x")?,
+            ))),
+            #[cfg(feature = "crypto-p256")]
+            (Some("EC"), Some("P-256")) => p256::PublicKey::from_sec1_bytes(&sec1_point(jwk)?)
+                .map(Self::P256)
+                .map_err(|e| Error::Cryptography(format!("Invalid P-256 public key: {}", e))),
+            #[cfg(feature = "crypto-secp256k1")]
+            (Some("EC"), Some("secp256k1")) => k256::PublicKey::from_sec1_bytes(&sec1_point(jwk)?)
+                .map(Self::Secp256k1)
+                .map_err(|e| Error::Cryptography(format!("Invalid secp256k1 public key: {}", e))),
+            _ => Err(Error::Cryptography(format!(
+                "Unsupported key type for key agreement: kty={:?}, crv={:?}",
+                kty, crv
+            ))),
+        }
+    }
+
+    /// Parse the ephemeral public key of a JWE protected header
+    pub fn from_epk(epk: &EphemeralPublicKey) -> Result<Self> {
+        if let EphemeralPublicKey::Okp { crv, .. } = epk {
+            if crv != "X25519" {
+                return Err(Error::Cryptography(format!(
+                    "Unsupported OKP ephemeral key curve: {}",
+                    crv
+                )));
+            }
+        }
+        let jwk = serde_json::to_value(epk).map_err(|e| {
+            Error::Serialization(format!("Failed to serialize ephemeral key: {}", e))
+        })?;
+        Self::from_jwk(&jwk)
+    }
+
+    /// Encode as a JWE ephemeral public key
+    pub fn to_epk(&self) -> EphemeralPublicKey {
+        match self {
+            #[cfg(feature = "crypto-ed25519")]
+            Self::X25519(key) => EphemeralPublicKey::Okp {
+                crv: EcdhCurve::X25519.to_string(),
+                x: base64::engine::general_purpose::STANDARD.encode(key.as_bytes()),
+            },
+            #[cfg(feature = "crypto-p256")]
+            Self::P256(key) => ec_epk(EcdhCurve::P256, key.to_encoded_point(false).as_bytes()),
+            #[cfg(feature = "crypto-secp256k1")]
+            Self::Secp256k1(key) => {
+                ec_epk(EcdhCurve::Secp256k1, key.to_encoded_point(false).as_bytes())
+            }
+        }
+    }
+
+    /// The curve of this key
+    pub fn curve(&self) -> EcdhCurve {
+        match self {
+            #[cfg(feature = "crypto-ed25519")]
+            Self::X25519(_) => EcdhCurve::X25519,
+            #[cfg(feature = "crypto-p256")]
+            Self::P256(_) => EcdhCurve::P256,
+            #[cfg(feature = "crypto-secp256k1")]
+            Self::Secp256k1(_) => EcdhCurve::Secp256k1,
+        }
+    }
+}
+
+/// A private key usable for ECDH key agreement
+pub enum EcdhSecretKey {
+    #[cfg(feature = "crypto-ed25519")]
+    X25519(x25519_dalek::StaticSecret),
+    #[cfg(feature = "crypto-p256")]
+    P256(p256::SecretKey),
+    #[cfg(feature = "crypto-secp256k1")]
+    Secp256k1(k256::SecretKey),
+}
+
+impl EcdhSecretKey {
+    /// Parse a private JWK, converting Ed25519 keys to X25519
+    pub fn from_jwk(jwk: &Value) -> Result<Self> {
+        let kty = jwk.get("kty").and_then(|v| v.as_str());
+        let crv = jwk.get("crv").and_then(|v| v.as_str());
+
+        match (kty, crv) {
+            #[cfg(feature = "crypto-ed25519")]
+            (Some("OKP"), Some("Ed25519")) => {
+                // Ed25519 seed -> X25519 secret: first 32 bytes of SHA-512(seed);
+                // x25519-dalek clamps the scalar
+                use sha2::Digest;
+                let seed = Zeroizing::new(jwk_bytes::<32>(jwk, "d")?);
+                let mut hash = sha2::Sha512::digest(seed.as_slice());
+                let mut secret = [0u8; 32];
+                secret.copy_from_slice(&hash[..32]);
+                hash.as_mut_slice().zeroize();
+                let key = x25519_dalek::StaticSecret::from(secret);
+                secret.zeroize();
+                Ok(Self::X25519(key))
+            }
+            #[cfg(feature = "crypto-ed25519")]
+            (Some("OKP"), Some("X25519")) => {
+                let mut secret = jwk_bytes::<32>(jwk, "d")?;
+                let key = x25519_dalek::StaticSecret::from(secret);
+                secret.zeroize();
+                Ok(Self::X25519(key))
+            }
+            #[cfg(feature = "crypto-p256")]
+            (Some("EC"), Some("P-256")) => {
+                p256::SecretKey::from_slice(&Zeroizing::new(jwk_param(jwk, "d")?))
+                    .map(Self::P256)
+                    .map_err(|e| Error::Cryptography(format!("Invalid P-256 private key: {}", e)))
+            }
+            #[cfg(feature = "crypto-secp256k1")]
+            (Some("EC"), Some("secp256k1")) => {
+                k256::SecretKey::from_slice(&Zeroizing::new(jwk_param(jwk, "d")?))
+                    .map(Self::Secp256k1)
+                    .map_err(|e| {
+                        Error::Cryptography(format!("Invalid secp256k1 private key: {}", e))
+                    })
+            }
+            _ => Err(Error::Cryptography(format!(
+                "Unsupported key type for key agreement: kty={:?}, crv={:?}",
+                kty, crv
+            ))),
+        }
+    }
+
+    /// Generate an ephemeral key on `curve`
+    pub fn generate(curve: EcdhCurve) -> Result<Self> {
+        match curve {
+            #[cfg(feature = "crypto-ed25519")]
+            EcdhCurve::X25519 => Ok(Self::X25519(x25519_dalek::StaticSecret::random_from_rng(
+                OsRng,
+            ))),
+            #[cfg(feature = "crypto-p256")]
+            EcdhCurve::P256 => Ok(Self::P256(p256::SecretKey::random(&mut OsRng))),
+            #[cfg(feature = "crypto-secp256k1")]
+            EcdhCurve::Secp256k1 => Ok(Self::Secp256k1(k256::SecretKey::random(&mut OsRng))),
+            #[allow(unreachable_patterns)]
+            _ => Err(Error::Cryptography(format!(
+                "{} key agreement not available - enable the corresponding crypto feature",
+                curve
+            ))),
+        }
+    }
+
+    /// The public key for this secret
+    pub fn public_key(&self) -> EcdhPublicKey {
+        match self {
+            #[cfg(feature = "crypto-ed25519")]
+            Self::X25519(secret) => EcdhPublicKey::X25519(x25519_dalek::PublicKey::from(secret)),
+            #[cfg(feature = "crypto-p256")]
+            Self::P256(secret) => EcdhPublicKey::P256(secret.public_key()),
+            #[cfg(feature = "crypto-secp256k1")]
+            Self::Secp256k1(secret) => EcdhPublicKey::Secp256k1(secret.public_key()),
+        }
+    }
+
+    /// The curve of this key
+    pub fn curve(&self) -> EcdhCurve {
+        match self {
+            #[cfg(feature = "crypto-ed25519")]
+            Self::X25519(_) => EcdhCurve::X25519,
+            #[cfg(feature = "crypto-p256")]
+            Self::P256(_) => EcdhCurve::P256,
+            #[cfg(feature = "crypto-secp256k1")]
+            Self::Secp256k1(_) => EcdhCurve::Secp256k1,
+        }
+    }
+
+    /// Compute the raw shared secret (Z) with `public`
+    pub fn diffie_hellman(&self, public: &EcdhPublicKey) -> Result<Vec<u8>> {
+        #[allow(unreachable_patterns)]
+        match (self, public) {
+            #[cfg(feature = "crypto-ed25519")]
+            (Self::X25519(secret), EcdhPublicKey::X25519(public)) => {
+                let shared = secret.diffie_hellman(public);
+                if !shared.was_contributory() {
+                    return Err(Error::Cryptography(
+                        "X25519 key agreement produced a low-order shared secret".to_string(),
+                    ));
+                }
+                Ok(shared.as_bytes().to_vec())
+            }
+            #[cfg(feature = "crypto-p256")]
+            (Self::P256(secret), EcdhPublicKey::P256(public)) => Ok(p256::ecdh::diffie_hellman(
+                secret.to_nonzero_scalar(),
+                public.as_affine(),
+            )
+            .raw_secret_bytes()
+            .to_vec()),
+            #[cfg(feature = "crypto-secp256k1")]
+            (Self::Secp256k1(secret), EcdhPublicKey::Secp256k1(public)) => Ok(
+                k256::ecdh::diffie_hellman(secret.to_nonzero_scalar(), public.as_affine())
+                    .raw_secret_bytes()
+                    .to_vec(),
+            ),
+            _ => Err(Error::Cryptography(format!(
+                "Key agreement curve mismatch: {} and {}",
+                self.curve(),
+                public.curve()
+            ))),
+        }
+    }
+}
+
+#[cfg(any(
+    feature = "crypto-ed25519",
+    feature = "crypto-p256",
+    feature = "crypto-secp256k1"
+))]
+fn jwk_param(jwk: &Value, name: &str) -> Result<Vec<u8>> {
+    let value = jwk
+        .get(name)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Error::Cryptography(format!("Missing '{}' in JWK", name)))?;
+    crate::message::base64_decode_flexible(value)
+        .map_err(|e| Error::Cryptography(format!("Failed to decode JWK '{}': {}", name, e)))
+}
+
+#[cfg(feature = "crypto-ed25519")]
+fn jwk_bytes<const N: usize>(jwk: &Value, name: &str) -> Result<[u8; N]> {
+    let bytes = Zeroizing::new(jwk_param(jwk, name)?);
+    bytes.as_slice().try_into().map_err(|_| {
+        Error::Cryptography(format!("Invalid length for JWK '{}', expected {}", name, N))
+    })
+}
+
+/// Uncompressed SEC1 point from the JWK x and y coordinates
+#[cfg(any(feature = "crypto-p256", feature = "crypto-secp256k1"))]
+fn sec1_point(jwk: &Value) -> Result<Vec<u8>> {
+    let mut point = vec![0x04];
+    point.extend_from_slice(&jwk_param(jwk, "x")?);
+    point.extend_from_slice(&jwk_param(jwk, "y")?);
+    Ok(point)
+}
+
+#[cfg(any(feature = "crypto-p256", feature = "crypto-secp256k1"))]
+fn ec_epk(curve: EcdhCurve, uncompressed_point: &[u8]) -> EphemeralPublicKey {
+    let engine = base64::engine::general_purpose::STANDARD;
+    EphemeralPublicKey::Ec {
+        crv: curve.to_string(),
+        x: engine.encode(&uncompressed_point[1..33]),
+        y: engine.encode(&uncompressed_point[33..65]),
+    }
+}
@@ -1,4 +1,4 @@
-//! ECDH-ES Key Derivation Function (Concat KDF)
+//! ECDH-ES and ECDH-1PU Key Derivation Function (Concat KDF)
 //!
 //! Implements the Concat KDF per NIST SP 800-56A and RFC 7518 Section 4.6.
 //! This is used to derive key encryption keys (KEK) from ECDH shared secrets
@@ -34,6 +34,53 @@ pub fn derive_key_ecdh_es(
     apu: &[u8],
     apv: &[u8],
     key_data_len: usize,
+) -> Result<Vec<u8>> {
+    concat_kdf(
+        shared_secret,
+        b"ECDH-ES+A256KW",
+        apu,
+        apv,
+        key_data_len,
+        None,
+    )
+}
+
+/// Derive a key for ECDH-1PU+A256KW (authenticated key agreement)
+///
+/// Per draft-madden-jose-ecdh-1pu-04, the shared secret is the concatenation
+/// of the ephemeral-static secret `Ze` and the static-static secret `Zs`.
+/// In key wrapping mode the content encryption tag is appended to
+/// SuppPubInfo, binding the wrapped key to the ciphertext:
+/// - SuppPubInfo: keydatalen (4 bytes) || length (4 bytes) || cc_tag
+pub fn derive_key_ecdh_1pu(
+    ephemeral_secret: &[u8],
+    static_secret: &[u8],
+    apu: &[u8],
+    apv: &[u8],
+    key_data_len: usize,
+    cc_tag: &[u8],
+) -> Result<Vec<u8>> {
+    let mut shared_secret = Vec::with_capacity(ephemeral_secret.len() + static_secret.len());
+    shared_secret.extend_from_slice(ephemeral_secret);
+    shared_secret.extend_from_slice(static_secret);
+
+    concat_kdf(
+        &shared_secret,
+        b"ECDH-1PU+A256KW",
+        apu,
+        apv,
+        key_data_len,
+        Some(cc_tag),
+    )
+}
+
+fn concat_kdf(
+    shared_secret: &[u8],
+    algorithm_id: &[u8],
+    apu: &[u8],
+    apv: &[u8],
+    key_data_len: usize,
+    cc_tag: Option<&[u8]>,
 ) -> Result<Vec<u8>> {
     if key_data_len == 0 || !key_data_len.is_multiple_of(8) {
         return Err(Error::Cryptography(
@@ -41,9 +88,6 @@ pub fn derive_key_ecdh_es(
         ));
     }
 
-    // Algorithm identifier for ECDH-ES+A256KW
-    let algorithm_id = b"ECDH-ES+A256KW";
-
     // Build OtherInfo per RFC 7518 Section 4.6.2
     let mut other_info = Vec::new();
 
@@ -62,6 +106,12 @@ pub fn derive_key_ecdh_es(
     // SuppPubInfo: keydatalen in bits as big-endian u32
     other_info.extend_from_slice(&(key_data_len as u32).to_be_bytes());
 
+    // ECDH-1PU key wrapping: tag length (4 bytes BE) || tag
+    if let Some(tag) = cc_tag {
+        other_info.extend_from_slice(&(tag.len() as u32).to_be_bytes());
+        other_info.extend_from_slice(tag);
+    }
+
     // Concat KDF with SHA-256 (produces 32 bytes per round)
     let key_data_len_bytes = key_data_len / 8;
     let hash_len = 32; // SHA-256 output size
@@ -130,4 +180,17 @@ mod tests {
         let k2 = derive_key_ecdh_es(&secret, b"a", b"c", 256).unwrap();
         assert_ne!(k1, k2);
     }
+
+    #[test]
+    fn test_kdf_1pu_binds_tag_and_static_secret() {
+        let ze = [0x42u8; 32];
+        let zs = [0x24u8; 32];
+        let k1 = derive_key_ecdh_1pu(&ze, &zs, b"a", b"b", 256, b"tag-1").unwrap();
+        let k2 = derive_key_ecdh_1pu(&ze, &zs, b"a", b"b", 256, b"tag-2").unwrap();
+        let k3 = derive_key_ecdh_1pu(&ze, &ze, b"a", b"b", 256, b"tag-1").unwrap();
+        assert_eq!(k1.len(), 32);
+        assert_ne!(k1, k2);
+        assert_ne!(k1, k3);
+        assert_ne!(k1, derive_key_ecdh_es(&ze, b"a", b"b", 256).unwrap());
+    }
 }
@@ -1,14 +1,21 @@
 //! Cryptographic primitives for TAP Agent
 //!
 //! This module provides secure implementations of:
-//! - ECDH-ES key derivation (Concat KDF per NIST SP 800-56A)
+//! - ECDH key agreement over X25519 (including converted Ed25519 keys),
+//!   P-256 and secp256k1
+//! - ECDH-ES and ECDH-1PU key derivation (Concat KDF per NIST SP 800-56A)
 //! - AES Key Wrap per RFC 3394
+//! - A256GCM and A256CBC-HS512 content encryption
 //!
 //! These primitives are used for JWE encryption and decryption in the
 //! DIDComm messaging layer.
 
+mod content;
+mod ecdh;
 mod kdf;
 mod key_wrap;
 
-pub use kdf::derive_key_ecdh_es;
+pub use content::{cek_len, decrypt_content, encrypt_content, generate_cek, EncryptedContent};
+pub use ecdh::{EcdhCurve, EcdhPublicKey, EcdhSecretKey};
+pub use kdf::{derive_key_ecdh_1pu, derive_key_ecdh_es};
 pub use key_wrap::{unwrap_key_aes_kw, wrap_key_aes_kw};
@@ -35,6 +35,7 @@ use std::sync::{Arc, RwLock};
 use tracing::debug;
 #[cfg(not(target_arch = "wasm32"))]
 use tracing::warn;
+use zeroize::Zeroize;
 
 use crate::error::{Error, Result};
 
@@ -69,6 +70,34 @@ pub struct DIDDoc {
     pub service: Vec<Service>,
 }
 
+impl DIDDoc {
+    /// IDs of the verification methods the document lists for a purpose
+    pub fn verification_methods_for(&self, purpose: KeyPurpose) -> &[String] {
+        match purpose {
+            KeyPurpose::Authentication => &self.authentication,
+            KeyPurpose::KeyAgreement => &self.key_agreement,
+            KeyPurpose::Attestation => &self.assertion_method,
+        }
+    }
+}
+
+/// What an agent uses a key for
+///
+/// DID documents with segregated keys list a different verification method
+/// for each purpose, and the key ID sent with each operation must name the
+/// method listed for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyPurpose {
+    /// Signing messages (the `authentication` relationship)
+    Authentication,
+    /// Encrypting messages (the `keyAgreement` relationship)
+    KeyAgreement,
+    /// Signing attestations and credentials (the `assertionMethod`
+    /// relationship)
+    Attestation,
+}
+
 /// Service definition in a DID Document
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Service {
@@ -160,7 +189,10 @@ pub enum KeyType {
 }
 
 /// Generated key information
-#[derive(Debug, Clone)]
+///
+/// The private key is zeroized when the key is dropped and redacted from its
+/// `Debug` output.
+#[derive(Clone)]
 pub struct GeneratedKey {
     /// The key type
     pub key_type: KeyType,
@@ -174,6 +206,24 @@ pub struct GeneratedKey {
     pub did_doc: DIDDoc,
 }
 
+impl Drop for GeneratedKey {
+    fn drop(&mut self) {
+        self.private_key.zeroize();
+    }
+}
+
+impl Debug for GeneratedKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GeneratedKey")
+            .field("key_type", &self.key_type)
+            .field("did", &self.did)
+            .field("public_key", &self.public_key)
+            .field("private_key", &"[redacted]")
+            .field("did_doc", &self.did_doc)
+            .finish()
+    }
+}
+
 /// Options for generating a DID
 #[derive(Debug, Clone)]
 pub struct DIDGenerationOptions {
@@ -378,9 +428,9 @@ impl WasmDIDMethodResolver for KeyResolver {
         let did_doc = DIDDoc {
             id: did_key.to_string(),
             verification_method: verification_methods,
-            authentication: vec![ed_vm_id],
+            authentication: vec![ed_vm_id.clone()],
             key_agreement,
-            assertion_method: Vec::new(),
+            assertion_method: vec![ed_vm_id],
             capability_invocation: Vec::new(),
             capability_delegation: Vec::new(),
             service: Vec::new(),
@@ -471,9 +521,9 @@ impl DIDMethodResolver for KeyResolver {
         let did_doc = DIDDoc {
             id: did_key.to_string(),
             verification_method: verification_methods,
-            authentication: vec![ed_vm_id],
+            authentication: vec![ed_vm_id.clone()],
             key_agreement,
-            assertion_method: Vec::new(),
+            assertion_method: vec![ed_vm_id],
             capability_invocation: Vec::new(),
             capability_delegation: Vec::new(),
             service: Vec::new(),
@@ -718,6 +768,17 @@ impl DIDMethodResolver for WebResolver {
                                                     })
                                                     .collect();
 
+                                                // Extract assertion method references
+                                                let assertion_method = json_value
+                                                    .get("assertionMethod")
+                                                    .and_then(|v| v.as_array())
+                                                    .unwrap_or(&empty_vec)
+                                                    .iter()
+                                                    .filter_map(|v| {
+                                                        v.as_str().map(|s| s.to_string())
+                                                    })
+                                                    .collect();
+
                                                 // We'll create an empty services list for the DIDDoc
                                                 // But save service information separately for display purposes
                                                 let services = Vec::new();
@@ -757,7 +818,7 @@ impl DIDMethodResolver for WebResolver {
                                                     verification_method: verification_methods,
                                                     authentication,
                                                     key_agreement,
-                                                    assertion_method: Vec::new(),
+                                                    assertion_method,
                                                     capability_invocation: Vec::new(),
                                                     capability_delegation: Vec::new(),
                                                     service: services,
@@ -986,6 +1047,15 @@ impl DIDMethodResolver for WebResolver {
                                     .filter_map(|v| v.as_str().map(|s| s.to_string()))
                                     .collect();
 
+                                // Extract assertion method references
+                                let assertion_method = json_value
+                                    .get("assertionMethod")
+                                    .and_then(|v| v.as_array())
+                                    .unwrap_or(&empty_vec)
+                                    .iter()
+                                    .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                                    .collect();
+
                                 // Create an empty services list for the DIDDoc
                                 let services = Vec::new();
 
@@ -1027,7 +1097,7 @@ Endpoint: {}",
                                     verification_method: verification_methods,
                                     authentication,
                                     key_agreement,
-                                    assertion_method: Vec::new(),
+                                    assertion_method,
                                     capability_invocation: Vec::new(),
                                     capability_delegation: Vec::new(),
                                     service: services,
@@ -1283,7 +1353,7 @@ impl DIDKeyGenerator {
         options: DIDGenerationOptions,
     ) -> Result<GeneratedKey> {
         // First, generate a key DID of the appropriate type
-        let key_did = self.generate_did(options)?;
+        let mut key_did = self.generate_did(options)?;
 
         // Format the did:web identifier
         let did = format!("did:web:{}", domain);
@@ -1311,7 +1381,7 @@ impl DIDKeyGenerator {
                 .iter()
                 .map(|vm| vm.id.clone())
                 .collect(),
-            key_agreement: key_did.did_doc.key_agreement,
+            key_agreement: std::mem::take(&mut key_did.did_doc.key_agreement),
             assertion_method: Vec::new(),
             capability_invocation: Vec::new(),
             capability_delegation: Vec::new(),
@@ -1322,8 +1392,8 @@ impl DIDKeyGenerator {
         Ok(GeneratedKey {
             key_type: key_did.key_type,
             did,
-            public_key: key_did.public_key,
-            private_key: key_did.private_key,
+            public_key: std::mem::take(&mut key_did.public_key),
+            private_key: std::mem::take(&mut key_did.private_key),
             did_doc,
         })
     }
@@ -1523,6 +1593,37 @@ impl SyncDIDResolver for MultiResolver {
     }
 }
 
+/// A resolver that serves a fixed set of DID documents
+///
+/// Used to re-verify past messages against the DID documents pinned when
+/// they were first verified, even if the signer has since rotated keys.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Default, Clone)]
+pub struct PinnedDIDResolver {
+    documents: HashMap<String, DIDDoc>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl PinnedDIDResolver {
+    /// Create a resolver serving the given documents, keyed by their `id`
+    pub fn new(documents: impl IntoIterator<Item = DIDDoc>) -> Self {
+        Self {
+            documents: documents
+                .into_iter()
+                .map(|doc| (doc.id.clone(), doc))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl SyncDIDResolver for PinnedDIDResolver {
+    async fn resolve(&self, did: &str) -> Result<Option<DIDDoc>> {
+        Ok(self.documents.get(did).cloned())
+    }
+}
+
 // DIDResolver trait from didcomm is no longer needed since we've removed the didcomm dependency
 
 #[cfg(target_arch = "wasm32")]
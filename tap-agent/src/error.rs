@@ -107,4 +107,8 @@ pub enum Error {
     /// Runtime error
     #[error("Runtime error: {0}")]
     Runtime(String),
+
+    /// The key manager is locked and needs its passphrase
+    #[error("Key manager locked: {0}")]
+    Locked(String),
 }
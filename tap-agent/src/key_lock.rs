@@ -0,0 +1,281 @@
+//! Passphrase lock for the private keys of a key manager
+//!
+//! Applications that keep an agent running for long periods can require a
+//! passphrase before its private keys are used again. Once a passphrase is
+//! set, [`AgentKeyManager::lock`](crate::agent_key_manager::AgentKeyManager::lock)
+//! refuses every operation that needs a private key (signing, decryption,
+//! encryption from the agent, key derivation) with [`Error::Locked`] until
+//! [`unlock`](crate::agent_key_manager::AgentKeyManager::unlock) is called
+//! with the passphrase. With an idle timeout, the key manager also locks
+//! itself when its private keys have not been used for that long:
+//!
+//! ```rust,no_run
+//! use tap_agent::agent_key_manager::AgentKeyManager;
+//! use std::time::Duration;
+//!
+//! # fn main() -> tap_agent::Result<()> {
+//! let key_manager = AgentKeyManager::new();
+//! key_manager.set_passphrase("correct horse battery staple")?;
+//! key_manager.set_idle_timeout(Some(Duration::from_secs(15 * 60)))?;
+//!
+//! key_manager.lock()?;
+//! assert!(key_manager.is_locked());
+//! key_manager.unlock("correct horse battery staple")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Only a PBKDF2-HMAC-SHA256 hash of the passphrase is kept, and it is
+//! compared in constant time. Locking does not encrypt the keys in memory;
+//! it stops the key manager from using them.
+
+use crate::error::{Error, Result};
+use aes_gcm::aead::{rand_core::RngCore, OsRng};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::Mutex;
+use std::time::Duration;
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, Zeroizing};
+
+/// PBKDF2 iterations for hashing the passphrase (fewer in unoptimized tests)
+const PBKDF2_ITERATIONS: u32 = if cfg!(test) { 1_000 } else { 100_000 };
+
+/// Hash of the passphrase with its salt
+struct PassphraseVerifier {
+    salt: [u8; 16],
+    hash: [u8; 32],
+}
+
+impl PassphraseVerifier {
+    fn new(passphrase: &str) -> Result<Self> {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let hash = pbkdf2_sha256(passphrase.as_bytes(), &salt, PBKDF2_ITERATIONS)?;
+        Ok(Self { salt, hash })
+    }
+
+    fn verify(&self, passphrase: &str) -> Result<bool> {
+        let hash = Zeroizing::new(pbkdf2_sha256(
+            passphrase.as_bytes(),
+            &self.salt,
+            PBKDF2_ITERATIONS,
+        )?);
+        Ok(bool::from(hash.ct_eq(&self.hash)))
+    }
+}
+
+impl Drop for PassphraseVerifier {
+    fn drop(&mut self) {
+        self.hash.zeroize();
+    }
+}
+
+#[derive(Default)]
+struct LockState {
+    verifier: Option<PassphraseVerifier>,
+    locked: bool,
+    idle_timeout: Option<Duration>,
+    last_used: Option<DateTime<Utc>>,
+}
+
+/// Lock state of a key manager, shared by its clones
+#[derive(Default)]
+pub(crate) struct KeyLock {
+    state: Mutex<LockState>,
+}
+
+impl std::fmt::Debug for KeyLock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyLock")
+            .field("locked", &self.is_locked())
+            .finish_non_exhaustive()
+    }
+}
+
+impl KeyLock {
+    fn state(&self) -> Result<std::sync::MutexGuard<'_, LockState>> {
+        self.state
+            .lock()
+            .map_err(|_| Error::Runtime("Key lock poisoned".to_string()))
+    }
+
+    /// Set or change the passphrase; the key manager must be unlocked
+    pub fn set_passphrase(&self, passphrase: &str) -> Result<()> {
+        if passphrase.is_empty() {
+            return Err(Error::Validation(
+                "The passphrase must not be empty".to_string(),
+            ));
+        }
+        let verifier = PassphraseVerifier::new(passphrase)?;
+        let mut state = self.state()?;
+        Self::check(&mut state)?;
+        state.verifier = Some(verifier);
+        Ok(())
+    }
+
+    /// Set the idle time after which the key manager locks itself
+    pub fn set_idle_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        let mut state = self.state()?;
+        if timeout.is_some() && state.verifier.is_none() {
+            return Err(Error::Validation(
+                "Set a passphrase before an idle timeout".to_string(),
+            ));
+        }
+        state.idle_timeout = timeout;
+        state.last_used = Some(Utc::now());
+        Ok(())
+    }
+
+    /// Lock the private keys
+    pub fn lock(&self) -> Result<()> {
+        let mut state = self.state()?;
+        if state.verifier.is_none() {
+            return Err(Error::Validation(
+                "Set a passphrase before locking the key manager".to_string(),
+            ));
+        }
+        state.locked = true;
+        Ok(())
+    }
+
+    /// Unlock the private keys with the passphrase
+    pub fn unlock(&self, passphrase: &str) -> Result<()> {
+        let mut state = self.state()?;
+        let Some(verifier) = state.verifier.as_ref() else {
+            return Ok(());
+        };
+        if !verifier.verify(passphrase)? {
+            return Err(Error::Locked("Wrong passphrase".to_string()));
+        }
+        state.locked = false;
+        state.last_used = Some(Utc::now());
+        Ok(())
+    }
+
+    /// Whether the private keys are locked, counting the idle timeout
+    pub fn is_locked(&self) -> bool {
+        match self.state.lock() {
+            Ok(mut state) => {
+                Self::lock_if_idle(&mut state);
+                state.locked
+            }
+            Err(_) => true,
+        }
+    }
+
+    /// Fail if the private keys are locked, and otherwise record their use
+    pub fn ensure_unlocked(&self) -> Result<()> {
+        let mut state = self.state()?;
+        Self::check(&mut state)
+    }
+
+    fn check(state: &mut LockState) -> Result<()> {
+        Self::lock_if_idle(state);
+        if state.locked {
+            return Err(Error::Locked(
+                "Unlock the key manager with its passphrase to use private keys".to_string(),
+            ));
+        }
+        if state.idle_timeout.is_some() {
+            state.last_used = Some(Utc::now());
+        }
+        Ok(())
+    }
+
+    fn lock_if_idle(state: &mut LockState) {
+        if state.locked {
+            return;
+        }
+        if let (Some(timeout), Some(last_used)) = (state.idle_timeout, state.last_used) {
+            let idle = (Utc::now() - last_used).to_std().unwrap_or_default();
+            if idle >= timeout {
+                state.locked = true;
+            }
+        }
+    }
+}
+
+/// PBKDF2 (RFC 8018) with HMAC-SHA256, for a single 32-byte block
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32) -> Result<[u8; 32]> {
+    let prf = <Hmac<Sha256> as Mac>::new_from_slice(password)
+        .map_err(|e| Error::Cryptography(e.to_string()))?;
+
+    let mut mac = prf.clone();
+    mac.update(salt);
+    mac.update(&1u32.to_be_bytes());
+    let mut block: [u8; 32] = mac.finalize().into_bytes().into();
+    let mut output = block;
+    for _ in 1..iterations {
+        let mut mac = prf.clone();
+        mac.update(&block);
+        block = mac.finalize().into_bytes().into();
+        for (out, byte) in output.iter_mut().zip(block.iter()) {
+            *out ^= byte;
+        }
+    }
+    block.zeroize();
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pbkdf2_sha256_vectors() {
+        let expected =
+            hex::decode("120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b")
+                .unwrap();
+        assert_eq!(
+            pbkdf2_sha256(b"password", b"salt", 1).unwrap().to_vec(),
+            expected
+        );
+        let expected =
+            hex::decode("ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43")
+                .unwrap();
+        assert_eq!(
+            pbkdf2_sha256(b"password", b"salt", 2).unwrap().to_vec(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_lock_and_unlock() {
+        let lock = KeyLock::default();
+        assert!(lock.lock().is_err());
+        lock.ensure_unlocked().unwrap();
+
+        lock.set_passphrase("secret").unwrap();
+        lock.lock().unwrap();
+        assert!(lock.is_locked());
+        assert!(matches!(lock.ensure_unlocked(), Err(Error::Locked(_))));
+        assert!(lock.set_passphrase("other").is_err());
+
+        assert!(matches!(lock.unlock("wrong"), Err(Error::Locked(_))));
+        assert!(lock.is_locked());
+        lock.unlock("secret").unwrap();
+        assert!(!lock.is_locked());
+        lock.ensure_unlocked().unwrap();
+    }
+
+    #[test]
+    fn test_locks_when_idle() {
+        let lock = KeyLock::default();
+        assert!(lock.set_idle_timeout(Some(Duration::ZERO)).is_err());
+        lock.set_passphrase("secret").unwrap();
+
+        lock.set_idle_timeout(Some(Duration::from_secs(3600)))
+            .unwrap();
+        lock.ensure_unlocked().unwrap();
+        assert!(!lock.is_locked());
+
+        lock.set_idle_timeout(Some(Duration::ZERO)).unwrap();
+        assert!(lock.is_locked());
+        assert!(lock.ensure_unlocked().is_err());
+        lock.unlock("secret").unwrap();
+        lock.set_idle_timeout(None).unwrap();
+        lock.ensure_unlocked().unwrap();
+    }
+}
@@ -2,7 +2,10 @@
 //!
 //! This module provides a key manager for storing and retrieving
 //! cryptographic keys used by the TAP Agent for DID operations.
-use crate::agent_key::{AgentKey, DecryptionKey, EncryptionKey, SigningKey, VerificationKey};
+use crate::agent_key::{
+    unwrap_jwe_from_sender, AgentKey, DecryptionKey, EncryptionKey, JweCipher, SigningKey,
+    VerificationKey,
+};
 use crate::did::{DIDGenerationOptions, DIDKeyGenerator, GeneratedKey};
 use crate::error::{Error, Result};
 use crate::local_agent_key::{LocalAgentKey, PublicVerificationKey};
@@ -13,6 +16,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
+use zeroize::Zeroize;
 
 // Secret key material types
 
@@ -25,7 +29,10 @@ pub enum SecretType {
 }
 
 /// Secret key material
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+///
+/// The private members of the JWK are zeroized when it is dropped and
+/// redacted from its `Debug` output.
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
 #[serde(untagged)]
 pub enum SecretMaterial {
     /// JSON Web Key
@@ -35,6 +42,49 @@ pub enum SecretMaterial {
     },
 }
 
+/// JWK members holding private key material (RFC 7518)
+const PRIVATE_JWK_MEMBERS: &[&str] = &["d", "p", "q", "dp", "dq", "qi", "k"];
+
+/// Overwrite the private members of a JWK with zeros
+pub(crate) fn zeroize_jwk(jwk: &mut Value) {
+    if let Some(members) = jwk.as_object_mut() {
+        for name in PRIVATE_JWK_MEMBERS {
+            if let Some(Value::String(value)) = members.get_mut(*name) {
+                value.zeroize();
+            }
+        }
+    }
+}
+
+impl Drop for SecretMaterial {
+    fn drop(&mut self) {
+        match self {
+            SecretMaterial::JWK { private_key_jwk } => zeroize_jwk(private_key_jwk),
+        }
+    }
+}
+
+impl std::fmt::Debug for SecretMaterial {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecretMaterial::JWK { private_key_jwk } => {
+                let mut redacted = private_key_jwk.clone();
+                zeroize_jwk(&mut redacted);
+                if let Some(members) = redacted.as_object_mut() {
+                    for name in PRIVATE_JWK_MEMBERS {
+                        if let Some(value) = members.get_mut(*name) {
+                            *value = Value::String("[redacted]".to_string());
+                        }
+                    }
+                }
+                f.debug_struct("JWK")
+                    .field("private_key_jwk", &redacted)
+                    .finish()
+            }
+        }
+    }
+}
+
 /// Secret for cryptographic operations
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Secret {
@@ -112,12 +162,14 @@ pub trait KeyManager: Send + Sync + std::fmt::Debug + 'static {
     async fn verify_jws(&self, jws: &str, expected_kid: Option<&str>) -> Result<Vec<u8>>;
 
     /// Encrypt data for a recipient
+    ///
+    /// Uses the sender key's recommended cipher when none is given.
     async fn encrypt_jwe(
         &self,
         sender_kid: &str,
         recipient_kid: &str,
         plaintext: &[u8],
-        protected_header: Option<crate::message::JweProtected>,
+        cipher: Option<JweCipher>,
     ) -> Result<String>;
 
     /// Decrypt a JWE
@@ -801,7 +853,7 @@ impl KeyManager for DefaultKeyManager {
         sender_kid: &str,
         recipient_kid: &str,
         plaintext: &[u8],
-        protected_header: Option<crate::message::JweProtected>,
+        cipher: Option<JweCipher>,
     ) -> Result<String> {
         // Get the encryption key
         let encryption_key = KeyManager::get_encryption_key(self, sender_kid).await?;
@@ -811,7 +863,7 @@ impl KeyManager for DefaultKeyManager {
 
         // Encrypt the plaintext
         let jwe = encryption_key
-            .create_jwe(plaintext, &[recipient_key], protected_header)
+            .create_jwe(plaintext, &[recipient_key], cipher)
             .await
             .map_err(|e| Error::Cryptography(e.to_string()))?;
 
@@ -825,6 +877,13 @@ impl KeyManager for DefaultKeyManager {
         let jwe: crate::message::Jwe = serde_json::from_str(jwe)
             .map_err(|e| Error::Serialization(format!("Failed to parse JWE: {}", e)))?;
 
+        // Authcrypt JWEs are decrypted against the sender's public key
+        let sender_key = match jwe.authcrypt_sender_kid() {
+            Some(skid) => Some(KeyManager::resolve_verification_key(self, &skid).await?),
+            None => None,
+        };
+        let sender_key = sender_key.as_deref().map(|k| k as &dyn VerificationKey);
+
         if let Some(kid) = expected_kid {
             // Verify recipient exists
             jwe.recipients
@@ -836,8 +895,7 @@ impl KeyManager for DefaultKeyManager {
 
             // Get the decryption key and unwrap JWE
             let decryption_key = KeyManager::get_decryption_key(self, kid).await?;
-            decryption_key
-                .unwrap_jwe(&jwe)
+            unwrap_jwe_from_sender(decryption_key.as_ref(), &jwe, sender_key)
                 .await
                 .map_err(|e| Error::Cryptography(e.to_string()))
         } else {
@@ -846,7 +904,9 @@ impl KeyManager for DefaultKeyManager {
                 if let Ok(decryption_key) =
                     KeyManager::get_decryption_key(self, &recipient.header.kid).await
                 {
-                    if let Ok(plaintext) = decryption_key.unwrap_jwe(&jwe).await {
+                    if let Ok(plaintext) =
+                        unwrap_jwe_from_sender(decryption_key.as_ref(), &jwe, sender_key).await
+                    {
                         return Ok(plaintext);
                     }
                 }
@@ -68,6 +68,10 @@
 /// Agent implementation
 pub mod agent;
 
+/// Key attestations binding agent keys to an organization
+#[cfg(not(target_arch = "wasm32"))]
+pub mod attestation;
+
 /// Cryptographic primitives (KDF, AES-KW)
 pub mod crypto;
 
@@ -80,6 +84,12 @@ pub mod agent_key;
 /// Agent key manager implementation
 pub mod agent_key_manager;
 
+/// Encrypted key backups split into Shamir shares
+pub mod backup;
+
+/// Payload compression for signed and encrypted envelopes
+pub mod compression;
+
 /// Agent configuration
 pub mod config;
 
@@ -92,12 +102,18 @@ pub mod did;
 /// Error types
 pub mod error;
 
+/// Passphrase lock for the private keys of a key manager
+pub mod key_lock;
+
 /// Key management
 pub mod key_manager;
 
 /// Local agent key implementation
 pub mod local_agent_key;
 
+/// Localization of human-readable message fields
+pub mod localization;
+
 /// Message types and utilities
 pub mod message;
 
@@ -110,6 +126,9 @@ pub mod oob;
 /// Payment link functionality
 pub mod payment_link;
 
+/// RDF canonicalization of JSON-LD documents for Data Integrity proofs
+pub mod rdf;
+
 /// Secret helper for external key management
 #[cfg(not(target_arch = "wasm32"))]
 pub mod secret_helper;
@@ -117,6 +136,9 @@ pub mod secret_helper;
 /// Key storage utilities
 pub mod storage;
 
+/// Ephemeral key agreement keys per thread
+pub mod thread_keys;
+
 /// Test utilities for temporary storage
 #[cfg(any(test, feature = "test-utils"))]
 pub mod test_utils;
@@ -130,9 +152,10 @@ pub mod verification;
 
 // Re-export key types for convenience
 pub use agent_key_manager::{AgentKeyManager, AgentKeyManagerBuilder};
+pub use compression::CompressionCodec;
 pub use config::AgentConfig;
 pub use did::{
-    DIDDoc, DIDGenerationOptions, DIDKeyGenerator, GeneratedKey, KeyResolver, KeyType,
+    DIDDoc, DIDGenerationOptions, DIDKeyGenerator, GeneratedKey, KeyPurpose, KeyResolver, KeyType,
     VerificationMaterial, VerificationMethod, VerificationMethodType,
 };
 pub use error::{Error, Result};
@@ -143,10 +166,11 @@ pub use storage::{KeyStorage, StoredKey};
 
 // Agent key re-exports
 pub use agent_key::{
-    AgentKey, DecryptionKey, EncryptionKey, JweAlgorithm, JweEncryption, JwsAlgorithm, SigningKey,
-    VerificationKey,
+    AgentKey, DecryptionKey, EncryptionKey, JweAlgorithm, JweCipher, JweEncryption, JwsAlgorithm,
+    SigningKey, VerificationKey,
 };
 pub use local_agent_key::{LocalAgentKey, PublicVerificationKey};
+pub use localization::MessageCatalog;
 pub use message::{Jwe, JweHeader, JweRecipient, Jws, JwsSignature, SecurityMode};
 pub use message_packing::{
     KeyManagerPacking, PackOptions, Packable, UnpackOptions, Unpackable, UnpackedMessage,
@@ -166,17 +190,23 @@ pub use secret_helper::{SecretHelperConfig, SecretHelperOutput};
 
 // Native-only DID resolver re-exports
 #[cfg(not(target_arch = "wasm32"))]
-pub use did::MultiResolver;
+pub use did::{MultiResolver, PinnedDIDResolver};
 
 // Native-only re-exports
 #[cfg(not(target_arch = "wasm32"))]
 pub use agent::{Agent, DeliveryResult, EnhancedAgentInfo, TapAgent};
 #[cfg(not(target_arch = "wasm32"))]
+pub use attestation::{verify_key_attestation, KeyAttestation};
+#[cfg(not(target_arch = "wasm32"))]
 pub use did::{DIDMethodResolver, SyncDIDResolver};
 #[cfg(not(target_arch = "wasm32"))]
 pub use message::PRESENTATION_MESSAGE_TYPE;
 #[cfg(not(target_arch = "wasm32"))]
-pub use verification::verify_jws;
+pub use verification::{
+    verify_credential, verify_jws, verify_jws_payload, verify_jws_with_details,
+    verify_presentation, JwsVerification, PresentationChallenge, VerifiedCredential,
+    VerifiedPresentation,
+};
 
 // WASM-only re-exports
 #[cfg(target_arch = "wasm32")]
@@ -4,15 +4,15 @@
 //! for keys that are stored locally, either in memory or on disk.
 
 use crate::agent_key::{
-    AgentKey, DecryptionKey, EncryptionKey, JweAlgorithm, JweEncryption, JwsAlgorithm, SigningKey,
-    VerificationKey,
+    AgentKey, DecryptionKey, EncryptionKey, JweAlgorithm, JweCipher, JweEncryption, JwsAlgorithm,
+    SigningKey, VerificationKey,
 };
+use crate::compression::CompressionCodec;
+use crate::crypto::{EcdhPublicKey, EcdhSecretKey};
 use crate::did::{KeyType, VerificationMaterial};
 use crate::error::{Error, Result};
 use crate::key_manager::{Secret, SecretMaterial};
-use crate::message::{EphemeralPublicKey, Jwe, JweProtected, Jws, JwsProtected, JwsSignature};
-#[cfg(feature = "crypto-p256")]
-use crate::message::{JweHeader, JweRecipient};
+use crate::message::{Jwe, JweHeader, JweProtected, JweRecipient, Jws, JwsProtected, JwsSignature};
 use aes_gcm::{AeadInPlace, Aes256Gcm, KeyInit, Nonce};
 use async_trait::async_trait;
 use base64::Engine;
@@ -21,9 +21,7 @@ use ed25519_dalek::{Signer as Ed25519Signer, Verifier, VerifyingKey};
 #[cfg(feature = "crypto-secp256k1")]
 use k256::{ecdsa::Signature as Secp256k1Signature, ecdsa::SigningKey as Secp256k1SigningKey};
 #[cfg(feature = "crypto-p256")]
-use p256::ecdh::EphemeralSecret as P256EphemeralSecret;
-#[cfg(feature = "crypto-p256")]
-use p256::elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint};
+use p256::elliptic_curve::sec1::FromEncodedPoint;
 #[cfg(feature = "crypto-p256")]
 use p256::EncodedPoint as P256EncodedPoint;
 #[cfg(feature = "crypto-p256")]
@@ -37,9 +35,10 @@ use p256::{ecdsa::Signature as P256Signature, ecdsa::SigningKey as P256SigningKe
 ))]
 use rand::{rngs::OsRng, RngCore};
 use serde_json::Value;
+use sha2::{Digest, Sha256};
 use std::convert::TryFrom;
 use std::sync::Arc;
-use uuid::Uuid;
+use zeroize::Zeroizing;
 
 /// A local implementation of the AgentKey that stores the key material directly
 #[derive(Debug, Clone)]
@@ -110,6 +109,7 @@ impl LocalAgentKey {
             typ: "JWT".to_string(),
             alg: self.recommended_jws_alg().as_str().to_string(),
             kid: crate::agent_key::AgentKey::key_id(self).to_string(),
+            zip: None,
         };
 
         // Verify the signature
@@ -125,156 +125,26 @@ impl LocalAgentKey {
         }
     }
 
-    /// Encrypt data to a JWK recipient using proper ECDH-ES+A256KW
+    /// Encrypt data to a JWK recipient
+    ///
+    /// Uses anoncrypt (ECDH-ES+A256KW with A256GCM) unless a cipher is given.
     pub async fn encrypt_to_jwk(
         &self,
         plaintext: &[u8],
         recipient_jwk: &Value,
-        protected_header: Option<JweProtected>,
+        cipher: Option<JweCipher>,
     ) -> Result<Jwe> {
-        #[cfg(feature = "crypto-p256")]
-        {
-            // 1. Generate random CEK (Content Encryption Key)
-            let mut cek = [0u8; 32];
-            OsRng.fill_bytes(&mut cek);
-
-            // 2. Generate random IV for AES-GCM
-            let mut iv_bytes = [0u8; 12];
-            OsRng.fill_bytes(&mut iv_bytes);
-
-            // 3. Generate ephemeral key pair for ECDH
-            let ephemeral_secret = P256EphemeralSecret::random(&mut OsRng);
-            let ephemeral_public_key = ephemeral_secret.public_key();
-
-            // Convert ephemeral public key to JWK coordinates
-            let point = ephemeral_public_key.to_encoded_point(false);
-            let x_bytes = point.x().unwrap().to_vec();
-            let y_bytes = point.y().unwrap().to_vec();
-            let x_b64 = base64::engine::general_purpose::STANDARD.encode(&x_bytes);
-            let y_b64 = base64::engine::general_purpose::STANDARD.encode(&y_bytes);
-
-            let ephemeral_key = crate::message::EphemeralPublicKey::Ec {
-                crv: "P-256".to_string(),
-                x: x_b64,
-                y: y_b64,
-            };
-
-            // 4. Extract recipient public key from JWK
-            let recipient_x_b64 =
-                recipient_jwk
-                    .get("x")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| {
-                        Error::Cryptography("Missing x coordinate in recipient JWK".to_string())
-                    })?;
-            let recipient_y_b64 =
-                recipient_jwk
-                    .get("y")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| {
-                        Error::Cryptography("Missing y coordinate in recipient JWK".to_string())
-                    })?;
-
-            let recipient_x = base64::engine::general_purpose::STANDARD
-                .decode(recipient_x_b64)
-                .map_err(|e| Error::Cryptography(format!("Failed to decode x: {}", e)))?;
-            let recipient_y = base64::engine::general_purpose::STANDARD
-                .decode(recipient_y_b64)
-                .map_err(|e| Error::Cryptography(format!("Failed to decode y: {}", e)))?;
-
-            // Reconstruct recipient public key
-            let mut recipient_point_bytes = vec![0x04]; // Uncompressed
-            recipient_point_bytes.extend_from_slice(&recipient_x);
-            recipient_point_bytes.extend_from_slice(&recipient_y);
-
-            let recipient_encoded_point = P256EncodedPoint::from_bytes(&recipient_point_bytes)
-                .map_err(|e| Error::Cryptography(format!("Invalid recipient point: {}", e)))?;
-
-            let recipient_pk = P256PublicKey::from_encoded_point(&recipient_encoded_point);
-            if recipient_pk.is_none().into() {
-                return Err(Error::Cryptography(
-                    "Invalid recipient public key".to_string(),
-                ));
-            }
-            let recipient_pk = recipient_pk.unwrap();
-
-            // 5. Perform ECDH
-            let shared_secret = ephemeral_secret.diffie_hellman(&recipient_pk);
-            let shared_bytes = shared_secret.raw_secret_bytes();
-
-            // 6. Create protected header
-            let apv_raw = Uuid::new_v4();
-            let apv_b64 = base64::engine::general_purpose::STANDARD.encode(apv_raw.as_bytes());
-
-            let protected = protected_header.unwrap_or_else(|| crate::message::JweProtected {
-                epk: ephemeral_key,
-                apv: apv_b64.clone(),
-                apu: String::new(),
-                typ: crate::message::DIDCOMM_ENCRYPTED.to_string(),
-                enc: "A256GCM".to_string(),
-                alg: "ECDH-ES+A256KW".to_string(),
-            });
-
-            // 7. Derive KEK using Concat KDF
-            let apv_bytes = base64::engine::general_purpose::STANDARD
-                .decode(&protected.apv)
-                .unwrap_or_default();
-            let kek =
-                crate::crypto::derive_key_ecdh_es(shared_bytes.as_slice(), b"", &apv_bytes, 256)?;
-
-            // 8. Wrap CEK with AES-KW
-            let mut kek_array = [0u8; 32];
-            kek_array.copy_from_slice(&kek);
-            let wrapped_cek = crate::crypto::wrap_key_aes_kw(&kek_array, &cek)?;
-
-            // 9. Encrypt plaintext with AES-GCM
-            let cipher = Aes256Gcm::new_from_slice(&cek)
-                .map_err(|e| Error::Cryptography(format!("Failed to create cipher: {}", e)))?;
-
-            let nonce = Nonce::from_slice(&iv_bytes);
-            let mut buffer = plaintext.to_vec();
-            let tag = cipher
-                .encrypt_in_place_detached(nonce, b"", &mut buffer)
-                .map_err(|e| Error::Cryptography(format!("Encryption failed: {:?}", e)))?;
-
-            // 10. Serialize protected header
-            let protected_json = serde_json::to_string(&protected).map_err(|e| {
-                Error::Serialization(format!("Failed to serialize protected header: {}", e))
-            })?;
-            let protected_b64 = base64::engine::general_purpose::STANDARD.encode(protected_json);
-
-            // Get recipient kid from JWK if available
-            let recipient_kid = recipient_jwk
-                .get("kid")
-                .and_then(|v| v.as_str())
-                .unwrap_or("recipient-key")
-                .to_string();
-
-            // 11. Build JWE
-            let jwe = crate::message::Jwe {
-                ciphertext: base64::engine::general_purpose::STANDARD.encode(&buffer),
-                protected: protected_b64,
-                recipients: vec![crate::message::JweRecipient {
-                    encrypted_key: base64::engine::general_purpose::STANDARD.encode(&wrapped_cek),
-                    header: crate::message::JweHeader {
-                        kid: recipient_kid,
-                        sender_kid: Some(AgentKey::key_id(self).to_string()),
-                    },
-                }],
-                tag: base64::engine::general_purpose::STANDARD.encode(tag),
-                iv: base64::engine::general_purpose::STANDARD.encode(iv_bytes),
-            };
-
-            Ok(jwe)
-        }
-
-        #[cfg(not(feature = "crypto-p256"))]
-        {
-            let _ = (plaintext, recipient_jwk, protected_header);
-            Err(Error::Cryptography(
-                "P-256 encryption not available - enable crypto-p256 feature".to_string(),
-            ))
-        }
+        let recipient_kid = recipient_jwk
+            .get("kid")
+            .and_then(|v| v.as_str())
+            .unwrap_or("recipient-key")
+            .to_string();
+        let recipient: Arc<dyn VerificationKey> = Arc::new(PublicVerificationKey::new(
+            recipient_kid,
+            recipient_jwk.clone(),
+        ));
+
+        self.create_jwe(plaintext, &[recipient], cipher).await
     }
 
     /// Create a new LocalAgentKey from a Secret and key type
@@ -462,6 +332,16 @@ impl LocalAgentKey {
         })
     }
 
+    /// Rename the key to `kid`, a verification method of `did`
+    pub(crate) fn with_key_id(mut self, did: &str, kid: &str) -> Self {
+        let SecretMaterial::JWK { private_key_jwk } = &mut self.secret.secret_material;
+        private_key_jwk["kid"] = Value::String(kid.to_string());
+        self.secret.id = did.to_string();
+        self.did = did.to_string();
+        self.kid = kid.to_string();
+        self
+    }
+
     /// Extract the private key JWK from the secret
     fn private_key_jwk(&self) -> Result<&Value> {
         match &self.secret.secret_material {
@@ -540,11 +420,13 @@ impl SigningKey for LocalAgentKey {
                     .ok_or_else(|| Error::Cryptography("Missing private key in JWK".to_string()))?;
 
                 // Decode the private key from base64
-                let private_key_bytes = base64::engine::general_purpose::STANDARD
-                    .decode(private_key_base64)
-                    .map_err(|e| {
-                        Error::Cryptography(format!("Failed to decode private key: {}", e))
-                    })?;
+                let private_key_bytes = Zeroizing::new(
+                    base64::engine::general_purpose::STANDARD
+                        .decode(private_key_base64)
+                        .map_err(|e| {
+                            Error::Cryptography(format!("Failed to decode private key: {}", e))
+                        })?,
+                );
 
                 // Ed25519 keys must be exactly 32 bytes
                 if private_key_bytes.len() != 32 {
@@ -581,11 +463,16 @@ impl SigningKey for LocalAgentKey {
                     })?;
 
                 // Decode the private key from base64
-                let private_key_bytes = base64::engine::general_purpose::STANDARD
-                    .decode(private_key_base64)
-                    .map_err(|e| {
-                        Error::Cryptography(format!("Failed to decode P-256 private key: {}", e))
-                    })?;
+                let private_key_bytes = Zeroizing::new(
+                    base64::engine::general_purpose::STANDARD
+                        .decode(private_key_base64)
+                        .map_err(|e| {
+                            Error::Cryptography(format!(
+                                "Failed to decode P-256 private key: {}",
+                                e
+                            ))
+                        })?,
+                );
 
                 // Create a P-256 signing key
                 let signing_key = P256SigningKey::from_slice(&private_key_bytes).map_err(|e| {
@@ -608,14 +495,16 @@ impl SigningKey for LocalAgentKey {
                     })?;
 
                 // Decode the private key from base64
-                let private_key_bytes = base64::engine::general_purpose::STANDARD
-                    .decode(private_key_base64)
-                    .map_err(|e| {
-                        Error::Cryptography(format!(
-                            "Failed to decode secp256k1 private key: {}",
-                            e
-                        ))
-                    })?;
+                let private_key_bytes = Zeroizing::new(
+                    base64::engine::general_purpose::STANDARD
+                        .decode(private_key_base64)
+                        .map_err(|e| {
+                            Error::Cryptography(format!(
+                                "Failed to decode secp256k1 private key: {}",
+                                e
+                            ))
+                        })?,
+                );
 
                 // Create a secp256k1 signing key
                 let signing_key =
@@ -674,6 +563,7 @@ impl SigningKey for LocalAgentKey {
                 typ: crate::message::DIDCOMM_SIGNED.to_string(),
                 alg: self.recommended_jws_alg().as_str().to_string(),
                 kid: crate::agent_key::AgentKey::key_id(self).to_string(),
+                zip: None,
             }
         };
 
@@ -928,217 +818,17 @@ impl EncryptionKey for LocalAgentKey {
         &self,
         plaintext: &[u8],
         recipients: &[Arc<dyn VerificationKey>],
-        protected_header: Option<JweProtected>,
+        cipher: Option<JweCipher>,
     ) -> Result<Jwe> {
-        if recipients.is_empty() {
-            return Err(Error::Validation(
-                "No recipients specified for JWE".to_string(),
-            ));
-        }
-
-        // 1. Generate a random content encryption key (CEK)
-        let mut cek = [0u8; 32]; // 256-bit key for AES-GCM
-        OsRng.fill_bytes(&mut cek);
-
-        // 2. Generate IV for AES-GCM
-        let mut iv_bytes = [0u8; 12]; // 96-bit IV for AES-GCM
-        OsRng.fill_bytes(&mut iv_bytes);
-
-        // 3. Generate an ephemeral key pair for ECDH
-        #[cfg(feature = "crypto-p256")]
-        let ephemeral_secret = P256EphemeralSecret::random(&mut OsRng);
-        #[cfg(feature = "crypto-p256")]
-        let ephemeral_public_key = ephemeral_secret.public_key();
-
-        // 4. Convert the public key to coordinates for the header
-        #[cfg(feature = "crypto-p256")]
-        let point = ephemeral_public_key.to_encoded_point(false); // Uncompressed format
-        #[cfg(feature = "crypto-p256")]
-        let x_bytes = point.x().unwrap().to_vec();
-        #[cfg(feature = "crypto-p256")]
-        let y_bytes = point.y().unwrap().to_vec();
-
-        // Base64 encode the coordinates for the ephemeral public key
-        #[cfg(feature = "crypto-p256")]
-        let x_b64 = base64::engine::general_purpose::STANDARD.encode(&x_bytes);
-        #[cfg(feature = "crypto-p256")]
-        let y_b64 = base64::engine::general_purpose::STANDARD.encode(&y_bytes);
-
-        // Create the ephemeral public key structure
-        #[cfg(feature = "crypto-p256")]
-        let ephemeral_key = EphemeralPublicKey::Ec {
-            crv: "P-256".to_string(),
-            x: x_b64,
-            y: y_b64,
-        };
-        #[cfg(not(feature = "crypto-p256"))]
-        let ephemeral_key = EphemeralPublicKey::Ec {
-            crv: "P-256".to_string(),
-            x: "".to_string(),
-            y: "".to_string(),
-        };
-
-        // 5. Create protected header
-        let protected = protected_header.unwrap_or_else(|| {
+        let cipher = cipher.unwrap_or_else(|| {
             let (alg, enc) = self.recommended_jwe_alg_enc();
-            JweProtected {
-                epk: ephemeral_key,
-                apv: base64::engine::general_purpose::STANDARD.encode(Uuid::new_v4().as_bytes()),
-                apu: String::new(),
-                typ: crate::message::DIDCOMM_ENCRYPTED.to_string(),
-                enc: enc.as_str().to_string(),
-                alg: alg.as_str().to_string(),
+            JweCipher {
+                alg,
+                enc,
+                zip: None,
             }
         });
-
-        // 6. Encrypt the plaintext with AES-GCM
-        let cipher = Aes256Gcm::new_from_slice(&cek)
-            .map_err(|e| Error::Cryptography(format!("Failed to create AES-GCM cipher: {}", e)))?;
-
-        // Create a nonce from the IV
-        let nonce = Nonce::from_slice(&iv_bytes);
-
-        // Encrypt the plaintext
-        let mut buffer = plaintext.to_vec();
-        let tag = cipher
-            .encrypt_in_place_detached(nonce, b"", &mut buffer)
-            .map_err(|e| Error::Cryptography(format!("AES-GCM encryption failed: {}", e)))?;
-
-        // 7. Process each recipient and create JWE
-        #[cfg(not(feature = "crypto-p256"))]
-        {
-            let _ = (recipients, protected, buffer, tag, iv_bytes); // Suppress unused variable warnings
-            return Err(Error::Cryptography(
-                "P-256 encryption not available - enable crypto-p256 feature".to_string(),
-            ));
-        }
-
-        #[cfg(feature = "crypto-p256")]
-        {
-            let mut jwe_recipients = Vec::with_capacity(recipients.len());
-
-            for recipient in recipients {
-                // Extract recipient's public key as JWK
-                let recipient_jwk = recipient.public_key_jwk()?;
-
-                // Extract key type and curve
-                let kty = recipient_jwk.get("kty").and_then(|v| v.as_str());
-                let crv = recipient_jwk.get("crv").and_then(|v| v.as_str());
-
-                let encrypted_key = match (kty, crv) {
-                    (Some("EC"), Some("P-256")) => {
-                        // Extract the public key coordinates
-                        let x_b64 =
-                            recipient_jwk
-                                .get("x")
-                                .and_then(|v| v.as_str())
-                                .ok_or_else(|| {
-                                    Error::Cryptography(
-                                        "Missing x coordinate in recipient JWK".to_string(),
-                                    )
-                                })?;
-                        let y_b64 =
-                            recipient_jwk
-                                .get("y")
-                                .and_then(|v| v.as_str())
-                                .ok_or_else(|| {
-                                    Error::Cryptography(
-                                        "Missing y coordinate in recipient JWK".to_string(),
-                                    )
-                                })?;
-
-                        let x_bytes = base64::engine::general_purpose::STANDARD
-                            .decode(x_b64)
-                            .map_err(|e| {
-                                Error::Cryptography(format!("Failed to decode x coordinate: {}", e))
-                            })?;
-                        let y_bytes = base64::engine::general_purpose::STANDARD
-                            .decode(y_b64)
-                            .map_err(|e| {
-                                Error::Cryptography(format!("Failed to decode y coordinate: {}", e))
-                            })?;
-
-                        // Create a P-256 encoded point from the coordinates
-                        let mut point_bytes = vec![0x04]; // Uncompressed point format
-                        point_bytes.extend_from_slice(&x_bytes);
-                        point_bytes.extend_from_slice(&y_bytes);
-
-                        let encoded_point =
-                            P256EncodedPoint::from_bytes(&point_bytes).map_err(|e| {
-                                Error::Cryptography(format!(
-                                    "Failed to create P-256 encoded point: {}",
-                                    e
-                                ))
-                            })?;
-
-                        // This checks if the point is on the curve and returns the public key
-                        let recipient_pk_opt = P256PublicKey::from_encoded_point(&encoded_point);
-                        if recipient_pk_opt.is_none().into() {
-                            return Err(Error::Cryptography(
-                                "Invalid P-256 public key".to_string(),
-                            ));
-                        }
-                        let recipient_pk = recipient_pk_opt.unwrap();
-
-                        // Perform ECDH to derive a shared secret
-                        let shared_secret = ephemeral_secret.diffie_hellman(&recipient_pk);
-                        let shared_bytes = shared_secret.raw_secret_bytes();
-
-                        // Derive KEK using Concat KDF per RFC 7518
-                        // Use APV from protected header for consistency with decryption
-                        let apv_bytes = base64::engine::general_purpose::STANDARD
-                            .decode(&protected.apv)
-                            .unwrap_or_default();
-                        let kek = crate::crypto::derive_key_ecdh_es(
-                            shared_bytes.as_slice(),
-                            b"", // apu - empty for anonymous sender
-                            &apv_bytes,
-                            256, // 256 bits for AES-256-KW
-                        )?;
-
-                        // Wrap CEK with AES-KW per RFC 3394
-                        let mut kek_array = [0u8; 32];
-                        kek_array.copy_from_slice(&kek);
-
-                        crate::crypto::wrap_key_aes_kw(&kek_array, &cek)?
-                    }
-                    // Handle other key types
-                    _ => {
-                        return Err(Error::Cryptography(format!(
-                            "Unsupported recipient key type for encryption: kty={:?}, crv={:?}",
-                            kty, crv
-                        )));
-                    }
-                };
-
-                // Add this recipient to the JWE
-                jwe_recipients.push(JweRecipient {
-                    encrypted_key: base64::engine::general_purpose::STANDARD.encode(encrypted_key),
-                    header: JweHeader {
-                        kid: (**recipient).key_id().to_string(),
-                        sender_kid: Some(crate::agent_key::AgentKey::key_id(self).to_string()),
-                    },
-                });
-            }
-
-            // 8. Serialize and encode the protected header
-            let protected_json = serde_json::to_string(&protected).map_err(|e| {
-                Error::Serialization(format!("Failed to serialize protected header: {}", e))
-            })?;
-
-            let protected_b64 = base64::engine::general_purpose::STANDARD.encode(protected_json);
-
-            // 9. Create the JWE
-            let jwe = Jwe {
-                ciphertext: base64::engine::general_purpose::STANDARD.encode(buffer),
-                protected: protected_b64,
-                recipients: jwe_recipients,
-                tag: base64::engine::general_purpose::STANDARD.encode(tag),
-                iv: base64::engine::general_purpose::STANDARD.encode(iv_bytes),
-            };
-
-            Ok(jwe)
-        }
+        build_jwe(plaintext, recipients, &cipher, Some(self))
     }
 }
 
@@ -1164,6 +854,25 @@ impl DecryptionKey for LocalAgentKey {
     }
 
     async fn unwrap_jwe(&self, jwe: &Jwe) -> Result<Vec<u8>> {
+        self.open_jwe(jwe, None)
+    }
+
+    async fn unwrap_authcrypt_jwe(
+        &self,
+        jwe: &Jwe,
+        sender_key: &dyn VerificationKey,
+    ) -> Result<Vec<u8>> {
+        self.open_jwe(jwe, Some(sender_key))
+    }
+}
+
+impl LocalAgentKey {
+    /// Decrypt a JWE addressed to this key
+    ///
+    /// `sender_key` must be given for ECDH-1PU (authcrypt) and omitted for
+    /// ECDH-ES (anoncrypt), so that callers never mistake an anonymous
+    /// message for an authenticated one.
+    fn open_jwe(&self, jwe: &Jwe, sender_key: Option<&dyn VerificationKey>) -> Result<Vec<u8>> {
         let our_kid = crate::agent_key::AgentKey::key_id(self);
 
         // Find recipient matching our key ID or any X25519 key agreement key derived from our DID
@@ -1187,6 +896,8 @@ impl DecryptionKey for LocalAgentKey {
 
         let protected: JweProtected = serde_json::from_slice(&protected_bytes)
             .map_err(|e| Error::Cryptography(format!("Failed to parse protected header: {}", e)))?;
+        let alg: JweAlgorithm = protected.alg.parse()?;
+        let enc: JweEncryption = protected.enc.parse()?;
 
         // Decode the JWE elements (accept both base64 and base64url)
         let ciphertext = crate::message::base64_decode_flexible(&jwe.ciphertext)
@@ -1201,88 +912,6 @@ impl DecryptionKey for LocalAgentKey {
         let tag = crate::message::base64_decode_flexible(&jwe.tag)
             .map_err(|e| Error::Cryptography(format!("Failed to decode tag: {}", e)))?;
 
-        // Perform ECDH based on EPK type
-        let shared_bytes: Vec<u8> = match &protected.epk {
-            #[cfg(feature = "crypto-ed25519")]
-            EphemeralPublicKey::Okp { crv, x } if crv == "X25519" => {
-                // X25519 ECDH: convert our Ed25519 private key to X25519 and perform DH
-                let epk_bytes = crate::message::base64_decode_flexible(x).map_err(|e| {
-                    Error::Cryptography(format!("Failed to decode X25519 EPK: {}", e))
-                })?;
-                if epk_bytes.len() != 32 {
-                    return Err(Error::Cryptography("Invalid X25519 EPK length".to_string()));
-                }
-
-                // Get our Ed25519 private key and convert to X25519
-                let jwk = self.private_key_jwk()?;
-                let d_b64 = jwk.get("d").and_then(|v| v.as_str()).ok_or_else(|| {
-                    Error::Cryptography("Missing private key (d) in JWK".to_string())
-                })?;
-                let d_bytes = crate::message::base64_decode_flexible(d_b64).map_err(|e| {
-                    Error::Cryptography(format!("Failed to decode private key: {}", e))
-                })?;
-
-                // Ed25519 seed → X25519 secret: SHA-512 hash of seed, first 32 bytes
-                // x25519-dalek handles clamping internally
-                use sha2::Digest;
-                let hash = sha2::Sha512::digest(&d_bytes);
-                let mut x25519_key_bytes = [0u8; 32];
-                x25519_key_bytes.copy_from_slice(&hash[..32]);
-
-                let secret = x25519_dalek::StaticSecret::from(x25519_key_bytes);
-                let epk_array: [u8; 32] = epk_bytes[..32].try_into().unwrap();
-                let public = x25519_dalek::PublicKey::from(epk_array);
-                let shared = secret.diffie_hellman(&public);
-                shared.as_bytes().to_vec()
-            }
-            #[cfg(feature = "crypto-p256")]
-            EphemeralPublicKey::Ec { x, y, .. } => {
-                // P-256 ECDH
-                let epk_x = crate::message::base64_decode_flexible(x)
-                    .map_err(|e| Error::Cryptography(format!("Failed to decode EPK x: {}", e)))?;
-                let epk_y = crate::message::base64_decode_flexible(y)
-                    .map_err(|e| Error::Cryptography(format!("Failed to decode EPK y: {}", e)))?;
-
-                let mut epk_point_bytes = vec![0x04];
-                epk_point_bytes.extend_from_slice(&epk_x);
-                epk_point_bytes.extend_from_slice(&epk_y);
-
-                let epk_encoded_point = P256EncodedPoint::from_bytes(&epk_point_bytes)
-                    .map_err(|e| Error::Cryptography(format!("Invalid EPK point: {}", e)))?;
-
-                let epk_public_key = P256PublicKey::from_encoded_point(&epk_encoded_point);
-                if epk_public_key.is_none().into() {
-                    return Err(Error::Cryptography("Invalid EPK public key".to_string()));
-                }
-                let epk_public_key = epk_public_key.unwrap();
-
-                let jwk = self.private_key_jwk()?;
-                let d_b64 = jwk.get("d").and_then(|v| v.as_str()).ok_or_else(|| {
-                    Error::Cryptography("Missing private key (d) in JWK".to_string())
-                })?;
-                let d_bytes = base64::engine::general_purpose::STANDARD
-                    .decode(d_b64)
-                    .map_err(|e| {
-                        Error::Cryptography(format!("Failed to decode private key: {}", e))
-                    })?;
-
-                let secret_key = p256::SecretKey::from_bytes((&d_bytes[..]).into())
-                    .map_err(|e| Error::Cryptography(format!("Invalid private key: {}", e)))?;
-
-                let shared_secret = p256::ecdh::diffie_hellman(
-                    secret_key.to_nonzero_scalar(),
-                    epk_public_key.as_affine(),
-                );
-                shared_secret.raw_secret_bytes().to_vec()
-            }
-            _ => {
-                return Err(Error::Cryptography(
-                    "Unsupported EPK type for JWE decryption".to_string(),
-                ))
-            }
-        };
-
-        // Derive KEK using Concat KDF
         // Per RFC 7518 Section 4.6.2, apu and apv are base64url-decoded before use
         let apu = if protected.apu.is_empty() {
             Vec::new()
@@ -1295,36 +924,221 @@ impl DecryptionKey for LocalAgentKey {
             crate::message::base64_decode_flexible(&protected.apv).unwrap_or_default()
         };
 
-        let kek = crate::crypto::derive_key_ecdh_es(&shared_bytes, &apu, &apv, 256)?;
+        let our_secret = EcdhSecretKey::from_jwk(self.private_key_jwk()?)?;
+        let epk = EcdhPublicKey::from_epk(&protected.epk)?;
+        let ephemeral_shared = our_secret.diffie_hellman(&epk)?;
+
+        let kek = match (&alg, sender_key) {
+            (JweAlgorithm::EcdhEsA256kw, None) => {
+                crate::crypto::derive_key_ecdh_es(&ephemeral_shared, &apu, &apv, 256)?
+            }
+            (JweAlgorithm::Ecdh1puA256kw, Some(sender_key)) => {
+                if protected.skid.as_deref() != Some(sender_key.key_id()) {
+                    return Err(Error::Cryptography(format!(
+                        "Sender key {} does not match JWE skid {:?}",
+                        sender_key.key_id(),
+                        protected.skid
+                    )));
+                }
+                let sender_public = EcdhPublicKey::from_jwk(&sender_key.public_key_jwk()?)?;
+                let static_shared = our_secret.diffie_hellman(&sender_public)?;
+                crate::crypto::derive_key_ecdh_1pu(
+                    &ephemeral_shared,
+                    &static_shared,
+                    &apu,
+                    &apv,
+                    256,
+                    &tag,
+                )?
+            }
+            (JweAlgorithm::Ecdh1puA256kw, None) => {
+                return Err(Error::Cryptography(
+                    "ECDH-1PU JWE requires the sender's key; use unwrap_authcrypt_jwe".to_string(),
+                ))
+            }
+            (JweAlgorithm::EcdhEsA256kw, Some(_)) => {
+                return Err(Error::Cryptography(
+                    "JWE is anoncrypt (ECDH-ES) and does not authenticate a sender".to_string(),
+                ))
+            }
+            (JweAlgorithm::EcdhEs, _) => {
+                return Err(Error::Cryptography(
+                    "ECDH-ES direct key agreement is not supported".to_string(),
+                ))
+            }
+        };
 
         // Unwrap CEK using AES-KW
         let mut kek_array = [0u8; 32];
         kek_array.copy_from_slice(&kek);
         let cek = crate::crypto::unwrap_key_aes_kw(&kek_array, &wrapped_cek)?;
 
-        // Decrypt ciphertext with AES-GCM using the CEK
-        let cipher = Aes256Gcm::new_from_slice(&cek)
-            .map_err(|e| Error::Cryptography(format!("Failed to create AES-GCM cipher: {}", e)))?;
+        let plaintext = crate::crypto::decrypt_content(
+            enc,
+            &cek,
+            &iv,
+            &ciphertext,
+            &tag,
+            jwe.protected.as_bytes(),
+        )
+        .or_else(|e| match enc {
+            // Retry with empty AAD for backwards compatibility
+            JweEncryption::A256GCM => {
+                crate::crypto::decrypt_content(enc, &cek, &iv, &ciphertext, &tag, b"")
+            }
+            _ => Err(e),
+        })?;
+
+        crate::compression::decompress_payload(protected.zip.as_deref(), plaintext)
+    }
+}
+
+/// Create an anoncrypt (ECDH-ES+A256KW) JWE without a sender key
+///
+/// The key agreement curve is negotiated from the recipients' key types.
+pub fn create_anoncrypt_jwe(
+    plaintext: &[u8],
+    recipients: &[Arc<dyn VerificationKey>],
+    enc: JweEncryption,
+    zip: Option<CompressionCodec>,
+) -> Result<Jwe> {
+    build_jwe(
+        plaintext,
+        recipients,
+        &JweCipher::anoncrypt(enc).with_compression(zip),
+        None,
+    )
+}
+
+/// Build a JWE, negotiating the key agreement curve from the recipients' keys
+///
+/// All recipients must share a curve once Ed25519 keys are converted to
+/// X25519. For ECDH-1PU the sender key must use the same curve.
+fn build_jwe(
+    plaintext: &[u8],
+    recipients: &[Arc<dyn VerificationKey>],
+    cipher: &JweCipher,
+    sender: Option<&LocalAgentKey>,
+) -> Result<Jwe> {
+    if recipients.is_empty() {
+        return Err(Error::Validation(
+            "No recipients specified for JWE".to_string(),
+        ));
+    }
+
+    let mut recipient_keys = Vec::with_capacity(recipients.len());
+    for recipient in recipients {
+        let key = EcdhPublicKey::from_jwk(&recipient.public_key_jwk()?)?;
+        recipient_keys.push(((**recipient).key_id().to_string(), key));
+    }
 
-        let nonce = Nonce::from_slice(&iv);
+    let curve = recipient_keys[0].1.curve();
+    if let Some((kid, key)) = recipient_keys.iter().find(|(_, key)| key.curve() != curve) {
+        return Err(Error::Cryptography(format!(
+            "Recipient {} uses {} but other recipients use {}",
+            kid,
+            key.curve(),
+            curve
+        )));
+    }
 
-        let mut padded_tag = [0u8; 16];
-        let copy_len = std::cmp::min(tag.len(), 16);
-        padded_tag[..copy_len].copy_from_slice(&tag[..copy_len]);
-        let tag_array = aes_gcm::Tag::from_slice(&padded_tag);
+    let sender_key = match cipher.alg {
+        JweAlgorithm::EcdhEsA256kw => None,
+        JweAlgorithm::Ecdh1puA256kw => {
+            let sender = sender
+                .ok_or_else(|| Error::Cryptography("ECDH-1PU requires a sender key".to_string()))?;
+            let key = EcdhSecretKey::from_jwk(sender.private_key_jwk()?)?;
+            if key.curve() != curve {
+                return Err(Error::Cryptography(format!(
+                    "Sender key {} uses {} but recipients use {}",
+                    AgentKey::key_id(sender),
+                    key.curve(),
+                    curve
+                )));
+            }
+            Some((AgentKey::key_id(sender).to_string(), key))
+        }
+        JweAlgorithm::EcdhEs => {
+            return Err(Error::Cryptography(
+                "ECDH-ES direct key agreement is not supported".to_string(),
+            ))
+        }
+    };
+    let skid = sender_key.as_ref().map(|(kid, _)| kid.clone());
+    let (plaintext, zip) = crate::compression::maybe_compress(cipher.zip, plaintext)?;
+
+    let ephemeral = EcdhSecretKey::generate(curve)?;
+
+    // DIDComm v2: apu is the sender key ID, apv the hash of the sorted recipient key IDs
+    let apu = skid.clone().unwrap_or_default().into_bytes();
+    let mut kids: Vec<&str> = recipient_keys.iter().map(|(kid, _)| kid.as_str()).collect();
+    kids.sort_unstable();
+    let apv = Sha256::digest(kids.join(".").as_bytes()).to_vec();
+
+    let engine = base64::engine::general_purpose::STANDARD;
+    let protected = JweProtected {
+        epk: ephemeral.public_key().to_epk(),
+        apv: engine.encode(&apv),
+        apu: if apu.is_empty() {
+            String::new()
+        } else {
+            engine.encode(&apu)
+        },
+        skid: skid.clone(),
+        zip: zip.map(|codec| codec.as_str().to_string()),
+        typ: crate::message::DIDCOMM_ENCRYPTED.to_string(),
+        enc: cipher.enc.as_str().to_string(),
+        alg: cipher.alg.as_str().to_string(),
+    };
+    let protected_json = serde_json::to_string(&protected).map_err(|e| {
+        Error::Serialization(format!("Failed to serialize protected header: {}", e))
+    })?;
+    let protected_b64 = engine.encode(protected_json);
+
+    // The encoded protected header is the AAD
+    let cek = crate::crypto::generate_cek(cipher.enc);
+    let content =
+        crate::crypto::encrypt_content(cipher.enc, &cek, &plaintext, protected_b64.as_bytes())?;
+
+    let mut jwe_recipients = Vec::with_capacity(recipient_keys.len());
+    for (kid, recipient_key) in &recipient_keys {
+        let ephemeral_shared = ephemeral.diffie_hellman(recipient_key)?;
+        let kek = match &sender_key {
+            Some((_, sender_secret)) => {
+                // ECDH-1PU binds the content tag into the KEK
+                let static_shared = sender_secret.diffie_hellman(recipient_key)?;
+                crate::crypto::derive_key_ecdh_1pu(
+                    &ephemeral_shared,
+                    &static_shared,
+                    &apu,
+                    &apv,
+                    256,
+                    &content.tag,
+                )?
+            }
+            None => crate::crypto::derive_key_ecdh_es(&ephemeral_shared, &apu, &apv, 256)?,
+        };
 
-        let mut buffer = ciphertext.to_vec();
-        cipher
-            .decrypt_in_place_detached(nonce, jwe.protected.as_bytes(), &mut buffer, tag_array)
-            .or_else(|_| {
-                // Retry with empty AAD for backwards compatibility
-                buffer = ciphertext.to_vec();
-                cipher.decrypt_in_place_detached(nonce, b"", &mut buffer, tag_array)
-            })
-            .map_err(|e| Error::Cryptography(format!("AES-GCM decryption failed: {:?}", e)))?;
+        let mut kek_array = [0u8; 32];
+        kek_array.copy_from_slice(&kek);
+        let encrypted_key = crate::crypto::wrap_key_aes_kw(&kek_array, &cek)?;
 
-        Ok(buffer)
+        jwe_recipients.push(JweRecipient {
+            encrypted_key: engine.encode(encrypted_key),
+            header: JweHeader {
+                kid: kid.clone(),
+                sender_kid: skid.clone(),
+            },
+        });
     }
+
+    Ok(Jwe {
+        ciphertext: engine.encode(&content.ciphertext),
+        protected: protected_b64,
+        recipients: jwe_recipients,
+        tag: engine.encode(&content.tag),
+        iv: engine.encode(&content.iv),
+    })
 }
 
 /// A standalone verification key that can be used to verify signatures
@@ -1397,6 +1211,7 @@ impl PublicVerificationKey {
             typ: "JWT".to_string(),
             alg: alg.to_string(),
             kid: self.kid.clone(),
+            zip: None,
         };
 
         // Verify the signature
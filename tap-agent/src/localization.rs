@@ -0,0 +1,301 @@
+//! Localization of human-readable message fields
+//!
+//! Reject and cancel reasons, payment memos and invoice line descriptions are
+//! read by people on the counterparty's side. A [`MessageCatalog`] maps the
+//! source text of these fields to translations, keyed by BCP 47 language tag.
+//! When a message is sent to a single counterparty, the agent picks the first
+//! of the counterparty's preferred locales that the catalog covers, replaces
+//! every field it has a translation for, and records the locale in the
+//! DIDComm `lang` header. Text without a translation is sent unchanged.
+//!
+//! Counterparties advertise their preferred locales through the `locales`
+//! property of their `DIDCommMessaging` service; a local override can be set
+//! per DID in [`AgentConfig`](crate::config::AgentConfig):
+//!
+//! ```json
+//! {
+//!   "id": "did:web:example.com#didcomm",
+//!   "type": "DIDCommMessaging",
+//!   "serviceEndpoint": "https://example.com/didcomm",
+//!   "locales": ["de-CH", "fr"]
+//! }
+//! ```
+
+use crate::did::DIDDoc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// DID document service property listing the locales an agent prefers
+pub const LOCALES_SERVICE_PROPERTY: &str = "locales";
+
+/// DIDComm header recording the locale of a message's human-readable fields
+pub const LANG_HEADER: &str = "lang";
+
+/// Dotted paths of the body fields that are localized
+///
+/// Arrays along a path are traversed element by element, so
+/// `invoice.lineItems.description` covers every line item.
+pub const LOCALIZED_FIELDS: &[&str] = &[
+    "reason",
+    "memo",
+    "invoice.note",
+    "invoice.lineItems.description",
+];
+
+/// Source text mapped to its translations by locale
+type CatalogEntries = HashMap<String, HashMap<String, String>>;
+
+/// Translations of human-readable message text
+///
+/// Entries are keyed by the source text as the application writes it, so
+/// messages built without a catalog remain valid and untranslated text
+/// passes through. Serializes as `{"<source text>": {"<locale>": "<translation>"}}`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "CatalogEntries", into = "CatalogEntries")]
+pub struct MessageCatalog {
+    entries: CatalogEntries,
+}
+
+impl From<CatalogEntries> for MessageCatalog {
+    fn from(entries: CatalogEntries) -> Self {
+        let mut catalog = Self::new();
+        for (text, translations) in entries {
+            for (locale, translation) in translations {
+                catalog.register(&text, &locale, &translation);
+            }
+        }
+        catalog
+    }
+}
+
+impl From<MessageCatalog> for CatalogEntries {
+    fn from(catalog: MessageCatalog) -> Self {
+        catalog.entries
+    }
+}
+
+impl MessageCatalog {
+    /// Creates an empty catalog
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the translation of `text` into `locale`
+    pub fn register(&mut self, text: &str, locale: &str, translation: &str) {
+        self.entries
+            .entry(text.to_string())
+            .or_default()
+            .insert(normalize_locale(locale), translation.to_string());
+    }
+
+    /// Adds a translation, builder style
+    pub fn with_translation(mut self, text: &str, locale: &str, translation: &str) -> Self {
+        self.register(text, locale, translation);
+        self
+    }
+
+    /// Returns true if the catalog has no translations
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether any text has a translation usable for `locale`
+    pub fn supports(&self, locale: &str) -> bool {
+        let candidates = locale_candidates(locale);
+        self.entries.values().any(|translations| {
+            candidates
+                .iter()
+                .any(|candidate| translations.contains_key(candidate))
+        })
+    }
+
+    /// Translates `text` into `locale`
+    ///
+    /// Falls back from a regional tag to its language (`pt-BR` to `pt`).
+    pub fn translate(&self, text: &str, locale: &str) -> Option<&str> {
+        let translations = self.entries.get(text)?;
+        locale_candidates(locale)
+            .iter()
+            .find_map(|candidate| translations.get(candidate))
+            .map(String::as_str)
+    }
+
+    /// Picks the first of the counterparty's `preferred` locales the catalog supports
+    pub fn select_locale<'a>(&self, preferred: &'a [String]) -> Option<&'a str> {
+        preferred
+            .iter()
+            .find(|locale| self.supports(locale))
+            .map(String::as_str)
+    }
+
+    /// Translates the [`LOCALIZED_FIELDS`] of a message body in place
+    ///
+    /// Returns the locale used if at least one field was translated.
+    pub fn localize_body(&self, body: &mut Value, preferred: &[String]) -> Option<String> {
+        let locale = self.select_locale(preferred)?;
+        let mut translated = false;
+        for field in LOCALIZED_FIELDS {
+            let path: Vec<&str> = field.split('.').collect();
+            translated |= self.localize_path(body, &path, locale);
+        }
+        translated.then(|| locale.to_string())
+    }
+
+    fn localize_path(&self, value: &mut Value, path: &[&str], locale: &str) -> bool {
+        match (value, path.split_first()) {
+            (Value::Array(items), Some(_)) => items.iter_mut().fold(false, |acc, item| {
+                self.localize_path(item, path, locale) | acc
+            }),
+            (Value::Object(map), Some((key, rest))) => match map.get_mut(*key) {
+                Some(child) => self.localize_path(child, rest, locale),
+                None => false,
+            },
+            (Value::String(text), None) => match self.translate(text, locale) {
+                Some(translation) => {
+                    *text = translation.to_string();
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+/// Locales advertised by a DID document's `DIDCommMessaging` services, in order
+pub fn advertised_locales(did_doc: &DIDDoc) -> Vec<String> {
+    did_doc
+        .service
+        .iter()
+        .filter(|service| service.type_ == "DIDCommMessaging")
+        .filter_map(|service| service.properties.get(LOCALES_SERVICE_PROPERTY))
+        .filter_map(|value| value.as_array())
+        .flatten()
+        .filter_map(|value| value.as_str())
+        .filter(|locale| !locale.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Lowercases a language tag and accepts `_` as a subtag separator
+fn normalize_locale(locale: &str) -> String {
+    locale.trim().replace('_', "-").to_ascii_lowercase()
+}
+
+/// The tag itself followed by its primary language subtag, if different
+fn locale_candidates(locale: &str) -> Vec<String> {
+    let normalized = normalize_locale(locale);
+    let mut candidates = vec![normalized.clone()];
+    if let Some((language, _)) = normalized.split_once('-') {
+        candidates.push(language.to_string());
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::did::Service;
+    use serde_json::json;
+
+    fn catalog() -> MessageCatalog {
+        MessageCatalog::new()
+            .with_translation("Insufficient funds", "de", "Unzureichende Deckung")
+            .with_translation("Insufficient funds", "fr-CA", "Fonds insuffisants")
+            .with_translation("Consulting", "de", "Beratung")
+    }
+
+    #[test]
+    fn test_translate_falls_back_to_language() {
+        let catalog = catalog();
+        assert_eq!(
+            catalog.translate("Insufficient funds", "de_CH"),
+            Some("Unzureichende Deckung")
+        );
+        assert_eq!(
+            catalog.translate("Insufficient funds", "FR-ca"),
+            Some("Fonds insuffisants")
+        );
+        assert_eq!(catalog.translate("Insufficient funds", "fr"), None);
+        assert_eq!(catalog.translate("Unknown", "de"), None);
+    }
+
+    #[test]
+    fn test_select_locale_follows_preference_order() {
+        let catalog = catalog();
+        let preferred = vec!["ja".to_string(), "fr-CA".to_string(), "de".to_string()];
+        assert_eq!(catalog.select_locale(&preferred), Some("fr-CA"));
+        assert_eq!(catalog.select_locale(&["es".to_string()]), None);
+    }
+
+    #[test]
+    fn test_localize_body() {
+        let catalog = catalog();
+        let mut body = json!({
+            "reason": "Insufficient funds",
+            "memo": "Order 42",
+            "invoice": {
+                "lineItems": [
+                    {"description": "Consulting"},
+                    {"description": "Travel"}
+                ]
+            },
+            "originator": {"name": "Consulting"}
+        });
+
+        let locale = catalog.localize_body(&mut body, &["de-AT".to_string()]);
+        assert_eq!(locale.as_deref(), Some("de-AT"));
+        assert_eq!(body["reason"], "Unzureichende Deckung");
+        assert_eq!(body["memo"], "Order 42");
+        assert_eq!(body["invoice"]["lineItems"][0]["description"], "Beratung");
+        assert_eq!(body["invoice"]["lineItems"][1]["description"], "Travel");
+        assert_eq!(body["originator"]["name"], "Consulting");
+
+        let mut untranslated = json!({"reason": "Sanctions hit"});
+        assert_eq!(
+            catalog.localize_body(&mut untranslated, &["de".to_string()]),
+            None
+        );
+        assert_eq!(untranslated["reason"], "Sanctions hit");
+    }
+
+    #[test]
+    fn test_catalog_serialization() {
+        let catalog = catalog();
+        let json = serde_json::to_value(&catalog).unwrap();
+        assert_eq!(json["Consulting"]["de"], "Beratung");
+        let parsed: MessageCatalog = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, catalog);
+
+        let parsed: MessageCatalog =
+            serde_json::from_value(json!({"Travel": {"pt_BR": "Viagem"}})).unwrap();
+        assert_eq!(parsed.translate("Travel", "pt-br"), Some("Viagem"));
+    }
+
+    #[test]
+    fn test_advertised_locales_from_did_doc() {
+        let mut properties = HashMap::new();
+        properties.insert(
+            LOCALES_SERVICE_PROPERTY.to_string(),
+            json!(["de-CH", "", 7, "fr"]),
+        );
+        let did_doc = DIDDoc {
+            id: "did:web:example.com".to_string(),
+            verification_method: vec![],
+            authentication: vec![],
+            key_agreement: vec![],
+            assertion_method: vec![],
+            capability_invocation: vec![],
+            capability_delegation: vec![],
+            service: vec![Service {
+                id: "did:web:example.com#didcomm".to_string(),
+                type_: "DIDCommMessaging".to_string(),
+                service_endpoint: "https://example.com/didcomm".to_string(),
+                properties,
+            }],
+        };
+
+        assert_eq!(advertised_locales(&did_doc), vec!["de-CH", "fr"]);
+    }
+}
@@ -3,6 +3,7 @@
 //! This module provides constants and types for working with TAP messages,
 //! including security modes and message type identifiers.
 
+use crate::error::Error;
 use base64::{engine::general_purpose, Engine};
 use serde::de::{self, MapAccess, Visitor};
 use serde::ser::SerializeMap;
@@ -17,6 +18,65 @@ pub fn base64_decode_flexible(input: &str) -> Result<Vec<u8>, base64::DecodeErro
         .or_else(|_| general_purpose::STANDARD_NO_PAD.decode(input))
 }
 
+/// Whether every segment of a compact serialization is base64url text
+fn is_base64url(segment: &str) -> bool {
+    segment
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// Decode the protected header of a compact serialization to a JSON object
+fn compact_header(
+    protected: &str,
+) -> crate::error::Result<serde_json::Map<String, serde_json::Value>> {
+    let bytes = base64_decode_flexible(protected)
+        .map_err(|e| Error::Serialization(format!("Invalid compact protected header: {}", e)))?;
+    match serde_json::from_slice(&bytes) {
+        Ok(serde_json::Value::Object(header)) => Ok(header),
+        _ => Err(Error::Serialization(
+            "Compact protected header is not a JSON object".to_string(),
+        )),
+    }
+}
+
+/// Convert an envelope in compact serialization to its JSON serialization
+///
+/// A compact JWS (`protected.payload.signature`) becomes a flattened JWS, and
+/// a compact JWE (`protected.encrypted_key.iv.ciphertext.tag`) a JWE with a
+/// single recipient. Returns None if `compact` is neither.
+pub fn compact_to_json(compact: &str) -> Option<serde_json::Value> {
+    match compact.split('.').count() {
+        3 => Jws::from_compact(compact)
+            .ok()
+            .and_then(|jws| serde_json::to_value(jws).ok()),
+        5 => Jwe::from_compact(compact)
+            .ok()
+            .and_then(|jwe| serde_json::to_value(jwe).ok()),
+        _ => None,
+    }
+}
+
+/// Normalize a received envelope to JSON serialization
+///
+/// A JSON string holding a compact JWS or JWE is converted with
+/// [`compact_to_json`]; anything else is returned unchanged.
+pub fn normalize_envelope(message: serde_json::Value) -> serde_json::Value {
+    match &message {
+        serde_json::Value::String(compact) => compact_to_json(compact.trim()).unwrap_or(message),
+        _ => message,
+    }
+}
+
+/// Parse a received envelope given as JSON or in compact serialization
+pub fn parse_envelope(raw: &str) -> crate::error::Result<serde_json::Value> {
+    if let Some(value) = compact_to_json(raw.trim()) {
+        return Ok(value);
+    }
+    serde_json::from_str(raw)
+        .map(normalize_envelope)
+        .map_err(|e| Error::Serialization(format!("Failed to parse message as JSON: {}", e)))
+}
+
 /// Security mode for message packing and unpacking.
 ///
 /// Defines the level of protection applied to messages:
@@ -39,6 +99,17 @@ pub enum SecurityMode {
     Any,
 }
 
+impl SecurityMode {
+    /// Purpose of the sender key that packing in this mode uses, if any
+    pub fn sender_key_purpose(&self) -> Option<crate::did::KeyPurpose> {
+        match self {
+            SecurityMode::Signed => Some(crate::did::KeyPurpose::Authentication),
+            SecurityMode::AuthCrypt => Some(crate::did::KeyPurpose::KeyAgreement),
+            SecurityMode::Plain | SecurityMode::AnonCrypt | SecurityMode::Any => None,
+        }
+    }
+}
+
 /// Message type identifiers used by the TAP Protocol
 /// These constant strings are used to identify different message types
 /// in the TAP protocol communications.
@@ -56,13 +127,64 @@ pub const DIDCOMM_ENCRYPTED: &str = "application/didcomm-encrypted+json";
 /// - Single signature: uses Flattened JWS format (`protected`, `payload`, `signature` at top level)
 /// - Multiple signatures: uses General JWS format (`payload`, `signatures` array)
 ///
-/// When deserializing: accepts both formats.
+/// When deserializing: accepts both formats. Compact serialization is
+/// converted with [`Jws::from_compact`].
 #[derive(Debug)]
 pub struct Jws {
     pub payload: String,
     pub signatures: Vec<JwsSignature>,
 }
 
+impl Jws {
+    /// Parse a JWS in compact serialization: `protected.payload.signature`
+    ///
+    /// Detached payloads are not supported, since the payload is the message.
+    pub fn from_compact(compact: &str) -> crate::error::Result<Self> {
+        let parts: Vec<&str> = compact.split('.').collect();
+        let [protected, payload, signature] = parts[..] else {
+            return Err(Error::Serialization(format!(
+                "Compact JWS must have 3 segments, found {}",
+                parts.len()
+            )));
+        };
+        if !parts.iter().all(|part| is_base64url(part)) {
+            return Err(Error::Serialization(
+                "Compact JWS segments must be base64url encoded".to_string(),
+            ));
+        }
+        if payload.is_empty() || signature.is_empty() {
+            return Err(Error::Serialization(
+                "Compact JWS must have a payload and a signature".to_string(),
+            ));
+        }
+        if !compact_header(protected)?.contains_key("alg") {
+            return Err(Error::Serialization(
+                "Compact JWS protected header has no 'alg'".to_string(),
+            ));
+        }
+
+        Ok(Jws {
+            payload: payload.to_string(),
+            signatures: vec![JwsSignature {
+                protected: protected.to_string(),
+                signature: signature.to_string(),
+            }],
+        })
+    }
+
+    /// Serialize a JWS with a single signature compactly, None if it has
+    /// several
+    pub fn to_compact(&self) -> Option<String> {
+        match &self.signatures[..] {
+            [signature] => Some(format!(
+                "{}.{}.{}",
+                signature.protected, self.payload, signature.signature
+            )),
+            _ => None,
+        }
+    }
+}
+
 impl Serialize for Jws {
     fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
         if self.signatures.len() == 1 {
@@ -158,6 +280,9 @@ pub struct JwsProtected {
     pub typ: String,
     pub alg: String,
     pub kid: String,
+    /// Compression codec applied to the payload before signing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zip: Option<String>,
 }
 
 // Helper function for JwsProtected typ default
@@ -194,6 +319,68 @@ pub struct Jwe {
     pub iv: String,
 }
 
+impl Jwe {
+    /// Parse a JWE in compact serialization:
+    /// `protected.encrypted_key.iv.ciphertext.tag`
+    ///
+    /// Compact serialization has no per-recipient header, so the recipient's
+    /// key ID is taken from the `kid` of the protected header.
+    pub fn from_compact(compact: &str) -> crate::error::Result<Self> {
+        let parts: Vec<&str> = compact.split('.').collect();
+        let [protected, encrypted_key, iv, ciphertext, tag] = parts[..] else {
+            return Err(Error::Serialization(format!(
+                "Compact JWE must have 5 segments, found {}",
+                parts.len()
+            )));
+        };
+        if !parts.iter().all(|part| is_base64url(part)) {
+            return Err(Error::Serialization(
+                "Compact JWE segments must be base64url encoded".to_string(),
+            ));
+        }
+        let header = compact_header(protected)?;
+        let kid = header
+            .get("kid")
+            .and_then(|kid| kid.as_str())
+            .ok_or_else(|| {
+                Error::Serialization(
+                    "Compact JWE protected header has no recipient 'kid'".to_string(),
+                )
+            })?;
+
+        Ok(Jwe {
+            ciphertext: ciphertext.to_string(),
+            protected: protected.to_string(),
+            recipients: vec![JweRecipient {
+                encrypted_key: encrypted_key.to_string(),
+                header: JweHeader {
+                    kid: kid.to_string(),
+                    sender_kid: None,
+                },
+            }],
+            tag: tag.to_string(),
+            iv: iv.to_string(),
+        })
+    }
+
+    /// Decodes and returns the protected header
+    pub fn get_protected_header(&self) -> Result<JweProtected, Box<dyn std::error::Error>> {
+        let protected_bytes = base64_decode_flexible(&self.protected)?;
+        let protected = serde_json::from_slice::<JweProtected>(&protected_bytes)?;
+        Ok(protected)
+    }
+
+    /// Returns the sender key ID of an authcrypt (ECDH-1PU) JWE
+    pub fn authcrypt_sender_kid(&self) -> Option<String> {
+        let protected = self.get_protected_header().ok()?;
+        if protected.alg.starts_with("ECDH-1PU") {
+            protected.skid
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct JweRecipient {
     pub encrypted_key: String,
@@ -215,6 +402,11 @@ pub struct JweProtected {
     pub apv: String,
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub apu: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skid: Option<String>,
+    /// Compression codec applied to the plaintext before encryption
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zip: Option<String>,
     #[serde(default = "default_didcomm_encrypted")]
     pub typ: String,
     pub enc: String,
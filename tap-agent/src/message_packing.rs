@@ -4,7 +4,8 @@
 //! how messages are prepared for transmission (packed) and processed
 //! upon receipt (unpacked).
 
-use crate::agent_key::VerificationKey;
+use crate::agent_key::{unwrap_jwe_from_sender, JweCipher, JweEncryption, VerificationKey};
+use crate::compression::CompressionCodec;
 use crate::error::{Error, Result};
 use crate::message::{Jwe, Jws, SecurityMode};
 use async_trait::async_trait;
@@ -16,7 +17,6 @@ use std::fmt::Debug;
 use std::sync::Arc;
 use tap_msg::didcomm::{PlainMessage, PlainMessageExt};
 use tap_msg::message::TapMessage;
-use uuid::Uuid;
 
 /// Result of unpacking a message containing both the PlainMessage
 /// and the parsed TAP message
@@ -114,6 +114,12 @@ pub struct PackOptions {
     pub recipient_kid: Option<String>,
     /// Key ID of the sender (for JWS and JWE)
     pub sender_kid: Option<String>,
+    /// Content encryption for JWE; defaults to A256CBC-HS512 for AuthCrypt
+    /// and A256GCM for AnonCrypt
+    pub content_encryption: Option<JweEncryption>,
+    /// Payload compression, normally negotiated from the recipient's
+    /// advertised codecs; small payloads are never compressed
+    pub compression: Option<CompressionCodec>,
 }
 
 impl Default for PackOptions {
@@ -129,6 +135,8 @@ impl PackOptions {
             security_mode: SecurityMode::Plain,
             recipient_kid: None,
             sender_kid: None,
+            content_encryption: None,
+            compression: None,
         }
     }
 
@@ -158,10 +166,36 @@ impl PackOptions {
         self
     }
 
+    /// Set the JWE content encryption algorithm
+    pub fn with_content_encryption(mut self, enc: JweEncryption) -> Self {
+        self.content_encryption = Some(enc);
+        self
+    }
+
+    /// Compress signed and encrypted payloads with the given codec
+    pub fn with_compression(mut self, codec: CompressionCodec) -> Self {
+        self.compression = Some(codec);
+        self
+    }
+
     /// Get the security mode
     pub fn security_mode(&self) -> SecurityMode {
         self.security_mode
     }
+
+    /// The cipher used for AuthCrypt (ECDH-1PU+A256KW)
+    pub fn authcrypt_cipher(&self) -> JweCipher {
+        JweCipher::authcrypt(
+            self.content_encryption
+                .unwrap_or(JweEncryption::A256CBCHS512),
+        )
+        .with_compression(self.compression)
+    }
+
+    /// The content encryption used for AnonCrypt (ECDH-ES+A256KW)
+    pub fn anoncrypt_encryption(&self) -> JweEncryption {
+        self.content_encryption.unwrap_or(JweEncryption::A256GCM)
+    }
 }
 
 /// Options for unpacking a message
@@ -273,17 +307,20 @@ impl Packable for PlainMessage {
                 // Prepare the message payload to sign
                 let payload =
                     serde_json::to_string(self).map_err(|e| Error::Serialization(e.to_string()))?;
+                let (payload, zip) =
+                    crate::compression::maybe_compress(options.compression, payload.as_bytes())?;
 
                 // Create protected header with the sender_kid
                 let protected_header = crate::message::JwsProtected {
                     typ: crate::message::DIDCOMM_SIGNED.to_string(),
                     alg: String::new(), // Will be set by create_jws based on key type
                     kid: sender_kid.clone(),
+                    zip: zip.map(|codec| codec.as_str().to_string()),
                 };
 
                 // Create a JWS
                 let jws = signing_key
-                    .create_jws(payload.as_bytes(), Some(protected_header))
+                    .create_jws(&payload, Some(protected_header))
                     .await
                     .map_err(|e| Error::Cryptography(format!("Failed to create JWS: {}", e)))?;
 
@@ -312,7 +349,11 @@ impl Packable for PlainMessage {
 
                 // Create a JWE for the recipient
                 let jwe = encryption_key
-                    .create_jwe(plaintext.as_bytes(), &[recipient_key], None)
+                    .create_jwe(
+                        plaintext.as_bytes(),
+                        &[recipient_key],
+                        Some(options.authcrypt_cipher()),
+                    )
                     .await
                     .map_err(|e| Error::Cryptography(format!("Failed to create JWE: {}", e)))?;
 
@@ -325,17 +366,6 @@ impl Packable for PlainMessage {
                     Error::Validation("AnonCrypt mode requires recipient_kid".to_string())
                 })?;
 
-                // We need some key for encryption - use the first available key if no sender specified
-                let encryption_key = if let Some(sender_kid) = &options.sender_kid {
-                    key_manager.get_encryption_key(sender_kid).await?
-                } else {
-                    // For anonymous encryption, we can use any available encryption key
-                    // In practice, this might need to be handled differently depending on requirements
-                    return Err(Error::Validation(
-                        "AnonCrypt mode requires a temporary encryption key".to_string(),
-                    ));
-                };
-
                 // Get the recipient's verification key
                 let recipient_key = key_manager.resolve_verification_key(&recipient_kid).await?;
 
@@ -344,10 +374,13 @@ impl Packable for PlainMessage {
                     serde_json::to_string(self).map_err(|e| Error::Serialization(e.to_string()))?;
 
                 // Create a JWE for the recipient without sender information
-                let jwe = encryption_key
-                    .create_jwe(plaintext.as_bytes(), &[recipient_key], None)
-                    .await
-                    .map_err(|e| Error::Cryptography(format!("Failed to create JWE: {}", e)))?;
+                let jwe = crate::local_agent_key::create_anoncrypt_jwe(
+                    plaintext.as_bytes(),
+                    &[recipient_key],
+                    options.anoncrypt_encryption(),
+                    options.compression,
+                )
+                .map_err(|e| Error::Cryptography(format!("Failed to create JWE: {}", e)))?;
 
                 // Serialize the JWE
                 serde_json::to_string(&jwe).map_err(|e| Error::Serialization(e.to_string()))
@@ -412,7 +445,7 @@ where
             let id_string = obj
                 .get("id")
                 .map(|v| v.as_str().unwrap_or_default().to_string())
-                .unwrap_or_else(|| Uuid::new_v4().to_string());
+                .unwrap_or_else(tap_msg::id::new_id);
             let id = id_string.as_str();
 
             // Extract type, or use default
@@ -455,17 +488,20 @@ where
             // Prepare the message payload to sign
             let payload = serde_json::to_string(&plain_message)
                 .map_err(|e| Error::Serialization(e.to_string()))?;
+            let (payload, zip) =
+                crate::compression::maybe_compress(options.compression, payload.as_bytes())?;
 
             // Create protected header with the sender_kid
             let protected_header = crate::message::JwsProtected {
                 typ: crate::message::DIDCOMM_SIGNED.to_string(),
                 alg: String::new(), // Will be set by create_jws based on key type
                 kid: sender_kid.clone(),
+                zip: zip.map(|codec| codec.as_str().to_string()),
             };
 
             // Create a JWS
             let jws = signing_key
-                .create_jws(payload.as_bytes(), Some(protected_header))
+                .create_jws(&payload, Some(protected_header))
                 .await
                 .map_err(|e| Error::Cryptography(format!("Failed to create JWS: {}", e)))?;
 
@@ -501,7 +537,7 @@ where
             let id_string = obj
                 .get("id")
                 .map(|v| v.as_str().unwrap_or_default().to_string())
-                .unwrap_or_else(|| Uuid::new_v4().to_string());
+                .unwrap_or_else(tap_msg::id::new_id);
             let id = id_string.as_str();
 
             // Extract type, or use default
@@ -547,7 +583,11 @@ where
 
             // Create a JWE for the recipient
             let jwe = encryption_key
-                .create_jwe(plaintext.as_bytes(), &[recipient_key], None)
+                .create_jwe(
+                    plaintext.as_bytes(),
+                    &[recipient_key],
+                    Some(options.authcrypt_cipher()),
+                )
                 .await
                 .map_err(|e| Error::Cryptography(format!("Failed to create JWE: {}", e)))?;
 
@@ -560,16 +600,6 @@ where
                 Error::Validation("AnonCrypt mode requires recipient_kid".to_string())
             })?;
 
-            // We need some key for encryption - use the first available key if no sender specified
-            let encryption_key = if let Some(sender_kid) = &options.sender_kid {
-                key_manager.get_encryption_key(sender_kid).await?
-            } else {
-                // For anonymous encryption, we can use any available encryption key
-                return Err(Error::Validation(
-                    "AnonCrypt mode requires a temporary encryption key".to_string(),
-                ));
-            };
-
             // Get the recipient's verification key
             let recipient_key = key_manager.resolve_verification_key(&recipient_kid).await?;
 
@@ -584,7 +614,7 @@ where
             let id_string = obj
                 .get("id")
                 .map(|v| v.as_str().unwrap_or_default().to_string())
-                .unwrap_or_else(|| Uuid::new_v4().to_string());
+                .unwrap_or_else(tap_msg::id::new_id);
 
             let msg_type = obj
                 .get("type")
@@ -620,10 +650,13 @@ where
                 .map_err(|e| Error::Serialization(e.to_string()))?;
 
             // Create a JWE for the recipient without sender information
-            let jwe = encryption_key
-                .create_jwe(plaintext.as_bytes(), &[recipient_key], None)
-                .await
-                .map_err(|e| Error::Cryptography(format!("Failed to create JWE: {}", e)))?;
+            let jwe = crate::local_agent_key::create_anoncrypt_jwe(
+                plaintext.as_bytes(),
+                &[recipient_key],
+                options.anoncrypt_encryption(),
+                options.compression,
+            )
+            .map_err(|e| Error::Cryptography(format!("Failed to create JWE: {}", e)))?;
 
             // Serialize the JWE
             serde_json::to_string(&jwe).map_err(|e| Error::Serialization(e.to_string()))
@@ -649,16 +682,8 @@ impl<T: DeserializeOwned + Send + 'static> Unpackable<Jws, T> for Jws {
         let payload_bytes = crate::message::base64_decode_flexible(&packed_message.payload)
             .map_err(|e| Error::Cryptography(format!("Failed to decode JWS payload: {}", e)))?;
 
-        // Convert to string
-        let payload_str = String::from_utf8(payload_bytes)
-            .map_err(|e| Error::Validation(format!("Invalid UTF-8 in payload: {}", e)))?;
-
-        // Parse as PlainMessage first
-        let plain_message: PlainMessage =
-            serde_json::from_str(&payload_str).map_err(|e| Error::Serialization(e.to_string()))?;
-
-        // Verify signatures
-        let mut verified = false;
+        // Verify signatures, keeping the protected header of the one that verified
+        let mut verified_header = None;
 
         for signature in &packed_message.signatures {
             // Decode the protected header (accept both base64 and base64url)
@@ -698,23 +723,33 @@ impl<T: DeserializeOwned + Send + 'static> Unpackable<Jws, T> for Jws {
                 .await
             {
                 Ok(true) => {
-                    verified = true;
+                    verified_header = Some(protected);
                     break;
                 }
                 _ => continue,
             }
         }
 
-        if !verified {
-            return Err(Error::Cryptography(
-                "Signature verification failed".to_string(),
-            ));
-        }
+        let protected = verified_header
+            .ok_or_else(|| Error::Cryptography("Signature verification failed".to_string()))?;
+
+        // Reverse any compression applied before signing
+        let payload_bytes =
+            crate::compression::decompress_payload(protected.zip.as_deref(), payload_bytes)?;
+
+        // Convert to string
+        let payload_str = String::from_utf8(payload_bytes)
+            .map_err(|e| Error::Validation(format!("Invalid UTF-8 in payload: {}", e)))?;
+
+        // Parse as PlainMessage
+        let plain_message: PlainMessage =
+            serde_json::from_str(&payload_str).map_err(|e| Error::Serialization(e.to_string()))?;
 
         // If we want the PlainMessage itself, return it
         if std::any::TypeId::of::<T>() == std::any::TypeId::of::<PlainMessage>() {
             // This is safe because we've verified that T is PlainMessage
-            let result = serde_json::to_value(plain_message).unwrap();
+            let result = serde_json::to_value(plain_message)
+                .map_err(|e| Error::Serialization(e.to_string()))?;
             return serde_json::from_value(result).map_err(|e| Error::Serialization(e.to_string()));
         }
 
@@ -744,6 +779,13 @@ impl<T: DeserializeOwned + Send + 'static> Unpackable<Jwe, T> for Jwe {
             packed_message.recipients.iter().collect::<Vec<_>>()
         };
 
+        // Authcrypt JWEs are decrypted against the sender's public key
+        let sender_key = match packed_message.authcrypt_sender_kid() {
+            Some(skid) => Some(key_manager.resolve_verification_key(&skid).await?),
+            None => None,
+        };
+        let sender_key = sender_key.as_deref().map(|k| k as &dyn VerificationKey);
+
         // Try each recipient until we find one we can decrypt
         let mut last_error = None;
         for recipient in recipients {
@@ -760,7 +802,8 @@ impl<T: DeserializeOwned + Send + 'static> Unpackable<Jwe, T> for Jwe {
             };
 
             // Try to decrypt
-            match decryption_key.unwrap_jwe(packed_message).await {
+            match unwrap_jwe_from_sender(decryption_key.as_ref(), packed_message, sender_key).await
+            {
                 Ok(plaintext) => {
                     // Convert to string
                     let plaintext_str = String::from_utf8(plaintext).map_err(|e| {
@@ -778,7 +821,8 @@ impl<T: DeserializeOwned + Send + 'static> Unpackable<Jwe, T> for Jwe {
                     // If we want the PlainMessage itself, return it
                     if std::any::TypeId::of::<T>() == std::any::TypeId::of::<PlainMessage>() {
                         // This is safe because we've verified that T is PlainMessage
-                        let result = serde_json::to_value(plain_message).unwrap();
+                        let result = serde_json::to_value(plain_message)
+                            .map_err(|e| Error::Serialization(e.to_string()))?;
                         return serde_json::from_value(result)
                             .map_err(|e| Error::Serialization(e.to_string()));
                     }
@@ -811,6 +855,16 @@ impl<T: DeserializeOwned + Send + 'static> Unpackable<String, T> for String {
         key_manager: &(impl KeyManagerPacking + ?Sized),
         options: UnpackOptions,
     ) -> Result<T> {
+        // Convert compact JWS and JWE serializations to JSON
+        let converted;
+        let packed_message = match crate::message::compact_to_json(packed_message.trim()) {
+            Some(value) => {
+                converted = value.to_string();
+                &converted
+            }
+            None => packed_message,
+        };
+
         // Try to parse as JSON first
         if let Ok(value) = serde_json::from_str::<Value>(packed_message) {
             // Check if it's a JWS (General or Flattened serialization)
@@ -847,7 +901,8 @@ impl<T: DeserializeOwned + Send + 'static> Unpackable<String, T> for String {
                 // If we want the PlainMessage itself, return it
                 if std::any::TypeId::of::<T>() == std::any::TypeId::of::<PlainMessage>() {
                     // This is safe because we've verified that T is PlainMessage
-                    let result = serde_json::to_value(plain).unwrap();
+                    let result = serde_json::to_value(plain)
+                        .map_err(|e| Error::Serialization(e.to_string()))?;
                     return serde_json::from_value(result)
                         .map_err(|e| Error::Serialization(e.to_string()));
                 }
@@ -1031,6 +1086,89 @@ mod tests {
         assert_eq!(unpacked.to, message.to);
     }
 
+    #[tokio::test]
+    async fn test_compact_jws_unpack() {
+        let key_manager = Arc::new(AgentKeyManagerBuilder::new().build().unwrap());
+        let key = key_manager
+            .generate_key(DIDGenerationOptions {
+                key_type: KeyType::Ed25519,
+            })
+            .unwrap();
+        let sender_kid = key.did_doc.verification_method[0].id.clone();
+
+        let message = PlainMessage::new(
+            "test-compact-1".to_string(),
+            "https://example.org/test".to_string(),
+            serde_json::json!({ "content": "Compact message" }),
+            key.did.clone(),
+        );
+        let packed = message
+            .pack(&*key_manager, PackOptions::new().with_sign(&sender_kid))
+            .await
+            .unwrap();
+        let compact = serde_json::from_str::<Jws>(&packed)
+            .unwrap()
+            .to_compact()
+            .unwrap();
+        assert_eq!(compact.split('.').count(), 3);
+
+        // Unpacks as is, and with surrounding whitespace as sent over HTTP
+        for input in [compact.clone(), format!("{}\r\n", compact)] {
+            let unpacked: PlainMessage =
+                String::unpack(&input, &*key_manager, UnpackOptions::new())
+                    .await
+                    .unwrap();
+            assert_eq!(unpacked.id, message.id);
+            assert_eq!(unpacked.body, message.body);
+        }
+
+        // A tampered payload fails verification like any other JWS
+        let mut parts: Vec<&str> = compact.split('.').collect();
+        let other = {
+            use base64::Engine;
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(b"{}")
+        };
+        parts[1] = &other;
+        let tampered = parts.join(".");
+        let result: Result<PlainMessage> =
+            String::unpack(&tampered, &*key_manager, UnpackOptions::new()).await;
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_compact_jwe_parse() {
+        use base64::Engine;
+        let encode = |bytes: &[u8]| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+        let protected = encode(
+            br#"{"alg":"ECDH-ES+A256KW","enc":"A256GCM","kid":"did:example:bob#key-1","epk":{"kty":"OKP","crv":"X25519","x":"AAAA"}}"#,
+        );
+        let compact = format!("{}.a2V5.aXY.Y2lwaGVy.dGFn", protected);
+
+        let jwe = Jwe::from_compact(&compact).unwrap();
+        assert_eq!(jwe.protected, protected);
+        assert_eq!(jwe.recipients.len(), 1);
+        assert_eq!(jwe.recipients[0].header.kid, "did:example:bob#key-1");
+        assert_eq!(jwe.recipients[0].encrypted_key, "a2V5");
+        assert_eq!(
+            (jwe.iv.as_str(), jwe.ciphertext.as_str(), jwe.tag.as_str()),
+            ("aXY", "Y2lwaGVy", "dGFn")
+        );
+        let value = crate::message::compact_to_json(&compact).unwrap();
+        assert_eq!(
+            value["recipients"][0]["header"]["kid"],
+            "did:example:bob#key-1"
+        );
+
+        // The recipient key must be named in the protected header
+        let anonymous = format!(
+            "{}.a2V5.aXY.Y2lwaGVy.dGFn",
+            encode(br#"{"alg":"ECDH-ES+A256KW"}"#)
+        );
+        assert!(Jwe::from_compact(&anonymous).is_err());
+        assert!(crate::message::compact_to_json("a.b.c.d").is_none());
+        assert!(crate::message::compact_to_json("e30.!!.sig").is_none());
+    }
+
     #[tokio::test]
     async fn test_different_key_types_jws() {
         // Test with different key types
@@ -1197,6 +1335,111 @@ mod tests {
         );
     }
 
+    fn encrypted_test_message(from: &str, to: &str) -> PlainMessage {
+        PlainMessage {
+            id: "test-encrypted".to_string(),
+            typ: "application/didcomm-plain+json".to_string(),
+            type_: "https://example.org/test".to_string(),
+            body: serde_json::json!({"content": "Encrypted"}),
+            from: from.to_string(),
+            to: vec![to.to_string()],
+            thid: None,
+            pthid: None,
+            created_time: Some(1234567890),
+            expires_time: None,
+            from_prior: None,
+            attachments: None,
+            extra_headers: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_pack_unpack_key_types() {
+        for key_type in [KeyType::Ed25519, KeyType::P256, KeyType::Secp256k1] {
+            let key_manager = Arc::new(AgentKeyManagerBuilder::new().build().unwrap());
+            let sender = key_manager
+                .generate_key(DIDGenerationOptions { key_type })
+                .unwrap();
+            let recipient = key_manager
+                .generate_key(DIDGenerationOptions { key_type })
+                .unwrap();
+
+            let sender_kid = sender.did_doc.verification_method[0].id.clone();
+            let recipient_kid = recipient.did_doc.verification_method[0].id.clone();
+            let message = encrypted_test_message(&sender.did, &recipient.did);
+
+            let options = [
+                PackOptions {
+                    security_mode: SecurityMode::AuthCrypt,
+                    sender_kid: Some(sender_kid.clone()),
+                    recipient_kid: Some(recipient_kid.clone()),
+                    content_encryption: None,
+                    compression: None,
+                },
+                PackOptions {
+                    security_mode: SecurityMode::AnonCrypt,
+                    sender_kid: None,
+                    recipient_kid: Some(recipient_kid.clone()),
+                    content_encryption: Some(JweEncryption::A256CBCHS512),
+                    compression: None,
+                },
+            ];
+
+            for pack_options in options {
+                let (expected_alg, expected_enc) = match pack_options.security_mode {
+                    SecurityMode::AuthCrypt => ("ECDH-1PU+A256KW", "A256CBC-HS512"),
+                    _ => ("ECDH-ES+A256KW", "A256CBC-HS512"),
+                };
+                let packed = message.pack(&*key_manager, pack_options).await.unwrap();
+
+                let jwe: Jwe = serde_json::from_str(&packed).unwrap();
+                let protected = jwe.get_protected_header().unwrap();
+                assert_eq!(protected.alg, expected_alg);
+                assert_eq!(protected.enc, expected_enc);
+
+                let unpacked: PlainMessage =
+                    String::unpack(&packed, &*key_manager, UnpackOptions::new())
+                        .await
+                        .unwrap();
+                assert_eq!(unpacked.id, "test-encrypted");
+                assert_eq!(unpacked.body, message.body);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_authcrypt_unpack_cross_agent_with_did_key() {
+        let sender_manager = Arc::new(AgentKeyManagerBuilder::new().build().unwrap());
+        let sender = sender_manager
+            .generate_key(DIDGenerationOptions {
+                key_type: KeyType::Ed25519,
+            })
+            .unwrap();
+        let recipient_manager = Arc::new(AgentKeyManagerBuilder::new().build().unwrap());
+        let recipient = recipient_manager
+            .generate_key(DIDGenerationOptions {
+                key_type: KeyType::Ed25519,
+            })
+            .unwrap();
+
+        let message = encrypted_test_message(&sender.did, &recipient.did);
+        let pack_options = PackOptions {
+            security_mode: SecurityMode::AuthCrypt,
+            sender_kid: Some(sender.did_doc.verification_method[0].id.clone()),
+            recipient_kid: Some(recipient.did_doc.verification_method[0].id.clone()),
+            content_encryption: None,
+            compression: None,
+        };
+        let packed = message.pack(&*sender_manager, pack_options).await.unwrap();
+
+        // The recipient resolves the sender's did:key to authenticate the message
+        let unpacked: PlainMessage =
+            String::unpack(&packed, &*recipient_manager, UnpackOptions::new())
+                .await
+                .unwrap();
+        assert_eq!(unpacked.body, message.body);
+    }
+
     #[tokio::test]
     async fn test_unpack_to_unpacked_message() {
         // Create a key manager with a test key
@@ -1316,4 +1559,89 @@ mod tests {
         // Verify TAP message parsing failed (unknown type)
         assert!(unpacked.tap_message.is_none());
     }
+
+    #[tokio::test]
+    async fn test_compressed_pack_unpack() {
+        let key_manager = Arc::new(AgentKeyManagerBuilder::new().build().unwrap());
+        let sender = key_manager
+            .generate_key(DIDGenerationOptions {
+                key_type: KeyType::Ed25519,
+            })
+            .unwrap();
+        let recipient = key_manager
+            .generate_key(DIDGenerationOptions {
+                key_type: KeyType::Ed25519,
+            })
+            .unwrap();
+        let sender_kid = sender.did_doc.verification_method[0].id.clone();
+        let recipient_kid = recipient.did_doc.verification_method[0].id.clone();
+
+        let mut message = encrypted_test_message(&sender.did, &recipient.did);
+        message.body = serde_json::json!({
+            "originator": vec!["Alice Example, 1 Main Street, Springfield"; 200]
+        });
+
+        for codec in [CompressionCodec::Deflate, CompressionCodec::Zstd] {
+            let modes = [
+                PackOptions::new().with_sign(&sender_kid),
+                PackOptions::new()
+                    .with_auth_crypt(&sender_kid, &serde_json::json!({ "kid": recipient_kid })),
+                PackOptions {
+                    security_mode: SecurityMode::AnonCrypt,
+                    recipient_kid: Some(recipient_kid.clone()),
+                    ..PackOptions::new()
+                },
+            ];
+
+            for pack_options in modes {
+                let uncompressed = message
+                    .pack(&*key_manager, pack_options.clone())
+                    .await
+                    .unwrap();
+                let packed = message
+                    .pack(&*key_manager, pack_options.with_compression(codec))
+                    .await
+                    .unwrap();
+                assert!(packed.len() < uncompressed.len());
+
+                let zip = match serde_json::from_str::<Jwe>(&packed) {
+                    Ok(jwe) => jwe.get_protected_header().unwrap().zip,
+                    Err(_) => {
+                        let jws: Jws = serde_json::from_str(&packed).unwrap();
+                        let resolver = crate::did::MultiResolver::default();
+                        let verified = crate::verification::verify_jws(&jws, &resolver)
+                            .await
+                            .unwrap();
+                        assert_eq!(verified.body, message.body);
+
+                        let protected =
+                            crate::message::base64_decode_flexible(&jws.signatures[0].protected)
+                                .unwrap();
+                        serde_json::from_slice::<crate::message::JwsProtected>(&protected)
+                            .unwrap()
+                            .zip
+                    }
+                };
+                assert_eq!(zip.as_deref(), Some(codec.as_str()));
+
+                let unpacked: PlainMessage =
+                    String::unpack(&packed, &*key_manager, UnpackOptions::new())
+                        .await
+                        .unwrap();
+                assert_eq!(unpacked.body, message.body);
+            }
+        }
+
+        // Small payloads are left uncompressed
+        let packed = encrypted_test_message(&sender.did, &recipient.did)
+            .pack(
+                &*key_manager,
+                PackOptions::new()
+                    .with_sign(&sender_kid)
+                    .with_compression(CompressionCodec::Zstd),
+            )
+            .await
+            .unwrap();
+        assert!(!packed.contains("zstd"));
+    }
 }
@@ -141,7 +141,7 @@ impl OutOfBandBuilder {
     pub fn build(self) -> OutOfBandInvitation {
         OutOfBandInvitation {
             type_: "https://didcomm.org/out-of-band/2.0/invitation".to_string(),
-            id: uuid::Uuid::new_v4().to_string(),
+            id: tap_msg::id::new_id(),
             from: self.from,
             body: OutOfBandBody {
                 goal_code: self.goal_code,
@@ -0,0 +1,468 @@
+//! RDF Dataset Canonicalization (RDFC-1.0)
+//!
+//! Implements the W3C RDFC-1.0 algorithm with SHA-256: blank nodes are
+//! relabeled `c14n0`, `c14n1`, ... from the hashes of the quads they take
+//! part in, so that isomorphic datasets serialize to the same N-Quads.
+
+use super::{Quad, Term, RDF_LANG_STRING, XSD_STRING};
+use crate::error::{Error, Result};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Most steps (Hash N-Degree Quads calls and permutations tried) one
+/// canonicalization may take
+///
+/// Datasets of many blank nodes that only their relations tell apart make
+/// the algorithm try factorially many permutations, so a crafted document
+/// could otherwise keep the verifier busy indefinitely.
+const MAX_STEPS: usize = 100_000;
+
+/// Canonicalize a dataset into sorted canonical N-Quads
+pub(super) fn canonicalize(quads: Vec<Quad>) -> Result<String> {
+    // A dataset is a set of quads
+    let quads: Vec<Quad> = quads
+        .into_iter()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    let mut canonicalizer = Canonicalizer::new(&quads);
+    canonicalizer.label()?;
+    let canonical = &canonicalizer.canonical;
+    let mut lines: Vec<String> = quads
+        .iter()
+        .map(|quad| {
+            nquad(quad, &|label| {
+                format!("_:{}", canonical.get(label).map_or(label, String::as_str))
+            })
+        })
+        .collect();
+    lines.sort();
+    Ok(lines.concat())
+}
+
+/// Issues identifiers with a prefix, remembering the order of issue
+#[derive(Debug, Clone)]
+struct IdentifierIssuer {
+    prefix: &'static str,
+    issued: Vec<String>,
+    identifiers: HashMap<String, String>,
+}
+
+impl IdentifierIssuer {
+    fn new(prefix: &'static str) -> Self {
+        Self {
+            prefix,
+            issued: Vec::new(),
+            identifiers: HashMap::new(),
+        }
+    }
+
+    fn get(&self, existing: &str) -> Option<&String> {
+        self.identifiers.get(existing)
+    }
+
+    fn issue(&mut self, existing: &str) -> String {
+        if let Some(identifier) = self.identifiers.get(existing) {
+            return identifier.clone();
+        }
+        let identifier = format!("{}{}", self.prefix, self.issued.len());
+        self.issued.push(existing.to_string());
+        self.identifiers
+            .insert(existing.to_string(), identifier.clone());
+        identifier
+    }
+}
+
+struct Canonicalizer<'a> {
+    /// The quads each blank node takes part in
+    blank_node_quads: BTreeMap<String, Vec<&'a Quad>>,
+    first_degree_hashes: HashMap<String, String>,
+    canonical: IdentifierIssuer,
+    steps: usize,
+}
+
+impl<'a> Canonicalizer<'a> {
+    fn new(quads: &'a [Quad]) -> Self {
+        let mut blank_node_quads: BTreeMap<String, Vec<&'a Quad>> = BTreeMap::new();
+        for quad in quads {
+            for term in [Some(&quad.subject), Some(&quad.object), quad.graph.as_ref()]
+                .into_iter()
+                .flatten()
+            {
+                if let Term::Blank(label) = term {
+                    let quads = blank_node_quads.entry(label.clone()).or_default();
+                    if !quads.iter().any(|q| std::ptr::eq(*q, quad)) {
+                        quads.push(quad);
+                    }
+                }
+            }
+        }
+        Self {
+            blank_node_quads,
+            first_degree_hashes: HashMap::new(),
+            canonical: IdentifierIssuer::new("c14n"),
+            steps: 0,
+        }
+    }
+
+    /// Issue canonical identifiers to all blank nodes
+    fn label(&mut self) -> Result<()> {
+        let mut hash_to_blank_nodes: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let labels: Vec<String> = self.blank_node_quads.keys().cloned().collect();
+        for label in labels {
+            let hash = self.first_degree_hash(&label);
+            hash_to_blank_nodes.entry(hash).or_default().push(label);
+        }
+
+        // Blank nodes with a unique first degree hash
+        hash_to_blank_nodes.retain(|_, labels| {
+            if labels.len() == 1 {
+                self.canonical.issue(&labels[0]);
+                false
+            } else {
+                true
+            }
+        });
+
+        // Blank nodes that only their relations tell apart
+        for labels in hash_to_blank_nodes.into_values() {
+            let mut results = Vec::new();
+            for label in labels {
+                if self.canonical.get(&label).is_some() {
+                    continue;
+                }
+                let mut issuer = IdentifierIssuer::new("b");
+                issuer.issue(&label);
+                results.push(self.hash_n_degree_quads(&label, issuer)?);
+            }
+            results.sort_by(|a, b| a.0.cmp(&b.0));
+            for (_, issuer) in results {
+                for existing in &issuer.issued {
+                    self.canonical.issue(existing);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn first_degree_hash(&mut self, label: &str) -> String {
+        if let Some(hash) = self.first_degree_hashes.get(label) {
+            return hash.clone();
+        }
+        let mut lines: Vec<String> = self.blank_node_quads[label]
+            .iter()
+            .map(|quad| {
+                nquad(quad, &|other| {
+                    if other == label { "_:a" } else { "_:z" }.to_string()
+                })
+            })
+            .collect();
+        lines.sort();
+        let hash = sha256_hex(&lines.concat());
+        self.first_degree_hashes
+            .insert(label.to_string(), hash.clone());
+        hash
+    }
+
+    fn hash_related_blank_node(
+        &mut self,
+        related: &str,
+        quad: &Quad,
+        issuer: &IdentifierIssuer,
+        position: char,
+    ) -> String {
+        let identifier = match self.canonical.get(related).or_else(|| issuer.get(related)) {
+            Some(identifier) => format!("_:{}", identifier),
+            None => self.first_degree_hash(related),
+        };
+        let mut input = position.to_string();
+        if position != 'g' {
+            input.push('<');
+            input.push_str(&quad.predicate);
+            input.push('>');
+        }
+        input.push_str(&identifier);
+        sha256_hex(&input)
+    }
+
+    fn step(&mut self) -> Result<()> {
+        self.steps += 1;
+        if self.steps > MAX_STEPS {
+            return Err(Error::Validation(
+                "Document is too complex to canonicalize".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn hash_n_degree_quads(
+        &mut self,
+        identifier: &str,
+        mut issuer: IdentifierIssuer,
+    ) -> Result<(String, IdentifierIssuer)> {
+        self.step()?;
+
+        let mut hash_to_related: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let quads = self.blank_node_quads[identifier].clone();
+        for quad in quads {
+            for (term, position) in [
+                (Some(&quad.subject), 's'),
+                (Some(&quad.object), 'o'),
+                (quad.graph.as_ref(), 'g'),
+            ] {
+                if let Some(Term::Blank(related)) = term {
+                    if related != identifier {
+                        let hash = self.hash_related_blank_node(related, quad, &issuer, position);
+                        hash_to_related
+                            .entry(hash)
+                            .or_default()
+                            .push(related.clone());
+                    }
+                }
+            }
+        }
+
+        let mut data_to_hash = String::new();
+        for (related_hash, related) in hash_to_related {
+            data_to_hash.push_str(&related_hash);
+            let mut chosen_path = String::new();
+            let mut chosen_issuer = None;
+
+            'permutations: for permutation in permutations(related) {
+                self.step()?;
+                let mut issuer_copy = issuer.clone();
+                let mut path = String::new();
+                let mut recursion_list = Vec::new();
+                for related in &permutation {
+                    if let Some(canonical) = self.canonical.get(related) {
+                        path.push_str("_:");
+                        path.push_str(canonical);
+                    } else {
+                        if issuer_copy.get(related).is_none() {
+                            recursion_list.push(related.clone());
+                        }
+                        path.push_str("_:");
+                        path.push_str(&issuer_copy.issue(related));
+                    }
+                    if worse(&path, &chosen_path) {
+                        continue 'permutations;
+                    }
+                }
+                for related in recursion_list {
+                    let (hash, result_issuer) =
+                        self.hash_n_degree_quads(&related, issuer_copy.clone())?;
+                    path.push_str("_:");
+                    path.push_str(&issuer_copy.issue(&related));
+                    path.push('<');
+                    path.push_str(&hash);
+                    path.push('>');
+                    issuer_copy = result_issuer;
+                    if worse(&path, &chosen_path) {
+                        continue 'permutations;
+                    }
+                }
+                if chosen_path.is_empty() || path < chosen_path {
+                    chosen_path = path;
+                    chosen_issuer = Some(issuer_copy);
+                }
+            }
+
+            data_to_hash.push_str(&chosen_path);
+            if let Some(chosen_issuer) = chosen_issuer {
+                issuer = chosen_issuer;
+            }
+        }
+        Ok((sha256_hex(&data_to_hash), issuer))
+    }
+}
+
+/// Whether a path being built can no longer beat the chosen one
+fn worse(path: &str, chosen_path: &str) -> bool {
+    !chosen_path.is_empty() && path.len() >= chosen_path.len() && path > chosen_path
+}
+
+/// All orderings of a list (Heap's algorithm)
+fn permutations(mut items: Vec<String>) -> impl Iterator<Item = Vec<String>> {
+    let mut counters = vec![0; items.len()];
+    let mut index = 0;
+    let mut first = true;
+    std::iter::from_fn(move || {
+        if first {
+            first = false;
+            return Some(items.clone());
+        }
+        while index < items.len() {
+            if counters[index] < index {
+                if index % 2 == 0 {
+                    items.swap(0, index);
+                } else {
+                    items.swap(counters[index], index);
+                }
+                counters[index] += 1;
+                index = 0;
+                return Some(items.clone());
+            }
+            counters[index] = 0;
+            index += 1;
+        }
+        None
+    })
+}
+
+fn sha256_hex(input: &str) -> String {
+    hex::encode(Sha256::digest(input.as_bytes()))
+}
+
+/// Serialize a quad as a canonical N-Quads line, naming blank nodes with
+/// `blank_node`
+fn nquad(quad: &Quad, blank_node: &dyn Fn(&str) -> String) -> String {
+    let mut line = term(&quad.subject, blank_node);
+    line.push_str(" <");
+    line.push_str(&quad.predicate);
+    line.push_str("> ");
+    line.push_str(&term(&quad.object, blank_node));
+    if let Some(graph) = &quad.graph {
+        line.push(' ');
+        line.push_str(&term(graph, blank_node));
+    }
+    line.push_str(" .\n");
+    line
+}
+
+fn term(term: &Term, blank_node: &dyn Fn(&str) -> String) -> String {
+    match term {
+        Term::Iri(iri) => format!("<{}>", iri),
+        Term::Blank(label) => blank_node(label),
+        Term::Literal {
+            value,
+            datatype,
+            language,
+        } => {
+            let mut literal = format!("\"{}\"", escape(value));
+            match language {
+                Some(language) if datatype == RDF_LANG_STRING => {
+                    literal.push('@');
+                    literal.push_str(language);
+                }
+                _ if datatype == XSD_STRING => {}
+                _ => {
+                    literal.push_str("^^<");
+                    literal.push_str(datatype);
+                    literal.push('>');
+                }
+            }
+            literal
+        }
+    }
+}
+
+/// Escape a literal as canonical N-Quads do
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\u{8}' => escaped.push_str("\\b"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\u{c}' => escaped.push_str("\\f"),
+            '\r' => escaped.push_str("\\r"),
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c <= '\u{1f}' || c == '\u{7f}' => {
+                escaped.push_str(&format!("\\u{:04X}", c as u32));
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blank(label: &str) -> Term {
+        Term::Blank(label.to_string())
+    }
+
+    fn quad(subject: Term, predicate: &str, object: Term) -> Quad {
+        Quad {
+            subject,
+            predicate: format!("http://example.com/{}", predicate),
+            object,
+            graph: None,
+        }
+    }
+
+    fn literal(value: &str) -> Term {
+        Term::Literal {
+            value: value.to_string(),
+            datatype: XSD_STRING.to_string(),
+            language: None,
+        }
+    }
+
+    #[test]
+    fn test_single_blank_node() {
+        let canonical = canonicalize(vec![quad(blank("x"), "name", literal("Alice"))]).unwrap();
+        assert_eq!(canonical, "_:c14n0 <http://example.com/name> \"Alice\" .\n");
+    }
+
+    #[test]
+    fn test_labels_do_not_depend_on_input_labels_or_order() {
+        let dataset = |a: &str, b: &str| {
+            vec![
+                quad(blank(a), "knows", blank(b)),
+                quad(blank(b), "knows", blank(a)),
+                quad(blank(a), "name", literal("Alice")),
+                quad(blank(b), "name", literal("Bob")),
+            ]
+        };
+        let canonical = canonicalize(dataset("x", "y")).unwrap();
+        assert_eq!(canonical, canonicalize(dataset("y", "x")).unwrap());
+        let mut reversed = dataset("p", "q");
+        reversed.reverse();
+        assert_eq!(canonical, canonicalize(reversed).unwrap());
+        assert!(canonical.contains("_:c14n0"));
+        assert!(canonical.contains("_:c14n1"));
+    }
+
+    #[test]
+    fn test_blank_nodes_told_apart_by_relations() {
+        // A cycle of three blank nodes with identical first degree quads
+        let dataset = |labels: [&str; 3]| {
+            vec![
+                quad(blank(labels[0]), "next", blank(labels[1])),
+                quad(blank(labels[1]), "next", blank(labels[2])),
+                quad(blank(labels[2]), "next", blank(labels[0])),
+            ]
+        };
+        let canonical = canonicalize(dataset(["a", "b", "c"])).unwrap();
+        assert_eq!(canonical, canonicalize(dataset(["c", "a", "b"])).unwrap());
+        assert_eq!(canonical.lines().count(), 3);
+        for label in ["_:c14n0", "_:c14n1", "_:c14n2"] {
+            assert!(canonical.contains(label));
+        }
+    }
+
+    #[test]
+    fn test_duplicate_quads_are_one() {
+        let q = quad(Term::Iri("http://example.com/s".into()), "p", literal("o"));
+        assert_eq!(canonicalize(vec![q.clone(), q]).unwrap().lines().count(), 1);
+    }
+
+    #[test]
+    fn test_literal_escaping() {
+        let canonical = canonicalize(vec![quad(
+            Term::Iri("http://example.com/s".into()),
+            "p",
+            literal("a\"b\\c\nd\u{1}"),
+        )])
+        .unwrap();
+        assert_eq!(
+            canonical,
+            "<http://example.com/s> <http://example.com/p> \"a\\\"b\\\\c\\nd\\u0001\" .\n"
+        );
+    }
+}
@@ -0,0 +1,1059 @@
+//! Conversion of JSON-LD documents to RDF datasets
+//!
+//! A JSON-LD 1.1 expansion and RDF serialization restricted to what
+//! verifiable credentials and presentations use: contexts bundled with the
+//! crate or embedded in the document, protected, property-scoped and
+//! type-scoped terms, `@vocab`, typed values, `@json` literals, and
+//! `@set`, `@list` and `@graph` containers. Language and index maps,
+//! reverse properties, `@nest`, `@included` and `@base` are rejected.
+
+use super::{
+    Quad, Term, RDF_FIRST, RDF_JSON, RDF_LANG_STRING, RDF_NIL, RDF_REST, RDF_TYPE, XSD_BOOLEAN,
+    XSD_DOUBLE, XSD_INTEGER, XSD_STRING,
+};
+use crate::error::{Error, Result};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use tap_msg::utils::jcs::canonicalize as canonicalize_json;
+
+/// The contexts documents may use, by URL
+pub const BUNDLED_CONTEXTS: &[(&str, &str)] = &[
+    (
+        "https://www.w3.org/2018/credentials/v1",
+        include_str!("contexts/credentials-v1.jsonld"),
+    ),
+    (
+        "https://www.w3.org/ns/credentials/v2",
+        include_str!("contexts/credentials-v2.jsonld"),
+    ),
+    (
+        "https://w3id.org/security/suites/ed25519-2020/v1",
+        include_str!("contexts/ed25519-2020-v1.jsonld"),
+    ),
+    (
+        "https://w3id.org/security/data-integrity/v2",
+        include_str!("contexts/data-integrity-v2.jsonld"),
+    ),
+];
+
+/// Deepest nesting of objects and contexts a document may have
+const MAX_DEPTH: usize = 64;
+
+/// Convert a JSON-LD document to its RDF dataset
+pub(super) fn to_rdf(document: &Value) -> Result<Vec<Quad>> {
+    let document = document
+        .as_object()
+        .ok_or_else(|| invalid("Document is not a JSON object"))?;
+    let mut converter = Converter::default();
+    converter.node(&Context::default(), None, document, &None, 0)?;
+    Ok(converter.quads)
+}
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::Validation(message.into())
+}
+
+/// The parsed bundled context of a URL
+fn bundled_context(url: &str) -> Result<&'static Value> {
+    static CONTEXTS: OnceLock<HashMap<&'static str, Value>> = OnceLock::new();
+    CONTEXTS
+        .get_or_init(|| {
+            BUNDLED_CONTEXTS
+                .iter()
+                .map(|(url, document)| {
+                    let document: Value =
+                        serde_json::from_str(document).expect("bundled contexts are valid JSON");
+                    (*url, document["@context"].clone())
+                })
+                .collect()
+        })
+        .get(url)
+        .ok_or_else(|| {
+            invalid(format!(
+                "Context {} is not one of the bundled contexts",
+                url
+            ))
+        })
+}
+
+/// Whether a string is a JSON-LD keyword
+fn is_keyword(value: &str) -> bool {
+    matches!(
+        value,
+        "@base"
+            | "@container"
+            | "@context"
+            | "@direction"
+            | "@graph"
+            | "@id"
+            | "@import"
+            | "@included"
+            | "@index"
+            | "@json"
+            | "@language"
+            | "@list"
+            | "@nest"
+            | "@none"
+            | "@prefix"
+            | "@propagate"
+            | "@protected"
+            | "@reverse"
+            | "@set"
+            | "@type"
+            | "@value"
+            | "@version"
+            | "@vocab"
+    )
+}
+
+/// Whether a string has the form of a keyword, which JSON-LD ignores
+fn is_keyword_like(value: &str) -> bool {
+    value.len() > 1 && value.starts_with('@') && value[1..].chars().all(|c| c.is_ascii_alphabetic())
+}
+
+/// Whether a string is an absolute IRI or a blank node identifier
+fn is_absolute(value: &str) -> bool {
+    match value.split_once(':') {
+        Some((scheme, _)) => {
+            scheme == "_"
+                || (scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && scheme
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')))
+        }
+        None => false,
+    }
+}
+
+/// A term definition
+#[derive(Debug, Clone)]
+struct TermDefinition {
+    /// The IRI, blank node identifier or keyword the term maps to
+    iri: String,
+    /// `@id`, `@vocab`, `@json` or a datatype IRI
+    type_mapping: Option<String>,
+    container: Vec<String>,
+    /// Property-scoped or type-scoped context
+    context: Option<Value>,
+    /// Whether the term may be the prefix of compact IRIs
+    prefix: bool,
+    protected: bool,
+}
+
+impl TermDefinition {
+    /// Whether two definitions are the same but for their protection
+    fn same_as(&self, other: &TermDefinition) -> bool {
+        self.iri == other.iri
+            && self.type_mapping == other.type_mapping
+            && self.container == other.container
+            && self.context == other.context
+            && self.prefix == other.prefix
+    }
+}
+
+/// An active context
+#[derive(Debug, Clone, Default)]
+struct Context {
+    /// Term definitions, None for terms explicitly mapped to null
+    terms: HashMap<String, Option<TermDefinition>>,
+    vocab: Option<String>,
+    /// The context before type-scoped contexts, which do not apply to
+    /// nested nodes
+    previous: Option<Box<Context>>,
+}
+
+impl Context {
+    fn definition(&self, term: &str) -> Option<&TermDefinition> {
+        self.terms.get(term).and_then(Option::as_ref)
+    }
+
+    /// Expand a term, compact IRI or IRI
+    ///
+    /// With `vocab`, terms and the vocabulary mapping apply; otherwise the
+    /// value is an IRI reference, which must be absolute. Returns None for
+    /// values that expand to nothing.
+    fn expand_iri(&self, value: &str, vocab: bool) -> Option<String> {
+        if is_keyword(value) {
+            return Some(value.to_string());
+        }
+        if is_keyword_like(value) {
+            return None;
+        }
+        if vocab {
+            if let Some(definition) = self.terms.get(value) {
+                return definition.as_ref().map(|d| d.iri.clone());
+            }
+        }
+        if let Some((prefix, suffix)) = value.split_once(':') {
+            if prefix == "_" || suffix.starts_with("//") {
+                return Some(value.to_string());
+            }
+            if let Some(definition) = self.definition(prefix).filter(|d| d.prefix) {
+                return Some(format!("{}{}", definition.iri, suffix));
+            }
+            if is_absolute(value) {
+                return Some(value.to_string());
+            }
+        }
+        match &self.vocab {
+            Some(vocab_iri) if vocab => Some(format!("{}{}", vocab_iri, value)),
+            _ => Some(value.to_string()),
+        }
+    }
+
+    /// Apply a local context, see the JSON-LD 1.1 Context Processing
+    /// Algorithm
+    fn process(
+        &self,
+        local: &Value,
+        override_protected: bool,
+        mut propagate: bool,
+        depth: usize,
+    ) -> Result<Context> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("Contexts are nested too deeply"));
+        }
+        let mut result = self.clone();
+        if let Some(value) = local.get("@propagate") {
+            propagate = value
+                .as_bool()
+                .ok_or_else(|| invalid("@propagate is not a boolean"))?;
+        }
+        if !propagate && result.previous.is_none() {
+            result.previous = Some(Box::new(self.clone()));
+        }
+
+        let contexts = match local {
+            Value::Array(contexts) => contexts.iter().collect(),
+            context => vec![context],
+        };
+        for context in contexts {
+            match context {
+                Value::Null => {
+                    if !override_protected && result.terms.values().flatten().any(|d| d.protected) {
+                        return Err(invalid("Cannot clear a context with protected terms"));
+                    }
+                    let before = result;
+                    result = Context::default();
+                    if !propagate {
+                        result.previous = Some(Box::new(before));
+                    }
+                }
+                Value::String(url) => {
+                    result = result.process(
+                        bundled_context(url)?,
+                        override_protected,
+                        true,
+                        depth + 1,
+                    )?;
+                }
+                Value::Object(definitions) => {
+                    result.define(definitions, override_protected)?;
+                }
+                _ => return Err(invalid("Invalid local context")),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Add the definitions of a context object
+    fn define(&mut self, context: &Map<String, Value>, override_protected: bool) -> Result<()> {
+        if context.contains_key("@import") {
+            return Err(invalid("@import in contexts is not supported"));
+        }
+        for key in ["@base", "@language", "@direction"] {
+            if context.get(key).is_some_and(|value| !value.is_null()) {
+                return Err(invalid(format!("{} in contexts is not supported", key)));
+            }
+        }
+        if let Some(version) = context.get("@version") {
+            if version.as_f64() != Some(1.1) {
+                return Err(invalid("Unsupported JSON-LD version"));
+            }
+        }
+        if let Some(vocab) = context.get("@vocab") {
+            self.vocab = match vocab {
+                Value::Null => None,
+                Value::String(vocab) => match self.expand_iri(vocab, true) {
+                    Some(iri) if is_absolute(&iri) => Some(iri),
+                    _ => return Err(invalid(format!("Invalid vocabulary mapping {}", vocab))),
+                },
+                _ => return Err(invalid("Invalid vocabulary mapping")),
+            };
+        }
+
+        let mut definer = Definer {
+            context,
+            defined: HashMap::new(),
+            protected: context
+                .get("@protected")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+            override_protected,
+        };
+        for term in context.keys() {
+            if !matches!(
+                term.as_str(),
+                "@base"
+                    | "@direction"
+                    | "@import"
+                    | "@language"
+                    | "@propagate"
+                    | "@protected"
+                    | "@version"
+                    | "@vocab"
+            ) {
+                definer.define(self, term)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Creates the term definitions of one context object, following the
+/// dependencies between its terms
+struct Definer<'a> {
+    context: &'a Map<String, Value>,
+    /// Terms being (false) or already (true) defined
+    defined: HashMap<String, bool>,
+    protected: bool,
+    override_protected: bool,
+}
+
+impl Definer<'_> {
+    /// Expand a value used in a definition, first defining the terms of
+    /// this context it depends on
+    fn expand_iri(&mut self, active: &mut Context, value: &str) -> Result<Option<String>> {
+        if self.context.contains_key(value) {
+            self.define(active, value)?;
+        }
+        if let Some((prefix, _)) = value.split_once(':') {
+            if self.context.contains_key(prefix) {
+                self.define(active, prefix)?;
+            }
+        }
+        Ok(active.expand_iri(value, true))
+    }
+
+    fn define(&mut self, active: &mut Context, term: &str) -> Result<()> {
+        match self.defined.get(term) {
+            Some(true) => return Ok(()),
+            Some(false) => return Err(invalid(format!("Cyclic definition of term {}", term))),
+            None => {}
+        }
+        self.defined.insert(term.to_string(), false);
+
+        let value = &self.context[term];
+        if is_keyword(term) {
+            return Err(invalid(format!("Cannot redefine keyword {}", term)));
+        }
+        if is_keyword_like(term) {
+            self.defined.insert(term.to_string(), true);
+            return Ok(());
+        }
+        let previous = active.terms.remove(term);
+
+        let definition = match value {
+            Value::Null => None,
+            Value::String(iri) => {
+                let iri = self.term_iri(active, term, Some(iri))?;
+                iri.map(|iri| TermDefinition {
+                    prefix: iri.ends_with([':', '/', '?', '#', '[', ']', '@']),
+                    iri,
+                    type_mapping: None,
+                    container: Vec::new(),
+                    context: None,
+                    protected: self.protected,
+                })
+            }
+            Value::Object(definition) => self.expanded_definition(active, term, definition)?,
+            _ => return Err(invalid(format!("Invalid definition of term {}", term))),
+        };
+
+        let definition = match previous {
+            Some(Some(previous)) if previous.protected && !self.override_protected => {
+                match &definition {
+                    Some(definition) if definition.same_as(&previous) => Some(previous),
+                    _ => return Err(invalid(format!("Cannot redefine protected term {}", term))),
+                }
+            }
+            _ => definition,
+        };
+        active.terms.insert(term.to_string(), definition);
+        self.defined.insert(term.to_string(), true);
+        Ok(())
+    }
+
+    fn expanded_definition(
+        &mut self,
+        active: &mut Context,
+        term: &str,
+        definition: &Map<String, Value>,
+    ) -> Result<Option<TermDefinition>> {
+        for key in definition.keys() {
+            if !matches!(
+                key.as_str(),
+                "@id" | "@type" | "@container" | "@context" | "@protected" | "@prefix"
+            ) {
+                return Err(invalid(format!(
+                    "{} in the definition of term {} is not supported",
+                    key, term
+                )));
+            }
+        }
+
+        let iri = match definition.get("@id") {
+            Some(Value::Null) => return Ok(None),
+            Some(Value::String(iri)) => self.term_iri(active, term, Some(iri))?,
+            Some(_) => return Err(invalid(format!("Invalid @id of term {}", term))),
+            None => self.term_iri(active, term, None)?,
+        };
+        let Some(iri) = iri else {
+            return Ok(None);
+        };
+
+        let type_mapping = match definition.get("@type") {
+            None => None,
+            Some(Value::String(t)) if matches!(t.as_str(), "@id" | "@vocab" | "@json") => {
+                Some(t.clone())
+            }
+            Some(Value::String(t)) => match self.expand_iri(active, t)? {
+                Some(t) if is_absolute(&t) && !t.starts_with("_:") => Some(t),
+                _ => return Err(invalid(format!("Invalid type mapping of term {}", term))),
+            },
+            Some(_) => return Err(invalid(format!("Invalid type mapping of term {}", term))),
+        };
+
+        let container = match definition.get("@container") {
+            None | Some(Value::Null) => Vec::new(),
+            Some(Value::String(c)) => vec![c.clone()],
+            Some(Value::Array(cs)) => cs
+                .iter()
+                .map(|c| c.as_str().map(String::from))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| invalid(format!("Invalid container of term {}", term)))?,
+            Some(_) => return Err(invalid(format!("Invalid container of term {}", term))),
+        };
+        if let Some(c) = container
+            .iter()
+            .find(|c| !matches!(c.as_str(), "@list" | "@set" | "@graph"))
+        {
+            return Err(invalid(format!("{} containers are not supported", c)));
+        }
+
+        Ok(Some(TermDefinition {
+            iri,
+            type_mapping,
+            container,
+            context: definition.get("@context").cloned(),
+            prefix: definition
+                .get("@prefix")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+            protected: definition
+                .get("@protected")
+                .and_then(Value::as_bool)
+                .unwrap_or(self.protected),
+        }))
+    }
+
+    /// The IRI a term maps to, given its `@id` if it has one
+    fn term_iri(
+        &mut self,
+        active: &mut Context,
+        term: &str,
+        id: Option<&str>,
+    ) -> Result<Option<String>> {
+        if let Some(id) = id.filter(|id| *id != term) {
+            if is_keyword(id) {
+                return Ok(Some(id.to_string()));
+            }
+            if is_keyword_like(id) {
+                return Ok(None);
+            }
+            return match self.expand_iri(active, id)? {
+                Some(iri) if is_absolute(&iri) => Ok(Some(iri)),
+                _ => Err(invalid(format!("Invalid IRI mapping of term {}", term))),
+            };
+        }
+        if let Some((prefix, suffix)) = term.split_once(':') {
+            if !prefix.is_empty() {
+                if self.context.contains_key(prefix) {
+                    self.define(active, prefix)?;
+                }
+                if let Some(definition) = active.definition(prefix) {
+                    return Ok(Some(format!("{}{}", definition.iri, suffix)));
+                }
+                if is_absolute(term) {
+                    return Ok(Some(term.to_string()));
+                }
+            }
+        }
+        if term == "@type" {
+            return Ok(Some(term.to_string()));
+        }
+        match &active.vocab {
+            Some(vocab) if !term.contains('/') => Ok(Some(format!("{}{}", vocab, term))),
+            _ => Err(invalid(format!("Term {} has no IRI mapping", term))),
+        }
+    }
+}
+
+/// Builds the quads of a document while expanding it
+#[derive(Default)]
+struct Converter {
+    quads: Vec<Quad>,
+    /// Blank node identifiers of the document, relabeled
+    blank_nodes: HashMap<String, String>,
+    next_blank_node: usize,
+}
+
+impl Converter {
+    fn fresh_blank_node(&mut self) -> Term {
+        let label = format!("b{}", self.next_blank_node);
+        self.next_blank_node += 1;
+        Term::Blank(label)
+    }
+
+    /// A resource named by an expanded IRI or blank node identifier
+    fn resource(&mut self, iri: &str) -> Term {
+        match iri.strip_prefix("_:") {
+            Some(label) => match self.blank_nodes.get(label) {
+                Some(relabeled) => Term::Blank(relabeled.clone()),
+                None => {
+                    let term = self.fresh_blank_node();
+                    if let Term::Blank(relabeled) = &term {
+                        self.blank_nodes
+                            .insert(label.to_string(), relabeled.clone());
+                    }
+                    term
+                }
+            },
+            None => Term::Iri(iri.to_string()),
+        }
+    }
+
+    /// Expand an IRI reference of the document, which must be absolute
+    fn reference(&mut self, context: &Context, value: &str, vocab: bool) -> Result<Term> {
+        match context.expand_iri(value, vocab) {
+            Some(iri) if is_absolute(&iri) && !is_keyword(&iri) => Ok(self.resource(&iri)),
+            _ => Err(invalid(format!("{} is not an absolute IRI", value))),
+        }
+    }
+
+    fn emit(&mut self, subject: &Term, predicate: &str, object: Term, graph: &Option<Term>) {
+        self.quads.push(Quad {
+            subject: subject.clone(),
+            predicate: predicate.to_string(),
+            object,
+            graph: graph.clone(),
+        });
+    }
+
+    /// Expand a node object into quads, returning its subject
+    fn node(
+        &mut self,
+        active: &Context,
+        property: Option<&TermDefinition>,
+        element: &Map<String, Value>,
+        graph: &Option<Term>,
+        depth: usize,
+    ) -> Result<Term> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("Document is nested too deeply"));
+        }
+
+        // Type-scoped contexts do not apply to nested nodes
+        let mut context = match &active.previous {
+            Some(previous)
+                if !(element.len() == 1
+                    && element
+                        .keys()
+                        .all(|k| active.expand_iri(k, true).as_deref() == Some("@id"))) =>
+            {
+                previous.as_ref().clone()
+            }
+            _ => active.clone(),
+        };
+        if let Some(scoped) = property.and_then(|p| p.context.as_ref()) {
+            context = context.process(scoped, true, true, 0)?;
+        }
+        if let Some(local) = element.get("@context") {
+            context = context.process(local, false, true, 0)?;
+        }
+
+        let type_scoped = context.clone();
+        let mut keys: Vec<&String> = element.keys().filter(|k| *k != "@context").collect();
+        keys.sort();
+        let mut types = Vec::new();
+        for key in &keys {
+            if type_scoped.expand_iri(key, true).as_deref() == Some("@type") {
+                match &element[key.as_str()] {
+                    Value::String(t) => types.push(t.clone()),
+                    Value::Array(ts) => {
+                        for t in ts {
+                            types.push(
+                                t.as_str()
+                                    .ok_or_else(|| invalid("Types must be strings"))?
+                                    .to_string(),
+                            );
+                        }
+                    }
+                    _ => return Err(invalid("Types must be strings")),
+                }
+            }
+        }
+        types.sort();
+        for t in &types {
+            if let Some(scoped) = type_scoped.definition(t).and_then(|d| d.context.as_ref()) {
+                context = context.process(scoped, false, false, 0)?;
+            }
+        }
+
+        let mut subject = None;
+        for key in &keys {
+            if context.expand_iri(key, true).as_deref() == Some("@id") {
+                let id = element[key.as_str()]
+                    .as_str()
+                    .ok_or_else(|| invalid("@id must be a string"))?;
+                subject = Some(self.reference(&context, id, false)?);
+            }
+        }
+        let subject = match subject {
+            Some(subject) => subject,
+            None => self.fresh_blank_node(),
+        };
+        for t in &types {
+            let object = self
+                .reference(&type_scoped, t, true)
+                .map_err(|_| invalid(format!("Type {} is not defined", t)))?;
+            self.emit(&subject, RDF_TYPE, object, graph);
+        }
+
+        for key in keys {
+            let value = &element[key.as_str()];
+            let predicate = match context.expand_iri(key, true) {
+                Some(iri) if iri == "@id" || iri == "@type" => continue,
+                Some(iri) if is_keyword(&iri) => {
+                    return Err(invalid(format!("{} is not supported", iri)))
+                }
+                Some(iri) if is_absolute(&iri) && !iri.starts_with("_:") => iri,
+                _ => {
+                    return Err(invalid(format!(
+                        "Property {} is not defined by the contexts of the document",
+                        key
+                    )))
+                }
+            };
+            let definition = context.definition(key).cloned();
+            self.property(
+                &context,
+                definition.as_ref(),
+                &subject,
+                &predicate,
+                value,
+                graph,
+                depth,
+            )?;
+        }
+        Ok(subject)
+    }
+
+    /// Expand the values of a property into quads
+    #[allow(clippy::too_many_arguments)]
+    fn property(
+        &mut self,
+        context: &Context,
+        definition: Option<&TermDefinition>,
+        subject: &Term,
+        predicate: &str,
+        value: &Value,
+        graph: &Option<Term>,
+        depth: usize,
+    ) -> Result<()> {
+        let container = definition.map(|d| d.container.as_slice()).unwrap_or(&[]);
+        if container.iter().any(|c| c == "@list") {
+            let items = match value {
+                Value::Array(items) => items.as_slice(),
+                value => std::slice::from_ref(value),
+            };
+            let list = self.list(context, definition, items, graph, depth)?;
+            self.emit(subject, predicate, list, graph);
+            return Ok(());
+        }
+
+        let values = match value {
+            Value::Array(values) => values.as_slice(),
+            value => std::slice::from_ref(value),
+        };
+        for value in values {
+            let object = match value {
+                Value::Object(node) if container.iter().any(|c| c == "@graph") && is_node(node) => {
+                    let graph_name = self.fresh_blank_node();
+                    self.node(
+                        context,
+                        definition,
+                        node,
+                        &Some(graph_name.clone()),
+                        depth + 1,
+                    )?;
+                    Some(graph_name)
+                }
+                Value::Array(_) => return Err(invalid("Nested arrays are not supported")),
+                value => self.object(context, definition, value, graph, depth)?,
+            };
+            if let Some(object) = object {
+                self.emit(subject, predicate, object, graph);
+            }
+        }
+        Ok(())
+    }
+
+    /// Expand one value into an RDF term, None for null
+    fn object(
+        &mut self,
+        context: &Context,
+        definition: Option<&TermDefinition>,
+        value: &Value,
+        graph: &Option<Term>,
+        depth: usize,
+    ) -> Result<Option<Term>> {
+        let type_mapping = definition.and_then(|d| d.type_mapping.as_deref());
+        if type_mapping == Some("@json") {
+            return Ok(Some(literal(canonicalize_json(value), RDF_JSON)));
+        }
+        match value {
+            Value::Null => Ok(None),
+            Value::String(s) => Ok(Some(match type_mapping {
+                Some("@id") => self.reference(&scalar_context(context, definition)?, s, false)?,
+                Some("@vocab") => self.reference(&scalar_context(context, definition)?, s, true)?,
+                Some(datatype) => literal(s.clone(), datatype),
+                None => literal(s.clone(), XSD_STRING),
+            })),
+            Value::Bool(_) | Value::Number(_) => {
+                native_literal(value, type_mapping.filter(|t| !t.starts_with('@'))).map(Some)
+            }
+            Value::Array(_) => Err(invalid("Nested arrays are not supported")),
+            Value::Object(object) => {
+                if object.contains_key("@value") {
+                    return self
+                        .value_object(&scalar_context(context, definition)?, object)
+                        .map(Some);
+                }
+                if let Some(items) = object.get("@list") {
+                    let items = match items {
+                        Value::Array(items) => items.as_slice(),
+                        item => std::slice::from_ref(item),
+                    };
+                    return self
+                        .list(context, definition, items, graph, depth)
+                        .map(Some);
+                }
+                if let Some(key) = object
+                    .keys()
+                    .find(|k| matches!(k.as_str(), "@set" | "@graph"))
+                {
+                    return Err(invalid(format!("{} objects are not supported", key)));
+                }
+                self.node(context, definition, object, graph, depth + 1)
+                    .map(Some)
+            }
+        }
+    }
+
+    /// Expand a value object
+    fn value_object(&mut self, context: &Context, object: &Map<String, Value>) -> Result<Term> {
+        if let Some(key) = object
+            .keys()
+            .find(|k| !matches!(k.as_str(), "@value" | "@type" | "@language"))
+        {
+            return Err(invalid(format!(
+                "{} in value objects is not supported",
+                key
+            )));
+        }
+        let datatype = match object.get("@type") {
+            None => None,
+            Some(Value::String(t)) if t == "@json" => Some(RDF_JSON.to_string()),
+            Some(Value::String(t)) => match context.expand_iri(t, true) {
+                Some(iri) if is_absolute(&iri) && !iri.starts_with("_:") => Some(iri),
+                _ => return Err(invalid(format!("Type {} is not defined", t))),
+            },
+            Some(_) => return Err(invalid("Invalid @type of value object")),
+        };
+        let value = &object["@value"];
+        if datatype.as_deref() == Some(RDF_JSON) {
+            return Ok(literal(canonicalize_json(value), RDF_JSON));
+        }
+        match (value, object.get("@language")) {
+            (Value::String(s), Some(Value::String(language))) if datatype.is_none() => {
+                Ok(Term::Literal {
+                    value: s.clone(),
+                    datatype: RDF_LANG_STRING.to_string(),
+                    language: Some(language.to_lowercase()),
+                })
+            }
+            (_, Some(_)) => Err(invalid("Invalid language-tagged value")),
+            (Value::String(s), None) => Ok(literal(
+                s.clone(),
+                datatype.as_deref().unwrap_or(XSD_STRING),
+            )),
+            (Value::Bool(_) | Value::Number(_), None) => native_literal(value, datatype.as_deref()),
+            _ => Err(invalid("Invalid @value")),
+        }
+    }
+
+    /// Expand a list into its RDF collection, returning its head
+    fn list(
+        &mut self,
+        context: &Context,
+        definition: Option<&TermDefinition>,
+        items: &[Value],
+        graph: &Option<Term>,
+        depth: usize,
+    ) -> Result<Term> {
+        let mut objects = Vec::new();
+        for item in items {
+            if let Some(object) = self.object(context, definition, item, graph, depth)? {
+                objects.push(object);
+            }
+        }
+        let mut head = Term::Iri(RDF_NIL.to_string());
+        for object in objects.into_iter().rev() {
+            let node = self.fresh_blank_node();
+            self.emit(&node, RDF_FIRST, object, graph);
+            self.emit(&node, RDF_REST, head, graph);
+            head = node;
+        }
+        Ok(head)
+    }
+}
+
+/// The context values of a property other than nodes are expanded with:
+/// the active context with the property-scoped context applied
+fn scalar_context(context: &Context, definition: Option<&TermDefinition>) -> Result<Context> {
+    match definition.and_then(|d| d.context.as_ref()) {
+        Some(scoped) => context.process(scoped, true, true, 0),
+        None => Ok(context.clone()),
+    }
+}
+
+/// Whether a JSON object is a node object rather than a value or list
+fn is_node(object: &Map<String, Value>) -> bool {
+    !object
+        .keys()
+        .any(|k| matches!(k.as_str(), "@value" | "@list" | "@set" | "@graph"))
+}
+
+fn literal(value: String, datatype: &str) -> Term {
+    Term::Literal {
+        value,
+        datatype: datatype.to_string(),
+        language: None,
+    }
+}
+
+/// The literal of a JSON boolean or number, in canonical lexical form
+fn native_literal(value: &Value, datatype: Option<&str>) -> Result<Term> {
+    match value {
+        Value::Bool(b) => Ok(literal(b.to_string(), datatype.unwrap_or(XSD_BOOLEAN))),
+        Value::Number(n) => {
+            let integral = n.is_i64()
+                || n.is_u64()
+                || n.as_f64()
+                    .is_some_and(|f| f.fract() == 0.0 && f.abs() < 1e21);
+            if integral && datatype != Some(XSD_DOUBLE) {
+                let lexical = match (n.as_i64(), n.as_u64()) {
+                    (Some(i), _) => i.to_string(),
+                    (_, Some(u)) => u.to_string(),
+                    _ => format!("{:.0}", n.as_f64().unwrap_or_default()),
+                };
+                Ok(literal(lexical, datatype.unwrap_or(XSD_INTEGER)))
+            } else {
+                let f = n
+                    .as_f64()
+                    .ok_or_else(|| invalid("Number is out of range"))?;
+                Ok(literal(canonical_double(f), datatype.unwrap_or(XSD_DOUBLE)))
+            }
+        }
+        _ => Err(invalid("Not a native value")),
+    }
+}
+
+/// The canonical lexical form of an xsd:double, e.g. `1.5E1`
+fn canonical_double(f: f64) -> String {
+    let formatted = format!("{:.15e}", f);
+    let (mantissa, exponent) = formatted.split_once('e').unwrap_or((&formatted, "0"));
+    let mut mantissa = mantissa.trim_end_matches('0').to_string();
+    if mantissa.ends_with('.') {
+        mantissa.push('0');
+    }
+    format!("{}E{}", mantissa, exponent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn nquads(document: Value) -> Vec<String> {
+        let mut lines: Vec<String> = to_rdf(&document)
+            .unwrap()
+            .iter()
+            .map(|quad| format!("{:?}", quad))
+            .collect();
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn test_bundled_contexts_load() {
+        for (url, _) in BUNDLED_CONTEXTS {
+            Context::default()
+                .process(&json!(url), false, true, 0)
+                .unwrap();
+        }
+        assert!(Context::default()
+            .process(
+                &json!([
+                    "https://www.w3.org/2018/credentials/v1",
+                    "https://w3id.org/security/suites/ed25519-2020/v1",
+                    "https://w3id.org/security/data-integrity/v2"
+                ]),
+                false,
+                true,
+                0
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn test_credential_terms() {
+        let quads = to_rdf(&json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1", {"name": "https://schema.org/name"}],
+            "id": "urn:uuid:1",
+            "type": ["VerifiableCredential"],
+            "issuer": "did:example:issuer",
+            "issuanceDate": "2025-01-01T00:00:00Z",
+            "credentialSubject": {"id": "did:example:alice", "name": "Alice"}
+        }))
+        .unwrap();
+        let credential = Term::Iri("urn:uuid:1".into());
+        let has = |predicate: &str, object: Term| {
+            quads
+                .iter()
+                .any(|q| q.subject == credential && q.predicate == predicate && q.object == object)
+        };
+        assert!(has(
+            RDF_TYPE,
+            Term::Iri("https://www.w3.org/2018/credentials#VerifiableCredential".into())
+        ));
+        assert!(has(
+            "https://www.w3.org/2018/credentials#issuer",
+            Term::Iri("did:example:issuer".into())
+        ));
+        assert!(has(
+            "https://www.w3.org/2018/credentials#issuanceDate",
+            literal(
+                "2025-01-01T00:00:00Z".into(),
+                "http://www.w3.org/2001/XMLSchema#dateTime"
+            )
+        ));
+        assert!(quads
+            .iter()
+            .any(|q| q.subject == Term::Iri("did:example:alice".into())
+                && q.predicate == "https://schema.org/name"
+                && q.object == literal("Alice".into(), XSD_STRING)));
+        assert_eq!(quads.len(), 5);
+    }
+
+    #[test]
+    fn test_type_scoped_terms_do_not_apply_to_nested_nodes() {
+        // issuer is only defined for VerifiableCredential nodes
+        let err = to_rdf(&json!({
+            "@context": "https://www.w3.org/2018/credentials/v1",
+            "type": "VerifiableCredential",
+            "credentialSubject": {"issuer": "did:example:issuer"}
+        }))
+        .unwrap_err();
+        assert!(err.to_string().contains("Property issuer is not defined"));
+    }
+
+    #[test]
+    fn test_undefined_terms_and_unknown_contexts_are_rejected() {
+        let err = to_rdf(&json!({
+            "@context": "https://www.w3.org/2018/credentials/v1",
+            "type": "VerifiableCredential",
+            "unsigned": "value"
+        }))
+        .unwrap_err();
+        assert!(err.to_string().contains("unsigned is not defined"));
+
+        let err = to_rdf(&json!({
+            "@context": "https://www.w3.org/2018/credentials/v1",
+            "type": ["VerifiableCredential", "UndefinedCredential"]
+        }))
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("UndefinedCredential is not defined"));
+
+        let err =
+            to_rdf(&json!({"@context": "https://example.com/context", "id": "urn:x"})).unwrap_err();
+        assert!(err.to_string().contains("not one of the bundled contexts"));
+    }
+
+    #[test]
+    fn test_protected_terms_cannot_be_redefined() {
+        let err = to_rdf(&json!({
+            "@context": [
+                "https://www.w3.org/2018/credentials/v1",
+                {"VerifiableCredential": "https://example.com/Other"}
+            ],
+            "type": "VerifiableCredential"
+        }))
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("protected term VerifiableCredential"));
+    }
+
+    #[test]
+    fn test_vocab_graph_containers_and_values() {
+        let lines = nquads(json!({
+            "@context": "https://www.w3.org/ns/credentials/v2",
+            "type": ["VerifiablePresentation"],
+            "verifiableCredential": [{
+                "@context": "https://www.w3.org/ns/credentials/v2",
+                "type": ["VerifiableCredential", "ExampleCredential"],
+                "credentialSubject": {"age": 42, "score": 1.5, "member": true, "tags": {"@list": ["a"]}}
+            }]
+        }));
+        let text = lines.join("\n");
+        assert!(text.contains("issuer-dependent#ExampleCredential"));
+        assert!(text.contains("XMLSchema#integer"));
+        assert!(text.contains("value: \"1.5E0\""));
+        assert!(text.contains("XMLSchema#boolean"));
+        assert!(text.contains("rdf-syntax-ns#first"));
+        // The credential is in a graph of its own
+        assert!(to_rdf(&json!({
+            "@context": "https://www.w3.org/ns/credentials/v2",
+            "type": ["VerifiablePresentation"],
+            "verifiableCredential": [{
+                "@context": "https://www.w3.org/ns/credentials/v2",
+                "type": ["VerifiableCredential"]
+            }]
+        }))
+        .unwrap()
+        .iter()
+        .any(|quad| quad.graph.is_some()));
+    }
+
+    #[test]
+    fn test_canonical_double() {
+        assert_eq!(canonical_double(1.5), "1.5E0");
+        assert_eq!(canonical_double(0.001), "1.0E-3");
+        assert_eq!(canonical_double(-123.25), "-1.2325E2");
+    }
+}
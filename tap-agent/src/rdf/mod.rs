@@ -0,0 +1,66 @@
+//! RDF Dataset Canonicalization of JSON-LD documents
+//!
+//! Data Integrity proofs of the `Ed25519Signature2020` and
+//! `eddsa-rdfc-2022` suites sign the RDF dataset a JSON-LD document stands
+//! for, canonicalized with RDFC-1.0, rather than its JSON. This module turns
+//! a document into that dataset ([`jsonld`]) and canonicalizes it as
+//! N-Quads ([`canon`]).
+//!
+//! Only contexts bundled with the crate are used, see [`BUNDLED_CONTEXTS`]:
+//! contexts are never fetched over the network, so the signed meaning of a
+//! document cannot change with what a remote server returns. A document
+//! naming any other context is rejected. The conversion runs in safe mode:
+//! properties and types that no context defines, and relative IRIs, are
+//! errors rather than silently dropped, so every part of a document is
+//! covered by its signature.
+
+mod canon;
+mod jsonld;
+
+use crate::error::Result;
+
+pub use jsonld::BUNDLED_CONTEXTS;
+
+/// The canonical N-Quads of the RDF dataset of a JSON-LD document
+///
+/// The document is converted with its bundled contexts and canonicalized
+/// with RDFC-1.0 (also known as URDNA2015).
+pub fn canonicalize(document: &serde_json::Value) -> Result<String> {
+    canon::canonicalize(jsonld::to_rdf(document)?)
+}
+
+/// A subject, object or graph name of an RDF quad
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Term {
+    /// An IRI
+    Iri(String),
+    /// A blank node, by its label without the `_:` prefix
+    Blank(String),
+    /// A literal with its datatype IRI and, for `rdf:langString`, its
+    /// language tag
+    Literal {
+        value: String,
+        datatype: String,
+        language: Option<String>,
+    },
+}
+
+/// An RDF quad, in the default graph if it has no graph name
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Quad {
+    subject: Term,
+    predicate: String,
+    object: Term,
+    graph: Option<Term>,
+}
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const RDF_FIRST: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#first";
+const RDF_REST: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#rest";
+const RDF_NIL: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#nil";
+const RDF_JSON: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#JSON";
+const RDF_LANG_STRING: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#langString";
+const XSD_STRING: &str = "http://www.w3.org/2001/XMLSchema#string";
+const XSD_BOOLEAN: &str = "http://www.w3.org/2001/XMLSchema#boolean";
+const XSD_INTEGER: &str = "http://www.w3.org/2001/XMLSchema#integer";
+const XSD_DOUBLE: &str = "http://www.w3.org/2001/XMLSchema#double";
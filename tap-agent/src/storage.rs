@@ -14,6 +14,7 @@ use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
+use zeroize::{Zeroize, Zeroizing};
 
 /// Default directory for TAP configuration and keys
 pub const DEFAULT_TAP_DIR: &str = ".tap";
@@ -21,7 +22,10 @@ pub const DEFAULT_TAP_DIR: &str = ".tap";
 pub const DEFAULT_KEYS_FILE: &str = "keys.json";
 
 /// A structure representing a stored key
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// The private key is zeroized when the key is dropped and redacted from its
+/// `Debug` output.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct StoredKey {
     /// The DID for this key
     pub did: String,
@@ -40,6 +44,25 @@ pub struct StoredKey {
     pub metadata: HashMap<String, String>,
 }
 
+impl Drop for StoredKey {
+    fn drop(&mut self) {
+        self.private_key.zeroize();
+    }
+}
+
+impl std::fmt::Debug for StoredKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StoredKey")
+            .field("did", &self.did)
+            .field("label", &self.label)
+            .field("key_type", &self.key_type)
+            .field("private_key", &"[redacted]")
+            .field("public_key", &self.public_key)
+            .field("metadata", &self.metadata)
+            .finish()
+    }
+}
+
 /// Serialization helper for KeyType
 mod key_type_serde {
     use super::KeyType;
@@ -448,8 +471,10 @@ impl KeyStorage {
         let mut storage = if !path.exists() {
             Self::new()
         } else {
-            let contents = fs::read_to_string(path)
-                .map_err(|e| Error::Storage(format!("Failed to read key storage file: {}", e)))?;
+            let contents =
+                Zeroizing::new(fs::read_to_string(path).map_err(|e| {
+                    Error::Storage(format!("Failed to read key storage file: {}", e))
+                })?);
 
             let mut storage: KeyStorage = serde_json::from_str(&contents)
                 .map_err(|e| Error::Storage(format!("Failed to parse key storage file: {}", e)))?;
@@ -507,10 +532,12 @@ impl KeyStorage {
     /// On Unix systems, the file is created with restrictive permissions (0o600)
     /// to protect private key material from unauthorized access.
     pub fn save_to_path(&self, path: &Path) -> Result<()> {
-        let contents = serde_json::to_string_pretty(self)
-            .map_err(|e| Error::Storage(format!("Failed to serialize key storage: {}", e)))?;
+        let contents = Zeroizing::new(
+            serde_json::to_string_pretty(self)
+                .map_err(|e| Error::Storage(format!("Failed to serialize key storage: {}", e)))?,
+        );
 
-        fs::write(path, &contents)
+        fs::write(path, contents.as_bytes())
             .map_err(|e| Error::Storage(format!("Failed to write key storage file: {}", e)))?;
 
         // Set restrictive permissions on Unix systems (owner read/write only)
@@ -669,7 +696,7 @@ fn sanitize_did(did: &str) -> String {
 ///
 /// This is a no-op on non-Unix systems.
 #[allow(unused_variables)]
-fn set_secure_file_permissions(path: &Path) -> Result<()> {
+pub(crate) fn set_secure_file_permissions(path: &Path) -> Result<()> {
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
@@ -0,0 +1,169 @@
+//! Ephemeral key agreement keys per thread
+//!
+//! Transaction threads can stay open for days, and everything encrypted to
+//! an agent's DID key stays readable by whoever obtains that key later. With
+//! [`AgentConfig::thread_keys`](crate::config::AgentConfig::thread_keys)
+//! enabled, an agent generates a fresh key agreement key for each thread it
+//! takes part in and advertises its public key in the `thread_key` header of
+//! the messages it sends in the thread:
+//!
+//! ```json
+//! {
+//!   "thread_key": {
+//!     "kty": "OKP",
+//!     "crv": "Ed25519",
+//!     "kid": "did:key:z6Mk...#thread-4f1c2a9b0e7d4c58a1b2c3d4e5f60718",
+//!     "x": "..."
+//!   }
+//! }
+//! ```
+//!
+//! Once a counterparty's thread key is known from an authenticated message
+//! (signed, or authcrypted by the counterparty), further messages encrypted
+//! to that counterparty in the thread are addressed to it instead of its DID
+//! key. The private thread keys are kept in memory by the
+//! [`AgentKeyManager`](crate::agent_key_manager::AgentKeyManager), are never
+//! written to key storage, and are dropped with
+//! [`retire_thread_keys`](crate::agent_key_manager::AgentKeyManager::retire_thread_keys)
+//! when the transaction ends, so that messages of the thread cannot be
+//! decrypted afterwards.
+
+use crate::crypto::EcdhPublicKey;
+use crate::did::KeyType;
+use crate::error::{Error, Result};
+use crate::local_agent_key::{LocalAgentKey, PublicVerificationKey};
+use serde_json::Value;
+use std::collections::HashMap;
+use tap_msg::didcomm::PlainMessage;
+
+/// DIDComm header carrying the sender's public key agreement key for the thread
+pub const THREAD_KEY_HEADER: &str = "thread_key";
+
+/// Thread keys of one thread
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ThreadKeys {
+    /// Public keys of our thread keys, by our DID
+    pub own: HashMap<String, Value>,
+    /// Key IDs of the counterparties' thread keys, by counterparty DID
+    pub peers: HashMap<String, String>,
+}
+
+/// ID of the thread a message belongs to
+///
+/// The first message of a thread, such as a Transfer, starts it with its own
+/// ID.
+pub fn thread_id(message: &PlainMessage) -> &str {
+    message.thid.as_deref().unwrap_or(&message.id)
+}
+
+/// Generate a thread key for `did` of the same key type as its DID key
+///
+/// The key ID is a fresh fragment of `did`, so recipients of messages
+/// encrypted to it find the agent the message is for.
+pub(crate) fn generate_thread_key(did: &str, key_type: KeyType) -> Result<LocalAgentKey> {
+    let key = match key_type {
+        #[cfg(feature = "crypto-ed25519")]
+        KeyType::Ed25519 => LocalAgentKey::generate_ed25519("")?,
+        #[cfg(feature = "crypto-p256")]
+        KeyType::P256 => LocalAgentKey::generate_p256("")?,
+        #[cfg(feature = "crypto-secp256k1")]
+        KeyType::Secp256k1 => LocalAgentKey::generate_secp256k1("")?,
+    };
+    let kid = format!("{}#thread-{}", did, uuid::Uuid::new_v4().simple());
+    Ok(key.with_key_id(did, &kid))
+}
+
+/// The thread key advertised in a message, if any
+///
+/// The key must be a public key agreement key identified as a thread key of
+/// the message's sender. Whether the message is authenticated is up to the
+/// caller.
+pub fn advertised_thread_key(message: &PlainMessage) -> Result<Option<PublicVerificationKey>> {
+    let Some(jwk) = message.extra_headers.get(THREAD_KEY_HEADER) else {
+        return Ok(None);
+    };
+    let kid = jwk
+        .get("kid")
+        .and_then(Value::as_str)
+        .ok_or_else(|| Error::Validation("Thread key has no kid".to_string()))?;
+    if !kid.starts_with(&format!("{}#thread-", message.from)) {
+        return Err(Error::Validation(format!(
+            "Thread key {} is not a thread key of the sender {}",
+            kid, message.from
+        )));
+    }
+    if jwk.get("d").is_some() {
+        return Err(Error::Validation(format!(
+            "Thread key {} contains private key material",
+            kid
+        )));
+    }
+    EcdhPublicKey::from_jwk(jwk)?;
+    Ok(Some(PublicVerificationKey::new(
+        kid.to_string(),
+        jwk.clone(),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent_key::AgentKey;
+    use serde_json::json;
+
+    fn message_from(from: &str, thread_key: Option<Value>) -> PlainMessage {
+        let mut message = PlainMessage::new(
+            "msg-1".to_string(),
+            "https://tap.rsvp/schema/1.0#Authorize".to_string(),
+            json!({}),
+            from.to_string(),
+        );
+        if let Some(thread_key) = thread_key {
+            message
+                .extra_headers
+                .insert(THREAD_KEY_HEADER.to_string(), thread_key);
+        }
+        message
+    }
+
+    #[test]
+    fn test_generated_keys_belong_to_the_did() {
+        let did = "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK";
+        let first = generate_thread_key(did, KeyType::Ed25519).unwrap();
+        let second = generate_thread_key(did, KeyType::Ed25519).unwrap();
+        assert!(first.key_id().starts_with(&format!("{}#thread-", did)));
+        assert_ne!(first.key_id(), second.key_id());
+        assert_eq!(AgentKey::did(&first), did);
+
+        let p256 = generate_thread_key(did, KeyType::P256).unwrap();
+        assert_eq!(p256.public_key_jwk().unwrap()["crv"], "P-256");
+    }
+
+    #[test]
+    fn test_advertised_thread_keys() {
+        let did = "did:example:alice";
+        let key = generate_thread_key(did, KeyType::Ed25519).unwrap();
+        let public_jwk = key.public_key_jwk().unwrap();
+
+        let advertised = advertised_thread_key(&message_from(did, Some(public_jwk.clone())))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            crate::agent_key::VerificationKey::key_id(&advertised),
+            key.key_id()
+        );
+        assert!(advertised_thread_key(&message_from(did, None))
+            .unwrap()
+            .is_none());
+
+        // Keys of other parties and private keys are refused
+        assert!(advertised_thread_key(&message_from(
+            "did:example:mallory",
+            Some(public_jwk.clone())
+        ))
+        .is_err());
+        let mut private_jwk = public_jwk;
+        private_jwk["d"] = json!("c2VjcmV0");
+        assert!(advertised_thread_key(&message_from(did, Some(private_jwk))).is_err());
+    }
+}
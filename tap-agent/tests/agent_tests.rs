@@ -275,3 +275,114 @@ async fn test_from_private_key_p256() {
     // Verify the received message is the same as the sent message
     assert_eq!(presentation.id, received_presentation.id);
 }
+
+#[tokio::test]
+async fn test_send_message_localizes_for_counterparty() {
+    use tap_agent::MessageCatalog;
+    use tap_msg::message::Reject;
+
+    let (mut agent, _) = TapAgent::from_ephemeral_key().await.unwrap();
+    agent.config = agent
+        .config
+        .clone()
+        .with_message_catalog(MessageCatalog::new().with_translation(
+            "Insufficient funds",
+            "de",
+            "Unzureichende Deckung",
+        ))
+        .with_counterparty_locales("did:example:456", vec!["ja".into(), "de-CH".into()]);
+
+    let reject = Reject::new("tx-1", "Insufficient funds");
+
+    // The counterparty prefers a locale the catalog covers
+    let (packed, _) = agent
+        .send_message(&reject, vec!["did:example:456"], false)
+        .await
+        .unwrap();
+    let plain_message = agent.receive_message(&packed).await.unwrap();
+    assert_eq!(plain_message.body["reason"], "Unzureichende Deckung");
+    assert_eq!(plain_message.extra_headers["lang"], "de-CH");
+
+    // A counterparty without locale preferences receives the source text
+    let (packed, _) = agent
+        .send_message(&reject, vec!["did:example:123"], false)
+        .await
+        .unwrap();
+    let plain_message = agent.receive_message(&packed).await.unwrap();
+    assert_eq!(plain_message.body["reason"], "Insufficient funds");
+    assert!(!plain_message.extra_headers.contains_key("lang"));
+}
+
+#[tokio::test]
+async fn test_signing_key_selected_by_purpose() {
+    use base64::Engine;
+    use ed25519_dalek::SigningKey;
+    use rand::rngs::OsRng;
+    use tap_agent::key_manager::KeyManager;
+    use tap_agent::message::base64_decode_flexible;
+    use tap_agent::{KeyPurpose, KeyType, LocalAgentKey};
+    use tap_msg::message::Reject;
+
+    let (mut agent, did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let primary_kid = agent.get_signing_kid().await.unwrap();
+
+    // Without configured keys, every purpose uses the authentication key
+    for purpose in [KeyPurpose::KeyAgreement, KeyPurpose::Attestation] {
+        assert_eq!(
+            agent.get_kid_for_purpose(purpose).await.unwrap(),
+            primary_kid
+        );
+    }
+
+    // A segregated authentication key, as listed in a did:web document
+    let auth_kid = format!("{}#authentication-2", did);
+    let signing_key = SigningKey::generate(&mut OsRng);
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let secret = Secret {
+        id: did.clone(),
+        type_: SecretType::JsonWebKey2020,
+        secret_material: SecretMaterial::JWK {
+            private_key_jwk: serde_json::json!({
+                "kty": "OKP",
+                "crv": "Ed25519",
+                "x": b64.encode(signing_key.verifying_key().to_bytes()),
+                "d": b64.encode(signing_key.to_bytes()),
+                "kid": auth_kid,
+            }),
+        },
+    };
+    agent
+        .key_manager()
+        .add_signing_key(Arc::new(LocalAgentKey::new(secret, KeyType::Ed25519)))
+        .await
+        .unwrap();
+    agent.config = agent
+        .config
+        .clone()
+        .with_key_id(KeyPurpose::Authentication, &auth_kid);
+
+    assert_eq!(agent.get_signing_kid().await.unwrap(), auth_kid);
+    assert_eq!(
+        agent
+            .get_kid_for_purpose(KeyPurpose::Attestation)
+            .await
+            .unwrap(),
+        auth_kid
+    );
+
+    // Signed messages name the segregated key
+    let (packed, _) = agent
+        .send_message(
+            &Reject::new("tx-1", "Insufficient funds"),
+            vec!["did:example:456"],
+            false,
+        )
+        .await
+        .unwrap();
+    let jws: serde_json::Value = serde_json::from_str(&packed).unwrap();
+    let protected: serde_json::Value = serde_json::from_slice(
+        &base64_decode_flexible(jws["protected"].as_str().unwrap()).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(protected["kid"], auth_kid);
+}
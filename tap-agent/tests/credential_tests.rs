@@ -0,0 +1,627 @@
+//! Tests for verifying verifiable credentials and presentations, issued as
+//! JWTs or secured with Data Integrity proofs
+
+use ed25519_dalek::{Signer, SigningKey};
+use multibase::Base;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tap_agent::key_manager::KeyManager;
+use tap_agent::message::JwsProtected;
+use tap_agent::{
+    rdf, verify_credential, verify_presentation, DIDDoc, MultiResolver, PresentationChallenge,
+    SyncDIDResolver, TapAgent,
+};
+use tap_msg::utils::jcs::canonicalize;
+
+const NOW: i64 = 1_750_000_000;
+
+/// Sign `claims` as a JWT with the key of `issuer`, returning the compact
+/// and JSON serializations
+async fn issue(issuer: &TapAgent, claims: serde_json::Value) -> (String, serde_json::Value) {
+    let kid = issuer.get_signing_kid().await.unwrap();
+    let protected = JwsProtected {
+        typ: "JWT".to_string(),
+        alg: String::new(),
+        kid: kid.clone(),
+        zip: None,
+    };
+    let jws = issuer
+        .key_manager()
+        .sign_jws(&kid, &serde_json::to_vec(&claims).unwrap(), Some(protected))
+        .await
+        .unwrap();
+    let jws: serde_json::Value = serde_json::from_str(&jws).unwrap();
+    let compact = format!(
+        "{}.{}.{}",
+        jws["protected"].as_str().unwrap(),
+        jws["payload"].as_str().unwrap(),
+        jws["signature"].as_str().unwrap()
+    );
+    (compact, jws)
+}
+
+fn license_claims(issuer: &str, subject: &str, exp: i64) -> serde_json::Value {
+    json!({
+        "iss": issuer,
+        "sub": subject,
+        "nbf": NOW - 60,
+        "exp": exp,
+        "vc": {
+            "type": ["VerifiableCredential", "VASPLicense"],
+            "credentialSubject": {
+                "id": subject,
+                "license": {"jurisdiction": "US", "number": "MSB-1234"}
+            }
+        }
+    })
+}
+
+#[tokio::test]
+async fn test_verify_credential() {
+    let (issuer, issuer_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let resolver = MultiResolver::default();
+    let (compact, json_jws) = issue(
+        &issuer,
+        license_claims(&issuer_did, "did:web:vasp.example", NOW + 3600),
+    )
+    .await;
+
+    for credential in [json!(compact), json_jws] {
+        let verified = verify_credential(&credential, &resolver, NOW)
+            .await
+            .unwrap();
+        assert_eq!(verified.issuer, issuer_did);
+        assert_eq!(verified.subject.as_deref(), Some("did:web:vasp.example"));
+        assert!(verified.has_type("VASPLicense"));
+        assert_eq!(verified.claim("license.jurisdiction"), Some(&json!("US")));
+        assert_eq!(verified.claim("license.missing"), None);
+        assert_eq!(verified.expires_at, Some(NOW + 3600));
+    }
+}
+
+#[tokio::test]
+async fn test_reject_invalid_credentials() {
+    let (issuer, issuer_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (_, other_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let resolver = MultiResolver::default();
+
+    // Expired
+    let (expired, _) = issue(
+        &issuer,
+        license_claims(&issuer_did, "did:web:vasp.example", NOW - 1),
+    )
+    .await;
+    let err = verify_credential(&json!(expired), &resolver, NOW)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("expired"));
+
+    // Not yet valid
+    let (future, _) = issue(
+        &issuer,
+        license_claims(&issuer_did, "did:web:vasp.example", NOW + 3600),
+    )
+    .await;
+    assert!(verify_credential(&json!(future), &resolver, NOW - 3600)
+        .await
+        .is_err());
+
+    // Claims to be issued by someone other than the signer
+    let (forged, _) = issue(
+        &issuer,
+        license_claims(&other_did, "did:web:vasp.example", NOW + 3600),
+    )
+    .await;
+    let err = verify_credential(&json!(forged), &resolver, NOW)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("signed by"));
+
+    // Tampered payload
+    let (valid, _) = issue(
+        &issuer,
+        license_claims(&issuer_did, "did:web:vasp.example", NOW + 3600),
+    )
+    .await;
+    let mut parts: Vec<&str> = valid.split('.').collect();
+    let (tampered, _) = issue(
+        &issuer,
+        license_claims(&issuer_did, "did:web:other.example", NOW + 3600),
+    )
+    .await;
+    parts[1] = tampered.split('.').nth(1).unwrap();
+    assert!(verify_credential(&json!(parts.join(".")), &resolver, NOW)
+        .await
+        .is_err());
+
+    assert!(verify_credential(&json!("not-a-jwt"), &resolver, NOW)
+        .await
+        .is_err());
+}
+
+/// An Ed25519 key with its did:key DID and verification method
+fn did_key(seed: u8) -> (SigningKey, String, String) {
+    let key = SigningKey::from_bytes(&[seed; 32]);
+    let mut public_key = vec![0xed, 0x01];
+    public_key.extend_from_slice(key.verifying_key().as_bytes());
+    let multibase = multibase::encode(Base::Base58Btc, public_key);
+    let did = format!("did:key:{}", multibase);
+    let kid = format!("{}#{}", did, multibase);
+    (key, did, kid)
+}
+
+/// The challenge the verifier of the tests sends with its requests
+fn request() -> PresentationChallenge {
+    PresentationChallenge {
+        challenge: "c0ffee".to_string(),
+        domain: "did:example:verifier".to_string(),
+    }
+}
+
+/// Secure a document with an eddsa-jcs-2022 Data Integrity proof
+fn secure(
+    document: serde_json::Value,
+    key: &SigningKey,
+    kid: &str,
+    purpose: &str,
+) -> serde_json::Value {
+    sign(document, key, proof(kid, purpose))
+}
+
+/// Secure a presentation with a holder proof over a challenge and domain
+fn present(
+    document: serde_json::Value,
+    key: &SigningKey,
+    kid: &str,
+    purpose: &str,
+    request: &PresentationChallenge,
+) -> serde_json::Value {
+    let mut proof = proof(kid, purpose);
+    proof["challenge"] = json!(request.challenge);
+    proof["domain"] = json!(request.domain);
+    sign(document, key, proof)
+}
+
+fn proof(kid: &str, purpose: &str) -> serde_json::Value {
+    json!({
+        "type": "DataIntegrityProof",
+        "cryptosuite": "eddsa-jcs-2022",
+        "verificationMethod": kid,
+        "proofPurpose": purpose,
+        "created": "2025-06-15T00:00:00Z",
+    })
+}
+
+fn sign(
+    document: serde_json::Value,
+    key: &SigningKey,
+    mut proof: serde_json::Value,
+) -> serde_json::Value {
+    let mut options = proof.clone();
+    options["@context"] = document["@context"].clone();
+    let mut hash_data = Sha256::digest(canonicalize(&options).as_bytes()).to_vec();
+    hash_data.extend_from_slice(&Sha256::digest(canonicalize(&document).as_bytes()));
+    proof["proofValue"] = json!(multibase::encode(
+        Base::Base58Btc,
+        key.sign(&hash_data).to_bytes()
+    ));
+
+    let mut secured = document;
+    secured["proof"] = proof;
+    secured
+}
+
+fn travel_rule_credential(issuer: &str, subject: &str, valid_until: &str) -> serde_json::Value {
+    json!({
+        "@context": ["https://www.w3.org/ns/credentials/v2", "https://intervasp.org/ivms101"],
+        "type": ["VerifiableCredential", "TravelRuleCredential"],
+        "issuer": issuer,
+        "validFrom": "2025-01-01T00:00:00Z",
+        "validUntil": valid_until,
+        "credentialSubject": {
+            "id": subject,
+            "originator": {"naturalPerson": {"name": {"nameIdentifiers": [{"primaryIdentifier": "Smith"}]}}}
+        },
+        "credentialStatus": {
+            "type": "BitstringStatusListEntry",
+            "statusPurpose": "revocation",
+            "statusListIndex": "94567",
+            "statusListCredential": "https://vasp.example/status/3"
+        }
+    })
+}
+
+fn presentation(holder: &str, credentials: Vec<serde_json::Value>) -> serde_json::Value {
+    json!({
+        "@context": ["https://www.w3.org/ns/credentials/v2"],
+        "type": ["VerifiablePresentation"],
+        "holder": holder,
+        "verifiableCredential": credentials,
+    })
+}
+
+#[tokio::test]
+async fn test_verify_secured_presentation() {
+    let (issuer_key, issuer_did, issuer_kid) = did_key(1);
+    let (holder_key, holder_did, holder_kid) = did_key(2);
+    let resolver = MultiResolver::default();
+
+    let credential = secure(
+        travel_rule_credential(&issuer_did, &holder_did, "2026-01-01T00:00:00Z"),
+        &issuer_key,
+        &issuer_kid,
+        "assertionMethod",
+    );
+    let verified = verify_credential(&credential, &resolver, NOW)
+        .await
+        .unwrap();
+    assert_eq!(verified.issuer, issuer_did);
+    assert_eq!(verified.subject.as_deref(), Some(holder_did.as_str()));
+    assert!(verified.has_type("TravelRuleCredential"));
+    assert_eq!(verified.verification.alg, "eddsa-jcs-2022");
+    assert_eq!(
+        verified.status.as_ref().unwrap()["statusListIndex"],
+        json!("94567")
+    );
+
+    // JWT and Data Integrity credentials in a presentation signed by the holder
+    let (jwt_issuer, jwt_issuer_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (jwt, _) = issue(
+        &jwt_issuer,
+        license_claims(&jwt_issuer_did, &holder_did, NOW + 3600),
+    )
+    .await;
+    let vp = present(
+        presentation(&holder_did, vec![credential.clone(), json!(jwt)]),
+        &holder_key,
+        &holder_kid,
+        "authentication",
+        &request(),
+    );
+    let verified = verify_presentation(&vp, &request(), &resolver, NOW)
+        .await
+        .unwrap();
+    assert_eq!(verified.holder.as_deref(), Some(holder_did.as_str()));
+    assert_eq!(verified.verification.signer_did, holder_did);
+    assert_eq!(verified.credentials.len(), 2);
+    assert!(verified.credentials[1].has_type("VASPLicense"));
+
+    // The same presentation as a JWT signed by the holder
+    let (jwt_holder, jwt_holder_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (jwt_vp, _) = issue(
+        &jwt_holder,
+        json!({
+            "iss": jwt_holder_did,
+            "exp": NOW + 60,
+            "nonce": "c0ffee",
+            "aud": ["did:example:verifier"],
+            "vp": {"type": ["VerifiablePresentation"], "verifiableCredential": [credential]}
+        }),
+    )
+    .await;
+    let verified = verify_presentation(&json!(jwt_vp), &request(), &resolver, NOW)
+        .await
+        .unwrap();
+    assert_eq!(verified.holder, Some(jwt_holder_did));
+    assert_eq!(verified.credentials[0].issuer, issuer_did);
+}
+
+#[tokio::test]
+async fn test_reject_invalid_presentations() {
+    let (issuer_key, issuer_did, issuer_kid) = did_key(1);
+    let (holder_key, holder_did, holder_kid) = did_key(2);
+    let resolver = MultiResolver::default();
+    let credential = secure(
+        travel_rule_credential(&issuer_did, &holder_did, "2026-01-01T00:00:00Z"),
+        &issuer_key,
+        &issuer_kid,
+        "assertionMethod",
+    );
+
+    // Tampered credential subject
+    let mut tampered = credential.clone();
+    tampered["credentialSubject"]["id"] = json!("did:example:mallory");
+    let err = verify_credential(&tampered, &resolver, NOW)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("verification failed"));
+
+    // Expired credential, reported with its position in the presentation
+    let expired = secure(
+        travel_rule_credential(&issuer_did, &holder_did, "2025-06-01T00:00:00Z"),
+        &issuer_key,
+        &issuer_kid,
+        "assertionMethod",
+    );
+    let vp = present(
+        presentation(&holder_did, vec![credential.clone(), expired]),
+        &holder_key,
+        &holder_kid,
+        "authentication",
+        &request(),
+    );
+    let err = verify_presentation(&vp, &request(), &resolver, NOW)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Credential 1: "));
+    assert!(err.to_string().contains("expired"));
+
+    // Credential signed by someone other than its issuer
+    let forged = secure(
+        travel_rule_credential(&holder_did, &holder_did, "2026-01-01T00:00:00Z"),
+        &issuer_key,
+        &issuer_kid,
+        "assertionMethod",
+    );
+    let err = verify_credential(&forged, &resolver, NOW)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("signed by"));
+
+    // Presentation signed by a key other than the holder's
+    let vp = present(
+        presentation(&holder_did, vec![credential.clone()]),
+        &issuer_key,
+        &issuer_kid,
+        "authentication",
+        &request(),
+    );
+    let err = verify_presentation(&vp, &request(), &resolver, NOW)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("signed by"));
+
+    // Holder proof made for the wrong purpose
+    let vp = present(
+        presentation(&holder_did, vec![credential.clone()]),
+        &holder_key,
+        &holder_kid,
+        "assertionMethod",
+        &request(),
+    );
+    assert!(verify_presentation(&vp, &request(), &resolver, NOW)
+        .await
+        .is_err());
+
+    // Presentations without a holder proof
+    let unsigned = presentation(&holder_did, vec![credential.clone()]);
+    let err = verify_presentation(&unsigned, &request(), &resolver, NOW)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("no holder proof"));
+    let unbound = secure(
+        presentation(&holder_did, vec![credential.clone()]),
+        &holder_key,
+        &holder_kid,
+        "authentication",
+    );
+    let err = verify_presentation(&unbound, &request(), &resolver, NOW)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("no challenge"));
+
+    // Holder proofs answering another request or made for another verifier
+    let vp = present(
+        presentation(&holder_did, vec![credential.clone()]),
+        &holder_key,
+        &holder_kid,
+        "authentication",
+        &request(),
+    );
+    let replayed = PresentationChallenge {
+        challenge: "decaf".to_string(),
+        ..request()
+    };
+    let err = verify_presentation(&vp, &replayed, &resolver, NOW)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("answers challenge c0ffee"));
+    let elsewhere = PresentationChallenge {
+        domain: "did:example:other".to_string(),
+        ..request()
+    };
+    let err = verify_presentation(&vp, &elsewhere, &resolver, NOW)
+        .await
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("not addressed to did:example:other"));
+
+    // A JWT presentation without the nonce of the request
+    let (jwt_holder, jwt_holder_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (jwt_vp, _) = issue(
+        &jwt_holder,
+        json!({
+            "iss": jwt_holder_did,
+            "exp": NOW + 60,
+            "aud": "did:example:verifier",
+            "vp": {"type": ["VerifiablePresentation"], "verifiableCredential": [credential.clone()]}
+        }),
+    )
+    .await;
+    assert!(
+        verify_presentation(&json!(jwt_vp), &request(), &resolver, NOW)
+            .await
+            .is_err()
+    );
+
+    // Proofs of a suite that is not supported
+    let mut secp = credential.clone();
+    secp["proof"]["cryptosuite"] = json!("ecdsa-rdfc-2019");
+    let err = verify_credential(&secp, &resolver, NOW).await.unwrap_err();
+    assert!(err.to_string().contains("Unsupported proof type"));
+}
+
+/// Secure a document with a proof over its RDF canonicalization
+fn sign_rdf(
+    document: serde_json::Value,
+    key: &SigningKey,
+    mut proof: serde_json::Value,
+) -> serde_json::Value {
+    let mut options = proof.clone();
+    options["@context"] = document["@context"].clone();
+    let mut hash_data = Sha256::digest(rdf::canonicalize(&options).unwrap().as_bytes()).to_vec();
+    hash_data.extend_from_slice(&Sha256::digest(
+        rdf::canonicalize(&document).unwrap().as_bytes(),
+    ));
+    proof["proofValue"] = json!(multibase::encode(
+        Base::Base58Btc,
+        key.sign(&hash_data).to_bytes()
+    ));
+
+    let mut secured = document;
+    secured["proof"] = proof;
+    secured
+}
+
+fn license_credential(issuer: &str, subject: &str) -> serde_json::Value {
+    json!({
+        "@context": [
+            "https://www.w3.org/2018/credentials/v1",
+            "https://w3id.org/security/suites/ed25519-2020/v1",
+            {"VASPLicense": "https://vasp.example/VASPLicense", "licenseNumber": "https://vasp.example/licenseNumber"}
+        ],
+        "id": "urn:uuid:7a1f5bb6-8d5e-4c8f-a61e-1b7d1c2e3f40",
+        "type": ["VerifiableCredential", "VASPLicense"],
+        "issuer": issuer,
+        "issuanceDate": "2025-01-01T00:00:00Z",
+        "expirationDate": "2026-01-01T00:00:00Z",
+        "credentialSubject": {"id": subject, "licenseNumber": "FCA-123"}
+    })
+}
+
+#[tokio::test]
+async fn test_verify_ed25519_signature_2020_credential() {
+    let (issuer_key, issuer_did, issuer_kid) = did_key(1);
+    let (_, holder_did, _) = did_key(2);
+    let resolver = MultiResolver::default();
+
+    let credential = sign_rdf(
+        license_credential(&issuer_did, &holder_did),
+        &issuer_key,
+        json!({
+            "type": "Ed25519Signature2020",
+            "created": "2025-06-15T00:00:00Z",
+            "verificationMethod": issuer_kid,
+            "proofPurpose": "assertionMethod",
+        }),
+    );
+    let verified = verify_credential(&credential, &resolver, NOW)
+        .await
+        .unwrap();
+    assert_eq!(verified.issuer, issuer_did);
+    assert!(verified.has_type("VASPLicense"));
+    assert_eq!(verified.verification.alg, "Ed25519Signature2020");
+
+    // The signature covers the claims, whatever their JSON layout
+    let mut reordered = credential.clone();
+    reordered["type"] = json!(["VASPLicense", "VerifiableCredential"]);
+    assert!(verify_credential(&reordered, &resolver, NOW).await.is_ok());
+    let mut tampered = credential.clone();
+    tampered["credentialSubject"]["licenseNumber"] = json!("FCA-999");
+    let err = verify_credential(&tampered, &resolver, NOW)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("signature verification failed"));
+
+    // Properties no context defines would not be signed
+    let mut unsigned = credential.clone();
+    unsigned["credentialSubject"]["note"] = json!("not signed");
+    let err = verify_credential(&unsigned, &resolver, NOW)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Property note is not defined"));
+
+    // Contexts are not fetched
+    let mut remote = credential;
+    remote["@context"][2] = json!("https://vasp.example/context");
+    let err = verify_credential(&remote, &resolver, NOW)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("not one of the bundled contexts"));
+}
+
+#[tokio::test]
+async fn test_verify_eddsa_rdfc_2022_presentation() {
+    let (issuer_key, issuer_did, issuer_kid) = did_key(1);
+    let (holder_key, holder_did, holder_kid) = did_key(2);
+    let resolver = MultiResolver::default();
+
+    let mut credential = travel_rule_credential(&issuer_did, &holder_did, "2026-01-01T00:00:00Z");
+    credential["@context"] = json!(["https://www.w3.org/ns/credentials/v2"]);
+    let rdfc_proof = |kid: &str, purpose: &str| {
+        let mut proof = proof(kid, purpose);
+        proof["cryptosuite"] = json!("eddsa-rdfc-2022");
+        proof
+    };
+    let credential = sign_rdf(
+        credential,
+        &issuer_key,
+        rdfc_proof(&issuer_kid, "assertionMethod"),
+    );
+    let verified = verify_credential(&credential, &resolver, NOW)
+        .await
+        .unwrap();
+    assert_eq!(verified.verification.alg, "eddsa-rdfc-2022");
+    assert!(verified.has_type("TravelRuleCredential"));
+
+    let mut holder_proof = rdfc_proof(&holder_kid, "authentication");
+    holder_proof["challenge"] = json!(request().challenge);
+    holder_proof["domain"] = json!(request().domain);
+    let vp = sign_rdf(
+        presentation(&holder_did, vec![credential]),
+        &holder_key,
+        holder_proof,
+    );
+    let verified = verify_presentation(&vp, &request(), &resolver, NOW)
+        .await
+        .unwrap();
+    assert_eq!(verified.holder.as_deref(), Some(holder_did.as_str()));
+    assert_eq!(verified.credentials[0].issuer, issuer_did);
+
+    // The holder's signature covers the embedded credential
+    let mut swapped = vp.clone();
+    swapped["verifiableCredential"][0]["credentialSubject"]["id"] = json!("did:example:mallory");
+    assert!(verify_presentation(&swapped, &request(), &resolver, NOW)
+        .await
+        .is_err());
+}
+
+/// Resolves did:key DIDs into documents that list no assertion keys
+#[derive(Debug, Default)]
+struct NoAssertionResolver(MultiResolver);
+
+#[async_trait::async_trait]
+impl SyncDIDResolver for NoAssertionResolver {
+    async fn resolve(&self, did: &str) -> tap_agent::Result<Option<DIDDoc>> {
+        let mut doc = self.0.resolve(did).await?;
+        if let Some(doc) = doc.as_mut() {
+            doc.assertion_method.clear();
+        }
+        Ok(doc)
+    }
+}
+
+#[tokio::test]
+async fn test_keys_must_be_listed_for_the_proof_purpose() {
+    let (issuer_key, issuer_did, issuer_kid) = did_key(1);
+    let (_, holder_did, _) = did_key(2);
+    let credential = secure(
+        travel_rule_credential(&issuer_did, &holder_did, "2026-01-01T00:00:00Z"),
+        &issuer_key,
+        &issuer_kid,
+        "assertionMethod",
+    );
+    assert!(
+        verify_credential(&credential, &MultiResolver::default(), NOW)
+            .await
+            .is_ok()
+    );
+
+    let err = verify_credential(&credential, &NoAssertionResolver::default(), NOW)
+        .await
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("not authorized for assertionMethod"));
+}
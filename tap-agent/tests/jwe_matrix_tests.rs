@@ -0,0 +1,186 @@
+//! DIDComm v2 encryption matrix tests
+//!
+//! Covers anoncrypt (ECDH-ES+A256KW) and authcrypt (ECDH-1PU+A256KW) with
+//! A256GCM and A256CBC-HS512 across Ed25519 (via X25519), P-256 and
+//! secp256k1 keys.
+
+#![cfg(all(
+    feature = "crypto-ed25519",
+    feature = "crypto-p256",
+    feature = "crypto-secp256k1"
+))]
+
+use std::sync::Arc;
+use tap_agent::agent_key::{
+    AgentKey, DecryptionKey, EncryptionKey, JweCipher, JweEncryption, VerificationKey,
+};
+use tap_agent::local_agent_key::{create_anoncrypt_jwe, LocalAgentKey, PublicVerificationKey};
+
+fn generate(key_type: &str) -> LocalAgentKey {
+    match key_type {
+        "Ed25519" => LocalAgentKey::generate_ed25519("key").unwrap(),
+        "P-256" => LocalAgentKey::generate_p256("key").unwrap(),
+        "secp256k1" => LocalAgentKey::generate_secp256k1("key").unwrap(),
+        _ => unreachable!(),
+    }
+}
+
+fn public_key(key: &LocalAgentKey) -> PublicVerificationKey {
+    PublicVerificationKey::new(
+        AgentKey::key_id(key).to_string(),
+        AgentKey::public_key_jwk(key).unwrap(),
+    )
+}
+
+const KEY_TYPES: [&str; 3] = ["Ed25519", "P-256", "secp256k1"];
+const ENCRYPTIONS: [JweEncryption; 2] = [JweEncryption::A256GCM, JweEncryption::A256CBCHS512];
+
+#[tokio::test]
+async fn test_anoncrypt_matrix() {
+    for key_type in KEY_TYPES {
+        for enc in ENCRYPTIONS {
+            let recipient = generate(key_type);
+            let recipients: Vec<Arc<dyn VerificationKey>> = vec![Arc::new(public_key(&recipient))];
+            let plaintext = format!("anoncrypt {} {}", key_type, enc.as_str());
+
+            let jwe = create_anoncrypt_jwe(plaintext.as_bytes(), &recipients, enc, None).unwrap();
+
+            let protected = jwe.get_protected_header().unwrap();
+            assert_eq!(protected.alg, "ECDH-ES+A256KW");
+            assert_eq!(protected.enc, enc.as_str());
+            assert!(protected.skid.is_none());
+            assert!(jwe.recipients[0].header.sender_kid.is_none());
+
+            let decrypted = recipient.unwrap_jwe(&jwe).await.unwrap();
+            assert_eq!(decrypted, plaintext.as_bytes(), "{} {:?}", key_type, enc);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_authcrypt_matrix() {
+    for key_type in KEY_TYPES {
+        for enc in ENCRYPTIONS {
+            let sender = generate(key_type);
+            let recipient = generate(key_type);
+            let recipients: Vec<Arc<dyn VerificationKey>> = vec![Arc::new(public_key(&recipient))];
+            let plaintext = format!("authcrypt {} {}", key_type, enc.as_str());
+
+            let jwe = sender
+                .create_jwe(
+                    plaintext.as_bytes(),
+                    &recipients,
+                    Some(JweCipher::authcrypt(enc)),
+                )
+                .await
+                .unwrap();
+
+            let protected = jwe.get_protected_header().unwrap();
+            assert_eq!(protected.alg, "ECDH-1PU+A256KW");
+            assert_eq!(protected.enc, enc.as_str());
+            assert_eq!(
+                jwe.authcrypt_sender_kid().as_deref(),
+                Some(AgentKey::key_id(&sender))
+            );
+
+            let decrypted = recipient
+                .unwrap_authcrypt_jwe(&jwe, &public_key(&sender))
+                .await
+                .unwrap();
+            assert_eq!(decrypted, plaintext.as_bytes(), "{} {:?}", key_type, enc);
+
+            // Authcrypt cannot be opened without authenticating the sender
+            assert!(recipient.unwrap_jwe(&jwe).await.is_err());
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_authcrypt_rejects_wrong_sender() {
+    let sender = generate("Ed25519");
+    let impostor = generate("Ed25519");
+    let recipient = generate("Ed25519");
+    let recipients: Vec<Arc<dyn VerificationKey>> = vec![Arc::new(public_key(&recipient))];
+
+    let jwe = sender
+        .create_jwe(
+            b"secret",
+            &recipients,
+            Some(JweCipher::authcrypt(JweEncryption::A256CBCHS512)),
+        )
+        .await
+        .unwrap();
+
+    // Impostor key claiming the sender's key ID
+    let forged = PublicVerificationKey::new(
+        AgentKey::key_id(&sender).to_string(),
+        AgentKey::public_key_jwk(&impostor).unwrap(),
+    );
+    assert!(recipient.unwrap_authcrypt_jwe(&jwe, &forged).await.is_err());
+
+    // A key with a different ID than the skid is refused
+    assert!(recipient
+        .unwrap_authcrypt_jwe(&jwe, &public_key(&impostor))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_multiple_recipients_share_curve() {
+    let sender = generate("P-256");
+    let first = generate("P-256");
+    let second = generate("P-256");
+    let recipients: Vec<Arc<dyn VerificationKey>> =
+        vec![Arc::new(public_key(&first)), Arc::new(public_key(&second))];
+
+    let jwe = sender
+        .create_jwe(
+            b"to both",
+            &recipients,
+            Some(JweCipher::authcrypt(JweEncryption::A256CBCHS512)),
+        )
+        .await
+        .unwrap();
+    assert_eq!(jwe.recipients.len(), 2);
+
+    for recipient in [&first, &second] {
+        let decrypted = recipient
+            .unwrap_authcrypt_jwe(&jwe, &public_key(&sender))
+            .await
+            .unwrap();
+        assert_eq!(decrypted, b"to both");
+    }
+}
+
+#[tokio::test]
+async fn test_curve_negotiation_errors() {
+    let ed25519 = generate("Ed25519");
+    let p256 = generate("P-256");
+
+    // Recipients on different curves cannot share a JWE
+    let mixed: Vec<Arc<dyn VerificationKey>> =
+        vec![Arc::new(public_key(&ed25519)), Arc::new(public_key(&p256))];
+    assert!(create_anoncrypt_jwe(b"x", &mixed, JweEncryption::A256GCM, None).is_err());
+
+    // Authcrypt requires the sender on the recipients' curve
+    let p256_recipient: Vec<Arc<dyn VerificationKey>> = vec![Arc::new(public_key(&p256))];
+    let result = ed25519
+        .create_jwe(
+            b"x",
+            &p256_recipient,
+            Some(JweCipher::authcrypt(JweEncryption::A256CBCHS512)),
+        )
+        .await;
+    assert!(result.is_err());
+
+    // Anoncrypt only depends on the recipient's curve
+    let jwe = ed25519
+        .create_jwe(
+            b"x",
+            &p256_recipient,
+            Some(JweCipher::anoncrypt(JweEncryption::A256GCM)),
+        )
+        .await
+        .unwrap();
+    assert_eq!(p256.unwrap_jwe(&jwe).await.unwrap(), b"x");
+}
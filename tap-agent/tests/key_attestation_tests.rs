@@ -0,0 +1,52 @@
+//! Tests for key attestations sent with outgoing messages
+
+use tap_agent::agent::{Agent, TapAgent};
+use tap_agent::attestation::KEY_ATTESTATION_HEADER;
+use tap_agent::{verify_key_attestation, MultiResolver};
+use tap_msg::message::Authorize;
+
+#[tokio::test]
+async fn test_messages_carry_the_key_attestation() {
+    let (organization, organization_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (mut alice, alice_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (bob, bob_did) = TapAgent::from_ephemeral_key().await.unwrap();
+
+    let attestation = organization
+        .attest_key(&alice_did, None, None)
+        .await
+        .unwrap();
+    alice.config = alice.config.clone().with_key_attestation(attestation);
+
+    let authorize = Authorize {
+        transaction_id: "tx-1".to_string(),
+        settlement_address: None,
+        expiry: None,
+    };
+    let (packed, _) = alice
+        .send_message(&authorize, vec![&bob_did], false)
+        .await
+        .unwrap();
+    let message = bob.receive_message(&packed).await.unwrap();
+    assert!(message.extra_headers.contains_key(KEY_ATTESTATION_HEADER));
+
+    let signer_kid = alice.get_signing_kid().await.unwrap();
+    let attestation = verify_key_attestation(
+        &message,
+        Some(&signer_kid),
+        &MultiResolver::default(),
+        chrono::Utc::now().timestamp(),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(attestation.organization, organization_did);
+    assert_eq!(attestation.agent_did, alice_did);
+
+    // Agents without an attestation send none
+    let (packed, _) = bob
+        .send_message(&authorize, vec![&alice_did], false)
+        .await
+        .unwrap();
+    let message = alice.receive_message(&packed).await.unwrap();
+    assert!(!message.extra_headers.contains_key(KEY_ATTESTATION_HEADER));
+}
@@ -0,0 +1,300 @@
+//! Negative tests for envelope unpacking
+//!
+//! Envelopes arrive from the open internet, so every malformed JWS, JWE or
+//! PlainMessage must be rejected with a typed error rather than a panic.
+//! These tests run the hostile inputs in `tests/corpus/envelopes` through
+//! the unpack path, then mutate valid signed and encrypted envelopes field
+//! by field and byte by byte. The same corpus seeds the cargo-fuzz targets
+//! in `tap-agent/fuzz`.
+
+use base64::Engine;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tap_agent::{
+    AgentKeyManager, AgentKeyManagerBuilder, DIDGenerationOptions, Jwe, Jws, KeyManager, KeyType,
+    PackOptions, Packable, PlainMessage, SecurityMode, UnpackOptions, Unpackable,
+};
+
+const CORPUS_DIR: &str = "tests/corpus/envelopes";
+
+/// Bytes likely to change how a mutated envelope parses
+const STRUCTURAL_BYTES: &[u8] = b"{}[]\",:\\0aA_-=";
+
+/// Values substituted for each field of a valid envelope
+fn hostile_values() -> Vec<Value> {
+    vec![
+        json!(""),
+        json!("!!!"),
+        json!("AA"),
+        json!("e30"),
+        json!("bnVsbA"),
+        json!("A".repeat(4096)),
+        json!("did:key:"),
+        json!("did:key:z"),
+        json!("#"),
+        json!(null),
+        json!(0),
+        json!(-1),
+        json!(u64::MAX),
+        json!(1.5e300),
+        json!(true),
+        json!([]),
+        json!({}),
+        json!([{}]),
+    ]
+}
+
+/// Deterministic xorshift generator so failures are reproducible
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn base64url(bytes: &[u8]) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+async fn setup() -> (Arc<AgentKeyManager>, String, String) {
+    let key_manager = Arc::new(AgentKeyManagerBuilder::new().build().unwrap());
+    let sender = key_manager
+        .generate_key(DIDGenerationOptions {
+            key_type: KeyType::Ed25519,
+        })
+        .unwrap();
+    let recipient = key_manager
+        .generate_key(DIDGenerationOptions {
+            key_type: KeyType::Ed25519,
+        })
+        .unwrap();
+    (
+        key_manager,
+        sender.did_doc.verification_method[0].id.clone(),
+        recipient.did_doc.verification_method[0].id.clone(),
+    )
+}
+
+fn test_message(sender_kid: &str, recipient_kid: &str) -> PlainMessage {
+    let did = |kid: &str| kid.split('#').next().unwrap().to_string();
+    PlainMessage {
+        id: "negative-test".to_string(),
+        typ: "application/didcomm-plain+json".to_string(),
+        type_: "https://tap.rsvp/schema/1.0#Authorize".to_string(),
+        body: json!({"transaction_id": "tx-1"}),
+        from: did(sender_kid),
+        to: vec![did(recipient_kid)],
+        thid: Some("tx-1".to_string()),
+        pthid: None,
+        created_time: Some(1234567890),
+        expires_time: None,
+        from_prior: None,
+        attachments: None,
+        extra_headers: Default::default(),
+    }
+}
+
+async fn packed_envelopes(
+    key_manager: &AgentKeyManager,
+    sender_kid: &str,
+    recipient_kid: &str,
+) -> Vec<(SecurityMode, String)> {
+    let message = test_message(sender_kid, recipient_kid);
+    let options = [
+        PackOptions::new().with_plain(),
+        PackOptions::new().with_sign(sender_kid),
+        PackOptions {
+            security_mode: SecurityMode::AuthCrypt,
+            sender_kid: Some(sender_kid.to_string()),
+            recipient_kid: Some(recipient_kid.to_string()),
+            content_encryption: None,
+            compression: None,
+        },
+        PackOptions {
+            security_mode: SecurityMode::AnonCrypt,
+            sender_kid: None,
+            recipient_kid: Some(recipient_kid.to_string()),
+            content_encryption: None,
+            compression: None,
+        },
+    ];
+
+    let mut envelopes = Vec::new();
+    for pack_options in options {
+        let mode = pack_options.security_mode;
+        let packed = message.pack(key_manager, pack_options).await.unwrap();
+        envelopes.push((mode, packed));
+    }
+    envelopes
+}
+
+/// Unpack through every entry point that accepts untrusted input
+///
+/// Returns whether the generic unpack succeeded; the caller decides whether
+/// that is acceptable. A panic anywhere fails the test.
+async fn unpack_all(key_manager: &AgentKeyManager, input: &str) -> bool {
+    if let Ok(jws) = serde_json::from_str::<Jws>(input) {
+        for signature in &jws.signatures {
+            let _ = signature.get_kid();
+            let _ = signature.get_protected_header();
+        }
+        let _: Result<PlainMessage, _> = Jws::unpack(&jws, key_manager, UnpackOptions::new()).await;
+    }
+    if let Ok(jwe) = serde_json::from_str::<Jwe>(input) {
+        let _ = jwe.get_protected_header();
+        let _ = jwe.authcrypt_sender_kid();
+        let _: Result<PlainMessage, _> = Jwe::unpack(&jwe, key_manager, UnpackOptions::new()).await;
+    }
+
+    let result: Result<PlainMessage, _> =
+        String::unpack(&input.to_string(), key_manager, UnpackOptions::new()).await;
+    result.is_ok()
+}
+
+/// Every string and value reachable from `value`, replaced one at a time
+fn field_mutations(value: &Value) -> Vec<Value> {
+    let mut mutations = Vec::new();
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                let mut removed = map.clone();
+                removed.remove(key);
+                mutations.push(Value::Object(removed));
+
+                for hostile in hostile_values() {
+                    let mut replaced = map.clone();
+                    replaced.insert(key.clone(), hostile);
+                    mutations.push(Value::Object(replaced));
+                }
+                for mutated_child in field_mutations(child) {
+                    let mut replaced = map.clone();
+                    replaced.insert(key.clone(), mutated_child);
+                    mutations.push(Value::Object(replaced));
+                }
+            }
+        }
+        Value::Array(items) => {
+            mutations.push(Value::Array(Vec::new()));
+            for (i, child) in items.iter().enumerate() {
+                for mutated_child in field_mutations(child) {
+                    let mut replaced = items.clone();
+                    replaced[i] = mutated_child;
+                    mutations.push(Value::Array(replaced));
+                }
+            }
+        }
+        Value::String(s) => {
+            // Mutate base64url-encoded JSON headers after decoding them
+            let decoded = base64::engine::general_purpose::URL_SAFE_NO_PAD
+                .decode(s)
+                .ok()
+                .and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok());
+            if let Some(header @ Value::Object(_)) = decoded {
+                for mutated in field_mutations(&header) {
+                    let encoded = base64url(mutated.to_string().as_bytes());
+                    mutations.push(Value::String(encoded));
+                }
+            }
+        }
+        _ => {}
+    }
+    mutations
+}
+
+#[tokio::test]
+async fn test_corpus_envelopes_are_rejected() {
+    let (key_manager, _, _) = setup().await;
+
+    let mut entries: Vec<_> = fs::read_dir(Path::new(CORPUS_DIR))
+        .expect("Failed to read envelope corpus")
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    entries.sort();
+    assert!(!entries.is_empty(), "Envelope corpus is empty");
+
+    for path in entries {
+        let input = fs::read_to_string(&path).unwrap();
+        assert!(
+            !unpack_all(&key_manager, &input).await,
+            "Corpus entry {} should be rejected",
+            path.display()
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_deeply_nested_envelopes_are_rejected() {
+    let (key_manager, _, _) = setup().await;
+
+    // serde_json stops at 128 levels of nesting
+    for depth in [1_000, 100_000] {
+        let nested = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        let inputs = [
+            nested.clone(),
+            format!(r#"{{"payload":{},"signatures":[]}}"#, nested),
+            format!(
+                r#"{{"id":"1","type":"https://tap.rsvp/schema/1.0#Authorize","from":"did:example:a","body":{}}}"#,
+                nested
+            ),
+        ];
+        for input in inputs {
+            assert!(!unpack_all(&key_manager, &input).await);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_field_mutations_do_not_panic() {
+    let (key_manager, sender_kid, recipient_kid) = setup().await;
+
+    for (mode, packed) in packed_envelopes(&key_manager, &sender_kid, &recipient_kid).await {
+        assert!(
+            unpack_all(&key_manager, &packed).await,
+            "Unmutated {:?} envelope should unpack",
+            mode
+        );
+
+        let envelope: Value = serde_json::from_str(&packed).unwrap();
+        for mutated in field_mutations(&envelope) {
+            unpack_all(&key_manager, &mutated.to_string()).await;
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_byte_mutations_do_not_panic() {
+    let (key_manager, sender_kid, recipient_kid) = setup().await;
+    let mut rng = XorShift(0x5eed_7a90_0001);
+
+    for (_, packed) in packed_envelopes(&key_manager, &sender_kid, &recipient_kid).await {
+        let bytes = packed.as_bytes();
+
+        for len in (0..bytes.len()).step_by(7) {
+            let truncated = String::from_utf8_lossy(&bytes[..len]);
+            unpack_all(&key_manager, &truncated).await;
+        }
+
+        for _ in 0..500 {
+            let mut mutated = bytes.to_vec();
+            for _ in 0..=rng.below(4) {
+                let i = rng.below(mutated.len());
+                mutated[i] = match rng.below(3) {
+                    0 => mutated[i] ^ (1 << rng.below(8)),
+                    1 => STRUCTURAL_BYTES[rng.below(STRUCTURAL_BYTES.len())],
+                    _ => rng.next() as u8,
+                };
+            }
+            unpack_all(&key_manager, &String::from_utf8_lossy(&mutated)).await;
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! Tests for ephemeral key agreement keys per thread
+
+use tap_agent::agent::{Agent, TapAgent};
+use tap_agent::thread_keys::THREAD_KEY_HEADER;
+use tap_agent::Jwe;
+use tap_msg::message::Authorize;
+
+async fn agent(security_mode: &str) -> (TapAgent, String) {
+    let (mut agent, did) = TapAgent::from_ephemeral_key().await.unwrap();
+    agent.config = agent
+        .config
+        .clone()
+        .with_security_mode(security_mode)
+        .with_thread_keys(true);
+    (agent, did)
+}
+
+fn authorize(transaction_id: &str) -> Authorize {
+    Authorize {
+        transaction_id: transaction_id.to_string(),
+        settlement_address: None,
+        expiry: None,
+    }
+}
+
+/// Key ID the packed message is encrypted to
+fn recipient_kid(packed: &str) -> String {
+    let jwe: Jwe = serde_json::from_str(packed).unwrap();
+    jwe.recipients[0].header.kid.clone()
+}
+
+#[tokio::test]
+async fn test_thread_messages_are_encrypted_to_thread_keys() {
+    let (alice, alice_did) = agent("AUTHCRYPT").await;
+    let (bob, bob_did) = agent("AUTHCRYPT").await;
+
+    // The first message goes to Bob's DID key and advertises Alice's thread key
+    let (packed, _) = alice
+        .send_message(&authorize("tx-1"), vec![&bob_did], false)
+        .await
+        .unwrap();
+    assert!(!recipient_kid(&packed).contains("#thread-"));
+    let message = bob.receive_message(&packed).await.unwrap();
+    let alice_kid = message.extra_headers[THREAD_KEY_HEADER]["kid"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert!(alice_kid.starts_with(&format!("{}#thread-", alice_did)));
+    assert_eq!(
+        bob.key_manager().peer_thread_kid("tx-1", &alice_did),
+        Some(alice_kid.clone())
+    );
+
+    // Replies in the thread go to the thread keys
+    let (reply, _) = bob
+        .send_message(&authorize("tx-1"), vec![&alice_did], false)
+        .await
+        .unwrap();
+    assert_eq!(recipient_kid(&reply), alice_kid);
+    let message = alice.receive_message(&reply).await.unwrap();
+    assert_eq!(message.thid.as_deref(), Some("tx-1"));
+
+    let (packed, _) = alice
+        .send_message(&authorize("tx-1"), vec![&bob_did], false)
+        .await
+        .unwrap();
+    assert!(recipient_kid(&packed).starts_with(&format!("{}#thread-", bob_did)));
+    bob.receive_message(&packed).await.unwrap();
+
+    // Other threads get their own keys
+    let (packed, _) = bob
+        .send_message(&authorize("tx-2"), vec![&alice_did], false)
+        .await
+        .unwrap();
+    assert!(!recipient_kid(&packed).contains("#thread-"));
+    alice.receive_message(&packed).await.unwrap();
+
+    // Once the transaction ends, its messages can no longer be decrypted
+    assert_eq!(alice.key_manager().retire_thread_keys("tx-1").unwrap(), 2);
+    assert!(!alice.key_manager().is_own_thread_key(&alice_kid));
+    assert!(alice.receive_message(&reply).await.is_err());
+    assert_eq!(alice.key_manager().thread_ids(), vec!["tx-2".to_string()]);
+}
+
+#[tokio::test]
+async fn test_unauthenticated_thread_keys_are_ignored() {
+    let (alice, alice_did) = agent("ANONCRYPT").await;
+    let (bob, bob_did) = agent("AUTHCRYPT").await;
+
+    let (packed, _) = alice
+        .send_message(&authorize("tx-1"), vec![&bob_did], false)
+        .await
+        .unwrap();
+    let message = bob.receive_message(&packed).await.unwrap();
+    assert!(message.extra_headers.contains_key(THREAD_KEY_HEADER));
+    assert_eq!(bob.key_manager().peer_thread_kid("tx-1", &alice_did), None);
+
+    // Bob keeps encrypting to Alice's DID key
+    let (reply, _) = bob
+        .send_message(&authorize("tx-1"), vec![&alice_did], false)
+        .await
+        .unwrap();
+    assert!(!recipient_kid(&reply).contains("#thread-"));
+    alice.receive_message(&reply).await.unwrap();
+}
@@ -1,3 +1,4 @@
+use crate::asset_id::AssetId;
 use crate::chain_id::ChainId;
 use crate::error::Error;
 use crate::validation::ValidationRegistry;
@@ -58,6 +59,25 @@ impl AccountId {
         &self.address
     }
 
+    /// Check that the account is on the same chain as an asset
+    ///
+    /// # Arguments
+    ///
+    /// * `asset` - The CAIP-19 Asset ID the account is meant to hold
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - An error if the chains differ
+    pub fn check_asset(&self, asset: &AssetId) -> Result<(), Error> {
+        if &self.chain_id != asset.chain_id() {
+            return Err(Error::ChainMismatch(
+                self.chain_id.to_string(),
+                asset.chain_id().to_string(),
+            ));
+        }
+        Ok(())
+    }
+
     /// Validate that the address is valid for the given chain
     fn validate_address(chain_id: &ChainId, address: &str) -> Result<(), Error> {
         // Validate basic address format (1-64 characters, alphanumeric with possible hyphens)
@@ -157,6 +177,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_check_asset() {
+        let usdc =
+            AssetId::from_str("eip155:1/erc20:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48").unwrap();
+        let eth_account =
+            AccountId::from_str("eip155:1:0x4b20993Bc481177ec7E8f571ceCaE8A9e22C02db").unwrap();
+        assert!(eth_account.check_asset(&usdc).is_ok());
+
+        let sol_usdc = AssetId::from_str(
+            "solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp/token:EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+        )
+        .unwrap();
+        assert!(matches!(
+            eth_account.check_asset(&sol_usdc),
+            Err(Error::ChainMismatch(_, _))
+        ));
+
+        let polygon_account =
+            AccountId::from_str("eip155:137:0x4b20993Bc481177ec7E8f571ceCaE8A9e22C02db").unwrap();
+        assert!(polygon_account.check_asset(&usdc).is_err());
+    }
+
     #[test]
     fn test_invalid_account_ids() {
         // Invalid: empty string
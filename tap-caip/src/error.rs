@@ -46,4 +46,12 @@ pub enum Error {
     /// Specific error for Bitcoin address validation
     #[error("Invalid Bitcoin address: {0}")]
     InvalidBitcoinAddress(String),
+
+    /// Specific error for Solana address validation
+    #[error("Invalid Solana address: {0}")]
+    InvalidSolanaAddress(String),
+
+    /// Error when an account is on a different chain than an asset
+    #[error("Account on chain {0} cannot hold asset on chain {1}")]
+    ChainMismatch(String, String),
 }
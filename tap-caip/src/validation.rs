@@ -51,6 +51,9 @@ impl ValidationRegistry {
         // Register Bitcoin validators
         registry.register_account_validator("bip122", bitcoin_address_validator);
 
+        // Register Solana validators
+        registry.register_account_validator("solana", solana_address_validator);
+
         registry
     }
 
@@ -138,6 +141,20 @@ fn bitcoin_address_validator(
     Ok(())
 }
 
+/// Validator for Solana addresses
+fn solana_address_validator(address: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // Solana addresses are base58-encoded 32-byte public keys
+    const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+    if address.len() < 32
+        || address.len() > 44
+        || !address.chars().all(|c| BASE58_ALPHABET.contains(c))
+    {
+        return Err(Error::InvalidSolanaAddress(address.to_string()).into());
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -166,6 +183,13 @@ mod tests {
         assert!(bitcoin_address_validator(too_long_address).is_err());
     }
 
+    #[test]
+    fn test_solana_validator() {
+        assert!(solana_address_validator("7S3P4HxJpyyigGzodYwHtCxZyUQe9JiBMHyRWXArAaKv").is_ok());
+        assert!(solana_address_validator("0x4b20993Bc481177ec7E8f571ceCaE8A9e22C02db").is_err());
+        assert!(solana_address_validator("7S3P4HxJ").is_err());
+    }
+
     #[test]
     fn test_registry() {
         let registry = ValidationRegistry::new_with_defaults();
@@ -177,6 +201,7 @@ mod tests {
 
         // Bitcoin validators should be registered
         assert!(registry.get_account_validator("bip122").is_some());
+        assert!(registry.get_account_validator("solana").is_some());
 
         // Non-registered validators should not be present
         assert!(registry.get_account_validator("polkadot").is_none());
@@ -3,13 +3,14 @@ use crate::output::{print_success, OutputFormat};
 use crate::tap_integration::TapIntegration;
 use clap::Subcommand;
 use serde::Serialize;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tap_agent::agent_key_manager::AgentKeyManagerBuilder;
 use tap_agent::config::AgentConfig;
 use tap_agent::did::{DIDGenerationOptions, KeyType};
 use tap_agent::key_manager::KeyManager;
 use tap_agent::storage::KeyStorage;
-use tap_agent::TapAgent;
+use tap_agent::{AgentKeyManager, SecretHelperOutput, TapAgent};
 use tracing::info;
 
 #[derive(Subcommand, Debug)]
@@ -22,6 +23,34 @@ pub enum AgentCommands {
     },
     /// List all registered agents
     List,
+    /// Provision agents in bulk from a CSV file or by generating new DIDs
+    #[command(long_about = "\
+Provision agents in bulk and register them with the node.
+
+The CSV file needs a header row naming its columns: did, private_key,
+key_type, encoding and label. Rows with a private_key import that key
+(key_type Ed25519, P256 or Secp256k1, encoding hex or base64, hex by
+default); a did given along with it must match the key. Rows without a
+private_key register a DID whose key is already in key storage. Values
+cannot be quoted, empty lines and lines starting with # are skipped.
+
+  did,private_key,key_type,label
+  ,9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60,Ed25519,vasp-1
+  did:key:z6Mk...,,,vasp-2")]
+    Provision {
+        /// CSV file of the DIDs and keys to provision
+        #[arg(long, conflicts_with = "count", required_unless_present = "count")]
+        csv: Option<PathBuf>,
+        /// Number of agents to generate new DIDs for
+        #[arg(long)]
+        count: Option<usize>,
+        /// Key type of generated DIDs (ed25519, p256, secp256k1)
+        #[arg(short = 't', long, default_value = "ed25519", requires = "count")]
+        key_type: String,
+        /// Label prefix for generated DIDs, numbered from 1
+        #[arg(long, requires = "count")]
+        label_prefix: Option<String>,
+    },
 }
 
 #[derive(Debug, Serialize)]
@@ -30,6 +59,30 @@ struct AgentCreatedResponse {
     label: Option<String>,
 }
 
+#[derive(Debug, Serialize)]
+struct ProvisionedAgent {
+    did: String,
+    label: String,
+    registered: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct AgentsProvisionedResponse {
+    agents: Vec<ProvisionedAgent>,
+    total: usize,
+    registered: usize,
+}
+
+/// A row of a provisioning CSV file
+#[derive(Debug, Default, PartialEq)]
+struct ProvisionRow {
+    did: Option<String>,
+    private_key: Option<String>,
+    key_type: Option<String>,
+    encoding: Option<String>,
+    label: Option<String>,
+}
+
 pub async fn handle(
     cmd: &AgentCommands,
     format: OutputFormat,
@@ -40,6 +93,22 @@ pub async fn handle(
             handle_create(label.clone(), format, tap_integration).await
         }
         AgentCommands::List => handle_list(format, tap_integration).await,
+        AgentCommands::Provision {
+            csv,
+            count,
+            key_type,
+            label_prefix,
+        } => {
+            handle_provision(
+                csv.as_deref(),
+                *count,
+                key_type,
+                label_prefix.as_deref(),
+                format,
+                tap_integration,
+            )
+            .await
+        }
     }
 }
 
@@ -86,7 +155,7 @@ async fn handle_create(
         .map_err(|e| Error::command_failed(format!("Failed to register agent: {}", e)))?;
 
     let response = AgentCreatedResponse {
-        did: generated_key.did,
+        did: generated_key.did.clone(),
         label,
     };
     print_success(format, &response);
@@ -98,3 +167,274 @@ async fn handle_list(format: OutputFormat, tap_integration: &TapIntegration) ->
     print_success(format, &agents);
     Ok(())
 }
+
+async fn handle_provision(
+    csv: Option<&std::path::Path>,
+    count: Option<usize>,
+    key_type: &str,
+    label_prefix: Option<&str>,
+    format: OutputFormat,
+    tap_integration: &TapIntegration,
+) -> Result<()> {
+    let mut storage = KeyStorage::load_default()
+        .map_err(|e| Error::configuration(format!("Failed to load key storage: {}", e)))?;
+
+    // Add all keys to the key storage first, so that it is written once
+    let mut dids = Vec::new();
+    if let Some(path) = csv {
+        let contents = std::fs::read_to_string(path)?;
+        for (line, row) in parse_provision_csv(&contents)? {
+            let did = provision_row(&mut storage, row)
+                .await
+                .map_err(|e| Error::invalid_parameter(format!("CSV line {}: {}", line, e)))?;
+            dids.push(did);
+        }
+    } else if let Some(count) = count {
+        let key_type = match key_type.to_lowercase().as_str() {
+            "ed25519" => KeyType::Ed25519,
+            "p256" => KeyType::P256,
+            "secp256k1" => KeyType::Secp256k1,
+            other => {
+                return Err(Error::invalid_parameter(format!(
+                    "Unsupported key type: {}",
+                    other
+                )))
+            }
+        };
+        let generator = AgentKeyManager::new();
+        for n in 1..=count {
+            let key = generator
+                .generate_key_without_save(DIDGenerationOptions { key_type })
+                .map_err(|e| Error::command_failed(format!("Failed to generate key: {}", e)))?;
+            let label = label_prefix
+                .map(|prefix| format!("{}-{}", prefix, n))
+                .unwrap_or_default();
+            storage.add_key(KeyStorage::from_generated_key_with_label(&key, &label));
+            dids.push(key.did.clone());
+        }
+    }
+    storage
+        .save_default()
+        .map_err(|e| Error::command_failed(format!("Failed to save key storage: {}", e)))?;
+    info!("Provisioned {} agents", dids.len());
+
+    // All agents share one key manager loaded from the updated key storage
+    let key_manager = Arc::new(
+        AgentKeyManagerBuilder::new()
+            .load_from_default_storage()
+            .build()
+            .map_err(|e| Error::configuration(format!("Failed to reload key manager: {}", e)))?,
+    );
+    let agents = dids
+        .iter()
+        .map(|did| {
+            let config = AgentConfig::new(did.clone()).with_debug(true);
+            Arc::new(TapAgent::new(config, key_manager.clone()))
+        })
+        .collect();
+    let registered = tap_integration
+        .node()
+        .register_agents(agents)
+        .await
+        .map_err(|e| Error::command_failed(format!("Failed to register agents: {}", e)))?;
+
+    let node = tap_integration.node();
+    let agents: Vec<ProvisionedAgent> = dids
+        .into_iter()
+        .map(|did| ProvisionedAgent {
+            label: storage
+                .keys
+                .get(&did)
+                .map(|key| key.label.clone())
+                .unwrap_or_default(),
+            registered: node.agents().has_agent(&did),
+            did,
+        })
+        .collect();
+    let response = AgentsProvisionedResponse {
+        total: agents.len(),
+        registered: registered.len(),
+        agents,
+    };
+    print_success(format, &response);
+    Ok(())
+}
+
+/// Add the key of a CSV row to the key storage, returning its DID
+async fn provision_row(storage: &mut KeyStorage, row: ProvisionRow) -> Result<String> {
+    let Some(private_key) = row.private_key else {
+        let did = row
+            .did
+            .ok_or_else(|| Error::invalid_parameter("Either did or private_key is required"))?;
+        let key = storage.keys.get_mut(&did).ok_or_else(|| {
+            Error::invalid_parameter(format!("No key for {} in key storage", did))
+        })?;
+        if let Some(label) = row.label {
+            key.label = label;
+        }
+        return Ok(did);
+    };
+
+    let secret = SecretHelperOutput {
+        private_key,
+        key_type: row.key_type.unwrap_or_else(|| "Ed25519".to_string()),
+        encoding: row.encoding.unwrap_or_else(|| "hex".to_string()),
+    };
+    let (bytes, key_type) = secret
+        .decode()
+        .map_err(|e| Error::invalid_parameter(e.to_string()))?;
+    let (agent, did) = TapAgent::from_private_key(&bytes, key_type, false)
+        .await
+        .map_err(|e| Error::invalid_parameter(format!("Invalid private key: {}", e)))?;
+    if let Some(expected) = row.did {
+        if expected != did {
+            return Err(Error::invalid_parameter(format!(
+                "The private key belongs to {}, not {}",
+                did, expected
+            )));
+        }
+    }
+    let key = agent
+        .key_manager()
+        .get_generated_key(&did)
+        .map_err(|e| Error::command_failed(e.to_string()))?;
+    storage.add_key(KeyStorage::from_generated_key_with_label(
+        &key,
+        &row.label.unwrap_or_default(),
+    ));
+    Ok(did)
+}
+
+/// Parse a provisioning CSV file into its rows with their line numbers
+fn parse_provision_csv(contents: &str) -> Result<Vec<(usize, ProvisionRow)>> {
+    let mut lines = contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+    let (_, header) = lines
+        .next()
+        .ok_or_else(|| Error::invalid_parameter("CSV file is empty"))?;
+    let columns: Vec<String> = header
+        .split(',')
+        .map(|column| column.trim().to_lowercase())
+        .collect();
+    for column in &columns {
+        if !["did", "private_key", "key_type", "encoding", "label"].contains(&column.as_str()) {
+            return Err(Error::invalid_parameter(format!(
+                "Unknown CSV column: {}",
+                column
+            )));
+        }
+    }
+    if !columns.iter().any(|c| c == "did" || c == "private_key") {
+        return Err(Error::invalid_parameter(
+            "CSV header needs a did or private_key column",
+        ));
+    }
+
+    lines
+        .map(|(line, text)| {
+            let values: Vec<&str> = text.split(',').map(str::trim).collect();
+            if values.len() != columns.len() {
+                return Err(Error::invalid_parameter(format!(
+                    "CSV line {} has {} values, expected {}",
+                    line,
+                    values.len(),
+                    columns.len()
+                )));
+            }
+            let mut row = ProvisionRow::default();
+            for (column, value) in columns.iter().zip(values) {
+                let value = (!value.is_empty()).then(|| value.to_string());
+                match column.as_str() {
+                    "did" => row.did = value,
+                    "private_key" => row.private_key = value,
+                    "key_type" => row.key_type = value,
+                    "encoding" => row.encoding = value,
+                    _ => row.label = value,
+                }
+            }
+            Ok((line, row))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_provision_csv() {
+        let rows = parse_provision_csv(
+            "# agents of the EU region\n\
+             Label, DID, Private_Key\n\
+             \n\
+             vasp-1,, 9d61b19d\n\
+             ,did:key:z6MkAlice,\n",
+        )
+        .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                (
+                    4,
+                    ProvisionRow {
+                        private_key: Some("9d61b19d".to_string()),
+                        label: Some("vasp-1".to_string()),
+                        ..Default::default()
+                    }
+                ),
+                (
+                    5,
+                    ProvisionRow {
+                        did: Some("did:key:z6MkAlice".to_string()),
+                        ..Default::default()
+                    }
+                ),
+            ]
+        );
+
+        assert!(parse_provision_csv("").is_err());
+        assert!(parse_provision_csv("label\nvasp-1\n").is_err());
+        assert!(parse_provision_csv("did,secret\ndid:key:z6MkAlice,x\n").is_err());
+        assert!(parse_provision_csv("did,label\ndid:key:z6MkAlice\n").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_provision_rows() {
+        let mut storage = KeyStorage::new();
+        let private_key = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+        let row = ProvisionRow {
+            private_key: Some(private_key.to_string()),
+            label: Some("vasp-1".to_string()),
+            ..Default::default()
+        };
+        let did = provision_row(&mut storage, row).await.unwrap();
+        assert!(did.starts_with("did:key:z6Mk"));
+        assert_eq!(storage.keys[&did].label, "vasp-1");
+
+        // Stored DIDs are provisioned without their key, other DIDs are not
+        let row = ProvisionRow {
+            did: Some(did.clone()),
+            label: Some("vasp-one".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(provision_row(&mut storage, row).await.unwrap(), did);
+        assert_eq!(storage.keys[&did].label, "vasp-one");
+        let row = ProvisionRow {
+            did: Some("did:key:z6MkUnknown".to_string()),
+            ..Default::default()
+        };
+        assert!(provision_row(&mut storage, row).await.is_err());
+
+        // The DID must match the key
+        let row = ProvisionRow {
+            did: Some("did:key:z6MkUnknown".to_string()),
+            private_key: Some(private_key.to_string()),
+            ..Default::default()
+        };
+        assert!(provision_row(&mut storage, row).await.is_err());
+    }
+}
@@ -93,7 +93,7 @@ pub async fn handle(
             offset,
         } => {
             let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
-            let storage = tap_integration.storage_for_agent(effective_did).await?;
+            let storage = tap_integration.reader_for_agent(effective_did).await?;
             let customers = storage
                 .list_customers(effective_did, *limit, *offset)
                 .await?;
@@ -0,0 +1,538 @@
+use crate::error::{Error, Result};
+use crate::output::{print_success, OutputFormat};
+use crate::tap_integration::TapIntegration;
+use chrono::{DateTime, Utc};
+use clap::Subcommand;
+use serde::Serialize;
+use std::path::PathBuf;
+use tap_node::backup::{agent_label, BackupDestination, BackupPolicy, BackupScheduler};
+use tap_node::reporting::AuthorizationReport;
+use tap_node::storage::postgres::{AgentMigration, PostgresMigration, DEFAULT_BATCH_SIZE};
+use tap_node::storage::{AgentStorageUsage, RebuildReport, StoredStateEvent, TransactionStateAt};
+
+#[derive(Subcommand, Debug)]
+pub enum DbCommands {
+    /// Report database size, largest tables and quota for each agent
+    Usage {
+        /// Only report this agent's database
+        #[arg(long)]
+        agent_did: Option<String>,
+        /// Number of largest tables to list per agent
+        #[arg(long, default_value = "10")]
+        top: usize,
+    },
+    /// List the state event log of an agent's database
+    #[command(long_about = "\
+List the state event log of an agent's database.
+
+The log records every change to transactions and deliveries when the node runs \
+with event sourcing enabled (tap-http --event-sourcing). Use it to trace how a \
+transaction reached its current state.
+
+Examples:
+  tap-cli db events --transaction-id <ID>
+  tap-cli db events --after 1200 --limit 100")]
+    Events {
+        /// Only list events of this transaction
+        #[arg(long)]
+        transaction_id: Option<String>,
+        /// Only list events after this sequence number
+        #[arg(long, default_value = "0")]
+        after: i64,
+        /// Maximum number of events to list
+        #[arg(long, default_value = "50")]
+        limit: u32,
+        /// Agent DID whose database to read (defaults to --agent-did global flag)
+        #[arg(long)]
+        agent_did: Option<String>,
+    },
+    /// Show a transaction as it stood at a point in time
+    #[command(long_about = "\
+Show a transaction as it stood at a point in time.
+
+Replays the transaction's state event log up to the given time and prints its \
+status, rejection and agents as known then. With --authorizations, prints the \
+state at each authorization of the transaction instead, to show what was known \
+when it was authorized. Requires event sourcing to have been enabled when the \
+transaction was created (tap-http --event-sourcing).
+
+Examples:
+  tap-cli db state-at --transaction-id <ID> --at 2026-01-01T12:00:00Z
+  tap-cli db state-at --transaction-id <ID> --authorizations")]
+    StateAt {
+        /// Reference ID of the transaction
+        #[arg(long)]
+        transaction_id: String,
+        /// Point in time as an RFC 3339 timestamp
+        #[arg(long, required_unless_present = "authorizations")]
+        at: Option<String>,
+        /// Show the state at each authorization instead
+        #[arg(long, conflicts_with = "at")]
+        authorizations: bool,
+        /// Agent DID whose database to read (defaults to --agent-did global flag)
+        #[arg(long)]
+        agent_did: Option<String>,
+    },
+    /// Rebuild the transaction and delivery tables from the state event log
+    #[command(long_about = "\
+Rebuild the transaction and delivery tables from the state event log.
+
+Clears the transactions, transaction_agents and deliveries tables of an agent's \
+database and replays its state event log to reconstruct them. The rebuild is \
+rolled back if the log does not cover all existing transactions and deliveries, \
+e.g. because event sourcing was enabled after they were created. Stop the node \
+using the database before rebuilding.
+
+Examples:
+  tap-cli db rebuild
+  tap-cli db rebuild --agent-did did:key:z6Mk...")]
+    Rebuild {
+        /// Agent DID whose database to rebuild (defaults to --agent-did global flag)
+        #[arg(long)]
+        agent_did: Option<String>,
+    },
+    /// Back up an agent's database to a directory
+    #[command(long_about = "\
+Back up an agent's database to a directory.
+
+Takes a consistent snapshot of the database with the SQLite online backup API, \
+so the node using it can keep running. Backups are stored as \
+{dir}/{agent}/transactions-{timestamp}.db, the layout the node's scheduled \
+backups use. With --keep-last, older backups of the agent are deleted.
+
+Examples:
+  tap-cli db backup --dir /mnt/backups
+  tap-cli db backup --dir /mnt/backups --keep-last 7")]
+    Backup {
+        /// Directory to store the backup in
+        #[arg(long)]
+        dir: PathBuf,
+        /// Number of most recent backups of the agent to keep
+        #[arg(long)]
+        keep_last: Option<usize>,
+        /// Agent DID whose database to back up (defaults to --agent-did global flag)
+        #[arg(long)]
+        agent_did: Option<String>,
+    },
+    /// Restore an agent's database from a backup
+    #[command(long_about = "\
+Restore an agent's database from a backup.
+
+Replaces the contents of the database with a backup file, or with a backup \
+taken by `tap-cli db backup` or the node's scheduled backups. With --dir, the \
+most recent backup of the agent is restored unless --name selects another. \
+Stop the node using the database before restoring.
+
+Examples:
+  tap-cli db restore --dir /mnt/backups
+  tap-cli db restore --dir /mnt/backups --name transactions-20260301T020000.000Z.db
+  tap-cli db restore --file ./transactions.db")]
+    Restore {
+        /// Backup file to restore
+        #[arg(long, required_unless_present = "dir", conflicts_with = "dir")]
+        file: Option<PathBuf>,
+        /// Backup directory to restore the agent's most recent backup from
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// Name of the backup in the directory to restore
+        #[arg(long, requires = "dir")]
+        name: Option<String>,
+        /// Agent DID whose database to restore (defaults to --agent-did global flag)
+        #[arg(long)]
+        agent_did: Option<String>,
+    },
+    /// Copy agent databases into Postgres and verify the copy
+    #[command(long_about = "\
+Copy agent databases into Postgres and verify the copy.
+
+Each agent's tables are created in a Postgres schema of their own, named tap_ \
+and a hash of the agent DID and listed in public.tap_agent_schemas. Rows are \
+copied in batches; an interrupted migration resumes after the last complete \
+batch, and running it again copies the rows added since. Every table is then \
+verified by comparing row counts and checksums of the rows, and copied again \
+if its rows changed after they were copied. The command fails if a table \
+does not verify.
+
+The SQLite databases are only read, so the node can keep running. For a \
+consistent copy, pause the node's intake (send tap-http SIGUSR1, and SIGUSR2 \
+to resume) and run the migration once more.
+
+Examples:
+  tap-cli db migrate-to-postgres --url postgres://tap@localhost/tap
+  tap-cli db migrate-to-postgres --url postgres://tap@localhost/tap --agent-did did:key:z6Mk...
+  tap-cli db migrate-to-postgres --url postgres://tap@localhost/tap --verify-only")]
+    MigrateToPostgres {
+        /// Postgres connection URL
+        #[arg(long, env = "TAP_POSTGRES_URL")]
+        url: String,
+        /// Only migrate this agent's database (defaults to every agent)
+        #[arg(long)]
+        agent_did: Option<String>,
+        /// Rows copied per batch
+        #[arg(long, default_value_t = DEFAULT_BATCH_SIZE)]
+        batch_size: usize,
+        /// Only verify an earlier copy, without copying rows
+        #[arg(long)]
+        verify_only: bool,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct DbEventsResponse {
+    events: Vec<StoredStateEvent>,
+    total: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct DbStateAtResponse {
+    transaction_id: String,
+    at: String,
+    state: Option<TransactionStateAt>,
+}
+
+#[derive(Debug, Serialize)]
+struct DbRebuildResponse {
+    agent_did: String,
+    #[serde(flatten)]
+    report: RebuildReport,
+}
+
+#[derive(Debug, Serialize)]
+struct DbBackupResponse {
+    agent_did: String,
+    name: String,
+    location: String,
+    taken_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct DbMigrateResponse {
+    agents: Vec<AgentMigration>,
+    verified: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct DbUsageResponse {
+    agents: Vec<AgentStorageUsage>,
+    total_bytes: u64,
+}
+
+pub async fn handle(
+    cmd: &DbCommands,
+    format: OutputFormat,
+    agent_did: &str,
+    tap_integration: &TapIntegration,
+) -> Result<()> {
+    let default_agent_did = agent_did;
+    match cmd {
+        DbCommands::Usage { agent_did, top } => {
+            let mut agents = match agent_did {
+                Some(did) => {
+                    let storage = tap_integration.storage_for_agent(did).await?;
+                    vec![AgentStorageUsage {
+                        agent_did: did.clone(),
+                        usage: storage.usage().await?,
+                    }]
+                }
+                None => tap_integration.node().storage_usage().await?,
+            };
+            for agent in &mut agents {
+                agent.usage.tables.truncate(*top);
+            }
+
+            let response = DbUsageResponse {
+                total_bytes: agents.iter().map(|a| a.usage.size_bytes).sum(),
+                agents,
+            };
+            print_success(format, &response);
+            Ok(())
+        }
+        DbCommands::Events {
+            transaction_id,
+            after,
+            limit,
+            agent_did,
+        } => {
+            let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
+            let storage = tap_integration.storage_for_agent(effective_did).await?;
+            let events = storage
+                .list_state_events(transaction_id.as_deref(), *after, *limit)
+                .await?;
+
+            let response = DbEventsResponse {
+                total: events.len(),
+                events,
+            };
+            print_success(format, &response);
+            Ok(())
+        }
+        DbCommands::StateAt {
+            transaction_id,
+            at,
+            authorizations,
+            agent_did,
+        } => {
+            let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
+            let storage = tap_integration.storage_for_agent(effective_did).await?;
+
+            if *authorizations {
+                let report = AuthorizationReport::generate(&storage, transaction_id).await?;
+                print_success(format, &report);
+                return Ok(());
+            }
+
+            let at = at.as_deref().unwrap_or_default();
+            let timestamp = DateTime::parse_from_rfc3339(at)
+                .map_err(|e| Error::invalid_parameter(format!("Invalid --at timestamp: {}", e)))?
+                .with_timezone(&Utc);
+            let state = storage
+                .get_transaction_state_at(transaction_id, timestamp)
+                .await?;
+
+            let response = DbStateAtResponse {
+                transaction_id: transaction_id.clone(),
+                at: timestamp.to_rfc3339(),
+                state,
+            };
+            print_success(format, &response);
+            Ok(())
+        }
+        DbCommands::Rebuild { agent_did } => {
+            let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
+            let storage = tap_integration.storage_for_agent(effective_did).await?;
+            let report = storage.rebuild_projections().await?;
+
+            let response = DbRebuildResponse {
+                agent_did: effective_did.to_string(),
+                report,
+            };
+            print_success(format, &response);
+            Ok(())
+        }
+        DbCommands::Backup {
+            dir,
+            keep_last,
+            agent_did,
+        } => {
+            let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
+            let storage = tap_integration.storage_for_agent(effective_did).await?;
+            let backup = directory_scheduler(dir, *keep_last)
+                .backup(&agent_label(effective_did), &storage)
+                .await?;
+
+            let response = DbBackupResponse {
+                agent_did: effective_did.to_string(),
+                name: backup.name,
+                location: backup.location,
+                taken_at: backup.taken_at.to_rfc3339(),
+            };
+            print_success(format, &response);
+            Ok(())
+        }
+        DbCommands::Restore {
+            file,
+            dir,
+            name,
+            agent_did,
+        } => {
+            let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
+            let storage = tap_integration.storage_for_agent(effective_did).await?;
+
+            let response = match (file, dir) {
+                (Some(file), _) => {
+                    storage.restore_from(file).await?;
+                    DbBackupResponse {
+                        agent_did: effective_did.to_string(),
+                        name: file
+                            .file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                            .unwrap_or_default(),
+                        location: file.display().to_string(),
+                        taken_at: String::new(),
+                    }
+                }
+                (None, Some(dir)) => {
+                    let backup = directory_scheduler(dir, None)
+                        .restore(&agent_label(effective_did), name.as_deref(), &storage)
+                        .await?;
+                    DbBackupResponse {
+                        agent_did: effective_did.to_string(),
+                        name: backup.name,
+                        location: backup.location,
+                        taken_at: backup.taken_at.to_rfc3339(),
+                    }
+                }
+                (None, None) => {
+                    return Err(Error::invalid_parameter(
+                        "Either --file or --dir is required",
+                    ))
+                }
+            };
+            print_success(format, &response);
+            Ok(())
+        }
+        DbCommands::MigrateToPostgres {
+            url,
+            agent_did,
+            batch_size,
+            verify_only,
+        } => {
+            let agent_dids = match agent_did {
+                Some(did) => vec![did.clone()],
+                None => {
+                    let mut dids = tap_integration.node().list_agents();
+                    dids.sort();
+                    dids
+                }
+            };
+            let migration = PostgresMigration::connect(url)
+                .await?
+                .with_batch_size(*batch_size);
+
+            let mut agents = Vec::new();
+            for did in &agent_dids {
+                let storage = tap_integration.storage_for_agent(did).await?;
+                agents.push(if *verify_only {
+                    migration.verify(did, &storage).await?
+                } else {
+                    migration.migrate(did, &storage).await?
+                });
+            }
+
+            let response = DbMigrateResponse {
+                verified: agents.iter().all(|agent| agent.verified),
+                agents,
+            };
+            print_success(format, &response);
+            if !response.verified {
+                return Err(Error::command_failed(
+                    "The copy in Postgres does not match the agent databases",
+                ));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Backups kept in a local directory, in the layout of the node's scheduled
+/// backups
+fn directory_scheduler(dir: &std::path::Path, keep_last: Option<usize>) -> BackupScheduler {
+    BackupScheduler::new(
+        BackupPolicy::new(BackupDestination::Directory(dir.to_path_buf()))
+            .with_keep_last(keep_last),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_db_usage_reports_agent_tables() {
+        let dir = tempdir().unwrap();
+        let (agent, did) = tap_agent::TapAgent::from_ephemeral_key().await.unwrap();
+        let integration = TapIntegration::new(
+            Some(&did),
+            Some(dir.path().to_str().unwrap()),
+            Some(std::sync::Arc::new(agent)),
+        )
+        .await
+        .unwrap();
+
+        let storage = integration.storage_for_agent(&did).await.unwrap();
+        let usage = storage.usage().await.unwrap();
+        assert!(usage.size_bytes > 0);
+        assert!(usage.quota.is_none());
+        assert!(usage.tables.iter().any(|t| t.name == "transactions"));
+
+        let cmd = DbCommands::Usage {
+            agent_did: Some(did.clone()),
+            top: 3,
+        };
+        assert!(handle(&cmd, OutputFormat::Json, &did, &integration)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_db_state_at_validates_timestamp() {
+        let dir = tempdir().unwrap();
+        let (agent, did) = tap_agent::TapAgent::from_ephemeral_key().await.unwrap();
+        let integration = TapIntegration::new(
+            Some(&did),
+            Some(dir.path().to_str().unwrap()),
+            Some(std::sync::Arc::new(agent)),
+        )
+        .await
+        .unwrap();
+
+        let state_at = |at: &str| DbCommands::StateAt {
+            transaction_id: "tx-unknown".to_string(),
+            at: Some(at.to_string()),
+            authorizations: false,
+            agent_did: None,
+        };
+        assert!(handle(
+            &state_at("yesterday"),
+            OutputFormat::Json,
+            &did,
+            &integration
+        )
+        .await
+        .is_err());
+        assert!(handle(
+            &state_at("2026-01-01T12:00:00Z"),
+            OutputFormat::Json,
+            &did,
+            &integration
+        )
+        .await
+        .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_db_backup_and_restore() {
+        let dir = tempdir().unwrap();
+        let backups = dir.path().join("backups");
+        let (agent, did) = tap_agent::TapAgent::from_ephemeral_key().await.unwrap();
+        let integration = TapIntegration::new(
+            Some(&did),
+            Some(dir.path().join("tap").to_str().unwrap()),
+            Some(std::sync::Arc::new(agent)),
+        )
+        .await
+        .unwrap();
+
+        let backup = DbCommands::Backup {
+            dir: backups.clone(),
+            keep_last: Some(1),
+            agent_did: None,
+        };
+        handle(&backup, OutputFormat::Json, &did, &integration)
+            .await
+            .unwrap();
+        let agent_dir = backups.join(agent_label(&did));
+        assert_eq!(std::fs::read_dir(&agent_dir).unwrap().count(), 1);
+
+        let restore = DbCommands::Restore {
+            file: None,
+            dir: Some(backups.clone()),
+            name: None,
+            agent_did: None,
+        };
+        handle(&restore, OutputFormat::Json, &did, &integration)
+            .await
+            .unwrap();
+
+        let missing = DbCommands::Restore {
+            file: None,
+            dir: Some(backups),
+            name: Some("transactions-20000101T000000.000Z.db".to_string()),
+            agent_did: None,
+        };
+        assert!(handle(&missing, OutputFormat::Json, &did, &integration)
+            .await
+            .is_err());
+    }
+}
@@ -151,7 +151,7 @@ pub async fn handle(
             limit,
         } => {
             let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
-            let storage = tap_integration.storage_for_agent(effective_did).await?;
+            let storage = tap_integration.reader_for_agent(effective_did).await?;
 
             let status_filter = status
                 .as_deref()
@@ -27,6 +27,77 @@ pub enum DeliveryCommands {
         #[arg(long, default_value = "0")]
         offset: u32,
     },
+    /// Manage the queue of pending and retrying external deliveries
+    Queue {
+        #[command(subcommand)]
+        cmd: QueueCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum QueueCommands {
+    /// List queued deliveries in the order of their next attempt
+    List {
+        /// Agent DID whose deliveries to list
+        #[arg(long)]
+        agent_did: Option<String>,
+        /// Maximum results
+        #[arg(long, default_value = "50")]
+        limit: u32,
+        /// Offset for pagination
+        #[arg(long, default_value = "0")]
+        offset: u32,
+    },
+    /// Show a queued delivery, including its last error
+    Show {
+        /// Delivery ID
+        delivery_id: i64,
+        /// Agent DID that sends the delivery
+        #[arg(long)]
+        agent_did: Option<String>,
+    },
+    /// Attempt a queued delivery immediately
+    Retry {
+        /// Delivery ID
+        delivery_id: i64,
+        /// Agent DID that sends the delivery
+        #[arg(long)]
+        agent_did: Option<String>,
+    },
+    /// Deliver a queued message to a different endpoint
+    Reassign {
+        /// Delivery ID
+        delivery_id: i64,
+        /// URL of the new endpoint
+        #[arg(long)]
+        endpoint: String,
+        /// Agent DID that sends the delivery
+        #[arg(long)]
+        agent_did: Option<String>,
+    },
+    /// Stop attempting a queued delivery
+    Cancel {
+        /// Delivery ID
+        delivery_id: i64,
+        /// Agent DID that sends the delivery
+        #[arg(long)]
+        agent_did: Option<String>,
+    },
+    /// Have the retrier attempt failed deliveries on its next run
+    Requeue {
+        /// Delivery ID
+        #[arg(required_unless_present = "all")]
+        delivery_id: Option<i64>,
+        /// Requeue all failed deliveries of the agent
+        #[arg(long, conflicts_with = "delivery_id")]
+        all: bool,
+        /// With --all, only requeue deliveries to this recipient
+        #[arg(long, requires = "all")]
+        recipient: Option<String>,
+        /// Agent DID that sends the delivery
+        #[arg(long)]
+        agent_did: Option<String>,
+    },
 }
 
 #[derive(Debug, Serialize)]
@@ -34,6 +105,7 @@ struct DeliveryInfo {
     id: i64,
     message_id: String,
     recipient_did: String,
+    delivery_url: Option<String>,
     status: String,
     retry_count: i32,
     delivery_type: String,
@@ -41,6 +113,7 @@ struct DeliveryInfo {
     updated_at: String,
     delivered_at: Option<String>,
     error_message: Option<String>,
+    next_attempt_at: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -54,6 +127,7 @@ fn to_delivery_info(d: &tap_node::storage::models::Delivery) -> DeliveryInfo {
         id: d.id,
         message_id: d.message_id.clone(),
         recipient_did: d.recipient_did.clone(),
+        delivery_url: d.delivery_url.clone(),
         status: format!("{:?}", d.status),
         retry_count: d.retry_count,
         delivery_type: format!("{:?}", d.delivery_type),
@@ -61,6 +135,7 @@ fn to_delivery_info(d: &tap_node::storage::models::Delivery) -> DeliveryInfo {
         updated_at: d.updated_at.clone(),
         delivered_at: d.delivered_at.clone(),
         error_message: d.error_message.clone(),
+        next_attempt_at: d.next_attempt_at.clone(),
     }
 }
 
@@ -108,5 +183,213 @@ pub async fn handle(
             print_success(format, &response);
             Ok(())
         }
+        DeliveryCommands::Queue { cmd } => {
+            handle_queue(cmd, format, default_agent_did, tap_integration).await
+        }
+    }
+}
+
+async fn handle_queue(
+    cmd: &QueueCommands,
+    format: OutputFormat,
+    default_agent_did: &str,
+    tap_integration: &TapIntegration,
+) -> Result<()> {
+    let node = tap_integration.node();
+    match cmd {
+        QueueCommands::List {
+            agent_did,
+            limit,
+            offset,
+        } => {
+            let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
+            let deliveries = node
+                .list_delivery_queue(effective_did, *limit, *offset)
+                .await?;
+
+            let delivery_infos: Vec<DeliveryInfo> =
+                deliveries.iter().map(to_delivery_info).collect();
+            let response = DeliveryListResponse {
+                total: delivery_infos.len(),
+                deliveries: delivery_infos,
+            };
+            print_success(format, &response);
+            Ok(())
+        }
+        QueueCommands::Show {
+            delivery_id,
+            agent_did,
+        } => {
+            let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
+            let queued = node
+                .delivery_queue()
+                .get(effective_did, *delivery_id)
+                .await?;
+            print_success(format, &to_delivery_info(&queued.delivery));
+            Ok(())
+        }
+        QueueCommands::Retry {
+            delivery_id,
+            agent_did,
+        } => {
+            let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
+            let delivery = node
+                .retry_delivery(effective_did, *delivery_id)
+                .await
+                .map_err(|e| {
+                    Error::command_failed(format!(
+                        "Failed to retry delivery {}: {}",
+                        delivery_id, e
+                    ))
+                })?;
+            print_success(format, &to_delivery_info(&delivery));
+            Ok(())
+        }
+        QueueCommands::Reassign {
+            delivery_id,
+            endpoint,
+            agent_did,
+        } => {
+            let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
+            let delivery = node
+                .reassign_delivery(effective_did, *delivery_id, endpoint)
+                .await?;
+            print_success(format, &to_delivery_info(&delivery));
+            Ok(())
+        }
+        QueueCommands::Cancel {
+            delivery_id,
+            agent_did,
+        } => {
+            let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
+            let delivery = node.cancel_delivery(effective_did, *delivery_id).await?;
+            print_success(format, &to_delivery_info(&delivery));
+            Ok(())
+        }
+        QueueCommands::Requeue {
+            delivery_id,
+            recipient,
+            agent_did,
+            ..
+        } => {
+            let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
+            let queue = node.delivery_queue();
+            match delivery_id {
+                Some(delivery_id) => {
+                    let queued = queue.requeue(effective_did, *delivery_id).await?;
+                    print_success(format, &to_delivery_info(&queued.delivery));
+                }
+                None => {
+                    let requeued = queue
+                        .requeue_failed(Some(effective_did), recipient.as_deref())
+                        .await?;
+                    let delivery_infos: Vec<DeliveryInfo> = requeued
+                        .iter()
+                        .map(|queued| to_delivery_info(&queued.delivery))
+                        .collect();
+                    let response = DeliveryListResponse {
+                        total: delivery_infos.len(),
+                        deliveries: delivery_infos,
+                    };
+                    print_success(format, &response);
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tap_msg::didcomm::PlainMessage;
+    use tap_node::storage::models::{DeliveryStatus, DeliveryType, MessageDirection};
+    use tempfile::tempdir;
+
+    async fn setup_test() -> (TapIntegration, String) {
+        let dir = tempdir().unwrap();
+        let tap_root = dir.path().to_str().unwrap();
+
+        let (agent, did) = tap_agent::TapAgent::from_ephemeral_key().await.unwrap();
+        let agent_arc = std::sync::Arc::new(agent);
+
+        let integration = TapIntegration::new(Some(&did), Some(tap_root), Some(agent_arc))
+            .await
+            .unwrap();
+
+        std::mem::forget(dir);
+        (integration, did)
+    }
+
+    #[tokio::test]
+    async fn test_delivery_queue_reassign_and_cancel() {
+        let (integration, did) = setup_test().await;
+        let storage = integration.storage_for_agent(&did).await.unwrap();
+        let message = PlainMessage::new(
+            "msg-1".to_string(),
+            "https://didcomm.org/trust_ping/2.0/ping".to_string(),
+            serde_json::json!({}),
+            did.clone(),
+        );
+        storage
+            .log_message(&message, MessageDirection::Outgoing)
+            .await
+            .unwrap();
+        let delivery_id = storage
+            .create_delivery(
+                "msg-1",
+                "{}",
+                "did:example:beneficiary",
+                Some("https://vasp.example/didcomm"),
+                DeliveryType::Https,
+            )
+            .await
+            .unwrap();
+
+        let list = DeliveryCommands::Queue {
+            cmd: QueueCommands::List {
+                agent_did: None,
+                limit: 50,
+                offset: 0,
+            },
+        };
+        handle(&list, OutputFormat::Json, &did, &integration)
+            .await
+            .unwrap();
+
+        let reassign = DeliveryCommands::Queue {
+            cmd: QueueCommands::Reassign {
+                delivery_id,
+                endpoint: "https://backup.example/didcomm".to_string(),
+                agent_did: None,
+            },
+        };
+        handle(&reassign, OutputFormat::Json, &did, &integration)
+            .await
+            .unwrap();
+
+        let cancel = DeliveryCommands::Queue {
+            cmd: QueueCommands::Cancel {
+                delivery_id,
+                agent_did: None,
+            },
+        };
+        handle(&cancel, OutputFormat::Json, &did, &integration)
+            .await
+            .unwrap();
+
+        let delivery = storage
+            .get_delivery_by_id(delivery_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            delivery.delivery_url.as_deref(),
+            Some("https://backup.example/didcomm")
+        );
+        assert_eq!(delivery.status, DeliveryStatus::Cancelled);
+        assert!(handle(&cancel, OutputFormat::Json, &did, &integration)
+            .await
+            .is_err());
     }
 }
@@ -168,7 +168,7 @@ async fn handle_generate(
     }
 
     let response = GeneratedDidResponse {
-        did: generated_key.did,
+        did: generated_key.did.clone(),
         key_type: format!("{:?}", generated_key.key_type),
         public_key: generated_key
             .public_key
@@ -0,0 +1,348 @@
+use crate::error::{Error, Result};
+use crate::output::{print_success, OutputFormat};
+use crate::tap_integration::TapIntegration;
+use clap::Subcommand;
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+use tap_node::storage::{Draft, DraftKind, DraftStatus};
+
+#[derive(Subcommand, Debug)]
+pub enum DraftCommands {
+    /// Create a draft of a Transfer or Payment
+    #[command(long_about = "\
+Create a draft of a Transfer or Payment.
+
+The body is the message body as JSON and may be incomplete; the draft records \
+why it cannot be sent yet. Drafts expire after 7 days unless --ttl-hours is \
+given.
+
+Examples:
+  tap-cli draft create --kind transfer --body '{\"asset\":\"eip155:1/slip44:60\"}'
+  tap-cli draft create --kind payment --ttl-hours 2")]
+    Create {
+        /// Kind of message: transfer or payment
+        #[arg(long)]
+        kind: String,
+        /// Message body as JSON, possibly incomplete
+        #[arg(long, default_value = "{}")]
+        body: String,
+        /// Hours until the draft can no longer be sent
+        #[arg(long)]
+        ttl_hours: Option<u64>,
+    },
+    /// Change the body of a draft
+    #[command(long_about = "\
+Change the body of a draft that was not sent.
+
+--body is a JSON merge patch (RFC 7386): its fields replace those of the \
+draft and null removes a field. --set changes a single field by its dotted \
+path; the value is parsed as JSON, or taken as a string if it is not JSON. \
+The draft is validated again after the change.
+
+Examples:
+  tap-cli draft edit --draft-id <id> --set amount=\\\"100.0\\\" --set originator.@id=did:key:z6Mk...
+  tap-cli draft edit --draft-id <id> --body '{\"memo\":null}'")]
+    Edit {
+        /// ID of the draft
+        #[arg(long)]
+        draft_id: String,
+        /// JSON merge patch of the body
+        #[arg(long)]
+        body: Option<String>,
+        /// Field to set, as path=value (repeatable)
+        #[arg(long = "set")]
+        set: Vec<String>,
+    },
+    /// List drafts
+    #[command(long_about = "\
+List the drafts of an agent, most recently changed first.
+
+Draft statuses:
+  invalid  The body cannot be sent yet (see validation_errors)
+  valid    The body can be sent
+  sent     The draft was sent as message_id
+
+Expired drafts that were not sent are left out unless --include-expired is given.
+
+Examples:
+  tap-cli draft list
+  tap-cli draft list --status valid")]
+    List {
+        /// Filter by status: invalid, valid, sent
+        #[arg(long)]
+        status: Option<String>,
+        /// Include expired drafts
+        #[arg(long)]
+        include_expired: bool,
+        /// Maximum results
+        #[arg(long, default_value = "50")]
+        limit: u32,
+        /// Offset for pagination
+        #[arg(long, default_value = "0")]
+        offset: u32,
+    },
+    /// Show a draft
+    Show {
+        /// ID of the draft
+        #[arg(long)]
+        draft_id: String,
+    },
+    /// Send a valid draft
+    #[command(long_about = "\
+Send a valid draft as a message from the agent.
+
+A draft is sent only once. If the message cannot be delivered, the draft \
+stays valid and can be sent again.
+
+Examples:
+  tap-cli draft send --draft-id <id>")]
+    Send {
+        /// ID of the draft
+        #[arg(long)]
+        draft_id: String,
+    },
+    /// Delete a draft that was not sent
+    Delete {
+        /// ID of the draft
+        #[arg(long)]
+        draft_id: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct DraftListResponse {
+    drafts: Vec<Draft>,
+    total: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct DraftDeleteResponse {
+    id: String,
+    deleted: bool,
+}
+
+pub async fn handle(
+    cmd: &DraftCommands,
+    format: OutputFormat,
+    agent_did: &str,
+    tap_integration: &TapIntegration,
+) -> Result<()> {
+    let node = tap_integration.node();
+    match cmd {
+        DraftCommands::Create {
+            kind,
+            body,
+            ttl_hours,
+        } => {
+            let kind = kind
+                .parse::<DraftKind>()
+                .map_err(|e| Error::invalid_parameter(format!("Invalid kind: {}", e)))?;
+            let body: Value = serde_json::from_str(body)
+                .map_err(|e| Error::invalid_parameter(format!("Invalid body JSON: {}", e)))?;
+            let ttl = ttl_hours.map(|hours| Duration::from_secs(hours * 3600));
+
+            let draft = node.create_draft(agent_did, kind, body, ttl).await?;
+            print_success(format, &draft);
+            Ok(())
+        }
+        DraftCommands::Edit {
+            draft_id,
+            body,
+            set,
+        } => {
+            let mut patch = match body {
+                Some(body) => serde_json::from_str(body)
+                    .map_err(|e| Error::invalid_parameter(format!("Invalid body JSON: {}", e)))?,
+                None => Value::Object(serde_json::Map::new()),
+            };
+            for assignment in set {
+                set_field(&mut patch, assignment)?;
+            }
+
+            let draft = node.update_draft(agent_did, draft_id, &patch).await?;
+            print_success(format, &draft);
+            Ok(())
+        }
+        DraftCommands::List {
+            status,
+            include_expired,
+            limit,
+            offset,
+        } => {
+            let status = status
+                .as_deref()
+                .map(DraftStatus::try_from)
+                .transpose()
+                .map_err(|e| Error::invalid_parameter(format!("Invalid status: {}", e)))?;
+            let storage = tap_integration.reader_for_agent(agent_did).await?;
+            let drafts = storage
+                .list_drafts(Some(agent_did), status, *include_expired, *limit, *offset)
+                .await?;
+
+            print_success(
+                format,
+                &DraftListResponse {
+                    total: drafts.len(),
+                    drafts,
+                },
+            );
+            Ok(())
+        }
+        DraftCommands::Show { draft_id } => {
+            let draft = node.get_draft(agent_did, draft_id).await?;
+            print_success(format, &draft);
+            Ok(())
+        }
+        DraftCommands::Send { draft_id } => {
+            let draft = node.send_draft(agent_did, draft_id).await.map_err(|e| {
+                Error::command_failed(format!("Failed to send draft {}: {}", draft_id, e))
+            })?;
+            print_success(format, &draft);
+            Ok(())
+        }
+        DraftCommands::Delete { draft_id } => {
+            let deleted = node.delete_draft(agent_did, draft_id).await?;
+            if !deleted {
+                return Err(Error::command_failed(format!(
+                    "Draft {} not found or already sent",
+                    draft_id
+                )));
+            }
+            print_success(
+                format,
+                &DraftDeleteResponse {
+                    id: draft_id.clone(),
+                    deleted,
+                },
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Add a `path=value` assignment to a merge patch
+fn set_field(patch: &mut Value, assignment: &str) -> Result<()> {
+    let (path, value) = assignment.split_once('=').ok_or_else(|| {
+        Error::invalid_parameter(format!("Expected path=value, got '{}'", assignment))
+    })?;
+    if path.is_empty() {
+        return Err(Error::invalid_parameter("Empty field path"));
+    }
+    let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+
+    let mut target = patch;
+    for key in path.split('.') {
+        if !target.is_object() {
+            *target = Value::Object(serde_json::Map::new());
+        }
+        target = target
+            .as_object_mut()
+            .expect("target was made an object")
+            .entry(key)
+            .or_insert(Value::Null);
+    }
+    *target = value;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    async fn setup_test() -> (TapIntegration, String) {
+        let dir = tempdir().unwrap();
+        let tap_root = dir.path().to_str().unwrap();
+
+        let (agent, did) = tap_agent::TapAgent::from_ephemeral_key().await.unwrap();
+        let agent_arc = std::sync::Arc::new(agent);
+
+        let integration = TapIntegration::new(Some(&did), Some(tap_root), Some(agent_arc))
+            .await
+            .unwrap();
+
+        std::mem::forget(dir);
+        (integration, did)
+    }
+
+    #[test]
+    fn test_set_field() {
+        let mut patch = json!({"memo": "rent"});
+        set_field(&mut patch, "amount=\"1.5\"").unwrap();
+        set_field(&mut patch, "originator.@id=did:example:alice").unwrap();
+        set_field(&mut patch, "memo=null").unwrap();
+        assert_eq!(
+            patch,
+            json!({"amount": "1.5", "originator": {"@id": "did:example:alice"}, "memo": null})
+        );
+        assert!(set_field(&mut patch, "amount").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_draft_create_edit_list_delete() {
+        let (integration, did) = setup_test().await;
+
+        let cmd = DraftCommands::Create {
+            kind: "transfer".to_string(),
+            body: r#"{"asset":"eip155:1/slip44:60"}"#.to_string(),
+            ttl_hours: None,
+        };
+        handle(&cmd, OutputFormat::Json, &did, &integration)
+            .await
+            .unwrap();
+
+        let storage = integration.storage_for_agent(&did).await.unwrap();
+        let drafts = storage
+            .list_drafts(Some(&did), None, false, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(drafts.len(), 1);
+        let draft_id = drafts[0].id.clone();
+
+        let cmd = DraftCommands::Edit {
+            draft_id: draft_id.clone(),
+            body: None,
+            set: vec![
+                "amount=\"1.5\"".to_string(),
+                format!("originator.@id={}", did),
+                "beneficiary.@id=did:example:bob".to_string(),
+                format!(
+                    r#"agents=[{{"@id":"{}","role":"SourceAgent","for":"{}"}}]"#,
+                    did, did
+                ),
+            ],
+        };
+        handle(&cmd, OutputFormat::Json, &did, &integration)
+            .await
+            .unwrap();
+        let draft = storage.get_draft(&draft_id).await.unwrap().unwrap();
+        assert_eq!(
+            draft.status,
+            DraftStatus::Valid,
+            "{:?}",
+            draft.validation_errors
+        );
+
+        let cmd = DraftCommands::List {
+            status: Some("bogus".to_string()),
+            include_expired: false,
+            limit: 50,
+            offset: 0,
+        };
+        assert!(handle(&cmd, OutputFormat::Json, &did, &integration)
+            .await
+            .is_err());
+
+        let cmd = DraftCommands::Delete {
+            draft_id: draft_id.clone(),
+        };
+        handle(&cmd, OutputFormat::Json, &did, &integration)
+            .await
+            .unwrap();
+        assert!(handle(&cmd, OutputFormat::Json, &did, &integration)
+            .await
+            .is_err());
+    }
+}
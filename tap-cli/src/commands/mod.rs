@@ -2,9 +2,15 @@ pub mod agent;
 pub mod agent_management;
 pub mod communication;
 pub mod customer;
+pub mod db;
 pub mod decision;
 pub mod delivery;
 pub mod did;
+pub mod draft;
+pub mod node;
 pub mod received;
+pub mod report;
+pub mod review;
+pub mod scheduler;
 pub mod transaction;
 pub mod transaction_actions;
@@ -0,0 +1,35 @@
+use crate::error::Result;
+use crate::output::{print_success, OutputFormat};
+use crate::tap_integration::TapIntegration;
+use clap::Subcommand;
+
+#[derive(Subcommand, Debug)]
+pub enum NodeCommands {
+    /// Report the optional subsystems active in the node
+    #[command(long_about = "\
+Report the optional subsystems active in the node.
+
+Lists the Cargo features the node was compiled with, its storage backend and \
+whether a travel rule processor, a policy engine, the WebSocket transport and \
+tracing metrics are active. The node is the one the CLI runs on the TAP root \
+(see --tap-root); tap-http publishes the same report in its attestation.
+
+Examples:
+  tap-cli node capabilities
+  tap-cli node capabilities --query storage")]
+    Capabilities,
+}
+
+pub async fn handle(
+    cmd: &NodeCommands,
+    format: OutputFormat,
+    _agent_did: &str,
+    tap_integration: &TapIntegration,
+) -> Result<()> {
+    match cmd {
+        NodeCommands::Capabilities => {
+            print_success(format, &tap_integration.node().capabilities());
+            Ok(())
+        }
+    }
+}
@@ -3,10 +3,14 @@ use crate::output::{print_success, OutputFormat};
 use crate::tap_integration::TapIntegration;
 use clap::Subcommand;
 use serde::Serialize;
+use tap_node::storage::ReceivedFilter;
 
 #[derive(Subcommand, Debug)]
 pub enum ReceivedCommands {
     /// List received messages
+    ///
+    /// With --unread or --since, lists the processed messages the agent
+    /// received from its message log instead of the raw received records.
     List {
         /// Agent DID for storage lookup
         #[arg(long)]
@@ -17,6 +21,27 @@ pub enum ReceivedCommands {
         /// Offset for pagination
         #[arg(long, default_value = "0")]
         offset: u32,
+        /// Only messages not yet marked read
+        #[arg(long)]
+        unread: bool,
+        /// Only messages after this message log ID
+        #[arg(long)]
+        since: Option<i64>,
+        /// Filter by message type (with --unread or --since)
+        #[arg(long = "type")]
+        message_type: Option<String>,
+        /// Mark the listed messages read
+        #[arg(long)]
+        mark_read: bool,
+    },
+    /// Mark received messages read
+    MarkRead {
+        /// Agent DID for storage lookup
+        #[arg(long)]
+        agent_did: Option<String>,
+        /// Mark messages up to and including this message log ID read (all if omitted)
+        #[arg(long)]
+        up_to: Option<i64>,
     },
     /// List pending (unprocessed) received messages
     Pending {
@@ -64,6 +89,32 @@ struct ReceivedViewResponse {
     processed_at: Option<String>,
 }
 
+#[derive(Debug, Serialize)]
+struct InboxMessageInfo {
+    id: i64,
+    message_id: String,
+    message_type: String,
+    from_did: Option<String>,
+    thread_id: Option<String>,
+    read: bool,
+    created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct InboxListResponse {
+    messages: Vec<InboxMessageInfo>,
+    total: usize,
+    next_cursor: i64,
+    read_cursor: i64,
+    unread_count: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct MarkReadResponse {
+    agent_did: String,
+    read_cursor: i64,
+}
+
 fn to_received_info(r: &tap_node::storage::models::Received) -> ReceivedInfo {
     ReceivedInfo {
         id: r.id,
@@ -82,13 +133,69 @@ pub async fn handle(
     tap_integration: &TapIntegration,
 ) -> Result<()> {
     match cmd {
+        ReceivedCommands::List {
+            agent_did,
+            unread,
+            since,
+            message_type,
+            mark_read,
+            limit,
+            ..
+        } if *unread || since.is_some() => {
+            let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
+            let filter = ReceivedFilter {
+                message_type: message_type.clone(),
+                unread_only: *unread,
+                ..Default::default()
+            };
+            let node = tap_integration.node();
+            let page = node
+                .list_received(effective_did, *since, &filter, Some(*limit))
+                .await?;
+
+            let (read_cursor, unread_count) = if *mark_read && !page.messages.is_empty() {
+                node.mark_received_read(effective_did, Some(page.next_cursor))
+                    .await?;
+                let marked = node
+                    .list_received(effective_did, None, &ReceivedFilter::default(), Some(1))
+                    .await?;
+                (marked.read_cursor, marked.unread_count)
+            } else {
+                (page.read_cursor, page.unread_count)
+            };
+
+            let messages: Vec<InboxMessageInfo> = page
+                .messages
+                .iter()
+                .map(|m| InboxMessageInfo {
+                    id: m.id,
+                    message_id: m.message_id.clone(),
+                    message_type: m.message_type.clone(),
+                    from_did: m.from_did.clone(),
+                    thread_id: m.thread_id.clone(),
+                    read: page.is_read(m),
+                    created_at: m.created_at.clone(),
+                })
+                .collect();
+
+            let response = InboxListResponse {
+                total: messages.len(),
+                messages,
+                next_cursor: page.next_cursor,
+                read_cursor,
+                unread_count,
+            };
+            print_success(format, &response);
+            Ok(())
+        }
         ReceivedCommands::List {
             agent_did,
             limit,
             offset,
+            ..
         } => {
             let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
-            let storage = tap_integration.storage_for_agent(effective_did).await?;
+            let storage = tap_integration.reader_for_agent(effective_did).await?;
             let received = storage.list_received(*limit, *offset, None, None).await?;
 
             let messages: Vec<ReceivedInfo> = received.iter().map(to_received_info).collect();
@@ -114,6 +221,20 @@ pub async fn handle(
             print_success(format, &response);
             Ok(())
         }
+        ReceivedCommands::MarkRead { agent_did, up_to } => {
+            let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
+            let read_cursor = tap_integration
+                .node()
+                .mark_received_read(effective_did, *up_to)
+                .await?;
+
+            let response = MarkReadResponse {
+                agent_did: effective_did.to_string(),
+                read_cursor,
+            };
+            print_success(format, &response);
+            Ok(())
+        }
         ReceivedCommands::View { id, agent_did } => {
             let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
             let storage = tap_integration.storage_for_agent(effective_did).await?;
@@ -0,0 +1,148 @@
+use crate::error::{Error, Result};
+use crate::output::{print_success, OutputFormat};
+use crate::tap_integration::TapIntegration;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use clap::Subcommand;
+
+#[derive(Subcommand, Debug)]
+pub enum ReportCommands {
+    /// Report the activity of a counterparty
+    #[command(long_about = "\
+Report the activity of a counterparty.
+
+Aggregates the agent's transactions with the counterparty: messages exchanged, \
+how many transactions it authorized, how long it took to authorize and to \
+settle (average and 50th, 90th and 99th percentiles), rejections by code and \
+volumes per asset. A transaction counts if the counterparty sent or received \
+its initiating message or is one of its agents.
+
+Examples:
+  tap-cli report counterparty --did did:web:vasp.example
+  tap-cli report counterparty --did did:web:vasp.example --days 30
+  tap-cli report counterparty --did did:web:vasp.example \\
+    --since 2026-01-01T00:00:00Z --until 2026-04-01T00:00:00Z")]
+    Counterparty {
+        /// DID of the counterparty
+        #[arg(long)]
+        did: String,
+        /// Only count activity at or after this time (RFC 3339)
+        #[arg(long, conflicts_with = "days")]
+        since: Option<String>,
+        /// Only count activity before this time (RFC 3339)
+        #[arg(long)]
+        until: Option<String>,
+        /// Only count activity of the last number of days
+        #[arg(long)]
+        days: Option<u32>,
+        /// Agent DID whose transactions to report on (defaults to --agent-did global flag)
+        #[arg(long)]
+        agent_did: Option<String>,
+    },
+    /// Report the envelope forms received from counterparties
+    #[command(long_about = "\
+Report the envelope forms received from counterparties.
+
+Lists each distinct envelope profile the node received from a counterparty: \
+plain, signed or encrypted, in JSON, flattened or compact serialization, its \
+alg and enc, media type and schema version, with the number of messages \
+received in that form, how many failed and the last error. Profiles that only \
+ever fail point at an interoperability problem.
+
+Examples:
+  tap-cli report envelopes
+  tap-cli report envelopes --did did:web:vasp.example")]
+    Envelopes {
+        /// Only report the envelopes of this counterparty
+        #[arg(long)]
+        did: Option<String>,
+    },
+    /// Report how long agents take to answer each message type
+    #[command(long_about = "\
+Report how long agents take to answer each message type.
+
+For each message type with a response deadline (see tap-http \
+--response-deadline), reports how many messages were answered, are still \
+awaiting an answer or were withdrawn by the counterparty, how many were not \
+answered by their deadline, and the time taken to answer them (average and \
+50th, 90th and 99th percentiles).
+
+Examples:
+  tap-cli report response-times
+  tap-cli report response-times --days 7")]
+    ResponseTimes {
+        /// Only count messages received at or after this time (RFC 3339)
+        #[arg(long, conflicts_with = "days")]
+        since: Option<String>,
+        /// Only count messages received in the last number of days
+        #[arg(long)]
+        days: Option<u32>,
+    },
+}
+
+pub async fn handle(
+    cmd: &ReportCommands,
+    format: OutputFormat,
+    agent_did: &str,
+    tap_integration: &TapIntegration,
+) -> Result<()> {
+    let default_agent_did = agent_did;
+    match cmd {
+        ReportCommands::Counterparty {
+            did,
+            since,
+            until,
+            days,
+            agent_did,
+        } => {
+            let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
+            for timestamp in [since, until].into_iter().flatten() {
+                DateTime::parse_from_rfc3339(timestamp).map_err(|e| {
+                    Error::invalid_parameter(format!("Invalid timestamp {}: {}", timestamp, e))
+                })?;
+            }
+            let since = match days {
+                Some(days) => Some(
+                    (Utc::now() - Duration::days(i64::from(*days)))
+                        .to_rfc3339_opts(SecondsFormat::Secs, true),
+                ),
+                None => since.clone(),
+            };
+
+            let storage = tap_integration.reader_for_agent(effective_did).await?;
+            let stats = storage
+                .get_counterparty_stats(did, since.as_deref(), until.as_deref())
+                .await?;
+            print_success(format, &stats);
+            Ok(())
+        }
+        ReportCommands::Envelopes { did } => {
+            let stats = tap_integration
+                .node()
+                .envelope_stats(did.as_deref())
+                .await?;
+            print_success(format, &stats);
+            Ok(())
+        }
+        ReportCommands::ResponseTimes { since, days } => {
+            if let Some(since) = since {
+                DateTime::parse_from_rfc3339(since).map_err(|e| {
+                    Error::invalid_parameter(format!("Invalid timestamp {}: {}", since, e))
+                })?;
+            }
+            let since = match days {
+                Some(days) => Some(
+                    (Utc::now() - Duration::days(i64::from(*days)))
+                        .to_rfc3339_opts(SecondsFormat::Secs, true),
+                ),
+                None => since.clone(),
+            };
+
+            let stats = tap_integration
+                .node()
+                .response_time_report(since.as_deref())
+                .await?;
+            print_success(format, &stats);
+            Ok(())
+        }
+    }
+}
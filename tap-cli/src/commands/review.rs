@@ -0,0 +1,460 @@
+use crate::commands::decision::auto_resolve_decisions;
+use crate::error::{Error, Result};
+use crate::output::{print_success, OutputFormat};
+use crate::tap_integration::TapIntegration;
+use clap::Subcommand;
+use serde::Serialize;
+use serde_json::Value;
+use tap_msg::message::tap_message_trait::TapMessageBody;
+use tap_msg::message::{Authorize, Reject, RejectionCode};
+use tap_node::storage::{DecisionType, ReviewItem, ReviewStatus};
+use tracing::debug;
+
+#[derive(Subcommand, Debug)]
+pub enum ReviewCommands {
+    /// List transactions held for manual review
+    #[command(long_about = "\
+List transactions held for manual review.
+
+Review items are created when a policy rule with the 'manual_review' action \
+trips for a new transaction. Each item is held on behalf of one of our agents \
+in the transaction until a reviewer approves or rejects it.
+
+Review statuses:
+  pending   Awaiting a reviewer
+  approved  Approved; Authorize was sent
+  rejected  Rejected; Reject was sent
+  expired   Transaction reached a terminal state before review
+
+Examples:
+  # List pending reviews
+  tap-cli review list --status pending
+
+  # List all reviews for a specific agent
+  tap-cli review list --agent-did did:key:z6Mk...")]
+    List {
+        /// Agent DID for storage lookup (defaults to --agent-did global flag)
+        #[arg(long)]
+        agent_did: Option<String>,
+        /// Filter by status: pending, approved, rejected, expired
+        #[arg(long)]
+        status: Option<String>,
+        /// Maximum results
+        #[arg(long, default_value = "50")]
+        limit: u32,
+        /// Offset for pagination
+        #[arg(long, default_value = "0")]
+        offset: u32,
+    },
+    /// Approve a pending review and authorize the transaction
+    #[command(long_about = "\
+Approve a pending review and authorize the transaction.
+
+Sends an Authorize message from the review item's agent and records the \
+reviewer on the item. Auto-resolves 'authorization_required' decisions for \
+the transaction.
+
+Examples:
+  tap-cli review approve --review-id 7 --reviewer alice@example.com
+  tap-cli review approve --review-id 7 --reviewer alice@example.com \\
+    --note \"Customer verified by phone\" --settlement-address eip155:1:0x742d35Cc...")]
+    Approve {
+        /// Review ID to approve (numeric, from 'review list' output)
+        #[arg(long)]
+        review_id: i64,
+        /// Identity of the reviewer, recorded on the review item
+        #[arg(long)]
+        reviewer: String,
+        /// Optional note recorded with the review
+        #[arg(long)]
+        note: Option<String>,
+        /// Settlement address to include in the Authorize (CAIP-10 format)
+        #[arg(long)]
+        settlement_address: Option<String>,
+        /// Agent DID for storage lookup (defaults to --agent-did global flag)
+        #[arg(long)]
+        agent_did: Option<String>,
+    },
+    /// Reject a pending review and reject the transaction
+    #[command(long_about = "\
+Reject a pending review and reject the transaction.
+
+Sends a Reject message from the review item's agent and records the reviewer \
+on the item. The note is used as the rejection reason; without a note, the \
+policy reason that triggered the review is used.
+
+Examples:
+  tap-cli review reject --review-id 7 --reviewer alice@example.com --note \"Structuring suspected\"")]
+    Reject {
+        /// Review ID to reject (numeric, from 'review list' output)
+        #[arg(long)]
+        review_id: i64,
+        /// Identity of the reviewer, recorded on the review item
+        #[arg(long)]
+        reviewer: String,
+        /// Optional note recorded with the review and used as the rejection reason
+        #[arg(long)]
+        note: Option<String>,
+        /// Agent DID for storage lookup (defaults to --agent-did global flag)
+        #[arg(long)]
+        agent_did: Option<String>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct ReviewInfo {
+    id: i64,
+    transaction_id: String,
+    agent_did: String,
+    rule: String,
+    reason: String,
+    details: Value,
+    status: String,
+    reviewer: Option<String>,
+    review_note: Option<String>,
+    message_id: Option<String>,
+    created_at: String,
+    reviewed_at: Option<String>,
+}
+
+impl From<ReviewItem> for ReviewInfo {
+    fn from(item: ReviewItem) -> Self {
+        Self {
+            id: item.id,
+            transaction_id: item.transaction_id,
+            agent_did: item.agent_did,
+            rule: item.rule,
+            reason: item.reason,
+            details: item.details,
+            status: item.status.to_string(),
+            reviewer: item.reviewer,
+            review_note: item.review_note,
+            message_id: item.message_id,
+            created_at: item.created_at,
+            reviewed_at: item.reviewed_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ReviewListResponse {
+    reviews: Vec<ReviewInfo>,
+    total: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct ReviewActionResponse {
+    review_id: i64,
+    transaction_id: String,
+    message_id: String,
+    status: String,
+    reviewer: String,
+    reviewed_at: String,
+}
+
+pub async fn handle(
+    cmd: &ReviewCommands,
+    format: OutputFormat,
+    default_agent_did: &str,
+    tap_integration: &TapIntegration,
+) -> Result<()> {
+    match cmd {
+        ReviewCommands::List {
+            agent_did,
+            status,
+            limit,
+            offset,
+        } => {
+            let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
+            let storage = tap_integration.reader_for_agent(effective_did).await?;
+
+            let status_filter = status
+                .as_deref()
+                .map(ReviewStatus::try_from)
+                .transpose()
+                .map_err(|e| Error::invalid_parameter(format!("Invalid status: {}", e)))?;
+
+            let items = storage
+                .list_review_items(Some(effective_did), status_filter, *limit, *offset)
+                .await?;
+
+            let reviews: Vec<ReviewInfo> = items.into_iter().map(ReviewInfo::from).collect();
+            let response = ReviewListResponse {
+                total: reviews.len(),
+                reviews,
+            };
+            print_success(format, &response);
+            Ok(())
+        }
+        ReviewCommands::Approve {
+            review_id,
+            reviewer,
+            note,
+            settlement_address,
+            agent_did,
+        } => {
+            let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
+            let item = pending_review(tap_integration, effective_did, *review_id).await?;
+
+            let authorize = Authorize {
+                transaction_id: item.transaction_id.clone(),
+                settlement_address: settlement_address.clone(),
+                expiry: None,
+            };
+            authorize.validate().map_err(|e| {
+                Error::invalid_parameter(format!("Authorize validation failed: {}", e))
+            })?;
+            let didcomm_message = authorize.to_didcomm(&item.agent_did).map_err(|e| {
+                Error::command_failed(format!("Failed to create DIDComm message: {}", e))
+            })?;
+
+            debug!(
+                "Approving review {} for transaction {}",
+                review_id, item.transaction_id
+            );
+
+            tap_integration
+                .node()
+                .send_message(item.agent_did.clone(), didcomm_message.clone())
+                .await
+                .map_err(|e| Error::command_failed(format!("Failed to send authorize: {}", e)))?;
+
+            complete_review(
+                tap_integration,
+                effective_did,
+                &item,
+                ReviewStatus::Approved,
+                reviewer,
+                note.as_deref(),
+                &didcomm_message.id,
+            )
+            .await?;
+
+            auto_resolve_decisions(
+                tap_integration,
+                &item.agent_did,
+                &item.transaction_id,
+                "authorize",
+                Some(DecisionType::AuthorizationRequired),
+            )
+            .await;
+
+            print_success(
+                format,
+                &ReviewActionResponse {
+                    review_id: *review_id,
+                    transaction_id: item.transaction_id,
+                    message_id: didcomm_message.id,
+                    status: ReviewStatus::Approved.to_string(),
+                    reviewer: reviewer.clone(),
+                    reviewed_at: chrono::Utc::now().to_rfc3339(),
+                },
+            );
+            Ok(())
+        }
+        ReviewCommands::Reject {
+            review_id,
+            reviewer,
+            note,
+            agent_did,
+        } => {
+            let effective_did = agent_did.as_deref().unwrap_or(default_agent_did);
+            let item = pending_review(tap_integration, effective_did, *review_id).await?;
+
+            let reject = Reject {
+                transaction_id: item.transaction_id.clone(),
+                code: Some(RejectionCode::Policy),
+                reason: Some(note.clone().unwrap_or_else(|| item.reason.clone())),
+            };
+            reject.validate().map_err(|e| {
+                Error::invalid_parameter(format!("Reject validation failed: {}", e))
+            })?;
+            let didcomm_message = reject.to_didcomm(&item.agent_did).map_err(|e| {
+                Error::command_failed(format!("Failed to create DIDComm message: {}", e))
+            })?;
+
+            debug!(
+                "Rejecting review {} for transaction {}",
+                review_id, item.transaction_id
+            );
+
+            tap_integration
+                .node()
+                .send_message(item.agent_did.clone(), didcomm_message.clone())
+                .await
+                .map_err(|e| Error::command_failed(format!("Failed to send reject: {}", e)))?;
+
+            complete_review(
+                tap_integration,
+                effective_did,
+                &item,
+                ReviewStatus::Rejected,
+                reviewer,
+                note.as_deref(),
+                &didcomm_message.id,
+            )
+            .await?;
+
+            auto_resolve_decisions(
+                tap_integration,
+                &item.agent_did,
+                &item.transaction_id,
+                "reject",
+                None,
+            )
+            .await;
+
+            print_success(
+                format,
+                &ReviewActionResponse {
+                    review_id: *review_id,
+                    transaction_id: item.transaction_id,
+                    message_id: didcomm_message.id,
+                    status: ReviewStatus::Rejected.to_string(),
+                    reviewer: reviewer.clone(),
+                    reviewed_at: chrono::Utc::now().to_rfc3339(),
+                },
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Load a review item and verify it is still pending
+async fn pending_review(
+    tap_integration: &TapIntegration,
+    agent_did: &str,
+    review_id: i64,
+) -> Result<ReviewItem> {
+    let storage = tap_integration.storage_for_agent(agent_did).await?;
+    let item = storage
+        .get_review_item(review_id)
+        .await?
+        .ok_or_else(|| Error::command_failed(format!("Review {} not found", review_id)))?;
+
+    if item.status != ReviewStatus::Pending {
+        return Err(Error::command_failed(format!(
+            "Review {} is already {} and cannot be acted on",
+            review_id, item.status
+        )));
+    }
+
+    Ok(item)
+}
+
+/// Record the reviewer's decision on a review item
+async fn complete_review(
+    tap_integration: &TapIntegration,
+    agent_did: &str,
+    item: &ReviewItem,
+    status: ReviewStatus,
+    reviewer: &str,
+    note: Option<&str>,
+    message_id: &str,
+) -> Result<()> {
+    let storage = tap_integration.storage_for_agent(agent_did).await?;
+    let completed = storage
+        .complete_review_item(item.id, status, reviewer, note, Some(message_id))
+        .await?;
+    if !completed {
+        return Err(Error::command_failed(format!(
+            "Review {} was completed by another reviewer",
+            item.id
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tap_integration::TapIntegration;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    async fn setup_test() -> (TapIntegration, String) {
+        let dir = tempdir().unwrap();
+        let tap_root = dir.path().to_str().unwrap();
+
+        let (agent, did) = tap_agent::TapAgent::from_ephemeral_key().await.unwrap();
+        let agent_arc = std::sync::Arc::new(agent);
+
+        let integration = TapIntegration::new(Some(&did), Some(tap_root), Some(agent_arc))
+            .await
+            .unwrap();
+
+        std::mem::forget(dir);
+        (integration, did)
+    }
+
+    #[tokio::test]
+    async fn test_review_list() {
+        let (integration, did) = setup_test().await;
+
+        let storage = integration.storage_for_agent(&did).await.unwrap();
+        storage
+            .insert_review_item(
+                "txn-review-1",
+                &did,
+                "daily-limit",
+                "Velocity limit exceeded",
+                &json!({}),
+            )
+            .await
+            .unwrap();
+
+        let cmd = ReviewCommands::List {
+            agent_did: Some(did.clone()),
+            status: Some("pending".to_string()),
+            limit: 50,
+            offset: 0,
+        };
+        assert!(handle(&cmd, OutputFormat::Json, &did, &integration)
+            .await
+            .is_ok());
+
+        let cmd = ReviewCommands::List {
+            agent_did: Some(did.clone()),
+            status: Some("invalid_status".to_string()),
+            limit: 50,
+            offset: 0,
+        };
+        assert!(handle(&cmd, OutputFormat::Json, &did, &integration)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_review_approve_not_pending() {
+        let (integration, did) = setup_test().await;
+
+        let storage = integration.storage_for_agent(&did).await.unwrap();
+        let review_id = storage
+            .insert_review_item(
+                "txn-review-2",
+                &did,
+                "daily-limit",
+                "Velocity limit exceeded",
+                &json!({}),
+            )
+            .await
+            .unwrap();
+        storage
+            .complete_review_item(review_id, ReviewStatus::Rejected, "bob", None, None)
+            .await
+            .unwrap();
+
+        for review_id in [review_id, 99999] {
+            let cmd = ReviewCommands::Approve {
+                review_id,
+                reviewer: "alice".to_string(),
+                note: None,
+                settlement_address: None,
+                agent_did: Some(did.clone()),
+            };
+            assert!(handle(&cmd, OutputFormat::Json, &did, &integration)
+                .await
+                .is_err());
+        }
+    }
+}
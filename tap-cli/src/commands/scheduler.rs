@@ -0,0 +1,75 @@
+use crate::error::Result;
+use crate::output::{print_success, OutputFormat};
+use crate::tap_integration::TapIntegration;
+use clap::Subcommand;
+use serde::Serialize;
+use tap_node::scheduler::JobTrigger;
+
+#[derive(Subcommand, Debug)]
+pub enum SchedulerCommands {
+    /// List the node's scheduled jobs with their last and next runs
+    List,
+    /// Run a scheduled job now
+    #[command(long_about = "\
+Run a scheduled job now.
+
+If the job is scheduled by a node running on the same database (e.g. \
+tap-http), the run is requested from that node, which picks it up within a \
+few seconds; `scheduler list` shows its outcome afterwards.
+
+Examples:
+  tap-cli scheduler run --job backup
+  tap-cli scheduler run --job retention_purge")]
+    Run {
+        /// Name of the job (see `scheduler list`)
+        #[arg(long)]
+        job: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct RunResponse {
+    job: String,
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_run_at: Option<String>,
+}
+
+pub async fn handle(
+    cmd: &SchedulerCommands,
+    format: OutputFormat,
+    _agent_did: &str,
+    tap_integration: &TapIntegration,
+) -> Result<()> {
+    match cmd {
+        SchedulerCommands::List => {
+            let jobs = tap_integration.node().scheduled_jobs().await?;
+            print_success(format, &jobs);
+            Ok(())
+        }
+        SchedulerCommands::Run { job } => {
+            let response = match tap_integration.node().run_scheduled_job(job).await? {
+                JobTrigger::Ran(run) => RunResponse {
+                    job: run.name,
+                    status: run.status.to_string(),
+                    error: run.error,
+                    duration_ms: Some(run.duration.as_millis()),
+                    next_run_at: run.next_run_at.map(|at| at.to_rfc3339()),
+                },
+                JobTrigger::Requested => RunResponse {
+                    job: job.clone(),
+                    status: "requested".to_string(),
+                    error: None,
+                    duration_ms: None,
+                    next_run_at: None,
+                },
+            };
+            print_success(format, &response);
+            Ok(())
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use crate::error::{Error, Result};
-use crate::output::{print_success, OutputFormat};
+use crate::output::{print_rendered, print_success, OutputFormat};
 use crate::tap_integration::TapIntegration;
 use clap::Subcommand;
 use serde::Serialize;
@@ -9,10 +9,12 @@ use tap_msg::message::payment::InvoiceReference;
 use tap_msg::message::tap_message_trait::TapMessageBody;
 use tap_msg::message::transfer::TransactionValue;
 use tap_msg::message::{
-    Agent, Capture, Connect, ConnectionConstraints, Escrow, Exchange, Party, Payment, Quote,
-    TransactionLimits, Transfer,
+    Agent, AgentRole, Capture, Connect, ConnectionConstraints, Escrow, Exchange, Party, Payment,
+    Quote, TransactionLimits, Transfer,
 };
 use tap_msg::settlement_address::SettlementAddress;
+use tap_msg::utils::message_transaction_hash;
+use tap_node::storage::{TransactionGraph, TransactionGraphNode};
 use tracing::debug;
 
 #[derive(Subcommand, Debug)]
@@ -277,6 +279,26 @@ Examples:
         #[arg(long, default_value = "0")]
         offset: u32,
     },
+    /// Show the graph of related transactions
+    #[command(long_about = "\
+Show the graph of transactions related to a transaction.
+
+Transactions are related through the parent thread ID (pthid) of their \
+messages, e.g. a Transfer settling a Payment, or a Transfer reverting an \
+earlier one. The graph starts at the earliest known ancestor of the \
+transaction and includes all its descendants; text output renders it as a \
+tree.
+
+Examples:
+  tap-cli transaction graph <TRANSACTION_ID>
+  tap-cli --format json transaction graph <TRANSACTION_ID>")]
+    Graph {
+        /// Transaction ID (reference ID of the Transfer or Payment)
+        transaction_id: String,
+        /// Agent DID whose database is read (defaults to --agent-did global flag)
+        #[arg(long)]
+        agent_did: Option<String>,
+    },
 }
 
 #[derive(Debug, Serialize)]
@@ -501,6 +523,13 @@ pub async fn handle(
             )
             .await
         }
+        TransactionCommands::Graph {
+            transaction_id,
+            agent_did: graph_agent_did,
+        } => {
+            let effective_did = graph_agent_did.as_deref().unwrap_or(agent_did);
+            handle_graph(effective_did, transaction_id, format, tap_integration).await
+        }
     }
 }
 
@@ -761,7 +790,7 @@ async fn handle_escrow(
 
     let escrow_agent_count = agents
         .iter()
-        .filter(|a| a.role == Some("EscrowAgent".to_string()))
+        .filter(|a| a.has_role(AgentRole::EscrowAgent))
         .count();
     if escrow_agent_count != 1 {
         return Err(Error::invalid_parameter(format!(
@@ -1002,6 +1031,9 @@ struct TransactionInfo {
     from: Option<String>,
     to: Option<String>,
     direction: String,
+    /// Canonical hash of the transaction, for Transfer and Payment messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    canonical_hash: Option<String>,
     created_at: String,
     body: serde_json::Value,
 }
@@ -1018,7 +1050,7 @@ async fn handle_list(
     format: OutputFormat,
     tap_integration: &TapIntegration,
 ) -> Result<()> {
-    let storage = tap_integration.storage_for_agent(agent_did).await?;
+    let storage = tap_integration.reader_for_agent(agent_did).await?;
     let direction_filter = None;
     let messages = storage
         .list_messages(limit, offset, direction_filter)
@@ -1060,6 +1092,7 @@ async fn handle_list(
             from: msg.from_did.clone(),
             to: msg.to_did.clone(),
             direction: msg.direction.to_string(),
+            canonical_hash: message_transaction_hash(&msg.message_json),
             created_at: msg.created_at.clone(),
             body: msg.message_json.clone(),
         })
@@ -1073,6 +1106,70 @@ async fn handle_list(
     Ok(())
 }
 
+#[derive(Debug, Serialize)]
+struct TransactionGraphResponse {
+    root_id: String,
+    transactions: Vec<TransactionGraphNode>,
+    total: usize,
+}
+
+async fn handle_graph(
+    agent_did: &str,
+    transaction_id: &str,
+    format: OutputFormat,
+    tap_integration: &TapIntegration,
+) -> Result<()> {
+    let storage = tap_integration.reader_for_agent(agent_did).await?;
+    let graph = storage
+        .get_transaction_graph(transaction_id)
+        .await?
+        .ok_or_else(|| {
+            Error::command_failed(format!("Transaction {} not found", transaction_id))
+        })?;
+
+    let tree = render_graph(&graph);
+    let response = TransactionGraphResponse {
+        root_id: graph.root_id,
+        total: graph.nodes.len(),
+        transactions: graph.nodes,
+    };
+    print_rendered(format, &response, &tree);
+    Ok(())
+}
+
+/// Render a transaction graph as a tree, one transaction per line
+fn render_graph(graph: &TransactionGraph) -> String {
+    fn render_children(graph: &TransactionGraph, parent_id: &str, prefix: &str, out: &mut String) {
+        let children: Vec<_> = graph.children(parent_id).collect();
+        for (i, child) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            out.push_str(&format!(
+                "{}{}{}\n",
+                prefix,
+                if last { "└── " } else { "├── " },
+                graph_label(child)
+            ));
+            let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            render_children(graph, &child.transaction_id, &prefix, out);
+        }
+    }
+
+    let mut out = String::new();
+    if let Some(root) = graph.get(&graph.root_id) {
+        out.push_str(&graph_label(root));
+        out.push('\n');
+        render_children(graph, &root.transaction_id, "", &mut out);
+    }
+    out
+}
+
+fn graph_label(node: &TransactionGraphNode) -> String {
+    format!(
+        "{} [{}, {}] {}",
+        node.transaction_id, node.transaction_type, node.status, node.created_at
+    )
+}
+
 fn parse_agents(json: Option<&str>) -> Result<Vec<Agent>> {
     match json {
         Some(j) => {
@@ -1086,3 +1183,38 @@ fn parse_agents(json: Option<&str>) -> Result<Vec<Agent>> {
         None => Ok(vec![]),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tap_node::storage::{TransactionStatus, TransactionType};
+
+    fn node(id: &str, parent_id: Option<&str>, depth: u32) -> TransactionGraphNode {
+        TransactionGraphNode {
+            transaction_id: id.to_string(),
+            parent_id: parent_id.map(String::from),
+            parent_thread_id: parent_id.map(String::from),
+            transaction_type: TransactionType::Transfer,
+            message_type: "https://tap.rsvp/schema/1.0#Transfer".to_string(),
+            status: TransactionStatus::Pending,
+            depth,
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_render_graph_as_tree() {
+        let graph = TransactionGraph {
+            root_id: "pay-1".to_string(),
+            nodes: vec![
+                node("pay-1", None, 0),
+                node("tx-1", Some("pay-1"), 1),
+                node("revert-1", Some("tx-1"), 2),
+                node("tx-2", Some("pay-1"), 1),
+            ],
+        };
+
+        let tree = render_graph(&graph).replace(" [transfer, pending] 2026-01-01T00:00:00Z", "");
+        assert_eq!(tree, "pay-1\n├── tx-1\n│   └── revert-1\n└── tx-2\n");
+    }
+}
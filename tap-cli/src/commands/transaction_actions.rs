@@ -5,7 +5,7 @@ use crate::tap_integration::TapIntegration;
 use clap::Subcommand;
 use serde::Serialize;
 use tap_msg::message::tap_message_trait::TapMessageBody;
-use tap_msg::message::{Authorize, Cancel, Reject, Revert, Settle};
+use tap_msg::message::{Authorize, Cancel, Reject, RejectionCode, Revert, Settle};
 use tap_node::storage::DecisionType;
 use tracing::debug;
 
@@ -43,8 +43,12 @@ Reject a transaction (TAIP-4).
 Sends a Reject message with a reason. This moves the transaction to a terminal \
 'Rejected' state. All pending decisions for this transaction are expired.
 
+An optional reason code classifies the rejection: policy, sanctions, \
+missing_travel_rule_data, customer_declined, fraud, unsupported_asset or other.
+
 Examples:
-  tap-cli action reject --transaction-id <ID> --reason \"AML policy violation\"")]
+  tap-cli action reject --transaction-id <ID> --reason \"AML policy violation\"
+  tap-cli action reject --transaction-id <ID> --code sanctions --reason \"OFAC match\"")]
     Reject {
         /// Transaction ID to reject
         #[arg(long)]
@@ -52,6 +56,9 @@ Examples:
         /// Rejection reason (required, describes why the transaction was rejected)
         #[arg(long)]
         reason: String,
+        /// Standardized rejection code
+        #[arg(long)]
+        code: Option<RejectionCode>,
     },
     /// Cancel a transaction (TAIP-5)
     #[command(long_about = "\
@@ -85,7 +92,9 @@ Auto-resolves 'settlement_required' decisions for this transaction.
 
 Examples:
   tap-cli action settle --transaction-id <ID> --settlement-id eip155:1:0xabcdef...
-  tap-cli action settle --transaction-id <ID> --settlement-id eip155:1:0xabcdef... --amount 75.0")]
+  tap-cli action settle --transaction-id <ID> --settlement-id eip155:1:0xabcdef... --amount 75.0
+  tap-cli action settle --transaction-id <ID> --settlement-id eip155:1:0xabcdef... \\
+    --settled-asset eip155:1/erc20:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48 --amount 100.05")]
     Settle {
         /// Transaction ID to settle
         #[arg(long)]
@@ -96,6 +105,10 @@ Examples:
         /// Settled amount (if different from original transaction amount)
         #[arg(long)]
         amount: Option<String>,
+        /// Asset settled in (CAIP-19 or ISO 4217), if different from the requested asset;
+        /// requires --amount in that asset
+        #[arg(long, requires = "amount")]
+        settled_asset: Option<String>,
     },
     /// Revert a settled transaction (TAIP-12)
     #[command(long_about = "\
@@ -155,7 +168,18 @@ pub async fn handle(
         ActionCommands::Reject {
             transaction_id,
             reason,
-        } => handle_reject(agent_did, transaction_id, reason, format, tap_integration).await,
+            code,
+        } => {
+            handle_reject(
+                agent_did,
+                transaction_id,
+                reason,
+                *code,
+                format,
+                tap_integration,
+            )
+            .await
+        }
         ActionCommands::Cancel {
             transaction_id,
             by,
@@ -175,12 +199,14 @@ pub async fn handle(
             transaction_id,
             settlement_id,
             amount,
+            settled_asset,
         } => {
             handle_settle(
                 agent_did,
                 transaction_id,
                 settlement_id,
                 amount.clone(),
+                settled_asset.clone(),
                 format,
                 tap_integration,
             )
@@ -258,11 +284,13 @@ async fn handle_reject(
     agent_did: &str,
     transaction_id: &str,
     reason: &str,
+    code: Option<RejectionCode>,
     format: OutputFormat,
     tap_integration: &TapIntegration,
 ) -> Result<()> {
     let reject = Reject {
         transaction_id: transaction_id.to_string(),
+        code,
         reason: Some(reason.to_string()),
     };
 
@@ -343,6 +371,7 @@ async fn handle_settle(
     transaction_id: &str,
     settlement_id: &str,
     amount: Option<String>,
+    settled_asset: Option<String>,
     format: OutputFormat,
     tap_integration: &TapIntegration,
 ) -> Result<()> {
@@ -350,6 +379,7 @@ async fn handle_settle(
         transaction_id: transaction_id.to_string(),
         settlement_id: Some(settlement_id.to_string()),
         amount,
+        settled_asset,
     };
 
     settle
@@ -25,8 +25,10 @@ full TAP transaction lifecycle: creating agents and DIDs, initiating transfers a
 payments, authorizing or rejecting transactions, settling on-chain, managing customers \
 for Travel Rule compliance, and inspecting the decision log.
 
-All commands output JSON by default (for scripting) or human-readable text with \
---format text. Data is stored under ~/.tap/ (override with --tap-root).
+All commands output JSON by default (for scripting), human-readable text with \
+--format text, or aligned columns with --format table. Use --quiet to print only \
+result IDs and --query to select fields (e.g. --query 'transactions[*].id'). \
+Data is stored under ~/.tap/ (override with --tap-root).
 
 Typical workflow:
   1. Create an agent:      tap-cli agent create
@@ -55,10 +57,18 @@ struct Cli {
     #[arg(long, global = true)]
     tap_root: Option<String>,
 
-    /// Output format
+    /// Output format: json, text or table
     #[arg(long, global = true, default_value = "json")]
     format: String,
 
+    /// Print only the primary ID of each result, one per line
+    #[arg(long, short, global = true)]
+    quiet: bool,
+
+    /// Select part of the result data, e.g. 'transactions[*].id'
+    #[arg(long, global = true)]
+    query: Option<String>,
+
     /// Secret helper command for external key management (replaces keys.json)
     #[arg(long, global = true, env = "TAP_SECRET_HELPER")]
     secret_helper: Option<String>,
@@ -164,6 +174,88 @@ Decision types:
         #[command(subcommand)]
         cmd: commands::decision::DecisionCommands,
     },
+    /// Database maintenance (usage, events, rebuild)
+    #[command(long_about = "\
+Database maintenance.
+
+  usage    Report each agent database's size, largest tables and storage quota
+  events   List the state event log of an agent's database
+  rebuild  Rebuild the transaction and delivery tables from the state event log
+  migrate-to-postgres  Copy agent databases into Postgres and verify the copy
+
+Quotas are configured on the node (see tap-http --storage-quota). Once a \
+database exceeds its quota, non-essential writes such as raw copies of \
+received messages are rejected or trigger archival.")]
+    Db {
+        #[command(subcommand)]
+        cmd: commands::db::DbCommands,
+    },
+    /// Drafts of transfers and payments (create, edit, list, show, send, delete)
+    #[command(long_about = "\
+Drafts of transfers and payments.
+
+A draft holds a Transfer or Payment body while it is built up over several \
+commands. It is validated after every change and can be sent once valid. \
+Drafts expire, by default after 7 days.
+
+  create  Create a draft, possibly incomplete
+  edit    Change fields of a draft
+  list    List drafts and their validation status
+  show    Show a draft
+  send    Send a valid draft
+  delete  Delete a draft that was not sent")]
+    Draft {
+        #[command(subcommand)]
+        cmd: commands::draft::DraftCommands,
+    },
+    /// Manual review queue (list, approve, reject)
+    #[command(long_about = "\
+Manual review queue.
+
+Transactions are queued for review when a policy rule with the 'manual_review' \
+action trips. Reviewers approve or reject them on behalf of our agents; the \
+decision sends Authorize or Reject and is recorded with the reviewer identity.
+
+  list     List review items
+  approve  Send Authorize and mark the item approved
+  reject   Send Reject and mark the item rejected")]
+    Review {
+        #[command(subcommand)]
+        cmd: commands::review::ReviewCommands,
+    },
+    /// Reports over stored transactions and messages (counterparty, envelopes)
+    #[command(long_about = "\
+Reports over stored transactions and messages.
+
+  counterparty  Volumes, authorization rate, latencies and rejections of a counterparty
+  envelopes     Envelope forms, algorithms and schema versions received from counterparties")]
+    Report {
+        #[command(subcommand)]
+        cmd: commands::report::ReportCommands,
+    },
+    /// Scheduled jobs of the node (list, run)
+    #[command(long_about = "\
+Scheduled jobs of the node.
+
+  list  List the jobs with their schedule, last run, outcome and next run
+  run   Run a job now
+
+Jobs are the node's periodic maintenance: transaction_expiry, delivery_retry, \
+response_timers, retention_purge, backup and clock_health, each enabled by the node's \
+configuration. Their schedules are set with tap-http --job-schedule.")]
+    Scheduler {
+        #[command(subcommand)]
+        cmd: commands::scheduler::SchedulerCommands,
+    },
+    /// The node the CLI runs on (capabilities)
+    #[command(long_about = "\
+The node the CLI runs on.
+
+  capabilities  Features, storage backend and optional subsystems active in the node")]
+    Node {
+        #[command(subcommand)]
+        cmd: commands::node::NodeCommands,
+    },
 }
 
 #[tokio::main]
@@ -190,6 +282,19 @@ async fn main() {
         OutputFormat::Json
     });
 
+    let query = match cli.query.as_deref().map(str::parse::<output::Query>) {
+        Some(Err(e)) => {
+            output::print_error(format, &e);
+            std::process::exit(1);
+        }
+        Some(Ok(query)) => Some(query),
+        None => None,
+    };
+    output::configure(output::OutputOptions {
+        quiet: cli.quiet,
+        query,
+    });
+
     // Initialize logging to stderr
     let level = if cli.debug { "debug" } else { "warn" };
     tracing_subscriber::registry()
@@ -264,6 +369,24 @@ async fn main() {
         Commands::Decision { ref cmd } => {
             commands::decision::handle(cmd, format, &agent_did, &tap_integration).await
         }
+        Commands::Draft { ref cmd } => {
+            commands::draft::handle(cmd, format, &agent_did, &tap_integration).await
+        }
+        Commands::Review { ref cmd } => {
+            commands::review::handle(cmd, format, &agent_did, &tap_integration).await
+        }
+        Commands::Db { ref cmd } => {
+            commands::db::handle(cmd, format, &agent_did, &tap_integration).await
+        }
+        Commands::Report { ref cmd } => {
+            commands::report::handle(cmd, format, &agent_did, &tap_integration).await
+        }
+        Commands::Scheduler { ref cmd } => {
+            commands::scheduler::handle(cmd, format, &agent_did, &tap_integration).await
+        }
+        Commands::Node { ref cmd } => {
+            commands::node::handle(cmd, format, &agent_did, &tap_integration).await
+        }
         Commands::Did { .. } => unreachable!(),
     };
 
@@ -348,7 +471,7 @@ async fn resolve_agent(cli: &Cli) -> Result<(Arc<TapAgent>, String)> {
                 let config = AgentConfig::new(generated_key.did.clone()).with_debug(true);
                 let agent = TapAgent::new(config, Arc::new(key_manager));
 
-                Ok((Arc::new(agent), generated_key.did))
+                Ok((Arc::new(agent), generated_key.did.clone()))
             }
         }
     }
@@ -1,11 +1,13 @@
 use serde::Serialize;
 use serde_json::Value;
+use std::sync::OnceLock;
 
 /// Output format for CLI responses
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OutputFormat {
     Json,
     Text,
+    Table,
 }
 
 impl std::str::FromStr for OutputFormat {
@@ -14,7 +16,181 @@ impl std::str::FromStr for OutputFormat {
         match s.to_lowercase().as_str() {
             "json" => Ok(Self::Json),
             "text" => Ok(Self::Text),
-            _ => Err(format!("Unknown format: {}. Use 'json' or 'text'", s)),
+            "table" => Ok(Self::Table),
+            _ => Err(format!(
+                "Unknown format: {}. Use 'json', 'text' or 'table'",
+                s
+            )),
+        }
+    }
+}
+
+/// Fields tried in order to find a record's primary ID for `--quiet`
+const PRIMARY_ID_FIELDS: &[&str] = &["id", "transaction_id", "message_id", "decision_id", "did"];
+
+/// Longest nested JSON value shown in a table cell before truncation
+const MAX_CELL_WIDTH: usize = 48;
+
+/// Output options that apply to every command
+#[derive(Debug, Clone, Default)]
+pub struct OutputOptions {
+    /// Print only the primary ID of each result, one per line
+    pub quiet: bool,
+    /// Filter applied to the result data before printing
+    pub query: Option<Query>,
+}
+
+static OPTIONS: OnceLock<OutputOptions> = OnceLock::new();
+
+/// Set the output options for this process
+///
+/// Must be called before any output is printed; later calls are ignored.
+pub fn configure(options: OutputOptions) {
+    let _ = OPTIONS.set(options);
+}
+
+fn options() -> &'static OutputOptions {
+    OPTIONS.get_or_init(OutputOptions::default)
+}
+
+/// One step of a [`Query`] path
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Field(String),
+    Index(i64),
+    Wildcard,
+}
+
+/// A JMESPath-style path expression selecting part of the output data
+///
+/// Supports field access (`total`, `transactions.0`), indexing
+/// (`transactions[0]`, `transactions[-1]`) and projections over arrays
+/// (`transactions[*].id` or `transactions[].id`). A leading `$.` or `.` is
+/// accepted for JSONPath familiarity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    segments: Vec<Segment>,
+}
+
+impl std::str::FromStr for Query {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let expr = s.trim();
+        let expr = expr
+            .strip_prefix("$.")
+            .or_else(|| expr.strip_prefix('.'))
+            .unwrap_or(expr);
+        let expr = if expr == "$" { "" } else { expr };
+
+        let mut segments = Vec::new();
+        let mut chars = expr.chars().peekable();
+        let mut field = String::new();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '.' => {
+                    // A dot may only follow a field name or a closing bracket
+                    if field.is_empty() && matches!(segments.last(), None | Some(Segment::Field(_)))
+                    {
+                        return Err(format!("Invalid query '{}': empty field name", s));
+                    }
+                    push_field(&mut segments, &mut field);
+                }
+                '[' => {
+                    push_field(&mut segments, &mut field);
+                    let mut index = String::new();
+                    loop {
+                        match chars.next() {
+                            Some(']') => break,
+                            Some(c) => index.push(c),
+                            None => return Err(format!("Invalid query '{}': missing ']'", s)),
+                        }
+                    }
+                    let index = index.trim();
+                    if index.is_empty() || index == "*" {
+                        segments.push(Segment::Wildcard);
+                    } else {
+                        let n = index
+                            .parse::<i64>()
+                            .map_err(|_| format!("Invalid query '{}': bad index '{}'", s, index))?;
+                        segments.push(Segment::Index(n));
+                    }
+                }
+                ']' => return Err(format!("Invalid query '{}': unexpected ']'", s)),
+                _ => field.push(c),
+            }
+        }
+        push_field(&mut segments, &mut field);
+
+        Ok(Query { segments })
+    }
+}
+
+fn push_field(segments: &mut Vec<Segment>, field: &mut String) {
+    if field.is_empty() {
+        return;
+    }
+    let name = std::mem::take(field);
+    segments.push(match name.as_str() {
+        "*" => Segment::Wildcard,
+        _ => Segment::Field(name),
+    });
+}
+
+impl Query {
+    /// Evaluate the query, returning `null` when the path does not match
+    pub fn apply(&self, value: &Value) -> Value {
+        apply_segments(value, &self.segments)
+    }
+}
+
+fn apply_segments(value: &Value, segments: &[Segment]) -> Value {
+    let Some((segment, rest)) = segments.split_first() else {
+        return value.clone();
+    };
+
+    match segment {
+        Segment::Field(name) => match value {
+            Value::Object(map) => map
+                .get(name)
+                .map(|v| apply_segments(v, rest))
+                .unwrap_or(Value::Null),
+            // Numeric fields index into arrays, e.g. `transactions.0`
+            Value::Array(items) => name
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| items.get(i))
+                .map(|v| apply_segments(v, rest))
+                .unwrap_or(Value::Null),
+            _ => Value::Null,
+        },
+        Segment::Index(index) => match value {
+            Value::Array(items) => {
+                let position = if *index < 0 {
+                    items.len().checked_sub(index.unsigned_abs() as usize)
+                } else {
+                    Some(*index as usize)
+                };
+                position
+                    .and_then(|i| items.get(i))
+                    .map(|v| apply_segments(v, rest))
+                    .unwrap_or(Value::Null)
+            }
+            _ => Value::Null,
+        },
+        Segment::Wildcard => {
+            let items: Box<dyn Iterator<Item = &Value>> = match value {
+                Value::Array(items) => Box::new(items.iter()),
+                Value::Object(map) => Box::new(map.values()),
+                _ => return Value::Null,
+            };
+            Value::Array(
+                items
+                    .map(|v| apply_segments(v, rest))
+                    .filter(|v| !v.is_null())
+                    .collect(),
+            )
         }
     }
 }
@@ -33,7 +209,41 @@ struct ErrorEnvelope {
 }
 
 /// Print a successful result in the chosen format
+///
+/// With `--quiet` only the primary IDs are printed. With `--query` the
+/// filtered data is printed without the status envelope, and JSON strings
+/// are printed unquoted so they can be used directly in shell scripts.
 pub fn print_success<T: Serialize>(format: OutputFormat, data: &T) {
+    let options = options();
+
+    if options.quiet {
+        let json = serde_json::to_value(data).unwrap_or(Value::Null);
+        let json = match options.query {
+            Some(ref query) => query.apply(&json),
+            None => json,
+        };
+        for id in primary_ids(&json) {
+            println!("{}", id);
+        }
+        return;
+    }
+
+    if let Some(ref query) = options.query {
+        let json = query.apply(&serde_json::to_value(data).unwrap_or(Value::Null));
+        match format {
+            OutputFormat::Json => match json {
+                Value::String(s) => println!("{}", s),
+                other => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&other).unwrap_or_default()
+                ),
+            },
+            OutputFormat::Text => print_text_value(&json, 0),
+            OutputFormat::Table => print!("{}", render_table(&json)),
+        }
+        return;
+    }
+
     match format {
         OutputFormat::Json => {
             let envelope = SuccessEnvelope {
@@ -54,7 +264,25 @@ pub fn print_success<T: Serialize>(format: OutputFormat, data: &T) {
             let json = serde_json::to_value(data).unwrap_or(Value::Null);
             print_text_value(&json, 0);
         }
+        OutputFormat::Table => {
+            let json = serde_json::to_value(data).unwrap_or(Value::Null);
+            print!("{}", render_table(&json));
+        }
+    }
+}
+
+/// Print `text` in text format, and `data` as [`print_success`] does in the
+/// other formats
+///
+/// For results with a plain-text rendering of their own, such as trees.
+/// With `--quiet` or `--query`, `data` is printed in every format.
+pub fn print_rendered<T: Serialize>(format: OutputFormat, data: &T, text: &str) {
+    let options = options();
+    if format == OutputFormat::Text && !options.quiet && options.query.is_none() {
+        print!("{}", text);
+        return;
     }
+    print_success(format, data);
 }
 
 /// Print an error in the chosen format
@@ -73,12 +301,144 @@ pub fn print_error(format: OutputFormat, error: &str) {
                 ))
             );
         }
-        OutputFormat::Text => {
+        OutputFormat::Text | OutputFormat::Table => {
             eprintln!("Error: {}", error);
         }
     }
 }
 
+/// The records of a list response
+///
+/// List commands return either an array or an object with a single array
+/// field alongside scalar metadata such as `total`.
+fn list_rows(value: &Value) -> Option<&Vec<Value>> {
+    match value {
+        Value::Array(items) => Some(items),
+        Value::Object(map) => {
+            let mut arrays = map.values().filter_map(Value::as_array);
+            match (arrays.next(), arrays.next()) {
+                (Some(items), None) => Some(items),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Primary IDs of the records in `value`
+fn primary_ids(value: &Value) -> Vec<String> {
+    let primary_id = |record: &Value| match record {
+        Value::Object(map) => PRIMARY_ID_FIELDS
+            .iter()
+            .filter_map(|field| map.get(*field))
+            .find(|v| !v.is_null() && !v.is_object() && !v.is_array())
+            .map(format_scalar),
+        Value::Null => None,
+        other => Some(format_scalar(other)),
+    };
+
+    match list_rows(value) {
+        Some(rows) => rows.iter().filter_map(primary_id).collect(),
+        None => primary_id(value).into_iter().collect(),
+    }
+}
+
+/// Render `value` as an aligned text table
+///
+/// List responses get one row per record with a column per field, the
+/// primary ID first. Other objects are shown as field/value pairs.
+fn render_table(value: &Value) -> String {
+    let (headers, rows): (Vec<String>, Vec<Vec<String>>) = match list_rows(value) {
+        Some(records) if records.is_empty() => return "No results\n".to_string(),
+        Some(records) if records.iter().all(Value::is_object) => {
+            let mut columns: Vec<String> = Vec::new();
+            for record in records {
+                for key in record.as_object().into_iter().flat_map(|m| m.keys()) {
+                    if !columns.contains(key) {
+                        columns.push(key.clone());
+                    }
+                }
+            }
+            if let Some(pos) = PRIMARY_ID_FIELDS
+                .iter()
+                .find_map(|field| columns.iter().position(|c| c == field))
+            {
+                let id = columns.remove(pos);
+                columns.insert(0, id);
+            }
+
+            let rows = records
+                .iter()
+                .map(|record| {
+                    columns
+                        .iter()
+                        .map(|c| format_cell(record.get(c).unwrap_or(&Value::Null)))
+                        .collect()
+                })
+                .collect();
+            (columns, rows)
+        }
+        Some(records) => (
+            vec!["value".to_string()],
+            records.iter().map(|v| vec![format_cell(v)]).collect(),
+        ),
+        None => match value {
+            Value::Object(map) => (
+                vec!["field".to_string(), "value".to_string()],
+                map.iter()
+                    .map(|(k, v)| vec![k.clone(), format_cell(v)])
+                    .collect(),
+            ),
+            other => return format!("{}\n", format_scalar(other)),
+        },
+    };
+
+    let widths: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(i, header)| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .chain(std::iter::once(header.len()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let format_row = |cells: &[String]| {
+        let line = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        format!("{}\n", line.trim_end())
+    };
+
+    let mut table = format_row(&headers.iter().map(|h| h.to_uppercase()).collect::<Vec<_>>());
+    for row in &rows {
+        table.push_str(&format_row(row));
+    }
+    table
+}
+
+/// Format a table cell, truncating nested JSON so rows stay readable
+fn format_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Object(_) | Value::Array(_) => {
+            let text = value.to_string();
+            if text.chars().count() > MAX_CELL_WIDTH {
+                let truncated: String = text.chars().take(MAX_CELL_WIDTH - 3).collect();
+                format!("{}...", truncated)
+            } else {
+                text
+            }
+        }
+        _ => format_scalar(value).replace('\n', " "),
+    }
+}
+
 /// Recursively print a JSON value in human-readable text format
 fn print_text_value(value: &Value, indent: usize) {
     let pad = " ".repeat(indent);
@@ -117,3 +477,79 @@ fn format_scalar(value: &Value) -> String {
         _ => value.to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn transactions() -> Value {
+        json!({
+            "transactions": [
+                {"type": "Transfer", "id": "tx-1", "status": "pending", "amount": "10"},
+                {"type": "Payment", "id": "tx-2", "status": "settled", "memo": null}
+            ],
+            "total": 2
+        })
+    }
+
+    fn query(expr: &str) -> Query {
+        expr.parse().unwrap()
+    }
+
+    #[test]
+    fn test_query_paths() {
+        let data = transactions();
+        assert_eq!(query("total").apply(&data), json!(2));
+        assert_eq!(query("$.total").apply(&data), json!(2));
+        assert_eq!(query("transactions[0].id").apply(&data), json!("tx-1"));
+        assert_eq!(query("transactions.1.id").apply(&data), json!("tx-2"));
+        assert_eq!(
+            query("transactions[-1].status").apply(&data),
+            json!("settled")
+        );
+        assert_eq!(
+            query("transactions[*].id").apply(&data),
+            json!(["tx-1", "tx-2"])
+        );
+        assert_eq!(query("transactions[].amount").apply(&data), json!(["10"]));
+        assert_eq!(query("transactions[5]").apply(&data), Value::Null);
+        assert_eq!(query("missing.field").apply(&data), Value::Null);
+        assert_eq!(query("").apply(&data), data);
+
+        assert!("transactions[".parse::<Query>().is_err());
+        assert!("transactions[x]".parse::<Query>().is_err());
+        assert!("a..b".parse::<Query>().is_err());
+    }
+
+    #[test]
+    fn test_primary_ids() {
+        assert_eq!(primary_ids(&transactions()), vec!["tx-1", "tx-2"]);
+        assert_eq!(
+            primary_ids(&json!({"decision_id": 7, "status": "resolved"})),
+            vec!["7"]
+        );
+        assert_eq!(primary_ids(&json!(["a", "b"])), vec!["a", "b"]);
+        assert!(primary_ids(&json!({"keys": [], "total": 0})).is_empty());
+    }
+
+    #[test]
+    fn test_render_table() {
+        let table = render_table(&transactions());
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "ID    AMOUNT  STATUS   TYPE      MEMO");
+        assert_eq!(lines[1], "tx-1  10      pending  Transfer");
+        assert_eq!(lines[2], "tx-2          settled  Payment");
+
+        assert_eq!(render_table(&json!({"items": []})), "No results\n");
+
+        let single = render_table(&json!({"did": "did:key:z6Mk", "saved": true}));
+        assert_eq!(single, "FIELD  VALUE\ndid    did:key:z6Mk\nsaved  true\n");
+
+        let nested = render_table(&json!([{"id": "tx-1", "body": {"memo": "x".repeat(100)}}]));
+        let row = nested.lines().nth(1).unwrap();
+        assert!(row.starts_with("tx-1  {\"memo\":\"xxx"));
+        assert!(row.ends_with("..."));
+    }
+}
@@ -1,9 +1,9 @@
 use crate::error::{Error, Result};
 use std::path::PathBuf;
 use std::sync::Arc;
-use tap_agent::TapAgent;
+use tap_agent::{Agent, TapAgent};
 use tap_node::{NodeConfig, TapNode};
-use tracing::{debug, error, info};
+use tracing::{debug, info};
 
 /// TAP ecosystem integration - thin wrapper around TapNode
 pub struct TapIntegration {
@@ -31,6 +31,9 @@ impl TapIntegration {
         config.enable_message_logging = true;
         config.log_message_content = true;
 
+        // Register an agent for every stored key when storage is initialized
+        config.auto_register_stored_agents = true;
+
         let mut node = TapNode::new(config);
 
         node.init_storage().await.map_err(|e| {
@@ -41,7 +44,10 @@ impl TapIntegration {
 
         let node_arc = Arc::new(node);
 
-        if let Some(agent) = agent {
+        // Register the primary agent unless it was registered from key storage
+        if let Some(agent) =
+            agent.filter(|agent| !node_arc.agents().has_agent(agent.get_agent_did()))
+        {
             node_arc
                 .register_agent(agent)
                 .await
@@ -49,38 +55,6 @@ impl TapIntegration {
             info!("Registered primary agent with TAP Node");
         }
 
-        // Load and register all additional agents from storage
-        match tap_agent::storage::KeyStorage::load_default() {
-            Ok(storage) => {
-                let stored_dids: Vec<String> = storage.keys.keys().cloned().collect();
-                info!("Found {} total keys in storage", stored_dids.len());
-
-                for stored_did in &stored_dids {
-                    if agent_did.is_some_and(|did| stored_did == did) {
-                        continue;
-                    }
-
-                    info!("Registering additional agent: {}", stored_did);
-                    match TapAgent::from_stored_keys(Some(stored_did.clone()), true).await {
-                        Ok(additional_agent) => {
-                            let additional_agent_arc = Arc::new(additional_agent);
-                            if let Err(e) = node_arc.register_agent(additional_agent_arc).await {
-                                error!("Failed to register additional agent {}: {}", stored_did, e);
-                            } else {
-                                info!("Successfully registered additional agent: {}", stored_did);
-                            }
-                        }
-                        Err(e) => {
-                            error!("Failed to load additional agent {}: {}", stored_did, e);
-                        }
-                    }
-                }
-            }
-            Err(e) => {
-                debug!("Could not load additional keys from storage: {}", e);
-            }
-        }
-
         Ok(Self {
             node: node_arc,
             storage_path: None,
@@ -153,6 +127,29 @@ impl TapIntegration {
         }
     }
 
+    /// Read-only handle for listing commands, see
+    /// [`AgentStorageManager::get_agent_reader`](tap_node::storage::AgentStorageManager::get_agent_reader)
+    pub async fn reader_for_agent(
+        &self,
+        agent_did: &str,
+    ) -> Result<Arc<tap_node::storage::ReadOnlyStorage>> {
+        if let Some(storage_manager) = self.node.agent_storage_manager() {
+            storage_manager
+                .get_agent_reader(agent_did)
+                .await
+                .map_err(|e| {
+                    Error::configuration(format!(
+                        "Failed to get read-only storage for agent {}: {}",
+                        agent_did, e
+                    ))
+                })
+        } else {
+            Err(Error::configuration(
+                "Agent storage manager not available".to_string(),
+            ))
+        }
+    }
+
     pub async fn list_agents(&self) -> Result<Vec<AgentInfo>> {
         let mut agents = Vec::new();
 
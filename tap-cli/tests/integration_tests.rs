@@ -87,6 +87,13 @@ async fn test_output_format_text() {
     assert_eq!(format, OutputFormat::Text);
 }
 
+#[tokio::test]
+#[serial]
+async fn test_output_format_table() {
+    let format = "table".parse::<OutputFormat>().unwrap();
+    assert_eq!(format, OutputFormat::Table);
+}
+
 #[tokio::test]
 #[serial]
 async fn test_output_format_invalid() {
@@ -180,6 +187,7 @@ async fn test_reject_message_creation() {
 
     let reject = Reject {
         transaction_id: "test-tx-123".to_string(),
+        code: None,
         reason: Some("Compliance issue".to_string()),
     };
 
@@ -199,6 +207,7 @@ async fn test_settle_message_creation() {
         transaction_id: "test-tx-123".to_string(),
         settlement_id: Some("eip155:1:tx/0xabc123".to_string()),
         amount: None,
+        settled_asset: None,
     };
 
     assert!(settle.validate().is_ok());
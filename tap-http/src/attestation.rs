@@ -0,0 +1,190 @@
+//! Signed node identity attestations.
+//!
+//! A TAP HTTP server can publish an attestation at `/.well-known/tap-node`:
+//! metadata about the node (supported TAIPs and protocol versions, the agent
+//! DIDs accepting traffic, the node's endpoints and its optional subsystems)
+//! signed as a JWS by one of the node's agents. A counterparty fetches and verifies it with
+//! [`DIDCommClient::fetch_attestation`](crate::DIDCommClient::fetch_attestation)
+//! before initiating flows with the node, which binds the node's endpoints
+//! to a DID it can resolve.
+
+use crate::config::TapHttpConfig;
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tap_agent::key_manager::KeyManager;
+use tap_agent::message::JwsProtected;
+use tap_agent::{verify_jws_payload, Jws, JwsVerification, KeyPurpose, SyncDIDResolver};
+use tap_node::{NodeCapabilities, TapNode};
+
+/// Path of the attestation endpoint.
+pub const ATTESTATION_PATH: &str = "/.well-known/tap-node";
+
+/// JWS `typ` header of node attestations.
+pub const ATTESTATION_TYP: &str = "application/tap-node-attestation+jws";
+
+/// TAIPs implemented by this node.
+pub const SUPPORTED_TAIPS: &[&str] = &[
+    "TAIP-2", "TAIP-3", "TAIP-4", "TAIP-5", "TAIP-6", "TAIP-7", "TAIP-8", "TAIP-9", "TAIP-10",
+    "TAIP-11", "TAIP-12", "TAIP-13", "TAIP-14", "TAIP-15", "TAIP-16", "TAIP-17", "TAIP-18",
+    "TAIP-20",
+];
+
+/// Protocol versions spoken by this node.
+pub const SUPPORTED_PROTOCOLS: &[&str] = &["https://tap.rsvp/schema/1.0", "didcomm/v2"];
+
+/// Tolerated clock difference in seconds when checking an attestation's
+/// issue time.
+const CLOCK_SKEW_SECS: u64 = 300;
+
+/// Metadata a node attests to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeAttestation {
+    /// DID of the agent that signed the attestation.
+    pub signer: String,
+    /// DIDs of the agents accepting traffic at this node.
+    pub agents: Vec<String>,
+    /// Supported TAIPs, e.g. `TAIP-3`.
+    pub taips: Vec<String>,
+    /// Supported protocol versions.
+    pub protocols: Vec<String>,
+    /// Endpoints of the node.
+    pub endpoints: NodeEndpoints,
+    /// Software serving the node, e.g. `tap-http/0.7.0`.
+    pub software: String,
+    /// Optional subsystems active in the node, absent from attestations of
+    /// older nodes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<NodeCapabilities>,
+    /// Unix time in seconds the attestation was issued.
+    pub issued_at: u64,
+    /// Unix time in seconds after which the attestation must not be trusted.
+    pub expires_at: u64,
+}
+
+/// Endpoint URLs of a node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeEndpoints {
+    /// URL accepting DIDComm messages.
+    pub didcomm: String,
+    /// Base URL of the mailbox inbox for remote agents.
+    pub inbox: String,
+    /// Base URL of authorization callbacks.
+    pub authorization: String,
+}
+
+impl NodeAttestation {
+    /// Builds the attestation of a node.
+    ///
+    /// `base_url` is the scheme and authority the node is reached at, e.g.
+    /// `https://vasp.example.com`.
+    pub fn for_node(node: &TapNode, config: &TapHttpConfig, signer: &str, base_url: &str) -> Self {
+        let mut agents = node.list_agents();
+        agents.sort();
+        let base_url = base_url.trim_end_matches('/');
+        let issued_at = unix_now();
+
+        Self {
+            signer: signer.to_string(),
+            agents,
+            taips: SUPPORTED_TAIPS.iter().map(|t| t.to_string()).collect(),
+            protocols: SUPPORTED_PROTOCOLS.iter().map(|p| p.to_string()).collect(),
+            endpoints: NodeEndpoints {
+                didcomm: format!("{}{}", base_url, config.didcomm_endpoint),
+                inbox: format!("{}{}", base_url, config.inbox_endpoint),
+                authorization: format!("{}{}", base_url, config.authorization_endpoint),
+            },
+            software: format!("tap-http/{}", env!("CARGO_PKG_VERSION")),
+            capabilities: Some(node.capabilities()),
+            issued_at,
+            expires_at: issued_at + config.attestation_ttl_secs,
+        }
+    }
+
+    /// Whether the agent with `did` accepts traffic at this node.
+    pub fn accepts(&self, did: &str) -> bool {
+        self.agents.iter().any(|agent| agent == did)
+    }
+
+    /// Signs the attestation with the key of its signer, an agent registered
+    /// with `node`.
+    pub async fn sign(&self, node: &TapNode) -> Result<Jws> {
+        let agent = node
+            .agents()
+            .get_agent(&self.signer)
+            .await
+            .map_err(|e| Error::Node(format!("Attestation signer not found: {}", e)))?;
+        let kid = agent
+            .get_kid_for_purpose(KeyPurpose::Attestation)
+            .await
+            .map_err(|e| Error::Node(format!("Failed to get signing key: {}", e)))?;
+        let payload = serde_json::to_vec(self).map_err(|e| Error::Json(e.to_string()))?;
+        let protected = JwsProtected {
+            typ: ATTESTATION_TYP.to_string(),
+            alg: String::new(),
+            kid: kid.clone(),
+            zip: None,
+        };
+
+        let jws = agent
+            .key_manager()
+            .sign_jws(&kid, &payload, Some(protected))
+            .await
+            .map_err(|e| Error::Node(format!("Failed to sign attestation: {}", e)))?;
+        serde_json::from_str(&jws).map_err(|e| Error::Json(e.to_string()))
+    }
+}
+
+/// A node attestation whose signature has been verified.
+#[derive(Debug, Clone)]
+pub struct VerifiedAttestation {
+    /// The attested metadata.
+    pub attestation: NodeAttestation,
+    /// Details of the verified signature.
+    pub verification: JwsVerification,
+}
+
+/// Verifies a signed node attestation.
+///
+/// The signature must verify against the signer's resolved DID document and
+/// be made by the DID the attestation names as its signer. The attestation
+/// must not be expired or issued in the future.
+pub async fn verify_attestation(
+    jws: &Jws,
+    resolver: &dyn SyncDIDResolver,
+) -> Result<VerifiedAttestation> {
+    let (payload, verification) = verify_jws_payload(jws, resolver)
+        .await
+        .map_err(|e| Error::Authentication(format!("Invalid attestation signature: {}", e)))?;
+    let attestation: NodeAttestation = serde_json::from_slice(&payload)
+        .map_err(|e| Error::Validation(format!("Invalid attestation: {}", e)))?;
+
+    if verification.signer_did != attestation.signer {
+        return Err(Error::Authentication(format!(
+            "Attestation for {} is signed by {}",
+            attestation.signer, verification.signer_did
+        )));
+    }
+
+    let now = unix_now();
+    if attestation.expires_at <= now {
+        return Err(Error::Validation("Attestation has expired".to_string()));
+    }
+    if attestation.issued_at > now + CLOCK_SKEW_SECS {
+        return Err(Error::Validation(
+            "Attestation is issued in the future".to_string(),
+        ));
+    }
+
+    Ok(VerifiedAttestation {
+        attestation,
+        verification,
+    })
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
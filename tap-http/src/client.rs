@@ -1,8 +1,10 @@
 //! HTTP client for delivering DIDComm messages to external endpoints.
 
+use crate::attestation::{verify_attestation, VerifiedAttestation, ATTESTATION_PATH};
 use crate::error::{Error, Result};
 use reqwest::{Client as ReqwestClient, StatusCode};
 use std::time::Duration;
+use tap_agent::{Jws, SyncDIDResolver};
 use tokio::time::timeout;
 use tracing::{debug, error, info};
 
@@ -86,6 +88,68 @@ impl DIDCommClient {
             }
         }
     }
+
+    /// Fetches and verifies the signed attestation of a TAP node.
+    ///
+    /// `base_url` is the scheme and authority of the node, e.g.
+    /// `https://vasp.example.com`. Besides the checks of
+    /// [`verify_attestation`], the attested DIDComm endpoint must be on the
+    /// host the attestation was fetched from, so an attestation copied from
+    /// another node is refused. Use [`NodeAttestation::accepts`] on the
+    /// result to check that the counterparty's agent is served by the node.
+    ///
+    /// [`NodeAttestation::accepts`]: crate::attestation::NodeAttestation::accepts
+    pub async fn fetch_attestation(
+        &self,
+        base_url: &str,
+        resolver: &dyn SyncDIDResolver,
+    ) -> Result<VerifiedAttestation> {
+        let url = format!("{}{}", base_url.trim_end_matches('/'), ATTESTATION_PATH);
+        info!("Fetching node attestation from {}", url);
+
+        let request_timeout = Duration::from_secs(self.timeout_secs);
+        let response = match timeout(request_timeout, self.client.get(&url).send()).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(e)) => return Err(Error::Http(format!("Failed to fetch attestation: {}", e))),
+            Err(_) => {
+                return Err(Error::Http(format!(
+                    "Request timed out after {} seconds",
+                    self.timeout_secs
+                )))
+            }
+        };
+        if response.status() != StatusCode::OK {
+            return Err(Error::Http(format!(
+                "Failed to fetch attestation: Status {}",
+                response.status()
+            )));
+        }
+
+        let jws: Jws = response
+            .json()
+            .await
+            .map_err(|e| Error::Json(format!("Invalid attestation: {}", e)))?;
+        let verified = verify_attestation(&jws, resolver).await?;
+
+        let host = |url: &str| {
+            reqwest::Url::parse(url)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_lowercase))
+        };
+        let fetched_host = host(&url);
+        if fetched_host.is_none() || host(&verified.attestation.endpoints.didcomm) != fetched_host {
+            return Err(Error::Authentication(format!(
+                "Attested DIDComm endpoint {} is not served by {}",
+                verified.attestation.endpoints.didcomm, base_url
+            )));
+        }
+
+        debug!(
+            "Verified attestation of {} signed by {}",
+            base_url, verified.attestation.signer
+        );
+        Ok(verified)
+    }
 }
 
 impl Default for DIDCommClient {
@@ -2,6 +2,7 @@
 
 use crate::event::EventLoggerConfig;
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 use std::time::Duration;
 
 /// Configuration for the TAP HTTP server.
@@ -16,9 +17,47 @@ pub struct TapHttpConfig {
     /// The endpoint path for receiving DIDComm messages.
     pub didcomm_endpoint: String,
 
-    /// Optional rate limiting configuration.
+    /// The endpoint path under which authorization URLs are redeemed.
+    /// Each URL is this path followed by a nonce issued by the node.
+    pub authorization_endpoint: String,
+
+    /// The endpoint path under which remote agents with a mailbox poll for
+    /// their messages (`{inbox_endpoint}/poll`) and acknowledge them
+    /// (`{inbox_endpoint}/ack`).
+    pub inbox_endpoint: String,
+
+    /// Longest time in seconds an inbox poll waits for a message to arrive.
+    pub inbox_max_wait_secs: u64,
+
+    /// The endpoint path under which wallets list the actions awaiting
+    /// approval and approve (`{actions_endpoint}/{id}/approve`) or reject
+    /// (`{actions_endpoint}/{id}/reject`) them.
+    pub actions_endpoint: String,
+
+    /// Bearer token authenticating requests to the actions endpoint. The
+    /// endpoint is only served when a token is set.
+    #[serde(skip_serializing)]
+    pub actions_token: Option<String>,
+
+    /// Optional per-IP rate limiting configuration.
     pub rate_limit: Option<RateLimitConfig>,
 
+    /// Addresses or CIDR ranges allowed to connect. When empty, every
+    /// address that is not denied may connect.
+    pub ip_allowlist: Vec<String>,
+
+    /// Addresses or CIDR ranges refused a connection. Takes precedence over
+    /// the allowlist.
+    pub ip_denylist: Vec<String>,
+
+    /// Maximum number of concurrent connections. Connections beyond the
+    /// limit are closed as soon as they are accepted.
+    pub max_connections: Option<usize>,
+
+    /// Time in seconds a client has to send request headers before its
+    /// connection is closed, or 0 for no limit.
+    pub header_read_timeout_secs: u64,
+
     /// Optional TLS configuration.
     pub tls: Option<TlsConfig>,
 
@@ -38,12 +77,23 @@ pub struct TapHttpConfig {
     /// Maximum number of agents that can be auto-created via the web DID endpoint.
     /// Prevents denial-of-service via unbounded agent creation.
     pub max_agents: usize,
+
+    /// Enable the `/.well-known/tap-node` endpoint serving a signed
+    /// attestation of the node's metadata.
+    pub enable_attestation: bool,
+
+    /// DID of the agent signing the node attestation. When unset, the
+    /// registered agent whose DID sorts first signs it.
+    pub attestation_signer: Option<String>,
+
+    /// Time in seconds a node attestation remains valid after it is issued.
+    pub attestation_ttl_secs: u64,
 }
 
-/// Configuration for rate limiting.
+/// Configuration for per-IP rate limiting.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RateLimitConfig {
-    /// Maximum number of requests per window.
+    /// Maximum number of requests from one IP address per window.
     pub max_requests: u32,
 
     /// Time window in seconds.
@@ -51,13 +101,81 @@ pub struct RateLimitConfig {
 }
 
 /// Configuration for TLS.
+///
+/// The certificate and key are reloaded when their files change or the
+/// process receives SIGHUP. Connections accepted before a reload keep the
+/// certificate they were established with.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TlsConfig {
-    /// Path to the certificate file.
+    /// Path to the PEM certificate chain file.
     pub cert_path: String,
 
-    /// Path to the key file.
+    /// Path to the PEM private key file.
     pub key_path: String,
+
+    /// Lowest TLS version clients may negotiate.
+    #[serde(default)]
+    pub min_version: TlsVersion,
+
+    /// Cipher suites offered to clients, by their IANA names such as
+    /// `TLS13_AES_256_GCM_SHA384`. When empty, the defaults of rustls are
+    /// offered.
+    #[serde(default)]
+    pub cipher_suites: Vec<String>,
+
+    /// Interval in seconds at which the certificate and key files are
+    /// checked for changes, or 0 to reload them only on SIGHUP.
+    #[serde(default = "default_tls_reload_interval")]
+    pub reload_interval_secs: u64,
+}
+
+fn default_tls_reload_interval() -> u64 {
+    60
+}
+
+impl TlsConfig {
+    /// Creates a TLS configuration with the given certificate chain and key
+    /// files, TLS 1.2 as the lowest version and the default cipher suites.
+    pub fn new(cert_path: impl Into<String>, key_path: impl Into<String>) -> Self {
+        Self {
+            cert_path: cert_path.into(),
+            key_path: key_path.into(),
+            min_version: TlsVersion::default(),
+            cipher_suites: Vec::new(),
+            reload_interval_secs: default_tls_reload_interval(),
+        }
+    }
+
+    /// Returns the interval at which the certificate and key files are
+    /// checked for changes, if they are checked.
+    pub fn reload_interval(&self) -> Option<Duration> {
+        (self.reload_interval_secs > 0).then(|| Duration::from_secs(self.reload_interval_secs))
+    }
+}
+
+/// A TLS protocol version.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TlsVersion {
+    /// TLS 1.2
+    #[default]
+    #[serde(rename = "1.2")]
+    Tls12,
+
+    /// TLS 1.3
+    #[serde(rename = "1.3")]
+    Tls13,
+}
+
+impl FromStr for TlsVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "1.2" => Ok(TlsVersion::Tls12),
+            "1.3" => Ok(TlsVersion::Tls13),
+            _ => Err(format!("Unsupported TLS version {} (use 1.2 or 1.3)", s)),
+        }
+    }
 }
 
 impl Default for TapHttpConfig {
@@ -66,12 +184,24 @@ impl Default for TapHttpConfig {
             host: "127.0.0.1".to_string(),
             port: 8000,
             didcomm_endpoint: "/didcomm".to_string(),
+            authorization_endpoint: "/authorize".to_string(),
+            inbox_endpoint: "/inbox".to_string(),
+            inbox_max_wait_secs: 30,
+            actions_endpoint: "/actions".to_string(),
+            actions_token: None,
             rate_limit: None,
+            ip_allowlist: Vec::new(),
+            ip_denylist: Vec::new(),
+            max_connections: None,
+            header_read_timeout_secs: 10,
             tls: None,
             request_timeout_secs: 30,
             event_logger: Some(EventLoggerConfig::default()),
             enable_web_did: false,
             max_agents: 100,
+            enable_attestation: false,
+            attestation_signer: None,
+            attestation_ttl_secs: 3600,
         }
     }
 }
@@ -95,6 +225,31 @@ impl TapHttpConfig {
         )
     }
 
+    /// Returns the base URL for authorization callbacks, to pass as
+    /// `callback_base_url` when requesting authorization from the node.
+    pub fn authorization_url(&self, secure: bool) -> String {
+        let protocol = if secure || self.tls.is_some() {
+            "https"
+        } else {
+            "http"
+        };
+        format!(
+            "{}://{}:{}{}",
+            protocol, self.host, self.port, self.authorization_endpoint
+        )
+    }
+
+    /// Returns the longest inbox poll wait as a Duration.
+    pub fn inbox_max_wait(&self) -> Duration {
+        Duration::from_secs(self.inbox_max_wait_secs)
+    }
+
+    /// Returns the header read timeout as a Duration, if one is set.
+    pub fn header_read_timeout(&self) -> Option<Duration> {
+        (self.header_read_timeout_secs > 0)
+            .then(|| Duration::from_secs(self.header_read_timeout_secs))
+    }
+
     /// Returns the request timeout as a Duration.
     pub fn request_timeout(&self) -> Duration {
         Duration::from_secs(self.request_timeout_secs)
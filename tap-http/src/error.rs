@@ -4,7 +4,9 @@
 //! It defines error types for various failure scenarios and provides conversions
 //! from common error types to the tap-http error type.
 
+use serde::Serialize;
 use thiserror::Error;
+use utoipa::ToSchema;
 use warp::Reply;
 
 /// Result type for tap-http operations.
@@ -118,19 +120,36 @@ impl Error {
         };
 
         warp::reply::with_status(
-            warp::reply::json(&serde_json::json!({
-                "status": "error",
-                "error": {
-                    "type": error_type,
-                    "message": message,
-                }
-            })),
+            warp::reply::json(&ErrorResponse {
+                status: "error".to_string(),
+                error: ErrorDetail {
+                    error_type: error_type.to_string(),
+                    message,
+                },
+            }),
             status,
         )
         .into_response()
     }
 }
 
+/// Body of an error response.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorResponse {
+    /// Always `error`
+    pub status: String,
+    pub error: ErrorDetail,
+}
+
+/// Kind and description of an error.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorDetail {
+    /// Kind of error, e.g. `validation_error`
+    #[serde(rename = "type")]
+    pub error_type: String,
+    pub message: String,
+}
+
 impl From<serde_json::Error> for Error {
     fn from(err: serde_json::Error) -> Self {
         Error::Json(err.to_string())
@@ -58,6 +58,8 @@ use serde_json::json;
 use tracing::{debug, error, info, trace, warn, Level};
 use warp::hyper::StatusCode;
 
+use crate::protection::BlockReason;
+
 /// HTTP server event types
 ///
 /// Represents the various events that can occur within the TAP HTTP server,
@@ -116,6 +118,14 @@ pub enum HttpEvent {
         /// The message ID (if available)
         message_id: Option<String>,
     },
+
+    /// Connection or request blocked by the server's protections
+    RequestBlocked {
+        /// The client IP address
+        client_ip: String,
+        /// Why the connection or request was blocked
+        reason: BlockReason,
+    },
 }
 
 /// Configuration for where event logs should be sent
@@ -361,6 +371,12 @@ impl EventBus {
         self.publish_event(event).await;
     }
 
+    /// Publish a request blocked event
+    pub async fn publish_request_blocked(&self, client_ip: String, reason: BlockReason) {
+        let event = HttpEvent::RequestBlocked { client_ip, reason };
+        self.publish_event(event).await;
+    }
+
     /// Publish an event to all subscribers
     async fn publish_event(&self, event: HttpEvent) {
         // Notify subscribers
@@ -542,6 +558,12 @@ impl EventLogger {
                     message_id.as_deref().unwrap_or("unknown")
                 )
             }
+            HttpEvent::RequestBlocked { client_ip, reason } => {
+                format!(
+                    "[{}] REQUEST BLOCKED: client_ip={}, reason={}",
+                    timestamp, client_ip, reason
+                )
+            }
         }
     }
 
@@ -611,6 +633,13 @@ impl EventLogger {
                     "message_id": message_id,
                 }),
             ),
+            HttpEvent::RequestBlocked { client_ip, reason } => (
+                "request_blocked",
+                json!({
+                    "client_ip": client_ip,
+                    "reason": reason.as_str(),
+                }),
+            ),
         };
 
         // Combine into a single JSON object
@@ -6,35 +6,102 @@
 //! The handlers leverage the TAP Node for message processing, which handles message validation,
 //! verification, and routing through the appropriate agent.
 
+use crate::attestation::{NodeAttestation, ATTESTATION_PATH};
+use crate::config::TapHttpConfig;
 use crate::error::{Error, Result};
 use crate::event::EventBus;
+use crate::protection::{ProtectionMetrics, ProtectionStats};
 use bytes::Bytes;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::convert::Infallible;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tap_agent::did::{DIDGenerationOptions, KeyType, Service};
 use tap_agent::key_manager::KeyManager;
 use tap_agent::{AgentConfig, AgentKeyManager, TapAgent};
-use tap_node::TapNode;
+use tap_node::federation::parse_forwarded_by;
+use tap_node::pending_actions::{
+    PendingAction, PendingActionFilter, PendingActionKind, PendingActionResponse,
+};
+use tap_node::storage::SourceType;
+use tap_node::{IngestOutcome, RejectionCode, TapNode};
 use tracing::{debug, error, info, warn};
+use utoipa::{IntoParams, ToSchema};
 use warp::{self, hyper::StatusCode, reply::json, Reply};
 
 /// Response structure for health checks.
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct HealthResponse {
     /// Status of the server, always "ok" when reachable
     status: String,
     /// Current version of the tap-http package
     version: String,
+    /// Database size of each registered agent
+    storage: Vec<StorageHealth>,
+    /// Connections and requests handled by the server's protections
+    protection: ProtectionStats,
+}
+
+/// Database size and quota of an agent, reported by health checks.
+#[derive(Serialize, ToSchema)]
+struct StorageHealth {
+    agent_did: String,
+    size_bytes: u64,
+    /// Quota in bytes, if one is configured
+    quota_bytes: Option<u64>,
+    quota_exceeded: bool,
+}
+
+/// Collect the database size of each registered agent.
+async fn storage_health(node: &TapNode) -> Vec<StorageHealth> {
+    let Some(storage_manager) = node.agent_storage_manager() else {
+        return Vec::new();
+    };
+
+    let mut agent_dids = node.list_agents();
+    agent_dids.sort();
+
+    let mut report = Vec::with_capacity(agent_dids.len());
+    for agent_did in agent_dids {
+        let storage = match storage_manager.get_agent_storage(&agent_did).await {
+            Ok(storage) => storage,
+            Err(e) => {
+                warn!("Failed to open storage for agent {}: {}", agent_did, e);
+                continue;
+            }
+        };
+        match storage.size_bytes().await {
+            Ok(size_bytes) => {
+                let quota_bytes = storage.quota().map(|q| q.max_bytes);
+                report.push(StorageHealth {
+                    agent_did,
+                    size_bytes,
+                    quota_bytes,
+                    quota_exceeded: quota_bytes.is_some_and(|max| size_bytes > max),
+                });
+            }
+            Err(e) => warn!("Failed to measure storage for agent {}: {}", agent_did, e),
+        }
+    }
+    report
 }
 
 /// Handler for health check requests.
 ///
-/// Returns a simple response with the status "ok" and the current version number.
+/// Returns a simple response with the status "ok", the current version number,
+/// the database size of each registered agent against its storage quota and the
+/// counters of blocked connections and requests.
 /// This endpoint allows monitoring systems to verify that the TAP HTTP server is operational.
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "node",
+    responses((status = 200, description = "The server is operational", body = HealthResponse))
+)]
 pub async fn handle_health_check(
+    node: Arc<TapNode>,
+    protection_metrics: Arc<ProtectionMetrics>,
     event_bus: Arc<EventBus>,
 ) -> std::result::Result<impl Reply, Infallible> {
     info!("Health check request received");
@@ -55,6 +122,8 @@ pub async fn handle_health_check(
     let response = HealthResponse {
         status: "ok".to_string(),
         version: env!("CARGO_PKG_VERSION").to_string(),
+        storage: storage_health(&node).await,
+        protection: protection_metrics.snapshot(),
     };
 
     // Convert response to JSON
@@ -85,11 +154,47 @@ pub async fn handle_health_check(
 /// 4. Forwarding the message to the TAP Node for further processing
 ///
 /// The handler returns appropriate success or error responses based on the outcome.
+#[utoipa::path(
+    post,
+    path = "/didcomm",
+    tag = "didcomm",
+    params(("TAP-Forwarded-By" = Option<String>, Header, description = "Comma-separated IDs of the nodes of a federation that forwarded the message, in order")),
+    request_body(
+        description = "A signed or encrypted DIDComm message, or a message pickup request",
+        content(
+            (Object = "application/didcomm-signed+json"),
+            (Object = "application/didcomm-encrypted+json")
+        )
+    ),
+    responses(
+        (status = 202, description = "The message was processed or queued for processing", body = IngestResponse),
+        (status = 200, description = "Messages delivered on the return route of a pickup request", body = Object, content_type = "application/didcomm-signed+json"),
+        (status = 400, description = "The message was rejected as malformed, invalid or unverified; sending it again will not help. A plain message or wrong content type is answered with an `ErrorResponse` instead", body = IngestResponse),
+        (status = 429, description = "The node is overloaded; send the message again after the `Retry-After` delay", body = IngestResponse,
+            headers(("Retry-After" = u64, description = "Seconds to wait before sending the message again"))),
+        (status = 502, description = "The message is for none of the node's agents and its upstream node could not take it; send the message again later", body = IngestResponse),
+        (status = 500, description = "The message could not be processed", body = StatusResponse)
+    )
+)]
 pub async fn handle_didcomm(
     content_type: Option<String>,
     body: Bytes,
     node: Arc<TapNode>,
     event_bus: Arc<EventBus>,
+) -> std::result::Result<impl Reply, Infallible> {
+    handle_forwarded_didcomm(content_type, None, body, node, event_bus).await
+}
+
+/// Handler for DIDComm messages that may have been forwarded by other nodes.
+///
+/// Like [`handle_didcomm`], passing the nodes named in the `TAP-Forwarded-By`
+/// header to the TAP Node so that it does not forward the message in a loop.
+pub async fn handle_forwarded_didcomm(
+    content_type: Option<String>,
+    forwarded_by: Option<String>,
+    body: Bytes,
+    node: Arc<TapNode>,
+    event_bus: Arc<EventBus>,
 ) -> std::result::Result<impl Reply, Infallible> {
     // Start timing the request
     let start_time = Instant::now();
@@ -135,7 +240,8 @@ pub async fn handle_didcomm(
         }
     };
 
-    let message_value: serde_json::Value = match serde_json::from_str(message_str) {
+    // Compact JWS and JWE are converted to their JSON serializations
+    let message_value: serde_json::Value = match tap_agent::message::parse_envelope(message_str) {
         Ok(v) => v,
         Err(e) => {
             error!("Failed to parse message as JSON: {}", e);
@@ -152,19 +258,84 @@ pub async fn handle_didcomm(
         }
     };
 
-    // Let the node handle routing
-    match node.receive_message(message_value).await {
-        Ok(_) => {
-            info!("DIDComm message processed successfully");
+    // Answer message pickup requests on the return route
+    match node.receive_pickup_request(&message_value).await {
+        Ok(Some(reply)) => {
+            info!("Answered message pickup request");
+
+            let response_size = reply.len();
+            let response = warp::reply::with_header(
+                warp::reply::with_status(reply, StatusCode::OK),
+                "content-type",
+                "application/didcomm-signed+json",
+            )
+            .into_response();
+            let duration_ms = start_time.elapsed().as_millis() as u64;
+
+            event_bus
+                .publish_response_sent(StatusCode::OK, response_size, duration_ms)
+                .await;
+
+            return Ok(response);
+        }
+        Ok(None) => {}
+        Err(e) => {
+            warn!("Rejected message pickup request: {}", e);
+
+            let response =
+                json_error_response(StatusCode::BAD_REQUEST, "Invalid message pickup request");
+            let duration_ms = start_time.elapsed().as_millis() as u64;
+
+            event_bus
+                .publish_response_sent(StatusCode::BAD_REQUEST, 200, duration_ms)
+                .await;
+
+            return Ok(response);
+        }
+    }
+
+    // Let the node handle routing. Compact envelopes are passed on as
+    // received so the node records their serialization.
+    let compact = message_str.trim();
+    let message_value = if tap_agent::message::compact_to_json(compact).is_some() {
+        serde_json::Value::String(compact.to_string())
+    } else {
+        message_value
+    };
+    let forwarded_by = forwarded_by
+        .as_deref()
+        .map(parse_forwarded_by)
+        .unwrap_or_default();
+    match node
+        .receive_forwarded_message(message_value, SourceType::Internal, None, forwarded_by)
+        .await
+    {
+        Ok(outcome) => {
+            match &outcome {
+                IngestOutcome::Accepted { .. } => info!("DIDComm message processed successfully"),
+                IngestOutcome::Queued { position } => {
+                    info!("DIDComm message queued at position {}", position)
+                }
+                IngestOutcome::Forwarded { .. } => {
+                    info!("DIDComm message forwarded to the upstream node")
+                }
+                IngestOutcome::Rejected { code, reason } => {
+                    warn!("DIDComm message rejected ({}): {}", code, reason);
+
+                    event_bus
+                        .publish_message_error(code.to_string(), reason.clone(), None)
+                        .await;
+                }
+            }
 
             // Calculate response size and duration
-            let response = json_success_response();
+            let (status, response) = ingest_response(&outcome);
             let response_size = 100; // Approximate size
             let duration_ms = start_time.elapsed().as_millis() as u64;
 
             // Log response sent event
             event_bus
-                .publish_response_sent(StatusCode::ACCEPTED, response_size, duration_ms)
+                .publish_response_sent(status, response_size, duration_ms)
                 .await;
 
             Ok(response)
@@ -228,6 +399,9 @@ fn validate_message_security(content_type: Option<&str>) -> Result<()> {
             } else if ct_lower.contains("application/didcomm-encrypted+json") {
                 debug!("Message security validation passed: encrypted message");
                 Ok(())
+            } else if ct_lower.contains("application/jose") {
+                debug!("Message security validation passed: JOSE message");
+                Ok(())
             } else if ct_lower.contains("application/didcomm-plain+json") {
                 Err(Error::Validation(
                     "Plain DIDComm messages are not allowed for security reasons. Only signed or encrypted messages are accepted.".to_string()
@@ -246,18 +420,772 @@ fn validate_message_security(content_type: Option<&str>) -> Result<()> {
     }
 }
 
-/// Create a JSON success response.
+/// Handler for authorization callback requests.
 ///
-/// Returns a standardized success response with a 202 Accepted status code.
-fn json_success_response() -> warp::reply::Response {
-    warp::reply::with_status(
-        json(&json!({
-            "status": "success",
-            "message": "Message received and processed"
-        })),
-        StatusCode::ACCEPTED,
+/// Redeems the nonce bound to an authorization URL sent in an
+/// AuthorizationRequired message. The node then sends Authorize for the
+/// transaction on behalf of the agent that issued the URL. Unknown nonces
+/// return 404 and nonces that were already used or have expired return 410.
+#[utoipa::path(
+    method(get, post),
+    path = "/authorize/{nonce}",
+    tag = "authorization",
+    params(("nonce" = String, Path, description = "Nonce of the authorization URL")),
+    responses(
+        (status = 200, description = "Authorize was sent for the transaction", body = AuthorizationResponse),
+        (status = 404, description = "Unknown authorization request", body = StatusResponse),
+        (status = 410, description = "The authorization URL was already used or has expired", body = StatusResponse),
+        (status = 500, description = "The authorization could not be completed", body = StatusResponse)
     )
-    .into_response()
+)]
+pub async fn handle_authorization_callback(
+    nonce: String,
+    method: warp::http::Method,
+    node: Arc<TapNode>,
+    event_bus: Arc<EventBus>,
+) -> std::result::Result<impl Reply, Infallible> {
+    let start_time = Instant::now();
+
+    event_bus
+        .publish_request_received(method.to_string(), "/authorize".to_string(), None)
+        .await;
+
+    let known = match node.storage() {
+        Some(storage) => storage.get_authorization_challenge(&nonce).await,
+        None => Ok(None),
+    };
+    let (status, response) = match known {
+        Ok(Some(_)) => match node.redeem_authorization(&nonce).await {
+            Ok(challenge) => {
+                info!(
+                    "Authorization redeemed for transaction {} by agent {}",
+                    challenge.transaction_id, challenge.agent_did
+                );
+                let response = warp::reply::with_status(
+                    json(&AuthorizationResponse {
+                        status: "success".to_string(),
+                        transaction_id: challenge.transaction_id,
+                        message_id: challenge.message_id,
+                    }),
+                    StatusCode::OK,
+                )
+                .into_response();
+                (StatusCode::OK, response)
+            }
+            Err(tap_node::Error::Validation(message)) => {
+                warn!("Authorization callback rejected: {}", message);
+                (
+                    StatusCode::GONE,
+                    json_error_response(StatusCode::GONE, &message),
+                )
+            }
+            Err(e) => {
+                error!("Failed to redeem authorization: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    json_error_response(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Failed to complete authorization",
+                    ),
+                )
+            }
+        },
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            json_error_response(StatusCode::NOT_FOUND, "Unknown authorization request"),
+        ),
+        Err(e) => {
+            error!("Failed to look up authorization request: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                json_error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to complete authorization",
+                ),
+            )
+        }
+    };
+
+    let duration_ms = start_time.elapsed().as_millis() as u64;
+    event_bus
+        .publish_response_sent(status, 200, duration_ms)
+        .await;
+
+    Ok(response)
+}
+
+/// Default number of messages returned by an inbox poll.
+const DEFAULT_INBOX_LIMIT: u32 = 50;
+
+/// Maximum number of messages returned by an inbox poll.
+const MAX_INBOX_LIMIT: u32 = 500;
+
+/// Query parameters of an inbox poll.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct InboxPollQuery {
+    /// Sequence number of the last message received
+    #[serde(default)]
+    pub after: i64,
+    /// Maximum number of messages to return
+    pub limit: Option<u32>,
+    /// Seconds to wait for a message when none is held
+    pub wait: Option<u64>,
+}
+
+/// Body of an inbox acknowledgement.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct InboxAck {
+    /// Sequence number of the last message processed
+    pub cursor: i64,
+}
+
+/// A message returned by an inbox poll.
+#[derive(Serialize, ToSchema)]
+struct InboxMessage {
+    /// Sequence number to acknowledge the message with
+    seq: i64,
+    /// The held DIDComm message
+    #[schema(value_type = Object)]
+    message: serde_json::Value,
+}
+
+/// Response to an inbox poll.
+#[derive(Serialize, ToSchema)]
+struct InboxPollResponse {
+    messages: Vec<InboxMessage>,
+}
+
+/// Response to an inbox acknowledgement.
+#[derive(Serialize, ToSchema)]
+struct InboxAckResponse {
+    /// Always `success`
+    status: String,
+    /// Number of messages marked as delivered
+    acknowledged: usize,
+}
+
+/// Response to a redeemed authorization URL.
+#[derive(Serialize, ToSchema)]
+struct AuthorizationResponse {
+    /// Always `success`
+    status: String,
+    /// Transaction that was authorized
+    transaction_id: String,
+    /// ID of the Authorize message sent
+    message_id: Option<String>,
+}
+
+/// What became of a received DIDComm message.
+#[derive(Serialize, ToSchema)]
+struct IngestResponse {
+    /// `success` if the message was accepted, queued or forwarded, `error`
+    /// if it was rejected
+    status: String,
+    message: String,
+    /// `accepted`, `queued`, `forwarded` or `rejected`
+    outcome: String,
+    /// ID of the accepted or forwarded message, unless it was encrypted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message_id: Option<String>,
+    /// Place of the queued message in the node's queue, starting at 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position: Option<usize>,
+    /// Why the message was rejected: `malformed`, `invalid`, `unverified`,
+    /// `overloaded` or `upstream_unavailable`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<String>,
+}
+
+/// Outcome of a request, with a description.
+#[derive(Serialize, ToSchema)]
+struct StatusResponse {
+    /// `success` or `error`
+    status: String,
+    message: String,
+}
+
+/// Resolve the remote agent a bearer token belongs to.
+fn authenticate_inbox(node: &TapNode, authorization: Option<&str>) -> Option<String> {
+    authorization
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .and_then(|token| node.authenticate_mailbox(token.trim()))
+}
+
+/// Response to an inbox request without a valid bearer token.
+fn bearer_unauthorized() -> (StatusCode, warp::reply::Response) {
+    (
+        StatusCode::UNAUTHORIZED,
+        json_error_response(StatusCode::UNAUTHORIZED, "Invalid or missing bearer token"),
+    )
+}
+
+/// Handler for inbox polls.
+///
+/// Returns the messages held for the authenticated remote agent after the
+/// `after` sequence number as `{"messages": [{"seq": 3, "message": {...}}]}`.
+/// When none is held, the request waits up to `wait` seconds, capped at
+/// `max_wait`, for one to arrive. Messages are returned again until they
+/// are acknowledged.
+#[utoipa::path(
+    get,
+    path = "/inbox/poll",
+    tag = "inbox",
+    params(InboxPollQuery),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Messages held for the agent", body = InboxPollResponse),
+        (status = 401, description = "Invalid or missing bearer token", body = StatusResponse),
+        (status = 500, description = "The messages could not be fetched", body = StatusResponse)
+    )
+)]
+pub async fn handle_inbox_poll(
+    authorization: Option<String>,
+    query: InboxPollQuery,
+    max_wait: Duration,
+    node: Arc<TapNode>,
+    event_bus: Arc<EventBus>,
+) -> std::result::Result<impl Reply, Infallible> {
+    let start_time = Instant::now();
+
+    event_bus
+        .publish_request_received("GET".to_string(), "/inbox/poll".to_string(), None)
+        .await;
+
+    let (status, response) = match authenticate_inbox(&node, authorization.as_deref()) {
+        Some(recipient_did) => {
+            let limit = query
+                .limit
+                .unwrap_or(DEFAULT_INBOX_LIMIT)
+                .clamp(1, MAX_INBOX_LIMIT);
+            let wait = query
+                .wait
+                .map(Duration::from_secs)
+                .map_or(max_wait, |wait| wait.min(max_wait));
+
+            match node
+                .poll_mailbox(&recipient_did, query.after, limit, wait)
+                .await
+            {
+                Ok(held) => {
+                    debug!(
+                        "Returning {} inbox messages to {}",
+                        held.len(),
+                        recipient_did
+                    );
+                    let messages: Vec<InboxMessage> = held
+                        .into_iter()
+                        .map(|m| InboxMessage {
+                            seq: m.seq,
+                            message: serde_json::from_str(&m.message_text)
+                                .unwrap_or(serde_json::Value::String(m.message_text)),
+                        })
+                        .collect();
+                    (
+                        StatusCode::OK,
+                        warp::reply::with_status(
+                            json(&InboxPollResponse { messages }),
+                            StatusCode::OK,
+                        )
+                        .into_response(),
+                    )
+                }
+                Err(e) => {
+                    error!("Failed to poll inbox of {}: {}", recipient_did, e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        json_error_response(
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            "Failed to fetch messages",
+                        ),
+                    )
+                }
+            }
+        }
+        None => {
+            warn!("Inbox poll rejected: invalid or missing bearer token");
+            bearer_unauthorized()
+        }
+    };
+
+    let duration_ms = start_time.elapsed().as_millis() as u64;
+    event_bus
+        .publish_response_sent(status, 200, duration_ms)
+        .await;
+
+    Ok(response)
+}
+
+/// Handler for inbox acknowledgements.
+///
+/// Marks the fetched messages of the authenticated remote agent up to and
+/// including `cursor` as delivered, after which they are no longer returned.
+#[utoipa::path(
+    post,
+    path = "/inbox/ack",
+    tag = "inbox",
+    request_body = InboxAck,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The messages were marked as delivered", body = InboxAckResponse),
+        (status = 401, description = "Invalid or missing bearer token", body = StatusResponse),
+        (status = 500, description = "The messages could not be acknowledged", body = StatusResponse)
+    )
+)]
+pub async fn handle_inbox_ack(
+    authorization: Option<String>,
+    ack: InboxAck,
+    node: Arc<TapNode>,
+    event_bus: Arc<EventBus>,
+) -> std::result::Result<impl Reply, Infallible> {
+    let start_time = Instant::now();
+
+    event_bus
+        .publish_request_received("POST".to_string(), "/inbox/ack".to_string(), None)
+        .await;
+
+    let (status, response) = match authenticate_inbox(&node, authorization.as_deref()) {
+        Some(recipient_did) => match node.acknowledge_mailbox(&recipient_did, ack.cursor).await {
+            Ok(acknowledged) => {
+                debug!(
+                    "{} acknowledged {} inbox messages up to {}",
+                    recipient_did, acknowledged, ack.cursor
+                );
+                (
+                    StatusCode::OK,
+                    warp::reply::with_status(
+                        json(&InboxAckResponse {
+                            status: "success".to_string(),
+                            acknowledged,
+                        }),
+                        StatusCode::OK,
+                    )
+                    .into_response(),
+                )
+            }
+            Err(e) => {
+                error!("Failed to acknowledge inbox of {}: {}", recipient_did, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    json_error_response(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Failed to acknowledge messages",
+                    ),
+                )
+            }
+        },
+        None => {
+            warn!("Inbox acknowledgement rejected: invalid or missing bearer token");
+            bearer_unauthorized()
+        }
+    };
+
+    let duration_ms = start_time.elapsed().as_millis() as u64;
+    event_bus
+        .publish_response_sent(status, 200, duration_ms)
+        .await;
+
+    Ok(response)
+}
+
+/// Query parameters of a pending actions listing.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PendingActionsQuery {
+    /// Only actions taken on behalf of this agent
+    pub agent_did: Option<String>,
+    /// Only actions of this kind: `manual_review`, `authorization`,
+    /// `relationship_confirmation` or `quarantined_message`
+    pub kind: Option<String>,
+    /// Maximum number of actions to return
+    pub limit: Option<u32>,
+}
+
+/// Body of a request approving or rejecting a pending action.
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct PendingActionDecision {
+    /// Who approves or rejects the action, recorded with the outcome
+    pub actor: Option<String>,
+    /// Note recorded with the outcome, and the reason of a Reject
+    pub note: Option<String>,
+    /// Settlement address sent with an Authorize
+    pub settlement_address: Option<String>,
+}
+
+/// Actor recorded when a request does not name one.
+const DEFAULT_ACTOR: &str = "api";
+
+/// Response to a pending actions listing.
+#[derive(Serialize, ToSchema)]
+struct PendingActionsResponse {
+    /// Actions awaiting approval, oldest first, each with its `id`, `kind`,
+    /// `agent_did`, `transaction_id`, `summary`, `details` and `created_at`
+    #[schema(value_type = Vec<Object>)]
+    actions: Vec<PendingAction>,
+}
+
+/// Response to an approved or rejected pending action.
+#[derive(Serialize, ToSchema)]
+struct PendingActionResult {
+    /// Always `success`
+    status: String,
+    /// ID of the action
+    id: String,
+    /// Kind of the action
+    kind: String,
+    /// Transaction the action was about
+    transaction_id: Option<String>,
+    /// ID of the message sent or replayed
+    message_id: Option<String>,
+}
+
+/// Whether a request carries the bearer token of the pending actions API.
+fn authenticate_actions(token: &str, authorization: Option<&str>) -> bool {
+    authorization
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .is_some_and(|presented| {
+            let presented = presented.trim().as_bytes();
+            // Compare in constant time so the token cannot be guessed byte by byte
+            presented.len() == token.len()
+                && presented
+                    .iter()
+                    .zip(token.as_bytes())
+                    .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+                    == 0
+        })
+}
+
+/// Handler for listings of the actions awaiting approval.
+///
+/// Lists the manual reviews, authorizations, relationship confirmations and
+/// quarantined messages awaiting a person's approval across the node's
+/// agents, oldest first.
+#[utoipa::path(
+    get,
+    path = "/actions",
+    tag = "actions",
+    params(PendingActionsQuery),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Actions awaiting approval", body = PendingActionsResponse),
+        (status = 400, description = "Unknown action kind", body = StatusResponse),
+        (status = 401, description = "Invalid or missing bearer token", body = StatusResponse),
+        (status = 500, description = "The actions could not be listed", body = StatusResponse)
+    )
+)]
+pub async fn handle_list_pending_actions(
+    authorization: Option<String>,
+    query: PendingActionsQuery,
+    token: Arc<String>,
+    node: Arc<TapNode>,
+    event_bus: Arc<EventBus>,
+) -> std::result::Result<impl Reply, Infallible> {
+    let start_time = Instant::now();
+
+    event_bus
+        .publish_request_received("GET".to_string(), "/actions".to_string(), None)
+        .await;
+
+    let kinds = query
+        .kind
+        .as_deref()
+        .map(str::parse::<PendingActionKind>)
+        .transpose();
+    let (status, response) = if !authenticate_actions(&token, authorization.as_deref()) {
+        warn!("Pending actions listing rejected: invalid or missing bearer token");
+        bearer_unauthorized()
+    } else {
+        match kinds {
+            Err(message) => (
+                StatusCode::BAD_REQUEST,
+                json_error_response(StatusCode::BAD_REQUEST, &message),
+            ),
+            Ok(kind) => {
+                let filter = PendingActionFilter {
+                    agent_did: query.agent_did,
+                    kinds: kind.into_iter().collect(),
+                };
+                match node.list_pending_actions(&filter, query.limit).await {
+                    Ok(actions) => (
+                        StatusCode::OK,
+                        warp::reply::with_status(
+                            json(&PendingActionsResponse { actions }),
+                            StatusCode::OK,
+                        )
+                        .into_response(),
+                    ),
+                    Err(e) => {
+                        error!("Failed to list pending actions: {}", e);
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            json_error_response(
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                                "Failed to list pending actions",
+                            ),
+                        )
+                    }
+                }
+            }
+        }
+    };
+
+    let duration_ms = start_time.elapsed().as_millis() as u64;
+    event_bus
+        .publish_response_sent(status, 200, duration_ms)
+        .await;
+
+    Ok(response)
+}
+
+/// Handler for approvals of pending actions.
+///
+/// Sends the response the action calls for: Authorize for a manual review or
+/// authorization, ConfirmRelationship for a relationship confirmation, and
+/// replays a quarantined message.
+#[utoipa::path(
+    post,
+    path = "/actions/{id}/approve",
+    tag = "actions",
+    params(("id" = String, Path, description = "ID of the action, e.g. `manual_review:12`")),
+    request_body = PendingActionDecision,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The action was approved", body = PendingActionResult),
+        (status = 400, description = "The action could not be approved", body = StatusResponse),
+        (status = 401, description = "Invalid or missing bearer token", body = StatusResponse),
+        (status = 404, description = "No such action awaits approval", body = StatusResponse),
+        (status = 500, description = "The action could not be completed", body = StatusResponse)
+    )
+)]
+pub async fn handle_approve_pending_action(
+    id: String,
+    authorization: Option<String>,
+    decision: PendingActionDecision,
+    token: Arc<String>,
+    node: Arc<TapNode>,
+    event_bus: Arc<EventBus>,
+) -> std::result::Result<impl Reply, Infallible> {
+    complete_pending_action(id, true, authorization, decision, token, node, event_bus).await
+}
+
+/// Handler for rejections of pending actions.
+///
+/// Sends Reject for the transaction of a manual review, authorization or
+/// relationship confirmation, and discards a quarantined message.
+#[utoipa::path(
+    post,
+    path = "/actions/{id}/reject",
+    tag = "actions",
+    params(("id" = String, Path, description = "ID of the action, e.g. `manual_review:12`")),
+    request_body = PendingActionDecision,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The action was rejected", body = PendingActionResult),
+        (status = 400, description = "The action could not be rejected", body = StatusResponse),
+        (status = 401, description = "Invalid or missing bearer token", body = StatusResponse),
+        (status = 404, description = "No such action awaits approval", body = StatusResponse),
+        (status = 500, description = "The action could not be completed", body = StatusResponse)
+    )
+)]
+pub async fn handle_reject_pending_action(
+    id: String,
+    authorization: Option<String>,
+    decision: PendingActionDecision,
+    token: Arc<String>,
+    node: Arc<TapNode>,
+    event_bus: Arc<EventBus>,
+) -> std::result::Result<impl Reply, Infallible> {
+    complete_pending_action(id, false, authorization, decision, token, node, event_bus).await
+}
+
+/// Approve or reject a pending action.
+async fn complete_pending_action(
+    id: String,
+    approve: bool,
+    authorization: Option<String>,
+    decision: PendingActionDecision,
+    token: Arc<String>,
+    node: Arc<TapNode>,
+    event_bus: Arc<EventBus>,
+) -> std::result::Result<warp::reply::Response, Infallible> {
+    let start_time = Instant::now();
+    let verb = if approve { "approve" } else { "reject" };
+
+    event_bus
+        .publish_request_received(
+            "POST".to_string(),
+            format!("/actions/{{id}}/{}", verb),
+            None,
+        )
+        .await;
+
+    // Clients may percent-encode the colon of the action ID
+    let id = id.replace("%3A", ":").replace("%3a", ":");
+    let (status, response) = if !authenticate_actions(&token, authorization.as_deref()) {
+        warn!(
+            "Pending action {} rejected: invalid or missing bearer token",
+            verb
+        );
+        bearer_unauthorized()
+    } else {
+        let response = PendingActionResponse {
+            actor: decision.actor.unwrap_or_else(|| DEFAULT_ACTOR.to_string()),
+            note: decision.note,
+            settlement_address: decision.settlement_address,
+        };
+        let outcome = if approve {
+            node.approve_pending_action(&id, &response).await
+        } else {
+            node.reject_pending_action(&id, &response).await
+        };
+        match outcome {
+            Ok(Some(outcome)) => {
+                info!("Pending action {} {}d by {}", id, verb, response.actor);
+                (
+                    StatusCode::OK,
+                    warp::reply::with_status(
+                        json(&PendingActionResult {
+                            status: "success".to_string(),
+                            id: outcome.id,
+                            kind: outcome.kind.to_string(),
+                            transaction_id: outcome.transaction_id,
+                            message_id: outcome.message_id,
+                        }),
+                        StatusCode::OK,
+                    )
+                    .into_response(),
+                )
+            }
+            Ok(None) => (
+                StatusCode::NOT_FOUND,
+                json_error_response(StatusCode::NOT_FOUND, "No such action awaits approval"),
+            ),
+            Err(tap_node::Error::Validation(message)) => {
+                warn!("Could not {} pending action {}: {}", verb, id, message);
+                (
+                    StatusCode::BAD_REQUEST,
+                    json_error_response(StatusCode::BAD_REQUEST, &message),
+                )
+            }
+            Err(e) => {
+                error!("Failed to {} pending action {}: {}", verb, id, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    json_error_response(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Failed to complete the action",
+                    ),
+                )
+            }
+        }
+    };
+
+    let duration_ms = start_time.elapsed().as_millis() as u64;
+    event_bus
+        .publish_response_sent(status, 200, duration_ms)
+        .await;
+
+    Ok(response)
+}
+
+/// Handler for requests of the OpenAPI document.
+///
+/// Serves the document built by [`crate::openapi::openapi`] for the server's
+/// configuration.
+pub async fn handle_openapi(
+    document: Arc<serde_json::Value>,
+    event_bus: Arc<EventBus>,
+) -> std::result::Result<impl Reply, Infallible> {
+    let start_time = Instant::now();
+
+    event_bus
+        .publish_request_received(
+            "GET".to_string(),
+            crate::openapi::OPENAPI_PATH.to_string(),
+            None,
+        )
+        .await;
+
+    let response = json(document.as_ref()).into_response();
+
+    let duration_ms = start_time.elapsed().as_millis() as u64;
+    event_bus
+        .publish_response_sent(StatusCode::OK, 0, duration_ms)
+        .await;
+    Ok(response)
+}
+
+/// Seconds an overloaded node asks senders to wait before sending again.
+const RETRY_AFTER_SECS: u64 = 1;
+
+/// Create the response to a received DIDComm message.
+///
+/// Accepted, queued and forwarded messages are answered with 202 Accepted.
+/// Rejected messages are answered with 429 Too Many Requests and a
+/// `Retry-After` header if the node was overloaded, so that the sender
+/// retries, with 502 Bad Gateway if the node's upstream node could not take
+/// the message, and with 400 Bad Request otherwise.
+fn ingest_response(outcome: &IngestOutcome) -> (StatusCode, warp::reply::Response) {
+    let (status, body) = match outcome {
+        IngestOutcome::Accepted { message_id } => (
+            StatusCode::ACCEPTED,
+            IngestResponse {
+                status: "success".to_string(),
+                message: "Message received and processed".to_string(),
+                outcome: "accepted".to_string(),
+                message_id: message_id.clone(),
+                position: None,
+                code: None,
+            },
+        ),
+        IngestOutcome::Queued { position } => (
+            StatusCode::ACCEPTED,
+            IngestResponse {
+                status: "success".to_string(),
+                message: "Message received and queued for processing".to_string(),
+                outcome: "queued".to_string(),
+                message_id: None,
+                position: Some(*position),
+                code: None,
+            },
+        ),
+        IngestOutcome::Forwarded { message_id } => (
+            StatusCode::ACCEPTED,
+            IngestResponse {
+                status: "success".to_string(),
+                message: "Message received and forwarded to the upstream node".to_string(),
+                outcome: "forwarded".to_string(),
+                message_id: message_id.clone(),
+                position: None,
+                code: None,
+            },
+        ),
+        IngestOutcome::Rejected { code, reason } => (
+            match code {
+                RejectionCode::UpstreamUnavailable => StatusCode::BAD_GATEWAY,
+                RejectionCode::Paused => StatusCode::SERVICE_UNAVAILABLE,
+                code if code.is_retryable() => StatusCode::TOO_MANY_REQUESTS,
+                _ => StatusCode::BAD_REQUEST,
+            },
+            IngestResponse {
+                status: "error".to_string(),
+                message: reason.clone(),
+                outcome: "rejected".to_string(),
+                message_id: None,
+                position: None,
+                code: Some(code.to_string()),
+            },
+        ),
+    };
+
+    let response = warp::reply::with_status(json(&body), status);
+    if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE {
+        let response =
+            warp::reply::with_header(response, "retry-after", RETRY_AFTER_SECS.to_string());
+        (status, response.into_response())
+    } else {
+        (status, response.into_response())
+    }
 }
 
 /// Create a JSON error response.
@@ -269,10 +1197,10 @@ fn json_success_response() -> warp::reply::Response {
 /// * `message` - The error message to include in the response
 fn json_error_response(status: StatusCode, message: &str) -> warp::reply::Response {
     warp::reply::with_status(
-        json(&json!({
-            "status": "error",
-            "message": message
-        })),
+        json(&StatusResponse {
+            status: "error".to_string(),
+            message: message.to_string(),
+        }),
         status,
     )
     .into_response()
@@ -492,6 +1420,15 @@ fn did_doc_to_json_ld(doc: &tap_agent::did::DIDDoc) -> serde_json::Value {
 ///
 /// The DID document includes the DIDComm messaging service endpoint and
 /// the agent's public keys.
+#[utoipa::path(
+    get,
+    path = "/.well-known/did.json",
+    tag = "node",
+    responses(
+        (status = 200, description = "DID document of the `did:web` DID of the host", body = Object),
+        (status = 400, description = "Invalid Host header", body = StatusResponse)
+    )
+)]
 pub async fn handle_well_known_did(
     host: Option<String>,
     node: Arc<TapNode>,
@@ -651,6 +1588,98 @@ pub async fn handle_well_known_did(
     Ok(response)
 }
 
+/// Handler for the signed node attestation at `/.well-known/tap-node`.
+///
+/// The attestation is signed by the configured signer, or else by the
+/// registered agent whose DID sorts first. Endpoint URLs are built from the
+/// `Host` header, so they match the name the node was reached at.
+#[utoipa::path(
+    get,
+    path = "/.well-known/tap-node",
+    tag = "node",
+    responses(
+        (status = 200, description = "The node attestation, as a JWS signing a `NodeAttestation`", body = Object),
+        (status = 400, description = "Invalid Host header", body = StatusResponse),
+        (status = 503, description = "No agent is available to sign the attestation", body = StatusResponse)
+    )
+)]
+pub async fn handle_node_attestation(
+    host: Option<String>,
+    forwarded_proto: Option<String>,
+    config: Arc<TapHttpConfig>,
+    node: Arc<TapNode>,
+    event_bus: Arc<EventBus>,
+) -> std::result::Result<impl Reply, Infallible> {
+    let start_time = Instant::now();
+
+    event_bus
+        .publish_request_received("GET".to_string(), ATTESTATION_PATH.to_string(), None)
+        .await;
+
+    let scheme = match forwarded_proto.as_deref() {
+        Some("https") => "https",
+        Some("http") => "http",
+        _ if config.tls.is_some() => "https",
+        _ => "http",
+    };
+    let authority = match host.as_deref().map(sanitize_domain) {
+        Some(Ok(domain)) => domain,
+        Some(Err(e)) => {
+            warn!("Invalid Host header for node attestation: {}", e);
+            let response = json_error_response(StatusCode::BAD_REQUEST, "Invalid Host header");
+            let duration_ms = start_time.elapsed().as_millis() as u64;
+            event_bus
+                .publish_response_sent(StatusCode::BAD_REQUEST, 200, duration_ms)
+                .await;
+            return Ok(response);
+        }
+        None => config.server_addr(),
+    };
+
+    let signer = config.attestation_signer.clone().or_else(|| {
+        let mut dids = node.list_agents();
+        dids.sort();
+        dids.into_iter().next()
+    });
+    let Some(signer) = signer else {
+        let response = json_error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "No agent is available to sign the node attestation",
+        );
+        let duration_ms = start_time.elapsed().as_millis() as u64;
+        event_bus
+            .publish_response_sent(StatusCode::SERVICE_UNAVAILABLE, 200, duration_ms)
+            .await;
+        return Ok(response);
+    };
+
+    let attestation = NodeAttestation::for_node(
+        &node,
+        &config,
+        &signer,
+        &format!("{}://{}", scheme, authority),
+    );
+    let response = match attestation.sign(&node).await {
+        Ok(jws) => {
+            warp::reply::with_status(warp::reply::json(&jws), StatusCode::OK).into_response()
+        }
+        Err(e) => {
+            error!("Failed to sign node attestation: {}", e);
+            json_error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to sign node attestation",
+            )
+        }
+    };
+
+    let status = response.status();
+    let duration_ms = start_time.elapsed().as_millis() as u64;
+    event_bus
+        .publish_response_sent(status, 500, duration_ms)
+        .await;
+    Ok(response)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -662,9 +1691,11 @@ mod tests {
     async fn test_health_check() {
         // Create a dummy event bus
         let event_bus = Arc::new(crate::event::EventBus::new());
+        let node = Arc::new(TapNode::new(NodeConfig::default()));
 
         // Call the health check handler
-        let response = handle_health_check(event_bus).await.unwrap();
+        let metrics = Arc::new(ProtectionMetrics::default());
+        let response = handle_health_check(node, metrics, event_bus).await.unwrap();
 
         // Convert the response to bytes and parse as JSON
         let response_bytes = to_bytes(response.into_response().into_body())
@@ -675,6 +1706,8 @@ mod tests {
         // Validate the response
         assert_eq!(response_json["status"], "ok");
         assert!(response_json["version"].is_string());
+        assert_eq!(response_json["storage"], serde_json::json!([]));
+        assert_eq!(response_json["protection"]["rate_limited_requests"], 0);
     }
 
     #[test]
@@ -695,6 +1728,10 @@ mod tests {
         let result = validate_message_security(Some("application/didcomm-encrypted+json"));
         assert!(result.is_ok());
 
+        // Test compact JOSE content type (should pass)
+        let result = validate_message_security(Some("application/jose"));
+        assert!(result.is_ok());
+
         // Test with charset parameter (should pass)
         let result =
             validate_message_security(Some("application/didcomm-signed+json; charset=utf-8"));
@@ -769,17 +1806,60 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_json_success_response() {
-        // Generate a success response
-        let response = json_success_response();
-
-        // Convert the response to bytes and parse as JSON
+    async fn test_ingest_response() {
+        let (status, response) = ingest_response(&IngestOutcome::Accepted {
+            message_id: Some("msg-1".to_string()),
+        });
+        assert_eq!(status, StatusCode::ACCEPTED);
         let response_bytes = to_bytes(response.into_body()).await.unwrap();
         let response_json: Value = serde_json::from_slice(&response_bytes).unwrap();
-
-        // Validate the response
         assert_eq!(response_json["status"], "success");
-        assert_eq!(response_json["message"], "Message received and processed");
+        assert_eq!(response_json["outcome"], "accepted");
+        assert_eq!(response_json["message_id"], "msg-1");
+
+        let (status, _) = ingest_response(&IngestOutcome::Queued { position: 3 });
+        assert_eq!(status, StatusCode::ACCEPTED);
+
+        let (status, response) = ingest_response(&IngestOutcome::Rejected {
+            code: RejectionCode::Invalid,
+            reason: "Message has expired".to_string(),
+        });
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(response.headers().get("retry-after").is_none());
+        let response_bytes = to_bytes(response.into_body()).await.unwrap();
+        let response_json: Value = serde_json::from_slice(&response_bytes).unwrap();
+        assert_eq!(response_json["status"], "error");
+        assert_eq!(response_json["code"], "invalid");
+        assert_eq!(response_json["message"], "Message has expired");
+
+        let (status, response) = ingest_response(&IngestOutcome::Rejected {
+            code: RejectionCode::Overloaded,
+            reason: "Too many messages are being processed".to_string(),
+        });
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["retry-after"], "1");
+
+        let (status, response) = ingest_response(&IngestOutcome::Forwarded {
+            message_id: Some("msg-2".to_string()),
+        });
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let response_bytes = to_bytes(response.into_body()).await.unwrap();
+        let response_json: Value = serde_json::from_slice(&response_bytes).unwrap();
+        assert_eq!(response_json["outcome"], "forwarded");
+
+        let (status, response) = ingest_response(&IngestOutcome::Rejected {
+            code: RejectionCode::UpstreamUnavailable,
+            reason: "Upstream node is unreachable".to_string(),
+        });
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert!(response.headers().get("retry-after").is_none());
+
+        let (status, response) = ingest_response(&IngestOutcome::Rejected {
+            code: RejectionCode::Paused,
+            reason: "The node is not accepting messages".to_string(),
+        });
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()["retry-after"], "1");
     }
 
     // --- Domain sanitization tests ---
@@ -27,12 +27,17 @@
 //! - **No Plain Messages**: Plain DIDComm messages are rejected for security
 //! - **Content-Type Validation**: Strict validation of message security types
 //! - **Event Logging**: All message processing events are logged for audit
+//! - **Connection Protection**: IP allow/deny lists, per-IP rate limits,
+//!   connection caps and header read timeouts
 //!
 //! # Key Components
 //!
 //! - **Handler**: Request/response processing with validation
 //! - **Server**: Warp-based HTTP server with configurable endpoints
 //! - **Client**: HTTP client for outgoing message delivery
+//! - **Sync**: Client mode pulling messages from a remote gateway
+//! - **Protection**: Connection and request limits applied by the server
+//! - **TLS**: TLS termination with certificates reloaded without downtime
 //! - **Event Bus**: Comprehensive event logging and monitoring
 //!
 //! # Example Usage
@@ -68,16 +73,23 @@
 //! ```
 
 // Public modules
+pub mod attestation;
 pub mod client;
 pub mod config;
 pub mod error;
 pub mod event;
 pub mod external_decision;
 pub mod handler;
+pub mod openapi;
+pub mod protection;
 pub mod server;
+pub mod sync;
+pub mod tls;
 
 // Re-exports
+pub use attestation::{NodeAttestation, VerifiedAttestation};
 pub use client::DIDCommClient;
 pub use config::TapHttpConfig;
 pub use error::{Error, Result};
 pub use server::TapHttpServer;
+pub use sync::{GatewaySyncClient, GatewaySyncConfig};
@@ -13,11 +13,24 @@ use tap_agent::key_manager::KeyManager;
 use tap_agent::storage::KeyStorage;
 use tap_agent::Agent;
 use tap_agent::TapAgent;
+use tap_http::config::{RateLimitConfig, TlsConfig, TlsVersion};
 use tap_http::event::{EventLoggerConfig, LogDestination};
 use tap_http::external_decision::{ExternalDecisionConfig, ExternalDecisionManager, SubscribeMode};
-use tap_http::{TapHttpConfig, TapHttpServer};
+use tap_http::{GatewaySyncClient, GatewaySyncConfig, TapHttpConfig, TapHttpServer};
 use tap_mcp::tap_integration::TapIntegration;
 use tap_mcp::tools::ToolRegistry;
+use tap_node::backup::{BackupDestination, BackupPolicy};
+use tap_node::log_context::ContextLogger;
+use tap_node::mailbox::MailboxRecipient;
+use tap_node::policy::{PolicyEngine, ScriptRule};
+use tap_node::response_timers::ResponseTimerPolicy;
+use tap_node::scheduler::Schedule;
+use tap_node::scripting::ScriptHook;
+use tap_node::simulation::CounterpartyBehavior;
+#[cfg(feature = "postgres")]
+use tap_node::storage::PostgresBackend;
+use tap_node::storage::{QuotaAction, StorageBackend, StorageQuota};
+use tap_node::validation::settlement_address_validator::AddressStrictness;
 use tap_node::{NodeConfig, TapNode};
 use tracing::{debug, error, info};
 
@@ -26,6 +39,20 @@ struct Args {
     host: String,
     port: u16,
     endpoint: String,
+    authorization_endpoint: String,
+    inbox_endpoint: String,
+    inbox_agents: Vec<String>,
+    inbox_ttl: u64,
+    inbox_max_wait: u64,
+    actions_endpoint: String,
+    actions_token: Option<String>,
+    allow_ips: Vec<String>,
+    deny_ips: Vec<String>,
+    rate_limit: Option<u32>,
+    rate_limit_window: u64,
+    max_connections: Option<usize>,
+    header_timeout: u64,
+    tls: Option<TlsConfig>,
     timeout: u64,
     verbose: bool,
     agent_did: Option<String>,
@@ -34,7 +61,25 @@ struct Args {
     structured_logs: bool,
     db_path: Option<String>,
     tap_root: Option<String>,
+    storage_quota_mb: Option<u64>,
+    storage_quota_action: String,
+    script: Option<String>,
+    settlement_address_check: String,
+    event_sourcing: bool,
+    shared_storage_url: Option<String>,
+    backup_dir: Option<String>,
+    backup_interval: u64,
+    backup_keep: usize,
+    response_deadline: Option<u64>,
+    job_schedules: Vec<String>,
+    job_jitter: u64,
+    simulated_counterparties: Vec<String>,
+    gateway_url: Option<String>,
+    gateway_token: Option<String>,
+    gateway_poll_interval: u64,
     enable_web_did: bool,
+    enable_attestation: bool,
+    attestation_signer: Option<String>,
     decision_mode: String,
     decision_exec: Option<String>,
     decision_exec_args: Vec<String>,
@@ -58,6 +103,46 @@ impl Args {
             process::exit(0);
         }
 
+        let tls = {
+            let cert: Option<String> = args
+                .opt_value_from_str("--tls-cert")?
+                .or_else(|| env::var("TAP_TLS_CERT").ok());
+            let key: Option<String> = args
+                .opt_value_from_str("--tls-key")?
+                .or_else(|| env::var("TAP_TLS_KEY").ok());
+            let min_version: TlsVersion = match args.opt_value_from_str("--tls-min-version")? {
+                Some(version) => version,
+                None => match env::var("TAP_TLS_MIN_VERSION") {
+                    Ok(version) => version.parse()?,
+                    Err(_) => TlsVersion::default(),
+                },
+            };
+            let cipher_suites = comma_list(
+                args.values_from_str("--tls-cipher-suite")?,
+                "TAP_TLS_CIPHER_SUITES",
+            );
+            let reload_interval: Option<u64> = args
+                .opt_value_from_str("--tls-reload-interval")?
+                .or_else(|| {
+                    env::var("TAP_TLS_RELOAD_INTERVAL")
+                        .ok()
+                        .and_then(|t| t.parse::<u64>().ok())
+                });
+            match (cert, key) {
+                (Some(cert), Some(key)) => {
+                    let mut tls = TlsConfig::new(cert, key);
+                    tls.min_version = min_version;
+                    tls.cipher_suites = cipher_suites;
+                    if let Some(reload_interval) = reload_interval {
+                        tls.reload_interval_secs = reload_interval;
+                    }
+                    Some(tls)
+                }
+                (None, None) => None,
+                _ => return Err("--tls-cert and --tls-key must be given together".into()),
+            }
+        };
+
         let result = Args {
             host: args
                 .opt_value_from_str(["-h", "--host"])?
@@ -77,6 +162,85 @@ impl Args {
                 .unwrap_or_else(|| {
                     env::var("TAP_HTTP_DIDCOMM_ENDPOINT").unwrap_or_else(|_| "/didcomm".to_string())
                 }),
+            authorization_endpoint: args
+                .opt_value_from_str("--authorization-endpoint")?
+                .unwrap_or_else(|| {
+                    env::var("TAP_HTTP_AUTHORIZATION_ENDPOINT")
+                        .unwrap_or_else(|_| "/authorize".to_string())
+                }),
+            inbox_endpoint: args
+                .opt_value_from_str("--inbox-endpoint")?
+                .unwrap_or_else(|| {
+                    env::var("TAP_HTTP_INBOX_ENDPOINT").unwrap_or_else(|_| "/inbox".to_string())
+                }),
+            inbox_agents: {
+                let agents: Vec<String> = args.values_from_str("--inbox-agent")?;
+                if agents.is_empty() {
+                    env::var("TAP_INBOX_AGENTS")
+                        .map(|s| {
+                            s.split(',')
+                                .map(|a| a.trim().to_string())
+                                .filter(|a| !a.is_empty())
+                                .collect()
+                        })
+                        .unwrap_or_default()
+                } else {
+                    agents
+                }
+            },
+            inbox_ttl: args.opt_value_from_str("--inbox-ttl")?.unwrap_or_else(|| {
+                env::var("TAP_INBOX_TTL")
+                    .ok()
+                    .and_then(|t| t.parse::<u64>().ok())
+                    .unwrap_or(86400)
+            }),
+            inbox_max_wait: args
+                .opt_value_from_str("--inbox-max-wait")?
+                .unwrap_or_else(|| {
+                    env::var("TAP_INBOX_MAX_WAIT")
+                        .ok()
+                        .and_then(|t| t.parse::<u64>().ok())
+                        .unwrap_or(30)
+                }),
+            actions_endpoint: args
+                .opt_value_from_str("--actions-endpoint")?
+                .unwrap_or_else(|| {
+                    env::var("TAP_HTTP_ACTIONS_ENDPOINT").unwrap_or_else(|_| "/actions".to_string())
+                }),
+            actions_token: args
+                .opt_value_from_str("--actions-token")?
+                .or_else(|| env::var("TAP_ACTIONS_TOKEN").ok()),
+            allow_ips: comma_list(args.values_from_str("--allow-ip")?, "TAP_HTTP_ALLOW_IPS"),
+            deny_ips: comma_list(args.values_from_str("--deny-ip")?, "TAP_HTTP_DENY_IPS"),
+            rate_limit: match args.opt_value_from_str("--rate-limit")? {
+                Some(limit) => Some(limit),
+                None => env::var("TAP_HTTP_RATE_LIMIT")
+                    .ok()
+                    .and_then(|l| l.parse::<u32>().ok()),
+            },
+            rate_limit_window: args
+                .opt_value_from_str("--rate-limit-window")?
+                .unwrap_or_else(|| {
+                    env::var("TAP_HTTP_RATE_LIMIT_WINDOW")
+                        .ok()
+                        .and_then(|w| w.parse::<u64>().ok())
+                        .unwrap_or(60)
+                }),
+            max_connections: match args.opt_value_from_str("--max-connections")? {
+                Some(max) => Some(max),
+                None => env::var("TAP_HTTP_MAX_CONNECTIONS")
+                    .ok()
+                    .and_then(|m| m.parse::<usize>().ok()),
+            },
+            header_timeout: args
+                .opt_value_from_str("--header-timeout")?
+                .unwrap_or_else(|| {
+                    env::var("TAP_HTTP_HEADER_TIMEOUT")
+                        .ok()
+                        .and_then(|t| t.parse::<u64>().ok())
+                        .unwrap_or(10)
+                }),
+            tls,
             timeout: args
                 .opt_value_from_str(["-t", "--timeout"])?
                 .unwrap_or_else(|| {
@@ -103,8 +267,113 @@ impl Args {
             tap_root: args
                 .opt_value_from_str("--tap-root")?
                 .or_else(|| env::var("TAP_ROOT").ok()),
+            storage_quota_mb: match args.opt_value_from_str("--storage-quota")? {
+                Some(mb) => Some(mb),
+                None => env::var("TAP_STORAGE_QUOTA")
+                    .ok()
+                    .and_then(|q| q.parse::<u64>().ok()),
+            },
+            storage_quota_action: args
+                .opt_value_from_str("--storage-quota-action")?
+                .unwrap_or_else(|| {
+                    env::var("TAP_STORAGE_QUOTA_ACTION").unwrap_or_else(|_| "reject".to_string())
+                }),
+            script: args
+                .opt_value_from_str("--script")?
+                .or_else(|| env::var("TAP_SCRIPT").ok()),
+            settlement_address_check: args
+                .opt_value_from_str("--settlement-address-check")?
+                .unwrap_or_else(|| {
+                    env::var("TAP_SETTLEMENT_ADDRESS_CHECK").unwrap_or_else(|_| "warn".to_string())
+                }),
+            event_sourcing: args.contains("--event-sourcing")
+                || env::var("TAP_EVENT_SOURCING").is_ok(),
+            shared_storage_url: args
+                .opt_value_from_str("--shared-storage-url")?
+                .or_else(|| env::var("TAP_SHARED_STORAGE_URL").ok()),
+            backup_dir: args
+                .opt_value_from_str("--backup-dir")?
+                .or_else(|| env::var("TAP_BACKUP_DIR").ok()),
+            backup_interval: args
+                .opt_value_from_str("--backup-interval")?
+                .unwrap_or_else(|| {
+                    env::var("TAP_BACKUP_INTERVAL")
+                        .ok()
+                        .and_then(|h| h.parse::<u64>().ok())
+                        .unwrap_or(24)
+                }),
+            backup_keep: args
+                .opt_value_from_str("--backup-keep")?
+                .unwrap_or_else(|| {
+                    env::var("TAP_BACKUP_KEEP")
+                        .ok()
+                        .and_then(|k| k.parse::<usize>().ok())
+                        .unwrap_or(7)
+                }),
+            response_deadline: args.opt_value_from_str("--response-deadline")?.or_else(|| {
+                env::var("TAP_RESPONSE_DEADLINE")
+                    .ok()
+                    .and_then(|m| m.parse::<u64>().ok())
+            }),
+            job_schedules: {
+                let schedules: Vec<String> = args.values_from_str("--job-schedule")?;
+                if schedules.is_empty() {
+                    // Cron expressions contain commas, so the list is separated by semicolons
+                    env::var("TAP_JOB_SCHEDULES")
+                        .map(|s| {
+                            s.split(';')
+                                .map(|j| j.trim().to_string())
+                                .filter(|j| !j.is_empty())
+                                .collect()
+                        })
+                        .unwrap_or_default()
+                } else {
+                    schedules
+                }
+            },
+            job_jitter: args.opt_value_from_str("--job-jitter")?.unwrap_or_else(|| {
+                env::var("TAP_JOB_JITTER")
+                    .ok()
+                    .and_then(|s| s.parse::<u64>().ok())
+                    .unwrap_or(0)
+            }),
+            simulated_counterparties: {
+                let behaviors: Vec<String> = args.values_from_str("--simulate-counterparty")?;
+                if behaviors.is_empty() {
+                    // Reject reasons may contain commas, so the list is separated by semicolons
+                    env::var("TAP_SIMULATE_COUNTERPARTIES")
+                        .map(|s| {
+                            s.split(';')
+                                .map(|b| b.trim().to_string())
+                                .filter(|b| !b.is_empty())
+                                .collect()
+                        })
+                        .unwrap_or_default()
+                } else {
+                    behaviors
+                }
+            },
+            gateway_url: args
+                .opt_value_from_str("--gateway-url")?
+                .or_else(|| env::var("TAP_GATEWAY_URL").ok()),
+            gateway_token: args
+                .opt_value_from_str("--gateway-token")?
+                .or_else(|| env::var("TAP_GATEWAY_TOKEN").ok()),
+            gateway_poll_interval: args
+                .opt_value_from_str("--gateway-poll-interval")?
+                .unwrap_or_else(|| {
+                    env::var("TAP_GATEWAY_POLL_INTERVAL")
+                        .ok()
+                        .and_then(|t| t.parse::<u64>().ok())
+                        .unwrap_or(5)
+                }),
             enable_web_did: args.contains("--enable-web-did")
                 || env::var("TAP_ENABLE_WEB_DID").is_ok(),
+            enable_attestation: args.contains("--enable-attestation")
+                || env::var("TAP_ENABLE_ATTESTATION").is_ok(),
+            attestation_signer: args
+                .opt_value_from_str("--attestation-signer")?
+                .or_else(|| env::var("TAP_ATTESTATION_SIGNER").ok()),
             decision_mode: {
                 let raw: Option<String> = args.opt_value_from_str(["-M", "--decision-mode"])?;
                 raw.unwrap_or_else(|| {
@@ -143,6 +412,33 @@ impl Args {
     }
 }
 
+/// Use the values given on the command line, or else the comma-separated
+/// list in an environment variable.
+fn comma_list(values: Vec<String>, env_var: &str) -> Vec<String> {
+    if !values.is_empty() {
+        return values;
+    }
+    env::var(env_var)
+        .map(|s| {
+            s.split(',')
+                .map(|ip| ip.trim().to_string())
+                .filter(|ip| !ip.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Connect to the Postgres database shared by several nodes
+#[cfg(feature = "postgres")]
+async fn connect_shared_storage(url: &str) -> Result<Arc<dyn StorageBackend>, Box<dyn Error>> {
+    Ok(Arc::new(PostgresBackend::connect(url).await?))
+}
+
+#[cfg(not(feature = "postgres"))]
+async fn connect_shared_storage(_url: &str) -> Result<Arc<dyn StorageBackend>, Box<dyn Error>> {
+    Err("--shared-storage-url requires tap-http to be built with the postgres feature".into())
+}
+
 fn print_help() {
     println!(
         "\
@@ -161,10 +457,40 @@ SERVER OPTIONS:
     -h, --host <HOST>              Host to bind to [default: 127.0.0.1]
     -p, --port <PORT>              Port to listen on [default: 8000]
     -e, --endpoint <ENDPOINT>      DIDComm endpoint path [default: /didcomm]
+    --authorization-endpoint <PATH>
+                                   Authorization callback path [default: /authorize]
     -t, --timeout <SECONDS>        Request timeout in seconds [default: 30]
     -v, --verbose                  Enable verbose logging
     --structured-logs              Use structured JSON logging
     --enable-web-did               Serve /.well-known/did.json for did:web hosting
+    --enable-attestation           Serve a signed node attestation at
+                                   /.well-known/tap-node
+    --attestation-signer <DID>     Agent signing the node attestation
+                                   [default: first agent DID]
+
+PROTECTION OPTIONS:
+    --allow-ip <IP|CIDR>           Only accept connections from these addresses
+                                   (repeatable)
+    --deny-ip <IP|CIDR>            Refuse connections from these addresses,
+                                   even if allowed (repeatable)
+    --rate-limit <REQUESTS>        Maximum requests per IP address per window
+    --rate-limit-window <SECONDS>  Rate limit window [default: 60]
+    --max-connections <COUNT>      Maximum concurrent connections
+    --header-timeout <SECONDS>     Time a client has to send request headers,
+                                   0 for no limit [default: 10]
+
+TLS OPTIONS:
+    --tls-cert <PATH>              PEM certificate chain; serves HTTPS when
+                                   given with --tls-key
+    --tls-key <PATH>               PEM private key
+    --tls-min-version <VERSION>    Lowest TLS version, 1.2 or 1.3 [default: 1.2]
+    --tls-cipher-suite <NAME>      Cipher suite to offer, by IANA name such as
+                                   TLS13_AES_256_GCM_SHA384 (repeatable)
+                                   [default: rustls defaults]
+    --tls-reload-interval <SECONDS>
+                                   How often the certificate and key files are
+                                   checked for changes, 0 to reload only on
+                                   SIGHUP [default: 60]
 
 AGENT OPTIONS:
     --agent-did <DID>              DID for the TAP agent (auto-generated if omitted)
@@ -173,6 +499,65 @@ AGENT OPTIONS:
     --db-path <PATH>               Database file path (overrides per-agent default)
     --logs-dir <DIR>               Event log directory [default: ~/.tap/logs]
     --secret-helper <CMD>          Secret helper command for external key management
+    --storage-quota <MB>           Maximum size of each agent database in megabytes
+    --storage-quota-action <ACTION>
+                                   When a database exceeds its quota [default: reject]
+                                     reject  - Reject non-essential writes
+                                     archive - Archive and compact the database
+    --script <PATH>                Rhai script defining route and policy hooks,
+                                   reloaded when the file changes
+    --settlement-address-check <MODE>
+                                   Settlement addresses on the wrong chain for
+                                   the asset [default: warn]
+                                     off    - Do not check
+                                     warn   - Log a warning
+                                     reject - Reject the message
+    --event-sourcing               Record transaction and delivery changes in an
+                                   event log the tables can be rebuilt from
+    --shared-storage-url <URL>     Run messages, transactions and deliveries on
+                                   this Postgres database, shared by several
+                                   nodes (requires the postgres feature)
+    --backup-dir <DIR>             Back up the node and agent databases to this
+                                   directory on a schedule
+    --backup-interval <HOURS>      Hours between scheduled backups [default: 24]
+    --backup-keep <COUNT>          Backups kept per database [default: 7]
+    --response-deadline <MINUTES>  Time agents have to answer Transfers,
+                                   Payments, Connects and other requests,
+                                   with events as the deadline approaches and
+                                   passes
+    --job-schedule <NAME=SCHEDULE> Run a scheduled job on another schedule, e.g.
+                                   backup=@daily or retention_purge=30 2 * * *
+                                   (repeatable; see tap-cli scheduler list)
+    --job-jitter <SECONDS>         Random delay of up to this long added to each
+                                   scheduled job run [default: 0]
+    --simulate-counterparty <BEHAVIOR>
+                                   Create a simulated counterparty that answers
+                                   Transfers and Payments with authorize[:SECS],
+                                   reject[:SECS[:REASON]],
+                                   request-presentation[:SECS] or never, for
+                                   local testing (repeatable)
+
+INBOX OPTIONS:
+    --inbox-agent <DID[=TOKEN]>    Hold messages for a remote agent that polls
+                                   {{inbox-endpoint}}/poll with the bearer token
+                                   or uses DIDComm message pickup, instead of
+                                   accepting inbound HTTP (repeatable)
+    --inbox-endpoint <PATH>        Inbox endpoint path [default: /inbox]
+    --inbox-ttl <SECONDS>          How long messages are held [default: 86400]
+    --inbox-max-wait <SECONDS>     Longest time a poll waits for a message
+                                   [default: 30]
+
+ACTIONS OPTIONS:
+    --actions-token <TOKEN>        Serve the actions awaiting approval at
+                                   {{actions-endpoint}} to clients presenting this
+                                   bearer token, to approve or reject them
+    --actions-endpoint <PATH>      Actions endpoint path [default: /actions]
+
+GATEWAY OPTIONS:
+    --gateway-url <URL>            Pull messages from a remote gateway
+    --gateway-token <TOKEN>        Bearer token for the gateway
+    --gateway-poll-interval <SECONDS>
+                                   Delay between polls when idle [default: 5]
 
 DECISION OPTIONS:
     -M, --decision-mode <MODE>     Decision handling mode [default: auto]
@@ -193,14 +578,55 @@ ENVIRONMENT VARIABLES:
     TAP_HTTP_HOST                  Host to bind to
     TAP_HTTP_PORT                  Port to listen on
     TAP_HTTP_DIDCOMM_ENDPOINT      DIDComm endpoint path
+    TAP_HTTP_AUTHORIZATION_ENDPOINT
+                                   Authorization callback path
     TAP_HTTP_TIMEOUT               Request timeout in seconds
+    TAP_HTTP_ALLOW_IPS             Comma-separated addresses or CIDR ranges
+                                   allowed to connect
+    TAP_HTTP_DENY_IPS              Comma-separated addresses or CIDR ranges
+                                   refused a connection
+    TAP_HTTP_RATE_LIMIT            Maximum requests per IP address per window
+    TAP_HTTP_RATE_LIMIT_WINDOW     Rate limit window in seconds
+    TAP_HTTP_MAX_CONNECTIONS       Maximum concurrent connections
+    TAP_HTTP_HEADER_TIMEOUT        Request header timeout in seconds
+    TAP_TLS_CERT                   PEM certificate chain file
+    TAP_TLS_KEY                    PEM private key file
+    TAP_TLS_MIN_VERSION            Lowest TLS version, 1.2 or 1.3
+    TAP_TLS_CIPHER_SUITES          Comma-separated cipher suites to offer
+    TAP_TLS_RELOAD_INTERVAL        Certificate change check interval in seconds
+    TAP_HTTP_INBOX_ENDPOINT        Inbox endpoint path
+    TAP_INBOX_AGENTS               Comma-separated DID=TOKEN pairs or DIDs of
+                                   remote agents that retrieve their messages
+                                   from the inbox
+    TAP_INBOX_TTL                  How long inbox messages are held in seconds
+    TAP_INBOX_MAX_WAIT             Longest inbox poll wait in seconds
+    TAP_HTTP_ACTIONS_ENDPOINT      Actions endpoint path
+    TAP_ACTIONS_TOKEN              Bearer token of the actions endpoint
     TAP_AGENT_DID                  DID for the TAP agent
     TAP_AGENT_KEY                  Private key for the TAP agent
     TAP_ROOT                       TAP root directory
     TAP_NODE_DB_PATH               Database file path
     TAP_LOGS_DIR                   Event log directory
+    TAP_STORAGE_QUOTA              Maximum size of each agent database in megabytes
+    TAP_STORAGE_QUOTA_ACTION       Quota action: reject or archive
+    TAP_SCRIPT                     Rhai script defining route and policy hooks
+    TAP_SETTLEMENT_ADDRESS_CHECK   Settlement address check: off, warn or reject
+    TAP_EVENT_SOURCING             Enable the state event log (set to any value)
+    TAP_SHARED_STORAGE_URL         Postgres database shared by several nodes
+    TAP_BACKUP_DIR                 Directory for scheduled database backups
+    TAP_BACKUP_INTERVAL            Hours between scheduled backups
+    TAP_BACKUP_KEEP                Backups kept per database
+    TAP_RESPONSE_DEADLINE          Minutes agents have to answer requests
+    TAP_JOB_SCHEDULES              Job schedules as NAME=SCHEDULE, separated by semicolons
+    TAP_JOB_JITTER                 Random delay added to scheduled job runs in seconds
+    TAP_SIMULATE_COUNTERPARTIES    Simulated counterparty behaviors, separated by semicolons
+    TAP_GATEWAY_URL                Remote gateway to pull messages from
+    TAP_GATEWAY_TOKEN              Bearer token for the gateway
+    TAP_GATEWAY_POLL_INTERVAL      Delay between gateway polls in seconds
     TAP_STRUCTURED_LOGS            Enable structured JSON logging (set to any value)
     TAP_ENABLE_WEB_DID             Enable did:web endpoint (set to any value)
+    TAP_ENABLE_ATTESTATION         Enable node attestation endpoint (set to any value)
+    TAP_ATTESTATION_SIGNER         Agent signing the node attestation
     TAP_SECRET_HELPER               Secret helper command (replaces keys.json)
     TAP_DECISION_MODE              Decision handling: auto, poll, or exec
     TAP_DECISION_EXEC              Path to external decision executable
@@ -298,9 +724,12 @@ async fn main() -> Result<(), Box<dyn Error>> {
         process::exit(1);
     });
 
-    // Initialize logging with appropriate level
+    // Initialize logging with appropriate level, prefixing records with the
+    // message being processed
     let log_level = if args.verbose { "debug" } else { "info" };
-    env_logger::Builder::from_env(Env::default().default_filter_or(log_level)).init();
+    let logger = env_logger::Builder::from_env(Env::default().default_filter_or(log_level)).build();
+    let max_level = logger.filter();
+    ContextLogger::new(logger).init(max_level)?;
 
     info!("Starting TAP HTTP server");
 
@@ -423,12 +852,27 @@ async fn main() -> Result<(), Box<dyn Error>> {
         host: args.host,
         port: args.port,
         didcomm_endpoint: args.endpoint,
+        authorization_endpoint: args.authorization_endpoint,
+        inbox_endpoint: args.inbox_endpoint,
+        inbox_max_wait_secs: args.inbox_max_wait,
+        actions_endpoint: args.actions_endpoint,
+        actions_token: args.actions_token,
         request_timeout_secs: args.timeout,
-        rate_limit: None,
-        tls: None,
+        rate_limit: args.rate_limit.map(|max_requests| RateLimitConfig {
+            max_requests,
+            window_secs: args.rate_limit_window,
+        }),
+        ip_allowlist: args.allow_ips,
+        ip_denylist: args.deny_ips,
+        max_connections: args.max_connections,
+        header_read_timeout_secs: args.header_timeout,
+        tls: args.tls,
         event_logger: None,
         enable_web_did: args.enable_web_did,
         max_agents: 100,
+        enable_attestation: args.enable_attestation,
+        attestation_signer: args.attestation_signer,
+        ..TapHttpConfig::default()
     };
 
     // Configure event logging - use TAP root-based default if not specified
@@ -454,8 +898,31 @@ async fn main() -> Result<(), Box<dyn Error>> {
     info!("  Host: {}", config.host);
     info!("  Port: {}", config.port);
     info!("  DIDComm endpoint: {}", config.didcomm_endpoint);
+    info!(
+        "  Authorization endpoint: {}",
+        config.authorization_endpoint
+    );
+    info!("  Inbox endpoint: {}", config.inbox_endpoint);
+    info!(
+        "  Actions endpoint: {}",
+        if config.actions_token.is_some() {
+            config.actions_endpoint.as_str()
+        } else {
+            "disabled"
+        }
+    );
     info!("  Request timeout: {} seconds", config.request_timeout_secs);
     info!("  Web DID hosting: {}", config.enable_web_did);
+    info!("  Node attestation: {}", config.enable_attestation);
+    if let Some(rate_limit) = &config.rate_limit {
+        info!(
+            "  Rate limit: {} requests per {} seconds per IP",
+            rate_limit.max_requests, rate_limit.window_secs
+        );
+    }
+    if let Some(max_connections) = config.max_connections {
+        info!("  Max connections: {}", max_connections);
+    }
     info!("  Agent DID: {}", agent_did);
     debug!("  Event logging: {}", log_path.to_string_lossy());
     debug!("  Structured logs: {}", args.structured_logs);
@@ -476,6 +943,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
         node_config.agent_did = Some(agent_did.clone());
         node_config.tap_root = tap_root_path.clone();
         let expected_path = tap_root_path
+            .clone()
             .unwrap_or_else(|| {
                 dirs::home_dir()
                     .expect("Could not find home directory")
@@ -486,6 +954,118 @@ async fn main() -> Result<(), Box<dyn Error>> {
         info!("Using database at: {:?}", expected_path);
     }
 
+    if let Some(quota_mb) = args.storage_quota_mb {
+        let action = match args.storage_quota_action.as_str() {
+            "reject" => QuotaAction::Reject,
+            "archive" => QuotaAction::Archive,
+            other => {
+                return Err(format!(
+                    "Invalid storage quota action '{}'. Use 'reject' or 'archive'",
+                    other
+                )
+                .into())
+            }
+        };
+        node_config.storage_quotas.default_quota =
+            Some(StorageQuota::new(quota_mb * 1024 * 1024).with_action(action));
+        info!(
+            "Storage quota: {} MB per agent ({})",
+            quota_mb, args.storage_quota_action
+        );
+    }
+
+    node_config.settlement_address_strictness = match args.settlement_address_check.as_str() {
+        "off" => AddressStrictness::Off,
+        "warn" => AddressStrictness::Warn,
+        "reject" => AddressStrictness::Reject,
+        other => {
+            return Err(format!(
+                "Invalid settlement address check '{}'. Use 'off', 'warn' or 'reject'",
+                other
+            )
+            .into())
+        }
+    };
+
+    for entry in &args.inbox_agents {
+        let recipient = match entry.split_once('=') {
+            Some((did, token)) if !did.is_empty() && !token.is_empty() => {
+                MailboxRecipient::new(did, token)
+            }
+            None if !entry.is_empty() => MailboxRecipient::pickup(entry.as_str()),
+            _ => {
+                return Err(format!("Invalid inbox agent '{}'. Use DID=TOKEN or DID", entry).into())
+            }
+        };
+        info!("Holding messages for {} in its inbox", recipient.did);
+        node_config.mailbox.recipients.push(recipient);
+    }
+    node_config.mailbox.ttl = std::time::Duration::from_secs(args.inbox_ttl);
+
+    node_config.event_sourcing = args.event_sourcing;
+    if args.event_sourcing {
+        info!("Event sourcing enabled");
+    }
+
+    if let Some(url) = &args.shared_storage_url {
+        node_config.shared_storage = Some(connect_shared_storage(url).await?);
+        info!("Running messages, transactions and deliveries on the shared Postgres database");
+    }
+
+    if let Some(dir) = &args.backup_dir {
+        if args.backup_interval == 0 {
+            return Err("Backup interval must be at least one hour".into());
+        }
+        node_config.backup_policy = Some(
+            BackupPolicy::new(BackupDestination::Directory(PathBuf::from(dir)))
+                .with_interval(std::time::Duration::from_secs(args.backup_interval * 3600))
+                .with_keep_last(Some(args.backup_keep.max(1))),
+        );
+        info!(
+            "Backing up databases to {} every {} hours",
+            dir, args.backup_interval
+        );
+    }
+
+    if let Some(minutes) = args.response_deadline {
+        if minutes == 0 {
+            return Err("Response deadline must be at least one minute".into());
+        }
+        node_config.response_timers = Some(
+            ResponseTimerPolicy::default()
+                .with_deadline(std::time::Duration::from_secs(minutes * 60)),
+        );
+        info!(
+            "Timing answers to requests against a {} minute deadline",
+            minutes
+        );
+    }
+
+    for entry in &args.job_schedules {
+        let Some((name, schedule)) = entry.split_once('=') else {
+            return Err(format!("Invalid job schedule {:?}, expected NAME=SCHEDULE", entry).into());
+        };
+        let schedule: Schedule = schedule.parse()?;
+        info!("Running job {} on schedule {}", name.trim(), schedule);
+        node_config
+            .scheduler
+            .schedules
+            .insert(name.trim().to_string(), schedule);
+    }
+    node_config.scheduler.jitter = std::time::Duration::from_secs(args.job_jitter);
+
+    if let Some(script) = args.script {
+        let hook = Arc::new(ScriptHook::from_file(&script)?);
+        node_config.script_hook = Some(hook.clone());
+        node_config.policy_engine = Some(Arc::new(
+            PolicyEngine::new().with_rule(ScriptRule::new(hook)),
+        ));
+        info!("Using script hooks from {}", script);
+    }
+
+    // Register an agent for every stored key when storage is initialized
+    node_config.auto_register_stored_agents = true;
+
     // Create TAP Node
     let mut node = TapNode::new(node_config);
 
@@ -495,54 +1075,22 @@ async fn main() -> Result<(), Box<dyn Error>> {
         return Err(e.into());
     }
 
-    // Register the primary agent with the node
-    if let Err(e) = node.register_agent(agent_arc.clone()).await {
-        error!("Failed to register agent: {}", e);
-        return Err(e.into());
+    // Register the primary agent unless it was registered from key storage
+    if !node.agents().has_agent(&agent_did) {
+        if let Err(e) = node.register_agent(agent_arc.clone()).await {
+            error!("Failed to register agent: {}", e);
+            return Err(e.into());
+        }
     }
+    info!("Registered {} agents", node.list_agents().len());
 
-    // Register all additional agents from storage
-    match KeyStorage::load_default() {
-        Ok(storage) => {
-            let stored_dids: Vec<String> = storage.keys.keys().cloned().collect();
-            info!("Found {} total keys in storage", stored_dids.len());
-
-            for stored_did in &stored_dids {
-                // Skip the primary agent as it's already registered
-                if stored_did == &agent_did {
-                    continue;
-                }
-
-                info!("Registering additional agent: {}", stored_did);
-                match TapAgent::from_stored_keys(Some(stored_did.clone()), true).await {
-                    Ok(additional_agent) => {
-                        let additional_agent_arc = Arc::new(additional_agent);
-                        if let Err(e) = node.register_agent(additional_agent_arc).await {
-                            error!("Failed to register additional agent {}: {}", stored_did, e);
-                        } else {
-                            info!("Successfully registered additional agent: {}", stored_did);
-                        }
-                    }
-                    Err(e) => {
-                        error!("Failed to load additional agent {}: {}", stored_did, e);
-                    }
-                }
-            }
-
-            if stored_dids.len() > 1 {
-                info!(
-                    "Registered {} agents total (1 primary + {} additional)",
-                    stored_dids.len(),
-                    stored_dids.len() - 1
-                );
-            } else {
-                info!("Registered 1 agent (primary only)");
-            }
-        }
-        Err(e) => {
-            info!("Could not load additional keys from storage: {}", e);
-            info!("Only the primary agent is registered");
-        }
+    for entry in &args.simulated_counterparties {
+        let behavior: CounterpartyBehavior = entry.parse()?;
+        let did = node.add_simulated_counterparty(behavior.clone()).await?;
+        info!(
+            "Simulated counterparty {} answers Transfers and Payments with {}",
+            did, behavior
+        );
     }
 
     // Determine effective decision mode
@@ -658,10 +1206,56 @@ async fn main() -> Result<(), Box<dyn Error>> {
         process::exit(1);
     }
 
+    // Pull messages from a remote gateway if configured
+    let gateway_shutdown = match args.gateway_url {
+        Some(gateway_url) => {
+            let cursor_path = tap_root_path
+                .clone()
+                .unwrap_or_else(|| {
+                    dirs::home_dir()
+                        .expect("Could not find home directory")
+                        .join(".tap")
+                })
+                .join("gateway_cursor.json");
+            let mut sync_config = GatewaySyncConfig::new(gateway_url, cursor_path);
+            sync_config.auth_token = args.gateway_token;
+            sync_config.poll_interval = std::time::Duration::from_secs(args.gateway_poll_interval);
+
+            let client = GatewaySyncClient::new(sync_config, server.node().clone())?;
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            tokio::spawn(client.run(rx));
+            Some(tx)
+        }
+        None => None,
+    };
+
+    // SIGUSR1 pauses the intake of received messages, e.g. while the
+    // databases are migrated, and SIGUSR2 resumes it
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut pause = signal(SignalKind::user_defined1())?;
+        let mut resume = signal(SignalKind::user_defined2())?;
+        let node = server.node().clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    Some(()) = pause.recv() => node.pause_intake(),
+                    Some(()) = resume.recv() => node.resume_intake(),
+                    else => break,
+                }
+            }
+        });
+    }
+
     // Wait for Ctrl-C to shut down
     tokio::signal::ctrl_c().await?;
     info!("Ctrl-C received, shutting down");
 
+    if let Some(tx) = gateway_shutdown {
+        let _ = tx.send(());
+    }
+
     // Stop external decision manager if running
     if let Some(manager) = _decision_manager {
         manager.shutdown().await;
@@ -0,0 +1,108 @@
+//! OpenAPI description of the TAP HTTP server.
+//!
+//! The document is generated from the `#[utoipa::path]` annotations of the
+//! handlers and the response types they serialize, so it follows the code.
+//! [`openapi`] adapts it to a configuration: endpoint paths are the configured
+//! ones and optional endpoints are only listed when they are enabled. The
+//! server serves the result at [`OPENAPI_PATH`].
+
+use crate::config::TapHttpConfig;
+use crate::error::ErrorResponse;
+use crate::handler;
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::openapi::OpenApi as OpenApiDocument;
+use utoipa::{Modify, OpenApi};
+
+/// Path the OpenAPI document is served at.
+pub const OPENAPI_PATH: &str = "/openapi.json";
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "TAP HTTP",
+        description = "HTTP server for the Transaction Authorization Protocol (TAP)"
+    ),
+    paths(
+        handler::handle_didcomm,
+        handler::handle_authorization_callback,
+        handler::handle_inbox_poll,
+        handler::handle_inbox_ack,
+        handler::handle_list_pending_actions,
+        handler::handle_approve_pending_action,
+        handler::handle_reject_pending_action,
+        handler::handle_health_check,
+        handler::handle_well_known_did,
+        handler::handle_node_attestation,
+    ),
+    components(schemas(ErrorResponse)),
+    modifiers(&BearerAuth),
+    tags(
+        (name = "didcomm", description = "Receiving DIDComm messages"),
+        (name = "authorization", description = "Redeeming authorization URLs"),
+        (name = "inbox", description = "Mailboxes of remote agents that poll for their messages"),
+        (name = "actions", description = "Actions awaiting a person's approval"),
+        (name = "node", description = "Node metadata and health")
+    )
+)]
+struct ApiDoc;
+
+/// Declares the bearer tokens that authenticate inbox and actions requests.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut OpenApiDocument) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearer",
+                SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+            );
+        }
+    }
+}
+
+/// Builds the OpenAPI document of a server with this configuration.
+pub fn openapi(config: &TapHttpConfig) -> OpenApiDocument {
+    let mut document = ApiDoc::openapi();
+    let inbox = endpoint(&config.inbox_endpoint);
+    let actions = endpoint(&config.actions_endpoint);
+    let renamed = [
+        ("/didcomm", endpoint(&config.didcomm_endpoint)),
+        (
+            "/authorize/{nonce}",
+            format!("{}/{{nonce}}", endpoint(&config.authorization_endpoint)),
+        ),
+        ("/inbox/poll", format!("{}/poll", inbox)),
+        ("/inbox/ack", format!("{}/ack", inbox)),
+        ("/actions", actions.clone()),
+        (
+            "/actions/{id}/approve",
+            format!("{}/{{id}}/approve", actions),
+        ),
+        ("/actions/{id}/reject", format!("{}/{{id}}/reject", actions)),
+    ];
+    for (default_path, path) in renamed {
+        if let Some(item) = document.paths.paths.remove(default_path) {
+            document.paths.paths.insert(path, item);
+        }
+    }
+
+    if !config.enable_web_did {
+        document.paths.paths.remove("/.well-known/did.json");
+    }
+    if !config.enable_attestation {
+        document.paths.paths.remove("/.well-known/tap-node");
+    }
+    if config.actions_token.is_none() {
+        let nested = format!("{}/", actions);
+        document
+            .paths
+            .paths
+            .retain(|path, _| *path != actions && !path.starts_with(&nested));
+    }
+    document
+}
+
+/// A configured endpoint path as served, e.g. `didcomm/` as `/didcomm`.
+fn endpoint(path: &str) -> String {
+    format!("/{}", path.trim_matches('/'))
+}
@@ -0,0 +1,368 @@
+//! Connection-level protection for the TAP HTTP server.
+//!
+//! Lightweight deployments can expose the server directly instead of behind
+//! a reverse proxy. This module provides the protections such a proxy would:
+//!
+//! - IP allow and deny lists of addresses or CIDR ranges, checked when a
+//!   connection is accepted
+//! - Per-IP request rate limits over a fixed window
+//! - A cap on the number of concurrent connections
+//! - A timeout for reading request headers, against slow-loris clients
+//!
+//! Each violation is published on the event bus as
+//! [`HttpEvent::RequestBlocked`](crate::event::HttpEvent::RequestBlocked)
+//! and counted in [`ProtectionMetrics`], which the health endpoint reports.
+
+use crate::config::{RateLimitConfig, TapHttpConfig};
+use crate::error::{Error, Result};
+use crate::event::EventBus;
+use ipnet::IpNet;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::warn;
+use utoipa::ToSchema;
+
+/// Number of tracked clients above which expired rate limit windows are pruned
+const RATE_LIMIT_PRUNE_THRESHOLD: usize = 4096;
+
+/// Why a connection or request was blocked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockReason {
+    /// The client IP is not allowed to connect
+    IpDenied,
+    /// The server is at its concurrent connection limit
+    ConnectionLimit,
+    /// The client exceeded its request rate limit
+    RateLimited,
+    /// The client did not send request headers in time
+    HeaderTimeout,
+}
+
+impl BlockReason {
+    /// Returns the reason as used in event logs
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BlockReason::IpDenied => "ip_denied",
+            BlockReason::ConnectionLimit => "connection_limit",
+            BlockReason::RateLimited => "rate_limited",
+            BlockReason::HeaderTimeout => "header_timeout",
+        }
+    }
+}
+
+impl fmt::Display for BlockReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Counters of connections and requests handled by the protections
+#[derive(Debug, Default)]
+pub struct ProtectionMetrics {
+    active_connections: AtomicU64,
+    accepted_connections: AtomicU64,
+    denied_connections: AtomicU64,
+    connection_limit_rejections: AtomicU64,
+    rate_limited_requests: AtomicU64,
+    header_timeouts: AtomicU64,
+}
+
+impl ProtectionMetrics {
+    /// Returns the current value of each counter
+    pub fn snapshot(&self) -> ProtectionStats {
+        ProtectionStats {
+            active_connections: self.active_connections.load(Ordering::Relaxed),
+            accepted_connections: self.accepted_connections.load(Ordering::Relaxed),
+            denied_connections: self.denied_connections.load(Ordering::Relaxed),
+            connection_limit_rejections: self.connection_limit_rejections.load(Ordering::Relaxed),
+            rate_limited_requests: self.rate_limited_requests.load(Ordering::Relaxed),
+            header_timeouts: self.header_timeouts.load(Ordering::Relaxed),
+        }
+    }
+
+    fn record(&self, reason: BlockReason) {
+        let counter = match reason {
+            BlockReason::IpDenied => &self.denied_connections,
+            BlockReason::ConnectionLimit => &self.connection_limit_rejections,
+            BlockReason::RateLimited => &self.rate_limited_requests,
+            BlockReason::HeaderTimeout => &self.header_timeouts,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Point-in-time values of the [`ProtectionMetrics`] counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, ToSchema)]
+pub struct ProtectionStats {
+    /// Connections currently open
+    pub active_connections: u64,
+    /// Connections accepted since the server started
+    pub accepted_connections: u64,
+    /// Connections refused because of the IP allow or deny list
+    pub denied_connections: u64,
+    /// Connections refused because the server was at its connection limit
+    pub connection_limit_rejections: u64,
+    /// Requests refused because the client exceeded its rate limit
+    pub rate_limited_requests: u64,
+    /// Connections closed because request headers were not sent in time
+    pub header_timeouts: u64,
+}
+
+/// IP allow and deny lists
+#[derive(Debug, Clone, Default)]
+pub struct IpFilter {
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+}
+
+impl IpFilter {
+    /// Parse allow and deny lists of addresses or CIDR ranges
+    ///
+    /// An empty allow list allows every address that is not denied.
+    pub fn new(allow: &[String], deny: &[String]) -> Result<Self> {
+        Ok(Self {
+            allow: parse_networks(allow)?,
+            deny: parse_networks(deny)?,
+        })
+    }
+
+    /// Whether a client IP may connect
+    ///
+    /// The deny list takes precedence over the allow list.
+    pub fn is_allowed(&self, ip: IpAddr) -> bool {
+        let ip = canonical_ip(ip);
+        if self.deny.iter().any(|net| net.contains(&ip)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|net| net.contains(&ip))
+    }
+}
+
+fn parse_networks(entries: &[String]) -> Result<Vec<IpNet>> {
+    entries
+        .iter()
+        .map(|entry| {
+            let entry = entry.trim();
+            entry
+                .parse::<IpNet>()
+                .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| Error::Config(format!("Invalid IP address or CIDR range: {}", entry)))
+        })
+        .collect()
+}
+
+/// Treat IPv4 clients of a dual-stack listener as IPv4 addresses
+fn canonical_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        v4 => v4,
+    }
+}
+
+/// Fixed-window request counter per client IP
+#[derive(Debug)]
+pub struct RateLimiter {
+    max_requests: u32,
+    window: Duration,
+    windows: Mutex<HashMap<IpAddr, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    /// Create a rate limiter from its configuration
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            max_requests: config.max_requests,
+            window: Duration::from_secs(config.window_secs.max(1)),
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Count a request from a client IP, returning whether it is within the limit
+    pub fn check(&self, ip: IpAddr) -> bool {
+        self.check_at(canonical_ip(ip), Instant::now())
+    }
+
+    fn check_at(&self, ip: IpAddr, now: Instant) -> bool {
+        let mut windows = self.windows.lock().unwrap();
+        if windows.len() >= RATE_LIMIT_PRUNE_THRESHOLD {
+            let window = self.window;
+            windows.retain(|_, (start, _)| now.duration_since(*start) < window);
+        }
+
+        let (start, count) = windows.entry(ip).or_insert((now, 0));
+        if now.duration_since(*start) >= self.window {
+            *start = now;
+            *count = 0;
+        }
+        if *count >= self.max_requests {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+}
+
+/// An open connection, counted until it is dropped
+#[derive(Debug)]
+pub struct ConnectionGuard {
+    _permit: Option<OwnedSemaphorePermit>,
+    metrics: Arc<ProtectionMetrics>,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.metrics
+            .active_connections
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// The protections applied to each connection and request
+pub struct Protection {
+    ip_filter: IpFilter,
+    rate_limiter: Option<RateLimiter>,
+    connections: Option<Arc<Semaphore>>,
+    header_read_timeout: Option<Duration>,
+    metrics: Arc<ProtectionMetrics>,
+    event_bus: Arc<EventBus>,
+}
+
+impl Protection {
+    /// Build the protections configured for a server
+    pub fn new(
+        config: &TapHttpConfig,
+        metrics: Arc<ProtectionMetrics>,
+        event_bus: Arc<EventBus>,
+    ) -> Result<Self> {
+        if config.max_connections == Some(0) {
+            return Err(Error::Config(
+                "max_connections must be greater than zero".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            ip_filter: IpFilter::new(&config.ip_allowlist, &config.ip_denylist)?,
+            rate_limiter: config.rate_limit.as_ref().map(RateLimiter::new),
+            connections: config
+                .max_connections
+                .map(|max| Arc::new(Semaphore::new(max))),
+            header_read_timeout: config.header_read_timeout(),
+            metrics,
+            event_bus,
+        })
+    }
+
+    /// Time allowed for a client to send request headers
+    pub fn header_read_timeout(&self) -> Option<Duration> {
+        self.header_read_timeout
+    }
+
+    /// Admit a newly accepted connection
+    ///
+    /// Returns `None` when the client IP may not connect or the server is at
+    /// its connection limit, in which case the connection should be closed.
+    pub async fn admit(&self, ip: IpAddr) -> Option<ConnectionGuard> {
+        if !self.ip_filter.is_allowed(ip) {
+            self.block(ip, BlockReason::IpDenied).await;
+            return None;
+        }
+
+        let permit = match &self.connections {
+            Some(connections) => match connections.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => {
+                    self.block(ip, BlockReason::ConnectionLimit).await;
+                    return None;
+                }
+            },
+            None => None,
+        };
+
+        self.metrics
+            .accepted_connections
+            .fetch_add(1, Ordering::Relaxed);
+        self.metrics
+            .active_connections
+            .fetch_add(1, Ordering::Relaxed);
+        Some(ConnectionGuard {
+            _permit: permit,
+            metrics: self.metrics.clone(),
+        })
+    }
+
+    /// Count a request against the rate limit of its client IP
+    ///
+    /// Returns `false` when the request should be refused.
+    pub async fn allow_request(&self, ip: IpAddr) -> bool {
+        match &self.rate_limiter {
+            Some(rate_limiter) if !rate_limiter.check(ip) => {
+                self.block(ip, BlockReason::RateLimited).await;
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Record a connection closed because its request headers timed out
+    pub async fn header_timed_out(&self, ip: IpAddr) {
+        self.block(ip, BlockReason::HeaderTimeout).await;
+    }
+
+    async fn block(&self, ip: IpAddr, reason: BlockReason) {
+        warn!("Blocked {}: {}", ip, reason);
+        self.metrics.record(reason);
+        self.event_bus
+            .publish_request_blocked(ip.to_string(), reason)
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(entries: &[&str]) -> Vec<String> {
+        entries.iter().map(|e| e.to_string()).collect()
+    }
+
+    #[test]
+    fn test_ip_filter() {
+        let filter =
+            IpFilter::new(&strings(&["10.0.0.0/8", "::1"]), &strings(&["10.0.0.13"])).unwrap();
+
+        assert!(filter.is_allowed("10.1.2.3".parse().unwrap()));
+        assert!(filter.is_allowed("::1".parse().unwrap()));
+        assert!(filter.is_allowed("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!filter.is_allowed("10.0.0.13".parse().unwrap()));
+        assert!(!filter.is_allowed("192.168.1.1".parse().unwrap()));
+
+        let open = IpFilter::new(&[], &strings(&["192.168.0.0/16"])).unwrap();
+        assert!(open.is_allowed("8.8.8.8".parse().unwrap()));
+        assert!(!open.is_allowed("192.168.1.1".parse().unwrap()));
+
+        assert!(IpFilter::new(&strings(&["not-an-ip"]), &[]).is_err());
+    }
+
+    #[test]
+    fn test_rate_limiter_window() {
+        let limiter = RateLimiter::new(&RateLimitConfig {
+            max_requests: 2,
+            window_secs: 10,
+        });
+        let client: IpAddr = "203.0.113.7".parse().unwrap();
+        let other: IpAddr = "203.0.113.8".parse().unwrap();
+        let start = Instant::now();
+
+        assert!(limiter.check_at(client, start));
+        assert!(limiter.check_at(client, start + Duration::from_secs(1)));
+        assert!(!limiter.check_at(client, start + Duration::from_secs(2)));
+        assert!(limiter.check_at(other, start + Duration::from_secs(2)));
+        assert!(limiter.check_at(client, start + Duration::from_secs(10)));
+    }
+}
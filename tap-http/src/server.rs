@@ -4,6 +4,8 @@
 //! Protocol (TAP). The server exposes endpoints for:
 //!
 //! - Processing DIDComm messages for TAP operations
+//! - Long-polling inboxes for counterparties that cannot accept inbound HTTP
+//! - Listing, approving and rejecting the actions awaiting a person's approval
 //! - Health checks for monitoring system availability
 //!
 //! The server is built using the Warp web framework and provides graceful shutdown capabilities.
@@ -13,7 +15,9 @@
 //! - HTTP/WebSocket messaging for DIDComm transport
 //! - Message validation for TAP protocol compliance
 //! - Configurable host, port, and endpoint paths
-//! - Support for optional TLS encryption
+//! - Support for optional TLS encryption, with certificates reloaded without
+//!   dropping connections
+//! - IP allow/deny lists, per-IP rate limits, connection caps and header read timeouts
 //! - Graceful shutdown handling
 //! - Health check monitoring endpoint
 //!
@@ -23,8 +27,10 @@
 //!
 //! - Host address and port
 //! - DIDComm endpoint path
-//! - TLS configuration (certificate and key paths)
-//! - Rate limiting options
+//! - TLS configuration (certificate and key paths, minimum version, cipher
+//!   suites and reload interval)
+//! - Connection protection: IP allow/deny lists, per-IP rate limits,
+//!   concurrent connection caps and header read timeouts
 //! - Request timeout settings
 //!
 //! # Example
@@ -62,24 +68,38 @@
 //! }
 //! ```
 
+use crate::attestation::ATTESTATION_PATH;
 use crate::config::TapHttpConfig;
 use crate::error::{Error, Result};
 use crate::event::{EventBus, EventLogger};
-use crate::handler::{handle_didcomm, handle_health_check, handle_well_known_did};
+use crate::handler::{
+    handle_approve_pending_action, handle_authorization_callback, handle_forwarded_didcomm,
+    handle_health_check, handle_inbox_ack, handle_inbox_poll, handle_list_pending_actions,
+    handle_node_attestation, handle_openapi, handle_reject_pending_action, handle_well_known_did,
+    InboxAck, InboxPollQuery, PendingActionDecision, PendingActionsQuery,
+};
+use crate::protection::{Protection, ProtectionMetrics, ProtectionStats};
+use crate::tls::TlsReloader;
 use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tap_node::TapNode;
-use tokio::sync::oneshot;
-use tracing::{error, info, warn};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::{oneshot, watch};
+use tracing::{debug, error, info, warn};
+use warp::hyper::server::conn::Http;
+use warp::hyper::service::{service_fn, Service};
+use warp::hyper::{Body, Request};
 use warp::{Filter, Rejection, Reply};
 
-// Rate limiter will be implemented in the future update
-
 /// TAP HTTP server for handling DIDComm messages.
 ///
 /// This server implementation provides endpoints for:
 /// - `/didcomm` - For processing DIDComm messages via the TAP protocol
+/// - `/inbox/poll` and `/inbox/ack` - For remote agents that poll for their messages
+/// - `/actions` - For wallets approving or rejecting the actions awaiting
+///   approval, when a token is configured
 /// - `/health` - For checking the server's operational status
 ///
 /// The server requires a configuration and a TapNode instance to function.
@@ -96,6 +116,12 @@ pub struct TapHttpServer {
 
     /// Event bus for tracking server events.
     event_bus: Arc<EventBus>,
+
+    /// Counters of connections and requests handled by the protections.
+    protection_metrics: Arc<ProtectionMetrics>,
+
+    /// Current TLS certificate while the server runs with TLS.
+    tls: Option<Arc<TlsReloader>>,
 }
 
 impl TapHttpServer {
@@ -108,16 +134,6 @@ impl TapHttpServer {
     /// # Returns
     /// A new TapHttpServer instance that can be started with the `start` method
     pub fn new(config: TapHttpConfig, node: TapNode) -> Self {
-        // Log if rate limiting is configured but not implemented yet
-        if config.rate_limit.is_some() {
-            warn!("Rate limiting is configured but not yet implemented");
-        }
-
-        // Log if TLS is configured but not implemented yet
-        if config.tls.is_some() {
-            warn!("TLS is configured but not yet fully implemented");
-        }
-
         // Create the event bus
         let event_bus = Arc::new(EventBus::new());
 
@@ -133,6 +149,8 @@ impl TapHttpServer {
             node: Arc::new(node),
             shutdown_tx: None,
             event_bus,
+            protection_metrics: Arc::new(ProtectionMetrics::default()),
+            tls: None,
         }
     }
 
@@ -172,18 +190,161 @@ impl TapHttpServer {
         let didcomm_route = warp::path(endpoint_path)
             .and(warp::post())
             .and(warp::header::optional::<String>("content-type"))
+            .and(warp::header::optional::<String>("tap-forwarded-by"))
             .and(warp::body::content_length_limit(1024 * 1024))
             .and(warp::body::bytes())
             .and(with_node(node.clone()))
             .and(with_event_bus(event_bus.clone()))
-            .and_then(handle_didcomm);
+            .and_then(handle_forwarded_didcomm);
+
+        // Authorization callback endpoint, `{authorization_endpoint}/{nonce}`
+        let authorization_path = self
+            .config
+            .authorization_endpoint
+            .trim_start_matches('/')
+            .to_string();
+        let authorization_route = warp::path(authorization_path)
+            .and(warp::path::param::<String>())
+            .and(warp::path::end())
+            .and(warp::get().or(warp::post()).unify())
+            .and(warp::method())
+            .and(with_node(node.clone()))
+            .and(with_event_bus(event_bus.clone()))
+            .and_then(handle_authorization_callback);
+
+        // Inbox endpoints, `{inbox_endpoint}/poll` and `{inbox_endpoint}/ack`
+        let inbox_path = self
+            .config
+            .inbox_endpoint
+            .trim_start_matches('/')
+            .to_string();
+        let max_wait = self.config.inbox_max_wait();
+        let inbox_poll_route = warp::path(inbox_path.clone())
+            .and(warp::path("poll"))
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(warp::query::<InboxPollQuery>())
+            .and(warp::any().map(move || max_wait))
+            .and(with_node(node.clone()))
+            .and(with_event_bus(event_bus.clone()))
+            .and_then(handle_inbox_poll);
+        let inbox_ack_route = warp::path(inbox_path)
+            .and(warp::path("ack"))
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(warp::body::content_length_limit(1024))
+            .and(warp::body::json::<InboxAck>())
+            .and(with_node(node.clone()))
+            .and(with_event_bus(event_bus.clone()))
+            .and_then(handle_inbox_ack);
+
+        // Pending actions endpoints, `{actions_endpoint}` and
+        // `{actions_endpoint}/{id}/approve|reject`, when a token is set
+        let actions_path = self
+            .config
+            .actions_endpoint
+            .trim_start_matches('/')
+            .to_string();
+        let actions_token = self.config.actions_token.clone().map(Arc::new);
+        if actions_token.is_some() {
+            info!(
+                "Pending actions API enabled at {}",
+                self.config.actions_endpoint
+            );
+        }
+        let with_actions_token = warp::any()
+            .and_then(move || {
+                let token = actions_token.clone();
+                async move { token.ok_or_else(warp::reject::not_found) }
+            })
+            .boxed();
+        let actions_list_route = warp::path(actions_path.clone())
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(warp::query::<PendingActionsQuery>())
+            .and(with_actions_token.clone())
+            .and(with_node(node.clone()))
+            .and(with_event_bus(event_bus.clone()))
+            .and_then(handle_list_pending_actions);
+        let actions_approve_route = warp::path(actions_path.clone())
+            .and(warp::path::param::<String>())
+            .and(warp::path("approve"))
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(warp::body::content_length_limit(16 * 1024))
+            .and(warp::body::json::<PendingActionDecision>())
+            .and(with_actions_token.clone())
+            .and(with_node(node.clone()))
+            .and(with_event_bus(event_bus.clone()))
+            .and_then(handle_approve_pending_action);
+        let actions_reject_route = warp::path(actions_path)
+            .and(warp::path::param::<String>())
+            .and(warp::path("reject"))
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(warp::body::content_length_limit(16 * 1024))
+            .and(warp::body::json::<PendingActionDecision>())
+            .and(with_actions_token)
+            .and(with_node(node.clone()))
+            .and(with_event_bus(event_bus.clone()))
+            .and_then(handle_reject_pending_action);
+        // Boxed so that the futures of the combined routes stay small
+        let actions_route = actions_list_route
+            .or(actions_approve_route)
+            .or(actions_reject_route)
+            .boxed();
 
         // Health check endpoint
+        let protection_metrics = self.protection_metrics.clone();
         let health_route = warp::path("health")
             .and(warp::get())
+            .and(with_node(node.clone()))
+            .and(warp::any().map(move || protection_metrics.clone()))
             .and(with_event_bus(event_bus.clone()))
             .and_then(handle_health_check);
 
+        // OpenAPI document of the endpoints enabled by the configuration
+        let openapi_document = Arc::new(
+            serde_json::to_value(crate::openapi::openapi(&self.config))
+                .map_err(|e| Error::Config(format!("Failed to build OpenAPI document: {}", e)))?,
+        );
+        let openapi_route = warp::path("openapi.json")
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(warp::any().map(move || openapi_document.clone()))
+            .and(with_event_bus(event_bus.clone()))
+            .and_then(handle_openapi);
+
+        // Signed node attestation at /.well-known/tap-node, when enabled
+        let enable_attestation = self.config.enable_attestation;
+        if enable_attestation {
+            info!("Node attestation enabled at {}", ATTESTATION_PATH);
+        }
+        let attestation_config = Arc::new(self.config.clone());
+        let attestation_route = warp::path(".well-known")
+            .and(warp::path("tap-node"))
+            .and(warp::path::end())
+            .and(warp::get())
+            .and_then(move || async move {
+                if enable_attestation {
+                    Ok(())
+                } else {
+                    Err(warp::reject::not_found())
+                }
+            })
+            .untuple_one()
+            .and(warp::header::optional::<String>("host"))
+            .and(warp::header::optional::<String>("x-forwarded-proto"))
+            .and(warp::any().map(move || attestation_config.clone()))
+            .and(with_node(node.clone()))
+            .and(with_event_bus(event_bus.clone()))
+            .and_then(handle_node_attestation);
+
         // Optionally add /.well-known/did.json for did:web hosting
         let enable_web_did = self.config.enable_web_did;
 
@@ -203,7 +364,13 @@ impl TapHttpServer {
                 .and_then(handle_well_known_did);
 
             let routes = didcomm_route
+                .or(authorization_route)
+                .or(inbox_poll_route)
+                .or(inbox_ack_route)
+                .or(actions_route)
                 .or(health_route)
+                .or(openapi_route)
+                .or(attestation_route)
                 .or(well_known_route)
                 .with(warp::log("tap_http"))
                 .with(warp::reply::with::header(
@@ -223,7 +390,13 @@ impl TapHttpServer {
 
         // Combine routes without well-known endpoint
         let routes = didcomm_route
+            .or(authorization_route)
+            .or(inbox_poll_route)
+            .or(inbox_ack_route)
+            .or(actions_route)
             .or(health_route)
+            .or(openapi_route)
+            .or(attestation_route)
             .with(warp::log("tap_http"))
             .with(warp::reply::with::header(
                 "X-Content-Type-Options",
@@ -250,6 +423,7 @@ impl TapHttpServer {
     pub async fn stop(&mut self) -> Result<()> {
         if let Some(tx) = self.shutdown_tx.take() {
             let _ = tx.send(());
+            self.tls = None;
             info!("Sent shutdown signal to TAP HTTP server");
         } else {
             warn!("TAP HTTP server is not running");
@@ -278,7 +452,29 @@ impl TapHttpServer {
         &self.event_bus
     }
 
-    /// Bind and spawn the warp server with the given routes.
+    /// Returns the counters of connections and requests handled by the
+    /// server's protections.
+    pub fn protection_stats(&self) -> ProtectionStats {
+        self.protection_metrics.snapshot()
+    }
+
+    /// Reloads the TLS certificate and key for new connections.
+    ///
+    /// Open connections keep the certificate they were established with. On
+    /// error the previous certificate stays in use.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the certificate and key were reloaded
+    /// * `Err(Error)` - If the server is not running with TLS, or the
+    ///   certificate or key could not be loaded
+    pub fn reload_tls(&self) -> Result<()> {
+        match &self.tls {
+            Some(tls) => tls.reload(),
+            None => Err(Error::Tls("Server is not running with TLS".to_string())),
+        }
+    }
+
+    /// Bind the listener and spawn the accept loop serving the given routes.
     async fn spawn_server<F>(
         &mut self,
         routes: F,
@@ -289,29 +485,191 @@ impl TapHttpServer {
         F: Filter<Error = Infallible> + Clone + Send + Sync + 'static,
         F::Extract: Reply,
     {
+        let protection = Arc::new(Protection::new(
+            &self.config,
+            self.protection_metrics.clone(),
+            event_bus.clone(),
+        )?);
+
+        let tls = match &self.config.tls {
+            Some(tls_config) => Some(Arc::new(TlsReloader::new(tls_config.clone())?)),
+            None => None,
+        };
+
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| Error::Http(format!("Failed to bind {}: {}", addr, e)))?;
+
         let (tx, rx) = oneshot::channel::<()>();
         self.shutdown_tx = Some(tx);
+        self.tls = tls.clone();
 
-        info!("Starting TAP HTTP server on {}", addr);
+        info!(
+            "Starting TAP HTTP server on {}{}",
+            addr,
+            if tls.is_some() { " with TLS" } else { "" }
+        );
 
         self.event_bus
             .publish_server_started(addr.to_string())
             .await;
 
-        let event_bus_clone = event_bus.clone();
-        let (_, server) = warp::serve(routes).bind_with_graceful_shutdown(addr, async move {
-            rx.await.ok();
-            info!("Shutting down TAP HTTP server");
-            event_bus_clone.publish_server_stopped().await;
-        });
-
-        tokio::spawn(server);
+        tokio::spawn(serve_connections(
+            listener,
+            warp::service(routes),
+            protection,
+            tls,
+            event_bus,
+            rx,
+        ));
 
         info!("TAP HTTP server started on {}", addr);
         Ok(())
     }
+}
 
-    // Rate limiting functionality will be implemented in a future update
+/// Accept connections until the shutdown signal, then wait for open
+/// connections to finish their requests.
+async fn serve_connections<S>(
+    listener: TcpListener,
+    service: S,
+    protection: Arc<Protection>,
+    tls: Option<Arc<TlsReloader>>,
+    event_bus: Arc<EventBus>,
+    mut shutdown_rx: oneshot::Receiver<()>,
+) where
+    S: Service<Request<Body>, Response = warp::reply::Response, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send,
+{
+    let mut http = Http::new();
+    if let Some(timeout) = protection.header_read_timeout() {
+        http.http1_header_read_timeout(timeout);
+    }
+    let http = Arc::new(http);
+    let (closing_tx, closing_rx) = watch::channel(false);
+
+    if let Some(tls) = &tls {
+        tls.clone().spawn(closing_rx.clone());
+    }
+
+    loop {
+        let (stream, peer) = tokio::select! {
+            _ = &mut shutdown_rx => break,
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("Failed to accept connection: {}", e);
+                    continue;
+                }
+            },
+        };
+
+        let Some(guard) = protection.admit(peer.ip()).await else {
+            continue;
+        };
+
+        let http = http.clone();
+        let service = service.clone();
+        let protection = protection.clone();
+        let closing_rx = closing_rx.clone();
+        let acceptor = tls.as_ref().map(|tls| tls.acceptor());
+        tokio::spawn(async move {
+            match acceptor {
+                Some(acceptor) => {
+                    // The handshake is bounded like reading request headers
+                    let handshake = acceptor.accept(stream);
+                    let stream = match protection.header_read_timeout() {
+                        Some(timeout) => match tokio::time::timeout(timeout, handshake).await {
+                            Ok(stream) => stream,
+                            Err(_) => {
+                                protection.header_timed_out(peer.ip()).await;
+                                return;
+                            }
+                        },
+                        None => handshake.await,
+                    };
+                    match stream {
+                        Ok(stream) => {
+                            serve_connection(http, stream, peer, service, protection, closing_rx)
+                                .await
+                        }
+                        Err(e) => debug!("TLS handshake with {} failed: {}", peer, e),
+                    }
+                }
+                None => serve_connection(http, stream, peer, service, protection, closing_rx).await,
+            }
+            drop(guard);
+        });
+    }
+
+    info!("Shutting down TAP HTTP server");
+    event_bus.publish_server_stopped().await;
+
+    // Ask open connections to close once their current request completes
+    let _ = closing_tx.send(true);
+    drop(closing_rx);
+    closing_tx.closed().await;
+}
+
+/// Serve the requests of one connection, applying the per-IP rate limit.
+async fn serve_connection<I, S>(
+    http: Arc<Http>,
+    stream: I,
+    peer: SocketAddr,
+    service: S,
+    protection: Arc<Protection>,
+    mut closing_rx: watch::Receiver<bool>,
+) where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Service<Request<Body>, Response = warp::reply::Response, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send,
+{
+    let request_protection = protection.clone();
+    let per_request = service_fn(move |request| {
+        let protection = request_protection.clone();
+        let mut service = service.clone();
+        async move {
+            if !protection.allow_request(peer.ip()).await {
+                return Ok(Error::RateLimit(
+                    "Too many requests, please try again later".to_string(),
+                )
+                .to_response());
+            }
+            service.call(request).await
+        }
+    });
+
+    let connection = http.serve_connection(stream, per_request);
+    tokio::pin!(connection);
+    let result = tokio::select! {
+        result = connection.as_mut() => result,
+        _ = closing_rx.changed() => {
+            connection.as_mut().graceful_shutdown();
+            connection.await
+        }
+    };
+
+    if let Err(e) = result {
+        if is_header_timeout(&e) {
+            protection.header_timed_out(peer.ip()).await;
+        } else {
+            debug!("Connection from {} closed with error: {}", peer, e);
+        }
+    }
+}
+
+/// Whether a connection failed because its request headers were not read in
+/// time. hyper 0.14 reports this only through the error message.
+fn is_header_timeout(error: &warp::hyper::Error) -> bool {
+    error
+        .to_string()
+        .starts_with("read header from client timeout")
 }
 
 /// Helper function to provide the TAP Node to route handlers.
@@ -328,11 +686,6 @@ fn with_event_bus(
     warp::any().map(move || event_bus.clone())
 }
 
-/// Custom rejection for rate limited requests
-#[derive(Debug)]
-struct RateLimitedError;
-impl warp::reject::Reject for RateLimitedError {}
-
 /// Handler for rejections.
 async fn handle_rejection(err: Rejection) -> std::result::Result<impl Reply, Infallible> {
     use crate::error::Error;
@@ -353,10 +706,6 @@ async fn handle_rejection(err: Rejection) -> std::result::Result<impl Reply, Inf
         // Method not allowed
         let err = Error::Http("Method not allowed".to_string());
         err.to_response()
-    } else if err.find::<RateLimitedError>().is_some() {
-        // Rate limiting
-        let err = Error::RateLimit("Too many requests, please try again later".to_string());
-        err.to_response()
     } else {
         // Unhandled error
         error!("Unhandled rejection: {:?}", err);
@@ -0,0 +1,439 @@
+//! Client mode for pulling messages from a remote gateway.
+//!
+//! Nodes that cannot accept inbound HTTP connections can pull their messages
+//! from a gateway instead. The gateway assigns each queued message an
+//! increasing sequence number, and the [`GatewaySyncClient`] keeps the number
+//! of the last message it processed as a cursor in a file. After a reconnect
+//! or restart it resumes from that cursor, so no messages are skipped, and it
+//! drops any message at or below the cursor that the gateway sends again, so
+//! none are processed twice.
+//!
+//! # Gateway Protocol
+//!
+//! - `GET {gateway}/messages?after=<cursor>&limit=<n>` returns the next
+//!   messages in ascending sequence order:
+//!   `{"messages": [{"seq": 3, "message": {...}}]}`. Each `message` is a
+//!   signed or encrypted DIDComm message as it would be posted to `/didcomm`.
+//! - `POST {gateway}/messages/ack` with `{"cursor": 3}` acknowledges every
+//!   message up to and including that sequence number, after which the
+//!   gateway may discard them.
+//!
+//! Both requests carry `Authorization: Bearer <token>` when a token is
+//! configured.
+//!
+//! The cursor is saved after each message is processed and before it is
+//! acknowledged. Messages the node fails to process are recorded as failed in
+//! the received table and are not retried, except those it rejects as
+//! overloaded, which are fetched again on the next sync.
+
+use crate::error::{Error, Result};
+use reqwest::Client as ReqwestClient;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tap_node::storage::SourceType;
+use tap_node::{IngestOutcome, TapNode};
+use tokio::sync::oneshot;
+use tracing::{debug, info, warn};
+
+/// Configuration for pulling messages from a gateway.
+#[derive(Debug, Clone)]
+pub struct GatewaySyncConfig {
+    /// Base URL of the gateway.
+    pub gateway_url: String,
+
+    /// File in which the delivery cursor is kept.
+    pub cursor_path: PathBuf,
+
+    /// Bearer token sent to the gateway.
+    pub auth_token: Option<String>,
+
+    /// Maximum number of messages fetched per request.
+    pub batch_size: u32,
+
+    /// Delay between polls when the gateway has no new messages.
+    pub poll_interval: Duration,
+
+    /// Upper bound for the delay between reconnection attempts.
+    pub max_backoff: Duration,
+
+    /// Request timeout.
+    pub request_timeout: Duration,
+}
+
+impl GatewaySyncConfig {
+    /// Creates a configuration with default batching and timing.
+    pub fn new(gateway_url: impl Into<String>, cursor_path: impl Into<PathBuf>) -> Self {
+        Self {
+            gateway_url: gateway_url.into().trim_end_matches('/').to_string(),
+            cursor_path: cursor_path.into(),
+            auth_token: None,
+            batch_size: 50,
+            poll_interval: Duration::from_secs(5),
+            max_backoff: Duration::from_secs(60),
+            request_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// A message queued at the gateway.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GatewayMessage {
+    /// Sequence number assigned by the gateway.
+    pub seq: u64,
+
+    /// The DIDComm message.
+    pub message: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageBatch {
+    messages: Vec<GatewayMessage>,
+}
+
+#[derive(Debug, Serialize)]
+struct Ack {
+    cursor: u64,
+}
+
+/// Cursor file contents.
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedCursor {
+    gateway_url: String,
+    cursor: u64,
+}
+
+/// Pulls messages from a gateway into a TAP Node.
+pub struct GatewaySyncClient {
+    config: GatewaySyncConfig,
+    client: ReqwestClient,
+    node: Arc<TapNode>,
+    cursor: u64,
+}
+
+impl GatewaySyncClient {
+    /// Creates a client, resuming from the cursor saved for the gateway.
+    ///
+    /// A cursor saved for a different gateway is ignored.
+    pub fn new(config: GatewaySyncConfig, node: Arc<TapNode>) -> Result<Self> {
+        let client = ReqwestClient::builder()
+            .timeout(config.request_timeout)
+            .build()
+            .map_err(|e| Error::Config(format!("Failed to create HTTP client: {}", e)))?;
+
+        let cursor = match std::fs::read_to_string(&config.cursor_path) {
+            Ok(contents) => {
+                let saved: SavedCursor = serde_json::from_str(&contents).map_err(|e| {
+                    Error::Json(format!(
+                        "Invalid cursor file {}: {}",
+                        config.cursor_path.display(),
+                        e
+                    ))
+                })?;
+                if saved.gateway_url == config.gateway_url {
+                    saved.cursor
+                } else {
+                    warn!(
+                        "Ignoring cursor saved for gateway {}, starting from the beginning",
+                        saved.gateway_url
+                    );
+                    0
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            config,
+            client,
+            node,
+            cursor,
+        })
+    }
+
+    /// Sequence number of the last processed message.
+    pub fn cursor(&self) -> u64 {
+        self.cursor
+    }
+
+    /// Fetches and processes one batch of messages.
+    ///
+    /// Returns the number of new messages processed.
+    pub async fn sync_once(&mut self) -> Result<usize> {
+        let mut batch = self.fetch().await?;
+        batch.sort_by_key(|entry| entry.seq);
+
+        let mut processed = 0;
+        for entry in batch {
+            if entry.seq <= self.cursor {
+                debug!("Skipping already processed message {}", entry.seq);
+                continue;
+            }
+
+            match self
+                .node
+                .receive_message_from_source(
+                    entry.message,
+                    SourceType::Pickup,
+                    Some(&self.config.gateway_url),
+                )
+                .await
+            {
+                Ok(IngestOutcome::Rejected { code, reason }) if code.is_retryable() => {
+                    // Leave the cursor before this message to fetch it again
+                    warn!(
+                        "Node is overloaded, deferring gateway message {}: {}",
+                        entry.seq, reason
+                    );
+                    break;
+                }
+                Ok(IngestOutcome::Rejected { code, reason }) => {
+                    warn!(
+                        "Rejected gateway message {} ({}): {}",
+                        entry.seq, code, reason
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Failed to process gateway message {}: {}", entry.seq, e),
+            }
+
+            self.save_cursor(entry.seq)?;
+            processed += 1;
+        }
+
+        if processed > 0 {
+            if let Err(e) = self.ack().await {
+                // The next acknowledgement covers these messages as well
+                warn!(
+                    "Failed to acknowledge messages up to {}: {}",
+                    self.cursor, e
+                );
+            }
+        }
+
+        Ok(processed)
+    }
+
+    /// Pulls messages until `shutdown` fires, reconnecting with exponential
+    /// backoff when the gateway is unreachable.
+    pub async fn run(mut self, mut shutdown: oneshot::Receiver<()>) {
+        info!(
+            "Pulling messages from {} after cursor {}",
+            self.config.gateway_url, self.cursor
+        );
+
+        let mut backoff = self.config.poll_interval;
+        loop {
+            let delay = match self.sync_once().await {
+                Ok(0) => {
+                    backoff = self.config.poll_interval;
+                    self.config.poll_interval
+                }
+                Ok(processed) => {
+                    debug!("Processed {} gateway messages", processed);
+                    backoff = self.config.poll_interval;
+                    Duration::ZERO
+                }
+                Err(e) => {
+                    warn!(
+                        "Gateway {} unavailable, retrying in {:?}: {}",
+                        self.config.gateway_url, backoff, e
+                    );
+                    let delay = backoff;
+                    backoff = (backoff * 2).min(self.config.max_backoff);
+                    delay
+                }
+            };
+
+            tokio::select! {
+                _ = &mut shutdown => break,
+                _ = tokio::time::sleep(delay) => {}
+            }
+        }
+
+        info!("Stopped pulling messages at cursor {}", self.cursor);
+    }
+
+    async fn fetch(&self) -> Result<Vec<GatewayMessage>> {
+        let request = self
+            .client
+            .get(format!("{}/messages", self.config.gateway_url))
+            .query(&[
+                ("after", self.cursor.to_string()),
+                ("limit", self.config.batch_size.to_string()),
+            ]);
+
+        let response = self
+            .authorize(request)
+            .send()
+            .await
+            .map_err(|e| Error::Http(format!("Failed to fetch messages: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(Error::Http(format!(
+                "Gateway returned status {} for messages",
+                response.status()
+            )));
+        }
+
+        let batch: MessageBatch = response
+            .json()
+            .await
+            .map_err(|e| Error::Json(format!("Invalid message batch: {}", e)))?;
+        Ok(batch.messages)
+    }
+
+    async fn ack(&self) -> Result<()> {
+        let request = self
+            .client
+            .post(format!("{}/messages/ack", self.config.gateway_url))
+            .json(&Ack {
+                cursor: self.cursor,
+            });
+
+        let response = self
+            .authorize(request)
+            .send()
+            .await
+            .map_err(|e| Error::Http(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(Error::Http(format!(
+                "Gateway returned status {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.config.auth_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Saves the cursor, replacing the file atomically.
+    fn save_cursor(&mut self, cursor: u64) -> Result<()> {
+        let path = &self.config.cursor_path;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let saved = SavedCursor {
+            gateway_url: self.config.gateway_url.clone(),
+            cursor,
+        };
+        let contents = serde_json::to_string(&saved).map_err(|e| Error::Json(e.to_string()))?;
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, contents)?;
+        std::fs::rename(&tmp, path)?;
+
+        self.cursor = cursor;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+    use serde_json::json;
+    use tap_node::NodeConfig;
+    use tempfile::tempdir;
+
+    fn entry(seq: u64) -> serde_json::Value {
+        json!({
+            "seq": seq,
+            "message": {
+                "id": format!("msg-{}", seq),
+                "typ": "application/didcomm-plain+json",
+                "type": "https://didcomm.org/basicmessage/2.0/message",
+                "from": "did:example:alice",
+                "to": ["did:example:bob"],
+                "body": { "content": "hello" }
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_resumes_from_saved_cursor_without_duplicates() {
+        let mut gateway = mockito::Server::new_async().await;
+        let dir = tempdir().unwrap();
+        let config = GatewaySyncConfig::new(gateway.url(), dir.path().join("cursor.json"));
+        let node = Arc::new(TapNode::new(NodeConfig::default()));
+
+        let first = gateway
+            .mock("GET", "/messages")
+            .match_query(Matcher::UrlEncoded("after".into(), "0".into()))
+            .with_body(json!({ "messages": [entry(2), entry(1)] }).to_string())
+            .create_async()
+            .await;
+        let ack = gateway
+            .mock("POST", "/messages/ack")
+            .match_body(Matcher::Json(json!({ "cursor": 2 })))
+            .with_status(500)
+            .create_async()
+            .await;
+
+        let mut client = GatewaySyncClient::new(config.clone(), node.clone()).unwrap();
+        assert_eq!(client.sync_once().await.unwrap(), 2);
+        assert_eq!(client.cursor(), 2);
+        first.assert_async().await;
+        ack.assert_async().await;
+
+        // After a restart the unacknowledged message 2 is sent again
+        let second = gateway
+            .mock("GET", "/messages")
+            .match_query(Matcher::UrlEncoded("after".into(), "2".into()))
+            .with_body(json!({ "messages": [entry(2), entry(3)] }).to_string())
+            .create_async()
+            .await;
+        let ack = gateway
+            .mock("POST", "/messages/ack")
+            .match_body(Matcher::Json(json!({ "cursor": 3 })))
+            .create_async()
+            .await;
+
+        let mut client = GatewaySyncClient::new(config, node).unwrap();
+        assert_eq!(client.cursor(), 2);
+        assert_eq!(client.sync_once().await.unwrap(), 1);
+        assert_eq!(client.cursor(), 3);
+        second.assert_async().await;
+        ack.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_gateway_errors_keep_cursor() {
+        let mut gateway = mockito::Server::new_async().await;
+        let dir = tempdir().unwrap();
+        let cursor_path = dir.path().join("cursor.json");
+        std::fs::write(
+            &cursor_path,
+            json!({ "gateway_url": gateway.url(), "cursor": 7 }).to_string(),
+        )
+        .unwrap();
+
+        gateway
+            .mock("GET", "/messages")
+            .with_status(503)
+            .create_async()
+            .await;
+
+        let node = Arc::new(TapNode::new(NodeConfig::default()));
+        let mut client =
+            GatewaySyncClient::new(GatewaySyncConfig::new(gateway.url(), &cursor_path), node)
+                .unwrap();
+        assert!(client.sync_once().await.is_err());
+        assert_eq!(client.cursor(), 7);
+
+        // A cursor saved for another gateway does not apply
+        let node = Arc::new(TapNode::new(NodeConfig::default()));
+        let client = GatewaySyncClient::new(
+            GatewaySyncConfig::new("http://other.example", &cursor_path),
+            node,
+        )
+        .unwrap();
+        assert_eq!(client.cursor(), 0);
+    }
+}
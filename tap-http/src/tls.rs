@@ -0,0 +1,259 @@
+//! TLS termination for the TAP HTTP server.
+//!
+//! Production certificates rotate every few months, so the server does not
+//! load its certificate once at startup. A [`TlsReloader`] holds the current
+//! rustls configuration and replaces it when:
+//!
+//! - the certificate or key file changes, checked every
+//!   [`reload_interval_secs`](crate::config::TlsConfig::reload_interval_secs)
+//! - the process receives SIGHUP (on Unix)
+//! - [`TapHttpServer::reload_tls`](crate::TapHttpServer::reload_tls) is called
+//!
+//! Each accepted connection is handshaken with the configuration current at
+//! that moment, so connections established before a reload keep their
+//! certificate until they close and no connection is dropped. A certificate
+//! or key that fails to load is logged and the previous configuration stays
+//! in use.
+
+use crate::config::{TlsConfig, TlsVersion};
+use crate::error::{Error, Result};
+use rustls::crypto::{ring, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{ServerConfig, SupportedCipherSuite, SupportedProtocolVersion};
+use std::fs;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::SystemTime;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio_rustls::TlsAcceptor;
+use tracing::{debug, info, warn};
+
+/// Modification time and length of the certificate and key files
+type FilesVersion = [Option<(SystemTime, u64)>; 2];
+
+/// The current TLS configuration of a server, reloaded when its certificate
+/// or key changes
+pub struct TlsReloader {
+    config: TlsConfig,
+    current: RwLock<Arc<ServerConfig>>,
+    files_version: Mutex<FilesVersion>,
+}
+
+impl TlsReloader {
+    /// Load the certificate and key of a TLS configuration
+    pub fn new(config: TlsConfig) -> Result<Self> {
+        let files_version = files_version(&config);
+        let server_config = server_config(&config)?;
+        info!(
+            "Loaded TLS certificate {} (minimum version {:?})",
+            config.cert_path, config.min_version
+        );
+        Ok(Self {
+            config,
+            current: RwLock::new(Arc::new(server_config)),
+            files_version: Mutex::new(files_version),
+        })
+    }
+
+    /// Acceptor for a new connection, using the current certificate
+    pub fn acceptor(&self) -> TlsAcceptor {
+        TlsAcceptor::from(self.current.read().unwrap().clone())
+    }
+
+    /// Load the certificate and key again and use them for new connections
+    ///
+    /// On error the previous certificate stays in use.
+    pub fn reload(&self) -> Result<()> {
+        *self.files_version.lock().unwrap() = files_version(&self.config);
+        let server_config = server_config(&self.config)?;
+        *self.current.write().unwrap() = Arc::new(server_config);
+        info!("Reloaded TLS certificate {}", self.config.cert_path);
+        Ok(())
+    }
+
+    /// Reload the certificate and key if either file changed since they
+    /// were last loaded
+    ///
+    /// Returns whether they were reloaded.
+    pub fn reload_if_changed(&self) -> Result<bool> {
+        if *self.files_version.lock().unwrap() == files_version(&self.config) {
+            return Ok(false);
+        }
+        self.reload().map(|()| true)
+    }
+
+    /// Spawn a task reloading the certificate and key on SIGHUP and when
+    /// their files change, until `closing` changes
+    pub fn spawn(self: Arc<Self>, mut closing: watch::Receiver<bool>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            #[cfg(unix)]
+            let mut hangup =
+                match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+                    Ok(hangup) => Some(hangup),
+                    Err(e) => {
+                        warn!("Cannot reload TLS certificates on SIGHUP: {}", e);
+                        None
+                    }
+                };
+            let mut interval = self.config.reload_interval().map(tokio::time::interval);
+
+            loop {
+                let hangup_received = async {
+                    #[cfg(unix)]
+                    if let Some(hangup) = hangup.as_mut() {
+                        return hangup.recv().await;
+                    }
+                    std::future::pending::<Option<()>>().await
+                };
+                let interval_elapsed = async {
+                    match interval.as_mut() {
+                        Some(interval) => interval.tick().await,
+                        None => std::future::pending().await,
+                    }
+                };
+
+                let result = tokio::select! {
+                    _ = closing.changed() => break,
+                    _ = hangup_received => {
+                        info!("Received SIGHUP, reloading TLS certificates");
+                        self.reload().map(|()| true)
+                    }
+                    _ = interval_elapsed => self.reload_if_changed(),
+                };
+                if let Err(e) = result {
+                    warn!("Keeping the previous TLS certificate: {}", e);
+                }
+            }
+            debug!("Stopped reloading TLS certificates");
+        })
+    }
+}
+
+/// Build the rustls configuration of a TLS configuration
+pub fn server_config(config: &TlsConfig) -> Result<ServerConfig> {
+    let certs = CertificateDer::pem_file_iter(&config.cert_path)
+        .and_then(|certs| certs.collect::<std::result::Result<Vec<_>, _>>())
+        .map_err(|e| {
+            Error::Tls(format!(
+                "Failed to read certificate {}: {}",
+                config.cert_path, e
+            ))
+        })?;
+    if certs.is_empty() {
+        return Err(Error::Tls(format!(
+            "No certificate found in {}",
+            config.cert_path
+        )));
+    }
+    let key = PrivateKeyDer::from_pem_file(&config.key_path)
+        .map_err(|e| Error::Tls(format!("Failed to read key {}: {}", config.key_path, e)))?;
+
+    let mut provider = ring::default_provider();
+    if !config.cipher_suites.is_empty() {
+        provider.cipher_suites = config
+            .cipher_suites
+            .iter()
+            .map(|name| cipher_suite(&provider, name))
+            .collect::<Result<_>>()?;
+    }
+    let versions: &[&SupportedProtocolVersion] = match config.min_version {
+        TlsVersion::Tls12 => &[&rustls::version::TLS13, &rustls::version::TLS12],
+        TlsVersion::Tls13 => &[&rustls::version::TLS13],
+    };
+
+    let mut server_config = ServerConfig::builder_with_provider(Arc::new(provider))
+        .with_protocol_versions(versions)
+        .map_err(|e| Error::Tls(format!("Invalid TLS versions or cipher suites: {}", e)))?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| Error::Tls(format!("Invalid certificate or key: {}", e)))?;
+    server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(server_config)
+}
+
+/// Find a cipher suite of a provider by its IANA name
+fn cipher_suite(provider: &CryptoProvider, name: &str) -> Result<SupportedCipherSuite> {
+    provider
+        .cipher_suites
+        .iter()
+        .find(|suite| format!("{:?}", suite.suite()).eq_ignore_ascii_case(name))
+        .copied()
+        .ok_or_else(|| Error::Tls(format!("Unsupported cipher suite {}", name)))
+}
+
+fn files_version(config: &TlsConfig) -> FilesVersion {
+    [&config.cert_path, &config.key_path].map(|path| {
+        fs::metadata(path)
+            .and_then(|metadata| Ok((metadata.modified()?, metadata.len())))
+            .ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_certificate(dir: &TempDir) -> TlsConfig {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let cert_path = dir.path().join("cert.pem");
+        let key_path = dir.path().join("key.pem");
+        fs::write(&cert_path, certified.cert.pem()).unwrap();
+        fs::write(&key_path, certified.key_pair.serialize_pem()).unwrap();
+        TlsConfig::new(cert_path.to_string_lossy(), key_path.to_string_lossy())
+    }
+
+    #[test]
+    fn test_server_config_versions_and_cipher_suites() {
+        let dir = TempDir::new().unwrap();
+        let config = write_certificate(&dir);
+        let server = server_config(&config).unwrap();
+        assert_eq!(server.alpn_protocols, vec![b"http/1.1".to_vec()]);
+        assert_eq!(
+            server.crypto_provider().cipher_suites.len(),
+            ring::default_provider().cipher_suites.len()
+        );
+
+        let restricted = TlsConfig {
+            min_version: TlsVersion::Tls13,
+            cipher_suites: vec!["tls13_aes_256_gcm_sha384".to_string()],
+            ..config.clone()
+        };
+        let server = server_config(&restricted).unwrap();
+        assert_eq!(server.crypto_provider().cipher_suites.len(), 1);
+
+        // No TLS 1.3 suite left to negotiate
+        let mismatched = TlsConfig {
+            min_version: TlsVersion::Tls13,
+            cipher_suites: vec!["TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384".to_string()],
+            ..config.clone()
+        };
+        assert!(matches!(server_config(&mismatched), Err(Error::Tls(_))));
+
+        let unknown = TlsConfig {
+            cipher_suites: vec!["TLS_RSA_WITH_RC4_128_MD5".to_string()],
+            ..config.clone()
+        };
+        assert!(matches!(server_config(&unknown), Err(Error::Tls(_))));
+    }
+
+    #[test]
+    fn test_reload_keeps_previous_certificate_on_error() {
+        let dir = TempDir::new().unwrap();
+        let config = write_certificate(&dir);
+        let reloader = TlsReloader::new(config.clone()).unwrap();
+        assert!(!reloader.reload_if_changed().unwrap());
+
+        fs::write(&config.key_path, "not a key").unwrap();
+        let before = reloader.current.read().unwrap().clone();
+        assert!(reloader.reload_if_changed().is_err());
+        assert!(Arc::ptr_eq(&before, &reloader.current.read().unwrap()));
+        // Not retried until the files change again
+        assert!(!reloader.reload_if_changed().unwrap());
+
+        write_certificate(&dir);
+        assert!(reloader.reload_if_changed().unwrap());
+        assert!(!Arc::ptr_eq(&before, &reloader.current.read().unwrap()));
+    }
+}
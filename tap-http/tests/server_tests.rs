@@ -235,7 +235,7 @@ async fn test_didcomm_endpoint_content_types() {
         message
     );
 
-    // Test 2: Signed message content type (should fail with no agent, but pass validation)
+    // Test 2: Signed message content type (should pass validation)
     let signed_msg = json!({
         "payload": "eyJ0ZXN0IjoidGVzdCJ9",
         "signatures": [{
@@ -256,19 +256,16 @@ async fn test_didcomm_endpoint_content_types() {
         .await
         .unwrap();
 
-    // Should get 500 (no agent to process), not 400 (validation error)
+    // Should pass content type validation and be rejected by the node,
+    // as its signature cannot be verified
     let status = response.status();
     let body = response.text().await.unwrap();
     let json: serde_json::Value = serde_json::from_str(&body).unwrap();
-    assert_eq!(status, 500);
+    assert_eq!(status, 400);
     assert_eq!(json["status"], "error");
-    // The message should be a generic internal server error (no internal details leaked)
-    let message = json["message"].as_str().unwrap_or("");
-    assert!(
-        message.contains("Internal server error"),
-        "Expected 'Internal server error' but got: {}",
-        message
-    );
+    assert_eq!(json["outcome"], "rejected");
+    assert!(json["code"] == "unverified" || json["code"] == "malformed");
+    assert!(json.get("error").is_none());
 
     // Test 3: Invalid content type (should be rejected)
     let response = client
@@ -302,3 +299,966 @@ async fn test_didcomm_endpoint_content_types() {
     // Stop the server
     server.stop().await.expect("Server should stop");
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_authorization_callback_redeems_nonce_once() {
+    use std::sync::Arc;
+    use tap_agent::TapAgent;
+    use tap_msg::message::tap_message_trait::TapMessageBody;
+    use tap_msg::message::{Agent, Party, Transfer};
+    use tap_node::authorization::AuthorizationRequest;
+    use tap_node::state_machine::fsm::DecisionMode;
+    use tap_node::storage::{ChallengeStatus, Storage};
+
+    // Decisions are left to the authorization flow rather than auto-approved
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let mut node = TapNode::new(NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        decision_mode: DecisionMode::EventBus,
+        ..Default::default()
+    });
+    node.set_storage(Storage::new_in_memory().await.unwrap())
+        .await
+        .unwrap();
+
+    let (vasp_agent, vasp_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (wallet_agent, wallet_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(vasp_agent)).await.unwrap();
+    node.register_agent(Arc::new(wallet_agent)).await.unwrap();
+
+    let transfer = Transfer {
+        transaction_id: None,
+        asset: "eip155:1/erc20:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"
+            .parse()
+            .unwrap(),
+        amount: "100.00".to_string(),
+        originator: Some(Party::new("did:example:customer")),
+        beneficiary: Some(Party::new("did:example:merchant")),
+        agents: vec![
+            Agent::new(&wallet_did, "SourceAgent", "did:example:customer"),
+            Agent::new(&vasp_did, "DestinationAgent", "did:example:merchant"),
+        ],
+        memo: None,
+        settlement_id: None,
+        expiry: None,
+        transaction_value: None,
+        connection_id: None,
+        metadata: Default::default(),
+    };
+    let transfer_message = transfer
+        .to_didcomm_with_route(&wallet_did, [vasp_did.as_str()])
+        .unwrap();
+    let transaction_id = transfer_message.id.clone();
+    node.send_message(wallet_did.clone(), transfer_message)
+        .await
+        .unwrap();
+
+    let port = find_unused_port().expect("Unable to find unused port");
+    let config = TapHttpConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+        ..TapHttpConfig::default()
+    };
+    let callback_base_url = config.authorization_url(false);
+    let mut server = TapHttpServer::new(config, node);
+    server.start().await.expect("Server should start");
+    sleep(Duration::from_millis(500)).await;
+
+    let challenge = server
+        .node()
+        .request_authorization(
+            AuthorizationRequest::new(&vasp_did, &transaction_id, &wallet_did, callback_base_url)
+                .with_party_type("customer"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        challenge.authorization_url,
+        format!("http://127.0.0.1:{}/authorize/{}", port, challenge.nonce)
+    );
+
+    let client = reqwest::Client::new();
+    let response = client
+        .get(&challenge.authorization_url)
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["transaction_id"], transaction_id.as_str());
+    let message_id = body["message_id"].as_str().unwrap().to_string();
+
+    let stored = server
+        .node()
+        .storage()
+        .unwrap()
+        .get_authorization_challenge(&challenge.nonce)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.status, ChallengeStatus::Redeemed);
+    assert_eq!(stored.message_id.as_deref(), Some(message_id.as_str()));
+
+    let vasp_storage = server
+        .node()
+        .agent_storage_manager()
+        .unwrap()
+        .get_agent_storage(&vasp_did)
+        .await
+        .unwrap();
+    let authorize = vasp_storage
+        .get_message_by_id(&message_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(authorize.message_type.ends_with("#Authorize"));
+    assert_eq!(
+        authorize.thread_id.as_deref(),
+        Some(transaction_id.as_str())
+    );
+
+    // The nonce cannot be redeemed twice
+    let response = client
+        .post(&challenge.authorization_url)
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 410);
+
+    let response = client
+        .get(format!("http://127.0.0.1:{}/authorize/unknown", port))
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+
+    server.stop().await.expect("Server should stop");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_inbox_long_poll_and_acknowledge() {
+    use std::sync::Arc;
+    use tap_agent::TapAgent;
+    use tap_msg::message::tap_message_trait::TapMessageBody;
+    use tap_msg::message::{Agent, Party, Transfer};
+    use tap_node::mailbox::MailboxRecipient;
+    use tap_node::state_machine::fsm::DecisionMode;
+    use tap_node::storage::{DeliveryStatus, DeliveryType, Storage};
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let (_, remote_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let mut node = TapNode::new(NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        decision_mode: DecisionMode::EventBus,
+        mailbox: tap_node::mailbox::MailboxConfig {
+            recipients: vec![MailboxRecipient::new(&remote_did, "secret-token")],
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    node.set_storage(Storage::new_in_memory().await.unwrap())
+        .await
+        .unwrap();
+    let (vasp_agent, vasp_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(vasp_agent)).await.unwrap();
+
+    let port = find_unused_port().expect("Unable to find unused port");
+    let config = TapHttpConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+        ..TapHttpConfig::default()
+    };
+    let mut server = TapHttpServer::new(config, node);
+    server.start().await.expect("Server should start");
+    sleep(Duration::from_millis(500)).await;
+
+    let client = reqwest::Client::new();
+    let poll_url = format!("http://127.0.0.1:{}/inbox/poll", port);
+    let ack_url = format!("http://127.0.0.1:{}/inbox/ack", port);
+
+    let response = client
+        .get(&poll_url)
+        .bearer_auth("wrong-token")
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+
+    // A poll waits for the message sent while it is open
+    let poll = tokio::spawn(
+        client
+            .get(format!("{}?after=0&wait=10", poll_url))
+            .bearer_auth("secret-token")
+            .timeout(Duration::from_secs(15))
+            .send(),
+    );
+    sleep(Duration::from_millis(300)).await;
+
+    let transfer = Transfer {
+        transaction_id: None,
+        asset: "eip155:1/slip44:60".parse().unwrap(),
+        amount: "1.00".to_string(),
+        originator: Some(Party::new("did:example:alice")),
+        beneficiary: Some(Party::new("did:example:bob")),
+        agents: vec![
+            Agent::new(&vasp_did, "originating_vasp", "did:example:alice"),
+            Agent::new(&remote_did, "beneficiary_vasp", "did:example:bob"),
+        ],
+        memo: None,
+        settlement_id: None,
+        expiry: None,
+        transaction_value: None,
+        connection_id: None,
+        metadata: Default::default(),
+    };
+    let message = transfer
+        .to_didcomm_with_route(&vasp_did, [remote_did.as_str()])
+        .unwrap();
+    let message_id = message.id.clone();
+    let started = std::time::Instant::now();
+    server
+        .node()
+        .send_message(vasp_did.clone(), message)
+        .await
+        .unwrap();
+
+    let response = poll.await.unwrap().unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(started.elapsed() < Duration::from_secs(5));
+    let body: serde_json::Value = response.json().await.unwrap();
+    let messages = body["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 1);
+    let seq = messages[0]["seq"].as_i64().unwrap();
+    assert!(messages[0]["message"].is_object());
+
+    let vasp_storage = server
+        .node()
+        .agent_storage_manager()
+        .unwrap()
+        .get_agent_storage(&vasp_did)
+        .await
+        .unwrap();
+    let delivery = vasp_storage
+        .get_deliveries_for_message(&message_id)
+        .await
+        .unwrap()
+        .remove(0);
+    assert_eq!(delivery.delivery_type, DeliveryType::Pickup);
+    assert_eq!(delivery.status, DeliveryStatus::Pending);
+
+    // Unacknowledged messages are returned again
+    let body: serde_json::Value = client
+        .get(format!("{}?after=0&wait=0", poll_url))
+        .bearer_auth("secret-token")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+
+    let response = client
+        .post(&ack_url)
+        .bearer_auth("secret-token")
+        .json(&json!({ "cursor": seq }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["acknowledged"], 1);
+
+    let body: serde_json::Value = client
+        .get(format!("{}?after=0&wait=0", poll_url))
+        .bearer_auth("secret-token")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(body["messages"].as_array().unwrap().is_empty());
+
+    let delivery = vasp_storage
+        .get_deliveries_for_message(&message_id)
+        .await
+        .unwrap()
+        .remove(0);
+    assert_eq!(delivery.status, DeliveryStatus::Success);
+
+    server.stop().await.expect("Server should stop");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_didcomm_message_pickup() {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tap_agent::message_packing::{PackOptions, Packable};
+    use tap_agent::{Agent as _, TapAgent};
+    use tap_msg::didcomm::{AttachmentData, PlainMessage};
+    use tap_msg::message::tap_message_trait::TapMessageBody;
+    use tap_msg::message::{Agent, Party, Transfer};
+    use tap_node::mailbox::MailboxRecipient;
+    use tap_node::pickup;
+    use tap_node::state_machine::fsm::DecisionMode;
+    use tap_node::storage::{DeliveryStatus, Storage};
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let (remote_agent, remote_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (_, stranger_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let mut node = TapNode::new(NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        decision_mode: DecisionMode::EventBus,
+        mailbox: tap_node::mailbox::MailboxConfig {
+            recipients: vec![MailboxRecipient::pickup(&remote_did)],
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    node.set_storage(Storage::new_in_memory().await.unwrap())
+        .await
+        .unwrap();
+    let (vasp_agent, vasp_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(vasp_agent)).await.unwrap();
+
+    let port = find_unused_port().expect("Unable to find unused port");
+    let config = TapHttpConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+        ..TapHttpConfig::default()
+    };
+    let mut server = TapHttpServer::new(config, node);
+    server.start().await.expect("Server should start");
+    sleep(Duration::from_millis(500)).await;
+
+    let transfer = Transfer {
+        transaction_id: None,
+        asset: "eip155:1/slip44:60".parse().unwrap(),
+        amount: "1.00".to_string(),
+        originator: Some(Party::new("did:example:alice")),
+        beneficiary: Some(Party::new("did:example:bob")),
+        agents: vec![
+            Agent::new(&vasp_did, "originating_vasp", "did:example:alice"),
+            Agent::new(&remote_did, "beneficiary_vasp", "did:example:bob"),
+        ],
+        memo: None,
+        settlement_id: None,
+        expiry: None,
+        transaction_value: None,
+        connection_id: None,
+        metadata: Default::default(),
+    };
+    let message = transfer
+        .to_didcomm_with_route(&vasp_did, [remote_did.as_str()])
+        .unwrap();
+    let message_id = message.id.clone();
+    server
+        .node()
+        .send_message(vasp_did.clone(), message)
+        .await
+        .unwrap();
+
+    // Sends a signed pickup request and returns the verified reply
+    let client = reqwest::Client::new();
+    let didcomm_url = format!("http://127.0.0.1:{}/didcomm", port);
+    let remote_kid = remote_agent.get_signing_kid().await.unwrap();
+    let request = |type_: &str, body: serde_json::Value| PlainMessage {
+        id: uuid::Uuid::new_v4().to_string(),
+        typ: "application/didcomm-plain+json".to_string(),
+        type_: type_.to_string(),
+        body,
+        from: remote_did.clone(),
+        to: vec![vasp_did.clone()],
+        thid: None,
+        pthid: None,
+        extra_headers: HashMap::new(),
+        attachments: None,
+        created_time: None,
+        expires_time: None,
+        from_prior: None,
+    };
+    let send = |request: PlainMessage| {
+        let client = client.clone();
+        let didcomm_url = didcomm_url.clone();
+        let remote_agent = &remote_agent;
+        let remote_kid = remote_kid.clone();
+        async move {
+            let signed = request
+                .pack(
+                    &**remote_agent.key_manager(),
+                    PackOptions::new().with_sign(&remote_kid),
+                )
+                .await
+                .unwrap();
+            client
+                .post(&didcomm_url)
+                .header("Content-Type", "application/didcomm-signed+json")
+                .body(signed)
+                .send()
+                .await
+                .unwrap()
+        }
+    };
+
+    let status_request = request(pickup::STATUS_REQUEST_TYPE, json!({}));
+    let status_request_id = status_request.id.clone();
+    let response = send(status_request).await;
+    assert_eq!(response.status(), 200);
+    let status = remote_agent
+        .receive_message(&response.text().await.unwrap())
+        .await
+        .unwrap();
+    assert_eq!(status.type_, pickup::STATUS_TYPE);
+    assert_eq!(status.from, vasp_did);
+    assert_eq!(status.thid.as_deref(), Some(status_request_id.as_str()));
+    assert_eq!(status.body["message_count"], 1);
+
+    let response = send(request(
+        pickup::DELIVERY_REQUEST_TYPE,
+        json!({ "limit": 10 }),
+    ))
+    .await;
+    assert_eq!(response.status(), 200);
+    let delivery = remote_agent
+        .receive_message(&response.text().await.unwrap())
+        .await
+        .unwrap();
+    assert_eq!(delivery.type_, pickup::DELIVERY_TYPE);
+    let attachments = delivery.attachments.unwrap();
+    assert_eq!(attachments.len(), 1);
+    assert_eq!(attachments[0].id.as_deref(), Some(message_id.as_str()));
+    assert!(matches!(attachments[0].data, AttachmentData::Json { .. }));
+
+    let vasp_storage = server
+        .node()
+        .agent_storage_manager()
+        .unwrap()
+        .get_agent_storage(&vasp_did)
+        .await
+        .unwrap();
+    let delivery_record = vasp_storage
+        .get_deliveries_for_message(&message_id)
+        .await
+        .unwrap()
+        .remove(0);
+    assert_eq!(delivery_record.status, DeliveryStatus::Pending);
+
+    let response = send(request(
+        pickup::MESSAGES_RECEIVED_TYPE,
+        json!({ "message_id_list": [message_id] }),
+    ))
+    .await;
+    assert_eq!(response.status(), 200);
+    let status = remote_agent
+        .receive_message(&response.text().await.unwrap())
+        .await
+        .unwrap();
+    assert_eq!(status.type_, pickup::STATUS_TYPE);
+    assert_eq!(status.body["message_count"], 0);
+
+    let delivery_record = vasp_storage
+        .get_deliveries_for_message(&message_id)
+        .await
+        .unwrap()
+        .remove(0);
+    assert_eq!(delivery_record.status, DeliveryStatus::Success);
+
+    // Nothing left to deliver
+    let response = send(request(
+        pickup::DELIVERY_REQUEST_TYPE,
+        json!({ "limit": 10 }),
+    ))
+    .await;
+    let status = remote_agent
+        .receive_message(&response.text().await.unwrap())
+        .await
+        .unwrap();
+    assert_eq!(status.type_, pickup::STATUS_TYPE);
+
+    // Picking up another agent's messages is refused
+    let response = send(request(
+        pickup::STATUS_REQUEST_TYPE,
+        json!({ "recipient_did": stranger_did }),
+    ))
+    .await;
+    assert_eq!(response.status(), 400);
+
+    server.stop().await.expect("Server should stop");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_connection_protection() {
+    use tap_http::config::RateLimitConfig;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Denied addresses are refused a connection
+    let port = find_unused_port().expect("Unable to find unused port");
+    let config = TapHttpConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+        ip_denylist: vec!["127.0.0.0/8".to_string()],
+        event_logger: None,
+        ..TapHttpConfig::default()
+    };
+    let mut server = TapHttpServer::new(config, create_mock_node());
+    server.start().await.expect("Server should start");
+    sleep(Duration::from_millis(100)).await;
+
+    let client = reqwest::Client::new();
+    let health_url = format!("http://127.0.0.1:{}/health", port);
+    let result = client
+        .get(&health_url)
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await;
+    assert!(result.is_err());
+    assert_eq!(server.protection_stats().denied_connections, 1);
+    server.stop().await.expect("Server should stop");
+
+    // Rate limits, connection caps and header read timeouts
+    let port = find_unused_port().expect("Unable to find unused port");
+    let config = TapHttpConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+        ip_allowlist: vec!["127.0.0.1".to_string()],
+        rate_limit: Some(RateLimitConfig {
+            max_requests: 2,
+            window_secs: 60,
+        }),
+        max_connections: Some(1),
+        header_read_timeout_secs: 1,
+        event_logger: None,
+        ..TapHttpConfig::default()
+    };
+    let mut server = TapHttpServer::new(config, create_mock_node());
+    server.start().await.expect("Server should start");
+    sleep(Duration::from_millis(100)).await;
+
+    let health_url = format!("http://127.0.0.1:{}/health", port);
+    for expected in [200, 200, 429] {
+        let client = reqwest::Client::builder()
+            .pool_max_idle_per_host(0)
+            .build()
+            .unwrap();
+        let response = client
+            .get(&health_url)
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), expected);
+        if expected == 200 {
+            let body: serde_json::Value = response.json().await.unwrap();
+            assert_eq!(body["protection"]["rate_limited_requests"], 0);
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+
+    // A client that never finishes its headers holds the only connection
+    // until it times out
+    let mut slow_client = tokio::net::TcpStream::connect(("127.0.0.1", port))
+        .await
+        .unwrap();
+    slow_client
+        .write_all(b"GET /health HTTP/1.1\r\n")
+        .await
+        .unwrap();
+    sleep(Duration::from_millis(100)).await;
+
+    let result = reqwest::Client::new()
+        .get(&health_url)
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await;
+    assert!(result.is_err());
+
+    let mut buffer = Vec::new();
+    tokio::time::timeout(Duration::from_secs(5), slow_client.read_to_end(&mut buffer))
+        .await
+        .expect("Slow connection should be closed")
+        .ok();
+    sleep(Duration::from_millis(100)).await;
+
+    let stats = server.protection_stats();
+    assert_eq!(stats.rate_limited_requests, 1);
+    assert_eq!(stats.connection_limit_rejections, 1);
+    assert_eq!(stats.header_timeouts, 1);
+    assert_eq!(stats.active_connections, 0);
+
+    server.stop().await.expect("Server should stop");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_tls_certificate_reload() {
+    use rustls::pki_types::{CertificateDer, ServerName};
+    use std::sync::Arc;
+    use tap_http::config::{TlsConfig, TlsVersion};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::client::TlsStream;
+    use tokio_rustls::TlsConnector;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let cert_path = dir.path().join("cert.pem");
+    let key_path = dir.path().join("key.pem");
+    let write_certificate = || {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        std::fs::write(&cert_path, certified.cert.pem()).unwrap();
+        std::fs::write(&key_path, certified.key_pair.serialize_pem()).unwrap();
+        certified.cert.der().clone()
+    };
+
+    async fn connect(
+        port: u16,
+        trusted: &CertificateDer<'static>,
+        versions: &[&'static rustls::SupportedProtocolVersion],
+    ) -> std::io::Result<TlsStream<tokio::net::TcpStream>> {
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(trusted.clone()).unwrap();
+        let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_protocol_versions(versions)
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+        let stream = tokio::net::TcpStream::connect(("127.0.0.1", port)).await?;
+        TlsConnector::from(Arc::new(config))
+            .connect(ServerName::try_from("localhost").unwrap(), stream)
+            .await
+    }
+
+    async fn get_health(stream: &mut TlsStream<tokio::net::TcpStream>) -> String {
+        stream
+            .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut buffer = [0; 64];
+        let read = stream.read(&mut buffer).await.unwrap();
+        String::from_utf8_lossy(&buffer[..read]).to_string()
+    }
+
+    let first = write_certificate();
+    let port = find_unused_port().expect("Unable to find unused port");
+    let config = TapHttpConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+        tls: Some(TlsConfig {
+            min_version: TlsVersion::Tls13,
+            reload_interval_secs: 0,
+            ..TlsConfig::new(cert_path.to_string_lossy(), key_path.to_string_lossy())
+        }),
+        event_logger: None,
+        ..TapHttpConfig::default()
+    };
+    let mut server = TapHttpServer::new(config, create_mock_node());
+    server.start().await.expect("Server should start");
+    sleep(Duration::from_millis(100)).await;
+
+    let mut open = connect(port, &first, rustls::ALL_VERSIONS).await.unwrap();
+
+    // Clients limited to TLS 1.2 are refused
+    assert!(connect(port, &first, &[&rustls::version::TLS12])
+        .await
+        .is_err());
+
+    // New connections get the rotated certificate
+    let second = write_certificate();
+    server.reload_tls().expect("Certificate should reload");
+    assert!(connect(port, &first, rustls::ALL_VERSIONS).await.is_err());
+    let mut rotated = connect(port, &second, rustls::ALL_VERSIONS).await.unwrap();
+    assert_eq!(rotated.get_ref().1.peer_certificates().unwrap()[0], second);
+    assert!(get_health(&mut rotated).await.starts_with("HTTP/1.1 200"));
+
+    // Connections established before the reload are still served
+    assert!(get_health(&mut open).await.starts_with("HTTP/1.1 200"));
+    assert_eq!(open.get_ref().1.peer_certificates().unwrap()[0], first);
+
+    server.stop().await.expect("Server should stop");
+    assert!(server.reload_tls().is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_node_attestation() {
+    use base64::Engine;
+    use std::sync::Arc;
+    use tap_agent::{Jws, MultiResolver, TapAgent};
+    use tap_http::attestation::verify_attestation;
+    use tap_http::DIDCommClient;
+
+    let node = create_mock_node();
+    let (agent, agent_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(agent)).await.unwrap();
+
+    let port = find_unused_port().expect("Unable to find unused port");
+    let config = TapHttpConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+        enable_attestation: true,
+        event_logger: None,
+        ..TapHttpConfig::default()
+    };
+    let mut server = TapHttpServer::new(config, node);
+    server.start().await.expect("Server should start");
+    sleep(Duration::from_millis(100)).await;
+
+    let base_url = format!("http://127.0.0.1:{}", port);
+    let resolver = MultiResolver::default();
+    let verified = DIDCommClient::new(None)
+        .fetch_attestation(&base_url, &resolver)
+        .await
+        .expect("Attestation should verify");
+    assert_eq!(verified.attestation.signer, agent_did);
+    assert_eq!(verified.verification.signer_did, agent_did);
+    assert!(verified.attestation.accepts(&agent_did));
+    assert!(!verified.attestation.accepts("did:example:other"));
+    assert_eq!(
+        verified.attestation.endpoints.didcomm,
+        format!("{}/didcomm", base_url)
+    );
+    assert!(verified.attestation.taips.contains(&"TAIP-3".to_string()));
+    let capabilities = verified.attestation.capabilities.as_ref().unwrap();
+    assert!(capabilities.has_feature("native"));
+    assert!(!capabilities.travel_rule);
+
+    // A tampered attestation does not verify
+    let mut jws: Jws = reqwest::get(format!("{}/.well-known/tap-node", base_url))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let mut attestation = verified.attestation.clone();
+    attestation.agents.push("did:example:intruder".to_string());
+    jws.payload = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .encode(serde_json::to_vec(&attestation).unwrap());
+    assert!(verify_attestation(&jws, &resolver).await.is_err());
+
+    server.stop().await.expect("Server should stop");
+
+    // The endpoint is not served unless enabled
+    let port = find_unused_port().expect("Unable to find unused port");
+    let config = TapHttpConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+        event_logger: None,
+        ..TapHttpConfig::default()
+    };
+    let mut server = TapHttpServer::new(config, create_mock_node());
+    server.start().await.expect("Server should start");
+    sleep(Duration::from_millis(100)).await;
+
+    let response = reqwest::get(format!("http://127.0.0.1:{}/.well-known/tap-node", port))
+        .await
+        .unwrap();
+    assert!(!response.status().is_success());
+
+    server.stop().await.expect("Server should stop");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_openapi_document() {
+    let node = create_mock_node();
+    let port = find_unused_port().expect("Unable to find unused port");
+    let config = TapHttpConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+        didcomm_endpoint: "/tap".to_string(),
+        enable_attestation: true,
+        ..TapHttpConfig::default()
+    };
+
+    let mut server = TapHttpServer::new(config, node);
+    server.start().await.expect("Server should start");
+    sleep(Duration::from_millis(500)).await;
+
+    let document: serde_json::Value =
+        reqwest::get(format!("http://127.0.0.1:{}/openapi.json", port))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+    assert!(document["openapi"].as_str().unwrap().starts_with("3.1"));
+
+    // Paths follow the configuration
+    let paths = document["paths"].as_object().unwrap();
+    let mut names: Vec<&str> = paths.keys().map(String::as_str).collect();
+    names.sort();
+    assert_eq!(
+        names,
+        vec![
+            "/.well-known/tap-node",
+            "/authorize/{nonce}",
+            "/health",
+            "/inbox/ack",
+            "/inbox/poll",
+            "/tap",
+        ]
+    );
+    assert!(paths["/authorize/{nonce}"]["get"].is_object());
+    assert!(paths["/authorize/{nonce}"]["post"].is_object());
+    assert!(paths["/tap"]["post"]["requestBody"]["content"]
+        .get("application/didcomm-signed+json")
+        .is_some());
+
+    // Response schemas are generated from the handlers' types
+    let schemas = document["components"]["schemas"].as_object().unwrap();
+    for schema in [
+        "HealthResponse",
+        "IngestResponse",
+        "InboxPollResponse",
+        "ErrorResponse",
+    ] {
+        assert!(schemas.contains_key(schema), "Missing schema {}", schema);
+    }
+    assert!(schemas["ProtectionStats"]["properties"]
+        .get("rate_limited_requests")
+        .is_some());
+    assert!(document["components"]["securitySchemes"]["bearer"].is_object());
+
+    server.stop().await.expect("Server should stop");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_pending_actions_api() {
+    use std::sync::Arc;
+    use tap_agent::TapAgent;
+    use tap_msg::message::tap_message_trait::TapMessageBody;
+    use tap_msg::message::{Agent, Party, Transfer};
+    use tap_node::mailbox::MailboxRecipient;
+    use tap_node::storage::{ReviewStatus, Storage};
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let (_, remote_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let mut node = TapNode::new(NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        mailbox: tap_node::mailbox::MailboxConfig {
+            recipients: vec![MailboxRecipient::new(&remote_did, "secret-token")],
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    node.set_storage(Storage::new_in_memory().await.unwrap())
+        .await
+        .unwrap();
+    let (vasp_agent, vasp_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(vasp_agent)).await.unwrap();
+
+    // A Transfer the remote agent sent, held for manual review
+    let transfer = Transfer {
+        transaction_id: None,
+        asset: "eip155:1/slip44:60".parse().unwrap(),
+        amount: "1.00".to_string(),
+        originator: Some(Party::new("did:example:alice")),
+        beneficiary: Some(Party::new("did:example:bob")),
+        agents: vec![
+            Agent::new(&remote_did, "originating_vasp", "did:example:alice"),
+            Agent::new(&vasp_did, "beneficiary_vasp", "did:example:bob"),
+        ],
+        memo: None,
+        settlement_id: None,
+        expiry: None,
+        transaction_value: None,
+        connection_id: None,
+        metadata: Default::default(),
+    };
+    let mut message = transfer.to_didcomm(&remote_did).unwrap();
+    message.id = "tx-review".to_string();
+    let storage = node.storage().unwrap().clone();
+    storage.insert_transaction(&message).await.unwrap();
+    let review_id = storage
+        .insert_review_item(
+            "tx-review",
+            &vasp_did,
+            "large-transfer",
+            "Amount exceeds 0.5",
+            &json!({}),
+        )
+        .await
+        .unwrap();
+
+    let port = find_unused_port().expect("Unable to find unused port");
+    let config = TapHttpConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+        actions_token: Some("actions-token".to_string()),
+        ..TapHttpConfig::default()
+    };
+    let mut server = TapHttpServer::new(config, node);
+    server.start().await.expect("Server should start");
+    sleep(Duration::from_millis(500)).await;
+
+    let client = reqwest::Client::new();
+    let actions_url = format!("http://127.0.0.1:{}/actions", port);
+
+    let response = client.get(&actions_url).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+    let response = client
+        .get(format!("{}?kind=unknown", actions_url))
+        .bearer_auth("actions-token")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+
+    let body: serde_json::Value = client
+        .get(&actions_url)
+        .bearer_auth("actions-token")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let actions = body["actions"].as_array().unwrap();
+    assert_eq!(actions.len(), 1);
+    let id = actions[0]["id"].as_str().unwrap().to_string();
+    assert_eq!(id, format!("manual_review:{}", review_id));
+    assert_eq!(actions[0]["kind"], "manual_review");
+    assert_eq!(actions[0]["transaction_id"], "tx-review");
+
+    // Approving sends Authorize to the remote agent
+    let approve_url = format!("{}/{}/approve", actions_url, id);
+    let response = client
+        .post(&approve_url)
+        .bearer_auth("actions-token")
+        .json(&json!({ "actor": "alice", "note": "Checked" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "success");
+    assert_eq!(body["transaction_id"], "tx-review");
+    assert!(body["message_id"].is_string());
+
+    let item = storage.get_review_item(review_id).await.unwrap().unwrap();
+    assert_eq!(item.status, ReviewStatus::Approved);
+    assert_eq!(item.reviewer.as_deref(), Some("alice"));
+    assert_eq!(
+        server
+            .node()
+            .mailbox_summary(&remote_did)
+            .await
+            .unwrap()
+            .message_count,
+        1
+    );
+
+    // The action no longer awaits approval
+    let response = client
+        .post(&approve_url)
+        .bearer_auth("actions-token")
+        .json(&json!({}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+
+    server.stop().await.expect("Server should stop");
+}
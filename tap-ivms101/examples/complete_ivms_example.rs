@@ -177,7 +177,7 @@ fn create_beneficiary_vasp() -> Result<LegalPerson, Box<dyn std::error::Error>>
     let vasp = LegalPersonBuilder::new()
         .name(name)
         .add_address(address)
-        .lei("213800HNOAA1KXQJUQ37")?
+        .lei("213800HNOAA1KXQJUQ43")?
         .country_of_registration("GB")
         .build()?;
 
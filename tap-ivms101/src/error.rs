@@ -48,6 +48,10 @@ pub enum Error {
     #[error("Invalid LEI: {0}")]
     InvalidLei(String),
 
+    /// LEI registry lookup error
+    #[error("LEI lookup failed: {0}")]
+    Lookup(String),
+
     /// BIC validation error
     #[error("Invalid BIC: {0}")]
     InvalidBic(String),
@@ -0,0 +1,447 @@
+//! LEI lookup against the GLEIF API
+//!
+//! With the `gleif` feature, [`GleifClient`] fetches LEI records from the
+//! [GLEIF API](https://www.gleif.org/en/lei-data/gleif-api) and checks legal
+//! persons against them. Verification reports a lapsed registration, an
+//! inactive entity and a legal name or country of registration that differs
+//! from the GLEIF record as warnings in the legal person's
+//! [`ValidationReport`], and fills in the registered name, address and country
+//! when the legal person does not provide them.
+//!
+//! ```rust,no_run
+//! use tap_ivms101::builder::*;
+//! use tap_ivms101::gleif::GleifClient;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut vasp = LegalPersonBuilder::new()
+//!     .name(LegalPersonNameBuilder::new().legal_name("Example VASP").build()?)
+//!     .lei("529900HNOAA1KXQJUQ27")?
+//!     .build()?;
+//!
+//! let verification = GleifClient::new().verify_legal_person(&mut vasp).await?;
+//! for violation in &verification.report.violations {
+//!     println!("{}", violation);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::person::{LegalPerson, LegalPersonNameIdentifier};
+use crate::report::{Severity, Validate, ValidationReport, Violation, ViolationCode};
+use crate::types::{AddressType, GeographicAddress, LegalPersonNameIdentifierType};
+use crate::validation::validate_lei;
+use serde::Deserialize;
+
+/// Default base URL of the GLEIF API
+pub const GLEIF_API_URL: &str = "https://api.gleif.org/api/v1";
+
+/// Registered data of a legal entity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeiRecord {
+    /// The LEI
+    pub lei: String,
+    /// Registered legal name
+    pub legal_name: String,
+    /// Registered legal address
+    pub legal_address: GeographicAddress,
+    /// Entity status, e.g. `ACTIVE` or `INACTIVE`
+    pub entity_status: String,
+    /// Registration status, e.g. `ISSUED` or `LAPSED`
+    pub registration_status: String,
+}
+
+impl LeiRecord {
+    /// Whether the entity is active and its LEI registration is current
+    pub fn is_active(&self) -> bool {
+        self.entity_status == "ACTIVE" && self.registration_status == "ISSUED"
+    }
+}
+
+/// Result of verifying a legal person against GLEIF
+#[derive(Debug, Clone)]
+pub struct LeiVerification {
+    /// The GLEIF record
+    pub record: LeiRecord,
+    /// Validation report of the legal person, with differences from the
+    /// record as warnings
+    pub report: ValidationReport,
+}
+
+/// Client for the GLEIF API
+#[derive(Debug, Clone)]
+pub struct GleifClient {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl Default for GleifClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GleifClient {
+    /// Create a client for the public GLEIF API
+    pub fn new() -> Self {
+        Self::with_base_url(GLEIF_API_URL)
+    }
+
+    /// Create a client for a GLEIF-compatible API at another URL
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Fetch the record of an LEI
+    pub async fn lookup(&self, lei: &str) -> Result<LeiRecord> {
+        validate_lei(lei)?;
+
+        let response = self
+            .client
+            .get(format!("{}/lei-records/{}", self.base_url, lei))
+            .header("Accept", "application/vnd.api+json")
+            .send()
+            .await
+            .map_err(|e| Error::Lookup(format!("GLEIF request failed: {}", e)))?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(Error::Lookup(format!("LEI {} is not registered", lei)));
+        }
+        if !response.status().is_success() {
+            return Err(Error::Lookup(format!(
+                "GLEIF returned status {}",
+                response.status()
+            )));
+        }
+
+        let document: GleifDocument = response
+            .json()
+            .await
+            .map_err(|e| Error::Lookup(format!("Invalid GLEIF response: {}", e)))?;
+        Ok(document.into_record())
+    }
+
+    /// Check a legal person against the GLEIF record of its LEI
+    ///
+    /// Missing registered name, address and country of registration are
+    /// filled in from the record. Differences are reported as warnings
+    /// alongside the legal person's own validation errors.
+    pub async fn verify_legal_person(&self, person: &mut LegalPerson) -> Result<LeiVerification> {
+        let lei = person
+            .national_identification
+            .as_ref()
+            .and_then(|id| id.lei_code.clone())
+            .ok_or_else(|| Error::MissingRequiredField("Legal person has no LEI".to_string()))?;
+
+        let record = self.lookup(&lei).await?;
+        let warnings = reconcile(person, &record);
+        let mut report = person.validate_all();
+        report.violations.extend(warnings);
+        Ok(LeiVerification { record, report })
+    }
+}
+
+/// Fill missing fields of a legal person from its record and list differences
+fn reconcile(person: &mut LegalPerson, record: &LeiRecord) -> Vec<Violation> {
+    let mut warnings = Vec::new();
+
+    if record.registration_status != "ISSUED" {
+        warnings.push(mismatch(
+            "/nationalIdentification/leiCode",
+            format!(
+                "LEI {} registration status is {}",
+                record.lei, record.registration_status
+            ),
+        ));
+    }
+    if record.entity_status != "ACTIVE" {
+        warnings.push(mismatch(
+            "/nationalIdentification/leiCode",
+            format!("Entity status is {}", record.entity_status),
+        ));
+    }
+
+    let identifiers = &mut person.name.name_identifiers;
+    match identifiers.iter().position(|id| {
+        id.legal_person_name_identifier_type == LegalPersonNameIdentifierType::LegalName
+    }) {
+        Some(index) => {
+            let name = &identifiers[index].legal_person_name;
+            if !same_name(name, &record.legal_name) {
+                warnings.push(mismatch(
+                    format!("/name/nameIdentifiers/{}/legalPersonName", index),
+                    format!(
+                        "Legal name '{}' does not match registered name '{}'",
+                        name, record.legal_name
+                    ),
+                ));
+            }
+        }
+        None => identifiers.push(LegalPersonNameIdentifier::new(
+            record.legal_name.clone(),
+            LegalPersonNameIdentifierType::LegalName,
+        )),
+    }
+
+    match &person.country_of_registration {
+        Some(country) if !country.eq_ignore_ascii_case(&record.legal_address.country) => {
+            warnings.push(mismatch(
+                "/countryOfRegistration",
+                format!(
+                    "Country of registration {} does not match registered country {}",
+                    country, record.legal_address.country
+                ),
+            ));
+        }
+        Some(_) => {}
+        None => person.country_of_registration = Some(record.legal_address.country.clone()),
+    }
+
+    if person
+        .geographic_addresses
+        .as_ref()
+        .is_none_or(|addresses| addresses.is_empty())
+    {
+        person.geographic_addresses = Some(vec![record.legal_address.clone()]);
+    }
+
+    warnings
+}
+
+fn mismatch(path: impl Into<String>, message: String) -> Violation {
+    Violation {
+        path: path.into(),
+        severity: Severity::Warning,
+        code: ViolationCode::RegistryMismatch,
+        message,
+    }
+}
+
+fn same_name(a: &str, b: &str) -> bool {
+    let normalize = |s: &str| {
+        s.split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase()
+    };
+    normalize(a) == normalize(b)
+}
+
+#[derive(Deserialize)]
+struct GleifDocument {
+    data: GleifData,
+}
+
+#[derive(Deserialize)]
+struct GleifData {
+    attributes: GleifAttributes,
+}
+
+#[derive(Deserialize)]
+struct GleifAttributes {
+    lei: String,
+    entity: GleifEntity,
+    registration: GleifRegistration,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GleifEntity {
+    legal_name: GleifName,
+    legal_address: GleifAddress,
+    status: String,
+}
+
+#[derive(Deserialize)]
+struct GleifName {
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GleifAddress {
+    #[serde(default)]
+    address_lines: Vec<String>,
+    city: String,
+    region: Option<String>,
+    country: String,
+    postal_code: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GleifRegistration {
+    status: String,
+}
+
+impl GleifDocument {
+    fn into_record(self) -> LeiRecord {
+        let attributes = self.data.attributes;
+        let address = attributes.entity.legal_address;
+        let mut lines = address.address_lines.into_iter();
+        let street_name = lines.next().unwrap_or_default();
+        let address_line: Vec<String> = lines.collect();
+
+        LeiRecord {
+            lei: attributes.lei,
+            legal_name: attributes.entity.legal_name.name,
+            legal_address: GeographicAddress {
+                address_type: Some(AddressType::Business),
+                department: None,
+                sub_department: None,
+                street_name,
+                building_number: None,
+                building_name: None,
+                floor: None,
+                post_box: None,
+                room: None,
+                post_code: address.postal_code.unwrap_or_default(),
+                town_name: address.city,
+                town_location_name: None,
+                district_name: None,
+                country_sub_division: address.region,
+                address_line: (!address_line.is_empty()).then_some(address_line),
+                country: address.country,
+            },
+            entity_status: attributes.entity.status,
+            registration_status: attributes.registration.status,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::{LegalPersonBuilder, LegalPersonNameBuilder};
+
+    const LEI: &str = "529900HNOAA1KXQJUQ27";
+
+    fn record_json(registration_status: &str) -> String {
+        serde_json::json!({
+            "data": {
+                "type": "lei-records",
+                "id": LEI,
+                "attributes": {
+                    "lei": LEI,
+                    "entity": {
+                        "legalName": { "name": "Example VASP Inc.", "language": "en" },
+                        "legalAddress": {
+                            "language": "en",
+                            "addressLines": ["1 Main Street", "Suite 100"],
+                            "city": "Wilmington",
+                            "region": "US-DE",
+                            "country": "US",
+                            "postalCode": "19801"
+                        },
+                        "status": "ACTIVE"
+                    },
+                    "registration": { "status": registration_status }
+                }
+            }
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_fills_missing_fields() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", format!("/lei-records/{}", LEI).as_str())
+            .with_body(record_json("ISSUED"))
+            .create_async()
+            .await;
+
+        let mut person = LegalPersonBuilder::new()
+            .name(
+                LegalPersonNameBuilder::new()
+                    .trading_name("Example")
+                    .build()
+                    .unwrap(),
+            )
+            .lei(LEI)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let verification = GleifClient::with_base_url(server.url())
+            .verify_legal_person(&mut person)
+            .await
+            .unwrap();
+        mock.assert_async().await;
+
+        assert!(verification.record.is_active());
+        assert!(verification.report.is_empty());
+        assert_eq!(
+            person.name.name_identifiers[1].legal_person_name,
+            "Example VASP Inc."
+        );
+        assert_eq!(person.country_of_registration.as_deref(), Some("US"));
+        let address = &person.geographic_addresses.as_ref().unwrap()[0];
+        assert_eq!(address.street_name, "1 Main Street");
+        assert_eq!(address.town_name, "Wilmington");
+        assert_eq!(address.address_line, Some(vec!["Suite 100".to_string()]));
+    }
+
+    #[tokio::test]
+    async fn test_reports_mismatches() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", format!("/lei-records/{}", LEI).as_str())
+            .with_body(record_json("LAPSED"))
+            .create_async()
+            .await;
+
+        let mut person = LegalPersonBuilder::new()
+            .name(
+                LegalPersonNameBuilder::new()
+                    .legal_name("Other Company Ltd")
+                    .build()
+                    .unwrap(),
+            )
+            .lei(LEI)
+            .unwrap()
+            .country_of_registration("GB")
+            .build()
+            .unwrap();
+
+        let verification = GleifClient::with_base_url(server.url())
+            .verify_legal_person(&mut person)
+            .await
+            .unwrap();
+
+        assert!(verification.report.is_valid());
+        let paths: Vec<&str> = verification
+            .report
+            .warnings()
+            .map(|w| w.path.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/nationalIdentification/leiCode",
+                "/name/nameIdentifiers/0/legalPersonName",
+                "/countryOfRegistration",
+            ]
+        );
+        assert_eq!(person.country_of_registration.as_deref(), Some("GB"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_lei() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", format!("/lei-records/{}", LEI).as_str())
+            .with_status(404)
+            .create_async()
+            .await;
+
+        let client = GleifClient::with_base_url(server.url());
+        assert!(matches!(client.lookup(LEI).await, Err(Error::Lookup(_))));
+        assert!(matches!(
+            client.lookup("529900HNOAA1KXQJUQ28").await,
+            Err(Error::InvalidLei(_))
+        ));
+    }
+}
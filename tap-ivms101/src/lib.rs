@@ -7,11 +7,13 @@
 //! ## Features
 //!
 //! - Complete IVMS 101.2023 data model implementation
-//! - Comprehensive validation for all data types
+//! - Comprehensive validation for all data types, reporting either the first
+//!   error or every violation with its JSON Pointer path
 //! - Serde JSON serialization/deserialization support
 //! - Builder patterns for easy construction
 //! - Type-safe enumerations for all IVMS code lists
 //! - ISO country code and currency code validation
+//! - LEI check digit validation and, with the `gleif` feature, GLEIF lookup
 //!
 //! ## Example Usage
 //!
@@ -64,8 +66,11 @@
 
 pub mod builder;
 pub mod error;
+#[cfg(feature = "gleif")]
+pub mod gleif;
 pub mod message;
 pub mod person;
+pub mod report;
 pub mod types;
 pub mod validation;
 
@@ -73,6 +78,7 @@ pub mod validation;
 pub use error::{Error, Result};
 pub use message::{IvmsMessage, Person};
 pub use person::{LegalPerson, NaturalPerson};
+pub use report::{Severity, Validate, ValidationReport, Violation, ViolationCode};
 
 #[cfg(test)]
 mod tests {
@@ -5,6 +5,7 @@
 
 use crate::error::{Error, Result};
 use crate::person::{LegalPerson, NaturalPerson};
+use crate::report::{Validate, ValidationReport};
 use crate::types::*;
 use serde::{Deserialize, Serialize};
 use tap_msg::utils::NameHashable;
@@ -22,10 +23,7 @@ pub enum Person {
 impl Person {
     /// Validate the person data
     pub fn validate(&self) -> Result<()> {
-        match self {
-            Person::NaturalPerson(person) => person.validate(),
-            Person::LegalPerson(person) => person.validate(),
-        }
+        self.validate_all().into_result()
     }
 
     /// Check if this is a natural person
@@ -47,6 +45,17 @@ impl Person {
     }
 }
 
+impl Validate for Person {
+    fn check(&self, path: &str, report: &mut ValidationReport) {
+        match self {
+            Person::NaturalPerson(person) => {
+                person.check(&format!("{}/naturalPerson", path), report)
+            }
+            Person::LegalPerson(person) => person.check(&format!("{}/legalPerson", path), report),
+        }
+    }
+}
+
 // Implement NameHashable for Person
 impl NameHashable for Person {}
 
@@ -76,26 +85,32 @@ impl Originator {
 
     /// Validate the originator data
     pub fn validate(&self) -> Result<()> {
-        if self.originator_persons.is_empty() {
-            return Err(Error::MissingRequiredField(
-                "At least one originator person is required".to_string(),
-            ));
-        }
+        self.validate_all().into_result()
+    }
+}
 
-        for person in &self.originator_persons {
-            person.validate()?;
+impl Validate for Originator {
+    fn check(&self, path: &str, report: &mut ValidationReport) {
+        let persons = format!("{}/originatorPersons", path);
+        if self.originator_persons.is_empty() {
+            report.error(
+                persons,
+                Error::MissingRequiredField(
+                    "At least one originator person is required".to_string(),
+                ),
+            );
+        } else {
+            self.originator_persons.check(&persons, report);
         }
 
         if let Some(ref bic) = self.bic {
             if bic.len() != 8 && bic.len() != 11 {
-                return Err(Error::InvalidBic(format!(
-                    "BIC must be 8 or 11 characters: {}",
-                    bic
-                )));
+                report.error(
+                    format!("{}/bic", path),
+                    Error::InvalidBic(format!("BIC must be 8 or 11 characters: {}", bic)),
+                );
             }
         }
-
-        Ok(())
     }
 }
 
@@ -121,17 +136,23 @@ impl Beneficiary {
 
     /// Validate the beneficiary data
     pub fn validate(&self) -> Result<()> {
-        if self.beneficiary_persons.is_empty() {
-            return Err(Error::MissingRequiredField(
-                "At least one beneficiary person is required".to_string(),
-            ));
-        }
+        self.validate_all().into_result()
+    }
+}
 
-        for person in &self.beneficiary_persons {
-            person.validate()?;
+impl Validate for Beneficiary {
+    fn check(&self, path: &str, report: &mut ValidationReport) {
+        let persons = format!("{}/beneficiaryPersons", path);
+        if self.beneficiary_persons.is_empty() {
+            report.error(
+                persons,
+                Error::MissingRequiredField(
+                    "At least one beneficiary person is required".to_string(),
+                ),
+            );
+        } else {
+            self.beneficiary_persons.check(&persons, report);
         }
-
-        Ok(())
     }
 }
 
@@ -157,18 +178,23 @@ impl OriginatingVasp {
 
     /// Validate the originating VASP data
     pub fn validate(&self) -> Result<()> {
-        self.originating_vasp.validate()?;
+        self.validate_all().into_result()
+    }
+}
+
+impl Validate for OriginatingVasp {
+    fn check(&self, path: &str, report: &mut ValidationReport) {
+        self.originating_vasp
+            .check(&format!("{}/originatingVasp", path), report);
 
         if let Some(ref bic) = self.bic {
             if bic.len() != 8 && bic.len() != 11 {
-                return Err(Error::InvalidBic(format!(
-                    "BIC must be 8 or 11 characters: {}",
-                    bic
-                )));
+                report.error(
+                    format!("{}/bic", path),
+                    Error::InvalidBic(format!("BIC must be 8 or 11 characters: {}", bic)),
+                );
             }
         }
-
-        Ok(())
     }
 }
 
@@ -188,7 +214,14 @@ impl BeneficiaryVasp {
 
     /// Validate the beneficiary VASP data
     pub fn validate(&self) -> Result<()> {
-        self.beneficiary_vasp.validate()
+        self.validate_all().into_result()
+    }
+}
+
+impl Validate for BeneficiaryVasp {
+    fn check(&self, path: &str, report: &mut ValidationReport) {
+        self.beneficiary_vasp
+            .check(&format!("{}/beneficiaryVasp", path), report);
     }
 }
 
@@ -220,34 +253,45 @@ pub struct TransactionData {
 impl TransactionData {
     /// Validate the transaction data
     pub fn validate(&self) -> Result<()> {
+        self.validate_all().into_result()
+    }
+}
+
+impl Validate for TransactionData {
+    fn check(&self, path: &str, report: &mut ValidationReport) {
         if self.amount.is_empty() {
-            return Err(Error::MissingRequiredField(
-                "Transaction amount is required".to_string(),
-            ));
+            report.error(
+                format!("{}/amount", path),
+                Error::MissingRequiredField("Transaction amount is required".to_string()),
+            );
         }
 
         if self.currency.len() != 3 {
-            return Err(Error::InvalidCurrencyCode(format!(
-                "Currency code must be 3 characters: {}",
-                self.currency
-            )));
+            report.error(
+                format!("{}/currency", path),
+                Error::InvalidCurrencyCode(format!(
+                    "Currency code must be 3 characters: {}",
+                    self.currency
+                )),
+            );
         }
 
         if self.transaction_identifier.is_empty() {
-            return Err(Error::MissingRequiredField(
-                "Transaction identifier is required".to_string(),
-            ));
+            report.error(
+                format!("{}/transactionIdentifier", path),
+                Error::MissingRequiredField("Transaction identifier is required".to_string()),
+            );
         }
 
-        // Validate datetime format
         if chrono::DateTime::parse_from_rfc3339(&self.transaction_datetime).is_err() {
-            return Err(Error::InvalidDate(format!(
-                "Invalid transaction datetime format: {}",
-                self.transaction_datetime
-            )));
+            report.error(
+                format!("{}/transactionDatetime", path),
+                Error::InvalidDate(format!(
+                    "Invalid transaction datetime format: {}",
+                    self.transaction_datetime
+                )),
+            );
         }
-
-        Ok(())
     }
 }
 
@@ -287,29 +331,11 @@ impl IvmsMessage {
 
     /// Validate the entire IVMS message
     pub fn validate(&self) -> Result<()> {
-        let mut issues = Vec::new();
-
-        if let Err(e) = self.originator.validate() {
-            issues.push(format!("Originator: {}", e));
-        }
-
-        if let Err(e) = self.beneficiary.validate() {
-            issues.push(format!("Beneficiary: {}", e));
-        }
-
-        if let Err(e) = self.originating_vasp.validate() {
-            issues.push(format!("Originating VASP: {}", e));
-        }
-
-        if let Some(ref beneficiary_vasp) = self.beneficiary_vasp {
-            if let Err(e) = beneficiary_vasp.validate() {
-                issues.push(format!("Beneficiary VASP: {}", e));
-            }
-        }
-
-        if let Err(e) = self.transaction.validate() {
-            issues.push(format!("Transaction: {}", e));
-        }
+        let issues: Vec<String> = self
+            .validate_all()
+            .errors()
+            .map(|violation| violation.to_string())
+            .collect();
 
         if !issues.is_empty() {
             return Err(Error::ValidationFailed { issues });
@@ -333,3 +359,18 @@ impl IvmsMessage {
         serde_json::from_str(json).map_err(Error::from)
     }
 }
+
+impl Validate for IvmsMessage {
+    fn check(&self, path: &str, report: &mut ValidationReport) {
+        self.originator
+            .check(&format!("{}/originator", path), report);
+        self.beneficiary
+            .check(&format!("{}/beneficiary", path), report);
+        self.originating_vasp
+            .check(&format!("{}/originatingVasp", path), report);
+        self.beneficiary_vasp
+            .check(&format!("{}/beneficiaryVasp", path), report);
+        self.transaction
+            .check(&format!("{}/transaction", path), report);
+    }
+}
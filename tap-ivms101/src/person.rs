@@ -4,7 +4,9 @@
 //! as defined in the IVMS 101.2023 specification.
 
 use crate::error::{Error, Result};
+use crate::report::{Validate, ValidationReport};
 use crate::types::*;
+use crate::validation::validate_lei;
 use serde::{Deserialize, Serialize};
 use tap_msg::utils::NameHashable;
 
@@ -36,22 +38,29 @@ impl NameIdentifier {
 
     /// Validate the name identifier
     pub fn validate(&self) -> Result<()> {
+        self.validate_all().into_result()
+    }
+}
+
+impl Validate for NameIdentifier {
+    fn check(&self, path: &str, report: &mut ValidationReport) {
         if self.primary_identifier.is_empty() {
-            return Err(Error::InvalidName(
-                "Primary identifier cannot be empty".to_string(),
-            ));
-        }
-        if self.primary_identifier.len() > 150 {
-            return Err(Error::InvalidName(
-                "Primary identifier exceeds 150 characters".to_string(),
-            ));
+            report.error(
+                format!("{}/primaryIdentifier", path),
+                Error::InvalidName("Primary identifier cannot be empty".to_string()),
+            );
+        } else if self.primary_identifier.len() > 150 {
+            report.error(
+                format!("{}/primaryIdentifier", path),
+                Error::InvalidName("Primary identifier exceeds 150 characters".to_string()),
+            );
         }
         if self.secondary_identifier.len() > 150 {
-            return Err(Error::InvalidName(
-                "Secondary identifier exceeds 150 characters".to_string(),
-            ));
+            report.error(
+                format!("{}/secondaryIdentifier", path),
+                Error::InvalidName("Secondary identifier exceeds 150 characters".to_string()),
+            );
         }
-        Ok(())
     }
 }
 
@@ -105,15 +114,7 @@ impl NaturalPersonName {
 
     /// Validate the natural person name
     pub fn validate(&self) -> Result<()> {
-        if self.name_identifiers.is_empty() {
-            return Err(Error::InvalidName(
-                "At least one name identifier is required".to_string(),
-            ));
-        }
-        for identifier in &self.name_identifiers {
-            identifier.validate()?;
-        }
-        Ok(())
+        self.validate_all().into_result()
     }
 
     /// Get the full name as a single string for TAIP-12 hashing
@@ -131,6 +132,20 @@ impl NaturalPersonName {
     }
 }
 
+impl Validate for NaturalPersonName {
+    fn check(&self, path: &str, report: &mut ValidationReport) {
+        let path = format!("{}/nameIdentifiers", path);
+        if self.name_identifiers.is_empty() {
+            report.error(
+                path,
+                Error::InvalidName("At least one name identifier is required".to_string()),
+            );
+        } else {
+            self.name_identifiers.check(&path, report);
+        }
+    }
+}
+
 // Implement NameHashable for NaturalPersonName
 impl NameHashable for NaturalPersonName {}
 
@@ -152,23 +167,32 @@ pub struct NationalIdentification {
 impl NationalIdentification {
     /// Validate the national identification
     pub fn validate(&self) -> Result<()> {
+        self.validate_all().into_result()
+    }
+}
+
+impl Validate for NationalIdentification {
+    fn check(&self, path: &str, report: &mut ValidationReport) {
         if self.national_identifier.is_empty() {
-            return Err(Error::InvalidNationalId(
-                "National identifier cannot be empty".to_string(),
-            ));
-        }
-        if self.national_identifier.len() > 35 {
-            return Err(Error::InvalidNationalId(
-                "National identifier exceeds 35 characters".to_string(),
-            ));
+            report.error(
+                format!("{}/nationalIdentifier", path),
+                Error::InvalidNationalId("National identifier cannot be empty".to_string()),
+            );
+        } else if self.national_identifier.len() > 35 {
+            report.error(
+                format!("{}/nationalIdentifier", path),
+                Error::InvalidNationalId("National identifier exceeds 35 characters".to_string()),
+            );
         }
         if self.country_of_issue.len() != 2 {
-            return Err(Error::InvalidCountryCode(format!(
-                "Country code must be 2 characters: {}",
-                self.country_of_issue
-            )));
+            report.error(
+                format!("{}/countryOfIssue", path),
+                Error::InvalidCountryCode(format!(
+                    "Country code must be 2 characters: {}",
+                    self.country_of_issue
+                )),
+            );
         }
-        Ok(())
     }
 }
 
@@ -185,17 +209,23 @@ pub struct CustomerIdentification {
 impl CustomerIdentification {
     /// Validate the customer identification
     pub fn validate(&self) -> Result<()> {
+        self.validate_all().into_result()
+    }
+}
+
+impl Validate for CustomerIdentification {
+    fn check(&self, path: &str, report: &mut ValidationReport) {
         if self.customer_identifier.is_empty() {
-            return Err(Error::InvalidCustomerId(
-                "Customer identifier cannot be empty".to_string(),
-            ));
-        }
-        if self.customer_identifier.len() > 50 {
-            return Err(Error::InvalidCustomerId(
-                "Customer identifier exceeds 50 characters".to_string(),
-            ));
+            report.error(
+                format!("{}/customerIdentifier", path),
+                Error::InvalidCustomerId("Customer identifier cannot be empty".to_string()),
+            );
+        } else if self.customer_identifier.len() > 50 {
+            report.error(
+                format!("{}/customerIdentifier", path),
+                Error::InvalidCustomerId("Customer identifier exceeds 50 characters".to_string()),
+            );
         }
-        Ok(())
     }
 }
 
@@ -237,26 +267,29 @@ impl NaturalPerson {
 
     /// Validate the natural person data
     pub fn validate(&self) -> Result<()> {
-        self.name.validate()?;
-
-        if let Some(ref id) = self.national_identification {
-            id.validate()?;
-        }
+        self.validate_all().into_result()
+    }
+}
 
-        if let Some(ref cust_id) = self.customer_identification {
-            cust_id.validate()?;
-        }
+impl Validate for NaturalPerson {
+    fn check(&self, path: &str, report: &mut ValidationReport) {
+        self.name.check(&format!("{}/name", path), report);
+        self.national_identification
+            .check(&format!("{}/nationalIdentification", path), report);
+        self.customer_identification
+            .check(&format!("{}/customerIdentification", path), report);
 
         if let Some(ref country) = self.country_of_residence {
             if country.len() != 2 {
-                return Err(Error::InvalidCountryCode(format!(
-                    "Country code must be 2 characters: {}",
-                    country
-                )));
+                report.error(
+                    format!("{}/countryOfResidence", path),
+                    Error::InvalidCountryCode(format!(
+                        "Country code must be 2 characters: {}",
+                        country
+                    )),
+                );
             }
         }
-
-        Ok(())
     }
 }
 
@@ -284,17 +317,23 @@ impl LegalPersonNameIdentifier {
 
     /// Validate the legal person name identifier
     pub fn validate(&self) -> Result<()> {
+        self.validate_all().into_result()
+    }
+}
+
+impl Validate for LegalPersonNameIdentifier {
+    fn check(&self, path: &str, report: &mut ValidationReport) {
         if self.legal_person_name.is_empty() {
-            return Err(Error::InvalidName(
-                "Legal person name cannot be empty".to_string(),
-            ));
+            report.error(
+                format!("{}/legalPersonName", path),
+                Error::InvalidName("Legal person name cannot be empty".to_string()),
+            );
+        } else if self.legal_person_name.len() > 150 {
+            report.error(
+                format!("{}/legalPersonName", path),
+                Error::InvalidName("Legal person name exceeds 150 characters".to_string()),
+            );
         }
-        if self.legal_person_name.len() > 150 {
-            return Err(Error::InvalidName(
-                "Legal person name exceeds 150 characters".to_string(),
-            ));
-        }
-        Ok(())
     }
 }
 
@@ -324,15 +363,7 @@ impl LegalPersonName {
 
     /// Validate the legal person name
     pub fn validate(&self) -> Result<()> {
-        if self.name_identifiers.is_empty() {
-            return Err(Error::InvalidName(
-                "At least one name identifier is required".to_string(),
-            ));
-        }
-        for identifier in &self.name_identifiers {
-            identifier.validate()?;
-        }
-        Ok(())
+        self.validate_all().into_result()
     }
 
     /// Get the full legal name as a single string for TAIP-12 hashing
@@ -343,6 +374,20 @@ impl LegalPersonName {
     }
 }
 
+impl Validate for LegalPersonName {
+    fn check(&self, path: &str, report: &mut ValidationReport) {
+        let path = format!("{}/nameIdentifiers", path);
+        if self.name_identifiers.is_empty() {
+            report.error(
+                path,
+                Error::InvalidName("At least one name identifier is required".to_string()),
+            );
+        } else {
+            self.name_identifiers.check(&path, report);
+        }
+    }
+}
+
 // Implement NameHashable for LegalPersonName
 impl NameHashable for LegalPersonName {}
 
@@ -379,22 +424,21 @@ pub struct LegalPersonNationalIdentification {
 impl LegalPersonNationalIdentification {
     /// Validate the legal person national identification
     pub fn validate(&self) -> Result<()> {
+        self.validate_all().into_result()
+    }
+}
+
+impl Validate for LegalPersonNationalIdentification {
+    fn check(&self, path: &str, report: &mut ValidationReport) {
         if self.national_identifier.is_empty() {
-            return Err(Error::InvalidNationalId(
-                "National identifier cannot be empty".to_string(),
-            ));
+            report.error(
+                format!("{}/nationalIdentifier", path),
+                Error::InvalidNationalId("National identifier cannot be empty".to_string()),
+            );
         }
-
         if let Some(ref lei) = self.lei_code {
-            if lei.len() != 20 {
-                return Err(Error::InvalidLei(format!(
-                    "LEI must be 20 characters: {}",
-                    lei
-                )));
-            }
+            report.record(format!("{}/leiCode", path), validate_lei(lei));
         }
-
-        Ok(())
     }
 }
 
@@ -432,25 +476,28 @@ impl LegalPerson {
 
     /// Validate the legal person data
     pub fn validate(&self) -> Result<()> {
-        self.name.validate()?;
-
-        if let Some(ref id) = self.national_identification {
-            id.validate()?;
-        }
+        self.validate_all().into_result()
+    }
+}
 
-        if let Some(ref cust_id) = self.customer_identification {
-            cust_id.validate()?;
-        }
+impl Validate for LegalPerson {
+    fn check(&self, path: &str, report: &mut ValidationReport) {
+        self.name.check(&format!("{}/name", path), report);
+        self.national_identification
+            .check(&format!("{}/nationalIdentification", path), report);
+        self.customer_identification
+            .check(&format!("{}/customerIdentification", path), report);
 
         if let Some(ref country) = self.country_of_registration {
             if country.len() != 2 {
-                return Err(Error::InvalidCountryCode(format!(
-                    "Country code must be 2 characters: {}",
-                    country
-                )));
+                report.error(
+                    format!("{}/countryOfRegistration", path),
+                    Error::InvalidCountryCode(format!(
+                        "Country code must be 2 characters: {}",
+                        country
+                    )),
+                );
             }
         }
-
-        Ok(())
     }
 }
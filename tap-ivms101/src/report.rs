@@ -0,0 +1,264 @@
+//! Structured validation reports
+//!
+//! `validate()` stops at the first problem. [`Validate::validate_all`] instead
+//! collects every violation in a [`ValidationReport`], each located by a
+//! [JSON Pointer](https://www.rfc-editor.org/rfc/rfc6901) into the serialized
+//! IVMS payload, so a UI can show all problems of a submitted payload at once.
+//!
+//! ```rust
+//! use tap_ivms101::{IvmsMessage, Validate};
+//!
+//! # fn example(message: IvmsMessage) {
+//! let report = message.validate_all();
+//! for violation in report.errors() {
+//!     println!("{}: {}", violation.path, violation.message);
+//! }
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How serious a violation is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The payload is invalid
+    Error,
+    /// The payload is valid but likely wrong
+    Warning,
+}
+
+/// Kind of a violation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationCode {
+    /// Invalid name
+    InvalidName,
+    /// Invalid address
+    InvalidAddress,
+    /// Invalid identifier
+    InvalidIdentifier,
+    /// Invalid date
+    InvalidDate,
+    /// Invalid national identification
+    InvalidNationalId,
+    /// Invalid customer identification
+    InvalidCustomerId,
+    /// Invalid country code
+    InvalidCountryCode,
+    /// Invalid currency code
+    InvalidCurrencyCode,
+    /// Invalid LEI
+    InvalidLei,
+    /// Invalid BIC
+    InvalidBic,
+    /// Missing required field
+    MissingRequiredField,
+    /// Field is inconsistent with a registry such as GLEIF
+    RegistryMismatch,
+    /// Any other problem
+    Other,
+}
+
+/// A single problem found in an IVMS payload
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Violation {
+    /// JSON Pointer to the offending field, relative to the validated value
+    pub path: String,
+    /// How serious the violation is
+    pub severity: Severity,
+    /// Kind of the violation
+    pub code: ViolationCode,
+    /// Human-readable description
+    pub message: String,
+}
+
+impl Violation {
+    /// Convert the violation into the error `validate()` returns for it
+    pub fn to_error(&self) -> Error {
+        let message = self.message.clone();
+        match self.code {
+            ViolationCode::InvalidName => Error::InvalidName(message),
+            ViolationCode::InvalidAddress => Error::InvalidAddress(message),
+            ViolationCode::InvalidIdentifier => Error::InvalidIdentifier(message),
+            ViolationCode::InvalidDate => Error::InvalidDate(message),
+            ViolationCode::InvalidNationalId => Error::InvalidNationalId(message),
+            ViolationCode::InvalidCustomerId => Error::InvalidCustomerId(message),
+            ViolationCode::InvalidCountryCode => Error::InvalidCountryCode(message),
+            ViolationCode::InvalidCurrencyCode => Error::InvalidCurrencyCode(message),
+            ViolationCode::InvalidLei => Error::InvalidLei(message),
+            ViolationCode::InvalidBic => Error::InvalidBic(message),
+            ViolationCode::MissingRequiredField => Error::MissingRequiredField(message),
+            ViolationCode::RegistryMismatch | ViolationCode::Other => Error::ValidationFailed {
+                issues: vec![message],
+            },
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// All violations found in an IVMS payload
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationReport {
+    /// Violations in document order
+    pub violations: Vec<Violation>,
+}
+
+impl ValidationReport {
+    /// Create an empty report
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a violation
+    pub fn push(&mut self, violation: Violation) {
+        self.violations.push(violation);
+    }
+
+    /// Record an error at `path`
+    pub fn error(&mut self, path: impl Into<String>, error: Error) {
+        let (code, message) = match error {
+            Error::InvalidName(m) => (ViolationCode::InvalidName, m),
+            Error::InvalidAddress(m) => (ViolationCode::InvalidAddress, m),
+            Error::InvalidIdentifier(m) => (ViolationCode::InvalidIdentifier, m),
+            Error::InvalidDate(m) => (ViolationCode::InvalidDate, m),
+            Error::InvalidNationalId(m) => (ViolationCode::InvalidNationalId, m),
+            Error::InvalidCustomerId(m) => (ViolationCode::InvalidCustomerId, m),
+            Error::InvalidCountryCode(m) => (ViolationCode::InvalidCountryCode, m),
+            Error::InvalidCurrencyCode(m) => (ViolationCode::InvalidCurrencyCode, m),
+            Error::InvalidLei(m) => (ViolationCode::InvalidLei, m),
+            Error::InvalidBic(m) => (ViolationCode::InvalidBic, m),
+            Error::MissingRequiredField(m) => (ViolationCode::MissingRequiredField, m),
+            Error::ValidationFailed { issues } => (ViolationCode::Other, issues.join("; ")),
+            other => (ViolationCode::Other, other.to_string()),
+        };
+        self.push(Violation {
+            path: path.into(),
+            severity: Severity::Error,
+            code,
+            message,
+        });
+    }
+
+    /// Record the error of a validation function at `path`
+    pub(crate) fn record(&mut self, path: impl Into<String>, result: Result<()>) {
+        if let Err(error) = result {
+            self.error(path, error);
+        }
+    }
+
+    /// Whether the report contains no errors
+    pub fn is_valid(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    /// Whether the report contains no violations at all
+    pub fn is_empty(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Violations with [`Severity::Error`]
+    pub fn errors(&self) -> impl Iterator<Item = &Violation> {
+        self.violations
+            .iter()
+            .filter(|v| v.severity == Severity::Error)
+    }
+
+    /// Violations with [`Severity::Warning`]
+    pub fn warnings(&self) -> impl Iterator<Item = &Violation> {
+        self.violations
+            .iter()
+            .filter(|v| v.severity == Severity::Warning)
+    }
+
+    /// Return the first error, if any
+    pub fn into_result(self) -> Result<()> {
+        match self.errors().next() {
+            Some(violation) => Err(violation.to_error()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Validation that reports every violation
+pub trait Validate {
+    /// Record every violation of this value, with paths under `path`
+    fn check(&self, path: &str, report: &mut ValidationReport);
+
+    /// Collect every violation of this value
+    fn validate_all(&self) -> ValidationReport {
+        let mut report = ValidationReport::new();
+        self.check("", &mut report);
+        report
+    }
+}
+
+impl<T: Validate> Validate for Option<T> {
+    fn check(&self, path: &str, report: &mut ValidationReport) {
+        if let Some(value) = self {
+            value.check(path, report);
+        }
+    }
+}
+
+impl<T: Validate> Validate for [T] {
+    fn check(&self, path: &str, report: &mut ValidationReport) {
+        for (index, value) in self.iter().enumerate() {
+            value.check(&format!("{}/{}", path, index), report);
+        }
+    }
+}
+
+impl<T: Validate> Validate for Vec<T> {
+    fn check(&self, path: &str, report: &mut ValidationReport) {
+        self.as_slice().check(path, report);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_round_trip() {
+        let mut report = ValidationReport::new();
+        report.push(Violation {
+            path: "/name".to_string(),
+            severity: Severity::Warning,
+            code: ViolationCode::RegistryMismatch,
+            message: "Name differs from registry".to_string(),
+        });
+        assert!(report.is_valid());
+        assert!(report.clone().into_result().is_ok());
+
+        report.error(
+            "/countryOfResidence",
+            Error::InvalidCountryCode("Country code must be 2 characters: USA".to_string()),
+        );
+        assert!(!report.is_valid());
+        assert_eq!(report.warnings().count(), 1);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["violations"][1]["severity"], "error");
+        assert_eq!(json["violations"][1]["code"], "invalid_country_code");
+        assert_eq!(
+            report.violations[1].to_string(),
+            "/countryOfResidence: Country code must be 2 characters: USA"
+        );
+        assert!(matches!(
+            report.into_result(),
+            Err(Error::InvalidCountryCode(_))
+        ));
+    }
+}
@@ -65,7 +65,7 @@ pub fn validate_currency_code(code: &str) -> Result<()> {
     Ok(())
 }
 
-/// Validate a Legal Entity Identifier (LEI)
+/// Validate a Legal Entity Identifier (LEI), including its ISO 17442 check digits
 pub fn validate_lei(lei: &str) -> Result<()> {
     if !lei_regex().is_match(lei) {
         return Err(Error::InvalidLei(format!(
@@ -74,7 +74,22 @@ pub fn validate_lei(lei: &str) -> Result<()> {
         )));
     }
 
-    // TODO: Could add checksum validation here
+    // ISO 17442 check digits: the LEI read as a base-36 number is 1 mod 97
+    let remainder = lei.chars().fold(0u32, |acc, c| {
+        let value = c.to_digit(36).unwrap_or(0);
+        if value < 10 {
+            (acc * 10 + value) % 97
+        } else {
+            (acc * 100 + value) % 97
+        }
+    });
+    if remainder != 1 {
+        return Err(Error::InvalidLei(format!(
+            "LEI check digits are invalid: {}",
+            lei
+        )));
+    }
+
     Ok(())
 }
 
@@ -146,7 +161,9 @@ mod tests {
     #[test]
     fn test_validate_lei() {
         assert!(validate_lei("529900HNOAA1KXQJUQ27").is_ok());
-        assert!(validate_lei("ABCDEFGHIJ1234567890").is_ok());
+        assert!(validate_lei("5493001KJTIIGC8Y1R12").is_ok());
+        assert!(validate_lei("ABCDEFGHIJ1234567890").is_err()); // Bad check digits
+        assert!(validate_lei("529900HNOAA1KXQJUQ28").is_err()); // Bad check digits
 
         assert!(validate_lei("529900HNOAA1KXQJUQ2").is_err()); // Too short
         assert!(validate_lei("529900HNOAA1KXQJUQ277").is_err()); // Too long
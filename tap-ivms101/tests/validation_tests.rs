@@ -21,6 +21,12 @@ fn test_lei_validation_edge_cases() {
 
     // Invalid: special characters
     assert!(validate_lei("529900-NOAA1KXQJUQ27").is_err());
+
+    // Invalid: check digits do not match
+    assert!(matches!(
+        validate_lei("529900HNOAA1KXQJUQ72"),
+        Err(Error::InvalidLei(_))
+    ));
 }
 
 #[test]
@@ -205,3 +211,88 @@ fn test_national_identification_validation() {
     };
     assert!(matches!(id.validate(), Err(Error::InvalidCountryCode(_))));
 }
+
+#[test]
+fn test_validate_all_reports_every_violation() {
+    use tap_ivms101::message::*;
+    use tap_ivms101::person::{NameIdentifier, NaturalPerson, NaturalPersonName};
+    use tap_ivms101::{Severity, Validate, ViolationCode};
+
+    let mut originator = NaturalPerson::new(NaturalPersonName::new(vec![NameIdentifier::new(
+        "",
+        "Alice",
+        NameIdentifierType::LegalName,
+    )]));
+    originator.country_of_residence = Some("USA".to_string());
+
+    let message = IvmsMessage::new(
+        Originator::new(vec![Person::NaturalPerson(originator)]),
+        Beneficiary::new(vec![]),
+        OriginatingVasp::new(Person::NaturalPerson(NaturalPerson::new(
+            NaturalPersonName::new(vec![NameIdentifier::new(
+                "Exchange",
+                "",
+                NameIdentifierType::LegalName,
+            )]),
+        ))),
+        TransactionData {
+            amount: "100".to_string(),
+            currency: "USD".to_string(),
+            direction: TransactionDirection::Outgoing,
+            payment_type: None,
+            transaction_identifier: "tx-1".to_string(),
+            transaction_datetime: "yesterday".to_string(),
+            transaction_network: None,
+            transaction_hash: None,
+        },
+    );
+
+    let report = message.validate_all();
+    assert!(!report.is_valid());
+    assert!(report
+        .violations
+        .iter()
+        .all(|v| v.severity == Severity::Error));
+
+    let paths: Vec<(&str, ViolationCode)> = report
+        .violations
+        .iter()
+        .map(|v| (v.path.as_str(), v.code))
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            (
+                "/originator/originatorPersons/0/naturalPerson/name/nameIdentifiers/0/primaryIdentifier",
+                ViolationCode::InvalidName
+            ),
+            (
+                "/originator/originatorPersons/0/naturalPerson/countryOfResidence",
+                ViolationCode::InvalidCountryCode
+            ),
+            (
+                "/beneficiary/beneficiaryPersons",
+                ViolationCode::MissingRequiredField
+            ),
+            (
+                "/transaction/transactionDatetime",
+                ViolationCode::InvalidDate
+            ),
+        ]
+    );
+
+    // Every path resolves in the serialized payload
+    let json = serde_json::to_value(&message).unwrap();
+    for violation in &report.violations {
+        assert!(
+            json.pointer(&violation.path).is_some(),
+            "{}",
+            violation.path
+        );
+    }
+
+    match message.validate() {
+        Err(Error::ValidationFailed { issues }) => assert_eq!(issues.len(), 4),
+        other => panic!("Expected all issues, got {:?}", other),
+    }
+}
@@ -35,6 +35,9 @@ pub enum Error {
 
     #[error("Configuration error: {0}")]
     Configuration(String),
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
 }
 
 impl Error {
@@ -53,4 +56,8 @@ impl Error {
     pub fn configuration(msg: impl Into<String>) -> Self {
         Self::Configuration(msg.into())
     }
+
+    pub fn unauthorized(msg: impl Into<String>) -> Self {
+        Self::Unauthorized(msg.into())
+    }
 }
@@ -4,6 +4,7 @@
 
 pub mod error;
 pub mod mcp;
+pub mod prompts;
 pub mod resources;
 pub mod tap_integration;
 pub mod tools;
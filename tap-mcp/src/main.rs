@@ -7,6 +7,7 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 mod error;
 mod mcp;
+mod prompts;
 mod resources;
 mod tap_integration;
 mod tools;
@@ -35,12 +36,39 @@ struct Args {
     /// Secret helper command for external key management (replaces keys.json)
     #[arg(long, env = "TAP_SECRET_HELPER")]
     secret_helper: Option<String>,
+
+    /// Require access tokens signed with this HS256 secret (at least 32 bytes)
+    #[arg(long, env = "TAP_MCP_AUTH_SECRET", hide_env_values = true)]
+    auth_secret: Option<String>,
+
+    /// Print an access token with this scope (read, transact or admin) and exit
+    #[arg(long, requires = "auth_secret")]
+    issue_token: Option<String>,
+
+    /// Subject of issued access tokens
+    #[arg(long, default_value = "tap-mcp-client")]
+    token_subject: String,
+
+    /// Lifetime of issued access tokens in seconds
+    #[arg(long, default_value = "86400")]
+    token_ttl: u64,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let mut args = Args::parse();
 
+    let auth = args
+        .auth_secret
+        .as_deref()
+        .map(|secret| mcp::auth::TokenAuthority::from_secret(secret.as_bytes()))
+        .transpose()?;
+    if let (Some(scope), Some(auth)) = (&args.issue_token, &auth) {
+        let token = auth.issue(&args.token_subject, scope.parse()?, args.token_ttl)?;
+        println!("{}", token);
+        return Ok(());
+    }
+
     // Apply environment variables as fallback if not provided via CLI
     // Priority: --tap-root > TAP_ROOT > TAP_HOME > default (~/.tap)
     if args.tap_root.is_none() {
@@ -149,7 +177,7 @@ async fn main() -> Result<()> {
                 let agent = TapAgent::new(config, Arc::new(key_manager));
 
                 info!("New key saved to storage successfully");
-                (Arc::new(agent), generated_key.did)
+                (Arc::new(agent), generated_key.did.clone())
             }
         }
     };
@@ -168,9 +196,16 @@ async fn main() -> Result<()> {
     );
 
     // Create and run MCP server
-    let mcp_server = mcp::McpServer::new(tap_integration).await?;
+    let mut mcp_server = mcp::McpServer::new(tap_integration).await?;
+    if let Some(auth) = auth {
+        info!("Access tokens are required for tool calls and resource reads");
+        mcp_server = mcp_server.with_auth(auth);
+    }
 
-    info!("Starting MCP server on stdio");
+    info!(
+        "Starting MCP server on stdio (session {})",
+        mcp_server.session_id()
+    );
     if let Err(e) = mcp_server.run().await {
         error!("MCP server error: {}", e);
         return Err(e);
@@ -0,0 +1,272 @@
+//! Access tokens for MCP sessions
+//!
+//! When the server is started with an auth secret, every request after
+//! `initialize`, listing tools, resources and prompts included, must carry a
+//! JWT signed with that secret (HS256), so that a session without a token
+//! cannot even enumerate what the server offers. The token
+//! is passed in the `_meta.authorization` field of the `initialize` params,
+//! where it applies to the whole session, or of an individual `tools/call`,
+//! where it overrides the session token for that call. A `Bearer ` prefix is
+//! accepted.
+//!
+//! The token's `scope` claim lists the granted [`Scope`]s separated by
+//! spaces. Scopes are ordered, so `transact` includes `read` and `admin`
+//! includes both. Tokens are checked on every invocation, so an expired token
+//! stops working mid-session.
+//!
+//! ```json
+//! { "sub": "ops-dashboard", "scope": "read", "exp": 1767225600 }
+//! ```
+
+use crate::error::{Error, Result};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Permission level granted by a token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// List and inspect transactions, customers, deliveries and events
+    Read,
+    /// Create, authorize, settle and otherwise act on transactions
+    Transact,
+    /// Manage agents and policies, and query the agent databases directly
+    Admin,
+}
+
+impl Scope {
+    /// Scope required to call a tool
+    ///
+    /// Tools that are not listed here require [`Scope::Admin`]. These include
+    /// `tap_query_database` and `tap_get_database_schema`: running SQL over
+    /// the agent databases reads every table, customers and messages
+    /// included, so it is not part of the read-only scope.
+    pub fn required_for_tool(tool: &str) -> Self {
+        match tool {
+            "tap_list_agents"
+            | "tap_list_transactions"
+            | "tap_list_deliveries_by_recipient"
+            | "tap_list_deliveries_by_message"
+            | "tap_list_deliveries_by_thread"
+            | "tap_list_customers"
+            | "tap_list_connections"
+            | "tap_get_customer_details"
+            | "tap_generate_ivms101"
+            | "tap_list_received"
+            | "tap_get_pending_received"
+            | "tap_view_raw_received"
+            | "tap_list_pending_decisions"
+            | "tap_list_reviews"
+            | "tap_list_scheduled_jobs"
+            | "tap_get_counterparty_stats"
+            | "tap_subscribe_events"
+            | "tap_unsubscribe_events"
+            | "tap_list_event_subscriptions" => Scope::Read,
+            "tap_create_transfer"
+            | "tap_payment"
+            | "tap_connect"
+            | "tap_escrow"
+            | "tap_capture"
+            | "tap_exchange"
+            | "tap_quote"
+            | "tap_authorize"
+            | "tap_reject"
+            | "tap_cancel"
+            | "tap_settle"
+            | "tap_revert"
+            | "tap_trust_ping"
+            | "tap_basic_message"
+            | "tap_create_customer"
+            | "tap_update_customer_profile"
+            | "tap_update_customer_from_ivms101"
+            | "tap_resolve_decision"
+            | "tap_approve_review"
+            | "tap_reject_review" => Scope::Transact,
+            _ => Scope::Admin,
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::Read => write!(f, "read"),
+            Scope::Transact => write!(f, "transact"),
+            Scope::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "read" => Ok(Scope::Read),
+            "transact" => Ok(Scope::Transact),
+            "admin" => Ok(Scope::Admin),
+            _ => Err(Error::invalid_parameter(format!("Unknown scope: {}", s))),
+        }
+    }
+}
+
+/// Claims carried by an access token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Claims {
+    /// Who the token was issued to, recorded in audit logs
+    pub sub: String,
+    /// Granted scopes, separated by spaces
+    pub scope: String,
+    /// Expiry as seconds since the Unix epoch
+    pub exp: u64,
+    /// Issue time as seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat: Option<u64>,
+}
+
+impl Claims {
+    /// Highest scope granted by the token, ignoring unknown scopes
+    pub fn max_scope(&self) -> Option<Scope> {
+        self.scope
+            .split_whitespace()
+            .filter_map(|s| s.parse().ok())
+            .max()
+    }
+
+    /// Whether the token grants at least the given scope
+    pub fn allows(&self, required: Scope) -> bool {
+        self.max_scope().is_some_and(|granted| granted >= required)
+    }
+}
+
+/// Verifies and issues access tokens signed with a shared secret
+#[derive(Clone)]
+pub struct TokenAuthority {
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    validation: Validation,
+}
+
+impl fmt::Debug for TokenAuthority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenAuthority").finish_non_exhaustive()
+    }
+}
+
+impl TokenAuthority {
+    /// Create an authority from a shared HS256 secret
+    pub fn from_secret(secret: &[u8]) -> Result<Self> {
+        if secret.len() < 32 {
+            return Err(Error::configuration(
+                "Auth secret must be at least 32 bytes",
+            ));
+        }
+
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_required_spec_claims(&["exp", "sub"]);
+        validation.leeway = 0;
+
+        Ok(Self {
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+            validation,
+        })
+    }
+
+    /// Issue a token for a subject with the given scope, valid for `ttl_secs`
+    pub fn issue(&self, subject: &str, scope: Scope, ttl_secs: u64) -> Result<String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let claims = Claims {
+            sub: subject.to_string(),
+            scope: scope.to_string(),
+            exp: now + ttl_secs,
+            iat: Some(now),
+        };
+        encode(&Header::new(Algorithm::HS256), &claims, &self.encoding_key)
+            .map_err(|e| Error::configuration(format!("Failed to issue token: {}", e)))
+    }
+
+    /// Verify a token's signature and expiry and return its claims
+    pub fn verify(&self, token: &str) -> Result<Claims> {
+        let token = token.strip_prefix("Bearer ").unwrap_or(token).trim();
+        decode::<Claims>(token, &self.decoding_key, &self.validation)
+            .map(|data| data.claims)
+            .map_err(|e| Error::unauthorized(format!("Invalid access token: {}", e)))
+    }
+
+    /// Verify a token and check that it grants the required scope
+    pub fn authorize(&self, token: Option<&str>, required: Scope) -> Result<Claims> {
+        let token = token.ok_or_else(|| Error::unauthorized("An access token is required"))?;
+        let claims = self.verify(token)?;
+        if !claims.allows(required) {
+            return Err(Error::unauthorized(format!(
+                "Token for {} does not grant the {} scope",
+                claims.sub, required
+            )));
+        }
+        Ok(claims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn test_scopes_are_ordered() {
+        let authority = TokenAuthority::from_secret(SECRET).unwrap();
+        let token = authority.issue("dashboard", Scope::Transact, 60).unwrap();
+
+        assert!(authority.authorize(Some(&token), Scope::Read).is_ok());
+        let claims = authority
+            .authorize(Some(&format!("Bearer {}", token)), Scope::Transact)
+            .unwrap();
+        assert_eq!(claims.sub, "dashboard");
+        assert!(authority.authorize(Some(&token), Scope::Admin).is_err());
+        assert!(authority.authorize(None, Scope::Read).is_err());
+    }
+
+    #[test]
+    fn test_rejects_foreign_and_expired_tokens() {
+        let authority = TokenAuthority::from_secret(SECRET).unwrap();
+        let other = TokenAuthority::from_secret(b"another-secret-another-secret-xx").unwrap();
+
+        let foreign = other.issue("dashboard", Scope::Admin, 60).unwrap();
+        assert!(authority.verify(&foreign).is_err());
+
+        let expired = encode(
+            &Header::new(Algorithm::HS256),
+            &Claims {
+                sub: "dashboard".to_string(),
+                scope: "admin".to_string(),
+                exp: chrono::Utc::now().timestamp() as u64 - 1,
+                iat: None,
+            },
+            &EncodingKey::from_secret(SECRET),
+        )
+        .unwrap();
+        assert!(authority.verify(&expired).is_err());
+
+        assert!(TokenAuthority::from_secret(b"short").is_err());
+    }
+
+    #[test]
+    fn test_tool_scopes() {
+        assert_eq!(
+            Scope::required_for_tool("tap_list_transactions"),
+            Scope::Read
+        );
+        assert_eq!(Scope::required_for_tool("tap_authorize"), Scope::Transact);
+        assert_eq!(Scope::required_for_tool("tap_add_agents"), Scope::Admin);
+        assert_eq!(Scope::required_for_tool("tap_query_database"), Scope::Admin);
+        assert_eq!(
+            Scope::required_for_tool("tap_get_database_schema"),
+            Scope::Admin
+        );
+        assert_eq!(Scope::required_for_tool("tap_unknown"), Scope::Admin);
+    }
+}
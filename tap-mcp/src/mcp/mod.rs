@@ -1,7 +1,9 @@
 //! Model Context Protocol implementation for TAP
 
+pub mod auth;
 pub mod protocol;
 pub mod server;
+pub mod subscriptions;
 pub mod transport;
 
 pub use server::McpServer;
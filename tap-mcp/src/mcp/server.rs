@@ -1,8 +1,11 @@
 //! MCP server implementation
 
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::mcp::auth::{Scope, TokenAuthority};
 use crate::mcp::protocol::*;
+use crate::mcp::subscriptions::EventSubscriptionManager;
 use crate::mcp::transport::StdioTransport;
+use crate::prompts::PromptRegistry;
 use crate::resources::ResourceRegistry;
 use crate::tap_integration::TapIntegration;
 use crate::tools::ToolRegistry;
@@ -15,7 +18,13 @@ pub struct McpServer {
     transport: StdioTransport,
     tool_registry: ToolRegistry,
     resource_registry: ResourceRegistry,
+    prompt_registry: PromptRegistry,
+    event_subscriptions: Arc<EventSubscriptionManager>,
+    tap_integration: Arc<TapIntegration>,
     initialized: bool,
+    auth: Option<TokenAuthority>,
+    session_id: String,
+    session_token: Option<String>,
 }
 
 impl McpServer {
@@ -24,33 +33,113 @@ impl McpServer {
         let tap_integration = Arc::new(tap_integration);
         let tool_registry = ToolRegistry::new(tap_integration.clone());
         let resource_registry = ResourceRegistry::new(tap_integration.clone());
+        let prompt_registry = PromptRegistry::new(tap_integration.clone());
+        let event_subscriptions = tap_integration.event_subscriptions().clone();
 
         Ok(Self {
             transport: StdioTransport::new(),
             tool_registry,
             resource_registry,
+            prompt_registry,
+            event_subscriptions,
+            tap_integration,
             initialized: false,
+            auth: None,
+            session_id: uuid::Uuid::new_v4().to_string(),
+            session_token: None,
         })
     }
 
+    /// Require access tokens issued by the given authority, see
+    /// [`auth`](crate::mcp::auth)
+    pub fn with_auth(mut self, authority: TokenAuthority) -> Self {
+        self.auth = Some(authority);
+        self
+    }
+
+    /// Identifier of this session, recorded in audit logs
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Check that a token grants the scope required for an action and record
+    /// the attempt in the audit log
+    ///
+    /// Without an authority every action is allowed and nothing is audited.
+    fn authorize(
+        &self,
+        token: Option<&str>,
+        action: &str,
+        required: Scope,
+    ) -> std::result::Result<(), JsonRpcError> {
+        let Some(authority) = &self.auth else {
+            return Ok(());
+        };
+
+        match authority.authorize(token, required) {
+            Ok(claims) => {
+                info!(
+                    target: "tap_mcp::audit",
+                    session = %self.session_id,
+                    subject = %claims.sub,
+                    action,
+                    "allowed"
+                );
+                Ok(())
+            }
+            Err(e) => {
+                warn!(
+                    target: "tap_mcp::audit",
+                    session = %self.session_id,
+                    action,
+                    "denied: {}",
+                    e
+                );
+                Err(JsonRpcError::unauthorized(e.to_string()))
+            }
+        }
+    }
+
     /// Run the MCP server
     pub async fn run(mut self) -> Result<()> {
         info!("MCP server started, waiting for requests");
 
+        let mut notifications = self.event_subscriptions.take_receiver().await;
+
         loop {
-            match self.transport.read_request().await {
-                Ok(Some(request)) => {
-                    if let Err(e) = self.handle_request(request).await {
-                        error!("Error handling request: {}", e);
+            tokio::select! {
+                request = self.transport.read_request() => match request {
+                    Ok(Some(request)) => {
+                        if let Err(e) = self.handle_request(request).await {
+                            error!("Error handling request: {}", e);
+                        }
+                    }
+                    Ok(None) => {
+                        info!("Client disconnected, shutting down");
+                        break;
+                    }
+                    Err(e) => {
+                        error!("Transport error: {}", e);
+                        break;
+                    }
+                },
+                // Notifications are held in the bounded queue until the client
+                // has sent `initialized`; anything beyond its capacity is
+                // counted and reported once delivery starts.
+                Some(notification) = async {
+                    match notifications.as_mut() {
+                        Some(receiver) => receiver.recv().await,
+                        None => std::future::pending().await,
+                    }
+                }, if self.initialized => {
+                    if let Some(dropped) = self.event_subscriptions.take_dropped() {
+                        if let Err(e) = self.transport.write_notification(dropped).await {
+                            error!("Failed to send notification: {}", e);
+                        }
+                    }
+                    if let Err(e) = self.transport.write_notification(notification).await {
+                        error!("Failed to send notification: {}", e);
                     }
-                }
-                Ok(None) => {
-                    info!("Client disconnected, shutting down");
-                    break;
-                }
-                Err(e) => {
-                    error!("Transport error: {}", e);
-                    break;
                 }
             }
         }
@@ -81,6 +170,8 @@ impl McpServer {
             "tools/call" => self.handle_call_tool(request.id, request.params).await,
             "resources/list" => self.handle_list_resources(request.id, request.params).await,
             "resources/read" => self.handle_read_resource(request.id, request.params).await,
+            "prompts/list" => self.handle_list_prompts(request.id, request.params).await,
+            "prompts/get" => self.handle_get_prompt(request.id, request.params).await,
             _ => {
                 // Don't send response for unknown notifications
                 if is_notification {
@@ -118,8 +209,8 @@ impl McpServer {
         };
 
         info!(
-            "Initializing with client: {} v{}",
-            params.client_info.name, params.client_info.version
+            "Initializing session {} with client: {} v{}",
+            self.session_id, params.client_info.name, params.client_info.version
         );
 
         // Check protocol version compatibility
@@ -134,7 +225,7 @@ impl McpServer {
             protocol_version: MCP_VERSION.to_string(),
             capabilities: ServerCapabilities {
                 logging: None,
-                prompts: None,
+                prompts: Some(serde_json::json!({ "listChanged": false })),
                 resources: Some(ResourcesCapability {
                     subscribe: Some(false),
                     list_changed: Some(false),
@@ -142,7 +233,12 @@ impl McpServer {
                 tools: Some(ToolsCapability {
                     list_changed: Some(false),
                 }),
-                experimental: None,
+                experimental: Some(serde_json::json!({
+                    "tapEventSubscriptions": {
+                        "notificationMethod": crate::mcp::subscriptions::EVENT_NOTIFICATION_METHOD,
+                    },
+                    "tapNode": self.tap_integration.node().capabilities(),
+                })),
             },
             server_info: ServerInfo {
                 name: "tap-mcp".to_string(),
@@ -151,7 +247,13 @@ impl McpServer {
         };
 
         match serde_json::to_value(result) {
-            Ok(value) => JsonRpcResponse::success(id, value),
+            Ok(value) => {
+                // The session token only takes effect once the handshake has
+                // produced a result, so a failed initialize leaves the
+                // session unauthenticated
+                self.session_token = params.meta.and_then(|meta| meta.authorization);
+                JsonRpcResponse::success(id, value)
+            }
             Err(e) => JsonRpcResponse::error(id, JsonRpcError::internal_error(e.to_string())),
         }
     }
@@ -166,6 +268,10 @@ impl McpServer {
             return JsonRpcResponse::error(id, JsonRpcError::invalid_request("Not initialized"));
         }
 
+        if let Err(e) = self.authorize(self.session_token.as_deref(), "tools/list", Scope::Read) {
+            return JsonRpcResponse::error(id, e);
+        }
+
         let tools = self.tool_registry.list_tools();
         let result = ListToolsResult {
             tools,
@@ -199,6 +305,18 @@ impl McpServer {
             }
         };
 
+        let token = params
+            .meta
+            .and_then(|meta| meta.authorization)
+            .or_else(|| self.session_token.clone());
+        if let Err(e) = self.authorize(
+            token.as_deref(),
+            &params.name,
+            Scope::required_for_tool(&params.name),
+        ) {
+            return JsonRpcResponse::error(id, e);
+        }
+
         match self
             .tool_registry
             .call_tool(&params.name, params.arguments)
@@ -236,6 +354,11 @@ impl McpServer {
             return JsonRpcResponse::error(id, JsonRpcError::invalid_request("Not initialized"));
         }
 
+        if let Err(e) = self.authorize(self.session_token.as_deref(), "resources/list", Scope::Read)
+        {
+            return JsonRpcResponse::error(id, e);
+        }
+
         let resources = self.resource_registry.list_resources().await;
         let result = ListResourcesResult {
             resources,
@@ -273,6 +396,10 @@ impl McpServer {
             }
         };
 
+        if let Err(e) = self.authorize(self.session_token.as_deref(), &params.uri, Scope::Read) {
+            return JsonRpcResponse::error(id, e);
+        }
+
         match self.resource_registry.read_resource(&params.uri).await {
             Ok(contents) => {
                 let result = ReadResourceResult { contents };
@@ -290,6 +417,79 @@ impl McpServer {
         }
     }
 
+    /// Handle list prompts request
+    async fn handle_list_prompts(
+        &self,
+        id: Option<Value>,
+        _params: Option<Value>,
+    ) -> JsonRpcResponse {
+        if !self.initialized {
+            return JsonRpcResponse::error(id, JsonRpcError::invalid_request("Not initialized"));
+        }
+
+        if let Err(e) = self.authorize(self.session_token.as_deref(), "prompts/list", Scope::Read) {
+            return JsonRpcResponse::error(id, e);
+        }
+
+        let result = ListPromptsResult {
+            prompts: self.prompt_registry.list_prompts(),
+            next_cursor: None,
+        };
+
+        match serde_json::to_value(result) {
+            Ok(value) => JsonRpcResponse::success(id, value),
+            Err(e) => JsonRpcResponse::error(id, JsonRpcError::internal_error(e.to_string())),
+        }
+    }
+
+    /// Handle get prompt request
+    async fn handle_get_prompt(&self, id: Option<Value>, params: Option<Value>) -> JsonRpcResponse {
+        if !self.initialized {
+            return JsonRpcResponse::error(id, JsonRpcError::invalid_request("Not initialized"));
+        }
+
+        let params: GetPromptParams = match params {
+            Some(p) => match serde_json::from_value(p) {
+                Ok(params) => params,
+                Err(e) => {
+                    return JsonRpcResponse::error(id, JsonRpcError::invalid_params(e.to_string()));
+                }
+            },
+            None => {
+                return JsonRpcResponse::error(
+                    id,
+                    JsonRpcError::invalid_params("Missing parameters"),
+                );
+            }
+        };
+
+        let token = params
+            .meta
+            .and_then(|meta| meta.authorization)
+            .or_else(|| self.session_token.clone());
+        if let Err(e) = self.authorize(token.as_deref(), &params.name, Scope::Read) {
+            return JsonRpcResponse::error(id, e);
+        }
+
+        match self
+            .prompt_registry
+            .get_prompt(&params.name, &params.arguments)
+            .await
+        {
+            Ok(result) => match serde_json::to_value(result) {
+                Ok(value) => JsonRpcResponse::success(id, value),
+                Err(e) => JsonRpcResponse::error(id, JsonRpcError::internal_error(e.to_string())),
+            },
+            Err(e @ (Error::InvalidParameter(_) | Error::ResourceNotFound(_))) => {
+                JsonRpcResponse::error(id, JsonRpcError::invalid_params(e.to_string()))
+            }
+            Err(e) => {
+                error!("Prompt rendering failed: {}", e);
+                JsonRpcResponse::error(id, JsonRpcError::internal_error(e.to_string()))
+            }
+        }
+    }
+
     /// Handle a request directly (for testing)
     #[allow(dead_code)]
     pub async fn handle_request_direct(
@@ -324,6 +524,8 @@ impl McpServer {
             "tools/call" => self.handle_call_tool(request.id, request.params).await,
             "resources/list" => self.handle_list_resources(request.id, request.params).await,
             "resources/read" => self.handle_read_resource(request.id, request.params).await,
+            "prompts/list" => self.handle_list_prompts(request.id, request.params).await,
+            "prompts/get" => self.handle_get_prompt(request.id, request.params).await,
             _ => {
                 warn!("Unknown method: {}", request.method);
                 JsonRpcResponse::error(request.id, JsonRpcError::method_not_found(request.method))
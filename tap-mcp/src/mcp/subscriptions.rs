@@ -0,0 +1,774 @@
+//! Event subscriptions delivered as MCP notifications
+//!
+//! Clients register subscriptions through the `tap_subscribe_events` tool.
+//! Node events matching a subscription's filter are converted to
+//! `notifications/tap/event` JSON-RPC notifications and queued for the
+//! server's transport.
+//!
+//! The queue is bounded. When the client cannot keep up, new notifications
+//! are dropped and counted; the server reports the count with a
+//! `notifications/tap/events_dropped` notification before the next event it
+//! delivers, so clients know to resynchronize.
+
+use crate::mcp::protocol::JsonRpcNotification;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tap_node::event::{EventSubscriber, NodeEvent};
+use tokio::sync::{mpsc, Mutex, RwLock};
+use tracing::{debug, warn};
+
+/// JSON-RPC method used for event notifications
+pub const EVENT_NOTIFICATION_METHOD: &str = "notifications/tap/event";
+
+/// JSON-RPC method used to report notifications dropped because the queue was full
+pub const EVENTS_DROPPED_NOTIFICATION_METHOD: &str = "notifications/tap/events_dropped";
+
+/// Default number of notifications queued for the transport before new ones are dropped
+pub const DEFAULT_NOTIFICATION_CAPACITY: usize = 1024;
+
+/// Filter applied to node events for a subscription
+///
+/// Empty lists and unset fields match everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventFilter {
+    /// Event types to forward (e.g. `transaction_state_changed`, `message_received`)
+    #[serde(default)]
+    pub event_types: Vec<String>,
+    /// Only forward events belonging to this transaction
+    #[serde(default)]
+    pub transaction_id: Option<String>,
+    /// Only forward message events whose DIDComm type contains one of these strings
+    #[serde(default)]
+    pub message_types: Vec<String>,
+    /// Only forward events related to this agent DID
+    #[serde(default)]
+    pub agent_did: Option<String>,
+}
+
+/// A flattened, serializable view of a node event
+#[derive(Debug, Clone, Serialize)]
+pub struct EventPayload {
+    pub event_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_did: Option<String>,
+    pub data: Value,
+}
+
+impl EventPayload {
+    /// Convert a node event into a payload, skipping events that are not forwarded
+    pub fn from_event(event: &NodeEvent) -> Option<Self> {
+        let payload = match event {
+            NodeEvent::MessageReceived { message, source } => Self {
+                event_type: "message_received".to_string(),
+                transaction_id: Some(message.thid.clone().unwrap_or_else(|| message.id.clone())),
+                message_type: Some(message.type_.clone()),
+                agent_did: message.to.first().cloned(),
+                data: json!({
+                    "message_id": message.id,
+                    "from": message.from,
+                    "to": message.to,
+                    "body": message.body,
+                    "source": source,
+                }),
+            },
+            NodeEvent::MessageSent {
+                message,
+                destination,
+            } => Self {
+                event_type: "message_sent".to_string(),
+                transaction_id: Some(message.thid.clone().unwrap_or_else(|| message.id.clone())),
+                message_type: Some(message.type_.clone()),
+                agent_did: Some(message.from.clone()),
+                data: json!({
+                    "message_id": message.id,
+                    "from": message.from,
+                    "to": message.to,
+                    "destination": destination,
+                }),
+            },
+            NodeEvent::MessageAccepted {
+                message_id,
+                message_type,
+                from,
+                to,
+            } => Self {
+                event_type: "message_accepted".to_string(),
+                transaction_id: None,
+                message_type: Some(message_type.clone()),
+                agent_did: Some(to.clone()),
+                data: json!({
+                    "message_id": message_id,
+                    "from": from,
+                    "to": to,
+                }),
+            },
+            NodeEvent::MessageRejected {
+                message_id,
+                reason,
+                from,
+                to,
+            } => Self {
+                event_type: "message_rejected".to_string(),
+                transaction_id: None,
+                message_type: None,
+                agent_did: Some(to.clone()),
+                data: json!({
+                    "message_id": message_id,
+                    "reason": reason,
+                    "from": from,
+                    "to": to,
+                }),
+            },
+            NodeEvent::TransactionCreated {
+                transaction,
+                agent_did,
+            } => Self {
+                event_type: "transaction_created".to_string(),
+                transaction_id: Some(transaction.reference_id.clone()),
+                message_type: Some(transaction.message_type.clone()),
+                agent_did: Some(agent_did.clone()),
+                data: json!({
+                    "transaction_type": transaction.transaction_type.to_string(),
+                    "status": transaction.status.to_string(),
+                    "from": transaction.from_did,
+                    "to": transaction.to_did,
+                }),
+            },
+            NodeEvent::TransactionStateChanged {
+                transaction_id,
+                old_state,
+                new_state,
+                agent_did,
+            } => Self {
+                event_type: "transaction_state_changed".to_string(),
+                transaction_id: Some(transaction_id.clone()),
+                message_type: None,
+                agent_did: agent_did.clone(),
+                data: json!({
+                    "old_state": old_state,
+                    "new_state": new_state,
+                }),
+            },
+            NodeEvent::DecisionRequired {
+                transaction_id,
+                transaction_state,
+                decision,
+                pending_agents,
+            } => Self {
+                event_type: "decision_required".to_string(),
+                transaction_id: Some(transaction_id.clone()),
+                message_type: None,
+                agent_did: None,
+                data: json!({
+                    "transaction_state": transaction_state,
+                    "decision": decision,
+                    "pending_agents": pending_agents,
+                }),
+            },
+            NodeEvent::CustomerUpdated {
+                customer_id,
+                agent_did,
+                update_type,
+            } => Self {
+                event_type: "customer_updated".to_string(),
+                transaction_id: None,
+                message_type: None,
+                agent_did: Some(agent_did.clone()),
+                data: json!({
+                    "customer_id": customer_id,
+                    "update_type": update_type,
+                }),
+            },
+            NodeEvent::PolicyTriggered {
+                transaction_id,
+                rule,
+                action,
+                reason,
+                details,
+            } => Self {
+                event_type: "policy_triggered".to_string(),
+                transaction_id: Some(transaction_id.clone()),
+                message_type: None,
+                agent_did: None,
+                data: json!({
+                    "rule": rule,
+                    "action": action,
+                    "reason": reason,
+                    "details": details,
+                }),
+            },
+            NodeEvent::ReconciliationCompleted {
+                reprocessed_messages,
+                redelivered_messages,
+                requeued_deliveries,
+                resumed_settlements,
+                expired_decisions,
+                expired_challenges,
+                flagged,
+            } => Self {
+                event_type: "reconciliation_completed".to_string(),
+                transaction_id: None,
+                message_type: None,
+                agent_did: None,
+                data: json!({
+                    "reprocessed_messages": reprocessed_messages,
+                    "redelivered_messages": redelivered_messages,
+                    "requeued_deliveries": requeued_deliveries,
+                    "resumed_settlements": resumed_settlements,
+                    "expired_decisions": expired_decisions,
+                    "expired_challenges": expired_challenges,
+                    "flagged": flagged,
+                }),
+            },
+            NodeEvent::TravelRuleDataValidated {
+                message_id,
+                sender,
+                role,
+                report,
+            } => Self {
+                event_type: "travel_rule_data_validated".to_string(),
+                transaction_id: None,
+                message_type: None,
+                agent_did: Some(sender.clone()),
+                data: json!({
+                    "message_id": message_id,
+                    "role": role,
+                    "valid": report.is_valid(),
+                    "violations": report.violations,
+                }),
+            },
+            NodeEvent::TravelRulePolicyEvaluated {
+                message_id,
+                transaction_id,
+                sender,
+                evaluation,
+            } => Self {
+                event_type: "travel_rule_policy_evaluated".to_string(),
+                transaction_id: Some(transaction_id.clone()),
+                message_type: None,
+                agent_did: Some(sender.clone()),
+                data: json!({
+                    "message_id": message_id,
+                    "about_party": evaluation.about_party,
+                    "satisfied": evaluation.satisfied,
+                    "missing": evaluation.missing,
+                }),
+            },
+            NodeEvent::TransactionExpired {
+                transaction_id,
+                agent_did,
+                expires_time,
+            } => Self {
+                event_type: "transaction_expired".to_string(),
+                transaction_id: Some(transaction_id.clone()),
+                message_type: None,
+                agent_did: Some(agent_did.clone()),
+                data: json!({
+                    "expires_time": expires_time,
+                }),
+            },
+            NodeEvent::ProblemReportReceived {
+                message_id,
+                from,
+                code,
+                comment,
+                pthid,
+                escalate_to,
+            } => Self {
+                event_type: "problem_report_received".to_string(),
+                // TAP threads are keyed by transaction ID
+                transaction_id: pthid.clone(),
+                message_type: Some(
+                    tap_msg::message::problem_report::PROBLEM_REPORT_TYPE.to_string(),
+                ),
+                agent_did: Some(from.clone()),
+                data: json!({
+                    "message_id": message_id,
+                    "code": code,
+                    "comment": comment,
+                    "escalate_to": escalate_to,
+                }),
+            },
+            NodeEvent::MessageProcessingTimedOut {
+                message_id,
+                stage,
+                timeout_ms,
+                dead_lettered,
+            } => Self {
+                event_type: "message_processing_timed_out".to_string(),
+                transaction_id: None,
+                message_type: None,
+                agent_did: None,
+                data: json!({
+                    "message_id": message_id,
+                    "stage": stage,
+                    "timeout_ms": timeout_ms,
+                    "dead_lettered": dead_lettered,
+                }),
+            },
+            NodeEvent::StorageCorruptionDetected { db_path, problems } => Self {
+                event_type: "storage_corruption_detected".to_string(),
+                transaction_id: None,
+                message_type: None,
+                agent_did: None,
+                data: json!({
+                    "db_path": db_path,
+                    "problems": problems,
+                }),
+            },
+            NodeEvent::StorageRecovered {
+                db_path,
+                strategy,
+                quarantined_path,
+                restored_from,
+                salvaged_messages,
+                rebuilt_transactions,
+            } => Self {
+                event_type: "storage_recovered".to_string(),
+                transaction_id: None,
+                message_type: None,
+                agent_did: None,
+                data: json!({
+                    "db_path": db_path,
+                    "strategy": strategy,
+                    "quarantined_path": quarantined_path,
+                    "restored_from": restored_from,
+                    "salvaged_messages": salvaged_messages,
+                    "rebuilt_transactions": rebuilt_transactions,
+                }),
+            },
+            NodeEvent::StorageRecoveryFailed { db_path, errors } => Self {
+                event_type: "storage_recovery_failed".to_string(),
+                transaction_id: None,
+                message_type: None,
+                agent_did: None,
+                data: json!({
+                    "db_path": db_path,
+                    "errors": errors,
+                }),
+            },
+            NodeEvent::ClockDriftDetected {
+                server,
+                offset_ms,
+                max_offset_ms,
+            } => Self {
+                event_type: "clock_drift_detected".to_string(),
+                transaction_id: None,
+                message_type: None,
+                agent_did: None,
+                data: json!({
+                    "server": server,
+                    "offset_ms": offset_ms,
+                    "max_offset_ms": max_offset_ms,
+                }),
+            },
+            NodeEvent::AnomalyDetected {
+                agent_did,
+                counterparty,
+                transaction_id,
+                kind,
+                reason,
+                details,
+            } => Self {
+                event_type: "anomaly_detected".to_string(),
+                transaction_id: Some(transaction_id.clone()),
+                message_type: None,
+                agent_did: Some(agent_did.clone()),
+                data: json!({
+                    "counterparty": counterparty,
+                    "kind": kind,
+                    "reason": reason,
+                    "details": details,
+                }),
+            },
+            NodeEvent::SettlementOutOfTolerance {
+                transaction_id,
+                requested_asset,
+                requested_amount,
+                settled_asset,
+                settled_amount,
+                expected_amount,
+                slippage,
+                max_slippage,
+            } => Self {
+                event_type: "settlement_out_of_tolerance".to_string(),
+                transaction_id: Some(transaction_id.clone()),
+                message_type: None,
+                agent_did: None,
+                data: json!({
+                    "requested_asset": requested_asset,
+                    "requested_amount": requested_amount,
+                    "settled_asset": settled_asset,
+                    "settled_amount": settled_amount,
+                    "expected_amount": expected_amount,
+                    "slippage": slippage,
+                    "max_slippage": max_slippage,
+                }),
+            },
+            NodeEvent::AddressReuseDetected {
+                transaction_id,
+                address,
+                kind,
+                reason,
+                details,
+            } => Self {
+                event_type: "address_reuse_detected".to_string(),
+                transaction_id: Some(transaction_id.clone()),
+                message_type: None,
+                agent_did: None,
+                data: json!({
+                    "address": address,
+                    "kind": kind,
+                    "reason": reason,
+                    "details": details,
+                }),
+            },
+            NodeEvent::ResponseDeadlineApproaching {
+                agent_did,
+                counterparty,
+                message_id,
+                message_type,
+                thread_id,
+                deadline_at,
+            } => Self {
+                event_type: "response_deadline_approaching".to_string(),
+                transaction_id: Some(thread_id.clone()),
+                message_type: None,
+                agent_did: Some(agent_did.clone()),
+                data: json!({
+                    "counterparty": counterparty,
+                    "message_id": message_id,
+                    "message_type": message_type,
+                    "deadline_at": deadline_at,
+                }),
+            },
+            NodeEvent::ResponseDeadlineBreached {
+                agent_did,
+                counterparty,
+                message_id,
+                message_type,
+                thread_id,
+                deadline_at,
+            } => Self {
+                event_type: "response_deadline_breached".to_string(),
+                transaction_id: Some(thread_id.clone()),
+                message_type: None,
+                agent_did: Some(agent_did.clone()),
+                data: json!({
+                    "counterparty": counterparty,
+                    "message_id": message_id,
+                    "message_type": message_type,
+                    "deadline_at": deadline_at,
+                }),
+            },
+            NodeEvent::AgentRegistered { did } => Self {
+                event_type: "agent_registered".to_string(),
+                transaction_id: None,
+                message_type: None,
+                agent_did: Some(did.clone()),
+                data: json!({}),
+            },
+            NodeEvent::AgentUnregistered { did } => Self {
+                event_type: "agent_unregistered".to_string(),
+                transaction_id: None,
+                message_type: None,
+                agent_did: Some(did.clone()),
+                data: json!({}),
+            },
+            _ => return None,
+        };
+        Some(payload)
+    }
+}
+
+impl EventFilter {
+    /// Check whether a payload passes this filter
+    pub fn matches(&self, payload: &EventPayload) -> bool {
+        if !self.event_types.is_empty() && !self.event_types.contains(&payload.event_type) {
+            return false;
+        }
+
+        if let Some(ref transaction_id) = self.transaction_id {
+            if payload.transaction_id.as_ref() != Some(transaction_id) {
+                return false;
+            }
+        }
+
+        if !self.message_types.is_empty() {
+            match payload.message_type {
+                Some(ref message_type) => {
+                    let message_type = message_type.to_lowercase();
+                    if !self
+                        .message_types
+                        .iter()
+                        .any(|t| message_type.contains(&t.to_lowercase()))
+                    {
+                        return false;
+                    }
+                }
+                None => return false,
+            }
+        }
+
+        if let Some(ref agent_did) = self.agent_did {
+            if payload.agent_did.as_ref() != Some(agent_did) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Tracks active event subscriptions and queues matching notifications
+pub struct EventSubscriptionManager {
+    subscriptions: RwLock<HashMap<String, EventFilter>>,
+    sender: mpsc::Sender<JsonRpcNotification>,
+    receiver: Mutex<Option<mpsc::Receiver<JsonRpcNotification>>>,
+    dropped: AtomicU64,
+}
+
+impl EventSubscriptionManager {
+    /// Create a new subscription manager with an empty subscription set
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_NOTIFICATION_CAPACITY)
+    }
+
+    /// Create a subscription manager whose notification queue holds at most `capacity` entries
+    pub fn with_capacity(capacity: usize) -> Self {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        Self {
+            subscriptions: RwLock::new(HashMap::new()),
+            sender,
+            receiver: Mutex::new(Some(receiver)),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Register a subscription and return its ID
+    pub async fn subscribe(&self, filter: EventFilter) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        self.subscriptions.write().await.insert(id.clone(), filter);
+        debug!("Registered event subscription {}", id);
+        id
+    }
+
+    /// Remove a subscription, returning whether it existed
+    pub async fn unsubscribe(&self, subscription_id: &str) -> bool {
+        self.subscriptions
+            .write()
+            .await
+            .remove(subscription_id)
+            .is_some()
+    }
+
+    /// List active subscriptions
+    pub async fn list(&self) -> Vec<(String, EventFilter)> {
+        self.subscriptions
+            .read()
+            .await
+            .iter()
+            .map(|(id, filter)| (id.clone(), filter.clone()))
+            .collect()
+    }
+
+    /// Take the notification receiver
+    ///
+    /// The server takes the receiver once and writes queued notifications to
+    /// its transport. Subsequent calls return `None`.
+    pub async fn take_receiver(&self) -> Option<mpsc::Receiver<JsonRpcNotification>> {
+        self.receiver.lock().await.take()
+    }
+
+    /// Take the number of notifications dropped since the last call
+    ///
+    /// Returns an `events_dropped` notification carrying the count, or `None`
+    /// if nothing was dropped.
+    pub fn take_dropped(&self) -> Option<JsonRpcNotification> {
+        let dropped = self.dropped.swap(0, Ordering::Relaxed);
+        if dropped == 0 {
+            return None;
+        }
+        Some(JsonRpcNotification::new(
+            EVENTS_DROPPED_NOTIFICATION_METHOD,
+            Some(json!({ "dropped": dropped })),
+        ))
+    }
+}
+
+impl Default for EventSubscriptionManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl EventSubscriber for EventSubscriptionManager {
+    async fn handle_event(&self, event: NodeEvent) {
+        let subscriptions = self.subscriptions.read().await;
+        if subscriptions.is_empty() {
+            return;
+        }
+
+        let payload = match EventPayload::from_event(&event) {
+            Some(payload) => payload,
+            None => return,
+        };
+
+        let timestamp = chrono::Utc::now().to_rfc3339();
+        for (subscription_id, filter) in subscriptions.iter() {
+            if !filter.matches(&payload) {
+                continue;
+            }
+
+            let params = json!({
+                "subscription_id": subscription_id,
+                "event_type": payload.event_type,
+                "transaction_id": payload.transaction_id,
+                "message_type": payload.message_type,
+                "agent_did": payload.agent_did,
+                "data": payload.data,
+                "timestamp": timestamp,
+            });
+
+            let notification = JsonRpcNotification::new(EVENT_NOTIFICATION_METHOD, Some(params));
+            match self.sender.try_send(notification) {
+                Ok(()) => {}
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                    if dropped == 1 {
+                        warn!(
+                            "Notification queue full, dropping events until the client catches up"
+                        );
+                    }
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => {
+                    debug!("Notification receiver dropped, discarding event");
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_changed(transaction_id: &str, new_state: &str) -> NodeEvent {
+        NodeEvent::TransactionStateChanged {
+            transaction_id: transaction_id.to_string(),
+            old_state: "received".to_string(),
+            new_state: new_state.to_string(),
+            agent_did: Some("did:example:agent".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_filter_matches_event_type_and_transaction() {
+        let payload = EventPayload::from_event(&state_changed("tx-1", "authorized")).unwrap();
+
+        assert!(EventFilter::default().matches(&payload));
+
+        let filter = EventFilter {
+            event_types: vec!["transaction_state_changed".to_string()],
+            transaction_id: Some("tx-1".to_string()),
+            ..Default::default()
+        };
+        assert!(filter.matches(&payload));
+
+        let other_tx = EventFilter {
+            transaction_id: Some("tx-2".to_string()),
+            ..Default::default()
+        };
+        assert!(!other_tx.matches(&payload));
+
+        let other_type = EventFilter {
+            event_types: vec!["message_received".to_string()],
+            ..Default::default()
+        };
+        assert!(!other_type.matches(&payload));
+
+        let message_type = EventFilter {
+            message_types: vec!["Presentation".to_string()],
+            ..Default::default()
+        };
+        assert!(!message_type.matches(&payload));
+    }
+
+    #[tokio::test]
+    async fn test_matching_events_are_queued_as_notifications() {
+        let manager = EventSubscriptionManager::new();
+        let mut receiver = manager.take_receiver().await.unwrap();
+        assert!(manager.take_receiver().await.is_none());
+
+        // No subscriptions: nothing is queued
+        manager
+            .handle_event(state_changed("tx-1", "authorized"))
+            .await;
+        assert!(receiver.try_recv().is_err());
+
+        let subscription_id = manager
+            .subscribe(EventFilter {
+                transaction_id: Some("tx-1".to_string()),
+                ..Default::default()
+            })
+            .await;
+
+        manager
+            .handle_event(state_changed("tx-2", "authorized"))
+            .await;
+        manager
+            .handle_event(state_changed("tx-1", "authorized"))
+            .await;
+
+        let notification = receiver.try_recv().unwrap();
+        assert_eq!(notification.method, EVENT_NOTIFICATION_METHOD);
+        let params = notification.params.unwrap();
+        assert_eq!(params["subscription_id"], subscription_id);
+        assert_eq!(params["transaction_id"], "tx-1");
+        assert_eq!(params["data"]["new_state"], "authorized");
+        assert!(receiver.try_recv().is_err());
+
+        assert!(manager.unsubscribe(&subscription_id).await);
+        assert!(!manager.unsubscribe(&subscription_id).await);
+        manager.handle_event(state_changed("tx-1", "settled")).await;
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_drops_and_counts_notifications() {
+        let manager = EventSubscriptionManager::with_capacity(2);
+        let mut receiver = manager.take_receiver().await.unwrap();
+        manager.subscribe(EventFilter::default()).await;
+
+        for i in 0..5 {
+            manager
+                .handle_event(state_changed(&format!("tx-{}", i), "authorized"))
+                .await;
+        }
+
+        // The two oldest events are kept, the rest are counted as dropped
+        assert_eq!(
+            receiver.try_recv().unwrap().params.unwrap()["transaction_id"],
+            "tx-0"
+        );
+        assert_eq!(
+            receiver.try_recv().unwrap().params.unwrap()["transaction_id"],
+            "tx-1"
+        );
+        assert!(receiver.try_recv().is_err());
+
+        let dropped = manager.take_dropped().unwrap();
+        assert_eq!(dropped.method, EVENTS_DROPPED_NOTIFICATION_METHOD);
+        assert_eq!(dropped.params.unwrap()["dropped"], 3);
+        assert!(manager.take_dropped().is_none());
+    }
+}
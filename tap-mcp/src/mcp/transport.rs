@@ -1,13 +1,13 @@
 //! MCP transport implementations
 
 use crate::error::{Error, Result};
-use crate::mcp::protocol::{JsonRpcRequest, JsonRpcResponse};
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use crate::mcp::protocol::{JsonRpcNotification, JsonRpcRequest, JsonRpcResponse};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
 use tracing::{debug, error, trace};
 
 /// Stdio transport for MCP communication
 pub struct StdioTransport {
-    stdin: BufReader<tokio::io::Stdin>,
+    stdin: Lines<BufReader<tokio::io::Stdin>>,
     stdout: tokio::io::Stdout,
 }
 
@@ -15,34 +15,32 @@ impl StdioTransport {
     /// Create a new stdio transport
     pub fn new() -> Self {
         Self {
-            stdin: BufReader::new(tokio::io::stdin()),
+            stdin: BufReader::new(tokio::io::stdin()).lines(),
             stdout: tokio::io::stdout(),
         }
     }
 
     /// Read a JSON-RPC request from stdin
+    ///
+    /// This method is cancellation safe, so it can be used in `tokio::select!`
+    /// alongside outgoing notifications.
     pub async fn read_request(&mut self) -> Result<Option<JsonRpcRequest>> {
         loop {
-            let mut line = String::new();
-
-            match self.stdin.read_line(&mut line).await {
-                Ok(0) => {
+            let mut line = match self.stdin.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => {
                     debug!("EOF reached on stdin");
                     return Ok(None);
                 }
-                Ok(_) => {}
                 Err(e) => {
                     error!("Failed to read from stdin: {}", e);
                     return Err(Error::Io(e));
                 }
-            }
+            };
 
-            // Remove trailing newline
-            if line.ends_with('\n') {
+            // Remove trailing carriage return
+            if line.ends_with('\r') {
                 line.pop();
-                if line.ends_with('\r') {
-                    line.pop();
-                }
             }
 
             if line.trim().is_empty() {
@@ -81,6 +79,19 @@ impl StdioTransport {
         debug!("Sent response for id={:?}", response.id);
         Ok(())
     }
+
+    /// Write a JSON-RPC notification to stdout
+    pub async fn write_notification(&mut self, notification: JsonRpcNotification) -> Result<()> {
+        let json = serde_json::to_string(&notification)?;
+        trace!("Sending notification: {}", json);
+
+        self.stdout.write_all(json.as_bytes()).await?;
+        self.stdout.write_all(b"\n").await?;
+        self.stdout.flush().await?;
+
+        debug!("Sent notification: {}", notification.method);
+        Ok(())
+    }
 }
 
 impl Default for StdioTransport {
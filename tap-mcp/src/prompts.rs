@@ -0,0 +1,374 @@
+//! MCP prompts for guided TAP workflows
+//!
+//! Prompts give LLM clients a starting point for common multi-step workflows.
+//! Each prompt validates its arguments against the node and embeds live data
+//! (agents, customers, counterparties, pending decisions) so the client works
+//! from the actual state of the node instead of guessing tool arguments.
+
+use crate::error::{Error, Result};
+use crate::mcp::protocol::{GetPromptResult, Prompt, PromptArgument, PromptMessage, ToolContent};
+use crate::tap_integration::TapIntegration;
+use serde_json::json;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use tap_caip::AssetId;
+use tap_node::storage::{DecisionStatus, ReviewStatus};
+use tracing::debug;
+
+/// Maximum number of live records embedded in a prompt per category
+const PROMPT_RECORD_LIMIT: u32 = 20;
+
+/// Registry for all available prompts
+pub struct PromptRegistry {
+    tap_integration: Arc<TapIntegration>,
+}
+
+impl PromptRegistry {
+    /// Create a new prompt registry
+    pub fn new(tap_integration: Arc<TapIntegration>) -> Self {
+        Self { tap_integration }
+    }
+
+    /// List all available prompts
+    pub fn list_prompts(&self) -> Vec<Prompt> {
+        vec![
+            Prompt {
+                name: "initiate_compliant_transfer".to_string(),
+                description: "Guide the initiation of a travel rule compliant Transfer from one of the node's agents, including originator and beneficiary information".to_string(),
+                arguments: vec![
+                    argument("agent_did", "DID of the agent initiating the transfer", true),
+                    argument("asset", "CAIP-19 asset identifier to transfer", false),
+                    argument("amount", "Amount to transfer as a decimal string", false),
+                    argument("beneficiary", "Customer ID, DID or name of the beneficiary", false),
+                ],
+            },
+            Prompt {
+                name: "respond_to_travel_rule_request".to_string(),
+                description: "Guide the response to an incoming Transfer that requires travel rule information before it can be authorized or rejected".to_string(),
+                arguments: vec![
+                    argument("agent_did", "DID of the agent responding to the request", true),
+                    argument("transaction_id", "ID of the transaction to respond to", true),
+                ],
+            },
+            Prompt {
+                name: "review_pending_transactions".to_string(),
+                description: "Walk through the pending decisions and review items of an agent and resolve them one by one".to_string(),
+                arguments: vec![argument(
+                    "agent_did",
+                    "DID of the agent whose pending work to review",
+                    true,
+                )],
+            },
+        ]
+    }
+
+    /// Render a prompt with the given arguments
+    pub async fn get_prompt(
+        &self,
+        name: &str,
+        arguments: &HashMap<String, String>,
+    ) -> Result<GetPromptResult> {
+        debug!("Rendering prompt: {}", name);
+
+        match name {
+            "initiate_compliant_transfer" => self.initiate_compliant_transfer(arguments).await,
+            "respond_to_travel_rule_request" => {
+                self.respond_to_travel_rule_request(arguments).await
+            }
+            "review_pending_transactions" => self.review_pending_transactions(arguments).await,
+            _ => Err(Error::resource_not_found(format!(
+                "Unknown prompt: {}",
+                name
+            ))),
+        }
+    }
+
+    /// Render the initiate_compliant_transfer prompt
+    async fn initiate_compliant_transfer(
+        &self,
+        arguments: &HashMap<String, String>,
+    ) -> Result<GetPromptResult> {
+        let agent_did = self.require_agent(arguments).await?;
+
+        let asset = optional_argument(arguments, "asset");
+        if let Some(asset) = asset {
+            AssetId::from_str(asset).map_err(|e| {
+                Error::invalid_parameter(format!("Invalid CAIP-19 asset '{}': {}", asset, e))
+            })?;
+        }
+
+        let amount = optional_argument(arguments, "amount");
+        if let Some(amount) = amount {
+            match amount.parse::<f64>() {
+                Ok(value) if value.is_finite() && value > 0.0 => {}
+                _ => {
+                    return Err(Error::invalid_parameter(format!(
+                        "Invalid amount '{}': must be a positive decimal number",
+                        amount
+                    )))
+                }
+            }
+        }
+
+        let reader = self.tap_integration.reader_for_agent(agent_did).await?;
+        let customers = reader
+            .list_customers(agent_did, PROMPT_RECORD_LIMIT, 0)
+            .await?;
+        let storage = self.tap_integration.storage_for_agent(agent_did).await?;
+        let counterparties = storage
+            .list_counterparty_profiles(PROMPT_RECORD_LIMIT, 0)
+            .await?;
+
+        let customers_json: Vec<_> = customers
+            .iter()
+            .map(|c| {
+                json!({
+                    "id": c.id,
+                    "type": c.schema_type,
+                    "name": c.display_name.as_ref().or(c.legal_name.as_ref()),
+                    "country": c.address_country,
+                })
+            })
+            .collect();
+        let counterparties_json: Vec<_> = counterparties
+            .iter()
+            .map(|p| {
+                json!({
+                    "did": p.counterparty_did,
+                    "connection_status": p.connection_status.to_string(),
+                    "endpoint_verified": p.endpoint_verified,
+                    "their_policies": p.their_policies,
+                })
+            })
+            .collect();
+
+        let text = format!(
+            "You are helping agent {agent} initiate a TAP Transfer (TAIP-3) that complies with travel rule requirements.\n\
+             \n\
+             Requested transfer:\n\
+             - Asset: {asset}\n\
+             - Amount: {amount}\n\
+             - Beneficiary: {beneficiary}\n\
+             \n\
+             Follow these steps and use the TAP tools rather than composing messages by hand:\n\
+             1. Confirm any missing transfer details above with the user. The asset must be a CAIP-19 identifier.\n\
+             2. Pick the originator from the agent's customers below, or create one with tap_create_customer. \
+             Use tap_generate_ivms101 to produce the originator's IVMS101 data.\n\
+             3. Identify the beneficiary and the agent acting for them. Prefer a counterparty below with an \
+             established connection and satisfy any policies they require.\n\
+             4. Create the transfer with tap_create_draft (kind \"transfer\") and fix every validation error it reports.\n\
+             5. Summarize the draft for the user and only send it with tap_send_draft after they confirm.\n\
+             6. Track the response with tap_list_transactions and tap_list_pending_decisions.\n\
+             \n\
+             Customers of this agent:\n{customers}\n\
+             \n\
+             Known counterparties:\n{counterparties}\n",
+            agent = agent_did,
+            asset = asset.unwrap_or("not specified, ask the user"),
+            amount = amount.unwrap_or("not specified, ask the user"),
+            beneficiary =
+                optional_argument(arguments, "beneficiary").unwrap_or("not specified, ask the user"),
+            customers = serde_json::to_string_pretty(&customers_json)?,
+            counterparties = serde_json::to_string_pretty(&counterparties_json)?,
+        );
+
+        Ok(GetPromptResult {
+            description: Some(format!("Initiate a compliant transfer from {}", agent_did)),
+            messages: vec![user_message(text)],
+        })
+    }
+
+    /// Render the respond_to_travel_rule_request prompt
+    async fn respond_to_travel_rule_request(
+        &self,
+        arguments: &HashMap<String, String>,
+    ) -> Result<GetPromptResult> {
+        let agent_did = self.require_agent(arguments).await?;
+        let transaction_id = required_argument(arguments, "transaction_id")?;
+
+        let storage = self.tap_integration.storage_for_agent(agent_did).await?;
+        let transaction = storage
+            .get_transaction_by_id(transaction_id)
+            .await?
+            .ok_or_else(|| {
+                Error::invalid_parameter(format!("Transaction not found: {}", transaction_id))
+            })?;
+
+        let reader = self.tap_integration.reader_for_agent(agent_did).await?;
+        let decisions: Vec<_> = reader
+            .list_decisions(
+                Some(agent_did),
+                Some(DecisionStatus::Pending),
+                None,
+                PROMPT_RECORD_LIMIT,
+            )
+            .await?
+            .into_iter()
+            .filter(|d| d.transaction_id == transaction_id)
+            .map(|d| {
+                json!({
+                    "decision_id": d.id,
+                    "type": d.decision_type.to_string(),
+                    "context": d.context_json,
+                })
+            })
+            .collect();
+        let warnings: Vec<_> = reader
+            .list_transaction_warnings(transaction_id)
+            .await?
+            .into_iter()
+            .map(|w| json!({ "rule": w.rule, "reason": w.reason }))
+            .collect();
+
+        let text = format!(
+            "You are helping agent {agent} respond to travel rule request {transaction}, \
+             a {message_type} currently in status '{status}'.\n\
+             \n\
+             Follow these steps:\n\
+             1. Review the originator, beneficiary and agents in the message below and note which \
+             party this agent acts for.\n\
+             2. Check that the originator information is complete. Compare the beneficiary with this \
+             agent's customers using tap_list_customers and tap_get_customer_details, and produce IVMS101 \
+             data for our customer with tap_generate_ivms101.\n\
+             3. Consider any warnings attached to the transaction before deciding.\n\
+             4. If the information is complete and acceptable, respond with tap_authorize, including a \
+             settlement address when this agent receives the funds. Otherwise respond with tap_reject and \
+             a clear reason.\n\
+             5. Resolve the pending decisions below with tap_resolve_decision once you have responded.\n\
+             Always confirm the response with the user before calling tap_authorize or tap_reject.\n\
+             \n\
+             Message:\n{message}\n\
+             \n\
+             Pending decisions:\n{decisions}\n\
+             \n\
+             Warnings:\n{warnings}\n",
+            agent = agent_did,
+            transaction = transaction_id,
+            message_type = transaction.message_type,
+            status = transaction.status,
+            message = serde_json::to_string_pretty(&transaction.message_json)?,
+            decisions = serde_json::to_string_pretty(&decisions)?,
+            warnings = serde_json::to_string_pretty(&warnings)?,
+        );
+
+        Ok(GetPromptResult {
+            description: Some(format!("Respond to travel rule request {}", transaction_id)),
+            messages: vec![user_message(text)],
+        })
+    }
+
+    /// Render the review_pending_transactions prompt
+    async fn review_pending_transactions(
+        &self,
+        arguments: &HashMap<String, String>,
+    ) -> Result<GetPromptResult> {
+        let agent_did = self.require_agent(arguments).await?;
+
+        let reader = self.tap_integration.reader_for_agent(agent_did).await?;
+        let decisions: Vec<_> = reader
+            .list_decisions(
+                Some(agent_did),
+                Some(DecisionStatus::Pending),
+                None,
+                PROMPT_RECORD_LIMIT,
+            )
+            .await?
+            .into_iter()
+            .map(|d| {
+                json!({
+                    "decision_id": d.id,
+                    "transaction_id": d.transaction_id,
+                    "type": d.decision_type.to_string(),
+                    "created_at": d.created_at,
+                })
+            })
+            .collect();
+        let reviews: Vec<_> = reader
+            .list_review_items(
+                Some(agent_did),
+                Some(ReviewStatus::Pending),
+                PROMPT_RECORD_LIMIT,
+                0,
+            )
+            .await?
+            .into_iter()
+            .map(|r| {
+                json!({
+                    "review_id": r.id,
+                    "transaction_id": r.transaction_id,
+                    "rule": r.rule,
+                    "reason": r.reason,
+                })
+            })
+            .collect();
+
+        let text = format!(
+            "You are helping agent {agent} work through its pending transactions. \
+             There are {decision_count} pending decisions and {review_count} pending review items.\n\
+             \n\
+             For each item, oldest first:\n\
+             1. Load the transaction with tap_list_transactions and explain it to the user.\n\
+             2. For review items, ask the user whether to release the held message with \
+             tap_approve_review or reject the transaction with tap_reject_review.\n\
+             3. For decisions, use the respond_to_travel_rule_request workflow for authorization \
+             decisions, and tap_settle for settlement decisions, then resolve the decision with \
+             tap_resolve_decision.\n\
+             Never act on an item without the user's confirmation.\n\
+             \n\
+             Pending decisions:\n{decisions}\n\
+             \n\
+             Pending review items:\n{reviews}\n",
+            agent = agent_did,
+            decision_count = decisions.len(),
+            review_count = reviews.len(),
+            decisions = serde_json::to_string_pretty(&decisions)?,
+            reviews = serde_json::to_string_pretty(&reviews)?,
+        );
+
+        Ok(GetPromptResult {
+            description: Some(format!("Review pending transactions of {}", agent_did)),
+            messages: vec![user_message(text)],
+        })
+    }
+
+    /// Get the agent_did argument and check the agent is managed by this node
+    async fn require_agent<'a>(&self, arguments: &'a HashMap<String, String>) -> Result<&'a str> {
+        let agent_did = required_argument(arguments, "agent_did")?;
+        let agents = self.tap_integration.list_agents().await?;
+        if !agents.iter().any(|agent| agent.id == agent_did) {
+            return Err(Error::invalid_parameter(format!(
+                "Agent {} is not managed by this node",
+                agent_did
+            )));
+        }
+        Ok(agent_did)
+    }
+}
+
+fn argument(name: &str, description: &str, required: bool) -> PromptArgument {
+    PromptArgument {
+        name: name.to_string(),
+        description: description.to_string(),
+        required,
+    }
+}
+
+fn required_argument<'a>(arguments: &'a HashMap<String, String>, name: &str) -> Result<&'a str> {
+    optional_argument(arguments, name)
+        .ok_or_else(|| Error::invalid_parameter(format!("Missing required argument: {}", name)))
+}
+
+fn optional_argument<'a>(arguments: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    arguments
+        .get(name)
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+}
+
+fn user_message(text: String) -> PromptMessage {
+    PromptMessage {
+        role: "user".to_string(),
+        content: ToolContent::Text { text },
+    }
+}
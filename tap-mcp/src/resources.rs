@@ -217,7 +217,7 @@ impl ResourceRegistry {
             // Use agent-specific storage
             let agent_storage = self
                 .tap_integration()
-                .storage_for_agent(agent_did)
+                .reader_for_agent(agent_did)
                 .await
                 .map_err(|e| {
                     Error::resource_not_found(format!("Failed to get agent storage: {}", e))
@@ -624,7 +624,7 @@ impl ResourceRegistry {
             // Use agent-specific storage
             let agent_storage = self
                 .tap_integration()
-                .storage_for_agent(agent_did)
+                .reader_for_agent(agent_did)
                 .await
                 .map_err(|e| {
                     Error::resource_not_found(format!("Failed to get agent storage: {}", e))
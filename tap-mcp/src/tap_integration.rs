@@ -1,17 +1,20 @@
 //! Integration layer with TAP ecosystem components
 
 use crate::error::{Error, Result};
+use crate::mcp::subscriptions::EventSubscriptionManager;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tap_agent::TapAgent;
+use tap_agent::{Agent, TapAgent};
 use tap_node::{NodeConfig, TapNode};
-use tracing::{debug, error, info};
+use tracing::{debug, info};
 
 /// TAP ecosystem integration - thin wrapper around TapNode
 pub struct TapIntegration {
     node: Arc<TapNode>,
     /// Custom storage path for testing (if set, overrides default ~/.tap)
     storage_path: Option<PathBuf>,
+    /// Event subscriptions forwarded to the MCP client as notifications
+    event_subscriptions: Arc<EventSubscriptionManager>,
 }
 
 impl TapIntegration {
@@ -38,6 +41,9 @@ impl TapIntegration {
         config.enable_message_logging = true;
         config.log_message_content = true;
 
+        // Register an agent for every stored key when storage is initialized
+        config.auto_register_stored_agents = true;
+
         // Create the node
         let mut node = TapNode::new(config);
 
@@ -50,8 +56,10 @@ impl TapIntegration {
 
         let node_arc = Arc::new(node);
 
-        // Register the primary agent if provided
-        if let Some(agent) = agent {
+        // Register the primary agent unless it was registered from key storage
+        if let Some(agent) =
+            agent.filter(|agent| !node_arc.agents().has_agent(agent.get_agent_did()))
+        {
             node_arc
                 .register_agent(agent)
                 .await
@@ -59,42 +67,16 @@ impl TapIntegration {
             info!("Registered primary agent with TAP Node");
         }
 
-        // Load and register all additional agents from storage
-        match tap_agent::storage::KeyStorage::load_default() {
-            Ok(storage) => {
-                let stored_dids: Vec<String> = storage.keys.keys().cloned().collect();
-                info!("Found {} total keys in storage", stored_dids.len());
-
-                for stored_did in &stored_dids {
-                    // Skip the primary agent if it's already registered
-                    if agent_did.is_some_and(|did| stored_did == did) {
-                        continue;
-                    }
-
-                    info!("Registering additional agent: {}", stored_did);
-                    match TapAgent::from_stored_keys(Some(stored_did.clone()), true).await {
-                        Ok(additional_agent) => {
-                            let additional_agent_arc = Arc::new(additional_agent);
-                            if let Err(e) = node_arc.register_agent(additional_agent_arc).await {
-                                error!("Failed to register additional agent {}: {}", stored_did, e);
-                            } else {
-                                info!("Successfully registered additional agent: {}", stored_did);
-                            }
-                        }
-                        Err(e) => {
-                            error!("Failed to load additional agent {}: {}", stored_did, e);
-                        }
-                    }
-                }
-            }
-            Err(e) => {
-                debug!("Could not load additional keys from storage: {}", e);
-            }
-        }
+        let event_subscriptions = Arc::new(EventSubscriptionManager::new());
+        node_arc
+            .event_bus()
+            .subscribe(event_subscriptions.clone())
+            .await;
 
         Ok(Self {
             node: node_arc,
             storage_path: None,
+            event_subscriptions,
         })
     }
 
@@ -137,9 +119,16 @@ impl TapIntegration {
             .await
             .map_err(|e| Error::configuration(format!("Failed to register test agent: {}", e)))?;
 
+        let event_subscriptions = Arc::new(EventSubscriptionManager::new());
+        node_arc
+            .event_bus()
+            .subscribe(event_subscriptions.clone())
+            .await;
+
         Ok(Self {
             node: node_arc,
             storage_path,
+            event_subscriptions,
         })
     }
 
@@ -155,6 +144,11 @@ impl TapIntegration {
         self.storage_path.as_ref()
     }
 
+    /// Get the event subscription manager
+    pub fn event_subscriptions(&self) -> &Arc<EventSubscriptionManager> {
+        &self.event_subscriptions
+    }
+
     /// Get storage reference (if available) - uses the primary node storage
     pub fn storage(&self) -> Option<&Arc<tap_node::storage::Storage>> {
         self.node.storage()
@@ -184,6 +178,31 @@ impl TapIntegration {
         }
     }
 
+    /// Get a read-only handle on a specific agent's database
+    ///
+    /// Listing and search tools use this so their queries run on a separate
+    /// connection pool, or a replica, instead of the node's primary storage.
+    pub async fn reader_for_agent(
+        &self,
+        agent_did: &str,
+    ) -> Result<Arc<tap_node::storage::ReadOnlyStorage>> {
+        if let Some(storage_manager) = self.node.agent_storage_manager() {
+            storage_manager
+                .get_agent_reader(agent_did)
+                .await
+                .map_err(|e| {
+                    Error::configuration(format!(
+                        "Failed to get read-only storage for agent {}: {}",
+                        agent_did, e
+                    ))
+                })
+        } else {
+            Err(Error::configuration(
+                "Agent storage manager not available".to_string(),
+            ))
+        }
+    }
+
     /// List all registered agents (from storage and in-memory registry)
     pub async fn list_agents(&self) -> Result<Vec<AgentInfo>> {
         let mut agents = Vec::new();
@@ -76,7 +76,7 @@ impl ToolHandler for ListCustomersTool {
         // Get storage for the agent
         let storage = match self
             .tap_integration()
-            .storage_for_agent(&params.agent_did)
+            .reader_for_agent(&params.agent_did)
             .await
         {
             Ok(storage) => storage,
@@ -73,7 +73,7 @@ impl ToolHandler for ListPendingDecisionsTool {
 
         let storage = match self
             .tap_integration
-            .storage_for_agent(&input.agent_did)
+            .reader_for_agent(&input.agent_did)
             .await
         {
             Ok(s) => s,
@@ -0,0 +1,543 @@
+//! Tools for drafts of transfers and payments
+
+use super::{default_limit, error_text_response, success_text_response, ToolHandler};
+use crate::error::Result;
+use crate::mcp::protocol::{CallToolResult, Tool};
+use crate::tap_integration::TapIntegration;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tap_node::storage::{Draft, DraftKind, DraftStatus};
+use tracing::{debug, error};
+
+/// Respond with a draft, or with the error of the action on it
+fn draft_response(action: &str, result: tap_node::Result<Draft>) -> CallToolResult {
+    match result {
+        Ok(draft) => success_text_response(serde_json::to_string_pretty(&draft).unwrap()),
+        Err(e) => {
+            error!("Failed to {}: {}", action, e);
+            error_text_response(format!("Failed to {}: {}", action, e))
+        }
+    }
+}
+
+// -----------------------------------------------------------------------
+// tap_create_draft
+// -----------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+pub struct CreateDraftInput {
+    pub agent_did: String,
+    pub kind: String,
+    #[serde(default = "empty_body")]
+    pub body: Value,
+    pub ttl_hours: Option<u64>,
+}
+
+fn empty_body() -> Value {
+    json!({})
+}
+
+pub struct CreateDraftTool {
+    tap_integration: Arc<TapIntegration>,
+}
+
+impl CreateDraftTool {
+    pub fn new(tap_integration: Arc<TapIntegration>) -> Self {
+        Self { tap_integration }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for CreateDraftTool {
+    async fn handle(&self, arguments: Option<Value>) -> Result<CallToolResult> {
+        let input: CreateDraftInput = match arguments {
+            Some(args) => serde_json::from_value(args)?,
+            None => {
+                return Ok(error_text_response(
+                    "Missing required arguments".to_string(),
+                ));
+            }
+        };
+
+        let kind = match DraftKind::try_from(input.kind.as_str()) {
+            Ok(kind) => kind,
+            Err(e) => return Ok(error_text_response(e)),
+        };
+        debug!("Creating {} draft for agent: {}", kind, input.agent_did);
+
+        let ttl = input
+            .ttl_hours
+            .map(|hours| Duration::from_secs(hours * 3600));
+        let result = self
+            .tap_integration
+            .node()
+            .create_draft(&input.agent_did, kind, input.body, ttl)
+            .await;
+        Ok(draft_response("create draft", result))
+    }
+
+    fn get_definition(&self) -> Tool {
+        Tool {
+            name: "tap_create_draft".to_string(),
+            description: "Create a draft of a Transfer or Payment to build up over several calls before sending it. The body may be incomplete; the draft's validation_errors say what is missing. Drafts expire after 7 days unless ttl_hours is given.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "agent_did": {
+                        "type": "string",
+                        "description": "The DID of the agent that will send the draft"
+                    },
+                    "kind": {
+                        "type": "string",
+                        "description": "Kind of message the draft is sent as",
+                        "enum": ["transfer", "payment"]
+                    },
+                    "body": {
+                        "type": "object",
+                        "description": "Message body so far, as in tap_create_transfer or tap_payment (e.g. asset, amount, originator, beneficiary, agents)"
+                    },
+                    "ttl_hours": {
+                        "type": "number",
+                        "description": "Hours until the draft can no longer be sent",
+                        "default": 168
+                    }
+                },
+                "required": ["agent_did", "kind"],
+                "additionalProperties": false
+            }),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------
+// tap_update_draft
+// -----------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateDraftInput {
+    pub agent_did: String,
+    pub draft_id: String,
+    pub patch: Value,
+}
+
+pub struct UpdateDraftTool {
+    tap_integration: Arc<TapIntegration>,
+}
+
+impl UpdateDraftTool {
+    pub fn new(tap_integration: Arc<TapIntegration>) -> Self {
+        Self { tap_integration }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for UpdateDraftTool {
+    async fn handle(&self, arguments: Option<Value>) -> Result<CallToolResult> {
+        let input: UpdateDraftInput = match arguments {
+            Some(args) => serde_json::from_value(args)?,
+            None => {
+                return Ok(error_text_response(
+                    "Missing required arguments".to_string(),
+                ));
+            }
+        };
+
+        debug!(
+            "Updating draft {} of agent: {}",
+            input.draft_id, input.agent_did
+        );
+        let result = self
+            .tap_integration
+            .node()
+            .update_draft(&input.agent_did, &input.draft_id, &input.patch)
+            .await;
+        Ok(draft_response("update draft", result))
+    }
+
+    fn get_definition(&self) -> Tool {
+        Tool {
+            name: "tap_update_draft".to_string(),
+            description: "Change the body of a draft that was not sent with a JSON merge patch (RFC 7386): fields of the patch replace those of the body and null removes a field. Returns the draft with its new validation status.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "agent_did": {
+                        "type": "string",
+                        "description": "The DID of the agent the draft belongs to"
+                    },
+                    "draft_id": {
+                        "type": "string",
+                        "description": "ID of the draft"
+                    },
+                    "patch": {
+                        "type": "object",
+                        "description": "JSON merge patch of the body"
+                    }
+                },
+                "required": ["agent_did", "draft_id", "patch"],
+                "additionalProperties": false
+            }),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------
+// tap_list_drafts
+// -----------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+pub struct ListDraftsInput {
+    pub agent_did: String,
+    pub status: Option<String>,
+    #[serde(default)]
+    pub include_expired: bool,
+    #[serde(default = "default_limit")]
+    pub limit: u32,
+    #[serde(default)]
+    pub offset: u32,
+}
+
+pub struct ListDraftsTool {
+    tap_integration: Arc<TapIntegration>,
+}
+
+impl ListDraftsTool {
+    pub fn new(tap_integration: Arc<TapIntegration>) -> Self {
+        Self { tap_integration }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for ListDraftsTool {
+    async fn handle(&self, arguments: Option<Value>) -> Result<CallToolResult> {
+        let input: ListDraftsInput = match arguments {
+            Some(args) => serde_json::from_value(args)?,
+            None => {
+                return Ok(error_text_response(
+                    "Missing required arguments".to_string(),
+                ));
+            }
+        };
+
+        debug!(
+            "Listing drafts for agent: {} status: {:?}",
+            input.agent_did, input.status
+        );
+
+        let status = match input.status.as_deref().map(DraftStatus::try_from) {
+            Some(Ok(status)) => Some(status),
+            Some(Err(e)) => return Ok(error_text_response(e)),
+            None => None,
+        };
+
+        let storage = match self
+            .tap_integration
+            .reader_for_agent(&input.agent_did)
+            .await
+        {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Failed to get agent storage: {}", e);
+                return Ok(error_text_response(format!(
+                    "Failed to get storage for agent {}: {}",
+                    input.agent_did, e
+                )));
+            }
+        };
+
+        let drafts = match storage
+            .list_drafts(
+                Some(&input.agent_did),
+                status,
+                input.include_expired,
+                input.limit,
+                input.offset,
+            )
+            .await
+        {
+            Ok(drafts) => drafts,
+            Err(e) => {
+                error!("Failed to list drafts: {}", e);
+                return Ok(error_text_response(format!("Failed to list drafts: {}", e)));
+            }
+        };
+
+        let total = drafts.len();
+        let response = json!({
+            "drafts": drafts,
+            "total": total,
+        });
+
+        Ok(success_text_response(
+            serde_json::to_string_pretty(&response).unwrap(),
+        ))
+    }
+
+    fn get_definition(&self) -> Tool {
+        Tool {
+            name: "tap_list_drafts".to_string(),
+            description: "List the drafts of an agent, most recently changed first, with their validation status. Expired drafts that were not sent are left out unless include_expired is set.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "agent_did": {
+                        "type": "string",
+                        "description": "The DID of the agent whose drafts to list"
+                    },
+                    "status": {
+                        "type": "string",
+                        "description": "Filter by status: invalid, valid, sent",
+                        "enum": ["invalid", "valid", "sent"]
+                    },
+                    "include_expired": {
+                        "type": "boolean",
+                        "description": "Include expired drafts",
+                        "default": false
+                    },
+                    "limit": {
+                        "type": "number",
+                        "description": "Maximum number of drafts to return",
+                        "default": 50
+                    },
+                    "offset": {
+                        "type": "number",
+                        "description": "Number of drafts to skip",
+                        "default": 0
+                    }
+                },
+                "required": ["agent_did"],
+                "additionalProperties": false
+            }),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------
+// tap_send_draft / tap_delete_draft
+// -----------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+pub struct DraftIdInput {
+    pub agent_did: String,
+    pub draft_id: String,
+}
+
+pub struct SendDraftTool {
+    tap_integration: Arc<TapIntegration>,
+}
+
+impl SendDraftTool {
+    pub fn new(tap_integration: Arc<TapIntegration>) -> Self {
+        Self { tap_integration }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for SendDraftTool {
+    async fn handle(&self, arguments: Option<Value>) -> Result<CallToolResult> {
+        let input: DraftIdInput = match arguments {
+            Some(args) => serde_json::from_value(args)?,
+            None => {
+                return Ok(error_text_response(
+                    "Missing required arguments".to_string(),
+                ));
+            }
+        };
+
+        debug!(
+            "Sending draft {} of agent: {}",
+            input.draft_id, input.agent_did
+        );
+        let result = self
+            .tap_integration
+            .node()
+            .send_draft(&input.agent_did, &input.draft_id)
+            .await;
+        Ok(draft_response("send draft", result))
+    }
+
+    fn get_definition(&self) -> Tool {
+        Tool {
+            name: "tap_send_draft".to_string(),
+            description: "Send a valid draft as a message from its agent. A draft is sent only once; if the message cannot be delivered, the draft stays valid and can be sent again. Returns the draft with the ID of the message in message_id.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "agent_did": {
+                        "type": "string",
+                        "description": "The DID of the agent the draft belongs to"
+                    },
+                    "draft_id": {
+                        "type": "string",
+                        "description": "ID of the draft"
+                    }
+                },
+                "required": ["agent_did", "draft_id"],
+                "additionalProperties": false
+            }),
+        }
+    }
+}
+
+pub struct DeleteDraftTool {
+    tap_integration: Arc<TapIntegration>,
+}
+
+impl DeleteDraftTool {
+    pub fn new(tap_integration: Arc<TapIntegration>) -> Self {
+        Self { tap_integration }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for DeleteDraftTool {
+    async fn handle(&self, arguments: Option<Value>) -> Result<CallToolResult> {
+        let input: DraftIdInput = match arguments {
+            Some(args) => serde_json::from_value(args)?,
+            None => {
+                return Ok(error_text_response(
+                    "Missing required arguments".to_string(),
+                ));
+            }
+        };
+
+        debug!(
+            "Deleting draft {} of agent: {}",
+            input.draft_id, input.agent_did
+        );
+        match self
+            .tap_integration
+            .node()
+            .delete_draft(&input.agent_did, &input.draft_id)
+            .await
+        {
+            Ok(true) => Ok(success_text_response(
+                serde_json::to_string_pretty(&json!({
+                    "id": input.draft_id,
+                    "deleted": true,
+                }))
+                .unwrap(),
+            )),
+            Ok(false) => Ok(error_text_response(format!(
+                "Draft {} not found or already sent",
+                input.draft_id
+            ))),
+            Err(e) => {
+                error!("Failed to delete draft: {}", e);
+                Ok(error_text_response(format!(
+                    "Failed to delete draft: {}",
+                    e
+                )))
+            }
+        }
+    }
+
+    fn get_definition(&self) -> Tool {
+        Tool {
+            name: "tap_delete_draft".to_string(),
+            description: "Delete a draft that was not sent.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "agent_did": {
+                        "type": "string",
+                        "description": "The DID of the agent the draft belongs to"
+                    },
+                    "draft_id": {
+                        "type": "string",
+                        "description": "ID of the draft"
+                    }
+                },
+                "required": ["agent_did", "draft_id"],
+                "additionalProperties": false
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tap_integration::TapIntegration;
+    use tempfile::tempdir;
+
+    async fn setup_test() -> (Arc<TapIntegration>, String) {
+        let dir = tempdir().unwrap();
+        let tap_root = dir.path().to_str().unwrap();
+
+        let (agent, did) = tap_agent::TapAgent::from_ephemeral_key().await.unwrap();
+        let agent_arc = Arc::new(agent);
+
+        let integration = TapIntegration::new(Some(&did), Some(tap_root), Some(agent_arc))
+            .await
+            .unwrap();
+
+        std::mem::forget(dir);
+
+        (Arc::new(integration), did)
+    }
+
+    fn response_json(result: &CallToolResult) -> Value {
+        match &result.content[0] {
+            crate::mcp::protocol::ToolContent::Text { text } => serde_json::from_str(text).unwrap(),
+            _ => panic!("Expected text content"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_update_and_list_drafts() {
+        let (integration, did) = setup_test().await;
+
+        let result = CreateDraftTool::new(integration.clone())
+            .handle(Some(json!({
+                "agent_did": did,
+                "kind": "payment",
+                "body": {"amount": "10"},
+            })))
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(false));
+        let draft = response_json(&result);
+        assert_eq!(draft["status"], "invalid");
+        let draft_id = draft["id"].as_str().unwrap().to_string();
+
+        let result = UpdateDraftTool::new(integration.clone())
+            .handle(Some(json!({
+                "agent_did": did,
+                "draft_id": draft_id,
+                "patch": {"amount": null, "memo": "invoice 7"},
+            })))
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(false));
+        let draft = response_json(&result);
+        assert_eq!(draft["body"], json!({"memo": "invoice 7"}));
+
+        let result = ListDraftsTool::new(integration.clone())
+            .handle(Some(json!({"agent_did": did, "status": "invalid"})))
+            .await
+            .unwrap();
+        assert_eq!(response_json(&result)["total"], 1);
+
+        let result = SendDraftTool::new(integration.clone())
+            .handle(Some(json!({"agent_did": did, "draft_id": draft_id})))
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+
+        let delete = DeleteDraftTool::new(integration);
+        let result = delete
+            .handle(Some(json!({"agent_did": did, "draft_id": draft_id})))
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(false));
+        let result = delete
+            .handle(Some(json!({"agent_did": did, "draft_id": draft_id})))
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+    }
+}
@@ -0,0 +1,353 @@
+//! Tools for subscribing to node events as MCP notifications
+
+use super::{error_text_response, success_text_response, ToolHandler};
+use crate::error::Result;
+use crate::mcp::protocol::{CallToolResult, Tool};
+use crate::mcp::subscriptions::{EventFilter, EVENT_NOTIFICATION_METHOD};
+use crate::tap_integration::TapIntegration;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::debug;
+
+/// Event types that can be used in subscription filters
+const EVENT_TYPES: &[&str] = &[
+    "message_received",
+    "message_sent",
+    "message_accepted",
+    "message_rejected",
+    "transaction_created",
+    "transaction_state_changed",
+    "transaction_expired",
+    "problem_report_received",
+    "message_processing_timed_out",
+    "decision_required",
+    "policy_triggered",
+    "travel_rule_data_validated",
+    "travel_rule_policy_evaluated",
+    "customer_updated",
+    "agent_registered",
+    "agent_unregistered",
+    "storage_corruption_detected",
+    "storage_recovered",
+    "storage_recovery_failed",
+    "clock_drift_detected",
+    "anomaly_detected",
+    "reconciliation_completed",
+    "settlement_out_of_tolerance",
+    "address_reuse_detected",
+    "response_deadline_approaching",
+    "response_deadline_breached",
+];
+
+// -----------------------------------------------------------------------
+// tap_subscribe_events
+// -----------------------------------------------------------------------
+
+pub struct SubscribeEventsTool {
+    tap_integration: Arc<TapIntegration>,
+}
+
+impl SubscribeEventsTool {
+    pub fn new(tap_integration: Arc<TapIntegration>) -> Self {
+        Self { tap_integration }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for SubscribeEventsTool {
+    async fn handle(&self, arguments: Option<Value>) -> Result<CallToolResult> {
+        let filter: EventFilter = match arguments {
+            Some(args) => serde_json::from_value(args)?,
+            None => EventFilter::default(),
+        };
+
+        if let Some(unknown) = filter
+            .event_types
+            .iter()
+            .find(|t| !EVENT_TYPES.contains(&t.as_str()))
+        {
+            return Ok(error_text_response(format!(
+                "Unknown event type: {}. Supported types: {}",
+                unknown,
+                EVENT_TYPES.join(", ")
+            )));
+        }
+
+        debug!("Subscribing to events with filter: {:?}", filter);
+
+        let subscription_id = self
+            .tap_integration
+            .event_subscriptions()
+            .subscribe(filter.clone())
+            .await;
+
+        let response = json!({
+            "subscription_id": subscription_id,
+            "notification_method": EVENT_NOTIFICATION_METHOD,
+            "filter": filter,
+        });
+
+        Ok(success_text_response(
+            serde_json::to_string_pretty(&response).unwrap(),
+        ))
+    }
+
+    fn get_definition(&self) -> Tool {
+        Tool {
+            name: "tap_subscribe_events".to_string(),
+            description: "Subscribe to TAP node events. Matching events are pushed as 'notifications/tap/event' MCP notifications, so you can react to received presentations, authorizations or state changes without polling. All filters are optional and combined with AND.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "event_types": {
+                        "type": "array",
+                        "items": {
+                            "type": "string",
+                            "enum": EVENT_TYPES
+                        },
+                        "description": "Event types to receive (all types if omitted)"
+                    },
+                    "transaction_id": {
+                        "type": "string",
+                        "description": "Only receive events for this transaction ID"
+                    },
+                    "message_types": {
+                        "type": "array",
+                        "items": {
+                            "type": "string"
+                        },
+                        "description": "Only receive message events whose type contains one of these values (e.g., 'Presentation', 'Authorize')"
+                    },
+                    "agent_did": {
+                        "type": "string",
+                        "description": "Only receive events related to this agent DID"
+                    }
+                },
+                "additionalProperties": false
+            }),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------
+// tap_unsubscribe_events
+// -----------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+pub struct UnsubscribeEventsInput {
+    pub subscription_id: String,
+}
+
+pub struct UnsubscribeEventsTool {
+    tap_integration: Arc<TapIntegration>,
+}
+
+impl UnsubscribeEventsTool {
+    pub fn new(tap_integration: Arc<TapIntegration>) -> Self {
+        Self { tap_integration }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for UnsubscribeEventsTool {
+    async fn handle(&self, arguments: Option<Value>) -> Result<CallToolResult> {
+        let input: UnsubscribeEventsInput = match arguments {
+            Some(args) => serde_json::from_value(args)?,
+            None => {
+                return Ok(error_text_response(
+                    "Missing required arguments".to_string(),
+                ));
+            }
+        };
+
+        if !self
+            .tap_integration
+            .event_subscriptions()
+            .unsubscribe(&input.subscription_id)
+            .await
+        {
+            return Ok(error_text_response(format!(
+                "Subscription {} not found",
+                input.subscription_id
+            )));
+        }
+
+        let response = json!({
+            "subscription_id": input.subscription_id,
+            "status": "unsubscribed",
+        });
+
+        Ok(success_text_response(
+            serde_json::to_string_pretty(&response).unwrap(),
+        ))
+    }
+
+    fn get_definition(&self) -> Tool {
+        Tool {
+            name: "tap_unsubscribe_events".to_string(),
+            description: "Cancel an event subscription created with tap_subscribe_events"
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "subscription_id": {
+                        "type": "string",
+                        "description": "The subscription ID returned by tap_subscribe_events"
+                    }
+                },
+                "required": ["subscription_id"],
+                "additionalProperties": false
+            }),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------
+// tap_list_event_subscriptions
+// -----------------------------------------------------------------------
+
+pub struct ListEventSubscriptionsTool {
+    tap_integration: Arc<TapIntegration>,
+}
+
+impl ListEventSubscriptionsTool {
+    pub fn new(tap_integration: Arc<TapIntegration>) -> Self {
+        Self { tap_integration }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for ListEventSubscriptionsTool {
+    async fn handle(&self, _arguments: Option<Value>) -> Result<CallToolResult> {
+        let subscriptions: Vec<Value> = self
+            .tap_integration
+            .event_subscriptions()
+            .list()
+            .await
+            .into_iter()
+            .map(|(id, filter)| json!({ "subscription_id": id, "filter": filter }))
+            .collect();
+
+        let total = subscriptions.len();
+        let response = json!({
+            "subscriptions": subscriptions,
+            "total": total,
+        });
+
+        Ok(success_text_response(
+            serde_json::to_string_pretty(&response).unwrap(),
+        ))
+    }
+
+    fn get_definition(&self) -> Tool {
+        Tool {
+            name: "tap_list_event_subscriptions".to_string(),
+            description: "List active event subscriptions and their filters".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {},
+                "additionalProperties": false
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::protocol::ToolContent;
+    use tempfile::tempdir;
+
+    async fn setup_test() -> Arc<TapIntegration> {
+        let dir = tempdir().unwrap();
+        let tap_root = dir.path().to_str().unwrap();
+
+        let (agent, did) = tap_agent::TapAgent::from_ephemeral_key().await.unwrap();
+        let integration = TapIntegration::new(Some(&did), Some(tap_root), Some(Arc::new(agent)))
+            .await
+            .unwrap();
+
+        // Leak the tempdir so it doesn't get cleaned up during test
+        std::mem::forget(dir);
+
+        Arc::new(integration)
+    }
+
+    fn parse_text(result: &CallToolResult) -> Value {
+        match &result.content[0] {
+            ToolContent::Text { text } => serde_json::from_str(text).unwrap(),
+            _ => panic!("Expected text content"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_receives_node_events() {
+        let integration = setup_test().await;
+        let mut receiver = integration
+            .event_subscriptions()
+            .take_receiver()
+            .await
+            .unwrap();
+
+        let result = SubscribeEventsTool::new(integration.clone())
+            .handle(Some(json!({
+                "event_types": ["transaction_state_changed"],
+                "transaction_id": "txn-500",
+            })))
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(false));
+        let subscription_id = parse_text(&result)["subscription_id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        integration
+            .node()
+            .event_bus()
+            .publish_transaction_state_changed(
+                "txn-500".to_string(),
+                "received".to_string(),
+                "authorized".to_string(),
+                None,
+            )
+            .await;
+
+        let notification = receiver.try_recv().unwrap();
+        let params = notification.params.unwrap();
+        assert_eq!(params["subscription_id"], subscription_id);
+        assert_eq!(params["data"]["new_state"], "authorized");
+
+        let list = ListEventSubscriptionsTool::new(integration.clone())
+            .handle(None)
+            .await
+            .unwrap();
+        assert_eq!(parse_text(&list)["total"], 1);
+
+        let result = UnsubscribeEventsTool::new(integration.clone())
+            .handle(Some(json!({ "subscription_id": subscription_id })))
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(false));
+
+        let result = UnsubscribeEventsTool::new(integration)
+            .handle(Some(json!({ "subscription_id": subscription_id })))
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_rejects_unknown_event_type() {
+        let integration = setup_test().await;
+
+        let result = SubscribeEventsTool::new(integration)
+            .handle(Some(json!({ "event_types": ["not_an_event"] })))
+            .await
+            .unwrap();
+
+        assert_eq!(result.is_error, Some(true));
+    }
+}
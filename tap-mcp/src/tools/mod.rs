@@ -7,8 +7,13 @@ mod customer_tools;
 mod database_tools;
 pub mod decision_tools;
 mod delivery_tools;
+mod draft_tools;
+mod event_tools;
 mod policy_tools;
 mod received_tools;
+mod report_tools;
+mod review_tools;
+mod scheduler_tools;
 mod schema;
 mod transaction_tools;
 
@@ -27,8 +32,13 @@ pub use customer_tools::*;
 pub use database_tools::*;
 pub use decision_tools::*;
 pub use delivery_tools::*;
+pub use draft_tools::*;
+pub use event_tools::*;
 pub use policy_tools::*;
 pub use received_tools::*;
+pub use report_tools::*;
+pub use review_tools::*;
+pub use scheduler_tools::*;
 pub use transaction_tools::*;
 
 /// Default limit for pagination
@@ -188,6 +198,14 @@ impl ToolRegistry {
             "tap_view_raw_received".to_string(),
             Box::new(ViewRawReceivedTool::new(tap_integration.clone())),
         );
+        tools.insert(
+            "tap_list_inbox".to_string(),
+            Box::new(ListInboxTool::new(tap_integration.clone())),
+        );
+        tools.insert(
+            "tap_mark_inbox_read".to_string(),
+            Box::new(MarkInboxReadTool::new(tap_integration.clone())),
+        );
 
         // Database tools
         tools.insert(
@@ -218,6 +236,10 @@ impl ToolRegistry {
             "tap_update_policies".to_string(),
             Box::new(UpdatePoliciesTool::new(tap_integration.clone())),
         );
+        tools.insert(
+            "tap_simulate_policies".to_string(),
+            Box::new(SimulatePoliciesTool::new(tap_integration.clone())),
+        );
 
         // Decision tools
         tools.insert(
@@ -229,6 +251,72 @@ impl ToolRegistry {
             Box::new(ResolveDecisionTool::new(tap_integration.clone())),
         );
 
+        // Review queue tools
+        tools.insert(
+            "tap_list_reviews".to_string(),
+            Box::new(ListReviewsTool::new(tap_integration.clone())),
+        );
+        tools.insert(
+            "tap_approve_review".to_string(),
+            Box::new(ApproveReviewTool::new(tap_integration.clone())),
+        );
+        tools.insert(
+            "tap_reject_review".to_string(),
+            Box::new(RejectReviewTool::new(tap_integration.clone())),
+        );
+
+        // Draft tools
+        tools.insert(
+            "tap_create_draft".to_string(),
+            Box::new(CreateDraftTool::new(tap_integration.clone())),
+        );
+        tools.insert(
+            "tap_update_draft".to_string(),
+            Box::new(UpdateDraftTool::new(tap_integration.clone())),
+        );
+        tools.insert(
+            "tap_list_drafts".to_string(),
+            Box::new(ListDraftsTool::new(tap_integration.clone())),
+        );
+        tools.insert(
+            "tap_send_draft".to_string(),
+            Box::new(SendDraftTool::new(tap_integration.clone())),
+        );
+        tools.insert(
+            "tap_delete_draft".to_string(),
+            Box::new(DeleteDraftTool::new(tap_integration.clone())),
+        );
+
+        // Report tools
+        tools.insert(
+            "tap_get_counterparty_stats".to_string(),
+            Box::new(GetCounterpartyStatsTool::new(tap_integration.clone())),
+        );
+
+        // Scheduled job tools
+        tools.insert(
+            "tap_list_scheduled_jobs".to_string(),
+            Box::new(ListScheduledJobsTool::new(tap_integration.clone())),
+        );
+        tools.insert(
+            "tap_run_scheduled_job".to_string(),
+            Box::new(RunScheduledJobTool::new(tap_integration.clone())),
+        );
+
+        // Event subscription tools
+        tools.insert(
+            "tap_subscribe_events".to_string(),
+            Box::new(SubscribeEventsTool::new(tap_integration.clone())),
+        );
+        tools.insert(
+            "tap_unsubscribe_events".to_string(),
+            Box::new(UnsubscribeEventsTool::new(tap_integration.clone())),
+        );
+        tools.insert(
+            "tap_list_event_subscriptions".to_string(),
+            Box::new(ListEventSubscriptionsTool::new(tap_integration.clone())),
+        );
+
         debug!("Initialized tool registry with {} tools", tools.len());
 
         Self { tools }
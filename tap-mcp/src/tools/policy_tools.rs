@@ -8,6 +8,7 @@ use crate::tap_integration::TapIntegration;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::sync::Arc;
+use tap_msg::didcomm::PlainMessage;
 use tap_msg::message::policy::Policy;
 use tap_msg::message::tap_message_trait::TapMessageBody;
 use tap_msg::message::UpdatePolicies;
@@ -177,3 +178,88 @@ impl ToolHandler for UpdatePoliciesTool {
         }
     }
 }
+
+/// Tool for dry runs of the node's policy rules
+pub struct SimulatePoliciesTool {
+    tap_integration: Arc<TapIntegration>,
+}
+
+/// Parameters for simulating policies
+#[derive(Debug, Deserialize)]
+struct SimulatePoliciesParams {
+    /// Transfer or Payment body, including its `@type`
+    transaction: Value,
+    /// DID of the agent the transaction would come from
+    from: String,
+    /// ID of the transaction, e.g. of a rejected one being explained
+    transaction_id: Option<String>,
+}
+
+impl SimulatePoliciesTool {
+    pub fn new(tap_integration: Arc<TapIntegration>) -> Self {
+        Self { tap_integration }
+    }
+}
+
+#[async_trait::async_trait]
+impl ToolHandler for SimulatePoliciesTool {
+    async fn handle(&self, arguments: Option<Value>) -> Result<CallToolResult> {
+        let params: SimulatePoliciesParams = match arguments {
+            Some(args) => serde_json::from_value(args)
+                .map_err(|e| Error::invalid_parameter(format!("Invalid parameters: {}", e)))?,
+            None => {
+                return Ok(error_text_response(
+                    "Missing required parameters".to_string(),
+                ))
+            }
+        };
+
+        let Some(message_type) = params.transaction.get("@type").and_then(Value::as_str) else {
+            return Ok(error_text_response(
+                "Transaction must have an @type".to_string(),
+            ));
+        };
+        let message = PlainMessage::new(
+            params
+                .transaction_id
+                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            message_type.to_string(),
+            params.transaction.clone(),
+            params.from,
+        );
+
+        debug!("Simulating policies for transaction: {}", message.id);
+
+        match self
+            .tap_integration
+            .node()
+            .simulate_policies(&message)
+            .await
+        {
+            Ok(simulation) => {
+                let response = serde_json::json!({
+                    "transaction_id": simulation.transaction_id,
+                    "decision": simulation.decision(),
+                    "rules_tripped": simulation.outcomes,
+                    "follow_ups": simulation.follow_ups,
+                });
+                let response_json = serde_json::to_string_pretty(&response).map_err(|e| {
+                    Error::tool_execution(format!("Failed to serialize response: {}", e))
+                })?;
+                Ok(success_text_response(response_json))
+            }
+            Err(e) => Ok(error_text_response(format!(
+                "Failed to simulate policies: {}",
+                e
+            ))),
+        }
+    }
+
+    fn get_definition(&self) -> Tool {
+        Tool {
+            name: "tap_simulate_policies".to_string(),
+            description: "Evaluates the node's policy rules against a hypothetical Transfer or Payment and reports which rules would trip, the resulting decision and required follow-ups, without storing or sending anything".to_string(),
+            input_schema: schema::simulate_policies_schema(),
+        }
+    }
+}
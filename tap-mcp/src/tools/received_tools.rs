@@ -8,7 +8,7 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::sync::Arc;
-use tap_node::storage::{ReceivedStatus, SourceType};
+use tap_node::storage::{ReceivedFilter, ReceivedStatus, SourceType};
 use tracing::{debug, error};
 
 /// Input for listing received messages
@@ -97,7 +97,7 @@ impl ToolHandler for ListReceivedTool {
         // Get agent storage
         let storage = match self
             .tap_integration
-            .storage_for_agent(&input.agent_did)
+            .reader_for_agent(&input.agent_did)
             .await
         {
             Ok(s) => s,
@@ -464,3 +464,275 @@ impl ToolHandler for ViewRawReceivedTool {
         }
     }
 }
+
+/// Input for listing the messages in an agent's inbox
+#[derive(Debug, Deserialize)]
+pub struct ListInboxInput {
+    /// The DID of the agent whose inbox to list
+    pub agent_did: String,
+    /// Only list messages with a larger message log ID
+    pub since_id: Option<i64>,
+    /// Only list messages not yet marked read
+    #[serde(default)]
+    pub unread: bool,
+    /// Filter by message type
+    pub message_type: Option<String>,
+    /// Filter by sender DID
+    pub from_did: Option<String>,
+    /// Filter by thread ID
+    pub thread_id: Option<String>,
+    /// Maximum number of messages to return
+    #[serde(default = "default_limit")]
+    pub limit: u32,
+}
+
+/// A message in an agent's inbox
+#[derive(Debug, Serialize)]
+pub struct InboxMessage {
+    /// Message log ID, usable as `since_id`
+    pub id: i64,
+    /// Message ID
+    pub message_id: String,
+    /// Message type
+    pub message_type: String,
+    /// Sender DID
+    pub from_did: Option<String>,
+    /// Thread ID
+    pub thread_id: Option<String>,
+    /// Whether the message has been marked read
+    pub read: bool,
+    /// When the message was received
+    pub created_at: String,
+    /// The plain message
+    pub message: Value,
+}
+
+/// Tool for listing the messages an agent received
+pub struct ListInboxTool {
+    tap_integration: Arc<TapIntegration>,
+}
+
+impl ListInboxTool {
+    pub fn new(tap_integration: Arc<TapIntegration>) -> Self {
+        Self { tap_integration }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for ListInboxTool {
+    async fn handle(&self, arguments: Option<Value>) -> Result<CallToolResult> {
+        let input: ListInboxInput = match arguments {
+            Some(args) => serde_json::from_value(args)?,
+            None => {
+                return Ok(error_text_response(
+                    "Missing required arguments".to_string(),
+                ));
+            }
+        };
+
+        debug!("Listing inbox of agent: {}", input.agent_did);
+
+        let filter = ReceivedFilter {
+            message_type: input.message_type.clone(),
+            from_did: input.from_did.clone(),
+            thread_id: input.thread_id.clone(),
+            unread_only: input.unread,
+        };
+
+        let page = match self
+            .tap_integration
+            .node()
+            .list_received(&input.agent_did, input.since_id, &filter, Some(input.limit))
+            .await
+        {
+            Ok(page) => page,
+            Err(e) => {
+                error!("Failed to list inbox: {}", e);
+                return Ok(error_text_response(format!(
+                    "Failed to list inbox of agent {}: {}",
+                    input.agent_did, e
+                )));
+            }
+        };
+
+        let messages: Vec<InboxMessage> = page
+            .messages
+            .iter()
+            .map(|m| InboxMessage {
+                id: m.id,
+                message_id: m.message_id.clone(),
+                message_type: m.message_type.clone(),
+                from_did: m.from_did.clone(),
+                thread_id: m.thread_id.clone(),
+                read: page.is_read(m),
+                created_at: m.created_at.clone(),
+                message: m.message_json.clone(),
+            })
+            .collect();
+
+        let text = format!(
+            "Found {} messages for agent {} ({} unread)",
+            messages.len(),
+            input.agent_did,
+            page.unread_count
+        );
+
+        Ok(CallToolResult {
+            content: vec![
+                ToolContent::Text { text },
+                ToolContent::Text {
+                    text: serde_json::to_string_pretty(&json!({
+                        "messages": messages,
+                        "next_cursor": page.next_cursor,
+                        "read_cursor": page.read_cursor,
+                        "unread_count": page.unread_count,
+                        "agent_did": input.agent_did,
+                    }))
+                    .unwrap_or_else(|_| "Failed to serialize JSON".to_string()),
+                },
+            ],
+            is_error: Some(false),
+        })
+    }
+
+    fn get_definition(&self) -> Tool {
+        Tool {
+            name: "tap_list_inbox".to_string(),
+            description: "Lists the messages an agent received, oldest first, from its message log. Pass next_cursor back as since_id to poll for newer messages, or set unread to list only messages not yet marked read with tap_mark_inbox_read.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "required": ["agent_did"],
+                "properties": {
+                    "agent_did": {
+                        "type": "string",
+                        "description": "The DID of the agent whose inbox to list"
+                    },
+                    "since_id": {
+                        "type": "number",
+                        "description": "Only list messages with a larger message log ID",
+                        "minimum": 0
+                    },
+                    "unread": {
+                        "type": "boolean",
+                        "description": "Only list messages not yet marked read",
+                        "default": false
+                    },
+                    "message_type": {
+                        "type": "string",
+                        "description": "Filter by message type URI"
+                    },
+                    "from_did": {
+                        "type": "string",
+                        "description": "Filter by sender DID"
+                    },
+                    "thread_id": {
+                        "type": "string",
+                        "description": "Filter by thread ID"
+                    },
+                    "limit": {
+                        "type": "number",
+                        "description": "Maximum number of messages to return",
+                        "default": 50,
+                        "minimum": 1,
+                        "maximum": 1000
+                    }
+                },
+                "additionalProperties": false
+            }),
+        }
+    }
+}
+
+/// Input for marking inbox messages read
+#[derive(Debug, Deserialize)]
+pub struct MarkInboxReadInput {
+    /// The DID of the agent whose messages to mark read
+    pub agent_did: String,
+    /// Mark messages up to and including this message log ID read; all if omitted
+    pub up_to: Option<i64>,
+}
+
+/// Tool for marking the messages an agent received as read
+pub struct MarkInboxReadTool {
+    tap_integration: Arc<TapIntegration>,
+}
+
+impl MarkInboxReadTool {
+    pub fn new(tap_integration: Arc<TapIntegration>) -> Self {
+        Self { tap_integration }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for MarkInboxReadTool {
+    async fn handle(&self, arguments: Option<Value>) -> Result<CallToolResult> {
+        let input: MarkInboxReadInput = match arguments {
+            Some(args) => serde_json::from_value(args)?,
+            None => {
+                return Ok(error_text_response(
+                    "Missing required arguments".to_string(),
+                ));
+            }
+        };
+
+        debug!("Marking inbox of agent {} read", input.agent_did);
+
+        let read_cursor = match self
+            .tap_integration
+            .node()
+            .mark_received_read(&input.agent_did, input.up_to)
+            .await
+        {
+            Ok(cursor) => cursor,
+            Err(e) => {
+                error!("Failed to mark inbox read: {}", e);
+                return Ok(error_text_response(format!(
+                    "Failed to mark inbox of agent {} read: {}",
+                    input.agent_did, e
+                )));
+            }
+        };
+
+        Ok(CallToolResult {
+            content: vec![
+                ToolContent::Text {
+                    text: format!(
+                        "Messages of agent {} up to {} are marked read",
+                        input.agent_did, read_cursor
+                    ),
+                },
+                ToolContent::Text {
+                    text: serde_json::to_string_pretty(&json!({
+                        "agent_did": input.agent_did,
+                        "read_cursor": read_cursor,
+                    }))
+                    .unwrap_or_else(|_| "Failed to serialize JSON".to_string()),
+                },
+            ],
+            is_error: Some(false),
+        })
+    }
+
+    fn get_definition(&self) -> Tool {
+        Tool {
+            name: "tap_mark_inbox_read".to_string(),
+            description: "Marks the messages an agent received as read, up to and including a message log ID or all of them. The read cursor never moves backwards.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "required": ["agent_did"],
+                "properties": {
+                    "agent_did": {
+                        "type": "string",
+                        "description": "The DID of the agent whose messages to mark read"
+                    },
+                    "up_to": {
+                        "type": "number",
+                        "description": "Message log ID up to which messages are read; all messages if omitted",
+                        "minimum": 0
+                    }
+                },
+                "additionalProperties": false
+            }),
+        }
+    }
+}
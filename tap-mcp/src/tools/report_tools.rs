@@ -0,0 +1,128 @@
+//! Tools for reports over stored transactions
+
+use super::{error_text_response, success_text_response, ToolHandler};
+use crate::error::Result;
+use crate::mcp::protocol::{CallToolResult, Tool};
+use crate::tap_integration::TapIntegration;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::{debug, error};
+
+// -----------------------------------------------------------------------
+// tap_get_counterparty_stats
+// -----------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+pub struct GetCounterpartyStatsInput {
+    pub agent_did: String,
+    pub counterparty_did: String,
+    pub since: Option<String>,
+    pub until: Option<String>,
+}
+
+pub struct GetCounterpartyStatsTool {
+    tap_integration: Arc<TapIntegration>,
+}
+
+impl GetCounterpartyStatsTool {
+    pub fn new(tap_integration: Arc<TapIntegration>) -> Self {
+        Self { tap_integration }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for GetCounterpartyStatsTool {
+    async fn handle(&self, arguments: Option<Value>) -> Result<CallToolResult> {
+        let input: GetCounterpartyStatsInput = match arguments {
+            Some(args) => serde_json::from_value(args)?,
+            None => {
+                return Ok(error_text_response(
+                    "Missing required arguments".to_string(),
+                ));
+            }
+        };
+
+        debug!(
+            "Getting stats of counterparty {} for agent {}",
+            input.counterparty_did, input.agent_did
+        );
+
+        for timestamp in [&input.since, &input.until].into_iter().flatten() {
+            if chrono::DateTime::parse_from_rfc3339(timestamp).is_err() {
+                return Ok(error_text_response(format!(
+                    "Invalid RFC 3339 timestamp: {}",
+                    timestamp
+                )));
+            }
+        }
+
+        let storage = match self
+            .tap_integration
+            .reader_for_agent(&input.agent_did)
+            .await
+        {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Failed to get agent storage: {}", e);
+                return Ok(error_text_response(format!(
+                    "Failed to get storage for agent {}: {}",
+                    input.agent_did, e
+                )));
+            }
+        };
+
+        let stats = match storage
+            .get_counterparty_stats(
+                &input.counterparty_did,
+                input.since.as_deref(),
+                input.until.as_deref(),
+            )
+            .await
+        {
+            Ok(stats) => stats,
+            Err(e) => {
+                error!("Failed to get counterparty stats: {}", e);
+                return Ok(error_text_response(format!(
+                    "Failed to get counterparty stats: {}",
+                    e
+                )));
+            }
+        };
+
+        Ok(success_text_response(
+            serde_json::to_string_pretty(&stats).unwrap(),
+        ))
+    }
+
+    fn get_definition(&self) -> Tool {
+        Tool {
+            name: "tap_get_counterparty_stats".to_string(),
+            description: "Get statistics of a counterparty from an agent's transactions: message counts, authorization rate, time to authorize and settle (average and percentiles), rejections by code and volumes per asset.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "agent_did": {
+                        "type": "string",
+                        "description": "The DID of the agent whose transactions to report on"
+                    },
+                    "counterparty_did": {
+                        "type": "string",
+                        "description": "The DID of the counterparty"
+                    },
+                    "since": {
+                        "type": "string",
+                        "description": "Only count activity at or after this time (RFC 3339)"
+                    },
+                    "until": {
+                        "type": "string",
+                        "description": "Only count activity before this time (RFC 3339)"
+                    }
+                },
+                "required": ["agent_did", "counterparty_did"],
+                "additionalProperties": false
+            }),
+        }
+    }
+}
@@ -0,0 +1,540 @@
+//! Tools for the manual review queue
+
+use super::transaction_tools::auto_resolve_decisions;
+use super::{default_limit, error_text_response, success_text_response, ToolHandler};
+use crate::error::Result;
+use crate::mcp::protocol::{CallToolResult, Tool};
+use crate::tap_integration::TapIntegration;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::tap_message_trait::TapMessageBody;
+use tap_msg::message::{Authorize, Reject, RejectionCode};
+use tap_node::storage::{DecisionType, ReviewItem, ReviewStatus};
+use tracing::{debug, error};
+
+#[derive(Debug, Serialize)]
+pub struct ReviewOutput {
+    pub id: i64,
+    pub transaction_id: String,
+    pub agent_did: String,
+    pub rule: String,
+    pub reason: String,
+    pub details: Value,
+    pub status: String,
+    pub reviewer: Option<String>,
+    pub review_note: Option<String>,
+    pub message_id: Option<String>,
+    pub created_at: String,
+    pub reviewed_at: Option<String>,
+}
+
+impl From<ReviewItem> for ReviewOutput {
+    fn from(item: ReviewItem) -> Self {
+        Self {
+            id: item.id,
+            transaction_id: item.transaction_id,
+            agent_did: item.agent_did,
+            rule: item.rule,
+            reason: item.reason,
+            details: item.details,
+            status: item.status.to_string(),
+            reviewer: item.reviewer,
+            review_note: item.review_note,
+            message_id: item.message_id,
+            created_at: item.created_at,
+            reviewed_at: item.reviewed_at,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------
+// tap_list_reviews
+// -----------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+pub struct ListReviewsInput {
+    pub agent_did: String,
+    pub status: Option<String>,
+    #[serde(default = "default_limit")]
+    pub limit: u32,
+    #[serde(default)]
+    pub offset: u32,
+}
+
+pub struct ListReviewsTool {
+    tap_integration: Arc<TapIntegration>,
+}
+
+impl ListReviewsTool {
+    pub fn new(tap_integration: Arc<TapIntegration>) -> Self {
+        Self { tap_integration }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for ListReviewsTool {
+    async fn handle(&self, arguments: Option<Value>) -> Result<CallToolResult> {
+        let input: ListReviewsInput = match arguments {
+            Some(args) => serde_json::from_value(args)?,
+            None => {
+                return Ok(error_text_response(
+                    "Missing required arguments".to_string(),
+                ));
+            }
+        };
+
+        debug!(
+            "Listing reviews for agent: {} status: {:?}",
+            input.agent_did, input.status
+        );
+
+        let status = match input.status.as_deref().map(ReviewStatus::try_from) {
+            Some(Ok(status)) => Some(status),
+            Some(Err(e)) => return Ok(error_text_response(e)),
+            None => None,
+        };
+
+        let storage = match self
+            .tap_integration
+            .reader_for_agent(&input.agent_did)
+            .await
+        {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Failed to get agent storage: {}", e);
+                return Ok(error_text_response(format!(
+                    "Failed to get storage for agent {}: {}",
+                    input.agent_did, e
+                )));
+            }
+        };
+
+        let items = match storage
+            .list_review_items(Some(&input.agent_did), status, input.limit, input.offset)
+            .await
+        {
+            Ok(items) => items,
+            Err(e) => {
+                error!("Failed to list reviews: {}", e);
+                return Ok(error_text_response(format!(
+                    "Failed to list reviews: {}",
+                    e
+                )));
+            }
+        };
+
+        let reviews: Vec<ReviewOutput> = items.into_iter().map(ReviewOutput::from).collect();
+        let total = reviews.len();
+        let response = json!({
+            "reviews": reviews,
+            "total": total,
+        });
+
+        Ok(success_text_response(
+            serde_json::to_string_pretty(&response).unwrap(),
+        ))
+    }
+
+    fn get_definition(&self) -> Tool {
+        Tool {
+            name: "tap_list_reviews".to_string(),
+            description: "List transactions held in the manual review queue. Items are created when a policy rule with the manual_review action trips.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "agent_did": {
+                        "type": "string",
+                        "description": "The DID of the agent whose review queue to list"
+                    },
+                    "status": {
+                        "type": "string",
+                        "description": "Filter by status: pending, approved, rejected, expired",
+                        "enum": ["pending", "approved", "rejected", "expired"]
+                    },
+                    "limit": {
+                        "type": "number",
+                        "description": "Maximum number of reviews to return",
+                        "default": 50
+                    },
+                    "offset": {
+                        "type": "number",
+                        "description": "Number of reviews to skip",
+                        "default": 0
+                    }
+                },
+                "required": ["agent_did"],
+                "additionalProperties": false
+            }),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------
+// tap_approve_review / tap_reject_review
+// -----------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewActionInput {
+    pub agent_did: String,
+    pub review_id: i64,
+    pub reviewer: String,
+    pub note: Option<String>,
+    pub settlement_address: Option<String>,
+}
+
+/// Send the message for a review decision and record the reviewer.
+///
+/// Returns the tool response; errors are reported as error responses.
+async fn complete_review(
+    tap_integration: &TapIntegration,
+    input: ReviewActionInput,
+    status: ReviewStatus,
+) -> Result<CallToolResult> {
+    let storage = match tap_integration.storage_for_agent(&input.agent_did).await {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Failed to get agent storage: {}", e);
+            return Ok(error_text_response(format!(
+                "Failed to get storage for agent {}: {}",
+                input.agent_did, e
+            )));
+        }
+    };
+
+    let item = match storage.get_review_item(input.review_id).await {
+        Ok(Some(item)) => item,
+        Ok(None) => {
+            return Ok(error_text_response(format!(
+                "Review {} not found",
+                input.review_id
+            )));
+        }
+        Err(e) => {
+            error!("Failed to get review: {}", e);
+            return Ok(error_text_response(format!("Failed to get review: {}", e)));
+        }
+    };
+
+    if item.status != ReviewStatus::Pending {
+        return Ok(error_text_response(format!(
+            "Review {} is already {} and cannot be acted on",
+            input.review_id, item.status
+        )));
+    }
+
+    let message: std::result::Result<PlainMessage, String> = match status {
+        ReviewStatus::Approved => {
+            let authorize = Authorize {
+                transaction_id: item.transaction_id.clone(),
+                settlement_address: input.settlement_address.clone(),
+                expiry: None,
+            };
+            authorize
+                .validate()
+                .map_err(|e| format!("Authorize validation failed: {}", e))
+                .and_then(|_| {
+                    authorize
+                        .to_didcomm(&item.agent_did)
+                        .map_err(|e| format!("Failed to create DIDComm message: {}", e))
+                })
+        }
+        _ => {
+            let reject = Reject {
+                transaction_id: item.transaction_id.clone(),
+                code: Some(RejectionCode::Policy),
+                reason: Some(input.note.clone().unwrap_or_else(|| item.reason.clone())),
+            };
+            reject
+                .validate()
+                .map_err(|e| format!("Reject validation failed: {}", e))
+                .and_then(|_| {
+                    reject
+                        .to_didcomm(&item.agent_did)
+                        .map_err(|e| format!("Failed to create DIDComm message: {}", e))
+                })
+        }
+    };
+    let didcomm_message = match message {
+        Ok(message) => message,
+        Err(e) => return Ok(error_text_response(e)),
+    };
+
+    debug!(
+        "Completing review {} as {} for transaction {}",
+        input.review_id, status, item.transaction_id
+    );
+
+    if let Err(e) = tap_integration
+        .node()
+        .send_message(item.agent_did.clone(), didcomm_message.clone())
+        .await
+    {
+        error!("Failed to send review message: {}", e);
+        return Ok(error_text_response(format!(
+            "Failed to send {} message: {}",
+            didcomm_message.type_, e
+        )));
+    }
+
+    match storage
+        .complete_review_item(
+            item.id,
+            status.clone(),
+            &input.reviewer,
+            input.note.as_deref(),
+            Some(&didcomm_message.id),
+        )
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            return Ok(error_text_response(format!(
+                "Review {} was completed by another reviewer",
+                item.id
+            )));
+        }
+        Err(e) => {
+            error!("Failed to complete review: {}", e);
+            return Ok(error_text_response(format!(
+                "Failed to complete review: {}",
+                e
+            )));
+        }
+    }
+
+    let (action, decision_type) = match status {
+        ReviewStatus::Approved => ("authorize", Some(DecisionType::AuthorizationRequired)),
+        _ => ("reject", None),
+    };
+    auto_resolve_decisions(
+        tap_integration,
+        &item.agent_did,
+        &item.transaction_id,
+        action,
+        decision_type,
+    )
+    .await;
+
+    let response = json!({
+        "review_id": item.id,
+        "transaction_id": item.transaction_id,
+        "message_id": didcomm_message.id,
+        "status": status.to_string(),
+        "reviewer": input.reviewer,
+        "reviewed_at": chrono::Utc::now().to_rfc3339(),
+    });
+
+    Ok(success_text_response(
+        serde_json::to_string_pretty(&response).unwrap(),
+    ))
+}
+
+pub struct ApproveReviewTool {
+    tap_integration: Arc<TapIntegration>,
+}
+
+impl ApproveReviewTool {
+    pub fn new(tap_integration: Arc<TapIntegration>) -> Self {
+        Self { tap_integration }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for ApproveReviewTool {
+    async fn handle(&self, arguments: Option<Value>) -> Result<CallToolResult> {
+        let input: ReviewActionInput = match arguments {
+            Some(args) => serde_json::from_value(args)?,
+            None => {
+                return Ok(error_text_response(
+                    "Missing required arguments".to_string(),
+                ));
+            }
+        };
+
+        complete_review(&self.tap_integration, input, ReviewStatus::Approved).await
+    }
+
+    fn get_definition(&self) -> Tool {
+        Tool {
+            name: "tap_approve_review".to_string(),
+            description: "Approve a pending review item. Sends an Authorize message for the transaction from the item's agent and records the reviewer.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "agent_did": {
+                        "type": "string",
+                        "description": "The DID of the agent that owns the review item"
+                    },
+                    "review_id": {
+                        "type": "number",
+                        "description": "The ID of the review item to approve"
+                    },
+                    "reviewer": {
+                        "type": "string",
+                        "description": "Identity of the reviewer, recorded on the review item"
+                    },
+                    "note": {
+                        "type": "string",
+                        "description": "Optional note recorded with the review"
+                    },
+                    "settlement_address": {
+                        "type": "string",
+                        "description": "Optional settlement address (CAIP-10) to include in the Authorize"
+                    }
+                },
+                "required": ["agent_did", "review_id", "reviewer"],
+                "additionalProperties": false
+            }),
+        }
+    }
+}
+
+pub struct RejectReviewTool {
+    tap_integration: Arc<TapIntegration>,
+}
+
+impl RejectReviewTool {
+    pub fn new(tap_integration: Arc<TapIntegration>) -> Self {
+        Self { tap_integration }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for RejectReviewTool {
+    async fn handle(&self, arguments: Option<Value>) -> Result<CallToolResult> {
+        let input: ReviewActionInput = match arguments {
+            Some(args) => serde_json::from_value(args)?,
+            None => {
+                return Ok(error_text_response(
+                    "Missing required arguments".to_string(),
+                ));
+            }
+        };
+
+        complete_review(&self.tap_integration, input, ReviewStatus::Rejected).await
+    }
+
+    fn get_definition(&self) -> Tool {
+        Tool {
+            name: "tap_reject_review".to_string(),
+            description: "Reject a pending review item. Sends a Reject message for the transaction from the item's agent and records the reviewer. The note is used as the rejection reason.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "agent_did": {
+                        "type": "string",
+                        "description": "The DID of the agent that owns the review item"
+                    },
+                    "review_id": {
+                        "type": "number",
+                        "description": "The ID of the review item to reject"
+                    },
+                    "reviewer": {
+                        "type": "string",
+                        "description": "Identity of the reviewer, recorded on the review item"
+                    },
+                    "note": {
+                        "type": "string",
+                        "description": "Optional note recorded with the review and used as the rejection reason"
+                    }
+                },
+                "required": ["agent_did", "review_id", "reviewer"],
+                "additionalProperties": false
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tap_integration::TapIntegration;
+    use tempfile::tempdir;
+
+    async fn setup_test() -> (Arc<TapIntegration>, String) {
+        let dir = tempdir().unwrap();
+        let tap_root = dir.path().to_str().unwrap();
+
+        let (agent, did) = tap_agent::TapAgent::from_ephemeral_key().await.unwrap();
+        let agent_arc = Arc::new(agent);
+
+        let integration = TapIntegration::new(Some(&did), Some(tap_root), Some(agent_arc))
+            .await
+            .unwrap();
+
+        std::mem::forget(dir);
+
+        (Arc::new(integration), did)
+    }
+
+    #[tokio::test]
+    async fn test_list_reviews() {
+        let (integration, did) = setup_test().await;
+
+        let storage = integration.storage_for_agent(&did).await.unwrap();
+        storage
+            .insert_review_item(
+                "txn-review-1",
+                &did,
+                "daily-limit",
+                "Velocity limit exceeded",
+                &json!({"aggregate_amount": 1100.0}),
+            )
+            .await
+            .unwrap();
+
+        let tool = ListReviewsTool::new(integration);
+        let result = tool
+            .handle(Some(json!({
+                "agent_did": did,
+                "status": "pending",
+            })))
+            .await
+            .unwrap();
+
+        assert_eq!(result.is_error, Some(false));
+        let text = match &result.content[0] {
+            crate::mcp::protocol::ToolContent::Text { text } => text,
+            _ => panic!("Expected text content"),
+        };
+        let parsed: Value = serde_json::from_str(text).unwrap();
+        assert_eq!(parsed["total"], 1);
+        assert_eq!(parsed["reviews"][0]["rule"], "daily-limit");
+    }
+
+    #[tokio::test]
+    async fn test_reject_review_already_completed() {
+        let (integration, did) = setup_test().await;
+
+        let storage = integration.storage_for_agent(&did).await.unwrap();
+        let review_id = storage
+            .insert_review_item(
+                "txn-review-2",
+                &did,
+                "daily-limit",
+                "Velocity limit exceeded",
+                &json!({}),
+            )
+            .await
+            .unwrap();
+        storage
+            .complete_review_item(review_id, ReviewStatus::Approved, "alice", None, None)
+            .await
+            .unwrap();
+
+        let tool = RejectReviewTool::new(integration);
+        let result = tool
+            .handle(Some(json!({
+                "agent_did": did,
+                "review_id": review_id,
+                "reviewer": "bob",
+            })))
+            .await
+            .unwrap();
+
+        assert_eq!(result.is_error, Some(true));
+    }
+}
@@ -0,0 +1,143 @@
+//! Tools for the node's scheduled jobs
+
+use super::{error_text_response, success_text_response, ToolHandler};
+use crate::error::Result;
+use crate::mcp::protocol::{CallToolResult, Tool};
+use crate::tap_integration::TapIntegration;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tap_node::scheduler::JobTrigger;
+use tracing::{debug, error};
+
+// -----------------------------------------------------------------------
+// tap_list_scheduled_jobs
+// -----------------------------------------------------------------------
+
+pub struct ListScheduledJobsTool {
+    tap_integration: Arc<TapIntegration>,
+}
+
+impl ListScheduledJobsTool {
+    pub fn new(tap_integration: Arc<TapIntegration>) -> Self {
+        Self { tap_integration }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for ListScheduledJobsTool {
+    async fn handle(&self, _arguments: Option<Value>) -> Result<CallToolResult> {
+        debug!("Listing scheduled jobs");
+
+        let jobs = match self.tap_integration.node().scheduled_jobs().await {
+            Ok(jobs) => jobs,
+            Err(e) => {
+                error!("Failed to list scheduled jobs: {}", e);
+                return Ok(error_text_response(format!(
+                    "Failed to list scheduled jobs: {}",
+                    e
+                )));
+            }
+        };
+
+        Ok(success_text_response(
+            serde_json::to_string_pretty(&json!({ "jobs": jobs })).unwrap(),
+        ))
+    }
+
+    fn get_definition(&self) -> Tool {
+        Tool {
+            name: "tap_list_scheduled_jobs".to_string(),
+            description: "List the node's scheduled jobs (transaction expiry, delivery retries, retention, backups, clock checks) with their schedule, last run, outcome and next run.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {},
+                "additionalProperties": false
+            }),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------
+// tap_run_scheduled_job
+// -----------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+pub struct RunScheduledJobInput {
+    pub job: String,
+}
+
+pub struct RunScheduledJobTool {
+    tap_integration: Arc<TapIntegration>,
+}
+
+impl RunScheduledJobTool {
+    pub fn new(tap_integration: Arc<TapIntegration>) -> Self {
+        Self { tap_integration }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for RunScheduledJobTool {
+    async fn handle(&self, arguments: Option<Value>) -> Result<CallToolResult> {
+        let input: RunScheduledJobInput = match arguments {
+            Some(args) => serde_json::from_value(args)?,
+            None => {
+                return Ok(error_text_response(
+                    "Missing required arguments".to_string(),
+                ));
+            }
+        };
+
+        debug!("Running scheduled job {}", input.job);
+
+        let response = match self
+            .tap_integration
+            .node()
+            .run_scheduled_job(&input.job)
+            .await
+        {
+            Ok(JobTrigger::Ran(run)) => json!({
+                "job": run.name,
+                "status": run.status.to_string(),
+                "error": run.error,
+                "duration_ms": run.duration.as_millis() as u64,
+                "next_run_at": run.next_run_at.map(|at| at.to_rfc3339()),
+            }),
+            Ok(JobTrigger::Requested) => json!({
+                "job": input.job,
+                "status": "requested",
+            }),
+            Err(e) => {
+                error!("Failed to run scheduled job {}: {}", input.job, e);
+                return Ok(error_text_response(format!(
+                    "Failed to run scheduled job {}: {}",
+                    input.job, e
+                )));
+            }
+        };
+
+        Ok(success_text_response(
+            serde_json::to_string_pretty(&response).unwrap(),
+        ))
+    }
+
+    fn get_definition(&self) -> Tool {
+        Tool {
+            name: "tap_run_scheduled_job".to_string(),
+            description: "Run one of the node's scheduled jobs now. A job scheduled by another node on the same database is requested from that node and runs within seconds.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "job": {
+                        "type": "string",
+                        "description": "Name of the job, as listed by tap_list_scheduled_jobs"
+                    }
+                },
+                "required": ["job"],
+                "additionalProperties": false
+            }),
+        }
+    }
+}
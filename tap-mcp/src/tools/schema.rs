@@ -1,6 +1,7 @@
 //! JSON schemas for tool parameters
 
 use serde_json::{json, Value};
+use tap_msg::message::RejectionCode;
 
 /// Schema for create_agent tool
 pub fn create_agent_schema() -> Value {
@@ -186,6 +187,11 @@ pub fn reject_schema() -> Value {
             "reason": {
                 "type": "string",
                 "description": "Reason for rejection"
+            },
+            "code": {
+                "type": "string",
+                "enum": RejectionCode::ALL.iter().map(|code| code.as_str()).collect::<Vec<_>>(),
+                "description": "Standardized rejection code"
             }
         },
         "required": ["agent_did", "transaction_id", "reason"],
@@ -239,7 +245,11 @@ pub fn settle_schema() -> Value {
             },
             "amount": {
                 "type": "string",
-                "description": "Optional amount settled"
+                "description": "Optional amount settled, in the settled asset if one is given"
+            },
+            "settled_asset": {
+                "type": "string",
+                "description": "Asset the transaction was settled in (CAIP-19 asset ID or ISO 4217 currency code), if different from the requested asset; requires amount"
             }
         },
         "required": ["agent_did", "transaction_id", "settlement_id"],
@@ -780,6 +790,37 @@ pub fn update_policies_schema() -> Value {
     })
 }
 
+/// Schema for simulate_policies tool
+pub fn simulate_policies_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "transaction": {
+                "type": "object",
+                "properties": {
+                    "@type": {
+                        "type": "string",
+                        "description": "Message type, e.g. 'https://tap.rsvp/schema/1.0#Transfer'"
+                    }
+                },
+                "required": ["@type"],
+                "additionalProperties": true,
+                "description": "Body of the candidate Transfer or Payment"
+            },
+            "from": {
+                "type": "string",
+                "description": "The DID of the agent the transaction would come from"
+            },
+            "transaction_id": {
+                "type": "string",
+                "description": "ID to evaluate the transaction under; a stored transaction with this ID is left out of velocity totals"
+            }
+        },
+        "required": ["transaction", "from"],
+        "additionalProperties": false
+    })
+}
+
 /// Schema for add_agents tool
 pub fn add_agents_schema() -> Value {
     json!({
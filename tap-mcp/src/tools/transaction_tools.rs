@@ -14,10 +14,11 @@ use tap_msg::message::payment::InvoiceReference;
 use tap_msg::message::tap_message_trait::TapMessageBody;
 use tap_msg::message::transfer::TransactionValue;
 use tap_msg::message::{
-    Agent, Authorize, Cancel, Capture, Connect, ConnectionConstraints, Escrow, Exchange, Party,
-    Payment, Quote, Reject, Revert, Settle, TransactionLimits, Transfer,
+    Agent, AgentRole, Authorize, Cancel, Capture, Connect, ConnectionConstraints, Escrow, Exchange,
+    Party, Payment, Quote, Reject, RejectionCode, Revert, Settle, TransactionLimits, Transfer,
 };
 use tap_msg::settlement_address::SettlementAddress;
+use tap_msg::utils::message_transaction_hash;
 use tap_node::storage::models::SchemaType;
 use tap_node::storage::DecisionType;
 use tracing::{debug, error};
@@ -28,7 +29,7 @@ use tracing::{debug, error};
 /// this function resolves matching pending/delivered decisions in the shared
 /// database. This enables poll-mode architectures where external processes
 /// act on decisions and the decision_log is automatically cleaned up.
-async fn auto_resolve_decisions(
+pub(crate) async fn auto_resolve_decisions(
     tap_integration: &TapIntegration,
     agent_did: &str,
     transaction_id: &str,
@@ -602,6 +603,8 @@ struct RejectParams {
     agent_did: String, // The DID of the agent that will sign and send this message
     transaction_id: String,
     reason: String,
+    #[serde(default)]
+    code: Option<RejectionCode>,
 }
 
 /// Response for rejecting a transaction
@@ -610,6 +613,8 @@ struct RejectResponse {
     transaction_id: String,
     message_id: String,
     status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<RejectionCode>,
     reason: String,
     rejected_at: String,
 }
@@ -645,6 +650,7 @@ impl ToolHandler for RejectTool {
         // Create reject message
         let reject = Reject {
             transaction_id: params.transaction_id.clone(),
+            code: params.code,
             reason: Some(params.reason.clone()),
         };
 
@@ -708,6 +714,7 @@ impl ToolHandler for RejectTool {
                     transaction_id: params.transaction_id,
                     message_id: didcomm_message.id,
                     status: "sent".to_string(),
+                    code: params.code,
                     reason: params.reason,
                     rejected_at: chrono::Utc::now().to_rfc3339(),
                 };
@@ -901,6 +908,8 @@ struct SettleParams {
     settlement_id: String,
     #[serde(default)]
     amount: Option<String>,
+    #[serde(default)]
+    settled_asset: Option<String>,
 }
 
 /// Response for settling a transaction
@@ -911,6 +920,8 @@ struct SettleResponse {
     message_id: String,
     status: String,
     amount: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    settled_asset: Option<String>,
     settled_at: String,
 }
 
@@ -947,6 +958,7 @@ impl ToolHandler for SettleTool {
             transaction_id: params.transaction_id.clone(),
             settlement_id: Some(params.settlement_id.clone()),
             amount: params.amount.clone(),
+            settled_asset: params.settled_asset.clone(),
         };
 
         // Validate the settle message
@@ -1011,6 +1023,7 @@ impl ToolHandler for SettleTool {
                     message_id: didcomm_message.id,
                     status: "sent".to_string(),
                     amount: params.amount,
+                    settled_asset: params.settled_asset,
                     settled_at: chrono::Utc::now().to_rfc3339(),
                 };
 
@@ -1242,6 +1255,9 @@ struct TransactionInfo {
     from: Option<String>,
     to: Option<String>,
     direction: String,
+    /// Canonical hash of the transaction, for Transfer and Payment messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    canonical_hash: Option<String>,
     created_at: String,
     body: serde_json::Value,
 }
@@ -1277,7 +1293,7 @@ impl ToolHandler for ListTransactionsTool {
         // Get messages from the agent's specific storage
         let storage = self
             .tap_integration()
-            .storage_for_agent(&params.agent_did)
+            .reader_for_agent(&params.agent_did)
             .await?;
         let direction_filter = None; // No direction filter for now
         let messages = storage
@@ -1325,6 +1341,7 @@ impl ToolHandler for ListTransactionsTool {
                 from: msg.from_did.clone(),
                 to: msg.to_did.clone(),
                 direction: msg.direction.to_string(),
+                canonical_hash: message_transaction_hash(&msg.message_json),
                 created_at: msg.created_at.clone(),
                 body: msg.message_json.clone(),
             })
@@ -1845,7 +1862,7 @@ impl ToolHandler for CreateEscrowTool {
         // Verify exactly one EscrowAgent exists
         let escrow_agent_count = agents
             .iter()
-            .filter(|a| a.role == Some("EscrowAgent".to_string()))
+            .filter(|a| a.has_role(AgentRole::EscrowAgent))
             .count();
         if escrow_agent_count != 1 {
             return Ok(error_text_response(format!(
@@ -137,6 +137,27 @@ impl McpTestClient {
             })),
         }
     }
+
+    fn create_list_prompts_request(&mut self) -> JsonRpcRequest {
+        JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: "prompts/list".to_string(),
+            id: Some(self.next_id()),
+            params: None,
+        }
+    }
+
+    fn create_get_prompt_request(&mut self, name: &str, arguments: Value) -> JsonRpcRequest {
+        JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: "prompts/get".to_string(),
+            id: Some(self.next_id()),
+            params: Some(json!({
+                "name": name,
+                "arguments": arguments
+            })),
+        }
+    }
 }
 
 #[tokio::test]
@@ -164,6 +185,12 @@ async fn test_mcp_initialization() -> Result<()> {
         assert_eq!(result["protocolVersion"], "2024-11-05");
         assert!(result["capabilities"]["tools"].is_object());
         assert!(result["capabilities"]["resources"].is_object());
+        let node = &result["capabilities"]["experimental"]["tapNode"];
+        assert_eq!(node["storage"], "sqlite");
+        assert!(node["features"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!("storage")));
     }
 
     Ok(())
@@ -195,7 +222,7 @@ async fn test_list_tools() -> Result<()> {
 
     if let Some(result) = response.result {
         let tools = result["tools"].as_array().unwrap();
-        assert_eq!(tools.len(), 38); // All 38 tools including decision and exchange tools
+        assert_eq!(tools.len(), 55); // All 55 tools including decision, review, draft, exchange, inbox, report, scheduler and event tools
 
         let tool_names: Vec<&str> = tools.iter().map(|t| t["name"].as_str().unwrap()).collect();
 
@@ -218,6 +245,8 @@ async fn test_list_tools() -> Result<()> {
         assert!(tool_names.contains(&"tap_list_received"));
         assert!(tool_names.contains(&"tap_get_pending_received"));
         assert!(tool_names.contains(&"tap_view_raw_received"));
+        assert!(tool_names.contains(&"tap_list_inbox"));
+        assert!(tool_names.contains(&"tap_mark_inbox_read"));
         assert!(tool_names.contains(&"tap_query_database"));
         assert!(tool_names.contains(&"tap_get_database_schema"));
         assert!(tool_names.contains(&"tap_revert"));
@@ -225,6 +254,12 @@ async fn test_list_tools() -> Result<()> {
         assert!(tool_names.contains(&"tap_remove_agent"));
         assert!(tool_names.contains(&"tap_replace_agent"));
         assert!(tool_names.contains(&"tap_update_policies"));
+        assert!(tool_names.contains(&"tap_simulate_policies"));
+        assert!(tool_names.contains(&"tap_create_draft"));
+        assert!(tool_names.contains(&"tap_send_draft"));
+        assert!(tool_names.contains(&"tap_subscribe_events"));
+        assert!(tool_names.contains(&"tap_unsubscribe_events"));
+        assert!(tool_names.contains(&"tap_list_event_subscriptions"));
     }
 
     Ok(())
@@ -1023,3 +1058,166 @@ async fn test_message_listing_shows_both_directions() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+#[serial]
+async fn test_tool_calls_require_scoped_token() -> Result<()> {
+    use tap_mcp::mcp::auth::{Scope, TokenAuthority};
+
+    let env = TestEnvironment::new()?;
+    let authority = TokenAuthority::from_secret(b"0123456789abcdef0123456789abcdef")?;
+    let mut server = env.create_server().await?.with_auth(authority.clone());
+    let mut client = McpTestClient::new();
+
+    let mut init_request = client.create_initialize_request();
+    init_request.params.as_mut().unwrap()["_meta"] = json!({
+        "authorization": format!("Bearer {}", authority.issue("dashboard", Scope::Read, 60)?)
+    });
+    server.handle_request_direct(init_request).await?;
+
+    let response = server
+        .handle_request_direct(client.create_list_tools_request())
+        .await?;
+    assert!(response.error.is_none());
+    let response = server
+        .handle_request_direct(client.create_call_tool_request("tap_list_transactions", json!({})))
+        .await?;
+    assert!(response.error.is_none());
+
+    // Raw SQL reads every table, so read-only tokens cannot run it
+    for tool in ["tap_query_database", "tap_get_database_schema"] {
+        let response = server
+            .handle_request_direct(client.create_call_tool_request(tool, json!({})))
+            .await?;
+        assert_eq!(response.error.unwrap().code, error_codes::UNAUTHORIZED);
+    }
+
+    let response = server
+        .handle_request_direct(client.create_call_tool_request("tap_remove_agent", json!({})))
+        .await?;
+    assert_eq!(response.error.unwrap().code, error_codes::UNAUTHORIZED);
+
+    // A token passed with the call overrides the session token
+    let mut admin_request = client.create_call_tool_request("tap_remove_agent", json!({}));
+    admin_request.params.as_mut().unwrap()["_meta"] = json!({
+        "authorization": authority.issue("operator", Scope::Admin, 60)?
+    });
+    let response = server.handle_request_direct(admin_request).await?;
+    assert!(response.error.is_none());
+
+    // A token sent with a failed initialize is not kept for the session
+    let mut server = env.create_server().await?.with_auth(authority.clone());
+    let mut bad_init = client.create_initialize_request();
+    let params = bad_init.params.as_mut().unwrap();
+    params.as_object_mut().unwrap().remove("protocolVersion");
+    params["_meta"] = json!({ "authorization": authority.issue("operator", Scope::Admin, 60)? });
+    let response = server.handle_request_direct(bad_init).await?;
+    assert_eq!(response.error.unwrap().code, error_codes::INVALID_PARAMS);
+
+    server
+        .handle_request_direct(client.create_initialize_request())
+        .await?;
+    let response = server
+        .handle_request_direct(client.create_call_tool_request("tap_list_transactions", json!({})))
+        .await?;
+    assert_eq!(response.error.unwrap().code, error_codes::UNAUTHORIZED);
+
+    // Sessions without a token cannot enumerate the tools either
+    let response = server
+        .handle_request_direct(client.create_list_tools_request())
+        .await?;
+    assert_eq!(response.error.unwrap().code, error_codes::UNAUTHORIZED);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn test_prompts() -> Result<()> {
+    let env = TestEnvironment::new()?;
+    let mut server = env.create_server().await?;
+    let mut client = McpTestClient::new();
+
+    let init_response = server
+        .handle_request_direct(client.create_initialize_request())
+        .await?;
+    assert!(init_response.result.unwrap()["capabilities"]["prompts"].is_object());
+
+    // List prompts
+    let response = server
+        .handle_request_direct(client.create_list_prompts_request())
+        .await?;
+    let result = response.result.unwrap();
+    let names: Vec<&str> = result["prompts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["name"].as_str().unwrap())
+        .collect();
+    assert!(names.contains(&"initiate_compliant_transfer"));
+    assert!(names.contains(&"respond_to_travel_rule_request"));
+    assert!(names.contains(&"review_pending_transactions"));
+
+    // Create an agent to render prompts for
+    let agent_response = server
+        .handle_request_direct(
+            client.create_call_tool_request("tap_create_agent", json!({ "label": "Prompt Agent" })),
+        )
+        .await?;
+    let agent_result_value = agent_response.result.unwrap();
+    let agent_content = agent_result_value["content"][0]["text"].as_str().unwrap();
+    let agent_result: Value = serde_json::from_str(agent_content)?;
+    let agent_did = agent_result["@id"].as_str().unwrap();
+
+    // Render a prompt with live node data
+    let response = server
+        .handle_request_direct(client.create_get_prompt_request(
+            "initiate_compliant_transfer",
+            json!({
+                "agent_did": agent_did,
+                "asset": "eip155:1/erc20:0xa0b86a33e6a4a3c3fcb4b0f0b2a4b6e1c9f8d5c4",
+                "amount": "100.00"
+            }),
+        ))
+        .await?;
+    assert!(response.error.is_none());
+    let result = response.result.unwrap();
+    let messages = result["messages"].as_array().unwrap();
+    assert_eq!(messages[0]["role"], "user");
+    let text = messages[0]["content"]["text"].as_str().unwrap();
+    assert!(text.contains(agent_did));
+    assert!(text.contains("tap_create_draft"));
+    assert!(text.contains("100.00"));
+
+    // Invalid arguments are rejected before rendering
+    let response = server
+        .handle_request_direct(client.create_get_prompt_request(
+            "initiate_compliant_transfer",
+            json!({ "agent_did": agent_did, "asset": "not-an-asset" }),
+        ))
+        .await?;
+    assert_eq!(response.error.unwrap().code, error_codes::INVALID_PARAMS);
+
+    let response = server
+        .handle_request_direct(client.create_get_prompt_request(
+            "review_pending_transactions",
+            json!({ "agent_did": "did:example:unknown" }),
+        ))
+        .await?;
+    assert_eq!(response.error.unwrap().code, error_codes::INVALID_PARAMS);
+
+    let response = server
+        .handle_request_direct(client.create_get_prompt_request(
+            "respond_to_travel_rule_request",
+            json!({ "agent_did": agent_did, "transaction_id": "missing-transaction" }),
+        ))
+        .await?;
+    assert_eq!(response.error.unwrap().code, error_codes::INVALID_PARAMS);
+
+    let response = server
+        .handle_request_direct(client.create_get_prompt_request("unknown_prompt", json!({})))
+        .await?;
+    assert_eq!(response.error.unwrap().code, error_codes::INVALID_PARAMS);
+
+    Ok(())
+}
@@ -6,6 +6,8 @@
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
 use syn::{parse_macro_input, Data, DeriveInput, Field, Fields};
 
 /// Procedural derive macro for implementing TapMessage, MessageContext, and optionally TapMessageBody traits.
@@ -80,6 +82,8 @@ use syn::{parse_macro_input, Data, DeriveInput, Field, Fields};
 /// - `#[tap(authorizable)]` - Auto-generates Authorizable trait implementation
 /// - `#[tap(transactable)]` - Auto-generates Transaction trait implementation
 /// - `#[tap(builder)]` - Auto-generates builder pattern
+/// - `#[tap(async_validation)]` - Auto-generates AsyncValidate, which runs `validate()` and then
+///   awaits a `validate_<struct_name_lowercase>_async(&self, context: &dyn ValidationContext)` method
 ///
 /// ## Field-level Attributes
 /// - `#[tap(participant)]` - Single participant field (Party or Agent, required or optional)
@@ -87,33 +91,83 @@ use syn::{parse_macro_input, Data, DeriveInput, Field, Fields};
 /// - `#[tap(transaction_id)]` - Transaction ID field (creates new transaction for initiators)
 /// - `#[tap(thread_id)]` - Thread ID field (references existing transaction for replies)
 /// - `#[tap(connection_id)]` - Connection ID field (for linking to Connect messages)
+///
+/// # Compile Errors
+///
+/// Invalid attributes are reported as compile errors pointing at the attribute:
+/// - unknown attributes, and field attributes used on the struct or the reverse
+/// - a `message_type` that is not a string literal or has no URI scheme
+/// - more than one `transaction_id`, `thread_id` or `connection_id` field
+/// - `authorizable` without a `transaction_id` or `thread_id` field
+/// - `transactable` on an `initiator` without a `transaction_id` field
+/// - `transactable` on a reply without a `thread_id` field referencing the transaction
 #[proc_macro_derive(TapMessage, attributes(tap))]
 pub fn derive_tap_message(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let expanded = impl_tap_message(&input);
+    let expanded = impl_tap_message(&input).unwrap_or_else(syn::Error::into_compile_error);
     TokenStream::from(expanded)
 }
 
 #[proc_macro_derive(TapMessageBody, attributes(tap))]
 pub fn derive_tap_message_body(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let expanded = impl_tap_message_body_only(&input);
+    let expanded =
+        impl_tap_message_body_only(&input).unwrap_or_else(syn::Error::into_compile_error);
     TokenStream::from(expanded)
 }
 
-fn impl_tap_message(input: &DeriveInput) -> TokenStream2 {
-    let name = &input.ident;
-    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
-
-    let fields = match &input.data {
+/// Attributes accepted on the struct
+const STRUCT_ATTRIBUTES: &[&str] = &[
+    "message_type",
+    "initiator",
+    "authorizable",
+    "transactable",
+    "builder",
+    "custom_validation",
+    "async_validation",
+    "generated_id",
+];
+
+/// Attributes accepted on fields
+const FIELD_ATTRIBUTES: &[&str] = &[
+    "participant",
+    "participant_list",
+    "transaction_id",
+    "optional_transaction_id",
+    "thread_id",
+    "connection_id",
+    "generated_id",
+];
+
+/// The named fields of the struct a derive is applied to
+fn named_fields<'a>(
+    input: &'a DeriveInput,
+    derive: &str,
+) -> syn::Result<&'a Punctuated<Field, syn::Token![,]>> {
+    match &input.data {
         Data::Struct(data) => match &data.fields {
-            Fields::Named(fields) => &fields.named,
-            _ => panic!("TapMessage can only be derived for structs with named fields"),
+            Fields::Named(fields) => Ok(&fields.named),
+            fields => Err(syn::Error::new_spanned(
+                fields,
+                format!(
+                    "{} can only be derived for structs with named fields",
+                    derive
+                ),
+            )),
         },
-        _ => panic!("TapMessage can only be derived for structs"),
-    };
+        _ => Err(syn::Error::new_spanned(
+            &input.ident,
+            format!("{} can only be derived for structs", derive),
+        )),
+    }
+}
+
+fn impl_tap_message(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
-    let field_info = analyze_fields(fields, &input.attrs);
+    let fields = named_fields(input, "TapMessage")?;
+    let field_info = analyze_fields(fields, &input.attrs)?;
 
     // Check if we're inside the tap-msg crate or external
     let is_internal = std::env::var("CARGO_CRATE_NAME").unwrap_or_default() == "tap_msg";
@@ -175,6 +229,18 @@ fn impl_tap_message(input: &DeriveInput) -> TokenStream2 {
         quote! {}
     };
 
+    let async_validate_impl = if field_info.async_validation {
+        impl_async_validate_trait(
+            name,
+            &impl_generics,
+            &ty_generics,
+            where_clause,
+            is_internal,
+        )
+    } else {
+        quote! {}
+    };
+
     let connectable_impl = if field_info.connection_id_field.is_some() || field_info.is_initiator {
         impl_connectable_trait(
             name,
@@ -188,14 +254,15 @@ fn impl_tap_message(input: &DeriveInput) -> TokenStream2 {
         quote! {}
     };
 
-    quote! {
+    Ok(quote! {
         #tap_message_impl
         #message_context_impl
         #tap_message_body_impl
         #authorizable_impl
         #transaction_impl
+        #async_validate_impl
         #connectable_impl
-    }
+    })
 }
 
 fn impl_connectable_trait(
@@ -268,12 +335,13 @@ struct FieldInfo {
     is_transactable: bool,
     generate_builder: bool,
     custom_validation: bool,
+    async_validation: bool,
 }
 
 fn analyze_fields(
-    fields: &syn::punctuated::Punctuated<Field, syn::Token![,]>,
+    fields: &Punctuated<Field, syn::Token![,]>,
     struct_attrs: &[syn::Attribute],
-) -> FieldInfo {
+) -> syn::Result<FieldInfo> {
     let mut field_info = FieldInfo {
         participant_fields: Vec::new(),
         optional_participant_fields: Vec::new(),
@@ -290,81 +358,206 @@ fn analyze_fields(
         is_transactable: false,
         generate_builder: false,
         custom_validation: false,
+        async_validation: false,
     };
+    let mut errors: Option<syn::Error> = None;
+    let mut authorizable_span = None;
+    let mut transactable_span = None;
 
     // First check struct-level attributes
-    for attr in struct_attrs {
-        if attr.path().is_ident("tap") {
-            let _ = attr.parse_nested_meta(|meta| {
-                if meta.path.is_ident("generated_id") {
-                    field_info.has_generated_id = true;
-                } else if meta.path.is_ident("message_type") {
-                    if let Ok(lit) = meta.value() {
-                        if let Ok(lit_str) = lit.parse::<syn::LitStr>() {
-                            field_info.message_type = Some(lit_str.value());
-                        }
-                    }
-                } else if meta.path.is_ident("initiator") {
-                    field_info.is_initiator = true;
-                } else if meta.path.is_ident("authorizable") {
-                    field_info.is_authorizable = true;
-                } else if meta.path.is_ident("transactable") {
-                    field_info.is_transactable = true;
-                } else if meta.path.is_ident("builder") {
-                    field_info.generate_builder = true;
-                } else if meta.path.is_ident("custom_validation") {
-                    field_info.custom_validation = true;
-                }
-                Ok(())
-            });
+    for attr in struct_attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("tap"))
+    {
+        let result = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("generated_id") {
+                field_info.has_generated_id = true;
+            } else if meta.path.is_ident("message_type") {
+                let lit: syn::LitStr = meta.value()?.parse()?;
+                check_message_type(&lit)?;
+                field_info.message_type = Some(lit.value());
+            } else if meta.path.is_ident("initiator") {
+                field_info.is_initiator = true;
+            } else if meta.path.is_ident("authorizable") {
+                field_info.is_authorizable = true;
+                authorizable_span = Some(meta.path.span());
+            } else if meta.path.is_ident("transactable") {
+                field_info.is_transactable = true;
+                transactable_span = Some(meta.path.span());
+            } else if meta.path.is_ident("builder") {
+                field_info.generate_builder = true;
+            } else if meta.path.is_ident("custom_validation") {
+                field_info.custom_validation = true;
+            } else if meta.path.is_ident("async_validation") {
+                field_info.async_validation = true;
+            } else {
+                return Err(unknown_attribute(&meta.path, "struct", FIELD_ATTRIBUTES));
+            }
+            Ok(())
+        });
+        if let Err(error) = result {
+            push_error(&mut errors, error);
         }
     }
 
     for field in fields {
         let field_name = field.ident.as_ref().expect("Field must have a name");
 
-        for attr in &field.attrs {
-            if attr.path().is_ident("tap") {
-                let _ = attr.parse_nested_meta(|meta| {
-                    if meta.path.is_ident("participant") {
-                        // Check if the field type is Option<Participant>
-                        if is_optional_type(&field.ty) {
-                            field_info
-                                .optional_participant_fields
-                                .push(field_name.clone());
-                        } else {
-                            field_info.participant_fields.push(field_name.clone());
-                        }
-                    } else if meta.path.is_ident("participant_list") {
-                        field_info.participant_list_fields.push(field_name.clone());
-                    } else if meta.path.is_ident("transaction_id") {
-                        // Check if the field type is Option<String>
-                        if is_optional_type(&field.ty) {
-                            field_info.optional_transaction_id_field = Some(field_name.clone());
-                        } else {
-                            field_info.transaction_id_field = Some(field_name.clone());
-                        }
-                    } else if meta.path.is_ident("optional_transaction_id") {
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("tap"))
+        {
+            let result = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("participant") {
+                    // Check if the field type is Option<Participant>
+                    if is_optional_type(&field.ty) {
+                        field_info
+                            .optional_participant_fields
+                            .push(field_name.clone());
+                    } else {
+                        field_info.participant_fields.push(field_name.clone());
+                    }
+                } else if meta.path.is_ident("participant_list") {
+                    field_info.participant_list_fields.push(field_name.clone());
+                } else if meta.path.is_ident("transaction_id")
+                    || meta.path.is_ident("optional_transaction_id")
+                {
+                    if field_info.transaction_id_field.is_some()
+                        || field_info.optional_transaction_id_field.is_some()
+                    {
+                        return Err(meta.error("only one field can be the transaction_id"));
+                    }
+                    // Check if the field type is Option<String>
+                    if meta.path.is_ident("optional_transaction_id") || is_optional_type(&field.ty)
+                    {
                         field_info.optional_transaction_id_field = Some(field_name.clone());
-                    } else if meta.path.is_ident("thread_id") {
-                        // Check if the field type is Option<String>
-                        if is_optional_type(&field.ty) {
-                            field_info.optional_thread_id_field = Some(field_name.clone());
-                        } else {
-                            field_info.thread_id_field = Some(field_name.clone());
-                        }
-                    } else if meta.path.is_ident("connection_id") {
-                        field_info.connection_id_field = Some(field_name.clone());
-                    } else if meta.path.is_ident("generated_id") {
-                        field_info.has_generated_id = true;
+                    } else {
+                        field_info.transaction_id_field = Some(field_name.clone());
+                    }
+                } else if meta.path.is_ident("thread_id") {
+                    if field_info.thread_id_field.is_some()
+                        || field_info.optional_thread_id_field.is_some()
+                    {
+                        return Err(meta.error("only one field can be the thread_id"));
+                    }
+                    // Check if the field type is Option<String>
+                    if is_optional_type(&field.ty) {
+                        field_info.optional_thread_id_field = Some(field_name.clone());
+                    } else {
+                        field_info.thread_id_field = Some(field_name.clone());
                     }
-                    Ok(())
-                });
+                } else if meta.path.is_ident("connection_id") {
+                    if field_info.connection_id_field.is_some() {
+                        return Err(meta.error("only one field can be the connection_id"));
+                    }
+                    field_info.connection_id_field = Some(field_name.clone());
+                } else if meta.path.is_ident("generated_id") {
+                    field_info.has_generated_id = true;
+                } else {
+                    return Err(unknown_attribute(&meta.path, "field", STRUCT_ATTRIBUTES));
+                }
+                Ok(())
+            });
+            if let Err(error) = result {
+                push_error(&mut errors, error);
             }
         }
     }
 
-    field_info
+    // Generated trait implementations need the transaction they act on
+    let has_transaction_id = field_info.transaction_id_field.is_some()
+        || field_info.optional_transaction_id_field.is_some();
+    let has_thread_id =
+        field_info.thread_id_field.is_some() || field_info.optional_thread_id_field.is_some();
+    if let Some(span) = authorizable_span {
+        if !has_transaction_id && !has_thread_id {
+            push_error(
+                &mut errors,
+                syn::Error::new(
+                    span,
+                    "`authorizable` requires a #[tap(transaction_id)] or #[tap(thread_id)] field \
+                     identifying the transaction",
+                ),
+            );
+        }
+    }
+    if let Some(span) = transactable_span {
+        if field_info.is_initiator && !has_transaction_id {
+            push_error(
+                &mut errors,
+                syn::Error::new(
+                    span,
+                    "`transactable` on an `initiator` requires a #[tap(transaction_id)] field",
+                ),
+            );
+        } else if !field_info.is_initiator && !has_thread_id {
+            push_error(
+                &mut errors,
+                syn::Error::new(
+                    span,
+                    "`transactable` on a message that is not an `initiator` requires a \
+                     #[tap(thread_id)] field referencing the transaction",
+                ),
+            );
+        }
+    }
+
+    match errors {
+        Some(errors) => Err(errors),
+        None => Ok(field_info),
+    }
+}
+
+/// Add an error to those reported so far
+fn push_error(errors: &mut Option<syn::Error>, error: syn::Error) {
+    match errors {
+        Some(errors) => errors.combine(error),
+        None => *errors = Some(error),
+    }
+}
+
+/// Error for an attribute not accepted where it is used
+///
+/// `other_level` lists the attributes of the other level, to point out
+/// attributes used on the struct instead of a field or the reverse.
+fn unknown_attribute(path: &syn::Path, level: &str, other_level: &[&str]) -> syn::Error {
+    let name = path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect::<Vec<_>>()
+        .join("::");
+    let message = if other_level.contains(&name.as_str()) {
+        format!("`{}` cannot be used as a {} attribute", name, level)
+    } else {
+        format!("unknown tap attribute `{}`", name)
+    };
+    syn::Error::new_spanned(path, message)
+}
+
+/// Check that a message type is an absolute URI
+fn check_message_type(lit: &syn::LitStr) -> syn::Result<()> {
+    let value = lit.value();
+    let has_scheme = value.split_once(':').is_some_and(|(scheme, rest)| {
+        !rest.is_empty()
+            && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    });
+    if has_scheme {
+        Ok(())
+    } else {
+        Err(syn::Error::new(
+            lit.span(),
+            format!(
+                "message_type \"{}\" must be an absolute URI with a scheme, \
+                 e.g. \"https://tap.rsvp/schema/1.0#Transfer\"",
+                value
+            ),
+        ))
+    }
 }
 
 fn is_optional_type(ty: &syn::Type) -> bool {
@@ -814,7 +1007,7 @@ fn generate_to_didcomm_impl(field_info: &FieldInfo, is_internal: bool) -> TokenS
 
         // Create the PlainMessage
         Ok(#crate_path::didcomm::PlainMessage {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: #crate_path::id::new_id(),
             typ: "application/didcomm-plain+json".to_string(),
             type_: Self::message_type().to_string(),
             body: body_json,
@@ -831,36 +1024,82 @@ fn generate_to_didcomm_impl(field_info: &FieldInfo, is_internal: bool) -> TokenS
     }
 }
 
-fn impl_tap_message_body_only(input: &DeriveInput) -> TokenStream2 {
+fn impl_tap_message_body_only(input: &DeriveInput) -> syn::Result<TokenStream2> {
     let name = &input.ident;
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
-    let fields = match &input.data {
-        Data::Struct(data) => match &data.fields {
-            Fields::Named(fields) => &fields.named,
-            _ => panic!("TapMessageBody can only be derived for structs with named fields"),
-        },
-        _ => panic!("TapMessageBody can only be derived for structs"),
-    };
-
-    let field_info = analyze_fields(fields, &input.attrs);
+    let fields = named_fields(input, "TapMessageBody")?;
+    let field_info = analyze_fields(fields, &input.attrs)?;
 
     // Check if we're inside the tap-msg crate or external
     let is_internal = std::env::var("CARGO_CRATE_NAME").unwrap_or_default() == "tap_msg";
 
     // TapMessageBody can only be derived if message_type is specified
     if field_info.message_type.is_none() {
-        panic!("TapMessageBody derive macro requires #[tap(message_type = \"...\")] attribute");
+        return Err(syn::Error::new_spanned(
+            name,
+            "TapMessageBody derive macro requires #[tap(message_type = \"...\")] attribute",
+        ));
     }
 
-    impl_tap_message_body_trait(
+    let tap_message_body_impl = impl_tap_message_body_trait(
         name,
         &field_info,
         &impl_generics,
         &ty_generics,
         where_clause,
         is_internal,
-    )
+    );
+
+    let async_validate_impl = if field_info.async_validation {
+        impl_async_validate_trait(
+            name,
+            &impl_generics,
+            &ty_generics,
+            where_clause,
+            is_internal,
+        )
+    } else {
+        quote! {}
+    };
+
+    Ok(quote! {
+        #tap_message_body_impl
+        #async_validate_impl
+    })
+}
+
+fn impl_async_validate_trait(
+    name: &syn::Ident,
+    impl_generics: &syn::ImplGenerics,
+    ty_generics: &syn::TypeGenerics,
+    where_clause: Option<&syn::WhereClause>,
+    is_internal: bool,
+) -> TokenStream2 {
+    let crate_path = if is_internal {
+        quote! { crate }
+    } else {
+        quote! { ::tap_msg }
+    };
+
+    // Delegate to a validate_<struct_name_lowercase>_async method after the synchronous validation
+    let method_name = syn::Ident::new(
+        &format!("validate_{}_async", name.to_string().to_lowercase()),
+        name.span(),
+    );
+
+    quote! {
+        #[#crate_path::async_trait]
+        impl #impl_generics #crate_path::message::tap_message_trait::AsyncValidate for #name #ty_generics #where_clause {
+            async fn validate_async(
+                &self,
+                context: &dyn #crate_path::message::tap_message_trait::ValidationContext,
+            ) -> #crate_path::error::Result<()> {
+                <Self as #crate_path::message::tap_message_trait::TapMessageBody>::validate(self)?;
+                self.#method_name(context).await
+            }
+        }
+    }
 }
 
 fn impl_authorizable_trait(
@@ -924,6 +1163,7 @@ fn impl_authorizable_trait(
             fn reject(&self, creator_did: &str, reason: &str) -> #crate_path::didcomm::PlainMessage<#crate_path::message::Reject> {
                 let reject = #crate_path::message::Reject {
                     transaction_id: (#tx_id_access).to_string(),
+                    code: None,
                     reason: Some(reason.to_string()),
                 };
                 let original_message = self
@@ -975,6 +1215,7 @@ fn impl_transaction_trait(
                     transaction_id: (#tx_id_access).to_string(),
                     settlement_id: Some(settlement_id.to_string()),
                     amount: amount.map(|s| s.to_string()),
+                    settled_asset: None,
                 };
                 let original_message = self
                     .to_didcomm(creator_did)
@@ -1061,7 +1302,7 @@ fn impl_transaction_trait(
             ) -> #crate_path::didcomm::PlainMessage<#crate_path::message::UpdateParty> {
                 let update_party = #crate_path::message::UpdateParty {
                     transaction_id: (#tx_id_access).to_string(),
-                    party_type: party_type.to_string(),
+                    party_type: party_type.into(),
                     party,
                     context: None,
                 };
@@ -0,0 +1,7 @@
+//! Compile errors of the derive macros for invalid attributes
+
+#[test]
+fn ui() {
+    let tests = trybuild::TestCases::new();
+    tests.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,9 @@
+use tap_msg_derive::TapMessageBody;
+
+#[derive(TapMessageBody)]
+struct Example {
+    #[tap(thread_id)]
+    thread_id: String,
+}
+
+fn main() {}
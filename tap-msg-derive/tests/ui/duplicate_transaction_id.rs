@@ -0,0 +1,13 @@
+use tap_msg_derive::TapMessage;
+
+#[derive(TapMessage)]
+#[tap(message_type = "https://tap.rsvp/schema/1.0#Example", initiator)]
+struct Example {
+    #[tap(transaction_id)]
+    transaction_id: String,
+
+    #[tap(transaction_id)]
+    other_id: Option<String>,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use tap_msg_derive::TapMessage;
+
+#[derive(TapMessage)]
+#[tap(message_type = Example)]
+struct Example {
+    #[tap(thread_id)]
+    thread_id: String,
+}
+
+fn main() {}
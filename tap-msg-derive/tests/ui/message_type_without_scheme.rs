@@ -0,0 +1,10 @@
+use tap_msg_derive::TapMessage;
+
+#[derive(TapMessage)]
+#[tap(message_type = "tap.rsvp/schema/1.0#Example")]
+struct Example {
+    #[tap(thread_id)]
+    thread_id: String,
+}
+
+fn main() {}
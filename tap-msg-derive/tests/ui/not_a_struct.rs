@@ -0,0 +1,11 @@
+use tap_msg_derive::TapMessage;
+
+#[derive(TapMessage)]
+enum Example {
+    First,
+}
+
+#[derive(TapMessage)]
+struct Tuple(String);
+
+fn main() {}
@@ -0,0 +1,9 @@
+use tap_msg_derive::TapMessage;
+
+#[derive(TapMessage)]
+#[tap(message_type = "https://tap.rsvp/schema/1.0#Example", initiator, transactable)]
+struct Example {
+    amount: String,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use tap_msg_derive::TapMessage;
+
+#[derive(TapMessage)]
+#[tap(message_type = "https://tap.rsvp/schema/1.0#Example", transactable)]
+struct Example {
+    #[tap(transaction_id)]
+    transaction_id: String,
+}
+
+fn main() {}
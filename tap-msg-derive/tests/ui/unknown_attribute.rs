@@ -0,0 +1,13 @@
+use tap_msg_derive::TapMessage;
+
+#[derive(TapMessage)]
+#[tap(message_type = "https://tap.rsvp/schema/1.0#Example", participant)]
+struct Example {
+    #[tap(thread_id)]
+    thread_id: String,
+
+    #[tap(participants)]
+    agents: Vec<String>,
+}
+
+fn main() {}
@@ -0,0 +1,29 @@
+#![no_main]
+
+//! Parses raw bytes the way a receiving node handles an unpacked message:
+//! PlainMessage deserialization, TAP message typing and validation. Any
+//! panic is a bug; malformed input must surface as a `tap_msg::Error`.
+//!
+//! Seed with the negative corpus:
+//!
+//! ```sh
+//! cargo fuzz run fuzz_plain_message corpus/fuzz_plain_message ../tests/corpus/plain_messages
+//! ```
+
+use libfuzzer_sys::fuzz_target;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::tap_message_trait::TapMessage as _;
+use tap_msg::message::TapMessage;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(message) = serde_json::from_slice::<PlainMessage>(data) else {
+        return;
+    };
+    let _ = message.validate();
+    let _ = TapMessage::from_plain_message(&message);
+
+    // Round trip whatever parsed
+    if let Ok(serialized) = serde_json::to_vec(&message) {
+        let _ = serde_json::from_slice::<PlainMessage>(&serialized);
+    }
+});
@@ -1,141 +1,131 @@
 #![no_main]
 
+//! Builds a PlainMessage of a known TAP type around a fuzzed body, so the
+//! fuzzer spends its time in the per-type body deserializers and validators
+//! instead of the envelope fields.
+
 use arbitrary::Arbitrary;
 use libfuzzer_sys::fuzz_target;
-use tap_msg::message::{TapMessage, TapMessageType, Validate};
-use std::collections::HashMap;
-use std::str::FromStr;
-use tap_caip::{ChainId, AccountId, AssetId};
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::tap_message_trait::TapMessageBody;
+use tap_msg::message::TapMessage;
+
+const MESSAGE_TYPES: &[&str] = &[
+    "https://tap.rsvp/schema/1.0#AddAgents",
+    "https://tap.rsvp/schema/1.0#Authorize",
+    "https://tap.rsvp/schema/1.0#AuthorizationRequired",
+    "https://didcomm.org/basicmessage/2.0/message",
+    "https://tap.rsvp/schema/1.0#Cancel",
+    "https://tap.rsvp/schema/1.0#Capture",
+    "https://tap.rsvp/schema/1.0#ConfirmRelationship",
+    "https://tap.rsvp/schema/1.0#Connect",
+    "https://didcomm.org/present-proof/3.0/presentation",
+    "https://tap.rsvp/schema/1.0#Error",
+    "https://tap.rsvp/schema/1.0#Lock",
+    "https://tap.rsvp/schema/1.0#RFQ",
+    "https://tap.rsvp/schema/1.0#OutOfBand",
+    "https://tap.rsvp/schema/1.0#Payment",
+    "https://tap.rsvp/schema/1.0#Quote",
+    "https://tap.rsvp/schema/1.0#Presentation",
+    "https://tap.rsvp/schema/1.0#Reject",
+    "https://tap.rsvp/schema/1.0#RemoveAgent",
+    "https://tap.rsvp/schema/1.0#ReplaceAgent",
+    "https://tap.rsvp/schema/1.0#RequestPresentation",
+    "https://tap.rsvp/schema/1.0#Revert",
+    "https://tap.rsvp/schema/1.0#Settle",
+    "https://tap.rsvp/schema/1.0#Transfer",
+    "https://didcomm.org/trust-ping/2.0/ping",
+    "https://didcomm.org/trust-ping/2.0/ping-response",
+    "https://didcomm.org/report-problem/2.0/problem-report",
+    "https://didcomm.org/discover-features/2.0/queries",
+    "https://didcomm.org/discover-features/2.0/disclose",
+    "https://tap.rsvp/schema/1.0#UpdateParty",
+    "https://tap.rsvp/schema/1.0#UpdatePolicies",
+];
 
-// Define a custom structure for arbitrary generation
 #[derive(Arbitrary, Debug)]
 struct FuzzTapMessage {
     message_type_index: u8,
-    id: String,
-    version: String,
-    created_time: String,
-    expires_time: Option<String>,
-    has_body: bool,
+    use_body_type: bool,
+    from: String,
+    thid: Option<String>,
+    created_time: Option<u64>,
+    expires_time: Option<u64>,
     body_json: String,
-    has_attachments: bool,
-    metadata_keys: Vec<String>,
-    metadata_values: Vec<String>,
-    // CAIP-related fields for transaction proposals
-    chain_id_str: String,
-    sender_address: String,
-    recipient_address: String,
-    asset_id_str: String,
-    amount: String,
 }
 
-// Map arbitrary data to a TapMessage
-fn create_tap_message(data: FuzzTapMessage) -> TapMessage {
-    // Map the arbitrary index to a TapMessageType
-    let message_type = match data.message_type_index % 5 {
-        0 => TapMessageType::TransactionProposal,
-        1 => TapMessageType::IdentityExchange,
-        2 => TapMessageType::TravelRuleInfo,
-        3 => TapMessageType::AuthorizationResponse,
-        _ => TapMessageType::Error,
+fn validate(message: &TapMessage) {
+    let _ = match message {
+        TapMessage::AddAgents(body) => body.validate(),
+        TapMessage::Authorize(body) => body.validate(),
+        TapMessage::AuthorizationRequired(body) => body.validate(),
+        TapMessage::BasicMessage(body) => body.validate(),
+        TapMessage::Cancel(body) => body.validate(),
+        TapMessage::Capture(body) => body.validate(),
+        TapMessage::ConfirmRelationship(body) => body.validate(),
+        TapMessage::Connect(body) => body.validate(),
+        TapMessage::DIDCommPresentation(body) => body.validate(),
+        TapMessage::Error(body) => body.validate(),
+        TapMessage::Lock(body) => body.validate(),
+        TapMessage::Rfq(body) => body.validate(),
+        TapMessage::OutOfBand(body) => body.validate(),
+        TapMessage::Payment(body) => body.validate(),
+        TapMessage::Quote(body) => body.validate(),
+        TapMessage::Presentation(body) => body.validate(),
+        TapMessage::Reject(body) => body.validate(),
+        TapMessage::RemoveAgent(body) => body.validate(),
+        TapMessage::ReplaceAgent(body) => body.validate(),
+        TapMessage::RequestPresentation(body) => body.validate(),
+        TapMessage::Revert(body) => body.validate(),
+        TapMessage::Settle(body) => body.validate(),
+        TapMessage::Transfer(body) => body.validate(),
+        TapMessage::TrustPing(body) => body.validate(),
+        TapMessage::TrustPingResponse(body) => body.validate(),
+        TapMessage::UpdateParty(body) => body.validate(),
+        TapMessage::UpdatePolicies(body) => body.validate(),
+        TapMessage::ProblemReport(body) => body.validate(),
+        TapMessage::DiscoverFeaturesQueries(body) => body.validate(),
+        TapMessage::DiscoverFeaturesDisclose(body) => body.validate(),
     };
+}
 
-    // Start with a basic message
-    let mut message = TapMessage {
-        message_type,
-        id: data.id,
-        version: data.version,
-        created_time: data.created_time,
-        expires_time: data.expires_time,
-        body: None,
-        attachments: None,
-        metadata: HashMap::new(),
-        from_did: None,
-        to_did: None,
+fuzz_target!(|data: FuzzTapMessage| {
+    let Ok(mut body) = serde_json::from_str::<serde_json::Value>(&data.body_json) else {
+        return;
     };
+    let message_type =
+        MESSAGE_TYPES[data.message_type_index as usize % MESSAGE_TYPES.len()].to_string();
 
-    // Try to add a body if required
-    if data.has_body {
-        // For TransactionProposal, try to create a valid CAIP-formatted body
-        if let TapMessageType::TransactionProposal = message.message_type {
-            // Attempt to create valid CAIP identifiers
-            let chain_id = ChainId::from_str(&format!("ethereum:{}", data.chain_id_str.chars().take(5).collect::<String>()));
-            let sender = AccountId::from_str(&format!("ethereum:{}:0x{}", 
-                data.chain_id_str.chars().take(5).collect::<String>(), 
-                data.sender_address.chars().take(40).collect::<String>()));
-            let recipient = AccountId::from_str(&format!("ethereum:{}:0x{}", 
-                data.chain_id_str.chars().take(5).collect::<String>(), 
-                data.recipient_address.chars().take(40).collect::<String>()));
-            let asset = AssetId::from_str(&format!("ethereum:{}/erc20:0x{}", 
-                data.chain_id_str.chars().take(5).collect::<String>(),
-                data.asset_id_str.chars().take(40).collect::<String>()));
-            
-            // If all identifiers are valid, create a transaction proposal body
-            if let (Ok(chain_id), Ok(sender), Ok(recipient), Ok(asset)) = (chain_id, sender, recipient, asset) {
-                let tx_proposal = serde_json::json!({
-                    "transaction_id": data.id,
-                    "network": chain_id.to_string(),
-                    "sender": sender.to_string(),
-                    "recipient": recipient.to_string(),
-                    "asset": asset.to_string(),
-                    "amount": data.amount,
-                    "memo": "Fuzz test transaction"
-                });
-                message.body = Some(tx_proposal);
-            } else {
-                // Fallback to the general approach if CAIP identifiers are invalid
-                if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(&data.body_json) {
-                    message.body = Some(json_value);
-                }
-            }
-        } else {
-            // For other message types, use the general approach
-            if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(&data.body_json) {
-                message.body = Some(json_value);
-            }
+    // The message type may also be carried in the body's @type field
+    let type_ = if data.use_body_type {
+        if let Some(object) = body.as_object_mut() {
+            object.insert("@type".to_string(), message_type.into());
         }
-    }
+        String::new()
+    } else {
+        message_type
+    };
 
-    // Try to add metadata
-    let metadata_count = std::cmp::min(data.metadata_keys.len(), data.metadata_values.len());
-    for i in 0..metadata_count {
-        if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(&data.metadata_values[i]) {
-            message.metadata.insert(data.metadata_keys[i].clone(), json_value);
-        }
-    }
+    let message = PlainMessage {
+        id: "fuzz".to_string(),
+        typ: "application/didcomm-plain+json".to_string(),
+        type_,
+        body,
+        from: data.from,
+        to: vec!["did:example:recipient".to_string()],
+        thid: data.thid,
+        pthid: None,
+        created_time: data.created_time,
+        expires_time: data.expires_time,
+        from_prior: None,
+        attachments: None,
+        extra_headers: Default::default(),
+    };
 
-    message
-}
+    if let Ok(tap_message) = TapMessage::from_plain_message(&message) {
+        validate(&tap_message);
 
-// The actual fuzz target
-fuzz_target!(|data: FuzzTapMessage| {
-    // Create a TAP message from fuzz data
-    let message = create_tap_message(data);
-    
-    // Try to validate the message (this is what we're testing)
-    let _ = message.validate();
-    
-    // Try to serialize and deserialize the message
-    if let Ok(serialized) = serde_json::to_string(&message) {
-        let _ = serde_json::from_str::<TapMessage>(&serialized);
-    }
-    
-    // If it's a transaction proposal, test CAIP validation specifically
-    if let TapMessageType::TransactionProposal = message.message_type {
-        if let Some(body) = &message.body {
-            // This shouldn't panic, but may fail depending on the generated data
-            if let Ok(serde_json::Value::String(network)) = body.get("network").map(|v| Ok(v.clone())).unwrap_or(Ok(serde_json::Value::Null)) {
-                if let Ok(serde_json::Value::String(sender)) = body.get("sender").map(|v| Ok(v.clone())).unwrap_or(Ok(serde_json::Value::Null)) {
-                    if let Ok(serde_json::Value::String(recipient)) = body.get("recipient").map(|v| Ok(v.clone())).unwrap_or(Ok(serde_json::Value::Null)) {
-                        if let Ok(serde_json::Value::String(asset)) = body.get("asset").map(|v| Ok(v.clone())).unwrap_or(Ok(serde_json::Value::Null)) {
-                            // Try to parse and validate CAIP identifiers
-                            let _ = ChainId::from_str(&network);
-                            let _ = AccountId::from_str(&sender);
-                            let _ = AccountId::from_str(&recipient);
-                            let _ = AssetId::from_str(&asset);
-                        }
-                    }
-                }
-            }
-        }
+        // Anything that parsed must serialize again
+        let _ = serde_json::to_value(&tap_message);
     }
 });
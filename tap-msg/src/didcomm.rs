@@ -155,7 +155,7 @@ where
     /// Create a new typed message
     pub fn new_typed(body: T, from: &str) -> Self {
         Self {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: crate::id::new_id(),
             typ: default_typ(),
             type_: T::message_type().to_string(),
             body,
@@ -255,7 +255,7 @@ where
         let participants = body.participant_dids();
 
         Self {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: crate::id::new_id(),
             typ: default_typ(),
             type_: T::message_type().to_string(),
             body,
@@ -66,6 +66,7 @@ pub fn process_transfer_message_example(message: &PlainMessage) -> Result<()> {
 pub fn create_reject_message_example(transaction_id: &str) -> Result<PlainMessage> {
     let reject_body = Reject {
         transaction_id: transaction_id.to_string(),
+        code: None,
         reason: "COMPLIANCE_FAILURE: Unable to comply with transfer requirements. Further documentation needed.".to_string(),
     };
 
@@ -83,6 +84,7 @@ pub fn create_settle_message_example(transaction_id: &str) -> Result<PlainMessag
         transaction_id: transaction_id.to_string(),
         settlement_id: "0x123456789abcdef".to_string(),
         amount: Some("100.0".to_string()),
+        settled_asset: None,
     };
 
     // Convert the Settle body to a DIDComm message
@@ -0,0 +1,169 @@
+//! Generation of message and transaction IDs
+//!
+//! Message IDs, and the transaction IDs of messages that start a
+//! transaction, are taken from an [`IdGenerator`] instead of calling
+//! `Uuid::new_v4()` directly. The generator is process-wide: it defaults to
+//! random UUID v4s and can be replaced with [`set_id_generator`], for example
+//! by UUID v7s or ULIDs, whose time-ordered IDs keep storage indexes local
+//! and sort log lines by creation time, or by a [`SequentialIdGenerator`]
+//! for deterministic IDs in tests.
+//!
+//! ```
+//! use tap_msg::id::{new_id, set_id_generator, IdStrategy};
+//!
+//! set_id_generator(IdStrategy::UuidV7.generator());
+//! let first = new_id();
+//! let second = new_id();
+//! assert!(first < second);
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// Source of unique IDs for messages and transactions
+pub trait IdGenerator: Send + Sync + fmt::Debug {
+    /// A new ID
+    fn generate(&self) -> String;
+}
+
+/// Random UUID v4s, the default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UuidV4Generator;
+
+impl IdGenerator for UuidV4Generator {
+    fn generate(&self) -> String {
+        uuid::Uuid::new_v4().to_string()
+    }
+}
+
+/// UUID v7s, ordered by the millisecond they were generated in
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UuidV7Generator;
+
+impl IdGenerator for UuidV7Generator {
+    fn generate(&self) -> String {
+        uuid::Uuid::now_v7().to_string()
+    }
+}
+
+/// ULIDs: a millisecond timestamp and 80 random bits in Crockford base32
+///
+/// IDs generated in the same millisecond are not ordered among each other.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UlidGenerator;
+
+const CROCKFORD_BASE32: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+impl IdGenerator for UlidGenerator {
+    fn generate(&self) -> String {
+        let millis = chrono::Utc::now().timestamp_millis().max(0) as u128 & ((1 << 48) - 1);
+        let random = rand::random::<u128>() & ((1 << 80) - 1);
+        let value = (millis << 80) | random;
+        (0..26)
+            .map(|i| CROCKFORD_BASE32[((value >> (125 - 5 * i)) & 31) as usize] as char)
+            .collect()
+    }
+}
+
+/// Deterministic IDs `<prefix>-1`, `<prefix>-2`, ... for tests
+#[derive(Debug)]
+pub struct SequentialIdGenerator {
+    prefix: String,
+    next: AtomicU64,
+}
+
+impl SequentialIdGenerator {
+    /// Create a generator whose first ID is `<prefix>-1`
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            next: AtomicU64::new(1),
+        }
+    }
+}
+
+impl IdGenerator for SequentialIdGenerator {
+    fn generate(&self) -> String {
+        format!(
+            "{}-{}",
+            self.prefix,
+            self.next.fetch_add(1, Ordering::Relaxed)
+        )
+    }
+}
+
+/// Named ID generation strategies, for configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdStrategy {
+    /// Random UUID v4s
+    #[default]
+    UuidV4,
+    /// Time-ordered UUID v7s
+    UuidV7,
+    /// Time-ordered ULIDs
+    Ulid,
+}
+
+impl IdStrategy {
+    /// A generator following this strategy
+    pub fn generator(self) -> Arc<dyn IdGenerator> {
+        match self {
+            IdStrategy::UuidV4 => Arc::new(UuidV4Generator),
+            IdStrategy::UuidV7 => Arc::new(UuidV7Generator),
+            IdStrategy::Ulid => Arc::new(UlidGenerator),
+        }
+    }
+}
+
+impl fmt::Display for IdStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdStrategy::UuidV4 => write!(f, "uuid-v4"),
+            IdStrategy::UuidV7 => write!(f, "uuid-v7"),
+            IdStrategy::Ulid => write!(f, "ulid"),
+        }
+    }
+}
+
+impl FromStr for IdStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "uuid-v4" | "uuidv4" | "v4" => Ok(IdStrategy::UuidV4),
+            "uuid-v7" | "uuidv7" | "v7" => Ok(IdStrategy::UuidV7),
+            "ulid" => Ok(IdStrategy::Ulid),
+            _ => Err(format!("Invalid ID strategy: {}", s)),
+        }
+    }
+}
+
+static ID_GENERATOR: RwLock<Option<Arc<dyn IdGenerator>>> = RwLock::new(None);
+
+/// Replace the process-wide ID generator
+pub fn set_id_generator(generator: Arc<dyn IdGenerator>) {
+    *ID_GENERATOR.write().unwrap_or_else(|e| e.into_inner()) = Some(generator);
+}
+
+/// The process-wide ID generator
+pub fn id_generator() -> Arc<dyn IdGenerator> {
+    ID_GENERATOR
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_else(|| Arc::new(UuidV4Generator))
+}
+
+/// A new ID from the process-wide ID generator
+pub fn new_id() -> String {
+    match ID_GENERATOR
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+    {
+        Some(generator) => generator.generate(),
+        None => UuidV4Generator.generate(),
+    }
+}
@@ -11,6 +11,7 @@
 // Internal modules
 pub mod didcomm;
 pub mod error;
+pub mod id;
 // pub mod examples; // Temporarily disabled during refactor
 pub mod message;
 pub mod settlement_address;
@@ -19,6 +20,10 @@ pub mod utils;
 // Re-export the derive macros from tap-msg-derive
 pub use tap_msg_derive::{TapMessage, TapMessageBody};
 
+// Used by code generated with #[tap(async_validation)]
+#[doc(hidden)]
+pub use async_trait::async_trait;
+
 // Re-export public types for easier access
 pub use didcomm::{
     Attachment, AttachmentData, Base64AttachmentData, JsonAttachmentData, LinksAttachmentData,
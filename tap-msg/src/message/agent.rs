@@ -8,6 +8,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use crate::message::policy::Policy;
+use crate::message::vocabulary::AgentRole;
 
 /// Common trait for TAP participants (agents and parties)
 pub trait TapParticipant {
@@ -89,7 +90,7 @@ pub struct Agent {
     /// Role of the agent in this transaction (optional).
     /// Examples: "SettlementAddress", "SourceAddress", etc.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub role: Option<String>,
+    pub role: Option<AgentRole>,
 
     /// DID or IRI of another Agent or Party that this agent acts on behalf of (REQUIRED per TAIP-5).
     /// Can be a single party or multiple parties.
@@ -118,7 +119,7 @@ impl Agent {
     pub fn new(id: &str, role: &str, for_party: &str) -> Self {
         Self {
             id: id.to_string(),
-            role: Some(role.to_string()),
+            role: Some(role.into()),
             for_parties: ForParties(vec![for_party.to_string()]),
             policies: None,
             metadata: HashMap::new(),
@@ -129,7 +130,7 @@ impl Agent {
     pub fn new_for_parties(id: &str, role: &str, for_parties: Vec<String>) -> Self {
         Self {
             id: id.to_string(),
-            role: Some(role.to_string()),
+            role: Some(role.into()),
             for_parties: ForParties(for_parties),
             policies: None,
             metadata: HashMap::new(),
@@ -156,7 +157,7 @@ impl Agent {
     ) -> Self {
         Self {
             id: id.to_string(),
-            role: Some(role.to_string()),
+            role: Some(role.into()),
             for_parties: ForParties(vec![for_party.to_string()]),
             policies: None,
             metadata,
@@ -194,9 +195,26 @@ impl Agent {
         self.metadata.get(key)
     }
 
+    /// Set the role of this agent.
+    pub fn with_role(mut self, role: AgentRole) -> Self {
+        self.role = Some(role);
+        self
+    }
+
     /// Check if this agent has a specific role.
-    pub fn has_role(&self, role: &str) -> bool {
-        self.role.as_ref().is_some_and(|r| r == role)
+    pub fn has_role(&self, role: impl Into<AgentRole>) -> bool {
+        self.role.as_ref().is_some_and(|r| *r == role.into())
+    }
+
+    /// Log warnings for the role and policy values of this agent that are
+    /// outside the known vocabularies.
+    pub fn warn_unknown_values(&self) {
+        if let Some(role) = &self.role {
+            role.warn_if_unknown();
+        }
+        for policy in self.policies.iter().flatten() {
+            policy.warn_unknown_values();
+        }
     }
 
     /// Check if this agent acts for a specific party.
@@ -325,9 +343,42 @@ impl Agent {
     pub fn service_url(&self) -> Option<&str> {
         self.metadata.get("serviceUrl").and_then(|v| v.as_str())
     }
+
+    /// Attach a verifiable credential, e.g. proof that the agent acts for a
+    /// licensed VASP.
+    ///
+    /// The credential is a JWT (compact JWS string) or a JSON-serialized JWS
+    /// signed by its issuer, and is kept in the `credentials` array.
+    pub fn with_credential(mut self, credential: serde_json::Value) -> Self {
+        match self.metadata.get_mut(CREDENTIALS_FIELD) {
+            Some(serde_json::Value::Array(credentials)) => credentials.push(credential),
+            _ => {
+                self.metadata.insert(
+                    CREDENTIALS_FIELD.to_string(),
+                    serde_json::Value::Array(vec![credential]),
+                );
+            }
+        }
+        self
+    }
+
+    /// Get the verifiable credentials attached to the agent.
+    pub fn credentials(&self) -> &[serde_json::Value] {
+        self.metadata
+            .get(CREDENTIALS_FIELD)
+            .and_then(|v| v.as_array())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
 }
 
+/// Metadata field holding the verifiable credentials of an agent.
+pub const CREDENTIALS_FIELD: &str = "credentials";
+
 /// Common agent roles used in TAP transactions.
+///
+/// These are the values of the known [`AgentRole`] variants, for code that
+/// still works with roles as strings.
 pub mod roles {
     /// Settlement address role for blockchain transactions.
     pub const SETTLEMENT_ADDRESS: &str = "SettlementAddress";
@@ -361,12 +412,28 @@ mod tests {
         let agent = Agent::new("did:web:example.com", "Exchange", "did:example:alice");
 
         assert_eq!(agent.id, "did:web:example.com");
-        assert_eq!(agent.role, Some("Exchange".to_string()));
+        assert_eq!(agent.role, Some(AgentRole::Exchange));
         assert_eq!(agent.for_parties.0, vec!["did:example:alice"]);
         assert!(agent.policies.is_none());
         assert!(agent.metadata.is_empty());
     }
 
+    #[test]
+    fn test_agent_credentials() {
+        let agent = Agent::new("did:web:example.com", "Exchange", "did:example:alice");
+        assert!(agent.credentials().is_empty());
+
+        let agent = agent
+            .with_credential(serde_json::json!("eyJhbGciOiJFZERTQSJ9.e30.c2ln"))
+            .with_credential(serde_json::json!({"payload": "e30", "signatures": []}));
+        assert_eq!(agent.credentials().len(), 2);
+
+        let json = serde_json::to_value(&agent).unwrap();
+        assert_eq!(json["credentials"][0], "eyJhbGciOiJFZERTQSJ9.e30.c2ln");
+        let parsed: Agent = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.credentials(), agent.credentials());
+    }
+
     #[test]
     fn test_agent_with_metadata() {
         let mut metadata = HashMap::new();
@@ -423,7 +490,7 @@ mod tests {
         let deserialized: Agent = serde_json::from_str(&json).unwrap();
 
         assert_eq!(agent, deserialized);
-        assert_eq!(deserialized.role, Some("SettlementAddress".to_string()));
+        assert_eq!(deserialized.role, Some(AgentRole::SettlementAddress));
         assert_eq!(deserialized.for_parties.0, vec!["did:example:alice"]);
     }
 
@@ -62,6 +62,7 @@ impl AddAgents {
             if agent.id().is_empty() {
                 return Err(Error::Validation("Agent ID cannot be empty".to_string()));
             }
+            agent.warn_unknown_values();
         }
 
         Ok(())
@@ -120,6 +121,7 @@ impl ReplaceAgent {
                 "Replacement agent ID is required in ReplaceAgent".to_string(),
             ));
         }
+        self.replacement.warn_unknown_values();
 
         Ok(())
     }
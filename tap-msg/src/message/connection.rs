@@ -9,6 +9,7 @@ use std::collections::HashMap;
 use crate::error::{Error, Result};
 use crate::message::agent::TapParticipant;
 use crate::message::tap_message_trait::{TapMessage as TapMessageTrait, TapMessageBody};
+use crate::message::vocabulary::{CategoryPurpose, Purpose};
 use crate::message::{Agent, Party};
 use crate::TapMessage;
 
@@ -121,11 +122,11 @@ pub struct TransactionLimits {
 pub struct ConnectionConstraints {
     /// Allowed TAIP-13 purpose codes.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub purposes: Option<Vec<String>>,
+    pub purposes: Option<Vec<Purpose>>,
 
     /// Allowed TAIP-13 category purpose codes.
     #[serde(rename = "categoryPurposes", skip_serializing_if = "Option::is_none")]
-    pub category_purposes: Option<Vec<String>>,
+    pub category_purposes: Option<Vec<CategoryPurpose>>,
 
     /// Transaction limits.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -150,6 +151,21 @@ pub struct ConnectionConstraints {
     pub allowed_assets: Option<Vec<String>>,
 }
 
+impl ConnectionConstraints {
+    /// Log warnings for the purpose codes that are outside the known
+    /// vocabularies.
+    pub fn warn_unknown_values(&self) {
+        self.purposes
+            .iter()
+            .flatten()
+            .for_each(Purpose::warn_if_unknown);
+        self.category_purposes
+            .iter()
+            .flatten()
+            .for_each(CategoryPurpose::warn_if_unknown);
+    }
+}
+
 /// Connect message body (TAIP-15).
 #[derive(Debug, Clone, Serialize, Deserialize, TapMessage)]
 #[tap(
@@ -306,6 +322,7 @@ impl Connect {
                     "Connection request must include constraints".to_string(),
                 ));
             }
+            self.warn_unknown_values();
             return Ok(());
         }
 
@@ -328,10 +345,20 @@ impl Connect {
                 "Connection request must include constraints".to_string(),
             ));
         }
+        self.warn_unknown_values();
 
         Ok(())
     }
 
+    /// Log warnings for the purpose codes and agent roles of the request
+    /// that are outside the known vocabularies
+    fn warn_unknown_values(&self) {
+        if let Some(constraints) = &self.constraints {
+            constraints.warn_unknown_values();
+        }
+        self.agents.iter().for_each(Agent::warn_unknown_values);
+    }
+
     /// Validation method that will be called by TapMessageBody trait
     pub fn validate(&self) -> Result<()> {
         self.validate_connect()
@@ -504,7 +531,7 @@ mod tests {
         let principal = Party::new("did:example:customer");
         let agent = Agent::new_without_role("did:example:b2b-service", "did:example:b2b-service");
         let constraints = ConnectionConstraints {
-            purposes: Some(vec!["BEXP".to_string()]),
+            purposes: Some(vec![Purpose::BusinessExpenses]),
             category_purposes: None,
             limits: Some(TransactionLimits {
                 per_transaction: Some("10000.00".to_string()),
@@ -541,7 +568,7 @@ mod tests {
         let principal = Party::new("did:example:customer");
         let agent = Agent::new_without_role("did:example:b2b-service", "did:example:b2b-service");
         let constraints = ConnectionConstraints {
-            purposes: Some(vec!["BEXP".to_string()]),
+            purposes: Some(vec![Purpose::BusinessExpenses]),
             category_purposes: None,
             limits: Some(TransactionLimits {
                 per_transaction: Some("10000.00".to_string()),
@@ -586,7 +613,7 @@ mod tests {
                 "did:example:service",
             )],
             constraints: Some(ConnectionConstraints {
-                purposes: Some(vec!["BEXP".to_string()]),
+                purposes: Some(vec![Purpose::BusinessExpenses]),
                 category_purposes: None,
                 limits: None,
                 allowed_beneficiaries: None,
@@ -617,7 +644,7 @@ mod tests {
             principal: Some(Party::new("did:example:customer")),
             agents: vec![],
             constraints: Some(ConnectionConstraints {
-                purposes: Some(vec!["BEXP".to_string()]),
+                purposes: Some(vec![Purpose::BusinessExpenses]),
                 category_purposes: None,
                 limits: None,
                 allowed_beneficiaries: None,
@@ -10,7 +10,7 @@ use crate::message::tap_message_trait::TapMessageBody;
 use crate::TapMessage;
 
 fn default_id() -> String {
-    uuid::Uuid::new_v4().to_string()
+    crate::id::new_id()
 }
 
 /// DIDComm Presentation message body.
@@ -99,7 +99,7 @@ impl TapMessageBody for DIDCommPresentation {
 
         // Create a new Message with required fields
         let message = crate::didcomm::PlainMessage {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: crate::id::new_id(),
             typ: "application/didcomm-plain+json".to_string(),
             type_: Self::message_type().to_string(),
             body: body_json,
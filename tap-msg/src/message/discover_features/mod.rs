@@ -0,0 +1,223 @@
+//! Discover Features Protocol Implementation
+//!
+//! Implementation of the DIDComm Discover Features 2.0 protocol as specified at:
+//! https://identity.foundation/didcomm-messaging/spec/#discover-features-protocol-20
+//!
+//! An agent sends queries for the protocols (or other features) it is
+//! interested in, and the recipient discloses those of its features that match.
+
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use tap_msg_derive::TapMessage;
+
+pub const DISCOVER_FEATURES_QUERIES_TYPE: &str =
+    "https://didcomm.org/discover-features/2.0/queries";
+pub const DISCOVER_FEATURES_DISCLOSE_TYPE: &str =
+    "https://didcomm.org/discover-features/2.0/disclose";
+
+/// Feature type of DIDComm protocols
+pub const PROTOCOL_FEATURE: &str = "protocol";
+
+/// A query for features of one type
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureQuery {
+    /// Type of the features queried, e.g. `protocol`
+    #[serde(rename = "feature-type")]
+    pub feature_type: String,
+
+    /// Feature identifier to match, where a trailing `*` matches any suffix
+    #[serde(rename = "match")]
+    pub pattern: String,
+}
+
+impl FeatureQuery {
+    /// Query for protocols whose identifier matches `pattern`
+    pub fn protocol(pattern: impl Into<String>) -> Self {
+        Self {
+            feature_type: PROTOCOL_FEATURE.to_string(),
+            pattern: pattern.into(),
+        }
+    }
+
+    /// Whether a feature matches this query
+    pub fn matches(&self, feature: &Disclosure) -> bool {
+        if feature.feature_type != self.feature_type {
+            return false;
+        }
+        match self.pattern.strip_suffix('*') {
+            Some(prefix) => feature.id.starts_with(prefix),
+            None => feature.id == self.pattern,
+        }
+    }
+}
+
+/// A feature disclosed in answer to a query
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Disclosure {
+    /// Type of the feature, e.g. `protocol`
+    #[serde(rename = "feature-type")]
+    pub feature_type: String,
+
+    /// Identifier of the feature, e.g. `https://didcomm.org/trust-ping/2.0`
+    pub id: String,
+
+    /// Roles the discloser plays in the protocol
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+}
+
+impl Disclosure {
+    /// Disclose a supported protocol
+    pub fn protocol(id: impl Into<String>) -> Self {
+        Self {
+            feature_type: PROTOCOL_FEATURE.to_string(),
+            id: id.into(),
+            roles: Vec::new(),
+        }
+    }
+}
+
+/// Discover Features queries message
+///
+/// Asks the recipient which of the queried features it supports.
+#[derive(Debug, Clone, Serialize, Deserialize, TapMessage)]
+#[tap(
+    message_type = "https://didcomm.org/discover-features/2.0/queries",
+    custom_validation
+)]
+pub struct DiscoverFeaturesQueries {
+    /// The queries, answered together
+    pub queries: Vec<FeatureQuery>,
+}
+
+/// Discover Features disclose message
+///
+/// Answers a queries message, in its thread, with the matching features.
+#[derive(Debug, Clone, Serialize, Deserialize, TapMessage)]
+#[tap(
+    message_type = "https://didcomm.org/discover-features/2.0/disclose",
+    custom_validation
+)]
+pub struct DiscoverFeaturesDisclose {
+    /// The supported features matching the queries
+    pub disclosures: Vec<Disclosure>,
+}
+
+impl DiscoverFeaturesQueries {
+    /// Create a queries message
+    pub fn new(queries: Vec<FeatureQuery>) -> Self {
+        Self { queries }
+    }
+
+    /// Disclose the features among `supported` that match any of the queries
+    pub fn disclose(&self, supported: &[Disclosure]) -> DiscoverFeaturesDisclose {
+        DiscoverFeaturesDisclose {
+            disclosures: supported
+                .iter()
+                .filter(|feature| self.queries.iter().any(|query| query.matches(feature)))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Custom validation for Discover Features queries
+    pub fn validate_discoverfeaturesqueries(&self) -> Result<()> {
+        if self.queries.is_empty() {
+            return Err(Error::Validation(
+                "Discover Features queries must include at least one query".to_string(),
+            ));
+        }
+        if self
+            .queries
+            .iter()
+            .any(|query| query.feature_type.is_empty() || query.pattern.is_empty())
+        {
+            return Err(Error::Validation(
+                "Discover Features queries require a feature type and a match".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl DiscoverFeaturesDisclose {
+    /// Custom validation for Discover Features disclosures
+    pub fn validate_discoverfeaturesdisclose(&self) -> Result<()> {
+        if self
+            .disclosures
+            .iter()
+            .any(|disclosure| disclosure.feature_type.is_empty() || disclosure.id.is_empty())
+        {
+            return Err(Error::Validation(
+                "Disclosed features require a feature type and an id".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::tap_message_trait::TapMessageBody;
+
+    #[test]
+    fn test_query_matching() {
+        let trust_ping = Disclosure::protocol("https://didcomm.org/trust-ping/2.0");
+        let tap = Disclosure::protocol("https://tap.rsvp/schema/1.0");
+
+        assert!(FeatureQuery::protocol("https://didcomm.org/*").matches(&trust_ping));
+        assert!(!FeatureQuery::protocol("https://didcomm.org/*").matches(&tap));
+        assert!(FeatureQuery::protocol("https://tap.rsvp/schema/1.0").matches(&tap));
+        assert!(FeatureQuery::protocol("*").matches(&tap));
+
+        let goal_code = FeatureQuery {
+            feature_type: "goal-code".to_string(),
+            pattern: "*".to_string(),
+        };
+        assert!(!goal_code.matches(&tap));
+    }
+
+    #[test]
+    fn test_disclose_matching_features() {
+        let supported = vec![
+            Disclosure::protocol("https://didcomm.org/trust-ping/2.0"),
+            Disclosure::protocol("https://tap.rsvp/schema/1.0"),
+        ];
+        let queries =
+            DiscoverFeaturesQueries::new(vec![FeatureQuery::protocol("https://tap.rsvp/*")]);
+
+        let disclose = queries.disclose(&supported);
+        assert_eq!(disclose.disclosures, vec![supported[1].clone()]);
+    }
+
+    #[test]
+    fn test_wire_format() {
+        let queries =
+            DiscoverFeaturesQueries::new(vec![FeatureQuery::protocol("https://didcomm.org/*")]);
+        let json = serde_json::to_value(&queries).unwrap();
+        assert_eq!(json["queries"][0]["feature-type"], "protocol");
+        assert_eq!(json["queries"][0]["match"], "https://didcomm.org/*");
+
+        let message = queries.to_didcomm("did:example:alice").unwrap();
+        assert_eq!(message.type_, DISCOVER_FEATURES_QUERIES_TYPE);
+        let parsed = DiscoverFeaturesQueries::from_didcomm(&message).unwrap();
+        assert_eq!(parsed.queries, queries.queries);
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(DiscoverFeaturesQueries::new(vec![]).validate().is_err());
+        assert!(
+            DiscoverFeaturesQueries::new(vec![FeatureQuery::protocol("*")])
+                .validate()
+                .is_ok()
+        );
+        let disclose = DiscoverFeaturesDisclose {
+            disclosures: vec![Disclosure::protocol("")],
+        };
+        assert!(disclose.validate().is_err());
+    }
+}
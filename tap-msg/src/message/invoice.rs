@@ -3,7 +3,9 @@
 //! This module defines the structured Invoice object that can be embedded
 //! in a TAIP-14 Payment Request message.
 
+use base64::Engine;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 
 /// Tax category for a line item or tax subtotal
@@ -213,6 +215,114 @@ pub struct DocumentReference {
     /// Optional URL where the document can be accessed
     #[serde(skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,
+
+    /// Optional MIME type of the document (e.g., "application/pdf")
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+
+    /// Optional hex-encoded SHA-256 digest of the document content
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+
+    /// Optional base64-encoded document content
+    #[serde(rename = "embeddedDocument", skip_serializing_if = "Option::is_none")]
+    pub embedded_document: Option<String>,
+}
+
+/// Hex-encoded SHA-256 digest of a document, as carried in [`DocumentReference::hash`]
+pub fn document_digest(content: &[u8]) -> String {
+    hex::encode(Sha256::digest(content))
+}
+
+impl DocumentReference {
+    /// Creates a reference without a location or content
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            document_type: None,
+            url: None,
+            mime_type: None,
+            hash: None,
+            embedded_document: None,
+        }
+    }
+
+    /// References a document hosted at `url`
+    ///
+    /// The digest of `content` is included so the receiver can check the
+    /// document it downloads is the one that was referenced.
+    pub fn external(id: impl Into<String>, url: impl Into<String>, content: &[u8]) -> Self {
+        Self {
+            url: Some(url.into()),
+            hash: Some(document_digest(content)),
+            ..Self::new(id)
+        }
+    }
+
+    /// Embeds a document, base64-encoded, along with its digest
+    pub fn embedded(id: impl Into<String>, mime_type: impl Into<String>, content: &[u8]) -> Self {
+        Self {
+            mime_type: Some(mime_type.into()),
+            hash: Some(document_digest(content)),
+            embedded_document: Some(base64::engine::general_purpose::STANDARD.encode(content)),
+            ..Self::new(id)
+        }
+    }
+
+    /// Sets the document type (e.g., "Contract", "Timesheet")
+    pub fn with_document_type(mut self, document_type: impl Into<String>) -> Self {
+        self.document_type = Some(document_type.into());
+        self
+    }
+
+    /// Sets the MIME type of the document
+    pub fn with_mime_type(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_type = Some(mime_type.into());
+        self
+    }
+
+    /// Check that `content` matches the digest of this reference
+    ///
+    /// Fails when the reference carries no digest, so unhashed documents are
+    /// never taken as verified.
+    pub fn verify(&self, content: &[u8]) -> crate::error::Result<()> {
+        use crate::error::Error;
+
+        let expected = self.hash.as_deref().ok_or_else(|| {
+            Error::Validation(format!("Document {} has no hash to verify", self.id))
+        })?;
+        let actual = document_digest(content);
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(Error::Validation(format!(
+                "Document {} hash mismatch: expected {}, got {}",
+                self.id, expected, actual
+            )));
+        }
+        Ok(())
+    }
+
+    /// Decode the embedded document, verifying it against the digest if one is given
+    ///
+    /// Returns `None` when the document is not embedded.
+    pub fn embedded_content(&self) -> crate::error::Result<Option<Vec<u8>>> {
+        use crate::error::Error;
+
+        let Some(encoded) = &self.embedded_document else {
+            return Ok(None);
+        };
+        let content = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(|e| {
+                Error::SerializationError(format!(
+                    "Document {} is not valid base64: {}",
+                    self.id, e
+                ))
+            })?;
+        if self.hash.is_some() {
+            self.verify(&content)?;
+        }
+        Ok(Some(content))
+    }
 }
 
 /// Invoice structure according to TAIP-16
@@ -14,6 +14,7 @@ use crate::error::{Error, Result};
 use crate::message::agent::Agent;
 use crate::message::party::Party;
 use crate::message::tap_message_trait::TapMessageBody;
+use crate::message::vocabulary::AgentRole;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
@@ -126,7 +127,7 @@ impl Lock {
     pub fn escrow_agent(&self) -> Option<&Agent> {
         self.agents
             .iter()
-            .find(|a| a.role == Some("EscrowAgent".to_string()))
+            .find(|a| a.has_role(AgentRole::EscrowAgent))
     }
 
     /// Find agents that can authorise release (agents acting `for` the beneficiary).
@@ -169,7 +170,7 @@ impl TapMessageBody for Lock {
         let escrow_agent_count = self
             .agents
             .iter()
-            .filter(|a| a.role == Some("EscrowAgent".to_string()))
+            .filter(|a| a.has_role(AgentRole::EscrowAgent))
             .count();
 
         if escrow_agent_count == 0 {
@@ -317,7 +318,7 @@ mod tests {
         assert!(lock.escrow_agent().is_some());
         assert_eq!(
             lock.escrow_agent().unwrap().role,
-            Some("EscrowAgent".to_string())
+            Some(AgentRole::EscrowAgent)
         );
     }
 
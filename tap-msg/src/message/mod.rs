@@ -12,6 +12,7 @@ pub mod cancel;
 pub mod connection;
 pub mod context;
 pub mod did_presentation;
+pub mod discover_features;
 pub mod error;
 pub mod invoice;
 pub mod lock;
@@ -19,6 +20,7 @@ pub mod party;
 pub mod payment;
 pub mod policy;
 pub mod presentation;
+pub mod problem_report;
 pub mod reject;
 pub mod relationship;
 pub mod revert;
@@ -27,10 +29,12 @@ pub mod settle;
 pub mod tap_message_enum;
 pub mod tap_message_trait;
 pub mod transfer;
+pub mod travel_rule_policy;
 pub mod trust_ping;
 pub mod update_party;
 pub mod update_policies;
 pub mod validation;
+pub mod vocabulary;
 
 // Re-export agent management types
 pub use agent_management::{AddAgents, RemoveAgent, ReplaceAgent};
@@ -56,6 +60,11 @@ pub use connection::{
 // Re-export DIDComm presentation types
 pub use did_presentation::DIDCommPresentation;
 
+// Re-export discover features types
+pub use discover_features::{
+    Disclosure, DiscoverFeaturesDisclose, DiscoverFeaturesQueries, FeatureQuery,
+};
+
 // Re-export error type
 pub use error::ErrorBody;
 
@@ -69,7 +78,8 @@ pub use rfq::{Exchange, Quote, Rfq};
 
 // Re-export invoice types
 pub use invoice::{
-    DocumentReference, Invoice, LineItem, OrderReference, TaxCategory, TaxSubtotal, TaxTotal,
+    document_digest, DocumentReference, Invoice, LineItem, OrderReference, TaxCategory,
+    TaxSubtotal, TaxTotal,
 };
 
 // Re-export agent types
@@ -87,8 +97,11 @@ pub use policy::{Policy, RequireAuthorization, RequirePresentation, RequireProof
 // Re-export presentation types
 pub use presentation::{Presentation, RequestPresentation};
 
+// Re-export problem report types
+pub use problem_report::{ProblemCode, ProblemReport, ProblemScope, ProblemSorter};
+
 // Re-export reject type
-pub use reject::Reject;
+pub use reject::{Reject, RejectionCode};
 
 // Re-export relationship type
 pub use relationship::ConfirmRelationship;
@@ -102,6 +115,9 @@ pub use settle::Settle;
 // Re-export transfer types
 pub use transfer::{TransactionValue, Transfer};
 
+// Re-export travel rule policy types
+pub use travel_rule_policy::{Ivms101Requirement, PolicyEvaluation, IVMS101_CONTEXT};
+
 // Re-export trust ping types
 pub use trust_ping::{TrustPing, TrustPingResponse};
 
@@ -111,10 +127,13 @@ pub use update_party::UpdateParty;
 // Re-export update policies type
 pub use update_policies::UpdatePolicies;
 
+// Re-export vocabulary types
+pub use vocabulary::{AgentRole, CategoryPurpose, Ivms101Field, PartyType, PolicyType, Purpose};
+
 // Re-export the TapMessage trait and related functionality
 pub use tap_message_trait::{
-    create_tap_message, typed_plain_message, Authorizable, Connectable,
-    TapMessage as TapMessageTrait, TapMessageBody, Transaction,
+    create_tap_message, typed_plain_message, AsyncValidate, Authorizable, Connectable,
+    TapMessage as TapMessageTrait, TapMessageBody, Transaction, ValidationContext,
 };
 
 // Re-export the TapMessage enum
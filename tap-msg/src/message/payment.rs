@@ -471,6 +471,8 @@ impl Payment {
             return Err(Error::Validation("Merchant ID is required".to_string()));
         }
 
+        self.agents.iter().for_each(Agent::warn_unknown_values);
+
         // Validate supported_assets if provided
         if let Some(supported_assets) = &self.supported_assets {
             if supported_assets.is_empty() {
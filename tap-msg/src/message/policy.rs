@@ -5,6 +5,8 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::message::vocabulary::{AgentRole, PartyType, PolicyType};
+
 /// FromType specifies who the policy applies to
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FromType {
@@ -14,11 +16,11 @@ pub enum FromType {
 
     /// Specific transaction roles
     #[serde(rename = "fromRole")]
-    FromRole(Vec<String>),
+    FromRole(Vec<PartyType>),
 
     /// Specific agent types
     #[serde(rename = "fromAgent")]
-    FromAgent(Vec<String>),
+    FromAgent(Vec<AgentRole>),
 }
 
 /// RequireAuthorization policy requires authorization from specific parties
@@ -30,11 +32,11 @@ pub struct RequireAuthorization {
 
     /// Optional list of roles this policy applies to
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub from_role: Option<Vec<String>>,
+    pub from_role: Option<Vec<PartyType>>,
 
     /// Optional list of agent types this policy applies to
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub from_agent: Option<Vec<String>>,
+    pub from_agent: Option<Vec<AgentRole>>,
 
     /// Optional human-readable purpose for this requirement
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -54,11 +56,11 @@ pub struct RequirePresentation {
 
     /// Optional list of roles this policy applies to
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub from_role: Option<Vec<String>>,
+    pub from_role: Option<Vec<PartyType>>,
 
     /// Optional list of agent types this policy applies to
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub from_agent: Option<Vec<String>>,
+    pub from_agent: Option<Vec<AgentRole>>,
 
     /// Party the presentation should be about
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -90,11 +92,11 @@ pub struct RequireProofOfControl {
 
     /// Optional list of roles this policy applies to
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub from_role: Option<Vec<String>>,
+    pub from_role: Option<Vec<PartyType>>,
 
     /// Optional list of agent types this policy applies to
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub from_agent: Option<Vec<String>>,
+    pub from_agent: Option<Vec<AgentRole>>,
 
     /// ID of the account or address that needs to be proven
     #[serde(default)]
@@ -110,7 +112,7 @@ pub struct RequireProofOfControl {
 pub struct RequireRelationshipConfirmation {
     /// Optional list of roles this policy applies to
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub from_role: Option<String>,
+    pub from_role: Option<PartyType>,
 
     /// Optional human-readable purpose for this requirement
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -139,6 +141,37 @@ pub enum Policy {
 }
 
 impl Policy {
+    /// The type of the policy, as in its `@type`
+    pub fn policy_type(&self) -> PolicyType {
+        match self {
+            Policy::RequireAuthorization(_) => PolicyType::RequireAuthorization,
+            Policy::RequirePresentation(_) => PolicyType::RequirePresentation,
+            Policy::RequireProofOfControl(_) => PolicyType::RequireProofOfControl,
+            Policy::RequireRelationshipConfirmation(_) => {
+                PolicyType::RequireRelationshipConfirmation
+            }
+        }
+    }
+
+    /// Log warnings for the role values of the policy that are outside the
+    /// known vocabularies
+    pub fn warn_unknown_values(&self) {
+        let (from_role, from_agent) = match self {
+            Policy::RequireAuthorization(p) => (p.from_role.as_deref(), p.from_agent.as_deref()),
+            Policy::RequirePresentation(p) => (p.from_role.as_deref(), p.from_agent.as_deref()),
+            Policy::RequireProofOfControl(p) => (p.from_role.as_deref(), p.from_agent.as_deref()),
+            Policy::RequireRelationshipConfirmation(p) => (Some(p.from_role.as_slice()), None),
+        };
+        from_role
+            .unwrap_or_default()
+            .iter()
+            .for_each(PartyType::warn_if_unknown);
+        from_agent
+            .unwrap_or_default()
+            .iter()
+            .for_each(AgentRole::warn_if_unknown);
+    }
+
     /// Validates the policy based on its specific type
     pub fn validate(&self) -> crate::error::Result<()> {
         // Basic validation logic for policies
@@ -73,7 +73,7 @@ impl Presentation {
             challenge,
             credentials,
             transaction_id,
-            id: uuid::Uuid::new_v4().to_string(),
+            id: crate::id::new_id(),
             metadata: HashMap::new(),
         }
     }
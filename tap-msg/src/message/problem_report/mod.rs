@@ -0,0 +1,339 @@
+//! Problem Report Protocol Implementation
+//!
+//! Implementation of the DIDComm Report Problem 2.0 protocol as specified at:
+//! https://identity.foundation/didcomm-messaging/spec/#problem-reports
+//!
+//! A problem report tells a counterparty that one of its messages could not
+//! be transported or processed. Unlike TAP's own [`ErrorBody`], it is
+//! understood by any DIDComm agent. The report belongs to the thread of the
+//! problematic message through its `pthid` header, and its code classifies
+//! the problem as `<sorter>.<scope>.<descriptors>`, e.g. `e.p.xfer.cant-use-endpoint`.
+//!
+//! [`ErrorBody`]: crate::message::ErrorBody
+
+use crate::didcomm::PlainMessage;
+use crate::error::{Error, Result};
+use crate::message::tap_message_trait::TapMessageBody;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use tap_msg_derive::TapMessage;
+
+pub const PROBLEM_REPORT_TYPE: &str = "https://didcomm.org/report-problem/2.0/problem-report";
+
+/// Standard problem code descriptors defined by the DIDComm specification
+pub mod descriptors {
+    /// Failed to achieve required trust
+    pub const TRUST: &str = "trust";
+    /// Cryptographic operation failed
+    pub const TRUST_CRYPTO: &str = "trust.crypto";
+    /// Unable to transport data
+    pub const XFER: &str = "xfer";
+    /// DID is unusable
+    pub const DID: &str = "did";
+    /// Bad message
+    pub const MSG: &str = "msg";
+    /// Internal error of the reporting agent
+    pub const ME: &str = "me";
+    /// A required resource is inadequate or unavailable
+    pub const ME_RES: &str = "me.res";
+    /// Circumstances don't satisfy requirements
+    pub const REQ: &str = "req";
+    /// Failed to satisfy timing constraints
+    pub const REQ_TIME: &str = "req.time";
+    /// Failed for legal reasons
+    pub const LEGAL: &str = "legal";
+}
+
+/// Whether a problem is an error or a warning
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProblemSorter {
+    /// The protocol or message failed
+    Error,
+    /// The problem is worth noting but processing may continue
+    Warning,
+}
+
+/// The extent of a problem
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ProblemScope {
+    /// The whole protocol instance failed
+    Protocol,
+    /// Only the offending message failed
+    Message,
+    /// The protocol reverts to the named state
+    State(String),
+}
+
+/// A parsed problem code such as `e.p.xfer.cant-use-endpoint`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProblemCode {
+    /// Error or warning
+    pub sorter: ProblemSorter,
+    /// Extent of the problem
+    pub scope: ProblemScope,
+    /// Dot-separated descriptors, most general first, e.g. `trust.crypto`
+    pub descriptor: String,
+}
+
+impl ProblemCode {
+    /// Creates an error code
+    pub fn error(scope: ProblemScope, descriptor: &str) -> Self {
+        Self {
+            sorter: ProblemSorter::Error,
+            scope,
+            descriptor: descriptor.to_string(),
+        }
+    }
+
+    /// Creates a warning code
+    pub fn warning(scope: ProblemScope, descriptor: &str) -> Self {
+        Self {
+            sorter: ProblemSorter::Warning,
+            scope,
+            descriptor: descriptor.to_string(),
+        }
+    }
+
+    /// Whether the code reports an error rather than a warning
+    pub fn is_error(&self) -> bool {
+        self.sorter == ProblemSorter::Error
+    }
+
+    /// Whether the descriptor is `descriptor` or one of its refinements
+    ///
+    /// `e.m.trust.crypto` has the descriptor `trust` as well as `trust.crypto`.
+    pub fn has_descriptor(&self, descriptor: &str) -> bool {
+        self.descriptor == descriptor
+            || self
+                .descriptor
+                .strip_prefix(descriptor)
+                .is_some_and(|rest| rest.starts_with('.'))
+    }
+}
+
+impl fmt::Display for ProblemCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sorter = match self.sorter {
+            ProblemSorter::Error => "e",
+            ProblemSorter::Warning => "w",
+        };
+        let scope = match &self.scope {
+            ProblemScope::Protocol => "p",
+            ProblemScope::Message => "m",
+            ProblemScope::State(state) => state,
+        };
+        write!(f, "{}.{}.{}", sorter, scope, self.descriptor)
+    }
+}
+
+impl FromStr for ProblemCode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::Validation(format!("Invalid problem code: {}", s));
+
+        let tokens: Vec<&str> = s.split('.').collect();
+        if tokens.len() < 3 || !tokens.iter().all(|token| is_kebab_token(token)) {
+            return Err(invalid());
+        }
+
+        let sorter = match tokens[0] {
+            "e" => ProblemSorter::Error,
+            "w" => ProblemSorter::Warning,
+            _ => return Err(invalid()),
+        };
+        let scope = match tokens[1] {
+            "p" => ProblemScope::Protocol,
+            "m" => ProblemScope::Message,
+            state => ProblemScope::State(state.to_string()),
+        };
+
+        Ok(Self {
+            sorter,
+            scope,
+            descriptor: tokens[2..].join("."),
+        })
+    }
+}
+
+/// Lowercase kebab-case token, as used by each part of a problem code
+fn is_kebab_token(token: &str) -> bool {
+    !token.is_empty()
+        && token
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// Problem Report message
+///
+/// Sent in reply to a message that could not be transported or processed.
+/// Use [`ProblemReport::to_reply`] to correlate it with that message.
+#[derive(Debug, Clone, Serialize, Deserialize, TapMessage)]
+#[tap(
+    message_type = "https://didcomm.org/report-problem/2.0/problem-report",
+    custom_validation
+)]
+pub struct ProblemReport {
+    /// Problem code, e.g. `e.p.xfer.cant-use-endpoint`
+    pub code: String,
+
+    /// Human-readable description, which may reference `args` as `{1}`, `{2}`, ...
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+
+    /// Values substituted into the comment
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+
+    /// URI where a human can be contacted about the problem
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub escalate_to: Option<String>,
+}
+
+impl ProblemReport {
+    /// Create a new Problem Report
+    pub fn new(code: ProblemCode, comment: &str) -> Self {
+        Self {
+            code: code.to_string(),
+            comment: Some(comment.to_string()),
+            args: Vec::new(),
+            escalate_to: None,
+        }
+    }
+
+    /// Set the values substituted into the comment
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Set where a human can be contacted about the problem
+    pub fn with_escalation(mut self, escalate_to: &str) -> Self {
+        self.escalate_to = Some(escalate_to.to_string());
+        self
+    }
+
+    /// Parse the problem code
+    pub fn problem_code(&self) -> Result<ProblemCode> {
+        self.code.parse()
+    }
+
+    /// The comment with `{1}`, `{2}`, ... replaced by the corresponding args
+    pub fn interpolated_comment(&self) -> Option<String> {
+        let comment = self.comment.as_ref()?;
+        let mut interpolated = comment.clone();
+        // Replace higher indices first so {1} does not clobber {10}
+        for (i, arg) in self.args.iter().enumerate().rev() {
+            interpolated = interpolated.replace(&format!("{{{}}}", i + 1), arg);
+        }
+        Some(interpolated)
+    }
+
+    /// Build the report as a reply to the problematic message
+    ///
+    /// The reply is addressed to the sender of `original`, its `pthid` is the
+    /// thread of `original` (or its ID when it starts a thread), and its
+    /// `ack` header lists the ID of `original`.
+    pub fn to_reply(&self, original: &PlainMessage, from: &str) -> Result<PlainMessage> {
+        let mut reply = self.to_didcomm_with_route(from, [original.from.as_str()])?;
+        reply.thid = None;
+        reply.pthid = Some(original.thid.clone().unwrap_or_else(|| original.id.clone()));
+        reply.extra_headers.insert(
+            "ack".to_string(),
+            serde_json::Value::Array(vec![serde_json::Value::String(original.id.clone())]),
+        );
+        Ok(reply)
+    }
+
+    /// Custom validation for Problem Report messages
+    pub fn validate_problemreport(&self) -> Result<()> {
+        self.problem_code()?;
+
+        if let Some(ref escalate_to) = self.escalate_to {
+            if !escalate_to.contains(':') {
+                return Err(Error::Validation(format!(
+                    "escalate_to must be a URI: {}",
+                    escalate_to
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_problem_code_roundtrip() {
+        let code: ProblemCode = "e.p.xfer.cant-use-endpoint".parse().unwrap();
+        assert!(code.is_error());
+        assert_eq!(code.scope, ProblemScope::Protocol);
+        assert_eq!(code.descriptor, "xfer.cant-use-endpoint");
+        assert!(code.has_descriptor(descriptors::XFER));
+        assert!(!code.has_descriptor("xf"));
+        assert_eq!(code.to_string(), "e.p.xfer.cant-use-endpoint");
+
+        let code: ProblemCode = "w.awaiting-approval.req.time".parse().unwrap();
+        assert!(!code.is_error());
+        assert_eq!(
+            code.scope,
+            ProblemScope::State("awaiting-approval".to_string())
+        );
+        assert!(code.has_descriptor(descriptors::REQ_TIME));
+
+        for invalid in ["", "e.p", "x.p.msg", "e.p.Msg", "e..msg", "e.p.msg."] {
+            assert!(invalid.parse::<ProblemCode>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_interpolated_comment() {
+        let report = ProblemReport::new(
+            ProblemCode::error(ProblemScope::Protocol, "xfer.cant-use-endpoint"),
+            "Unable to use the {1} endpoint for {2}.",
+        )
+        .with_args(vec![
+            "https://agents.example/inbox".to_string(),
+            "did:example:alice".to_string(),
+        ]);
+        assert_eq!(
+            report.interpolated_comment().unwrap(),
+            "Unable to use the https://agents.example/inbox endpoint for did:example:alice."
+        );
+    }
+
+    #[test]
+    fn test_reply_is_correlated_with_original_thread() {
+        let original = PlainMessage::new(
+            "msg-1".to_string(),
+            "https://tap.rsvp/schema/1.0#Authorize".to_string(),
+            serde_json::json!({"transaction_id": "tx-1"}),
+            "did:example:alice".to_string(),
+        )
+        .with_recipient("did:example:bob")
+        .with_thread_id(Some("tx-1".to_string()));
+
+        let report = ProblemReport::new(
+            ProblemCode::error(ProblemScope::Message, descriptors::MSG),
+            "Unknown transaction",
+        )
+        .with_escalation("mailto:compliance@bob.example");
+        let reply = report.to_reply(&original, "did:example:bob").unwrap();
+
+        assert_eq!(reply.type_, PROBLEM_REPORT_TYPE);
+        assert_eq!(reply.from, "did:example:bob");
+        assert_eq!(reply.to, vec!["did:example:alice"]);
+        assert_eq!(reply.thid, None);
+        assert_eq!(reply.pthid.as_deref(), Some("tx-1"));
+        assert_eq!(reply.extra_headers["ack"], serde_json::json!(["msg-1"]));
+
+        let parsed = ProblemReport::from_didcomm(&reply).unwrap();
+        assert_eq!(parsed.code, "e.m.msg");
+        assert!(parsed.validate().is_ok());
+        assert!(parsed.with_escalation("compliance").validate().is_err());
+    }
+}
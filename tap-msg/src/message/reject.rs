@@ -4,10 +4,78 @@
 //! for rejecting transactions in the TAP protocol.
 
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 
 use crate::error::{Error, Result};
 use crate::TapMessage;
 
+/// Standardized reason code of a Reject message.
+///
+/// Codes let the receiving agent act on a rejection, and aggregate
+/// rejections, without parsing the free-text reason.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectionCode {
+    /// The transaction violates a policy of the rejecting agent.
+    Policy,
+    /// A party or address matched a sanctions screening.
+    Sanctions,
+    /// Travel Rule data required by the rejecting agent was not provided.
+    MissingTravelRuleData,
+    /// The customer declined the transaction.
+    CustomerDeclined,
+    /// The transaction is suspected to be fraudulent.
+    Fraud,
+    /// The asset or settlement method is not supported.
+    UnsupportedAsset,
+    /// Any other reason, described in the free-text reason.
+    Other,
+}
+
+impl RejectionCode {
+    /// All rejection codes.
+    pub const ALL: [RejectionCode; 7] = [
+        RejectionCode::Policy,
+        RejectionCode::Sanctions,
+        RejectionCode::MissingTravelRuleData,
+        RejectionCode::CustomerDeclined,
+        RejectionCode::Fraud,
+        RejectionCode::UnsupportedAsset,
+        RejectionCode::Other,
+    ];
+
+    /// The code as it appears in the message body.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RejectionCode::Policy => "policy",
+            RejectionCode::Sanctions => "sanctions",
+            RejectionCode::MissingTravelRuleData => "missing_travel_rule_data",
+            RejectionCode::CustomerDeclined => "customer_declined",
+            RejectionCode::Fraud => "fraud",
+            RejectionCode::UnsupportedAsset => "unsupported_asset",
+            RejectionCode::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for RejectionCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RejectionCode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        RejectionCode::ALL
+            .into_iter()
+            .find(|code| code.as_str() == s)
+            .ok_or_else(|| Error::Validation(format!("Unknown rejection code: {}", s)))
+    }
+}
+
 /// Reject message body (TAIP-4).
 #[derive(Debug, Clone, Serialize, Deserialize, TapMessage)]
 #[tap(message_type = "https://tap.rsvp/schema/1.0#Reject")]
@@ -16,6 +84,10 @@ pub struct Reject {
     #[tap(thread_id)]
     pub transaction_id: String,
 
+    /// Standardized reason code.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub code: Option<RejectionCode>,
+
     /// Reason for rejection.
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub reason: Option<String>,
@@ -26,6 +98,16 @@ impl Reject {
     pub fn new(transaction_id: &str, reason: &str) -> Self {
         Self {
             transaction_id: transaction_id.to_string(),
+            code: None,
+            reason: Some(reason.to_string()),
+        }
+    }
+
+    /// Create a new Reject message with a standardized reason code
+    pub fn with_code(transaction_id: &str, code: RejectionCode, reason: &str) -> Self {
+        Self {
+            transaction_id: transaction_id.to_string(),
+            code: Some(code),
             reason: Some(reason.to_string()),
         }
     }
@@ -34,6 +116,7 @@ impl Reject {
     pub fn minimal(transaction_id: &str) -> Self {
         Self {
             transaction_id: transaction_id.to_string(),
+            code: None,
             reason: None,
         }
     }
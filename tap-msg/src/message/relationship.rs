@@ -4,6 +4,7 @@
 //! relationships between agents in the TAP protocol.
 
 use crate::error::{Error, Result};
+use crate::message::vocabulary::AgentRole;
 use crate::TapMessage;
 use serde::{Deserialize, Serialize};
 
@@ -28,7 +29,7 @@ pub struct ConfirmRelationship {
 
     /// The role of the agent (optional).
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub role: Option<String>,
+    pub role: Option<AgentRole>,
 }
 
 impl ConfirmRelationship {
@@ -43,8 +44,8 @@ impl ConfirmRelationship {
     }
 
     /// Add a role to the confirmation.
-    pub fn with_role(mut self, role: &str) -> Self {
-        self.role = Some(role.to_string());
+    pub fn with_role(mut self, role: impl Into<AgentRole>) -> Self {
+        self.role = Some(role.into());
         self
     }
 }
@@ -72,6 +73,10 @@ impl ConfirmRelationship {
             ));
         }
 
+        if let Some(role) = &self.role {
+            role.warn_if_unknown();
+        }
+
         Ok(())
     }
 }
@@ -6,6 +6,7 @@
 use crate::error::{Error, Result};
 use crate::TapMessage;
 use serde::{Deserialize, Serialize};
+use tap_caip::AssetId;
 
 /// Settle message body (TAIP-4).
 #[derive(Debug, Clone, Serialize, Deserialize, TapMessage)]
@@ -24,8 +25,18 @@ pub struct Settle {
     pub settlement_id: Option<String>,
 
     /// Optional amount settled. If specified, must be less than or equal to the original amount.
+    /// With `settled_asset`, the amount is in the settled asset.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub amount: Option<String>,
+
+    /// Asset the transaction was settled in (CAIP-19 asset ID or ISO 4217 currency code),
+    /// if it differs from the requested asset, e.g. USDC settling a Payment in USD.
+    #[serde(
+        rename = "settledAsset",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub settled_asset: Option<String>,
 }
 
 impl Settle {
@@ -35,6 +46,7 @@ impl Settle {
             transaction_id: transaction_id.to_string(),
             settlement_id: Some(settlement_id.to_string()),
             amount: None,
+            settled_asset: None,
         }
     }
 
@@ -44,6 +56,7 @@ impl Settle {
             transaction_id: transaction_id.to_string(),
             settlement_id: Some(settlement_id.to_string()),
             amount: Some(amount.to_string()),
+            settled_asset: None,
         }
     }
 
@@ -53,8 +66,16 @@ impl Settle {
             transaction_id: transaction_id.to_string(),
             settlement_id: None,
             amount: None,
+            settled_asset: None,
         }
     }
+
+    /// Settle in another asset than the requested one, `amount` being in that asset
+    pub fn with_settled_asset(mut self, settled_asset: &str, amount: &str) -> Self {
+        self.settled_asset = Some(settled_asset.to_string());
+        self.amount = Some(amount.to_string());
+        self
+    }
 }
 
 impl Settle {
@@ -131,6 +152,22 @@ impl Settle {
             }
         }
 
+        if let Some(settled_asset) = &self.settled_asset {
+            let is_currency_code =
+                settled_asset.len() == 3 && settled_asset.chars().all(|c| c.is_ascii_uppercase());
+            if !is_currency_code && settled_asset.parse::<AssetId>().is_err() {
+                return Err(Error::Validation(
+                    "Invalid format for 'settledAsset', CAIP-19 asset ID or ISO 4217 currency code expected"
+                        .to_string(),
+                ));
+            }
+            if self.amount.is_none() {
+                return Err(Error::Validation(
+                    "Amount is required when 'settledAsset' is provided".to_string(),
+                ));
+            }
+        }
+
         Ok(())
     }
 }
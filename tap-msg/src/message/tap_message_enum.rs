@@ -7,9 +7,10 @@ use crate::didcomm::PlainMessage;
 use crate::error::{Error, Result};
 use crate::message::{
     AddAgents, AuthorizationRequired, Authorize, BasicMessage, Cancel, Capture,
-    ConfirmRelationship, Connect, DIDCommPresentation, ErrorBody, Lock, OutOfBand, Payment,
-    Presentation, Quote, Reject, RemoveAgent, ReplaceAgent, RequestPresentation, Revert, Rfq,
-    Settle, Transfer, TrustPing, TrustPingResponse, UpdateParty, UpdatePolicies,
+    ConfirmRelationship, Connect, DIDCommPresentation, DiscoverFeaturesDisclose,
+    DiscoverFeaturesQueries, ErrorBody, Lock, OutOfBand, Payment, Presentation, ProblemReport,
+    Quote, Reject, RemoveAgent, ReplaceAgent, RequestPresentation, Revert, Rfq, Settle, Transfer,
+    TrustPing, TrustPingResponse, UpdateParty, UpdatePolicies,
 };
 use serde::{Deserialize, Serialize};
 
@@ -74,6 +75,12 @@ pub enum TapMessage {
     UpdateParty(UpdateParty),
     /// Update policies message (TAIP-7)
     UpdatePolicies(UpdatePolicies),
+    /// Problem Report message (DIDComm 2.0)
+    ProblemReport(ProblemReport),
+    /// Discover Features queries message (DIDComm 2.0)
+    DiscoverFeaturesQueries(DiscoverFeaturesQueries),
+    /// Discover Features disclose message (DIDComm 2.0)
+    DiscoverFeaturesDisclose(DiscoverFeaturesDisclose),
 }
 
 impl TapMessage {
@@ -300,6 +307,33 @@ impl TapMessage {
                     })?;
                 Ok(TapMessage::TrustPingResponse(msg))
             }
+            "https://didcomm.org/report-problem/2.0/problem-report" => {
+                let msg: ProblemReport =
+                    serde_json::from_value(plain_msg.body.clone()).map_err(|e| {
+                        Error::SerializationError(format!("Failed to parse ProblemReport: {}", e))
+                    })?;
+                Ok(TapMessage::ProblemReport(msg))
+            }
+            "https://didcomm.org/discover-features/2.0/queries" => {
+                let msg: DiscoverFeaturesQueries = serde_json::from_value(plain_msg.body.clone())
+                    .map_err(|e| {
+                    Error::SerializationError(format!(
+                        "Failed to parse DiscoverFeaturesQueries: {}",
+                        e
+                    ))
+                })?;
+                Ok(TapMessage::DiscoverFeaturesQueries(msg))
+            }
+            "https://didcomm.org/discover-features/2.0/disclose" => {
+                let msg: DiscoverFeaturesDisclose = serde_json::from_value(plain_msg.body.clone())
+                    .map_err(|e| {
+                        Error::SerializationError(format!(
+                            "Failed to parse DiscoverFeaturesDisclose: {}",
+                            e
+                        ))
+                    })?;
+                Ok(TapMessage::DiscoverFeaturesDisclose(msg))
+            }
             _ => Err(Error::Validation(format!(
                 "Unknown message type: {}",
                 message_type
@@ -341,6 +375,13 @@ impl TapMessage {
             TapMessage::TrustPingResponse(_) => "https://didcomm.org/trust-ping/2.0/ping-response",
             TapMessage::UpdateParty(_) => "https://tap.rsvp/schema/1.0#UpdateParty",
             TapMessage::UpdatePolicies(_) => "https://tap.rsvp/schema/1.0#UpdatePolicies",
+            TapMessage::ProblemReport(_) => "https://didcomm.org/report-problem/2.0/problem-report",
+            TapMessage::DiscoverFeaturesQueries(_) => {
+                "https://didcomm.org/discover-features/2.0/queries"
+            }
+            TapMessage::DiscoverFeaturesDisclose(_) => {
+                "https://didcomm.org/discover-features/2.0/disclose"
+            }
         }
     }
 }
@@ -10,6 +10,7 @@ use crate::message::{
     AddAgents, Agent, Authorize, Cancel, ConfirmRelationship, Party, Reject, RemoveAgent,
     ReplaceAgent, Revert, Settle, UpdateParty, UpdatePolicies,
 };
+use async_trait::async_trait;
 use chrono::Utc;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
@@ -40,7 +41,7 @@ pub trait TapMessageBody: Serialize + DeserializeOwned + Send + Sync {
         }
 
         // Create a unique ID for the message
-        let id = uuid::Uuid::new_v4().to_string();
+        let id = crate::id::new_id();
 
         // Get current timestamp in milliseconds since Unix epoch
         let now = Utc::now().timestamp_millis() as u64;
@@ -185,6 +186,35 @@ pub trait TapMessageBody: Serialize + DeserializeOwned + Send + Sync {
     }
 }
 
+/// Lookups available to [`AsyncValidate`], answered by the node validating the message.
+#[async_trait]
+pub trait ValidationContext: Send + Sync {
+    /// Check whether a transaction with the given ID is known.
+    async fn transaction_exists(&self, transaction_id: &str) -> Result<bool>;
+}
+
+/// Validation of a message body that needs storage or network lookups.
+///
+/// Generated by the `TapMessage` derive with `#[tap(async_validation)]`: the
+/// generated implementation runs [`TapMessageBody::validate`] first, then awaits
+/// an inherent `validate_<struct_name_lowercase>_async` method of the type:
+///
+/// ```ignore
+/// impl Receipt {
+///     async fn validate_receipt_async(&self, context: &dyn ValidationContext) -> Result<()> {
+///         if !context.transaction_exists(&self.transaction_id).await? {
+///             return Err(Error::Validation("Unknown transaction".to_string()));
+///         }
+///         Ok(())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait AsyncValidate: TapMessageBody {
+    /// Validate the message body, looking up what it refers to through the context.
+    async fn validate_async(&self, context: &dyn ValidationContext) -> Result<()>;
+}
+
 /// A trait for messages that can be connected to a prior Connect message.
 ///
 /// This trait provides functionality for linking messages to a previous Connect message,
@@ -116,7 +116,7 @@ impl Transfer {
 
     /// Generates a unique message ID for authorization, rejection, or settlement
     pub fn message_id(&self) -> String {
-        uuid::Uuid::new_v4().to_string()
+        crate::id::new_id()
     }
 }
 
@@ -290,6 +290,8 @@ impl Transfer {
             return Err(Error::Validation("Amount is required".to_string()));
         }
 
+        self.agents.iter().for_each(Agent::warn_unknown_values);
+
         // Validate amount is a finite positive number
         match self.amount.parse::<f64>() {
             Ok(amount) if !amount.is_finite() => {
@@ -0,0 +1,310 @@
+//! IVMS101 travel rule requirements of RequirePresentation policies (TAIP-10).
+//!
+//! A VASP that needs travel rule data about a party declares it with a
+//! `RequirePresentation` policy (TAIP-7) whose `@context` includes the
+//! IVMS101 context. [`Ivms101Requirement`] is the typed form of such a
+//! policy: the party the data is about and the IVMS101 elements needed,
+//! carried in the `credentials` of the policy under `TravelRuleCredential`.
+//!
+//! From a requirement, [`Ivms101Requirement::presentation_definition`]
+//! generates the matching DIF Presentation Exchange definition, and
+//! [`Ivms101Requirement::evaluate`] checks whether a received verifiable
+//! presentation provides every required element.
+//!
+//! ```
+//! use tap_msg::message::{Ivms101Field, Ivms101Requirement, PartyType, Policy};
+//!
+//! let requirement = Ivms101Requirement::new(PartyType::Originator).with_fields(vec![
+//!     Ivms101Field::Name,
+//!     Ivms101Field::GeographicAddress,
+//! ]);
+//! let policy = Policy::RequirePresentation(requirement.to_policy());
+//! assert_eq!(Ivms101Requirement::from_policies(&[policy]), vec![requirement.clone()]);
+//!
+//! let presentation = serde_json::json!({
+//!     "verifiableCredential": [{
+//!         "credentialSubject": {
+//!             "originator": {
+//!                 "naturalPerson": {
+//!                     "name": {"nameIdentifiers": [{"primaryIdentifier": "Smith"}]}
+//!                 }
+//!             }
+//!         }
+//!     }]
+//! });
+//! let evaluation = requirement.evaluate(&presentation);
+//! assert!(!evaluation.satisfied);
+//! assert_eq!(evaluation.missing, vec![Ivms101Field::GeographicAddress]);
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use crate::message::policy::{Policy, RequirePresentation};
+use crate::message::vocabulary::{Ivms101Field, PartyType};
+
+/// JSON-LD context identifying IVMS101 data
+pub const IVMS101_CONTEXT: &str = "https://intervasp.org/ivms101";
+
+/// Credential type the required IVMS101 elements are listed under
+pub const TRAVEL_RULE_CREDENTIAL: &str = "TravelRuleCredential";
+
+/// IVMS101 data required about a party, the typed form of a
+/// RequirePresentation policy with the IVMS101 context
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ivms101Requirement {
+    /// Party the data is about, any party when not given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub about_party: Option<PartyType>,
+
+    /// Required IVMS101 elements
+    pub fields: Vec<Ivms101Field>,
+
+    /// Optional human-readable purpose for this requirement
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+}
+
+/// Whether a presentation satisfies an [`Ivms101Requirement`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyEvaluation {
+    /// Party the requirement is about
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub about_party: Option<PartyType>,
+
+    /// Whether the presentation provides every required element
+    pub satisfied: bool,
+
+    /// Required elements the presentation does not provide
+    pub missing: Vec<Ivms101Field>,
+}
+
+impl Ivms101Requirement {
+    /// Require the name of `about_party`, the minimum of IVMS101 data
+    pub fn new(about_party: PartyType) -> Self {
+        Self {
+            about_party: Some(about_party),
+            fields: vec![Ivms101Field::Name],
+            purpose: None,
+        }
+    }
+
+    /// Set the required IVMS101 elements
+    pub fn with_fields(mut self, fields: Vec<Ivms101Field>) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    /// Set the purpose of the requirement
+    pub fn with_purpose(mut self, purpose: impl Into<String>) -> Self {
+        self.purpose = Some(purpose.into());
+        self
+    }
+
+    /// The requirement of a RequirePresentation policy, if the policy asks
+    /// for IVMS101 data
+    ///
+    /// The fields are those listed under any credential type of the policy,
+    /// or the name alone if it lists none.
+    pub fn from_policy(policy: &RequirePresentation) -> Option<Self> {
+        let is_ivms101 = policy.context.iter().flatten().any(|context| {
+            let context = context.to_lowercase();
+            context.contains("ivms") || context.contains("intervasp")
+        });
+        if !is_ivms101 {
+            return None;
+        }
+
+        let mut fields: Vec<Ivms101Field> = Vec::new();
+        for field in policy.credentials.iter().flat_map(|c| c.values()).flatten() {
+            let field = Ivms101Field::from(field.as_str());
+            field.warn_if_unknown();
+            if !fields.contains(&field) {
+                fields.push(field);
+            }
+        }
+        if fields.is_empty() {
+            fields.push(Ivms101Field::Name);
+        }
+
+        Some(Self {
+            about_party: policy.about_party.as_deref().map(PartyType::from),
+            fields,
+            purpose: policy.purpose.clone(),
+        })
+    }
+
+    /// The IVMS101 requirements among a set of policies
+    pub fn from_policies(policies: &[Policy]) -> Vec<Self> {
+        policies
+            .iter()
+            .filter_map(|policy| match policy {
+                Policy::RequirePresentation(policy) => Self::from_policy(policy),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The RequirePresentation policy declaring this requirement
+    pub fn to_policy(&self) -> RequirePresentation {
+        let fields = self.fields.iter().map(|f| f.as_str().to_string()).collect();
+        RequirePresentation {
+            context: Some(vec![IVMS101_CONTEXT.to_string()]),
+            about_party: self.about_party.as_ref().map(|p| p.as_str().to_string()),
+            purpose: self.purpose.clone(),
+            credentials: Some(HashMap::from([(
+                TRAVEL_RULE_CREDENTIAL.to_string(),
+                fields,
+            )])),
+            ..Default::default()
+        }
+    }
+
+    /// The DIF Presentation Exchange definition a presentation satisfying
+    /// this requirement conforms to
+    ///
+    /// Each required element may be given for a natural or a legal person.
+    pub fn presentation_definition(&self) -> Value {
+        let id = match &self.about_party {
+            Some(party) => format!("ivms101-{}", party),
+            None => "ivms101".to_string(),
+        };
+        let subject = match &self.about_party {
+            Some(party) => format!("$.credentialSubject.{}", party),
+            None => "$.credentialSubject".to_string(),
+        };
+        let subject = &subject;
+
+        let mut fields = vec![json!({
+            "path": ["$.type"],
+            "filter": {"type": "array", "contains": {"const": TRAVEL_RULE_CREDENTIAL}},
+        })];
+        for field in &self.fields {
+            let paths: Vec<String> = ["naturalPerson", "legalPerson"]
+                .into_iter()
+                .flat_map(|person| {
+                    element_names(field, person)
+                        .into_iter()
+                        .map(move |name| format!("{}.{}.{}", subject, person, name))
+                })
+                .collect();
+            fields.push(json!({"id": field.as_str(), "path": paths}));
+        }
+
+        let mut definition = json!({
+            "id": id,
+            "input_descriptors": [{
+                "id": id,
+                "name": "IVMS101 travel rule data",
+                "constraints": {"fields": fields},
+            }],
+        });
+        if let Some(purpose) = &self.purpose {
+            definition["purpose"] = json!(purpose);
+            definition["input_descriptors"][0]["purpose"] = json!(purpose);
+        }
+        definition
+    }
+
+    /// Evaluate whether a verifiable presentation, or a single credential,
+    /// provides every required element
+    ///
+    /// The IVMS101 data is looked up in the credential subjects, under the
+    /// party the requirement is about. Elements that do not apply to the
+    /// kind of person given, like the country of residence of a legal
+    /// person, are not required. When several credentials describe the
+    /// party, the most complete one is evaluated.
+    pub fn evaluate(&self, presentation: &Value) -> PolicyEvaluation {
+        let credentials: Vec<&Value> = match presentation
+            .get("verifiableCredential")
+            .and_then(|vc| vc.as_array())
+        {
+            Some(credentials) => credentials.iter().collect(),
+            None => vec![presentation],
+        };
+
+        let missing = credentials
+            .into_iter()
+            .filter_map(|credential| credential.get("credentialSubject"))
+            .filter_map(|subject| self.person_data(subject))
+            .map(|(kind, person)| {
+                self.fields
+                    .iter()
+                    .filter(|field| {
+                        let names = element_names(field, kind);
+                        !names.is_empty()
+                            && !names
+                                .iter()
+                                .any(|name| is_present(person.get(name.as_str())))
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .min_by_key(|missing| missing.len())
+            .unwrap_or_else(|| self.fields.clone());
+
+        PolicyEvaluation {
+            about_party: self.about_party.clone(),
+            satisfied: missing.is_empty(),
+            missing,
+        }
+    }
+
+    /// The person data about the party in a credential subject, with its
+    /// kind (`naturalPerson` or `legalPerson`)
+    fn person_data<'a>(&self, subject: &'a Value) -> Option<(&'static str, &'a Value)> {
+        let party = match &self.about_party {
+            Some(party) => subject.get(party.as_str())?,
+            None => ["originator", "beneficiary"]
+                .into_iter()
+                .find_map(|role| subject.get(role))
+                .unwrap_or(subject),
+        };
+        ["naturalPerson", "legalPerson"]
+            .into_iter()
+            .find_map(|kind| party.get(kind).map(|person| (kind, person)))
+    }
+}
+
+impl RequirePresentation {
+    /// A policy requiring IVMS101 data about a party
+    pub fn ivms101(about_party: PartyType, fields: Vec<Ivms101Field>) -> Self {
+        Ivms101Requirement::new(about_party)
+            .with_fields(fields)
+            .to_policy()
+    }
+
+    /// The IVMS101 data the policy requires, if it asks for any
+    pub fn ivms101_requirement(&self) -> Option<Ivms101Requirement> {
+        Ivms101Requirement::from_policy(self)
+    }
+}
+
+/// Names an element takes in the IVMS101 data of a kind of person, none if
+/// it does not apply to that kind
+fn element_names(field: &Ivms101Field, person: &str) -> Vec<String> {
+    let legal = person == "legalPerson";
+    let names: &[&str] = match field {
+        // Both the IVMS101 name and the plural used by some implementations
+        Ivms101Field::GeographicAddress => &["geographicAddress", "geographicAddresses"],
+        Ivms101Field::CustomerIdentification if legal => {
+            &["customerIdentification", "customerNumber"]
+        }
+        Ivms101Field::DateAndPlaceOfBirth | Ivms101Field::CountryOfResidence if legal => &[],
+        Ivms101Field::CountryOfRegistration if !legal => &[],
+        other => return vec![other.as_str().to_string()],
+    };
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+/// Whether an element holds a value
+fn is_present(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => false,
+        Some(Value::String(s)) => !s.trim().is_empty(),
+        Some(Value::Array(a)) => !a.is_empty(),
+        Some(Value::Object(o)) => !o.is_empty(),
+        Some(_) => true,
+    }
+}
@@ -5,6 +5,7 @@
 
 use crate::error::{Error, Result};
 use crate::message::agent::TapParticipant;
+use crate::message::vocabulary::PartyType;
 use crate::message::Party;
 use crate::TapMessage;
 use serde::{Deserialize, Serialize};
@@ -47,7 +48,7 @@ pub struct UpdateParty {
 
     /// Type of party being updated (e.g., 'originator', 'beneficiary').
     #[serde(rename = "partyType")]
-    pub party_type: String,
+    pub party_type: PartyType,
 
     /// Updated party information.
     #[tap(participant)]
@@ -60,10 +61,10 @@ pub struct UpdateParty {
 
 impl UpdateParty {
     /// Creates a new UpdateParty message body.
-    pub fn new(transaction_id: &str, party_type: &str, party: Party) -> Self {
+    pub fn new(transaction_id: &str, party_type: impl Into<PartyType>, party: Party) -> Self {
         Self {
             transaction_id: transaction_id.to_string(),
-            party_type: party_type.to_string(),
+            party_type: party_type.into(),
             party,
             context: None,
         }
@@ -77,9 +78,10 @@ impl UpdateParty {
             ));
         }
 
-        if self.party_type.is_empty() {
+        if self.party_type.as_str().is_empty() {
             return Err(Error::Validation("partyType cannot be empty".to_string()));
         }
+        self.party_type.warn_if_unknown();
 
         if self.party.id().is_empty() {
             return Err(Error::Validation("party.id cannot be empty".to_string()));
@@ -6,7 +6,8 @@ use crate::didcomm::PlainMessage;
 use crate::error::{Error, Result};
 use crate::message::tap_message_trait::TapMessageBody;
 use crate::message::{
-    AddAgents, Authorize, DIDCommPresentation, ErrorBody, Presentation, Reject, Settle, Transfer,
+    AddAgents, Authorize, DIDCommPresentation, ErrorBody, Presentation, ProblemReport, Reject,
+    Settle, Transfer,
 };
 use serde_json::Value;
 
@@ -71,6 +72,11 @@ pub fn validate_message_body(message_type: &str, body: &Value) -> Result<()> {
                 .map_err(|e| Error::SerializationError(e.to_string()))?;
             error.validate()
         }
+        "https://didcomm.org/report-problem/2.0/problem-report" => {
+            let problem_report: ProblemReport = serde_json::from_value(body.clone())
+                .map_err(|e| Error::SerializationError(e.to_string()))?;
+            problem_report.validate()
+        }
         _ => {
             // For custom types, we don't have specific validation
             Ok(())
@@ -0,0 +1,312 @@
+//! Typed vocabularies for TAP message fields.
+//!
+//! Agent roles, party types, policy types and TAIP-13 purpose codes are
+//! strings on the wire. The enums in this module give the values defined by
+//! the TAIPs, and by common practice, a variant each, so code matching on
+//! them is checked at compile time. Any other value is kept as received in
+//! an `Other` variant and serialized back unchanged, so messages from
+//! counterparties using newer or private vocabularies still round-trip.
+//!
+//! Validation of the messages carrying these fields logs a warning for
+//! values outside the known vocabulary, without rejecting the message.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+
+macro_rules! vocabulary {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident ($what:literal) {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident => $value:literal,
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        #[non_exhaustive]
+        pub enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant,
+            )*
+            /// A value outside the known vocabulary, kept as received.
+            Other(String),
+        }
+
+        impl $name {
+            /// The known values.
+            pub const KNOWN: &'static [$name] = &[$($name::$variant),*];
+
+            /// The value as it appears in messages.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $value,)*
+                    $name::Other(value) => value,
+                }
+            }
+
+            /// Whether the value is part of the known vocabulary.
+            pub fn is_known(&self) -> bool {
+                !matches!(self, $name::Other(_))
+            }
+
+            /// Log a warning if the value is outside the known vocabulary.
+            pub fn warn_if_unknown(&self) {
+                if let $name::Other(value) = self {
+                    tracing::warn!(concat!("Unknown ", $what, ": {}"), value);
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = Infallible;
+
+            fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+                Ok(Self::from(s))
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(value: &str) -> Self {
+                match value {
+                    $($value => $name::$variant,)*
+                    other => $name::Other(other.to_string()),
+                }
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(value: String) -> Self {
+                match Self::from(value.as_str()) {
+                    $name::Other(_) => $name::Other(value),
+                    known => known,
+                }
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.as_str() == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.as_str() == *other
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+                String::deserialize(deserializer).map(Self::from)
+            }
+        }
+    };
+}
+
+vocabulary! {
+    /// Role of an agent in a transaction (TAIP-5).
+    pub enum AgentRole ("agent role") {
+        /// Blockchain address funds are settled to.
+        SettlementAddress => "SettlementAddress",
+        /// Blockchain address funds are sent from.
+        SourceAddress => "SourceAddress",
+        /// Custodial service holding funds for a party.
+        CustodialService => "CustodialService",
+        /// Wallet service of a party.
+        WalletService => "WalletService",
+        /// Exchange acting for a party.
+        Exchange => "Exchange",
+        /// Bridge for cross-chain transactions.
+        Bridge => "Bridge",
+        /// DeFi protocol.
+        DeFiProtocol => "DeFiProtocol",
+        /// Agent holding locked funds until release (TAIP-17).
+        EscrowAgent => "EscrowAgent",
+        /// Compliance service reviewing the transaction.
+        Compliance => "compliance",
+    }
+}
+
+vocabulary! {
+    /// Role of a party in a transaction, e.g. in UpdateParty messages (TAIP-6).
+    pub enum PartyType ("party type") {
+        /// Party sending a Transfer.
+        Originator => "originator",
+        /// Party receiving a Transfer.
+        Beneficiary => "beneficiary",
+        /// Party paying a Payment.
+        Customer => "customer",
+        /// Party requesting a Payment.
+        Merchant => "merchant",
+    }
+}
+
+vocabulary! {
+    /// Type of a policy (TAIP-7), the `@type` of its JSON object.
+    pub enum PolicyType ("policy type") {
+        /// Authorization required from specified agents.
+        RequireAuthorization => "RequireAuthorization",
+        /// Verifiable credential presentation required.
+        RequirePresentation => "RequirePresentation",
+        /// Proof of control of an account or address required.
+        RequireProofOfControl => "RequireProofOfControl",
+        /// Confirmation of a relationship required.
+        RequireRelationshipConfirmation => "RequireRelationshipConfirmation",
+    }
+}
+
+vocabulary! {
+    /// Element of IVMS101 person data required by a travel rule policy
+    /// (TAIP-10), named as in IVMS101 JSON.
+    pub enum Ivms101Field ("IVMS101 field") {
+        /// Name of the natural or legal person.
+        Name => "name",
+        /// Geographic address.
+        GeographicAddress => "geographicAddress",
+        /// National identification, e.g. a passport number or LEI.
+        NationalIdentification => "nationalIdentification",
+        /// Identifier of the person as a customer of its VASP.
+        CustomerIdentification => "customerIdentification",
+        /// Date and place of birth of a natural person.
+        DateAndPlaceOfBirth => "dateAndPlaceOfBirth",
+        /// Country of residence of a natural person.
+        CountryOfResidence => "countryOfResidence",
+        /// Country of registration of a legal person.
+        CountryOfRegistration => "countryOfRegistration",
+    }
+}
+
+vocabulary! {
+    /// Purpose of a payment, an ISO 20022 purpose code (TAIP-13).
+    pub enum Purpose ("purpose code") {
+        /// Business expenses.
+        BusinessExpenses => "BEXP",
+        /// Cash management transfer.
+        CashManagementTransfer => "CASH",
+        /// Charity payment.
+        CharityPayment => "CHAR",
+        /// Commercial payment.
+        CommercialPayment => "COMC",
+        /// Purchase or sale of goods.
+        PurchaseSaleOfGoods => "GDDS",
+        /// Gift.
+        Gift => "GIFT",
+        /// Intra-company payment.
+        IntraCompanyPayment => "INTC",
+        /// Investment and securities.
+        InvestmentAndSecurities => "INVS",
+        /// Loan.
+        Loan => "LOAN",
+        /// Pension payment.
+        PensionPayment => "PENS",
+        /// Rent.
+        Rent => "RENT",
+        /// Salary payment.
+        SalaryPayment => "SALA",
+        /// Purchase or sale of services.
+        PurchaseSaleOfServices => "SCVE",
+        /// Supplier payment.
+        SupplierPayment => "SUPP",
+        /// Tax payment.
+        TaxPayment => "TAXS",
+        /// Trade services.
+        TradeServices => "TRAD",
+        /// Other payment, the explicit ISO 20022 code for it.
+        OtherPayment => "OTHR",
+    }
+}
+
+vocabulary! {
+    /// Category of a payment's purpose, an ISO 20022 category purpose code
+    /// (TAIP-13).
+    pub enum CategoryPurpose ("category purpose code") {
+        /// Cash management transfer.
+        CashManagementTransfer => "CASH",
+        /// Trade settlement payment.
+        TradeSettlementPayment => "CORT",
+        /// Dividend.
+        Dividend => "DIVI",
+        /// Government payment.
+        GovernmentPayment => "GOVT",
+        /// Hedging.
+        Hedging => "HEDG",
+        /// Intra-company payment.
+        IntraCompanyPayment => "INTC",
+        /// Interest.
+        Interest => "INTE",
+        /// Loan.
+        Loan => "LOAN",
+        /// Pension payment.
+        PensionPayment => "PENS",
+        /// Salary payment.
+        SalaryPayment => "SALA",
+        /// Securities.
+        Securities => "SECU",
+        /// Social security benefit.
+        SocialSecurityBenefit => "SSBE",
+        /// Supplier payment.
+        SupplierPayment => "SUPP",
+        /// Tax payment.
+        TaxPayment => "TAXS",
+        /// Trade.
+        Trade => "TRAD",
+        /// Treasury payment.
+        TreasuryPayment => "TREA",
+        /// Value added tax payment.
+        ValueAddedTaxPayment => "VATX",
+        /// Withholding.
+        Withholding => "WHLD",
+        /// Other payment, the explicit ISO 20022 code for it.
+        OtherPayment => "OTHR",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_and_unknown_values_round_trip() {
+        let roles: Vec<AgentRole> =
+            serde_json::from_str(r#"["SettlementAddress", "compliance", "Custodian"]"#).unwrap();
+        assert_eq!(
+            roles,
+            vec![
+                AgentRole::SettlementAddress,
+                AgentRole::Compliance,
+                AgentRole::Other("Custodian".to_string()),
+            ]
+        );
+        assert!(!roles[2].is_known());
+        assert_eq!(
+            serde_json::to_string(&roles).unwrap(),
+            r#"["SettlementAddress","compliance","Custodian"]"#
+        );
+
+        assert_eq!(PartyType::from("originator"), PartyType::Originator);
+        assert_eq!(
+            PartyType::from("guarantor".to_string()),
+            PartyType::Other("guarantor".to_string())
+        );
+        assert_eq!(CategoryPurpose::from("CASH").to_string(), "CASH");
+        assert_eq!(Purpose::SupplierPayment, "SUPP");
+        assert!(PolicyType::KNOWN.iter().all(PolicyType::is_known));
+    }
+}
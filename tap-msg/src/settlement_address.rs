@@ -7,6 +7,7 @@
 use serde::{de, Deserialize, Deserializer, Serialize};
 use std::fmt;
 use std::str::FromStr;
+use tap_caip::{AccountId, AssetId};
 use thiserror::Error;
 
 /// Errors that can occur when parsing settlement addresses.
@@ -23,6 +24,10 @@ pub enum SettlementAddressError {
     /// Unknown settlement address format.
     #[error("Unknown settlement address format")]
     UnknownFormat,
+
+    /// Address cannot hold the asset being settled.
+    #[error("Settlement address {0} does not match asset: {1}")]
+    AssetMismatch(String, String),
 }
 
 /// A PayTo URI per RFC 8905 for traditional payment systems.
@@ -132,6 +137,38 @@ impl SettlementAddress {
         }
     }
 
+    /// Create a settlement address from an agent identifier.
+    ///
+    /// Accepts `did:pkh` DIDs, whose method-specific identifier is a CAIP-10
+    /// account, as well as plain CAIP-10 accounts and PayTo URIs. Returns
+    /// None for other DIDs.
+    pub fn from_agent_id(id: &str) -> Option<Self> {
+        if let Some(account) = id.strip_prefix("did:pkh:") {
+            return Some(SettlementAddress::Caip10(account.to_string()));
+        }
+        if id.starts_with("did:") {
+            return None;
+        }
+        Self::from_string(id.to_string()).ok()
+    }
+
+    /// Check that the address can receive the given asset.
+    ///
+    /// CAIP-10 addresses must be valid accounts on the asset's chain. PayTo
+    /// URIs are not tied to a chain and always pass.
+    pub fn check_asset(&self, asset: &AssetId) -> Result<(), SettlementAddressError> {
+        let SettlementAddress::Caip10(address) = self else {
+            return Ok(());
+        };
+        let mismatch = |e: tap_caip::Error| {
+            SettlementAddressError::AssetMismatch(address.clone(), e.to_string())
+        };
+        AccountId::from_str(address)
+            .map_err(mismatch)?
+            .check_asset(asset)
+            .map_err(mismatch)
+    }
+
     /// Check if this is a blockchain address.
     pub fn is_blockchain(&self) -> bool {
         matches!(self, SettlementAddress::Caip10(_))
@@ -180,6 +217,40 @@ impl<'de> Deserialize<'de> for SettlementAddress {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_settlement_address_check_asset() {
+        let usdc =
+            AssetId::from_str("eip155:1/erc20:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48").unwrap();
+        let sol_usdc = AssetId::from_str(
+            "solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp/token:EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+        )
+        .unwrap();
+
+        let eth = SettlementAddress::from_agent_id(
+            "did:pkh:eip155:1:0x4b20993Bc481177ec7E8f571ceCaE8A9e22C02db",
+        )
+        .unwrap();
+        assert!(eth.check_asset(&usdc).is_ok());
+        assert!(matches!(
+            eth.check_asset(&sol_usdc),
+            Err(SettlementAddressError::AssetMismatch(_, _))
+        ));
+
+        // An Ethereum address on the Solana chain is not a valid Solana account
+        let bad = SettlementAddress::from_string(
+            "solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp:0x4b20993Bc481177ec7E8f571ceCaE8A9e22C02db"
+                .to_string(),
+        )
+        .unwrap();
+        assert!(bad.check_asset(&sol_usdc).is_err());
+
+        let iban =
+            SettlementAddress::from_string("payto://iban/DE75512108001245126199".to_string())
+                .unwrap();
+        assert!(iban.check_asset(&sol_usdc).is_ok());
+        assert!(SettlementAddress::from_agent_id("did:web:vasp.example").is_none());
+    }
+
     #[test]
     fn test_payto_uri_creation() {
         let uri = PayToUri::new("payto://iban/DE75512108001245126199".to_string()).unwrap();
@@ -2,8 +2,10 @@
 //!
 //! This module provides utility functions used throughout the TAP core library.
 
+pub mod jcs;
 pub mod memo_hash;
 pub mod name_hash;
+pub mod transaction_hash;
 
 use crate::error::Error;
 use crate::Result;
@@ -13,6 +15,7 @@ pub use memo_hash::{
     encode_binary_memo, encode_text_memo, tap_memo_hash, verify_binary_memo, verify_text_memo,
 };
 pub use name_hash::{hash_name, NameHashable};
+pub use transaction_hash::{canonical_transaction_hash, message_transaction_hash};
 
 /// Gets the current time as a unix timestamp (seconds since the epoch)
 ///
@@ -0,0 +1,134 @@
+//! JSON Canonicalization Scheme (RFC 8785).
+//!
+//! JCS serializes a JSON value the same way whatever produced it: object
+//! members sorted by the UTF-16 code units of their names, no whitespace,
+//! strings escaped minimally, and numbers written as ECMAScript writes them.
+//! Two copies of a message body that differ only in member order, spacing
+//! or number formatting canonicalize to the same text, so a hash of the
+//! canonical text identifies the content.
+
+use serde_json::{Number, Value};
+
+/// Serialize a JSON value in its canonical form.
+pub fn canonicalize(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(value, &mut out);
+    out
+}
+
+fn write_value(value: &Value, out: &mut String) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => out.push_str(&number(n)),
+        Value::String(s) => write_string(s, out),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(members) => {
+            let mut members: Vec<(&String, &Value)> = members.iter().collect();
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (i, (name, value)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(name, out);
+                out.push(':');
+                write_value(value, out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+/// Strings are escaped as by `JSON.stringify`, which is what serde_json
+/// does: quotes, backslashes and control characters only, with lowercase
+/// hex in `\u` escapes.
+fn write_string(s: &str, out: &mut String) {
+    out.push_str(&serde_json::to_string(s).unwrap_or_default());
+}
+
+/// A number as ECMAScript's `Number.prototype.toString` writes it.
+fn number(n: &Number) -> String {
+    if n.is_i64() || n.is_u64() {
+        return n.to_string();
+    }
+    let f = n.as_f64().unwrap_or_default();
+    if f == 0.0 {
+        return "0".to_string();
+    }
+    let abs = f.abs();
+    if (1e-6..1e21).contains(&abs) {
+        // Shortest round-tripping digits, without a trailing ".0"
+        let s = f.to_string();
+        return s.strip_suffix(".0").map(str::to_string).unwrap_or(s);
+    }
+    // Exponent form: "1e+21", "1.5e-7"
+    let s = format!("{:e}", f);
+    match s.split_once('e') {
+        Some((mantissa, exponent)) if !exponent.starts_with('-') => {
+            format!("{}e+{}", mantissa, exponent)
+        }
+        _ => s,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_members_sorted_without_whitespace() {
+        let value = json!({"b": [1, {"z": null, "a": true}], "a": "x"});
+        assert_eq!(
+            canonicalize(&value),
+            r#"{"a":"x","b":[1,{"a":true,"z":null}]}"#
+        );
+    }
+
+    #[test]
+    fn test_member_names_sorted_by_utf16() {
+        // U+1F600 sorts before U+FB33 in UTF-16, after it in UTF-8
+        let value = json!({"\u{fb33}": 1, "\u{1f600}": 2, "\r": 3, "1": 4});
+        assert_eq!(
+            canonicalize(&value),
+            "{\"\\r\":3,\"1\":4,\"\u{1f600}\":2,\"\u{fb33}\":1}"
+        );
+    }
+
+    #[test]
+    fn test_strings() {
+        let value = json!("\u{8}\t\n\u{1f}\"\\/\u{7f}é€");
+        assert_eq!(
+            canonicalize(&value),
+            "\"\\b\\t\\n\\u001f\\\"\\\\/\u{7f}é€\""
+        );
+    }
+
+    #[test]
+    fn test_numbers() {
+        let cases = [
+            (json!(0), "0"),
+            (json!(-0.0), "0"),
+            (json!(1.0), "1"),
+            (json!(-1.5), "-1.5"),
+            (json!(0.000001), "0.000001"),
+            (json!(1e-7), "1e-7"),
+            (json!(1e21), "1e+21"),
+            (json!(123456789012345680000.0), "123456789012345680000"),
+            (json!(9007199254740993u64), "9007199254740993"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(canonicalize(&value), expected, "{}", value);
+        }
+    }
+}
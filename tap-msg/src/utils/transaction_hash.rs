@@ -0,0 +1,93 @@
+//! Canonical transaction hash for referencing transactions across systems.
+//!
+//! Copies of a transaction held by different systems need not agree on the
+//! message ID: a Transfer re-sent after a failed delivery, imported from a
+//! backup, or re-serialized by another implementation can carry a new ID and
+//! reorder the members of its body. The canonical transaction hash depends
+//! only on the content of the initiating message:
+//!
+//! `hash = lowercase-hex(SHA-256(JCS(body) || UTF8(sender_did)))`
+//!
+//! where `JCS` is the JSON Canonicalization Scheme of RFC 8785 (see
+//! [`jcs`](super::jcs)), so core banking, case management and other
+//! external systems can reference the transaction by a value every copy
+//! shares.
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::jcs::canonicalize;
+
+/// Message types that initiate a transaction
+const INITIATING_TYPES: &[&str] = &[
+    "https://tap.rsvp/schema/1.0#Transfer",
+    "https://tap.rsvp/schema/1.0#Payment",
+];
+
+/// Compute the canonical hash of a transaction from the body of its
+/// initiating message and the DID of the sender
+pub fn canonical_transaction_hash(body: &Value, sender_did: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(canonicalize(body).as_bytes());
+    hasher.update(sender_did.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// The canonical transaction hash of a serialized plain message, if it is
+/// a Transfer or Payment initiating a transaction
+pub fn message_transaction_hash(message: &Value) -> Option<String> {
+    let message_type = message.get("type")?.as_str()?;
+    if !INITIATING_TYPES.contains(&message_type) {
+        return None;
+    }
+    let body = message.get("body")?;
+    let sender = message.get("from")?.as_str()?;
+    Some(canonical_transaction_hash(body, sender))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_independent_of_member_order_and_formatting() {
+        let a: Value = serde_json::from_str(
+            r#"{"asset": "eip155:1/slip44:60", "amount": "1.0", "originator": {"@id": "did:example:alice"}}"#,
+        )
+        .unwrap();
+        let b: Value = serde_json::from_str(
+            r#"{"originator":{"@id":"did:example:alice"},"amount":"1.0","asset":"eip155:1/slip44:60"}"#,
+        )
+        .unwrap();
+        let hash = canonical_transaction_hash(&a, "did:example:alice");
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, canonical_transaction_hash(&b, "did:example:alice"));
+        assert_ne!(hash, canonical_transaction_hash(&a, "did:example:bob"));
+    }
+
+    #[test]
+    fn test_message_transaction_hash() {
+        let body = json!({"amount": "1.0"});
+        let transfer = json!({
+            "id": "msg-1",
+            "type": "https://tap.rsvp/schema/1.0#Transfer",
+            "from": "did:example:alice",
+            "body": body,
+        });
+        let mut resent = transfer.clone();
+        resent["id"] = json!("msg-2");
+        assert_eq!(
+            message_transaction_hash(&transfer),
+            Some(canonical_transaction_hash(&body, "did:example:alice"))
+        );
+        assert_eq!(
+            message_transaction_hash(&transfer),
+            message_transaction_hash(&resent)
+        );
+
+        let mut authorize = transfer;
+        authorize["type"] = json!("https://tap.rsvp/schema/1.0#Authorize");
+        assert_eq!(message_transaction_hash(&authorize), None);
+    }
+}
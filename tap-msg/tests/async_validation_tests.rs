@@ -0,0 +1,75 @@
+//! Tests for asynchronous validation derived with `#[tap(async_validation)]`
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tap_msg::message::{AsyncValidate, ValidationContext};
+use tap_msg::{Error, Result, TapMessage};
+
+#[derive(Debug, Clone, Serialize, Deserialize, TapMessage)]
+#[tap(
+    message_type = "https://example.com/protocols/receipt/1.0/receipt",
+    custom_validation,
+    async_validation
+)]
+struct Receipt {
+    #[tap(thread_id)]
+    transaction_id: String,
+    amount: String,
+}
+
+impl Receipt {
+    fn validate_receipt(&self) -> Result<()> {
+        if self.amount.is_empty() {
+            return Err(Error::Validation("Amount is required".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn validate_receipt_async(&self, context: &dyn ValidationContext) -> Result<()> {
+        if !context.transaction_exists(&self.transaction_id).await? {
+            return Err(Error::Validation(format!(
+                "Unknown transaction {}",
+                self.transaction_id
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Context knowing a single transaction
+struct KnownTransaction(&'static str);
+
+#[async_trait]
+impl ValidationContext for KnownTransaction {
+    async fn transaction_exists(&self, transaction_id: &str) -> Result<bool> {
+        Ok(transaction_id == self.0)
+    }
+}
+
+fn receipt(transaction_id: &str, amount: &str) -> Receipt {
+    Receipt {
+        transaction_id: transaction_id.to_string(),
+        amount: amount.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_async_validation_awaits_the_context() {
+    let context = KnownTransaction("tx-1");
+    assert!(receipt("tx-1", "10").validate_async(&context).await.is_ok());
+
+    let error = receipt("tx-2", "10")
+        .validate_async(&context)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("Unknown transaction tx-2"));
+}
+
+#[tokio::test]
+async fn test_sync_validation_runs_first() {
+    let error = receipt("tx-2", "")
+        .validate_async(&KnownTransaction("tx-1"))
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("Amount is required"));
+}
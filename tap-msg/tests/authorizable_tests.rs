@@ -6,7 +6,7 @@ use std::str::FromStr;
 use tap_caip::AssetId;
 use tap_msg::message::tap_message_trait::Authorizable;
 use tap_msg::message::tap_message_trait::TapMessageBody;
-use tap_msg::message::{Agent, Party, Reject, Settle, Transfer, UpdateParty};
+use tap_msg::message::{Agent, Party, PartyType, Reject, Settle, Transfer, UpdateParty};
 
 #[test]
 fn test_transfer_authorizable() {
@@ -39,6 +39,7 @@ fn test_transfer_authorizable() {
             .transaction_id
             .clone()
             .unwrap_or_else(|| transfer_message.id.clone()),
+        code: None,
         reason: Some(format!("{}: {}", reject_code, reject_reason)),
     };
     assert_eq!(
@@ -51,6 +52,7 @@ fn test_transfer_authorizable() {
         transaction_id: "tx-123".to_string(),
         settlement_id: Some("settle-123".to_string()),
         amount: Some("50.00".to_string()),
+        settled_asset: None,
     };
 
     assert_eq!(settle.settlement_id, Some("settle-123".to_string()));
@@ -86,6 +88,7 @@ fn test_didcomm_message_authorizable() {
             .transaction_id
             .clone()
             .unwrap_or_else(|| transfer_message.id.clone()),
+        code: None,
         reason: Some(format!("{}: {}", reject_code, reject_reason)),
     };
     assert_eq!(
@@ -101,6 +104,7 @@ fn test_didcomm_message_authorizable() {
             .unwrap_or_else(|| transfer_message.id.clone()),
         settlement_id: Some("tx-12345".to_string()),
         amount: Some("100".to_string()),
+        settled_asset: None,
     };
 
     assert_eq!(settle.settlement_id, Some("tx-12345".to_string()));
@@ -123,6 +127,7 @@ fn test_full_flow() {
             .unwrap_or_else(|| "tx-123".to_string()),
         settlement_id: Some("tx-12345".to_string()),
         amount: Some("100".to_string()),
+        settled_asset: None,
     };
 
     // Convert settle to DIDComm message
@@ -150,7 +155,7 @@ fn test_update_party_message() {
     // Create an UpdateParty message
     let update_party = UpdateParty {
         transaction_id: transfer_id.clone(),
-        party_type: "beneficiary".to_string(),
+        party_type: PartyType::Beneficiary,
         party: updated_participant.clone(),
         context: None,
     };
@@ -181,7 +186,7 @@ fn test_update_party_message() {
     // Test using update_party from manual creation
     let update_party_from_manual = UpdateParty {
         transaction_id: transfer_id.clone(),
-        party_type: "beneficiary".to_string(),
+        party_type: PartyType::Beneficiary,
         party: updated_participant,
         context: None,
     };
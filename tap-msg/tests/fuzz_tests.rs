@@ -1,9 +1,16 @@
+use proptest::prelude::*;
+use serde_json::Value;
 use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
 use std::str::FromStr;
 use tap_caip::AssetId;
 use tap_msg::didcomm::PlainMessage;
-use tap_msg::message::tap_message_trait::{Connectable, TapMessageBody};
-use tap_msg::message::{Agent, Connect, Party, Payment, Transfer};
+use tap_msg::message::tap_message_trait::{self, Connectable, TapMessageBody};
+use tap_msg::message::{
+    AddAgents, Agent, Authorize, Cancel, Connect, Party, Payment, Reject, Settle, TapMessage,
+    Transfer, UpdateParty,
+};
 
 /// This module contains fuzzing tests for TAP message types.
 /// These tests are designed to ensure that our code handles malformed inputs gracefully.
@@ -224,6 +231,209 @@ fn test_fuzz_didcomm_conversion() {
     );
 }
 
+#[test]
+fn test_fuzz_plain_message_corpus() {
+    // Every entry in the corpus must be rejected by parsing, typing or validation
+    let mut entries: Vec<_> = fs::read_dir(Path::new(CORPUS_DIR))
+        .expect("Failed to read PlainMessage corpus")
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    entries.sort();
+    assert!(!entries.is_empty(), "PlainMessage corpus is empty");
+
+    for path in entries {
+        let input = fs::read(&path).unwrap();
+        assert!(
+            parse_and_validate(&input).is_err(),
+            "Corpus entry {} should be rejected",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn test_fuzz_plain_message_seeds_are_valid() {
+    for seed in seed_messages() {
+        let input = serde_json::to_vec(&seed).unwrap();
+        assert!(
+            parse_and_validate(&input).is_ok(),
+            "Seed {} should be valid",
+            seed.type_
+        );
+    }
+}
+
+proptest! {
+    // Replacing any field of a valid message must not panic
+    #[test]
+    fn test_fuzz_plain_message_field_replacement(
+        seed in prop::sample::select(seed_messages()),
+        field in any::<prop::sample::Index>(),
+        in_body in any::<bool>(),
+        value in json_strategy(),
+    ) {
+        let mut message = serde_json::to_value(&seed).unwrap();
+        let target = if in_body { &mut message["body"] } else { &mut message };
+        if let Value::Object(map) = target {
+            let key = map.keys().nth(field.index(map.len())).cloned().unwrap();
+            map.insert(key, value);
+        }
+        let _ = parse_and_validate(&serde_json::to_vec(&message).unwrap());
+    }
+
+    // Arbitrary bodies for every known message type must not panic
+    #[test]
+    fn test_fuzz_plain_message_arbitrary_body(
+        type_index in any::<prop::sample::Index>(),
+        body in json_strategy(),
+    ) {
+        let message = serde_json::json!({
+            "id": "fuzz",
+            "type": MESSAGE_TYPES[type_index.index(MESSAGE_TYPES.len())],
+            "from": "did:example:alice",
+            "to": ["did:example:bob"],
+            "body": body,
+        });
+        let _ = parse_and_validate(&serde_json::to_vec(&message).unwrap());
+    }
+
+    // Arbitrary bytes must not panic
+    #[test]
+    fn test_fuzz_plain_message_arbitrary_bytes(input in prop::collection::vec(any::<u8>(), 0..512)) {
+        let _ = parse_and_validate(&input);
+    }
+}
+
+/// Hostile PlainMessages, one per file
+const CORPUS_DIR: &str = "tests/corpus/plain_messages";
+
+const MESSAGE_TYPES: &[&str] = &[
+    "https://tap.rsvp/schema/1.0#AddAgents",
+    "https://tap.rsvp/schema/1.0#Authorize",
+    "https://tap.rsvp/schema/1.0#AuthorizationRequired",
+    "https://didcomm.org/basicmessage/2.0/message",
+    "https://tap.rsvp/schema/1.0#Cancel",
+    "https://tap.rsvp/schema/1.0#Capture",
+    "https://tap.rsvp/schema/1.0#ConfirmRelationship",
+    "https://tap.rsvp/schema/1.0#Connect",
+    "https://didcomm.org/present-proof/3.0/presentation",
+    "https://tap.rsvp/schema/1.0#Error",
+    "https://tap.rsvp/schema/1.0#Lock",
+    "https://tap.rsvp/schema/1.0#RFQ",
+    "https://tap.rsvp/schema/1.0#OutOfBand",
+    "https://tap.rsvp/schema/1.0#Payment",
+    "https://tap.rsvp/schema/1.0#Quote",
+    "https://tap.rsvp/schema/1.0#Presentation",
+    "https://tap.rsvp/schema/1.0#Reject",
+    "https://tap.rsvp/schema/1.0#RemoveAgent",
+    "https://tap.rsvp/schema/1.0#ReplaceAgent",
+    "https://tap.rsvp/schema/1.0#RequestPresentation",
+    "https://tap.rsvp/schema/1.0#Revert",
+    "https://tap.rsvp/schema/1.0#Settle",
+    "https://tap.rsvp/schema/1.0#Transfer",
+    "https://didcomm.org/trust-ping/2.0/ping",
+    "https://didcomm.org/trust-ping/2.0/ping-response",
+    "https://didcomm.org/report-problem/2.0/problem-report",
+    "https://didcomm.org/discover-features/2.0/queries",
+    "https://didcomm.org/discover-features/2.0/disclose",
+    "https://tap.rsvp/schema/1.0#UpdateParty",
+    "https://tap.rsvp/schema/1.0#UpdatePolicies",
+];
+
+// Strategy for generating arbitrary JSON values, biased towards TAP-like strings
+fn json_strategy() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        any::<i64>().prop_map(Value::from),
+        any::<u64>().prop_map(Value::from),
+        any::<f64>().prop_map(Value::from),
+        ".{0,16}".prop_map(Value::String),
+        "did:[a-z]{0,5}:[a-zA-Z0-9#:]{0,12}".prop_map(Value::String),
+        "[a-z0-9]{0,8}:[a-z0-9]{0,8}(/[a-z0-9]{0,8}:[a-zA-Z0-9]{0,8})?".prop_map(Value::String),
+        "-?[0-9]{0,6}(\\.[0-9]{0,6})?".prop_map(Value::String),
+    ];
+    leaf.prop_recursive(4, 32, 6, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..6).prop_map(Value::Array),
+            prop::collection::hash_map("@?[a-z_]{0,12}", inner, 0..6)
+                .prop_map(|map| Value::Object(map.into_iter().collect())),
+        ]
+    })
+}
+
+/// Parse, type and validate untrusted PlainMessage bytes the way a receiving
+/// node does
+fn parse_and_validate(input: &[u8]) -> tap_msg::Result<()> {
+    let message: PlainMessage = serde_json::from_slice(input)?;
+    tap_message_trait::TapMessage::validate(&message)?;
+    match TapMessage::from_plain_message(&message)? {
+        TapMessage::AddAgents(body) => body.validate(),
+        TapMessage::Authorize(body) => body.validate(),
+        TapMessage::AuthorizationRequired(body) => body.validate(),
+        TapMessage::BasicMessage(body) => body.validate(),
+        TapMessage::Cancel(body) => body.validate(),
+        TapMessage::Capture(body) => body.validate(),
+        TapMessage::ConfirmRelationship(body) => body.validate(),
+        TapMessage::Connect(body) => body.validate(),
+        TapMessage::DIDCommPresentation(body) => body.validate(),
+        TapMessage::Error(body) => body.validate(),
+        TapMessage::Lock(body) => body.validate(),
+        TapMessage::Rfq(body) => body.validate(),
+        TapMessage::OutOfBand(body) => body.validate(),
+        TapMessage::Payment(body) => body.validate(),
+        TapMessage::Quote(body) => body.validate(),
+        TapMessage::Presentation(body) => body.validate(),
+        TapMessage::Reject(body) => body.validate(),
+        TapMessage::RemoveAgent(body) => body.validate(),
+        TapMessage::ReplaceAgent(body) => body.validate(),
+        TapMessage::RequestPresentation(body) => body.validate(),
+        TapMessage::Revert(body) => body.validate(),
+        TapMessage::Settle(body) => body.validate(),
+        TapMessage::Transfer(body) => body.validate(),
+        TapMessage::TrustPing(body) => body.validate(),
+        TapMessage::TrustPingResponse(body) => body.validate(),
+        TapMessage::UpdateParty(body) => body.validate(),
+        TapMessage::UpdatePolicies(body) => body.validate(),
+        TapMessage::ProblemReport(body) => body.validate(),
+        TapMessage::DiscoverFeaturesQueries(body) => body.validate(),
+        TapMessage::DiscoverFeaturesDisclose(body) => body.validate(),
+    }
+}
+
+// Valid messages that the mutation tests start from
+fn seed_messages() -> Vec<PlainMessage> {
+    let from = "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK";
+    let transaction_id = "9a3c1a5e-6f1d-4c1b-9d4e-2f6b7c8d9e0f";
+    let agent = Agent::new(
+        "did:key:z6MkpDYxrwJw5WoD1o4YVfthJJgZfxrECpW6Da6QCWagRHLx",
+        "agent",
+        from,
+    );
+    vec![
+        create_test_transfer().to_didcomm(from).unwrap(),
+        Authorize::new(transaction_id).to_didcomm(from).unwrap(),
+        Reject::new(transaction_id, "Compliance failure")
+            .to_didcomm(from)
+            .unwrap(),
+        Cancel::with_reason(transaction_id, "originator", "No longer needed")
+            .to_didcomm(from)
+            .unwrap(),
+        Settle::new(
+            transaction_id,
+            "eip155:1:tx/0x3edb98c24d46d148eb926c714f4fbaa117c47b0c0821f38bfce9763604457c33",
+        )
+        .to_didcomm(from)
+        .unwrap(),
+        UpdateParty::new(transaction_id, "beneficiary", Party::new(from))
+            .to_didcomm(from)
+            .unwrap(),
+        AddAgents::new(transaction_id, vec![agent])
+            .to_didcomm(from)
+            .unwrap(),
+    ]
+}
+
 // Helper function to create a test Transfer
 fn create_test_transfer() -> Transfer {
     let asset =
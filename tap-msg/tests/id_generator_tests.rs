@@ -0,0 +1,52 @@
+use std::sync::Arc;
+use tap_msg::id::{
+    new_id, set_id_generator, IdGenerator, IdStrategy, SequentialIdGenerator, UlidGenerator,
+    UuidV7Generator,
+};
+use tap_msg::message::tap_message_trait::TapMessageBody;
+use tap_msg::message::{Party, Transfer};
+
+#[test]
+fn test_uuid_v7_ids_are_time_ordered() {
+    let ids: Vec<String> = (0..100).map(|_| UuidV7Generator.generate()).collect();
+    let parsed = uuid::Uuid::parse_str(&ids[0]).unwrap();
+    assert_eq!(parsed.get_version_num(), 7);
+    let mut sorted = ids.clone();
+    sorted.sort();
+    assert_eq!(ids, sorted);
+}
+
+#[test]
+fn test_ulids_are_crockford_base32_and_time_ordered() {
+    let first = UlidGenerator.generate();
+    std::thread::sleep(std::time::Duration::from_millis(2));
+    let second = UlidGenerator.generate();
+
+    assert_eq!(first.len(), 26);
+    assert!(first
+        .chars()
+        .all(|c| "0123456789ABCDEFGHJKMNPQRSTVWXYZ".contains(c)));
+    assert!(first < second);
+}
+
+#[test]
+fn test_id_strategy_names() {
+    for strategy in [IdStrategy::UuidV4, IdStrategy::UuidV7, IdStrategy::Ulid] {
+        assert_eq!(strategy.to_string().parse::<IdStrategy>(), Ok(strategy));
+    }
+    assert!("snowflake".parse::<IdStrategy>().is_err());
+}
+
+#[test]
+fn test_messages_take_ids_from_the_configured_generator() {
+    set_id_generator(Arc::new(SequentialIdGenerator::new("test")));
+    assert_eq!(new_id(), "test-1");
+
+    let transfer = Transfer::builder()
+        .asset("eip155:1/slip44:60".parse().unwrap())
+        .originator(Party::new("did:example:alice"))
+        .amount("10".to_string())
+        .build();
+    let message = transfer.to_didcomm("did:example:alice").unwrap();
+    assert_eq!(message.id, "test-2");
+}
@@ -126,7 +126,7 @@ fn test_add_agents() -> Result<()> {
     // Validate the created message
     assert_eq!(add_agents.agents.len(), 1);
     assert_eq!(add_agents.agents[0].id, new_agent_did);
-    assert_eq!(add_agents.agents[0].role, Some("observer".to_string()));
+    assert_eq!(add_agents.agents[0].role, Some("observer".into()));
 
     // Convert to DIDComm message and check that it can be properly deserialized
     let didcomm_message = add_agents.to_didcomm("did:example:sender_vasp")?;
@@ -173,7 +173,7 @@ fn test_authorizable_trait_methods() -> Result<()> {
     );
     assert_eq!(
         replace_agent_message.body.replacement.role,
-        Some("replacement_agent".to_string())
+        Some("replacement_agent".into())
     );
 
     // Test RemoveAgent
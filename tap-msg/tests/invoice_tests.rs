@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 use std::str::FromStr;
 use tap_caip::AssetId;
-use tap_msg::message::invoice::{Invoice, LineItem, TaxCategory, TaxSubtotal, TaxTotal};
+use tap_msg::message::invoice::{
+    document_digest, DocumentReference, Invoice, LineItem, TaxCategory, TaxSubtotal, TaxTotal,
+};
 use tap_msg::message::tap_message_trait::TapMessageBody;
 use tap_msg::message::{Agent, Party};
 use tap_msg::message::{Payment, PaymentBuilder};
@@ -235,3 +237,40 @@ fn test_payment_request_with_invoice() {
         panic!("Expected InvoiceReference::Object, got URL");
     }
 }
+
+#[test]
+fn test_document_reference_digests() {
+    let pdf = b"%PDF-1.7 invoice INV001";
+
+    let external = DocumentReference::external("doc-1", "https://example.com/inv.pdf", pdf)
+        .with_document_type("Invoice")
+        .with_mime_type("application/pdf");
+    assert_eq!(
+        external.hash.as_deref(),
+        Some(document_digest(pdf).as_str())
+    );
+    assert_eq!(external.hash.as_ref().unwrap().len(), 64);
+    assert!(external.verify(pdf).is_ok());
+    assert!(external.verify(b"tampered").is_err());
+    assert_eq!(external.embedded_content().unwrap(), None);
+
+    let embedded = DocumentReference::embedded("doc-2", "application/pdf", pdf);
+    assert_eq!(embedded.embedded_content().unwrap().unwrap(), pdf);
+
+    // Field names follow the invoice schema and survive a round trip
+    let json = serde_json::to_value(&embedded).unwrap();
+    assert_eq!(json["mimeType"], "application/pdf");
+    assert_eq!(json["hash"], document_digest(pdf));
+    assert!(json["embeddedDocument"].is_string());
+    let received: DocumentReference = serde_json::from_value(json).unwrap();
+    assert_eq!(received.embedded_content().unwrap().unwrap(), pdf);
+
+    // Content that does not match its digest is rejected on receipt
+    let mut tampered = embedded.clone();
+    tampered.hash = Some(document_digest(b"another document"));
+    assert!(tampered.embedded_content().is_err());
+
+    // A reference without a digest cannot be verified
+    let unhashed = DocumentReference::new("doc-3");
+    assert!(unhashed.verify(pdf).is_err());
+}
@@ -7,7 +7,7 @@
 
 use tap_msg::didcomm::PlainMessage;
 use tap_msg::message::tap_message_trait::TapMessageBody;
-use tap_msg::message::{Agent, Capture, Lock, Party, TapMessage};
+use tap_msg::message::{Agent, AgentRole, Capture, Lock, Party, TapMessage};
 
 #[test]
 fn test_lock_payment_guarantee_flow() {
@@ -43,7 +43,7 @@ fn test_lock_payment_guarantee_flow() {
     assert!(lock.validate().is_ok());
 
     let escrow_agent = lock.escrow_agent().unwrap();
-    assert_eq!(escrow_agent.role, Some("EscrowAgent".to_string()));
+    assert_eq!(escrow_agent.role, Some(AgentRole::EscrowAgent));
     assert_eq!(escrow_agent.id, "did:web:paymentprocessor.example");
 
     let capture = Capture::with_amount("95.00".to_string())
@@ -5,7 +5,9 @@ use std::str::FromStr;
 use tap_caip::AssetId;
 use tap_msg::message::tap_message_trait::Authorizable;
 use tap_msg::message::tap_message_trait::TapMessageBody;
-use tap_msg::message::{Agent, Party, Payment, PaymentBuilder, Transfer, UpdateParty};
+use tap_msg::message::{
+    Agent, Party, PartyType, Payment, PaymentBuilder, Reject, RejectionCode, Transfer, UpdateParty,
+};
 
 // Helper function to create a simple agent
 
@@ -71,6 +73,30 @@ fn test_create_message() {
 }
 
 // --- Payment Tests Module ---
+#[test]
+fn test_reject_with_code() {
+    let reject = Reject::with_code("tx-1", RejectionCode::MissingTravelRuleData, "No IVMS101");
+    assert!(reject.validate().is_ok());
+
+    let json = serde_json::to_value(&reject).unwrap();
+    assert_eq!(json["code"], "missing_travel_rule_data");
+    let parsed: Reject = serde_json::from_value(json).unwrap();
+    assert_eq!(parsed.code, Some(RejectionCode::MissingTravelRuleData));
+
+    let minimal = serde_json::to_value(Reject::minimal("tx-1")).unwrap();
+    assert!(minimal.get("code").is_none());
+
+    for code in RejectionCode::ALL {
+        assert_eq!(RejectionCode::from_str(code.as_str()).unwrap(), code);
+        assert_eq!(serde_json::to_value(code).unwrap(), code.as_str());
+    }
+    assert!(RejectionCode::from_str("bored").is_err());
+    assert!(serde_json::from_value::<Reject>(
+        serde_json::json!({ "transaction_id": "tx-1", "code": "bored" })
+    )
+    .is_err());
+}
+
 #[cfg(test)]
 mod payment_tests {
     use super::*;
@@ -194,6 +220,7 @@ mod payment_tests {
         let reject_reason = "Insufficient funds".to_string();
         let reject = tap_msg::message::Reject {
             transaction_id: payment.transaction_id.clone().unwrap(),
+            code: None,
             reason: Some(format!("{}: {}", reject_code, reject_reason)),
         };
         assert_eq!(reject.reason, Some("E001: Insufficient funds".to_string()));
@@ -203,6 +230,7 @@ mod payment_tests {
             transaction_id: payment.transaction_id.clone().unwrap(),
             settlement_id: Some("tx-abc".to_string()),
             amount: Some("100.0".to_string()),
+            settled_asset: None,
         };
         assert_eq!(settle.settlement_id, Some("tx-abc".to_string()));
         assert_eq!(settle.amount, Some("100.0".to_string()));
@@ -213,7 +241,7 @@ mod payment_tests {
 
         let update_party = UpdateParty {
             transaction_id: transaction_id.clone().unwrap(),
-            party_type: "beneficiary".to_string(),
+            party_type: PartyType::Beneficiary,
             party: updated_participant.clone(),
             context: None,
         };
@@ -134,7 +134,7 @@ fn test_connect_message() {
     };
 
     let constraints = ConnectionConstraints {
-        purposes: Some(vec!["trading".to_string()]),
+        purposes: Some(vec!["trading".into()]),
         category_purposes: None,
         limits: Some(transaction_limits),
         allowed_beneficiaries: None,
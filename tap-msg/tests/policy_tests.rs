@@ -1,8 +1,8 @@
 //! Tests for TAIP-7 policy implementations.
 
 use tap_msg::message::{
-    Agent, Policy, RequireAuthorization, RequirePresentation, RequireProofOfControl,
-    TapMessageBody, TapMessageTrait, UpdatePolicies,
+    Agent, Ivms101Field, Ivms101Requirement, PartyType, Policy, RequireAuthorization,
+    RequirePresentation, RequireProofOfControl, TapMessageBody, TapMessageTrait, UpdatePolicies,
 };
 
 /// Test creating an agent with policies
@@ -255,3 +255,125 @@ fn test_update_policies_didcomm_conversion() {
         _ => panic!("Expected RequireProofOfControl policy"),
     }
 }
+
+/// Test IVMS101 requirements round-trip through RequirePresentation policies
+#[test]
+fn test_ivms101_requirement_policy() {
+    let requirement = Ivms101Requirement::new(PartyType::Beneficiary)
+        .with_fields(vec![
+            Ivms101Field::Name,
+            Ivms101Field::NationalIdentification,
+        ])
+        .with_purpose("Travel rule");
+
+    let policy = Policy::RequirePresentation(requirement.to_policy());
+    let json = serde_json::to_value(&policy).unwrap();
+    assert_eq!(json["@type"], "RequirePresentation");
+    assert_eq!(json["@context"][0], "https://intervasp.org/ivms101");
+    assert_eq!(json["about_party"], "beneficiary");
+    assert_eq!(
+        json["credentials"]["TravelRuleCredential"],
+        serde_json::json!(["name", "nationalIdentification"])
+    );
+
+    let policy: Policy = serde_json::from_value(json).unwrap();
+    assert_eq!(
+        Ivms101Requirement::from_policies(&[policy]),
+        vec![requirement]
+    );
+
+    // Policies without the IVMS101 context are not travel rule requirements
+    let kyc = RequirePresentation {
+        context: Some(vec!["https://schema.org/".to_string()]),
+        ..Default::default()
+    };
+    assert!(kyc.ivms101_requirement().is_none());
+
+    // Without listed credentials, the name is required
+    let name_only = RequirePresentation {
+        context: Some(vec!["https://intervasp.org/ivms101".to_string()]),
+        ..Default::default()
+    };
+    let requirement = name_only.ivms101_requirement().unwrap();
+    assert_eq!(requirement.about_party, None);
+    assert_eq!(requirement.fields, vec![Ivms101Field::Name]);
+}
+
+/// Test the presentation definition generated for an IVMS101 requirement
+#[test]
+fn test_ivms101_presentation_definition() {
+    let requirement = Ivms101Requirement::new(PartyType::Originator)
+        .with_fields(vec![Ivms101Field::Name, Ivms101Field::DateAndPlaceOfBirth]);
+    let definition = requirement.presentation_definition();
+
+    assert_eq!(definition["id"], "ivms101-originator");
+    let fields = definition["input_descriptors"][0]["constraints"]["fields"]
+        .as_array()
+        .unwrap();
+    assert_eq!(fields.len(), 3);
+    assert_eq!(fields[0]["path"][0], "$.type");
+    assert_eq!(
+        fields[1]["path"],
+        serde_json::json!([
+            "$.credentialSubject.originator.naturalPerson.name",
+            "$.credentialSubject.originator.legalPerson.name"
+        ])
+    );
+    // Legal persons have no date of birth
+    assert_eq!(
+        fields[2]["path"],
+        serde_json::json!(["$.credentialSubject.originator.naturalPerson.dateAndPlaceOfBirth"])
+    );
+}
+
+/// Test evaluating presentations against an IVMS101 requirement
+#[test]
+fn test_ivms101_requirement_evaluation() {
+    let requirement = Ivms101Requirement::new(PartyType::Originator).with_fields(vec![
+        Ivms101Field::Name,
+        Ivms101Field::GeographicAddress,
+        Ivms101Field::CountryOfResidence,
+    ]);
+    let name = serde_json::json!({
+        "nameIdentifiers": [{"primaryIdentifier": "Smith", "nameIdentifierType": "LEGL"}]
+    });
+
+    let complete = serde_json::json!({
+        "verifiableCredential": [{
+            "credentialSubject": {
+                "originator": {
+                    "naturalPerson": {
+                        "name": name,
+                        "geographicAddresses": [{"country": "US"}],
+                        "countryOfResidence": "US"
+                    }
+                }
+            }
+        }]
+    });
+    let evaluation = requirement.evaluate(&complete);
+    assert!(evaluation.satisfied);
+    assert!(evaluation.missing.is_empty());
+
+    // The country of residence does not apply to legal persons
+    let legal = serde_json::json!({
+        "credentialSubject": {
+            "originator": {
+                "legalPerson": {"name": name, "geographicAddress": []}
+            }
+        }
+    });
+    let evaluation = requirement.evaluate(&legal);
+    assert!(!evaluation.satisfied);
+    assert_eq!(evaluation.missing, vec![Ivms101Field::GeographicAddress]);
+
+    // Data about another party does not count
+    let beneficiary = serde_json::json!({
+        "verifiableCredential": [{
+            "credentialSubject": {"beneficiary": {"naturalPerson": {"name": name}}}
+        }]
+    });
+    let evaluation = requirement.evaluate(&beneficiary);
+    assert!(!evaluation.satisfied);
+    assert_eq!(evaluation.missing, requirement.fields);
+}
@@ -8,8 +8,8 @@ use tap_caip::AssetId;
 use tap_msg::error::Error;
 use tap_msg::message::tap_message_trait::{Connectable, TapMessageBody}; // Import trait for methods
 use tap_msg::message::{
-    Agent, Authorize, Connect, ConnectionConstraints, Party, Payment, PaymentBuilder, Reject,
-    Settle, TransactionLimits, Transfer, UpdateParty,
+    Agent, Authorize, Connect, ConnectionConstraints, Party, PartyType, Payment, PaymentBuilder,
+    Reject, Settle, TransactionLimits, Transfer, UpdateParty,
 };
 use tap_msg::Result;
 
@@ -102,6 +102,7 @@ fn test_full_tap_flow() -> Result<()> {
         transaction_id: transfer_message.id.clone(),
         settlement_id: Some("tx-12345".to_string()),
         amount: Some("100".to_string()),
+        settled_asset: None,
     };
 
     // Convert to DIDComm message
@@ -212,6 +213,7 @@ fn test_payment_flow() {
     // Reject the payment
     let reject_body = Reject {
         transaction_id: payment_message2.id.clone(),
+        code: None,
         reason: Some("REJECT-001: Rejected due to compliance issues".to_string()),
     };
 
@@ -323,6 +325,7 @@ fn test_complex_message_flow() -> Result<()> {
     let _transfer_body_1: Transfer = serde_json::from_value(transfer_body_json_1.clone())?;
     let reject = Reject {
         transaction_id: transfer_messages[1].id.clone(),
+        code: None,
         reason: Some("REJECT-002: Rejected due to amount too high".to_string()),
     };
 
@@ -351,6 +354,7 @@ fn test_complex_message_flow() -> Result<()> {
         transaction_id: transfer_messages[2].id.clone(),
         settlement_id: Some("tx-67890".to_string()),
         amount: Some("50".to_string()),
+        settled_asset: None,
     };
 
     // Convert to DIDComm message
@@ -368,7 +372,7 @@ fn test_complex_message_flow() -> Result<()> {
 
     let update_party = UpdateParty {
         transaction_id: transfer_messages[2].id.clone(),
-        party_type: "originator".to_string(),
+        party_type: PartyType::Originator,
         party: updated_originator.clone(),
         context: None,
     };
@@ -428,7 +432,7 @@ fn create_test_connect() -> Connect {
     };
 
     let constraints = ConnectionConstraints {
-        purposes: Some(vec!["trading".to_string()]),
+        purposes: Some(vec!["trading".into()]),
         category_purposes: None,
         limits: Some(transaction_limits),
         allowed_beneficiaries: None,
@@ -499,3 +503,37 @@ fn create_test_payment_request() -> Payment {
     payment.expiry = Some("2023-12-31T23:59:59Z".to_string());
     payment
 }
+
+/// A Payment in a fiat currency settled with a stablecoin names the settled asset
+#[test]
+fn test_cross_asset_settle() {
+    let usdc = "eip155:1/erc20:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    let settle = Settle::new(
+        "payment-001",
+        "eip155:1:tx/0x3edb98c24d46d148eb926c714f4fbaa117c47b0c0821f38bfce9763604457c33",
+    )
+    .with_settled_asset(usdc, "100.05");
+    assert!(settle.validate().is_ok());
+
+    let json = serde_json::to_value(&settle).unwrap();
+    assert_eq!(json["settledAsset"], usdc);
+    assert_eq!(json["amount"], "100.05");
+    let parsed: Settle = serde_json::from_value(json).unwrap();
+    assert_eq!(parsed.settled_asset.as_deref(), Some(usdc));
+
+    // Same-asset settlements leave it out
+    let json = serde_json::to_value(Settle::minimal("payment-001")).unwrap();
+    assert!(json.get("settledAsset").is_none());
+
+    // Fiat settlements use an ISO 4217 code
+    assert!(Settle::minimal("payment-001")
+        .with_settled_asset("EUR", "92.10")
+        .validate()
+        .is_ok());
+
+    let mut invalid = Settle::minimal("payment-001").with_settled_asset("usdc", "100");
+    assert!(invalid.validate().is_err());
+    invalid.settled_asset = Some(usdc.to_string());
+    invalid.amount = None;
+    assert!(invalid.validate().is_err());
+}
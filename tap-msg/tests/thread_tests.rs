@@ -206,7 +206,7 @@ fn test_confirm_relationship() -> Result<()> {
         transaction_id: transfer_id.to_string(),
         agent_id: _bob_did.to_string(),
         for_entity: _org_did.to_string(),
-        role: Some("custodian".to_string()),
+        role: Some("custodian".into()),
     };
 
     // Validate the message using the trait method (auto-generated)
@@ -235,7 +235,7 @@ fn test_confirm_relationship() -> Result<()> {
     assert_eq!(extracted_confirm.transaction_id, transfer_id);
     assert_eq!(extracted_confirm.agent_id, _bob_did);
     assert_eq!(extracted_confirm.for_entity, _org_did);
-    assert_eq!(extracted_confirm.role, Some("custodian".to_string()));
+    assert_eq!(extracted_confirm.role, Some("custodian".into()));
 
     // Test using the Authorizable trait
     let transfer = Transfer {
@@ -278,7 +278,7 @@ fn test_confirm_relationship() -> Result<()> {
         transaction_id: transfer_id.to_string(),
         agent_id: _bob_did.to_string(),
         for_entity: _org_did.to_string(),
-        role: Some("custodian".to_string()),
+        role: Some("custodian".into()),
     };
 
     // Create a DIDComm message from the confirm_relationship
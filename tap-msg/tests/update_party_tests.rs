@@ -4,7 +4,7 @@ use std::collections::HashMap;
 use std::str::FromStr;
 use tap_caip::AssetId;
 use tap_msg::message::tap_message_trait::TapMessageBody;
-use tap_msg::message::{Agent, Party, Transfer, UpdateParty};
+use tap_msg::message::{Agent, Party, PartyType, Transfer, UpdateParty};
 
 #[test]
 fn test_update_party_creation() {
@@ -18,7 +18,7 @@ fn test_update_party_creation() {
     // Create an UpdateParty message
     let update_party = UpdateParty {
         transaction_id: transaction_id.to_string(),
-        party_type: "beneficiary".to_string(),
+        party_type: PartyType::Beneficiary,
         party: updated_participant.clone(),
         context: None,
     };
@@ -45,7 +45,7 @@ fn test_update_party_validation() {
     // Test with valid data
     let valid_update = UpdateParty {
         transaction_id: "transfer-123".to_string(),
-        party_type: "beneficiary".to_string(),
+        party_type: PartyType::Beneficiary,
         party: Party::new("did:key:z6MkmRsjkKHNrBiVz5mhiqhJVYf9E9mxg3MVGqgqMkRwCJd6"),
         context: None,
     };
@@ -62,7 +62,7 @@ fn test_update_party_validation() {
 
     // Test with empty party_type
     let invalid_party_type = UpdateParty {
-        party_type: "".to_string(),
+        party_type: PartyType::from(""),
         ..valid_update.clone()
     };
 
@@ -82,7 +82,7 @@ fn test_update_party_didcomm_conversion() {
     // Create a valid UpdateParty message
     let update_party = UpdateParty {
         transaction_id: "transfer-456".to_string(),
-        party_type: "originator".to_string(),
+        party_type: PartyType::Originator,
         party: Party::new("did:key:z6MkmRsjkKHNrBiVz5mhiqhJVYf9E9mxg3MVGqgqMkRwCJd6"),
         context: None,
     };
@@ -118,7 +118,7 @@ fn test_update_party_beneficiary() {
 
     let update_party = UpdateParty {
         transaction_id: transaction_id.clone(),
-        party_type: "beneficiary".to_string(),
+        party_type: PartyType::Beneficiary,
         party: updated_participant.clone(),
         context: None,
     };
@@ -143,7 +143,7 @@ fn test_update_party_intermediary() {
 
     let update_party = UpdateParty {
         transaction_id: transaction_id.clone(),
-        party_type: "intermediary".to_string(),
+        party_type: "intermediary".into(),
         party: updated_participant.clone(),
         context: None,
     };
@@ -177,7 +177,7 @@ fn test_authorizable_with_update_party() {
     // Create the first UpdateParty message manually, referencing the transfer message ID
     let update_party = UpdateParty {
         transaction_id: transfer_message.id.clone(), // Use the ID from the message
-        party_type: "beneficiary".to_string(),
+        party_type: PartyType::Beneficiary,
         party: updated_participant_1.clone(),
         context: None,
     };
@@ -194,7 +194,7 @@ fn test_authorizable_with_update_party() {
     // Create the second UpdateParty message
     let update_party_from_message = UpdateParty {
         transaction_id: transfer_message.id.clone(), // Still references the same transfer
-        party_type: "originator".to_string(),
+        party_type: PartyType::Originator,
         party: updated_participant_2.clone(),
         context: None,
     };
@@ -54,6 +54,7 @@ fn stress_test(c: &mut Criterion) {
         workers: 16,
         channel_capacity: 1000,
         worker_timeout: Duration::from_secs(30),
+        ..Default::default()
     };
 
     let node_config = NodeConfig {
@@ -70,6 +71,70 @@ fn stress_test(c: &mut Criterion) {
         #[cfg(feature = "storage")]
         tap_root: None,
         decision_mode: Default::default(),
+        #[cfg(feature = "storage")]
+        retention_policy: None,
+        #[cfg(feature = "storage")]
+        backup_policy: None,
+        #[cfg(feature = "storage")]
+        transaction_expiry: None,
+        #[cfg(feature = "storage")]
+        delivery_retry: None,
+        #[cfg(feature = "storage")]
+        anomaly_detection: None,
+        #[cfg(feature = "storage")]
+        response_timers: None,
+        #[cfg(feature = "storage")]
+        message_encryption: None,
+        #[cfg(feature = "storage")]
+        policy_engine: None,
+        #[cfg(feature = "storage")]
+        valuation: None,
+        settlement_conversion: None,
+        #[cfg(feature = "storage")]
+        address_reuse: None,
+        #[cfg(feature = "storage")]
+        settlement_addresses: None,
+        #[cfg(feature = "storage")]
+        storage_quotas: Default::default(),
+        #[cfg(feature = "storage")]
+        shared_storage: None,
+        #[cfg(feature = "scripting")]
+        script_hook: None,
+        #[cfg(feature = "storage")]
+        settlement_address_strictness: Default::default(),
+        clock: None,
+        #[cfg(feature = "storage")]
+        timestamp_drift: Default::default(),
+        #[cfg(feature = "native")]
+        clock_health: None,
+        id_generator: None,
+        enrichment: None,
+        message_types: None,
+        #[cfg(feature = "storage")]
+        async_validators: None,
+        #[cfg(feature = "storage")]
+        agent_groups: Vec::new(),
+        #[cfg(feature = "storage")]
+        event_sourcing: false,
+        #[cfg(feature = "storage")]
+        mailbox: Default::default(),
+        problem_reports: false,
+        answer_handshakes: false,
+        #[cfg(feature = "storage")]
+        read_replicas: Default::default(),
+        #[cfg(feature = "storage")]
+        storage_recovery: Default::default(),
+        auto_register_stored_agents: false,
+        key_storage_path: None,
+        credential_verification: None,
+        key_attestation_verification: None,
+        presentation_verification: None,
+        processing_timeouts: Default::default(),
+        intake: Default::default(),
+        #[cfg(all(feature = "native", feature = "storage"))]
+        federation: None,
+        #[cfg(feature = "storage")]
+        scheduler: Default::default(),
     };
 
     // For testing, we'll create some DIDs that don't rely on external resolvers
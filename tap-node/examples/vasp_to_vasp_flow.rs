@@ -0,0 +1,295 @@
+//! End-to-end transfer between two VASPs running separate TAP nodes
+//!
+//! This example shows:
+//! - Two in-process nodes exchanging signed messages over loopback HTTP
+//! - A Transfer from the originating VASP to the beneficiary VASP
+//! - The beneficiary VASP requesting Travel Rule data with UpdatePolicies
+//! - The originating VASP presenting IVMS101 data, validated on receipt
+//! - Authorization by both VASPs and settlement by the originator
+//!
+//! Run with:
+//!
+//! ```sh
+//! cargo run -p tap-node --example vasp_to_vasp_flow --features test-harness
+//! ```
+//!
+//! Set `RUST_LOG=debug` to see every step the nodes take.
+
+use std::collections::HashMap;
+use std::time::Duration;
+use tap_ivms101::builder::{
+    GeographicAddressBuilder, NaturalPersonBuilder, NaturalPersonNameBuilder,
+};
+use tap_ivms101::types::AddressType;
+use tap_ivms101::Person;
+use tap_msg::didcomm::Attachment;
+use tap_msg::message::{
+    Agent as MessageAgent, Authorize, DIDCommPresentation, Party, Policy, RequirePresentation,
+    Settle, TapMessageBody, Transfer, UpdatePolicies,
+};
+use tap_node::state_machine::fsm::DecisionMode;
+use tap_node::testing::TestNode;
+use tap_node::{NodeConfig, NodeEvent};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    println!("=== VASP to VASP Transfer ===\n");
+
+    // Step 1: Start one node per VASP. Decisions are published as events
+    // so that each step below is taken explicitly.
+    println!("1. Starting a node for each VASP...");
+    let config = NodeConfig {
+        decision_mode: DecisionMode::EventBus,
+        ..Default::default()
+    };
+    let mut originator_node = TestNode::start("originator", config.clone()).await?;
+    let mut beneficiary_node = TestNode::start("beneficiary", config).await?;
+    println!("   - Originating VASP node: {}", originator_node.endpoint());
+    println!(
+        "   - Beneficiary VASP node: {}",
+        beneficiary_node.endpoint()
+    );
+
+    // Step 2: Register an agent per VASP and connect the nodes
+    println!("\n2. Registering VASP agents...");
+    let originating_vasp = originator_node.add_agent().await?;
+    let beneficiary_vasp = beneficiary_node.add_agent().await?;
+    originator_node.connect(&beneficiary_node);
+    println!("   - Originating VASP: {}", originating_vasp);
+    println!("   - Beneficiary VASP: {}", beneficiary_vasp);
+
+    // Step 3: The originating VASP sends a Transfer on behalf of Alice
+    println!("\n3. Originating VASP sends a Transfer...");
+    let alice = Party::new("did:example:alice");
+    let bob = Party::new("did:example:bob");
+    let transfer = Transfer {
+        asset: "eip155:1/slip44:60".parse()?,
+        originator: Some(alice.clone()),
+        beneficiary: Some(bob.clone()),
+        amount: "1.25".to_string(),
+        agents: vec![
+            MessageAgent::new(&originating_vasp, "originating_vasp", &alice.id),
+            MessageAgent::new(&beneficiary_vasp, "beneficiary_vasp", &bob.id),
+        ],
+        memo: None,
+        settlement_id: None,
+        expiry: None,
+        transaction_value: None,
+        transaction_id: None,
+        connection_id: None,
+        metadata: HashMap::new(),
+    };
+    let transfer_message =
+        transfer.to_didcomm_with_route(&originating_vasp, [beneficiary_vasp.as_str()])?;
+    let transaction_id = transfer_message.id.clone();
+    originator_node
+        .node()
+        .send_message(originating_vasp.clone(), transfer_message)
+        .await?;
+    println!("   - Transaction: {}", transaction_id);
+
+    let decision = beneficiary_node
+        .wait_for_event(TIMEOUT, |event| {
+            matches!(event, NodeEvent::DecisionRequired { .. })
+        })
+        .await?;
+    if let NodeEvent::DecisionRequired {
+        transaction_state, ..
+    } = decision.as_ref()
+    {
+        println!(
+            "   - Beneficiary node awaits a decision in state {}",
+            transaction_state
+        );
+    }
+
+    // Step 4: The beneficiary VASP requires Travel Rule data before authorizing
+    println!("\n4. Beneficiary VASP requests Travel Rule data...");
+    let policy = Policy::RequirePresentation(RequirePresentation {
+        context: Some(vec!["https://intervasp.org/ivms101".to_string()]),
+        credentials: None,
+        purpose: Some("Travel Rule Compliance - FATF R.16".to_string()),
+        from: None,
+        from_role: Some(vec!["originating_vasp".to_string()]),
+        from_agent: None,
+        about_party: Some("originator".to_string()),
+        about_agent: None,
+        presentation_definition: None,
+    });
+    let update_policies = UpdatePolicies::new(&transaction_id, vec![policy]);
+    beneficiary_node
+        .node()
+        .send_message(
+            beneficiary_vasp.clone(),
+            update_policies
+                .to_didcomm_with_route(&beneficiary_vasp, [originating_vasp.as_str()])?,
+        )
+        .await?;
+    println!("   - Sent UpdatePolicies with RequirePresentation for IVMS101");
+
+    // Step 5: The originating VASP presents IVMS101 data about Alice
+    println!("\n5. Originating VASP presents IVMS101 data...");
+    let alice_ivms = Person::NaturalPerson(
+        NaturalPersonBuilder::new()
+            .name(
+                NaturalPersonNameBuilder::new()
+                    .legal_name("Smith", "Alice")
+                    .build()?,
+            )
+            .add_address(
+                GeographicAddressBuilder::new()
+                    .address_type(AddressType::Home)
+                    .street_name("123 Main Street")
+                    .town_name("New York")
+                    .post_code("10001")
+                    .country("US")
+                    .build()?,
+            )
+            .country_of_residence("US")
+            .build()?,
+    );
+    let credentials = serde_json::json!({
+        "@context": [
+            "https://www.w3.org/2018/credentials/v1",
+            "https://intervasp.org/ivms101"
+        ],
+        "type": ["VerifiablePresentation", "PresentationSubmission"],
+        "verifiableCredential": [{
+            "@context": [
+                "https://www.w3.org/2018/credentials/v1",
+                "https://intervasp.org/ivms101"
+            ],
+            "type": ["VerifiableCredential", "TravelRuleCredential"],
+            "issuer": originating_vasp,
+            "credentialSubject": { "originator": alice_ivms }
+        }]
+    });
+    let attachment = Attachment::json(credentials)
+        .id("ivms101-vp".to_string())
+        .media_type("application/json".to_string())
+        .format("dif/presentation-exchange/submission@v1.0".to_string())
+        .finalize();
+    let presentation = DIDCommPresentation::new(
+        vec!["dif/presentation-exchange/submission@v1.0".to_string()],
+        vec![attachment],
+        Some(transaction_id.clone()),
+    );
+    let mut presentation_message =
+        presentation.to_didcomm_with_route(&originating_vasp, [beneficiary_vasp.as_str()])?;
+    presentation_message.thid = Some(transaction_id.clone());
+    originator_node
+        .node()
+        .send_message(originating_vasp.clone(), presentation_message)
+        .await?;
+
+    let validated = beneficiary_node
+        .wait_for_event(TIMEOUT, |event| {
+            matches!(event, NodeEvent::TravelRuleDataValidated { .. })
+        })
+        .await?;
+    if let NodeEvent::TravelRuleDataValidated { role, report, .. } = validated.as_ref() {
+        println!(
+            "   - Beneficiary node validated {} data: {} error(s), {} warning(s)",
+            role.as_deref().unwrap_or("party"),
+            report.errors().count(),
+            report.warnings().count()
+        );
+    }
+
+    // Step 6: The originating VASP confirms the transfer with its own Authorize
+    println!("\n6. Originating VASP authorizes the transfer...");
+    originator_node
+        .node()
+        .send_message(
+            originating_vasp.clone(),
+            Authorize::new(&transaction_id)
+                .to_didcomm_with_route(&originating_vasp, [beneficiary_vasp.as_str()])?,
+        )
+        .await?;
+    println!("   - Sent Authorize");
+
+    // Step 7: The beneficiary VASP authorizes and names a settlement address
+    println!("\n7. Beneficiary VASP authorizes the transfer...");
+    let authorize = Authorize::with_settlement_address(
+        &transaction_id,
+        "eip155:1:0x1234567890123456789012345678901234567890",
+    );
+    beneficiary_node
+        .node()
+        .send_message(
+            beneficiary_vasp.clone(),
+            authorize.to_didcomm_with_route(&beneficiary_vasp, [originating_vasp.as_str()])?,
+        )
+        .await?;
+    println!("   - Sent Authorize with settlement address");
+
+    let decision = originator_node
+        .wait_for_event(TIMEOUT, |event| {
+            matches!(event, NodeEvent::DecisionRequired { transaction_state, .. } if transaction_state == "ready_to_settle")
+        })
+        .await?;
+    if let NodeEvent::DecisionRequired { decision, .. } = decision.as_ref() {
+        println!(
+            "   - Originator node awaits a {} decision",
+            decision["type"].as_str().unwrap_or("settlement")
+        );
+    }
+
+    // Step 8: The originating VASP settles on chain and reports it
+    println!("\n8. Originating VASP settles the transfer...");
+    let settle = Settle::with_amount(&transaction_id, "eip155:1:tx/0xabc123", "1.25");
+    originator_node
+        .node()
+        .send_message(
+            originating_vasp.clone(),
+            settle.to_didcomm_with_route(&originating_vasp, [beneficiary_vasp.as_str()])?,
+        )
+        .await?;
+    println!("   - Sent Settle");
+
+    beneficiary_node
+        .wait_for_event(TIMEOUT, |event| {
+            matches!(
+                event,
+                NodeEvent::TransactionStateChanged { new_state, .. } if new_state == "settled"
+            )
+        })
+        .await?;
+    println!("   - Beneficiary node recorded the settlement");
+
+    // Step 9: Each VASP holds the transaction in its own databases
+    println!("\n9. Checking each VASP's records...");
+    for (test_node, vasp) in [
+        (&originator_node, &originating_vasp),
+        (&beneficiary_node, &beneficiary_vasp),
+    ] {
+        let node = test_node.node();
+        let status = node
+            .storage()
+            .ok_or("storage is not available")?
+            .get_transaction_by_id(&transaction_id)
+            .await?
+            .map(|tx| tx.status.to_string())
+            .unwrap_or_else(|| "missing".to_string());
+        let messages = node
+            .agent_storage_manager()
+            .ok_or("agent storage is not available")?
+            .get_agent_storage(vasp)
+            .await?
+            .list_messages(100, 0, None)
+            .await?;
+        println!(
+            "   - {} node: transaction {}, {} messages logged",
+            test_node.name(),
+            status,
+            messages.len()
+        );
+    }
+
+    println!("\n=== Transfer Complete ===");
+    Ok(())
+}
@@ -0,0 +1,27 @@
+-- Tombstones for customers whose personal data has been erased.
+-- The customer row itself is kept (with personal fields redacted) so that
+-- transactions referencing the customer remain intact. The profile hash lets
+-- an auditor prove that a previously disclosed record matches what was erased.
+
+CREATE TABLE IF NOT EXISTS customer_erasures (
+    customer_id TEXT PRIMARY KEY,
+    agent_did TEXT NOT NULL,
+    profile_hash TEXT NOT NULL, -- SHA-256 of the customer record before erasure
+    name_hash TEXT,
+    reason TEXT,
+    erased_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_customer_erasures_agent_did ON customer_erasures(agent_did);
+CREATE INDEX IF NOT EXISTS idx_customer_erasures_erased_at ON customer_erasures(erased_at);
+
+-- Only stamp updated_at when the statement did not set it explicitly, so
+-- retention purges can clear data without resetting a profile's age.
+DROP TRIGGER IF EXISTS update_customers_updated_at;
+CREATE TRIGGER update_customers_updated_at
+    AFTER UPDATE ON customers
+    FOR EACH ROW
+    WHEN NEW.updated_at IS OLD.updated_at
+BEGIN
+    UPDATE customers SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
@@ -0,0 +1,27 @@
+-- Manual review queue for transactions held by the policy engine.
+-- Each row is a transaction awaiting a reviewer's approval on behalf of one
+-- of our agents. Approving sends Authorize, rejecting sends Reject.
+
+CREATE TABLE IF NOT EXISTS review_queue (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    transaction_id TEXT NOT NULL,
+    agent_did TEXT NOT NULL,
+    rule TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    details_json TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN (
+        'pending',
+        'approved',
+        'rejected',
+        'expired'
+    )),
+    reviewer TEXT,
+    review_note TEXT,
+    message_id TEXT, -- Authorize or Reject message sent for the review
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+    reviewed_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_review_queue_transaction_id ON review_queue(transaction_id);
+CREATE INDEX IF NOT EXISTS idx_review_queue_agent_did ON review_queue(agent_did);
+CREATE INDEX IF NOT EXISTS idx_review_queue_status ON review_queue(status);
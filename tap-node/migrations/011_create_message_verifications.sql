@@ -0,0 +1,17 @@
+-- Signature verifications of incoming signed messages.
+-- Records which key verified each message and a hash of the signer's DID
+-- document at the time, so auditors can confirm how a message was verified.
+
+CREATE TABLE IF NOT EXISTS message_verifications (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    message_id TEXT NOT NULL,
+    signer_did TEXT NOT NULL,
+    kid TEXT NOT NULL,
+    algorithm TEXT NOT NULL,
+    did_doc_hash TEXT NOT NULL,
+    verified_at TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_message_verifications_message_id ON message_verifications(message_id);
+CREATE INDEX IF NOT EXISTS idx_message_verifications_signer_did ON message_verifications(signer_did);
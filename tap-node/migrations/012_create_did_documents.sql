@@ -0,0 +1,12 @@
+-- DID documents pinned at message verification time.
+-- Keyed by the SHA-256 hash recorded in message_verifications, so past
+-- verifications stay reproducible after a counterparty rotates or removes keys.
+
+CREATE TABLE IF NOT EXISTS did_documents (
+    did_doc_hash TEXT PRIMARY KEY,
+    did TEXT NOT NULL,
+    document_json TEXT NOT NULL,
+    pinned_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_did_documents_did ON did_documents(did);
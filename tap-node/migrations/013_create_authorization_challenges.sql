@@ -0,0 +1,24 @@
+-- Nonces bound to the authorization URLs sent in AuthorizationRequired
+-- messages. Redeeming a nonce through the node's HTTP callback authorizes the
+-- transaction on behalf of the agent that issued the URL.
+
+CREATE TABLE IF NOT EXISTS authorization_challenges (
+    nonce TEXT PRIMARY KEY,
+    transaction_id TEXT NOT NULL,
+    agent_did TEXT NOT NULL, -- Our agent that authorizes once the nonce is redeemed
+    recipient_did TEXT NOT NULL, -- Agent the AuthorizationRequired was sent to
+    party_type TEXT, -- Party type required to open the URL, e.g. customer
+    authorization_url TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN (
+        'pending',
+        'redeemed',
+        'expired'
+    )),
+    message_id TEXT, -- Authorize message sent when the nonce was redeemed
+    expires_at TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+    redeemed_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_authorization_challenges_transaction_id ON authorization_challenges(transaction_id);
+CREATE INDEX IF NOT EXISTS idx_authorization_challenges_status ON authorization_challenges(status);
@@ -0,0 +1,8 @@
+-- Reason of the Reject message that failed a transaction. The code is one of
+-- the standardized rejection codes of the Reject message; the reason is its
+-- free text.
+
+ALTER TABLE transactions ADD COLUMN rejection_code TEXT;
+ALTER TABLE transactions ADD COLUMN rejection_reason TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_transactions_rejection_code ON transactions(rejection_code);
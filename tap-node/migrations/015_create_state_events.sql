@@ -0,0 +1,13 @@
+-- Append-only log of mutations of the transactions, transaction_agents and
+-- deliveries tables, written in event sourcing mode. Replaying the log in
+-- sequence order reconstructs those tables.
+
+CREATE TABLE IF NOT EXISTS state_events (
+    sequence INTEGER PRIMARY KEY AUTOINCREMENT,
+    event_type TEXT NOT NULL,
+    transaction_id TEXT, -- Reference ID of the transaction the event belongs to
+    event_json TEXT NOT NULL,
+    recorded_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_state_events_transaction_id ON state_events(transaction_id);
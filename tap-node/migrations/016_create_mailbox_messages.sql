@@ -0,0 +1,25 @@
+-- Messages held for remote agents that poll the node for their messages
+-- instead of accepting inbound HTTP. Each message gets an increasing sequence
+-- number that the agent uses as its cursor.
+
+CREATE TABLE IF NOT EXISTS mailbox_messages (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    recipient_did TEXT NOT NULL, -- Remote agent that polls for the message
+    sender_did TEXT NOT NULL, -- Our agent that sent the message
+    message_id TEXT NOT NULL,
+    message_text TEXT NOT NULL, -- Signed or encrypted message
+    delivery_id INTEGER, -- Delivery record in the sender's database
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN (
+        'pending',
+        'fetched',
+        'delivered',
+        'expired'
+    )),
+    expires_at TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+    fetched_at TEXT,
+    delivered_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_mailbox_messages_recipient ON mailbox_messages(recipient_did, status, seq);
+CREATE INDEX IF NOT EXISTS idx_mailbox_messages_expires_at ON mailbox_messages(status, expires_at);
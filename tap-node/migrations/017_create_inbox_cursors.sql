@@ -0,0 +1,8 @@
+-- Read cursors of agents over the incoming messages in the message log.
+-- Messages up to and including `read_up_to` (a messages.id) count as read.
+
+CREATE TABLE IF NOT EXISTS inbox_cursors (
+    agent_did TEXT PRIMARY KEY,
+    read_up_to INTEGER NOT NULL DEFAULT 0,
+    updated_at TEXT NOT NULL
+);
@@ -0,0 +1,25 @@
+-- Merge proposals for customer records that describe the same party.
+-- Confirming a proposal folds the duplicate into the surviving customer: its
+-- identifiers and relationships move over and the duplicate row is removed.
+-- The duplicate record is kept in the merge row, so the lineage of a merged
+-- profile stays traceable. Rejected proposals are kept so the same pair is
+-- not proposed again.
+
+CREATE TABLE IF NOT EXISTS customer_merges (
+    id TEXT PRIMARY KEY,
+    agent_did TEXT NOT NULL,
+    primary_customer_id TEXT NOT NULL,
+    duplicate_customer_id TEXT NOT NULL,
+    confidence REAL NOT NULL,
+    signals TEXT NOT NULL, -- JSON array of the matched signals
+    status TEXT NOT NULL DEFAULT 'proposed' CHECK (status IN ('proposed', 'confirmed', 'rejected')),
+    duplicate_snapshot TEXT, -- JSON of the duplicate record when the merge was confirmed
+    created_at TEXT NOT NULL,
+    resolved_at TEXT,
+
+    UNIQUE(primary_customer_id, duplicate_customer_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_customer_merges_agent_did ON customer_merges(agent_did);
+CREATE INDEX IF NOT EXISTS idx_customer_merges_status ON customer_merges(status);
+CREATE INDEX IF NOT EXISTS idx_customer_merges_duplicate ON customer_merges(duplicate_customer_id);
@@ -0,0 +1,16 @@
+-- Messages set aside after their processing exceeded a timeout.
+-- A message whose verification, validation or hooks hang (e.g. a DID that
+-- resolves via an unresponsive endpoint) is cancelled and, if the node is
+-- configured to, kept here for inspection or replay instead of being retried.
+
+CREATE TABLE IF NOT EXISTS dead_letters (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    message_id TEXT, -- NULL when the message could not be parsed before it timed out
+    raw_message TEXT NOT NULL,
+    stage TEXT NOT NULL CHECK (stage IN ('verification', 'validation', 'hook')),
+    reason TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_dead_letters_message_id ON dead_letters(message_id);
+CREATE INDEX IF NOT EXISTS idx_dead_letters_created_at ON dead_letters(created_at);
@@ -0,0 +1,17 @@
+-- Connect requests (TAIP-15) sent or received by the node's agents.
+-- A Connect is not a transaction, so it is tracked here rather than in the
+-- transactions table; Authorize, Reject and AuthorizationRequired replies
+-- that reference it update its status.
+
+CREATE TABLE IF NOT EXISTS connections (
+    reference_id TEXT PRIMARY KEY, -- ID of the Connect message
+    from_did TEXT NOT NULL,
+    to_dids TEXT NOT NULL, -- JSON array of the recipients
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'authorization_required', 'authorized', 'rejected')),
+    message_json TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_connections_from_did ON connections(from_did);
+CREATE INDEX IF NOT EXISTS idx_connections_status ON connections(status);
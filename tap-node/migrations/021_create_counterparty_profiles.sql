@@ -0,0 +1,22 @@
+-- Relationship profiles of counterparties an agent has onboarded.
+-- An onboarding handshake sends a Connect, exchanges supported features and
+-- policies and verifies the counterparty's endpoint with a trust ping; its
+-- result is kept here, one row per counterparty, and replaced when the
+-- handshake is run again.
+
+CREATE TABLE IF NOT EXISTS counterparty_profiles (
+    counterparty_did TEXT PRIMARY KEY,
+    connection_id TEXT NOT NULL, -- ID of the Connect message of the handshake
+    connection_status TEXT NOT NULL CHECK (connection_status IN ('pending', 'authorization_required', 'authorized', 'rejected')),
+    authorization_url TEXT,
+    rejection_reason TEXT,
+    features TEXT, -- JSON array of the disclosed features, NULL if the counterparty did not answer
+    our_policies TEXT NOT NULL, -- JSON array
+    their_policies TEXT NOT NULL, -- JSON array
+    endpoint_verified INTEGER NOT NULL DEFAULT 0,
+    ping_rtt_ms INTEGER,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_counterparty_profiles_connection_id ON counterparty_profiles(connection_id);
@@ -0,0 +1,17 @@
+-- Fiat value of Transfers and Payments at the time they were recorded.
+-- The rate, its source and its timestamp are kept with the value so that
+-- reports do not depend on exchange rates at report time.
+
+CREATE TABLE IF NOT EXISTS transaction_valuations (
+    transaction_id TEXT PRIMARY KEY, -- reference_id of the transaction
+    asset TEXT NOT NULL, -- CAIP-19 asset ID or ISO 4217 currency code
+    amount TEXT NOT NULL, -- amount of the transaction in its asset
+    currency TEXT NOT NULL, -- ISO 4217 code of the fiat currency
+    fiat_amount REAL NOT NULL,
+    rate REAL NOT NULL,
+    rate_source TEXT NOT NULL,
+    rate_timestamp TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_transaction_valuations_created_at ON transaction_valuations(created_at);
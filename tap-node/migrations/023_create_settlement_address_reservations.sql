@@ -0,0 +1,16 @@
+-- Settlement addresses handed out in Authorize messages. An address is
+-- reserved for one transaction and never handed out again.
+
+CREATE TABLE IF NOT EXISTS settlement_address_reservations (
+    chain_id TEXT NOT NULL, -- CAIP-2 chain ID of the address
+    address TEXT NOT NULL, -- address on the chain, without the chain prefix
+    agent_did TEXT NOT NULL, -- our agent that authorized the transaction
+    transaction_id TEXT NOT NULL, -- reference_id of the transaction
+    asset TEXT NOT NULL, -- CAIP-19 asset ID of the transaction
+    derivation_index INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (chain_id, address),
+    UNIQUE (transaction_id, agent_did, chain_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_settlement_address_reservations_transaction ON settlement_address_reservations(transaction_id);
@@ -0,0 +1,19 @@
+-- Transfers and Payments built up before they are sent. The body is the
+-- message body as edited so far; it is validated on every change and sent
+-- once valid.
+
+CREATE TABLE IF NOT EXISTS drafts (
+    id TEXT PRIMARY KEY,
+    agent_did TEXT NOT NULL, -- our agent that will send the draft
+    kind TEXT NOT NULL CHECK (kind IN ('transfer', 'payment')),
+    body JSON NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('invalid', 'valid', 'sent')),
+    validation_errors JSON NOT NULL DEFAULT '[]',
+    expires_at TEXT, -- drafts cannot be sent after this time
+    message_id TEXT, -- ID of the message the draft was sent as
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_drafts_agent_status ON drafts(agent_did, status);
+CREATE INDEX IF NOT EXISTS idx_drafts_expires_at ON drafts(expires_at);
@@ -0,0 +1,24 @@
+-- Agents that took part in a transaction, as set by the Transfer or Payment
+-- and changed by AddAgents, ReplaceAgent and RemoveAgent messages. Rows are
+-- never deleted: the current agent set is the rows without removed_at, and
+-- the rest record who removed or replaced whom and when.
+
+CREATE TABLE IF NOT EXISTS transaction_participants (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    transaction_id TEXT NOT NULL, -- reference_id of the transaction
+    agent_did TEXT NOT NULL,
+    role TEXT, -- role of the agent in the message that added it
+    for_parties JSON NOT NULL DEFAULT '[]', -- parties the agent acts for
+    added_by TEXT NOT NULL, -- DID of the sender of the message that added the agent
+    added_in TEXT NOT NULL, -- ID of the message that added the agent
+    added_at TEXT NOT NULL,
+    removed_by TEXT, -- DID of the sender of the message that removed the agent
+    removed_in TEXT, -- ID of the message that removed the agent
+    removed_at TEXT,
+    replaced_by TEXT -- DID of the agent that replaced this one
+);
+
+CREATE INDEX IF NOT EXISTS idx_transaction_participants_transaction ON transaction_participants(transaction_id);
+CREATE INDEX IF NOT EXISTS idx_transaction_participants_agent ON transaction_participants(agent_did);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_transaction_participants_current
+    ON transaction_participants(transaction_id, agent_did) WHERE removed_at IS NULL;
@@ -0,0 +1,46 @@
+-- Queue of external deliveries: a delivery can be cancelled, and a failed
+-- delivery that will be retried records when. SQLite cannot change the CHECK
+-- constraint on status in place, so the table is rebuilt.
+
+CREATE TABLE deliveries_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    message_id TEXT NOT NULL,
+    message_text TEXT NOT NULL,
+    recipient_did TEXT NOT NULL,
+    delivery_url TEXT,
+    delivery_type TEXT NOT NULL DEFAULT 'https' CHECK (delivery_type IN ('https', 'internal', 'return_path', 'pickup')),
+    status TEXT NOT NULL CHECK (status IN ('pending', 'success', 'failed', 'cancelled')) DEFAULT 'pending',
+    retry_count INTEGER NOT NULL DEFAULT 0,
+    last_http_status_code INTEGER,
+    error_message TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    delivered_at TIMESTAMP,
+    next_attempt_at TEXT, -- when a failed delivery is retried, NULL if it is not
+
+    FOREIGN KEY (message_id) REFERENCES messages(message_id)
+);
+
+INSERT INTO deliveries_new (id, message_id, message_text, recipient_did, delivery_url, delivery_type, status,
+                            retry_count, last_http_status_code, error_message, created_at, updated_at, delivered_at)
+SELECT id, message_id, message_text, recipient_did, delivery_url, delivery_type, status,
+       retry_count, last_http_status_code, error_message, created_at, updated_at, delivered_at
+FROM deliveries;
+
+DROP TABLE deliveries;
+ALTER TABLE deliveries_new RENAME TO deliveries;
+
+CREATE INDEX idx_deliveries_message_id ON deliveries(message_id);
+CREATE INDEX idx_deliveries_recipient_did ON deliveries(recipient_did);
+CREATE INDEX idx_deliveries_status ON deliveries(status);
+CREATE INDEX idx_deliveries_type ON deliveries(delivery_type);
+CREATE INDEX idx_deliveries_pending_retry ON deliveries(status, retry_count, created_at);
+CREATE INDEX idx_deliveries_type_status_retry ON deliveries(delivery_type, status, retry_count);
+CREATE INDEX idx_deliveries_next_attempt ON deliveries(next_attempt_at) WHERE next_attempt_at IS NOT NULL;
+
+CREATE TRIGGER update_deliveries_updated_at
+    AFTER UPDATE ON deliveries
+    FOR EACH ROW
+BEGIN
+    UPDATE deliveries SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
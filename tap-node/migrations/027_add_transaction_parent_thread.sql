@@ -0,0 +1,13 @@
+-- Parent thread of a transaction, the pthid of its Transfer or Payment
+-- message. A transaction started because of another one, such as a Transfer
+-- settling a Payment or one reverting an earlier Transfer, names the thread
+-- of that transaction as its parent, linking related transactions into a
+-- graph.
+
+ALTER TABLE transactions ADD COLUMN parent_thread_id TEXT;
+
+UPDATE transactions
+SET parent_thread_id = json_extract(message_json, '$.pthid')
+WHERE json_valid(message_json);
+
+CREATE INDEX IF NOT EXISTS idx_transactions_parent_thread_id ON transactions(parent_thread_id);
@@ -0,0 +1,8 @@
+-- Usual activity of each counterparty, learned by the anomaly detector from
+-- the transactions it sent, so baselines survive restarts.
+
+CREATE TABLE IF NOT EXISTS counterparty_baselines (
+    counterparty_did TEXT PRIMARY KEY,
+    baseline JSON NOT NULL,
+    updated_at TEXT NOT NULL
+);
@@ -0,0 +1,16 @@
+-- Running totals of the Transfers and Payments made under a connection, which
+-- its TAIP-15 limits are enforced against.
+
+CREATE TABLE IF NOT EXISTS connection_spending (
+    connection_id TEXT NOT NULL,
+    transaction_id TEXT NOT NULL,
+    -- Currency the limits are expressed in, or the asset of the transaction
+    -- when the connection has no limit currency
+    currency TEXT NOT NULL,
+    amount REAL NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (connection_id, transaction_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_connection_spending_created_at
+    ON connection_spending(connection_id, created_at);
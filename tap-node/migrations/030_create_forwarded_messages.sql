@@ -0,0 +1,19 @@
+-- Received messages for none of the node's agents that it forwarded to its
+-- upstream node, refused to forward to break a loop, or failed to forward.
+
+CREATE TABLE IF NOT EXISTS forwarded_messages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- SHA-256 digest of the envelope, which recognizes it if it comes back
+    digest TEXT NOT NULL,
+    envelope_kind TEXT NOT NULL CHECK (envelope_kind IN ('signed', 'encrypted')),
+    message_id TEXT,
+    from_did TEXT,
+    recipients TEXT NOT NULL, -- JSON array of the recipient DIDs
+    upstream_url TEXT NOT NULL,
+    forwarded_by TEXT NOT NULL, -- JSON array of the nodes that forwarded it here
+    status TEXT NOT NULL CHECK (status IN ('forwarded', 'refused', 'failed')),
+    error TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_forwarded_messages_digest ON forwarded_messages(digest);
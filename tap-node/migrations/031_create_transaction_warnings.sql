@@ -0,0 +1,14 @@
+-- Warnings attached to transactions by the policy rules that tripped when
+-- they were authorized, such as likely duplicates.
+
+CREATE TABLE IF NOT EXISTS transaction_warnings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    transaction_id TEXT NOT NULL,
+    rule TEXT NOT NULL,
+    action TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    details_json TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_transaction_warnings_transaction_id ON transaction_warnings(transaction_id);
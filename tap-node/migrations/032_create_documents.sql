@@ -0,0 +1,19 @@
+-- Documents referenced by the invoices of received Payments, along with the
+-- outcome of checking embedded content against its SHA-256 digest.
+
+CREATE TABLE IF NOT EXISTS documents (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    transaction_id TEXT NOT NULL,
+    message_id TEXT NOT NULL,
+    document_id TEXT NOT NULL,
+    document_type TEXT,
+    url TEXT,
+    mime_type TEXT,
+    hash TEXT,
+    content BLOB,
+    status TEXT NOT NULL CHECK (status IN ('verified', 'unverified', 'mismatch')),
+    created_at TEXT NOT NULL,
+    UNIQUE(transaction_id, document_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_documents_transaction_id ON documents(transaction_id);
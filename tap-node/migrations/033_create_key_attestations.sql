@@ -0,0 +1,17 @@
+-- Outcome of verifying the key attestations sent with incoming messages,
+-- which bind the sending agent to an organization.
+
+CREATE TABLE IF NOT EXISTS key_attestations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    message_id TEXT NOT NULL UNIQUE,
+    agent_did TEXT NOT NULL,
+    organization TEXT,
+    kid TEXT,
+    status TEXT NOT NULL CHECK (status IN ('verified', 'invalid')),
+    reason TEXT,
+    did_doc_hash TEXT,
+    expires_at INTEGER,
+    verified_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_key_attestations_agent_did ON key_attestations(agent_did);
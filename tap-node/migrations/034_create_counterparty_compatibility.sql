@@ -0,0 +1,8 @@
+-- Compatibility rules of counterparties that deviate from the specification,
+-- applied to the messages received from and sent to them.
+
+CREATE TABLE IF NOT EXISTS counterparty_compatibility (
+    counterparty_did TEXT PRIMARY KEY,
+    rules JSON NOT NULL,
+    updated_at TEXT NOT NULL
+);
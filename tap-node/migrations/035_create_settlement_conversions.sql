@@ -0,0 +1,20 @@
+-- Settlements made in another asset than the transaction requested, with the
+-- rate between the two assets at the time of the Settle and the slippage of
+-- the settled amount from the amount the rate gives.
+
+CREATE TABLE IF NOT EXISTS settlement_conversions (
+    transaction_id TEXT PRIMARY KEY, -- reference_id of the transaction
+    requested_asset TEXT NOT NULL, -- CAIP-19 asset ID or ISO 4217 currency code
+    requested_amount TEXT NOT NULL,
+    settled_asset TEXT NOT NULL, -- CAIP-19 asset ID or ISO 4217 currency code
+    settled_amount TEXT NOT NULL,
+    rate REAL, -- units of the settled asset per unit of the requested asset
+    rate_source TEXT,
+    expected_amount REAL,
+    slippage REAL, -- (settled - expected) / expected
+    max_slippage REAL NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('within_tolerance', 'out_of_tolerance', 'unpriced')),
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_settlement_conversions_status ON settlement_conversions(status);
@@ -0,0 +1,14 @@
+-- Last and next runs of the node's scheduled jobs, so schedules survive
+-- restarts, and runs requested by other processes sharing the database.
+
+CREATE TABLE IF NOT EXISTS scheduled_jobs (
+    name TEXT PRIMARY KEY,
+    schedule TEXT NOT NULL,
+    last_run_at TEXT,
+    last_status TEXT CHECK (last_status IN ('succeeded', 'failed')),
+    last_error TEXT,
+    last_duration_ms INTEGER,
+    next_run_at TEXT,
+    run_requested_at TEXT,
+    updated_at TEXT NOT NULL
+);
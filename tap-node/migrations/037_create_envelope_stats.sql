@@ -0,0 +1,21 @@
+-- Envelope profiles received from each counterparty, for debugging
+-- interoperability. Fields that do not apply to a profile are stored as ''
+-- so that they take part in the primary key.
+
+CREATE TABLE IF NOT EXISTS envelope_stats (
+    counterparty_did TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('plain', 'signed', 'encrypted')),
+    serialization TEXT NOT NULL CHECK (serialization IN ('json', 'flattened', 'compact')),
+    alg TEXT NOT NULL DEFAULT '',
+    enc TEXT NOT NULL DEFAULT '',
+    media_type TEXT NOT NULL DEFAULT '',
+    schema_version TEXT NOT NULL DEFAULT '',
+    messages INTEGER NOT NULL DEFAULT 0,
+    failures INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    first_seen_at TEXT NOT NULL,
+    last_seen_at TEXT NOT NULL,
+    PRIMARY KEY (counterparty_did, kind, serialization, alg, enc, media_type, schema_version)
+);
+
+CREATE INDEX IF NOT EXISTS idx_envelope_stats_last_seen ON envelope_stats(last_seen_at);
@@ -0,0 +1,14 @@
+-- Settlement addresses seen in transactions, to cross-reference addresses
+-- reused across transactions, counterparties and originators.
+
+CREATE TABLE IF NOT EXISTS settlement_address_sightings (
+    address TEXT NOT NULL, -- CAIP-10 account ID or PayTo URI, lowercased on EVM chains
+    transaction_id TEXT NOT NULL, -- reference_id of the transaction
+    provided_by TEXT NOT NULL, -- DID of the agent that gave the address
+    originator_did TEXT,
+    beneficiary_did TEXT,
+    seen_at TEXT NOT NULL,
+    PRIMARY KEY (address, transaction_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_settlement_address_sightings_transaction ON settlement_address_sightings(transaction_id);
@@ -0,0 +1,33 @@
+-- Personal data of the parties of transactions, encrypted with a data key of
+-- each transaction, and the log of reads of it.
+
+CREATE TABLE IF NOT EXISTS transaction_data_keys (
+    transaction_id TEXT PRIMARY KEY, -- reference_id of the transaction
+    wrapped_key TEXT, -- data key encrypted with the storage key, NULL once revoked
+    created_at TEXT NOT NULL,
+    revoked_at TEXT,
+    revoked_by TEXT,
+    revocation_reason TEXT
+);
+
+CREATE TABLE IF NOT EXISTS transaction_pii (
+    transaction_id TEXT NOT NULL,
+    role TEXT NOT NULL, -- originator, beneficiary, ...
+    customer_id TEXT,
+    ciphertext TEXT NOT NULL, -- party JSON encrypted with the data key
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (transaction_id, role)
+);
+
+CREATE INDEX IF NOT EXISTS idx_transaction_pii_customer ON transaction_pii(customer_id);
+
+CREATE TABLE IF NOT EXISTS pii_access_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    transaction_id TEXT NOT NULL,
+    accessor TEXT NOT NULL,
+    purpose TEXT NOT NULL,
+    granted BOOLEAN NOT NULL,
+    accessed_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_pii_access_log_transaction ON pii_access_log(transaction_id);
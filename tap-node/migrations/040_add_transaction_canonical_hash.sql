@@ -0,0 +1,9 @@
+-- Canonical hash of a transaction, SHA-256 of the JCS-canonicalized body of
+-- its initiating message followed by the sender DID. Copies of a transaction
+-- share the hash even when their message IDs differ, so external systems
+-- can reference the transaction by it. Existing transactions are hashed by
+-- the storage when it opens the database, as SQL cannot canonicalize JSON.
+
+ALTER TABLE transactions ADD COLUMN canonical_hash TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_transactions_canonical_hash ON transactions(canonical_hash);
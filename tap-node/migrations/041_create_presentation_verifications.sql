@@ -0,0 +1,20 @@
+-- Outcome of verifying the verifiable presentations carried by incoming
+-- Presentation messages: the holder and issuer signatures, validity periods
+-- and revocation status of the credentials.
+
+CREATE TABLE IF NOT EXISTS presentation_verifications (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    message_id TEXT NOT NULL,
+    presentation_index INTEGER NOT NULL, -- position among the presentations of the message
+    transaction_id TEXT, -- thread of the Presentation message
+    sender_did TEXT NOT NULL,
+    holder TEXT,
+    status TEXT NOT NULL CHECK (status IN ('verified', 'invalid')),
+    reason TEXT,
+    credentials_json TEXT NOT NULL DEFAULT '[]', -- PresentedCredential array
+    verified_at TEXT NOT NULL,
+    UNIQUE (message_id, presentation_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_presentation_verifications_transaction_id
+    ON presentation_verifications(transaction_id);
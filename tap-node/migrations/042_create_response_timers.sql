@@ -0,0 +1,27 @@
+-- Response timers: the time our agents take to answer the messages
+-- counterparties send them, against the deadlines configured per message
+-- type. A timer starts when the message arrives and stops when our agent
+-- sends a reply that answers it.
+
+CREATE TABLE IF NOT EXISTS response_timers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    message_id TEXT NOT NULL,
+    message_type TEXT NOT NULL, -- short name, e.g. Transfer
+    thread_id TEXT NOT NULL,
+    agent_did TEXT NOT NULL, -- our agent expected to answer
+    counterparty_did TEXT,
+    responses_json TEXT NOT NULL DEFAULT '[]', -- short names of the answering types
+    status TEXT NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'answered', 'cancelled')),
+    started_at TEXT NOT NULL,
+    warn_at TEXT NOT NULL,
+    deadline_at TEXT NOT NULL,
+    warned INTEGER NOT NULL DEFAULT 0,
+    breached INTEGER NOT NULL DEFAULT 0,
+    response_message_id TEXT,
+    response_type TEXT,
+    closed_at TEXT,
+    UNIQUE (message_id, agent_did)
+);
+
+CREATE INDEX IF NOT EXISTS idx_response_timers_thread ON response_timers(thread_id, agent_did);
+CREATE INDEX IF NOT EXISTS idx_response_timers_open ON response_timers(status, deadline_at);
@@ -0,0 +1,17 @@
+-- Presentations our agents have requested: the challenge of each
+-- RequestPresentation sent, which the holder proof of the Presentation that
+-- answers it must sign, together with the requesting agent as its domain.
+
+CREATE TABLE IF NOT EXISTS presentation_requests (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    message_id TEXT NOT NULL,
+    transaction_id TEXT NOT NULL, -- thread of the RequestPresentation message
+    verifier_did TEXT NOT NULL, -- our agent that sent the request
+    recipient_did TEXT NOT NULL, -- agent asked to present
+    challenge TEXT NOT NULL,
+    requested_at TEXT NOT NULL,
+    UNIQUE (message_id, recipient_did)
+);
+
+CREATE INDEX IF NOT EXISTS idx_presentation_requests_thread
+    ON presentation_requests(transaction_id, recipient_did);
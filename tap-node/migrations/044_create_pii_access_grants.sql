@@ -0,0 +1,11 @@
+-- Accessors other than the participants of a transaction allowed to read the
+-- personal data of its parties, granted by a participant.
+
+CREATE TABLE IF NOT EXISTS pii_access_grants (
+    transaction_id TEXT NOT NULL, -- reference_id of the transaction
+    accessor TEXT NOT NULL, -- DID allowed to read the personal data
+    granted_by TEXT NOT NULL,
+    granted_at TEXT NOT NULL,
+    revoked_at TEXT,
+    PRIMARY KEY (transaction_id, accessor)
+);
@@ -0,0 +1,504 @@
+//! Detection of settlement addresses reused across transactions
+//!
+//! With [`NodeConfig::address_reuse`](crate::NodeConfig::address_reuse) set,
+//! the node records every settlement address it sees, the address of an
+//! Authorize and the fallback settlement addresses of a Payment, as an
+//! [`AddressSighting`]: the transaction, the agent that gave the address and
+//! the originator and beneficiary of the transaction. An address is
+//! normally given by one agent for one beneficiary. When it turns up
+//!
+//! - given by another agent, or for another beneficiary, than before
+//!   ([`AddressReuseKind::SharedAddress`]), which points at an operational
+//!   mistake or at an address shared between customers, or
+//! - paid by more distinct originators than
+//!   [`AddressReuseConfig::max_originators`]
+//!   ([`AddressReuseKind::ManyOriginators`]), the pattern of a mule account,
+//!
+//! the node attaches an `address_reuse` warning to the transaction and
+//! publishes a
+//! [`NodeEvent::AddressReuseDetected`](crate::event::NodeEvent::AddressReuseDetected)
+//! event. The transaction is processed as usual.
+//!
+//! [`Storage::list_address_sightings`] cross-references the transactions
+//! an address was seen in and [`Storage::list_reused_addresses`] lists the
+//! addresses seen in more than one transaction.
+
+#[cfg(feature = "state-machine")]
+use crate::event::{EventBus, NodeEvent};
+use crate::storage::AddressSighting;
+#[cfg(feature = "state-machine")]
+use crate::storage::Storage;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashSet};
+#[cfg(feature = "state-machine")]
+use tap_msg::message::TapMessage;
+
+/// Name of the warning attached to transactions paying a reused address
+pub const ADDRESS_REUSE_RULE: &str = "address_reuse";
+
+/// Default number of distinct originators an address may be paid by
+pub const DEFAULT_MAX_ORIGINATORS: usize = 3;
+
+/// How reused settlement addresses are detected
+#[derive(Debug, Clone)]
+pub struct AddressReuseConfig {
+    /// Number of distinct originators an address may be paid by before it is
+    /// flagged
+    pub max_originators: usize,
+    /// Addresses that are expected to be reused, e.g. the deposit address of
+    /// an omnibus account, normalized with [`normalize_address`]
+    pub ignored_addresses: HashSet<String>,
+}
+
+impl Default for AddressReuseConfig {
+    fn default() -> Self {
+        Self {
+            max_originators: DEFAULT_MAX_ORIGINATORS,
+            ignored_addresses: HashSet::new(),
+        }
+    }
+}
+
+/// Why an address was flagged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressReuseKind {
+    /// Given by another agent or for another beneficiary than before
+    SharedAddress,
+    /// Paid by more distinct originators than tolerated
+    ManyOriginators,
+}
+
+impl AddressReuseKind {
+    /// Snake-case name of the kind, e.g. `shared_address`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AddressReuseKind::SharedAddress => "shared_address",
+            AddressReuseKind::ManyOriginators => "many_originators",
+        }
+    }
+}
+
+/// A reuse of a settlement address found by [`AddressReuseConfig::check`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AddressReuse {
+    pub kind: AddressReuseKind,
+    /// Human-readable explanation
+    pub reason: String,
+    /// The earlier sightings the reuse was found from
+    pub details: Value,
+}
+
+/// Normalize a settlement address so that spellings of the same address
+/// compare equal
+///
+/// EVM addresses are case-insensitive (mixed case is only a checksum), so
+/// `eip155` account IDs are lowercased. Other addresses are kept as they are.
+pub fn normalize_address(address: &str) -> String {
+    let address = address.trim();
+    if address.starts_with("eip155:") {
+        address.to_lowercase()
+    } else {
+        address.to_string()
+    }
+}
+
+impl AddressReuseConfig {
+    /// Detect reuse with the default limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Flag addresses paid by more than `max_originators` distinct
+    /// originators
+    pub fn with_max_originators(mut self, max_originators: usize) -> Self {
+        self.max_originators = max_originators;
+        self
+    }
+
+    /// Do not flag reuse of `address`
+    pub fn ignore(mut self, address: &str) -> Self {
+        self.ignored_addresses.insert(normalize_address(address));
+        self
+    }
+
+    /// Whether reuse of `address` is expected
+    pub fn is_ignored(&self, address: &str) -> bool {
+        self.ignored_addresses.contains(&normalize_address(address))
+    }
+
+    /// Compare a sighting of an address with its earlier sightings
+    pub fn check(
+        &self,
+        sighting: &AddressSighting,
+        earlier: &[AddressSighting],
+    ) -> Vec<AddressReuse> {
+        let others: Vec<&AddressSighting> = earlier
+            .iter()
+            .filter(|other| other.transaction_id != sighting.transaction_id)
+            .collect();
+        let mut reuses = Vec::new();
+
+        let shared: Vec<&&AddressSighting> = others
+            .iter()
+            .filter(|other| {
+                other.provided_by != sighting.provided_by
+                    || matches!(
+                        (&other.beneficiary_did, &sighting.beneficiary_did),
+                        (Some(earlier), Some(current)) if earlier != current
+                    )
+            })
+            .collect();
+        if !shared.is_empty() {
+            let providers: BTreeSet<&str> = shared
+                .iter()
+                .map(|other| other.provided_by.as_str())
+                .collect();
+            let beneficiaries: BTreeSet<&str> = shared
+                .iter()
+                .filter_map(|other| other.beneficiary_did.as_deref())
+                .collect();
+            reuses.push(AddressReuse {
+                kind: AddressReuseKind::SharedAddress,
+                reason: format!(
+                    "Settlement address {} was given by another agent or for another beneficiary in {} earlier transaction(s)",
+                    sighting.address,
+                    shared.len()
+                ),
+                details: json!({
+                    "address": sighting.address,
+                    "provided_by": sighting.provided_by,
+                    "beneficiary_did": sighting.beneficiary_did,
+                    "earlier_transactions": shared
+                        .iter()
+                        .map(|other| other.transaction_id.as_str())
+                        .collect::<Vec<_>>(),
+                    "earlier_providers": providers,
+                    "earlier_beneficiaries": beneficiaries,
+                }),
+            });
+        }
+
+        // Only the transaction that brings in a new originator is flagged
+        if let Some(originator) = &sighting.originator_did {
+            let mut originators: BTreeSet<&str> = others
+                .iter()
+                .filter_map(|other| other.originator_did.as_deref())
+                .collect();
+            if originators.insert(originator) && originators.len() > self.max_originators {
+                reuses.push(AddressReuse {
+                    kind: AddressReuseKind::ManyOriginators,
+                    reason: format!(
+                        "Settlement address {} is paid by {} distinct originators, more than the tolerated {}",
+                        sighting.address,
+                        originators.len(),
+                        self.max_originators
+                    ),
+                    details: json!({
+                        "address": sighting.address,
+                        "originators": originators,
+                        "max_originators": self.max_originators,
+                    }),
+                });
+            }
+        }
+
+        reuses
+    }
+
+    /// Record a settlement address seen in a transaction and flag the
+    /// transaction if the address is reused, logging failures
+    ///
+    /// Returns the reuses found. An address already recorded for the
+    /// transaction is not checked again.
+    #[cfg(feature = "state-machine")]
+    pub(crate) async fn record(
+        &self,
+        storage: &Storage,
+        event_bus: &EventBus,
+        transaction_id: &str,
+        address: &str,
+        provided_by: &str,
+    ) -> Vec<AddressReuse> {
+        if self.is_ignored(address) {
+            return Vec::new();
+        }
+        let (originator_did, beneficiary_did) =
+            match storage.get_transaction_by_id(transaction_id).await {
+                Ok(Some(transaction)) => parties(&transaction.message_json),
+                Ok(None) => (None, None),
+                Err(e) => {
+                    log::warn!("Failed to load transaction {}: {}", transaction_id, e);
+                    (None, None)
+                }
+            };
+        let sighting = AddressSighting {
+            address: normalize_address(address),
+            transaction_id: transaction_id.to_string(),
+            provided_by: provided_by.to_string(),
+            originator_did,
+            beneficiary_did,
+            seen_at: storage
+                .clock()
+                .now()
+                .format("%Y-%m-%dT%H:%M:%SZ")
+                .to_string(),
+        };
+
+        let earlier = match storage.list_address_sightings(&sighting.address).await {
+            Ok(earlier) => earlier,
+            Err(e) => {
+                log::warn!(
+                    "Failed to load sightings of address {}: {}",
+                    sighting.address,
+                    e
+                );
+                return Vec::new();
+            }
+        };
+        match storage.record_address_sighting(&sighting).await {
+            Ok(true) => {}
+            Ok(false) => return Vec::new(),
+            Err(e) => {
+                log::warn!(
+                    "Failed to record address {} of transaction {}: {}",
+                    sighting.address,
+                    transaction_id,
+                    e
+                );
+                return Vec::new();
+            }
+        }
+
+        let reuses = self.check(&sighting, &earlier);
+        for reuse in &reuses {
+            Self::flag(storage, event_bus, &sighting, reuse).await;
+        }
+        reuses
+    }
+
+    /// Attach a warning to the transaction paying a reused address and
+    /// publish it
+    #[cfg(feature = "state-machine")]
+    async fn flag(
+        storage: &Storage,
+        event_bus: &EventBus,
+        sighting: &AddressSighting,
+        reuse: &AddressReuse,
+    ) {
+        log::warn!(
+            "Transaction {} uses a reused settlement address: {}",
+            sighting.transaction_id,
+            reuse.reason
+        );
+        if let Err(e) = storage
+            .insert_transaction_warning(
+                &sighting.transaction_id,
+                ADDRESS_REUSE_RULE,
+                "warn",
+                &reuse.reason,
+                &reuse.details,
+            )
+            .await
+        {
+            log::warn!(
+                "Failed to flag address reuse in transaction {}: {}",
+                sighting.transaction_id,
+                e
+            );
+        }
+        event_bus
+            .publish_event(NodeEvent::AddressReuseDetected {
+                transaction_id: sighting.transaction_id.clone(),
+                address: sighting.address.clone(),
+                kind: reuse.kind.as_str().to_string(),
+                reason: reuse.reason.clone(),
+                details: reuse.details.clone(),
+            })
+            .await;
+    }
+}
+
+/// Originator and beneficiary of a stored Transfer, or customer and
+/// merchant of a stored Payment
+#[cfg(feature = "state-machine")]
+fn parties(message_json: &Value) -> (Option<String>, Option<String>) {
+    let Some(message) = serde_json::from_value(message_json.clone()).ok() else {
+        return (None, None);
+    };
+    match TapMessage::from_plain_message(&message) {
+        Ok(TapMessage::Transfer(transfer)) => (
+            transfer.originator.map(|party| party.id),
+            transfer.beneficiary.map(|party| party.id),
+        ),
+        Ok(TapMessage::Payment(payment)) => (
+            payment.customer.map(|party| party.id),
+            Some(payment.merchant.id),
+        ),
+        _ => (None, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "eip155:1:0x742d35cc6634c0532925a3b844bc454e4438f44e";
+
+    fn sighting(
+        transaction_id: &str,
+        provided_by: &str,
+        originator: &str,
+        beneficiary: &str,
+    ) -> AddressSighting {
+        AddressSighting {
+            address: ADDRESS.to_string(),
+            transaction_id: transaction_id.to_string(),
+            provided_by: provided_by.to_string(),
+            originator_did: Some(originator.to_string()),
+            beneficiary_did: Some(beneficiary.to_string()),
+            seen_at: "2026-03-01T12:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_normalize_address() {
+        assert_eq!(
+            normalize_address(" eip155:1:0x742d35Cc6634C0532925a3b844Bc454e4438f44e "),
+            ADDRESS
+        );
+        // Base58 addresses are case-sensitive
+        assert_eq!(
+            normalize_address("solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp:7S3P4HxJpyyigGzodYwHtCxZyUQe9JiBMHyRWXArAaKv"),
+            "solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp:7S3P4HxJpyyigGzodYwHtCxZyUQe9JiBMHyRWXArAaKv"
+        );
+    }
+
+    #[test]
+    fn test_same_agent_and_beneficiary_is_not_flagged() {
+        let config = AddressReuseConfig::new();
+        let earlier = vec![
+            sighting(
+                "tx-1",
+                "did:web:vasp-b",
+                "did:example:alice",
+                "did:example:bob",
+            ),
+            // The same transaction seen again
+            sighting(
+                "tx-2",
+                "did:web:vasp-b",
+                "did:example:carol",
+                "did:example:bob",
+            ),
+        ];
+        let current = sighting(
+            "tx-2",
+            "did:web:vasp-b",
+            "did:example:carol",
+            "did:example:bob",
+        );
+        assert!(config.check(&current, &earlier).is_empty());
+        assert!(config.check(&current, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_shared_address() {
+        let config = AddressReuseConfig::new();
+        let earlier = vec![
+            sighting(
+                "tx-1",
+                "did:web:vasp-b",
+                "did:example:alice",
+                "did:example:bob",
+            ),
+            sighting(
+                "tx-2",
+                "did:web:vasp-c",
+                "did:example:alice",
+                "did:example:dave",
+            ),
+        ];
+
+        // Another beneficiary at the same agent
+        let current = sighting(
+            "tx-3",
+            "did:web:vasp-b",
+            "did:example:alice",
+            "did:example:erin",
+        );
+        let reuses = config.check(&current, &earlier);
+        assert_eq!(reuses.len(), 1);
+        assert_eq!(reuses[0].kind, AddressReuseKind::SharedAddress);
+        assert_eq!(
+            reuses[0].details["earlier_transactions"],
+            json!(["tx-1", "tx-2"])
+        );
+
+        // The same beneficiary through another agent
+        let current = sighting(
+            "tx-3",
+            "did:web:vasp-c",
+            "did:example:alice",
+            "did:example:dave",
+        );
+        let reuses = config.check(&current, &earlier);
+        assert_eq!(reuses.len(), 1);
+        assert_eq!(reuses[0].details["earlier_transactions"], json!(["tx-1"]));
+        assert_eq!(
+            reuses[0].details["earlier_providers"],
+            json!(["did:web:vasp-b"])
+        );
+    }
+
+    #[test]
+    fn test_many_originators() {
+        let config = AddressReuseConfig::new().with_max_originators(2);
+        let earlier = vec![
+            sighting(
+                "tx-1",
+                "did:web:vasp-b",
+                "did:example:alice",
+                "did:example:bob",
+            ),
+            sighting(
+                "tx-2",
+                "did:web:vasp-b",
+                "did:example:carol",
+                "did:example:bob",
+            ),
+        ];
+
+        // An originator that paid the address before does not count twice
+        let current = sighting(
+            "tx-3",
+            "did:web:vasp-b",
+            "did:example:alice",
+            "did:example:bob",
+        );
+        assert!(config.check(&current, &earlier).is_empty());
+
+        let current = sighting(
+            "tx-3",
+            "did:web:vasp-b",
+            "did:example:dave",
+            "did:example:bob",
+        );
+        let reuses = config.check(&current, &earlier);
+        assert_eq!(reuses.len(), 1);
+        assert_eq!(reuses[0].kind, AddressReuseKind::ManyOriginators);
+        assert_eq!(
+            reuses[0].details["originators"],
+            json!(["did:example:alice", "did:example:carol", "did:example:dave"])
+        );
+    }
+
+    #[test]
+    fn test_ignored_addresses() {
+        let config =
+            AddressReuseConfig::new().ignore("eip155:1:0x742d35Cc6634C0532925a3b844Bc454e4438f44e");
+        assert!(config.is_ignored(ADDRESS));
+        assert!(!config.is_ignored("eip155:1:0x0000000000000000000000000000000000000001"));
+    }
+}
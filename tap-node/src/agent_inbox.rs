@@ -0,0 +1,117 @@
+//! Inbox of the messages each agent received
+//!
+//! Embedders that do not react to messages as they are processed can poll an
+//! agent's inbox instead: "what arrived for this agent since X". The inbox is
+//! backed by the incoming messages in the agent's message log. Each message
+//! is identified by its message log ID, which callers pass back as `since` to
+//! continue where they left off.
+//!
+//! Each agent also has a read cursor. Messages up to and including the cursor
+//! count as read, so [`ReceivedFilter::unread_only`] lists only the messages
+//! not yet marked read with [`TapNode::mark_received_read`].
+
+use crate::error::{Error, Result};
+use crate::storage::{Message, ReceivedFilter, Storage};
+use crate::TapNode;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Default number of messages returned by [`TapNode::list_received`]
+pub const DEFAULT_RECEIVED_LIMIT: u32 = 50;
+
+/// Maximum number of messages returned by [`TapNode::list_received`]
+pub const MAX_RECEIVED_LIMIT: u32 = 1000;
+
+/// Messages an agent received, as returned by [`TapNode::list_received`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceivedPage {
+    /// Incoming messages, oldest first
+    pub messages: Vec<Message>,
+    /// Pass as `since` to list the messages received after these
+    pub next_cursor: i64,
+    /// Messages with an ID up to and including this cursor are read
+    pub read_cursor: i64,
+    /// Number of incoming messages after the read cursor
+    pub unread_count: u64,
+}
+
+impl ReceivedPage {
+    /// Whether a listed message has been marked read
+    pub fn is_read(&self, message: &Message) -> bool {
+        message.id <= self.read_cursor
+    }
+}
+
+impl TapNode {
+    /// List the messages an agent received after a message log ID
+    ///
+    /// With `since` unset, listing starts at the first message, or after
+    /// the read cursor when the filter asks for unread messages only. At
+    /// most `limit` messages are returned, [`DEFAULT_RECEIVED_LIMIT`] if
+    /// unset and never more than [`MAX_RECEIVED_LIMIT`].
+    pub async fn list_received(
+        &self,
+        agent_did: &str,
+        since: Option<i64>,
+        filter: &ReceivedFilter,
+        limit: Option<u32>,
+    ) -> Result<ReceivedPage> {
+        let storage = self.inbox_storage(agent_did).await?;
+        let read_cursor = storage
+            .get_inbox_cursor(agent_did)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+
+        let mut after = since.unwrap_or(0);
+        if filter.unread_only {
+            after = after.max(read_cursor);
+        }
+        let limit = limit
+            .unwrap_or(DEFAULT_RECEIVED_LIMIT)
+            .clamp(1, MAX_RECEIVED_LIMIT);
+
+        let messages = storage
+            .list_incoming_messages(after, filter, limit)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        let unread_count = storage
+            .count_incoming_messages(read_cursor)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+
+        Ok(ReceivedPage {
+            next_cursor: messages.last().map_or(after, |m| m.id),
+            messages,
+            read_cursor,
+            unread_count,
+        })
+    }
+
+    /// Mark the messages an agent received up to and including a message
+    /// log ID as read, or all of them if `up_to` is unset
+    ///
+    /// The read cursor never moves backwards. Returns the cursor after the
+    /// update.
+    pub async fn mark_received_read(&self, agent_did: &str, up_to: Option<i64>) -> Result<i64> {
+        let storage = self.inbox_storage(agent_did).await?;
+        let up_to = match up_to {
+            Some(up_to) => up_to,
+            None => storage
+                .last_incoming_message_id()
+                .await
+                .map_err(|e| Error::Storage(e.to_string()))?,
+        };
+
+        storage
+            .advance_inbox_cursor(agent_did, up_to)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    async fn inbox_storage(&self, agent_did: &str) -> Result<Arc<Storage>> {
+        let storage_manager = self
+            .agent_storage_manager()
+            .ok_or_else(|| Error::Storage("Agent storage is not initialized".to_string()))?;
+        storage_manager.get_agent_storage(agent_did).await
+    }
+}
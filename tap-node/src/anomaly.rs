@@ -0,0 +1,558 @@
+//! Anomaly detection
+//!
+//! The [`AnomalyDetector`] learns the usual activity of every counterparty
+//! from the Transfers and Payments it sends: how many per rate window, at
+//! which hours of the day, in which assets and for what amounts. Each new
+//! transaction is compared with that baseline before it is folded into it,
+//! and departures are published as [`NodeEvent::AnomalyDetected`] events for
+//! downstream alerting:
+//!
+//! - a sudden spike in the number of transactions
+//! - a transaction at an hour the counterparty is rarely active
+//! - a transaction in an asset never seen from the counterparty
+//! - an amount far above the counterparty's mean in that asset
+//!
+//! Anomalies do not change how transactions are processed; rules that block
+//! or hold transactions belong in the [policy engine](crate::policy).
+//!
+//! Baselines are stored in the database of the agent that received the
+//! transactions, so they survive restarts. Rate, hour and amount anomalies
+//! are only reported once a baseline holds `warmup_transactions`
+//! transactions.
+
+use crate::clock::{system_clock, Clock};
+use crate::error::{Error, Result};
+use crate::event::{EventBus, NodeEvent};
+use crate::policy::velocity::TransactionAmount;
+use crate::storage::{
+    AgentStorageManager, AssetBaseline, CounterpartyBaseline, Storage, Transaction,
+};
+use chrono::{DateTime, Timelike, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::TapMessage;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// When departures from a counterparty's baseline are reported
+#[derive(Debug, Clone)]
+pub struct AnomalyThresholds {
+    /// Window transactions are counted in for the rate baseline
+    pub rate_window: Duration,
+    /// How many times the usual number of transactions per window makes a
+    /// spike
+    pub spike_factor: f64,
+    /// Fewest transactions in a window that can make a spike
+    pub min_spike_count: u64,
+    /// Weight of the latest window in the moving average of transactions
+    /// per window, between 0 and 1
+    pub smoothing: f64,
+    /// Share of a counterparty's transactions below which an hour of the day
+    /// is unusual for it
+    pub unusual_hour_share: f64,
+    /// How many times the mean amount in an asset makes an unusual amount
+    pub amount_factor: f64,
+    /// Transactions a baseline needs before rate, hour and amount anomalies
+    /// are reported
+    pub warmup_transactions: u64,
+}
+
+impl Default for AnomalyThresholds {
+    fn default() -> Self {
+        Self {
+            rate_window: Duration::from_secs(3600),
+            spike_factor: 3.0,
+            min_spike_count: 5,
+            smoothing: 0.2,
+            unusual_hour_share: 0.02,
+            amount_factor: 5.0,
+            warmup_transactions: 20,
+        }
+    }
+}
+
+/// A kind of departure from a counterparty's baseline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    /// Far more transactions in the current window than usual
+    TransactionSpike,
+    /// A transaction at an hour the counterparty is rarely active
+    UnusualHour,
+    /// A transaction in an asset never seen from the counterparty
+    NewAsset,
+    /// An amount far above the counterparty's mean in the asset
+    UnusualAmount,
+}
+
+impl AnomalyKind {
+    /// Snake-case name of the kind, e.g. `transaction_spike`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnomalyKind::TransactionSpike => "transaction_spike",
+            AnomalyKind::UnusualHour => "unusual_hour",
+            AnomalyKind::NewAsset => "new_asset",
+            AnomalyKind::UnusualAmount => "unusual_amount",
+        }
+    }
+}
+
+/// A departure from a counterparty's baseline
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Anomaly {
+    pub kind: AnomalyKind,
+    /// Human-readable explanation
+    pub reason: String,
+    /// Observed values and the baseline they were compared with
+    pub details: Value,
+}
+
+/// Compares the transactions of counterparties with their baselines
+#[derive(Debug, Clone)]
+pub struct AnomalyDetector {
+    thresholds: AnomalyThresholds,
+    clock: Arc<dyn Clock>,
+}
+
+impl AnomalyDetector {
+    /// Create a new detector with the given thresholds
+    pub fn new(thresholds: AnomalyThresholds) -> Self {
+        Self {
+            thresholds,
+            clock: system_clock(),
+        }
+    }
+
+    /// Use the given clock instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Get the thresholds
+    pub fn thresholds(&self) -> &AnomalyThresholds {
+        &self.thresholds
+    }
+
+    /// Compare a transaction made at `at` with a baseline, then fold it in
+    ///
+    /// `asset_amount` is the asset (or currency) and amount of the
+    /// transaction, if known.
+    pub fn observe(
+        &self,
+        baseline: &mut CounterpartyBaseline,
+        asset_amount: Option<(&str, f64)>,
+        at: DateTime<Utc>,
+    ) -> Vec<Anomaly> {
+        let t = &self.thresholds;
+        let mut anomalies = Vec::new();
+        let warmed_up = baseline.transactions >= t.warmup_transactions;
+
+        // Roll the rate window forward, folding the finished windows into
+        // the moving average
+        let window = t.rate_window.as_secs().max(1) as i64;
+        let now = at.timestamp();
+        if baseline.transactions == 0 {
+            baseline.window_start = now;
+        } else if now >= baseline.window_start + window {
+            let finished = (now - baseline.window_start) / window;
+            baseline.mean_window_count = baseline.mean_window_count * (1.0 - t.smoothing)
+                + baseline.window_count as f64 * t.smoothing;
+            // Windows without transactions
+            baseline.mean_window_count *= (1.0 - t.smoothing).powi((finished - 1).min(1000) as i32);
+            baseline.window_start += finished * window;
+            baseline.window_count = 0;
+        }
+        baseline.window_count += 1;
+
+        let spike_threshold = t.spike_factor * baseline.mean_window_count.max(1.0);
+        let is_spike = |count: u64| count >= t.min_spike_count && count as f64 > spike_threshold;
+        // Reported once per window, when the count crosses the threshold
+        if warmed_up && is_spike(baseline.window_count) && !is_spike(baseline.window_count - 1) {
+            anomalies.push(Anomaly {
+                kind: AnomalyKind::TransactionSpike,
+                reason: format!(
+                    "{} transactions in the current window, usually {:.1}",
+                    baseline.window_count, baseline.mean_window_count
+                ),
+                details: json!({
+                    "window_count": baseline.window_count,
+                    "mean_window_count": baseline.mean_window_count,
+                    "window_secs": window,
+                    "spike_factor": t.spike_factor,
+                }),
+            });
+        }
+
+        if baseline.hours.len() != 24 {
+            baseline.hours = vec![0; 24];
+        }
+        let hour = at.hour() as usize;
+        if warmed_up {
+            let share = baseline.hours[hour] as f64 / baseline.transactions as f64;
+            if share < t.unusual_hour_share {
+                anomalies.push(Anomaly {
+                    kind: AnomalyKind::UnusualHour,
+                    reason: format!(
+                        "Transaction at {:02}:00 UTC, when {:.1}% of the counterparty's transactions are made",
+                        hour,
+                        share * 100.0
+                    ),
+                    details: json!({
+                        "hour": hour,
+                        "share": share,
+                        "unusual_hour_share": t.unusual_hour_share,
+                    }),
+                });
+            }
+        }
+        baseline.hours[hour] += 1;
+
+        if let Some((asset, amount)) = asset_amount {
+            match baseline.assets.get_mut(asset) {
+                Some(known) => {
+                    if known.transactions >= t.warmup_transactions
+                        && known.mean_amount > 0.0
+                        && amount > t.amount_factor * known.mean_amount
+                    {
+                        anomalies.push(Anomaly {
+                            kind: AnomalyKind::UnusualAmount,
+                            reason: format!(
+                                "Amount {} of {} is {:.1} times the usual {:.2}",
+                                amount,
+                                asset,
+                                amount / known.mean_amount,
+                                known.mean_amount
+                            ),
+                            details: json!({
+                                "asset": asset,
+                                "amount": amount,
+                                "mean_amount": known.mean_amount,
+                                "amount_factor": t.amount_factor,
+                            }),
+                        });
+                    }
+                    known.transactions += 1;
+                    known.mean_amount += (amount - known.mean_amount) / known.transactions as f64;
+                }
+                None => {
+                    if baseline.transactions > 0 {
+                        let mut known_assets: Vec<_> = baseline.assets.keys().cloned().collect();
+                        known_assets.sort();
+                        anomalies.push(Anomaly {
+                            kind: AnomalyKind::NewAsset,
+                            reason: format!("First transaction in {} from the counterparty", asset),
+                            details: json!({
+                                "asset": asset,
+                                "amount": amount,
+                                "known_assets": known_assets,
+                            }),
+                        });
+                    }
+                    baseline.assets.insert(
+                        asset.to_string(),
+                        AssetBaseline {
+                            transactions: 1,
+                            mean_amount: amount,
+                        },
+                    );
+                }
+            }
+        }
+
+        baseline.transactions += 1;
+        baseline.last_seen = at.to_rfc3339();
+        anomalies
+    }
+
+    /// Compare a transaction an agent received with the baseline of its
+    /// sender, store the updated baseline and publish the anomalies found
+    ///
+    /// Transactions the agent sent itself are skipped.
+    pub async fn check(
+        &self,
+        storage: &Storage,
+        agent_did: &str,
+        transaction: &Transaction,
+        event_bus: &EventBus,
+    ) -> Result<Vec<Anomaly>> {
+        let Some(counterparty) = transaction
+            .from_did
+            .as_deref()
+            .filter(|from| *from != agent_did)
+        else {
+            return Ok(Vec::new());
+        };
+
+        let now = self.clock.now();
+        let mut baseline = storage
+            .get_counterparty_baseline(counterparty)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?
+            .unwrap_or_else(|| CounterpartyBaseline {
+                counterparty_did: counterparty.to_string(),
+                transactions: 0,
+                window_start: now.timestamp(),
+                window_count: 0,
+                mean_window_count: 0.0,
+                hours: vec![0; 24],
+                assets: Default::default(),
+                first_seen: now.to_rfc3339(),
+                last_seen: now.to_rfc3339(),
+            });
+
+        let amount = serde_json::from_value::<PlainMessage>(transaction.message_json.clone())
+            .ok()
+            .and_then(|message| TapMessage::from_plain_message(&message).ok())
+            .and_then(|message| TransactionAmount::from_tap_message(&message).ok().flatten());
+        let asset_amount = amount
+            .as_ref()
+            .and_then(|a| Some((a.asset.as_deref()?, a.amount.to_f64()?)));
+
+        let anomalies = self.observe(&mut baseline, asset_amount, now);
+        storage
+            .upsert_counterparty_baseline(&baseline)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+
+        for anomaly in &anomalies {
+            info!(
+                "Anomaly in transaction {} from {}: {}",
+                transaction.reference_id, counterparty, anomaly.reason
+            );
+            event_bus
+                .publish_event(NodeEvent::AnomalyDetected {
+                    agent_did: agent_did.to_string(),
+                    counterparty: counterparty.to_string(),
+                    transaction_id: transaction.reference_id.clone(),
+                    kind: anomaly.kind.as_str().to_string(),
+                    reason: anomaly.reason.clone(),
+                    details: anomaly.details.clone(),
+                })
+                .await;
+        }
+        Ok(anomalies)
+    }
+
+    /// Spawn a background task that checks every transaction created in an
+    /// agent database
+    ///
+    /// The task holds weak references to the event bus and the storage
+    /// manager and stops once either is dropped.
+    pub fn spawn(
+        self,
+        event_bus: &Arc<EventBus>,
+        agent_storage_manager: &Arc<AgentStorageManager>,
+    ) -> JoinHandle<()> {
+        let mut events = event_bus.subscribe_channel();
+        let event_bus = Arc::downgrade(event_bus);
+        let agent_storage_manager = Arc::downgrade(agent_storage_manager);
+
+        info!(
+            "Starting anomaly detector (rate window: {:?}, warmup: {} transactions)",
+            self.thresholds.rate_window, self.thresholds.warmup_transactions
+        );
+
+        tokio::spawn(async move {
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Anomaly detector skipped {} events", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                let NodeEvent::TransactionCreated {
+                    transaction,
+                    agent_did,
+                } = event.as_ref()
+                else {
+                    continue;
+                };
+
+                let (Some(event_bus), Some(manager)) = (
+                    Weak::upgrade(&event_bus),
+                    Weak::upgrade(&agent_storage_manager),
+                ) else {
+                    debug!("Node dropped, stopping anomaly detector");
+                    break;
+                };
+                let Some(storage) = manager.get_cached_agent_storage(agent_did) else {
+                    continue;
+                };
+
+                if let Err(e) = self
+                    .check(&storage, agent_did, transaction, &event_bus)
+                    .await
+                {
+                    warn!(
+                        "Could not check transaction {} for anomalies: {}",
+                        transaction.reference_id, e
+                    );
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use chrono::TimeZone;
+
+    fn transfer(id: &str, from: &str, asset: &str, amount: &str) -> Transaction {
+        let message = PlainMessage::new(
+            id.to_string(),
+            "https://tap.rsvp/schema/1.0#Transfer".to_string(),
+            json!({
+                "@type": "https://tap.rsvp/schema/1.0#Transfer",
+                "asset": asset,
+                "amount": amount,
+                "originator": {"@id": "did:example:alice"},
+                "agents": [],
+            }),
+            from.to_string(),
+        )
+        .with_recipient("did:example:us");
+        Transaction {
+            id: 0,
+            transaction_type: crate::storage::TransactionType::Transfer,
+            reference_id: id.to_string(),
+            from_did: Some(from.to_string()),
+            to_did: Some("did:example:us".to_string()),
+            thread_id: None,
+            message_type: message.type_.clone(),
+            status: crate::storage::TransactionStatus::Pending,
+            message_json: serde_json::to_value(&message).unwrap(),
+            rejection_code: None,
+            rejection_reason: None,
+            canonical_hash: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_baseline_anomalies() {
+        let detector = AnomalyDetector::new(AnomalyThresholds {
+            warmup_transactions: 10,
+            ..Default::default()
+        });
+        let mut baseline = CounterpartyBaseline {
+            counterparty_did: "did:example:vasp".to_string(),
+            transactions: 0,
+            window_start: 0,
+            window_count: 0,
+            mean_window_count: 0.0,
+            hours: vec![0; 24],
+            assets: Default::default(),
+            first_seen: String::new(),
+            last_seen: String::new(),
+        };
+        let start = Utc.with_ymd_and_hms(2026, 3, 2, 0, 0, 0).unwrap();
+        let usdc = "eip155:1/erc20:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+
+        // Two transfers a day at 10:00 and 14:00 for a week
+        for day in 0..7 {
+            for hour in [10, 14] {
+                let at = start + chrono::Duration::days(day) + chrono::Duration::hours(hour);
+                assert!(detector
+                    .observe(&mut baseline, Some((usdc, 100.0)), at)
+                    .is_empty());
+            }
+        }
+
+        let day = start + chrono::Duration::days(7);
+        let kinds = |anomalies: Vec<Anomaly>| anomalies.iter().map(|a| a.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds(detector.observe(
+                &mut baseline,
+                Some((usdc, 900.0)),
+                day + chrono::Duration::hours(3)
+            )),
+            vec![AnomalyKind::UnusualHour, AnomalyKind::UnusualAmount]
+        );
+        assert_eq!(
+            kinds(detector.observe(
+                &mut baseline,
+                Some(("eip155:1/slip44:60", 1.0)),
+                day + chrono::Duration::hours(10)
+            )),
+            vec![AnomalyKind::NewAsset]
+        );
+
+        // A burst within the 14:00 window, reported once
+        let burst: Vec<_> = (0..8)
+            .flat_map(|i| {
+                detector.observe(
+                    &mut baseline,
+                    Some((usdc, 100.0)),
+                    day + chrono::Duration::hours(14) + chrono::Duration::minutes(i),
+                )
+            })
+            .collect();
+        assert_eq!(kinds(burst), vec![AnomalyKind::TransactionSpike]);
+    }
+
+    #[tokio::test]
+    async fn test_check_persists_baseline_and_publishes() {
+        let storage = Storage::new_in_memory().await.unwrap();
+        let event_bus = EventBus::new();
+        let mut events = event_bus.subscribe_channel();
+        let clock = Arc::new(MockClock::default());
+        let detector = AnomalyDetector::new(AnomalyThresholds::default()).with_clock(clock);
+
+        let first = transfer("tx-1", "did:example:vasp", "eip155:1/slip44:60", "1.0");
+        assert!(detector
+            .check(&storage, "did:example:us", &first, &event_bus)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // A restarted detector picks up the stored baseline
+        let detector = AnomalyDetector::new(AnomalyThresholds::default());
+        let second = transfer("tx-2", "did:example:vasp", "eip155:1/slip44:0", "0.5");
+        let anomalies = detector
+            .check(&storage, "did:example:us", &second, &event_bus)
+            .await
+            .unwrap();
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].kind, AnomalyKind::NewAsset);
+        match events.recv().await.unwrap().as_ref() {
+            NodeEvent::AnomalyDetected {
+                counterparty,
+                transaction_id,
+                kind,
+                ..
+            } => {
+                assert_eq!(counterparty, "did:example:vasp");
+                assert_eq!(transaction_id, "tx-2");
+                assert_eq!(kind, "new_asset");
+            }
+            event => panic!("Unexpected event: {:?}", event),
+        }
+
+        let baseline = storage
+            .get_counterparty_baseline("did:example:vasp")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(baseline.transactions, 2);
+        assert_eq!(baseline.assets.len(), 2);
+
+        // Transactions the agent sent are not checked
+        let ours = transfer("tx-3", "did:example:us", "eip155:1/slip44:2", "3.0");
+        assert!(detector
+            .check(&storage, "did:example:us", &ours, &event_bus)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
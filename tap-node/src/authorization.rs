@@ -0,0 +1,226 @@
+//! Interactive authorization (TAIP-4, TAIP-15)
+//!
+//! When a transaction or connection needs a person to approve it, an agent
+//! sends an AuthorizationRequired message with a URL for them to open. The node
+//! binds each URL to a single-use nonce stored with an expiry. Opening the URL
+//! redeems the nonce through the HTTP callback, after which the node sends
+//! Authorize for the transaction on behalf of the agent that issued the URL.
+
+use crate::error::{Error, Result};
+use crate::storage::{AuthorizationChallenge, ChallengeStatus, DecisionType};
+use crate::TapNode;
+use std::time::Duration;
+use tap_msg::message::tap_message_trait::TapMessageBody;
+use tap_msg::message::{AuthorizationRequired, Authorize, PartyType};
+
+/// Default lifetime of an authorization URL
+pub const DEFAULT_AUTHORIZATION_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// A request for a person to authorize a transaction by opening a URL
+#[derive(Debug, Clone)]
+pub struct AuthorizationRequest {
+    /// Our agent that authorizes the transaction once the URL is opened
+    pub agent_did: String,
+    /// The transaction or connection awaiting authorization
+    pub transaction_id: String,
+    /// Agent that receives the AuthorizationRequired message
+    pub recipient_did: String,
+    /// Public URL of the node's authorization callback, e.g.
+    /// `https://vasp.example/authorize`
+    pub callback_base_url: String,
+    /// Party type required to open the URL, e.g. "customer"
+    pub party_type: Option<PartyType>,
+    /// How long the URL remains valid
+    pub ttl: Duration,
+}
+
+impl AuthorizationRequest {
+    /// Create a request using [`DEFAULT_AUTHORIZATION_TTL`]
+    pub fn new(
+        agent_did: impl Into<String>,
+        transaction_id: impl Into<String>,
+        recipient_did: impl Into<String>,
+        callback_base_url: impl Into<String>,
+    ) -> Self {
+        Self {
+            agent_did: agent_did.into(),
+            transaction_id: transaction_id.into(),
+            recipient_did: recipient_did.into(),
+            callback_base_url: callback_base_url.into(),
+            party_type: None,
+            ttl: DEFAULT_AUTHORIZATION_TTL,
+        }
+    }
+
+    /// Require a party type to open the URL
+    pub fn with_party_type(mut self, party_type: impl Into<PartyType>) -> Self {
+        self.party_type = Some(party_type.into());
+        self
+    }
+
+    /// Set how long the URL remains valid
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+}
+
+/// Build the authorization URL for a nonce under the callback base URL
+pub fn authorization_url(callback_base_url: &str, nonce: &str) -> String {
+    format!("{}/{}", callback_base_url.trim_end_matches('/'), nonce)
+}
+
+impl TapNode {
+    /// Send AuthorizationRequired with a URL bound to a new nonce
+    ///
+    /// The nonce is stored in the node's storage until it is redeemed with
+    /// [`TapNode::redeem_authorization`] or expires.
+    pub async fn request_authorization(
+        &self,
+        request: AuthorizationRequest,
+    ) -> Result<AuthorizationChallenge> {
+        let storage = self
+            .storage()
+            .ok_or_else(|| Error::Storage("Storage is not initialized".to_string()))?;
+
+        let ttl = chrono::Duration::from_std(request.ttl)
+            .map_err(|e| Error::Configuration(format!("Invalid authorization TTL: {}", e)))?;
+        let now = self.clock().now();
+        let nonce = uuid::Uuid::new_v4().simple().to_string();
+        let challenge = AuthorizationChallenge {
+            authorization_url: authorization_url(&request.callback_base_url, &nonce),
+            nonce,
+            transaction_id: request.transaction_id,
+            agent_did: request.agent_did,
+            recipient_did: request.recipient_did,
+            party_type: request.party_type.map(|p| p.to_string()),
+            status: ChallengeStatus::Pending,
+            message_id: None,
+            expires_at: (now + ttl).format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            created_at: now.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            redeemed_at: None,
+        };
+        storage
+            .insert_authorization_challenge(&challenge)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+
+        let mut body = AuthorizationRequired::new(
+            challenge.authorization_url.clone(),
+            challenge.expires_at.clone(),
+        );
+        body.from = challenge.party_type.clone();
+        let mut message = body
+            .to_didcomm_with_route(&challenge.agent_did, [challenge.recipient_did.as_str()])
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+        message.thid = Some(challenge.transaction_id.clone());
+
+        self.send_message(challenge.agent_did.clone(), message)
+            .await?;
+
+        Ok(challenge)
+    }
+
+    /// Redeem the nonce of an authorization URL and authorize the transaction
+    ///
+    /// Each nonce can be redeemed once before it expires. On success our agent
+    /// sends Authorize to the agent that received the URL and pending
+    /// authorization decisions for the transaction are resolved.
+    pub async fn redeem_authorization(&self, nonce: &str) -> Result<AuthorizationChallenge> {
+        let storage = self
+            .storage()
+            .ok_or_else(|| Error::Storage("Storage is not initialized".to_string()))?;
+
+        let redeemed = storage
+            .redeem_authorization_challenge(nonce)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        let challenge = storage
+            .get_authorization_challenge(nonce)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?
+            .ok_or_else(|| Error::Validation("Unknown authorization request".to_string()))?;
+
+        if !redeemed {
+            return Err(match challenge.status {
+                ChallengeStatus::Redeemed => {
+                    Error::Validation("Authorization request was already used".to_string())
+                }
+                ChallengeStatus::Pending | ChallengeStatus::Expired => {
+                    if let Err(e) = storage.expire_authorization_challenges().await {
+                        log::warn!("Failed to expire authorization challenges: {}", e);
+                    }
+                    Error::Validation("Authorization request has expired".to_string())
+                }
+            });
+        }
+
+        let authorize = Authorize {
+            transaction_id: challenge.transaction_id.clone(),
+            settlement_address: None,
+            expiry: None,
+        };
+        let mut message = authorize
+            .to_didcomm_with_route(&challenge.agent_did, [challenge.recipient_did.as_str()])
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+        message.thid = Some(challenge.transaction_id.clone());
+        let message_id = message.id.clone();
+
+        self.send_message(challenge.agent_did.clone(), message)
+            .await?;
+
+        storage
+            .set_authorization_challenge_message(nonce, &message_id)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+
+        if let Some(storage_manager) = self.agent_storage_manager() {
+            match storage_manager
+                .get_agent_storage(&challenge.agent_did)
+                .await
+            {
+                Ok(agent_storage) => {
+                    if let Err(e) = agent_storage
+                        .update_transaction_agent_status(
+                            &challenge.transaction_id,
+                            &challenge.agent_did,
+                            "authorized",
+                        )
+                        .await
+                    {
+                        log::debug!(
+                            "Could not mark agent {} authorized for transaction {}: {}",
+                            challenge.agent_did,
+                            challenge.transaction_id,
+                            e
+                        );
+                    }
+                    if let Err(e) = agent_storage
+                        .resolve_decisions_for_transaction(
+                            &challenge.transaction_id,
+                            "authorize",
+                            Some(DecisionType::AuthorizationRequired),
+                        )
+                        .await
+                    {
+                        log::warn!(
+                            "Failed to resolve decisions for transaction {}: {}",
+                            challenge.transaction_id,
+                            e
+                        );
+                    }
+                }
+                Err(e) => log::warn!(
+                    "Failed to get storage for agent {}: {}",
+                    challenge.agent_did,
+                    e
+                ),
+            }
+        }
+
+        Ok(AuthorizationChallenge {
+            message_id: Some(message_id),
+            ..challenge
+        })
+    }
+}
@@ -0,0 +1,513 @@
+//! Scheduled backups of node and agent databases
+//!
+//! A [`BackupPolicy`] sets where backups go, how often they are taken and how
+//! long they are kept. The [`BackupScheduler`] snapshots databases with the
+//! SQLite online backup API ([`Storage::backup_to`]), either on demand with
+//! [`BackupScheduler::backup`] or periodically as the [`BACKUP_JOB`] of the
+//! node's scheduler.
+//!
+//! The backups of a database are named `transactions-{timestamp}.db` and kept
+//! under a label of their own: `node` for the node's database and the
+//! sanitized DID of the agent for agent databases. A
+//! [`BackupDestination::Directory`] stores them in `{dir}/{label}/`; with the
+//! `s3` feature, [`BackupDestination::S3`] uploads them to an S3-compatible
+//! bucket under `{prefix}{label}/`. After each backup, the backups of the
+//! database beyond `keep_last`, or older than `max_age`, are deleted.
+
+#[cfg(feature = "s3")]
+pub mod s3;
+
+use crate::clock::{system_clock, Clock};
+use crate::error::{Error, Result};
+use crate::scheduler::{JobControl, Schedule, ScheduledJob, Scheduler};
+use crate::storage::{AgentStorageManager, Storage};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+/// Name of the scheduled job backing up the databases
+pub const BACKUP_JOB: &str = "backup";
+
+/// Label of the backups of the node's own database
+pub const NODE_BACKUP_LABEL: &str = "node";
+
+/// Format of the timestamp in backup names
+const BACKUP_TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+/// Where backups are stored
+#[derive(Debug, Clone)]
+pub enum BackupDestination {
+    /// A local directory, e.g. a mounted network volume
+    Directory(PathBuf),
+    /// A bucket of an S3-compatible object store
+    #[cfg(feature = "s3")]
+    S3(s3::S3Destination),
+}
+
+/// How often databases are backed up and how long backups are kept
+#[derive(Debug, Clone)]
+pub struct BackupPolicy {
+    /// Where backups are stored
+    pub destination: BackupDestination,
+    /// How often the background scheduler backs up each database
+    pub interval: Duration,
+    /// Number of most recent backups kept per database (None keeps all)
+    pub keep_last: Option<usize>,
+    /// Maximum age of backups before they are deleted (None keeps them
+    /// regardless of age)
+    pub max_age: Option<Duration>,
+}
+
+impl BackupPolicy {
+    /// Back up daily to `destination`, keeping the last 7 backups
+    pub fn new(destination: BackupDestination) -> Self {
+        Self {
+            destination,
+            interval: Duration::from_secs(24 * 3600),
+            keep_last: Some(7),
+            max_age: None,
+        }
+    }
+
+    /// Set how often the background scheduler backs up each database
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Set the number of most recent backups kept per database
+    pub fn with_keep_last(mut self, keep_last: Option<usize>) -> Self {
+        self.keep_last = keep_last;
+        self
+    }
+
+    /// Set the maximum age of backups
+    pub fn with_max_age(mut self, max_age: Option<Duration>) -> Self {
+        self.max_age = max_age;
+        self
+    }
+}
+
+/// A stored backup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupInfo {
+    /// Label of the backed up database, see [`agent_label`]
+    pub label: String,
+    /// File name of the backup, `transactions-{timestamp}.db`
+    pub name: String,
+    /// Path or object URL of the backup
+    pub location: String,
+    /// When the backup was taken
+    pub taken_at: DateTime<Utc>,
+}
+
+/// Label of the backups of an agent's database: its sanitized DID, as used
+/// for its storage directory
+pub fn agent_label(agent_did: &str) -> String {
+    Storage::sanitize_did(agent_did)
+}
+
+/// File name of a backup taken at `taken_at`
+fn backup_name(taken_at: DateTime<Utc>) -> String {
+    format!(
+        "transactions-{}.db",
+        taken_at.format(BACKUP_TIMESTAMP_FORMAT)
+    )
+}
+
+/// When the backup with the given file name was taken, None for other files
+fn parse_backup_name(name: &str) -> Option<DateTime<Utc>> {
+    let timestamp = name.strip_prefix("transactions-")?.strip_suffix(".db")?;
+    NaiveDateTime::parse_from_str(timestamp, BACKUP_TIMESTAMP_FORMAT)
+        .ok()
+        .map(|taken_at| taken_at.and_utc())
+}
+
+impl BackupDestination {
+    /// Store the backup file `file` as `name` under `label`
+    async fn store(&self, label: &str, name: &str, file: &Path) -> Result<String> {
+        match self {
+            BackupDestination::Directory(dir) => {
+                let target = dir.join(label).join(name);
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent).map_err(backup_error)?;
+                }
+                std::fs::rename(file, &target)
+                    .or_else(|_| std::fs::copy(file, &target).map(|_| ()))
+                    .map_err(backup_error)?;
+                Ok(target.display().to_string())
+            }
+            #[cfg(feature = "s3")]
+            BackupDestination::S3(s3) => {
+                let body = std::fs::read(file).map_err(backup_error)?;
+                s3.put(&format!("{}/{}", label, name), body).await
+            }
+        }
+    }
+
+    /// Names and locations of the files stored under `label`
+    async fn list(&self, label: &str) -> Result<Vec<(String, String)>> {
+        match self {
+            BackupDestination::Directory(dir) => {
+                let entries = match std::fs::read_dir(dir.join(label)) {
+                    Ok(entries) => entries,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                    Err(e) => return Err(backup_error(e)),
+                };
+                Ok(entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| {
+                        (
+                            entry.file_name().to_string_lossy().to_string(),
+                            entry.path().display().to_string(),
+                        )
+                    })
+                    .collect())
+            }
+            #[cfg(feature = "s3")]
+            BackupDestination::S3(s3) => {
+                let prefix = format!("{}/", label);
+                Ok(s3
+                    .list(&prefix)
+                    .await?
+                    .into_iter()
+                    .filter_map(|key| {
+                        let name = key.rsplit('/').next()?.to_string();
+                        let location = s3.object_url(&key);
+                        Some((name, location))
+                    })
+                    .collect())
+            }
+        }
+    }
+
+    /// Delete the backup `name` stored under `label`
+    async fn delete(&self, label: &str, name: &str) -> Result<()> {
+        match self {
+            BackupDestination::Directory(dir) => {
+                std::fs::remove_file(dir.join(label).join(name)).map_err(backup_error)
+            }
+            #[cfg(feature = "s3")]
+            BackupDestination::S3(s3) => s3.delete(&format!("{}/{}", label, name)).await,
+        }
+    }
+
+    /// Copy the backup `name` stored under `label` to the local file `file`
+    async fn fetch(&self, label: &str, name: &str, file: &Path) -> Result<()> {
+        match self {
+            BackupDestination::Directory(dir) => std::fs::copy(dir.join(label).join(name), file)
+                .map(|_| ())
+                .map_err(backup_error),
+            #[cfg(feature = "s3")]
+            BackupDestination::S3(s3) => {
+                let body = s3.get(&format!("{}/{}", label, name)).await?;
+                std::fs::write(file, body).map_err(backup_error)
+            }
+        }
+    }
+}
+
+fn backup_error(e: std::io::Error) -> Error {
+    Error::Storage(format!("Backup failed: {}", e))
+}
+
+/// Takes, prunes and restores backups according to a [`BackupPolicy`]
+#[derive(Debug, Clone)]
+pub struct BackupScheduler {
+    policy: BackupPolicy,
+    clock: Arc<dyn Clock>,
+}
+
+impl BackupScheduler {
+    /// Create a new scheduler for the given policy
+    pub fn new(policy: BackupPolicy) -> Self {
+        Self {
+            policy,
+            clock: system_clock(),
+        }
+    }
+
+    /// Timestamp backups and measure their ages with the given clock instead
+    /// of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Get the backup policy
+    pub fn policy(&self) -> &BackupPolicy {
+        &self.policy
+    }
+
+    /// Back up a database under `label`, then delete its expired backups
+    pub async fn backup(&self, label: &str, storage: &Storage) -> Result<BackupInfo> {
+        let taken_at = self.clock.now();
+        let name = backup_name(taken_at);
+
+        // Snapshot next to the database first, so that only complete backups
+        // reach the destination
+        let staging = staging_path(storage, &name);
+        storage
+            .backup_to(&staging)
+            .await
+            .map_err(|e| Error::Storage(format!("Backup of {} failed: {}", label, e)))?;
+        let stored = self.policy.destination.store(label, &name, &staging).await;
+        let _ = std::fs::remove_file(&staging);
+        let location = stored?;
+        info!("Backed up {} to {}", label, location);
+
+        self.prune(label).await?;
+        Ok(BackupInfo {
+            label: label.to_string(),
+            name,
+            location,
+            taken_at,
+        })
+    }
+
+    /// List the backups stored under `label`, oldest first
+    pub async fn list(&self, label: &str) -> Result<Vec<BackupInfo>> {
+        let mut backups: Vec<BackupInfo> = self
+            .policy
+            .destination
+            .list(label)
+            .await?
+            .into_iter()
+            .filter_map(|(name, location)| {
+                Some(BackupInfo {
+                    taken_at: parse_backup_name(&name)?,
+                    label: label.to_string(),
+                    name,
+                    location,
+                })
+            })
+            .collect();
+        backups.sort_by_key(|backup| backup.taken_at);
+        Ok(backups)
+    }
+
+    /// Delete the backups under `label` beyond `keep_last` or older than
+    /// `max_age`, returning how many were deleted
+    pub async fn prune(&self, label: &str) -> Result<usize> {
+        let backups = self.list(label).await?;
+        let cutoff = self
+            .policy
+            .max_age
+            .and_then(|age| chrono::Duration::from_std(age).ok())
+            .and_then(|age| self.clock.now().checked_sub_signed(age));
+        let keep_from = self
+            .policy
+            .keep_last
+            .map_or(0, |keep| backups.len().saturating_sub(keep));
+
+        let mut deleted = 0;
+        for (position, backup) in backups.iter().enumerate() {
+            let expired = cutoff.is_some_and(|cutoff| backup.taken_at < cutoff);
+            if position < keep_from || expired {
+                debug!("Deleting expired backup {}", backup.location);
+                self.policy.destination.delete(label, &backup.name).await?;
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Restore a database from a backup stored under `label`, the most recent
+    /// one if `name` is None
+    pub async fn restore(
+        &self,
+        label: &str,
+        name: Option<&str>,
+        storage: &Storage,
+    ) -> Result<BackupInfo> {
+        let backups = self.list(label).await?;
+        let backup = match name {
+            Some(name) => backups.into_iter().find(|backup| backup.name == name),
+            None => backups.into_iter().last(),
+        }
+        .ok_or_else(|| {
+            Error::Storage(format!(
+                "No backup {} of {}",
+                name.unwrap_or("at all"),
+                label
+            ))
+        })?;
+
+        let staging = staging_path(storage, &backup.name);
+        self.policy
+            .destination
+            .fetch(label, &backup.name, &staging)
+            .await?;
+        let restored = storage.restore_from(&staging).await;
+        let _ = std::fs::remove_file(&staging);
+        restored.map_err(|e| Error::Storage(format!("Restore of {} failed: {}", label, e)))?;
+        info!("Restored {} from {}", label, backup.location);
+        Ok(backup)
+    }
+
+    /// Register the scheduler as the [`BACKUP_JOB`] of the node's scheduler,
+    /// running every backup interval unless configured otherwise
+    ///
+    /// The job covers the node's main storage and every cached agent storage.
+    /// It holds weak references only and stops once both are dropped.
+    pub async fn schedule(
+        self,
+        scheduler: &Scheduler,
+        storage: Option<&Arc<Storage>>,
+        agent_storage_manager: Option<&Arc<AgentStorageManager>>,
+    ) {
+        let schedule = Schedule::Every(self.policy.interval);
+        let job = BackupJob {
+            backups: self,
+            storage: storage.map(Arc::downgrade),
+            agent_storage_manager: agent_storage_manager.map(Arc::downgrade),
+        };
+        scheduler
+            .register(BACKUP_JOB, schedule, Arc::new(job))
+            .await;
+    }
+}
+
+struct BackupJob {
+    backups: BackupScheduler,
+    storage: Option<Weak<Storage>>,
+    agent_storage_manager: Option<Weak<AgentStorageManager>>,
+}
+
+#[async_trait]
+impl ScheduledJob for BackupJob {
+    async fn run(&self) -> Result<JobControl> {
+        let storage = self.storage.as_ref().and_then(Weak::upgrade);
+        let manager = self.agent_storage_manager.as_ref().and_then(Weak::upgrade);
+        if storage.is_none() && manager.is_none() {
+            debug!("Storage dropped, stopping scheduled backups");
+            return Ok(JobControl::Stop);
+        }
+
+        let mut targets: Vec<(String, Arc<Storage>)> = Vec::new();
+        if let Some(storage) = storage {
+            targets.push((NODE_BACKUP_LABEL.to_string(), storage));
+        }
+        if let Some(manager) = manager {
+            for agent_did in manager.cached_agent_dids() {
+                if let Some(agent_storage) = manager.get_cached_agent_storage(&agent_did) {
+                    targets.push((agent_label(&agent_did), agent_storage));
+                }
+            }
+        }
+
+        let mut failed = Vec::new();
+        for (label, target) in targets {
+            if let Err(e) = self.backups.backup(&label, &target).await {
+                error!("Scheduled backup failed for {}: {}", label, e);
+                failed.push(label);
+            }
+        }
+        if !failed.is_empty() {
+            return Err(Error::Storage(format!(
+                "Backup failed for {}",
+                failed.join(", ")
+            )));
+        }
+        Ok(JobControl::Continue)
+    }
+}
+
+/// Temporary file for a backup on its way to or from the destination
+fn staging_path(storage: &Storage, name: &str) -> PathBuf {
+    let dir = match storage.db_path().parent() {
+        Some(parent) if !storage.db_path().as_os_str().is_empty() && parent.exists() => {
+            parent.to_path_buf()
+        }
+        _ => std::env::temp_dir(),
+    };
+    dir.join(format!(".{}.{}", uuid::Uuid::new_v4(), name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::storage::MessageDirection;
+    use tap_msg::didcomm::PlainMessage;
+    use tempfile::TempDir;
+
+    fn message(id: &str) -> PlainMessage {
+        PlainMessage::new(
+            id.to_string(),
+            "https://tap.rsvp/schema/1.0#Reject".to_string(),
+            serde_json::json!({ "reason": "Sanctioned" }),
+            "did:example:sender".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_backup_names_round_trip() {
+        let taken_at = "2026-03-01T12:30:00.250Z".parse::<DateTime<Utc>>().unwrap();
+        let name = backup_name(taken_at);
+        assert_eq!(name, "transactions-20260301T123000.250Z.db");
+        assert_eq!(parse_backup_name(&name), Some(taken_at));
+        assert_eq!(parse_backup_name("notes.txt"), None);
+    }
+
+    #[tokio::test]
+    async fn test_backups_are_pruned_and_restored() {
+        let dir = TempDir::new().unwrap();
+        let storage = Storage::new(Some(dir.path().join("db").join("transactions.db")))
+            .await
+            .unwrap();
+        let clock = Arc::new(MockClock::new(
+            "2026-03-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap(),
+        ));
+        let scheduler = BackupScheduler::new(
+            BackupPolicy::new(BackupDestination::Directory(dir.path().join("backups")))
+                .with_keep_last(Some(2)),
+        )
+        .with_clock(clock.clone());
+
+        for id in ["msg-1", "msg-2", "msg-3"] {
+            storage
+                .log_message(&message(id), MessageDirection::Incoming)
+                .await
+                .unwrap();
+            scheduler
+                .backup("did_example_vasp", &storage)
+                .await
+                .unwrap();
+            clock.advance(chrono::Duration::hours(1));
+        }
+
+        let backups = scheduler.list("did_example_vasp").await.unwrap();
+        assert_eq!(
+            backups.iter().map(|b| b.name.as_str()).collect::<Vec<_>>(),
+            vec![
+                "transactions-20260301T010000.000Z.db",
+                "transactions-20260301T020000.000Z.db",
+            ]
+        );
+
+        // Restore the database as it was after its second message
+        let restored = scheduler
+            .restore("did_example_vasp", Some(&backups[0].name), &storage)
+            .await
+            .unwrap();
+        assert_eq!(restored, backups[0]);
+        assert_eq!(storage.list_messages(10, 0, None).await.unwrap().len(), 2);
+
+        // Backups older than the maximum age go too
+        let scheduler = BackupScheduler::new(
+            BackupPolicy::new(BackupDestination::Directory(dir.path().join("backups")))
+                .with_max_age(Some(Duration::from_secs(90 * 60))),
+        )
+        .with_clock(clock);
+        assert_eq!(scheduler.prune("did_example_vasp").await.unwrap(), 1);
+        assert!(scheduler
+            .restore("did_example_vasp", Some(&backups[0].name), &storage)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,303 @@
+//! Callbacks for received messages of one type
+//!
+//! Reacting to a single message type, say the Authorize messages an agent
+//! receives, does not need an [`Agent`](crate::agent::Agent) or
+//! [`PlainMessageProcessor`](crate::message::PlainMessageProcessor)
+//! implementation: [`TapNode::on_message`](crate::TapNode::on_message)
+//! registers an async callback for an agent DID and a TAP message type,
+//! which receives the message with its typed body.
+//! [`TapNode::on_message_type`](crate::TapNode::on_message_type) does the
+//! same for any type URI, with the body as JSON.
+//!
+//! Callbacks run after a message passed validation and was delivered to the
+//! agent, on tasks of their own so they do not hold up processing. The
+//! callbacks of messages in the same thread (`thid`, or the message ID when
+//! there is none) run one at a time, in the order the messages were
+//! delivered; those of other threads run concurrently. A callback that fails
+//! is logged and does not affect the message or other callbacks.
+//!
+//! ```no_run
+//! use tap_msg::message::Authorize;
+//! # async fn example(node: &tap_node::TapNode) {
+//! let callback_id = node.on_message(
+//!     "did:example:merchant",
+//!     |received: tap_node::callbacks::ReceivedMessage<Authorize>| async move {
+//!         println!(
+//!             "{} authorized {}",
+//!             received.message.from, received.body.transaction_id
+//!         );
+//!         Ok(())
+//!     },
+//! );
+//! // ...
+//! node.remove_callback(&callback_id);
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tap_msg::didcomm::PlainMessage;
+use tokio::sync::mpsc;
+
+/// A received message passed to a callback, with its body
+#[derive(Debug, Clone)]
+pub struct ReceivedMessage<T> {
+    /// The agent the message was delivered to
+    pub agent_did: String,
+    /// The message
+    pub message: PlainMessage,
+    /// The body of the message
+    pub body: T,
+}
+
+/// A callback, with the body deserialized
+type Callback =
+    Arc<dyn Fn(&str, &PlainMessage) -> Result<BoxFuture<'static, Result<()>>> + Send + Sync>;
+
+/// A registered callback
+struct Registration {
+    agent_did: String,
+    message_type: String,
+    callback: Callback,
+}
+
+/// Callbacks for (agent DID, message type) pairs
+#[derive(Default)]
+pub struct MessageCallbacks {
+    registrations: DashMap<String, Registration>,
+    /// Queues of the threads whose callbacks are running
+    threads: Arc<Mutex<HashMap<String, mpsc::UnboundedSender<BoxFuture<'static, ()>>>>>,
+}
+
+impl MessageCallbacks {
+    /// Create an empty set of callbacks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a callback for the messages of `message_type` delivered to
+    /// `agent_did`, receiving the body deserialized as `T`, returning its ID
+    pub fn register<T, F, Fut>(&self, agent_did: &str, message_type: &str, callback: F) -> String
+    where
+        T: DeserializeOwned + Send + 'static,
+        F: Fn(ReceivedMessage<T>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let callback: Callback = Arc::new(move |agent_did: &str, message: &PlainMessage| {
+            let body = serde_json::from_value(message.body.clone()).map_err(|e| {
+                Error::Serialization(format!("Invalid body of {}: {}", message.type_, e))
+            })?;
+            Ok(Box::pin(callback(ReceivedMessage {
+                agent_did: agent_did.to_string(),
+                message: message.clone(),
+                body,
+            })) as BoxFuture<'static, Result<()>>)
+        });
+        let id = uuid::Uuid::new_v4().to_string();
+        self.registrations.insert(
+            id.clone(),
+            Registration {
+                agent_did: agent_did.to_string(),
+                message_type: message_type.to_string(),
+                callback,
+            },
+        );
+        id
+    }
+
+    /// Remove a callback, returning whether it was registered
+    pub fn remove(&self, callback_id: &str) -> bool {
+        self.registrations.remove(callback_id).is_some()
+    }
+
+    /// Number of registered callbacks
+    pub fn len(&self) -> usize {
+        self.registrations.len()
+    }
+
+    /// Whether no callback is registered
+    pub fn is_empty(&self) -> bool {
+        self.registrations.is_empty()
+    }
+
+    /// Run the callbacks registered for a message delivered to `agent_did`,
+    /// after those of the earlier messages of its thread
+    pub fn dispatch(&self, agent_did: &str, message: &PlainMessage) {
+        let mut callbacks = Vec::new();
+        for registration in self.registrations.iter() {
+            if registration.agent_did != agent_did || registration.message_type != message.type_ {
+                continue;
+            }
+            match (registration.callback)(agent_did, message) {
+                Ok(callback) => callbacks.push((registration.key().clone(), callback)),
+                Err(e) => log::warn!(
+                    "Callback {} cannot take message {}: {}",
+                    registration.key(),
+                    message.id,
+                    e
+                ),
+            }
+        }
+        if callbacks.is_empty() {
+            return;
+        }
+
+        let message_id = message.id.clone();
+        let job: BoxFuture<'static, ()> = Box::pin(async move {
+            for (callback_id, callback) in callbacks {
+                if let Err(e) = callback.await {
+                    log::warn!(
+                        "Callback {} failed on message {}: {}",
+                        callback_id,
+                        message_id,
+                        e
+                    );
+                }
+            }
+        });
+        let thread = message.thid.clone().unwrap_or_else(|| message.id.clone());
+        self.enqueue(thread, job);
+    }
+
+    /// Queue a job after the running jobs of its thread, starting a task
+    /// for the thread if none runs
+    fn enqueue(&self, thread: String, job: BoxFuture<'static, ()>) {
+        let mut threads = self.threads.lock().unwrap_or_else(|e| e.into_inner());
+        let job = match threads.get(&thread) {
+            Some(queue) => match queue.send(job) {
+                Ok(()) => return,
+                Err(mpsc::error::SendError(job)) => job,
+            },
+            None => job,
+        };
+        let (queue, mut jobs) = mpsc::unbounded_channel();
+        let _ = queue.send(job);
+        threads.insert(thread.clone(), queue);
+
+        let threads = self.threads.clone();
+        tokio::spawn(async move {
+            loop {
+                let job = match jobs.try_recv() {
+                    Ok(job) => job,
+                    Err(_) => {
+                        // The queue is only dropped while no job can be added
+                        let mut threads = threads.lock().unwrap_or_else(|e| e.into_inner());
+                        match jobs.try_recv() {
+                            Ok(job) => job,
+                            Err(_) => {
+                                threads.remove(&thread);
+                                return;
+                            }
+                        }
+                    }
+                };
+                job.await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::time::Duration;
+
+    #[derive(Debug, Deserialize)]
+    struct Ping {
+        sequence: u32,
+    }
+
+    fn message(id: &str, thid: &str, sequence: u32) -> PlainMessage {
+        PlainMessage::new(
+            id.to_string(),
+            "https://example.org/ping".to_string(),
+            serde_json::json!({"sequence": sequence}),
+            "did:example:them".to_string(),
+        )
+        .with_recipient("did:example:us")
+        .with_thread_id(Some(thid.to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_callbacks_run_in_order_per_thread() {
+        let callbacks = MessageCallbacks::new();
+        let (sender, mut received) = mpsc::unbounded_channel();
+        callbacks.register(
+            "did:example:us",
+            "https://example.org/ping",
+            move |ping: ReceivedMessage<Ping>| {
+                let sender = sender.clone();
+                async move {
+                    // Earlier messages take longer, so only the queue keeps them in order
+                    tokio::time::sleep(Duration::from_millis(u64::from(20 - ping.body.sequence)))
+                        .await;
+                    sender
+                        .send((ping.message.thid.unwrap(), ping.body.sequence))
+                        .unwrap();
+                    Ok(())
+                }
+            },
+        );
+
+        for sequence in 0..10 {
+            callbacks.dispatch(
+                "did:example:us",
+                &message(&format!("a-{}", sequence), "thread-a", sequence),
+            );
+            callbacks.dispatch(
+                "did:example:us",
+                &message(&format!("b-{}", sequence), "thread-b", sequence),
+            );
+        }
+
+        let mut order: HashMap<String, Vec<u32>> = HashMap::new();
+        for _ in 0..20 {
+            let (thread, sequence) = received.recv().await.unwrap();
+            order.entry(thread).or_default().push(sequence);
+        }
+        let expected: Vec<u32> = (0..10).collect();
+        assert_eq!(order["thread-a"], expected);
+        assert_eq!(order["thread-b"], expected);
+    }
+
+    #[tokio::test]
+    async fn test_callbacks_match_agent_and_type() {
+        let callbacks = MessageCallbacks::new();
+        let (sender, mut received) = mpsc::unbounded_channel();
+        let id = callbacks.register(
+            "did:example:us",
+            "https://example.org/ping",
+            move |ping: ReceivedMessage<Ping>| {
+                let sender = sender.clone();
+                async move {
+                    sender.send(ping.message.id).unwrap();
+                    Ok(())
+                }
+            },
+        );
+        assert_eq!(callbacks.len(), 1);
+
+        callbacks.dispatch("did:example:other", &message("m-1", "t", 1));
+        let mut other_type = message("m-2", "t", 2);
+        other_type.type_ = "https://example.org/pong".to_string();
+        callbacks.dispatch("did:example:us", &other_type);
+        // Bodies that do not deserialize are skipped
+        let mut invalid = message("m-3", "t", 3);
+        invalid.body = serde_json::json!({"sequence": "three"});
+        callbacks.dispatch("did:example:us", &invalid);
+        callbacks.dispatch("did:example:us", &message("m-4", "t", 4));
+        assert_eq!(received.recv().await.unwrap(), "m-4");
+
+        assert!(callbacks.remove(&id));
+        assert!(!callbacks.remove(&id));
+        callbacks.dispatch("did:example:us", &message("m-5", "t", 5));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(received.try_recv().is_err());
+    }
+}
@@ -0,0 +1,172 @@
+//! Optional subsystems active in a node
+//!
+//! Nodes differ in the features they were compiled with and in how they
+//! were configured: one keeps transactions in SQLite and runs a policy
+//! engine, another is a WASM build without storage. [`TapNode::capabilities`]
+//! reports which of the optional subsystems are active, so tooling talking
+//! to a node (the attestation published by tap-http, the MCP server's
+//! initialization and `tap-cli node capabilities`) can adapt instead of
+//! failing on calls the node cannot serve.
+
+use crate::message::PlainMessageProcessorType;
+use crate::TapNode;
+use serde::{Deserialize, Serialize};
+
+/// Cargo features of tap-node that change what a node can do
+const FEATURES: &[(&str, bool)] = &[
+    ("native", cfg!(feature = "native")),
+    ("storage", cfg!(feature = "storage")),
+    ("state-machine", cfg!(feature = "state-machine")),
+    ("customers", cfg!(feature = "customers")),
+    ("travel-rule", cfg!(feature = "travel-rule")),
+    ("delivery-tracking", cfg!(feature = "delivery-tracking")),
+    ("websocket", cfg!(feature = "websocket")),
+    ("scripting", cfg!(feature = "scripting")),
+    ("s3", cfg!(feature = "s3")),
+    ("postgres", cfg!(feature = "postgres")),
+    ("diagnostics", cfg!(feature = "diagnostics")),
+    ("wasm", cfg!(feature = "wasm")),
+];
+
+/// Where a node keeps its transactions and messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    /// SQLite databases on disk
+    Sqlite,
+    /// An in-memory SQLite database, lost when the node stops
+    SqliteInMemory,
+}
+
+/// Optional subsystems active in a node, see the [module documentation](self)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeCapabilities {
+    /// Version of tap-node
+    pub version: String,
+    /// Cargo features the node was compiled with
+    pub features: Vec<String>,
+    /// Storage backend, none if the node keeps no storage
+    pub storage: Option<StorageBackend>,
+    /// Name of the shared backend agent databases also write to, e.g.
+    /// `postgres`
+    #[serde(default)]
+    pub shared_storage: Option<String>,
+    /// Whether a travel rule processor handles IVMS101 data of messages
+    pub travel_rule: bool,
+    /// Whether a policy engine evaluates transactions requiring
+    /// authorization
+    pub policy_engine: bool,
+    /// Whether messages can be sent over WebSocket
+    pub websocket: bool,
+    /// Whether processing metrics are emitted through `tracing`, see
+    /// [`diagnostics`](crate::diagnostics)
+    pub metrics: bool,
+}
+
+impl NodeCapabilities {
+    /// Whether the node was compiled with a Cargo feature
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.features.iter().any(|f| f == feature)
+    }
+}
+
+impl TapNode {
+    /// Optional subsystems active in this node
+    pub fn capabilities(&self) -> NodeCapabilities {
+        let is_travel_rule =
+            |processor: &PlainMessageProcessorType| processor.name() == "travel_rule";
+
+        NodeCapabilities {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            features: FEATURES
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(feature, _)| feature.to_string())
+                .collect(),
+            storage: self.storage_backend(),
+            shared_storage: self.shared_storage_backend(),
+            travel_rule: self.incoming_processor.contains(&is_travel_rule)
+                || self.outgoing_processor.contains(&is_travel_rule),
+            policy_engine: self.has_policy_engine(),
+            websocket: cfg!(feature = "websocket"),
+            metrics: cfg!(feature = "diagnostics"),
+        }
+    }
+
+    #[cfg(feature = "storage")]
+    fn storage_backend(&self) -> Option<StorageBackend> {
+        match &self.storage {
+            Some(storage) if storage.is_in_memory() => Some(StorageBackend::SqliteInMemory),
+            Some(_) => Some(StorageBackend::Sqlite),
+            None => self
+                .agent_storage_manager
+                .as_ref()
+                .map(|_| StorageBackend::Sqlite),
+        }
+    }
+
+    #[cfg(not(feature = "storage"))]
+    fn storage_backend(&self) -> Option<StorageBackend> {
+        None
+    }
+
+    #[cfg(feature = "storage")]
+    fn shared_storage_backend(&self) -> Option<String> {
+        self.config
+            .shared_storage
+            .as_ref()
+            .map(|backend| backend.name().to_string())
+    }
+
+    #[cfg(not(feature = "storage"))]
+    fn shared_storage_backend(&self) -> Option<String> {
+        None
+    }
+
+    #[cfg(feature = "storage")]
+    fn has_policy_engine(&self) -> bool {
+        self.config.policy_engine.is_some()
+    }
+
+    #[cfg(not(feature = "storage"))]
+    fn has_policy_engine(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::customer::CustomerManager;
+    use crate::message::{CompositePlainMessageProcessor, TravelRuleProcessor};
+    use crate::storage::Storage;
+    use crate::NodeConfig;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_capabilities() {
+        let mut node = TapNode::new(NodeConfig::default());
+        let capabilities = node.capabilities();
+        assert_eq!(capabilities.version, env!("CARGO_PKG_VERSION"));
+        assert!(capabilities.has_feature("storage"));
+        assert!(capabilities.has_feature("travel-rule"));
+        // Agents keep their databases on disk
+        assert_eq!(capabilities.storage, Some(StorageBackend::Sqlite));
+        assert!(!capabilities.travel_rule);
+        assert!(!capabilities.policy_engine);
+
+        // Processors nested in a composite count
+        let storage = Arc::new(Storage::new_in_memory().await.unwrap());
+        let travel_rule = TravelRuleProcessor::new(Arc::new(CustomerManager::new(storage)));
+        node.add_outgoing_processor(PlainMessageProcessorType::Composite(
+            CompositePlainMessageProcessor::new(vec![PlainMessageProcessorType::TravelRule(
+                travel_rule,
+            )]),
+        ));
+        assert!(node.capabilities().travel_rule);
+
+        let json = serde_json::to_value(node.capabilities()).unwrap();
+        assert_eq!(json["storage"], "sqlite");
+        assert_eq!(json["travel_rule"], true);
+    }
+}
@@ -0,0 +1,80 @@
+//! Time source for node components
+//!
+//! Components that check timestamps, compute expiries or record when
+//! something happened read the current time from a [`Clock`] instead of
+//! calling `Utc::now()` directly. Nodes use the [`SystemClock`] unless
+//! [`NodeConfig::clock`](crate::NodeConfig::clock) is set; tests can use a
+//! [`MockClock`] to control time deterministically.
+//!
+//! ```
+//! use chrono::{Duration, TimeZone, Utc};
+//! use tap_node::clock::{Clock, MockClock};
+//!
+//! let clock = MockClock::new(Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap());
+//! clock.advance(Duration::minutes(5));
+//! assert_eq!(clock.now(), Utc.with_ymd_and_hms(2025, 1, 1, 12, 5, 0).unwrap());
+//! ```
+
+use chrono::{DateTime, Duration, Utc};
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+/// Source of the current time
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// The current time
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Clock reading the system time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Clock that only moves when told to
+#[derive(Debug)]
+pub struct MockClock {
+    now: RwLock<DateTime<Utc>>,
+}
+
+impl MockClock {
+    /// Create a clock stopped at the given time
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: RwLock::new(now),
+        }
+    }
+
+    /// Set the current time
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.write().unwrap() = now;
+    }
+
+    /// Move the current time forward (or backward, for a negative duration)
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.write().unwrap();
+        *now += duration;
+    }
+}
+
+impl Default for MockClock {
+    /// A clock stopped at the current system time
+    fn default() -> Self {
+        Self::new(Utc::now())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.read().unwrap()
+    }
+}
+
+/// Shared handle to the system clock
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
@@ -0,0 +1,375 @@
+//! Local clock health
+//!
+//! Incoming messages are checked against the local clock: a message whose
+//! `created_time` is too far in the future is rejected. When the node's own
+//! clock drifts, every counterparty seems skewed and valid messages are
+//! rejected. The [`ClockHealthMonitor`] compares the local clock with NTP
+//! servers when the node starts and periodically afterwards, and publishes a
+//! [`NodeEvent::ClockDriftDetected`] warning whenever the offset exceeds the
+//! policy's tolerance.
+//!
+//! The checks run as the [`CLOCK_HEALTH_JOB`] of the node's scheduler. The
+//! latest check is kept for [`TapNode::clock_metrics`], next to the skew
+//! observed in the messages of each counterparty.
+//!
+//! [`TapNode::clock_metrics`]: crate::TapNode::clock_metrics
+
+use crate::clock::{system_clock, Clock};
+use crate::error::{Error, Result};
+use crate::event::{EventBus, NodeEvent};
+#[cfg(feature = "storage")]
+use crate::scheduler::{JobControl, Schedule, ScheduledJob, Scheduler};
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Serialize;
+use std::fmt;
+#[cfg(feature = "storage")]
+use std::sync::Weak;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tracing::{debug, warn};
+
+/// Name of the scheduled job checking the local clock
+pub const CLOCK_HEALTH_JOB: &str = "clock_health";
+
+/// Seconds between the NTP epoch (1900) and the Unix epoch (1970)
+const NTP_UNIX_OFFSET_SECS: i64 = 2_208_988_800;
+
+/// Settings for checking the local clock against NTP servers
+#[derive(Debug, Clone)]
+pub struct ClockHealthPolicy {
+    /// NTP servers as `host:port`, queried in order until one answers
+    pub servers: Vec<String>,
+    /// Offset from the NTP time past which the local clock is reported as
+    /// drifting
+    pub max_offset: Duration,
+    /// How often the clock is checked after the check on startup
+    pub check_interval: Duration,
+    /// How long to wait for each server to answer
+    pub timeout: Duration,
+}
+
+impl Default for ClockHealthPolicy {
+    fn default() -> Self {
+        Self {
+            servers: vec!["pool.ntp.org:123".to_string()],
+            max_offset: Duration::from_secs(5),
+            check_interval: Duration::from_secs(3600),
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Source of reference time the local clock is compared with
+#[async_trait]
+pub trait TimeReference: Send + Sync + fmt::Debug {
+    /// Offset of the local clock from a server's time
+    ///
+    /// The offset is the time to add to the local clock to match the server,
+    /// so it is positive when the local clock is behind.
+    async fn offset(
+        &self,
+        server: &str,
+        local: &dyn Clock,
+        timeout: Duration,
+    ) -> Result<chrono::Duration>;
+}
+
+/// Queries NTP servers with a single SNTP (RFC 4330) request
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SntpClient;
+
+impl SntpClient {
+    fn to_ntp(time: DateTime<Utc>) -> [u8; 8] {
+        let secs = (time.timestamp() + NTP_UNIX_OFFSET_SECS) as u32;
+        let fraction = ((time.timestamp_subsec_nanos() as u64) << 32) / 1_000_000_000;
+        let mut bytes = [0u8; 8];
+        bytes[..4].copy_from_slice(&secs.to_be_bytes());
+        bytes[4..].copy_from_slice(&(fraction as u32).to_be_bytes());
+        bytes
+    }
+
+    fn from_ntp(bytes: &[u8]) -> Option<DateTime<Utc>> {
+        let secs = u32::from_be_bytes(bytes[..4].try_into().ok()?) as i64;
+        let fraction = u32::from_be_bytes(bytes[4..8].try_into().ok()?) as u64;
+        let nanos = (fraction * 1_000_000_000) >> 32;
+        Utc.timestamp_opt(secs - NTP_UNIX_OFFSET_SECS, nanos as u32)
+            .single()
+    }
+}
+
+#[async_trait]
+impl TimeReference for SntpClient {
+    async fn offset(
+        &self,
+        server: &str,
+        local: &dyn Clock,
+        timeout: Duration,
+    ) -> Result<chrono::Duration> {
+        let query = async {
+            let socket = UdpSocket::bind("0.0.0.0:0").await?;
+            socket.connect(server).await?;
+
+            // LI 0, version 4, mode 3 (client), with our transmit time
+            let mut request = [0u8; 48];
+            request[0] = 0x23;
+            let sent = local.now();
+            request[40..48].copy_from_slice(&Self::to_ntp(sent));
+            socket.send(&request).await?;
+
+            let mut response = [0u8; 48];
+            let len = socket.recv(&mut response).await?;
+            Ok::<_, std::io::Error>((sent, local.now(), len, response))
+        };
+        let (sent, received, len, response) = tokio::time::timeout(timeout, query)
+            .await
+            .map_err(|_| Error::Processing(format!("NTP server {} did not answer", server)))?
+            .map_err(|e| Error::Processing(format!("NTP query to {} failed: {}", server, e)))?;
+
+        if len < 48 || response[1] == 0 {
+            return Err(Error::Processing(format!(
+                "NTP server {} sent an invalid or unsynchronized answer",
+                server
+            )));
+        }
+        let (Some(server_received), Some(server_sent)) = (
+            Self::from_ntp(&response[32..40]),
+            Self::from_ntp(&response[40..48]),
+        ) else {
+            return Err(Error::Processing(format!(
+                "NTP server {} sent invalid timestamps",
+                server
+            )));
+        };
+
+        Ok(((server_received - sent) + (server_sent - received)) / 2)
+    }
+}
+
+/// Result of comparing the local clock with an NTP server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ClockCheck {
+    /// The server that answered
+    pub server: String,
+    /// Time to add to the local clock to match the server, in milliseconds
+    pub offset_ms: i64,
+    /// Tolerated offset, in milliseconds
+    pub max_offset_ms: u64,
+    /// Whether the offset exceeds the tolerance
+    pub drifting: bool,
+    /// When the check was made, by the local clock
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Clock health of the node
+#[derive(Debug, Clone, Serialize)]
+pub struct ClockMetrics {
+    /// Latest check of the local clock (None before the first check, or
+    /// without a clock health policy)
+    pub local_clock: Option<ClockCheck>,
+    /// Skew observed in the messages of each counterparty
+    #[cfg(feature = "storage")]
+    pub counterparties: Vec<crate::validation::timestamp_validator::CounterpartySkew>,
+}
+
+/// Checks the local clock against NTP servers
+#[derive(Debug, Clone)]
+pub struct ClockHealthMonitor {
+    policy: ClockHealthPolicy,
+    clock: Arc<dyn Clock>,
+    reference: Arc<dyn TimeReference>,
+    last_check: Arc<RwLock<Option<ClockCheck>>>,
+}
+
+impl ClockHealthMonitor {
+    /// Create a new monitor for the given policy
+    pub fn new(policy: ClockHealthPolicy) -> Self {
+        Self {
+            policy,
+            clock: system_clock(),
+            reference: Arc::new(SntpClient),
+            last_check: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Check the given clock instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Compare the local clock with the given reference instead of SNTP
+    pub fn with_reference(mut self, reference: Arc<dyn TimeReference>) -> Self {
+        self.reference = reference;
+        self
+    }
+
+    /// Get the clock health policy
+    pub fn policy(&self) -> &ClockHealthPolicy {
+        &self.policy
+    }
+
+    /// The latest check, shared by all clones of the monitor
+    pub fn last_check(&self) -> Option<ClockCheck> {
+        self.last_check.read().unwrap().clone()
+    }
+
+    /// Compare the local clock with the first server that answers
+    ///
+    /// Publishes a [`NodeEvent::ClockDriftDetected`] event when the offset
+    /// exceeds the tolerance. Fails when no server answers.
+    pub async fn check(&self, event_bus: &EventBus) -> Result<ClockCheck> {
+        let mut errors = Vec::new();
+        for server in &self.policy.servers {
+            let offset = match self
+                .reference
+                .offset(server, self.clock.as_ref(), self.policy.timeout)
+                .await
+            {
+                Ok(offset) => offset,
+                Err(e) => {
+                    debug!("Clock check against {} failed: {}", server, e);
+                    errors.push(e.to_string());
+                    continue;
+                }
+            };
+
+            let max_offset_ms = self.policy.max_offset.as_millis() as u64;
+            let offset_ms = offset.num_milliseconds();
+            let check = ClockCheck {
+                server: server.clone(),
+                offset_ms,
+                max_offset_ms,
+                drifting: offset_ms.unsigned_abs() > max_offset_ms,
+                checked_at: self.clock.now(),
+            };
+            *self.last_check.write().unwrap() = Some(check.clone());
+
+            if check.drifting {
+                warn!(
+                    "Local clock is {} ms off the time of {} (tolerance: {} ms)",
+                    offset_ms, server, max_offset_ms
+                );
+                event_bus
+                    .publish_event(NodeEvent::ClockDriftDetected {
+                        server: server.clone(),
+                        offset_ms,
+                        max_offset_ms,
+                    })
+                    .await;
+            }
+            return Ok(check);
+        }
+
+        Err(Error::Processing(format!(
+            "No NTP server answered: {}",
+            errors.join("; ")
+        )))
+    }
+
+    /// Register the monitor as the [`CLOCK_HEALTH_JOB`] of a scheduler,
+    /// checking the clock right away and then every check interval unless
+    /// configured otherwise
+    ///
+    /// The job holds a weak reference to the event bus and stops once it is
+    /// dropped.
+    #[cfg(feature = "storage")]
+    pub async fn schedule(self, scheduler: &Scheduler, event_bus: &Arc<EventBus>) {
+        let schedule = Schedule::Every(self.policy.check_interval);
+        debug!(
+            "Registering clock health monitor (tolerance: {:?})",
+            self.policy.max_offset
+        );
+        let job = ClockHealthJob {
+            monitor: self,
+            event_bus: Arc::downgrade(event_bus),
+        };
+        scheduler
+            .register(CLOCK_HEALTH_JOB, schedule, Arc::new(job))
+            .await;
+    }
+}
+
+#[cfg(feature = "storage")]
+struct ClockHealthJob {
+    monitor: ClockHealthMonitor,
+    event_bus: Weak<EventBus>,
+}
+
+#[cfg(feature = "storage")]
+#[async_trait]
+impl ScheduledJob for ClockHealthJob {
+    async fn run(&self) -> Result<JobControl> {
+        let Some(event_bus) = Weak::upgrade(&self.event_bus) else {
+            debug!("Event bus dropped, stopping clock health monitor");
+            return Ok(JobControl::Stop);
+        };
+
+        self.monitor
+            .check(&event_bus)
+            .await
+            .map_err(|e| Error::Processing(format!("Could not check the local clock: {}", e)))?;
+        Ok(JobControl::Continue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[derive(Debug)]
+    struct FixedOffset(chrono::Duration);
+
+    #[async_trait]
+    impl TimeReference for FixedOffset {
+        async fn offset(
+            &self,
+            server: &str,
+            _local: &dyn Clock,
+            _timeout: Duration,
+        ) -> Result<chrono::Duration> {
+            if server == "down:123" {
+                return Err(Error::Processing("unreachable".to_string()));
+            }
+            Ok(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drift_publishes_warning() {
+        let event_bus = EventBus::new();
+        let mut events = event_bus.subscribe_channel();
+        let policy = ClockHealthPolicy {
+            servers: vec!["down:123".to_string(), "up:123".to_string()],
+            ..Default::default()
+        };
+        let monitor = ClockHealthMonitor::new(policy.clone())
+            .with_clock(Arc::new(MockClock::default()))
+            .with_reference(Arc::new(FixedOffset(chrono::Duration::seconds(-8))));
+
+        let check = monitor.check(&event_bus).await.unwrap();
+        assert_eq!(check.server, "up:123");
+        assert_eq!(check.offset_ms, -8000);
+        assert!(check.drifting);
+        assert_eq!(monitor.clone().last_check(), Some(check));
+        match events.recv().await.unwrap().as_ref() {
+            NodeEvent::ClockDriftDetected { offset_ms, .. } => assert_eq!(*offset_ms, -8000),
+            event => panic!("Unexpected event: {:?}", event),
+        }
+
+        let healthy = ClockHealthMonitor::new(policy)
+            .with_reference(Arc::new(FixedOffset(chrono::Duration::milliseconds(300))));
+        assert!(!healthy.check(&event_bus).await.unwrap().drifting);
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_ntp_timestamp_round_trip() {
+        let time = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap()
+            + chrono::Duration::milliseconds(250);
+        let parsed = SntpClient::from_ntp(&SntpClient::to_ntp(time)).unwrap();
+        assert!((parsed - time).num_microseconds().unwrap().abs() < 1);
+    }
+}
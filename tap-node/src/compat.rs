@@ -0,0 +1,355 @@
+//! Compatibility rules for counterparties that deviate from the specification
+//!
+//! Real-world counterparties do not always follow the specification: they
+//! leave out `typ`, use a different `@context` URL or an older version of the
+//! message type URIs. Rather than loosening validation for everyone, a node
+//! keeps [`CompatibilityRules`] for each counterparty DID that needs them,
+//! stored in the node's storage alongside its other counterparty data:
+//!
+//! * inbound rules normalize the messages received from the counterparty
+//!   before they enter the processing pipeline
+//! * outbound rules format the messages sent to the counterparty right before
+//!   they are packed, after they have been validated and stored in their
+//!   standard form
+//!
+//! Rules are set with [`TapNode::set_counterparty_compatibility`] and cached
+//! by the node, so looking them up adds no storage query per message once a
+//! counterparty has been seen.
+//!
+//! ```
+//! use tap_node::compat::{CompatibilityRule, CompatibilityRules};
+//!
+//! let rules = CompatibilityRules {
+//!     inbound: vec![CompatibilityRule::RenameType {
+//!         from: "https://tap.rsvp/schema/1.1".to_string(),
+//!         to: "https://tap.rsvp/schema/1.0".to_string(),
+//!     }],
+//!     outbound: vec![CompatibilityRule::OmitField {
+//!         pointer: "/body/memo".to_string(),
+//!     }],
+//! };
+//! assert!(!rules.is_empty());
+//! ```
+
+use crate::error::{Error, Result};
+use crate::TapNode;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use tap_msg::didcomm::PlainMessage;
+
+/// A normalization or formatting rule applied to the messages of a
+/// counterparty
+///
+/// Fields are addressed with JSON pointers (RFC 6901) into the plain
+/// message, e.g. `/typ` or `/body/@context`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum CompatibilityRule {
+    /// Set the media type (`typ`) of the message
+    MediaType { typ: String },
+    /// Replace the start of the message type URI, e.g. to map a schema
+    /// version onto another
+    RenameType { from: String, to: String },
+    /// Replace a `@context` URL of the message body
+    ReplaceContext { from: String, to: String },
+    /// Set a field that is missing
+    DefaultField { pointer: String, value: Value },
+    /// Remove a field
+    OmitField { pointer: String },
+}
+
+impl CompatibilityRule {
+    /// Apply the rule to a message in its JSON form
+    fn apply(&self, message: &mut Value) -> Result<()> {
+        match self {
+            CompatibilityRule::MediaType { typ } => {
+                message["typ"] = Value::String(typ.clone());
+            }
+            CompatibilityRule::RenameType { from, to } => {
+                if let Some(Value::String(type_)) = message.get_mut("type") {
+                    if let Some(rest) = type_.strip_prefix(from.as_str()) {
+                        *type_ = format!("{}{}", to, rest);
+                    }
+                }
+            }
+            CompatibilityRule::ReplaceContext { from, to } => {
+                match message.pointer_mut("/body/@context") {
+                    Some(Value::String(context)) if context == from => *context = to.clone(),
+                    Some(Value::Array(contexts)) => {
+                        for context in contexts.iter_mut() {
+                            if context.as_str() == Some(from) {
+                                *context = Value::String(to.clone());
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            CompatibilityRule::DefaultField { pointer, value } => {
+                if message.pointer(pointer).is_none() {
+                    let (parent, key) = split_pointer(pointer)?;
+                    if let Some(Value::Object(object)) = message.pointer_mut(parent) {
+                        object.insert(key, value.clone());
+                    }
+                }
+            }
+            CompatibilityRule::OmitField { pointer } => {
+                let (parent, key) = split_pointer(pointer)?;
+                if let Some(Value::Object(object)) = message.pointer_mut(parent) {
+                    object.remove(&key);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Split a JSON pointer into the pointer to the parent and the unescaped key
+fn split_pointer(pointer: &str) -> Result<(&str, String)> {
+    match pointer.rfind('/') {
+        Some(index) => Ok((
+            &pointer[..index],
+            pointer[index + 1..].replace("~1", "/").replace("~0", "~"),
+        )),
+        None => Err(Error::Configuration(format!(
+            "Invalid JSON pointer: {}",
+            pointer
+        ))),
+    }
+}
+
+/// Compatibility rules of a counterparty
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CompatibilityRules {
+    /// Rules normalizing the messages received from the counterparty
+    #[serde(default)]
+    pub inbound: Vec<CompatibilityRule>,
+    /// Rules formatting the messages sent to the counterparty
+    #[serde(default)]
+    pub outbound: Vec<CompatibilityRule>,
+}
+
+impl CompatibilityRules {
+    /// Whether there are no rules
+    pub fn is_empty(&self) -> bool {
+        self.inbound.is_empty() && self.outbound.is_empty()
+    }
+
+    /// Normalize a message received from the counterparty
+    pub fn normalize_inbound(&self, message: PlainMessage) -> Result<PlainMessage> {
+        Self::apply(&self.inbound, message)
+    }
+
+    /// Format a message sent to the counterparty
+    pub fn format_outbound(&self, message: PlainMessage) -> Result<PlainMessage> {
+        Self::apply(&self.outbound, message)
+    }
+
+    fn apply(rules: &[CompatibilityRule], message: PlainMessage) -> Result<PlainMessage> {
+        if rules.is_empty() {
+            return Ok(message);
+        }
+        let mut value =
+            serde_json::to_value(&message).map_err(|e| Error::Serialization(e.to_string()))?;
+        for rule in rules {
+            rule.apply(&mut value)?;
+        }
+        serde_json::from_value(value).map_err(|e| {
+            Error::Serialization(format!(
+                "Compatibility rules produced an invalid message: {}",
+                e
+            ))
+        })
+    }
+}
+
+/// Compatibility rules of counterparties, cached by DID
+///
+/// A DID without rules is cached as `None` so its messages do not query
+/// storage again.
+#[derive(Default)]
+pub struct CompatibilityCache {
+    rules: DashMap<String, Option<Arc<CompatibilityRules>>>,
+}
+
+impl TapNode {
+    /// Set the compatibility rules of a counterparty, replacing any it had
+    pub async fn set_counterparty_compatibility(
+        &self,
+        counterparty_did: &str,
+        rules: CompatibilityRules,
+    ) -> Result<()> {
+        let storage = self.compatibility_storage()?;
+        storage
+            .upsert_counterparty_compatibility(counterparty_did, &rules)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        log::info!(
+            "Set {} inbound and {} outbound compatibility rules for {}",
+            rules.inbound.len(),
+            rules.outbound.len(),
+            counterparty_did
+        );
+        self.compatibility
+            .rules
+            .insert(counterparty_did.to_string(), Some(Arc::new(rules)));
+        Ok(())
+    }
+
+    /// Remove the compatibility rules of a counterparty
+    ///
+    /// Returns false if the counterparty had none.
+    pub async fn remove_counterparty_compatibility(&self, counterparty_did: &str) -> Result<bool> {
+        let storage = self.compatibility_storage()?;
+        let removed = storage
+            .delete_counterparty_compatibility(counterparty_did)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        self.compatibility
+            .rules
+            .insert(counterparty_did.to_string(), None);
+        Ok(removed)
+    }
+
+    /// The compatibility rules of a counterparty, if it has any
+    pub async fn counterparty_compatibility(
+        &self,
+        counterparty_did: &str,
+    ) -> Result<Option<Arc<CompatibilityRules>>> {
+        if let Some(rules) = self.compatibility.rules.get(counterparty_did) {
+            return Ok(rules.clone());
+        }
+        let Some(storage) = &self.storage else {
+            return Ok(None);
+        };
+        let rules = storage
+            .get_counterparty_compatibility(counterparty_did)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?
+            .map(Arc::new);
+        self.compatibility
+            .rules
+            .insert(counterparty_did.to_string(), rules.clone());
+        Ok(rules)
+    }
+
+    /// Normalize a received message with the inbound rules of its sender
+    pub(crate) async fn normalize_inbound(&self, message: PlainMessage) -> Result<PlainMessage> {
+        match self.counterparty_compatibility(&message.from).await? {
+            Some(rules) => {
+                let message = rules.normalize_inbound(message)?;
+                log::debug!(
+                    "Normalized message {} from {} with {} compatibility rules",
+                    message.id,
+                    message.from,
+                    rules.inbound.len()
+                );
+                Ok(message)
+            }
+            None => Ok(message),
+        }
+    }
+
+    /// Format a message for the wire with the outbound rules of its
+    /// recipients, in the order they are listed
+    pub(crate) async fn format_outbound(&self, mut message: PlainMessage) -> Result<PlainMessage> {
+        for recipient in message.to.clone() {
+            if let Some(rules) = self.counterparty_compatibility(&recipient).await? {
+                message = rules.format_outbound(message)?;
+            }
+        }
+        Ok(message)
+    }
+
+    fn compatibility_storage(&self) -> Result<&Arc<crate::storage::Storage>> {
+        self.storage
+            .as_ref()
+            .ok_or_else(|| Error::Storage("Storage is not initialized".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message(value: Value) -> PlainMessage {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_inbound_normalization() {
+        let rules: CompatibilityRules = serde_json::from_value(json!({
+            "inbound": [
+                {"rule": "rename_type", "from": "https://tap.rsvp/schema/1.1", "to": "https://tap.rsvp/schema/1.0"},
+                {"rule": "replace_context", "from": "https://tap.rsvp/schema/1.1", "to": "https://tap.rsvp/schema/1.0"},
+                {"rule": "default_field", "pointer": "/body/memo", "value": "none"}
+            ]
+        }))
+        .unwrap();
+
+        let normalized = rules
+            .normalize_inbound(message(json!({
+                "id": "msg-1",
+                "type": "https://tap.rsvp/schema/1.1#Transfer",
+                "from": "did:example:them",
+                "to": ["did:example:us"],
+                "body": {"@context": "https://tap.rsvp/schema/1.1", "memo": "kept"}
+            })))
+            .unwrap();
+
+        assert_eq!(normalized.type_, "https://tap.rsvp/schema/1.0#Transfer");
+        assert_eq!(normalized.body["@context"], "https://tap.rsvp/schema/1.0");
+        assert_eq!(normalized.body["memo"], "kept");
+        // Outbound rules do not apply to received messages
+        assert_eq!(normalized.typ, "application/didcomm-plain+json");
+    }
+
+    #[test]
+    fn test_outbound_formatting() {
+        let rules = CompatibilityRules {
+            inbound: Vec::new(),
+            outbound: vec![
+                CompatibilityRule::MediaType {
+                    typ: "application/json".to_string(),
+                },
+                CompatibilityRule::OmitField {
+                    pointer: "/body/memo".to_string(),
+                },
+            ],
+        };
+
+        let formatted = rules
+            .format_outbound(message(json!({
+                "id": "msg-1",
+                "type": "https://tap.rsvp/schema/1.0#Transfer",
+                "from": "did:example:us",
+                "to": ["did:example:them"],
+                "body": {"memo": "dropped", "amount": "10"}
+            })))
+            .unwrap();
+
+        assert_eq!(formatted.typ, "application/json");
+        assert!(formatted.body.get("memo").is_none());
+        assert_eq!(formatted.body["amount"], "10");
+    }
+
+    #[test]
+    fn test_invalid_result_is_an_error() {
+        let rules = CompatibilityRules {
+            inbound: vec![CompatibilityRule::OmitField {
+                pointer: "/from".to_string(),
+            }],
+            outbound: Vec::new(),
+        };
+        let result = rules.normalize_inbound(message(json!({
+            "id": "msg-1",
+            "type": "https://tap.rsvp/schema/1.0#Transfer",
+            "from": "did:example:them",
+            "body": {}
+        })));
+        assert!(result.is_err());
+    }
+}
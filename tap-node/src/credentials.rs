@@ -0,0 +1,309 @@
+//! Verifiable credentials presented by agents
+//!
+//! Agents in a Transfer or Payment can carry verifiable credentials in their
+//! `credentials` metadata, e.g. proof that the agent acts for a licensed
+//! VASP (see [`Agent::with_credential`]). A [`CredentialVerifier`] checks each
+//! credential's issuer signature and validity period with
+//! [`tap_agent::verify_credential`], and additionally requires that the
+//! issuer is trusted and that the credential is about the agent presenting
+//! it.
+//!
+//! With [`NodeConfig::credential_verification`](crate::NodeConfig::credential_verification)
+//! set, the node verifies the credentials of incoming messages and logs or
+//! rejects invalid ones, and
+//! [`CredentialRule`](crate::policy::CredentialRule)s can require credentials
+//! with particular claims before a transaction is authorized.
+//!
+//! Agents can also send a key attestation from their organization with their
+//! messages. With [`NodeConfig::key_attestation_verification`](crate::NodeConfig::key_attestation_verification)
+//! set, the node verifies and records them, and
+//! [`KeyAttestationRule`](crate::policy::KeyAttestationRule)s can require
+//! an attestation from particular organizations.
+//!
+//! Presentation messages carry verifiable presentations, JWTs or JSON-LD
+//! documents secured with Data Integrity proofs, holding the credentials a
+//! counterparty was asked for. A [`PresentationVerifier`] checks the holder
+//! and issuer signatures with [`tap_agent::verify_presentation`], that the
+//! holder signed the challenge of the node's RequestPresentation, the
+//! trusted issuers, and the revocation status of each credential through a
+//! [`CredentialStatusChecker`]. With
+//! [`NodeConfig::presentation_verification`](crate::NodeConfig::presentation_verification)
+//! set, the node verifies and records them, and
+//! [`PresentationRule`](crate::policy::PresentationRule)s can require a
+//! verified presentation before a transaction is authorized.
+
+use crate::clock::{Clock, SystemClock};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tap_agent::{
+    verify_credential, verify_presentation, PresentationChallenge, SyncDIDResolver,
+    VerifiedCredential,
+};
+use tap_msg::message::Agent;
+
+/// How the node verifies credentials presented by agents
+#[derive(Debug, Clone, Default)]
+pub struct CredentialVerification {
+    /// DIDs of the issuers whose credentials are accepted
+    pub trusted_issuers: Vec<String>,
+    /// Reject incoming messages whose agents present credentials that fail
+    /// verification (otherwise they are logged and ignored)
+    pub reject_invalid: bool,
+}
+
+/// How the node verifies the key attestations sent with incoming messages
+/// (see [`tap_agent::attestation`])
+#[derive(Debug, Clone, Default)]
+pub struct KeyAttestationVerification {
+    /// Reject incoming messages whose key attestation fails verification
+    /// (otherwise it is recorded as invalid and the message accepted)
+    pub reject_invalid: bool,
+}
+
+/// How the node verifies the verifiable presentations of incoming
+/// Presentation messages
+#[derive(Debug, Clone, Default)]
+pub struct PresentationVerification {
+    /// DIDs of the issuers whose credentials are accepted (any issuer when
+    /// empty, e.g. for travel rule data issued by the counterparty VASP)
+    pub trusted_issuers: Vec<String>,
+    /// Checks the revocation status of credentials with a `credentialStatus`
+    /// (None records them as unchecked)
+    pub status_checker: Option<Arc<dyn CredentialStatusChecker>>,
+    /// Reject Presentation messages whose presentations fail verification
+    /// (otherwise they are recorded as invalid and the message accepted)
+    pub reject_invalid: bool,
+}
+
+/// Checks whether credentials have been revoked or suspended
+///
+/// Status lists are published by issuers, usually over HTTP, so how they
+/// are fetched and cached is left to the application.
+#[async_trait]
+pub trait CredentialStatusChecker: Send + Sync + std::fmt::Debug {
+    /// Whether the credential, which has a `credentialStatus`, is revoked
+    /// or suspended
+    async fn is_revoked(&self, credential: &VerifiedCredential) -> Result<bool, String>;
+}
+
+/// Revocation status of a presented credential
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevocationStatus {
+    /// The credential has no `credentialStatus`
+    NoStatus,
+    /// The status checker found the credential not revoked
+    NotRevoked,
+    /// The credential has a `credentialStatus` but no status checker is
+    /// configured
+    Unchecked,
+}
+
+/// Credential held by a verified presentation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PresentedCredential {
+    pub issuer: String,
+    pub subject: Option<String>,
+    pub types: Vec<String>,
+    /// Unix time in seconds after which the credential is no longer valid
+    pub expires_at: Option<i64>,
+    pub revocation: RevocationStatus,
+}
+
+/// Holder and credentials of a presentation that verified
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerifiedPresentationSummary {
+    pub holder: Option<String>,
+    pub credentials: Vec<PresentedCredential>,
+}
+
+/// A credential presented by an agent that failed verification
+#[derive(Debug, Clone, PartialEq)]
+pub struct CredentialFailure {
+    /// Position of the credential in the agent's `credentials`
+    pub index: usize,
+    /// Why the credential was not accepted
+    pub reason: String,
+}
+
+/// The outcome of verifying an agent's credentials
+#[derive(Debug, Clone, Default)]
+pub struct AgentCredentials {
+    /// Credentials that passed verification
+    pub verified: Vec<VerifiedCredential>,
+    /// Credentials that failed verification
+    pub failures: Vec<CredentialFailure>,
+}
+
+/// Verifies the credentials presented by agents against a list of trusted
+/// issuers
+pub struct CredentialVerifier {
+    trusted_issuers: HashSet<String>,
+    resolver: Arc<dyn SyncDIDResolver>,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for CredentialVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CredentialVerifier")
+            .field("trusted_issuers", &self.trusted_issuers)
+            .finish()
+    }
+}
+
+impl CredentialVerifier {
+    /// Create a verifier resolving issuer DIDs with `resolver`
+    pub fn new(trusted_issuers: Vec<String>, resolver: Arc<dyn SyncDIDResolver>) -> Self {
+        Self {
+            trusted_issuers: trusted_issuers.into_iter().collect(),
+            resolver,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Use a custom clock for checking expiry
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Whether credentials issued by `issuer` are accepted
+    pub fn is_trusted(&self, issuer: &str) -> bool {
+        self.trusted_issuers.contains(issuer)
+    }
+
+    /// Verify a credential presented by the agent with DID `agent_did`
+    pub async fn verify(
+        &self,
+        agent_did: &str,
+        credential: &serde_json::Value,
+    ) -> Result<VerifiedCredential, String> {
+        let now = self.clock.now().timestamp();
+        let verified = verify_credential(credential, self.resolver.as_ref(), now)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if !self.is_trusted(&verified.issuer) {
+            return Err(format!(
+                "Credential issuer {} is not trusted",
+                verified.issuer
+            ));
+        }
+        if let Some(subject) = &verified.subject {
+            if subject != agent_did {
+                return Err(format!(
+                    "Credential is about {}, not {}",
+                    subject, agent_did
+                ));
+            }
+        }
+        Ok(verified)
+    }
+
+    /// Verify every credential presented by `agent`
+    pub async fn verify_agent(&self, agent: &Agent) -> AgentCredentials {
+        let mut result = AgentCredentials::default();
+        for (index, credential) in agent.credentials().iter().enumerate() {
+            match self.verify(&agent.id, credential).await {
+                Ok(verified) => result.verified.push(verified),
+                Err(reason) => result.failures.push(CredentialFailure { index, reason }),
+            }
+        }
+        result
+    }
+}
+
+/// Verifies verifiable presentations and the credentials they hold
+pub struct PresentationVerifier {
+    verification: PresentationVerification,
+    resolver: Arc<dyn SyncDIDResolver>,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for PresentationVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PresentationVerifier")
+            .field("verification", &self.verification)
+            .finish()
+    }
+}
+
+impl PresentationVerifier {
+    /// Create a verifier resolving holder and issuer DIDs with `resolver`
+    pub fn new(verification: PresentationVerification, resolver: Arc<dyn SyncDIDResolver>) -> Self {
+        Self {
+            verification,
+            resolver,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Use a custom clock for checking expiry
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Verify a presentation answering a request
+    ///
+    /// Besides the signatures, validity periods and the binding of the holder
+    /// proof to the challenge and domain of the request checked by
+    /// [`tap_agent::verify_presentation`], the credentials must be issued by
+    /// trusted issuers and not be revoked.
+    pub async fn verify(
+        &self,
+        presentation: &serde_json::Value,
+        expected: &PresentationChallenge,
+    ) -> Result<VerifiedPresentationSummary, String> {
+        let now = self.clock.now().timestamp();
+        let presentation = verify_presentation(presentation, expected, self.resolver.as_ref(), now)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let trusted = &self.verification.trusted_issuers;
+        let mut credentials = Vec::with_capacity(presentation.credentials.len());
+        for credential in &presentation.credentials {
+            if !trusted.is_empty() && !trusted.contains(&credential.issuer) {
+                return Err(format!(
+                    "Credential issuer {} is not trusted",
+                    credential.issuer
+                ));
+            }
+
+            let revocation = match (&credential.status, &self.verification.status_checker) {
+                (None, _) => RevocationStatus::NoStatus,
+                (Some(_), None) => RevocationStatus::Unchecked,
+                (Some(_), Some(checker)) => match checker.is_revoked(credential).await {
+                    Ok(false) => RevocationStatus::NotRevoked,
+                    Ok(true) => {
+                        return Err(format!(
+                            "Credential issued by {} has been revoked",
+                            credential.issuer
+                        ))
+                    }
+                    Err(e) => {
+                        return Err(format!(
+                            "Could not check the status of the credential issued by {}: {}",
+                            credential.issuer, e
+                        ))
+                    }
+                },
+            };
+
+            credentials.push(PresentedCredential {
+                issuer: credential.issuer.clone(),
+                subject: credential.subject.clone(),
+                types: credential.types.clone(),
+                expires_at: credential.expires_at,
+                revocation,
+            });
+        }
+
+        Ok(VerifiedPresentationSummary {
+            holder: presentation.holder,
+            credentials,
+        })
+    }
+}
@@ -6,14 +6,22 @@
 //! - Multiple identifier support (DIDs, email, phone, URLs)
 //! - Relationship tracking for TAIP-9 compliance
 //! - IVMS101 data caching for Travel Rule compliance
+//! - Erasure of personal data with tombstones for auditability
+//! - Per-transaction encryption of party data with an access log
+//! - Duplicate-party detection and merging with lineage tracking
+
+pub mod dedup;
 
 use crate::error::{Error, Result};
 use crate::storage::{
-    Customer, CustomerIdentifier, CustomerRelationship, IdentifierType, SchemaType, Storage,
+    Customer, CustomerErasure, CustomerIdentifier, CustomerMerge, CustomerRelationship,
+    IdentifierType, MergeStatus, PiiAccess, SchemaType, Storage, TransactionPii,
 };
-use chrono::Utc;
+pub use dedup::{DedupConfig, DuplicateScore};
 use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use tap_agent::{agent::Agent, TapAgent};
 use tap_ivms101::{
     builder::{GeographicAddressBuilder, NaturalPersonBuilder, NaturalPersonNameBuilder},
     message::Person,
@@ -23,9 +31,13 @@ use tap_msg::message::Party;
 use tap_msg::utils::NameHashable;
 use uuid::Uuid;
 
+/// Number of customers loaded per page when detecting duplicates
+const DEDUP_PAGE_SIZE: u32 = 500;
+
 /// Customer manager handles all customer-related operations
 pub struct CustomerManager {
     storage: Arc<Storage>,
+    dedup: DedupConfig,
 }
 
 impl CustomerManager {
@@ -38,7 +50,21 @@ impl CustomerManager {
 
     /// Create a new customer manager
     pub fn new(storage: Arc<Storage>) -> Self {
-        Self { storage }
+        Self {
+            storage,
+            dedup: DedupConfig::default(),
+        }
+    }
+
+    /// Set the configuration of duplicate-party detection
+    pub fn with_dedup_config(mut self, config: DedupConfig) -> Self {
+        self.dedup = config;
+        self
+    }
+
+    /// Score how likely two customer records describe the same party
+    pub fn score_duplicate(&self, a: &Customer, b: &Customer) -> DuplicateScore {
+        self.dedup.score(a, b)
     }
 
     /// Get a reference to the storage
@@ -53,8 +79,10 @@ impl CustomerManager {
         agent_did: &str,
         _role: &str, // "originator", "beneficiary", etc.
     ) -> Result<String> {
-        // Determine customer ID and primary identifier
+        // Determine customer ID and primary identifier, following merges so
+        // a merged duplicate is not recreated
         let (customer_id, primary_identifier) = self.determine_customer_id(&party.id);
+        let customer_id = self.resolve_merged_customer(&customer_id).await?;
 
         // Check if customer exists
         let existing = self
@@ -100,7 +128,7 @@ impl CustomerManager {
         let (given_name, family_name, display_name, address_country) =
             self.extract_structured_data(&profile);
 
-        let now = Utc::now().to_rfc3339();
+        let now = self.storage.clock().now().to_rfc3339();
 
         let mut customer = Customer {
             id: customer_id.clone(),
@@ -137,6 +165,12 @@ impl CustomerManager {
             customer.add_name_hash_to_profile();
         }
 
+        // With an encryption key, the party's personal data is only kept
+        // sealed under the data keys of its transactions
+        if self.storage.message_encryption().is_some() {
+            redact_personal_data(&mut customer, existing.as_ref());
+        }
+
         // Upsert customer
         self.storage
             .upsert_customer(&customer)
@@ -151,7 +185,7 @@ impl CustomerManager {
             verified: false,
             verification_method: None,
             verified_at: None,
-            created_at: Utc::now().to_rfc3339(),
+            created_at: self.storage.clock().now().to_rfc3339(),
         };
         self.storage
             .add_customer_identifier(&identifier)
@@ -195,7 +229,7 @@ impl CustomerManager {
         customer.family_name = family_name.or(customer.family_name);
         customer.display_name = display_name.or(customer.display_name);
         customer.address_country = address_country.or(customer.address_country);
-        customer.updated_at = Utc::now().to_rfc3339();
+        customer.updated_at = self.storage.clock().now().to_rfc3339();
 
         // Regenerate name hash if names have changed
         customer.add_name_hash_to_profile();
@@ -274,7 +308,7 @@ impl CustomerManager {
         // Cache the generated IVMS101 data
         let mut customer = customer;
         customer.ivms101_data = Some(ivms101_json.clone());
-        customer.updated_at = Utc::now().to_rfc3339();
+        customer.updated_at = self.storage.clock().now().to_rfc3339();
         self.storage
             .upsert_customer(&customer)
             .await
@@ -344,7 +378,7 @@ impl CustomerManager {
 
         // Store the IVMS101 data
         customer.ivms101_data = Some(ivms101_data.clone());
-        customer.updated_at = Utc::now().to_rfc3339();
+        customer.updated_at = self.storage.clock().now().to_rfc3339();
 
         // Regenerate name hash if names have changed
         customer.add_name_hash_to_profile();
@@ -370,8 +404,8 @@ impl CustomerManager {
             relationship_type: relationship_type.to_string(),
             related_identifier: related_identifier.to_string(),
             proof,
-            confirmed_at: Some(Utc::now().to_rfc3339()),
-            created_at: Utc::now().to_rfc3339(),
+            confirmed_at: Some(self.storage.clock().now().to_rfc3339()),
+            created_at: self.storage.clock().now().to_rfc3339(),
         };
 
         self.storage
@@ -381,8 +415,416 @@ impl CustomerManager {
         Ok(())
     }
 
+    /// Erase a customer's personal data
+    ///
+    /// `customer` may be a customer ID or any identifier linked to the
+    /// customer (e.g. a DID). See [`Storage::erase_customer`] for what is
+    /// redacted and what is preserved.
+    pub async fn erase_customer(
+        &self,
+        customer: &str,
+        reason: Option<&str>,
+    ) -> Result<Option<CustomerErasure>> {
+        let customer_id = match self
+            .storage
+            .get_customer(customer)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?
+        {
+            Some(found) => found.id,
+            None => match self
+                .storage
+                .get_customer_by_identifier(customer)
+                .await
+                .map_err(|e| Error::Storage(e.to_string()))?
+            {
+                Some(found) => found.id,
+                None => return Ok(None),
+            },
+        };
+
+        self.storage
+            .erase_customer(&customer_id, reason)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    /// Read the personal data of the parties of a transaction
+    ///
+    /// `accessor` must be a participant of the transaction or have been
+    /// granted access with [`CustomerManager::grant_transaction_pii_access`].
+    /// The read is recorded in the access log of the transaction with the
+    /// accessor's DID and `purpose`, which must be given. See
+    /// [`Storage::read_transaction_pii`].
+    pub async fn transaction_pii(
+        &self,
+        transaction_id: &str,
+        accessor: &TapAgent,
+        purpose: &str,
+    ) -> Result<Vec<TransactionPii>> {
+        if purpose.trim().is_empty() {
+            return Err(Error::Validation(
+                "A purpose is required to read personal data".to_string(),
+            ));
+        }
+        self.storage
+            .read_transaction_pii(transaction_id, accessor.get_agent_did(), purpose)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    /// Allow another DID to read the personal data of a transaction
+    ///
+    /// `granted_by` must be a participant of the transaction.
+    pub async fn grant_transaction_pii_access(
+        &self,
+        transaction_id: &str,
+        granted_by: &TapAgent,
+        accessor: &str,
+    ) -> Result<()> {
+        self.storage
+            .grant_transaction_pii_access(transaction_id, accessor, granted_by.get_agent_did())
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    /// Withdraw access granted with
+    /// [`CustomerManager::grant_transaction_pii_access`]
+    ///
+    /// Returns false if `accessor` had no access to withdraw.
+    pub async fn revoke_transaction_pii_access(
+        &self,
+        transaction_id: &str,
+        revoked_by: &TapAgent,
+        accessor: &str,
+    ) -> Result<bool> {
+        self.storage
+            .revoke_transaction_pii_access(transaction_id, accessor, revoked_by.get_agent_did())
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    /// Revoke access to the personal data of a transaction
+    ///
+    /// `revoked_by` must be a participant of the transaction. Returns false
+    /// if the transaction holds no personal data or access was already
+    /// revoked.
+    pub async fn revoke_transaction_pii(
+        &self,
+        transaction_id: &str,
+        revoked_by: &TapAgent,
+        reason: Option<&str>,
+    ) -> Result<bool> {
+        self.storage
+            .revoke_transaction_data_key(transaction_id, revoked_by.get_agent_did(), reason)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    /// Link the sealed personal data of a party of a transaction to its
+    /// customer
+    ///
+    /// The customer's profile keeps a `piiRef` to each transaction and role
+    /// its personal data is sealed under. Does nothing if no data of the
+    /// role is sealed.
+    pub async fn link_transaction_pii(
+        &self,
+        customer_id: &str,
+        transaction_id: &str,
+        role: &str,
+    ) -> Result<()> {
+        let linked = self
+            .storage
+            .link_transaction_pii(transaction_id, role, customer_id)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        if !linked {
+            return Ok(());
+        }
+
+        let mut customer = self
+            .storage
+            .get_customer(customer_id)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?
+            .ok_or_else(|| Error::Storage("Customer not found".to_string()))?;
+        let reference = json!({"transactionId": transaction_id, "role": role});
+        if let Value::Object(ref mut map) = customer.profile {
+            if let Value::Array(references) = map.entry("piiRef").or_insert_with(|| json!([])) {
+                if !references.contains(&reference) {
+                    references.push(reference);
+                }
+            }
+        }
+        customer.updated_at = self.storage.clock().now().to_rfc3339();
+
+        self.storage
+            .upsert_customer(&customer)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    /// Who read the personal data of a transaction, when and why
+    pub async fn transaction_pii_access(&self, transaction_id: &str) -> Result<Vec<PiiAccess>> {
+        self.storage
+            .list_pii_access(transaction_id)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    /// Detect duplicate customers of an agent and propose merging them
+    ///
+    /// Customers are compared with the configured [`DedupConfig`], and each
+    /// pair scoring at least [`DedupConfig::min_confidence`] is recorded as
+    /// a merge proposal. The older customer of a pair is the one that
+    /// survives. Pairs already proposed, confirmed or rejected are not
+    /// proposed again.
+    ///
+    /// Returns the newly recorded proposals, most confident first.
+    pub async fn find_duplicates(&self, agent_did: &str) -> Result<Vec<CustomerMerge>> {
+        let mut customers = Vec::new();
+        loop {
+            let page = self
+                .storage
+                .list_customers(agent_did, DEDUP_PAGE_SIZE, customers.len() as u32)
+                .await
+                .map_err(|e| Error::Storage(e.to_string()))?;
+            let done = page.len() < DEDUP_PAGE_SIZE as usize;
+            customers.extend(page);
+            if done {
+                break;
+            }
+        }
+        customers.retain(|customer| !dedup::is_erased(customer));
+
+        let mut blocks: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, customer) in customers.iter().enumerate() {
+            for key in dedup::blocking_keys(customer) {
+                blocks.entry(key).or_default().push(index);
+            }
+        }
+        let mut pairs = HashSet::new();
+        for indices in blocks.values() {
+            for (position, &left) in indices.iter().enumerate() {
+                for &right in &indices[position + 1..] {
+                    pairs.insert((left.min(right), left.max(right)));
+                }
+            }
+        }
+
+        let mut proposals = Vec::new();
+        for (left, right) in pairs {
+            let score = self.dedup.score(&customers[left], &customers[right]);
+            if score.signals.is_empty() || score.confidence < self.dedup.min_confidence {
+                continue;
+            }
+
+            let (primary, duplicate) = if (&customers[right].created_at, &customers[right].id)
+                < (&customers[left].created_at, &customers[left].id)
+            {
+                (&customers[right], &customers[left])
+            } else {
+                (&customers[left], &customers[right])
+            };
+            let merge = CustomerMerge {
+                id: Uuid::new_v4().to_string(),
+                agent_did: agent_did.to_string(),
+                primary_customer_id: primary.id.clone(),
+                duplicate_customer_id: duplicate.id.clone(),
+                confidence: score.confidence,
+                signals: score.signals,
+                status: MergeStatus::Proposed,
+                duplicate_snapshot: None,
+                created_at: self.storage.clock().now().to_rfc3339(),
+                resolved_at: None,
+            };
+            if self
+                .storage
+                .insert_customer_merge(&merge)
+                .await
+                .map_err(|e| Error::Storage(e.to_string()))?
+            {
+                proposals.push(merge);
+            }
+        }
+
+        proposals.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        Ok(proposals)
+    }
+
+    /// List merge proposals of an agent, optionally only those with a status
+    pub async fn list_merge_proposals(
+        &self,
+        agent_did: &str,
+        status: Option<MergeStatus>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<CustomerMerge>> {
+        self.storage
+            .list_customer_merges(agent_did, status, limit, offset)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    /// Confirm a merge proposal
+    ///
+    /// The duplicate is folded into the surviving customer: fields the
+    /// survivor lacks are taken from the duplicate, the duplicate's
+    /// identifiers and relationships move over, and the duplicate record is
+    /// removed. The record as it was is kept on the merge for lineage.
+    ///
+    /// Returns the merged customer.
+    pub async fn confirm_merge(&self, merge_id: &str) -> Result<Customer> {
+        let merge = self.get_open_merge(merge_id).await?;
+        let primary = self.get_merge_customer(&merge.primary_customer_id).await?;
+        let duplicate = self
+            .get_merge_customer(&merge.duplicate_customer_id)
+            .await?;
+
+        let mut merged = primary.clone();
+        merged.given_name = merged.given_name.or_else(|| duplicate.given_name.clone());
+        merged.family_name = merged.family_name.or_else(|| duplicate.family_name.clone());
+        merged.display_name = merged
+            .display_name
+            .or_else(|| duplicate.display_name.clone());
+        merged.legal_name = merged.legal_name.or_else(|| duplicate.legal_name.clone());
+        merged.lei_code = merged.lei_code.or_else(|| duplicate.lei_code.clone());
+        merged.mcc_code = merged.mcc_code.or_else(|| duplicate.mcc_code.clone());
+        merged.address_country = merged
+            .address_country
+            .or_else(|| duplicate.address_country.clone());
+        merged.address_locality = merged
+            .address_locality
+            .or_else(|| duplicate.address_locality.clone());
+        merged.postal_code = merged.postal_code.or_else(|| duplicate.postal_code.clone());
+        merged.street_address = merged
+            .street_address
+            .or_else(|| duplicate.street_address.clone());
+        merged.ivms101_data = merged
+            .ivms101_data
+            .or_else(|| duplicate.ivms101_data.clone());
+        merged.verified_at = merged.verified_at.or_else(|| duplicate.verified_at.clone());
+        if let (Value::Object(merged_profile), Value::Object(duplicate_profile)) =
+            (&mut merged.profile, &duplicate.profile)
+        {
+            for (key, value) in duplicate_profile {
+                merged_profile
+                    .entry(key.clone())
+                    .or_insert_with(|| value.clone());
+            }
+        }
+        merged.updated_at = self.storage.clock().now().to_rfc3339();
+        merged.add_name_hash_to_profile();
+
+        let snapshot =
+            serde_json::to_value(&duplicate).map_err(|e| Error::Serialization(e.to_string()))?;
+        if !self
+            .storage
+            .confirm_customer_merge(merge_id, &merged, &snapshot)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?
+        {
+            return Err(Error::Validation(format!(
+                "Customer merge {} is no longer proposed",
+                merge_id
+            )));
+        }
+
+        Ok(merged)
+    }
+
+    /// Reject a merge proposal, so the pair is not proposed again
+    pub async fn reject_merge(&self, merge_id: &str) -> Result<()> {
+        self.get_open_merge(merge_id).await?;
+        if !self
+            .storage
+            .reject_customer_merge(merge_id)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?
+        {
+            return Err(Error::Validation(format!(
+                "Customer merge {} is no longer proposed",
+                merge_id
+            )));
+        }
+        Ok(())
+    }
+
+    /// Get the lineage of a customer: every confirmed merge that folded a
+    /// record into it, directly or through an earlier merge, oldest first
+    pub async fn customer_lineage(&self, customer_id: &str) -> Result<Vec<CustomerMerge>> {
+        let mut lineage = Vec::new();
+        let mut pending = vec![customer_id.to_string()];
+        let mut seen = HashSet::new();
+        while let Some(id) = pending.pop() {
+            if !seen.insert(id.clone()) {
+                continue;
+            }
+            let merges = self
+                .storage
+                .list_customer_merges_into(&id)
+                .await
+                .map_err(|e| Error::Storage(e.to_string()))?;
+            pending.extend(merges.iter().map(|m| m.duplicate_customer_id.clone()));
+            lineage.extend(merges);
+        }
+
+        lineage.sort_by(|a, b| a.resolved_at.cmp(&b.resolved_at));
+        Ok(lineage)
+    }
+
+    /// Resolve a customer ID to the customer it was merged into, if any
+    pub async fn resolve_merged_customer(&self, customer_id: &str) -> Result<String> {
+        let mut current = customer_id.to_string();
+        let mut seen = HashSet::new();
+        while seen.insert(current.clone()) {
+            match self
+                .storage
+                .get_customer_merged_into(&current)
+                .await
+                .map_err(|e| Error::Storage(e.to_string()))?
+            {
+                Some(primary) => current = primary,
+                None => break,
+            }
+        }
+        Ok(current)
+    }
+
     // Helper methods
 
+    async fn get_open_merge(&self, merge_id: &str) -> Result<CustomerMerge> {
+        let merge = self
+            .storage
+            .get_customer_merge(merge_id)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?
+            .ok_or_else(|| Error::Storage("Customer merge not found".to_string()))?;
+        if merge.status != MergeStatus::Proposed {
+            return Err(Error::Validation(format!(
+                "Customer merge {} is already {}",
+                merge_id, merge.status
+            )));
+        }
+        Ok(merge)
+    }
+
+    async fn get_merge_customer(&self, customer_id: &str) -> Result<Customer> {
+        let customer = self
+            .storage
+            .get_customer(customer_id)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?
+            .ok_or_else(|| Error::Storage(format!("Customer {} not found", customer_id)))?;
+        if dedup::is_erased(&customer) {
+            return Err(Error::Validation(format!(
+                "Customer {} has been erased and cannot be merged",
+                customer_id
+            )));
+        }
+        Ok(customer)
+    }
+
     fn determine_customer_id(&self, account: &str) -> (String, String) {
         // If it's a DID, use it as the customer ID
         if account.starts_with("did:") {
@@ -441,7 +883,7 @@ impl CustomerManager {
                         verified: false,
                         verification_method: None,
                         verified_at: None,
-                        created_at: Utc::now().to_rfc3339(),
+                        created_at: self.storage.clock().now().to_rfc3339(),
                     };
                     let _ = self.storage.add_customer_identifier(&identifier).await;
                 }
@@ -490,10 +932,38 @@ impl CustomerManager {
     }
 }
 
+/// Strip the personal data of a customer, keeping its identifier, its name
+/// hash and the references to its sealed data
+fn redact_personal_data(customer: &mut Customer, existing: Option<&Customer>) {
+    let name_hash = customer
+        .get_name_hash()
+        .or_else(|| existing.and_then(Customer::get_name_hash));
+    let references = existing.and_then(|c| c.profile.get("piiRef").cloned());
+
+    let mut profile = json!({
+        "@context": customer.profile["@context"].clone(),
+        "@type": customer.profile["@type"].clone(),
+        "identifier": customer.profile["identifier"].clone(),
+    });
+    if let Some(name_hash) = name_hash {
+        profile["nameHash"] = json!(name_hash);
+    }
+    if let Some(references) = references {
+        profile["piiRef"] = references;
+    }
+
+    customer.profile = profile;
+    customer.given_name = None;
+    customer.family_name = None;
+    customer.display_name = None;
+    customer.address_country = None;
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashMap;
+    use crate::storage::{MatchSignal, MessageCipher, MessageEncryption};
+    use crate::test_fixtures;
     use tempfile::tempdir;
 
     #[tokio::test]
@@ -556,4 +1026,298 @@ mod tests {
         assert_eq!(identifiers[0].id, "mailto:alice@example.com");
         assert_eq!(identifiers[0].identifier_type, IdentifierType::Email);
     }
+
+    #[tokio::test]
+    async fn test_erase_customer_by_identifier() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let storage = Arc::new(Storage::new(Some(db_path)).await.unwrap());
+
+        let manager = CustomerManager::new(storage.clone());
+
+        let mut metadata = HashMap::new();
+        metadata.insert("name".to_string(), json!("Alice"));
+        let party = Party::with_metadata("alice@example.com", metadata);
+
+        let customer_id = manager
+            .extract_customer_from_party(&party, "did:key:agent", "beneficiary")
+            .await
+            .unwrap();
+
+        let erasure = manager
+            .erase_customer("mailto:alice@example.com", Some("data subject request"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(erasure.customer_id, customer_id);
+
+        let customer = storage.get_customer(&customer_id).await.unwrap().unwrap();
+        assert!(customer.display_name.is_none());
+        assert!(storage
+            .get_customer_by_identifier("mailto:alice@example.com")
+            .await
+            .unwrap()
+            .is_none());
+
+        assert!(manager
+            .erase_customer("mailto:bob@example.com", None)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_erased_customer_stays_erased_when_party_is_seen_again() {
+        let storage = Arc::new(Storage::new_in_memory().await.unwrap());
+        let manager = CustomerManager::new(storage.clone());
+
+        let mut metadata = HashMap::new();
+        metadata.insert("name".to_string(), json!("Alice Smith"));
+        metadata.insert("addressCountry".to_string(), json!("DE"));
+        let party = Party::with_metadata("did:example:alice, mailto:alice@example.com", metadata);
+
+        let customer_id = manager
+            .extract_customer_from_party(&party, "did:key:agent", "originator")
+            .await
+            .unwrap();
+        manager
+            .erase_customer(&customer_id, Some("data subject request"))
+            .await
+            .unwrap()
+            .unwrap();
+
+        // A later Transfer names the same party
+        assert_eq!(
+            manager
+                .extract_customer_from_party(&party, "did:key:agent", "originator")
+                .await
+                .unwrap(),
+            customer_id
+        );
+
+        let customer = storage.get_customer(&customer_id).await.unwrap().unwrap();
+        assert!(customer.display_name.is_none());
+        assert!(customer.given_name.is_none());
+        assert!(customer.address_country.is_none());
+        assert!(customer.ivms101_data.is_none());
+        assert_eq!(customer.profile["erased"], true);
+        assert!(customer.profile.get("name").is_none());
+        assert!(storage
+            .get_customer_by_identifier("mailto:alice@example.com")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_transaction_pii_access_and_revocation() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let storage = Arc::new(
+            Storage::new(Some(db_path))
+                .await
+                .unwrap()
+                .with_message_encryption(MessageCipher::new(
+                    &[7u8; 32],
+                    MessageEncryption::default(),
+                )),
+        );
+        let manager = CustomerManager::new(storage.clone());
+        let (agent, agent_did) = TapAgent::from_ephemeral_key().await.unwrap();
+        let (auditor, auditor_did) = TapAgent::from_ephemeral_key().await.unwrap();
+
+        let mut metadata = HashMap::new();
+        metadata.insert("name".to_string(), json!("Alice"));
+        let alice = Party::with_metadata("did:example:alice", metadata);
+        let transfer = tap_msg::message::Transfer {
+            originator: Some(alice.clone()),
+            ..test_fixtures::transfer_body("did:example:alice", "did:example:bob", "1.0")
+        };
+        storage
+            .insert_transaction(&test_fixtures::transfer_message(
+                "tx-1", &transfer, &agent_did,
+            ))
+            .await
+            .unwrap();
+
+        // The customer keeps no personal data, only a reference to it
+        let customer_id = manager
+            .extract_customer_from_party(&alice, &agent_did, "originator")
+            .await
+            .unwrap();
+        manager
+            .link_transaction_pii(&customer_id, "tx-1", "originator")
+            .await
+            .unwrap();
+        let customer = storage.get_customer(&customer_id).await.unwrap().unwrap();
+        assert!(customer.display_name.is_none());
+        assert!(customer.profile.get("name").is_none());
+        assert!(customer.get_name_hash().is_some());
+        assert_eq!(
+            customer.profile["piiRef"],
+            json!([{"transactionId": "tx-1", "role": "originator"}])
+        );
+
+        assert!(matches!(
+            manager.transaction_pii("tx-1", &agent, " ").await,
+            Err(Error::Validation(_))
+        ));
+        let pii = manager
+            .transaction_pii("tx-1", &agent, "travel rule review")
+            .await
+            .unwrap();
+        assert_eq!(pii.len(), 1);
+        assert_eq!(pii[0].role, "originator");
+        assert_eq!(pii[0].customer_id.as_deref(), Some(customer_id.as_str()));
+        assert_eq!(pii[0].data, serde_json::to_value(&alice).unwrap());
+
+        // Others read it once a participant grants them access
+        assert!(manager
+            .transaction_pii("tx-1", &auditor, "audit")
+            .await
+            .is_err());
+        assert!(manager
+            .revoke_transaction_pii("tx-1", &auditor, None)
+            .await
+            .is_err());
+        manager
+            .grant_transaction_pii_access("tx-1", &agent, &auditor_did)
+            .await
+            .unwrap();
+        assert_eq!(
+            manager
+                .transaction_pii("tx-1", &auditor, "audit")
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        assert!(manager
+            .revoke_transaction_pii("tx-1", &agent, Some("out of scope"))
+            .await
+            .unwrap());
+        assert!(!manager
+            .revoke_transaction_pii("tx-1", &agent, None)
+            .await
+            .unwrap());
+        assert!(manager
+            .transaction_pii("tx-1", &auditor, "audit")
+            .await
+            .is_err());
+
+        let access = manager.transaction_pii_access("tx-1").await.unwrap();
+        let reads: Vec<_> = access
+            .iter()
+            .map(|a| (a.accessor.as_str(), a.purpose.as_str(), a.granted))
+            .collect();
+        assert_eq!(
+            reads,
+            [
+                (agent_did.as_str(), "travel rule review", true),
+                (auditor_did.as_str(), "audit", false),
+                (auditor_did.as_str(), "audit", true),
+                (auditor_did.as_str(), "audit", false),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_and_merge_duplicates() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let storage = Arc::new(Storage::new(Some(db_path)).await.unwrap());
+
+        let manager = CustomerManager::new(storage.clone());
+
+        let parties = [
+            ("did:example:jose-1", "José", "Pérez", "1980-02-01"),
+            ("did:example:jose-2", "Jose", "Perez", "1980-02-01"),
+            ("did:example:jose-3", "Josè", "Peres", "1980-02-01"),
+            ("did:example:maria", "Maria", "Perez", "1975-09-30"),
+        ];
+        for (did, given, family, birth_date) in parties {
+            let mut metadata = HashMap::new();
+            metadata.insert("givenName".to_string(), json!(given));
+            metadata.insert("familyName".to_string(), json!(family));
+            metadata.insert("birthDate".to_string(), json!(birth_date));
+            let party = Party::with_metadata(did, metadata);
+            manager
+                .extract_customer_from_party(&party, "did:key:agent", "originator")
+                .await
+                .unwrap();
+        }
+        manager
+            .update_customer_profile("did:example:jose-2", json!({"telephone": "+4912345"}))
+            .await
+            .unwrap();
+
+        let proposals = manager.find_duplicates("did:key:agent").await.unwrap();
+        assert_eq!(proposals.len(), 3);
+        assert!(proposals
+            .iter()
+            .all(|p| p.duplicate_customer_id != "did:example:maria"
+                && p.primary_customer_id != "did:example:maria"));
+        assert!(proposals[0].signals.contains(&MatchSignal::NameExact));
+
+        // Nothing new is proposed on a second run
+        assert!(manager
+            .find_duplicates("did:key:agent")
+            .await
+            .unwrap()
+            .is_empty());
+
+        let exact = proposals
+            .iter()
+            .find(|p| p.signals.contains(&MatchSignal::NameExact))
+            .unwrap();
+        let (primary_id, duplicate_id) = (
+            exact.primary_customer_id.clone(),
+            exact.duplicate_customer_id.clone(),
+        );
+        let merged = manager.confirm_merge(&exact.id).await.unwrap();
+        assert_eq!(merged.id, primary_id);
+        assert_eq!(merged.profile["telephone"], json!("+4912345"));
+        assert!(storage.get_customer(&duplicate_id).await.unwrap().is_none());
+        assert_eq!(
+            storage
+                .get_customer_by_identifier(&duplicate_id)
+                .await
+                .unwrap()
+                .unwrap()
+                .id,
+            primary_id
+        );
+
+        // The open proposal involving the merged duplicate is dropped
+        let open = manager
+            .list_merge_proposals("did:key:agent", Some(MergeStatus::Proposed), 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(open.len(), 1);
+        manager.reject_merge(&open[0].id).await.unwrap();
+        assert!(manager.reject_merge(&open[0].id).await.is_err());
+        assert!(manager
+            .find_duplicates("did:key:agent")
+            .await
+            .unwrap()
+            .is_empty());
+
+        let lineage = manager.customer_lineage(&primary_id).await.unwrap();
+        assert_eq!(lineage.len(), 1);
+        assert_eq!(lineage[0].duplicate_customer_id, duplicate_id);
+        assert_eq!(
+            lineage[0].duplicate_snapshot.as_ref().unwrap()["id"],
+            json!(duplicate_id)
+        );
+
+        // Party data for the merged duplicate updates the survivor
+        let party = Party::with_metadata(&duplicate_id, HashMap::new());
+        let customer_id = manager
+            .extract_customer_from_party(&party, "did:key:agent", "originator")
+            .await
+            .unwrap();
+        assert_eq!(customer_id, primary_id);
+        assert!(storage.get_customer(&duplicate_id).await.unwrap().is_none());
+    }
 }
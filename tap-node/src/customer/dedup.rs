@@ -0,0 +1,423 @@
+//! Duplicate-party detection for customer records
+//!
+//! Party data arrives with every transaction, and the same person or
+//! organization is often written slightly differently each time ("José
+//! Pérez" and "Jose  Perez", or with and without a postal code). Each such
+//! variation can end up as its own customer record.
+//!
+//! [`DedupConfig::score`] compares two customer records on normalized names,
+//! dates of birth, national identifiers, LEIs and addresses and returns a
+//! confidence between 0 and 1 along with the signals that matched. A
+//! conflicting date of birth, national identifier or LEI rules a pair out,
+//! and a pair matching neither on name nor on an identifier is never a
+//! duplicate. [`CustomerManager::find_duplicates`](super::CustomerManager::find_duplicates)
+//! proposes the pairs scoring at least [`DedupConfig::min_confidence`] as
+//! merges.
+
+use crate::storage::{Customer, MatchSignal, SchemaType};
+use serde_json::Value;
+
+/// Configuration of duplicate-party detection
+///
+/// Each matched signal adds its weight to the confidence of a pair, which is
+/// capped at 1.
+#[derive(Debug, Clone)]
+pub struct DedupConfig {
+    /// Minimum confidence for a pair to be proposed as a merge
+    pub min_confidence: f64,
+    /// Minimum similarity (0 to 1) for normalized names that differ to
+    /// still count as a name match
+    pub name_similarity: f64,
+    /// Weight of identical normalized names. Similar names add this weight
+    /// scaled by their similarity
+    pub name_weight: f64,
+    /// Weight of a matching date of birth
+    pub birth_date_weight: f64,
+    /// Weight of a matching national identifier or LEI
+    pub identifier_weight: f64,
+    /// Weight of a matching address
+    pub address_weight: f64,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            min_confidence: 0.7,
+            name_similarity: 0.85,
+            name_weight: 0.5,
+            birth_date_weight: 0.3,
+            identifier_weight: 0.5,
+            address_weight: 0.2,
+        }
+    }
+}
+
+/// How likely two customer records describe the same party
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateScore {
+    /// Confidence between 0 and 1
+    pub confidence: f64,
+    /// Signals that matched
+    pub signals: Vec<MatchSignal>,
+}
+
+impl DuplicateScore {
+    fn none() -> Self {
+        Self {
+            confidence: 0.0,
+            signals: Vec::new(),
+        }
+    }
+}
+
+impl DedupConfig {
+    /// Score how likely two customer records describe the same party
+    pub fn score(&self, a: &Customer, b: &Customer) -> DuplicateScore {
+        if a.id == b.id || a.schema_type != b.schema_type || is_erased(a) || is_erased(b) {
+            return DuplicateScore::none();
+        }
+
+        let mut signals = Vec::new();
+        let mut confidence = 0.0;
+
+        for (signal, left, right) in [
+            (MatchSignal::BirthDate, birth_date(a), birth_date(b)),
+            (
+                MatchSignal::NationalIdentifier,
+                national_identifier(a),
+                national_identifier(b),
+            ),
+            (MatchSignal::LeiCode, lei_code(a), lei_code(b)),
+        ] {
+            match (left, right) {
+                (Some(left), Some(right)) if left == right => {
+                    signals.push(signal);
+                    confidence += if signal == MatchSignal::BirthDate {
+                        self.birth_date_weight
+                    } else {
+                        self.identifier_weight
+                    };
+                }
+                (Some(_), Some(_)) => return DuplicateScore::none(),
+                _ => {}
+            }
+        }
+
+        if let (Some(left), Some(right)) = (display_name(a), display_name(b)) {
+            let (left, right) = (normalize_name(&left), normalize_name(&right));
+            if !left.is_empty() && left == right {
+                signals.push(MatchSignal::NameExact);
+                confidence += self.name_weight;
+            } else if !left.is_empty() && !right.is_empty() {
+                let similarity = name_similarity(&left, &right);
+                if similarity >= self.name_similarity {
+                    signals.push(MatchSignal::NameSimilar);
+                    confidence += self.name_weight * similarity;
+                }
+            }
+        }
+
+        if !signals.iter().any(|signal| {
+            matches!(
+                signal,
+                MatchSignal::NameExact
+                    | MatchSignal::NameSimilar
+                    | MatchSignal::NationalIdentifier
+                    | MatchSignal::LeiCode
+            )
+        }) {
+            return DuplicateScore::none();
+        }
+
+        if same_address(a, b) {
+            signals.push(MatchSignal::Address);
+            confidence += self.address_weight;
+        }
+
+        DuplicateScore {
+            confidence: confidence.min(1.0),
+            signals,
+        }
+    }
+}
+
+/// Normalize a name for comparison
+///
+/// Letters are lowercased and common Latin diacritics folded, punctuation is
+/// dropped and the words are sorted, so "Pérez, José" and "jose perez"
+/// normalize to the same name.
+pub fn normalize_name(name: &str) -> String {
+    let folded: String = name
+        .chars()
+        .flat_map(char::to_lowercase)
+        .map(fold_diacritic)
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    let mut words: Vec<&str> = folded.split_whitespace().collect();
+    words.sort_unstable();
+    words.join(" ")
+}
+
+/// Keys two records must share at least one of to be compared
+///
+/// Comparing only records that share a name word, date of birth or
+/// identifier keeps detection from comparing every pair of customers.
+pub(crate) fn blocking_keys(customer: &Customer) -> Vec<String> {
+    let mut keys = Vec::new();
+    if let Some(name) = display_name(customer) {
+        keys.extend(
+            normalize_name(&name)
+                .split(' ')
+                .filter(|word| word.chars().count() > 1)
+                .map(|word| format!("name:{}", word)),
+        );
+    }
+    if let Some(date) = birth_date(customer) {
+        keys.push(format!("birth:{}", date));
+    }
+    if let Some(id) = national_identifier(customer) {
+        keys.push(format!("id:{}", id));
+    }
+    if let Some(lei) = lei_code(customer) {
+        keys.push(format!("lei:{}", lei));
+    }
+    keys.sort_unstable();
+    keys.dedup();
+    keys
+}
+
+/// Whether a customer's personal data has been erased
+pub(crate) fn is_erased(customer: &Customer) -> bool {
+    customer.profile.get("erased").and_then(Value::as_bool) == Some(true)
+}
+
+fn display_name(customer: &Customer) -> Option<String> {
+    match customer.schema_type {
+        SchemaType::Person => match (&customer.given_name, &customer.family_name) {
+            (None, None) => customer.display_name.clone(),
+            (given, family) => Some(format!(
+                "{} {}",
+                given.as_deref().unwrap_or(""),
+                family.as_deref().unwrap_or("")
+            )),
+        },
+        SchemaType::Organization => customer
+            .legal_name
+            .clone()
+            .or_else(|| customer.display_name.clone()),
+        SchemaType::Thing => customer.display_name.clone(),
+    }
+}
+
+fn birth_date(customer: &Customer) -> Option<String> {
+    customer
+        .profile
+        .get("birthDate")
+        .and_then(Value::as_str)
+        .or_else(|| {
+            customer
+                .ivms101_data
+                .as_ref()?
+                .pointer("/naturalPerson/dateAndPlaceOfBirth/dateOfBirth")?
+                .as_str()
+        })
+        .map(|date| date.trim().to_string())
+        .filter(|date| !date.is_empty())
+}
+
+fn national_identifier(customer: &Customer) -> Option<String> {
+    customer
+        .ivms101_data
+        .as_ref()
+        .and_then(|data| {
+            data.pointer("/naturalPerson/nationalIdentification/nationalIdentifier")
+                .or_else(|| data.pointer("/legalPerson/nationalIdentification/nationalIdentifier"))
+        })
+        .or_else(|| customer.profile.get("taxID"))
+        .and_then(Value::as_str)
+        .map(normalize_identifier)
+        .filter(|id| !id.is_empty())
+}
+
+fn lei_code(customer: &Customer) -> Option<String> {
+    customer
+        .lei_code
+        .as_deref()
+        .or_else(|| customer.profile.get("leiCode").and_then(Value::as_str))
+        .map(normalize_identifier)
+        .filter(|lei| !lei.is_empty())
+}
+
+fn same_address(a: &Customer, b: &Customer) -> bool {
+    let country = |c: &Customer| c.address_country.as_deref().map(str::to_uppercase);
+    if country(a).is_none() || country(a) != country(b) {
+        return false;
+    }
+
+    let postal_code = |c: &Customer| c.postal_code.as_deref().map(normalize_identifier);
+    let locality = |c: &Customer| c.address_locality.as_deref().map(normalize_name);
+    (postal_code(a).is_some() && postal_code(a) == postal_code(b))
+        || (locality(a).is_some() && locality(a) == locality(b))
+}
+
+fn normalize_identifier(id: &str) -> String {
+    id.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+/// Similarity of two names from their edit distance: 1 for identical
+/// names, 0 for names sharing nothing
+fn name_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    1.0 - previous[b.len()] as f64 / longest as f64
+}
+
+fn fold_diacritic(c: char) -> char {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => 'a',
+        'ç' | 'ć' | 'č' => 'c',
+        'ď' | 'đ' => 'd',
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => 'e',
+        'ğ' => 'g',
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => 'i',
+        'ł' | 'ľ' => 'l',
+        'ñ' | 'ń' | 'ň' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => 'o',
+        'ř' => 'r',
+        'ś' | 'š' | 'ş' => 's',
+        'ť' | 'ţ' => 't',
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' | 'ų' => 'u',
+        'ý' | 'ÿ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn person(id: &str, given: &str, family: &str) -> Customer {
+        Customer {
+            id: id.to_string(),
+            agent_did: "did:example:agent".to_string(),
+            schema_type: SchemaType::Person,
+            given_name: Some(given.to_string()),
+            family_name: Some(family.to_string()),
+            display_name: None,
+            legal_name: None,
+            lei_code: None,
+            mcc_code: None,
+            address_country: None,
+            address_locality: None,
+            postal_code: None,
+            street_address: None,
+            profile: json!({"@type": "Person"}),
+            ivms101_data: None,
+            verified_at: None,
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            updated_at: "2024-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("Pérez, José"), "jose perez");
+        assert_eq!(normalize_name("  JOSE   perez "), "jose perez");
+        assert_eq!(normalize_name("O'Brien"), "brien o");
+    }
+
+    #[test]
+    fn test_score_name_and_birth_date() {
+        let config = DedupConfig::default();
+        let mut a = person("did:example:a", "José", "Pérez");
+        let mut b = person("did:example:b", "Jose", "Perez");
+        a.profile["birthDate"] = json!("1980-02-01");
+        b.ivms101_data = Some(json!({
+            "naturalPerson": {"dateAndPlaceOfBirth": {"dateOfBirth": "1980-02-01"}}
+        }));
+
+        let score = config.score(&a, &b);
+        assert_eq!(
+            score.signals,
+            vec![MatchSignal::BirthDate, MatchSignal::NameExact]
+        );
+        assert!((score.confidence - 0.8).abs() < 1e-9);
+
+        // A typo still matches, with a lower confidence
+        let mut c = person("did:example:c", "Josè", "Peres");
+        c.profile["birthDate"] = json!("1980-02-01");
+        let score = config.score(&a, &c);
+        assert_eq!(
+            score.signals,
+            vec![MatchSignal::BirthDate, MatchSignal::NameSimilar]
+        );
+        assert!(score.confidence >= config.min_confidence && score.confidence < 0.8);
+    }
+
+    #[test]
+    fn test_score_conflicts_and_weak_matches() {
+        let config = DedupConfig::default();
+        let mut a = person("did:example:a", "Alice", "Smith");
+        let mut b = person("did:example:b", "Alice", "Smith");
+        a.profile["birthDate"] = json!("1980-02-01");
+        b.profile["birthDate"] = json!("1991-07-12");
+        assert_eq!(config.score(&a, &b), DuplicateScore::none());
+
+        // Sharing only a date of birth and an address is not a duplicate
+        let mut c = person("did:example:c", "Bob", "Jones");
+        let mut d = person("did:example:d", "Carol", "Jones");
+        for customer in [&mut c, &mut d] {
+            customer.profile["birthDate"] = json!("1980-02-01");
+            customer.address_country = Some("DE".to_string());
+            customer.postal_code = Some("10115".to_string());
+        }
+        assert_eq!(config.score(&c, &d), DuplicateScore::none());
+
+        // The same national identifier matches despite a changed name
+        c.ivms101_data = Some(json!({
+            "naturalPerson": {"nationalIdentification": {"nationalIdentifier": "ab-123 456"}}
+        }));
+        d.profile["taxID"] = json!("AB123456");
+        let score = config.score(&c, &d);
+        assert_eq!(
+            score.signals,
+            vec![
+                MatchSignal::BirthDate,
+                MatchSignal::NationalIdentifier,
+                MatchSignal::Address
+            ]
+        );
+        assert!((score.confidence - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_blocking_keys() {
+        let mut a = person("did:example:a", "José", "Pérez");
+        a.profile["birthDate"] = json!("1980-02-01");
+        assert_eq!(
+            blocking_keys(&a),
+            vec!["birth:1980-02-01", "name:jose", "name:perez"]
+        );
+    }
+}
@@ -0,0 +1,821 @@
+//! Queue of external deliveries
+//!
+//! Messages to agents outside the node are delivered over HTTPS to the
+//! recipient's service endpoint by the [`HttpPlainMessageSenderWithTracking`],
+//! which records every attempt as a delivery in the database of the sending
+//! agent. A delivery that failed stays queued: with a [`DeliveryRetryPolicy`]
+//! configured, its next attempt is scheduled with exponential backoff and the
+//! [`DeliveryRetrier`] makes it once due, until the policy's attempts are used
+//! up.
+//!
+//! Operators manage the queue through [`TapNode::list_delivery_queue`],
+//! [`TapNode::retry_delivery`] to attempt a delivery immediately,
+//! [`TapNode::reassign_delivery`] to deliver it to a different endpoint and
+//! [`TapNode::cancel_delivery`] to give up on it. [`TapNode::delivery_queue`]
+//! covers the queues of all our agents at once, inspects queued deliveries
+//! and requeues failed ones for the retrier, e.g. once a recipient's endpoint
+//! is back up.
+
+use crate::agent::AgentRegistry;
+use crate::clock::{system_clock, Clock};
+use crate::error::{Error, Result};
+use crate::message::sender::HttpPlainMessageSenderWithTracking;
+use crate::scheduler::{JobControl, Schedule, ScheduledJob, Scheduler};
+use crate::storage::{AgentStorageManager, Delivery, DeliveryStatus, DeliveryType, Storage};
+use crate::TapNode;
+use async_trait::async_trait;
+use serde::Serialize;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Name of the scheduled job retrying due deliveries
+pub const DELIVERY_RETRY_JOB: &str = "delivery_retry";
+
+/// Number of due deliveries attempted per agent and sweep
+const RETRY_BATCH_SIZE: u32 = 100;
+
+/// Settings for retrying failed external deliveries
+#[derive(Debug, Clone)]
+pub struct DeliveryRetryPolicy {
+    /// Attempts made in total, including the first, before a delivery is no
+    /// longer retried
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled for every further retry
+    pub initial_backoff: Duration,
+    /// Longest wait between two attempts
+    pub max_backoff: Duration,
+    /// Fraction of each wait, between 0 and 1, added at random so that
+    /// deliveries that failed together are not all retried at once
+    pub jitter: f64,
+    /// How often the background retrier looks for due deliveries
+    pub check_interval: Duration,
+}
+
+impl Default for DeliveryRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(3600),
+            jitter: 0.2,
+            check_interval: Duration::from_secs(15),
+        }
+    }
+}
+
+impl DeliveryRetryPolicy {
+    /// Wait before the next attempt of a delivery that failed `failures`
+    /// times, None once the attempts are used up
+    pub fn backoff(&self, failures: u32) -> Option<Duration> {
+        if failures == 0 || failures >= self.max_attempts {
+            return None;
+        }
+        let factor = 2u32.checked_pow(failures - 1).unwrap_or(u32::MAX);
+        Some(
+            self.initial_backoff
+                .checked_mul(factor)
+                .unwrap_or(self.max_backoff)
+                .min(self.max_backoff),
+        )
+    }
+
+    /// The [`backoff`](Self::backoff) with up to its jitter fraction added at
+    /// random
+    pub fn backoff_with_jitter(&self, failures: u32) -> Option<Duration> {
+        let backoff = self.backoff(failures)?;
+        let random = rand::random::<f64>();
+        let jitter = Duration::try_from_secs_f64(
+            backoff.as_secs_f64() * self.jitter.clamp(0.0, 1.0) * random,
+        )
+        .unwrap_or_default();
+        Some(backoff.checked_add(jitter).unwrap_or(backoff))
+    }
+}
+
+/// Schedule the next attempt of a failed delivery under `policy`, or none
+/// once its attempts are used up
+pub(crate) async fn schedule_retry(
+    storage: &Storage,
+    delivery_id: i64,
+    policy: &DeliveryRetryPolicy,
+    clock: &dyn Clock,
+) -> Result<Option<String>> {
+    let delivery = get_delivery(storage, delivery_id).await?;
+    let failures = u32::try_from(delivery.retry_count).unwrap_or(0);
+    let next_attempt_at = policy
+        .backoff_with_jitter(failures)
+        .and_then(|backoff| chrono::Duration::from_std(backoff).ok())
+        .and_then(|backoff| clock.now().checked_add_signed(backoff))
+        .map(|at| at.format("%Y-%m-%dT%H:%M:%SZ").to_string());
+
+    storage
+        .schedule_delivery_attempt(delivery_id, next_attempt_at.as_deref())
+        .await
+        .map_err(|e| Error::Storage(e.to_string()))?;
+    Ok(next_attempt_at)
+}
+
+/// Attempt a delivery now through the [`HttpPlainMessageSenderWithTracking`]
+/// and record the outcome
+///
+/// A failed attempt is scheduled for retry under `policy`, if any.
+async fn attempt_delivery(
+    storage: &Arc<Storage>,
+    delivery: &Delivery,
+    policy: Option<&DeliveryRetryPolicy>,
+    clock: &dyn Clock,
+) -> Result<Delivery> {
+    storage
+        .schedule_delivery_attempt(delivery.id, None)
+        .await
+        .map_err(|e| Error::Storage(e.to_string()))?;
+
+    let sender = HttpPlainMessageSenderWithTracking::for_endpoints(storage.clone());
+    let attempted = sender.redeliver(delivery).await?;
+    match attempted.status {
+        DeliveryStatus::Success => {
+            debug!(
+                "Delivered message {} to {} (HTTP {:?})",
+                delivery.message_id, delivery.recipient_did, attempted.last_http_status_code
+            );
+            Ok(attempted)
+        }
+        _ => {
+            warn!(
+                "Failed to deliver message {} to {}: {}",
+                delivery.message_id,
+                delivery.recipient_did,
+                attempted
+                    .error_message
+                    .as_deref()
+                    .unwrap_or("unknown error")
+            );
+            match policy {
+                Some(policy) => {
+                    schedule_retry(storage, delivery.id, policy, clock).await?;
+                    get_delivery(storage, delivery.id).await
+                }
+                None => Ok(attempted),
+            }
+        }
+    }
+}
+
+/// Retries failed external deliveries once their next attempt is due
+#[derive(Debug, Clone)]
+pub struct DeliveryRetrier {
+    policy: DeliveryRetryPolicy,
+    clock: Arc<dyn Clock>,
+}
+
+impl DeliveryRetrier {
+    /// Create a new retrier for the given policy
+    pub fn new(policy: DeliveryRetryPolicy) -> Self {
+        Self {
+            policy,
+            clock: system_clock(),
+        }
+    }
+
+    /// Compare next attempts against the given clock instead of the system
+    /// clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Get the retry policy
+    pub fn policy(&self) -> &DeliveryRetryPolicy {
+        &self.policy
+    }
+
+    /// Attempt the due deliveries in the storage of an agent
+    ///
+    /// Returns the deliveries after their attempt.
+    pub async fn sweep(&self, storage: &Arc<Storage>) -> Result<Vec<Delivery>> {
+        let now = self.clock.now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+        let due = storage
+            .get_due_deliveries(&now, RETRY_BATCH_SIZE)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+
+        let mut attempted = Vec::new();
+        for delivery in due {
+            match attempt_delivery(storage, &delivery, Some(&self.policy), &*self.clock).await {
+                Ok(delivery) => attempted.push(delivery),
+                Err(e) => warn!("Failed to retry delivery {}: {}", delivery.id, e),
+            }
+        }
+        Ok(attempted)
+    }
+
+    /// Register the retrier as the [`DELIVERY_RETRY_JOB`] of a scheduler,
+    /// running every check interval unless configured otherwise
+    ///
+    /// The job holds weak references to the agents and their storage and
+    /// stops once either is dropped.
+    pub async fn schedule(
+        self,
+        scheduler: &Scheduler,
+        agents: &Arc<AgentRegistry>,
+        agent_storage_manager: &Arc<AgentStorageManager>,
+    ) {
+        let schedule = Schedule::Every(self.policy.check_interval);
+        debug!(
+            "Registering delivery retrier (max attempts: {})",
+            self.policy.max_attempts
+        );
+        let job = DeliveryRetryJob {
+            retrier: self,
+            agents: Arc::downgrade(agents),
+            agent_storage_manager: Arc::downgrade(agent_storage_manager),
+        };
+        scheduler
+            .register(DELIVERY_RETRY_JOB, schedule, Arc::new(job))
+            .await;
+    }
+}
+
+struct DeliveryRetryJob {
+    retrier: DeliveryRetrier,
+    agents: Weak<AgentRegistry>,
+    agent_storage_manager: Weak<AgentStorageManager>,
+}
+
+#[async_trait]
+impl ScheduledJob for DeliveryRetryJob {
+    async fn run(&self) -> Result<JobControl> {
+        let (Some(agents), Some(manager)) = (
+            Weak::upgrade(&self.agents),
+            Weak::upgrade(&self.agent_storage_manager),
+        ) else {
+            debug!("Node dropped, stopping delivery retrier");
+            return Ok(JobControl::Stop);
+        };
+
+        for agent_did in manager.cached_agent_dids() {
+            if !agents.has_agent(&agent_did) {
+                continue;
+            }
+            let Some(storage) = manager.get_cached_agent_storage(&agent_did) else {
+                continue;
+            };
+            match self.retrier.sweep(&storage).await {
+                Ok(attempted) if !attempted.is_empty() => {
+                    info!("Retried {} deliveries of {}", attempted.len(), agent_did)
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Delivery retry sweep for {} failed: {}", agent_did, e),
+            }
+        }
+        Ok(JobControl::Continue)
+    }
+}
+
+impl TapNode {
+    /// List the queued external deliveries of one of our agents: those
+    /// pending or failed, in the order of their next attempt
+    pub async fn list_delivery_queue(
+        &self,
+        agent_did: &str,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<Delivery>> {
+        self.delivery_storage(agent_did)
+            .await?
+            .get_delivery_queue(limit, offset)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    /// Attempt a queued delivery immediately, whether or not its next attempt
+    /// is due
+    pub async fn retry_delivery(&self, agent_did: &str, delivery_id: i64) -> Result<Delivery> {
+        let storage = self.delivery_storage(agent_did).await?;
+        let delivery = queued_delivery(&storage, delivery_id).await?;
+        attempt_delivery(
+            &storage,
+            &delivery,
+            self.config.delivery_retry.as_ref(),
+            &*self.clock(),
+        )
+        .await
+    }
+
+    /// Deliver a queued message to a different endpoint from its next attempt
+    /// on
+    pub async fn reassign_delivery(
+        &self,
+        agent_did: &str,
+        delivery_id: i64,
+        delivery_url: &str,
+    ) -> Result<Delivery> {
+        if !(delivery_url.starts_with("https://") || delivery_url.starts_with("http://")) {
+            return Err(Error::Validation(format!(
+                "Endpoint {} is not an HTTP(S) URL",
+                delivery_url
+            )));
+        }
+
+        let storage = self.delivery_storage(agent_did).await?;
+        queued_delivery(&storage, delivery_id).await?;
+        storage
+            .reassign_delivery(delivery_id, delivery_url)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        log::info!(
+            "Reassigned delivery {} of {} to {}",
+            delivery_id,
+            agent_did,
+            delivery_url
+        );
+        get_delivery(&storage, delivery_id).await
+    }
+
+    /// Stop attempting a queued delivery
+    pub async fn cancel_delivery(&self, agent_did: &str, delivery_id: i64) -> Result<Delivery> {
+        let storage = self.delivery_storage(agent_did).await?;
+        queued_delivery(&storage, delivery_id).await?;
+        storage
+            .update_delivery_status(
+                delivery_id,
+                DeliveryStatus::Cancelled,
+                None,
+                Some("Cancelled by operator"),
+            )
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        log::info!("Cancelled delivery {} of {}", delivery_id, agent_did);
+        get_delivery(&storage, delivery_id).await
+    }
+
+    /// The queued external deliveries of all our agents, see
+    /// [`DeliveryQueue`]
+    pub fn delivery_queue(&self) -> DeliveryQueue {
+        DeliveryQueue { node: self.clone() }
+    }
+
+    async fn delivery_storage(&self, agent_did: &str) -> Result<Arc<Storage>> {
+        if !self.agents.has_agent(agent_did) {
+            return Err(Error::AgentNotFound(agent_did.to_string()));
+        }
+        let storage_manager = self
+            .agent_storage_manager()
+            .ok_or_else(|| Error::Storage("Agent storage is not initialized".to_string()))?;
+        storage_manager.get_agent_storage(agent_did).await
+    }
+}
+
+/// A delivery in the queue of one of our agents
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedDelivery {
+    /// Our agent sending the message
+    pub agent_did: String,
+    /// The delivery
+    #[serde(flatten)]
+    pub delivery: Delivery,
+}
+
+/// The queued external deliveries of all agents of a node
+///
+/// Obtained from [`TapNode::delivery_queue`]. Requeued deliveries are
+/// attempted by the [`DeliveryRetrier`] on its next run rather than
+/// immediately as with [`TapNode::retry_delivery`], so the retrier must run
+/// on the node or on another node sharing its storage.
+#[derive(Clone)]
+pub struct DeliveryQueue {
+    node: TapNode,
+}
+
+impl DeliveryQueue {
+    /// List up to `limit` queued deliveries of all our agents, in the order
+    /// of their next attempt
+    pub async fn list(&self, limit: u32) -> Result<Vec<QueuedDelivery>> {
+        let mut queued = Vec::new();
+        for agent_did in self.node.agents.get_all_dids() {
+            let deliveries = self.node.list_delivery_queue(&agent_did, limit, 0).await?;
+            queued.extend(deliveries.into_iter().map(|delivery| QueuedDelivery {
+                agent_did: agent_did.clone(),
+                delivery,
+            }));
+        }
+        queued.sort_by(|a, b| queue_order(&a.delivery).cmp(&queue_order(&b.delivery)));
+        queued.truncate(limit as usize);
+        Ok(queued)
+    }
+
+    /// Inspect a queued delivery of one of our agents, including the packed
+    /// message, its attempts and its last error
+    pub async fn get(&self, agent_did: &str, delivery_id: i64) -> Result<QueuedDelivery> {
+        let storage = self.node.delivery_storage(agent_did).await?;
+        Ok(QueuedDelivery {
+            agent_did: agent_did.to_string(),
+            delivery: queued_delivery(&storage, delivery_id).await?,
+        })
+    }
+
+    /// Have the retrier attempt a failed delivery on its next run
+    ///
+    /// A delivery whose attempts are used up gets one more attempt.
+    pub async fn requeue(&self, agent_did: &str, delivery_id: i64) -> Result<QueuedDelivery> {
+        let storage = self.node.delivery_storage(agent_did).await?;
+        let delivery = queued_delivery(&storage, delivery_id).await?;
+        if delivery.status != DeliveryStatus::Failed {
+            return Err(Error::Validation(format!(
+                "Delivery {} is {}, only failed deliveries are requeued",
+                delivery_id, delivery.status
+            )));
+        }
+        self.requeue_now(&storage, delivery_id).await?;
+        log::info!("Requeued delivery {} of {}", delivery_id, agent_did);
+        Ok(QueuedDelivery {
+            agent_did: agent_did.to_string(),
+            delivery: get_delivery(&storage, delivery_id).await?,
+        })
+    }
+
+    /// Requeue the failed deliveries of one of our agents, or of all of them,
+    /// optionally only those to one recipient, e.g. once the recipient's
+    /// endpoint is back up
+    ///
+    /// Returns the deliveries requeued.
+    pub async fn requeue_failed(
+        &self,
+        agent_did: Option<&str>,
+        recipient_did: Option<&str>,
+    ) -> Result<Vec<QueuedDelivery>> {
+        let agent_dids = match agent_did {
+            Some(agent_did) => vec![agent_did.to_string()],
+            None => self.node.agents.get_all_dids(),
+        };
+
+        let mut requeued = Vec::new();
+        for agent_did in agent_dids {
+            let storage = self.node.delivery_storage(&agent_did).await?;
+            let failed = storage
+                .get_failed_deliveries(recipient_did)
+                .await
+                .map_err(|e| Error::Storage(e.to_string()))?;
+            for delivery in failed {
+                self.requeue_now(&storage, delivery.id).await?;
+                requeued.push(QueuedDelivery {
+                    agent_did: agent_did.clone(),
+                    delivery: get_delivery(&storage, delivery.id).await?,
+                });
+            }
+        }
+        if !requeued.is_empty() {
+            log::info!("Requeued {} failed deliveries", requeued.len());
+        }
+        Ok(requeued)
+    }
+
+    async fn requeue_now(&self, storage: &Storage, delivery_id: i64) -> Result<()> {
+        let now = self
+            .node
+            .clock()
+            .now()
+            .format("%Y-%m-%dT%H:%M:%SZ")
+            .to_string();
+        storage
+            .schedule_delivery_attempt(delivery_id, Some(&now))
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+}
+
+/// Order of the delivery queue: by next attempt, deliveries without one last
+fn queue_order(delivery: &Delivery) -> (bool, Option<&str>, &str, i64) {
+    (
+        delivery.next_attempt_at.is_none(),
+        delivery.next_attempt_at.as_deref(),
+        &delivery.created_at,
+        delivery.id,
+    )
+}
+
+async fn get_delivery(storage: &Storage, delivery_id: i64) -> Result<Delivery> {
+    storage
+        .get_delivery_by_id(delivery_id)
+        .await
+        .map_err(|e| Error::Storage(e.to_string()))?
+        .ok_or_else(|| Error::Storage(format!("Delivery {} not found", delivery_id)))
+}
+
+/// A delivery that is in the queue of external deliveries
+async fn queued_delivery(storage: &Storage, delivery_id: i64) -> Result<Delivery> {
+    let delivery = get_delivery(storage, delivery_id).await?;
+    if delivery.delivery_type != DeliveryType::Https {
+        return Err(Error::Validation(format!(
+            "Delivery {} is a {} delivery, not an external one",
+            delivery_id, delivery.delivery_type
+        )));
+    }
+    if matches!(
+        delivery.status,
+        DeliveryStatus::Success | DeliveryStatus::Cancelled
+    ) {
+        return Err(Error::Validation(format!(
+            "Delivery {} is already {}",
+            delivery_id, delivery.status
+        )));
+    }
+    Ok(delivery)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::storage::MessageDirection;
+    use crate::NodeConfig;
+    use chrono::{TimeZone, Utc};
+    use tap_agent::TapAgent;
+    use tap_msg::didcomm::PlainMessage;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_limit() {
+        let policy = DeliveryRetryPolicy {
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(100),
+            jitter: 0.0,
+            check_interval: Duration::from_secs(15),
+        };
+        assert_eq!(policy.backoff(1), Some(Duration::from_secs(30)));
+        assert_eq!(policy.backoff(2), Some(Duration::from_secs(60)));
+        assert_eq!(policy.backoff(3), Some(Duration::from_secs(100)));
+        assert_eq!(policy.backoff(5), None);
+        assert_eq!(policy.backoff_with_jitter(2), Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_jitter_stays_within_its_fraction() {
+        let policy = DeliveryRetryPolicy {
+            jitter: 0.5,
+            ..Default::default()
+        };
+        for _ in 0..100 {
+            let backoff = policy.backoff_with_jitter(2).unwrap();
+            assert!(backoff >= Duration::from_secs(60));
+            assert!(backoff <= Duration::from_secs(90));
+        }
+        assert_eq!(policy.backoff_with_jitter(5), None);
+    }
+
+    #[test]
+    fn test_jitter_spreads_over_its_whole_range() {
+        let policy = DeliveryRetryPolicy {
+            initial_backoff: Duration::from_secs(100),
+            jitter: 1.0,
+            ..Default::default()
+        };
+        let samples: Vec<f64> = (0..1000)
+            .map(|_| policy.backoff_with_jitter(1).unwrap().as_secs_f64())
+            .collect();
+        assert!(samples.iter().all(|s| (100.0..=200.0).contains(s)));
+
+        // With 1000 uniform samples, each end of the range is hit with
+        // overwhelming probability
+        assert!(samples.iter().any(|s| *s < 110.0));
+        assert!(samples.iter().any(|s| *s > 190.0));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_failed_retry_is_rescheduled_until_cancelled() {
+        let clock = Arc::new(MockClock::new(
+            Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap(),
+        ));
+        let temp_dir = TempDir::new().unwrap();
+        let mut node = TapNode::new(NodeConfig {
+            tap_root: Some(temp_dir.path().to_path_buf()),
+            storage_path: Some(temp_dir.path().join("node.db")),
+            clock: Some(clock.clone()),
+            delivery_retry: Some(DeliveryRetryPolicy {
+                jitter: 0.0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        node.init_storage().await.unwrap();
+        let (agent, did) = TapAgent::from_ephemeral_key().await.unwrap();
+        node.register_agent(Arc::new(agent)).await.unwrap();
+
+        let storage = node.delivery_storage(&did).await.unwrap();
+        let message = PlainMessage::new(
+            "msg-1".to_string(),
+            "https://didcomm.org/trust_ping/2.0/ping".to_string(),
+            serde_json::json!({}),
+            did.clone(),
+        );
+        storage
+            .log_message(&message, MessageDirection::Outgoing)
+            .await
+            .unwrap();
+        // Nothing listens on the discard port, so every attempt fails
+        let delivery_id = storage
+            .create_delivery(
+                "msg-1",
+                "{}",
+                "did:example:beneficiary",
+                Some("http://127.0.0.1:9/didcomm"),
+                DeliveryType::Https,
+            )
+            .await
+            .unwrap();
+
+        let delivery = node.retry_delivery(&did, delivery_id).await.unwrap();
+        assert_eq!(delivery.status, DeliveryStatus::Failed);
+        assert_eq!(delivery.retry_count, 1);
+        assert_eq!(
+            delivery.next_attempt_at.as_deref(),
+            Some("2025-01-01T12:00:30Z")
+        );
+
+        let delivery = node
+            .reassign_delivery(&did, delivery_id, "https://other.example/didcomm")
+            .await
+            .unwrap();
+        assert_eq!(
+            delivery.delivery_url.as_deref(),
+            Some("https://other.example/didcomm")
+        );
+        assert!(node
+            .reassign_delivery(&did, delivery_id, "ftp://other.example")
+            .await
+            .is_err());
+
+        let queue = node.list_delivery_queue(&did, 10, 0).await.unwrap();
+        assert_eq!(queue.len(), 1);
+
+        let delivery = node.cancel_delivery(&did, delivery_id).await.unwrap();
+        assert_eq!(delivery.status, DeliveryStatus::Cancelled);
+        assert!(delivery.next_attempt_at.is_none());
+        assert!(node
+            .list_delivery_queue(&did, 10, 0)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(node.retry_delivery(&did, delivery_id).await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_requeue_failed_deliveries() {
+        let clock = Arc::new(MockClock::new(
+            Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap(),
+        ));
+        let temp_dir = TempDir::new().unwrap();
+        let mut node = TapNode::new(NodeConfig {
+            tap_root: Some(temp_dir.path().to_path_buf()),
+            storage_path: Some(temp_dir.path().join("node.db")),
+            clock: Some(clock.clone()),
+            delivery_retry: Some(DeliveryRetryPolicy {
+                jitter: 0.0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        node.init_storage().await.unwrap();
+        let (agent, did) = TapAgent::from_ephemeral_key().await.unwrap();
+        node.register_agent(Arc::new(agent)).await.unwrap();
+
+        let storage = node.delivery_storage(&did).await.unwrap();
+        let mut delivery_ids = Vec::new();
+        for (message_id, recipient) in [
+            ("msg-1", "did:example:beneficiary"),
+            ("msg-2", "did:example:other"),
+        ] {
+            let message = PlainMessage::new(
+                message_id.to_string(),
+                "https://didcomm.org/trust_ping/2.0/ping".to_string(),
+                serde_json::json!({}),
+                did.clone(),
+            );
+            storage
+                .log_message(&message, MessageDirection::Outgoing)
+                .await
+                .unwrap();
+            delivery_ids.push(
+                storage
+                    .create_delivery(
+                        message_id,
+                        "{}",
+                        recipient,
+                        Some("http://127.0.0.1:9/didcomm"),
+                        DeliveryType::Https,
+                    )
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        // Only failed deliveries are requeued
+        let queue = node.delivery_queue();
+        assert!(queue.requeue(&did, delivery_ids[0]).await.is_err());
+
+        for delivery_id in &delivery_ids {
+            node.retry_delivery(&did, *delivery_id).await.unwrap();
+        }
+        clock.advance(chrono::Duration::seconds(10));
+
+        let listed = queue.list(10).await.unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].agent_did, did);
+        assert_eq!(
+            listed[0].delivery.next_attempt_at.as_deref(),
+            Some("2025-01-01T12:00:30Z")
+        );
+
+        let inspected = queue.get(&did, delivery_ids[1]).await.unwrap();
+        assert_eq!(inspected.delivery.recipient_did, "did:example:other");
+        assert_eq!(inspected.delivery.retry_count, 1);
+        assert!(inspected.delivery.error_message.is_some());
+
+        let requeued = queue.requeue(&did, delivery_ids[0]).await.unwrap();
+        assert_eq!(
+            requeued.delivery.next_attempt_at.as_deref(),
+            Some("2025-01-01T12:00:10Z")
+        );
+
+        let requeued = queue
+            .requeue_failed(None, Some("did:example:other"))
+            .await
+            .unwrap();
+        assert_eq!(requeued.len(), 1);
+        assert_eq!(requeued[0].delivery.id, delivery_ids[1]);
+        assert_eq!(
+            queue.requeue_failed(Some(&did), None).await.unwrap().len(),
+            2
+        );
+
+        // The retrier attempts both on its next run
+        let retrier = DeliveryRetrier::new(node.config.delivery_retry.clone().unwrap())
+            .with_clock(clock.clone());
+        let attempted = retrier.sweep(&storage).await.unwrap();
+        assert_eq!(attempted.len(), 2);
+        assert!(attempted.iter().all(|delivery| delivery.retry_count == 2));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_error_response_fails_delivery() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/didcomm", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let n = stream.read(&mut buf).await.unwrap();
+            let request = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+            stream
+                .write_all(b"HTTP/1.1 500 Internal Server Error\r\nConnection: close\r\nContent-Length: 4\r\n\r\ndown")
+                .await
+                .unwrap();
+            request
+        });
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut node = TapNode::new(NodeConfig {
+            tap_root: Some(temp_dir.path().to_path_buf()),
+            storage_path: Some(temp_dir.path().join("node.db")),
+            ..Default::default()
+        });
+        node.init_storage().await.unwrap();
+        let (agent, did) = TapAgent::from_ephemeral_key().await.unwrap();
+        node.register_agent(Arc::new(agent)).await.unwrap();
+
+        let storage = node.delivery_storage(&did).await.unwrap();
+        let message = PlainMessage::new(
+            "msg-1".to_string(),
+            "https://didcomm.org/trust_ping/2.0/ping".to_string(),
+            serde_json::json!({}),
+            did.clone(),
+        );
+        storage
+            .log_message(&message, MessageDirection::Outgoing)
+            .await
+            .unwrap();
+        let packed = r#"{"payload":"e30","signatures":[]}"#;
+        let delivery_id = storage
+            .create_delivery(
+                "msg-1",
+                packed,
+                "did:example:beneficiary",
+                Some(&endpoint),
+                DeliveryType::Https,
+            )
+            .await
+            .unwrap();
+
+        let delivery = node.retry_delivery(&did, delivery_id).await.unwrap();
+        assert_eq!(delivery.status, DeliveryStatus::Failed);
+        assert_eq!(delivery.last_http_status_code, Some(500));
+        assert!(delivery.error_message.unwrap().contains("down"));
+
+        let request = server.await.unwrap();
+        assert!(request.contains("content-type: application/didcomm-signed+json"));
+        assert!(request.contains("idempotency-key: "));
+    }
+}
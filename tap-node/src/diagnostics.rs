@@ -0,0 +1,251 @@
+//! Live diagnostics of message processing
+//!
+//! [`TapNode::diagnostics`] reports where messages are waiting and where
+//! processing time goes: the depth of each processor pool lane, messages
+//! queued under the intake limits, messages being received and sent, the time
+//! spent in each message processor, and the backlog of the event bus and its
+//! subscribers.
+//!
+//! With the `diagnostics` feature, the same activity is visible through
+//! `tracing`: processor pool tasks and processor calls run in spans, and
+//! every call to [`TapNode::diagnostics`] emits the snapshot as a
+//! `tap_node::diagnostics` event. Built with `RUSTFLAGS="--cfg tokio_unstable"`
+//! and a `console-subscriber` layer installed, the node's tasks can be
+//! watched live in `tokio-console`.
+
+use crate::event::EventBusMetrics;
+use crate::message::MessagePriority;
+use crate::TapNode;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Direction in which a message passes through the processors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessingDirection {
+    Incoming,
+    Outgoing,
+}
+
+impl ProcessingDirection {
+    /// Snake-case name of the direction
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProcessingDirection::Incoming => "incoming",
+            ProcessingDirection::Outgoing => "outgoing",
+        }
+    }
+}
+
+/// Accumulated time of one processor in one direction
+#[derive(Debug, Default, Clone, Copy)]
+struct TimingTotals {
+    calls: u64,
+    total: Duration,
+    max: Duration,
+}
+
+/// Time spent in each message processor, filled in by
+/// [`CompositePlainMessageProcessor`](crate::message::CompositePlainMessageProcessor)
+#[derive(Debug, Default)]
+pub struct ProcessorTimings {
+    totals: Mutex<BTreeMap<(&'static str, ProcessingDirection), TimingTotals>>,
+}
+
+impl ProcessorTimings {
+    /// Record a call of a processor
+    pub fn record(&self, processor: &'static str, direction: ProcessingDirection, took: Duration) {
+        if let Ok(mut totals) = self.totals.lock() {
+            let totals = totals.entry((processor, direction)).or_default();
+            totals.calls += 1;
+            totals.total += took;
+            totals.max = totals.max.max(took);
+        }
+    }
+
+    /// Timings recorded so far, by processor name then direction
+    pub fn snapshot(&self) -> Vec<ProcessorTiming> {
+        let Ok(totals) = self.totals.lock() else {
+            return Vec::new();
+        };
+        totals
+            .iter()
+            .map(|((processor, direction), totals)| ProcessorTiming {
+                processor: processor.to_string(),
+                direction: *direction,
+                calls: totals.calls,
+                total_micros: totals.total.as_micros() as u64,
+                mean_micros: (totals.total / totals.calls.max(1) as u32).as_micros() as u64,
+                max_micros: totals.max.as_micros() as u64,
+            })
+            .collect()
+    }
+}
+
+/// Time spent in a message processor
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessorTiming {
+    /// Processor name, see
+    /// [`PlainMessageProcessorType::name`](crate::message::PlainMessageProcessorType::name)
+    pub processor: String,
+    pub direction: ProcessingDirection,
+    /// Messages the processor handled
+    pub calls: u64,
+    pub total_micros: u64,
+    pub mean_micros: u64,
+    /// Longest single call
+    pub max_micros: u64,
+}
+
+/// Count of messages being handled
+#[derive(Debug, Default)]
+pub(crate) struct InFlight(AtomicUsize);
+
+impl InFlight {
+    /// Count a message until the returned guard is dropped
+    pub fn enter(&self) -> InFlightGuard<'_> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard(&self.0)
+    }
+
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Counts a message as in flight while it lives
+pub(crate) struct InFlightGuard<'a>(&'a AtomicUsize);
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Diagnostic counters of a node
+#[derive(Debug, Default)]
+pub(crate) struct DiagnosticCounters {
+    pub timings: Arc<ProcessorTimings>,
+    /// Received messages being processed
+    pub incoming: InFlight,
+    /// Messages being sent
+    pub outgoing: InFlight,
+}
+
+/// Messages waiting in each processor pool lane
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct LaneDepths {
+    pub high: usize,
+    pub normal: usize,
+    pub low: usize,
+}
+
+/// Snapshot of message processing, see the [module documentation](self)
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeDiagnostics {
+    /// Lane depths of the processor pool, if the node was started with one
+    pub processor_pool: Option<LaneDepths>,
+    /// Whether the intake of received messages is paused
+    pub intake_paused: bool,
+    /// Received messages waiting under the intake limits
+    pub intake_queued: usize,
+    /// Received messages being processed
+    pub incoming_in_flight: usize,
+    /// Messages being sent
+    pub outgoing_in_flight: usize,
+    /// Time spent in each processor
+    pub processors: Vec<ProcessorTiming>,
+    /// Event bus activity, including the backlog of channel receivers and
+    /// subscriber queues
+    pub event_bus: EventBusMetrics,
+}
+
+impl TapNode {
+    /// Snapshot of queue depths, in-flight messages, processor timings and
+    /// event bus backlog
+    pub async fn diagnostics(&self) -> NodeDiagnostics {
+        let processor_pool = self.processor_pool.as_ref().map(|pool| LaneDepths {
+            high: pool.queued(MessagePriority::High),
+            normal: pool.queued(MessagePriority::Normal),
+            low: pool.queued(MessagePriority::Low),
+        });
+        let diagnostics = NodeDiagnostics {
+            processor_pool,
+            intake_paused: self.intake.is_paused(),
+            intake_queued: self.intake.queued(),
+            incoming_in_flight: self.diagnostics.incoming.get(),
+            outgoing_in_flight: self.diagnostics.outgoing.get(),
+            processors: self.diagnostics.timings.snapshot(),
+            event_bus: self.event_bus.metrics().await,
+        };
+
+        #[cfg(feature = "diagnostics")]
+        tracing::debug!(
+            target: "tap_node::diagnostics",
+            intake_queued = diagnostics.intake_queued,
+            incoming_in_flight = diagnostics.incoming_in_flight,
+            outgoing_in_flight = diagnostics.outgoing_in_flight,
+            event_bus_backlog = diagnostics.event_bus.channel_backlog,
+            subscriber_queue_depth = diagnostics
+                .event_bus
+                .subscribers
+                .iter()
+                .map(|subscriber| subscriber.queue_depth)
+                .sum::<usize>(),
+            snapshot = %serde_json::to_string(&diagnostics).unwrap_or_default(),
+            "Node diagnostics"
+        );
+
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_processor_timings() {
+        let timings = ProcessorTimings::default();
+        timings.record(
+            "validation",
+            ProcessingDirection::Incoming,
+            Duration::from_micros(100),
+        );
+        timings.record(
+            "validation",
+            ProcessingDirection::Incoming,
+            Duration::from_micros(300),
+        );
+        timings.record(
+            "logging",
+            ProcessingDirection::Outgoing,
+            Duration::from_micros(10),
+        );
+
+        let snapshot = timings.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[0].processor, "logging");
+        let validation = &snapshot[1];
+        assert_eq!(validation.direction, ProcessingDirection::Incoming);
+        assert_eq!(validation.calls, 2);
+        assert_eq!(validation.total_micros, 400);
+        assert_eq!(validation.mean_micros, 200);
+        assert_eq!(validation.max_micros, 300);
+    }
+
+    #[test]
+    fn test_in_flight_guard() {
+        let in_flight = InFlight::default();
+        let first = in_flight.enter();
+        let second = in_flight.enter();
+        assert_eq!(in_flight.get(), 2);
+        drop(first);
+        assert_eq!(in_flight.get(), 1);
+        drop(second);
+        assert_eq!(in_flight.get(), 0);
+    }
+}
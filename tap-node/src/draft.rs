@@ -0,0 +1,405 @@
+//! Drafts of Transfers and Payments
+//!
+//! A draft holds the body of a Transfer or Payment while it is built up, for
+//! example over several CLI invocations or MCP tool calls, so that a
+//! half-built transaction does not only live in the memory of the process
+//! building it. Drafts are stored in the database of the agent that will send
+//! them.
+//!
+//! Changes to a draft are JSON merge patches (RFC 7386) of its body: fields
+//! in the patch replace those of the body and `null` removes a field. The
+//! body is validated after every change, and the errors are recorded on the
+//! draft. Once valid, [`TapNode::send_draft`] sends it as a message from the
+//! draft's agent.
+//!
+//! Drafts expire, by default [`DEFAULT_DRAFT_TTL`] after they were created,
+//! and cannot be sent afterwards. Expired drafts that were not sent are
+//! deleted when the agent creates its next draft.
+
+use crate::error::{Error, Result};
+use crate::storage::{Draft, DraftKind, DraftStatus, Storage};
+use crate::TapNode;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::tap_message_trait::TapMessageBody;
+use tap_msg::message::{Payment, Transfer};
+
+/// How long drafts can be sent unless created with a time to live of their own
+pub const DEFAULT_DRAFT_TTL: Duration = Duration::from_secs(7 * 24 * 3600);
+
+/// Why the body of a draft cannot be sent as a message, empty if it can
+pub fn validate_draft(kind: DraftKind, body: &Value) -> Vec<String> {
+    match draft_body(kind, body, "did:example:validation") {
+        Ok(_) => Vec::new(),
+        Err(e) => vec![e],
+    }
+}
+
+/// Apply a JSON merge patch (RFC 7386) to `target`
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(serde_json::Map::new());
+    }
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(key);
+            } else {
+                merge_patch(target.entry(key.as_str()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+/// The message a draft's body is sent as, or why it cannot be sent
+fn draft_body(
+    kind: DraftKind,
+    body: &Value,
+    from: &str,
+) -> std::result::Result<PlainMessage, String> {
+    match kind {
+        DraftKind::Transfer => {
+            let transfer: Transfer =
+                serde_json::from_value(body.clone()).map_err(|e| e.to_string())?;
+            TapMessageBody::validate(&transfer).map_err(|e| e.to_string())?;
+            transfer.to_didcomm(from).map_err(|e| e.to_string())
+        }
+        DraftKind::Payment => {
+            let payment: Payment =
+                serde_json::from_value(body.clone()).map_err(|e| e.to_string())?;
+            TapMessageBody::validate(&payment).map_err(|e| e.to_string())?;
+            payment.to_didcomm(from).map_err(|e| e.to_string())
+        }
+    }
+}
+
+fn status_of(validation_errors: &[String]) -> DraftStatus {
+    if validation_errors.is_empty() {
+        DraftStatus::Valid
+    } else {
+        DraftStatus::Invalid
+    }
+}
+
+impl TapNode {
+    /// Store a draft of a Transfer or Payment to be sent by one of our agents
+    ///
+    /// `body` may be incomplete; the draft records why it cannot be sent yet.
+    /// The draft expires after `ttl`, or [`DEFAULT_DRAFT_TTL`] if None.
+    pub async fn create_draft(
+        &self,
+        agent_did: &str,
+        kind: DraftKind,
+        body: Value,
+        ttl: Option<Duration>,
+    ) -> Result<Draft> {
+        let storage = self.draft_storage(agent_did).await?;
+        let purged = storage
+            .delete_expired_drafts()
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        if purged > 0 {
+            log::debug!("Deleted {} expired drafts of {}", purged, agent_did);
+        }
+
+        let now = self.clock().now();
+        let expires_at = chrono::Duration::from_std(ttl.unwrap_or(DEFAULT_DRAFT_TTL))
+            .ok()
+            .and_then(|ttl| now.checked_add_signed(ttl))
+            .map(|at| at.format("%Y-%m-%dT%H:%M:%SZ").to_string());
+        let validation_errors = validate_draft(kind, &body);
+        let timestamp = now.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+        let draft = Draft {
+            id: tap_msg::id::new_id(),
+            agent_did: agent_did.to_string(),
+            kind,
+            body,
+            status: status_of(&validation_errors),
+            validation_errors,
+            expires_at,
+            message_id: None,
+            created_at: timestamp.clone(),
+            updated_at: timestamp,
+        };
+        storage
+            .insert_draft(&draft)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        log::info!(
+            "Created {} draft {} for {}",
+            draft.kind,
+            draft.id,
+            agent_did
+        );
+        Ok(draft)
+    }
+
+    /// Apply a JSON merge patch to the body of a draft that was not sent
+    pub async fn update_draft(
+        &self,
+        agent_did: &str,
+        draft_id: &str,
+        patch: &Value,
+    ) -> Result<Draft> {
+        let storage = self.draft_storage(agent_did).await?;
+        let mut draft = self.open_draft(&storage, draft_id).await?;
+
+        merge_patch(&mut draft.body, patch);
+        draft.validation_errors = validate_draft(draft.kind, &draft.body);
+        draft.status = status_of(&draft.validation_errors);
+        let updated = storage
+            .update_draft(
+                draft_id,
+                &draft.body,
+                draft.status,
+                &draft.validation_errors,
+            )
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        if !updated {
+            return Err(Error::Validation(format!(
+                "Draft {} was sent while it was being updated",
+                draft_id
+            )));
+        }
+        self.get_draft(agent_did, draft_id).await
+    }
+
+    /// Get a draft of one of our agents
+    pub async fn get_draft(&self, agent_did: &str, draft_id: &str) -> Result<Draft> {
+        self.draft_storage(agent_did)
+            .await?
+            .get_draft(draft_id)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?
+            .ok_or_else(|| Error::Storage(format!("Draft {} not found", draft_id)))
+    }
+
+    /// Send a valid draft as a message from its agent
+    ///
+    /// The draft is marked sent, with the ID of the message, before the
+    /// message is sent, so that it is sent only once; if sending fails it can
+    /// be sent again.
+    pub async fn send_draft(&self, agent_did: &str, draft_id: &str) -> Result<Draft> {
+        let storage = self.draft_storage(agent_did).await?;
+        let draft = self.open_draft(&storage, draft_id).await?;
+        let message = draft_body(draft.kind, &draft.body, agent_did)
+            .map_err(|e| Error::Validation(format!("Draft {} is not valid: {}", draft_id, e)))?;
+
+        let claimed = storage
+            .mark_draft_sent(draft_id, &message.id)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        if !claimed {
+            return Err(Error::Validation(format!(
+                "Draft {} is not valid or was already sent",
+                draft_id
+            )));
+        }
+
+        if let Err(e) = self
+            .send_message(agent_did.to_string(), message.clone())
+            .await
+        {
+            if let Err(reopen) = storage.reopen_draft(draft_id, &message.id).await {
+                log::warn!("Could not reopen draft {}: {}", draft_id, reopen);
+            }
+            return Err(e);
+        }
+        log::info!(
+            "Sent draft {} of {} as message {}",
+            draft_id,
+            agent_did,
+            message.id
+        );
+        self.get_draft(agent_did, draft_id).await
+    }
+
+    /// Delete a draft that was not sent, returning whether it existed
+    pub async fn delete_draft(&self, agent_did: &str, draft_id: &str) -> Result<bool> {
+        self.draft_storage(agent_did)
+            .await?
+            .delete_draft(draft_id)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    /// A draft that can still be changed or sent
+    async fn open_draft(&self, storage: &Storage, draft_id: &str) -> Result<Draft> {
+        let draft = storage
+            .get_draft(draft_id)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?
+            .ok_or_else(|| Error::Storage(format!("Draft {} not found", draft_id)))?;
+        if draft.status == DraftStatus::Sent {
+            return Err(Error::Validation(format!(
+                "Draft {} was already sent as message {}",
+                draft_id,
+                draft.message_id.as_deref().unwrap_or_default()
+            )));
+        }
+        if draft.is_expired(self.clock().now()) {
+            return Err(Error::Validation(format!("Draft {} has expired", draft_id)));
+        }
+        Ok(draft)
+    }
+
+    async fn draft_storage(&self, agent_did: &str) -> Result<Arc<Storage>> {
+        if !self.agents.has_agent(agent_did) {
+            return Err(Error::AgentNotFound(agent_did.to_string()));
+        }
+        let storage_manager = self
+            .agent_storage_manager()
+            .ok_or_else(|| Error::Storage("Agent storage is not initialized".to_string()))?;
+        storage_manager.get_agent_storage(agent_did).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, MockClock};
+    use crate::NodeConfig;
+    use chrono::{DateTime, Utc};
+    use serde_json::json;
+    use tap_agent::TapAgent;
+    use tempfile::TempDir;
+
+    async fn node_with_agent(clock: Arc<MockClock>) -> (TempDir, TapNode, String) {
+        let temp_dir = TempDir::new().unwrap();
+        let mut node = TapNode::new(NodeConfig {
+            tap_root: Some(temp_dir.path().to_path_buf()),
+            storage_path: Some(temp_dir.path().join("node.db")),
+            clock: Some(clock),
+            ..Default::default()
+        });
+        node.init_storage().await.unwrap();
+        let (agent, did) = TapAgent::from_ephemeral_key().await.unwrap();
+        node.register_agent(Arc::new(agent)).await.unwrap();
+        (temp_dir, node, did)
+    }
+
+    #[test]
+    fn test_merge_patch() {
+        let mut body = json!({"amount": "10", "originator": {"@id": "did:example:a"}});
+        merge_patch(
+            &mut body,
+            &json!({"amount": null, "originator": {"name": "Alice"}, "memo": "rent"}),
+        );
+        assert_eq!(
+            body,
+            json!({"originator": {"@id": "did:example:a", "name": "Alice"}, "memo": "rent"})
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_draft_is_built_up_and_sent_once() {
+        // Messages are sent at the system time, so the clock must not be far off
+        let clock = Arc::new(MockClock::default());
+        let (_temp_dir, node, did) = node_with_agent(clock.clone()).await;
+
+        let draft = node
+            .create_draft(
+                &did,
+                DraftKind::Transfer,
+                json!({"asset": "eip155:1/slip44:60"}),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(draft.status, DraftStatus::Invalid);
+        assert!(draft.validation_errors[0].contains("amount"));
+        let expires_at = clock.now() + chrono::Duration::days(7);
+        assert_eq!(
+            draft.expires_at,
+            Some(expires_at.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        );
+        assert!(node.send_draft(&did, &draft.id).await.is_err());
+
+        let draft = node
+            .update_draft(
+                &did,
+                &draft.id,
+                &json!({
+                    "amount": "1.5",
+                    "originator": {"@id": did},
+                    "beneficiary": {"@id": "did:example:beneficiary"},
+                    "agents": [{"@id": did, "role": "SourceAgent", "for": did}]
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            draft.status,
+            DraftStatus::Valid,
+            "{:?}",
+            draft.validation_errors
+        );
+
+        // A draft whose message could not be delivered can be sent again
+        assert!(node.send_draft(&did, &draft.id).await.is_err());
+        let draft = node.get_draft(&did, &draft.id).await.unwrap();
+        assert_eq!(draft.status, DraftStatus::Valid);
+        assert!(draft.message_id.is_none());
+
+        let (beneficiary, beneficiary_did) = TapAgent::from_ephemeral_key().await.unwrap();
+        node.register_agent(Arc::new(beneficiary)).await.unwrap();
+        node.update_draft(
+            &did,
+            &draft.id,
+            &json!({"beneficiary": {"@id": beneficiary_did}}),
+        )
+        .await
+        .unwrap();
+
+        let sent = node.send_draft(&did, &draft.id).await.unwrap();
+        assert_eq!(sent.status, DraftStatus::Sent);
+        let message_id = sent.message_id.clone().unwrap();
+        let storage = node.draft_storage(&did).await.unwrap();
+        assert!(storage
+            .get_message_by_id(&message_id)
+            .await
+            .unwrap()
+            .is_some());
+
+        // Sent drafts can be neither changed nor sent again
+        assert!(node.send_draft(&did, &draft.id).await.is_err());
+        assert!(node
+            .update_draft(&did, &draft.id, &json!({"memo": "late"}))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_expired_drafts_cannot_be_sent_and_are_purged() {
+        let clock = Arc::new(MockClock::new(
+            "2026-03-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap(),
+        ));
+        let (_temp_dir, node, did) = node_with_agent(clock.clone()).await;
+
+        let draft = node
+            .create_draft(
+                &did,
+                DraftKind::Payment,
+                json!({}),
+                Some(Duration::from_secs(3600)),
+            )
+            .await
+            .unwrap();
+        clock.advance(chrono::Duration::hours(2));
+        let error = node.send_draft(&did, &draft.id).await.unwrap_err();
+        assert!(error.to_string().contains("expired"));
+
+        node.create_draft(&did, DraftKind::Payment, json!({}), None)
+            .await
+            .unwrap();
+        assert!(node.get_draft(&did, &draft.id).await.is_err());
+        assert!(!node.delete_draft(&did, &draft.id).await.unwrap());
+    }
+}
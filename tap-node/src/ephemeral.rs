@@ -0,0 +1,232 @@
+//! Ephemeral agents
+//!
+//! Gateways that act for short-lived sessions can give each session its own
+//! identity without accumulating state. [`TapNode::register_ephemeral_agent`]
+//! registers an agent with a fresh in-memory key for one transaction or
+//! thread. No storage is set up for it at registration, and the agent is
+//! unregistered when the transaction settles or ends (it is rejected,
+//! cancelled, reverted or expires), or when its time to live runs out,
+//! whichever comes first. A database created for the agent while it handled
+//! messages is deleted when it is unregistered.
+
+use crate::agent::AgentRegistry;
+use crate::clock::Clock;
+use crate::error::{Error, Result};
+use crate::event::{EventBus, NodeEvent};
+#[cfg(feature = "state-machine")]
+use crate::state_machine::fsm::TransactionState;
+use crate::storage::AgentStorageManager;
+use crate::TapNode;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock, Weak};
+use std::time::Duration;
+use tap_agent::TapAgent;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, info, warn};
+
+/// How often agents are checked for an expired time to live
+const SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// An agent registered for one transaction or thread
+#[derive(Debug, Clone)]
+pub struct EphemeralAgent {
+    pub did: String,
+    /// Transaction or thread whose end unregisters the agent
+    pub thread_id: String,
+    /// When the agent is unregistered if the thread has not ended
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Ephemeral agents of a node, by DID
+#[derive(Default)]
+pub struct EphemeralAgents {
+    agents: DashMap<String, EphemeralAgent>,
+    /// Storage manager whose databases of released agents are deleted
+    storage_manager: RwLock<Weak<AgentStorageManager>>,
+    /// Whether the task unregistering agents is running
+    reaper_started: AtomicBool,
+}
+
+impl EphemeralAgents {
+    /// The registered ephemeral agents
+    pub fn list(&self) -> Vec<EphemeralAgent> {
+        self.agents
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
+    /// DIDs of the agents of a thread that the event ends
+    fn ended_by(&self, event: &NodeEvent) -> Vec<String> {
+        let thread_id = match event {
+            #[cfg(feature = "state-machine")]
+            NodeEvent::TransactionStateChanged {
+                transaction_id,
+                new_state,
+                ..
+            } => match new_state.parse::<TransactionState>() {
+                Ok(state) if state == TransactionState::Settled || state.is_terminal() => {
+                    transaction_id
+                }
+                _ => return Vec::new(),
+            },
+            NodeEvent::TransactionExpired { transaction_id, .. } => transaction_id,
+            _ => return Vec::new(),
+        };
+        self.agents
+            .iter()
+            .filter(|entry| &entry.thread_id == thread_id)
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
+    /// DIDs of the agents whose time to live has run out
+    fn expired(&self, now: DateTime<Utc>) -> Vec<String> {
+        self.agents
+            .iter()
+            .filter(|entry| entry.expires_at <= now)
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
+    /// Unregister an ephemeral agent and delete its storage
+    ///
+    /// Returns whether the agent was an ephemeral agent.
+    async fn release(
+        &self,
+        did: &str,
+        agents: &AgentRegistry,
+        event_bus: &EventBus,
+    ) -> Result<bool> {
+        if self.agents.remove(did).is_none() {
+            return Ok(false);
+        }
+        if agents.has_agent(did) {
+            agents.unregister_agent(did).await?;
+            event_bus.publish_agent_unregistered(did.to_string()).await;
+        }
+        let storage_manager = self
+            .storage_manager
+            .read()
+            .map(|manager| manager.upgrade())
+            .unwrap_or_default();
+        if let Some(storage_manager) = storage_manager {
+            storage_manager.delete_agent_storage(did).await?;
+        }
+        debug!("Released ephemeral agent {}", did);
+        Ok(true)
+    }
+
+    /// Start the task releasing agents, unless it is running
+    fn start_reaper(
+        self: &Arc<Self>,
+        agents: &Arc<AgentRegistry>,
+        event_bus: &Arc<EventBus>,
+        clock: Arc<dyn Clock>,
+    ) {
+        if self.reaper_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let mut events = event_bus.subscribe_channel();
+        let ephemeral = Arc::downgrade(self);
+        let agents = Arc::downgrade(agents);
+        let event_bus = Arc::downgrade(event_bus);
+        info!("Starting release of ephemeral agents");
+
+        tokio::spawn(async move {
+            let mut sweep = tokio::time::interval(SWEEP_INTERVAL);
+            loop {
+                let Some(current) = ephemeral.upgrade() else {
+                    break;
+                };
+                let released = tokio::select! {
+                    event = events.recv() => match event {
+                        Ok(event) => current.ended_by(&event),
+                        Err(RecvError::Lagged(skipped)) => {
+                            warn!("Release of ephemeral agents skipped {} events", skipped);
+                            continue;
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
+                    _ = sweep.tick() => current.expired(clock.now()),
+                };
+                if released.is_empty() {
+                    continue;
+                }
+
+                let (Some(agents), Some(event_bus)) = (agents.upgrade(), event_bus.upgrade())
+                else {
+                    debug!("Node dropped, stopping release of ephemeral agents");
+                    break;
+                };
+                for did in released {
+                    if let Err(e) = current.release(&did, &agents, &event_bus).await {
+                        warn!("Failed to release ephemeral agent {}: {}", did, e);
+                    }
+                }
+            }
+        });
+    }
+}
+
+impl TapNode {
+    /// Register an agent with a fresh in-memory key for one transaction or
+    /// thread
+    ///
+    /// The agent is unregistered when the transaction with ID `thread_id`
+    /// settles or ends, or after `ttl`, and its storage is deleted. See the
+    /// [module documentation](crate::ephemeral).
+    pub async fn register_ephemeral_agent(
+        &self,
+        thread_id: impl Into<String>,
+        ttl: Duration,
+    ) -> Result<Arc<TapAgent>> {
+        let ttl = chrono::Duration::from_std(ttl)
+            .map_err(|e| Error::Configuration(format!("Invalid time to live: {}", e)))?;
+        let (agent, did) = TapAgent::from_ephemeral_key()
+            .await
+            .map_err(|e| Error::Agent(e.to_string()))?;
+        let agent = Arc::new(agent);
+
+        if let Some(storage_manager) = &self.agent_storage_manager {
+            if let Ok(mut manager) = self.ephemeral_agents.storage_manager.write() {
+                *manager = Arc::downgrade(storage_manager);
+            }
+        }
+        self.ephemeral_agents
+            .start_reaper(&self.agents, &self.event_bus, self.clock());
+
+        self.agents
+            .register_agent(did.clone(), agent.clone())
+            .await?;
+        self.ephemeral_agents.agents.insert(
+            did.clone(),
+            EphemeralAgent {
+                did: did.clone(),
+                thread_id: thread_id.into(),
+                expires_at: self.clock().now() + ttl,
+            },
+        );
+        self.event_bus.publish_agent_registered(did).await;
+
+        Ok(agent)
+    }
+
+    /// Unregister an ephemeral agent before its thread ends, deleting its
+    /// storage
+    ///
+    /// Returns false if `did` is not an ephemeral agent.
+    pub async fn release_ephemeral_agent(&self, did: &str) -> Result<bool> {
+        self.ephemeral_agents
+            .release(did, &self.agents, &self.event_bus)
+            .await
+    }
+
+    /// The registered ephemeral agents
+    pub fn ephemeral_agents(&self) -> Vec<EphemeralAgent> {
+        self.ephemeral_agents.list()
+    }
+}
@@ -64,6 +64,21 @@ pub enum Error {
     /// Validation error
     #[error("Validation error: {0}")]
     Validation(String),
+
+    /// Script error
+    #[error("Script error: {0}")]
+    Script(String),
+
+    /// Outgoing message aborted by an enrichment hook
+    #[error("Enrichment aborted by {hook}: {reason}")]
+    EnrichmentAborted { hook: String, reason: String },
+
+    /// A stage of message processing exceeded its timeout
+    #[error("Message processing timed out during {stage} after {timeout_ms}ms")]
+    ProcessingTimedOut {
+        stage: crate::timeouts::ProcessingStage,
+        timeout_ms: u64,
+    },
 }
 
 /// Result type for TAP Node
@@ -5,10 +5,12 @@
 //! - Updates customer records from UpdateParty messages
 //! - Manages relationships from ConfirmRelationship messages
 //! - Generates IVMS101 data when needed
+//! - Links the customers of new transactions to their personal data sealed
+//!   under the transaction's data key, when the storage has an encryption key
 
 use crate::customer::CustomerManager;
 use crate::error::Result;
-use crate::event::{EventSubscriber, NodeEvent};
+use crate::event::{EventKind, EventSubscriber, NodeEvent};
 use crate::storage::Storage;
 use async_trait::async_trait;
 use serde_json::{json, Value};
@@ -63,7 +65,7 @@ impl EventSubscriber for CustomerEventHandler {
 
                         // Extract originator if present
                         if let Some(originator) = &transfer.originator {
-                            match manager
+                            let customer_id = match manager
                                 .extract_customer_from_party(
                                     originator,
                                     &self.agent_did,
@@ -75,15 +77,28 @@ impl EventSubscriber for CustomerEventHandler {
                                     log::debug!(
                                         "Created/updated originator customer: {}",
                                         customer_id
-                                    )
+                                    );
+                                    Some(customer_id)
                                 }
-                                Err(e) => log::error!("Failed to extract originator: {}", e),
+                                Err(e) => {
+                                    log::error!("Failed to extract originator: {}", e);
+                                    None
+                                }
+                            };
+                            if let Some(customer_id) = customer_id {
+                                self.link_party(
+                                    &manager,
+                                    &transaction.reference_id,
+                                    "originator",
+                                    &customer_id,
+                                )
+                                .await;
                             }
                         }
 
                         // Extract beneficiary
                         if let Some(beneficiary) = &transfer.beneficiary {
-                            match manager
+                            let customer_id = match manager
                                 .extract_customer_from_party(
                                     beneficiary,
                                     &self.agent_did,
@@ -91,11 +106,26 @@ impl EventSubscriber for CustomerEventHandler {
                                 )
                                 .await
                             {
-                                Ok(customer_id) => log::debug!(
-                                    "Created/updated beneficiary customer: {}",
-                                    customer_id
-                                ),
-                                Err(e) => log::error!("Failed to extract beneficiary: {}", e),
+                                Ok(customer_id) => {
+                                    log::debug!(
+                                        "Created/updated beneficiary customer: {}",
+                                        customer_id
+                                    );
+                                    Some(customer_id)
+                                }
+                                Err(e) => {
+                                    log::error!("Failed to extract beneficiary: {}", e);
+                                    None
+                                }
+                            };
+                            if let Some(customer_id) = customer_id {
+                                self.link_party(
+                                    &manager,
+                                    &transaction.reference_id,
+                                    "beneficiary",
+                                    &customer_id,
+                                )
+                                .await;
                             }
                         }
                     }
@@ -109,9 +139,42 @@ impl EventSubscriber for CustomerEventHandler {
             log::error!("Customer event handler error: {}", e);
         }
     }
+
+    fn event_kinds(&self) -> Option<&'static [EventKind]> {
+        Some(&[
+            EventKind::MessageReceived,
+            EventKind::MessageSent,
+            EventKind::TransactionCreated,
+        ])
+    }
 }
 
 impl CustomerEventHandler {
+    /// Link the customer of a party of a transaction to the party's personal
+    /// data sealed by the storage, if it has an encryption key
+    async fn link_party(
+        &self,
+        manager: &CustomerManager,
+        transaction_id: &str,
+        role: &str,
+        customer_id: &str,
+    ) {
+        if self.storage.message_encryption().is_none() {
+            return;
+        }
+        if let Err(e) = manager
+            .link_transaction_pii(customer_id, transaction_id, role)
+            .await
+        {
+            log::error!(
+                "Failed to link the {} of transaction {}: {}",
+                role,
+                transaction_id,
+                e
+            );
+        }
+    }
+
     async fn handle_transfer_message(
         &self,
         message: &tap_msg::didcomm::PlainMessage,
@@ -172,12 +235,15 @@ impl CustomerEventHandler {
                 .extract_customer_from_party(
                     &update_party.party,
                     &self.agent_did,
-                    &update_party.party_type,
+                    update_party.party_type.as_str(),
                 )
                 .await?;
 
-            // If the party has additional schema.org data, update the profile
-            if let Some(profile_data) = extract_schema_org_data(&update_party.party) {
+            // If the party has additional schema.org data, update the profile,
+            // unless the storage keeps personal data sealed
+            if let Some(profile_data) = extract_schema_org_data(&update_party.party)
+                .filter(|_| self.storage.message_encryption().is_none())
+            {
                 manager
                     .update_customer_profile(&customer_id, profile_data)
                     .await?;
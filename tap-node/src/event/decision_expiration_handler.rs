@@ -5,7 +5,7 @@
 //! Reverted). This prevents external decision processes from acting on stale
 //! decisions after a transaction has already been resolved.
 
-use super::{EventSubscriber, NodeEvent};
+use super::{EventKind, EventSubscriber, NodeEvent};
 use crate::state_machine::fsm::TransactionState;
 use crate::storage::Storage;
 use async_trait::async_trait;
@@ -64,6 +64,10 @@ impl EventSubscriber for DecisionExpirationHandler {
             }
         }
     }
+
+    fn event_kinds(&self) -> Option<&'static [EventKind]> {
+        Some(&[EventKind::TransactionStateChanged])
+    }
 }
 
 #[cfg(test)]
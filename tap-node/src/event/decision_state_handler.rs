@@ -1,12 +1,12 @@
 //! Decision state handler
 //!
 //! Listens for `TransactionStateChanged` events and manages decision lifecycle:
-//! - Expires pending/delivered decisions when a transaction reaches a terminal state
-//!   (Rejected, Cancelled, Reverted)
+//! - Expires pending/delivered decisions and pending review items when a transaction
+//!   reaches a terminal state (Rejected, Cancelled, Reverted)
 //! - Resolves pending/delivered decisions when the corresponding action is observed
 //!   (e.g., authorization_required decisions resolved when state becomes Authorized)
 
-use super::{EventSubscriber, NodeEvent};
+use super::{EventKind, EventSubscriber, NodeEvent};
 use crate::state_machine::fsm::TransactionState;
 use crate::storage::{DecisionType, Storage};
 use async_trait::async_trait;
@@ -65,6 +65,26 @@ impl EventSubscriber for DecisionStateHandler {
                         );
                     }
                 }
+                match self
+                    .storage
+                    .expire_review_items_for_transaction(&transaction_id)
+                    .await
+                {
+                    Ok(count) => {
+                        if count > 0 {
+                            debug!(
+                                "Expired {} review items for transaction {}",
+                                count, transaction_id
+                            );
+                        }
+                    }
+                    Err(e) => {
+                        error!(
+                            "Failed to expire review items for transaction {}: {}",
+                            transaction_id, e
+                        );
+                    }
+                }
             } else {
                 // Non-terminal state changes: resolve matching decisions
                 let resolution = match state {
@@ -106,6 +126,10 @@ impl EventSubscriber for DecisionStateHandler {
             }
         }
     }
+
+    fn event_kinds(&self) -> Option<&'static [EventKind]> {
+        Some(&[EventKind::TransactionStateChanged])
+    }
 }
 
 #[cfg(test)]
@@ -1,21 +1,30 @@
 //! Event handlers for updating message and transaction statuses
 //!
 //! This module provides event handlers that respond to message acceptance/rejection events
-//! and update the corresponding database records.
+//! and update the corresponding database records, and that retire the thread keys of
+//! ended transactions.
 
-use super::{EventSubscriber, NodeEvent};
-use crate::storage::Storage;
+use super::{EventKind, EventSubscriber, NodeEvent};
+#[cfg(feature = "state-machine")]
+use crate::agent::AgentRegistry;
+#[cfg(feature = "state-machine")]
+use crate::state_machine::fsm::TransactionState;
+#[cfg(feature = "storage")]
+use crate::storage::{Storage, TransactionStatus};
 use async_trait::async_trait;
+#[cfg(feature = "storage")]
 use std::sync::Arc;
 
 /// Event handler for updating message status in the database
 ///
 /// This handler listens for MessageAccepted and MessageRejected events
 /// and updates the corresponding message status in the database.
+#[cfg(feature = "storage")]
 pub struct MessageStatusHandler {
     storage: Arc<Storage>,
 }
 
+#[cfg(feature = "storage")]
 impl MessageStatusHandler {
     /// Create a new message status handler
     pub fn new(storage: Arc<Storage>) -> Self {
@@ -30,6 +39,7 @@ impl MessageStatusHandler {
     }
 }
 
+#[cfg(feature = "storage")]
 #[async_trait]
 impl EventSubscriber for MessageStatusHandler {
     async fn handle_event(&self, event: NodeEvent) {
@@ -43,16 +53,22 @@ impl EventSubscriber for MessageStatusHandler {
             _ => {} // Ignore other events
         }
     }
+
+    fn event_kinds(&self) -> Option<&'static [EventKind]> {
+        Some(&[EventKind::MessageAccepted, EventKind::MessageRejected])
+    }
 }
 
 /// Event handler for updating transaction state in the database
 ///
 /// This handler listens for TransactionStateChanged events
 /// and updates the corresponding transaction status in the database.
+#[cfg(feature = "storage")]
 pub struct TransactionStateHandler {
     storage: Arc<Storage>,
 }
 
+#[cfg(feature = "storage")]
 impl TransactionStateHandler {
     /// Create a new transaction state handler
     pub fn new(storage: Arc<Storage>) -> Self {
@@ -75,6 +91,21 @@ impl TransactionStateHandler {
     }
 }
 
+/// Stored transaction status for a transaction state
+///
+/// Intermediate FSM states such as `partially_authorized` keep the stored
+/// status unchanged.
+#[cfg(feature = "storage")]
+fn status_for_state(state: &str) -> Option<&str> {
+    match state {
+        "settled" => Some("confirmed"),
+        "rejected" => Some("failed"),
+        status if TransactionStatus::try_from(status).is_ok() => Some(status),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "storage")]
 #[async_trait]
 impl EventSubscriber for TransactionStateHandler {
     async fn handle_event(&self, event: NodeEvent) {
@@ -84,10 +115,16 @@ impl EventSubscriber for TransactionStateHandler {
             ..
         } = event
         {
-            self.update_transaction_status(&transaction_id, &new_state)
-                .await;
+            if let Some(status) = status_for_state(&new_state) {
+                self.update_transaction_status(&transaction_id, status)
+                    .await;
+            }
         }
     }
+
+    fn event_kinds(&self) -> Option<&'static [EventKind]> {
+        Some(&[EventKind::TransactionStateChanged])
+    }
 }
 
 /// Event handler for logging transaction state transitions
@@ -174,4 +211,80 @@ impl EventSubscriber for TransactionAuditHandler {
             _ => {} // Ignore other events
         }
     }
+
+    fn event_kinds(&self) -> Option<&'static [EventKind]> {
+        Some(&[
+            EventKind::TransactionStateChanged,
+            EventKind::MessageAccepted,
+            EventKind::MessageRejected,
+            EventKind::ReplyReceived,
+        ])
+    }
+}
+
+/// Event handler retiring the thread keys of ended transactions
+///
+/// When a transaction settles or ends (it is rejected, cancelled, reverted
+/// or expires), the node's agents drop the ephemeral key agreement keys they
+/// hold for its thread, so that its messages cannot be decrypted afterwards.
+#[cfg(feature = "state-machine")]
+pub struct ThreadKeyHandler {
+    agents: Arc<AgentRegistry>,
+}
+
+#[cfg(feature = "state-machine")]
+impl ThreadKeyHandler {
+    /// Create a new thread key handler for the agents of a registry
+    pub fn new(agents: Arc<AgentRegistry>) -> Self {
+        Self { agents }
+    }
+}
+
+#[cfg(feature = "state-machine")]
+#[async_trait]
+impl EventSubscriber for ThreadKeyHandler {
+    async fn handle_event(&self, event: NodeEvent) {
+        let transaction_id = match &event {
+            NodeEvent::TransactionStateChanged {
+                transaction_id,
+                new_state,
+                ..
+            } => match new_state.parse::<TransactionState>() {
+                Ok(state) if state == TransactionState::Settled || state.is_terminal() => {
+                    transaction_id
+                }
+                _ => return,
+            },
+            NodeEvent::TransactionExpired { transaction_id, .. } => transaction_id,
+            _ => return,
+        };
+
+        for did in self.agents.get_all_dids() {
+            let Ok(agent) = self.agents.get_agent(&did).await else {
+                continue;
+            };
+            match agent.key_manager().retire_thread_keys(transaction_id) {
+                Ok(0) => {}
+                Ok(retired) => log::debug!(
+                    "Retired {} thread keys of agent {} for transaction {}",
+                    retired,
+                    did,
+                    transaction_id
+                ),
+                Err(e) => log::warn!(
+                    "Failed to retire thread keys of agent {} for transaction {}: {}",
+                    did,
+                    transaction_id,
+                    e
+                ),
+            }
+        }
+    }
+
+    fn event_kinds(&self) -> Option<&'static [EventKind]> {
+        Some(&[
+            EventKind::TransactionStateChanged,
+            EventKind::TransactionExpired,
+        ])
+    }
 }
@@ -48,7 +48,7 @@ use std::time::SystemTime;
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use serde_json::json;
+use serde_json::{json, Value};
 use tracing::{debug, error, info, trace, warn};
 
 use crate::error::{Error, Result};
@@ -306,6 +306,8 @@ impl EventLogger {
                     timestamp, destination, message.type_, message.id
                 )
             }
+            #[cfg(feature = "storage")]
+            #[cfg(feature = "storage")]
             NodeEvent::TransactionCreated {
                 transaction,
                 agent_did,
@@ -339,6 +341,218 @@ impl EventLogger {
                     pending_agents.join(", ")
                 )
             }
+            NodeEvent::PolicyTriggered {
+                transaction_id,
+                rule,
+                action,
+                reason,
+                ..
+            } => {
+                format!(
+                    "[{}] POLICY TRIGGERED: tx={}, rule={}, action={}, reason={}",
+                    timestamp, transaction_id, rule, action, reason
+                )
+            }
+            NodeEvent::TravelRuleDataValidated {
+                message_id,
+                sender,
+                report,
+                ..
+            } => {
+                format!(
+                    "[{}] TRAVEL RULE DATA VALIDATED: message={}, sender={}, errors={}, warnings={}",
+                    timestamp,
+                    message_id,
+                    sender,
+                    report.errors().count(),
+                    report.warnings().count()
+                )
+            }
+            NodeEvent::TravelRulePolicyEvaluated {
+                message_id,
+                transaction_id,
+                evaluation,
+                ..
+            } => {
+                format!(
+                    "[{}] TRAVEL RULE POLICY EVALUATED: message={}, tx={}, satisfied={}, missing={:?}",
+                    timestamp,
+                    message_id,
+                    transaction_id,
+                    evaluation.satisfied,
+                    evaluation.missing.iter().map(|f| f.as_str()).collect::<Vec<_>>()
+                )
+            }
+            NodeEvent::TransactionExpired {
+                transaction_id,
+                agent_did,
+                expires_time,
+            } => {
+                format!(
+                    "[{}] TRANSACTION EXPIRED: tx={}, cancelled_by={}, expires_time={}",
+                    timestamp, transaction_id, agent_did, expires_time
+                )
+            }
+            NodeEvent::ProblemReportReceived {
+                message_id,
+                from,
+                code,
+                comment,
+                pthid,
+                ..
+            } => {
+                format!(
+                    "[{}] PROBLEM REPORT RECEIVED: id={}, from={}, code={}, thread={}, comment={}",
+                    timestamp,
+                    message_id,
+                    from,
+                    code,
+                    pthid.as_deref().unwrap_or("none"),
+                    comment.as_deref().unwrap_or("")
+                )
+            }
+            NodeEvent::MessageProcessingTimedOut {
+                message_id,
+                stage,
+                timeout_ms,
+                dead_lettered,
+            } => {
+                format!(
+                    "[{}] MESSAGE PROCESSING TIMED OUT: id={}, stage={}, timeout_ms={}, dead_lettered={}",
+                    timestamp,
+                    message_id.as_deref().unwrap_or("unknown"),
+                    stage,
+                    timeout_ms,
+                    dead_lettered
+                )
+            }
+            NodeEvent::StorageCorruptionDetected { db_path, problems } => {
+                format!(
+                    "[{}] STORAGE CORRUPTION DETECTED: db={}, problems={}",
+                    timestamp,
+                    db_path,
+                    problems.join("; ")
+                )
+            }
+            NodeEvent::StorageRecovered {
+                db_path,
+                strategy,
+                quarantined_path,
+                ..
+            } => {
+                format!(
+                    "[{}] STORAGE RECOVERED: db={}, strategy={}, quarantined={}",
+                    timestamp,
+                    db_path,
+                    strategy,
+                    quarantined_path.as_deref().unwrap_or("none")
+                )
+            }
+            NodeEvent::StorageRecoveryFailed { db_path, errors } => {
+                format!(
+                    "[{}] STORAGE RECOVERY FAILED: db={}, errors={}",
+                    timestamp,
+                    db_path,
+                    errors.join("; ")
+                )
+            }
+            NodeEvent::ClockDriftDetected {
+                server,
+                offset_ms,
+                max_offset_ms,
+            } => {
+                format!(
+                    "[{}] CLOCK DRIFT DETECTED: server={}, offset_ms={}, max_offset_ms={}",
+                    timestamp, server, offset_ms, max_offset_ms
+                )
+            }
+            NodeEvent::AnomalyDetected {
+                agent_did,
+                counterparty,
+                transaction_id,
+                kind,
+                reason,
+                ..
+            } => {
+                format!(
+                    "[{}] ANOMALY DETECTED: agent={}, counterparty={}, transaction={}, kind={}, reason={}",
+                    timestamp, agent_did, counterparty, transaction_id, kind, reason
+                )
+            }
+            NodeEvent::ReconciliationCompleted {
+                reprocessed_messages,
+                redelivered_messages,
+                requeued_deliveries,
+                resumed_settlements,
+                flagged,
+                ..
+            } => {
+                format!(
+                    "[{}] RECONCILIATION COMPLETED: reprocessed={}, redelivered={}, requeued={}, resumed_settlements={}, flagged={}",
+                    timestamp,
+                    reprocessed_messages,
+                    redelivered_messages,
+                    requeued_deliveries,
+                    resumed_settlements,
+                    flagged.as_array().map_or(0, Vec::len)
+                )
+            }
+            NodeEvent::SettlementOutOfTolerance {
+                transaction_id,
+                settled_asset,
+                settled_amount,
+                expected_amount,
+                slippage,
+                max_slippage,
+                ..
+            } => {
+                format!(
+                    "[{}] SETTLEMENT OUT OF TOLERANCE: transaction={}, settled={} {}, expected={}, slippage={:.4}, max_slippage={:.4}",
+                    timestamp,
+                    transaction_id,
+                    settled_amount,
+                    settled_asset,
+                    expected_amount,
+                    slippage,
+                    max_slippage
+                )
+            }
+            NodeEvent::AddressReuseDetected {
+                transaction_id,
+                address,
+                kind,
+                reason,
+                ..
+            } => {
+                format!(
+                    "[{}] ADDRESS REUSE DETECTED: transaction={}, address={}, kind={}, reason={}",
+                    timestamp, transaction_id, address, kind, reason
+                )
+            }
+            NodeEvent::ResponseDeadlineApproaching {
+                agent_did,
+                message_id,
+                message_type,
+                deadline_at,
+                ..
+            } => {
+                format!(
+                    "[{}] RESPONSE DEADLINE APPROACHING: agent={}, message={}, type={}, deadline={}",
+                    timestamp, agent_did, message_id, message_type, deadline_at
+                )
+            }
+            NodeEvent::ResponseDeadlineBreached {
+                agent_did,
+                message_id,
+                message_type,
+                deadline_at,
+                ..
+            } => {
+                format!(
+                    "[{}] RESPONSE DEADLINE BREACHED: agent={}, message={}, type={}, deadline={}",
+                    timestamp, agent_did, message_id, message_type, deadline_at
+                )
+            }
         }
     }
 
@@ -348,155 +562,7 @@ impl EventLogger {
         let timestamp = DateTime::<Utc>::from(SystemTime::now()).to_rfc3339();
 
         // Create event-specific fields
-        let (event_type, event_data) = match event {
-            NodeEvent::PlainMessageReceived { message } => (
-                "message_received",
-                json!({
-                    "message": message,
-                }),
-            ),
-            NodeEvent::PlainMessageSent { message, from, to } => (
-                "message_sent",
-                json!({
-                    "from": from,
-                    "to": to,
-                    "message": message,
-                }),
-            ),
-            NodeEvent::AgentRegistered { did } => (
-                "agent_registered",
-                json!({
-                    "did": did,
-                }),
-            ),
-            NodeEvent::AgentUnregistered { did } => (
-                "agent_unregistered",
-                json!({
-                    "did": did,
-                }),
-            ),
-            NodeEvent::DidResolved { did, success } => (
-                "did_resolved",
-                json!({
-                    "did": did,
-                    "success": success,
-                }),
-            ),
-            NodeEvent::AgentPlainMessage { did, message } => (
-                "agent_message",
-                json!({
-                    "did": did,
-                    "message_length": message.len(),
-                }),
-            ),
-            NodeEvent::MessageRejected {
-                message_id,
-                reason,
-                from,
-                to,
-            } => (
-                "message_rejected",
-                json!({
-                    "message_id": message_id,
-                    "reason": reason,
-                    "from": from,
-                    "to": to,
-                }),
-            ),
-            NodeEvent::MessageAccepted {
-                message_id,
-                message_type,
-                from,
-                to,
-            } => (
-                "message_accepted",
-                json!({
-                    "message_id": message_id,
-                    "message_type": message_type,
-                    "from": from,
-                    "to": to,
-                }),
-            ),
-            NodeEvent::ReplyReceived {
-                original_message_id,
-                reply_message,
-                original_message,
-            } => (
-                "reply_received",
-                json!({
-                    "original_message_id": original_message_id,
-                    "reply_message": serde_json::to_value(reply_message).unwrap_or(json!(null)),
-                    "original_message": serde_json::to_value(original_message).unwrap_or(json!(null)),
-                }),
-            ),
-            NodeEvent::TransactionStateChanged {
-                transaction_id,
-                old_state,
-                new_state,
-                agent_did,
-            } => (
-                "transaction_state_changed",
-                json!({
-                    "transaction_id": transaction_id,
-                    "old_state": old_state,
-                    "new_state": new_state,
-                    "agent_did": agent_did,
-                }),
-            ),
-            NodeEvent::MessageReceived { message, source } => (
-                "message_received_new",
-                json!({
-                    "message": serde_json::to_value(message).unwrap_or(json!(null)),
-                    "source": source,
-                }),
-            ),
-            NodeEvent::MessageSent {
-                message,
-                destination,
-            } => (
-                "message_sent_new",
-                json!({
-                    "message": serde_json::to_value(message).unwrap_or(json!(null)),
-                    "destination": destination,
-                }),
-            ),
-            NodeEvent::TransactionCreated {
-                transaction,
-                agent_did,
-            } => (
-                "transaction_created",
-                json!({
-                    "transaction_id": transaction.id,
-                    "agent_did": agent_did,
-                }),
-            ),
-            NodeEvent::CustomerUpdated {
-                customer_id,
-                agent_did,
-                update_type,
-            } => (
-                "customer_updated",
-                json!({
-                    "customer_id": customer_id,
-                    "agent_did": agent_did,
-                    "update_type": update_type,
-                }),
-            ),
-            NodeEvent::DecisionRequired {
-                transaction_id,
-                transaction_state,
-                decision,
-                pending_agents,
-            } => (
-                "decision_required",
-                json!({
-                    "transaction_id": transaction_id,
-                    "transaction_state": transaction_state,
-                    "decision": decision,
-                    "pending_agents": pending_agents,
-                }),
-            ),
-        };
+        let (event_type, event_data) = structured_event(event);
 
         // Combine into a single JSON object
         let log_entry = json!({
@@ -510,6 +576,407 @@ impl EventLogger {
     }
 }
 
+/// The type name and JSON fields of an event, as written in structured logs
+pub(crate) fn structured_event(event: &NodeEvent) -> (&'static str, Value) {
+    match event {
+        NodeEvent::PlainMessageReceived { message } => (
+            "message_received",
+            json!({
+                "message": message,
+            }),
+        ),
+        NodeEvent::PlainMessageSent { message, from, to } => (
+            "message_sent",
+            json!({
+                "from": from,
+                "to": to,
+                "message": message,
+            }),
+        ),
+        NodeEvent::AgentRegistered { did } => (
+            "agent_registered",
+            json!({
+                "did": did,
+            }),
+        ),
+        NodeEvent::AgentUnregistered { did } => (
+            "agent_unregistered",
+            json!({
+                "did": did,
+            }),
+        ),
+        NodeEvent::DidResolved { did, success } => (
+            "did_resolved",
+            json!({
+                "did": did,
+                "success": success,
+            }),
+        ),
+        NodeEvent::AgentPlainMessage { did, message } => (
+            "agent_message",
+            json!({
+                "did": did,
+                "message_length": message.len(),
+            }),
+        ),
+        NodeEvent::MessageRejected {
+            message_id,
+            reason,
+            from,
+            to,
+        } => (
+            "message_rejected",
+            json!({
+                "message_id": message_id,
+                "reason": reason,
+                "from": from,
+                "to": to,
+            }),
+        ),
+        NodeEvent::MessageAccepted {
+            message_id,
+            message_type,
+            from,
+            to,
+        } => (
+            "message_accepted",
+            json!({
+                "message_id": message_id,
+                "message_type": message_type,
+                "from": from,
+                "to": to,
+            }),
+        ),
+        NodeEvent::ReplyReceived {
+            original_message_id,
+            reply_message,
+            original_message,
+        } => (
+            "reply_received",
+            json!({
+                "original_message_id": original_message_id,
+                "reply_message": serde_json::to_value(reply_message).unwrap_or(json!(null)),
+                "original_message": serde_json::to_value(original_message).unwrap_or(json!(null)),
+            }),
+        ),
+        NodeEvent::TransactionStateChanged {
+            transaction_id,
+            old_state,
+            new_state,
+            agent_did,
+        } => (
+            "transaction_state_changed",
+            json!({
+                "transaction_id": transaction_id,
+                "old_state": old_state,
+                "new_state": new_state,
+                "agent_did": agent_did,
+            }),
+        ),
+        NodeEvent::MessageReceived { message, source } => (
+            "message_received_new",
+            json!({
+                "message": serde_json::to_value(message).unwrap_or(json!(null)),
+                "source": source,
+            }),
+        ),
+        NodeEvent::MessageSent {
+            message,
+            destination,
+        } => (
+            "message_sent_new",
+            json!({
+                "message": serde_json::to_value(message).unwrap_or(json!(null)),
+                "destination": destination,
+            }),
+        ),
+        #[cfg(feature = "storage")]
+        NodeEvent::TransactionCreated {
+            transaction,
+            agent_did,
+        } => (
+            "transaction_created",
+            json!({
+                "transaction_id": transaction.id,
+                "agent_did": agent_did,
+            }),
+        ),
+        NodeEvent::CustomerUpdated {
+            customer_id,
+            agent_did,
+            update_type,
+        } => (
+            "customer_updated",
+            json!({
+                "customer_id": customer_id,
+                "agent_did": agent_did,
+                "update_type": update_type,
+            }),
+        ),
+        NodeEvent::DecisionRequired {
+            transaction_id,
+            transaction_state,
+            decision,
+            pending_agents,
+        } => (
+            "decision_required",
+            json!({
+                "transaction_id": transaction_id,
+                "transaction_state": transaction_state,
+                "decision": decision,
+                "pending_agents": pending_agents,
+            }),
+        ),
+        NodeEvent::PolicyTriggered {
+            transaction_id,
+            rule,
+            action,
+            reason,
+            details,
+        } => (
+            "policy_triggered",
+            json!({
+                "transaction_id": transaction_id,
+                "rule": rule,
+                "action": action,
+                "reason": reason,
+                "details": details,
+            }),
+        ),
+        NodeEvent::TravelRuleDataValidated {
+            message_id,
+            sender,
+            role,
+            report,
+        } => (
+            "travel_rule_data_validated",
+            json!({
+                "message_id": message_id,
+                "sender": sender,
+                "role": role,
+                "valid": report.is_valid(),
+                "violations": report.violations,
+            }),
+        ),
+        NodeEvent::TravelRulePolicyEvaluated {
+            message_id,
+            transaction_id,
+            sender,
+            evaluation,
+        } => (
+            "travel_rule_policy_evaluated",
+            json!({
+                "message_id": message_id,
+                "transaction_id": transaction_id,
+                "sender": sender,
+                "about_party": evaluation.about_party,
+                "satisfied": evaluation.satisfied,
+                "missing": evaluation.missing,
+            }),
+        ),
+        NodeEvent::TransactionExpired {
+            transaction_id,
+            agent_did,
+            expires_time,
+        } => (
+            "transaction_expired",
+            json!({
+                "transaction_id": transaction_id,
+                "agent_did": agent_did,
+                "expires_time": expires_time,
+            }),
+        ),
+        NodeEvent::ProblemReportReceived {
+            message_id,
+            from,
+            code,
+            comment,
+            pthid,
+            escalate_to,
+        } => (
+            "problem_report_received",
+            json!({
+                "message_id": message_id,
+                "from": from,
+                "code": code,
+                "comment": comment,
+                "pthid": pthid,
+                "escalate_to": escalate_to,
+            }),
+        ),
+        NodeEvent::MessageProcessingTimedOut {
+            message_id,
+            stage,
+            timeout_ms,
+            dead_lettered,
+        } => (
+            "message_processing_timed_out",
+            json!({
+                "message_id": message_id,
+                "stage": stage,
+                "timeout_ms": timeout_ms,
+                "dead_lettered": dead_lettered,
+            }),
+        ),
+        NodeEvent::StorageCorruptionDetected { db_path, problems } => (
+            "storage_corruption_detected",
+            json!({
+                "db_path": db_path,
+                "problems": problems,
+            }),
+        ),
+        NodeEvent::StorageRecovered {
+            db_path,
+            strategy,
+            quarantined_path,
+            restored_from,
+            salvaged_messages,
+            rebuilt_transactions,
+        } => (
+            "storage_recovered",
+            json!({
+                "db_path": db_path,
+                "strategy": strategy,
+                "quarantined_path": quarantined_path,
+                "restored_from": restored_from,
+                "salvaged_messages": salvaged_messages,
+                "rebuilt_transactions": rebuilt_transactions,
+            }),
+        ),
+        NodeEvent::StorageRecoveryFailed { db_path, errors } => (
+            "storage_recovery_failed",
+            json!({
+                "db_path": db_path,
+                "errors": errors,
+            }),
+        ),
+        NodeEvent::ClockDriftDetected {
+            server,
+            offset_ms,
+            max_offset_ms,
+        } => (
+            "clock_drift_detected",
+            json!({
+                "server": server,
+                "offset_ms": offset_ms,
+                "max_offset_ms": max_offset_ms,
+            }),
+        ),
+        NodeEvent::AnomalyDetected {
+            agent_did,
+            counterparty,
+            transaction_id,
+            kind,
+            reason,
+            details,
+        } => (
+            "anomaly_detected",
+            json!({
+                "agent_did": agent_did,
+                "counterparty": counterparty,
+                "transaction_id": transaction_id,
+                "kind": kind,
+                "reason": reason,
+                "details": details,
+            }),
+        ),
+        NodeEvent::ReconciliationCompleted {
+            reprocessed_messages,
+            redelivered_messages,
+            requeued_deliveries,
+            resumed_settlements,
+            expired_decisions,
+            expired_challenges,
+            flagged,
+        } => (
+            "reconciliation_completed",
+            json!({
+                "reprocessed_messages": reprocessed_messages,
+                "redelivered_messages": redelivered_messages,
+                "requeued_deliveries": requeued_deliveries,
+                "resumed_settlements": resumed_settlements,
+                "expired_decisions": expired_decisions,
+                "expired_challenges": expired_challenges,
+                "flagged": flagged,
+            }),
+        ),
+        NodeEvent::SettlementOutOfTolerance {
+            transaction_id,
+            requested_asset,
+            requested_amount,
+            settled_asset,
+            settled_amount,
+            expected_amount,
+            slippage,
+            max_slippage,
+        } => (
+            "settlement_out_of_tolerance",
+            json!({
+                "transaction_id": transaction_id,
+                "requested_asset": requested_asset,
+                "requested_amount": requested_amount,
+                "settled_asset": settled_asset,
+                "settled_amount": settled_amount,
+                "expected_amount": expected_amount,
+                "slippage": slippage,
+                "max_slippage": max_slippage,
+            }),
+        ),
+        NodeEvent::AddressReuseDetected {
+            transaction_id,
+            address,
+            kind,
+            reason,
+            details,
+        } => (
+            "address_reuse_detected",
+            json!({
+                "transaction_id": transaction_id,
+                "address": address,
+                "kind": kind,
+                "reason": reason,
+                "details": details,
+            }),
+        ),
+        NodeEvent::ResponseDeadlineApproaching {
+            agent_did,
+            counterparty,
+            message_id,
+            message_type,
+            thread_id,
+            deadline_at,
+        } => (
+            "response_deadline_approaching",
+            json!({
+                "agent_did": agent_did,
+                "counterparty": counterparty,
+                "message_id": message_id,
+                "message_type": message_type,
+                "thread_id": thread_id,
+                "deadline_at": deadline_at,
+            }),
+        ),
+        NodeEvent::ResponseDeadlineBreached {
+            agent_did,
+            counterparty,
+            message_id,
+            message_type,
+            thread_id,
+            deadline_at,
+        } => (
+            "response_deadline_breached",
+            json!({
+                "agent_did": agent_did,
+                "counterparty": counterparty,
+                "message_id": message_id,
+                "message_type": message_type,
+                "thread_id": thread_id,
+                "deadline_at": deadline_at,
+            }),
+        ),
+    }
+}
+
 #[async_trait]
 impl EventSubscriber for EventLogger {
     async fn handle_event(&self, event: NodeEvent) {
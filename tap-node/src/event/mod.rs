@@ -22,6 +22,13 @@
 //! 1. **Callback-based**: Implementing the `EventSubscriber` trait to receive events via callbacks
 //! 2. **Channel-based**: Using `tokio::sync::broadcast` channels to receive events asynchronously
 //!
+//! Callback subscribers declare the event kinds they handle and are only
+//! dispatched matching events. A subscriber registered with
+//! `DeliveryPolicy::Isolated` runs on its own task behind a bounded queue, so
+//! a slow handler drops its own events instead of stalling publication.
+//! `EventBus::metrics` reports publication counts, channel backlog, and
+//! per-subscriber delivery and drop counts.
+//!
 //! ## Built-in Event Handlers
 //!
 //! The event system includes several built-in event handlers:
@@ -76,7 +83,7 @@
 //!     // Process events in a separate task
 //!     spawn(async move {
 //!         while let Ok(event) = receiver.recv().await {
-//!             match event {
+//!             match event.as_ref() {
 //!                 NodeEvent::PlainMessageSent { message, from, to } => {
 //!                     println!("PlainMessage sent from {} to {}", from, to);
 //!                 },
@@ -122,23 +129,29 @@
 //! by appropriate synchronization primitives. The `EventBus` can be safely shared
 //! across threads using `Arc<EventBus>`.
 
-#[cfg(feature = "storage")]
+#[cfg(feature = "customers")]
 pub mod customer_handler;
-#[cfg(feature = "storage")]
+#[cfg(feature = "state-machine")]
 pub mod decision_expiration_handler;
-#[cfg(feature = "storage")]
+#[cfg(feature = "state-machine")]
 pub mod decision_log_handler;
-#[cfg(feature = "storage")]
+#[cfg(feature = "state-machine")]
 pub mod decision_state_handler;
 pub mod handlers;
 pub mod logger;
 pub mod trust_ping_handler;
+#[cfg(feature = "native")]
+pub mod webhook;
 
 use async_trait::async_trait;
+use serde::Serialize;
 use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tap_msg::didcomm::PlainMessage;
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tracing::warn;
 
 /// Event types that can be emitted by the TAP Node
 ///
@@ -433,6 +446,7 @@ pub enum NodeEvent {
     },
 
     /// A new transaction was created
+    #[cfg(feature = "storage")]
     TransactionCreated {
         /// The transaction data
         transaction: crate::storage::Transaction,
@@ -473,6 +487,565 @@ pub enum NodeEvent {
         /// DIDs of agents that still need to act
         pending_agents: Vec<String>,
     },
+
+    /// A policy rule tripped while authorizing a transaction
+    ///
+    /// Published once per tripped rule by the policy engine, e.g. when a
+    /// velocity limit is exceeded.
+    ///
+    /// # Parameters
+    ///
+    /// - `transaction_id`: The transaction being authorized
+    /// - `rule`: Name of the rule that tripped
+    /// - `action`: The action requested by the rule (`reject`, `manual_review`, `require_presentation`, `warn`)
+    /// - `reason`: Human-readable explanation
+    /// - `details`: Rule-specific details such as thresholds and observed values
+    PolicyTriggered {
+        /// The transaction being authorized
+        transaction_id: String,
+        /// Name of the rule that tripped
+        rule: String,
+        /// The action requested by the rule
+        action: String,
+        /// Human-readable explanation
+        reason: String,
+        /// Rule-specific details
+        details: Value,
+    },
+
+    /// IVMS101 data received in a presentation was validated
+    ///
+    /// Published by the `TravelRuleProcessor` for every IVMS101 payload it
+    /// receives, whether valid or not, so UIs can show all problems at once.
+    ///
+    /// # Parameters
+    ///
+    /// - `message_id`: The presentation message carrying the data
+    /// - `sender`: DID of the sender of the presentation
+    /// - `role`: The party the data describes (`originator` or `beneficiary`), if stated
+    /// - `report`: Every violation found, located by JSON Pointer into the payload
+    TravelRuleDataValidated {
+        /// The presentation message carrying the data
+        message_id: String,
+        /// DID of the sender of the presentation
+        sender: String,
+        /// The party the data describes
+        role: Option<String>,
+        /// The validation report
+        report: tap_ivms101::ValidationReport,
+    },
+
+    /// A received presentation was evaluated against the IVMS101 data our
+    /// policies declared for its transaction
+    ///
+    /// Published by the `TravelRuleProcessor` for each declared requirement
+    /// when a presentation arrives in the transaction.
+    ///
+    /// # Parameters
+    ///
+    /// - `message_id`: The presentation message
+    /// - `transaction_id`: The transaction the policy was declared for
+    /// - `sender`: DID of the sender of the presentation
+    /// - `evaluation`: Whether the requirement is satisfied, and the missing elements
+    TravelRulePolicyEvaluated {
+        /// The presentation message
+        message_id: String,
+        /// The transaction the policy was declared for
+        transaction_id: String,
+        /// DID of the sender of the presentation
+        sender: String,
+        /// The outcome of the evaluation
+        evaluation: tap_msg::message::PolicyEvaluation,
+    },
+
+    /// A transaction was cancelled because its expiry passed
+    ///
+    /// Published by the transaction expiry sweeper after one of our agents
+    /// sent a Cancel for a transaction that was still pending past its
+    /// `expires_time` and grace period.
+    ///
+    /// # Parameters
+    ///
+    /// - `transaction_id`: The expired transaction
+    /// - `agent_did`: DID of our agent that cancelled it
+    /// - `expires_time`: Unix time in seconds the transaction expired
+    TransactionExpired {
+        /// The expired transaction
+        transaction_id: String,
+        /// DID of our agent that cancelled it
+        agent_did: String,
+        /// Unix time in seconds the transaction expired
+        expires_time: u64,
+    },
+
+    /// A counterparty reported a problem with one of our messages
+    ///
+    /// Published when a DIDComm problem report is received. The report's
+    /// `pthid` names the thread of the message it concerns, which for TAP
+    /// messages is the transaction ID.
+    ///
+    /// # Parameters
+    ///
+    /// - `message_id`: ID of the problem report
+    /// - `from`: DID of the reporting agent
+    /// - `code`: Problem code, e.g. `e.p.xfer.cant-use-endpoint`
+    /// - `comment`: Description with its arguments interpolated
+    /// - `pthid`: Thread of the problematic message
+    /// - `escalate_to`: Where a human can be contacted about the problem
+    ProblemReportReceived {
+        /// ID of the problem report
+        message_id: String,
+        /// DID of the reporting agent
+        from: String,
+        /// Problem code
+        code: String,
+        /// Description with its arguments interpolated
+        comment: Option<String>,
+        /// Thread of the problematic message
+        pthid: Option<String>,
+        /// Where a human can be contacted about the problem
+        escalate_to: Option<String>,
+    },
+
+    /// A stage of message processing exceeded its timeout and was cancelled
+    ///
+    /// Published when verification, validation or an enrichment hook takes
+    /// longer than its budget in
+    /// [`NodeConfig::processing_timeouts`](crate::NodeConfig::processing_timeouts).
+    ///
+    /// # Parameters
+    ///
+    /// - `message_id`: ID of the message, if known before the timeout
+    /// - `stage`: The stage that timed out
+    /// - `timeout_ms`: The budget of the stage in milliseconds
+    /// - `dead_lettered`: Whether the message was kept in the dead letters
+    MessageProcessingTimedOut {
+        /// ID of the message, if known before the timeout
+        message_id: Option<String>,
+        /// The stage that timed out
+        stage: crate::timeouts::ProcessingStage,
+        /// The budget of the stage in milliseconds
+        timeout_ms: u64,
+        /// Whether the message was kept in the dead letters
+        dead_lettered: bool,
+    },
+
+    /// A database failed its integrity check when it was opened
+    ///
+    /// Followed by [`NodeEvent::StorageRecovered`] or
+    /// [`NodeEvent::StorageRecoveryFailed`], see
+    /// [`NodeConfig::storage_recovery`](crate::NodeConfig::storage_recovery).
+    ///
+    /// # Parameters
+    ///
+    /// - `db_path`: Path of the corrupt database
+    /// - `problems`: Problems reported by SQLite
+    StorageCorruptionDetected {
+        /// Path of the corrupt database
+        db_path: String,
+        /// Problems reported by SQLite
+        problems: Vec<String>,
+    },
+
+    /// A corrupt database was replaced by a usable one
+    ///
+    /// # Parameters
+    ///
+    /// - `db_path`: Path of the database
+    /// - `strategy`: The recovery strategy that succeeded, e.g. `restore_snapshot`
+    /// - `quarantined_path`: Where the corrupt database was moved
+    /// - `restored_from`: The snapshot the database was restored from
+    /// - `salvaged_messages`: Messages copied from the corrupt database
+    /// - `rebuilt_transactions`: Transactions rebuilt from those messages
+    StorageRecovered {
+        /// Path of the database
+        db_path: String,
+        /// The recovery strategy that succeeded
+        strategy: String,
+        /// Where the corrupt database was moved
+        quarantined_path: Option<String>,
+        /// The snapshot the database was restored from
+        restored_from: Option<String>,
+        /// Messages copied from the corrupt database
+        salvaged_messages: u64,
+        /// Transactions rebuilt from those messages
+        rebuilt_transactions: u64,
+    },
+
+    /// No recovery strategy could replace a corrupt database, which is left
+    /// unused
+    ///
+    /// # Parameters
+    ///
+    /// - `db_path`: Path of the database
+    /// - `errors`: Why each configured strategy failed
+    StorageRecoveryFailed {
+        /// Path of the database
+        db_path: String,
+        /// Why each configured strategy failed
+        errors: Vec<String>,
+    },
+
+    /// The local clock drifted from NTP time past the tolerance of the
+    /// node's [`ClockHealthPolicy`](crate::clock_health::ClockHealthPolicy)
+    ///
+    /// Incoming messages may be rejected as coming from the future, or as
+    /// expired, until the clock is corrected.
+    ///
+    /// # Parameters
+    ///
+    /// - `server`: The NTP server the clock was compared with
+    /// - `offset_ms`: Time to add to the local clock to match the server
+    /// - `max_offset_ms`: Tolerated offset
+    ClockDriftDetected {
+        /// The NTP server the clock was compared with
+        server: String,
+        /// Time to add to the local clock to match the server, in milliseconds
+        offset_ms: i64,
+        /// Tolerated offset, in milliseconds
+        max_offset_ms: u64,
+    },
+
+    /// A transaction from a counterparty departs from its usual activity
+    ///
+    /// Published by the [`AnomalyDetector`](crate::anomaly::AnomalyDetector)
+    /// for downstream alerting; the transaction is processed as usual.
+    ///
+    /// # Parameters
+    ///
+    /// - `agent_did`: The agent that received the transaction
+    /// - `counterparty`: DID of the sender of the transaction
+    /// - `transaction_id`: The transaction
+    /// - `kind`: The anomaly (`transaction_spike`, `unusual_hour`, `new_asset`, `unusual_amount`)
+    /// - `reason`: Human-readable explanation
+    /// - `details`: Observed values and the baseline they were compared with
+    AnomalyDetected {
+        /// The agent that received the transaction
+        agent_did: String,
+        /// DID of the sender of the transaction
+        counterparty: String,
+        /// The transaction
+        transaction_id: String,
+        /// The anomaly
+        kind: String,
+        /// Human-readable explanation
+        reason: String,
+        /// Observed values and baseline
+        details: Value,
+    },
+
+    /// A reconciliation pass over processing interrupted by a stop of the
+    /// node finished
+    ///
+    /// Published by [`TapNode::reconcile`](crate::TapNode::reconcile), which
+    /// [`TapNode::init_storage`](crate::TapNode::init_storage) runs at
+    /// startup, also when nothing needed reconciling.
+    ///
+    /// # Parameters
+    ///
+    /// - `reprocessed_messages`: Received messages processed again
+    /// - `redelivered_messages`: Deliveries to our agents made again
+    /// - `requeued_deliveries`: Interrupted external deliveries marked failed
+    /// - `resumed_settlements`: Authorized transactions whose settlement resumed
+    /// - `expired_decisions`: Open decisions of ended transactions expired
+    /// - `expired_challenges`: Authorization challenges past their expiry expired
+    /// - `flagged`: Records left for an operator, with the agent, record and reason
+    ReconciliationCompleted {
+        /// Received messages processed again
+        reprocessed_messages: u64,
+        /// Deliveries to our agents made again
+        redelivered_messages: u64,
+        /// Interrupted external deliveries marked failed
+        requeued_deliveries: u64,
+        /// Authorized transactions whose settlement resumed
+        resumed_settlements: u64,
+        /// Open decisions of ended transactions expired
+        expired_decisions: u64,
+        /// Authorization challenges past their expiry expired
+        expired_challenges: u64,
+        /// Records left for an operator
+        flagged: Value,
+    },
+
+    /// A transaction settled in another asset than it requested, with an
+    /// amount that slipped from the rate more than tolerated
+    ///
+    /// Published when a Settle is processed with
+    /// [`NodeConfig::settlement_conversion`](crate::NodeConfig::settlement_conversion)
+    /// set. The transaction also gets a `settlement_slippage` warning.
+    ///
+    /// # Parameters
+    ///
+    /// - `transaction_id`: The transaction
+    /// - `requested_asset`: Asset or currency the transaction requested
+    /// - `requested_amount`: Amount the transaction requested
+    /// - `settled_asset`: Asset or currency the transaction settled in
+    /// - `settled_amount`: Amount settled, in the settled asset
+    /// - `expected_amount`: Amount of the settled asset the rate gives
+    /// - `slippage`: Deviation of the settled amount, as a fraction of the expected amount
+    /// - `max_slippage`: Tolerated slippage
+    SettlementOutOfTolerance {
+        /// The transaction
+        transaction_id: String,
+        /// Asset or currency the transaction requested
+        requested_asset: String,
+        /// Amount the transaction requested
+        requested_amount: String,
+        /// Asset or currency the transaction settled in
+        settled_asset: String,
+        /// Amount settled, in the settled asset
+        settled_amount: String,
+        /// Amount of the settled asset the rate gives
+        expected_amount: f64,
+        /// Deviation of the settled amount, as a fraction of the expected amount
+        slippage: f64,
+        /// Tolerated slippage
+        max_slippage: f64,
+    },
+
+    /// A transaction uses a settlement address seen in other transactions
+    /// with another agent, beneficiary or too many originators
+    ///
+    /// Published when an Authorize or Payment is processed with
+    /// [`NodeConfig::address_reuse`](crate::NodeConfig::address_reuse) set.
+    /// The transaction also gets an `address_reuse` warning.
+    ///
+    /// # Parameters
+    ///
+    /// - `transaction_id`: The transaction
+    /// - `address`: The settlement address, normalized
+    /// - `kind`: The reuse (`shared_address`, `many_originators`)
+    /// - `reason`: Human-readable explanation
+    /// - `details`: The earlier transactions, agents, beneficiaries or originators of the address
+    AddressReuseDetected {
+        /// The transaction
+        transaction_id: String,
+        /// The settlement address, normalized
+        address: String,
+        /// The reuse (`shared_address`, `many_originators`)
+        kind: String,
+        /// Human-readable explanation
+        reason: String,
+        /// The earlier transactions, agents, beneficiaries or originators of the address
+        details: Value,
+    },
+
+    /// One of our agents has not yet answered a message whose response
+    /// deadline is approaching
+    ///
+    /// Published by the
+    /// [`ResponseTimerMonitor`](crate::response_timers::ResponseTimerMonitor)
+    /// once per message, when the warning time of its rule before the
+    /// deadline is reached.
+    ///
+    /// # Parameters
+    ///
+    /// - `agent_did`: Our agent expected to answer
+    /// - `counterparty`: DID of the sender of the message
+    /// - `message_id`: The message awaiting an answer
+    /// - `message_type`: Short name of its type, e.g. `Transfer`
+    /// - `thread_id`: Thread of the message
+    /// - `deadline_at`: Time the answer is due (RFC 3339)
+    ResponseDeadlineApproaching {
+        /// Our agent expected to answer
+        agent_did: String,
+        /// DID of the sender of the message
+        counterparty: Option<String>,
+        /// The message awaiting an answer
+        message_id: String,
+        /// Short name of its type
+        message_type: String,
+        /// Thread of the message
+        thread_id: String,
+        /// Time the answer is due (RFC 3339)
+        deadline_at: String,
+    },
+
+    /// One of our agents did not answer a message by its response deadline
+    ///
+    /// Published by the
+    /// [`ResponseTimerMonitor`](crate::response_timers::ResponseTimerMonitor)
+    /// once per message. The timer keeps running until the message is
+    /// answered, so its response time is still reported.
+    ///
+    /// # Parameters
+    ///
+    /// - `agent_did`: Our agent expected to answer
+    /// - `counterparty`: DID of the sender of the message
+    /// - `message_id`: The unanswered message
+    /// - `message_type`: Short name of its type, e.g. `Transfer`
+    /// - `thread_id`: Thread of the message
+    /// - `deadline_at`: Time the answer was due (RFC 3339)
+    ResponseDeadlineBreached {
+        /// Our agent expected to answer
+        agent_did: String,
+        /// DID of the sender of the message
+        counterparty: Option<String>,
+        /// The unanswered message
+        message_id: String,
+        /// Short name of its type
+        message_type: String,
+        /// Thread of the message
+        thread_id: String,
+        /// Time the answer was due (RFC 3339)
+        deadline_at: String,
+    },
+}
+
+/// The kind of a [`NodeEvent`], without its payload
+///
+/// Subscribers declare the kinds they handle through
+/// [`EventSubscriber::event_kinds`] so the `EventBus` only dispatches
+/// matching events to them. Each variant mirrors the `NodeEvent` variant
+/// of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    PlainMessageReceived,
+    PlainMessageSent,
+    AgentRegistered,
+    AgentUnregistered,
+    DidResolved,
+    AgentPlainMessage,
+    MessageRejected,
+    MessageAccepted,
+    ReplyReceived,
+    TransactionStateChanged,
+    MessageReceived,
+    MessageSent,
+    TransactionCreated,
+    CustomerUpdated,
+    DecisionRequired,
+    PolicyTriggered,
+    TravelRuleDataValidated,
+    TravelRulePolicyEvaluated,
+    TransactionExpired,
+    ProblemReportReceived,
+    MessageProcessingTimedOut,
+    StorageCorruptionDetected,
+    StorageRecovered,
+    StorageRecoveryFailed,
+    ClockDriftDetected,
+    AnomalyDetected,
+    ReconciliationCompleted,
+    SettlementOutOfTolerance,
+    AddressReuseDetected,
+    ResponseDeadlineApproaching,
+    ResponseDeadlineBreached,
+}
+
+impl EventKind {
+    /// All event kinds
+    pub const ALL: [EventKind; 31] = [
+        EventKind::PlainMessageReceived,
+        EventKind::PlainMessageSent,
+        EventKind::AgentRegistered,
+        EventKind::AgentUnregistered,
+        EventKind::DidResolved,
+        EventKind::AgentPlainMessage,
+        EventKind::MessageRejected,
+        EventKind::MessageAccepted,
+        EventKind::ReplyReceived,
+        EventKind::TransactionStateChanged,
+        EventKind::MessageReceived,
+        EventKind::MessageSent,
+        EventKind::TransactionCreated,
+        EventKind::CustomerUpdated,
+        EventKind::DecisionRequired,
+        EventKind::PolicyTriggered,
+        EventKind::TravelRuleDataValidated,
+        EventKind::TravelRulePolicyEvaluated,
+        EventKind::TransactionExpired,
+        EventKind::ProblemReportReceived,
+        EventKind::MessageProcessingTimedOut,
+        EventKind::StorageCorruptionDetected,
+        EventKind::StorageRecovered,
+        EventKind::StorageRecoveryFailed,
+        EventKind::ClockDriftDetected,
+        EventKind::AnomalyDetected,
+        EventKind::ReconciliationCompleted,
+        EventKind::SettlementOutOfTolerance,
+        EventKind::AddressReuseDetected,
+        EventKind::ResponseDeadlineApproaching,
+        EventKind::ResponseDeadlineBreached,
+    ];
+
+    /// Snake-case name of the kind, e.g. `transaction_state_changed`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::PlainMessageReceived => "plain_message_received",
+            EventKind::PlainMessageSent => "plain_message_sent",
+            EventKind::AgentRegistered => "agent_registered",
+            EventKind::AgentUnregistered => "agent_unregistered",
+            EventKind::DidResolved => "did_resolved",
+            EventKind::AgentPlainMessage => "agent_plain_message",
+            EventKind::MessageRejected => "message_rejected",
+            EventKind::MessageAccepted => "message_accepted",
+            EventKind::ReplyReceived => "reply_received",
+            EventKind::TransactionStateChanged => "transaction_state_changed",
+            EventKind::MessageReceived => "message_received",
+            EventKind::MessageSent => "message_sent",
+            EventKind::TransactionCreated => "transaction_created",
+            EventKind::CustomerUpdated => "customer_updated",
+            EventKind::DecisionRequired => "decision_required",
+            EventKind::PolicyTriggered => "policy_triggered",
+            EventKind::TravelRuleDataValidated => "travel_rule_data_validated",
+            EventKind::TravelRulePolicyEvaluated => "travel_rule_policy_evaluated",
+            EventKind::TransactionExpired => "transaction_expired",
+            EventKind::ProblemReportReceived => "problem_report_received",
+            EventKind::MessageProcessingTimedOut => "message_processing_timed_out",
+            EventKind::StorageCorruptionDetected => "storage_corruption_detected",
+            EventKind::StorageRecovered => "storage_recovered",
+            EventKind::StorageRecoveryFailed => "storage_recovery_failed",
+            EventKind::ClockDriftDetected => "clock_drift_detected",
+            EventKind::AnomalyDetected => "anomaly_detected",
+            EventKind::ReconciliationCompleted => "reconciliation_completed",
+            EventKind::SettlementOutOfTolerance => "settlement_out_of_tolerance",
+            EventKind::AddressReuseDetected => "address_reuse_detected",
+            EventKind::ResponseDeadlineApproaching => "response_deadline_approaching",
+            EventKind::ResponseDeadlineBreached => "response_deadline_breached",
+        }
+    }
+}
+
+impl NodeEvent {
+    /// The kind of this event
+    pub fn kind(&self) -> EventKind {
+        match self {
+            NodeEvent::PlainMessageReceived { .. } => EventKind::PlainMessageReceived,
+            NodeEvent::PlainMessageSent { .. } => EventKind::PlainMessageSent,
+            NodeEvent::AgentRegistered { .. } => EventKind::AgentRegistered,
+            NodeEvent::AgentUnregistered { .. } => EventKind::AgentUnregistered,
+            NodeEvent::DidResolved { .. } => EventKind::DidResolved,
+            NodeEvent::AgentPlainMessage { .. } => EventKind::AgentPlainMessage,
+            NodeEvent::MessageRejected { .. } => EventKind::MessageRejected,
+            NodeEvent::MessageAccepted { .. } => EventKind::MessageAccepted,
+            NodeEvent::ReplyReceived { .. } => EventKind::ReplyReceived,
+            NodeEvent::TransactionStateChanged { .. } => EventKind::TransactionStateChanged,
+            NodeEvent::MessageReceived { .. } => EventKind::MessageReceived,
+            NodeEvent::MessageSent { .. } => EventKind::MessageSent,
+            #[cfg(feature = "storage")]
+            NodeEvent::TransactionCreated { .. } => EventKind::TransactionCreated,
+            NodeEvent::CustomerUpdated { .. } => EventKind::CustomerUpdated,
+            NodeEvent::DecisionRequired { .. } => EventKind::DecisionRequired,
+            NodeEvent::PolicyTriggered { .. } => EventKind::PolicyTriggered,
+            NodeEvent::TravelRuleDataValidated { .. } => EventKind::TravelRuleDataValidated,
+            NodeEvent::TravelRulePolicyEvaluated { .. } => EventKind::TravelRulePolicyEvaluated,
+            NodeEvent::TransactionExpired { .. } => EventKind::TransactionExpired,
+            NodeEvent::ProblemReportReceived { .. } => EventKind::ProblemReportReceived,
+            NodeEvent::MessageProcessingTimedOut { .. } => EventKind::MessageProcessingTimedOut,
+            NodeEvent::StorageCorruptionDetected { .. } => EventKind::StorageCorruptionDetected,
+            NodeEvent::StorageRecovered { .. } => EventKind::StorageRecovered,
+            NodeEvent::StorageRecoveryFailed { .. } => EventKind::StorageRecoveryFailed,
+            NodeEvent::ClockDriftDetected { .. } => EventKind::ClockDriftDetected,
+            NodeEvent::AnomalyDetected { .. } => EventKind::AnomalyDetected,
+            NodeEvent::ReconciliationCompleted { .. } => EventKind::ReconciliationCompleted,
+            NodeEvent::SettlementOutOfTolerance { .. } => EventKind::SettlementOutOfTolerance,
+            NodeEvent::AddressReuseDetected { .. } => EventKind::AddressReuseDetected,
+            NodeEvent::ResponseDeadlineApproaching { .. } => EventKind::ResponseDeadlineApproaching,
+            NodeEvent::ResponseDeadlineBreached { .. } => EventKind::ResponseDeadlineBreached,
+        }
+    }
 }
 
 /// Event subscriber trait for receiving node events
@@ -486,6 +1059,11 @@ pub enum NodeEvent {
 /// All implementations must be `Send + Sync` to ensure they can be safely
 /// used in multithreaded environments.
 ///
+/// # Routing
+///
+/// Subscribers that only care about some events should override
+/// `event_kinds` so the bus can skip them for everything else.
+///
 /// # Usage
 ///
 /// ```
@@ -527,6 +1105,201 @@ pub trait EventSubscriber: Send + Sync {
     /// - For long-running operations, spawn a separate task
     /// - Handle errors gracefully, as exceptions may disrupt the event system
     async fn handle_event(&self, event: NodeEvent);
+
+    /// The event kinds this subscriber handles
+    ///
+    /// The `EventBus` only dispatches events of these kinds to the subscriber,
+    /// so events it would ignore are never cloned for it. `None` (the default)
+    /// subscribes to every event.
+    fn event_kinds(&self) -> Option<&'static [EventKind]> {
+        None
+    }
+
+    /// Name used to identify this subscriber in `EventBus` metrics
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// How the `EventBus` delivers events to a callback subscriber
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "mode")]
+pub enum DeliveryPolicy {
+    /// The subscriber is awaited during `publish_event`
+    ///
+    /// Publishers observe the subscriber's side effects as soon as
+    /// publication returns, but a slow subscriber delays the publisher.
+    Inline,
+    /// The subscriber runs on its own task, fed by a bounded queue
+    ///
+    /// Publication never waits for the subscriber. Events published while
+    /// the queue is full are dropped for this subscriber only and counted in
+    /// its metrics.
+    Isolated {
+        /// Maximum number of events queued for the subscriber
+        capacity: usize,
+    },
+}
+
+/// Delivery statistics for a single callback subscriber
+#[derive(Debug, Clone, Serialize)]
+pub struct SubscriberMetrics {
+    /// Subscriber name, see [`EventSubscriber::name`]
+    pub name: String,
+    /// Delivery policy of the subscriber
+    pub policy: DeliveryPolicy,
+    /// Event kinds routed to the subscriber, or `None` for all kinds
+    pub event_kinds: Option<Vec<EventKind>>,
+    /// Events handled by the subscriber
+    pub delivered: u64,
+    /// Events dropped because the subscriber's queue was full
+    pub dropped: u64,
+    /// Events waiting in the subscriber's queue
+    pub queue_depth: usize,
+}
+
+/// Snapshot of `EventBus` activity
+#[derive(Debug, Clone, Serialize)]
+pub struct EventBusMetrics {
+    /// Events published per kind, omitting kinds that were never published
+    pub published: BTreeMap<EventKind, u64>,
+    /// Number of channel receivers from `subscribe_channel`
+    pub channel_receivers: usize,
+    /// Events buffered in the broadcast channel and not yet read by every
+    /// receiver; receivers more than the channel capacity behind lag and
+    /// miss events
+    pub channel_backlog: usize,
+    /// Callback subscribers in subscription order
+    pub subscribers: Vec<SubscriberMetrics>,
+}
+
+impl EventBusMetrics {
+    /// Total events published
+    pub fn total_published(&self) -> u64 {
+        self.published.values().sum()
+    }
+
+    /// Total events dropped across all subscribers
+    pub fn total_dropped(&self) -> u64 {
+        self.subscribers.iter().map(|s| s.dropped).sum()
+    }
+}
+
+#[derive(Default)]
+struct SubscriberStats {
+    delivered: AtomicU64,
+    dropped: AtomicU64,
+}
+
+/// A registered callback subscriber
+struct Subscription {
+    /// Registration order, used to dispatch in subscription order
+    seq: u64,
+    subscriber: Arc<dyn EventSubscriber>,
+    kinds: Option<&'static [EventKind]>,
+    policy: DeliveryPolicy,
+    /// Queue feeding the subscriber's task for `DeliveryPolicy::Isolated`
+    queue: Option<mpsc::Sender<Arc<NodeEvent>>>,
+    stats: Arc<SubscriberStats>,
+}
+
+impl Subscription {
+    async fn deliver(&self, event: &Arc<NodeEvent>) {
+        match &self.queue {
+            None => {
+                self.subscriber.handle_event(NodeEvent::clone(event)).await;
+                self.stats.delivered.fetch_add(1, Ordering::Relaxed);
+            }
+            Some(queue) => {
+                if queue.try_send(event.clone()).is_err() {
+                    let dropped = self.stats.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                    warn!(
+                        "Dropped {} event for slow subscriber {} ({} dropped so far)",
+                        event.kind().as_str(),
+                        self.subscriber.name(),
+                        dropped
+                    );
+                }
+            }
+        }
+    }
+
+    fn metrics(&self) -> SubscriberMetrics {
+        let queue_depth = self
+            .queue
+            .as_ref()
+            .map(|queue| queue.max_capacity() - queue.capacity())
+            .unwrap_or(0);
+        SubscriberMetrics {
+            name: self.subscriber.name().to_string(),
+            policy: self.policy,
+            event_kinds: self.kinds.map(|kinds| kinds.to_vec()),
+            delivered: self.stats.delivered.load(Ordering::Relaxed),
+            dropped: self.stats.dropped.load(Ordering::Relaxed),
+            queue_depth,
+        }
+    }
+}
+
+/// Callback subscribers indexed by the event kinds they handle
+#[derive(Default)]
+struct SubscriberRegistry {
+    next_seq: u64,
+    /// Subscribers that receive every event
+    wildcard: Vec<Arc<Subscription>>,
+    /// Subscribers that declared specific event kinds
+    by_kind: HashMap<EventKind, Vec<Arc<Subscription>>>,
+}
+
+impl SubscriberRegistry {
+    fn insert(&mut self, mut subscription: Subscription) {
+        subscription.seq = self.next_seq;
+        self.next_seq += 1;
+
+        let subscription = Arc::new(subscription);
+        match subscription.kinds {
+            None => self.wildcard.push(subscription),
+            Some(kinds) => {
+                for kind in kinds {
+                    let subscribers = self.by_kind.entry(*kind).or_default();
+                    if !subscribers.iter().any(|s| s.seq == subscription.seq) {
+                        subscribers.push(subscription.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    fn remove(&mut self, subscriber: &Arc<dyn EventSubscriber>) {
+        self.wildcard
+            .retain(|s| !Arc::ptr_eq(&s.subscriber, subscriber));
+        for subscribers in self.by_kind.values_mut() {
+            subscribers.retain(|s| !Arc::ptr_eq(&s.subscriber, subscriber));
+        }
+        self.by_kind
+            .retain(|_, subscribers| !subscribers.is_empty());
+    }
+
+    /// Subscribers interested in `kind`, in subscription order
+    fn interested(&self, kind: EventKind) -> Vec<Arc<Subscription>> {
+        let by_kind = self.by_kind.get(&kind).map(Vec::as_slice).unwrap_or(&[]);
+        let mut subscribers: Vec<_> = self.wildcard.iter().chain(by_kind).cloned().collect();
+        subscribers.sort_by_key(|s| s.seq);
+        subscribers
+    }
+
+    /// Every subscriber once, in subscription order
+    fn all(&self) -> Vec<Arc<Subscription>> {
+        let mut subscribers: Vec<_> = self
+            .wildcard
+            .iter()
+            .chain(self.by_kind.values().flatten())
+            .cloned()
+            .collect();
+        subscribers.sort_by_key(|s| s.seq);
+        subscribers.dedup_by_key(|s| s.seq);
+        subscribers
+    }
 }
 
 /// Event bus for publishing and subscribing to node events
@@ -535,7 +1308,15 @@ pub trait EventSubscriber: Send + Sync {
 /// components to publish events and provides two mechanisms for subscribing to events:
 ///
 /// 1. Callback-based: Register an `EventSubscriber` to receive events via callbacks
-/// 2. Channel-based: Get a `broadcast::Receiver<NodeEvent>` for async event processing
+/// 2. Channel-based: Get a `broadcast::Receiver<Arc<NodeEvent>>` for async event processing
+///
+/// # Dispatch
+///
+/// Each published event is wrapped in an `Arc` once and shared by every
+/// channel receiver. Callback subscribers are indexed by the event kinds they
+/// declare, so an event is only cloned for subscribers that handle its kind.
+/// Subscribers registered with [`DeliveryPolicy::Isolated`] run on their own
+/// task so that a blocked handler cannot stall publication.
 ///
 /// # Thread Safety
 ///
@@ -570,9 +1351,11 @@ pub trait EventSubscriber: Send + Sync {
 /// ```
 pub struct EventBus {
     /// Sender for events
-    sender: broadcast::Sender<NodeEvent>,
+    sender: broadcast::Sender<Arc<NodeEvent>>,
     /// Subscribers
-    subscribers: RwLock<Vec<Arc<dyn EventSubscriber>>>,
+    subscribers: RwLock<SubscriberRegistry>,
+    /// Events published per kind, indexed like `EventKind::ALL`
+    published: [AtomicU64; EventKind::ALL.len()],
 }
 
 impl Default for EventBus {
@@ -585,7 +1368,8 @@ impl Clone for EventBus {
     fn clone(&self) -> Self {
         Self {
             sender: self.sender.clone(),
-            subscribers: RwLock::new(Vec::new()),
+            subscribers: RwLock::new(SubscriberRegistry::default()),
+            published: Default::default(),
         }
     }
 }
@@ -598,25 +1382,89 @@ impl EventBus {
 
         Self {
             sender,
-            subscribers: RwLock::new(Vec::new()),
+            subscribers: RwLock::new(SubscriberRegistry::default()),
+            published: Default::default(),
         }
     }
 
     /// Subscribe to node events
+    ///
+    /// The subscriber is awaited inline for every event of the kinds it
+    /// declares via [`EventSubscriber::event_kinds`].
     pub async fn subscribe(&self, subscriber: Arc<dyn EventSubscriber>) {
-        let mut subscribers = self.subscribers.write().await;
-        subscribers.push(subscriber);
+        self.subscribe_with_policy(subscriber, DeliveryPolicy::Inline)
+            .await;
+    }
+
+    /// Subscribe to node events with an explicit delivery policy
+    pub async fn subscribe_with_policy(
+        &self,
+        subscriber: Arc<dyn EventSubscriber>,
+        policy: DeliveryPolicy,
+    ) {
+        let stats = Arc::new(SubscriberStats::default());
+        let queue = match policy {
+            DeliveryPolicy::Inline => None,
+            DeliveryPolicy::Isolated { capacity } => {
+                let (queue, mut receiver) = mpsc::channel::<Arc<NodeEvent>>(capacity.max(1));
+                let subscriber = subscriber.clone();
+                let stats = stats.clone();
+                tokio::spawn(async move {
+                    while let Some(event) = receiver.recv().await {
+                        subscriber.handle_event(NodeEvent::clone(&event)).await;
+                        stats.delivered.fetch_add(1, Ordering::Relaxed);
+                    }
+                });
+                Some(queue)
+            }
+        };
+
+        self.subscribers.write().await.insert(Subscription {
+            seq: 0,
+            kinds: subscriber.event_kinds(),
+            subscriber,
+            policy,
+            queue,
+            stats,
+        });
     }
 
     /// Get a receiver for node events
-    pub fn subscribe_channel(&self) -> broadcast::Receiver<NodeEvent> {
+    pub fn subscribe_channel(&self) -> broadcast::Receiver<Arc<NodeEvent>> {
         self.sender.subscribe()
     }
 
     /// Remove a subscriber from the event bus
+    ///
+    /// An isolated subscriber's task finishes the events already queued for
+    /// it and then exits.
     pub async fn unsubscribe(&self, subscriber: &Arc<dyn EventSubscriber>) {
-        let mut subscribers = self.subscribers.write().await;
-        subscribers.retain(|s| !Arc::ptr_eq(s, subscriber));
+        self.subscribers.write().await.remove(subscriber);
+    }
+
+    /// Snapshot of publication and per-subscriber delivery metrics
+    pub async fn metrics(&self) -> EventBusMetrics {
+        let published = EventKind::ALL
+            .iter()
+            .zip(self.published.iter())
+            .map(|(kind, count)| (*kind, count.load(Ordering::Relaxed)))
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        let subscribers = self
+            .subscribers
+            .read()
+            .await
+            .all()
+            .iter()
+            .map(|s| s.metrics())
+            .collect();
+
+        EventBusMetrics {
+            published,
+            channel_receivers: self.sender.receiver_count(),
+            channel_backlog: self.sender.len(),
+            subscribers,
+        }
     }
 
     /// Publish a message received event
@@ -744,14 +1592,126 @@ impl EventBus {
         self.publish_event(event).await;
     }
 
+    /// Publish a policy triggered event
+    pub async fn publish_policy_triggered(
+        &self,
+        transaction_id: String,
+        rule: String,
+        action: String,
+        reason: String,
+        details: Value,
+    ) {
+        let event = NodeEvent::PolicyTriggered {
+            transaction_id,
+            rule,
+            action,
+            reason,
+            details,
+        };
+        self.publish_event(event).await;
+    }
+
+    /// Publish a travel rule data validated event
+    pub async fn publish_travel_rule_data_validated(
+        &self,
+        message_id: String,
+        sender: String,
+        role: Option<String>,
+        report: tap_ivms101::ValidationReport,
+    ) {
+        let event = NodeEvent::TravelRuleDataValidated {
+            message_id,
+            sender,
+            role,
+            report,
+        };
+        self.publish_event(event).await;
+    }
+
+    /// Publish a travel rule policy evaluated event
+    pub async fn publish_travel_rule_policy_evaluated(
+        &self,
+        message_id: String,
+        transaction_id: String,
+        sender: String,
+        evaluation: tap_msg::message::PolicyEvaluation,
+    ) {
+        let event = NodeEvent::TravelRulePolicyEvaluated {
+            message_id,
+            transaction_id,
+            sender,
+            evaluation,
+        };
+        self.publish_event(event).await;
+    }
+
+    /// Publish a transaction expired event
+    pub async fn publish_transaction_expired(
+        &self,
+        transaction_id: String,
+        agent_did: String,
+        expires_time: u64,
+    ) {
+        let event = NodeEvent::TransactionExpired {
+            transaction_id,
+            agent_did,
+            expires_time,
+        };
+        self.publish_event(event).await;
+    }
+
+    /// Publish a problem report received event
+    pub async fn publish_problem_report_received(
+        &self,
+        message_id: String,
+        from: String,
+        report: &tap_msg::message::ProblemReport,
+        pthid: Option<String>,
+    ) {
+        let event = NodeEvent::ProblemReportReceived {
+            message_id,
+            from,
+            code: report.code.clone(),
+            comment: report.interpolated_comment(),
+            pthid,
+            escalate_to: report.escalate_to.clone(),
+        };
+        self.publish_event(event).await;
+    }
+
+    /// Publish a message processing timed out event
+    pub async fn publish_message_processing_timed_out(
+        &self,
+        message_id: Option<String>,
+        stage: crate::timeouts::ProcessingStage,
+        timeout_ms: u64,
+        dead_lettered: bool,
+    ) {
+        let event = NodeEvent::MessageProcessingTimedOut {
+            message_id,
+            stage,
+            timeout_ms,
+            dead_lettered,
+        };
+        self.publish_event(event).await;
+    }
+
     /// Publish an event to all subscribers
     pub async fn publish_event(&self, event: NodeEvent) {
+        let kind = event.kind();
+        self.published[kind as usize].fetch_add(1, Ordering::Relaxed);
+        let event = Arc::new(event);
+
         // Send to channel
-        let _ = self.sender.send(event.clone());
+        if self.sender.receiver_count() > 0 {
+            let _ = self.sender.send(event.clone());
+        }
 
-        // Notify subscribers
-        for subscriber in self.subscribers.read().await.iter() {
-            subscriber.handle_event(event.clone()).await;
+        // Notify subscribers without holding the registry lock, so handlers
+        // may subscribe or unsubscribe while handling an event
+        let subscribers = self.subscribers.read().await.interested(kind);
+        for subscription in subscribers {
+            subscription.deliver(&event).await;
         }
     }
 }
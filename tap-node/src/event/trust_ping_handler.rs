@@ -3,7 +3,7 @@
 //! This module provides an event handler that listens for Trust Ping response events
 //! and sends them through the appropriate channels.
 
-use crate::event::{EventSubscriber, NodeEvent};
+use crate::event::{EventKind, EventSubscriber, NodeEvent};
 use crate::message::sender::PlainMessageSender;
 use async_trait::async_trait;
 use std::sync::Arc;
@@ -55,4 +55,8 @@ impl EventSubscriber for TrustPingResponseHandler {
             }
         }
     }
+
+    fn event_kinds(&self) -> Option<&'static [EventKind]> {
+        Some(&[EventKind::PlainMessageSent])
+    }
 }
@@ -0,0 +1,414 @@
+//! Scoped webhooks
+//!
+//! A [`Webhook`] POSTs node events to an HTTP endpoint. Its [`WebhookScope`]
+//! selects the events it receives: every event, or only those of one
+//! transaction, one counterparty DID or one Connect session, such as the
+//! callback URL a merchant attached to a payment link.
+//!
+//! Events are matched to counterparties and connections through the messages
+//! the node sends and receives while webhooks are registered: the
+//! [`WebhookRegistry`] remembers the parties (`from`, `to` and the agents in
+//! the body) and the connection (`pthid` or `connection_id`) of each thread,
+//! so that e.g. a `TransactionStateChanged` event reaches the webhook of the
+//! transaction's counterparty. A webhook set
+//! to expire on completion is removed after the first transaction it matched
+//! is settled, rejected, cancelled, reverted or expires, once the event
+//! announcing it has been delivered.
+//!
+//! Each delivery is a JSON object with the `webhook_id`, a `timestamp`, and
+//! the `event_type` and `data` of the event as written by the structured
+//! [`EventLogger`](super::logger::EventLogger). Deliveries are made in order,
+//! on the registry's own task, and are not retried.
+//!
+//! ```no_run
+//! use tap_node::event::webhook::{Webhook, WebhookScope};
+//! # async fn example(node: &tap_node::TapNode) -> tap_node::Result<()> {
+//! let webhook = Webhook::new(
+//!     "https://merchant.example/tap/callback",
+//!     WebhookScope::Connection("connect-123".to_string()),
+//! )
+//! .expiring();
+//! let webhook_id = node.register_webhook(webhook).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use super::logger::structured_event;
+use super::{DeliveryPolicy, EventBus, EventSubscriber, NodeEvent};
+use crate::error::{Error, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tap_msg::didcomm::PlainMessage;
+use tracing::{debug, warn};
+
+/// Time allowed for a webhook endpoint to answer
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Events queued for delivery before further events are dropped
+const QUEUE_CAPACITY: usize = 1024;
+
+/// The events a webhook receives
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type", content = "id")]
+pub enum WebhookScope {
+    /// Every event of the node
+    All,
+    /// Events of the transaction with this ID
+    Transaction(String),
+    /// Events of messages exchanged with this DID, and of their transactions
+    Counterparty(String),
+    /// Events of the transactions started in this Connect session, identified
+    /// by the ID of its Connect message
+    Connection(String),
+}
+
+/// An HTTP endpoint that receives node events
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Webhook {
+    /// ID of the webhook, included in each delivery
+    pub id: String,
+    /// URL events are POSTed to
+    pub url: String,
+    /// Events the webhook receives
+    pub scope: WebhookScope,
+    /// Remove the webhook once a transaction it receives events for completes
+    pub expire_on_completion: bool,
+}
+
+impl Webhook {
+    /// A webhook receiving the events of `scope` at `url`
+    ///
+    /// Transaction webhooks expire when their transaction completes; other
+    /// webhooks stay registered until unregistered.
+    pub fn new(url: impl Into<String>, scope: WebhookScope) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            url: url.into(),
+            expire_on_completion: matches!(scope, WebhookScope::Transaction(_)),
+            scope,
+        }
+    }
+
+    /// Remove the webhook once a transaction it receives events for completes
+    pub fn expiring(mut self) -> Self {
+        self.expire_on_completion = true;
+        self
+    }
+
+    /// Keep the webhook registered until it is unregistered
+    pub fn persistent(mut self) -> Self {
+        self.expire_on_completion = false;
+        self
+    }
+}
+
+/// What an event is about
+#[derive(Debug, Default)]
+struct EventScope {
+    transaction_id: Option<String>,
+    parties: HashSet<String>,
+    connection_id: Option<String>,
+}
+
+/// What is known of a thread from its messages
+#[derive(Debug, Default)]
+struct ThreadScope {
+    parties: HashSet<String>,
+    connection_id: Option<String>,
+}
+
+/// Registered webhooks, delivering the events of their scope
+pub struct WebhookRegistry {
+    webhooks: DashMap<String, Webhook>,
+    threads: DashMap<String, ThreadScope>,
+    client: reqwest::Client,
+    attached: AtomicBool,
+}
+
+impl WebhookRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self {
+            webhooks: DashMap::new(),
+            threads: DashMap::new(),
+            client: reqwest::Client::builder()
+                .timeout(DELIVERY_TIMEOUT)
+                .user_agent("TAP-Node/0.1")
+                .build()
+                .unwrap_or_default(),
+            attached: AtomicBool::new(false),
+        }
+    }
+
+    /// Subscribe the registry to `event_bus`, unless it already is
+    pub async fn attach(self: &Arc<Self>, event_bus: &EventBus) {
+        if !self.attached.swap(true, Ordering::SeqCst) {
+            event_bus
+                .subscribe_with_policy(
+                    self.clone(),
+                    DeliveryPolicy::Isolated {
+                        capacity: QUEUE_CAPACITY,
+                    },
+                )
+                .await;
+        }
+    }
+
+    /// Register a webhook, returning its ID
+    pub fn register(&self, webhook: Webhook) -> Result<String> {
+        let url = reqwest::Url::parse(&webhook.url)
+            .map_err(|e| Error::Configuration(format!("Invalid webhook URL: {}", e)))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(Error::Configuration(format!(
+                "Webhook URL must use http or https: {}",
+                webhook.url
+            )));
+        }
+        let id = webhook.id.clone();
+        self.webhooks.insert(id.clone(), webhook);
+        Ok(id)
+    }
+
+    /// Unregister a webhook, returning whether it was registered
+    pub fn unregister(&self, webhook_id: &str) -> bool {
+        self.webhooks.remove(webhook_id).is_some()
+    }
+
+    /// The registered webhooks
+    pub fn list(&self) -> Vec<Webhook> {
+        self.webhooks
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
+    /// Remember the parties and connection of a message's thread
+    fn learn(&self, message: &PlainMessage) {
+        let thread = message.thid.clone().unwrap_or_else(|| message.id.clone());
+        let mut scope = self.threads.entry(thread).or_default();
+        scope.parties.extend(message_parties(message));
+        if scope.connection_id.is_none() {
+            scope.connection_id = message.pthid.clone().or_else(|| {
+                message
+                    .body
+                    .get("connection_id")
+                    .and_then(Value::as_str)
+                    .map(String::from)
+            });
+        }
+    }
+
+    /// What an event is about, including what is known of its thread
+    fn scope_of(&self, event: &NodeEvent) -> EventScope {
+        let mut scope = EventScope::default();
+        match event {
+            NodeEvent::MessageReceived { message, .. } | NodeEvent::MessageSent { message, .. } => {
+                scope.transaction_id =
+                    Some(message.thid.clone().unwrap_or_else(|| message.id.clone()));
+                scope.parties.extend(message_parties(message));
+            }
+            NodeEvent::ReplyReceived { reply_message, .. } => {
+                scope.transaction_id = reply_message.thid.clone();
+                scope.parties.extend(message_parties(reply_message));
+            }
+            NodeEvent::MessageAccepted {
+                message_id,
+                from,
+                to,
+                ..
+            }
+            | NodeEvent::MessageRejected {
+                message_id,
+                from,
+                to,
+                ..
+            } => {
+                scope.transaction_id = Some(message_id.clone());
+                scope.parties.extend([from.clone(), to.clone()]);
+            }
+            #[cfg(feature = "storage")]
+            NodeEvent::TransactionCreated { transaction, .. } => {
+                scope.transaction_id = Some(transaction.reference_id.clone());
+                scope.parties.extend(
+                    transaction
+                        .from_did
+                        .iter()
+                        .chain(&transaction.to_did)
+                        .cloned(),
+                );
+            }
+            NodeEvent::TransactionStateChanged { transaction_id, .. }
+            | NodeEvent::DecisionRequired { transaction_id, .. }
+            | NodeEvent::PolicyTriggered { transaction_id, .. }
+            | NodeEvent::TransactionExpired { transaction_id, .. } => {
+                scope.transaction_id = Some(transaction_id.clone());
+            }
+            NodeEvent::ProblemReportReceived { pthid, from, .. } => {
+                scope.transaction_id = pthid.clone();
+                scope.parties.insert(from.clone());
+            }
+            NodeEvent::MessageProcessingTimedOut { message_id, .. } => {
+                scope.transaction_id = message_id.clone();
+            }
+            NodeEvent::ResponseDeadlineApproaching {
+                agent_did,
+                counterparty,
+                thread_id,
+                ..
+            }
+            | NodeEvent::ResponseDeadlineBreached {
+                agent_did,
+                counterparty,
+                thread_id,
+                ..
+            } => {
+                scope.transaction_id = Some(thread_id.clone());
+                scope.parties.insert(agent_did.clone());
+                scope.parties.extend(counterparty.iter().cloned());
+            }
+            _ => {}
+        }
+
+        if let Some(thread) = scope
+            .transaction_id
+            .as_ref()
+            .and_then(|id| self.threads.get(id))
+        {
+            scope.parties.extend(thread.parties.iter().cloned());
+            scope.connection_id = thread.connection_id.clone();
+        }
+        scope
+    }
+
+    /// Deliver an event to a webhook
+    async fn deliver(&self, webhook: &Webhook, event_type: &str, data: &Value) {
+        let payload = json!({
+            "webhook_id": webhook.id,
+            "timestamp": Utc::now().to_rfc3339(),
+            "event_type": event_type,
+            "data": data,
+        });
+        match self.client.post(&webhook.url).json(&payload).send().await {
+            Ok(response) if response.status().is_success() => {
+                debug!("Delivered {} to webhook {}", event_type, webhook.id);
+            }
+            Ok(response) => warn!(
+                "Webhook {} answered {} to {}",
+                webhook.id,
+                response.status(),
+                event_type
+            ),
+            Err(e) => warn!(
+                "Failed to deliver {} to webhook {}: {}",
+                event_type, webhook.id, e
+            ),
+        }
+    }
+}
+
+impl Default for WebhookRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebhookScope {
+    fn matches(&self, event: &EventScope) -> bool {
+        match self {
+            WebhookScope::All => true,
+            WebhookScope::Transaction(id) => event.transaction_id.as_ref() == Some(id),
+            WebhookScope::Counterparty(did) => event.parties.contains(did),
+            WebhookScope::Connection(id) => {
+                event.connection_id.as_ref() == Some(id)
+                    || event.transaction_id.as_ref() == Some(id)
+            }
+        }
+    }
+}
+
+/// DIDs taking part in a message
+fn message_parties(message: &PlainMessage) -> impl Iterator<Item = String> + '_ {
+    let agents = message
+        .body
+        .get("agents")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|agent| agent.get("@id").and_then(Value::as_str).map(String::from));
+    std::iter::once(message.from.clone())
+        .chain(message.to.iter().cloned())
+        .chain(agents)
+}
+
+/// Whether an event announces that its transaction completed
+fn completes_transaction(event: &NodeEvent) -> bool {
+    match event {
+        NodeEvent::TransactionStateChanged { new_state, .. } => matches!(
+            new_state.as_str(),
+            "settled" | "rejected" | "cancelled" | "reverted"
+        ),
+        NodeEvent::TransactionExpired { .. } => true,
+        _ => false,
+    }
+}
+
+#[async_trait]
+impl EventSubscriber for WebhookRegistry {
+    async fn handle_event(&self, event: NodeEvent) {
+        if self.webhooks.is_empty() {
+            return;
+        }
+        if let NodeEvent::MessageReceived { message, .. } | NodeEvent::MessageSent { message, .. } =
+            &event
+        {
+            self.learn(message);
+        }
+
+        let scope = self.scope_of(&event);
+        let matching: Vec<Webhook> = self
+            .webhooks
+            .iter()
+            .filter(|entry| entry.scope.matches(&scope))
+            .map(|entry| entry.value().clone())
+            .collect();
+
+        if !matching.is_empty() {
+            let (event_type, data) = structured_event(&event);
+            for webhook in &matching {
+                self.deliver(webhook, event_type, &data).await;
+            }
+        }
+
+        if completes_transaction(&event) {
+            for webhook in &matching {
+                if webhook.expire_on_completion && webhook.scope != WebhookScope::All {
+                    debug!("Webhook {} expired with its transaction", webhook.id);
+                    self.webhooks.remove(&webhook.id);
+                }
+            }
+            if let Some(transaction_id) = &scope.transaction_id {
+                self.threads.remove(transaction_id);
+            }
+        }
+    }
+
+    fn name(&self) -> &str {
+        "webhooks"
+    }
+}
+
+impl std::fmt::Debug for WebhookRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookRegistry")
+            .field("webhooks", &self.webhooks.len())
+            .field("threads", &self.threads.len())
+            .finish()
+    }
+}
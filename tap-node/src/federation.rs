@@ -0,0 +1,296 @@
+//! Federation of nodes under an upstream node
+//!
+//! A node with a [`FederationConfig`] forwards the signed and encrypted
+//! messages it receives for none of its registered agents to an upstream
+//! node, so that e.g. regional nodes can hand traffic they do not serve to a
+//! central compliance node. The envelope is passed through as received, and
+//! the upstream node verifies or decrypts it itself.
+//!
+//! Each forward names the nodes the message passed through in the
+//! `TAP-Forwarded-By` header. A node refuses to forward a message whose chain
+//! names it, one already forwarded by as many nodes as its `max_hops`, and
+//! one it forwarded before, so that a misconfigured federation cannot pass a
+//! message around in circles. Forwards, refusals and failed forwards are
+//! recorded in the node's storage (see [`TapNode::forwarded_messages`]).
+//!
+//! The client of the upstream node is set up by [`TapNode::init_storage`],
+//! which fails if the upstream URL is invalid or the client cannot be built,
+//! rather than forwarding without the configured timeout.
+
+use crate::error::{Error, Result};
+use crate::intake::{IngestOutcome, RejectionCode};
+use crate::message::sender::{idempotency_key, response_snippet};
+use crate::message::{inspect_envelope, EnvelopeInfo, EnvelopeKind};
+use crate::storage::{ForwardStatus, ForwardedMessage};
+use crate::TapNode;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use tap_agent::message::normalize_envelope;
+
+/// Header naming the nodes a message was forwarded by, in order
+pub const FORWARDED_BY_HEADER: &str = "TAP-Forwarded-By";
+
+/// Default number of nodes a message may be forwarded by
+pub const DEFAULT_MAX_HOPS: usize = 8;
+
+/// Upstream node that a node forwards messages for unknown recipients to
+#[derive(Debug, Clone)]
+pub struct FederationConfig {
+    /// DIDComm endpoint of the upstream node
+    pub upstream_url: String,
+    /// ID of this node in `TAP-Forwarded-By` headers, unique within the
+    /// federation
+    pub node_id: String,
+    /// Number of nodes a message may have been forwarded by before this
+    /// node refuses to forward it further
+    pub max_hops: usize,
+    /// How long to wait for the upstream node to accept a message
+    pub timeout: Duration,
+}
+
+impl FederationConfig {
+    /// Forward to the DIDComm endpoint at `upstream_url` as `node_id`
+    pub fn new(upstream_url: impl Into<String>, node_id: impl Into<String>) -> Self {
+        Self {
+            upstream_url: upstream_url.into(),
+            node_id: node_id.into(),
+            max_hops: DEFAULT_MAX_HOPS,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// IDs of the nodes listed in a `TAP-Forwarded-By` header
+pub fn parse_forwarded_by(header: &str) -> Vec<String> {
+    header
+        .split(',')
+        .map(str::trim)
+        .filter(|node_id| !node_id.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Client of the upstream node
+#[derive(Debug)]
+pub(crate) struct Upstream {
+    config: FederationConfig,
+    client: reqwest::Client,
+}
+
+impl Upstream {
+    pub(crate) fn new(config: FederationConfig) -> Result<Self> {
+        reqwest::Url::parse(&config.upstream_url).map_err(|e| {
+            Error::Configuration(format!(
+                "Invalid upstream URL {}: {}",
+                config.upstream_url, e
+            ))
+        })?;
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .user_agent("TAP-Node/0.1")
+            .build()
+            .map_err(|e| {
+                Error::Configuration(format!(
+                    "Failed to build the client of upstream node {}: {}",
+                    config.upstream_url, e
+                ))
+            })?;
+        Ok(Self { config, client })
+    }
+
+    /// Post an envelope to the upstream node, adding this node to the nodes
+    /// it was forwarded by
+    ///
+    /// A message the upstream node turns down is rejected for the same
+    /// reason. If the upstream node is unreachable, overloaded or failing,
+    /// the message is rejected as [`RejectionCode::UpstreamUnavailable`] so
+    /// that its sender retries.
+    async fn post(
+        &self,
+        envelope: &EnvelopeInfo,
+        envelope_text: &str,
+        forwarded_by: &[String],
+    ) -> std::result::Result<(), (RejectionCode, String)> {
+        let content_type = match envelope.kind {
+            EnvelopeKind::Encrypted => "application/didcomm-encrypted+json",
+            _ => "application/didcomm-signed+json",
+        };
+        let mut chain = forwarded_by.to_vec();
+        chain.push(self.config.node_id.clone());
+
+        let response = self
+            .client
+            .post(&self.config.upstream_url)
+            .header("Content-Type", content_type)
+            .header("Idempotency-Key", idempotency_key(envelope_text))
+            .header(FORWARDED_BY_HEADER, chain.join(", "))
+            .body(envelope_text.to_string())
+            .send()
+            .await
+            .map_err(|e| {
+                (
+                    RejectionCode::UpstreamUnavailable,
+                    format!("Upstream node is unreachable: {}", e),
+                )
+            })?;
+
+        let status = response.status();
+        // A conflict means the upstream node already has the message
+        if status.is_success() || status == reqwest::StatusCode::CONFLICT {
+            return Ok(());
+        }
+        let body = response_snippet(&response.text().await.unwrap_or_default());
+        let code = if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+            RejectionCode::Invalid
+        } else {
+            RejectionCode::UpstreamUnavailable
+        };
+        Err((
+            code,
+            format!("Upstream node answered HTTP {} - {}", status, body),
+        ))
+    }
+}
+
+impl TapNode {
+    /// Forward a received message to the upstream node if it is for none of
+    /// our agents, returning what became of it
+    ///
+    /// Returns None for the messages this node processes itself: plain
+    /// messages, messages with a registered recipient, and all messages if
+    /// the node has no upstream.
+    pub(crate) async fn forward_upstream(
+        &self,
+        message: &serde_json::Value,
+        forwarded_by: &[String],
+    ) -> Result<Option<IngestOutcome>> {
+        let Some(upstream) = &self.upstream else {
+            return Ok(None);
+        };
+        // Messages that cannot be inspected are rejected by their processing
+        let Ok(envelope) = inspect_envelope(message) else {
+            return Ok(None);
+        };
+        if envelope.kind == EnvelopeKind::Plain
+            || envelope.recipients.is_empty()
+            || envelope
+                .recipients
+                .iter()
+                .any(|did| self.agents.has_agent(did))
+        {
+            return Ok(None);
+        }
+
+        let envelope_text = serde_json::to_string(&normalize_envelope(message.clone()))
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+        let digest = format!("{:x}", Sha256::digest(envelope_text.as_bytes()));
+        let node_id = &upstream.config.node_id;
+        let forwarded_before = match self.storage() {
+            Some(storage) => storage
+                .has_forwarded(&digest)
+                .await
+                .map_err(|e| Error::Storage(e.to_string()))?,
+            None => false,
+        };
+
+        let refusal = if forwarded_by.contains(node_id) {
+            Some(format!(
+                "Forwarding loop: the message was already forwarded by {}",
+                node_id
+            ))
+        } else if forwarded_by.len() >= upstream.config.max_hops {
+            Some(format!(
+                "The message was already forwarded by {} nodes",
+                forwarded_by.len()
+            ))
+        } else if forwarded_before {
+            Some("Forwarding loop: the message was already forwarded upstream".to_string())
+        } else {
+            None
+        };
+
+        let (status, outcome) = match refusal {
+            Some(reason) => {
+                log::warn!("Refused to forward message upstream: {}", reason);
+                (
+                    ForwardStatus::Refused,
+                    IngestOutcome::Rejected {
+                        code: RejectionCode::Invalid,
+                        reason,
+                    },
+                )
+            }
+            None => match upstream.post(&envelope, &envelope_text, forwarded_by).await {
+                Ok(()) => {
+                    log::info!(
+                        "Forwarded message for {} to upstream node {}",
+                        envelope.recipients.join(", "),
+                        upstream.config.upstream_url
+                    );
+                    (
+                        ForwardStatus::Forwarded,
+                        IngestOutcome::Forwarded {
+                            message_id: envelope.message_id.clone(),
+                        },
+                    )
+                }
+                Err((code, reason)) => {
+                    log::warn!("Failed to forward message upstream: {}", reason);
+                    (
+                        ForwardStatus::Failed,
+                        IngestOutcome::Rejected { code, reason },
+                    )
+                }
+            },
+        };
+
+        if let Some(storage) = self.storage() {
+            let error = match &outcome {
+                IngestOutcome::Rejected { reason, .. } => Some(reason.as_str()),
+                _ => None,
+            };
+            storage
+                .record_forwarded_message(
+                    &digest,
+                    &envelope,
+                    &upstream.config.upstream_url,
+                    forwarded_by,
+                    status,
+                    error,
+                )
+                .await
+                .map_err(|e| Error::Storage(e.to_string()))?;
+        }
+        Ok(Some(outcome))
+    }
+
+    /// Messages forwarded to the upstream node, refused or that failed to
+    /// be forwarded, newest first
+    pub async fn forwarded_messages(
+        &self,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<ForwardedMessage>> {
+        let storage = self
+            .storage()
+            .ok_or_else(|| Error::Storage("Storage is not initialized".to_string()))?;
+        storage
+            .list_forwarded_messages(limit, offset)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_forwarded_by() {
+        assert_eq!(
+            parse_forwarded_by("regional-eu, regional-eu-west,"),
+            vec!["regional-eu".to_string(), "regional-eu-west".to_string()]
+        );
+        assert!(parse_forwarded_by(" ").is_empty());
+    }
+}
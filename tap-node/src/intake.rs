@@ -0,0 +1,294 @@
+//! Intake of received messages
+//!
+//! [`TapNode::receive_message`](crate::TapNode::receive_message) reports what
+//! became of a message as an [`IngestOutcome`], so that a transport can tell
+//! its sender whether to retry: a message that was processed, queued or
+//! forwarded must not be sent again, a message rejected as overloaded or for
+//! want of an upstream node can be retried later, and any other rejected
+//! message will be rejected again.
+//!
+//! [`IntakeLimits`] bounds how many received messages are processed at once.
+//! Messages arriving while the node is at its limit wait in a queue, in
+//! arrival order, and are processed in the background; once the queue is
+//! full they are rejected as [`RejectionCode::Overloaded`].
+//!
+//! The intake can also be paused, e.g. while the node's storage is migrated
+//! ([`TapNode::pause_intake`](crate::TapNode::pause_intake)). Messages
+//! received while it is paused are rejected as [`RejectionCode::Paused`];
+//! those already queued are still processed.
+
+use crate::error::Error;
+use serde::Serialize;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// What became of a received message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "outcome")]
+pub enum IngestOutcome {
+    /// The message was processed
+    Accepted {
+        /// ID of the message (not known for a JWE, which is decrypted by the
+        /// agent it is addressed to)
+        message_id: Option<String>,
+    },
+    /// The node is at its processing limit; the message will be processed
+    /// once the messages ahead of it are
+    Queued {
+        /// Place of the message in the queue, starting at 1
+        position: usize,
+    },
+    /// The message was for none of the node's agents and was passed to its
+    /// upstream node (see [`crate::federation`])
+    Forwarded {
+        /// ID of the message (not known for a JWE)
+        message_id: Option<String>,
+    },
+    /// The message was not processed
+    Rejected { code: RejectionCode, reason: String },
+}
+
+impl IngestOutcome {
+    /// Whether the message was processed or will be
+    pub fn is_accepted(&self) -> bool {
+        !matches!(self, IngestOutcome::Rejected { .. })
+    }
+
+    /// The outcome of a message whose processing failed with `error`, or the
+    /// error itself if it is the node's fault rather than the message's
+    pub(crate) fn from_error(error: Error) -> crate::Result<Self> {
+        let (code, reason) = match error {
+            Error::Serialization(reason) => (RejectionCode::Malformed, reason),
+            Error::Validation(reason) => (RejectionCode::Invalid, reason),
+            Error::Verification(reason) => (RejectionCode::Unverified, reason),
+            error => return Err(error),
+        };
+        Ok(IngestOutcome::Rejected { code, reason })
+    }
+}
+
+/// Why a received message was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectionCode {
+    /// The message could not be parsed
+    Malformed,
+    /// The message failed validation, e.g. it has expired or is a duplicate
+    Invalid,
+    /// The message's signature could not be verified
+    Unverified,
+    /// The node is at its processing limit and its queue is full; the
+    /// message can be sent again later
+    Overloaded,
+    /// The message was to be forwarded to the node's upstream node, which
+    /// could not take it; the message can be sent again later
+    UpstreamUnavailable,
+    /// The node's intake is paused; the message can be sent again later
+    Paused,
+}
+
+impl RejectionCode {
+    /// Snake-case name of the code, e.g. `overloaded`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RejectionCode::Malformed => "malformed",
+            RejectionCode::Invalid => "invalid",
+            RejectionCode::Unverified => "unverified",
+            RejectionCode::Overloaded => "overloaded",
+            RejectionCode::UpstreamUnavailable => "upstream_unavailable",
+            RejectionCode::Paused => "paused",
+        }
+    }
+
+    /// Whether the same message may be accepted if sent again later
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            RejectionCode::Overloaded | RejectionCode::UpstreamUnavailable | RejectionCode::Paused
+        )
+    }
+}
+
+impl fmt::Display for RejectionCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Bounds on the received messages processed at once
+#[derive(Debug, Clone, Default)]
+pub struct IntakeLimits {
+    /// Messages processed at once (None for no limit)
+    pub max_concurrent: Option<usize>,
+    /// Messages waiting for processing once `max_concurrent` is reached;
+    /// further messages are rejected as overloaded
+    pub max_queued: usize,
+}
+
+impl IntakeLimits {
+    /// Process at most `max_concurrent` messages at once, queueing up to
+    /// `max_queued` more
+    pub fn new(max_concurrent: usize, max_queued: usize) -> Self {
+        Self {
+            max_concurrent: Some(max_concurrent),
+            max_queued,
+        }
+    }
+}
+
+/// Admission of received messages under the node's [`IntakeLimits`]
+#[derive(Debug, Clone)]
+pub(crate) struct Intake {
+    permits: Option<Arc<Semaphore>>,
+    queued: Arc<AtomicUsize>,
+    max_queued: usize,
+    paused: Arc<AtomicBool>,
+}
+
+/// Whether a received message may be processed
+pub(crate) enum Admission {
+    /// Process it now, holding the permit if there is a limit
+    Now(Option<OwnedSemaphorePermit>),
+    /// Process it once the ticket is admitted
+    Queued(QueueTicket),
+    /// Reject it, the queue is full
+    Full,
+    /// Reject it, the intake is paused
+    Paused,
+}
+
+/// A message's place in the intake queue
+pub(crate) struct QueueTicket {
+    permits: Arc<Semaphore>,
+    queued: Arc<AtomicUsize>,
+    pub position: usize,
+}
+
+impl QueueTicket {
+    /// Wait for the message's turn to be processed
+    pub async fn admitted(self) -> Option<OwnedSemaphorePermit> {
+        let permit = self.permits.acquire_owned().await.ok();
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+        permit
+    }
+}
+
+impl Intake {
+    pub fn new(limits: &IntakeLimits) -> Self {
+        Self {
+            permits: limits
+                .max_concurrent
+                .map(|max| Arc::new(Semaphore::new(max.max(1)))),
+            queued: Arc::new(AtomicUsize::new(0)),
+            max_queued: limits.max_queued,
+            paused: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Pause or resume the admission of messages
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::SeqCst);
+    }
+
+    /// Whether the admission of messages is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Number of messages waiting for a permit
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    /// Admit a received message
+    pub fn admit(&self) -> Admission {
+        if self.is_paused() {
+            return Admission::Paused;
+        }
+        let Some(permits) = &self.permits else {
+            return Admission::Now(None);
+        };
+        // Queued messages go first
+        if self.queued.load(Ordering::SeqCst) == 0 {
+            if let Ok(permit) = permits.clone().try_acquire_owned() {
+                return Admission::Now(Some(permit));
+            }
+        }
+        let claimed = self
+            .queued
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |queued| {
+                (queued < self.max_queued).then_some(queued + 1)
+            });
+        match claimed {
+            Ok(ahead) => Admission::Queued(QueueTicket {
+                permits: permits.clone(),
+                queued: self.queued.clone(),
+                position: ahead + 1,
+            }),
+            Err(_) => Admission::Full,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejections_from_errors() {
+        assert_eq!(
+            IngestOutcome::from_error(Error::Validation("expired".to_string())).unwrap(),
+            IngestOutcome::Rejected {
+                code: RejectionCode::Invalid,
+                reason: "expired".to_string()
+            }
+        );
+        assert!(IngestOutcome::from_error(Error::Storage("disk full".to_string())).is_err());
+        assert!(!RejectionCode::Unverified.is_retryable());
+        assert!(RejectionCode::Overloaded.is_retryable());
+        assert!(RejectionCode::UpstreamUnavailable.is_retryable());
+        assert!(RejectionCode::Paused.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_intake_queues_then_rejects() {
+        let intake = Intake::new(&IntakeLimits::new(1, 2));
+
+        let Admission::Now(Some(permit)) = intake.admit() else {
+            panic!("First message should be processed now");
+        };
+        let Admission::Queued(first) = intake.admit() else {
+            panic!("Second message should be queued");
+        };
+        let Admission::Queued(second) = intake.admit() else {
+            panic!("Third message should be queued");
+        };
+        assert_eq!((first.position, second.position), (1, 2));
+        assert!(matches!(intake.admit(), Admission::Full));
+
+        drop(permit);
+        let permit = first.admitted().await;
+        assert!(permit.is_some());
+        // The queue has room again, behind the message still waiting
+        let Admission::Queued(third) = intake.admit() else {
+            panic!("Message should be queued behind the waiting one");
+        };
+        assert_eq!(third.position, 2);
+
+        assert!(matches!(
+            Intake::new(&IntakeLimits::default()).admit(),
+            Admission::Now(None)
+        ));
+    }
+
+    #[test]
+    fn test_paused_intake_rejects() {
+        let intake = Intake::new(&IntakeLimits::default());
+        intake.set_paused(true);
+        assert!(matches!(intake.admit(), Admission::Paused));
+        intake.set_paused(false);
+        assert!(matches!(intake.admit(), Admission::Now(None)));
+    }
+}
@@ -0,0 +1,400 @@
+//! Envelope statistics of counterparties, for debugging interoperability
+//!
+//! Counterparties do not all pack messages the same way: some send compact
+//! JWS, others flattened JSON, some encrypt with `A256CBC-HS512` and others
+//! with `A256GCM`, and their messages may use an older schema version. When
+//! their messages fail, the node's logs only show the failures. The node
+//! therefore records, for each counterparty, every distinct
+//! [`EnvelopeProfile`] it received from it (envelope kind and
+//! serialization, algorithms, media type and schema version) with how many
+//! messages arrived in that form, how many of them failed and the last
+//! error, so a mismatch can be diagnosed without capturing traffic.
+//!
+//! Envelopes are read with [`sniff_envelope`] before the message is
+//! processed; nothing they report is verified. Statistics are kept in the
+//! node's storage and listed with [`TapNode::envelope_stats`], or
+//! `tap-cli report envelopes`.
+//!
+//! ```
+//! use serde_json::json;
+//! use tap_node::interop::{sniff_envelope, EnvelopeSerialization};
+//! use tap_node::message::EnvelopeKind;
+//!
+//! let sample = sniff_envelope(&json!({
+//!     "id": "msg-1",
+//!     "typ": "application/didcomm-plain+json",
+//!     "type": "https://tap.rsvp/schema/1.0#Transfer",
+//!     "from": "did:example:them",
+//!     "to": ["did:example:us"],
+//!     "body": {}
+//! }))
+//! .unwrap();
+//! assert_eq!(sample.counterparty_did.as_deref(), Some("did:example:them"));
+//! assert_eq!(sample.profile.kind, EnvelopeKind::Plain);
+//! assert_eq!(sample.profile.serialization, EnvelopeSerialization::Json);
+//! assert_eq!(
+//!     sample.profile.schema_version.as_deref(),
+//!     Some("https://tap.rsvp/schema/1.0")
+//! );
+//! ```
+
+use crate::error::{Error, Result};
+use crate::message::{inspect_envelope, EnvelopeKind};
+use crate::TapNode;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tap_agent::message::base64_decode_flexible;
+
+/// Counterparty recorded for envelopes that do not reveal their sender, like
+/// an anoncrypt JWE
+pub const UNKNOWN_COUNTERPARTY: &str = "unknown";
+
+/// How an envelope is serialized
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvelopeSerialization {
+    /// General JSON serialization of a JWS or JWE, or a plain message
+    Json,
+    /// Flattened JSON serialization of a JWS or JWE
+    Flattened,
+    /// Compact serialization of a JWS or JWE
+    Compact,
+}
+
+impl EnvelopeSerialization {
+    /// Snake-case name of the serialization, e.g. `compact`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EnvelopeSerialization::Json => "json",
+            EnvelopeSerialization::Flattened => "flattened",
+            EnvelopeSerialization::Compact => "compact",
+        }
+    }
+}
+
+impl TryFrom<&str> for EnvelopeSerialization {
+    type Error = String;
+
+    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
+        match value {
+            "json" => Ok(EnvelopeSerialization::Json),
+            "flattened" => Ok(EnvelopeSerialization::Flattened),
+            "compact" => Ok(EnvelopeSerialization::Compact),
+            _ => Err(format!("Invalid envelope serialization: {}", value)),
+        }
+    }
+}
+
+/// The form of a received envelope
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EnvelopeProfile {
+    pub kind: EnvelopeKind,
+    pub serialization: EnvelopeSerialization,
+    /// Signature algorithm of a JWS, or key management algorithm of a JWE.
+    /// Distinct algorithms of several signatures or recipients are listed
+    /// separated by commas.
+    pub alg: Option<String>,
+    /// Content encryption algorithm of a JWE
+    pub enc: Option<String>,
+    /// `typ` of the protected header, or of a plain message
+    pub media_type: Option<String>,
+    /// Message type URI up to the fragment, e.g.
+    /// `https://tap.rsvp/schema/1.0` (not known for a JWE)
+    pub schema_version: Option<String>,
+}
+
+/// An envelope profile and the counterparty it was received from
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvelopeSample {
+    /// DID the envelope claims to be from, if it reveals one
+    pub counterparty_did: Option<String>,
+    pub profile: EnvelopeProfile,
+}
+
+/// Messages received from a counterparty in one envelope profile
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EnvelopeStats {
+    /// DID of the counterparty, or [`UNKNOWN_COUNTERPARTY`]
+    pub counterparty_did: String,
+    #[serde(flatten)]
+    pub profile: EnvelopeProfile,
+    /// Messages received in this profile
+    pub messages: u64,
+    /// Messages in this profile that were rejected or failed to process
+    pub failures: u64,
+    /// Error of the last failed message
+    pub last_error: Option<String>,
+    pub first_seen_at: String,
+    pub last_seen_at: String,
+}
+
+/// Read the envelope profile and sender of a received message, as received
+///
+/// Compact serializations are recognized when the message is still the JSON
+/// string it was received as. Returns None if the message is neither a JSON
+/// object nor a compact JWS or JWE.
+pub fn sniff_envelope(message: &Value) -> Option<EnvelopeSample> {
+    let mut headers = Vec::new();
+    let (kind, serialization) = match message {
+        Value::String(compact) => {
+            let segments: Vec<&str> = compact.trim().split('.').collect();
+            let kind = match segments.len() {
+                3 => EnvelopeKind::Signed,
+                5 => EnvelopeKind::Encrypted,
+                _ => return None,
+            };
+            headers.extend(decode_header(segments[0]));
+            (kind, EnvelopeSerialization::Compact)
+        }
+        Value::Object(envelope) if envelope.contains_key("payload") => {
+            match envelope.get("signatures").and_then(Value::as_array) {
+                Some(signatures) => {
+                    headers.extend(
+                        signatures
+                            .iter()
+                            .filter_map(|signature| signature["protected"].as_str())
+                            .filter_map(decode_header),
+                    );
+                    (EnvelopeKind::Signed, EnvelopeSerialization::Json)
+                }
+                None => {
+                    headers.extend(
+                        envelope
+                            .get("protected")
+                            .and_then(Value::as_str)
+                            .and_then(decode_header),
+                    );
+                    (EnvelopeKind::Signed, EnvelopeSerialization::Flattened)
+                }
+            }
+        }
+        Value::Object(envelope) if envelope.contains_key("ciphertext") => {
+            headers.extend(
+                envelope
+                    .get("protected")
+                    .and_then(Value::as_str)
+                    .and_then(decode_header),
+            );
+            match envelope.get("recipients").and_then(Value::as_array) {
+                Some(recipients) => {
+                    headers.extend(
+                        recipients
+                            .iter()
+                            .filter_map(|recipient| recipient["header"].as_object().cloned()),
+                    );
+                    (EnvelopeKind::Encrypted, EnvelopeSerialization::Json)
+                }
+                None => {
+                    headers.extend(envelope.get("header").and_then(Value::as_object).cloned());
+                    (EnvelopeKind::Encrypted, EnvelopeSerialization::Flattened)
+                }
+            }
+        }
+        Value::Object(_) => (EnvelopeKind::Plain, EnvelopeSerialization::Json),
+        _ => return None,
+    };
+
+    let header_values = |name: &str| {
+        let mut values: Vec<&str> = Vec::new();
+        for value in headers
+            .iter()
+            .filter_map(|header| header.get(name).and_then(Value::as_str))
+        {
+            if !values.contains(&value) {
+                values.push(value);
+            }
+        }
+        (!values.is_empty()).then(|| values.join(","))
+    };
+    let media_type = match kind {
+        EnvelopeKind::Plain => message["typ"].as_str().map(String::from),
+        _ => header_values("typ"),
+    };
+
+    // Undecodable messages are still counted, without a sender
+    let info = inspect_envelope(message).ok();
+    let counterparty_did = info.as_ref().and_then(|info| {
+        info.from.clone().or_else(|| {
+            info.sender_kids
+                .first()
+                .map(|kid| kid.split('#').next().unwrap_or(kid).to_string())
+        })
+    });
+    let schema_version = info.and_then(|info| info.message_type).map(|message_type| {
+        match message_type.split_once('#') {
+            Some((schema, _)) => schema.to_string(),
+            None => message_type,
+        }
+    });
+
+    Some(EnvelopeSample {
+        counterparty_did,
+        profile: EnvelopeProfile {
+            kind,
+            serialization,
+            alg: header_values("alg"),
+            enc: header_values("enc"),
+            media_type,
+            schema_version,
+        },
+    })
+}
+
+/// Decode a base64url protected header to a JSON object
+fn decode_header(protected: &str) -> Option<Map<String, Value>> {
+    let bytes = base64_decode_flexible(protected).ok()?;
+    match serde_json::from_slice(&bytes) {
+        Ok(Value::Object(header)) => Some(header),
+        _ => None,
+    }
+}
+
+impl TapNode {
+    /// Envelope profiles received from a counterparty, or from all
+    /// counterparties, with their message and failure counts
+    pub async fn envelope_stats(
+        &self,
+        counterparty_did: Option<&str>,
+    ) -> Result<Vec<EnvelopeStats>> {
+        let storage = self
+            .storage
+            .as_ref()
+            .ok_or_else(|| Error::Storage("Storage is not initialized".to_string()))?;
+        storage
+            .list_envelope_stats(counterparty_did)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    /// Count a received message in the statistics of its envelope profile
+    ///
+    /// Failing to record is logged and does not affect the message.
+    pub(crate) async fn record_envelope(&self, sample: &EnvelopeSample, error: Option<&str>) {
+        let Some(storage) = &self.storage else {
+            return;
+        };
+        let counterparty_did = sample
+            .counterparty_did
+            .as_deref()
+            .unwrap_or(UNKNOWN_COUNTERPARTY);
+        if let Err(e) = storage
+            .record_envelope(counterparty_did, &sample.profile, error)
+            .await
+        {
+            log::warn!(
+                "Failed to record envelope statistics for {}: {}",
+                counterparty_did,
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use serde_json::json;
+
+    fn b64(value: Value) -> String {
+        URL_SAFE_NO_PAD.encode(value.to_string())
+    }
+
+    #[test]
+    fn test_sniff_signed_envelopes() {
+        let protected = b64(json!({
+            "typ": "application/didcomm-signed+json",
+            "alg": "EdDSA",
+            "kid": "did:example:them#keys-1"
+        }));
+        let payload = b64(json!({
+            "id": "msg-1",
+            "type": "https://tap.rsvp/schema/1.1#Authorize",
+            "from": "did:example:them",
+            "to": ["did:example:us"],
+            "body": {}
+        }));
+
+        let compact = Value::String(format!("{}.{}.c2ln", protected, payload));
+        let sample = sniff_envelope(&compact).unwrap();
+        assert_eq!(sample.counterparty_did.as_deref(), Some("did:example:them"));
+        assert_eq!(
+            sample.profile,
+            EnvelopeProfile {
+                kind: EnvelopeKind::Signed,
+                serialization: EnvelopeSerialization::Compact,
+                alg: Some("EdDSA".to_string()),
+                enc: None,
+                media_type: Some("application/didcomm-signed+json".to_string()),
+                schema_version: Some("https://tap.rsvp/schema/1.1".to_string()),
+            }
+        );
+
+        let flattened = json!({"payload": payload, "protected": protected, "signature": "c2ln"});
+        let sample = sniff_envelope(&flattened).unwrap();
+        assert_eq!(
+            sample.profile.serialization,
+            EnvelopeSerialization::Flattened
+        );
+        assert_eq!(sample.profile.alg.as_deref(), Some("EdDSA"));
+
+        let es256 = b64(json!({"alg": "ES256", "kid": "did:example:them#keys-2"}));
+        let general = json!({
+            "payload": payload,
+            "signatures": [
+                {"protected": protected, "signature": "c2ln"},
+                {"protected": es256, "signature": "c2ln"},
+                {"protected": protected, "signature": "c2ln"}
+            ]
+        });
+        let sample = sniff_envelope(&general).unwrap();
+        assert_eq!(sample.profile.serialization, EnvelopeSerialization::Json);
+        assert_eq!(sample.profile.alg.as_deref(), Some("EdDSA,ES256"));
+    }
+
+    #[test]
+    fn test_sniff_encrypted_envelopes() {
+        let protected = b64(json!({
+            "typ": "application/didcomm-encrypted+json",
+            "alg": "ECDH-1PU+A256KW",
+            "enc": "A256CBC-HS512",
+            "skid": "did:example:them#keys-1",
+            "epk": {"kty": "OKP", "crv": "X25519", "x": "eHh4"}
+        }));
+        let jwe = json!({
+            "protected": protected,
+            "recipients": [
+                {"encrypted_key": "a2V5", "header": {"kid": "did:example:us#keys-1"}}
+            ],
+            "iv": "aXY",
+            "ciphertext": "Y2lwaGVy",
+            "tag": "dGFn"
+        });
+        let sample = sniff_envelope(&jwe).unwrap();
+        assert_eq!(sample.counterparty_did.as_deref(), Some("did:example:them"));
+        assert_eq!(
+            sample.profile,
+            EnvelopeProfile {
+                kind: EnvelopeKind::Encrypted,
+                serialization: EnvelopeSerialization::Json,
+                alg: Some("ECDH-1PU+A256KW".to_string()),
+                enc: Some("A256CBC-HS512".to_string()),
+                media_type: Some("application/didcomm-encrypted+json".to_string()),
+                schema_version: None,
+            }
+        );
+
+        // Anoncrypt does not reveal the sender
+        let anoncrypt = Value::String(format!(
+            "{}.a2V5.aXY.Y2lwaGVy.dGFn",
+            b64(json!({"alg": "ECDH-ES+A256KW", "enc": "A256GCM"}))
+        ));
+        let sample = sniff_envelope(&anoncrypt).unwrap();
+        assert_eq!(sample.counterparty_did, None);
+        assert_eq!(sample.profile.serialization, EnvelopeSerialization::Compact);
+        assert_eq!(sample.profile.enc.as_deref(), Some("A256GCM"));
+
+        assert!(sniff_envelope(&Value::String("not an envelope".to_string())).is_none());
+        assert!(sniff_envelope(&json!([1, 2])).is_none());
+    }
+}
@@ -84,24 +84,94 @@
 //! }
 //! ```
 
+#[cfg(feature = "storage")]
+pub mod address_reuse;
 pub mod agent;
 #[cfg(feature = "storage")]
+pub mod agent_inbox;
+#[cfg(feature = "storage")]
+pub mod anomaly;
+#[cfg(feature = "storage")]
+pub mod authorization;
+#[cfg(feature = "storage")]
+pub mod backup;
+#[cfg(feature = "native")]
+pub mod callbacks;
+pub mod capabilities;
+pub mod clock;
+#[cfg(feature = "native")]
+pub mod clock_health;
+#[cfg(feature = "storage")]
+pub mod compat;
+pub mod credentials;
+#[cfg(feature = "customers")]
 pub mod customer;
+#[cfg(feature = "delivery-tracking")]
+pub mod delivery;
+pub mod diagnostics;
+#[cfg(feature = "storage")]
+pub mod draft;
+#[cfg(feature = "storage")]
+pub mod ephemeral;
 pub mod error;
 pub mod event;
+#[cfg(all(feature = "native", feature = "storage"))]
+pub mod federation;
+pub mod intake;
+#[cfg(feature = "storage")]
+pub mod interop;
+pub mod log_context;
+#[cfg(feature = "storage")]
+pub mod mailbox;
 pub mod message;
 #[cfg(feature = "storage")]
+pub mod onboarding;
+#[cfg(feature = "storage")]
+pub mod pending_actions;
+#[cfg(feature = "storage")]
+pub mod pickup;
+#[cfg(feature = "storage")]
+pub mod policy;
+#[cfg(feature = "storage")]
+pub mod reconciliation;
+#[cfg(feature = "storage")]
+pub mod reporting;
+#[cfg(feature = "storage")]
+pub mod response_timers;
+#[cfg(feature = "storage")]
+pub mod retention;
+#[cfg(feature = "storage")]
+pub mod scheduler;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+#[cfg(feature = "storage")]
+pub mod settlement_address;
+#[cfg(feature = "storage")]
+pub mod settlement_conversion;
+#[cfg(feature = "native")]
+pub mod simulation;
+#[cfg(feature = "state-machine")]
 pub mod state_machine;
 pub mod storage;
+#[cfg(test)]
+pub(crate) mod test_fixtures;
+#[cfg(feature = "test-harness")]
+pub mod testing;
+pub mod timeouts;
 #[cfg(feature = "storage")]
 pub mod validation;
+#[cfg(feature = "storage")]
+pub mod valuation;
 
+pub use capabilities::{NodeCapabilities, StorageBackend};
 pub use error::{Error, Result};
 pub use event::logger::{EventLogger, EventLoggerConfig, LogDestination};
 pub use event::{EventSubscriber, NodeEvent};
+pub use intake::{IngestOutcome, IntakeLimits, RejectionCode};
+#[cfg(feature = "delivery-tracking")]
+pub use message::sender::HttpPlainMessageSenderWithTracking;
 pub use message::sender::{
-    HttpPlainMessageSender, HttpPlainMessageSenderWithTracking, NodePlainMessageSender,
-    PlainMessageSender, WebSocketPlainMessageSender,
+    HttpPlainMessageSender, NodePlainMessageSender, PlainMessageSender, WebSocketPlainMessageSender,
 };
 #[cfg(feature = "storage")]
 pub use storage::{
@@ -114,6 +184,8 @@ use std::sync::Arc;
 use tap_agent::{Agent, TapAgent};
 // use tap_agent::message_packing::PackOptions;
 use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::problem_report::PROBLEM_REPORT_TYPE;
+use tap_msg::message::{ProblemCode, ProblemReport, ProblemScope};
 
 use crate::message::processor::PlainMessageProcessor;
 use crate::message::{
@@ -126,6 +198,10 @@ use tap_agent::did::MultiResolver;
 
 use async_trait::async_trait;
 
+/// Number of agent storages [`TapNode::register_agents`] initializes at once
+#[cfg(feature = "storage")]
+const AGENT_STORAGE_INIT_CONCURRENCY: usize = 16;
+
 // Extension trait for TapAgent to add serialization methods
 ///
 /// This trait extends the TapAgent with methods for serializing and packing
@@ -197,7 +273,151 @@ pub struct NodeConfig {
     ///   external systems to handle. No automatic action is taken.
     /// - `Custom(handler)`: Delegate to a caller-provided
     ///   [`DecisionHandler`](state_machine::fsm::DecisionHandler).
+    #[cfg(feature = "state-machine")]
     pub decision_mode: state_machine::fsm::DecisionMode,
+    /// Retention policy for customer data (None disables the purger)
+    #[cfg(feature = "storage")]
+    pub retention_policy: Option<retention::RetentionPolicy>,
+    /// Scheduled backups of the node and agent databases (None disables the
+    /// scheduler)
+    #[cfg(feature = "storage")]
+    pub backup_policy: Option<backup::BackupPolicy>,
+    /// Cancellation of transactions still pending past their expiry (None
+    /// leaves them pending)
+    #[cfg(feature = "state-machine")]
+    pub transaction_expiry: Option<state_machine::expiry::ExpiryPolicy>,
+    /// Retries of failed external deliveries (None leaves them failed until
+    /// retried by hand)
+    #[cfg(feature = "delivery-tracking")]
+    pub delivery_retry: Option<delivery::DeliveryRetryPolicy>,
+    /// Detection of anomalies in the transactions counterparties send,
+    /// published as AnomalyDetected events (None disables it)
+    #[cfg(feature = "storage")]
+    pub anomaly_detection: Option<anomaly::AnomalyThresholds>,
+    /// Deadlines for our agents to answer the messages they receive,
+    /// published as ResponseDeadlineApproaching and ResponseDeadlineBreached
+    /// events (None does not time answers)
+    #[cfg(feature = "storage")]
+    pub response_timers: Option<response_timers::ResponseTimerPolicy>,
+    /// Message types whose stored copies are encrypted in agent databases,
+    /// keyed from each agent's key manager (None stores them in the clear)
+    #[cfg(feature = "storage")]
+    pub message_encryption: Option<storage::MessageEncryption>,
+    /// Policy rules evaluated when a new transaction requires authorization
+    #[cfg(feature = "storage")]
+    pub policy_engine: Option<Arc<policy::PolicyEngine>>,
+    /// Tagging of recorded Transfers and Payments with their fiat value (None
+    /// leaves them untagged)
+    #[cfg(feature = "storage")]
+    pub valuation: Option<valuation::ValuationConfig>,
+    /// Source of the settlement addresses sent when auto-authorizing as the
+    /// beneficiary's agent (None sends Authorize without one)
+    #[cfg(feature = "storage")]
+    pub settlement_addresses: Option<Arc<dyn settlement_address::SettlementAddressProvider>>,
+    /// Checking of settlements made in another asset than requested against
+    /// exchange rates (None records no conversions)
+    #[cfg(feature = "storage")]
+    pub settlement_conversion: Option<settlement_conversion::SettlementConversionConfig>,
+    /// Detection of settlement addresses reused across transactions (None
+    /// does not track addresses)
+    #[cfg(feature = "storage")]
+    pub address_reuse: Option<address_reuse::AddressReuseConfig>,
+    /// Size quotas for agent databases
+    #[cfg(feature = "storage")]
+    pub storage_quotas: storage::StorageQuotas,
+    /// Backend, e.g. a Postgres database several nodes share, that the
+    /// messages, transactions and deliveries of agent databases run on (None
+    /// keeps them in the agent databases only)
+    #[cfg(feature = "storage")]
+    pub shared_storage: Option<Arc<dyn storage::StorageBackend>>,
+    /// Script whose `route` function can override the routing of incoming
+    /// messages
+    #[cfg(feature = "scripting")]
+    pub script_hook: Option<Arc<scripting::ScriptHook>>,
+    /// How settlement addresses that do not match the asset's chain are
+    /// handled, for incoming and outgoing messages
+    #[cfg(feature = "storage")]
+    pub settlement_address_strictness: validation::settlement_address_validator::AddressStrictness,
+    /// Clock used for timestamp validation, expiries and storage timestamps
+    /// (None uses the system clock)
+    pub clock: Option<Arc<dyn clock::Clock>>,
+    /// How far in the future the timestamps of incoming messages may be,
+    /// overall and per counterparty
+    #[cfg(feature = "storage")]
+    pub timestamp_drift: validation::timestamp_validator::DriftAllowances,
+    /// Checks of the local clock against NTP servers on startup and
+    /// periodically (None skips them)
+    #[cfg(feature = "native")]
+    pub clock_health: Option<clock_health::ClockHealthPolicy>,
+    /// Generator of the IDs of the messages and transactions the node
+    /// creates, installed process-wide when the node is created (None keeps
+    /// the current generator, by default random UUID v4s)
+    pub id_generator: Option<Arc<dyn tap_msg::id::IdGenerator>>,
+    /// Hooks that enrich outgoing Transfers and Payments before they are
+    /// signed
+    pub enrichment: Option<Arc<message::EnrichmentPipeline>>,
+    /// Custom message types the node accepts besides TAP and DIDComm types,
+    /// with the handlers they are dispatched to
+    pub message_types: Option<Arc<message::MessageTypeRegistry>>,
+    /// Message body types whose asynchronous validation received messages
+    /// must pass, with lookups answered from the node's storage
+    #[cfg(feature = "storage")]
+    pub async_validators: Option<Arc<validation::async_validator::AsyncValidators>>,
+    /// Groups of agents whose storage can be queried together
+    #[cfg(feature = "storage")]
+    pub agent_groups: Vec<storage::AgentGroup>,
+    /// Record every transaction and delivery change in the state event log
+    /// of each database, from which the tables can be rebuilt
+    #[cfg(feature = "storage")]
+    pub event_sourcing: bool,
+    /// Remote agents that poll the node for their messages instead of
+    /// accepting inbound HTTP
+    #[cfg(feature = "storage")]
+    pub mailbox: mailbox::MailboxConfig,
+    /// Reply with a DIDComm problem report when a message addressed to one of
+    /// our agents fails processing
+    pub problem_reports: bool,
+    /// Answer the Discover Features queries and trust pings that other nodes
+    /// send our agents during an onboarding handshake (see [`onboarding`])
+    #[cfg(feature = "storage")]
+    pub answer_handshakes: bool,
+    /// Where read-only handles for list, search and report queries on agent
+    /// databases are opened
+    #[cfg(feature = "storage")]
+    pub read_replicas: storage::ReadReplicaConfig,
+    /// How databases are checked for corruption when opened, and recovered
+    /// when corrupt (by default corruption fails the opening)
+    #[cfg(feature = "storage")]
+    pub storage_recovery: storage::StorageRecovery,
+    /// Register an agent for every key in the key storage when storage is
+    /// initialized
+    pub auto_register_stored_agents: bool,
+    /// Key storage file, or the directory holding `keys.json`, scanned for
+    /// stored agents (None uses the default, `~/.tap/keys.json`)
+    pub key_storage_path: Option<std::path::PathBuf>,
+    /// Verification of credentials presented by agents in incoming messages
+    /// (None leaves them unchecked)
+    pub credential_verification: Option<credentials::CredentialVerification>,
+    /// Verification of the key attestations sent with incoming messages
+    /// (None leaves them unchecked)
+    pub key_attestation_verification: Option<credentials::KeyAttestationVerification>,
+    /// Verification of the verifiable presentations of incoming Presentation
+    /// messages (None leaves them unchecked)
+    pub presentation_verification: Option<credentials::PresentationVerification>,
+    /// Time budgets for verifying, validating and enriching messages, past
+    /// which their processing is cancelled
+    pub processing_timeouts: timeouts::ProcessingTimeouts,
+    /// Bounds on the received messages processed at once, past which they
+    /// are queued and then rejected (unbounded by default)
+    pub intake: intake::IntakeLimits,
+    /// Upstream node that messages for none of the node's agents are
+    /// forwarded to (None processes them locally), from `init_storage` on
+    #[cfg(all(feature = "native", feature = "storage"))]
+    pub federation: Option<federation::FederationConfig>,
+    /// Schedules and jitter of the node's periodic jobs (expiry, delivery
+    /// retries, response deadlines, retention, backups and clock checks)
+    #[cfg(feature = "storage")]
+    pub scheduler: scheduler::SchedulerConfig,
 }
 
 /// # The TAP Node
@@ -242,6 +462,47 @@ pub struct TapNode {
     resolver: Arc<MultiResolver>,
     /// Worker pool for handling messages
     processor_pool: Option<ProcessorPool>,
+    /// Service endpoints that take precedence over DID resolution
+    service_endpoints: Arc<dashmap::DashMap<String, String>>,
+    /// Remote agents whose messages are held in a mailbox, with the hash of
+    /// their bearer token if they poll for them
+    #[cfg(feature = "storage")]
+    mailboxes: Arc<dashmap::DashMap<String, Option<[u8; 32]>>>,
+    /// Wakes mailbox polls when a message is held
+    #[cfg(feature = "storage")]
+    mailbox_notify: Arc<tokio::sync::Notify>,
+    /// Admission of received messages under the intake limits
+    intake: intake::Intake,
+    /// In-flight messages and processor timings
+    diagnostics: Arc<diagnostics::DiagnosticCounters>,
+    /// Verifier of credentials presented by agents
+    credential_verifier: Option<Arc<credentials::CredentialVerifier>>,
+    /// Verifier of the presentations of Presentation messages
+    #[cfg(feature = "storage")]
+    presentation_verifier: Option<Arc<credentials::PresentationVerifier>>,
+    /// Webhooks receiving node events
+    #[cfg(feature = "native")]
+    webhooks: Arc<event::webhook::WebhookRegistry>,
+    /// Callbacks for the messages of a type delivered to an agent
+    #[cfg(feature = "native")]
+    callbacks: Arc<callbacks::MessageCallbacks>,
+    /// Onboarding handshakes waiting for the replies of counterparties
+    #[cfg(feature = "storage")]
+    reply_listeners: Arc<onboarding::ReplyListeners>,
+    /// Agents registered for a single transaction or thread
+    #[cfg(feature = "storage")]
+    ephemeral_agents: Arc<ephemeral::EphemeralAgents>,
+    /// Compatibility rules of counterparties, see [`compat`]
+    #[cfg(feature = "storage")]
+    compatibility: Arc<compat::CompatibilityCache>,
+    /// Counterparties answering our agents' messages in place of remote
+    /// agents, see [`simulation`]
+    #[cfg(feature = "native")]
+    simulated_counterparties: Arc<dashmap::DashMap<String, simulation::SimulatedCounterparty>>,
+    /// When the node was created; processing recorded before then was
+    /// interrupted, see [`TapNode::reconcile`]
+    #[cfg(feature = "storage")]
+    created_at: chrono::DateTime<chrono::Utc>,
     /// Node configuration
     config: NodeConfig,
     /// Storage for transactions (legacy centralized storage)
@@ -251,13 +512,29 @@ pub struct TapNode {
     #[cfg(feature = "storage")]
     agent_storage_manager: Option<Arc<storage::AgentStorageManager>>,
     /// Transaction state processor
-    #[cfg(feature = "storage")]
+    #[cfg(feature = "state-machine")]
     state_processor: Option<Arc<state_machine::StandardTransactionProcessor>>,
+    /// Clock skew observed in the messages of each counterparty
+    #[cfg(feature = "storage")]
+    clock_skew: Arc<validation::timestamp_validator::ClockSkewTracker>,
+    /// Checks of the local clock against NTP servers
+    #[cfg(feature = "native")]
+    clock_health: Option<clock_health::ClockHealthMonitor>,
+    /// Upstream node of the federation the node belongs to
+    #[cfg(all(feature = "native", feature = "storage"))]
+    upstream: Option<Arc<federation::Upstream>>,
+    /// Scheduler running the node's periodic jobs
+    #[cfg(feature = "storage")]
+    scheduler: Arc<scheduler::Scheduler>,
 }
 
 impl TapNode {
     /// Create a new TAP node with the given configuration
     pub fn new(config: NodeConfig) -> Self {
+        if let Some(generator) = &config.id_generator {
+            tap_msg::id::set_id_generator(generator.clone());
+        }
+
         // Create the agent registry
         let agents = Arc::new(AgentRegistry::new(config.max_agents));
 
@@ -271,40 +548,105 @@ impl TapNode {
 
         // Create the message processors
         let logging_processor = PlainMessageProcessorType::Logging(LoggingPlainMessageProcessor);
-        let validation_processor =
-            PlainMessageProcessorType::Validation(ValidationPlainMessageProcessor);
+        let mut validation_processor = ValidationPlainMessageProcessor::with_clock(
+            config.clock.clone().unwrap_or_else(clock::system_clock),
+        );
+        if let Some(message_types) = &config.message_types {
+            validation_processor = validation_processor.with_message_types(message_types.clone());
+        }
+        let validation_processor = PlainMessageProcessorType::Validation(validation_processor);
         let trust_ping_processor = PlainMessageProcessorType::TrustPing(
             TrustPingProcessor::with_event_bus(event_bus.clone()),
         );
+        let problem_report_processor = PlainMessageProcessorType::ProblemReport(
+            ProblemReportProcessor::with_event_bus(event_bus.clone()),
+        );
         let default_processor = PlainMessageProcessorType::Default(DefaultPlainMessageProcessor);
 
+        let diagnostics = Arc::new(diagnostics::DiagnosticCounters::default());
         let incoming_processor = CompositePlainMessageProcessor::new(vec![
             logging_processor.clone(),
             validation_processor.clone(),
             trust_ping_processor.clone(),
+            problem_report_processor.clone(),
             default_processor.clone(),
-        ]);
+        ])
+        .with_timings(diagnostics.timings.clone());
 
         let outgoing_processor = CompositePlainMessageProcessor::new(vec![
             logging_processor,
             validation_processor,
             trust_ping_processor,
+            problem_report_processor,
             default_processor,
-        ]);
+        ])
+        .with_timings(diagnostics.timings.clone());
 
         // Create the resolver
         let resolver = Arc::new(MultiResolver::default());
 
+        let credential_verifier = config.credential_verification.as_ref().map(|verification| {
+            Arc::new(
+                credentials::CredentialVerifier::new(
+                    verification.trusted_issuers.clone(),
+                    resolver.clone(),
+                )
+                .with_clock(config.clock.clone().unwrap_or_else(clock::system_clock)),
+            )
+        });
+        #[cfg(feature = "storage")]
+        let presentation_verifier = config
+            .presentation_verification
+            .as_ref()
+            .map(|verification| {
+                Arc::new(
+                    credentials::PresentationVerifier::new(verification.clone(), resolver.clone())
+                        .with_clock(config.clock.clone().unwrap_or_else(clock::system_clock)),
+                )
+            });
+
         // Storage will be initialized on first use
         #[cfg(feature = "storage")]
         let storage = None;
         #[cfg(feature = "storage")]
-        let agent_storage_manager = Some(Arc::new(storage::AgentStorageManager::new(
-            config.tap_root.clone(),
-        )));
-        #[cfg(feature = "storage")]
+        let agent_storage_manager = {
+            let manager = storage::AgentStorageManager::new(config.tap_root.clone())
+                .with_quotas(config.storage_quotas.clone())
+                .with_clock(config.clock.clone().unwrap_or_else(clock::system_clock))
+                .with_event_sourcing(config.event_sourcing)
+                .with_read_replicas(config.read_replicas.clone())
+                .with_recovery(config.storage_recovery.clone())
+                .with_event_bus(event_bus.clone());
+            let manager = match config.message_encryption.clone() {
+                Some(policy) => manager.with_message_encryption(policy),
+                None => manager,
+            };
+            let manager = match config.shared_storage.clone() {
+                Some(backend) => manager.with_shared_storage(backend),
+                None => manager,
+            };
+            for group in &config.agent_groups {
+                if let Err(e) = manager.define_group(group.clone()) {
+                    log::warn!("Ignoring agent group {}: {}", group.name, e);
+                }
+            }
+            Some(Arc::new(manager))
+        };
+        #[cfg(feature = "state-machine")]
         let state_processor = None;
 
+        #[cfg(feature = "native")]
+        let clock_health = config.clock_health.clone().map(|policy| {
+            clock_health::ClockHealthMonitor::new(policy)
+                .with_clock(config.clock.clone().unwrap_or_else(clock::system_clock))
+        });
+
+        #[cfg(feature = "storage")]
+        let scheduler = Arc::new(
+            scheduler::Scheduler::new(config.scheduler.clone())
+                .with_clock(config.clock.clone().unwrap_or_else(clock::system_clock)),
+        );
+
         let node = Self {
             agents,
             event_bus,
@@ -313,15 +655,56 @@ impl TapNode {
             router,
             resolver,
             processor_pool: None,
+            service_endpoints: Arc::new(dashmap::DashMap::new()),
+            #[cfg(feature = "storage")]
+            mailboxes: Arc::new(dashmap::DashMap::new()),
+            #[cfg(feature = "storage")]
+            mailbox_notify: Arc::new(tokio::sync::Notify::new()),
+            intake: intake::Intake::new(&config.intake),
+            diagnostics,
+            credential_verifier,
+            #[cfg(feature = "storage")]
+            presentation_verifier,
+            #[cfg(feature = "native")]
+            webhooks: Arc::new(event::webhook::WebhookRegistry::new()),
+            #[cfg(feature = "native")]
+            callbacks: Arc::new(callbacks::MessageCallbacks::new()),
+            #[cfg(feature = "storage")]
+            reply_listeners: Arc::new(onboarding::ReplyListeners::default()),
+            #[cfg(feature = "storage")]
+            ephemeral_agents: Arc::new(ephemeral::EphemeralAgents::default()),
+            #[cfg(feature = "storage")]
+            compatibility: Arc::new(compat::CompatibilityCache::default()),
+            #[cfg(feature = "native")]
+            simulated_counterparties: Arc::new(dashmap::DashMap::new()),
+            #[cfg(feature = "storage")]
+            created_at: config
+                .clock
+                .clone()
+                .unwrap_or_else(clock::system_clock)
+                .now(),
             config,
             #[cfg(feature = "storage")]
             storage,
             #[cfg(feature = "storage")]
             agent_storage_manager,
-            #[cfg(feature = "storage")]
+            #[cfg(feature = "state-machine")]
             state_processor,
+            #[cfg(feature = "storage")]
+            clock_skew: Arc::new(validation::timestamp_validator::ClockSkewTracker::new()),
+            #[cfg(feature = "native")]
+            clock_health,
+            #[cfg(all(feature = "native", feature = "storage"))]
+            upstream: None,
+            #[cfg(feature = "storage")]
+            scheduler,
         };
 
+        #[cfg(feature = "storage")]
+        for recipient in &node.config.mailbox.recipients {
+            node.register_mailbox(recipient.clone());
+        }
+
         // Set up the event logger if configured
         if let Some(logger_config) = &node.config.event_logger {
             let event_logger = Arc::new(EventLogger::new(logger_config.clone()));
@@ -329,11 +712,16 @@ impl TapNode {
             // We need to handle the async subscribe in a blocking context
             // This is safe because EventBus methods are designed to be called in this way
             let event_bus = node.event_bus.clone();
+            #[cfg(feature = "native")]
             tokio::task::block_in_place(|| {
                 tokio::runtime::Handle::current().block_on(async {
                     event_bus.subscribe(event_logger).await;
                 })
             });
+            // Without a multi-threaded runtime the subscription, which only
+            // takes an uncontended lock, completes on the spot
+            #[cfg(not(feature = "native"))]
+            futures::executor::block_on(event_bus.subscribe(event_logger));
         }
 
         node
@@ -342,36 +730,43 @@ impl TapNode {
     /// Initialize storage asynchronously
     #[cfg(feature = "storage")]
     pub async fn init_storage(&mut self) -> Result<()> {
-        let storage = if let Some(agent_did) = &self.config.agent_did {
+        #[cfg(feature = "native")]
+        if let Some(federation) = self.config.federation.clone() {
+            self.upstream = Some(Arc::new(federation::Upstream::new(federation)?));
+        }
+
+        let storage_path = match &self.config.agent_did {
             // Use new DID-based storage structure
-            match storage::Storage::new_with_did(agent_did, self.config.tap_root.clone()).await {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("Failed to initialize storage with DID: {}", e);
-                    return Err(Error::Storage(e.to_string()));
-                }
-            }
-        } else if let Some(storage_path) = self.config.storage_path.clone() {
-            // Use explicit path
-            match storage::Storage::new(Some(storage_path)).await {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("Failed to initialize storage: {}", e);
-                    return Err(Error::Storage(e.to_string()));
+            Some(agent_did) => Some(
+                storage::Storage::agent_db_location(agent_did, self.config.tap_root.clone())
+                    .map_err(|e| Error::Storage(e.to_string()))?,
+            ),
+            // Use explicit path, or the default one
+            None => self.config.storage_path.clone(),
+        };
+        let storage =
+            match storage::Storage::open_checked(storage_path, &self.config.storage_recovery).await
+            {
+                Ok((storage, report)) => {
+                    if let Some(report) = report {
+                        storage::recovery::publish_report(&self.event_bus, &report).await;
+                    }
+                    storage
                 }
-            }
-        } else {
-            // Initialize with default path
-            match storage::Storage::new(None).await {
-                Ok(s) => s,
                 Err(e) => {
+                    if let storage::StorageError::Corrupted(report) = &e {
+                        storage::recovery::publish_report(&self.event_bus, report).await;
+                    }
                     log::error!("Failed to initialize storage: {}", e);
                     return Err(Error::Storage(e.to_string()));
                 }
-            }
-        };
+            };
 
-        let storage_arc = Arc::new(storage);
+        let storage_arc = Arc::new(
+            storage
+                .with_clock(self.clock())
+                .with_event_sourcing(self.config.event_sourcing),
+        );
 
         // Subscribe event handlers
         let message_status_handler = Arc::new(event::handlers::MessageStatusHandler::new(
@@ -387,16 +782,96 @@ impl TapNode {
         let transaction_audit_handler = Arc::new(event::handlers::TransactionAuditHandler::new());
         self.event_bus.subscribe(transaction_audit_handler).await;
 
-        // Create state processor with configured decision mode
-        let state_processor = Arc::new(state_machine::StandardTransactionProcessor::new(
-            storage_arc.clone(),
-            self.event_bus.clone(),
-            self.agents.clone(),
-            self.config.decision_mode.clone(),
-        ));
+        #[cfg(feature = "state-machine")]
+        {
+            let thread_key_handler =
+                Arc::new(event::handlers::ThreadKeyHandler::new(self.agents.clone()));
+            self.event_bus.subscribe(thread_key_handler).await;
+        }
+
+        #[cfg(feature = "state-machine")]
+        let state_processor = self.new_state_processor(&storage_arc);
+
+        self.scheduler.set_storage(&storage_arc);
+
+        #[cfg(feature = "state-machine")]
+        if let Some(policy) = self.config.transaction_expiry.clone() {
+            state_machine::expiry::ExpirySweeper::new(policy)
+                .with_clock(self.clock())
+                .schedule(&self.scheduler, &state_processor)
+                .await;
+        }
+
+        #[cfg(feature = "delivery-tracking")]
+        if let (Some(policy), Some(manager)) = (
+            self.config.delivery_retry.clone(),
+            self.agent_storage_manager.as_ref(),
+        ) {
+            delivery::DeliveryRetrier::new(policy)
+                .with_clock(self.clock())
+                .schedule(&self.scheduler, &self.agents, manager)
+                .await;
+        }
+
+        if let (Some(thresholds), Some(manager)) = (
+            self.config.anomaly_detection.clone(),
+            self.agent_storage_manager.as_ref(),
+        ) {
+            anomaly::AnomalyDetector::new(thresholds)
+                .with_clock(self.clock())
+                .spawn(&self.event_bus, manager);
+        }
+
+        if let Some(policy) = self.config.response_timers.clone() {
+            response_timers::ResponseTimerMonitor::new(policy)
+                .with_clock(self.clock())
+                .schedule(&self.scheduler, &storage_arc, &self.event_bus)
+                .await;
+        }
+
+        #[cfg(feature = "native")]
+        if let Some(monitor) = self.clock_health.clone() {
+            monitor.schedule(&self.scheduler, &self.event_bus).await;
+        }
+
+        if let Some(policy) = self.config.retention_policy.clone() {
+            retention::RetentionPurger::new(policy)
+                .with_clock(self.clock())
+                .schedule(
+                    &self.scheduler,
+                    Some(&storage_arc),
+                    self.agent_storage_manager.as_ref(),
+                )
+                .await;
+        }
+
+        if let Some(policy) = self.config.backup_policy.clone() {
+            backup::BackupScheduler::new(policy)
+                .with_clock(self.clock())
+                .schedule(
+                    &self.scheduler,
+                    Some(&storage_arc),
+                    self.agent_storage_manager.as_ref(),
+                )
+                .await;
+        }
+        self.scheduler.start();
 
         self.storage = Some(storage_arc);
-        self.state_processor = Some(state_processor);
+        #[cfg(feature = "state-machine")]
+        {
+            self.state_processor = Some(state_processor);
+        }
+
+        if self.config.auto_register_stored_agents {
+            if let Err(e) = self.register_stored_agents().await {
+                log::warn!("Could not register stored agents: {}", e);
+            }
+        }
+
+        if let Err(e) = self.reconcile().await {
+            log::warn!("Could not reconcile interrupted processing: {}", e);
+        }
         Ok(())
     }
 
@@ -420,11 +895,17 @@ impl TapNode {
     ///
     /// * `message` - The message as a JSON Value (can be plain, JWS, or JWE)
     ///
+    /// Messages are processed within the node's
+    /// [`IntakeLimits`](intake::IntakeLimits): once it is processing as many
+    /// messages as allowed, the message is queued and processed in the
+    /// background, and once the queue is full it is rejected.
+    ///
     /// # Returns
     ///
-    /// * `Ok(())` if the message was successfully processed
-    /// * `Err(Error)` if there was an error during processing
-    pub async fn receive_message(&self, message: serde_json::Value) -> Result<()> {
+    /// * `Ok(IngestOutcome)` telling whether the message was accepted, queued
+    ///   or rejected, and why it was rejected
+    /// * `Err(Error)` if the node failed to process the message
+    pub async fn receive_message(&self, message: serde_json::Value) -> Result<IngestOutcome> {
         // Default to internal source when no source is specified
         self.receive_message_from_source(message, storage::SourceType::Internal, None)
             .await
@@ -443,14 +924,156 @@ impl TapNode {
     ///
     /// # Returns
     ///
-    /// * `Ok(())` if the message was successfully processed
-    /// * `Err(Error)` if there was an error during processing
+    /// * `Ok(IngestOutcome)` telling whether the message was accepted, queued
+    ///   or rejected, and why it was rejected
+    /// * `Err(Error)` if the node failed to process the message
     pub async fn receive_message_from_source(
         &self,
         message: serde_json::Value,
         source_type: storage::SourceType,
         source_identifier: Option<&str>,
-    ) -> Result<()> {
+    ) -> Result<IngestOutcome> {
+        self.receive_forwarded_message(message, source_type, source_identifier, Vec::new())
+            .await
+    }
+
+    /// Receive and process a message that other nodes forwarded to this one
+    ///
+    /// Nodes of a federation forward the messages for none of their agents
+    /// to their upstream node (see [`federation`]), naming themselves in the
+    /// `TAP-Forwarded-By` header. Passing them on lets this node refuse to
+    /// forward a message in a loop.
+    ///
+    /// # Parameters
+    ///
+    /// * `message` - The message as a JSON Value (can be plain, JWS, or JWE)
+    /// * `source_type` - The type of source (https, internal, websocket, etc.)
+    /// * `source_identifier` - Optional identifier for the source (URL, agent DID, etc.)
+    /// * `forwarded_by` - IDs of the nodes that forwarded the message, in order
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(IngestOutcome)` telling whether the message was accepted, queued,
+    ///   forwarded or rejected, and why it was rejected
+    /// * `Err(Error)` if the node failed to process the message
+    pub async fn receive_forwarded_message(
+        &self,
+        message: serde_json::Value,
+        source_type: storage::SourceType,
+        source_identifier: Option<&str>,
+        forwarded_by: Vec<String>,
+    ) -> Result<IngestOutcome> {
+        let ticket = match self.intake.admit() {
+            intake::Admission::Now(_permit) => {
+                return self
+                    .ingest(message, source_type, source_identifier, &forwarded_by)
+                    .await;
+            }
+            intake::Admission::Queued(ticket) => ticket,
+            intake::Admission::Full => {
+                return Ok(IngestOutcome::Rejected {
+                    code: RejectionCode::Overloaded,
+                    reason: "Too many messages are being processed".to_string(),
+                });
+            }
+            intake::Admission::Paused => {
+                return Ok(IngestOutcome::Rejected {
+                    code: RejectionCode::Paused,
+                    reason: "The node is not accepting messages".to_string(),
+                });
+            }
+        };
+
+        let position = ticket.position;
+        let node = self.clone();
+        let source_identifier = source_identifier.map(String::from);
+        tokio::spawn(async move {
+            let _permit = ticket.admitted().await;
+            match node
+                .ingest(
+                    message,
+                    source_type,
+                    source_identifier.as_deref(),
+                    &forwarded_by,
+                )
+                .await
+            {
+                Ok(IngestOutcome::Rejected { code, reason }) => {
+                    log::warn!("Queued message rejected ({}): {}", code, reason);
+                }
+                Ok(_) => {}
+                Err(e) => log::error!("Failed to process queued message: {}", e),
+            }
+        });
+        Ok(IngestOutcome::Queued { position })
+    }
+
+    /// Stop accepting received messages, e.g. while migrating storage
+    ///
+    /// Messages received from now on are rejected as
+    /// [`RejectionCode::Paused`] so their senders retry later. Messages
+    /// already accepted or queued are still processed, and the node's own
+    /// agents keep sending.
+    pub fn pause_intake(&self) {
+        self.intake.set_paused(true);
+        log::info!("Intake of received messages paused");
+    }
+
+    /// Accept received messages again after [`TapNode::pause_intake`]
+    pub fn resume_intake(&self) {
+        self.intake.set_paused(false);
+        log::info!("Intake of received messages resumed");
+    }
+
+    /// Whether the intake of received messages is paused
+    pub fn is_intake_paused(&self) -> bool {
+        self.intake.is_paused()
+    }
+
+    /// Process a received message, rejecting it if it is at fault
+    async fn ingest(
+        &self,
+        message: serde_json::Value,
+        source_type: storage::SourceType,
+        source_identifier: Option<&str>,
+        forwarded_by: &[String],
+    ) -> Result<IngestOutcome> {
+        let _in_flight = self.diagnostics.incoming.enter();
+        #[cfg(all(feature = "native", feature = "storage"))]
+        if let Some(outcome) = self.forward_upstream(&message, forwarded_by).await? {
+            return Ok(outcome);
+        }
+        #[cfg(not(all(feature = "native", feature = "storage")))]
+        let _ = forwarded_by;
+        // Read the envelope before compact serializations are converted
+        #[cfg(feature = "storage")]
+        let envelope = interop::sniff_envelope(&message);
+        let result = self
+            .process_received(message, source_type, source_identifier)
+            .await;
+        #[cfg(feature = "storage")]
+        if let Some(envelope) = &envelope {
+            let error = result.as_ref().err().map(|e| e.to_string());
+            self.record_envelope(envelope, error.as_deref()).await;
+        }
+        match result {
+            Ok(message_id) => Ok(IngestOutcome::Accepted { message_id }),
+            Err(e) => IngestOutcome::from_error(e),
+        }
+    }
+
+    /// Process a received message, returning its ID unless it was a JWE
+    async fn process_received(
+        &self,
+        message: serde_json::Value,
+        source_type: storage::SourceType,
+        source_identifier: Option<&str>,
+    ) -> Result<Option<String>> {
+        // Handle compact JWS and JWE as their JSON serializations
+        let message = tap_agent::message::normalize_envelope(message);
+        #[cfg(not(feature = "storage"))]
+        let _ = (source_type, source_identifier);
+
         // Store the raw message for logging
         let raw_message = serde_json::to_string(&message).ok();
 
@@ -458,7 +1081,7 @@ impl TapNode {
         #[cfg(feature = "storage")]
         let mut received_ids: Vec<(String, i64)> = Vec::new();
 
-        use tap_agent::{verify_jws, Jwe, Jws};
+        use tap_agent::{verify_jws_with_details, Jwe, Jws};
 
         // Determine message type
         let is_encrypted =
@@ -511,9 +1134,17 @@ impl TapNode {
             let jws: Jws = serde_json::from_value(message)
                 .map_err(|e| Error::Serialization(format!("Failed to parse JWS: {}", e)))?;
 
-            let plain_message = verify_jws(&jws, &*self.resolver)
-                .await
+            let (plain_message, verification) = self
+                .within_timeout(
+                    timeouts::ProcessingStage::Verification,
+                    None,
+                    || raw_message.clone().unwrap_or_default(),
+                    verify_jws_with_details(&jws, &*self.resolver),
+                )
+                .await?
                 .map_err(|e| Error::Verification(format!("JWS verification failed: {}", e)))?;
+            #[cfg(not(feature = "storage"))]
+            let _ = verification;
 
             // Store in recipient agents' storage
             #[cfg(feature = "storage")]
@@ -553,11 +1184,47 @@ impl TapNode {
                             received_ids.push((plain_message.from.clone(), id));
                         }
                     }
+
+                    // Record which key verified the message for audit
+                    for (agent_did, _) in &received_ids {
+                        if let Ok(agent_storage) =
+                            storage_manager.get_agent_storage(agent_did).await
+                        {
+                            if let Err(e) = agent_storage
+                                .insert_message_verification(&plain_message.id, &verification)
+                                .await
+                            {
+                                log::warn!(
+                                    "Failed to record verification of message {} for agent {}: {}",
+                                    plain_message.id,
+                                    agent_did,
+                                    e
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+
+            // Record the verifying key in node storage as well, where
+            // validators such as the key attestation check look it up
+            #[cfg(feature = "storage")]
+            if let Some(ref storage) = self.storage {
+                if let Err(e) = storage
+                    .insert_message_verification(&plain_message.id, &verification)
+                    .await
+                {
+                    log::warn!(
+                        "Failed to record verification of message {}: {}",
+                        plain_message.id,
+                        e
+                    );
                 }
             }
 
             // Process the verified plain message
-            let result = self.process_plain_message(plain_message).await;
+            let message_id = plain_message.id.clone();
+            let result = self.process_and_report_problems(plain_message).await;
 
             // Update the received records
             #[cfg(feature = "storage")]
@@ -587,7 +1254,7 @@ impl TapNode {
                 }
             }
 
-            result
+            result.map(|_| Some(message_id))
         } else if is_encrypted {
             // Route encrypted message to each matching agent
             let jwe: Jwe = serde_json::from_value(message.clone())
@@ -623,6 +1290,7 @@ impl TapNode {
 
             // Find agents that match recipients
             let mut processed = false;
+            let mut last_error = None;
             for recipient in &jwe.recipients {
                 if let Some(did) = recipient.header.kid.split('#').next() {
                     if let Ok(agent) = self.agents.get_agent(did).await {
@@ -641,6 +1309,7 @@ impl TapNode {
                                     did,
                                     e
                                 );
+                                last_error = Some(e);
                             }
                         }
                     }
@@ -648,11 +1317,12 @@ impl TapNode {
             }
 
             let result = if !processed {
-                Err(Error::Processing(
-                    "No agent could process the encrypted message".to_string(),
-                ))
+                Err(Error::Processing(match last_error {
+                    Some(e) => format!("No agent could process the encrypted message: {}", e),
+                    None => "No agent could process the encrypted message".to_string(),
+                }))
             } else {
-                Ok(())
+                Ok(None)
             };
 
             // Update the received records for encrypted messages
@@ -731,7 +1401,8 @@ impl TapNode {
                 }
             }
 
-            let result = self.process_plain_message(plain_message).await;
+            let message_id = plain_message.id.clone();
+            let result = self.process_and_report_problems(plain_message).await;
 
             // Update the received records
             #[cfg(feature = "storage")]
@@ -761,61 +1432,288 @@ impl TapNode {
                 }
             }
 
-            result
+            result.map(|_| Some(message_id))
         }
     }
 
-    /// Process a plain message through the pipeline
-    async fn process_plain_message(&self, message: PlainMessage) -> Result<()> {
-        // Validate the message if storage/validation is available
+    /// Report the envelope type, claimed sender, recipients, message type and
+    /// thread of a received message without processing it
+    ///
+    /// The message is not verified or decrypted, no DIDs are resolved and
+    /// nothing is stored or published, so gateways can make routing or
+    /// queueing decisions before handing the message to
+    /// [`TapNode::receive_message`]. See [`message::inspect_envelope`].
+    pub fn inspect_message(&self, message: &serde_json::Value) -> Result<message::EnvelopeInfo> {
+        message::inspect_envelope(message)
+    }
+
+    /// Process a plain message, reporting a failure to its sender if
+    /// [`NodeConfig::problem_reports`] is enabled
+    async fn process_and_report_problems(&self, message: PlainMessage) -> Result<()> {
+        // Bring messages of counterparties that deviate from the
+        // specification into their standard form
         #[cfg(feature = "storage")]
-        {
-            if let Some(ref storage) = self.storage {
-                // Create validator
-                let validator_config = validation::StandardValidatorConfig {
-                    max_timestamp_drift_secs: 60,
-                    storage: storage.clone(),
-                };
-                let validator = validation::create_standard_validator(validator_config).await;
+        let message = self.normalize_inbound(message).await?;
+        let original = self.config.problem_reports.then(|| message.clone());
+        let mut context = log_context::MessageLogContext::for_message(&message);
+        if let Some(agent_did) = message.to.iter().find(|did| self.agents.has_agent(did)) {
+            context = context.with_agent(agent_did.clone());
+        }
+        let result =
+            log_context::in_context(context, "receive", self.process_plain_message(message)).await;
+
+        if let (Err(e), Some(original)) = (&result, original) {
+            let report = Self::problem_report_for(e);
+            if let Err(report_error) = self.send_problem_report(&original, report).await {
+                log::warn!(
+                    "Failed to report problem with message {} to {}: {}",
+                    original.id,
+                    original.from,
+                    report_error
+                );
+            }
+        }
 
-                // Validate the message
-                use crate::validation::{MessageValidator, ValidationResult};
-                match validator.validate(&message).await {
-                    ValidationResult::Accept => {
-                        // Publish accepted event
-                        self.event_bus
-                            .publish_message_accepted(
-                                message.id.clone(),
-                                message.type_.clone(),
-                                message.from.clone(),
-                                message.to.first().cloned().unwrap_or_default(),
-                            )
-                            .await;
-                    }
-                    ValidationResult::Reject(reason) => {
-                        // Publish rejected event
-                        self.event_bus
-                            .publish_message_rejected(
-                                message.id.clone(),
-                                reason.clone(),
-                                message.from.clone(),
-                                message.to.first().cloned().unwrap_or_default(),
-                            )
-                            .await;
+        result
+    }
 
-                        // Return error to stop processing
-                        return Err(Error::Validation(reason));
+    /// Problem report describing a processing failure
+    fn problem_report_for(error: &Error) -> ProblemReport {
+        use tap_msg::message::problem_report::descriptors;
+
+        let (descriptor, comment) = match error {
+            Error::Validation(reason) => (descriptors::MSG, reason.clone()),
+            Error::Verification(reason) => (descriptors::TRUST, reason.clone()),
+            // Internal details stay with us
+            _ => (
+                descriptors::ME,
+                "Message could not be processed".to_string(),
+            ),
+        };
+        ProblemReport::new(
+            ProblemCode::error(ProblemScope::Message, descriptor),
+            &comment,
+        )
+    }
+
+    /// Run a stage of message processing within its configured timeout
+    ///
+    /// If the stage exceeds its budget, it is cancelled, the message is
+    /// dead-lettered if configured, a
+    /// [`NodeEvent::MessageProcessingTimedOut`] is published and
+    /// [`Error::ProcessingTimedOut`] is returned. `raw_message` is only
+    /// called to dead-letter the message.
+    async fn within_timeout<T>(
+        &self,
+        stage: timeouts::ProcessingStage,
+        message_id: Option<&str>,
+        raw_message: impl FnOnce() -> String,
+        future: impl std::future::Future<Output = T>,
+    ) -> Result<T> {
+        let budget = self.config.processing_timeouts.budget(stage);
+        if let Some(output) = timeouts::run_within(budget, future).await {
+            return Ok(output);
+        }
+
+        let timeout_ms = budget.unwrap_or_default().as_millis() as u64;
+        log::warn!(
+            "Processing of message {} timed out during {} after {}ms",
+            message_id.unwrap_or("unknown"),
+            stage,
+            timeout_ms
+        );
+        let dead_lettered = self
+            .dead_letter(stage, message_id, raw_message, timeout_ms)
+            .await;
+        self.event_bus
+            .publish_message_processing_timed_out(
+                message_id.map(String::from),
+                stage,
+                timeout_ms,
+                dead_lettered,
+            )
+            .await;
+
+        Err(Error::ProcessingTimedOut { stage, timeout_ms })
+    }
+
+    /// Keep a message whose processing timed out in the dead letters, if
+    /// configured, returning whether it was kept
+    #[cfg(feature = "storage")]
+    async fn dead_letter(
+        &self,
+        stage: timeouts::ProcessingStage,
+        message_id: Option<&str>,
+        raw_message: impl FnOnce() -> String,
+        timeout_ms: u64,
+    ) -> bool {
+        if !self.config.processing_timeouts.dead_letter {
+            return false;
+        }
+        let Some(ref storage) = self.storage else {
+            log::warn!(
+                "Cannot dead-letter message {} without storage",
+                message_id.unwrap_or("unknown")
+            );
+            return false;
+        };
+
+        let reason = format!("{} timed out after {}ms", stage, timeout_ms);
+        match storage
+            .insert_dead_letter(message_id, &raw_message(), stage, &reason)
+            .await
+        {
+            Ok(_) => true,
+            Err(e) => {
+                log::warn!(
+                    "Failed to dead-letter message {}: {}",
+                    message_id.unwrap_or("unknown"),
+                    e
+                );
+                false
+            }
+        }
+    }
+
+    /// Dead letters require storage
+    #[cfg(not(feature = "storage"))]
+    async fn dead_letter(
+        &self,
+        _stage: timeouts::ProcessingStage,
+        _message_id: Option<&str>,
+        _raw_message: impl FnOnce() -> String,
+        _timeout_ms: u64,
+    ) -> bool {
+        false
+    }
+
+    /// Process a plain message through the pipeline
+    async fn process_plain_message(&self, message: PlainMessage) -> Result<()> {
+        let context = log_context::current()
+            .unwrap_or_else(|| log_context::MessageLogContext::for_message(&message));
+        let script_routes = self.script_routes(&message);
+
+        // Run the validators of custom message types
+        let custom_type = self
+            .config
+            .message_types
+            .as_ref()
+            .and_then(|registry| registry.get(&message.type_))
+            .cloned();
+        if let Some(custom_type) = &custom_type {
+            if let Err(reason) = custom_type.validate(&message) {
+                self.event_bus
+                    .publish_message_rejected(
+                        message.id.clone(),
+                        reason.clone(),
+                        message.from.clone(),
+                        message.to.first().cloned().unwrap_or_default(),
+                    )
+                    .await;
+                return Err(Error::Validation(reason));
+            }
+        }
+
+        // Validate the message if storage/validation is available
+        #[cfg(feature = "storage")]
+        {
+            if let Some(ref storage) = self.storage {
+                // Create validator
+                let validator_config = validation::StandardValidatorConfig {
+                    timestamp_drift: self.config.timestamp_drift.clone(),
+                    skew_tracker: Some(self.clock_skew.clone()),
+                    storage: storage.clone(),
+                    clock: self.clock(),
+                    settlement_address_strictness: self.config.settlement_address_strictness,
+                    credential_verifier: self.credential_verifier.clone(),
+                    reject_invalid_credentials: self
+                        .config
+                        .credential_verification
+                        .as_ref()
+                        .is_some_and(|verification| verification.reject_invalid),
+                    key_attestation_resolver: self
+                        .config
+                        .key_attestation_verification
+                        .as_ref()
+                        .map(|_| self.resolver.clone() as Arc<dyn tap_agent::SyncDIDResolver>),
+                    reject_invalid_key_attestations: self
+                        .config
+                        .key_attestation_verification
+                        .as_ref()
+                        .is_some_and(|verification| verification.reject_invalid),
+                    presentation_verifier: self.presentation_verifier.clone(),
+                    reject_invalid_presentations: self
+                        .config
+                        .presentation_verification
+                        .as_ref()
+                        .is_some_and(|verification| verification.reject_invalid),
+                    async_validators: self.config.async_validators.clone(),
+                };
+                let validator = validation::create_standard_validator(validator_config).await;
+
+                // Validate the message
+                use crate::validation::{MessageValidator, ValidationResult};
+                let validation = self
+                    .within_timeout(
+                        timeouts::ProcessingStage::Validation,
+                        Some(&message.id),
+                        || serde_json::to_string(&message).unwrap_or_default(),
+                        log_context::in_context(
+                            context.clone(),
+                            "validate",
+                            validator.validate(&message),
+                        ),
+                    )
+                    .await?;
+                match validation {
+                    ValidationResult::Accept => {
+                        // Publish accepted event
+                        self.event_bus
+                            .publish_message_accepted(
+                                message.id.clone(),
+                                message.type_.clone(),
+                                message.from.clone(),
+                                message.to.first().cloned().unwrap_or_default(),
+                            )
+                            .await;
+                    }
+                    ValidationResult::Reject(reason) => {
+                        // Publish rejected event
+                        self.event_bus
+                            .publish_message_rejected(
+                                message.id.clone(),
+                                reason.clone(),
+                                message.from.clone(),
+                                message.to.first().cloned().unwrap_or_default(),
+                            )
+                            .await;
+
+                        // Return error to stop processing
+                        return Err(Error::Validation(reason));
                     }
                 }
             }
         }
 
-        // Process message through state machine if available
+        // Time the answers of our agents before the state machine may send
+        // them
         #[cfg(feature = "storage")]
-        {
+        if custom_type.is_none() {
+            self.start_response_timers(&message).await;
+        }
+
+        // Process message through state machine if available
+        #[cfg(feature = "state-machine")]
+        if custom_type.is_none() {
             if let Some(ref state_processor) = self.state_processor {
                 use crate::state_machine::TransactionStateProcessor;
-                if let Err(e) = state_processor.process_message(&message).await {
+                let processed = log_context::in_context(
+                    context.clone(),
+                    "state_machine",
+                    state_processor.process_message(&message),
+                )
+                .await;
+                if let Err(e) = processed {
                     log::warn!("State processor error: {}", e);
                     // Don't fail the entire message processing, just log the error
                 }
@@ -827,8 +1725,9 @@ impl TapNode {
             if let Some(ref storage_manager) = self.agent_storage_manager {
                 // Check if this is a transaction message
                 let message_type_lower = message.type_.to_lowercase();
-                let is_transaction = message_type_lower.contains("transfer")
-                    || message_type_lower.contains("payment");
+                let is_transaction = custom_type.is_none()
+                    && (message_type_lower.contains("transfer")
+                        || message_type_lower.contains("payment"));
                 log::debug!(
                     "Message type: {}, is_transaction: {}",
                     message.type_,
@@ -837,7 +1736,12 @@ impl TapNode {
 
                 if is_transaction {
                     // For transactions, store in ALL involved agents' databases
-                    let involved_agents = self.extract_transaction_agents(&message);
+                    let mut involved_agents = self.extract_transaction_agents(&message);
+                    for agent_did in &script_routes {
+                        if !involved_agents.contains(agent_did) {
+                            involved_agents.push(agent_did.clone());
+                        }
+                    }
 
                     if involved_agents.is_empty() {
                         log::warn!("No registered agents found for transaction: {}", message.id);
@@ -859,9 +1763,8 @@ impl TapNode {
                                     .await
                                 {
                                     Ok(_) => log::debug!(
-                                        "Logged incoming message to agent {}: {}",
-                                        agent_did,
-                                        message.id
+                                        "Logged incoming message to agent {}",
+                                        agent_did
                                     ),
                                     Err(e) => log::warn!(
                                         "Failed to log incoming message for agent {}: {}",
@@ -873,11 +1776,7 @@ impl TapNode {
                                 // Store as transaction
                                 match agent_storage.insert_transaction(&message).await {
                                     Ok(()) => {
-                                        log::debug!(
-                                            "Stored transaction for agent {}: {}",
-                                            agent_did,
-                                            message.id
-                                        );
+                                        log::debug!("Stored transaction for agent {}", agent_did);
 
                                         // Publish TransactionCreated event
                                         // Use the message.id as the reference_id to fetch the transaction
@@ -906,8 +1805,13 @@ impl TapNode {
                 } else {
                     // For non-transaction messages, log to all recipient agents' storage
                     let mut logged_to_any = false;
+                    let recipients = if script_routes.is_empty() {
+                        &message.to
+                    } else {
+                        &script_routes
+                    };
 
-                    for recipient_did in &message.to {
+                    for recipient_did in recipients {
                         // Check if this recipient is a registered agent
                         if self.agents.has_agent(recipient_did) {
                             if let Ok(agent_storage) =
@@ -920,9 +1824,8 @@ impl TapNode {
                                 {
                                     Ok(_) => {
                                         log::debug!(
-                                            "Logged incoming message to recipient {}: {}",
-                                            recipient_did,
-                                            message.id
+                                            "Logged incoming message to recipient {}",
+                                            recipient_did
                                         );
                                         logged_to_any = true;
                                     }
@@ -956,11 +1859,7 @@ impl TapNode {
                                         )
                                         .await
                                     {
-                                        Ok(_) => log::debug!(
-                                            "Logged incoming message to fallback agent {}: {}",
-                                            agent_did,
-                                            message.id
-                                        ),
+                                        Ok(_) => log::debug!("Logged incoming message to fallback agent {}", agent_did),
                                         Err(e) => log::warn!(
                                             "Failed to log incoming message for fallback agent {}: {}",
                                             agent_did,
@@ -992,21 +1891,58 @@ impl TapNode {
             }
         }
 
+        // Pass replies to waiting onboarding handshakes, and answer those of
+        // other nodes if configured
+        #[cfg(feature = "storage")]
+        {
+            self.reply_listeners.deliver(&message);
+            if self.config.answer_handshakes {
+                self.answer_handshake(&message).await;
+            }
+        }
+
+        // Dispatch messages of custom types with a handler to the handler
+        if let Some(handler) = custom_type.as_ref().and_then(|t| t.handler()) {
+            let handled =
+                log_context::in_context(context, "custom_handler", handler.handle(&message)).await;
+            if let Err(e) = handled {
+                log::warn!(
+                    "Handler of {} failed to process message {}: {}",
+                    message.type_,
+                    message.id,
+                    e
+                );
+            }
+            return Ok(());
+        }
+
         // Process the incoming message
-        let processed_message = match self.incoming_processor.process_incoming(message).await? {
+        let processed_message = match log_context::in_context(
+            context.clone(),
+            "route",
+            self.incoming_processor.process_incoming(message),
+        )
+        .await?
+        {
             Some(msg) => msg,
             None => return Ok(()), // PlainMessage was dropped during processing
         };
 
-        // Deliver the message to all recipients in the 'to' field
+        // Deliver the message to all recipients in the 'to' field, unless the
+        // script hook routes it elsewhere
         let mut delivery_success = false;
+        let recipients = if script_routes.is_empty() {
+            processed_message.to.clone()
+        } else {
+            script_routes
+        };
 
-        for recipient_did in &processed_message.to {
+        for recipient_did in &recipients {
             // Check if we have a registered agent for this recipient
             match self.agents.get_agent(recipient_did).await {
                 Ok(agent) => {
                     // Create delivery record for internal delivery tracking
-                    #[cfg(feature = "storage")]
+                    #[cfg(feature = "delivery-tracking")]
                     let delivery_id = if let Some(ref storage_manager) = self.agent_storage_manager
                     {
                         if let Ok(agent_storage) =
@@ -1028,9 +1964,8 @@ impl TapNode {
                             {
                                 Ok(id) => {
                                     log::debug!(
-                                        "Created internal delivery record {} for message {} to {}",
+                                        "Created internal delivery record {} for message to {}",
                                         id,
-                                        processed_message.id,
                                         recipient_did
                                     );
                                     Some(id)
@@ -1048,16 +1983,24 @@ impl TapNode {
                     };
 
                     // Let the agent process the plain message
-                    match agent.receive_plain_message(processed_message.clone()).await {
+                    let delivered = log_context::in_context(
+                        context.clone().with_agent(recipient_did.clone()),
+                        "deliver",
+                        agent.receive_plain_message(processed_message.clone()),
+                    )
+                    .await;
+                    match delivered {
                         Ok(_) => {
                             log::debug!(
                                 "Successfully delivered message to agent: {}",
                                 recipient_did
                             );
                             delivery_success = true;
+                            #[cfg(feature = "native")]
+                            self.callbacks.dispatch(recipient_did, &processed_message);
 
                             // Update delivery record to success
-                            #[cfg(feature = "storage")]
+                            #[cfg(feature = "delivery-tracking")]
                             if let (Some(delivery_id), Some(ref storage_manager)) =
                                 (delivery_id, &self.agent_storage_manager)
                             {
@@ -1082,7 +2025,7 @@ impl TapNode {
                             log::warn!("Agent {} failed to process message: {}", recipient_did, e);
 
                             // Update delivery record to failed
-                            #[cfg(feature = "storage")]
+                            #[cfg(feature = "delivery-tracking")]
                             if let (Some(delivery_id), Some(ref storage_manager)) =
                                 (delivery_id, &self.agent_storage_manager)
                             {
@@ -1118,7 +2061,13 @@ impl TapNode {
 
         // If no recipients were successfully processed, try the router as fallback
         if !delivery_success {
-            let target_did = match self.router.route_message(&processed_message).await {
+            let routed = log_context::in_context(
+                context.clone(),
+                "route",
+                self.router.route_message(&processed_message),
+            )
+            .await;
+            let target_did = match routed {
                 Ok(did) => did,
                 Err(e) => {
                     log::warn!("Unable to route message and no recipients processed: {}", e);
@@ -1136,7 +2085,7 @@ impl TapNode {
             };
 
             // Create delivery record for internal delivery tracking
-            #[cfg(feature = "storage")]
+            #[cfg(feature = "delivery-tracking")]
             let delivery_id = if let Some(ref storage_manager) = self.agent_storage_manager {
                 if let Ok(agent_storage) = storage_manager.get_agent_storage(&target_did).await {
                     // Serialize message for storage
@@ -1155,9 +2104,8 @@ impl TapNode {
                     {
                         Ok(id) => {
                             log::debug!(
-                                "Created internal delivery record {} for routed message {} to {}",
+                                "Created internal delivery record {} for routed message to {}",
                                 id,
-                                processed_message.id,
                                 target_did
                             );
                             Some(id)
@@ -1178,12 +2126,20 @@ impl TapNode {
             };
 
             // Let the agent process the plain message
-            match agent.receive_plain_message(processed_message).await {
+            let delivered = log_context::in_context(
+                context.with_agent(target_did.clone()),
+                "deliver",
+                agent.receive_plain_message(processed_message.clone()),
+            )
+            .await;
+            match delivered {
                 Ok(_) => {
                     log::debug!("Successfully routed message to agent: {}", target_did);
+                    #[cfg(feature = "native")]
+                    self.callbacks.dispatch(&target_did, &processed_message);
 
                     // Update delivery record to success
-                    #[cfg(feature = "storage")]
+                    #[cfg(feature = "delivery-tracking")]
                     if let (Some(delivery_id), Some(ref storage_manager)) =
                         (delivery_id, &self.agent_storage_manager)
                     {
@@ -1211,7 +2167,7 @@ impl TapNode {
                     log::warn!("Agent failed to process message: {}", e);
 
                     // Update delivery record to failed
-                    #[cfg(feature = "storage")]
+                    #[cfg(feature = "delivery-tracking")]
                     if let (Some(delivery_id), Some(ref storage_manager)) =
                         (delivery_id, &self.agent_storage_manager)
                     {
@@ -1256,20 +2212,134 @@ impl TapNode {
         Ok(())
     }
 
+    /// Send a problem report about `original` back to its sender
+    ///
+    /// The report is sent by the first recipient of `original` registered
+    /// with this node and is threaded to `original` through its `pthid`.
+    /// Problem reports are never answered with problem reports, so no report
+    /// is sent about one and `Ok(None)` is returned, as it is when none of
+    /// the recipients is a local agent.
+    pub async fn send_problem_report(
+        &self,
+        original: &PlainMessage,
+        report: ProblemReport,
+    ) -> Result<Option<String>> {
+        if original.type_ == PROBLEM_REPORT_TYPE || original.from.is_empty() {
+            return Ok(None);
+        }
+        let Some(from) = original.to.iter().find(|did| self.agents.has_agent(did)) else {
+            return Ok(None);
+        };
+
+        let reply = report
+            .to_reply(original, from)
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+        // Local recipients receive the report through this node again
+        Box::pin(self.send_message(from.clone(), reply))
+            .await
+            .map(Some)
+    }
+
     /// Send a message to an agent
     ///
     /// This method now includes comprehensive delivery tracking and actual message delivery.
+    /// Transfers and Payments first pass through the configured
+    /// [`EnrichmentPipeline`](message::EnrichmentPipeline).
     /// For internal recipients (registered agents), messages are delivered directly.
     /// For external recipients, messages are delivered via HTTP with tracking.
     pub async fn send_message(&self, sender_did: String, message: PlainMessage) -> Result<String> {
+        let _in_flight = self.diagnostics.outgoing.enter();
+        let context = log_context::MessageLogContext::for_message(&message).with_agent(&sender_did);
+        log_context::in_context(
+            context,
+            "send",
+            self.send_message_in_context(sender_did, message),
+        )
+        .await
+    }
+
+    /// Send a message, within the logging context set by
+    /// [`TapNode::send_message`]
+    async fn send_message_in_context(
+        &self,
+        sender_did: String,
+        message: PlainMessage,
+    ) -> Result<String> {
+        let context = log_context::current().unwrap_or_else(|| {
+            log_context::MessageLogContext::for_message(&message).with_agent(&sender_did)
+        });
+
+        // Let enrichment hooks amend or abort outgoing transactions
+        let message = match &self.config.enrichment {
+            Some(pipeline) => {
+                let original = message.clone();
+                self.within_timeout(
+                    timeouts::ProcessingStage::Hook,
+                    Some(&original.id),
+                    || serde_json::to_string(&original).unwrap_or_default(),
+                    pipeline.enrich(&sender_did, message),
+                )
+                .await??
+            }
+            None => message,
+        };
+
+        // Check settlement addresses and connection limits before anything
+        // is stored or signed
+        #[cfg(feature = "storage")]
+        {
+            use crate::validation::connection_limit_validator::ConnectionLimitValidator;
+            use crate::validation::settlement_address_validator::SettlementAddressValidator;
+            use crate::validation::{MessageValidator, ValidationResult};
+
+            let mut validator =
+                SettlementAddressValidator::new(self.config.settlement_address_strictness);
+            if let Some(ref storage) = self.storage {
+                validator = validator.with_storage(storage.clone());
+            }
+            if let ValidationResult::Reject(reason) = validator.validate(&message).await {
+                return Err(Error::Validation(reason));
+            }
+            if let Some(ref storage) = self.storage {
+                let limits = ConnectionLimitValidator::new(storage.clone());
+                if let ValidationResult::Reject(reason) = limits.validate(&message).await {
+                    return Err(Error::Validation(reason));
+                }
+            }
+        }
+
+        #[cfg(feature = "storage")]
+        self.stop_response_timers(&sender_did, &message).await;
+
+        // Remember the challenge of presentation requests, which the
+        // presentation answering them must sign
+        #[cfg(feature = "storage")]
+        if message.type_.ends_with("#RequestPresentation") {
+            if let Some(ref storage) = self.storage {
+                if let Err(e) = storage.insert_presentation_request(&message).await {
+                    log::warn!(
+                        "Failed to record presentation request {}: {}",
+                        message.id,
+                        e
+                    );
+                }
+            }
+        }
+
         // Log outgoing messages to agent-specific storage
         #[cfg(feature = "storage")]
         {
             if let Some(ref storage_manager) = self.agent_storage_manager {
                 // Check if this is a transaction message
                 let message_type_lower = message.type_.to_lowercase();
-                let is_transaction = message_type_lower.contains("transfer")
-                    || message_type_lower.contains("payment");
+                let is_custom_type = self
+                    .config
+                    .message_types
+                    .as_ref()
+                    .is_some_and(|registry| registry.contains(&message.type_));
+                let is_transaction = !is_custom_type
+                    && (message_type_lower.contains("transfer")
+                        || message_type_lower.contains("payment"));
                 log::debug!(
                     "Message type: {}, is_transaction: {}",
                     message.type_,
@@ -1303,9 +2373,8 @@ impl TapNode {
                                     .await
                                 {
                                     Ok(_) => log::debug!(
-                                        "Logged outgoing message to agent {}: {}",
-                                        agent_did,
-                                        message.id
+                                        "Logged outgoing message to agent {}",
+                                        agent_did
                                     ),
                                     Err(e) => log::warn!(
                                         "Failed to log outgoing message for agent {}: {}",
@@ -1318,9 +2387,8 @@ impl TapNode {
                                 match agent_storage.insert_transaction(&message).await {
                                     Ok(()) => {
                                         log::debug!(
-                                            "Stored outgoing transaction for agent {}: {}",
-                                            agent_did,
-                                            message.id
+                                            "Stored outgoing transaction for agent {}",
+                                            agent_did
                                         );
 
                                         // Publish TransactionCreated event
@@ -1356,11 +2424,9 @@ impl TapNode {
                             .log_message(&message, storage::MessageDirection::Outgoing)
                             .await
                         {
-                            Ok(_) => log::debug!(
-                                "Logged outgoing message for agent {}: {}",
-                                sender_did,
-                                message.id
-                            ),
+                            Ok(_) => {
+                                log::debug!("Logged outgoing message for agent {}", sender_did)
+                            }
                             Err(e) => log::warn!(
                                 "Failed to log outgoing message for agent {}: {}",
                                 sender_did,
@@ -1405,8 +2471,11 @@ impl TapNode {
             SecurityMode::Signed
         };
 
-        // Get sender key ID
-        let sender_kid = agent.get_signing_kid().await?;
+        // Get the ID of the sender key the security mode needs
+        let sender_kid = match security_mode.sender_key_purpose() {
+            Some(purpose) => agent.get_kid_for_purpose(purpose).await?,
+            None => agent.get_signing_kid().await?,
+        };
 
         // For single recipient auth-crypt, get recipient key
         let recipient_kid =
@@ -1422,10 +2491,39 @@ impl TapNode {
             security_mode,
             sender_kid: Some(sender_kid),
             recipient_kid,
+            content_encryption: None,
+            compression: None,
         };
 
+        // Format the message for recipients that deviate from the
+        // specification; only the message on the wire is changed
+        #[cfg(feature = "storage")]
+        let wire_message = self.format_outbound(processed_message.clone()).await?;
+        #[cfg(not(feature = "storage"))]
+        let wire_message = processed_message.clone();
+
         // Pack/sign the message properly
-        let packed = processed_message.pack(&**key_manager, pack_options).await?;
+        let packed = wire_message.pack(&**key_manager, pack_options).await?;
+
+        // Track the transaction state of messages leaving the node; messages
+        // for local agents are tracked on internal delivery instead
+        #[cfg(feature = "state-machine")]
+        if let Some(ref state_processor) = self.state_processor {
+            use crate::state_machine::TransactionStateProcessor;
+            let local_agents = self.agents.get_all_dids();
+            if !processed_message
+                .to
+                .iter()
+                .any(|recipient| local_agents.contains(recipient))
+            {
+                if let Err(e) = state_processor
+                    .process_outgoing_message(&processed_message)
+                    .await
+                {
+                    log::debug!("State processor skipped outgoing message: {}", e);
+                }
+            }
+        }
 
         // Deliver to all recipients in the message
         let mut delivery_errors = Vec::new();
@@ -1440,7 +2538,7 @@ impl TapNode {
                 // Internal delivery - deliver to registered agent with tracking
                 log::debug!("Delivering message internally to agent: {}", recipient_did);
 
-                #[cfg(feature = "storage")]
+                #[cfg(feature = "delivery-tracking")]
                 let delivery_id = if let Some(ref storage_manager) = self.agent_storage_manager {
                     if let Ok(sender_storage) = storage_manager.get_agent_storage(&sender_did).await
                     {
@@ -1457,9 +2555,8 @@ impl TapNode {
                         {
                             Ok(id) => {
                                 log::debug!(
-                                    "Created internal delivery record {} for message {} to {}",
+                                    "Created internal delivery record {} for message to {}",
                                     id,
-                                    processed_message.id,
                                     recipient_did
                                 );
                                 Some(id)
@@ -1476,8 +2573,9 @@ impl TapNode {
                     None
                 };
 
-                // Process the message internally through receive_message_from_source
-                // to ensure it gets recorded in the received table
+                // Process the message internally like a received one, to
+                // ensure it gets recorded in the received table, without
+                // the intake limits meant for external senders
                 // Pass the packed (signed) message just like external messages
                 let message_value = match serde_json::from_str::<serde_json::Value>(&packed) {
                     Ok(val) => val,
@@ -1487,14 +2585,17 @@ impl TapNode {
                     }
                 };
 
-                match self
-                    .receive_message_from_source(
+                let delivered = log_context::in_context(
+                    context.clone(),
+                    "deliver",
+                    self.process_received(
                         message_value,
                         storage::SourceType::Internal,
                         Some(&sender_did),
-                    )
-                    .await
-                {
+                    ),
+                )
+                .await;
+                match delivered {
                     Ok(_) => {
                         log::debug!(
                             "Successfully delivered message internally to: {}",
@@ -1502,7 +2603,7 @@ impl TapNode {
                         );
 
                         // Update delivery record to success
-                        #[cfg(feature = "storage")]
+                        #[cfg(feature = "delivery-tracking")]
                         if let (Some(delivery_id), Some(ref storage_manager)) =
                             (delivery_id, &self.agent_storage_manager)
                         {
@@ -1531,7 +2632,7 @@ impl TapNode {
                         );
 
                         // Update delivery record to failed
-                        #[cfg(feature = "storage")]
+                        #[cfg(feature = "delivery-tracking")]
                         if let (Some(delivery_id), Some(ref storage_manager)) =
                             (delivery_id, &self.agent_storage_manager)
                         {
@@ -1560,11 +2661,40 @@ impl TapNode {
                 // External delivery - use TapAgent's built-in HTTP delivery with tracking
                 log::debug!("Attempting external delivery to: {}", recipient_did);
 
+                // Simulated counterparties answer in place of a remote agent
+                #[cfg(feature = "native")]
+                if self.is_simulated_counterparty(recipient_did) {
+                    self.deliver_to_simulated_counterparty(recipient_did, &processed_message);
+                    continue;
+                }
+
+                // Remote agents with a mailbox poll the node for their messages
+                #[cfg(feature = "storage")]
+                if self.has_mailbox(recipient_did) {
+                    if let Err(e) = self
+                        .hold_in_mailbox(&sender_did, recipient_did, &processed_message.id, &packed)
+                        .await
+                    {
+                        log::error!(
+                            "Failed to hold message {} for {}: {}",
+                            processed_message.id,
+                            recipient_did,
+                            e
+                        );
+                        delivery_errors.push((recipient_did.clone(), e));
+                    }
+                    continue;
+                }
+
                 // Get the sender agent for HTTP delivery
                 let sender_agent = self.agents.get_agent(&sender_did).await?;
 
                 // Resolve the service endpoint for the recipient
-                let endpoint = match sender_agent.get_service_endpoint(recipient_did).await? {
+                let endpoint = match self.service_endpoint_override(recipient_did) {
+                    Some(ep) => Some(ep),
+                    None => sender_agent.get_service_endpoint(recipient_did).await?,
+                };
+                let endpoint = match endpoint {
                     Some(ep) => ep,
                     None => {
                         log::warn!(
@@ -1573,7 +2703,7 @@ impl TapNode {
                         );
 
                         // Create failed delivery record
-                        #[cfg(feature = "storage")]
+                        #[cfg(feature = "delivery-tracking")]
                         if let Some(ref storage_manager) = self.agent_storage_manager {
                             if let Ok(sender_storage) =
                                 storage_manager.get_agent_storage(&sender_did).await
@@ -1611,78 +2741,111 @@ impl TapNode {
                     }
                 };
 
-                // Create delivery record before attempting delivery
-                #[cfg(feature = "storage")]
-                let delivery_id = if let Some(ref storage_manager) = self.agent_storage_manager {
-                    if let Ok(sender_storage) = storage_manager.get_agent_storage(&sender_did).await
-                    {
-                        match sender_storage
-                            .create_delivery(
-                                &processed_message.id,
-                                &packed, // Store the signed/packed message
+                // Deliver through the tracking sender, which records the
+                // delivery in the sender's database
+                #[cfg(feature = "delivery-tracking")]
+                if let Some(ref storage_manager) = self.agent_storage_manager {
+                    let sender_storage = storage_manager.get_agent_storage(&sender_did).await?;
+                    let sender =
+                        HttpPlainMessageSenderWithTracking::for_endpoints(sender_storage.clone());
+                    let delivery = log_context::in_context(
+                        context.clone(),
+                        "deliver",
+                        sender.deliver_to_endpoint(
+                            &processed_message.id,
+                            &packed,
+                            recipient_did,
+                            &endpoint,
+                        ),
+                    )
+                    .await;
+                    let delivery = match delivery {
+                        Ok(delivery) => delivery,
+                        Err(e) => {
+                            log::error!(
+                                "Failed to deliver message {} to {} at {}: {}",
+                                processed_message.id,
                                 recipient_did,
-                                Some(&endpoint),
-                                storage::models::DeliveryType::Https,
-                            )
-                            .await
+                                endpoint,
+                                e
+                            );
+                            delivery_errors.push((recipient_did.clone(), e));
+                            continue;
+                        }
+                    };
+                    if delivery.status == storage::models::DeliveryStatus::Success {
+                        log::debug!(
+                            "Delivered message to {} at {} (delivery {})",
+                            recipient_did,
+                            endpoint,
+                            delivery.id
+                        );
+                        continue;
+                    }
+
+                    let error = delivery
+                        .error_message
+                        .unwrap_or_else(|| "Unknown error".to_string());
+                    log::error!(
+                        "Failed to deliver message {} to {} at {}: {}",
+                        processed_message.id,
+                        recipient_did,
+                        endpoint,
+                        error
+                    );
+                    if let Some(policy) = &self.config.delivery_retry {
+                        if let Err(e) = delivery::schedule_retry(
+                            &sender_storage,
+                            delivery.id,
+                            policy,
+                            &*self.clock(),
+                        )
+                        .await
                         {
-                            Ok(id) => {
-                                log::debug!(
-                                    "Created external delivery record {} for message {} to {} at {}",
-                                    id,
-                                    processed_message.id,
-                                    recipient_did,
-                                    endpoint
-                                );
-                                Some(id)
-                            }
-                            Err(e) => {
-                                log::warn!("Failed to create external delivery record: {}", e);
-                                None
-                            }
+                            log::warn!("Failed to schedule delivery retry: {}", e);
                         }
-                    } else {
-                        None
                     }
-                } else {
-                    None
-                };
+                    delivery_errors.push((
+                        recipient_did.clone(),
+                        Error::Dispatch(format!(
+                            "HTTP delivery failed for {}: {}",
+                            recipient_did, error
+                        )),
+                    ));
+                    continue;
+                }
 
-                // Attempt HTTP delivery using TapAgent's built-in functionality
-                match sender_agent.send_to_endpoint(&packed, &endpoint).await {
-                    Ok(status_code) => {
+                // Without storage, the delivery is not recorded
+                let delivered = log_context::in_context(
+                    context.clone(),
+                    "deliver",
+                    sender_agent.send_to_endpoint(&packed, &endpoint),
+                )
+                .await;
+                match delivered {
+                    Ok(status_code) if (200..300).contains(&status_code) => {
                         log::debug!(
-                            "Successfully delivered message {} to {} at {} (HTTP {})",
+                            "Successfully delivered message to {} at {} (HTTP {})",
+                            recipient_did,
+                            endpoint,
+                            status_code
+                        );
+                    }
+                    Ok(status_code) => {
+                        log::error!(
+                            "Failed to deliver message {} to {} at {}: HTTP {}",
                             processed_message.id,
                             recipient_did,
                             endpoint,
                             status_code
                         );
-
-                        // Update delivery record to success
-                        #[cfg(feature = "storage")]
-                        if let (Some(delivery_id), Some(ref storage_manager)) =
-                            (delivery_id, &self.agent_storage_manager)
-                        {
-                            if let Ok(sender_storage) =
-                                storage_manager.get_agent_storage(&sender_did).await
-                            {
-                                if let Err(e) = sender_storage
-                                    .update_delivery_status(
-                                        delivery_id,
-                                        storage::models::DeliveryStatus::Success,
-                                        Some(status_code as i32),
-                                        None,
-                                    )
-                                    .await
-                                {
-                                    log::warn!(
-                                        "Failed to update external delivery status to success: {}",
-                                        e
-                                    );
-                                }
-                            }
-                        }
+                        delivery_errors.push((
+                            recipient_did.clone(),
+                            Error::Dispatch(format!(
+                                "HTTP delivery failed for {}: HTTP {}",
+                                recipient_did, status_code
+                            )),
+                        ));
                     }
                     Err(e) => {
                         log::error!(
@@ -1692,52 +2855,6 @@ impl TapNode {
                             endpoint,
                             e
                         );
-
-                        // Update delivery record to failed
-                        #[cfg(feature = "storage")]
-                        if let (Some(delivery_id), Some(ref storage_manager)) =
-                            (delivery_id, &self.agent_storage_manager)
-                        {
-                            if let Ok(sender_storage) =
-                                storage_manager.get_agent_storage(&sender_did).await
-                            {
-                                // Extract HTTP status code from error if possible
-                                let error_msg = e.to_string();
-                                let http_status_code = if error_msg.contains("status:") {
-                                    error_msg
-                                        .split("status:")
-                                        .nth(1)
-                                        .and_then(|s| s.split_whitespace().next())
-                                        .and_then(|s| s.parse::<i32>().ok())
-                                } else {
-                                    None
-                                };
-
-                                if let Err(e2) = sender_storage
-                                    .update_delivery_status(
-                                        delivery_id,
-                                        storage::models::DeliveryStatus::Failed,
-                                        http_status_code,
-                                        Some(&error_msg),
-                                    )
-                                    .await
-                                {
-                                    log::warn!(
-                                        "Failed to update external delivery status to failed: {}",
-                                        e2
-                                    );
-                                }
-
-                                // Increment retry count for future retry processing
-                                if let Err(e2) = sender_storage
-                                    .increment_delivery_retry_count(delivery_id)
-                                    .await
-                                {
-                                    log::warn!("Failed to increment retry count: {}", e2);
-                                }
-                            }
-                        }
-
                         delivery_errors.push((
                             recipient_did.clone(),
                             Error::Dispatch(format!(
@@ -1745,7 +2862,6 @@ impl TapNode {
                                 recipient_did, e
                             )),
                         ));
-                        continue; // Continue to next recipient
                     }
                 }
             }
@@ -1789,7 +2905,8 @@ impl TapNode {
     /// When an agent is registered, a dedicated SQLite database is created for that agent's DID.
     /// This ensures transaction isolation between different agents while maintaining a consistent
     /// storage structure. If storage initialization fails, the agent registration continues but
-    /// a warning is logged.
+    /// a warning is logged. With at-rest encryption configured, an agent whose encryption key
+    /// cannot be derived from its key manager is not registered.
     ///
     /// # Arguments
     ///
@@ -1804,48 +2921,183 @@ impl TapNode {
 
         // Initialize storage for this agent if storage is enabled
         #[cfg(feature = "storage")]
-        {
-            if let Some(ref storage_manager) = self.agent_storage_manager {
-                match storage_manager.ensure_agent_storage(&agent_did).await {
-                    Ok(_) => {
-                        log::info!("Initialized storage for agent: {}", agent_did);
+        self.init_agent_storage(&agent).await?;
 
-                        // Get the agent storage and register customer event handler
-                        if let Ok(agent_storage) =
-                            storage_manager.get_agent_storage(&agent_did).await
-                        {
-                            let customer_handler =
-                                Arc::new(event::customer_handler::CustomerEventHandler::new(
-                                    agent_storage,
-                                    agent_did.clone(),
-                                ));
-                            self.event_bus.subscribe(customer_handler).await;
-                            log::debug!(
-                                "Registered customer event handler for agent: {}",
-                                agent_did
+        self.agents.register_agent(agent_did.clone(), agent).await?;
+
+        // Publish event about agent registration
+        self.event_bus.publish_agent_registered(agent_did).await;
+
+        Ok(())
+    }
+
+    /// Register many agents with the node
+    ///
+    /// Registers each agent like [`register_agent`](Self::register_agent),
+    /// but initializes the agents' storages concurrently, at most 16 at a
+    /// time. Agents that are already registered, or appear earlier in
+    /// `agents`, are skipped. An agent that fails to register is logged and
+    /// skipped. Each registration publishes an `AgentRegistered` event.
+    ///
+    /// # Returns
+    ///
+    /// The DIDs of the newly registered agents, in the order of `agents`
+    pub async fn register_agents(&self, agents: Vec<Arc<TapAgent>>) -> Result<Vec<String>> {
+        let mut dids = std::collections::HashSet::new();
+        let agents: Vec<Arc<TapAgent>> = agents
+            .into_iter()
+            .filter(|agent| {
+                let did = agent.get_agent_did();
+                !self.agents.has_agent(did) && dids.insert(did.to_string())
+            })
+            .collect();
+
+        #[cfg(feature = "storage")]
+        let agents: Vec<Arc<TapAgent>> = {
+            use futures::StreamExt;
+            futures::stream::iter(agents)
+                .map(|agent| async move {
+                    let initialized = self.init_agent_storage(&agent).await;
+                    (agent, initialized)
+                })
+                .buffered(AGENT_STORAGE_INIT_CONCURRENCY)
+                .filter_map(|(agent, initialized)| async move {
+                    match initialized {
+                        Ok(()) => Some(agent),
+                        Err(e) => {
+                            log::error!(
+                                "Failed to register agent {}: {}",
+                                agent.get_agent_did(),
+                                e
                             );
+                            None
                         }
                     }
-                    Err(e) => {
-                        log::warn!(
-                            "Failed to initialize storage for agent {}: {}",
-                            agent_did,
-                            e
-                        );
-                        // Don't fail the registration, just log the warning
-                    }
+                })
+                .collect()
+                .await
+        };
+
+        let mut registered = Vec::new();
+        for agent in agents {
+            let did = agent.get_agent_did().to_string();
+            match self.agents.register_agent(did.clone(), agent).await {
+                Ok(()) => {
+                    self.event_bus.publish_agent_registered(did.clone()).await;
+                    registered.push(did);
                 }
+                Err(e) => log::error!("Failed to register agent {}: {}", did, e),
             }
         }
 
-        self.agents.register_agent(agent_did.clone(), agent).await?;
+        Ok(registered)
+    }
 
-        // Publish event about agent registration
-        self.event_bus.publish_agent_registered(agent_did).await;
+    /// Initialize the storage of an agent being registered
+    ///
+    /// With at-rest encryption configured, an agent whose encryption key
+    /// cannot be derived is refused rather than having its sensitive
+    /// messages stored in the clear. Other failures are logged, and the
+    /// agent is registered without its storage.
+    #[cfg(feature = "storage")]
+    async fn init_agent_storage(&self, agent: &TapAgent) -> Result<()> {
+        let Some(ref storage_manager) = self.agent_storage_manager else {
+            return Ok(());
+        };
+        let agent_did = agent.get_agent_did().to_string();
 
+        if storage_manager.message_encryption().is_some() {
+            let key = agent
+                .key_manager()
+                .derive_symmetric_key(&agent_did, storage::encryption::ENCRYPTION_KEY_CONTEXT)
+                .map_err(|e| {
+                    Error::Storage(format!(
+                        "Cannot encrypt the messages of agent {}, no key to encrypt them with: {}",
+                        agent_did, e
+                    ))
+                })?;
+            storage_manager.set_encryption_key(&agent_did, key);
+        }
+        match storage_manager.ensure_agent_storage(&agent_did).await {
+            Ok(_) => {
+                log::info!("Initialized storage for agent: {}", agent_did);
+
+                // Get the agent storage and register customer event handler
+                #[cfg(feature = "customers")]
+                if let Ok(agent_storage) = storage_manager.get_agent_storage(&agent_did).await {
+                    let customer_handler =
+                        Arc::new(event::customer_handler::CustomerEventHandler::new(
+                            agent_storage,
+                            agent_did.clone(),
+                        ));
+                    self.event_bus.subscribe(customer_handler).await;
+                    log::debug!("Registered customer event handler for agent: {}", agent_did);
+                }
+            }
+            Err(e) => {
+                log::warn!(
+                    "Failed to initialize storage for agent {}: {}",
+                    agent_did,
+                    e
+                );
+            }
+        }
         Ok(())
     }
 
+    /// Register an agent for every key in the key storage
+    ///
+    /// Reads the key storage at [`NodeConfig::key_storage_path`], skipping
+    /// keys whose agents are already registered, and registers the agents
+    /// with [`register_agents`](Self::register_agents). An agent that fails
+    /// to register is logged and skipped. Each registration publishes an
+    /// `AgentRegistered` event.
+    ///
+    /// # Returns
+    ///
+    /// The DIDs of the newly registered agents
+    pub async fn register_stored_agents(&self) -> Result<Vec<String>> {
+        let key_path = match &self.config.key_storage_path {
+            Some(path) if path.is_dir() => path.join(tap_agent::storage::DEFAULT_KEYS_FILE),
+            Some(path) => path.clone(),
+            None => tap_agent::storage::KeyStorage::default_key_path().ok_or_else(|| {
+                Error::Configuration("Could not determine the default key storage path".into())
+            })?,
+        };
+        let key_storage = tap_agent::storage::KeyStorage::load_from_path(&key_path)
+            .map_err(|e| Error::Configuration(format!("Failed to load key storage: {}", e)))?;
+
+        let mut stored_dids: Vec<String> = key_storage.keys.keys().cloned().collect();
+        stored_dids.sort();
+        log::info!(
+            "Found {} stored keys in {}",
+            stored_dids.len(),
+            key_path.display()
+        );
+
+        // The agents share one key manager rather than each loading the key storage
+        let key_manager = Arc::new(
+            tap_agent::AgentKeyManagerBuilder::new()
+                .load_from_path(key_path)
+                .build()
+                .map_err(|e| Error::Configuration(format!("Failed to load keys: {}", e)))?,
+        );
+        let agents = stored_dids
+            .into_iter()
+            .map(|did| {
+                let config = tap_agent::AgentConfig::new(did).with_debug(self.config.debug);
+                Arc::new(TapAgent::new(config, key_manager.clone()))
+            })
+            .collect();
+
+        let registered = self.register_agents(agents).await?;
+        for did in &registered {
+            log::info!("Registered stored agent: {}", did);
+        }
+
+        Ok(registered)
+    }
+
     /// Unregister an agent from the node
     pub async fn unregister_agent(&self, did: &str) -> Result<()> {
         self.agents.unregister_agent(did).await?;
@@ -1873,11 +3125,106 @@ impl TapNode {
         &self.event_bus
     }
 
+    /// Register a webhook receiving the events of its scope, returning its ID
+    #[cfg(feature = "native")]
+    pub async fn register_webhook(&self, webhook: event::webhook::Webhook) -> Result<String> {
+        let id = self.webhooks.register(webhook)?;
+        self.webhooks.attach(&self.event_bus).await;
+        Ok(id)
+    }
+
+    /// Unregister a webhook, returning whether it was registered
+    #[cfg(feature = "native")]
+    pub fn unregister_webhook(&self, webhook_id: &str) -> bool {
+        self.webhooks.unregister(webhook_id)
+    }
+
+    /// The registered webhooks
+    #[cfg(feature = "native")]
+    pub fn webhooks(&self) -> Vec<event::webhook::Webhook> {
+        self.webhooks.list()
+    }
+
+    /// Register an async callback for the `T` messages delivered to
+    /// `agent_did`, returning its ID
+    ///
+    /// See [`callbacks`] for when callbacks run and in which order.
+    #[cfg(feature = "native")]
+    pub fn on_message<T, F, Fut>(&self, agent_did: &str, callback: F) -> String
+    where
+        T: tap_msg::message::tap_message_trait::TapMessageBody + 'static,
+        F: Fn(callbacks::ReceivedMessage<T>) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<()>> + Send + 'static,
+    {
+        self.callbacks
+            .register(agent_did, T::message_type(), callback)
+    }
+
+    /// Register an async callback for the messages of `message_type`
+    /// delivered to `agent_did`, with their body as JSON, returning its ID
+    #[cfg(feature = "native")]
+    pub fn on_message_type<F, Fut>(
+        &self,
+        agent_did: &str,
+        message_type: &str,
+        callback: F,
+    ) -> String
+    where
+        F: Fn(callbacks::ReceivedMessage<serde_json::Value>) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<()>> + Send + 'static,
+    {
+        self.callbacks.register(agent_did, message_type, callback)
+    }
+
+    /// Remove a message callback, returning whether it was registered
+    #[cfg(feature = "native")]
+    pub fn remove_callback(&self, callback_id: &str) -> bool {
+        self.callbacks.remove(callback_id)
+    }
+
     /// Get a reference to the resolver
     pub fn resolver(&self) -> &Arc<MultiResolver> {
         &self.resolver
     }
 
+    /// Get the verifier of credentials presented by agents, if credential
+    /// verification is configured
+    pub fn credential_verifier(&self) -> Option<&Arc<credentials::CredentialVerifier>> {
+        self.credential_verifier.as_ref()
+    }
+
+    /// Deliver messages for a DID to the given endpoint
+    ///
+    /// The endpoint takes precedence over the service endpoint in the DID
+    /// document, which is useful for peers whose DIDs carry no endpoint, such
+    /// as `did:key` agents, and for pointing nodes at each other in tests.
+    pub fn set_service_endpoint(&self, did: impl Into<String>, endpoint: impl Into<String>) {
+        self.service_endpoints.insert(did.into(), endpoint.into());
+    }
+
+    /// Remove an endpoint set with [`TapNode::set_service_endpoint`]
+    pub fn remove_service_endpoint(&self, did: &str) -> Option<String> {
+        self.service_endpoints
+            .remove(did)
+            .map(|(_, endpoint)| endpoint)
+    }
+
+    fn service_endpoint_override(&self, did: &str) -> Option<String> {
+        self.service_endpoints
+            .get(did)
+            .map(|endpoint| endpoint.value().clone())
+    }
+
+    /// Append a processor to the incoming message pipeline
+    pub fn add_incoming_processor(&mut self, processor: PlainMessageProcessorType) {
+        self.incoming_processor.add_processor(processor);
+    }
+
+    /// Append a processor to the outgoing message pipeline
+    pub fn add_outgoing_processor(&mut self, processor: PlainMessageProcessorType) {
+        self.outgoing_processor.add_processor(processor);
+    }
+
     /// Get a mutable reference to the processor pool
     /// This is a reference to `Option<ProcessorPool>` to allow starting the pool after node creation
     pub fn processor_pool_mut(&mut self) -> &mut Option<ProcessorPool> {
@@ -1889,10 +3236,69 @@ impl TapNode {
         &self.config
     }
 
+    /// Get the clock used by the node's components
+    pub fn clock(&self) -> Arc<dyn clock::Clock> {
+        self.config
+            .clock
+            .clone()
+            .unwrap_or_else(clock::system_clock)
+    }
+
+    /// Clock health of the node, to debug messages rejected for their
+    /// timestamps
+    ///
+    /// Reports the latest check of the local clock against NTP, see
+    /// [`NodeConfig::clock_health`], and the skew observed in the messages
+    /// of each counterparty.
+    #[cfg(feature = "native")]
+    pub fn clock_metrics(&self) -> clock_health::ClockMetrics {
+        clock_health::ClockMetrics {
+            local_clock: self
+                .clock_health
+                .as_ref()
+                .and_then(|monitor| monitor.last_check()),
+            #[cfg(feature = "storage")]
+            counterparties: self.clock_skew.snapshot(),
+        }
+    }
+
+    /// Create the transaction state processor of a storage, with the
+    /// configured decision mode, policy engine, valuation and settlement
+    /// providers
+    #[cfg(feature = "state-machine")]
+    fn new_state_processor(
+        &self,
+        storage: &Arc<storage::Storage>,
+    ) -> Arc<state_machine::StandardTransactionProcessor> {
+        let mut state_processor = state_machine::StandardTransactionProcessor::new(
+            storage.clone(),
+            self.event_bus.clone(),
+            self.agents.clone(),
+            self.config.decision_mode.clone(),
+        );
+        if let Some(policy_engine) = self.config.policy_engine.clone() {
+            state_processor = state_processor.with_policy_engine(policy_engine);
+        }
+        if let Some(valuation) = self.config.valuation.clone() {
+            state_processor = state_processor.with_valuation(valuation);
+        }
+        if let Some(provider) = self.config.settlement_addresses.clone() {
+            state_processor = state_processor.with_settlement_addresses(provider);
+        }
+        if let Some(conversion) = self.config.settlement_conversion.clone() {
+            state_processor = state_processor.with_settlement_conversion(conversion);
+        }
+        if let Some(address_reuse) = self.config.address_reuse.clone() {
+            state_processor = state_processor.with_address_reuse(address_reuse);
+        }
+        Arc::new(state_processor)
+    }
+
     /// Set the decision mode at runtime.
     ///
     /// Call this after `init_storage()` but before processing any messages
     /// to configure how the FSM handles decision points.
+    #[cfg(feature = "state-machine")]
     pub fn set_decision_mode(&mut self, mode: state_machine::fsm::DecisionMode) {
         self.config.decision_mode = mode;
     }
@@ -1909,11 +3315,78 @@ impl TapNode {
         self.agent_storage_manager.as_ref()
     }
 
+    /// Report the disk usage of each registered agent's database
+    #[cfg(feature = "storage")]
+    pub async fn storage_usage(&self) -> Result<Vec<storage::AgentStorageUsage>> {
+        match &self.agent_storage_manager {
+            Some(storage_manager) => {
+                let mut agent_dids = self.list_agents();
+                agent_dids.sort();
+                storage_manager.usage(&agent_dids).await
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Define a group of agents whose storage can be queried together
+    #[cfg(feature = "storage")]
+    pub fn define_agent_group(&self, group: storage::AgentGroup) -> Result<()> {
+        match &self.agent_storage_manager {
+            Some(storage_manager) => storage_manager.define_group(group),
+            None => Err(Error::Storage("Agent storage is not available".to_string())),
+        }
+    }
+
+    /// List the defined agent groups
+    #[cfg(feature = "storage")]
+    pub fn agent_groups(&self) -> Vec<storage::AgentGroup> {
+        self.agent_storage_manager
+            .as_ref()
+            .map(|storage_manager| storage_manager.groups())
+            .unwrap_or_default()
+    }
+
+    /// Open a view that aggregates the storage of an agent group's members
+    #[cfg(feature = "storage")]
+    pub async fn group_storage(&self, group_id: &str) -> Result<storage::GroupStorageView> {
+        match &self.agent_storage_manager {
+            Some(storage_manager) => storage_manager.group_view(group_id).await,
+            None => Err(Error::Storage("Agent storage is not available".to_string())),
+        }
+    }
+
+    /// Report what can still be spent under a connection (TAIP-15)
+    ///
+    /// Returns None if the connection is not known or has no transaction
+    /// limits. When its limits have no currency, `asset` names the asset or
+    /// currency code of the totals.
+    #[cfg(feature = "storage")]
+    pub async fn connection_allowance(
+        &self,
+        connection_id: &str,
+        asset: Option<&str>,
+    ) -> Result<Option<validation::connection_limit_validator::ConnectionAllowance>> {
+        match &self.storage {
+            Some(storage) => {
+                validation::connection_limit_validator::ConnectionLimitValidator::new(
+                    storage.clone(),
+                )
+                .allowance(connection_id, asset)
+                .await
+            }
+            None => Err(Error::Storage("Storage is not available".to_string())),
+        }
+    }
+
     /// Set storage for testing purposes
     /// This allows injecting in-memory databases for complete test isolation
     #[cfg(feature = "storage")]
     pub async fn set_storage(&mut self, storage: storage::Storage) -> Result<()> {
-        let storage_arc = Arc::new(storage);
+        let storage = match self.config.clock.clone() {
+            Some(clock) => storage.with_clock(clock),
+            None => storage,
+        };
+        let storage_arc = Arc::new(storage.with_event_sourcing(self.config.event_sourcing));
 
         // Subscribe event handlers
         let message_status_handler = Arc::new(event::handlers::MessageStatusHandler::new(
@@ -1929,16 +3402,79 @@ impl TapNode {
         let transaction_audit_handler = Arc::new(event::handlers::TransactionAuditHandler::new());
         self.event_bus.subscribe(transaction_audit_handler).await;
 
-        // Create state processor with configured decision mode
-        let state_processor = Arc::new(state_machine::StandardTransactionProcessor::new(
-            storage_arc.clone(),
-            self.event_bus.clone(),
-            self.agents.clone(),
-            self.config.decision_mode.clone(),
-        ));
+        #[cfg(feature = "state-machine")]
+        let state_processor = self.new_state_processor(&storage_arc);
+
+        self.scheduler.set_storage(&storage_arc);
+
+        #[cfg(feature = "state-machine")]
+        if let Some(policy) = self.config.transaction_expiry.clone() {
+            state_machine::expiry::ExpirySweeper::new(policy)
+                .with_clock(self.clock())
+                .schedule(&self.scheduler, &state_processor)
+                .await;
+        }
+
+        #[cfg(feature = "delivery-tracking")]
+        if let (Some(policy), Some(manager)) = (
+            self.config.delivery_retry.clone(),
+            self.agent_storage_manager.as_ref(),
+        ) {
+            delivery::DeliveryRetrier::new(policy)
+                .with_clock(self.clock())
+                .schedule(&self.scheduler, &self.agents, manager)
+                .await;
+        }
+
+        if let (Some(thresholds), Some(manager)) = (
+            self.config.anomaly_detection.clone(),
+            self.agent_storage_manager.as_ref(),
+        ) {
+            anomaly::AnomalyDetector::new(thresholds)
+                .with_clock(self.clock())
+                .spawn(&self.event_bus, manager);
+        }
+
+        if let Some(policy) = self.config.response_timers.clone() {
+            response_timers::ResponseTimerMonitor::new(policy)
+                .with_clock(self.clock())
+                .schedule(&self.scheduler, &storage_arc, &self.event_bus)
+                .await;
+        }
+
+        #[cfg(feature = "native")]
+        if let Some(monitor) = self.clock_health.clone() {
+            monitor.schedule(&self.scheduler, &self.event_bus).await;
+        }
+
+        if let Some(policy) = self.config.retention_policy.clone() {
+            retention::RetentionPurger::new(policy)
+                .with_clock(self.clock())
+                .schedule(
+                    &self.scheduler,
+                    Some(&storage_arc),
+                    self.agent_storage_manager.as_ref(),
+                )
+                .await;
+        }
+
+        if let Some(policy) = self.config.backup_policy.clone() {
+            backup::BackupScheduler::new(policy)
+                .with_clock(self.clock())
+                .schedule(
+                    &self.scheduler,
+                    Some(&storage_arc),
+                    self.agent_storage_manager.as_ref(),
+                )
+                .await;
+        }
+        self.scheduler.start();
 
         self.storage = Some(storage_arc);
-        self.state_processor = Some(state_processor);
+        #[cfg(feature = "state-machine")]
+        {
+            self.state_processor = Some(state_processor);
+        }
         Ok(())
     }
 
@@ -1972,6 +3508,56 @@ impl TapNode {
         ))
     }
 
+    /// Local agents the script hook routes an incoming message to
+    ///
+    /// Empty unless the script overrides the message's routing. DIDs of agents
+    /// not registered with this node are ignored.
+    #[cfg(feature = "scripting")]
+    fn script_routes(&self, message: &PlainMessage) -> Vec<String> {
+        let Some(hook) = &self.config.script_hook else {
+            return Vec::new();
+        };
+
+        match hook.route(message) {
+            Ok(routes) => {
+                let routes: Vec<String> = routes
+                    .into_iter()
+                    .filter(|did| {
+                        let registered = self.agents.has_agent(did);
+                        if !registered {
+                            log::warn!(
+                                "Script {} routed message {} to unregistered agent {}",
+                                hook.name(),
+                                message.id,
+                                did
+                            );
+                        }
+                        registered
+                    })
+                    .collect();
+                if !routes.is_empty() {
+                    log::info!(
+                        "Script {} routed message {} to {:?}",
+                        hook.name(),
+                        message.id,
+                        routes
+                    );
+                }
+                routes
+            }
+            Err(e) => {
+                log::error!("Ignoring script routing for message {}: {}", message.id, e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Local agents the script hook routes an incoming message to
+    #[cfg(not(feature = "scripting"))]
+    fn script_routes(&self, _message: &PlainMessage) -> Vec<String> {
+        Vec::new()
+    }
+
     /// Extract all agent DIDs involved in a transaction
     ///
     /// For Transfer and Payment messages, this includes:
@@ -2071,6 +3657,7 @@ impl TapNode {
 
 // Namespace imports
 // These imports make the implementation cleaner, but should be hidden from public API
+use message::problem_report_processor::ProblemReportProcessor;
 use message::processor::DefaultPlainMessageProcessor;
 use message::processor::LoggingPlainMessageProcessor;
 use message::processor::ValidationPlainMessageProcessor;
@@ -0,0 +1,215 @@
+//! Logging context of the message being processed
+//!
+//! Each stage of message processing (receive, validate, state machine,
+//! route, deliver, and send for outgoing messages) runs inside a `tracing`
+//! span named `tap_message` whose fields identify the message: its
+//! `message_id`, its thread (`thid`, which for TAP messages is the
+//! transaction ID) and the local `agent_did` it is processed for. With a
+//! `tracing` subscriber installed, every log line emitted during processing
+//! carries these fields, so logs can be filtered by transaction. Records
+//! emitted through the `log` crate are included when the subscriber bridges
+//! them, as `tracing_subscriber`'s default `init` does.
+//!
+//! Deployments that only configure a `log` logger, such as `env_logger`, can
+//! wrap it in a [`ContextLogger`], which prefixes each record with the
+//! context of the message being processed by the current task:
+//!
+//! ```no_run
+//! use tap_node::log_context::ContextLogger;
+//!
+//! let logger = env_logger::Builder::from_default_env().build();
+//! let max_level = logger.filter();
+//! ContextLogger::new(logger).init(max_level).unwrap();
+//! ```
+
+use std::fmt;
+use std::future::Future;
+use tap_msg::didcomm::PlainMessage;
+use tracing::Instrument;
+
+tokio::task_local! {
+    static CONTEXT: MessageLogContext;
+}
+
+/// Identifies the message being processed in log output
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageLogContext {
+    /// ID of the message
+    pub message_id: String,
+    /// Thread of the message, or its parent thread
+    pub thid: Option<String>,
+    /// Local agent the message is processed for
+    pub agent_did: Option<String>,
+}
+
+impl MessageLogContext {
+    /// Context of a message, not yet tied to an agent
+    pub fn for_message(message: &PlainMessage) -> Self {
+        Self {
+            message_id: message.id.clone(),
+            thid: message.thid.clone().or_else(|| message.pthid.clone()),
+            agent_did: None,
+        }
+    }
+
+    /// The same context, processed for `agent_did`
+    pub fn with_agent(mut self, agent_did: impl Into<String>) -> Self {
+        self.agent_did = Some(agent_did.into());
+        self
+    }
+
+    /// The span of a processing stage in this context
+    pub fn span(&self, stage: &'static str) -> tracing::Span {
+        tracing::info_span!(
+            "tap_message",
+            stage,
+            message_id = %self.message_id,
+            thid = self.thid.as_deref(),
+            agent_did = self.agent_did.as_deref(),
+        )
+    }
+}
+
+impl fmt::Display for MessageLogContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "message_id={}", self.message_id)?;
+        if let Some(thid) = &self.thid {
+            write!(f, " thid={}", thid)?;
+        }
+        if let Some(agent_did) = &self.agent_did {
+            write!(f, " agent_did={}", agent_did)?;
+        }
+        Ok(())
+    }
+}
+
+/// The context of the message processed by the current task, if any
+pub fn current() -> Option<MessageLogContext> {
+    CONTEXT.try_with(Clone::clone).ok()
+}
+
+/// Run a processing stage of a message in its logging context
+pub async fn in_context<F: Future>(
+    context: MessageLogContext,
+    stage: &'static str,
+    future: F,
+) -> F::Output {
+    let span = context.span(stage);
+    // Stages nest, e.g. deliver within send; boxing keeps each stage's
+    // future from being inlined into its caller's, which would otherwise
+    // exhaust the stack of runtime worker threads
+    CONTEXT
+        .scope(context, Box::pin(future.instrument(span)))
+        .await
+}
+
+/// A `log` logger that prefixes records with the context of the message
+/// being processed
+///
+/// Records emitted outside of message processing are passed on unchanged.
+#[derive(Debug)]
+pub struct ContextLogger<L> {
+    inner: L,
+}
+
+impl<L: log::Log + 'static> ContextLogger<L> {
+    /// Wrap `inner`, which writes the records
+    pub fn new(inner: L) -> Self {
+        Self { inner }
+    }
+
+    /// Install as the global logger with the given maximum level
+    pub fn init(self, max_level: log::LevelFilter) -> Result<(), log::SetLoggerError> {
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+}
+
+impl<L: log::Log> log::Log for ContextLogger<L> {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        let Some(context) = current() else {
+            self.inner.log(record);
+            return;
+        };
+        self.inner.log(
+            &log::Record::builder()
+                .args(format_args!("[{}] {}", context, record.args()))
+                .metadata(record.metadata().clone())
+                .module_path(record.module_path())
+                .file(record.file())
+                .line(record.line())
+                .build(),
+        );
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Collects the formatted records
+    #[derive(Debug, Clone, Default)]
+    struct Collector(Arc<Mutex<Vec<String>>>);
+
+    impl log::Log for Collector {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+
+        fn flush(&self) {}
+    }
+
+    fn record(logger: &dyn log::Log, message: &str) {
+        logger.log(
+            &log::Record::builder()
+                .args(format_args!("{}", message))
+                .level(log::Level::Info)
+                .build(),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_context_logger_prefixes_message_context() {
+        let collector = Collector::default();
+        let logger = ContextLogger::new(collector.clone());
+
+        let message = PlainMessage::new(
+            "msg-1".to_string(),
+            "https://tap.rsvp/schema/1.0#Authorize".to_string(),
+            serde_json::json!({}),
+            "did:example:alice".to_string(),
+        )
+        .with_thread_id(Some("tx-1".to_string()));
+        let context = MessageLogContext::for_message(&message).with_agent("did:example:bob");
+
+        record(&logger, "before");
+        in_context(context.clone(), "validate", async {
+            assert_eq!(current(), Some(context.clone()));
+            record(&logger, "validating");
+        })
+        .await;
+        assert_eq!(current(), None);
+
+        assert_eq!(
+            *collector.0.lock().unwrap(),
+            vec![
+                "before".to_string(),
+                "[message_id=msg-1 thid=tx-1 agent_did=did:example:bob] validating".to_string(),
+            ]
+        );
+    }
+}
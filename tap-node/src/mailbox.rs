@@ -0,0 +1,306 @@
+//! Mailboxes for counterparties that cannot accept inbound HTTP
+//!
+//! A remote agent with a mailbox receives its messages by polling the node
+//! instead of at a service endpoint. Messages our agents send to it are held
+//! in the node's storage, each with an increasing sequence number, until the
+//! agent fetches and acknowledges them or they expire. The sender's delivery
+//! record is marked successful on acknowledgement and failed on expiry.
+//!
+//! Remote agents poll with a bearer token registered for their mailbox, of
+//! which the node only keeps a SHA-256 hash, or retrieve their messages with
+//! DIDComm message pickup (see [`crate::pickup`]).
+
+use crate::error::{Error, Result};
+use crate::storage::{DeliveryStatus, DeliveryType, MailboxMessage, MailboxSummary, Storage};
+use crate::TapNode;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default time a message is held before it expires
+pub const DEFAULT_MAILBOX_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Mailboxes held by a node
+#[derive(Debug, Clone)]
+pub struct MailboxConfig {
+    /// Remote agents that poll the node for their messages
+    pub recipients: Vec<MailboxRecipient>,
+    /// How long a message is held before it expires
+    pub ttl: Duration,
+}
+
+impl Default for MailboxConfig {
+    fn default() -> Self {
+        Self {
+            recipients: Vec::new(),
+            ttl: DEFAULT_MAILBOX_TTL,
+        }
+    }
+}
+
+/// A remote agent that polls the node for its messages
+#[derive(Debug, Clone)]
+pub struct MailboxRecipient {
+    /// DID of the remote agent
+    pub did: String,
+    token_hash: Option<[u8; 32]>,
+}
+
+impl MailboxRecipient {
+    /// Create a recipient that authenticates with a bearer token
+    pub fn new(did: impl Into<String>, token: &str) -> Self {
+        Self {
+            did: did.into(),
+            token_hash: Some(hash_token(token)),
+        }
+    }
+
+    /// Create a recipient that only retrieves its messages with DIDComm
+    /// message pickup
+    pub fn pickup(did: impl Into<String>) -> Self {
+        Self {
+            did: did.into(),
+            token_hash: None,
+        }
+    }
+}
+
+fn hash_token(token: &str) -> [u8; 32] {
+    Sha256::digest(token.as_bytes()).into()
+}
+
+impl TapNode {
+    /// Hold messages for a remote agent until it polls for them
+    ///
+    /// Messages our agents send to the DID are held in its mailbox instead of
+    /// being delivered to its service endpoint. Registering a DID again
+    /// replaces its token.
+    pub fn register_mailbox(&self, recipient: MailboxRecipient) {
+        self.mailboxes.insert(recipient.did, recipient.token_hash);
+    }
+
+    /// Deliver messages for a remote agent to its service endpoint again
+    ///
+    /// Messages already held for it remain available until they expire.
+    pub fn remove_mailbox(&self, did: &str) -> bool {
+        self.mailboxes.remove(did).is_some()
+    }
+
+    /// Whether messages for a DID are held in a mailbox
+    pub fn has_mailbox(&self, did: &str) -> bool {
+        self.mailboxes.contains_key(did)
+    }
+
+    /// The DID of the remote agent that a bearer token belongs to
+    pub fn authenticate_mailbox(&self, token: &str) -> Option<String> {
+        let token_hash = hash_token(token);
+        self.mailboxes
+            .iter()
+            .find(|entry| *entry.value() == Some(token_hash))
+            .map(|entry| entry.key().clone())
+    }
+
+    /// Fetch the messages held for a remote agent after a sequence number
+    ///
+    /// Waits up to `wait` for a message to arrive when none is held. Fetched
+    /// messages are returned again until they are acknowledged with
+    /// [`TapNode::acknowledge_mailbox`] or expire.
+    pub async fn poll_mailbox(
+        &self,
+        recipient_did: &str,
+        after: i64,
+        limit: u32,
+        wait: Duration,
+    ) -> Result<Vec<MailboxMessage>> {
+        let storage = self.mailbox_storage()?;
+        self.expire_mailbox_messages().await?;
+
+        let deadline = tokio::time::Instant::now() + wait;
+        loop {
+            // Register for notifications before looking, so that a message
+            // held in between is not missed
+            let held = self.mailbox_notify.notified();
+            tokio::pin!(held);
+            held.as_mut().enable();
+
+            let messages = storage
+                .fetch_mailbox_messages(recipient_did, after, limit)
+                .await
+                .map_err(|e| Error::Storage(e.to_string()))?;
+            if !messages.is_empty() {
+                return Ok(messages);
+            }
+            if tokio::time::timeout_at(deadline, held).await.is_err() {
+                return Ok(messages);
+            }
+        }
+    }
+
+    /// Acknowledge the fetched messages of a remote agent up to and
+    /// including a sequence number
+    ///
+    /// The messages are marked delivered, as are their delivery records.
+    /// Returns the number of messages acknowledged.
+    pub async fn acknowledge_mailbox(&self, recipient_did: &str, cursor: i64) -> Result<usize> {
+        let messages = self
+            .mailbox_storage()?
+            .acknowledge_mailbox_messages(recipient_did, cursor)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+
+        for message in &messages {
+            self.update_mailbox_delivery(message, DeliveryStatus::Success, None)
+                .await;
+        }
+        Ok(messages.len())
+    }
+
+    /// Acknowledge fetched messages of a remote agent by message ID
+    ///
+    /// The messages are marked delivered, as are their delivery records.
+    /// Returns the number of messages acknowledged.
+    pub async fn acknowledge_mailbox_messages(
+        &self,
+        recipient_did: &str,
+        message_ids: &[String],
+    ) -> Result<usize> {
+        let messages = self
+            .mailbox_storage()?
+            .acknowledge_mailbox_messages_by_id(recipient_did, message_ids)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+
+        for message in &messages {
+            self.update_mailbox_delivery(message, DeliveryStatus::Success, None)
+                .await;
+        }
+        Ok(messages.len())
+    }
+
+    /// Summarize the messages waiting in the mailbox of a remote agent
+    pub async fn mailbox_summary(&self, recipient_did: &str) -> Result<MailboxSummary> {
+        self.mailbox_storage()?
+            .mailbox_summary(recipient_did)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    /// Expire mailbox messages that were not acknowledged in time
+    ///
+    /// Their delivery records are marked failed. Returns the number of
+    /// messages expired.
+    pub async fn expire_mailbox_messages(&self) -> Result<usize> {
+        let messages = self
+            .mailbox_storage()?
+            .expire_mailbox_messages()
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+
+        for message in &messages {
+            log::info!(
+                "Message {} for {} expired in its mailbox",
+                message.message_id,
+                message.recipient_did
+            );
+            self.update_mailbox_delivery(
+                message,
+                DeliveryStatus::Failed,
+                Some("Mailbox message expired before it was acknowledged"),
+            )
+            .await;
+        }
+        Ok(messages.len())
+    }
+
+    /// Hold a packed message in the mailbox of a remote agent
+    pub(crate) async fn hold_in_mailbox(
+        &self,
+        sender_did: &str,
+        recipient_did: &str,
+        message_id: &str,
+        packed: &str,
+    ) -> Result<i64> {
+        let storage = self.mailbox_storage()?;
+
+        let delivery_id = match self.agent_storage_manager() {
+            Some(storage_manager) => match storage_manager.get_agent_storage(sender_did).await {
+                Ok(sender_storage) => sender_storage
+                    .create_delivery(
+                        message_id,
+                        packed,
+                        recipient_did,
+                        None,
+                        DeliveryType::Pickup,
+                    )
+                    .await
+                    .map_err(|e| log::warn!("Failed to create pickup delivery record: {}", e))
+                    .ok(),
+                Err(e) => {
+                    log::warn!("Failed to get storage for agent {}: {}", sender_did, e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let ttl = chrono::Duration::from_std(self.config.mailbox.ttl)
+            .map_err(|e| Error::Configuration(format!("Invalid mailbox TTL: {}", e)))?;
+        let expires_at = (self.clock().now() + ttl)
+            .format("%Y-%m-%dT%H:%M:%SZ")
+            .to_string();
+        let seq = storage
+            .insert_mailbox_message(
+                recipient_did,
+                sender_did,
+                message_id,
+                packed,
+                delivery_id,
+                &expires_at,
+            )
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        self.mailbox_notify.notify_waiters();
+
+        log::debug!(
+            "Holding message {} for {} in its mailbox as {}",
+            message_id,
+            recipient_did,
+            seq
+        );
+        Ok(seq)
+    }
+
+    fn mailbox_storage(&self) -> Result<&Arc<Storage>> {
+        self.storage()
+            .ok_or_else(|| Error::Storage("Storage is not initialized".to_string()))
+    }
+
+    async fn update_mailbox_delivery(
+        &self,
+        message: &MailboxMessage,
+        status: DeliveryStatus,
+        error_message: Option<&str>,
+    ) {
+        let (Some(delivery_id), Some(storage_manager)) =
+            (message.delivery_id, self.agent_storage_manager())
+        else {
+            return;
+        };
+
+        let result = match storage_manager.get_agent_storage(&message.sender_did).await {
+            Ok(sender_storage) => sender_storage
+                .update_delivery_status(delivery_id, status, None, error_message)
+                .await
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(e) = result {
+            log::warn!(
+                "Failed to update delivery {} of message {}: {}",
+                delivery_id,
+                message.message_id,
+                e
+            );
+        }
+    }
+}
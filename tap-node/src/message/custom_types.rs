@@ -0,0 +1,247 @@
+//! Custom message types
+//!
+//! Applications can extend the node with their own DIDComm message types
+//! without forking message processing. Each [`CustomMessageType`] registered
+//! in a [`MessageTypeRegistry`] names its type URI, the validators its
+//! messages must pass and, optionally, the handler they are dispatched to.
+//!
+//! Messages of a registered type go through the same steps as TAP messages:
+//! their signature or encryption is verified on receipt, the standard and
+//! registered validators run before they are stored in the databases of the
+//! recipient agents, and they are routed to those agents. When the type has
+//! a handler, the handler processes the message instead of the default
+//! processor and the agents. Messages of unregistered types outside the TAP
+//! and DIDComm protocols are dropped, as before.
+
+use crate::error::{Error, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use tap_msg::didcomm::PlainMessage;
+
+/// Prefix of TAP message types, which cannot be registered
+const TAP_TYPE_PREFIX: &str = "https://tap.rsvp/schema/";
+
+/// A check that messages of a custom type must pass
+///
+/// Closures taking a message and returning `Result<(), String>` are
+/// validators.
+pub trait CustomMessageValidator: Send + Sync {
+    /// Validate a message, returning the reason it is rejected
+    fn validate(&self, message: &PlainMessage) -> std::result::Result<(), String>;
+}
+
+impl<F> CustomMessageValidator for F
+where
+    F: Fn(&PlainMessage) -> std::result::Result<(), String> + Send + Sync,
+{
+    fn validate(&self, message: &PlainMessage) -> std::result::Result<(), String> {
+        self(message)
+    }
+}
+
+/// Processes received messages of a custom type
+#[async_trait]
+pub trait CustomMessageHandler: Send + Sync {
+    /// Handle a message that passed validation and was stored
+    async fn handle(&self, message: &PlainMessage) -> Result<()>;
+}
+
+/// Validator requiring fields in the message body
+#[derive(Debug, Clone)]
+pub struct RequiredBodyFields {
+    fields: Vec<String>,
+}
+
+impl RequiredBodyFields {
+    /// Require the given top-level fields in the body
+    pub fn new<I, S>(fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            fields: fields.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl CustomMessageValidator for RequiredBodyFields {
+    fn validate(&self, message: &PlainMessage) -> std::result::Result<(), String> {
+        let body = message
+            .body
+            .as_object()
+            .ok_or_else(|| format!("Body of {} is not an object", message.type_))?;
+        match self.fields.iter().find(|field| !body.contains_key(*field)) {
+            Some(field) => Err(format!("Missing field {} in {}", field, message.type_)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A message type registered by an application
+#[derive(Clone)]
+pub struct CustomMessageType {
+    type_uri: String,
+    validators: Vec<Arc<dyn CustomMessageValidator>>,
+    handler: Option<Arc<dyn CustomMessageHandler>>,
+}
+
+impl CustomMessageType {
+    /// Describe the message type with the given type URI
+    pub fn new(type_uri: impl Into<String>) -> Self {
+        Self {
+            type_uri: type_uri.into(),
+            validators: Vec::new(),
+            handler: None,
+        }
+    }
+
+    /// Add a validator, which runs after the validators already added
+    pub fn with_validator(mut self, validator: impl CustomMessageValidator + 'static) -> Self {
+        self.validators.push(Arc::new(validator));
+        self
+    }
+
+    /// Dispatch received messages of this type to a handler instead of the
+    /// default processor
+    pub fn with_handler(mut self, handler: Arc<dyn CustomMessageHandler>) -> Self {
+        self.handler = Some(handler);
+        self
+    }
+
+    /// Type URI of the messages
+    pub fn type_uri(&self) -> &str {
+        &self.type_uri
+    }
+
+    /// Handler of received messages, if any
+    pub fn handler(&self) -> Option<&Arc<dyn CustomMessageHandler>> {
+        self.handler.as_ref()
+    }
+
+    /// Run the validators on a message, returning the reason of the first
+    /// that rejects it
+    pub fn validate(&self, message: &PlainMessage) -> std::result::Result<(), String> {
+        self.validators
+            .iter()
+            .try_for_each(|validator| validator.validate(message))
+    }
+}
+
+impl fmt::Debug for CustomMessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomMessageType")
+            .field("type_uri", &self.type_uri)
+            .field("validators", &self.validators.len())
+            .field("handler", &self.handler.is_some())
+            .finish()
+    }
+}
+
+/// Custom message types known to a node, by type URI
+#[derive(Debug, Clone, Default)]
+pub struct MessageTypeRegistry {
+    types: HashMap<String, Arc<CustomMessageType>>,
+}
+
+impl MessageTypeRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a message type
+    ///
+    /// Fails for TAP message types and type URIs already registered.
+    pub fn register(&mut self, message_type: CustomMessageType) -> Result<()> {
+        let type_uri = message_type.type_uri();
+        if type_uri.is_empty() || type_uri.starts_with(TAP_TYPE_PREFIX) {
+            return Err(Error::Configuration(format!(
+                "Cannot register {:?} as a custom message type",
+                type_uri
+            )));
+        }
+        if self.types.contains_key(type_uri) {
+            return Err(Error::Configuration(format!(
+                "Message type {} is already registered",
+                type_uri
+            )));
+        }
+        self.types
+            .insert(type_uri.to_string(), Arc::new(message_type));
+        Ok(())
+    }
+
+    /// The registered type with the given type URI
+    pub fn get(&self, type_uri: &str) -> Option<&Arc<CustomMessageType>> {
+        self.types.get(type_uri)
+    }
+
+    /// Whether a type URI is registered
+    pub fn contains(&self, type_uri: &str) -> bool {
+        self.types.contains_key(type_uri)
+    }
+
+    /// Type URIs of the registered types
+    pub fn type_uris(&self) -> impl Iterator<Item = &str> {
+        self.types.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const INVOICE: &str = "https://example.com/protocols/invoice/1.0/invoice";
+
+    fn message(body: serde_json::Value) -> PlainMessage {
+        PlainMessage::new(
+            "invoice-1".to_string(),
+            INVOICE.to_string(),
+            body,
+            "did:example:sender".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_registration_and_validation() {
+        let mut registry = MessageTypeRegistry::new();
+        registry
+            .register(
+                CustomMessageType::new(INVOICE)
+                    .with_validator(RequiredBodyFields::new(["amount", "currency"]))
+                    .with_validator(|message: &PlainMessage| {
+                        match message.body["amount"].as_str() {
+                            Some(amount) if amount.parse::<f64>().is_ok() => Ok(()),
+                            _ => Err("Invalid amount".to_string()),
+                        }
+                    }),
+            )
+            .unwrap();
+
+        assert!(registry.contains(INVOICE));
+        assert!(registry.register(CustomMessageType::new(INVOICE)).is_err());
+        assert!(registry
+            .register(CustomMessageType::new(
+                "https://tap.rsvp/schema/1.0#Transfer"
+            ))
+            .is_err());
+
+        let invoice = registry.get(INVOICE).unwrap();
+        assert!(invoice.handler().is_none());
+        assert!(invoice
+            .validate(&message(json!({"amount": "10.00", "currency": "USD"})))
+            .is_ok());
+        assert_eq!(
+            invoice.validate(&message(json!({"amount": "10.00"}))),
+            Err(format!("Missing field currency in {}", INVOICE))
+        );
+        assert_eq!(
+            invoice.validate(&message(json!({"amount": "ten", "currency": "USD"}))),
+            Err("Invalid amount".to_string())
+        );
+    }
+}
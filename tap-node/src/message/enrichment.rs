@@ -0,0 +1,333 @@
+//! Enrichment of outgoing transactions
+//!
+//! An [`EnrichmentPipeline`] runs registered [`EnrichmentHook`]s on every
+//! Transfer and Payment sent through [`TapNode::send_message`](crate::TapNode::send_message),
+//! before it is stored, validated and signed. Hooks receive the parsed body
+//! and may change it, for example to add purpose codes to the metadata, add
+//! agents or attach policies to agents, or abort sending with a reason.
+//!
+//! Hooks run in the order they were added to the pipeline, and each sees the
+//! changes made by the hooks before it. Agents added by hooks are also added
+//! to the message recipients.
+
+use crate::error::{Error, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::tap_message_trait::TapMessageBody;
+use tap_msg::message::{Agent, Payment, Transfer};
+
+/// Draft body of an outgoing transaction
+#[derive(Debug, Clone)]
+pub enum TransactionDraft {
+    Transfer(Transfer),
+    Payment(Payment),
+}
+
+impl TransactionDraft {
+    /// Parse the body of a Transfer or Payment message
+    ///
+    /// Returns None for other message types.
+    pub fn from_message(message: &PlainMessage) -> Result<Option<Self>> {
+        let draft = if message.type_ == Transfer::message_type() {
+            Self::Transfer(parse_body(message)?)
+        } else if message.type_ == Payment::message_type() {
+            Self::Payment(parse_body(message)?)
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(draft))
+    }
+
+    /// Agents involved in the transaction
+    pub fn agents(&self) -> &[Agent] {
+        match self {
+            Self::Transfer(transfer) => &transfer.agents,
+            Self::Payment(payment) => &payment.agents,
+        }
+    }
+
+    /// Mutable access to the agents involved in the transaction
+    pub fn agents_mut(&mut self) -> &mut Vec<Agent> {
+        match self {
+            Self::Transfer(transfer) => &mut transfer.agents,
+            Self::Payment(payment) => &mut payment.agents,
+        }
+    }
+
+    /// Additional metadata of the transaction
+    pub fn metadata_mut(&mut self) -> &mut HashMap<String, serde_json::Value> {
+        match self {
+            Self::Transfer(transfer) => &mut transfer.metadata,
+            Self::Payment(payment) => &mut payment.metadata,
+        }
+    }
+
+    /// Serialize the draft as a message body, including its `@type`
+    fn to_body(&self) -> Result<serde_json::Value> {
+        let (body, message_type) = match self {
+            Self::Transfer(transfer) => (serde_json::to_value(transfer), Transfer::message_type()),
+            Self::Payment(payment) => (serde_json::to_value(payment), Payment::message_type()),
+        };
+        let mut body = body.map_err(|e| Error::Serialization(e.to_string()))?;
+        if let Some(object) = body.as_object_mut() {
+            object.insert("@type".to_string(), message_type.into());
+        }
+        Ok(body)
+    }
+}
+
+fn parse_body<T: serde::de::DeserializeOwned>(message: &PlainMessage) -> Result<T> {
+    serde_json::from_value(message.body.clone()).map_err(|e| {
+        Error::InvalidPlainMessage(format!("Failed to parse {}: {}", message.type_, e))
+    })
+}
+
+/// What happens after a hook has run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnrichmentDecision {
+    /// Pass the draft on to the next hook
+    Continue,
+    /// Stop sending the message
+    Abort(String),
+}
+
+/// The outgoing message being enriched
+#[derive(Debug)]
+pub struct EnrichmentContext<'a> {
+    /// DID of the agent sending the message
+    pub sender_did: &'a str,
+    /// The message as passed to the node, before any hook ran
+    pub message: &'a PlainMessage,
+}
+
+/// A hook that may change an outgoing transaction before it is signed
+#[async_trait]
+pub trait EnrichmentHook: Send + Sync + fmt::Debug {
+    /// Name of the hook, reported when it aborts a message
+    fn name(&self) -> &str;
+
+    /// Enrich the draft, or abort sending it
+    async fn enrich(
+        &self,
+        ctx: &EnrichmentContext<'_>,
+        draft: &mut TransactionDraft,
+    ) -> Result<EnrichmentDecision>;
+}
+
+/// Ordered collection of enrichment hooks
+#[derive(Debug, Clone, Default)]
+pub struct EnrichmentPipeline {
+    hooks: Vec<Arc<dyn EnrichmentHook>>,
+}
+
+impl EnrichmentPipeline {
+    /// Create an empty pipeline
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a hook, which runs after the hooks already added
+    pub fn with_hook(mut self, hook: Arc<dyn EnrichmentHook>) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// Hooks in the order they run
+    pub fn hooks(&self) -> &[Arc<dyn EnrichmentHook>] {
+        &self.hooks
+    }
+
+    /// Run the hooks on an outgoing message
+    ///
+    /// Messages other than Transfer and Payment are returned unchanged. If a
+    /// hook aborts, [`Error::EnrichmentAborted`] is returned.
+    pub async fn enrich(&self, sender_did: &str, message: PlainMessage) -> Result<PlainMessage> {
+        if self.hooks.is_empty() {
+            return Ok(message);
+        }
+        let Some(mut draft) = TransactionDraft::from_message(&message)? else {
+            return Ok(message);
+        };
+
+        let ctx = EnrichmentContext {
+            sender_did,
+            message: &message,
+        };
+        for hook in &self.hooks {
+            if let EnrichmentDecision::Abort(reason) = hook.enrich(&ctx, &mut draft).await? {
+                log::info!(
+                    "Enrichment hook {} aborted message {}: {}",
+                    hook.name(),
+                    message.id,
+                    reason
+                );
+                return Err(Error::EnrichmentAborted {
+                    hook: hook.name().to_string(),
+                    reason,
+                });
+            }
+        }
+
+        let mut enriched = message.clone();
+        enriched.body = draft.to_body()?;
+        for agent in draft.agents() {
+            if agent.id != sender_did && !enriched.to.contains(&agent.id) {
+                enriched.to.push(agent.id.clone());
+            }
+        }
+        Ok(enriched)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::transfer_body;
+
+    #[derive(Debug)]
+    struct PurposeCode;
+
+    #[async_trait]
+    impl EnrichmentHook for PurposeCode {
+        fn name(&self) -> &str {
+            "purpose-code"
+        }
+
+        async fn enrich(
+            &self,
+            _ctx: &EnrichmentContext<'_>,
+            draft: &mut TransactionDraft,
+        ) -> Result<EnrichmentDecision> {
+            draft
+                .metadata_mut()
+                .insert("purpose".to_string(), "GDDS".into());
+            Ok(EnrichmentDecision::Continue)
+        }
+    }
+
+    #[derive(Debug)]
+    struct AddComplianceAgent;
+
+    #[async_trait]
+    impl EnrichmentHook for AddComplianceAgent {
+        fn name(&self) -> &str {
+            "compliance-agent"
+        }
+
+        async fn enrich(
+            &self,
+            _ctx: &EnrichmentContext<'_>,
+            draft: &mut TransactionDraft,
+        ) -> Result<EnrichmentDecision> {
+            // Sees the purpose code added by the previous hook
+            if draft.metadata_mut().get("purpose") == Some(&"GDDS".into()) {
+                draft.agents_mut().push(Agent::new(
+                    "did:example:compliance",
+                    "Compliance",
+                    "did:example:originator",
+                ));
+            }
+            Ok(EnrichmentDecision::Continue)
+        }
+    }
+
+    #[derive(Debug)]
+    struct BlockMemo;
+
+    #[async_trait]
+    impl EnrichmentHook for BlockMemo {
+        fn name(&self) -> &str {
+            "block-memo"
+        }
+
+        async fn enrich(
+            &self,
+            _ctx: &EnrichmentContext<'_>,
+            draft: &mut TransactionDraft,
+        ) -> Result<EnrichmentDecision> {
+            match draft {
+                TransactionDraft::Transfer(transfer) if transfer.memo.is_some() => Ok(
+                    EnrichmentDecision::Abort("Memos are not allowed".to_string()),
+                ),
+                _ => Ok(EnrichmentDecision::Continue),
+            }
+        }
+    }
+
+    fn transfer(memo: Option<&str>) -> PlainMessage {
+        let transfer = Transfer {
+            agents: vec![Agent::new(
+                "did:example:sender",
+                "SettlementAddress",
+                "did:example:originator",
+            )],
+            memo: memo.map(String::from),
+            ..transfer_body("did:example:originator", "did:example:beneficiary", "100")
+        };
+        transfer
+            .to_didcomm("did:example:sender")
+            .unwrap()
+            .with_recipient("did:example:beneficiary-vasp")
+    }
+
+    #[tokio::test]
+    async fn test_hooks_run_in_order() {
+        let pipeline = EnrichmentPipeline::new()
+            .with_hook(Arc::new(PurposeCode))
+            .with_hook(Arc::new(AddComplianceAgent));
+
+        let enriched = pipeline
+            .enrich("did:example:sender", transfer(None))
+            .await
+            .unwrap();
+        assert_eq!(enriched.body["metadata"]["purpose"], "GDDS");
+        assert_eq!(enriched.body["@type"], Transfer::message_type());
+        assert_eq!(enriched.body["agents"][1]["@id"], "did:example:compliance");
+        assert!(enriched.to.contains(&"did:example:compliance".to_string()));
+        assert!(!enriched.to.contains(&"did:example:sender".to_string()));
+
+        // In the other order the compliance agent sees no purpose code yet
+        let pipeline = EnrichmentPipeline::new()
+            .with_hook(Arc::new(AddComplianceAgent))
+            .with_hook(Arc::new(PurposeCode));
+        let enriched = pipeline
+            .enrich("did:example:sender", transfer(None))
+            .await
+            .unwrap();
+        assert_eq!(enriched.body["agents"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_hook_aborts_with_reason() {
+        let pipeline = EnrichmentPipeline::new()
+            .with_hook(Arc::new(BlockMemo))
+            .with_hook(Arc::new(PurposeCode));
+
+        match pipeline
+            .enrich("did:example:sender", transfer(Some("hello")))
+            .await
+        {
+            Err(Error::EnrichmentAborted { hook, reason }) => {
+                assert_eq!(hook, "block-memo");
+                assert_eq!(reason, "Memos are not allowed");
+            }
+            other => panic!("Expected abort, got {:?}", other.map(|m| m.body)),
+        }
+
+        // Other message types pass through untouched
+        let ping = PlainMessage::new(
+            "ping-1".to_string(),
+            "https://didcomm.org/trust-ping/2.0/ping".to_string(),
+            serde_json::json!({}),
+            "did:example:sender".to_string(),
+        );
+        let passed = pipeline
+            .enrich("did:example:sender", ping.clone())
+            .await
+            .unwrap();
+        assert_eq!(passed.body, ping.body);
+    }
+}
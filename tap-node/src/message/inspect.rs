@@ -0,0 +1,268 @@
+//! Inspection of inbound envelopes
+//!
+//! [`inspect_envelope`] reads what a gateway needs for routing or queueing
+//! decisions from a received message (its envelope type, the sender it
+//! claims, its recipients, message type and thread) without verifying
+//! signatures, decrypting, resolving DIDs or storing anything. Nothing
+//! reported is authenticated: a signed payload is decoded but not verified,
+//! and an encrypted message only reveals its recipients and, for authcrypt,
+//! its sender's key.
+
+use crate::error::{Error, Result};
+use serde::Serialize;
+use serde_json::Value;
+use tap_agent::compression::decompress_payload;
+use tap_agent::message::{base64_decode_flexible, normalize_envelope};
+use tap_agent::{Jwe, Jws};
+
+/// How a received message is packed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvelopeKind {
+    /// An unpacked DIDComm plain message
+    Plain,
+    /// A JWS
+    Signed,
+    /// A JWE
+    Encrypted,
+}
+
+impl EnvelopeKind {
+    /// Snake-case name of the kind, e.g. `signed`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EnvelopeKind::Plain => "plain",
+            EnvelopeKind::Signed => "signed",
+            EnvelopeKind::Encrypted => "encrypted",
+        }
+    }
+}
+
+impl TryFrom<&str> for EnvelopeKind {
+    type Error = String;
+
+    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
+        match value {
+            "plain" => Ok(EnvelopeKind::Plain),
+            "signed" => Ok(EnvelopeKind::Signed),
+            "encrypted" => Ok(EnvelopeKind::Encrypted),
+            _ => Err(format!("Invalid envelope kind: {}", value)),
+        }
+    }
+}
+
+/// What can be read from a received message without processing it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EnvelopeInfo {
+    pub kind: EnvelopeKind,
+    /// DID the message claims to be from: its `from`, or for an authcrypt
+    /// JWE the DID of the sender key
+    pub from: Option<String>,
+    /// Key IDs the message claims to be signed or authcrypted with
+    pub sender_kids: Vec<String>,
+    /// DIDs the message is addressed to: its `to`, or for a JWE the DIDs of
+    /// the recipient keys
+    pub recipients: Vec<String>,
+    /// Message ID (not known for a JWE)
+    pub message_id: Option<String>,
+    /// Message type URI (not known for a JWE)
+    pub message_type: Option<String>,
+    /// Thread ID, or parent thread ID (not known for a JWE)
+    pub thid: Option<String>,
+}
+
+impl EnvelopeInfo {
+    fn from_plain(kind: EnvelopeKind, message: &Value, sender_kids: Vec<String>) -> Self {
+        let field = |name: &str| message.get(name).and_then(Value::as_str).map(String::from);
+        Self {
+            kind,
+            from: field("from"),
+            sender_kids,
+            recipients: message
+                .get("to")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|did| did.as_str().map(String::from))
+                .collect(),
+            message_id: field("id"),
+            message_type: field("type"),
+            thid: field("thid").or_else(|| field("pthid")),
+        }
+    }
+}
+
+/// Report what a received message is and who it is for, without verifying,
+/// decrypting or processing it
+pub fn inspect_envelope(message: &Value) -> Result<EnvelopeInfo> {
+    let normalized;
+    let message = match message {
+        Value::String(_) => {
+            normalized = normalize_envelope(message.clone());
+            &normalized
+        }
+        _ => message,
+    };
+    if !message.is_object() {
+        return Err(Error::Serialization(
+            "Message is not a JSON object".to_string(),
+        ));
+    }
+
+    let is_encrypted = message.get("protected").is_some() && message.get("recipients").is_some();
+    let is_signed = message.get("payload").is_some()
+        && (message.get("signatures").is_some() || message.get("signature").is_some());
+
+    if is_signed {
+        let jws: Jws = serde_json::from_value(message.clone())
+            .map_err(|e| Error::Serialization(format!("Failed to parse JWS: {}", e)))?;
+
+        let mut sender_kids = Vec::new();
+        let mut zip = None;
+        for signature in &jws.signatures {
+            if let Ok(protected) = signature.get_protected_header() {
+                zip = zip.or(protected.zip);
+                sender_kids.push(protected.kid);
+            }
+        }
+
+        let payload = base64_decode_flexible(&jws.payload)
+            .map_err(|e| Error::Serialization(format!("Failed to decode JWS payload: {}", e)))?;
+        let payload = decompress_payload(zip.as_deref(), payload).map_err(|e| {
+            Error::Serialization(format!("Failed to decompress JWS payload: {}", e))
+        })?;
+        let payload: Value = serde_json::from_slice(&payload)
+            .map_err(|e| Error::Serialization(format!("Failed to parse JWS payload: {}", e)))?;
+
+        Ok(EnvelopeInfo::from_plain(
+            EnvelopeKind::Signed,
+            &payload,
+            sender_kids,
+        ))
+    } else if is_encrypted {
+        let jwe: Jwe = serde_json::from_value(message.clone())
+            .map_err(|e| Error::Serialization(format!("Failed to parse JWE: {}", e)))?;
+
+        let mut recipients: Vec<String> = Vec::new();
+        for recipient in &jwe.recipients {
+            let did = did_of(&recipient.header.kid);
+            if !recipients.iter().any(|known| known == did) {
+                recipients.push(did.to_string());
+            }
+        }
+        let sender_kids: Vec<String> = jwe.authcrypt_sender_kid().into_iter().collect();
+
+        Ok(EnvelopeInfo {
+            kind: EnvelopeKind::Encrypted,
+            from: sender_kids.first().map(|kid| did_of(kid).to_string()),
+            sender_kids,
+            recipients,
+            message_id: None,
+            message_type: None,
+            thid: None,
+        })
+    } else {
+        Ok(EnvelopeInfo::from_plain(
+            EnvelopeKind::Plain,
+            message,
+            Vec::new(),
+        ))
+    }
+}
+
+/// The DID part of a key ID
+fn did_of(kid: &str) -> &str {
+    kid.split('#').next().unwrap_or(kid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tap_agent::message::SecurityMode;
+    use tap_agent::message_packing::{PackOptions, Packable};
+    use tap_agent::TapAgent;
+    use tap_msg::didcomm::PlainMessage;
+
+    fn authorize(from: &str, to: &str) -> PlainMessage {
+        PlainMessage::new(
+            "msg-1".to_string(),
+            "https://tap.rsvp/schema/1.0#Authorize".to_string(),
+            serde_json::json!({"transaction_id": "tx-1"}),
+            from.to_string(),
+        )
+        .with_recipient(to)
+        .with_thread_id(Some("tx-1".to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_inspect_envelope() {
+        let (alice, alice_did) = TapAgent::from_ephemeral_key().await.unwrap();
+        let (_bob, bob_did) = TapAgent::from_ephemeral_key().await.unwrap();
+        let message = authorize(&alice_did, &bob_did);
+        let alice_kid = alice.get_signing_kid().await.unwrap();
+
+        // Plain
+        let info = inspect_envelope(&serde_json::to_value(&message).unwrap()).unwrap();
+        assert_eq!(info.kind, EnvelopeKind::Plain);
+        assert_eq!(info.from.as_deref(), Some(alice_did.as_str()));
+        assert_eq!(info.recipients, vec![bob_did.clone()]);
+        assert_eq!(info.message_id.as_deref(), Some("msg-1"));
+        assert_eq!(
+            info.message_type.as_deref(),
+            Some("https://tap.rsvp/schema/1.0#Authorize")
+        );
+        assert_eq!(info.thid.as_deref(), Some("tx-1"));
+
+        // Signed
+        let signed = message
+            .pack(
+                &**alice.key_manager(),
+                PackOptions::new().with_sign(&alice_kid),
+            )
+            .await
+            .unwrap();
+        let info = inspect_envelope(&serde_json::from_str(&signed).unwrap()).unwrap();
+        assert_eq!(info.kind, EnvelopeKind::Signed);
+        assert_eq!(info.sender_kids, vec![alice_kid.clone()]);
+        assert_eq!(info.recipients, vec![bob_did.clone()]);
+        assert_eq!(info.thid.as_deref(), Some("tx-1"));
+
+        // Signed, in compact serialization
+        let compact = serde_json::from_str::<Jws>(&signed)
+            .unwrap()
+            .to_compact()
+            .unwrap();
+        let info = inspect_envelope(&serde_json::Value::String(compact)).unwrap();
+        assert_eq!(info.kind, EnvelopeKind::Signed);
+        assert_eq!(info.sender_kids, vec![alice_kid.clone()]);
+        assert_eq!(info.message_id.as_deref(), Some("msg-1"));
+
+        // Encrypted
+        let encrypted = message
+            .pack(
+                &**alice.key_manager(),
+                PackOptions {
+                    security_mode: SecurityMode::AuthCrypt,
+                    sender_kid: Some(alice_kid.clone()),
+                    recipient_kid: Some(alice.get_encryption_kid(&bob_did).await.unwrap()),
+                    content_encryption: None,
+                    compression: None,
+                },
+            )
+            .await
+            .unwrap();
+        let info = inspect_envelope(&serde_json::from_str(&encrypted).unwrap()).unwrap();
+        assert_eq!(info.kind, EnvelopeKind::Encrypted);
+        assert_eq!(info.recipients, vec![bob_did]);
+        assert_eq!(info.from.as_deref(), Some(alice_did.as_str()));
+        assert_eq!(info.message_type, None);
+
+        assert!(inspect_envelope(&serde_json::json!("not a message")).is_err());
+        assert!(inspect_envelope(&serde_json::json!({
+            "payload": "!!!",
+            "protected": "e30",
+            "signature": "sig"
+        }))
+        .is_err());
+    }
+}
@@ -2,30 +2,52 @@
 //!
 //! This module provides functionality for processing and routing TAP messages between agents.
 
+pub mod custom_types;
+pub mod enrichment;
+pub mod inspect;
+pub mod problem_report_processor;
 pub mod processor;
 pub mod processor_pool;
 pub mod router;
 pub mod sender;
+#[cfg(feature = "travel-rule")]
 pub mod travel_rule_processor;
 pub mod trust_ping_processor;
 #[cfg(test)]
 pub mod trust_ping_tests;
 
 // Re-export processors, routers, and senders
+pub use custom_types::{
+    CustomMessageHandler, CustomMessageType, CustomMessageValidator, MessageTypeRegistry,
+    RequiredBodyFields,
+};
+pub use enrichment::{
+    EnrichmentContext, EnrichmentDecision, EnrichmentHook, EnrichmentPipeline, TransactionDraft,
+};
+pub use inspect::{inspect_envelope, EnvelopeInfo, EnvelopeKind};
+pub use problem_report_processor::ProblemReportProcessor;
+#[cfg(feature = "state-machine")]
+pub use processor::StateMachineIntegrationProcessor;
 pub use processor::{
     DefaultPlainMessageProcessor, LoggingPlainMessageProcessor, PlainMessageProcessor,
-    StateMachineIntegrationProcessor, ValidationPlainMessageProcessor,
+    ValidationPlainMessageProcessor,
+};
+pub use processor_pool::{
+    LaneWeights, MessagePriority, PriorityConfig, ProcessorPool, ProcessorPoolConfig,
 };
-pub use processor_pool::{ProcessorPool, ProcessorPoolConfig};
 pub use router::{DefaultPlainMessageRouter, IntraNodePlainMessageRouter};
 pub use sender::{HttpPlainMessageSender, NodePlainMessageSender, PlainMessageSender};
+#[cfg(feature = "travel-rule")]
 pub use travel_rule_processor::TravelRuleProcessor;
 pub use trust_ping_processor::TrustPingProcessor;
 
 // Import the PlainMessage type from tap-msg
+use crate::diagnostics::{ProcessingDirection, ProcessorTimings};
 use crate::error::Result;
 use async_trait::async_trait;
+use std::sync::Arc;
 use tap_msg::didcomm::PlainMessage;
+use tokio::time::Instant;
 
 /// Router to determine the destination agent for a message
 pub trait PlainMessageRouter: Send + Sync {
@@ -53,9 +75,12 @@ pub enum PlainMessageProcessorType {
     Default(DefaultPlainMessageProcessor),
     Logging(LoggingPlainMessageProcessor),
     Validation(ValidationPlainMessageProcessor),
+    #[cfg(feature = "state-machine")]
     StateMachine(StateMachineIntegrationProcessor),
+    #[cfg(feature = "travel-rule")]
     TravelRule(TravelRuleProcessor),
     TrustPing(TrustPingProcessor),
+    ProblemReport(ProblemReportProcessor),
     Composite(CompositePlainMessageProcessor),
 }
 
@@ -66,55 +91,130 @@ pub enum PlainMessageRouterType {
     IntraNode(IntraNodePlainMessageRouter),
 }
 
+impl PlainMessageProcessorType {
+    /// Snake-case name of the processor, e.g. `validation`
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlainMessageProcessorType::Default(_) => "default",
+            PlainMessageProcessorType::Logging(_) => "logging",
+            PlainMessageProcessorType::Validation(_) => "validation",
+            #[cfg(feature = "state-machine")]
+            PlainMessageProcessorType::StateMachine(_) => "state_machine",
+            #[cfg(feature = "travel-rule")]
+            PlainMessageProcessorType::TravelRule(_) => "travel_rule",
+            PlainMessageProcessorType::TrustPing(_) => "trust_ping",
+            PlainMessageProcessorType::ProblemReport(_) => "problem_report",
+            PlainMessageProcessorType::Composite(_) => "composite",
+        }
+    }
+}
+
+#[async_trait]
+impl PlainMessageProcessor for PlainMessageProcessorType {
+    async fn process_incoming(&self, message: PlainMessage) -> Result<Option<PlainMessage>> {
+        match self {
+            PlainMessageProcessorType::Default(p) => p.process_incoming(message).await,
+            PlainMessageProcessorType::Logging(p) => p.process_incoming(message).await,
+            PlainMessageProcessorType::Validation(p) => p.process_incoming(message).await,
+            #[cfg(feature = "state-machine")]
+            PlainMessageProcessorType::StateMachine(p) => p.process_incoming(message).await,
+            #[cfg(feature = "travel-rule")]
+            PlainMessageProcessorType::TravelRule(p) => p.process_incoming(message).await,
+            PlainMessageProcessorType::TrustPing(p) => p.process_incoming(message).await,
+            PlainMessageProcessorType::ProblemReport(p) => p.process_incoming(message).await,
+            PlainMessageProcessorType::Composite(p) => p.process_incoming(message).await,
+        }
+    }
+
+    async fn process_outgoing(&self, message: PlainMessage) -> Result<Option<PlainMessage>> {
+        match self {
+            PlainMessageProcessorType::Default(p) => p.process_outgoing(message).await,
+            PlainMessageProcessorType::Logging(p) => p.process_outgoing(message).await,
+            PlainMessageProcessorType::Validation(p) => p.process_outgoing(message).await,
+            #[cfg(feature = "state-machine")]
+            PlainMessageProcessorType::StateMachine(p) => p.process_outgoing(message).await,
+            #[cfg(feature = "travel-rule")]
+            PlainMessageProcessorType::TravelRule(p) => p.process_outgoing(message).await,
+            PlainMessageProcessorType::TrustPing(p) => p.process_outgoing(message).await,
+            PlainMessageProcessorType::ProblemReport(p) => p.process_outgoing(message).await,
+            PlainMessageProcessorType::Composite(p) => p.process_outgoing(message).await,
+        }
+    }
+}
+
 /// A message processor that applies multiple processors in sequence
 #[derive(Clone, Debug)]
 pub struct CompositePlainMessageProcessor {
     processors: Vec<PlainMessageProcessorType>,
+    /// Where the time taken by each processor is recorded
+    timings: Option<Arc<ProcessorTimings>>,
 }
 
 impl CompositePlainMessageProcessor {
     /// Create a new composite message processor
     pub fn new(processors: Vec<PlainMessageProcessorType>) -> Self {
-        Self { processors }
+        Self {
+            processors,
+            timings: None,
+        }
+    }
+
+    /// Record the time taken by each processor
+    pub fn with_timings(mut self, timings: Arc<ProcessorTimings>) -> Self {
+        self.timings = Some(timings);
+        self
     }
 
     /// Add a processor to the chain
     pub fn add_processor(&mut self, processor: PlainMessageProcessorType) {
         self.processors.push(processor);
     }
-}
 
-#[async_trait]
-impl PlainMessageProcessor for CompositePlainMessageProcessor {
-    async fn process_incoming(&self, message: PlainMessage) -> Result<Option<PlainMessage>> {
+    /// Whether a processor of the chain, or of a composite within it,
+    /// matches a predicate
+    pub fn contains(&self, predicate: &dyn Fn(&PlainMessageProcessorType) -> bool) -> bool {
+        self.processors.iter().any(|processor| {
+            predicate(processor)
+                || matches!(processor, PlainMessageProcessorType::Composite(composite) if composite.contains(predicate))
+        })
+    }
+
+    /// Apply the processors in sequence
+    async fn process(
+        &self,
+        message: PlainMessage,
+        direction: ProcessingDirection,
+    ) -> Result<Option<PlainMessage>> {
         let mut current_message = message;
 
         for processor in &self.processors {
-            let processed = match processor {
-                PlainMessageProcessorType::Default(p) => {
-                    p.process_incoming(current_message).await?
-                }
-                PlainMessageProcessorType::Logging(p) => {
-                    p.process_incoming(current_message).await?
-                }
-                PlainMessageProcessorType::Validation(p) => {
-                    p.process_incoming(current_message).await?
-                }
-                PlainMessageProcessorType::StateMachine(p) => {
-                    p.process_incoming(current_message).await?
-                }
-                PlainMessageProcessorType::TravelRule(p) => {
-                    p.process_incoming(current_message).await?
-                }
-                PlainMessageProcessorType::TrustPing(p) => {
-                    p.process_incoming(current_message).await?
-                }
-                PlainMessageProcessorType::Composite(p) => {
-                    p.process_incoming(current_message).await?
+            let call = async {
+                match direction {
+                    ProcessingDirection::Incoming => {
+                        processor.process_incoming(current_message).await
+                    }
+                    ProcessingDirection::Outgoing => {
+                        processor.process_outgoing(current_message).await
+                    }
                 }
             };
+            #[cfg(feature = "diagnostics")]
+            let call = tracing::Instrument::instrument(
+                call,
+                tracing::debug_span!(
+                    "processor",
+                    processor = processor.name(),
+                    direction = direction.as_str()
+                ),
+            );
+
+            let started = self.timings.as_ref().map(|_| Instant::now());
+            let processed = call.await;
+            if let (Some(timings), Some(started)) = (&self.timings, started) {
+                timings.record(processor.name(), direction, started.elapsed());
+            }
 
-            if let Some(msg) = processed {
+            if let Some(msg) = processed? {
                 current_message = msg;
             } else {
                 // PlainMessage was filtered out
@@ -124,44 +224,16 @@ impl PlainMessageProcessor for CompositePlainMessageProcessor {
 
         Ok(Some(current_message))
     }
+}
 
-    async fn process_outgoing(&self, message: PlainMessage) -> Result<Option<PlainMessage>> {
-        let mut current_message = message;
-
-        for processor in &self.processors {
-            let processed = match processor {
-                PlainMessageProcessorType::Default(p) => {
-                    p.process_outgoing(current_message).await?
-                }
-                PlainMessageProcessorType::Logging(p) => {
-                    p.process_outgoing(current_message).await?
-                }
-                PlainMessageProcessorType::Validation(p) => {
-                    p.process_outgoing(current_message).await?
-                }
-                PlainMessageProcessorType::StateMachine(p) => {
-                    p.process_outgoing(current_message).await?
-                }
-                PlainMessageProcessorType::TravelRule(p) => {
-                    p.process_outgoing(current_message).await?
-                }
-                PlainMessageProcessorType::TrustPing(p) => {
-                    p.process_outgoing(current_message).await?
-                }
-                PlainMessageProcessorType::Composite(p) => {
-                    p.process_outgoing(current_message).await?
-                }
-            };
-
-            if let Some(msg) = processed {
-                current_message = msg;
-            } else {
-                // PlainMessage was filtered out
-                return Ok(None);
-            }
-        }
+#[async_trait]
+impl PlainMessageProcessor for CompositePlainMessageProcessor {
+    async fn process_incoming(&self, message: PlainMessage) -> Result<Option<PlainMessage>> {
+        self.process(message, ProcessingDirection::Incoming).await
+    }
 
-        Ok(Some(current_message))
+    async fn process_outgoing(&self, message: PlainMessage) -> Result<Option<PlainMessage>> {
+        self.process(message, ProcessingDirection::Outgoing).await
     }
 }
 
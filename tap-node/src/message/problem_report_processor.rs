@@ -0,0 +1,156 @@
+//! Problem Report Protocol Processor
+//!
+//! Surfaces DIDComm problem reports received from counterparties as
+//! [`NodeEvent::ProblemReportReceived`](crate::event::NodeEvent::ProblemReportReceived)
+//! events so applications can react to failures on the other side.
+
+use crate::error::Result;
+use crate::event::EventBus;
+use crate::message::processor::PlainMessageProcessor;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::problem_report::PROBLEM_REPORT_TYPE;
+use tap_msg::message::ProblemReport;
+
+/// Processor that publishes received Problem Report messages
+pub struct ProblemReportProcessor {
+    /// Optional event bus for publishing problem report events
+    event_bus: Option<Arc<EventBus>>,
+}
+
+// Manual Debug implementation
+impl std::fmt::Debug for ProblemReportProcessor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProblemReportProcessor")
+            .field("event_bus", &self.event_bus.is_some())
+            .finish()
+    }
+}
+
+// Manual Clone implementation
+impl Clone for ProblemReportProcessor {
+    fn clone(&self) -> Self {
+        Self {
+            event_bus: self.event_bus.clone(),
+        }
+    }
+}
+
+impl Default for ProblemReportProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProblemReportProcessor {
+    /// Create a new Problem Report processor
+    pub fn new() -> Self {
+        Self { event_bus: None }
+    }
+
+    /// Create a new Problem Report processor with an event bus for publishing reports
+    pub fn with_event_bus(event_bus: Arc<EventBus>) -> Self {
+        Self {
+            event_bus: Some(event_bus),
+        }
+    }
+}
+
+#[async_trait]
+impl PlainMessageProcessor for ProblemReportProcessor {
+    async fn process_incoming(&self, message: PlainMessage) -> Result<Option<PlainMessage>> {
+        if message.type_ != PROBLEM_REPORT_TYPE {
+            return Ok(Some(message));
+        }
+
+        match serde_json::from_value::<ProblemReport>(message.body.clone()) {
+            Ok(report) => {
+                log::warn!(
+                    "Received problem report {} from {}: code={}, thread={:?}, comment={:?}",
+                    message.id,
+                    message.from,
+                    report.code,
+                    message.pthid,
+                    report.interpolated_comment()
+                );
+
+                if let Some(ref event_bus) = self.event_bus {
+                    event_bus
+                        .publish_problem_report_received(
+                            message.id.clone(),
+                            message.from.clone(),
+                            &report,
+                            message.pthid.clone(),
+                        )
+                        .await;
+                }
+            }
+            Err(e) => {
+                log::warn!("Failed to parse problem report {}: {}", message.id, e);
+            }
+        }
+
+        // Always pass the message through unchanged
+        Ok(Some(message))
+    }
+
+    async fn process_outgoing(&self, message: PlainMessage) -> Result<Option<PlainMessage>> {
+        // No special processing for outgoing messages
+        Ok(Some(message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::NodeEvent;
+    use tap_msg::message::{ProblemCode, ProblemScope};
+
+    #[tokio::test]
+    async fn test_problem_report_publishes_event() {
+        let event_bus = Arc::new(EventBus::new());
+        let mut receiver = event_bus.subscribe_channel();
+        let processor = ProblemReportProcessor::with_event_bus(event_bus);
+
+        let original = PlainMessage::new(
+            "msg-1".to_string(),
+            "https://tap.rsvp/schema/1.0#Authorize".to_string(),
+            serde_json::json!({"transaction_id": "tx-1"}),
+            "did:example:alice".to_string(),
+        )
+        .with_recipient("did:example:bob")
+        .with_thread_id(Some("tx-1".to_string()));
+        let report = ProblemReport::new(
+            ProblemCode::error(ProblemScope::Protocol, "xfer.cant-use-endpoint"),
+            "Unable to use the {1} endpoint.",
+        )
+        .with_args(vec!["https://alice.example/didcomm".to_string()]);
+        let message = report.to_reply(&original, "did:example:bob").unwrap();
+
+        let result = processor.process_incoming(message.clone()).await.unwrap();
+        assert_eq!(result.unwrap().id, message.id);
+
+        match receiver.recv().await.unwrap().as_ref() {
+            NodeEvent::ProblemReportReceived {
+                message_id,
+                from,
+                code,
+                comment,
+                pthid,
+                escalate_to,
+            } => {
+                assert_eq!(message_id, &message.id);
+                assert_eq!(from, "did:example:bob");
+                assert_eq!(code, "e.p.xfer.cant-use-endpoint");
+                assert_eq!(
+                    comment.as_deref(),
+                    Some("Unable to use the https://alice.example/didcomm endpoint.")
+                );
+                assert_eq!(pthid.as_deref(), Some("tx-1"));
+                assert_eq!(escalate_to, &None);
+            }
+            other => panic!("Unexpected event: {:?}", other),
+        }
+    }
+}
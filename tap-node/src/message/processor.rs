@@ -47,7 +47,9 @@ use log::{debug, info};
 use std::sync::Arc;
 use tap_msg::didcomm::PlainMessage;
 
+use crate::clock::{system_clock, Clock};
 use crate::error::Result;
+use crate::message::custom_types::MessageTypeRegistry;
 
 /// Trait for processing DIDComm messages in TAP nodes
 ///
@@ -188,7 +190,40 @@ impl PlainMessageProcessor for LoggingPlainMessageProcessor {
 /// messages by returning Ok(None), or let valid messages continue through the
 /// pipeline by returning Ok(Some(message)).
 #[derive(Debug, Clone)]
-pub struct ValidationPlainMessageProcessor;
+pub struct ValidationPlainMessageProcessor {
+    clock: Arc<dyn Clock>,
+    message_types: Option<Arc<MessageTypeRegistry>>,
+}
+
+impl ValidationPlainMessageProcessor {
+    /// Create a validator that checks timestamps against the given clock
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            message_types: None,
+        }
+    }
+
+    /// Accept messages of the custom types in a registry, whatever their
+    /// protocol
+    pub fn with_message_types(mut self, message_types: Arc<MessageTypeRegistry>) -> Self {
+        self.message_types = Some(message_types);
+        self
+    }
+
+    /// Whether a message type is a registered custom type
+    fn is_custom_type(&self, message_type: &str) -> bool {
+        self.message_types
+            .as_ref()
+            .is_some_and(|registry| registry.contains(message_type))
+    }
+}
+
+impl Default for ValidationPlainMessageProcessor {
+    fn default() -> Self {
+        Self::with_clock(system_clock())
+    }
+}
 
 #[async_trait]
 impl PlainMessageProcessor for ValidationPlainMessageProcessor {
@@ -242,15 +277,13 @@ impl PlainMessageProcessor for ValidationPlainMessageProcessor {
             // Detect if timestamp is in seconds or milliseconds
             // Timestamps in seconds since 1970 are much smaller than timestamps in milliseconds
             // A reasonable cutoff is 10^10 (around year 2286 in seconds, or year 1970 + 4 months in milliseconds)
-            let (normalized_created_time, now) = if created_time < 10_000_000_000 {
+            let now = self.clock.now().timestamp_millis() as u64;
+            let normalized_created_time = if created_time < 10_000_000_000 {
                 // Timestamp is likely in seconds, convert to milliseconds
-                (
-                    created_time * 1000,
-                    chrono::Utc::now().timestamp_millis() as u64,
-                )
+                created_time * 1000
             } else {
                 // Timestamp is likely in milliseconds
-                (created_time, chrono::Utc::now().timestamp_millis() as u64)
+                created_time
             };
 
             // Check if the timestamp is more than 5 minutes in the future (300,000 milliseconds)
@@ -262,6 +295,9 @@ impl PlainMessageProcessor for ValidationPlainMessageProcessor {
 
         // Protocol-specific validation based on message type
         let message_type = &message.type_;
+        if self.is_custom_type(message_type) {
+            return Ok(Some(message));
+        }
 
         // Validate TAP messages
         if message_type.starts_with("https://tap.rsvp/schema/") {
@@ -384,15 +420,13 @@ impl PlainMessageProcessor for ValidationPlainMessageProcessor {
             // Detect if timestamp is in seconds or milliseconds
             // Timestamps in seconds since 1970 are much smaller than timestamps in milliseconds
             // A reasonable cutoff is 10^10 (around year 2286 in seconds, or year 1970 + 4 months in milliseconds)
-            let (normalized_created_time, now) = if created_time < 10_000_000_000 {
+            let now = self.clock.now().timestamp_millis() as u64;
+            let normalized_created_time = if created_time < 10_000_000_000 {
                 // Timestamp is likely in seconds, convert to milliseconds
-                (
-                    created_time * 1000,
-                    chrono::Utc::now().timestamp_millis() as u64,
-                )
+                created_time * 1000
             } else {
                 // Timestamp is likely in milliseconds
-                (created_time, chrono::Utc::now().timestamp_millis() as u64)
+                created_time
             };
 
             // Check if the timestamp is more than 5 minutes in the future (300,000 milliseconds)
@@ -404,6 +438,9 @@ impl PlainMessageProcessor for ValidationPlainMessageProcessor {
 
         // Protocol-specific validation based on message type
         let message_type = &message.type_;
+        if self.is_custom_type(message_type) {
+            return Ok(Some(message));
+        }
 
         // Validate TAP messages
         if message_type.starts_with("https://tap.rsvp/schema/") {
@@ -512,8 +549,9 @@ impl DefaultPlainMessageProcessorImpl {
     pub fn new() -> Self {
         let logging_processor =
             crate::message::PlainMessageProcessorType::Logging(LoggingPlainMessageProcessor);
-        let validation_processor =
-            crate::message::PlainMessageProcessorType::Validation(ValidationPlainMessageProcessor);
+        let validation_processor = crate::message::PlainMessageProcessorType::Validation(
+            ValidationPlainMessageProcessor::default(),
+        );
 
         let mut processor = crate::message::CompositePlainMessageProcessor::new(Vec::new());
         processor.add_processor(validation_processor);
@@ -538,18 +576,23 @@ impl PlainMessageProcessor for DefaultPlainMessageProcessorImpl {
             crate::message::PlainMessageProcessorType::Validation(p) => {
                 p.process_incoming(message).await
             }
+            #[cfg(feature = "state-machine")]
             crate::message::PlainMessageProcessorType::StateMachine(p) => {
                 p.process_incoming(message).await
             }
             crate::message::PlainMessageProcessorType::Composite(p) => {
                 p.process_incoming(message).await
             }
+            #[cfg(feature = "travel-rule")]
             crate::message::PlainMessageProcessorType::TravelRule(p) => {
                 p.process_incoming(message).await
             }
             crate::message::PlainMessageProcessorType::TrustPing(p) => {
                 p.process_incoming(message).await
             }
+            crate::message::PlainMessageProcessorType::ProblemReport(p) => {
+                p.process_incoming(message).await
+            }
         }
     }
 
@@ -564,22 +607,28 @@ impl PlainMessageProcessor for DefaultPlainMessageProcessorImpl {
             crate::message::PlainMessageProcessorType::Validation(p) => {
                 p.process_outgoing(message).await
             }
+            #[cfg(feature = "state-machine")]
             crate::message::PlainMessageProcessorType::StateMachine(p) => {
                 p.process_outgoing(message).await
             }
             crate::message::PlainMessageProcessorType::Composite(p) => {
                 p.process_outgoing(message).await
             }
+            #[cfg(feature = "travel-rule")]
             crate::message::PlainMessageProcessorType::TravelRule(p) => {
                 p.process_outgoing(message).await
             }
             crate::message::PlainMessageProcessorType::TrustPing(p) => {
                 p.process_outgoing(message).await
             }
+            crate::message::PlainMessageProcessorType::ProblemReport(p) => {
+                p.process_outgoing(message).await
+            }
         }
     }
 }
 
+#[cfg(feature = "state-machine")]
 /// State machine integration processor
 ///
 /// This processor integrates the message processing pipeline with the transaction state machine.
@@ -590,6 +639,7 @@ pub struct StateMachineIntegrationProcessor {
     state_processor: Option<Arc<dyn crate::state_machine::TransactionStateProcessor>>,
 }
 
+#[cfg(feature = "state-machine")]
 impl std::fmt::Debug for StateMachineIntegrationProcessor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("StateMachineIntegrationProcessor")
@@ -598,12 +648,14 @@ impl std::fmt::Debug for StateMachineIntegrationProcessor {
     }
 }
 
+#[cfg(feature = "state-machine")]
 impl Default for StateMachineIntegrationProcessor {
     fn default() -> Self {
         Self::new()
     }
 }
 
+#[cfg(feature = "state-machine")]
 impl StateMachineIntegrationProcessor {
     /// Create a new state machine integration processor
     pub fn new() -> Self {
@@ -622,6 +674,7 @@ impl StateMachineIntegrationProcessor {
     }
 }
 
+#[cfg(feature = "state-machine")]
 #[async_trait]
 impl PlainMessageProcessor for StateMachineIntegrationProcessor {
     async fn process_incoming(&self, message: PlainMessage) -> Result<Option<PlainMessage>> {
@@ -1,9 +1,18 @@
 //! Processor pool for concurrent message processing.
 //!
 //! This module provides a processor pool for handling concurrent message processing.
+//!
+//! Submitted messages wait in one of three priority lanes. A dispatcher hands
+//! them to the workers in weighted rounds, so replies that unblock a
+//! transaction, such as `Authorize` and `Reject`, are not stuck behind a
+//! backlog of new transfers, while lower lanes still make progress.
 
+use std::collections::HashMap;
 use tap_msg::didcomm::PlainMessage;
-use tokio::sync::mpsc::{channel, Sender};
+use tap_msg::message::{
+    Authorize, Cancel, Reject, Revert, Settle, TapMessageBody, TrustPing, TrustPingResponse,
+};
+use tokio::sync::mpsc::{channel, Receiver, Sender};
 use tokio::time::Duration;
 use tracing::error;
 
@@ -11,15 +20,134 @@ use crate::error::{Error, Result};
 use crate::message::processor::PlainMessageProcessor;
 use crate::message::{CompositePlainMessageProcessor, PlainMessageProcessorType};
 
+/// Priority lane of a message in the processor pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MessagePriority {
+    /// Time-sensitive replies such as `Authorize` and `Reject`
+    High,
+    /// Regular traffic such as new transfers
+    Normal,
+    /// Background traffic such as trust pings
+    Low,
+}
+
+impl MessagePriority {
+    /// All priorities, highest first
+    pub const ALL: [MessagePriority; 3] = [
+        MessagePriority::High,
+        MessagePriority::Normal,
+        MessagePriority::Low,
+    ];
+
+    fn lane(self) -> usize {
+        match self {
+            MessagePriority::High => 0,
+            MessagePriority::Normal => 1,
+            MessagePriority::Low => 2,
+        }
+    }
+}
+
+/// Number of messages taken from each lane per scheduling round
+///
+/// A lane is only skipped when it is empty, so every lane with queued
+/// messages gets its share of each round. Weights below 1 are treated as 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LaneWeights {
+    /// Messages per round from the high priority lane
+    pub high: u32,
+    /// Messages per round from the normal priority lane
+    pub normal: u32,
+    /// Messages per round from the low priority lane
+    pub low: u32,
+}
+
+impl Default for LaneWeights {
+    fn default() -> Self {
+        Self {
+            high: 8,
+            normal: 3,
+            low: 1,
+        }
+    }
+}
+
+impl LaneWeights {
+    fn credits(&self) -> [u32; 3] {
+        [self.high.max(1), self.normal.max(1), self.low.max(1)]
+    }
+}
+
+/// Mapping from message type to priority lane
+#[derive(Debug, Clone)]
+pub struct PriorityConfig {
+    /// Priority per message type URI, e.g. `https://tap.rsvp/schema/1.0#Authorize`
+    pub message_types: HashMap<String, MessagePriority>,
+    /// Priority of message types missing from `message_types`
+    pub default: MessagePriority,
+    /// Share of each lane in a scheduling round
+    pub weights: LaneWeights,
+}
+
+impl Default for PriorityConfig {
+    fn default() -> Self {
+        let high = [
+            Authorize::message_type(),
+            Reject::message_type(),
+            Cancel::message_type(),
+            Settle::message_type(),
+            Revert::message_type(),
+        ];
+        let low = [TrustPing::message_type(), TrustPingResponse::message_type()];
+
+        let message_types = high
+            .into_iter()
+            .map(|t| (t.to_string(), MessagePriority::High))
+            .chain(
+                low.into_iter()
+                    .map(|t| (t.to_string(), MessagePriority::Low)),
+            )
+            .collect();
+
+        Self {
+            message_types,
+            default: MessagePriority::Normal,
+            weights: LaneWeights::default(),
+        }
+    }
+}
+
+impl PriorityConfig {
+    /// Set the priority of a message type
+    pub fn with_priority(
+        mut self,
+        message_type: impl Into<String>,
+        priority: MessagePriority,
+    ) -> Self {
+        self.message_types.insert(message_type.into(), priority);
+        self
+    }
+
+    /// Priority of a message
+    pub fn classify(&self, message: &PlainMessage) -> MessagePriority {
+        self.message_types
+            .get(&message.type_)
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
 /// Configuration for the processor pool
 #[derive(Debug, Clone)]
 pub struct ProcessorPoolConfig {
     /// The number of worker tasks to create
     pub workers: usize,
-    /// The capacity of the message channel
+    /// The capacity of each priority lane
     pub channel_capacity: usize,
     /// The maximum duration to wait for a worker to process a message
     pub worker_timeout: Duration,
+    /// Priority lanes of message types and their scheduling weights
+    pub priorities: PriorityConfig,
 }
 
 impl Default for ProcessorPoolConfig {
@@ -28,6 +156,7 @@ impl Default for ProcessorPoolConfig {
             workers: 4,
             channel_capacity: 100,
             worker_timeout: Duration::from_secs(30),
+            priorities: PriorityConfig::default(),
         }
     }
 }
@@ -37,31 +166,40 @@ impl Default for ProcessorPoolConfig {
 pub struct ProcessorPool {
     /// The message processor to use
     processor: CompositePlainMessageProcessor,
-    /// Channel for submitting messages for processing
-    tx: Sender<PlainMessage>,
+    /// Channels of the priority lanes, highest first
+    lanes: [Sender<PlainMessage>; 3],
+    /// Mapping from message type to lane
+    priorities: PriorityConfig,
 }
 
 impl ProcessorPool {
     /// Create a new processor pool
     pub fn new(config: ProcessorPoolConfig) -> Self {
-        let (tx, mut rx) = channel::<PlainMessage>(config.channel_capacity);
+        let (high_tx, high_rx) = channel::<PlainMessage>(config.channel_capacity);
+        let (normal_tx, normal_rx) = channel::<PlainMessage>(config.channel_capacity);
+        let (low_tx, low_rx) = channel::<PlainMessage>(config.channel_capacity);
         let processors: Vec<PlainMessageProcessorType> = Vec::new();
         let processor = CompositePlainMessageProcessor::new(processors);
         let processor_for_workers = processor.clone();
+        let weights = config.priorities.weights;
 
         // Spawn a single task to distribute messages to workers
-        tokio::spawn(async move {
-            // Create worker channels
+        let dispatcher = async move {
+            // Create worker channels. Each worker holds at most one waiting
+            // message so that the backlog stays in the priority lanes.
             let mut worker_channels = Vec::with_capacity(config.workers);
             for _ in 0..config.workers {
-                let (worker_tx, mut worker_rx) = channel::<PlainMessage>(config.channel_capacity);
+                let (worker_tx, mut worker_rx) = channel::<PlainMessage>(1);
+                #[cfg(feature = "diagnostics")]
+                let span =
+                    tracing::debug_span!("processor_pool_worker", worker = worker_channels.len());
                 worker_channels.push(worker_tx);
 
                 let worker_processor = processor_for_workers.clone();
                 let worker_timeout = config.worker_timeout;
 
                 // Spawn a worker to process messages from its channel
-                tokio::spawn(async move {
+                let worker = async move {
                     while let Some(message) = worker_rx.recv().await {
                         match tokio::time::timeout(
                             worker_timeout,
@@ -82,12 +220,18 @@ impl ProcessorPool {
                             }
                         }
                     }
-                });
+                };
+                #[cfg(feature = "diagnostics")]
+                let worker = tracing::Instrument::instrument(worker, span);
+                tokio::spawn(worker);
             }
 
+            let mut lanes = [high_rx, normal_rx, low_rx];
+            let mut scheduler = LaneScheduler::new(weights);
+
             // Round-robin distribute messages to workers
             let mut current_worker = 0;
-            while let Some(message) = rx.recv().await {
+            while let Some(message) = scheduler.next(&mut lanes).await {
                 if worker_channels.is_empty() {
                     break;
                 }
@@ -107,16 +251,43 @@ impl ProcessorPool {
                 // Advance to next worker
                 current_worker = (current_worker + 1) % worker_channels.len();
             }
-        });
+        };
+        #[cfg(feature = "diagnostics")]
+        let dispatcher =
+            tracing::Instrument::instrument(dispatcher, tracing::debug_span!("processor_pool"));
+        tokio::spawn(dispatcher);
 
-        Self { processor, tx }
+        Self {
+            processor,
+            lanes: [high_tx, normal_tx, low_tx],
+            priorities: config.priorities,
+        }
     }
 
-    /// Submit a message for processing
+    /// Submit a message for processing in the lane of its message type
     pub async fn submit(&self, message: PlainMessage) -> Result<()> {
-        self.tx.send(message).await.map_err(|e| {
-            Error::Processing(format!("Failed to submit message to processor pool: {}", e))
-        })
+        let priority = self.priorities.classify(&message);
+        self.submit_with_priority(message, priority).await
+    }
+
+    /// Submit a message for processing in the given lane
+    pub async fn submit_with_priority(
+        &self,
+        message: PlainMessage,
+        priority: MessagePriority,
+    ) -> Result<()> {
+        self.lanes[priority.lane()]
+            .send(message)
+            .await
+            .map_err(|e| {
+                Error::Processing(format!("Failed to submit message to processor pool: {}", e))
+            })
+    }
+
+    /// Number of messages waiting in a lane
+    pub fn queued(&self, priority: MessagePriority) -> usize {
+        let lane = &self.lanes[priority.lane()];
+        lane.max_capacity() - lane.capacity()
     }
 
     /// Add a processor to the pool
@@ -124,3 +295,134 @@ impl ProcessorPool {
         self.processor.add_processor(processor);
     }
 }
+
+/// Weighted round-robin over the priority lanes
+struct LaneScheduler {
+    weights: [u32; 3],
+    credits: [u32; 3],
+}
+
+impl LaneScheduler {
+    fn new(weights: LaneWeights) -> Self {
+        let weights = weights.credits();
+        Self {
+            weights,
+            credits: weights,
+        }
+    }
+
+    /// Take the next message, waiting if all lanes are empty
+    ///
+    /// Returns `None` once all lanes are closed and drained.
+    async fn next(&mut self, lanes: &mut [Receiver<PlainMessage>; 3]) -> Option<PlainMessage> {
+        if let Some(message) = self.try_next(lanes) {
+            return Some(message);
+        }
+
+        let [high, normal, low] = lanes;
+        let (lane, message) = tokio::select! {
+            biased;
+            Some(message) = high.recv() => (0, message),
+            Some(message) = normal.recv() => (1, message),
+            Some(message) = low.recv() => (2, message),
+            else => return None,
+        };
+        self.credits[lane] = self.credits[lane].saturating_sub(1);
+        Some(message)
+    }
+
+    /// Take the next queued message of the current round
+    ///
+    /// Starts a new round when every lane with queued messages has used its
+    /// share of the current one.
+    fn try_next(&mut self, lanes: &mut [Receiver<PlainMessage>; 3]) -> Option<PlainMessage> {
+        for _ in 0..2 {
+            for (lane, receiver) in lanes.iter_mut().enumerate() {
+                if self.credits[lane] == 0 {
+                    continue;
+                }
+                if let Ok(message) = receiver.try_recv() {
+                    self.credits[lane] -= 1;
+                    return Some(message);
+                }
+            }
+            self.credits = self.weights;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(type_: &str, id: usize) -> PlainMessage {
+        PlainMessage::new(
+            id.to_string(),
+            type_.to_string(),
+            serde_json::json!({}),
+            "did:example:sender".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_default_classification() {
+        let config = PriorityConfig::default();
+        assert_eq!(
+            config.classify(&message(Authorize::message_type(), 0)),
+            MessagePriority::High
+        );
+        assert_eq!(
+            config.classify(&message("https://tap.rsvp/schema/1.0#Transfer", 0)),
+            MessagePriority::Normal
+        );
+        assert_eq!(
+            config.classify(&message(TrustPing::message_type(), 0)),
+            MessagePriority::Low
+        );
+
+        let config =
+            config.with_priority("https://tap.rsvp/schema/1.0#Transfer", MessagePriority::Low);
+        assert_eq!(
+            config.classify(&message("https://tap.rsvp/schema/1.0#Transfer", 0)),
+            MessagePriority::Low
+        );
+    }
+
+    #[tokio::test]
+    async fn test_weighted_scheduling() {
+        let (high_tx, high_rx) = channel(100);
+        let (normal_tx, normal_rx) = channel(100);
+        let (low_tx, low_rx) = channel(100);
+        for id in 0..10 {
+            normal_tx.send(message("normal", id)).await.unwrap();
+            low_tx.send(message("low", id)).await.unwrap();
+        }
+        for id in 0..4 {
+            high_tx.send(message("high", id)).await.unwrap();
+        }
+        drop((high_tx, normal_tx, low_tx));
+
+        let mut lanes = [high_rx, normal_rx, low_rx];
+        let mut scheduler = LaneScheduler::new(LaneWeights {
+            high: 2,
+            normal: 2,
+            low: 1,
+        });
+        let mut order = Vec::new();
+        while let Some(message) = scheduler.next(&mut lanes).await {
+            order.push(message.type_);
+        }
+
+        assert_eq!(order.len(), 24);
+        assert_eq!(
+            &order[..10],
+            [
+                "high", "high", "normal", "normal", "low", "high", "high", "normal", "normal",
+                "low"
+            ]
+        );
+        // Once the high lane is drained, the others share the rounds
+        assert_eq!(&order[10..13], ["normal", "normal", "low"]);
+    }
+}
@@ -72,8 +72,9 @@ use std::fmt::{self, Debug};
 use std::sync::Arc;
 
 use crate::error::{Error, Result};
+#[cfg(feature = "delivery-tracking")]
 use crate::storage::{
-    models::{DeliveryStatus, DeliveryType},
+    models::{Delivery, DeliveryStatus, DeliveryType},
     Storage,
 };
 
@@ -135,7 +136,8 @@ impl PlainMessageSender for NodePlainMessageSender {
 /// - Connection timeouts
 /// - Request failures
 /// - Invalid responses
-/// - Rate limiting
+/// - Rate limiting, waiting for the `Retry-After` delay of 429 and 503 responses
+/// - Duplicates, treating 409 responses to the `Idempotency-Key` header as delivered
 ///
 /// # Configuration
 ///
@@ -154,6 +156,7 @@ pub struct HttpPlainMessageSender {
     #[allow(dead_code)] // Used for future timeout configuration
     timeout_ms: u64,
     /// Maximum number of retries
+    #[cfg_attr(not(feature = "reqwest"), allow(dead_code))]
     max_retries: u32,
 }
 
@@ -214,6 +217,119 @@ impl HttpPlainMessageSender {
     }
 }
 
+/// Longest Retry-After delay the HTTP senders wait for before giving up
+pub const MAX_RETRY_AFTER: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Maximum number of characters of a response body kept in delivery errors
+#[cfg(all(not(target_arch = "wasm32"), feature = "reqwest"))]
+const RESPONSE_SNIPPET_LEN: usize = 512;
+
+/// Extract the DIDComm message ID from a packed message
+///
+/// Plain messages carry the ID at the top level and signed messages inside
+/// their JWS payload. Encrypted messages do not expose it, so None is returned.
+pub fn packed_message_id(packed_message: &str) -> Option<String> {
+    use base64::Engine;
+
+    let value: serde_json::Value = serde_json::from_str(packed_message).ok()?;
+    if let Some(id) = value.get("id").and_then(|v| v.as_str()) {
+        return Some(id.to_string());
+    }
+
+    let payload = value.get("payload")?.as_str()?;
+    let decoded = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .ok()?;
+    let payload: serde_json::Value = serde_json::from_slice(&decoded).ok()?;
+    payload.get("id")?.as_str().map(String::from)
+}
+
+/// Idempotency-Key header value for a packed message
+///
+/// This is the message ID, so a recipient can recognize a resent message as a
+/// duplicate. Encrypted messages use a SHA-256 digest of the packed message.
+pub fn idempotency_key(packed_message: &str) -> String {
+    packed_message_id(packed_message).unwrap_or_else(|| {
+        use sha2::{Digest, Sha256};
+        format!("{:x}", Sha256::digest(packed_message.as_bytes()))
+    })
+}
+
+/// Content-Type header value for a packed message
+///
+/// DIDComm endpoints such as tap-http accept signed and encrypted messages
+/// under their own media types.
+pub fn didcomm_content_type(packed_message: &str) -> &'static str {
+    let value: serde_json::Value = serde_json::from_str(packed_message).unwrap_or_default();
+    if value.get("protected").is_some() && value.get("recipients").is_some() {
+        "application/didcomm-encrypted+json"
+    } else if value.get("payload").is_some() {
+        "application/didcomm-signed+json"
+    } else {
+        "application/didcomm-plain+json"
+    }
+}
+
+/// Result of delivering a packed message to one recipient over HTTP
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    any(feature = "reqwest", feature = "delivery-tracking")
+))]
+#[derive(Debug, Clone)]
+struct HttpDeliveryOutcome {
+    /// Status code of the last response received
+    status_code: Option<u16>,
+    /// Why delivery failed, including the start of the response body
+    error: Option<String>,
+}
+
+/// Parse a Retry-After header given in seconds or as an HTTP date
+#[cfg(all(not(target_arch = "wasm32"), feature = "reqwest"))]
+fn parse_retry_after(value: &str) -> Option<std::time::Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(std::time::Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// Truncate a response body for logging and delivery records
+#[cfg(all(not(target_arch = "wasm32"), feature = "reqwest"))]
+pub(crate) fn response_snippet(body: &str) -> String {
+    let body = body.trim();
+    match body.char_indices().nth(RESPONSE_SNIPPET_LEN) {
+        Some((end, _)) => format!("{}...", &body[..end]),
+        None => body.to_string(),
+    }
+}
+
+/// Combine per-recipient delivery failures into a single error
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    any(feature = "reqwest", feature = "delivery-tracking")
+))]
+fn delivery_failures_result(failures: Vec<(String, String)>) -> Result<()> {
+    if failures.is_empty() {
+        return Ok(());
+    }
+
+    let failure_messages = failures
+        .iter()
+        .map(|(did, err)| format!("{}: {}", did, err))
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    Err(Error::Dispatch(format!(
+        "Failed to send message to some recipients: {}",
+        failure_messages
+    )))
+}
+
 /// WebSocket message sender implementation for sending messages over WebSockets
 ///
 /// This sender enables real-time bidirectional communication between TAP nodes,
@@ -629,105 +745,174 @@ impl PlainMessageSender for WebSocketPlainMessageSender {
 }
 
 #[cfg(all(not(target_arch = "wasm32"), feature = "reqwest"))]
-#[async_trait]
-impl PlainMessageSender for HttpPlainMessageSender {
-    async fn send(&self, packed_message: String, recipient_dids: Vec<String>) -> Result<()> {
-        if recipient_dids.is_empty() {
-            return Err(Error::Dispatch("No recipients specified".to_string()));
-        }
+impl HttpPlainMessageSender {
+    /// Deliver a packed message to one recipient at its endpoint under the
+    /// base URL
+    async fn deliver(&self, recipient: &str, packed_message: &str) -> HttpDeliveryOutcome {
+        self.deliver_to(recipient, &self.get_endpoint_url(recipient), packed_message)
+            .await
+    }
 
-        // Track failures to report them at the end
-        let mut failures = Vec::new();
+    /// Deliver a packed message to one recipient at the given endpoint,
+    /// retrying transient failures
+    ///
+    /// 429 and 503 responses are retried after their Retry-After delay, giving
+    /// up if it exceeds [`MAX_RETRY_AFTER`]. A 409 response means the recipient
+    /// already has a message with the same Idempotency-Key, so it counts as
+    /// delivered.
+    async fn deliver_to(
+        &self,
+        recipient: &str,
+        endpoint: &str,
+        packed_message: &str,
+    ) -> HttpDeliveryOutcome {
+        let key = idempotency_key(packed_message);
+        log::info!("Sending message to {} via HTTP at {}", recipient, endpoint);
+
+        let mut outcome = HttpDeliveryOutcome {
+            status_code: None,
+            error: Some("Unknown error".to_string()),
+        };
+        let mut attempt = 0;
 
-        // Send the message to each recipient
-        for recipient in &recipient_dids {
-            let endpoint = self.get_endpoint_url(recipient);
-            log::info!("Sending message to {} via HTTP at {}", recipient, endpoint);
+        while attempt < self.max_retries {
+            attempt += 1;
+            let mut retry_after = None;
 
-            // Retry logic
-            let mut attempt = 0;
-            let mut success = false;
-            let mut last_error = None;
+            match self
+                .client
+                .post(endpoint)
+                .header("Content-Type", didcomm_content_type(packed_message))
+                .header("Idempotency-Key", &key)
+                .body(packed_message.to_string())
+                .send()
+                .await
+            {
+                Ok(response) => {
+                    let status = response.status();
+                    outcome.status_code = Some(status.as_u16());
 
-            while attempt < self.max_retries && !success {
-                attempt += 1;
+                    if status.is_success() {
+                        log::debug!("Successfully sent message to {}", recipient);
+                        outcome.error = None;
+                        return outcome;
+                    }
+                    if status == reqwest::StatusCode::CONFLICT {
+                        log::debug!("{} already received message {}", recipient, key);
+                        outcome.error = None;
+                        return outcome;
+                    }
 
-                // Exponential backoff for retries
-                if attempt > 1 {
-                    let backoff_ms = 100 * (2_u64.pow(attempt - 1));
-                    tokio::time::sleep(std::time::Duration::from_millis(backoff_ms)).await;
-                }
+                    if matches!(status.as_u16(), 429 | 503) {
+                        retry_after = response
+                            .headers()
+                            .get(reqwest::header::RETRY_AFTER)
+                            .and_then(|value| value.to_str().ok())
+                            .and_then(parse_retry_after);
+                    }
+                    let body = response_snippet(&response.text().await.unwrap_or_default());
+                    log::warn!(
+                        "Failed to send message to {} (attempt {}/{}): HTTP {} - {}",
+                        recipient,
+                        attempt,
+                        self.max_retries,
+                        status,
+                        body
+                    );
+                    outcome.error = Some(format!("HTTP error: {} - {}", status, body));
 
-                // Make the HTTP request
-                match self
-                    .client
-                    .post(&endpoint)
-                    .header("Content-Type", "application/didcomm-message+json")
-                    .body(packed_message.clone())
-                    .send()
-                    .await
-                {
-                    Ok(response) => {
-                        // Check if the response was successful (2xx status code)
-                        if response.status().is_success() {
-                            log::debug!("Successfully sent message to {}", recipient);
-                            success = true;
-                        } else {
-                            let status = response.status();
-                            let body = response.text().await.unwrap_or_default();
-                            log::warn!(
-                                "Failed to send message to {} (attempt {}/{}): HTTP {} - {}",
-                                recipient,
-                                attempt,
-                                self.max_retries,
-                                status,
-                                body
-                            );
-                            last_error = Some(format!("HTTP error: {} - {}", status, body));
-
-                            // Don't retry certain status codes
-                            if status.as_u16() == 404 || status.as_u16() == 400 {
-                                break; // Don't retry not found or bad request
-                            }
-                        }
+                    // Don't retry not found or bad request
+                    if status.as_u16() == 404 || status.as_u16() == 400 {
+                        break;
                     }
-                    Err(err) => {
+                    if let Some(delay) = retry_after.filter(|delay| *delay > MAX_RETRY_AFTER) {
                         log::warn!(
-                            "Failed to send message to {} (attempt {}/{}): {}",
+                            "Not retrying delivery to {}: Retry-After of {}s exceeds {}s",
                             recipient,
-                            attempt,
-                            self.max_retries,
-                            err
+                            delay.as_secs(),
+                            MAX_RETRY_AFTER.as_secs()
                         );
-                        last_error = Some(format!("Request error: {}", err));
+                        break;
                     }
                 }
+                Err(err) => {
+                    log::warn!(
+                        "Failed to send message to {} (attempt {}/{}): {}",
+                        recipient,
+                        attempt,
+                        self.max_retries,
+                        err
+                    );
+                    outcome.error = Some(format!("Request error: {}", err));
+                }
             }
 
-            if !success {
-                // Record the failure
-                failures.push((
-                    recipient.clone(),
-                    last_error.unwrap_or_else(|| "Unknown error".to_string()),
-                ));
+            // Exponential backoff unless the recipient asked for a delay
+            if attempt < self.max_retries {
+                let delay = retry_after
+                    .unwrap_or_else(|| std::time::Duration::from_millis(100 * 2_u64.pow(attempt)));
+                tokio::time::sleep(delay).await;
             }
         }
 
-        // Report failures if any
-        if !failures.is_empty() {
-            let failure_messages = failures
-                .iter()
-                .map(|(did, err)| format!("{}: {}", did, err))
-                .collect::<Vec<_>>()
-                .join("; ");
+        outcome
+    }
+}
 
-            return Err(Error::Dispatch(format!(
-                "Failed to send message to some recipients: {}",
-                failure_messages
-            )));
+#[cfg(all(not(target_arch = "wasm32"), feature = "reqwest"))]
+#[async_trait]
+impl PlainMessageSender for HttpPlainMessageSender {
+    async fn send(&self, packed_message: String, recipient_dids: Vec<String>) -> Result<()> {
+        if recipient_dids.is_empty() {
+            return Err(Error::Dispatch("No recipients specified".to_string()));
         }
 
-        Ok(())
+        let mut failures = Vec::new();
+        for recipient in &recipient_dids {
+            let outcome = self.deliver(recipient, &packed_message).await;
+            if let Some(error) = outcome.error {
+                failures.push((recipient.clone(), error));
+            }
+        }
+
+        delivery_failures_result(failures)
+    }
+}
+
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    not(feature = "reqwest"),
+    feature = "delivery-tracking"
+))]
+impl HttpPlainMessageSender {
+    /// Deliver a packed message to one recipient
+    async fn deliver(&self, recipient: &str, packed_message: &str) -> HttpDeliveryOutcome {
+        let result = self
+            .send(packed_message.to_string(), vec![recipient.to_string()])
+            .await;
+        HttpDeliveryOutcome {
+            status_code: None,
+            error: result.err().map(|e| e.to_string()),
+        }
+    }
+
+    /// Deliver a packed message to one recipient at the given endpoint, which
+    /// fails without an HTTP client
+    async fn deliver_to(
+        &self,
+        recipient: &str,
+        endpoint: &str,
+        _packed_message: &str,
+    ) -> HttpDeliveryOutcome {
+        log::warn!(
+            "Cannot deliver message to {} at {}: reqwest not available",
+            recipient,
+            endpoint
+        );
+        HttpDeliveryOutcome {
+            status_code: None,
+            error: Some("HTTP client not available".to_string()),
+        }
     }
 }
 
@@ -829,7 +1014,7 @@ impl PlainMessageSender for HttpPlainMessageSender {
                 // Set headers
                 if let Err(err) = request
                     .headers()
-                    .set("Content-Type", "application/didcomm-message+json")
+                    .set("Content-Type", didcomm_content_type(&packed_message))
                 {
                     let err_msg = format!("Failed to set headers: {:?}", err);
                     log::warn!("{}", err_msg);
@@ -956,7 +1141,7 @@ impl PlainMessageSender for HttpPlainMessageSender {
 /// - Status updates after delivery attempts
 /// - Retry count tracking
 /// - HTTP status code recording
-/// - Error message logging
+/// - Error message logging, including the start of the response body
 ///
 /// # Usage
 ///
@@ -978,6 +1163,7 @@ impl PlainMessageSender for HttpPlainMessageSender {
 /// # Ok(())
 /// # }
 /// ```
+#[cfg(feature = "delivery-tracking")]
 #[derive(Debug)]
 pub struct HttpPlainMessageSenderWithTracking {
     /// The underlying HTTP sender
@@ -986,6 +1172,7 @@ pub struct HttpPlainMessageSenderWithTracking {
     storage: Arc<Storage>,
 }
 
+#[cfg(feature = "delivery-tracking")]
 impl HttpPlainMessageSenderWithTracking {
     /// Create a new HttpPlainMessageSenderWithTracking
     pub fn new(base_url: String, storage: Arc<Storage>) -> Self {
@@ -1009,6 +1196,151 @@ impl HttpPlainMessageSenderWithTracking {
     }
 }
 
+#[cfg(all(not(target_arch = "wasm32"), feature = "delivery-tracking"))]
+impl HttpPlainMessageSenderWithTracking {
+    /// Create a sender for deliveries to resolved service endpoints through
+    /// [`deliver_to_endpoint`](Self::deliver_to_endpoint) and
+    /// [`redeliver`](Self::redeliver)
+    ///
+    /// Each delivery is attempted once, as failed deliveries are retried by
+    /// the node's [`DeliveryRetrier`](crate::delivery::DeliveryRetrier).
+    pub fn for_endpoints(storage: Arc<Storage>) -> Self {
+        Self::with_options(String::new(), 30000, 1, storage)
+    }
+
+    /// Deliver a packed message to a recipient at its resolved service
+    /// endpoint, recording the delivery
+    ///
+    /// Returns the delivery after the attempt, failed if the endpoint could
+    /// not be reached or did not accept the message.
+    pub async fn deliver_to_endpoint(
+        &self,
+        message_id: &str,
+        packed_message: &str,
+        recipient_did: &str,
+        endpoint: &str,
+    ) -> Result<Delivery> {
+        let delivery_id = self
+            .storage
+            .create_delivery(
+                message_id,
+                packed_message,
+                recipient_did,
+                Some(endpoint),
+                DeliveryType::Https,
+            )
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        log::debug!(
+            "Created delivery record {} for message {} to {} at {}",
+            delivery_id,
+            message_id,
+            recipient_did,
+            endpoint
+        );
+
+        let outcome = self
+            .http_sender
+            .deliver_to(recipient_did, endpoint, packed_message)
+            .await;
+        self.record_outcome(delivery_id, &outcome).await;
+        self.get_delivery(delivery_id).await
+    }
+
+    /// Attempt a recorded delivery again at its endpoint
+    ///
+    /// Returns the delivery after the attempt.
+    pub async fn redeliver(&self, delivery: &Delivery) -> Result<Delivery> {
+        let endpoint = delivery
+            .delivery_url
+            .as_deref()
+            .ok_or_else(|| Error::Dispatch(format!("Delivery {} has no endpoint", delivery.id)))?;
+
+        let outcome = self
+            .http_sender
+            .deliver_to(&delivery.recipient_did, endpoint, &delivery.message_text)
+            .await;
+        self.record_outcome(delivery.id, &outcome).await;
+        self.get_delivery(delivery.id).await
+    }
+
+    async fn get_delivery(&self, delivery_id: i64) -> Result<Delivery> {
+        self.storage
+            .get_delivery_by_id(delivery_id)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?
+            .ok_or_else(|| Error::Storage(format!("Delivery {} not found", delivery_id)))
+    }
+}
+
+#[cfg(feature = "delivery-tracking")]
+impl HttpPlainMessageSenderWithTracking {
+    /// Record the outcome of a delivery attempt
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn record_outcome(&self, delivery_id: i64, outcome: &HttpDeliveryOutcome) {
+        let http_status_code = outcome.status_code.map(i32::from);
+
+        let Some(error_msg) = &outcome.error else {
+            if let Err(e) = self
+                .storage
+                .update_delivery_status(
+                    delivery_id,
+                    DeliveryStatus::Success,
+                    http_status_code,
+                    None,
+                )
+                .await
+            {
+                log::error!(
+                    "Failed to update delivery record {} to success: {}",
+                    delivery_id,
+                    e
+                );
+            } else {
+                log::debug!("Updated delivery record {} to success", delivery_id);
+            }
+            return;
+        };
+
+        if let Err(e) = self
+            .storage
+            .update_delivery_status(
+                delivery_id,
+                DeliveryStatus::Failed,
+                http_status_code,
+                Some(error_msg),
+            )
+            .await
+        {
+            log::error!(
+                "Failed to update delivery record {} to failed: {}",
+                delivery_id,
+                e
+            );
+        } else {
+            log::debug!(
+                "Updated delivery record {} to failed with error: {}",
+                delivery_id,
+                error_msg
+            );
+        }
+
+        // Increment retry count for future retry processing
+        if let Err(e) = self
+            .storage
+            .increment_delivery_retry_count(delivery_id)
+            .await
+        {
+            log::error!(
+                "Failed to increment retry count for delivery record {}: {}",
+                delivery_id,
+                e
+            );
+        }
+    }
+}
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "delivery-tracking"))]
 #[async_trait]
 impl PlainMessageSender for HttpPlainMessageSenderWithTracking {
     async fn send(&self, packed_message: String, recipient_dids: Vec<String>) -> Result<()> {
@@ -1016,129 +1348,49 @@ impl PlainMessageSender for HttpPlainMessageSenderWithTracking {
             return Err(Error::Dispatch("No recipients specified".to_string()));
         }
 
-        // Extract message ID from packed message for tracking
-        // This is a simplified approach - in production you might want a more robust way to get the message ID
-        let message_id = format!("msg_{}", uuid::Uuid::new_v4());
+        let message_id = packed_message_id(&packed_message)
+            .unwrap_or_else(|| format!("msg_{}", uuid::Uuid::new_v4()));
 
-        // Create delivery records for each recipient before attempting delivery
-        let mut delivery_ids = Vec::new();
+        let mut failures = Vec::new();
         for recipient in &recipient_dids {
-            let delivery_url = Some(self.http_sender.get_endpoint_url(recipient));
-            match self
+            // Create the delivery record before attempting delivery
+            let delivery_url = self.http_sender.get_endpoint_url(recipient);
+            let delivery_id = match self
                 .storage
                 .create_delivery(
                     &message_id,
                     &packed_message,
                     recipient,
-                    delivery_url.as_deref(),
+                    Some(&delivery_url),
                     DeliveryType::Https,
                 )
                 .await
             {
                 Ok(delivery_id) => {
-                    delivery_ids.push((recipient.clone(), delivery_id));
                     log::debug!(
                         "Created delivery record {} for message {} to {}",
                         delivery_id,
                         message_id,
                         recipient
                     );
+                    Some(delivery_id)
                 }
                 Err(e) => {
-                    log::error!("Failed to create delivery record for {}: {}", recipient, e);
                     // Continue with delivery attempt even if we can't track it
-                    delivery_ids.push((recipient.clone(), -1)); // Use -1 to indicate no tracking
+                    log::error!("Failed to create delivery record for {}: {}", recipient, e);
+                    None
                 }
-            }
-        }
-
-        // Now attempt delivery using the underlying HTTP sender
-        let delivery_result = self
-            .http_sender
-            .send(packed_message, recipient_dids.clone())
-            .await;
+            };
 
-        // Update delivery records based on the result
-        for (_recipient, delivery_id) in delivery_ids {
-            if delivery_id == -1 {
-                continue; // Skip if we couldn't create the delivery record
+            let outcome = self.http_sender.deliver(recipient, &packed_message).await;
+            if let Some(delivery_id) = delivery_id {
+                self.record_outcome(delivery_id, &outcome).await;
             }
-
-            match &delivery_result {
-                Ok(_) => {
-                    // Delivery successful
-                    if let Err(e) = self
-                        .storage
-                        .update_delivery_status(
-                            delivery_id,
-                            DeliveryStatus::Success,
-                            Some(200), // Assume 200 for successful delivery
-                            None,
-                        )
-                        .await
-                    {
-                        log::error!(
-                            "Failed to update delivery record {} to success: {}",
-                            delivery_id,
-                            e
-                        );
-                    } else {
-                        log::debug!("Updated delivery record {} to success", delivery_id);
-                    }
-                }
-                Err(e) => {
-                    // Delivery failed - extract HTTP status code if possible
-                    let error_msg = e.to_string();
-                    let http_status_code = if error_msg.contains("HTTP error: ") {
-                        // Try to extract status code from error message
-                        error_msg
-                            .split("HTTP error: ")
-                            .nth(1)
-                            .and_then(|s| s.split(' ').next())
-                            .and_then(|s| s.parse::<i32>().ok())
-                    } else {
-                        None
-                    };
-
-                    if let Err(e) = self
-                        .storage
-                        .update_delivery_status(
-                            delivery_id,
-                            DeliveryStatus::Failed,
-                            http_status_code,
-                            Some(&error_msg),
-                        )
-                        .await
-                    {
-                        log::error!(
-                            "Failed to update delivery record {} to failed: {}",
-                            delivery_id,
-                            e
-                        );
-                    } else {
-                        log::debug!(
-                            "Updated delivery record {} to failed with error: {}",
-                            delivery_id,
-                            error_msg
-                        );
-                    }
-
-                    // Increment retry count for future retry processing
-                    if let Err(e) = self
-                        .storage
-                        .increment_delivery_retry_count(delivery_id)
-                        .await
-                    {
-                        log::error!(
-                            "Failed to increment retry count for delivery record {}: {}",
-                            delivery_id,
-                            e
-                        );
-                    }
-                }
+            if let Some(error) = outcome.error {
+                failures.push((recipient.clone(), error));
             }
         }
 
-        delivery_result
+        delivery_failures_result(failures)
     }
 }
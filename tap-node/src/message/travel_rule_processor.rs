@@ -2,65 +2,217 @@
 //!
 //! This processor handles the Travel Rule flow as specified in TAIP-10, including:
 //! - Handling UpdatePolicies messages that require IVMS101 presentations
-//! - Processing Presentation messages containing IVMS101 data
+//! - Processing Presentation messages containing IVMS101 data, publishing a
+//!   validation report of the data as a `TravelRuleDataValidated` event
 //! - Generating and attaching IVMS101 presentations to outgoing Transfer messages
+//!
+//! The IVMS101 requirements of the UpdatePolicies messages we send are
+//! recorded per transaction. A presentation received in the transaction is
+//! evaluated against each of them, publishing a `TravelRulePolicyEvaluated`
+//! event; requirements are dropped once satisfied. Requirements received
+//! from counterparties are recorded as well, see
+//! [`TravelRuleProcessor::requested_requirements`].
 
 use async_trait::async_trait;
+use dashmap::DashMap;
 use log::{info, warn};
 use serde_json::{json, Value};
 use std::sync::Arc;
+use tap_ivms101::{Person, Validate, ValidationReport};
 use tap_msg::didcomm::{Attachment, AttachmentData, PlainMessage};
+use tap_msg::message::{Ivms101Requirement, Policy, PolicyEvaluation};
 
 use crate::customer::CustomerManager;
 use crate::error::Result;
+use crate::event::EventBus;
 use crate::message::processor::PlainMessageProcessor;
 
 /// Travel Rule processor that handles TAIP-10 compliant message flows
 #[derive(Clone)]
 pub struct TravelRuleProcessor {
     customer_manager: Arc<CustomerManager>,
+    event_bus: Option<Arc<EventBus>>,
+    /// IVMS101 requirements our policies declared, by transaction
+    declared: Arc<DashMap<String, Vec<Ivms101Requirement>>>,
+    /// IVMS101 requirements counterparties declared, by transaction
+    requested: Arc<DashMap<String, Vec<Ivms101Requirement>>>,
 }
 
 impl TravelRuleProcessor {
     /// Create a new Travel Rule processor
     pub fn new(customer_manager: Arc<CustomerManager>) -> Self {
-        Self { customer_manager }
+        Self {
+            customer_manager,
+            event_bus: None,
+            declared: Arc::new(DashMap::new()),
+            requested: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Publish validation reports of received IVMS101 data on an event bus
+    pub fn with_event_bus(mut self, event_bus: Arc<EventBus>) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// Record the IVMS101 requirements among policies we declared for a
+    /// transaction, against which received presentations are evaluated
+    ///
+    /// The policies of the UpdatePolicies messages passing through
+    /// [`process_outgoing`](PlainMessageProcessor::process_outgoing) are
+    /// declared automatically.
+    pub fn declare_policies(&self, transaction_id: &str, policies: &[Policy]) {
+        let requirements = Ivms101Requirement::from_policies(policies);
+        if !requirements.is_empty() {
+            self.declared
+                .entry(transaction_id.to_string())
+                .or_default()
+                .extend(requirements);
+        }
+    }
+
+    /// The IVMS101 requirements we declared for a transaction that no
+    /// presentation has satisfied yet
+    pub fn declared_requirements(&self, transaction_id: &str) -> Vec<Ivms101Requirement> {
+        self.declared
+            .get(transaction_id)
+            .map(|r| r.clone())
+            .unwrap_or_default()
+    }
+
+    /// The IVMS101 requirements counterparties declared for a transaction
+    ///
+    /// [`Ivms101Requirement::presentation_definition`] gives the definition
+    /// a presentation answering a requirement has to conform to.
+    pub fn requested_requirements(&self, transaction_id: &str) -> Vec<Ivms101Requirement> {
+        self.requested
+            .get(transaction_id)
+            .map(|r| r.clone())
+            .unwrap_or_default()
+    }
+
+    /// The policies and transaction of an UpdatePolicies message
+    fn update_policies(message: &PlainMessage) -> Option<(String, Vec<Policy>)> {
+        let transaction_id = message
+            .body
+            .get("transaction_id")
+            .and_then(|t| t.as_str())
+            .map(String::from)
+            .or_else(|| message.thid.clone())?;
+        let policies = message
+            .body
+            .get("policies")
+            .and_then(|p| p.as_array())?
+            .iter()
+            .filter_map(|policy| serde_json::from_value(policy.clone()).ok())
+            .collect();
+        Some((transaction_id, policies))
     }
 
     /// Process UpdatePolicies message to check for IVMS101 requirements
     async fn handle_update_policies(&self, message: &PlainMessage) -> Result<()> {
-        if let Some(policies) = message.body.get("policies").and_then(|p| p.as_array()) {
-            for policy in policies {
-                if let Some(policy_type) = policy.get("@type").and_then(|t| t.as_str()) {
-                    if policy_type == "RequirePresentation" {
-                        // Check if IVMS101 data is being requested
-                        if let Some(context) = policy.get("@context").and_then(|c| c.as_array()) {
-                            let requires_ivms = context.iter().any(|ctx| {
-                                ctx.as_str()
-                                    .map(|s| s.contains("ivms") || s.contains("intervasp"))
-                                    .unwrap_or(false)
-                            });
-
-                            if requires_ivms {
-                                info!(
-                                    "Received IVMS101 presentation request in message {}",
-                                    message.id
-                                );
-                                // Store this requirement for later response
-                                // In a production system, you'd store this in a proper state manager
-                            }
-                        }
-                    }
-                }
-            }
+        let Some((transaction_id, policies)) = Self::update_policies(message) else {
+            return Ok(());
+        };
+        let requirements = Ivms101Requirement::from_policies(&policies);
+        for requirement in &requirements {
+            info!(
+                "Received IVMS101 presentation request in message {}: {:?} about {}",
+                message.id,
+                requirement
+                    .fields
+                    .iter()
+                    .map(|f| f.as_str())
+                    .collect::<Vec<_>>(),
+                requirement
+                    .about_party
+                    .as_ref()
+                    .map(|p| p.as_str())
+                    .unwrap_or("any party")
+            );
+        }
+        if !requirements.is_empty() {
+            self.requested
+                .entry(transaction_id)
+                .or_default()
+                .extend(requirements);
         }
         Ok(())
     }
 
+    /// Evaluate a received presentation against the requirements declared
+    /// for its transaction, dropping those it satisfies
+    async fn evaluate_declared_policies(&self, message: &PlainMessage, presentations: &[&Value]) {
+        let Some(transaction_id) = message
+            .body
+            .get("transaction_id")
+            .and_then(|t| t.as_str())
+            .map(String::from)
+            .or_else(|| message.thid.clone())
+            .or_else(|| message.pthid.clone())
+        else {
+            return;
+        };
+        let Some(requirements) = self.declared.get(&transaction_id).map(|r| r.clone()) else {
+            return;
+        };
+
+        let mut unsatisfied = Vec::new();
+        for requirement in requirements {
+            let evaluation = presentations
+                .iter()
+                .map(|presentation| requirement.evaluate(presentation))
+                .min_by_key(|evaluation| evaluation.missing.len())
+                .unwrap_or_else(|| PolicyEvaluation {
+                    about_party: requirement.about_party.clone(),
+                    satisfied: false,
+                    missing: requirement.fields.clone(),
+                });
+
+            if !evaluation.satisfied {
+                warn!(
+                    "Presentation {} does not satisfy the IVMS101 policy of transaction {}, missing {:?}",
+                    message.id,
+                    transaction_id,
+                    evaluation.missing.iter().map(|f| f.as_str()).collect::<Vec<_>>()
+                );
+                unsatisfied.push(requirement);
+            }
+            if let Some(event_bus) = &self.event_bus {
+                event_bus
+                    .publish_travel_rule_policy_evaluated(
+                        message.id.clone(),
+                        transaction_id.clone(),
+                        message.from.clone(),
+                        evaluation,
+                    )
+                    .await;
+            }
+        }
+
+        if unsatisfied.is_empty() {
+            self.declared.remove(&transaction_id);
+        } else {
+            self.declared.insert(transaction_id, unsatisfied);
+        }
+    }
+
     /// Process Presentation message containing IVMS101 data
     async fn handle_presentation(&self, message: &PlainMessage) -> Result<()> {
+        // DIDComm present-proof messages carry their attachments in the body
+        let body_attachments: Vec<Attachment> = message
+            .body
+            .get("attachments")
+            .and_then(|a| serde_json::from_value(a.clone()).ok())
+            .unwrap_or_default();
+
         // Look for IVMS101 data in attachments
-        if let Some(attachments) = message.attachments.as_ref() {
+        {
+            let attachments = message
+                .attachments
+                .iter()
+                .flatten()
+                .chain(&body_attachments);
             for attachment in attachments {
                 if let Some(media_type) = &attachment.media_type {
                     if media_type == "application/json" {
@@ -76,6 +228,13 @@ impl TravelRuleProcessor {
 
                                     // Extract IVMS101 data from the credential
                                     if let Some(ivms_data) = self.extract_ivms101_data(json_data) {
+                                        self.report_ivms101_data(
+                                            message,
+                                            self.ivms101_role(json_data),
+                                            &ivms_data,
+                                        )
+                                        .await;
+
                                         // Update customer records with received IVMS101 data
                                         let from_did = &message.from;
                                         if let Ok(customer_id) =
@@ -106,6 +265,29 @@ impl TravelRuleProcessor {
                 }
             }
         }
+
+        // Presentations in JSON attachments, or in the credentials of a
+        // TAP Presentation body
+        let presentations: Vec<&Value> = message
+            .attachments
+            .iter()
+            .flatten()
+            .chain(&body_attachments)
+            .filter_map(|attachment| match &attachment.data {
+                AttachmentData::Json { value } => Some(&value.json),
+                _ => None,
+            })
+            .chain(
+                message
+                    .body
+                    .get("credentials")
+                    .and_then(|c| c.as_array())
+                    .into_iter()
+                    .flatten(),
+            )
+            .collect();
+        self.evaluate_declared_policies(message, &presentations)
+            .await;
         Ok(())
     }
 
@@ -170,6 +352,57 @@ impl TravelRuleProcessor {
         None
     }
 
+    /// Role of the party described by the IVMS101 data in a credential
+    fn ivms101_role(&self, credential_data: &Value) -> Option<String> {
+        credential_data
+            .get("verifiableCredential")
+            .and_then(|vc| vc.as_array())?
+            .iter()
+            .filter_map(|cred| cred.get("credentialSubject"))
+            .find_map(|subject| {
+                ["originator", "beneficiary"]
+                    .into_iter()
+                    .find(|role| subject.get(*role).is_some())
+            })
+            .map(String::from)
+    }
+
+    /// Validate received IVMS101 person data and publish the report
+    async fn report_ivms101_data(
+        &self,
+        message: &PlainMessage,
+        role: Option<String>,
+        data: &Value,
+    ) {
+        let report = match serde_json::from_value::<Person>(data.clone()) {
+            Ok(person) => person.validate_all(),
+            Err(e) => {
+                let mut report = ValidationReport::new();
+                report.error("", tap_ivms101::Error::Serialization(e));
+                report
+            }
+        };
+
+        if !report.is_valid() {
+            warn!(
+                "IVMS101 data in message {} has {} error(s)",
+                message.id,
+                report.errors().count()
+            );
+        }
+
+        if let Some(event_bus) = &self.event_bus {
+            event_bus
+                .publish_travel_rule_data_validated(
+                    message.id.clone(),
+                    message.from.clone(),
+                    role,
+                    report,
+                )
+                .await;
+        }
+    }
+
     /// Find customer by DID
     async fn find_customer_by_did(&self, did: &str) -> Result<String> {
         // In a production system, this would query the database
@@ -249,6 +482,13 @@ impl PlainMessageProcessor for TravelRuleProcessor {
     }
 
     async fn process_outgoing(&self, mut message: PlainMessage) -> Result<Option<PlainMessage>> {
+        // Record the IVMS101 requirements we declare
+        if message.type_.contains("UpdatePolicies") {
+            if let Some((transaction_id, policies)) = Self::update_policies(&message) {
+                self.declare_policies(&transaction_id, &policies);
+            }
+        }
+
         // Check if this is a Transfer message that needs IVMS101 data
         if message.type_.contains("Transfer") && self.should_attach_ivms101(&message).await {
             // Extract originator information from the message body
@@ -379,4 +619,145 @@ mod tests {
         let data = extracted.unwrap();
         assert!(data.get("naturalPerson").is_some());
     }
+
+    #[tokio::test]
+    async fn test_presentation_validation_event() {
+        let storage = Arc::new(Storage::new_in_memory().await.unwrap());
+        let customer_manager = Arc::new(CustomerManager::new(storage));
+        let event_bus = Arc::new(EventBus::new());
+        let mut events = event_bus.subscribe_channel();
+        let processor = TravelRuleProcessor::new(customer_manager).with_event_bus(event_bus);
+
+        let presentation = json!({
+            "@context": ["https://intervasp.org/ivms101"],
+            "verifiableCredential": [{
+                "credentialSubject": {
+                    "beneficiary": {
+                        "naturalPerson": {
+                            "name": {
+                                "nameIdentifiers": [{
+                                    "primaryIdentifier": "",
+                                    "secondaryIdentifier": "Bob",
+                                    "nameIdentifierType": "LEGAL_NAME"
+                                }]
+                            },
+                            "countryOfResidence": "USA"
+                        }
+                    }
+                }
+            }]
+        });
+        let message = PlainMessage::new(
+            "presentation-1".to_string(),
+            "https://didcomm.org/present-proof/3.0/presentation".to_string(),
+            json!({}),
+            "did:example:beneficiary-vasp".to_string(),
+        )
+        .with_attachments(vec![Attachment::json(presentation)
+            .media_type("application/json".to_string())
+            .finalize()]);
+
+        processor.process_incoming(message).await.unwrap();
+
+        match events.recv().await.unwrap().as_ref() {
+            crate::event::NodeEvent::TravelRuleDataValidated {
+                message_id,
+                role,
+                report,
+                ..
+            } => {
+                assert_eq!(message_id, "presentation-1");
+                assert_eq!(role.as_deref(), Some("beneficiary"));
+                let paths: Vec<&str> = report.errors().map(|v| v.path.as_str()).collect();
+                assert_eq!(
+                    paths,
+                    vec![
+                        "/naturalPerson/name/nameIdentifiers/0/primaryIdentifier",
+                        "/naturalPerson/countryOfResidence",
+                    ]
+                );
+            }
+            other => panic!("Unexpected event {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_declared_policy_evaluation() {
+        use tap_msg::message::{Ivms101Field, PartyType, RequirePresentation};
+
+        let storage = Arc::new(Storage::new_in_memory().await.unwrap());
+        let customer_manager = Arc::new(CustomerManager::new(storage));
+        let event_bus = Arc::new(EventBus::new());
+        let mut events = event_bus.subscribe_channel();
+        let processor = TravelRuleProcessor::new(customer_manager).with_event_bus(event_bus);
+
+        let policy = Policy::RequirePresentation(RequirePresentation::ivms101(
+            PartyType::Originator,
+            vec![Ivms101Field::Name, Ivms101Field::GeographicAddress],
+        ));
+        let update = PlainMessage::new(
+            "policies-1".to_string(),
+            "https://tap.rsvp/schema/1.0#UpdatePolicies".to_string(),
+            json!({"transaction_id": "tx-1", "policies": [policy]}),
+            "did:example:beneficiary-vasp".to_string(),
+        );
+        processor.process_outgoing(update.clone()).await.unwrap();
+        assert_eq!(processor.declared_requirements("tx-1").len(), 1);
+
+        let presentation = |id: &str, originator: Value| {
+            PlainMessage::new(
+                id.to_string(),
+                "https://didcomm.org/present-proof/3.0/presentation".to_string(),
+                json!({}),
+                "did:example:originator-vasp".to_string(),
+            )
+            .with_thread_id(Some("tx-1".to_string()))
+            .with_attachments(vec![Attachment::json(json!({
+                "verifiableCredential": [{"credentialSubject": {"originator": originator}}]
+            }))
+            .media_type("application/json".to_string())
+            .finalize()])
+        };
+        let mut next_evaluation = async || loop {
+            if let crate::event::NodeEvent::TravelRulePolicyEvaluated {
+                transaction_id,
+                evaluation,
+                ..
+            } = events.recv().await.unwrap().as_ref()
+            {
+                assert_eq!(transaction_id, "tx-1");
+                return evaluation.clone();
+            }
+        };
+
+        let name = json!({"nameIdentifiers": [{"primaryIdentifier": "Smith"}]});
+        processor
+            .process_incoming(presentation(
+                "presentation-1",
+                json!({"naturalPerson": {"name": name}}),
+            ))
+            .await
+            .unwrap();
+        let evaluation = next_evaluation().await;
+        assert!(!evaluation.satisfied);
+        assert_eq!(evaluation.missing, vec![Ivms101Field::GeographicAddress]);
+        assert_eq!(processor.declared_requirements("tx-1").len(), 1);
+
+        processor
+            .process_incoming(presentation(
+                "presentation-2",
+                json!({"naturalPerson": {"name": name, "geographicAddress": [{"country": "US"}]}}),
+            ))
+            .await
+            .unwrap();
+        assert!(next_evaluation().await.satisfied);
+        assert!(processor.declared_requirements("tx-1").is_empty());
+
+        // Policies received from counterparties are recorded separately
+        processor.process_incoming(update).await.unwrap();
+        let requested = processor.requested_requirements("tx-1");
+        assert_eq!(requested.len(), 1);
+        assert_eq!(requested[0].about_party, Some(PartyType::Originator));
+        assert!(processor.declared_requirements("tx-1").is_empty());
+    }
 }
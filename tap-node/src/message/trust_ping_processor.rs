@@ -68,7 +68,7 @@ impl TrustPingProcessor {
 
         // Create the response PlainMessage
         let response_message = PlainMessage {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: tap_msg::id::new_id(),
             typ: "application/didcomm-plain+json".to_string(),
             type_: TrustPingResponse::message_type().to_string(),
             body: serde_json::to_value(&response).map_err(|e| {
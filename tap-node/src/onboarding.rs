@@ -0,0 +1,444 @@
+//! Onboarding handshake with a new counterparty
+//!
+//! Before transacting with a counterparty, an agent connects to it and learns
+//! what it supports and requires. [`TapNode::onboard_counterparty`] runs the
+//! whole handshake:
+//!
+//! 1. a Connect request (TAIP-15), recorded as a connection
+//! 2. a Discover Features query, answered with the features it discloses
+//! 3. our policies, sent as UpdatePolicies (TAIP-7) referencing the Connect
+//! 4. a trust ping, whose response verifies the counterparty's endpoint
+//!
+//! The counterparty's answer to the Connect (Authorize, Reject or
+//! AuthorizationRequired) and its own policies are collected as they arrive.
+//! A reply that does not arrive in time is recorded as missing rather than
+//! failing the handshake, and the result is stored in our agent's storage as
+//! a [`CounterpartyProfile`].
+//!
+//! A node answers the Discover Features queries and trust pings of other
+//! nodes' handshakes when [`NodeConfig::answer_handshakes`](crate::NodeConfig::answer_handshakes)
+//! is enabled. Whether to authorize a Connect stays with the counterparty's
+//! agent.
+
+use crate::error::{Error, Result};
+use crate::storage::{ConnectionStatus, CounterpartyProfile};
+use crate::TapNode;
+use dashmap::DashMap;
+use std::time::Duration;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::discover_features::DISCOVER_FEATURES_DISCLOSE_TYPE;
+use tap_msg::message::tap_message_trait::TapMessageBody;
+use tap_msg::message::trust_ping::TRUST_PING_RESPONSE_TYPE;
+use tap_msg::message::{
+    Connect, Disclosure, DiscoverFeaturesQueries, FeatureQuery, Policy, TapMessage, TrustPing,
+    TrustPingResponse, UpdatePolicies,
+};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+/// Default time to wait for each reply of the counterparty
+pub const DEFAULT_STEP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Protocols a node discloses when it answers Discover Features queries
+pub const SUPPORTED_PROTOCOLS: &[&str] = &[
+    "https://tap.rsvp/schema/1.0",
+    "https://didcomm.org/trust-ping/2.0",
+    "https://didcomm.org/discover-features/2.0",
+    "https://didcomm.org/report-problem/2.0",
+    "https://didcomm.org/basicmessage/2.0",
+];
+
+/// An onboarding handshake to run with a counterparty
+#[derive(Debug, Clone)]
+pub struct OnboardingRequest {
+    /// Our agent that connects
+    pub agent_did: String,
+    /// Agent of the counterparty
+    pub counterparty_did: String,
+    /// The Connect request to send
+    pub connect: Connect,
+    /// Policies to send the counterparty (none are sent if empty)
+    pub policies: Vec<Policy>,
+    /// Features to ask the counterparty about
+    pub feature_queries: Vec<FeatureQuery>,
+    /// How long to wait for each reply
+    pub step_timeout: Duration,
+}
+
+impl OnboardingRequest {
+    /// Create a handshake that asks about TAP support and sends no policies
+    pub fn new(
+        agent_did: impl Into<String>,
+        counterparty_did: impl Into<String>,
+        connect: Connect,
+    ) -> Self {
+        Self {
+            agent_did: agent_did.into(),
+            counterparty_did: counterparty_did.into(),
+            connect,
+            policies: Vec::new(),
+            feature_queries: vec![FeatureQuery::protocol("https://tap.rsvp/schema/*")],
+            step_timeout: DEFAULT_STEP_TIMEOUT,
+        }
+    }
+
+    /// Send these policies to the counterparty
+    pub fn with_policies(mut self, policies: Vec<Policy>) -> Self {
+        self.policies = policies;
+        self
+    }
+
+    /// Ask the counterparty about these features instead
+    pub fn with_feature_queries(mut self, queries: Vec<FeatureQuery>) -> Self {
+        self.feature_queries = queries;
+        self
+    }
+
+    /// Set how long to wait for each reply
+    pub fn with_step_timeout(mut self, step_timeout: Duration) -> Self {
+        self.step_timeout = step_timeout;
+        self
+    }
+}
+
+/// Handshakes waiting for replies, by thread
+#[derive(Debug, Default)]
+pub(crate) struct ReplyListeners {
+    listeners: DashMap<String, (String, mpsc::UnboundedSender<PlainMessage>)>,
+}
+
+impl ReplyListeners {
+    /// Pass a received message to the handshake waiting for replies in its
+    /// thread from its sender
+    pub fn deliver(&self, message: &PlainMessage) {
+        let Some(thid) = &message.thid else {
+            return;
+        };
+        if let Some(listener) = self.listeners.get(thid) {
+            let (counterparty_did, sender) = listener.value();
+            if *counterparty_did == message.from {
+                let _ = sender.send(message.clone());
+            }
+        }
+    }
+}
+
+/// The replies of one handshake, received through its listeners
+struct Replies<'a> {
+    listeners: &'a ReplyListeners,
+    counterparty_did: String,
+    threads: Vec<String>,
+    sender: mpsc::UnboundedSender<PlainMessage>,
+    receiver: mpsc::UnboundedReceiver<PlainMessage>,
+    received: Vec<PlainMessage>,
+}
+
+impl<'a> Replies<'a> {
+    fn new(listeners: &'a ReplyListeners, counterparty_did: &str) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self {
+            listeners,
+            counterparty_did: counterparty_did.to_string(),
+            threads: Vec::new(),
+            sender,
+            receiver,
+            received: Vec::new(),
+        }
+    }
+
+    /// Receive the counterparty's replies in a thread
+    fn listen(&mut self, thid: &str) {
+        self.listeners.listeners.insert(
+            thid.to_string(),
+            (self.counterparty_did.clone(), self.sender.clone()),
+        );
+        self.threads.push(thid.to_string());
+    }
+
+    /// Take the first reply matching `predicate`, waiting until `deadline`
+    async fn wait_for<F>(&mut self, deadline: Instant, predicate: F) -> Option<PlainMessage>
+    where
+        F: Fn(&PlainMessage) -> bool,
+    {
+        loop {
+            if let Some(index) = self.received.iter().position(&predicate) {
+                return Some(self.received.remove(index));
+            }
+            match tokio::time::timeout_at(deadline, self.receiver.recv()).await {
+                Ok(Some(message)) => self.received.push(message),
+                _ => return None,
+            }
+        }
+    }
+
+    /// Take the replies received so far that match `predicate`
+    fn drain<F>(&mut self, predicate: F) -> Vec<PlainMessage>
+    where
+        F: Fn(&PlainMessage) -> bool,
+    {
+        while let Ok(message) = self.receiver.try_recv() {
+            self.received.push(message);
+        }
+        let (matching, rest) = std::mem::take(&mut self.received)
+            .into_iter()
+            .partition(|message| predicate(message));
+        self.received = rest;
+        matching
+    }
+}
+
+impl Drop for Replies<'_> {
+    fn drop(&mut self) {
+        for thid in &self.threads {
+            self.listeners.listeners.remove(thid);
+        }
+    }
+}
+
+/// The counterparty's answer to a Connect request
+#[derive(Debug, Clone, PartialEq)]
+enum ConnectAnswer {
+    Authorized,
+    AuthorizationRequired { url: String },
+    Rejected { reason: Option<String> },
+}
+
+impl ConnectAnswer {
+    fn from_reply(message: &PlainMessage) -> Option<Self> {
+        match TapMessage::from_plain_message(message).ok()? {
+            TapMessage::Authorize(_) => Some(ConnectAnswer::Authorized),
+            TapMessage::Reject(reject) => Some(ConnectAnswer::Rejected {
+                reason: reject.reason,
+            }),
+            TapMessage::AuthorizationRequired(required) => {
+                Some(ConnectAnswer::AuthorizationRequired {
+                    url: required.authorization_url,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether no further answer is expected
+    fn is_final(&self) -> bool {
+        !matches!(self, ConnectAnswer::AuthorizationRequired { .. })
+    }
+}
+
+impl TapNode {
+    /// Run an onboarding handshake with a counterparty and store its profile
+    ///
+    /// See the [module documentation](crate::onboarding) for the steps. Fails
+    /// if our agent is not registered, storage is not initialized or a
+    /// message of the handshake cannot be sent; replies that do not arrive
+    /// within the request's step timeout are left out of the profile.
+    pub async fn onboard_counterparty(
+        &self,
+        request: OnboardingRequest,
+    ) -> Result<CounterpartyProfile> {
+        if !self.agents.has_agent(&request.agent_did) {
+            return Err(Error::AgentNotFound(request.agent_did));
+        }
+        let storage = self
+            .storage()
+            .ok_or_else(|| Error::Storage("Storage is not initialized".to_string()))?;
+        let agent_did = request.agent_did.as_str();
+        let counterparty_did = request.counterparty_did.as_str();
+        let mut replies = Replies::new(&self.reply_listeners, counterparty_did);
+
+        // Connect, recorded so that its answer is accepted and tracked
+        let connect = request
+            .connect
+            .to_didcomm_with_route(agent_did, [counterparty_did])
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+        let connection_id = connect.id.clone();
+        storage
+            .insert_connection(&connect)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        replies.listen(&connection_id);
+        self.send_message(agent_did.to_string(), connect).await?;
+
+        // Features
+        let queries = DiscoverFeaturesQueries::new(request.feature_queries.clone())
+            .to_didcomm_with_route(agent_did, [counterparty_did])
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+        let queries_id = queries.id.clone();
+        replies.listen(&queries_id);
+        self.send_message(agent_did.to_string(), queries).await?;
+        let features = replies
+            .wait_for(Instant::now() + request.step_timeout, |reply| {
+                reply.type_ == DISCOVER_FEATURES_DISCLOSE_TYPE
+                    && reply.thid.as_deref() == Some(queries_id.as_str())
+            })
+            .await
+            .and_then(|reply| {
+                match tap_msg::message::DiscoverFeaturesDisclose::from_didcomm(&reply) {
+                    Ok(disclose) => Some(disclose.disclosures),
+                    Err(e) => {
+                        log::warn!("Ignoring invalid disclosure from {}: {}", reply.from, e);
+                        None
+                    }
+                }
+            });
+
+        // Policies
+        if !request.policies.is_empty() {
+            let update = UpdatePolicies::new(&connection_id, request.policies.clone())
+                .to_didcomm_with_route(agent_did, [counterparty_did])
+                .map_err(|e| Error::Serialization(e.to_string()))?;
+            self.send_message(agent_did.to_string(), update).await?;
+        }
+
+        // Endpoint verification
+        let ping = TrustPing::new()
+            .to_didcomm_with_route(agent_did, [counterparty_did])
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+        let ping_id = ping.id.clone();
+        replies.listen(&ping_id);
+        let ping_sent = Instant::now();
+        self.send_message(agent_did.to_string(), ping).await?;
+        let ping_rtt_ms = replies
+            .wait_for(ping_sent + request.step_timeout, |reply| {
+                reply.type_ == TRUST_PING_RESPONSE_TYPE
+                    && reply.thid.as_deref() == Some(ping_id.as_str())
+            })
+            .await
+            .map(|_| ping_sent.elapsed().as_millis() as u64);
+
+        // Answer to the Connect, which may come after an AuthorizationRequired
+        let is_answer = |reply: &PlainMessage| {
+            reply.thid.as_deref() == Some(connection_id.as_str())
+                && ConnectAnswer::from_reply(reply).is_some()
+        };
+        let deadline = Instant::now() + request.step_timeout;
+        let mut answer = None;
+        while let Some(reply) = replies.wait_for(deadline, is_answer).await {
+            answer = ConnectAnswer::from_reply(&reply);
+            if answer.as_ref().is_some_and(ConnectAnswer::is_final) {
+                break;
+            }
+        }
+
+        let their_policies = replies
+            .drain(|reply| reply.thid.as_deref() == Some(connection_id.as_str()))
+            .iter()
+            .filter_map(|reply| UpdatePolicies::from_didcomm(reply).ok())
+            .flat_map(|update| update.policies)
+            .collect();
+
+        let now = self.clock().now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+        let (connection_status, authorization_url, rejection_reason) = match answer {
+            Some(ConnectAnswer::Authorized) => (ConnectionStatus::Authorized, None, None),
+            Some(ConnectAnswer::AuthorizationRequired { url }) => {
+                (ConnectionStatus::AuthorizationRequired, Some(url), None)
+            }
+            Some(ConnectAnswer::Rejected { reason }) => (ConnectionStatus::Rejected, None, reason),
+            None => (ConnectionStatus::Pending, None, None),
+        };
+        let profile = CounterpartyProfile {
+            counterparty_did: request.counterparty_did.clone(),
+            connection_id,
+            connection_status,
+            authorization_url,
+            rejection_reason,
+            features,
+            our_policies: request.policies,
+            their_policies,
+            endpoint_verified: ping_rtt_ms.is_some(),
+            ping_rtt_ms,
+            created_at: now.clone(),
+            updated_at: now,
+        };
+
+        let agent_storage = match &self.agent_storage_manager {
+            Some(manager) => manager.get_agent_storage(agent_did).await?,
+            None => storage.clone(),
+        };
+        agent_storage
+            .upsert_counterparty_profile(&profile)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        log::info!(
+            "Onboarded counterparty {} for agent {}: connection {}, endpoint {}",
+            profile.counterparty_did,
+            agent_did,
+            profile.connection_status,
+            if profile.endpoint_verified {
+                "verified"
+            } else {
+                "unverified"
+            }
+        );
+
+        // Keep the original creation time of a profile that is re-onboarded
+        Ok(agent_storage
+            .get_counterparty_profile(&profile.counterparty_did)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?
+            .unwrap_or(profile))
+    }
+
+    /// Get the stored profile of a counterparty onboarded by one of our agents
+    pub async fn get_counterparty_profile(
+        &self,
+        agent_did: &str,
+        counterparty_did: &str,
+    ) -> Result<Option<CounterpartyProfile>> {
+        let manager = self
+            .agent_storage_manager
+            .as_ref()
+            .ok_or_else(|| Error::Storage("Storage is not initialized".to_string()))?;
+        manager
+            .get_agent_storage(agent_did)
+            .await?
+            .get_counterparty_profile(counterparty_did)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    /// Answer the Discover Features queries and trust pings of other nodes'
+    /// onboarding handshakes addressed to our agents
+    pub(crate) async fn answer_handshake(&self, message: &PlainMessage) {
+        if message.from.is_empty() {
+            return;
+        }
+        let Some(from) = message.to.iter().find(|did| self.agents.has_agent(did)) else {
+            return;
+        };
+        let reply = match TapMessage::from_plain_message(message) {
+            Ok(TapMessage::DiscoverFeaturesQueries(queries)) => {
+                let supported: Vec<Disclosure> = SUPPORTED_PROTOCOLS
+                    .iter()
+                    .map(|protocol| Disclosure::protocol(*protocol))
+                    .collect();
+                queries
+                    .disclose(&supported)
+                    .to_didcomm_with_route(from, [message.from.as_str()])
+            }
+            Ok(TapMessage::TrustPing(ping)) if ping.response_requested => {
+                TrustPingResponse::new(message.id.clone())
+                    .to_didcomm_with_route(from, [message.from.as_str()])
+            }
+            _ => return,
+        };
+        let mut reply = match reply {
+            Ok(reply) => reply,
+            Err(e) => {
+                log::warn!("Failed to answer message {}: {}", message.id, e);
+                return;
+            }
+        };
+        reply.thid = Some(message.id.clone());
+
+        // Local recipients receive the reply through this node again
+        if let Err(e) = Box::pin(self.send_message(from.clone(), reply)).await {
+            log::warn!(
+                "Failed to answer {} from {}: {}",
+                message.type_,
+                message.from,
+                e
+            );
+        }
+    }
+}
@@ -0,0 +1,746 @@
+//! Actions awaiting a person's approval
+//!
+//! Several parts of the node hold a transaction or message until a person
+//! decides on it: policy rules queue transactions for manual review, poll
+//! mode logs authorization decisions, counterparties ask our agents to
+//! confirm their relationship to a party, and messages whose processing
+//! timed out are kept in the dead letters. [`TapNode::list_pending_actions`]
+//! gathers all of them from the node's storage, across agents, so that a
+//! wallet or operator console has a single list to show.
+//!
+//! Each action has an ID such as `manual_review:12`, made of its kind and the
+//! ID of the row it comes from. [`TapNode::approve_pending_action`] and
+//! [`TapNode::reject_pending_action`] send the TAP response the action calls
+//! for and record the outcome where the action came from:
+//!
+//! | Kind | Approve | Reject |
+//! |------|---------|--------|
+//! | `manual_review` | Authorize | Reject (`policy`) |
+//! | `authorization` | Authorize | Reject (`customer_declined`) |
+//! | `relationship_confirmation` | ConfirmRelationship | Reject (`customer_declined`) |
+//! | `quarantined_message` | Replay the message | Discard the message |
+
+use crate::error::{Error, Result};
+use crate::storage::{DecisionLogEntry, DecisionStatus, DecisionType, ReviewStatus, Storage};
+use crate::timeouts::ProcessingStage;
+use crate::{IngestOutcome, TapNode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::tap_message_trait::TapMessageBody;
+use tap_msg::message::{Authorize, ConfirmRelationship, Reject, RejectionCode};
+
+/// Default number of actions returned by [`TapNode::list_pending_actions`]
+pub const DEFAULT_PENDING_ACTIONS_LIMIT: u32 = 100;
+
+/// Maximum number of actions returned by [`TapNode::list_pending_actions`],
+/// and of each kind considered
+pub const MAX_PENDING_ACTIONS_LIMIT: u32 = 1000;
+
+/// What an action awaits approval of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PendingActionKind {
+    /// A transaction a policy rule held in the review queue
+    ManualReview,
+    /// A transaction awaiting our agent's authorization
+    Authorization,
+    /// A counterparty's request that our agent confirm the party it acts for
+    RelationshipConfirmation,
+    /// A message kept in the dead letters after its processing timed out
+    QuarantinedMessage,
+}
+
+impl PendingActionKind {
+    /// All kinds of actions
+    pub const ALL: [PendingActionKind; 4] = [
+        PendingActionKind::ManualReview,
+        PendingActionKind::Authorization,
+        PendingActionKind::RelationshipConfirmation,
+        PendingActionKind::QuarantinedMessage,
+    ];
+
+    /// Snake-case name of the kind, e.g. `manual_review`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PendingActionKind::ManualReview => "manual_review",
+            PendingActionKind::Authorization => "authorization",
+            PendingActionKind::RelationshipConfirmation => "relationship_confirmation",
+            PendingActionKind::QuarantinedMessage => "quarantined_message",
+        }
+    }
+}
+
+impl fmt::Display for PendingActionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PendingActionKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        PendingActionKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| format!("Unknown pending action kind: {}", s))
+    }
+}
+
+/// Something awaiting a person's approval
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingAction {
+    /// ID to approve or reject the action with, e.g. `manual_review:12`
+    pub id: String,
+    pub kind: PendingActionKind,
+    /// Our agent the action is taken on behalf of, if known
+    pub agent_did: Option<String>,
+    /// Transaction the action is about, if any
+    pub transaction_id: Option<String>,
+    /// Human-readable description of what awaits approval
+    pub summary: String,
+    /// Kind-specific details, e.g. the policy outcomes of a manual review
+    pub details: Value,
+    pub created_at: String,
+}
+
+/// Which actions [`TapNode::list_pending_actions`] lists
+#[derive(Debug, Clone, Default)]
+pub struct PendingActionFilter {
+    /// Only actions taken on behalf of this agent
+    pub agent_did: Option<String>,
+    /// Only actions of these kinds, or of every kind if empty
+    pub kinds: Vec<PendingActionKind>,
+}
+
+impl PendingActionFilter {
+    fn includes(&self, kind: PendingActionKind) -> bool {
+        self.kinds.is_empty() || self.kinds.contains(&kind)
+    }
+}
+
+/// The person approving or rejecting an action, and what they add to it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PendingActionResponse {
+    /// Identity of the person, recorded with the outcome
+    pub actor: String,
+    /// Note recorded with the outcome, and the reason of a Reject
+    pub note: Option<String>,
+    /// Settlement address of an Authorize
+    pub settlement_address: Option<String>,
+}
+
+impl PendingActionResponse {
+    /// A response by `actor`
+    pub fn new(actor: impl Into<String>) -> Self {
+        Self {
+            actor: actor.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Record a note with the outcome
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+
+    /// Authorize with a settlement address
+    pub fn with_settlement_address(mut self, address: impl Into<String>) -> Self {
+        self.settlement_address = Some(address.into());
+        self
+    }
+}
+
+/// What approving or rejecting an action did
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingActionOutcome {
+    pub id: String,
+    pub kind: PendingActionKind,
+    pub transaction_id: Option<String>,
+    /// ID of the message sent or replayed, none for a discarded message
+    pub message_id: Option<String>,
+}
+
+/// The kind and row ID of an action ID
+fn parse_action_id(id: &str) -> Result<(PendingActionKind, i64)> {
+    id.split_once(':')
+        .and_then(|(kind, row)| Some((kind.parse().ok()?, row.parse().ok()?)))
+        .ok_or_else(|| Error::Validation(format!("Invalid pending action ID: {}", id)))
+}
+
+fn action_id(kind: PendingActionKind, row: i64) -> String {
+    format!("{}:{}", kind, row)
+}
+
+/// Whether a decision still awaits a response
+fn is_open(decision: &DecisionLogEntry) -> bool {
+    matches!(
+        decision.status,
+        DecisionStatus::Pending | DecisionStatus::Delivered
+    )
+}
+
+/// The RequireRelationshipConfirmation policy of an UpdatePolicies message
+fn relationship_policy(message: &Value) -> Option<&Value> {
+    message["body"]["policies"]
+        .as_array()?
+        .iter()
+        .find(|policy| policy["@type"] == "RequireRelationshipConfirmation")
+}
+
+impl TapNode {
+    /// List the actions awaiting a person's approval, oldest first
+    ///
+    /// At most `limit` actions are returned,
+    /// [`DEFAULT_PENDING_ACTIONS_LIMIT`] if unset and never more than
+    /// [`MAX_PENDING_ACTIONS_LIMIT`].
+    pub async fn list_pending_actions(
+        &self,
+        filter: &PendingActionFilter,
+        limit: Option<u32>,
+    ) -> Result<Vec<PendingAction>> {
+        let storage = self.pending_actions_storage()?;
+        let agent_did = filter.agent_did.as_deref();
+        let mut actions = Vec::new();
+
+        let reviews = storage
+            .list_review_items(
+                agent_did,
+                Some(ReviewStatus::Pending),
+                MAX_PENDING_ACTIONS_LIMIT,
+                0,
+            )
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        let reviewed: HashSet<(String, String)> = reviews
+            .iter()
+            .map(|item| (item.transaction_id.clone(), item.agent_did.clone()))
+            .collect();
+        if filter.includes(PendingActionKind::ManualReview) {
+            actions.extend(reviews.into_iter().map(|item| PendingAction {
+                id: action_id(PendingActionKind::ManualReview, item.id),
+                kind: PendingActionKind::ManualReview,
+                agent_did: Some(item.agent_did),
+                transaction_id: Some(item.transaction_id),
+                summary: item.reason.clone(),
+                details: json!({
+                    "rule": item.rule,
+                    "reason": item.reason,
+                    "details": item.details,
+                }),
+                created_at: item.created_at,
+            }));
+        }
+
+        if filter.includes(PendingActionKind::Authorization)
+            || filter.includes(PendingActionKind::RelationshipConfirmation)
+        {
+            for status in [DecisionStatus::Pending, DecisionStatus::Delivered] {
+                let decisions = storage
+                    .list_decisions(agent_did, Some(status), None, MAX_PENDING_ACTIONS_LIMIT)
+                    .await
+                    .map_err(|e| Error::Storage(e.to_string()))?;
+                for decision in decisions {
+                    match decision.decision_type {
+                        // A transaction held for review is authorized by its reviewer
+                        DecisionType::AuthorizationRequired
+                            if filter.includes(PendingActionKind::Authorization)
+                                && !reviewed.contains(&(
+                                    decision.transaction_id.clone(),
+                                    decision.agent_did.clone(),
+                                )) =>
+                        {
+                            actions.push(PendingAction {
+                                id: action_id(PendingActionKind::Authorization, decision.id),
+                                kind: PendingActionKind::Authorization,
+                                agent_did: self.decision_agent(&decision),
+                                summary: format!(
+                                    "Transaction {} awaits authorization",
+                                    decision.transaction_id
+                                ),
+                                transaction_id: Some(decision.transaction_id),
+                                details: decision.context_json,
+                                created_at: decision.created_at,
+                            });
+                        }
+                        DecisionType::PolicySatisfactionRequired
+                            if filter.includes(PendingActionKind::RelationshipConfirmation) =>
+                        {
+                            let Some(request) = self
+                                .relationship_request(&storage, &decision.transaction_id)
+                                .await?
+                            else {
+                                continue;
+                            };
+                            actions.push(PendingAction {
+                                id: action_id(
+                                    PendingActionKind::RelationshipConfirmation,
+                                    decision.id,
+                                ),
+                                kind: PendingActionKind::RelationshipConfirmation,
+                                agent_did: self.decision_agent(&decision),
+                                summary: format!(
+                                    "{} asks to confirm the party acted for in transaction {}",
+                                    request["requested_by"].as_str().unwrap_or("A counterparty"),
+                                    decision.transaction_id
+                                ),
+                                transaction_id: Some(decision.transaction_id),
+                                details: request,
+                                created_at: decision.created_at,
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        if filter.includes(PendingActionKind::QuarantinedMessage) {
+            let dead_letters = storage
+                .list_dead_letters(MAX_PENDING_ACTIONS_LIMIT, 0)
+                .await
+                .map_err(|e| Error::Storage(e.to_string()))?;
+            for dead_letter in dead_letters {
+                let message: Value = serde_json::from_str(&dead_letter.raw_message)
+                    .unwrap_or_else(|_| Value::String(dead_letter.raw_message.clone()));
+                // Outgoing messages time out in the hooks, incoming ones
+                // before any hook runs
+                let agents: Vec<String> = if dead_letter.stage == ProcessingStage::Hook {
+                    message["from"]
+                        .as_str()
+                        .map(String::from)
+                        .into_iter()
+                        .collect()
+                } else {
+                    message["to"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|to| to.as_str())
+                        .filter(|to| self.agents.has_agent(to))
+                        .map(String::from)
+                        .collect()
+                };
+                if agent_did.is_some_and(|did| !agents.iter().any(|a| a == did)) {
+                    continue;
+                }
+
+                actions.push(PendingAction {
+                    id: action_id(PendingActionKind::QuarantinedMessage, dead_letter.id),
+                    kind: PendingActionKind::QuarantinedMessage,
+                    agent_did: agents.into_iter().next(),
+                    transaction_id: message["thid"].as_str().map(String::from),
+                    summary: dead_letter.reason.clone(),
+                    details: json!({
+                        "message_id": dead_letter.message_id,
+                        "stage": dead_letter.stage,
+                        "reason": dead_letter.reason,
+                        "message": message,
+                    }),
+                    created_at: dead_letter.created_at,
+                });
+            }
+        }
+
+        actions.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+        actions.truncate(
+            limit
+                .unwrap_or(DEFAULT_PENDING_ACTIONS_LIMIT)
+                .clamp(1, MAX_PENDING_ACTIONS_LIMIT) as usize,
+        );
+        Ok(actions)
+    }
+
+    /// Approve a pending action, sending the TAP response it calls for
+    ///
+    /// Returns `None` if there is no such action or it no longer awaits
+    /// approval.
+    pub async fn approve_pending_action(
+        &self,
+        id: &str,
+        response: &PendingActionResponse,
+    ) -> Result<Option<PendingActionOutcome>> {
+        self.complete_pending_action(id, true, response).await
+    }
+
+    /// Reject a pending action, sending the TAP response it calls for
+    ///
+    /// Returns `None` if there is no such action or it no longer awaits
+    /// approval.
+    pub async fn reject_pending_action(
+        &self,
+        id: &str,
+        response: &PendingActionResponse,
+    ) -> Result<Option<PendingActionOutcome>> {
+        self.complete_pending_action(id, false, response).await
+    }
+
+    async fn complete_pending_action(
+        &self,
+        id: &str,
+        approve: bool,
+        response: &PendingActionResponse,
+    ) -> Result<Option<PendingActionOutcome>> {
+        let (kind, row) = parse_action_id(id)?;
+        let storage = self.pending_actions_storage()?;
+
+        let (transaction_id, message_id) = match kind {
+            PendingActionKind::ManualReview => {
+                let Some(item) = storage
+                    .get_review_item(row)
+                    .await
+                    .map_err(|e| Error::Storage(e.to_string()))?
+                    .filter(|item| item.status == ReviewStatus::Pending)
+                else {
+                    return Ok(None);
+                };
+
+                let (status, resolution, message_id) = if approve {
+                    let message_id = self
+                        .send_authorize(&item.agent_did, &item.transaction_id, response)
+                        .await?;
+                    (ReviewStatus::Approved, "authorize", message_id)
+                } else {
+                    let reason = response.note.clone().unwrap_or(item.reason.clone());
+                    let message_id = self
+                        .send_reject(
+                            &item.agent_did,
+                            &item.transaction_id,
+                            RejectionCode::Policy,
+                            reason,
+                        )
+                        .await?;
+                    (ReviewStatus::Rejected, "reject", message_id)
+                };
+
+                let completed = storage
+                    .complete_review_item(
+                        item.id,
+                        status,
+                        &response.actor,
+                        response.note.as_deref(),
+                        Some(&message_id),
+                    )
+                    .await
+                    .map_err(|e| Error::Storage(e.to_string()))?;
+                if !completed {
+                    log::warn!("Review {} was completed by another reviewer", item.id);
+                }
+                storage
+                    .resolve_decisions_for_transaction(
+                        &item.transaction_id,
+                        resolution,
+                        Some(DecisionType::AuthorizationRequired),
+                    )
+                    .await
+                    .map_err(|e| Error::Storage(e.to_string()))?;
+                (Some(item.transaction_id), Some(message_id))
+            }
+            PendingActionKind::Authorization | PendingActionKind::RelationshipConfirmation => {
+                let decision_type = if kind == PendingActionKind::Authorization {
+                    DecisionType::AuthorizationRequired
+                } else {
+                    DecisionType::PolicySatisfactionRequired
+                };
+                let Some(decision) = storage
+                    .get_decision_by_id(row)
+                    .await
+                    .map_err(|e| Error::Storage(e.to_string()))?
+                    .filter(|d| d.decision_type == decision_type && is_open(d))
+                else {
+                    return Ok(None);
+                };
+                let agent_did = self.decision_agent(&decision).ok_or_else(|| {
+                    Error::Validation(format!(
+                        "None of our agents is to act on transaction {}",
+                        decision.transaction_id
+                    ))
+                })?;
+                let transaction_id = decision.transaction_id.as_str();
+
+                let (resolution, message_id) = match (kind, approve) {
+                    (PendingActionKind::Authorization, true) => (
+                        "authorize",
+                        self.send_authorize(&agent_did, transaction_id, response)
+                            .await?,
+                    ),
+                    (PendingActionKind::RelationshipConfirmation, true) => {
+                        let for_entity = self
+                            .party_acted_for(&storage, transaction_id, &agent_did)
+                            .await?;
+                        let confirmation =
+                            ConfirmRelationship::new(transaction_id, &agent_did, &for_entity);
+                        (
+                            "confirm_relationship",
+                            self.send_transaction_response(
+                                &agent_did,
+                                transaction_id,
+                                &confirmation,
+                            )
+                            .await?,
+                        )
+                    }
+                    _ => {
+                        let reason = response
+                            .note
+                            .clone()
+                            .unwrap_or_else(|| format!("Declined by {}", response.actor));
+                        (
+                            "reject",
+                            self.send_reject(
+                                &agent_did,
+                                transaction_id,
+                                RejectionCode::CustomerDeclined,
+                                reason,
+                            )
+                            .await?,
+                        )
+                    }
+                };
+
+                let detail = json!({
+                    "message_id": message_id,
+                    "actor": response.actor,
+                    "note": response.note,
+                });
+                storage
+                    .update_decision_status(
+                        decision.id,
+                        DecisionStatus::Resolved,
+                        Some(resolution),
+                        Some(&detail),
+                    )
+                    .await
+                    .map_err(|e| Error::Storage(e.to_string()))?;
+                (Some(decision.transaction_id), Some(message_id))
+            }
+            PendingActionKind::QuarantinedMessage => {
+                let Some(dead_letter) = storage
+                    .get_dead_letter(row)
+                    .await
+                    .map_err(|e| Error::Storage(e.to_string()))?
+                else {
+                    return Ok(None);
+                };
+                let message: Value = serde_json::from_str(&dead_letter.raw_message)
+                    .map_err(|e| Error::Serialization(e.to_string()))?;
+                let transaction_id = message["thid"].as_str().map(String::from);
+
+                let message_id = if !approve {
+                    None
+                } else if dead_letter.stage == ProcessingStage::Hook {
+                    let message: PlainMessage = serde_json::from_value(message)
+                        .map_err(|e| Error::Serialization(e.to_string()))?;
+                    Some(self.send_message(message.from.clone(), message).await?)
+                } else {
+                    match self.receive_message(message).await? {
+                        IngestOutcome::Rejected { reason, .. } => {
+                            return Err(Error::Validation(format!(
+                                "Replayed message was rejected: {}",
+                                reason
+                            )))
+                        }
+                        _ => dead_letter.message_id.clone(),
+                    }
+                };
+
+                log::info!(
+                    "Dead letter {} {} by {}",
+                    dead_letter.id,
+                    if approve { "replayed" } else { "discarded" },
+                    response.actor
+                );
+                storage
+                    .delete_dead_letter(dead_letter.id)
+                    .await
+                    .map_err(|e| Error::Storage(e.to_string()))?;
+                (transaction_id, message_id)
+            }
+        };
+
+        Ok(Some(PendingActionOutcome {
+            id: id.to_string(),
+            kind,
+            transaction_id,
+            message_id,
+        }))
+    }
+
+    fn pending_actions_storage(&self) -> Result<Arc<Storage>> {
+        self.storage()
+            .cloned()
+            .ok_or_else(|| Error::Storage("Storage is not initialized".to_string()))
+    }
+
+    /// Our agent a decision is to be made by
+    fn decision_agent(&self, decision: &DecisionLogEntry) -> Option<String> {
+        if !decision.agent_did.is_empty() {
+            return Some(decision.agent_did.clone());
+        }
+        decision.context_json["pending_agents"]
+            .as_array()?
+            .iter()
+            .filter_map(|did| did.as_str())
+            .find(|did| self.agents.has_agent(did))
+            .map(String::from)
+    }
+
+    /// The counterparty's request to confirm a relationship in a
+    /// transaction, if it sent one
+    async fn relationship_request(
+        &self,
+        storage: &Storage,
+        transaction_id: &str,
+    ) -> Result<Option<Value>> {
+        let timeline = storage
+            .get_transaction_timeline(transaction_id)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        Ok(timeline.iter().rev().find_map(|entry| {
+            let message = &entry.message;
+            if !message.message_type.ends_with("#UpdatePolicies") {
+                return None;
+            }
+            relationship_policy(&message.message_json).map(|policy| {
+                json!({
+                    "requested_by": message.from_did,
+                    "message_id": message.message_id,
+                    "policy": policy,
+                })
+            })
+        }))
+    }
+
+    /// The party our agent acts for in a transaction
+    async fn party_acted_for(
+        &self,
+        storage: &Storage,
+        transaction_id: &str,
+        agent_did: &str,
+    ) -> Result<String> {
+        let participants = storage
+            .get_transaction_participants(transaction_id, false)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        if let Some(party) = participants
+            .iter()
+            .filter(|p| p.agent_did == agent_did)
+            .find_map(|p| p.for_parties.first())
+        {
+            return Ok(party.clone());
+        }
+
+        let transaction = storage
+            .get_transaction_by_id(transaction_id)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        transaction
+            .and_then(|t| {
+                t.message_json["body"]["agents"]
+                    .as_array()?
+                    .iter()
+                    .find(|agent| agent["@id"] == agent_did)
+                    .and_then(|agent| agent["for"].as_str())
+                    .map(String::from)
+            })
+            .ok_or_else(|| {
+                Error::Validation(format!(
+                    "Agent {} acts for no party of transaction {}",
+                    agent_did, transaction_id
+                ))
+            })
+    }
+
+    async fn send_authorize(
+        &self,
+        agent_did: &str,
+        transaction_id: &str,
+        response: &PendingActionResponse,
+    ) -> Result<String> {
+        let authorize = Authorize {
+            transaction_id: transaction_id.to_string(),
+            settlement_address: response.settlement_address.clone(),
+            expiry: None,
+        };
+        self.send_transaction_response(agent_did, transaction_id, &authorize)
+            .await
+    }
+
+    async fn send_reject(
+        &self,
+        agent_did: &str,
+        transaction_id: &str,
+        code: RejectionCode,
+        reason: String,
+    ) -> Result<String> {
+        let reject = Reject {
+            transaction_id: transaction_id.to_string(),
+            code: Some(code),
+            reason: Some(reason),
+        };
+        self.send_transaction_response(agent_did, transaction_id, &reject)
+            .await
+    }
+
+    /// Send a reply in a transaction from our agent to the other agents in
+    /// it, returning the ID of the message sent
+    async fn send_transaction_response<T: TapMessageBody>(
+        &self,
+        agent_did: &str,
+        transaction_id: &str,
+        body: &T,
+    ) -> Result<String> {
+        body.validate()
+            .map_err(|e| Error::Validation(e.to_string()))?;
+
+        let storage = self.pending_actions_storage()?;
+        let transaction = storage
+            .get_transaction_by_id(transaction_id)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        let participants = storage
+            .get_transaction_participants(transaction_id, false)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+
+        // The sender of the transaction, the agents it names and those
+        // added to it since
+        let mut recipients: Vec<String> = Vec::new();
+        let candidates = transaction
+            .iter()
+            .flat_map(|t| {
+                let agents = t.message_json["body"]["agents"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|agent| agent["@id"].as_str().map(String::from));
+                t.from_did.clone().into_iter().chain(agents)
+            })
+            .chain(participants.into_iter().map(|p| p.agent_did));
+        for did in candidates {
+            if did != agent_did && !self.agents.has_agent(&did) && !recipients.contains(&did) {
+                recipients.push(did);
+            }
+        }
+        if recipients.is_empty() {
+            return Err(Error::Validation(format!(
+                "Transaction {} has no counterparty to reply to",
+                transaction_id
+            )));
+        }
+
+        let mut message = body
+            .to_didcomm_with_route(agent_did, recipients.iter().map(String::as_str))
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+        message.thid = Some(transaction_id.to_string());
+        let message_id = message.id.clone();
+        self.send_message(agent_did.to_string(), message).await?;
+        Ok(message_id)
+    }
+}
@@ -0,0 +1,356 @@
+//! DIDComm message pickup for remote agents with a mailbox
+//!
+//! Implements the mediator side of [Message Pickup 3.0], so standard DIDComm
+//! clients can retrieve the messages held in their mailbox (see
+//! [`crate::mailbox`]). The client sends a signed pickup request to one of our
+//! agents, which answers on the return route of the same request:
+//!
+//! - `status-request` is answered with a `status` message
+//! - `delivery-request` is answered with a `delivery` message carrying up to
+//!   `limit` held messages as attachments, or with a `status` message when
+//!   none is held
+//! - `messages-received` acknowledges the delivered messages by attachment ID,
+//!   marking their delivery records successful, and is answered with a
+//!   `status` message
+//!
+//! Live delivery is not supported; `live-delivery-change` is answered with a
+//! problem report.
+//!
+//! [Message Pickup 3.0]: https://didcomm.org/messagepickup/3.0/
+
+use crate::error::{Error, Result};
+use crate::storage::MailboxSummary;
+use crate::TapNode;
+use base64::Engine;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::time::Duration;
+use tap_agent::message_packing::{PackOptions, Packable};
+use tap_agent::{verify_jws_with_details, Jws};
+use tap_msg::didcomm::{Attachment, PlainMessage};
+use tap_msg::message::problem_report::PROBLEM_REPORT_TYPE;
+
+/// Message type prefix of the message pickup protocol
+pub const PICKUP_PROTOCOL: &str = "https://didcomm.org/messagepickup/3.0/";
+/// Asks for the status of the sender's mailbox
+pub const STATUS_REQUEST_TYPE: &str = "https://didcomm.org/messagepickup/3.0/status-request";
+/// Reports the status of a mailbox
+pub const STATUS_TYPE: &str = "https://didcomm.org/messagepickup/3.0/status";
+/// Asks for up to `limit` held messages
+pub const DELIVERY_REQUEST_TYPE: &str = "https://didcomm.org/messagepickup/3.0/delivery-request";
+/// Carries held messages as attachments
+pub const DELIVERY_TYPE: &str = "https://didcomm.org/messagepickup/3.0/delivery";
+/// Acknowledges delivered messages by attachment ID
+pub const MESSAGES_RECEIVED_TYPE: &str = "https://didcomm.org/messagepickup/3.0/messages-received";
+/// Asks to switch live delivery on or off
+pub const LIVE_DELIVERY_CHANGE_TYPE: &str =
+    "https://didcomm.org/messagepickup/3.0/live-delivery-change";
+
+/// Maximum number of messages returned by a delivery
+pub const MAX_DELIVERY_LIMIT: u32 = 500;
+
+#[derive(Debug, Deserialize)]
+struct StatusRequest {
+    recipient_did: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeliveryRequest {
+    limit: u32,
+    recipient_did: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagesReceived {
+    message_id_list: Vec<String>,
+}
+
+impl TapNode {
+    /// Answer a DIDComm message pickup request
+    ///
+    /// Returns `None` when `message` is not a signed message pickup request,
+    /// in which case it is received as usual. Otherwise returns the reply,
+    /// signed by the agent the request was addressed to, to send back on the
+    /// return route of the request. Requests are only answered for senders
+    /// with a mailbox, and only for their own messages.
+    pub async fn receive_pickup_request(
+        &self,
+        message: &serde_json::Value,
+    ) -> Result<Option<String>> {
+        let Some(jws) = pickup_request_jws(message) else {
+            return Ok(None);
+        };
+
+        let (request, _) = verify_jws_with_details(&jws, &*self.resolver)
+            .await
+            .map_err(|e| Error::Verification(format!("JWS verification failed: {}", e)))?;
+
+        let mediator_did = request
+            .to
+            .iter()
+            .find(|did| self.agents.has_agent(did))
+            .cloned()
+            .ok_or_else(|| {
+                Error::Routing(format!(
+                    "Pickup request {} is not addressed to an agent of this node",
+                    request.id
+                ))
+            })?;
+        if !self.has_mailbox(&request.from) {
+            return Err(Error::Validation(format!(
+                "No mailbox is held for {}",
+                request.from
+            )));
+        }
+        if let Some(recipient_did) = request.body.get("recipient_did").and_then(|v| v.as_str()) {
+            if recipient_did != request.from {
+                return Err(Error::Validation(format!(
+                    "{} cannot pick up messages for {}",
+                    request.from, recipient_did
+                )));
+            }
+        }
+
+        log::debug!(
+            "Answering {} from {} via {}",
+            request.type_,
+            request.from,
+            mediator_did
+        );
+        let reply = self.answer_pickup_request(&mediator_did, &request).await?;
+
+        let agent = self.agents.get_agent(&mediator_did).await?;
+        let sender_kid = agent.get_signing_kid().await?;
+        let packed = reply
+            .pack(
+                &**agent.key_manager(),
+                PackOptions::new().with_sign(&sender_kid),
+            )
+            .await?;
+        Ok(Some(packed))
+    }
+
+    async fn answer_pickup_request(
+        &self,
+        mediator_did: &str,
+        request: &PlainMessage,
+    ) -> Result<PlainMessage> {
+        let recipient_did = &request.from;
+
+        match request.type_.as_str() {
+            STATUS_REQUEST_TYPE => {
+                let status: StatusRequest = parse_body(request)?;
+                self.pickup_status(mediator_did, request, status.recipient_did)
+                    .await
+            }
+            DELIVERY_REQUEST_TYPE => {
+                let delivery: DeliveryRequest = parse_body(request)?;
+                let limit = delivery.limit.clamp(1, MAX_DELIVERY_LIMIT);
+                let held = self
+                    .poll_mailbox(recipient_did, 0, limit, Duration::ZERO)
+                    .await?;
+                if held.is_empty() {
+                    return self
+                        .pickup_status(mediator_did, request, delivery.recipient_did)
+                        .await;
+                }
+
+                let attachments = held
+                    .into_iter()
+                    .map(|m| {
+                        let content = serde_json::from_str(&m.message_text)
+                            .unwrap_or(serde_json::Value::String(m.message_text));
+                        Attachment::json(content).id(m.message_id).finalize()
+                    })
+                    .collect();
+                let body = match delivery.recipient_did {
+                    Some(did) => json!({ "recipient_did": did }),
+                    None => json!({}),
+                };
+                let mut reply = self.pickup_reply(mediator_did, request, DELIVERY_TYPE, body);
+                reply.attachments = Some(attachments);
+                Ok(reply)
+            }
+            MESSAGES_RECEIVED_TYPE => {
+                let received: MessagesReceived = parse_body(request)?;
+                let acknowledged = self
+                    .acknowledge_mailbox_messages(recipient_did, &received.message_id_list)
+                    .await?;
+                log::debug!(
+                    "{} acknowledged {} mailbox messages",
+                    recipient_did,
+                    acknowledged
+                );
+                self.pickup_status(mediator_did, request, None).await
+            }
+            LIVE_DELIVERY_CHANGE_TYPE => {
+                let mut reply = self.pickup_reply(
+                    mediator_did,
+                    request,
+                    PROBLEM_REPORT_TYPE,
+                    json!({
+                        "code": "e.m.live-mode-not-supported",
+                        "comment": "Connection does not support Live Delivery",
+                    }),
+                );
+                reply.thid = None;
+                reply.pthid = Some(request.thid.clone().unwrap_or(request.id.clone()));
+                Ok(reply)
+            }
+            other => Err(Error::Validation(format!(
+                "Unsupported message pickup message type: {}",
+                other
+            ))),
+        }
+    }
+
+    async fn pickup_status(
+        &self,
+        mediator_did: &str,
+        request: &PlainMessage,
+        recipient_did: Option<String>,
+    ) -> Result<PlainMessage> {
+        let summary = self.mailbox_summary(&request.from).await?;
+        let body = status_body(
+            &summary,
+            recipient_did,
+            self.clock().now().timestamp() as u64,
+        );
+        Ok(self.pickup_reply(mediator_did, request, STATUS_TYPE, body))
+    }
+
+    fn pickup_reply(
+        &self,
+        mediator_did: &str,
+        request: &PlainMessage,
+        type_: &str,
+        body: serde_json::Value,
+    ) -> PlainMessage {
+        PlainMessage {
+            id: tap_msg::id::new_id(),
+            typ: "application/didcomm-plain+json".to_string(),
+            type_: type_.to_string(),
+            body,
+            from: mediator_did.to_string(),
+            to: vec![request.from.clone()],
+            thid: Some(request.thid.clone().unwrap_or(request.id.clone())),
+            pthid: None,
+            extra_headers: HashMap::new(),
+            attachments: None,
+            created_time: Some(self.clock().now().timestamp() as u64),
+            expires_time: None,
+            from_prior: None,
+        }
+    }
+}
+
+/// Parse a signed message whose payload is a message pickup request
+///
+/// The payload is inspected before the signature is verified so that other
+/// messages are not verified twice.
+fn pickup_request_jws(message: &serde_json::Value) -> Option<Jws> {
+    let payload = message.get("payload")?.as_str()?;
+    if message.get("signatures").is_none() && message.get("signature").is_none() {
+        return None;
+    }
+
+    let decoded = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload)
+        .ok()?;
+    let payload: serde_json::Value = serde_json::from_slice(&decoded).ok()?;
+    if !payload.get("type")?.as_str()?.starts_with(PICKUP_PROTOCOL) {
+        return None;
+    }
+
+    serde_json::from_value(message.clone()).ok()
+}
+
+fn parse_body<T: serde::de::DeserializeOwned>(request: &PlainMessage) -> Result<T> {
+    serde_json::from_value(request.body.clone()).map_err(|e| {
+        Error::Validation(format!(
+            "Invalid {} message {}: {}",
+            request.type_, request.id, e
+        ))
+    })
+}
+
+fn status_body(
+    summary: &MailboxSummary,
+    recipient_did: Option<String>,
+    now: u64,
+) -> serde_json::Value {
+    let mut body = json!({
+        "message_count": summary.message_count,
+        "total_bytes": summary.total_bytes,
+        "live_delivery": false,
+    });
+    if let Some(did) = recipient_did {
+        body["recipient_did"] = json!(did);
+    }
+    if let Some(oldest) = summary.oldest_created_at.as_deref().and_then(epoch_seconds) {
+        body["oldest_received_time"] = json!(oldest);
+        body["longest_waited_seconds"] = json!(now.saturating_sub(oldest));
+    }
+    if let Some(newest) = summary.newest_created_at.as_deref().and_then(epoch_seconds) {
+        body["newest_received_time"] = json!(newest);
+    }
+    body
+}
+
+fn epoch_seconds(timestamp: &str) -> Option<u64> {
+    chrono::NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%SZ")
+        .ok()
+        .map(|t| t.and_utc().timestamp() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_body() {
+        let summary = MailboxSummary {
+            message_count: 2,
+            total_bytes: 512,
+            oldest_created_at: Some("2026-01-01T12:00:00Z".to_string()),
+            newest_created_at: Some("2026-01-01T12:05:00Z".to_string()),
+        };
+        let now = epoch_seconds("2026-01-01T12:10:00Z").unwrap();
+
+        let body = status_body(&summary, Some("did:example:bob".to_string()), now);
+        assert_eq!(body["message_count"], 2);
+        assert_eq!(body["total_bytes"], 512);
+        assert_eq!(body["longest_waited_seconds"], 600);
+        assert_eq!(body["newest_received_time"], now - 300);
+        assert_eq!(body["recipient_did"], "did:example:bob");
+        assert_eq!(body["live_delivery"], false);
+
+        let empty = status_body(&MailboxSummary::default(), None, now);
+        assert_eq!(empty["message_count"], 0);
+        assert!(empty.get("oldest_received_time").is_none());
+        assert!(empty.get("recipient_did").is_none());
+    }
+
+    #[test]
+    fn test_only_pickup_requests_are_intercepted() {
+        let encode = |payload: serde_json::Value| {
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(payload.to_string())
+        };
+        let signed = |payload: serde_json::Value| {
+            json!({
+                "payload": encode(payload),
+                "protected": "e30",
+                "signature": "c2ln",
+                "header": { "kid": "did:example:bob#key-1" },
+            })
+        };
+
+        assert!(pickup_request_jws(&signed(json!({ "type": STATUS_REQUEST_TYPE }))).is_some());
+        assert!(pickup_request_jws(&signed(
+            json!({ "type": "https://tap.rsvp/schema/1.0#Transfer" })
+        ))
+        .is_none());
+        assert!(pickup_request_jws(&json!({ "type": STATUS_REQUEST_TYPE })).is_none());
+    }
+}
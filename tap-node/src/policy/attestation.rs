@@ -0,0 +1,198 @@
+//! Key attestation rules
+//!
+//! A [`KeyAttestationRule`] requires the agent that sent a Transfer or
+//! Payment to have sent it with a valid key attestation, optionally from one
+//! of a set of organizations. Attestations are verified on receipt (see
+//! [`NodeConfig::key_attestation_verification`](crate::NodeConfig::key_attestation_verification))
+//! and the rule reads the outcome recorded for the message with
+//! [`Storage::get_key_attestation`].
+
+use super::{PolicyAction, PolicyContext, PolicyOutcome, PolicyRule};
+use crate::error::{Error, Result};
+use crate::storage::KeyAttestationStatus;
+use async_trait::async_trait;
+use serde_json::json;
+use tap_msg::message::TapMessage;
+
+/// Requires the sending agent to be attested by an organization
+#[derive(Debug, Clone)]
+pub struct KeyAttestationRule {
+    name: String,
+    organizations: Vec<String>,
+    action: PolicyAction,
+}
+
+impl KeyAttestationRule {
+    /// Create a key attestation rule
+    ///
+    /// The rule trips when the message was sent without a key attestation,
+    /// or with one that failed verification.
+    pub fn new(name: impl Into<String>, action: PolicyAction) -> Self {
+        Self {
+            name: name.into(),
+            organizations: Vec::new(),
+            action,
+        }
+    }
+
+    /// Only accept attestations issued by this organization; may be given
+    /// several times to accept any of them
+    pub fn require_organization(mut self, organization: impl Into<String>) -> Self {
+        self.organizations.push(organization.into());
+        self
+    }
+}
+
+#[async_trait]
+impl PolicyRule for KeyAttestationRule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn evaluate(&self, ctx: &PolicyContext<'_>) -> Result<Option<PolicyOutcome>> {
+        if !matches!(
+            ctx.tap_message,
+            TapMessage::Transfer(_) | TapMessage::Payment(_)
+        ) {
+            return Ok(None);
+        }
+
+        let sender = &ctx.message.from;
+        let attestation = ctx
+            .storage
+            .get_key_attestation(&ctx.message.id)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        let reason = match &attestation {
+            None => format!("Agent {} sent no key attestation", sender),
+            Some(record) if record.status != KeyAttestationStatus::Verified => format!(
+                "Key attestation of agent {} is invalid: {}",
+                sender,
+                record.reason.as_deref().unwrap_or("unknown reason")
+            ),
+            Some(record)
+                if !self.organizations.is_empty()
+                    && !record
+                        .organization
+                        .as_ref()
+                        .is_some_and(|org| self.organizations.contains(org)) =>
+            {
+                format!(
+                    "Agent {} is attested by {}, not a required organization",
+                    sender,
+                    record.organization.as_deref().unwrap_or("nobody")
+                )
+            }
+            Some(_) => return Ok(None),
+        };
+
+        Ok(Some(PolicyOutcome {
+            rule: self.name.clone(),
+            action: self.action,
+            reason,
+            details: json!({
+                "agent": sender,
+                "organizations": self.organizations,
+                "organization": attestation.as_ref().and_then(|r| r.organization.clone()),
+                "status": attestation.as_ref().map(|r| r.status.to_string()),
+            }),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Storage;
+    use crate::test_fixtures::{transfer_body, transfer_message};
+    use tap_agent::{verify_key_attestation, MultiResolver, TapAgent};
+    use tap_msg::didcomm::PlainMessage;
+
+    async fn evaluate(
+        rule: &KeyAttestationRule,
+        storage: &Storage,
+        message: &PlainMessage,
+    ) -> Option<PolicyOutcome> {
+        let tap_message = TapMessage::from_plain_message(message).unwrap();
+        rule.evaluate(&PolicyContext {
+            transaction_id: &message.id,
+            message,
+            tap_message: &tap_message,
+            storage,
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_key_attestation_rule() {
+        let storage = Storage::new_in_memory().await.unwrap();
+        let (organization, organization_did) = TapAgent::from_ephemeral_key().await.unwrap();
+        let (_, agent_did) = TapAgent::from_ephemeral_key().await.unwrap();
+        let rule = KeyAttestationRule::new("attested", PolicyAction::ManualReview)
+            .require_organization(&organization_did);
+
+        // No attestation
+        let unattested = transfer_message(
+            "tx-1",
+            &transfer_body("did:example:alice", "did:example:bob", "10"),
+            &agent_did,
+        );
+        let outcome = evaluate(&rule, &storage, &unattested).await.unwrap();
+        assert_eq!(outcome.action, PolicyAction::ManualReview);
+        assert!(outcome.reason.contains("no key attestation"));
+
+        // Attested by the required organization
+        let mut attested = transfer_message(
+            "tx-2",
+            &transfer_body("did:example:alice", "did:example:bob", "10"),
+            &agent_did,
+        );
+        attested.extra_headers.insert(
+            tap_agent::attestation::KEY_ATTESTATION_HEADER.to_string(),
+            organization
+                .attest_key(&agent_did, None, None)
+                .await
+                .unwrap(),
+        );
+        let attestation = verify_key_attestation(&attested, None, &MultiResolver::default(), 0)
+            .await
+            .unwrap()
+            .unwrap();
+        storage
+            .insert_key_attestation("tx-2", &agent_did, Ok(&attestation))
+            .await
+            .unwrap();
+        assert!(evaluate(&rule, &storage, &attested).await.is_none());
+
+        // Attested, but by another organization
+        let other = KeyAttestationRule::new("attested", PolicyAction::Reject)
+            .require_organization("did:web:other.example");
+        let outcome = evaluate(&other, &storage, &attested).await.unwrap();
+        assert_eq!(outcome.details["organization"], organization_did.as_str());
+        assert!(KeyAttestationRule::new("any", PolicyAction::Reject)
+            .evaluate(&PolicyContext {
+                transaction_id: "tx-2",
+                message: &attested,
+                tap_message: &TapMessage::from_plain_message(&attested).unwrap(),
+                storage: &storage,
+            })
+            .await
+            .unwrap()
+            .is_none());
+
+        // Attestation failed verification
+        let invalid = transfer_message(
+            "tx-3",
+            &transfer_body("did:example:alice", "did:example:bob", "10"),
+            &agent_did,
+        );
+        storage
+            .insert_key_attestation("tx-3", &agent_did, Err("Credential has expired"))
+            .await
+            .unwrap();
+        let outcome = evaluate(&rule, &storage, &invalid).await.unwrap();
+        assert_eq!(outcome.details["status"], "invalid");
+        assert!(outcome.reason.contains("Credential has expired"));
+    }
+}
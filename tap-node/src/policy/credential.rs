@@ -0,0 +1,230 @@
+//! Credential rules
+//!
+//! A [`CredentialRule`] requires the agent that sent a Transfer or Payment to
+//! present a verifiable credential from a trusted issuer, optionally of a
+//! given type and with given claims, e.g. a `VASPLicense` whose
+//! `license.jurisdiction` is `US`. Credentials are taken from the sending
+//! agent's entry in the message's `agents` and checked with a
+//! [`CredentialVerifier`].
+
+use super::{PolicyAction, PolicyContext, PolicyOutcome, PolicyRule};
+use crate::credentials::CredentialVerifier;
+use crate::error::Result;
+use async_trait::async_trait;
+use serde_json::json;
+use std::sync::Arc;
+use tap_agent::VerifiedCredential;
+use tap_msg::message::TapMessage;
+
+/// Requires the sending agent to present a matching trusted credential
+#[derive(Debug, Clone)]
+pub struct CredentialRule {
+    name: String,
+    verifier: Arc<CredentialVerifier>,
+    credential_type: Option<String>,
+    claims: Vec<(String, serde_json::Value)>,
+    action: PolicyAction,
+}
+
+impl CredentialRule {
+    /// Create a credential rule
+    ///
+    /// The rule trips when none of the sending agent's credentials verifies
+    /// and matches the required type and claims.
+    pub fn new(
+        name: impl Into<String>,
+        verifier: Arc<CredentialVerifier>,
+        action: PolicyAction,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            verifier,
+            credential_type: None,
+            claims: Vec::new(),
+            action,
+        }
+    }
+
+    /// Require a credential of this type
+    pub fn require_type(mut self, credential_type: impl Into<String>) -> Self {
+        self.credential_type = Some(credential_type.into());
+        self
+    }
+
+    /// Require the `credentialSubject` claim at `path` (dot-separated) to
+    /// equal `value`
+    pub fn require_claim(mut self, path: impl Into<String>, value: serde_json::Value) -> Self {
+        self.claims.push((path.into(), value));
+        self
+    }
+
+    fn matches(&self, credential: &VerifiedCredential) -> bool {
+        self.credential_type
+            .as_deref()
+            .is_none_or(|t| credential.has_type(t))
+            && self
+                .claims
+                .iter()
+                .all(|(path, value)| credential.claim(path) == Some(value))
+    }
+}
+
+#[async_trait]
+impl PolicyRule for CredentialRule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn evaluate(&self, ctx: &PolicyContext<'_>) -> Result<Option<PolicyOutcome>> {
+        let agents = match ctx.tap_message {
+            TapMessage::Transfer(transfer) => &transfer.agents,
+            TapMessage::Payment(payment) => &payment.agents,
+            _ => return Ok(None),
+        };
+
+        let sender = &ctx.message.from;
+        let mut failures = Vec::new();
+        if let Some(agent) = agents.iter().find(|agent| &agent.id == sender) {
+            let result = self.verifier.verify_agent(agent).await;
+            if result.verified.iter().any(|c| self.matches(c)) {
+                return Ok(None);
+            }
+            failures = result
+                .failures
+                .into_iter()
+                .map(|failure| failure.reason)
+                .collect();
+        }
+
+        let required = self.credential_type.as_deref().unwrap_or("trusted");
+        Ok(Some(PolicyOutcome {
+            rule: self.name.clone(),
+            action: self.action,
+            reason: format!("Agent {} has no {} credential", sender, required),
+            details: json!({
+                "agent": sender,
+                "credential_type": self.credential_type,
+                "claims": self.claims.iter().cloned().collect::<serde_json::Map<_, _>>(),
+                "failures": failures,
+            }),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Storage;
+    use crate::test_fixtures::{transfer_body, transfer_message};
+    use tap_agent::key_manager::KeyManager;
+    use tap_agent::message::JwsProtected;
+    use tap_agent::{MultiResolver, TapAgent};
+    use tap_msg::didcomm::PlainMessage;
+    use tap_msg::message::{Agent, Transfer};
+
+    const SENDER: &str = "did:web:vasp.example";
+
+    async fn issue(issuer: &TapAgent, issuer_did: &str, jurisdiction: &str) -> serde_json::Value {
+        let kid = issuer.get_signing_kid().await.unwrap();
+        let claims = json!({
+            "iss": issuer_did,
+            "sub": SENDER,
+            "exp": chrono::Utc::now().timestamp() + 3600,
+            "vc": {
+                "type": ["VerifiableCredential", "VASPLicense"],
+                "credentialSubject": {"id": SENDER, "jurisdiction": jurisdiction}
+            }
+        });
+        let protected = JwsProtected {
+            typ: "JWT".to_string(),
+            alg: String::new(),
+            kid: kid.clone(),
+            zip: None,
+        };
+        let jws = issuer
+            .key_manager()
+            .sign_jws(&kid, &serde_json::to_vec(&claims).unwrap(), Some(protected))
+            .await
+            .unwrap();
+        serde_json::from_str(&jws).unwrap()
+    }
+
+    /// A Transfer whose sending agent carries `credentials`
+    fn transfer_with_credentials(credentials: Vec<serde_json::Value>) -> Transfer {
+        let agent = credentials.into_iter().fold(
+            Agent::new(SENDER, "VASP", "did:example:alice"),
+            Agent::with_credential,
+        );
+        Transfer {
+            agents: vec![agent],
+            ..transfer_body("did:example:alice", "did:example:bob", "10")
+        }
+    }
+
+    async fn evaluate(rule: &CredentialRule, message: &PlainMessage) -> Option<PolicyOutcome> {
+        let storage = Storage::new_in_memory().await.unwrap();
+        let tap_message = TapMessage::from_plain_message(message).unwrap();
+        rule.evaluate(&PolicyContext {
+            transaction_id: &message.id,
+            message,
+            tap_message: &tap_message,
+            storage: &storage,
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_credential_rule() {
+        let (regulator, regulator_did) = TapAgent::from_ephemeral_key().await.unwrap();
+        let (impostor, impostor_did) = TapAgent::from_ephemeral_key().await.unwrap();
+        let verifier = Arc::new(CredentialVerifier::new(
+            vec![regulator_did.clone()],
+            Arc::new(MultiResolver::default()),
+        ));
+        let rule = CredentialRule::new("licensed-vasp", verifier, PolicyAction::ManualReview)
+            .require_type("VASPLicense")
+            .require_claim("jurisdiction", json!("US"));
+
+        // No credential
+        let outcome = evaluate(
+            &rule,
+            &transfer_message("tx-1", &transfer_with_credentials(vec![]), SENDER),
+        )
+        .await
+        .unwrap();
+        assert_eq!(outcome.action, PolicyAction::ManualReview);
+        assert_eq!(outcome.details["agent"], SENDER);
+
+        // Trusted issuer, matching claims
+        let licensed = issue(&regulator, &regulator_did, "US").await;
+        assert!(evaluate(
+            &rule,
+            &transfer_message("tx-1", &transfer_with_credentials(vec![licensed]), SENDER)
+        )
+        .await
+        .is_none());
+
+        // Claim does not match
+        let other = issue(&regulator, &regulator_did, "FR").await;
+        assert!(evaluate(
+            &rule,
+            &transfer_message("tx-1", &transfer_with_credentials(vec![other]), SENDER)
+        )
+        .await
+        .is_some());
+
+        // Untrusted issuer
+        let untrusted = issue(&impostor, &impostor_did, "US").await;
+        let outcome = evaluate(
+            &rule,
+            &transfer_message("tx-1", &transfer_with_credentials(vec![untrusted]), SENDER),
+        )
+        .await
+        .unwrap();
+        assert!(outcome.details["failures"][0]
+            .as_str()
+            .unwrap()
+            .contains("not trusted"));
+    }
+}
@@ -0,0 +1,197 @@
+//! Duplicate transfer detection
+//!
+//! A [`DuplicateRule`] flags Transfers that are likely resubmissions of an
+//! earlier Transfer, e.g. by an upstream system retrying a request that
+//! already went through: the same originator, beneficiary, asset and amount
+//! within a short window. Failed, cancelled and reverted transactions are
+//! not considered, so a Transfer sent again after a rejection is not
+//! flagged.
+//!
+//! The rule warns by default ([`PolicyAction::Warn`]), attaching a warning to
+//! the transaction and letting it proceed. Use
+//! [`PolicyAction::ManualReview`] to hold likely duplicates for a reviewer.
+
+use super::{PolicyAction, PolicyContext, PolicyOutcome, PolicyRule};
+use crate::error::{Error, Result};
+use async_trait::async_trait;
+use serde_json::json;
+use std::time::Duration;
+use tap_msg::message::TapMessage;
+
+/// Flags Transfers repeating an earlier Transfer within a window
+#[derive(Debug, Clone)]
+pub struct DuplicateRule {
+    name: String,
+    window: Duration,
+    action: PolicyAction,
+}
+
+impl DuplicateRule {
+    /// Create a duplicate rule
+    ///
+    /// The rule trips when a Transfer from the same originator to the same
+    /// beneficiary, in the same asset and for the same amount, was created
+    /// within `window` before the Transfer being evaluated.
+    pub fn new(name: impl Into<String>, window: Duration) -> Self {
+        Self {
+            name: name.into(),
+            window,
+            action: PolicyAction::Warn,
+        }
+    }
+
+    /// Take this action instead of warning, e.g. [`PolicyAction::ManualReview`]
+    pub fn with_action(mut self, action: PolicyAction) -> Self {
+        self.action = action;
+        self
+    }
+}
+
+#[async_trait]
+impl PolicyRule for DuplicateRule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn evaluate(&self, ctx: &PolicyContext<'_>) -> Result<Option<PolicyOutcome>> {
+        let TapMessage::Transfer(transfer) = ctx.tap_message else {
+            return Ok(None);
+        };
+        let amount = transfer.amount.parse::<f64>().map_err(|e| {
+            Error::Validation(format!("Invalid amount '{}': {}", transfer.amount, e))
+        })?;
+        let originator = transfer.originator.as_ref().map(|p| p.id.as_str());
+        let beneficiary = transfer.beneficiary.as_ref().map(|p| p.id.as_str());
+        let asset = transfer.asset.to_string();
+
+        let window = chrono::Duration::from_std(self.window)
+            .map_err(|e| Error::Configuration(format!("Invalid duplicate window: {}", e)))?;
+        let since = (ctx.storage.clock().now() - window).to_rfc3339();
+
+        let duplicates = ctx
+            .storage
+            .find_matching_transfers(
+                originator,
+                beneficiary,
+                &asset,
+                amount,
+                &since,
+                ctx.transaction_id,
+            )
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        let Some(earlier) = duplicates.first() else {
+            return Ok(None);
+        };
+
+        Ok(Some(PolicyOutcome {
+            rule: self.name.clone(),
+            action: self.action,
+            reason: format!(
+                "Likely duplicate of transaction {}: same parties, asset and amount within {}s",
+                earlier,
+                self.window.as_secs()
+            ),
+            details: json!({
+                "originator": originator,
+                "beneficiary": beneficiary,
+                "asset": asset,
+                "amount": amount,
+                "window_secs": self.window.as_secs(),
+                "duplicate_of": duplicates,
+            }),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Storage;
+    use crate::test_fixtures::{transfer_body, transfer_message};
+    use tap_msg::didcomm::PlainMessage;
+    use tap_msg::message::Transfer;
+
+    const SENDER: &str = "did:example:sender";
+
+    async fn evaluate(
+        rule: &DuplicateRule,
+        storage: &Storage,
+        message: &PlainMessage,
+    ) -> Option<PolicyOutcome> {
+        storage.insert_transaction(message).await.unwrap();
+        let tap_message = TapMessage::from_plain_message(message).unwrap();
+        rule.evaluate(&PolicyContext {
+            transaction_id: &message.id,
+            message,
+            tap_message: &tap_message,
+            storage,
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_rule_flags_repeated_transfers() {
+        let storage = Storage::new_in_memory().await.unwrap();
+        let rule = DuplicateRule::new("duplicates", Duration::from_secs(600));
+        let alice = "did:example:alice";
+        let bob = "did:example:bob";
+
+        let first = transfer_message("tx-1", &transfer_body(alice, bob, "100"), SENDER);
+        assert!(evaluate(&rule, &storage, &first).await.is_none());
+
+        // Another amount, beneficiary or a missing beneficiary is no duplicate
+        let other_amount = transfer_message("tx-2", &transfer_body(alice, bob, "100.5"), SENDER);
+        assert!(evaluate(&rule, &storage, &other_amount).await.is_none());
+        let other_beneficiary = transfer_message(
+            "tx-3",
+            &transfer_body(alice, "did:example:carol", "100"),
+            SENDER,
+        );
+        assert!(evaluate(&rule, &storage, &other_beneficiary)
+            .await
+            .is_none());
+        let no_beneficiary = transfer_message(
+            "tx-4",
+            &Transfer {
+                beneficiary: None,
+                ..transfer_body(alice, bob, "100")
+            },
+            SENDER,
+        );
+        assert!(evaluate(&rule, &storage, &no_beneficiary).await.is_none());
+
+        // The same amount written differently is
+        let repeated = transfer_message("tx-5", &transfer_body(alice, bob, "100.00"), SENDER);
+        let outcome = evaluate(&rule, &storage, &repeated).await.unwrap();
+        assert_eq!(outcome.action, PolicyAction::Warn);
+        assert_eq!(outcome.rule, "duplicates");
+        assert_eq!(outcome.details["duplicate_of"], json!(["tx-1"]));
+        assert!(outcome.reason.contains("tx-1"));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_rule_ignores_failed_transfers() {
+        let storage = Storage::new_in_memory().await.unwrap();
+        let rule = DuplicateRule::new("duplicates", Duration::from_secs(600))
+            .with_action(PolicyAction::ManualReview);
+        let alice = "did:example:alice";
+        let bob = "did:example:bob";
+
+        let rejected = transfer_message("tx-1", &transfer_body(alice, bob, "100"), SENDER);
+        assert!(evaluate(&rule, &storage, &rejected).await.is_none());
+        storage
+            .update_transaction_status("tx-1", "failed")
+            .await
+            .unwrap();
+
+        let resubmitted = transfer_message("tx-2", &transfer_body(alice, bob, "100"), SENDER);
+        assert!(evaluate(&rule, &storage, &resubmitted).await.is_none());
+
+        let duplicate = transfer_message("tx-3", &transfer_body(alice, bob, "100"), SENDER);
+        let outcome = evaluate(&rule, &storage, &duplicate).await.unwrap();
+        assert_eq!(outcome.action, PolicyAction::ManualReview);
+        assert_eq!(outcome.details["duplicate_of"], json!(["tx-2"]));
+    }
+}
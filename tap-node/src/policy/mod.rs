@@ -0,0 +1,203 @@
+//! Policy engine for transaction authorization
+//!
+//! The policy engine evaluates a set of [`PolicyRule`]s against an incoming
+//! Transfer or Payment at authorization time, i.e. when the state machine
+//! produces an `AuthorizationRequired` decision. Each rule that trips yields
+//! a [`PolicyOutcome`] carrying the [`PolicyAction`] to take.
+//!
+//! When any rule trips, the `StandardTransactionProcessor`:
+//!
+//! - publishes a [`NodeEvent::PolicyTriggered`](crate::event::NodeEvent::PolicyTriggered)
+//!   event for every outcome
+//! - attaches every outcome to the transaction as a warning (see
+//!   [`Storage::list_transaction_warnings`])
+//! - enforces the most severe action (see [`PolicyAction`])
+//! - skips automatic authorization, even in `AutoApprove` mode, unless the
+//!   rules that tripped only warn
+//!
+//! ## Sub-modules
+//!
+//! - [`velocity`]: Aggregate amount limits per party/counterparty over a
+//!   rolling time window.
+//! - [`duplicate`]: Transfers repeating an earlier Transfer within a short
+//!   window.
+//! - [`credential`]: Verifiable credentials the sending agent must present.
+//! - [`attestation`]: Key attestations binding the sending agent to an
+//!   organization.
+//! - [`presentation`]: Verifiable presentations received for the
+//!   transaction.
+//! - `script`: Rules written as scripts (requires the `scripting` feature).
+//! - [`simulation`]: Dry runs of the rules against hypothetical transactions.
+
+pub mod attestation;
+pub mod credential;
+pub mod duplicate;
+pub mod presentation;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod simulation;
+pub mod velocity;
+
+use crate::error::Result;
+use crate::storage::Storage;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::TapMessage;
+
+pub use attestation::KeyAttestationRule;
+pub use credential::CredentialRule;
+pub use duplicate::DuplicateRule;
+pub use presentation::PresentationRule;
+/// Decimal amount of velocity limits
+pub use rust_decimal::Decimal;
+#[cfg(feature = "scripting")]
+pub use script::ScriptRule;
+pub use simulation::PolicySimulation;
+pub use velocity::{VelocityRule, VelocityScope};
+
+/// Action to take when a policy rule trips
+///
+/// Variants are ordered by severity; when several rules trip, the most
+/// severe action is enforced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyAction {
+    /// Attach a warning to the transaction and let it proceed, including to
+    /// automatic authorization
+    Warn,
+    /// Ask the counterparty for a verifiable presentation by sending an
+    /// `UpdatePolicies` message with a `RequirePresentation` policy
+    RequirePresentation,
+    /// Hold the transaction in the review queue until a reviewer approves
+    /// or rejects it
+    ManualReview,
+    /// Reject the transaction on behalf of our agents
+    Reject,
+}
+
+impl fmt::Display for PolicyAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyAction::Warn => write!(f, "warn"),
+            PolicyAction::RequirePresentation => write!(f, "require_presentation"),
+            PolicyAction::ManualReview => write!(f, "manual_review"),
+            PolicyAction::Reject => write!(f, "reject"),
+        }
+    }
+}
+
+impl TryFrom<&str> for PolicyAction {
+    type Error = String;
+
+    fn try_from(s: &str) -> std::result::Result<Self, Self::Error> {
+        match s {
+            "warn" => Ok(PolicyAction::Warn),
+            "require_presentation" => Ok(PolicyAction::RequirePresentation),
+            "manual_review" => Ok(PolicyAction::ManualReview),
+            "reject" => Ok(PolicyAction::Reject),
+            _ => Err(format!("Invalid policy action: {}", s)),
+        }
+    }
+}
+
+impl std::str::FromStr for PolicyAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::try_from(s)
+    }
+}
+
+/// The result of a rule that tripped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyOutcome {
+    /// Name of the rule that tripped
+    pub rule: String,
+    /// Action requested by the rule
+    pub action: PolicyAction,
+    /// Human-readable reason, used as the Reject reason or presentation purpose
+    pub reason: String,
+    /// Rule-specific details (thresholds, observed values, etc.)
+    pub details: serde_json::Value,
+}
+
+/// Input available to policy rules
+pub struct PolicyContext<'a> {
+    /// The transaction being authorized
+    pub transaction_id: &'a str,
+    /// The plain message that created the transaction
+    pub message: &'a PlainMessage,
+    /// The parsed Transfer or Payment
+    pub tap_message: &'a TapMessage,
+    /// Storage holding the transaction history
+    pub storage: &'a Storage,
+}
+
+/// A rule evaluated by the [`PolicyEngine`]
+#[async_trait]
+pub trait PolicyRule: Send + Sync + fmt::Debug {
+    /// Name of the rule, reported in outcomes and events
+    fn name(&self) -> &str;
+
+    /// Evaluate the rule, returning an outcome if it trips
+    async fn evaluate(&self, ctx: &PolicyContext<'_>) -> Result<Option<PolicyOutcome>>;
+}
+
+/// Ordered collection of policy rules
+#[derive(Debug, Clone, Default)]
+pub struct PolicyEngine {
+    rules: Vec<Arc<dyn PolicyRule>>,
+}
+
+impl PolicyEngine {
+    /// Create an engine without rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a rule
+    pub fn with_rule<R: PolicyRule + 'static>(mut self, rule: R) -> Self {
+        self.rules.push(Arc::new(rule));
+        self
+    }
+
+    /// Add a shared rule
+    pub fn add_rule(&mut self, rule: Arc<dyn PolicyRule>) {
+        self.rules.push(rule);
+    }
+
+    /// Get the configured rules
+    pub fn rules(&self) -> &[Arc<dyn PolicyRule>] {
+        &self.rules
+    }
+
+    /// Evaluate all rules and return the outcomes of those that tripped
+    ///
+    /// A rule that fails to evaluate is logged and skipped.
+    pub async fn evaluate(&self, ctx: &PolicyContext<'_>) -> Vec<PolicyOutcome> {
+        let mut outcomes = Vec::new();
+        for rule in &self.rules {
+            match rule.evaluate(ctx).await {
+                Ok(Some(outcome)) => outcomes.push(outcome),
+                Ok(None) => {}
+                Err(e) => {
+                    log::warn!(
+                        "Policy rule {} failed for transaction {}: {}",
+                        rule.name(),
+                        ctx.transaction_id,
+                        e
+                    );
+                }
+            }
+        }
+        outcomes
+    }
+
+    /// The most severe action among a set of outcomes
+    pub fn strictest_action(outcomes: &[PolicyOutcome]) -> Option<PolicyAction> {
+        outcomes.iter().map(|o| o.action).max()
+    }
+}
@@ -0,0 +1,219 @@
+//! Presentation rules
+//!
+//! A [`PresentationRule`] acts on the verifiable presentations received for
+//! a transaction, typically in answer to a `RequirePresentation` policy.
+//! Presentations are verified on receipt (see
+//! [`NodeConfig::presentation_verification`](crate::NodeConfig::presentation_verification))
+//! and the rule reads the outcomes recorded for the transaction with
+//! [`Storage::list_presentation_verifications`](crate::storage::Storage::list_presentation_verifications).
+
+use super::{PolicyAction, PolicyContext, PolicyOutcome, PolicyRule};
+use crate::error::{Error, Result};
+use crate::storage::{PresentationVerificationRecord, PresentationVerificationStatus};
+use async_trait::async_trait;
+use serde_json::json;
+
+/// Requires the presentations received for a transaction to verify
+#[derive(Debug, Clone)]
+pub struct PresentationRule {
+    name: String,
+    require_verified: bool,
+    credential_type: Option<String>,
+    action: PolicyAction,
+}
+
+impl PresentationRule {
+    /// Create a presentation rule
+    ///
+    /// The rule trips when a presentation received for the transaction
+    /// failed verification.
+    pub fn new(name: impl Into<String>, action: PolicyAction) -> Self {
+        Self {
+            name: name.into(),
+            require_verified: false,
+            credential_type: None,
+            action,
+        }
+    }
+
+    /// Also trip when no presentation received for the transaction verified
+    pub fn require_verified(mut self) -> Self {
+        self.require_verified = true;
+        self
+    }
+
+    /// Trip unless a verified presentation holds a credential of this type
+    pub fn require_credential_type(mut self, credential_type: impl Into<String>) -> Self {
+        self.credential_type = Some(credential_type.into());
+        self.require_verified = true;
+        self
+    }
+
+    fn matches(&self, record: &PresentationVerificationRecord) -> bool {
+        record.status == PresentationVerificationStatus::Verified
+            && self.credential_type.as_ref().is_none_or(|t| {
+                record
+                    .credentials
+                    .iter()
+                    .any(|credential| credential.types.contains(t))
+            })
+    }
+}
+
+#[async_trait]
+impl PolicyRule for PresentationRule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn evaluate(&self, ctx: &PolicyContext<'_>) -> Result<Option<PolicyOutcome>> {
+        let records = ctx
+            .storage
+            .list_presentation_verifications(ctx.transaction_id)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+
+        let invalid = records
+            .iter()
+            .find(|record| record.status == PresentationVerificationStatus::Invalid);
+        let reason = if let Some(record) = invalid {
+            format!(
+                "Presentation from {} failed verification: {}",
+                record.sender_did,
+                record.reason.as_deref().unwrap_or("unknown reason")
+            )
+        } else if self.require_verified && !records.iter().any(|record| self.matches(record)) {
+            match &self.credential_type {
+                Some(t) => format!("No verified presentation holds a {} credential", t),
+                None => "No verified presentation was received".to_string(),
+            }
+        } else {
+            return Ok(None);
+        };
+
+        Ok(Some(PolicyOutcome {
+            rule: self.name.clone(),
+            action: self.action,
+            reason,
+            details: json!({
+                "credential_type": self.credential_type,
+                "presentations": records
+                    .iter()
+                    .map(|record| json!({
+                        "message_id": record.message_id,
+                        "status": record.status.to_string(),
+                        "reason": record.reason,
+                    }))
+                    .collect::<Vec<_>>(),
+            }),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::credentials::{PresentedCredential, RevocationStatus, VerifiedPresentationSummary};
+    use crate::storage::Storage;
+    use crate::test_fixtures::{transfer_body, transfer_message};
+    use tap_msg::didcomm::PlainMessage;
+    use tap_msg::message::TapMessage;
+
+    fn presentation_message(id: &str, transaction_id: &str) -> PlainMessage {
+        PlainMessage::new(
+            id.to_string(),
+            "https://tap.rsvp/schema/1.0#Presentation".to_string(),
+            json!({}),
+            "did:example:beneficiary-vasp".to_string(),
+        )
+        .with_thread_id(Some(transaction_id.to_string()))
+    }
+
+    async fn evaluate(
+        rule: &PresentationRule,
+        storage: &Storage,
+        message: &PlainMessage,
+    ) -> Option<PolicyOutcome> {
+        let tap_message = TapMessage::from_plain_message(message).unwrap();
+        rule.evaluate(&PolicyContext {
+            transaction_id: &message.id,
+            message,
+            tap_message: &tap_message,
+            storage,
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_presentation_rule() {
+        let storage = Storage::new_in_memory().await.unwrap();
+        let transfer = transfer_message(
+            "tx-1",
+            &transfer_body("did:example:alice", "did:example:bob", "10"),
+            "did:example:originator-vasp",
+        );
+        let any = PresentationRule::new("presented", PolicyAction::ManualReview);
+        let verified =
+            PresentationRule::new("verified", PolicyAction::ManualReview).require_verified();
+        let travel_rule = PresentationRule::new("travel-rule", PolicyAction::Reject)
+            .require_credential_type("TravelRuleCredential");
+
+        // Nothing received yet
+        assert!(evaluate(&any, &storage, &transfer).await.is_none());
+        let outcome = evaluate(&verified, &storage, &transfer).await.unwrap();
+        assert_eq!(outcome.reason, "No verified presentation was received");
+
+        // A verified presentation holding a license
+        let summary = VerifiedPresentationSummary {
+            holder: Some("did:example:beneficiary-vasp".to_string()),
+            credentials: vec![PresentedCredential {
+                issuer: "did:example:regulator".to_string(),
+                subject: Some("did:example:beneficiary-vasp".to_string()),
+                types: vec![
+                    "VerifiableCredential".to_string(),
+                    "VASPLicense".to_string(),
+                ],
+                expires_at: None,
+                revocation: RevocationStatus::NoStatus,
+            }],
+        };
+        storage
+            .insert_presentation_verification(&presentation_message("p-1", "tx-1"), 0, Ok(&summary))
+            .await
+            .unwrap();
+        assert!(evaluate(&verified, &storage, &transfer).await.is_none());
+        let outcome = evaluate(&travel_rule, &storage, &transfer).await.unwrap();
+        assert_eq!(outcome.action, PolicyAction::Reject);
+        assert!(outcome.reason.contains("TravelRuleCredential"));
+
+        // Any presentation that failed verification trips every rule
+        storage
+            .insert_presentation_verification(
+                &presentation_message("p-2", "tx-1"),
+                0,
+                Err("Credential has expired"),
+            )
+            .await
+            .unwrap();
+        let outcome = evaluate(&any, &storage, &transfer).await.unwrap();
+        assert!(outcome.reason.contains("Credential has expired"));
+        assert_eq!(
+            outcome.details["presentations"].as_array().unwrap().len(),
+            2
+        );
+
+        // Presentations of other transactions are ignored
+        assert!(evaluate(
+            &any,
+            &storage,
+            &transfer_message(
+                "tx-2",
+                &transfer_body("did:example:alice", "did:example:bob", "10"),
+                "did:example:originator-vasp"
+            )
+        )
+        .await
+        .is_none());
+    }
+}
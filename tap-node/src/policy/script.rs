@@ -0,0 +1,74 @@
+//! Script rules
+//!
+//! A [`ScriptRule`] evaluates the `policy` function of a
+//! [`ScriptHook`](crate::scripting::ScriptHook). The function returns a map
+//! with the `action` to take and a `reason`, or nothing when the transaction
+//! passes. Other keys in the map are reported as the outcome details.
+//!
+//! ```text
+//! fn policy(message) {
+//!     if message.body.asset.starts_with("eip155:1/") && parse_float(message.body.amount) > 10000.0 {
+//!         return #{ action: "manual_review", reason: "Large mainnet transfer" };
+//!     }
+//! }
+//! ```
+
+use super::{PolicyAction, PolicyContext, PolicyOutcome, PolicyRule};
+use crate::error::{Error, Result};
+use crate::scripting::ScriptHook;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Policy rule evaluated by a script
+#[derive(Debug, Clone)]
+pub struct ScriptRule {
+    name: String,
+    hook: Arc<ScriptHook>,
+}
+
+impl ScriptRule {
+    /// Create a rule from the `policy` function of a script
+    pub fn new(hook: Arc<ScriptHook>) -> Self {
+        Self {
+            name: format!("script:{}", hook.name()),
+            hook,
+        }
+    }
+}
+
+#[async_trait]
+impl PolicyRule for ScriptRule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn evaluate(&self, ctx: &PolicyContext<'_>) -> Result<Option<PolicyOutcome>> {
+        let Some(mut result) = self.hook.policy(ctx.transaction_id, ctx.message)? else {
+            return Ok(None);
+        };
+
+        let Some(map) = result.as_object_mut() else {
+            return Err(Error::Script(format!(
+                "policy in {} must return a map, not {}",
+                self.hook.name(),
+                result
+            )));
+        };
+        let action = map
+            .remove("action")
+            .and_then(|a| a.as_str().map(PolicyAction::try_from))
+            .ok_or_else(|| Error::Script("policy result is missing an action".to_string()))?
+            .map_err(Error::Script)?;
+        let reason = map
+            .remove("reason")
+            .and_then(|r| r.as_str().map(String::from))
+            .unwrap_or_else(|| format!("Flagged by {}", self.name));
+
+        Ok(Some(PolicyOutcome {
+            rule: self.name.clone(),
+            action,
+            reason,
+            details: result,
+        }))
+    }
+}
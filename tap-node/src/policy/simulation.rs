@@ -0,0 +1,174 @@
+//! Dry runs of the policy rules against hypothetical transactions
+//!
+//! [`TapNode::simulate_policies`] evaluates the configured rules against a
+//! candidate Transfer or Payment the way the state machine would when the
+//! transaction requires authorization, and reports which rules trip, the
+//! action that would be enforced and what would have to happen next. Nothing
+//! is persisted, no event is published and no message is sent, so it can be
+//! used to tune thresholds or explain why a transaction was held or rejected.
+
+use super::{PolicyAction, PolicyContext, PolicyEngine, PolicyOutcome};
+use crate::error::{Error, Result};
+#[cfg(feature = "state-machine")]
+use crate::state_machine::fsm::DecisionMode;
+use crate::TapNode;
+use serde::{Deserialize, Serialize};
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::TapMessage;
+
+/// What the policy rules would do with a transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicySimulation {
+    /// The transaction evaluated
+    pub transaction_id: String,
+    /// Outcomes of the rules that would trip, in rule order
+    pub outcomes: Vec<PolicyOutcome>,
+    /// Action that would be enforced, or None if no rule trips and the
+    /// transaction would proceed to authorization
+    pub action: Option<PolicyAction>,
+    /// What would have to happen for the transaction to proceed
+    pub follow_ups: Vec<String>,
+}
+
+impl PolicySimulation {
+    /// The resulting decision: the enforced action, or "authorize"
+    pub fn decision(&self) -> String {
+        self.action
+            .map(|action| action.to_string())
+            .unwrap_or_else(|| "authorize".to_string())
+    }
+}
+
+impl PolicyEngine {
+    /// Evaluate all rules without enforcing their outcomes
+    pub async fn simulate(&self, ctx: &PolicyContext<'_>) -> PolicySimulation {
+        let outcomes = self.evaluate(ctx).await;
+        let action = Self::strictest_action(&outcomes);
+        let follow_ups = match action {
+            Some(action) => outcomes
+                .iter()
+                .filter(|outcome| outcome.action == action)
+                .map(|outcome| match action {
+                    PolicyAction::Warn => format!(
+                        "None: a warning would be attached to the transaction ({})",
+                        outcome.reason
+                    ),
+                    PolicyAction::RequirePresentation => format!(
+                        "The originator must present a verifiable credential ({}) requested with UpdatePolicies",
+                        outcome.reason
+                    ),
+                    PolicyAction::ManualReview => format!(
+                        "A reviewer must approve the transaction in the review queue ({})",
+                        outcome.reason
+                    ),
+                    PolicyAction::Reject => format!(
+                        "None: our agents would send Reject with code policy ({})",
+                        outcome.reason
+                    ),
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        PolicySimulation {
+            transaction_id: ctx.transaction_id.to_string(),
+            outcomes,
+            action,
+            follow_ups,
+        }
+    }
+}
+
+impl TapNode {
+    /// Evaluate the node's policy rules against a candidate Transfer or
+    /// Payment without persisting, publishing or sending anything
+    ///
+    /// The transaction history used by rules such as velocity limits is the
+    /// node's storage; the candidate itself is not part of it.
+    pub async fn simulate_policies(&self, message: &PlainMessage) -> Result<PolicySimulation> {
+        let storage = self
+            .storage()
+            .ok_or_else(|| Error::Storage("Storage is not initialized".to_string()))?;
+        let tap_message = TapMessage::from_plain_message(message)
+            .map_err(|e| Error::InvalidPlainMessage(e.to_string()))?;
+        if !matches!(
+            tap_message,
+            TapMessage::Transfer(_) | TapMessage::Payment(_)
+        ) {
+            return Err(Error::Validation(format!(
+                "Policies apply to Transfer and Payment messages, not {}",
+                message.type_
+            )));
+        }
+
+        let ctx = PolicyContext {
+            transaction_id: &message.id,
+            message,
+            tap_message: &tap_message,
+            storage,
+        };
+        #[cfg_attr(not(feature = "state-machine"), allow(unused_mut))]
+        let mut simulation = match &self.config().policy_engine {
+            Some(policy_engine) => policy_engine.simulate(&ctx).await,
+            None => PolicyEngine::new().simulate(&ctx).await,
+        };
+        #[cfg(feature = "state-machine")]
+        if matches!(simulation.action, None | Some(PolicyAction::Warn)) {
+            simulation
+                .follow_ups
+                .push(match self.config().decision_mode {
+                    DecisionMode::AutoApprove => {
+                        "None: our agents would send Authorize automatically".to_string()
+                    }
+                    _ => "An authorization_required decision would be raised for our agents"
+                        .to_string(),
+                });
+        }
+        Ok(simulation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::{Decimal, VelocityRule, VelocityScope};
+    use crate::storage::Storage;
+    use crate::test_fixtures::{transfer_body, transfer_message};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_simulate_reports_tripped_rules_without_storing() {
+        let storage = Storage::new(Some(":memory:".into())).await.unwrap();
+        let engine = PolicyEngine::new().with_rule(VelocityRule::new(
+            "daily-limit",
+            VelocityScope::Party,
+            Decimal::from(1000),
+            Duration::from_secs(86_400),
+            PolicyAction::ManualReview,
+        ));
+
+        for (amount, expected) in [("10", None), ("5000", Some(PolicyAction::ManualReview))] {
+            let message = transfer_message(
+                "candidate",
+                &transfer_body("did:example:alice", "did:example:bob", amount),
+                "did:example:originator-vasp",
+            );
+            let tap_message = TapMessage::from_plain_message(&message).unwrap();
+            let simulation = engine
+                .simulate(&PolicyContext {
+                    transaction_id: &message.id,
+                    message: &message,
+                    tap_message: &tap_message,
+                    storage: &storage,
+                })
+                .await;
+            assert_eq!(simulation.action, expected);
+            assert_eq!(simulation.outcomes.len(), expected.iter().count());
+        }
+        assert!(storage
+            .get_transaction_by_id("candidate")
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
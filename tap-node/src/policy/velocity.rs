@@ -0,0 +1,384 @@
+//! Velocity rules
+//!
+//! A [`VelocityRule`] limits the aggregate amount moved by a party, to a
+//! counterparty, or between a specific pair within a rolling time window.
+//! For Transfers the party is the originator and the counterparty the
+//! beneficiary; for Payments they are the customer and the merchant.
+//!
+//! The aggregate is the amount the node has sent: Transfers and Payments
+//! received from other agents do not count towards it. Amounts are only
+//! aggregated within the asset (or currency) of the transaction being
+//! evaluated, and are added up as exact decimals, like the decimal strings
+//! TAP messages carry them as. Failed, cancelled and reverted transactions
+//! do not count towards the limit.
+
+use super::{PolicyAction, PolicyContext, PolicyOutcome, PolicyRule};
+use crate::error::{Error, Result};
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+use tap_msg::message::TapMessage;
+
+/// Which transactions are aggregated together
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VelocityScope {
+    /// All transactions sent by the same party
+    Party,
+    /// All transactions sent to the same counterparty
+    Counterparty,
+    /// Transactions between the same party and counterparty
+    PartyAndCounterparty,
+}
+
+/// Maximum aggregate amount per rolling time window
+#[derive(Debug, Clone)]
+pub struct VelocityRule {
+    name: String,
+    scope: VelocityScope,
+    asset: Option<String>,
+    max_amount: Decimal,
+    window: Duration,
+    action: PolicyAction,
+}
+
+impl VelocityRule {
+    /// Create a velocity rule
+    ///
+    /// The rule trips when the transaction being evaluated brings the total
+    /// for its scope above `max_amount` within `window`.
+    pub fn new(
+        name: impl Into<String>,
+        scope: VelocityScope,
+        max_amount: Decimal,
+        window: Duration,
+        action: PolicyAction,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            scope,
+            asset: None,
+            max_amount,
+            window,
+            action,
+        }
+    }
+
+    /// Only apply the rule to transactions in this asset (CAIP-19) or currency code
+    pub fn for_asset(mut self, asset: impl Into<String>) -> Self {
+        self.asset = Some(asset.into());
+        self
+    }
+}
+
+/// Party, counterparty, asset and amount of a Transfer or Payment
+pub(crate) struct TransactionAmount {
+    pub(crate) party: Option<String>,
+    pub(crate) counterparty: Option<String>,
+    pub(crate) asset: Option<String>,
+    pub(crate) amount: Decimal,
+}
+
+impl TransactionAmount {
+    pub(crate) fn from_tap_message(tap_message: &TapMessage) -> Result<Option<Self>> {
+        let (party, counterparty, asset, amount) = match tap_message {
+            TapMessage::Transfer(transfer) => (
+                transfer.originator.as_ref().map(|p| p.id.clone()),
+                transfer.beneficiary.as_ref().map(|p| p.id.clone()),
+                Some(transfer.asset.to_string()),
+                &transfer.amount,
+            ),
+            TapMessage::Payment(payment) => (
+                payment.customer.as_ref().map(|p| p.id.clone()),
+                Some(payment.merchant.id.clone()),
+                payment
+                    .asset
+                    .as_ref()
+                    .map(|a| a.to_string())
+                    .or_else(|| payment.currency_code.clone()),
+                &payment.amount,
+            ),
+            _ => return Ok(None),
+        };
+
+        let amount = Decimal::from_str_exact(amount)
+            .map_err(|e| Error::Validation(format!("Invalid amount '{}': {}", amount, e)))?;
+
+        Ok(Some(Self {
+            party,
+            counterparty,
+            asset,
+            amount,
+        }))
+    }
+}
+
+#[async_trait]
+impl PolicyRule for VelocityRule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn evaluate(&self, ctx: &PolicyContext<'_>) -> Result<Option<PolicyOutcome>> {
+        let current = match TransactionAmount::from_tap_message(ctx.tap_message)? {
+            Some(current) => current,
+            None => return Ok(None),
+        };
+
+        if self.asset.is_some() && current.asset != self.asset {
+            return Ok(None);
+        }
+
+        let (party, counterparty) = match self.scope {
+            VelocityScope::Party => (current.party.as_deref(), None),
+            VelocityScope::Counterparty => (None, current.counterparty.as_deref()),
+            VelocityScope::PartyAndCounterparty => {
+                (current.party.as_deref(), current.counterparty.as_deref())
+            }
+        };
+        let scope_resolved = match self.scope {
+            VelocityScope::Party => party.is_some(),
+            VelocityScope::Counterparty => counterparty.is_some(),
+            VelocityScope::PartyAndCounterparty => party.is_some() && counterparty.is_some(),
+        };
+        if !scope_resolved {
+            return Ok(None);
+        }
+
+        let window = chrono::Duration::from_std(self.window)
+            .map_err(|e| Error::Configuration(format!("Invalid velocity window: {}", e)))?;
+        let since = (ctx.storage.clock().now() - window).to_rfc3339();
+
+        let (previous_total, previous_count) = ctx
+            .storage
+            .sum_transaction_amounts(
+                party,
+                counterparty,
+                current.asset.as_deref(),
+                &since,
+                Some(ctx.transaction_id),
+            )
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+
+        let aggregate = previous_total
+            .checked_add(current.amount)
+            .ok_or_else(|| Error::Validation("Aggregate amount overflows".to_string()))?;
+        if aggregate <= self.max_amount {
+            return Ok(None);
+        }
+
+        Ok(Some(PolicyOutcome {
+            rule: self.name.clone(),
+            action: self.action,
+            reason: format!(
+                "Velocity limit exceeded: {} in the last {}s exceeds limit of {}",
+                aggregate,
+                self.window.as_secs(),
+                self.max_amount
+            ),
+            details: json!({
+                "scope": self.scope,
+                "party": party,
+                "counterparty": counterparty,
+                "asset": current.asset,
+                "amount": current.amount,
+                "aggregate_amount": aggregate,
+                "max_amount": self.max_amount,
+                "window_secs": self.window.as_secs(),
+                "previous_transactions": previous_count,
+            }),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{MessageDirection, Storage};
+    use crate::test_fixtures::{transfer_body, transfer_message};
+    use tap_msg::didcomm::PlainMessage;
+
+    const SENDER: &str = "did:example:sender";
+
+    /// Evaluate a rule for a message, as the node does for the Transfers it
+    /// receives, then record it as sent by the node
+    async fn evaluate(
+        rule: &VelocityRule,
+        storage: &Storage,
+        message: &PlainMessage,
+    ) -> Option<PolicyOutcome> {
+        evaluate_in(rule, storage, message, MessageDirection::Outgoing).await
+    }
+
+    async fn evaluate_in(
+        rule: &VelocityRule,
+        storage: &Storage,
+        message: &PlainMessage,
+        direction: MessageDirection,
+    ) -> Option<PolicyOutcome> {
+        storage.log_message(message, direction).await.unwrap();
+        storage.insert_transaction(message).await.unwrap();
+        let tap_message = TapMessage::from_plain_message(message).unwrap();
+        rule.evaluate(&PolicyContext {
+            transaction_id: &message.id,
+            message,
+            tap_message: &tap_message,
+            storage,
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_velocity_rule_trips_on_aggregate() {
+        let storage = Storage::new_in_memory().await.unwrap();
+        let rule = VelocityRule::new(
+            "daily-originator-limit",
+            VelocityScope::Party,
+            Decimal::from(1000),
+            Duration::from_secs(86400),
+            PolicyAction::ManualReview,
+        );
+
+        let first = transfer_message(
+            "tx-1",
+            &transfer_body("did:example:alice", "did:example:bob", "600"),
+            SENDER,
+        );
+        assert!(evaluate(&rule, &storage, &first).await.is_none());
+
+        // Different originator is tracked separately
+        let other = transfer_message(
+            "tx-2",
+            &transfer_body("did:example:carol", "did:example:bob", "900"),
+            SENDER,
+        );
+        assert!(evaluate(&rule, &storage, &other).await.is_none());
+
+        let second = transfer_message(
+            "tx-3",
+            &transfer_body("did:example:alice", "did:example:dave", "500"),
+            SENDER,
+        );
+        let outcome = evaluate(&rule, &storage, &second).await.unwrap();
+        assert_eq!(outcome.action, PolicyAction::ManualReview);
+        assert_eq!(outcome.rule, "daily-originator-limit");
+        assert_eq!(outcome.details["aggregate_amount"], "1100");
+        assert_eq!(outcome.details["previous_transactions"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_velocity_rule_ignores_failed_and_other_scopes() {
+        let storage = Storage::new_in_memory().await.unwrap();
+        let rule = VelocityRule::new(
+            "pair-limit",
+            VelocityScope::PartyAndCounterparty,
+            Decimal::from(1000),
+            Duration::from_secs(3600),
+            PolicyAction::Reject,
+        );
+
+        let rejected = transfer_message(
+            "tx-1",
+            &transfer_body("did:example:alice", "did:example:bob", "900"),
+            SENDER,
+        );
+        assert!(evaluate(&rule, &storage, &rejected).await.is_none());
+        storage
+            .update_transaction_status("tx-1", "failed")
+            .await
+            .unwrap();
+
+        let other_pair = transfer_message(
+            "tx-2",
+            &transfer_body("did:example:alice", "did:example:dave", "900"),
+            SENDER,
+        );
+        assert!(evaluate(&rule, &storage, &other_pair).await.is_none());
+
+        let same_pair = transfer_message(
+            "tx-3",
+            &transfer_body("did:example:alice", "did:example:bob", "200"),
+            SENDER,
+        );
+        assert!(evaluate(&rule, &storage, &same_pair).await.is_none());
+
+        let over = transfer_message(
+            "tx-4",
+            &transfer_body("did:example:alice", "did:example:bob", "850"),
+            SENDER,
+        );
+        let outcome = evaluate(&rule, &storage, &over).await.unwrap();
+        assert_eq!(outcome.action, PolicyAction::Reject);
+
+        // Rules restricted to another asset do not apply
+        let usdc_only = VelocityRule::new(
+            "usdc",
+            VelocityScope::Party,
+            Decimal::ONE,
+            Duration::from_secs(3600),
+            PolicyAction::Reject,
+        )
+        .for_asset("eip155:1/erc20:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let message = transfer_message(
+            "tx-5",
+            &transfer_body("did:example:alice", "did:example:bob", "5"),
+            SENDER,
+        );
+        assert!(evaluate(&usdc_only, &storage, &message).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_velocity_rule_counts_outgoing_amounts_exactly() {
+        let storage = Storage::new_in_memory().await.unwrap();
+        let rule = VelocityRule::new(
+            "daily-originator-limit",
+            VelocityScope::Party,
+            Decimal::new(3, 1),
+            Duration::from_secs(86400),
+            PolicyAction::Reject,
+        );
+
+        // Transfers received from other agents are not sent by the node
+        let received = transfer_message(
+            "tx-1",
+            &transfer_body("did:example:alice", "did:example:bob", "900"),
+            SENDER,
+        );
+        assert!(
+            evaluate_in(&rule, &storage, &received, MessageDirection::Incoming)
+                .await
+                .is_some()
+        );
+
+        // 0.1 + 0.2 is exactly at the limit, not above it
+        let first = transfer_message(
+            "tx-2",
+            &transfer_body("did:example:alice", "did:example:bob", "0.1"),
+            SENDER,
+        );
+        assert!(evaluate(&rule, &storage, &first).await.is_none());
+        let second = transfer_message(
+            "tx-3",
+            &transfer_body("did:example:alice", "did:example:bob", "0.2"),
+            SENDER,
+        );
+        assert!(evaluate(&rule, &storage, &second).await.is_none());
+
+        let third = transfer_message(
+            "tx-4",
+            &transfer_body(
+                "did:example:alice",
+                "did:example:bob",
+                "0.000000000000000001",
+            ),
+            SENDER,
+        );
+        let outcome = evaluate(&rule, &storage, &third).await.unwrap();
+        assert_eq!(outcome.details["aggregate_amount"], "0.300000000000000001");
+        assert_eq!(outcome.details["previous_transactions"], 2);
+    }
+}
@@ -0,0 +1,390 @@
+//! Startup reconciliation of interrupted processing
+//!
+//! A node that stops in the middle of processing a message leaves its records
+//! half way: a received message stays `pending`, a delivery to one of our
+//! agents is created but never marked delivered, an external delivery whose
+//! attempt was cut short is neither failed nor retried, a transaction that all
+//! agents authorized is never settled, and the decisions of a transaction that
+//! ended stay open. [`TapNode::reconcile`] finds these records and re-drives
+//! them where it can, flags them where it cannot, and publishes the outcome as
+//! a [`NodeEvent::ReconciliationCompleted`] event.
+//!
+//! Only received messages and deliveries recorded before the node was created
+//! are considered, so processing that is under way is left alone.
+//! [`TapNode::init_storage`] reconciles the node's storage and the agents it
+//! registers from stored keys; a node that registers its agents afterwards
+//! calls [`TapNode::reconcile`] once they are registered.
+
+#[cfg(feature = "delivery-tracking")]
+use crate::delivery;
+use crate::error::{Error, Result};
+use crate::event::NodeEvent;
+use crate::storage::{DecisionStatus, ReceivedStatus, Storage, TransactionStatus};
+#[cfg(feature = "delivery-tracking")]
+use crate::storage::{DeliveryStatus, DeliveryType};
+use crate::TapNode;
+use serde::Serialize;
+use std::collections::HashSet;
+#[cfg(feature = "delivery-tracking")]
+use tap_agent::Agent;
+#[cfg(feature = "delivery-tracking")]
+use tap_msg::didcomm::PlainMessage;
+use tracing::{info, warn};
+
+/// Number of records of each kind reconciled per database and pass
+const RECONCILE_BATCH_SIZE: u32 = 1000;
+
+/// A record that reconciliation could not repair
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FlaggedRecord {
+    /// Agent whose database holds the record, None for the node's database
+    pub agent_did: Option<String>,
+    /// The record, e.g. `delivery 12` or `transaction 3f2a...`
+    pub record: String,
+    /// Why it was left as it is
+    pub reason: String,
+}
+
+/// Outcome of a reconciliation pass
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ReconciliationReport {
+    /// Received messages whose processing was interrupted and that were
+    /// processed again
+    pub reprocessed_messages: u64,
+    /// Deliveries to our agents that were made again
+    pub redelivered_messages: u64,
+    /// Interrupted external deliveries that were marked failed, and
+    /// scheduled for retry under the node's retry policy
+    pub requeued_deliveries: u64,
+    /// Transactions authorized by all agents whose settlement was resumed
+    pub resumed_settlements: u64,
+    /// Open decisions of transactions that already ended, now expired
+    pub expired_decisions: u64,
+    /// Authorization challenges past their expiry, now expired
+    pub expired_challenges: u64,
+    /// Records that need an operator
+    pub flagged: Vec<FlaggedRecord>,
+}
+
+impl ReconciliationReport {
+    /// Whether the pass found nothing to reconcile
+    pub fn is_clean(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn flag(&mut self, agent_did: Option<&str>, record: String, reason: impl Into<String>) {
+        self.flagged.push(FlaggedRecord {
+            agent_did: agent_did.map(String::from),
+            record,
+            reason: reason.into(),
+        });
+    }
+}
+
+impl TapNode {
+    /// Re-drive or flag processing that was interrupted before the node was
+    /// created
+    ///
+    /// See the [module documentation](crate::reconciliation). The report is
+    /// also published as a [`NodeEvent::ReconciliationCompleted`] event.
+    pub async fn reconcile(&self) -> Result<ReconciliationReport> {
+        let mut report = ReconciliationReport::default();
+
+        if let Some(storage) = self.storage.clone() {
+            self.reconcile_transactions(&storage, &mut report).await?;
+        }
+
+        if let Some(manager) = self.agent_storage_manager.clone() {
+            let mut reprocessed = HashSet::new();
+            for agent_did in self.agents.get_all_dids() {
+                let Some(storage) = manager.get_existing_agent_storage(&agent_did).await? else {
+                    continue;
+                };
+                #[cfg(feature = "delivery-tracking")]
+                self.reconcile_deliveries(&agent_did, &storage, &mut report)
+                    .await?;
+                self.reconcile_received(&agent_did, &storage, &mut reprocessed, &mut report)
+                    .await?;
+            }
+        }
+
+        if report.is_clean() {
+            info!("Reconciliation found no interrupted processing");
+        } else {
+            info!(
+                "Reconciliation reprocessed {} messages, redelivered {}, requeued {} deliveries, \
+                 resumed {} settlements and flagged {} records",
+                report.reprocessed_messages,
+                report.redelivered_messages,
+                report.requeued_deliveries,
+                report.resumed_settlements,
+                report.flagged.len()
+            );
+        }
+        self.event_bus
+            .publish_event(NodeEvent::ReconciliationCompleted {
+                reprocessed_messages: report.reprocessed_messages,
+                redelivered_messages: report.redelivered_messages,
+                requeued_deliveries: report.requeued_deliveries,
+                resumed_settlements: report.resumed_settlements,
+                expired_decisions: report.expired_decisions,
+                expired_challenges: report.expired_challenges,
+                flagged: serde_json::to_value(&report.flagged).unwrap_or_default(),
+            })
+            .await;
+
+        Ok(report)
+    }
+
+    /// Resume settlements and close decisions and challenges left open
+    async fn reconcile_transactions(
+        &self,
+        storage: &Storage,
+        report: &mut ReconciliationReport,
+    ) -> Result<()> {
+        report.expired_challenges = storage
+            .expire_authorization_challenges()
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+
+        let open = storage
+            .list_decisions(
+                None,
+                Some(DecisionStatus::Pending),
+                None,
+                RECONCILE_BATCH_SIZE,
+            )
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        let mut ended = HashSet::new();
+        for decision in open {
+            if ended.contains(&decision.transaction_id) {
+                continue;
+            }
+            let transaction = storage
+                .get_transaction_by_id(&decision.transaction_id)
+                .await
+                .map_err(|e| Error::Storage(e.to_string()))?;
+            if transaction.is_some_and(|t| t.status != TransactionStatus::Pending) {
+                report.expired_decisions += storage
+                    .expire_decisions_for_transaction(&decision.transaction_id)
+                    .await
+                    .map_err(|e| Error::Storage(e.to_string()))?;
+                storage
+                    .expire_review_items_for_transaction(&decision.transaction_id)
+                    .await
+                    .map_err(|e| Error::Storage(e.to_string()))?;
+                ended.insert(decision.transaction_id);
+            }
+        }
+
+        #[cfg(feature = "state-machine")]
+        self.resume_settlements(storage, report).await?;
+        Ok(())
+    }
+
+    /// Settle the authorized transactions of our agents left unsettled
+    #[cfg(feature = "state-machine")]
+    async fn resume_settlements(
+        &self,
+        storage: &Storage,
+        report: &mut ReconciliationReport,
+    ) -> Result<()> {
+        // Without automatic settlement, settling is left to the operator
+        let Some(state_processor) = self
+            .state_processor
+            .as_ref()
+            .filter(|processor| processor.auto_acts())
+        else {
+            return Ok(());
+        };
+        let authorized = storage
+            .list_authorized_pending_transactions(RECONCILE_BATCH_SIZE)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        for transaction in authorized {
+            let ours = transaction
+                .from_did
+                .as_deref()
+                .is_some_and(|did| self.agents.has_agent(did));
+            if !ours {
+                continue;
+            }
+            let record = format!("transaction {}", transaction.reference_id);
+            if let Err(e) = state_processor
+                .check_and_send_settle(&transaction.reference_id)
+                .await
+            {
+                report.flag(None, record, format!("Settlement failed: {}", e));
+                continue;
+            }
+            let settled = storage
+                .get_transaction_by_id(&transaction.reference_id)
+                .await
+                .map_err(|e| Error::Storage(e.to_string()))?
+                .is_some_and(|t| t.status == TransactionStatus::Confirmed);
+            if settled {
+                report.resumed_settlements += 1;
+            } else {
+                report.flag(None, record, "Authorized but names no agent to settle with");
+            }
+        }
+        Ok(())
+    }
+
+    /// Re-drive deliveries that were created but never completed
+    #[cfg(feature = "delivery-tracking")]
+    async fn reconcile_deliveries(
+        &self,
+        agent_did: &str,
+        storage: &Storage,
+        report: &mut ReconciliationReport,
+    ) -> Result<()> {
+        // Deliveries record their creation in SQLite's timestamp format
+        let cutoff = self.created_at.format("%Y-%m-%d %H:%M:%S").to_string();
+        let pending = storage
+            .get_pending_deliveries(i32::MAX, RECONCILE_BATCH_SIZE)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        for delivery in pending
+            .into_iter()
+            .filter(|delivery| delivery.created_at < cutoff)
+        {
+            let record = format!("delivery {}", delivery.id);
+            match delivery.delivery_type {
+                DeliveryType::Internal => {
+                    let outcome = match (
+                        self.agents.get_agent(&delivery.recipient_did).await,
+                        serde_json::from_str::<PlainMessage>(&delivery.message_text),
+                    ) {
+                        (Ok(agent), Ok(message)) => agent
+                            .receive_plain_message(message)
+                            .await
+                            .map_err(|e| e.to_string()),
+                        (Err(_), _) => Err(format!(
+                            "Recipient {} is not registered",
+                            delivery.recipient_did
+                        )),
+                        (_, Err(e)) => Err(format!("Message cannot be read: {}", e)),
+                    };
+                    let (status, error) = match &outcome {
+                        Ok(()) => (DeliveryStatus::Success, None),
+                        Err(e) => (DeliveryStatus::Failed, Some(e.as_str())),
+                    };
+                    storage
+                        .update_delivery_status(delivery.id, status, None, error)
+                        .await
+                        .map_err(|e| Error::Storage(e.to_string()))?;
+                    match outcome {
+                        Ok(()) => report.redelivered_messages += 1,
+                        Err(e) => report.flag(Some(agent_did), record, e),
+                    }
+                }
+                DeliveryType::Https => {
+                    storage
+                        .update_delivery_status(
+                            delivery.id,
+                            DeliveryStatus::Failed,
+                            None,
+                            Some("Delivery attempt interrupted"),
+                        )
+                        .await
+                        .map_err(|e| Error::Storage(e.to_string()))?;
+                    storage
+                        .increment_delivery_retry_count(delivery.id)
+                        .await
+                        .map_err(|e| Error::Storage(e.to_string()))?;
+                    let scheduled = match &self.config.delivery_retry {
+                        Some(policy) => {
+                            delivery::schedule_retry(storage, delivery.id, policy, &*self.clock())
+                                .await?
+                        }
+                        None => None,
+                    };
+                    report.requeued_deliveries += 1;
+                    if scheduled.is_none() {
+                        report.flag(
+                            Some(agent_did),
+                            record,
+                            "Delivery attempt interrupted and not scheduled for retry",
+                        );
+                    }
+                }
+                // Return path and pickup deliveries are collected by the
+                // recipient, which asks again if it did not get them
+                DeliveryType::ReturnPath | DeliveryType::Pickup => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Process received messages again whose processing was interrupted
+    ///
+    /// A message received for several of our agents is processed once;
+    /// `reprocessed` holds the messages processed so far.
+    async fn reconcile_received(
+        &self,
+        agent_did: &str,
+        storage: &Storage,
+        reprocessed: &mut HashSet<String>,
+        report: &mut ReconciliationReport,
+    ) -> Result<()> {
+        let cutoff = self.created_at.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+        let pending = storage
+            .get_pending_received(RECONCILE_BATCH_SIZE)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        for received in pending
+            .into_iter()
+            .filter(|received| received.received_at < cutoff)
+        {
+            // The message is recorded again when it is processed
+            storage
+                .update_received_status(
+                    received.id,
+                    ReceivedStatus::Failed,
+                    None,
+                    Some("Processing interrupted, message processed again"),
+                )
+                .await
+                .map_err(|e| Error::Storage(e.to_string()))?;
+            if !reprocessed.insert(received.raw_message.clone()) {
+                continue;
+            }
+
+            let message = match serde_json::from_str(&received.raw_message) {
+                Ok(message) => message,
+                Err(e) => {
+                    report.flag(
+                        Some(agent_did),
+                        format!("received {}", received.id),
+                        format!("Message cannot be read: {}", e),
+                    );
+                    continue;
+                }
+            };
+            match self
+                .process_received(
+                    message,
+                    received.source_type,
+                    received.source_identifier.as_deref(),
+                )
+                .await
+            {
+                Ok(_) => report.reprocessed_messages += 1,
+                Err(e) => {
+                    warn!(
+                        "Reprocessing received message {} failed: {}",
+                        received.id, e
+                    );
+                    report.flag(
+                        Some(agent_did),
+                        format!("received {}", received.id),
+                        format!("Processing failed again: {}", e),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
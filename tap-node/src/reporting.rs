@@ -0,0 +1,384 @@
+//! Aggregate reports over stored transactions
+//!
+//! A [`RejectionReport`] counts rejected transactions by the standardized
+//! [`RejectionCode`] of the Reject message that failed them, reading through
+//! a [`ReadOnlyStorage`] handle so reporting does not hold connections that
+//! message processing needs. Reports of
+//! several storage instances, e.g. the node's and each agent's, combine with
+//! [`RejectionReport::merge`].
+//!
+//! An [`AuthorizationReport`] answers what was known about a transaction when
+//! each of its agents authorized it, by replaying the state event log up to
+//! every authorization. It requires event sourcing to have been enabled when
+//! the transaction was created.
+//!
+//! A [`ValuationReport`] lists the fiat values of transactions tagged at the
+//! time they were recorded (see [`valuation`](crate::valuation)), with totals
+//! per currency, and exports them as CSV for reconciliation.
+
+use crate::storage::{
+    ReadOnlyStorage, StateEvent, Storage, StorageError, TransactionStateAt, TransactionValuation,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tap_msg::message::RejectionCode;
+
+/// Rejected transactions by rejection code
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RejectionReport {
+    /// Number of rejected transactions
+    pub total: u64,
+    /// Number of rejected transactions per rejection code
+    pub by_code: BTreeMap<String, u64>,
+    /// Number of transactions rejected without a code
+    pub uncoded: u64,
+}
+
+impl RejectionReport {
+    /// Count the rejected transactions in a database
+    ///
+    /// `since` and `until` bound the creation time of the counted
+    /// transactions as RFC 3339 timestamps; `until` is exclusive.
+    pub async fn generate(
+        storage: &ReadOnlyStorage,
+        since: Option<&str>,
+        until: Option<&str>,
+    ) -> Result<Self, StorageError> {
+        let mut report = Self::default();
+        for (code, count) in storage.count_rejections_by_code(since, until).await? {
+            let count = count as u64;
+            report.total += count;
+            match code {
+                Some(code) => *report.by_code.entry(code).or_default() += count,
+                None => report.uncoded += count,
+            }
+        }
+        Ok(report)
+    }
+
+    /// Add the counts of another report
+    pub fn merge(&mut self, other: &RejectionReport) {
+        self.total += other.total;
+        self.uncoded += other.uncoded;
+        for (code, count) in &other.by_code {
+            *self.by_code.entry(code.clone()).or_default() += count;
+        }
+    }
+
+    /// Number of transactions rejected with a code
+    pub fn count(&self, code: RejectionCode) -> u64 {
+        self.by_code.get(code.as_str()).copied().unwrap_or(0)
+    }
+
+    /// Fraction of rejected transactions rejected with a code
+    pub fn share(&self, code: RejectionCode) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.count(code) as f64 / self.total as f64
+        }
+    }
+}
+
+/// The state of a transaction when an agent authorized it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthorizationSnapshot {
+    /// DID of the authorizing agent
+    pub agent_did: String,
+    /// When the authorization was recorded
+    pub authorized_at: String,
+    /// The transaction as known at the authorization, including it
+    pub state: TransactionStateAt,
+}
+
+/// What was known about a transaction at each of its authorizations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthorizationReport {
+    /// Reference ID of the transaction
+    pub transaction_id: String,
+    /// One snapshot per authorization, in the order they were recorded
+    pub authorizations: Vec<AuthorizationSnapshot>,
+}
+
+impl AuthorizationReport {
+    /// Replay the state event log of a transaction up to each authorization
+    pub async fn generate(storage: &Storage, transaction_id: &str) -> Result<Self, StorageError> {
+        let events = storage
+            .list_state_events(Some(transaction_id), 0, u32::MAX)
+            .await?;
+
+        let mut authorizations = Vec::new();
+        for (i, stored) in events.iter().enumerate() {
+            if let StateEvent::TransactionAgentStatusUpdated {
+                agent_did, status, ..
+            } = &stored.event
+            {
+                if status != "authorized" {
+                    continue;
+                }
+                let state = TransactionStateAt::replay(&events[..=i])?.ok_or_else(|| {
+                    StorageError::Replay(format!(
+                        "The event log does not cover the creation of transaction {}",
+                        transaction_id
+                    ))
+                })?;
+                authorizations.push(AuthorizationSnapshot {
+                    agent_did: agent_did.clone(),
+                    authorized_at: stored.recorded_at.clone(),
+                    state,
+                });
+            }
+        }
+
+        Ok(Self {
+            transaction_id: transaction_id.to_string(),
+            authorizations,
+        })
+    }
+}
+
+/// Fiat values of transactions at the rates they were recorded at
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ValuationReport {
+    /// Valued transactions, oldest first
+    pub transactions: Vec<TransactionValuation>,
+    /// Total fiat value per currency
+    pub totals: BTreeMap<String, f64>,
+}
+
+impl ValuationReport {
+    /// Collect the valuations in a database
+    ///
+    /// `since` and `until` bound the time the transactions were valued as
+    /// RFC 3339 timestamps; `until` is exclusive.
+    pub async fn generate(
+        storage: &ReadOnlyStorage,
+        since: Option<&str>,
+        until: Option<&str>,
+    ) -> Result<Self, StorageError> {
+        let mut report = Self::default();
+        for valuation in storage.list_transaction_valuations(since, until).await? {
+            report.add(valuation);
+        }
+        Ok(report)
+    }
+
+    /// Add the valuations of another report
+    pub fn merge(&mut self, other: &ValuationReport) {
+        for valuation in &other.transactions {
+            self.add(valuation.clone());
+        }
+        self.transactions
+            .sort_by(|a, b| a.created_at.cmp(&b.created_at));
+    }
+
+    fn add(&mut self, valuation: TransactionValuation) {
+        *self.totals.entry(valuation.currency.clone()).or_default() += valuation.fiat_amount;
+        self.transactions.push(valuation);
+    }
+
+    /// Export the valued transactions as CSV, one row per transaction
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "transaction_id,canonical_hash,asset,amount,currency,fiat_amount,rate,rate_source,rate_timestamp,recorded_at\n",
+        );
+        for v in &self.transactions {
+            let fields = [
+                v.transaction_id.clone(),
+                v.canonical_hash.clone().unwrap_or_default(),
+                v.asset.clone(),
+                v.amount.clone(),
+                v.currency.clone(),
+                v.fiat_amount.to_string(),
+                v.rate.to_string(),
+                v.rate_source.clone(),
+                v.rate_timestamp.clone(),
+                v.created_at.clone(),
+            ];
+            let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tap_msg::didcomm::PlainMessage;
+
+    async fn rejected(storage: &Storage, id: &str, code: Option<RejectionCode>) {
+        let transfer = PlainMessage::new(
+            id.to_string(),
+            "https://tap.rsvp/schema/1.0#Transfer".to_string(),
+            serde_json::json!({ "asset": "eip155:1/slip44:60", "amount": "1" }),
+            "did:example:originator".to_string(),
+        )
+        .with_recipient("did:example:beneficiary");
+        storage.insert_transaction(&transfer).await.unwrap();
+        storage
+            .update_transaction_status(id, "failed")
+            .await
+            .unwrap();
+        storage
+            .record_rejection(
+                id,
+                code.as_ref().map(RejectionCode::as_str),
+                Some("Declined"),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejection_report() {
+        let storage = Storage::new_in_memory().await.unwrap();
+        rejected(&storage, "tx-1", Some(RejectionCode::Sanctions)).await;
+        rejected(&storage, "tx-2", Some(RejectionCode::Sanctions)).await;
+        rejected(&storage, "tx-3", Some(RejectionCode::Policy)).await;
+        rejected(&storage, "tx-4", None).await;
+
+        let reader = ReadOnlyStorage::for_primary(&storage, None, 1)
+            .await
+            .unwrap();
+        let mut report = RejectionReport::generate(&reader, None, None)
+            .await
+            .unwrap();
+        assert_eq!(report.total, 4);
+        assert_eq!(report.count(RejectionCode::Sanctions), 2);
+        assert_eq!(report.count(RejectionCode::Policy), 1);
+        assert_eq!(report.count(RejectionCode::Fraud), 0);
+        assert_eq!(report.uncoded, 1);
+        assert_eq!(report.share(RejectionCode::Sanctions), 0.5);
+
+        let stored = storage
+            .get_transaction_by_id("tx-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.rejection_code.as_deref(), Some("sanctions"));
+        assert_eq!(stored.rejection_reason.as_deref(), Some("Declined"));
+
+        report.merge(&report.clone());
+        assert_eq!(report.total, 8);
+        assert_eq!(report.count(RejectionCode::Policy), 2);
+
+        let future = RejectionReport::generate(&reader, Some("2999-01-01T00:00:00Z"), None)
+            .await
+            .unwrap();
+        assert_eq!(future, RejectionReport::default());
+    }
+
+    #[tokio::test]
+    async fn test_authorization_report() {
+        let storage = Storage::new_in_memory()
+            .await
+            .unwrap()
+            .with_event_sourcing(true);
+        rejected(&storage, "tx-0", None).await;
+        let transfer = PlainMessage::new(
+            "tx-1".to_string(),
+            "https://tap.rsvp/schema/1.0#Transfer".to_string(),
+            serde_json::json!({ "asset": "eip155:1/slip44:60", "amount": "1" }),
+            "did:example:originator".to_string(),
+        );
+        storage.insert_transaction(&transfer).await.unwrap();
+        for agent in ["did:example:vasp-a", "did:example:vasp-b"] {
+            storage
+                .insert_transaction_agent("tx-1", agent, "other")
+                .await
+                .unwrap();
+        }
+        storage
+            .update_transaction_agent_status("tx-1", "did:example:vasp-a", "authorized")
+            .await
+            .unwrap();
+        storage
+            .insert_transaction_agent("tx-1", "did:example:compliance", "compliance")
+            .await
+            .unwrap();
+        storage
+            .update_transaction_agent_status("tx-1", "did:example:vasp-b", "authorized")
+            .await
+            .unwrap();
+
+        let report = AuthorizationReport::generate(&storage, "tx-1")
+            .await
+            .unwrap();
+        assert_eq!(report.authorizations.len(), 2);
+
+        let first = &report.authorizations[0];
+        assert_eq!(first.agent_did, "did:example:vasp-a");
+        assert_eq!(first.state.agents.len(), 2);
+        assert_eq!(first.state.agents[1].status, "pending");
+
+        let second = &report.authorizations[1];
+        assert_eq!(second.agent_did, "did:example:vasp-b");
+        assert_eq!(second.state.agents.len(), 3);
+        assert!(second
+            .state
+            .agents
+            .iter()
+            .filter(|agent| agent.agent_did != "did:example:compliance")
+            .all(|agent| agent.status == "authorized"));
+
+        let none = AuthorizationReport::generate(&storage, "tx-0")
+            .await
+            .unwrap();
+        assert!(none.authorizations.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_valuation_report() {
+        let storage = Storage::new_in_memory().await.unwrap();
+        for (id, asset, fiat_amount, created_at) in [
+            ("tx-1", "eip155:1/slip44:60", 2500.0, "2026-03-01T10:00:00Z"),
+            ("tx-2", "EUR", 90.5, "2026-03-01T11:00:00Z"),
+            ("tx-3", "eip155:1/slip44:60", 1250.0, "2026-03-02T10:00:00Z"),
+        ] {
+            storage
+                .record_transaction_valuation(&TransactionValuation {
+                    transaction_id: id.to_string(),
+                    asset: asset.to_string(),
+                    amount: "1".to_string(),
+                    currency: "USD".to_string(),
+                    fiat_amount,
+                    rate: fiat_amount,
+                    rate_source: "feed, v2".to_string(),
+                    rate_timestamp: created_at.to_string(),
+                    canonical_hash: None,
+                    created_at: created_at.to_string(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let reader = ReadOnlyStorage::for_primary(&storage, None, 1)
+            .await
+            .unwrap();
+        let report = ValuationReport::generate(&reader, None, Some("2026-03-02T00:00:00Z"))
+            .await
+            .unwrap();
+        assert_eq!(report.transactions.len(), 2);
+        assert_eq!(report.totals["USD"], 2590.5);
+
+        let csv = report.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("transaction_id,canonical_hash,asset,amount,currency"));
+        assert_eq!(
+            lines[2],
+            "tx-2,,EUR,1,USD,90.5,90.5,\"feed, v2\",2026-03-01T11:00:00Z,2026-03-01T11:00:00Z"
+        );
+    }
+}
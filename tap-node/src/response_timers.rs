@@ -0,0 +1,509 @@
+//! Response deadlines of our agents
+//!
+//! The TAIPs expect the agents of a transaction to answer each other's
+//! requests promptly: a Transfer is authorized or rejected, a Connect is
+//! authorized, a RequestPresentation is answered with a Presentation. An
+//! operator that commits to answering within a service level needs to know
+//! when it is about to miss it. With
+//! [`NodeConfig::response_timers`](crate::NodeConfig::response_timers) set,
+//! the node starts a timer when a message of a type with a
+//! [`ResponseTimerRule`] arrives for one of our agents, and stops it when
+//! that agent sends one of the replies the rule lists on the message's
+//! thread. A counterparty's Reject or Cancel on the thread withdraws the
+//! request and cancels the timer.
+//!
+//! The [`ResponseTimerMonitor`] publishes a
+//! [`NodeEvent::ResponseDeadlineApproaching`] when a timer nears its
+//! deadline and a [`NodeEvent::ResponseDeadlineBreached`] once it passes.
+//! Timers are kept in the node's storage; [`TapNode::response_time_report`]
+//! (or `tap-cli report response-times`) reports the time taken to answer
+//! each message type.
+
+use crate::clock::{system_clock, Clock};
+use crate::error::{Error, Result};
+use crate::event::{EventBus, NodeEvent};
+use crate::scheduler::{JobControl, Schedule, ScheduledJob, Scheduler};
+use crate::storage::{ResponseTimeStats, ResponseTimer, ResponseTimerStatus, Storage};
+use crate::TapNode;
+use async_trait::async_trait;
+use chrono::DateTime;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tap_msg::didcomm::PlainMessage;
+use tracing::{debug, info, warn};
+
+/// Name of the scheduled job publishing deadline warnings and breaches
+pub const RESPONSE_TIMER_JOB: &str = "response_timers";
+
+/// Number of due timers handled per check
+const RESPONSE_TIMER_BATCH_SIZE: u32 = 100;
+
+/// Message types whose arrival withdraws the requests of their thread
+const WITHDRAWING_TYPES: [&str; 2] = ["Reject", "Cancel"];
+
+/// Short name of a message type, e.g. `Transfer` for
+/// `https://tap.rsvp/schema/1.0#Transfer`
+pub fn short_type(message_type: &str) -> &str {
+    message_type
+        .rsplit(['#', '/'])
+        .next()
+        .unwrap_or(message_type)
+}
+
+/// Deadline for answering one message type
+#[derive(Debug, Clone)]
+pub struct ResponseTimerRule {
+    /// Short name of the message type, e.g. `Transfer`
+    pub message_type: String,
+    /// Time our agent has to answer
+    pub deadline: Duration,
+    /// Time before the deadline at which a warning is published
+    pub warning: Duration,
+    /// Short names of the message types that answer it
+    pub responses: Vec<String>,
+}
+
+impl ResponseTimerRule {
+    /// Create a rule, warning when a quarter of the deadline remains
+    pub fn new(message_type: &str, deadline: Duration, responses: &[&str]) -> Self {
+        Self {
+            message_type: message_type.to_string(),
+            deadline,
+            warning: deadline / 4,
+            responses: responses.iter().map(|r| r.to_string()).collect(),
+        }
+    }
+
+    /// Warn when the given time remains before the deadline
+    pub fn with_warning(mut self, warning: Duration) -> Self {
+        self.warning = warning;
+        self
+    }
+
+    /// Whether a message type answers the rule's message type
+    pub fn answered_by(&self, message_type: &str) -> bool {
+        let message_type = short_type(message_type);
+        self.responses
+            .iter()
+            .any(|response| response.eq_ignore_ascii_case(message_type))
+    }
+}
+
+/// Deadlines for answering the messages counterparties send our agents
+#[derive(Debug, Clone)]
+pub struct ResponseTimerPolicy {
+    /// Deadline of each timed message type
+    pub rules: Vec<ResponseTimerRule>,
+    /// How often the monitor looks for timers nearing or past their deadline
+    pub check_interval: Duration,
+}
+
+impl Default for ResponseTimerPolicy {
+    /// One hour to answer the requests of TAIP-3 (Transfer), TAIP-14
+    /// (Payment), TAIP-17 (Escrow), TAIP-15 (Connect), TAIP-8
+    /// (RequestPresentation) and TAIP-18 (RFQ)
+    fn default() -> Self {
+        let hour = Duration::from_secs(3600);
+        Self {
+            rules: vec![
+                ResponseTimerRule::new("Transfer", hour, &["Authorize", "Reject", "Cancel"]),
+                ResponseTimerRule::new("Payment", hour, &["Authorize", "Reject", "Cancel"]),
+                ResponseTimerRule::new("Escrow", hour, &["Authorize", "Reject", "Cancel"]),
+                ResponseTimerRule::new("Connect", hour, &["Authorize", "Reject"]),
+                ResponseTimerRule::new("RequestPresentation", hour, &["Presentation", "Reject"]),
+                ResponseTimerRule::new("RFQ", hour, &["Quote", "Reject"]),
+            ],
+            check_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl ResponseTimerPolicy {
+    /// Add a rule, replacing the rule of the same message type
+    pub fn with_rule(mut self, rule: ResponseTimerRule) -> Self {
+        self.rules
+            .retain(|r| !r.message_type.eq_ignore_ascii_case(&rule.message_type));
+        self.rules.push(rule);
+        self
+    }
+
+    /// Give every rule the same deadline, warning when a quarter of it
+    /// remains
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        for rule in &mut self.rules {
+            rule.deadline = deadline;
+            rule.warning = deadline / 4;
+        }
+        self
+    }
+
+    /// The rule of a message type, if it is timed
+    pub fn rule(&self, message_type: &str) -> Option<&ResponseTimerRule> {
+        let message_type = short_type(message_type);
+        self.rules
+            .iter()
+            .find(|rule| rule.message_type.eq_ignore_ascii_case(message_type))
+    }
+}
+
+/// Publishes the warnings and breaches of response deadlines
+#[derive(Debug, Clone)]
+pub struct ResponseTimerMonitor {
+    policy: ResponseTimerPolicy,
+    clock: Arc<dyn Clock>,
+}
+
+impl ResponseTimerMonitor {
+    /// Create a new monitor for the given policy
+    pub fn new(policy: ResponseTimerPolicy) -> Self {
+        Self {
+            policy,
+            clock: system_clock(),
+        }
+    }
+
+    /// Compare deadlines against the given clock instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Get the response timer policy
+    pub fn policy(&self) -> &ResponseTimerPolicy {
+        &self.policy
+    }
+
+    /// Publish a warning for the open timers nearing their deadline and a
+    /// breach for those past it
+    ///
+    /// Each timer is warned about and breached at most once; a timer found
+    /// past its deadline is only reported as breached. At most
+    /// [`RESPONSE_TIMER_BATCH_SIZE`] timers are handled per call.
+    ///
+    /// Returns the number of events published.
+    pub async fn check(&self, storage: &Storage, event_bus: &EventBus) -> Result<usize> {
+        let now = self.clock.now();
+        let due = storage
+            .list_due_response_timers(&now, RESPONSE_TIMER_BATCH_SIZE)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+
+        let mut published = 0;
+        for timer in due {
+            let past_deadline = DateTime::parse_from_rfc3339(&timer.deadline_at)
+                .is_ok_and(|deadline| deadline <= now);
+            let marked = if past_deadline {
+                storage.mark_response_timer_breached(timer.id).await
+            } else {
+                storage.mark_response_timer_warned(timer.id).await
+            };
+            match marked {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    warn!("Failed to update response timer {}: {}", timer.id, e);
+                    continue;
+                }
+            }
+
+            let event = if past_deadline {
+                NodeEvent::ResponseDeadlineBreached {
+                    agent_did: timer.agent_did,
+                    counterparty: timer.counterparty_did,
+                    message_id: timer.message_id,
+                    message_type: timer.message_type,
+                    thread_id: timer.thread_id,
+                    deadline_at: timer.deadline_at,
+                }
+            } else {
+                NodeEvent::ResponseDeadlineApproaching {
+                    agent_did: timer.agent_did,
+                    counterparty: timer.counterparty_did,
+                    message_id: timer.message_id,
+                    message_type: timer.message_type,
+                    thread_id: timer.thread_id,
+                    deadline_at: timer.deadline_at,
+                }
+            };
+            event_bus.publish_event(event).await;
+            published += 1;
+        }
+
+        Ok(published)
+    }
+
+    /// Register the monitor as the [`RESPONSE_TIMER_JOB`] of a scheduler,
+    /// running every check interval unless configured otherwise
+    ///
+    /// The job holds weak references to the storage and event bus and stops
+    /// once either is dropped.
+    pub async fn schedule(
+        self,
+        scheduler: &Scheduler,
+        storage: &Arc<Storage>,
+        event_bus: &Arc<EventBus>,
+    ) {
+        let schedule = Schedule::Every(self.policy.check_interval);
+        debug!(
+            "Registering response timer monitor ({} message types)",
+            self.policy.rules.len()
+        );
+        let job = ResponseTimerJob {
+            monitor: self,
+            storage: Arc::downgrade(storage),
+            event_bus: Arc::downgrade(event_bus),
+        };
+        scheduler
+            .register(RESPONSE_TIMER_JOB, schedule, Arc::new(job))
+            .await;
+    }
+}
+
+struct ResponseTimerJob {
+    monitor: ResponseTimerMonitor,
+    storage: Weak<Storage>,
+    event_bus: Weak<EventBus>,
+}
+
+#[async_trait]
+impl ScheduledJob for ResponseTimerJob {
+    async fn run(&self) -> Result<JobControl> {
+        let (Some(storage), Some(event_bus)) = (self.storage.upgrade(), self.event_bus.upgrade())
+        else {
+            debug!("Storage dropped, stopping response timer monitor");
+            return Ok(JobControl::Stop);
+        };
+
+        let published = self.monitor.check(&storage, &event_bus).await?;
+        if published > 0 {
+            info!("Published {} response deadline events", published);
+        }
+        Ok(JobControl::Continue)
+    }
+}
+
+impl TapNode {
+    /// Time taken by our agents to answer each timed message type, for the
+    /// messages that arrived at or after `since` (RFC 3339)
+    pub async fn response_time_report(
+        &self,
+        since: Option<&str>,
+    ) -> Result<Vec<ResponseTimeStats>> {
+        self.response_timer_storage()?
+            .response_time_report(since)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    /// List response timers, most recently started first
+    pub async fn list_response_timers(
+        &self,
+        agent_did: Option<&str>,
+        status: Option<ResponseTimerStatus>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<ResponseTimer>> {
+        self.response_timer_storage()?
+            .list_response_timers(agent_did, status, limit, offset)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    fn response_timer_storage(&self) -> Result<&Arc<Storage>> {
+        self.storage
+            .as_ref()
+            .ok_or_else(|| Error::Storage("Storage is not initialized".to_string()))
+    }
+
+    /// Start the response timers of our agents the message is addressed to,
+    /// or cancel those of its thread if it withdraws them
+    ///
+    /// Failing to record is logged and does not affect the message.
+    pub(crate) async fn start_response_timers(&self, message: &PlainMessage) {
+        let (Some(policy), Some(storage)) = (&self.config.response_timers, &self.storage) else {
+            return;
+        };
+        let rule = policy.rule(&message.type_);
+        let withdraws = WITHDRAWING_TYPES
+            .iter()
+            .any(|t| t.eq_ignore_ascii_case(short_type(&message.type_)));
+        if rule.is_none() && !withdraws {
+            return;
+        }
+
+        let thread_id = message.thid.as_deref().unwrap_or(&message.id);
+        for agent_did in message.to.iter().filter(|did| self.agents.has_agent(did)) {
+            let recorded = match rule {
+                Some(rule) => storage
+                    .start_response_timer(message, agent_did, rule)
+                    .await
+                    .map(|_| ()),
+                None => storage
+                    .cancel_response_timers(thread_id, agent_did)
+                    .await
+                    .map(|_| ()),
+            };
+            if let Err(e) = recorded {
+                warn!(
+                    "Failed to update response timers of {} for message {}: {}",
+                    agent_did, message.id, e
+                );
+            }
+        }
+    }
+
+    /// Stop the timers of the sender's thread that the message answers
+    ///
+    /// Failing to record is logged and does not affect the message.
+    pub(crate) async fn stop_response_timers(&self, sender_did: &str, message: &PlainMessage) {
+        let (Some(_), Some(storage), Some(thread_id)) = (
+            &self.config.response_timers,
+            &self.storage,
+            message.thid.as_deref(),
+        ) else {
+            return;
+        };
+        if let Err(e) = storage
+            .answer_response_timers(
+                thread_id,
+                sender_did,
+                short_type(&message.type_),
+                Some(&message.id),
+            )
+            .await
+        {
+            warn!(
+                "Failed to stop response timers of {} for message {}: {}",
+                sender_did, message.id, e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::test_fixtures::transfer_body;
+    use tap_msg::message::tap_message_trait::TapMessageBody;
+    use tap_msg::message::Transfer;
+
+    const AGENT: &str = "did:example:us";
+    const COUNTERPARTY: &str = "did:example:them";
+
+    fn transfer(id: &str) -> PlainMessage {
+        let transfer = Transfer {
+            asset: "eip155:1/erc20:0x6b175474e89094c44da98b954eedeac495271d0f"
+                .parse()
+                .unwrap(),
+            transaction_id: Some(id.to_string()),
+            ..transfer_body("did:example:alice", "did:example:bob", "100.0")
+        };
+        let mut message = transfer.to_didcomm(COUNTERPARTY).unwrap();
+        message.id = id.to_string();
+        message.to = vec![AGENT.to_string()];
+        message
+    }
+
+    #[test]
+    fn test_default_rules() {
+        let policy = ResponseTimerPolicy::default();
+        let rule = policy.rule("https://tap.rsvp/schema/1.0#Transfer").unwrap();
+        assert_eq!(rule.deadline, Duration::from_secs(3600));
+        assert_eq!(rule.warning, Duration::from_secs(900));
+        assert!(rule.answered_by("https://tap.rsvp/schema/1.0#Authorize"));
+        assert!(!rule.answered_by("https://tap.rsvp/schema/1.0#Settle"));
+        assert!(policy
+            .rule("RequestPresentation")
+            .unwrap()
+            .answered_by("https://didcomm.org/present-proof/3.0/presentation"));
+        assert!(policy.rule("Authorize").is_none());
+
+        let policy = policy.with_deadline(Duration::from_secs(600));
+        assert!(policy
+            .rules
+            .iter()
+            .all(|rule| rule.deadline == Duration::from_secs(600)));
+    }
+
+    #[tokio::test]
+    async fn test_monitor_warns_then_breaches() {
+        let clock = Arc::new(MockClock::default());
+        let storage = Storage::new_in_memory()
+            .await
+            .unwrap()
+            .with_clock(clock.clone());
+        let event_bus = EventBus::new();
+        let mut events = event_bus.subscribe_channel();
+        let policy = ResponseTimerPolicy::default();
+        let monitor = ResponseTimerMonitor::new(policy.clone()).with_clock(clock.clone());
+
+        let message = transfer("tx-1");
+        storage
+            .start_response_timer(&message, AGENT, policy.rule("Transfer").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(monitor.check(&storage, &event_bus).await.unwrap(), 0);
+
+        clock.advance(chrono::Duration::minutes(50));
+        assert_eq!(monitor.check(&storage, &event_bus).await.unwrap(), 1);
+        assert!(matches!(
+            events.recv().await.unwrap().as_ref(),
+            NodeEvent::ResponseDeadlineApproaching { message_id, .. } if message_id == "tx-1"
+        ));
+        // Warned once only
+        assert_eq!(monitor.check(&storage, &event_bus).await.unwrap(), 0);
+
+        clock.advance(chrono::Duration::minutes(20));
+        assert_eq!(monitor.check(&storage, &event_bus).await.unwrap(), 1);
+        assert!(matches!(
+            events.recv().await.unwrap().as_ref(),
+            NodeEvent::ResponseDeadlineBreached { agent_did, .. } if agent_did == AGENT
+        ));
+        assert_eq!(monitor.check(&storage, &event_bus).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_answer_stops_timer_and_reports_response_time() {
+        let clock = Arc::new(MockClock::default());
+        let storage = Storage::new_in_memory()
+            .await
+            .unwrap()
+            .with_clock(clock.clone());
+        let policy = ResponseTimerPolicy::default();
+
+        let message = transfer("tx-2");
+        storage
+            .start_response_timer(&message, AGENT, policy.rule("Transfer").unwrap())
+            .await
+            .unwrap();
+        clock.advance(chrono::Duration::minutes(10));
+
+        // A Settle does not answer a Transfer
+        let answered = storage
+            .answer_response_timers("tx-2", AGENT, "Settle", None)
+            .await
+            .unwrap();
+        assert_eq!(answered, 0);
+
+        let answered = storage
+            .answer_response_timers("tx-2", AGENT, "Authorize", Some("reply-1"))
+            .await
+            .unwrap();
+        assert_eq!(answered, 1);
+
+        let timers = storage
+            .list_response_timers(Some(AGENT), None, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(timers[0].status, ResponseTimerStatus::Answered);
+        assert_eq!(timers[0].response_type.as_deref(), Some("Authorize"));
+        assert!(!timers[0].breached);
+
+        let report = storage.response_time_report(None).await.unwrap();
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].message_type, "Transfer");
+        assert_eq!(report[0].answered, 1);
+        assert_eq!(report[0].response_time.average_secs, Some(600.0));
+    }
+}
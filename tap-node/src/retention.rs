@@ -0,0 +1,411 @@
+//! Customer data retention
+//!
+//! A [`RetentionPolicy`] sets a maximum age for each class of customer data.
+//! The [`RetentionPurger`] applies the policy to a storage instance, either on
+//! demand with [`RetentionPurger::purge`] or periodically as the
+//! [`RETENTION_JOB`] of the node's scheduler.
+//!
+//! Expired customer profiles are erased with [`Storage::erase_customer`], which
+//! redacts personal data but leaves a tombstone so transactions that reference
+//! the customer remain verifiable. Expired IVMS101 data is simply cleared, since
+//! it can be regenerated from the profile when needed.
+
+use crate::clock::{system_clock, Clock};
+use crate::scheduler::{JobControl, Schedule, ScheduledJob, Scheduler};
+use crate::storage::{AgentStorageManager, Storage, StorageError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+/// Name of the scheduled job applying the retention policy
+pub const RETENTION_JOB: &str = "retention_purge";
+
+/// Reason recorded on tombstones created by the retention purger
+pub const RETENTION_ERASURE_REASON: &str = "retention_policy";
+
+/// Number of customers erased per storage query
+const ERASURE_BATCH_SIZE: u32 = 100;
+
+/// Maximum ages for customer data
+///
+/// Ages are measured from the customer's last update. `None` keeps the data
+/// class indefinitely.
+#[derive(Debug, Clone)]
+pub struct RetentionPolicy {
+    /// Maximum age of customer profiles before they are erased
+    pub customer_profile_max_age: Option<Duration>,
+    /// Maximum age of cached IVMS101 data before it is cleared
+    pub ivms101_max_age: Option<Duration>,
+    /// How often the scheduled purge applies the policy, unless the
+    /// scheduler configures another schedule
+    pub purge_interval: Duration,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            customer_profile_max_age: None,
+            ivms101_max_age: None,
+            purge_interval: Duration::from_secs(3600),
+        }
+    }
+}
+
+/// Outcome of a single purge run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionReport {
+    /// Number of customer profiles erased
+    pub customers_erased: usize,
+    /// Number of customers whose IVMS101 data was cleared
+    pub ivms101_purged: u64,
+}
+
+/// Applies a [`RetentionPolicy`] to customer storage
+#[derive(Debug, Clone)]
+pub struct RetentionPurger {
+    policy: RetentionPolicy,
+    clock: Arc<dyn Clock>,
+}
+
+impl RetentionPurger {
+    /// Create a new purger for the given policy
+    pub fn new(policy: RetentionPolicy) -> Self {
+        Self {
+            policy,
+            clock: system_clock(),
+        }
+    }
+
+    /// Measure data ages against the given clock instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Get the retention policy
+    pub fn policy(&self) -> &RetentionPolicy {
+        &self.policy
+    }
+
+    /// Apply the retention policy to a storage instance once
+    pub async fn purge(&self, storage: &Storage) -> Result<RetentionReport, StorageError> {
+        let mut report = RetentionReport::default();
+        let now = self.clock.now();
+
+        if let Some(cutoff) = self
+            .policy
+            .customer_profile_max_age
+            .and_then(|age| cutoff(now, age))
+        {
+            loop {
+                let expired = storage
+                    .list_customers_updated_before(&cutoff, ERASURE_BATCH_SIZE)
+                    .await?;
+                for customer_id in &expired {
+                    storage
+                        .erase_customer(customer_id, Some(RETENTION_ERASURE_REASON))
+                        .await?;
+                    report.customers_erased += 1;
+                }
+                if expired.len() < ERASURE_BATCH_SIZE as usize {
+                    break;
+                }
+            }
+        }
+
+        if let Some(cutoff) = self.policy.ivms101_max_age.and_then(|age| cutoff(now, age)) {
+            report.ivms101_purged = storage.purge_ivms101_data_before(&cutoff).await?;
+        }
+
+        Ok(report)
+    }
+
+    /// Register the purger as the [`RETENTION_JOB`] of a scheduler, running
+    /// every purge interval unless configured otherwise
+    ///
+    /// The job covers the node's main storage and every cached agent storage.
+    /// It holds weak references only and stops once both are dropped.
+    pub async fn schedule(
+        self,
+        scheduler: &Scheduler,
+        storage: Option<&Arc<Storage>>,
+        agent_storage_manager: Option<&Arc<AgentStorageManager>>,
+    ) {
+        let schedule = Schedule::Every(self.policy.purge_interval);
+        let job = RetentionJob {
+            purger: self,
+            storage: storage.map(Arc::downgrade),
+            agent_storage_manager: agent_storage_manager.map(Arc::downgrade),
+        };
+        scheduler
+            .register(RETENTION_JOB, schedule, Arc::new(job))
+            .await;
+    }
+}
+
+struct RetentionJob {
+    purger: RetentionPurger,
+    storage: Option<Weak<Storage>>,
+    agent_storage_manager: Option<Weak<AgentStorageManager>>,
+}
+
+#[async_trait]
+impl ScheduledJob for RetentionJob {
+    async fn run(&self) -> crate::Result<JobControl> {
+        let storage = self.storage.as_ref().and_then(Weak::upgrade);
+        let manager = self.agent_storage_manager.as_ref().and_then(Weak::upgrade);
+        if storage.is_none() && manager.is_none() {
+            debug!("Storage dropped, stopping retention purger");
+            return Ok(JobControl::Stop);
+        }
+
+        let mut targets: Vec<(String, Arc<Storage>)> = Vec::new();
+        if let Some(storage) = storage {
+            targets.push(("node".to_string(), storage));
+        }
+        if let Some(manager) = manager {
+            for agent_did in manager.cached_agent_dids() {
+                if let Some(agent_storage) = manager.get_cached_agent_storage(&agent_did) {
+                    targets.push((agent_did, agent_storage));
+                }
+            }
+        }
+
+        for (label, target) in targets {
+            match self.purger.purge(&target).await {
+                Ok(report) => {
+                    if report != RetentionReport::default() {
+                        info!(
+                            "Retention purge for {}: {} customers erased, {} IVMS101 records cleared",
+                            label, report.customers_erased, report.ivms101_purged
+                        );
+                    }
+                }
+                Err(e) => {
+                    error!("Retention purge failed for {}: {}", label, e);
+                }
+            }
+        }
+        Ok(JobControl::Continue)
+    }
+}
+
+/// Compute the RFC 3339 cutoff for a maximum age, or `None` if it predates all data
+fn cutoff(now: DateTime<Utc>, max_age: Duration) -> Option<String> {
+    let max_age = chrono::Duration::from_std(max_age).ok()?;
+    now.checked_sub_signed(max_age)
+        .map(|cutoff| cutoff.to_rfc3339())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Customer, CustomerIdentifier, IdentifierType, SchemaType};
+    use serde_json::json;
+    use tap_msg::utils::NameHashable;
+
+    fn customer(id: &str, updated_at: &str) -> Customer {
+        Customer {
+            id: id.to_string(),
+            agent_did: "did:example:agent".to_string(),
+            schema_type: SchemaType::Person,
+            given_name: Some("Alice".to_string()),
+            family_name: Some("Smith".to_string()),
+            display_name: Some("Alice Smith".to_string()),
+            legal_name: None,
+            lei_code: None,
+            mcc_code: None,
+            address_country: Some("DE".to_string()),
+            address_locality: Some("Berlin".to_string()),
+            postal_code: None,
+            street_address: Some("Unter den Linden 1".to_string()),
+            profile: json!({
+                "@context": "https://schema.org",
+                "@type": "Person",
+                "identifier": id,
+                "givenName": "Alice",
+                "familyName": "Smith",
+            }),
+            ivms101_data: Some(json!({"naturalPerson": {"name": "Alice Smith"}})),
+            verified_at: None,
+            created_at: updated_at.to_string(),
+            updated_at: updated_at.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_erase_customer_redacts_and_records_tombstone() {
+        let storage = Storage::new_in_memory().await.unwrap();
+        let now = Utc::now().to_rfc3339();
+        storage
+            .upsert_customer(&customer("did:example:alice", &now))
+            .await
+            .unwrap();
+        for (id, identifier_type) in [
+            ("did:example:alice", IdentifierType::Did),
+            ("mailto:alice@example.com", IdentifierType::Email),
+        ] {
+            storage
+                .add_customer_identifier(&CustomerIdentifier {
+                    id: id.to_string(),
+                    customer_id: "did:example:alice".to_string(),
+                    identifier_type,
+                    verified: false,
+                    verification_method: None,
+                    verified_at: None,
+                    created_at: now.clone(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let erasure = storage
+            .erase_customer("did:example:alice", Some("data subject request"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(erasure.profile_hash.len(), 64);
+        assert_eq!(erasure.name_hash, Some(Customer::hash_name("Alice Smith")));
+
+        let erased = storage
+            .get_customer("did:example:alice")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(erased.given_name.is_none());
+        assert!(erased.family_name.is_none());
+        assert!(erased.street_address.is_none());
+        assert!(erased.ivms101_data.is_none());
+        assert_eq!(erased.profile["erased"], true);
+        assert!(erased.profile.get("givenName").is_none());
+        assert_eq!(erased.get_name_hash(), erasure.name_hash);
+
+        let identifiers = storage
+            .get_customer_identifiers("did:example:alice")
+            .await
+            .unwrap();
+        assert_eq!(identifiers.len(), 1);
+        assert_eq!(identifiers[0].identifier_type, IdentifierType::Did);
+
+        // Erasing again returns the original tombstone
+        let again = storage
+            .erase_customer("did:example:alice", None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(again.profile_hash, erasure.profile_hash);
+        assert_eq!(again.reason.as_deref(), Some("data subject request"));
+
+        assert!(storage
+            .erase_customer("did:example:unknown", None)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_purge_applies_max_ages() {
+        let storage = Storage::new_in_memory().await.unwrap();
+        let now = Utc::now();
+        let days_ago = |days: i64| (now - chrono::Duration::days(days)).to_rfc3339();
+
+        storage
+            .upsert_customer(&customer("did:example:stale", &days_ago(400)))
+            .await
+            .unwrap();
+        storage
+            .upsert_customer(&customer("did:example:aging", &days_ago(60)))
+            .await
+            .unwrap();
+        storage
+            .upsert_customer(&customer("did:example:fresh", &days_ago(1)))
+            .await
+            .unwrap();
+
+        let purger = RetentionPurger::new(RetentionPolicy {
+            customer_profile_max_age: Some(Duration::from_secs(365 * 86400)),
+            ivms101_max_age: Some(Duration::from_secs(30 * 86400)),
+            ..Default::default()
+        });
+
+        let report = purger.purge(&storage).await.unwrap();
+        assert_eq!(report.customers_erased, 1);
+        assert_eq!(report.ivms101_purged, 1);
+
+        assert!(storage
+            .get_customer_erasure("did:example:stale")
+            .await
+            .unwrap()
+            .is_some());
+
+        let aging = storage
+            .get_customer("did:example:aging")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(aging.ivms101_data.is_none());
+        assert_eq!(aging.given_name.as_deref(), Some("Alice"));
+
+        // Clearing IVMS101 data does not reset the profile's age
+        let cutoff = days_ago(59);
+        assert_eq!(
+            storage
+                .list_customers_updated_before(&cutoff, 10)
+                .await
+                .unwrap(),
+            vec!["did:example:aging".to_string()]
+        );
+
+        let fresh = storage
+            .get_customer("did:example:fresh")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(fresh.ivms101_data.is_some());
+
+        // A second run has nothing left to do
+        assert_eq!(
+            purger.purge(&storage).await.unwrap(),
+            RetentionReport::default()
+        );
+
+        let erasures = storage
+            .list_customer_erasures("did:example:agent", 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(erasures.len(), 1);
+        assert_eq!(
+            erasures[0].reason.as_deref(),
+            Some(RETENTION_ERASURE_REASON)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_purge_measures_age_with_clock() {
+        use crate::clock::MockClock;
+
+        let clock = Arc::new(MockClock::default());
+        let storage = Storage::new_in_memory()
+            .await
+            .unwrap()
+            .with_clock(clock.clone());
+        storage
+            .upsert_customer(&customer("did:example:alice", &clock.now().to_rfc3339()))
+            .await
+            .unwrap();
+
+        let purger = RetentionPurger::new(RetentionPolicy {
+            ivms101_max_age: Some(Duration::from_secs(30 * 86400)),
+            ..Default::default()
+        })
+        .with_clock(clock.clone());
+
+        clock.advance(chrono::Duration::days(29));
+        assert_eq!(purger.purge(&storage).await.unwrap().ivms101_purged, 0);
+
+        clock.advance(chrono::Duration::days(2));
+        assert_eq!(purger.purge(&storage).await.unwrap().ivms101_purged, 1);
+    }
+}
@@ -0,0 +1,978 @@
+//! Scheduled jobs of the node
+//!
+//! Periodic maintenance (expiring transactions, retrying deliveries, purging
+//! customer data, backups and clock checks) runs as jobs of the node's
+//! [`Scheduler`] rather than in loops of their own. A job implements
+//! [`ScheduledJob`] and is registered under a name with its default
+//! [`Schedule`], which [`SchedulerConfig::schedules`] can override:
+//!
+//! * `@every 5m`, `@every 1h30m` runs the job at a fixed interval
+//! * a five-field cron expression (`minute hour day-of-month month
+//!   day-of-week`, in UTC), e.g. `30 2 * * *` or `*/15 8-18 * * 1-5`, or one
+//!   of `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly`
+//!
+//! The time of the last run of each job is kept in the node's storage, so a
+//! restart does not reset the schedule: a job running every 24 hours that
+//! last ran an hour before the restart runs again 23 hours later, and a cron
+//! job whose time passed while the node was down runs once on startup.
+//! [`SchedulerConfig::jitter`] delays each run by a random amount so that
+//! nodes sharing a schedule do not all run it at the same moment.
+//!
+//! [`Scheduler::run_now`] runs a job immediately. Other processes sharing the
+//! node's database, like `tap-cli scheduler run`, request a run through
+//! storage with [`Storage::request_job_run`](crate::storage::Storage::request_job_run);
+//! the scheduler picks up requests every
+//! [`SchedulerConfig::request_poll_interval`].
+//!
+//! ```
+//! use tap_node::scheduler::Schedule;
+//!
+//! let schedule: Schedule = "*/15 8-18 * * 1-5".parse().unwrap();
+//! assert_eq!(schedule.to_string(), "*/15 8-18 * * 1-5");
+//! let schedule: Schedule = "@every 90m".parse().unwrap();
+//! assert_eq!(schedule.to_string(), "@every 90m");
+//! ```
+
+use crate::clock::{system_clock, Clock};
+use crate::error::{Error, Result};
+use crate::storage::{ScheduledJobRecord, ScheduledJobStatus, Storage};
+use crate::TapNode;
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, TimeZone, Timelike, Utc};
+use dashmap::DashMap;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// How far ahead a cron schedule is searched for its next time
+const CRON_SEARCH_YEARS: i32 = 5;
+
+/// When a job runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// At a fixed interval from the previous run
+    Every(Duration),
+    /// At the times matching a cron expression
+    Cron(CronSchedule),
+}
+
+impl Schedule {
+    /// The first time the job runs after a run at `last`
+    pub fn next_after(&self, last: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Every(interval) => {
+                last.checked_add_signed(ChronoDuration::from_std(*interval).ok()?)
+            }
+            Schedule::Cron(cron) => cron.next_after(last),
+        }
+    }
+
+    /// The first time the job runs when it has never run
+    ///
+    /// Interval jobs run immediately; cron jobs wait for their next time.
+    fn first_after(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Every(_) => Some(now),
+            Schedule::Cron(cron) => cron.next_after(now),
+        }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Every(interval) => write!(f, "@every {}", format_interval(*interval)),
+            Schedule::Cron(cron) => write!(f, "{}", cron),
+        }
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some(interval) = s.strip_prefix("@every") {
+            let interval = parse_interval(interval.trim())?;
+            if interval.is_zero() {
+                return Err(Error::Configuration(
+                    "The interval of a schedule must not be zero".to_string(),
+                ));
+            }
+            return Ok(Schedule::Every(interval));
+        }
+        Ok(Schedule::Cron(s.parse()?))
+    }
+}
+
+/// Parse an interval like `30s`, `5m`, `1h30m` or `2d`
+fn parse_interval(s: &str) -> Result<Duration> {
+    let invalid = || Error::Configuration(format!("Invalid interval: {:?}", s));
+    if s.is_empty() {
+        return Err(invalid());
+    }
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return Err(invalid()),
+        };
+        let value: u64 = number.parse().map_err(|_| invalid())?;
+        seconds = value
+            .checked_mul(unit)
+            .and_then(|value| seconds.checked_add(value))
+            .ok_or_else(invalid)?;
+        number.clear();
+    }
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Format an interval in the largest unit that divides it
+fn format_interval(interval: Duration) -> String {
+    let seconds = interval.as_secs();
+    match seconds {
+        0 => "0s".to_string(),
+        s if s % 86400 == 0 => format!("{}d", s / 86400),
+        s if s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+/// A five-field cron expression, evaluated in UTC
+///
+/// When both the day of the month and the day of the week are restricted, a
+/// day matching either runs the job, as in cron.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+impl CronSchedule {
+    /// The first time matching the expression strictly after `after`
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut time = after
+            .with_second(0)?
+            .with_nanosecond(0)?
+            .checked_add_signed(ChronoDuration::minutes(1))?;
+        let limit = after.year() + CRON_SEARCH_YEARS;
+
+        while time.year() <= limit {
+            if !bit(self.months, time.month()) {
+                time = start_of_next_month(time)?;
+            } else if !self.matches_day(time) {
+                time = start_of_day(time)?.checked_add_signed(ChronoDuration::days(1))?;
+            } else if !bit(self.hours, time.hour()) {
+                time = time
+                    .with_minute(0)?
+                    .checked_add_signed(ChronoDuration::hours(1))?;
+            } else if !bit(self.minutes, time.minute()) {
+                time = time.checked_add_signed(ChronoDuration::minutes(1))?;
+            } else {
+                return Some(time);
+            }
+        }
+        None
+    }
+
+    fn matches_day(&self, time: DateTime<Utc>) -> bool {
+        let day_of_month = bit(self.days_of_month, time.day());
+        let day_of_week = bit(self.days_of_week, time.weekday().num_days_from_sunday());
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (false, false) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        }
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let expression = s.trim();
+        let expanded = match expression {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minutes, hours, days_of_month, months, days_of_week] = fields[..] else {
+            return Err(Error::Configuration(format!(
+                "A cron expression has five fields (minute hour day-of-month month day-of-week): {:?}",
+                expression
+            )));
+        };
+
+        let mut days_of_week = parse_field(days_of_week, 0, 7)?;
+        // Both 0 and 7 are Sunday
+        if bit(days_of_week, 7) {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            expression: expression.to_string(),
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)?,
+            days_of_month: parse_field(days_of_month, 1, 31)?,
+            months: parse_field(months, 1, 12)?,
+            days_of_week,
+            any_day_of_month: fields[2] == "*",
+            any_day_of_week: fields[4] == "*",
+        })
+    }
+}
+
+/// Parse a cron field (`*`, `5`, `1-5`, `*/15`, `0-30/10`, or a comma-separated
+/// list of them) into a bitset of the values it matches
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64> {
+    let invalid = || Error::Configuration(format!("Invalid cron field: {:?}", field));
+    let mut set = 0u64;
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (item, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (
+                start.parse().map_err(|_| invalid())?,
+                end.parse().map_err(|_| invalid())?,
+            )
+        } else {
+            let start = range.parse().map_err(|_| invalid())?;
+            // `5/10` runs from 5 to the end of the range
+            (start, if item.contains('/') { max } else { start })
+        };
+        if start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+fn bit(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+fn start_of_day(time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    Utc.from_local_datetime(&time.date_naive().and_hms_opt(0, 0, 0)?)
+        .single()
+}
+
+fn start_of_next_month(time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let (year, month) = match time.month() {
+        12 => (time.year() + 1, 1),
+        month => (time.year(), month + 1),
+    };
+    Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single()
+}
+
+/// Whether a job keeps running after a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobControl {
+    /// Run the job again at its next scheduled time
+    Continue,
+    /// Unregister the job, e.g. because what it works on was dropped
+    Stop,
+}
+
+/// A job run by the [`Scheduler`]
+#[async_trait]
+pub trait ScheduledJob: Send + Sync {
+    /// Run the job once
+    ///
+    /// An error is logged and recorded as the outcome of the run; the job
+    /// still runs again at its next scheduled time.
+    async fn run(&self) -> Result<JobControl>;
+}
+
+/// Settings of the [`Scheduler`]
+#[derive(Debug, Clone)]
+pub struct SchedulerConfig {
+    /// Schedules replacing the default schedule of jobs, by job name
+    pub schedules: HashMap<String, Schedule>,
+    /// Maximum random delay added to each scheduled run
+    pub jitter: Duration,
+    /// How often storage is checked for runs requested by other processes
+    pub request_poll_interval: Duration,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            schedules: HashMap::new(),
+            jitter: Duration::ZERO,
+            request_poll_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Outcome of a run of a job
+#[derive(Debug, Clone)]
+pub struct JobRun {
+    /// Name of the job
+    pub name: String,
+    pub started_at: DateTime<Utc>,
+    pub duration: Duration,
+    pub status: ScheduledJobStatus,
+    /// Why the run failed
+    pub error: Option<String>,
+    /// When the job runs next (None if it stopped)
+    pub next_run_at: Option<DateTime<Utc>>,
+}
+
+struct JobState {
+    /// When the job runs next, or None while it is running
+    next_run_at: Option<DateTime<Utc>>,
+    last_run: Option<JobRun>,
+}
+
+struct JobEntry {
+    name: String,
+    schedule: Schedule,
+    job: Arc<dyn ScheduledJob>,
+    state: Mutex<JobState>,
+    /// Held while the job runs, so runs of a job never overlap
+    running: tokio::sync::Mutex<()>,
+}
+
+/// Runs the registered jobs on their schedules
+///
+/// See the [module documentation](crate::scheduler).
+pub struct Scheduler {
+    config: SchedulerConfig,
+    clock: Arc<dyn Clock>,
+    storage: RwLock<Option<Weak<Storage>>>,
+    jobs: DashMap<String, Arc<JobEntry>>,
+    wake: Notify,
+    started: AtomicBool,
+}
+
+impl fmt::Debug for Scheduler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scheduler")
+            .field("config", &self.config)
+            .field("jobs", &self.job_names())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Scheduler {
+    /// Create a scheduler without jobs
+    pub fn new(config: SchedulerConfig) -> Self {
+        Self {
+            config,
+            clock: system_clock(),
+            storage: RwLock::new(None),
+            jobs: DashMap::new(),
+            wake: Notify::new(),
+            started: AtomicBool::new(false),
+        }
+    }
+
+    /// Use a different clock (e.g. a mock clock in tests)
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Keep the last runs of jobs in this storage
+    ///
+    /// Jobs registered afterwards resume their schedule from their last
+    /// recorded run. The scheduler holds a weak reference only.
+    pub fn set_storage(&self, storage: &Arc<Storage>) {
+        *self.storage.write().unwrap() = Some(Arc::downgrade(storage));
+    }
+
+    fn storage(&self) -> Option<Arc<Storage>> {
+        self.storage
+            .read()
+            .unwrap()
+            .as_ref()
+            .and_then(Weak::upgrade)
+    }
+
+    /// Register a job, replacing any registered under the same name
+    ///
+    /// The job runs on the schedule configured for its name in
+    /// [`SchedulerConfig::schedules`], or else on `default_schedule`.
+    pub async fn register(
+        &self,
+        name: &str,
+        default_schedule: Schedule,
+        job: Arc<dyn ScheduledJob>,
+    ) {
+        let schedule = self
+            .config
+            .schedules
+            .get(name)
+            .cloned()
+            .unwrap_or(default_schedule);
+        let now = self.clock.now();
+
+        let mut last_run_at = None;
+        if let Some(storage) = self.storage() {
+            match storage.get_scheduled_job(name).await {
+                Ok(record) => {
+                    last_run_at = record
+                        .and_then(|record| record.last_run_at)
+                        .and_then(|at| DateTime::parse_from_rfc3339(&at).ok())
+                        .map(|at| at.with_timezone(&Utc));
+                }
+                Err(e) => warn!("Could not load the last run of job {}: {}", name, e),
+            }
+        }
+        let next_run_at = match last_run_at {
+            Some(last) => schedule.next_after(last).map(|next| next.max(now)),
+            None => schedule.first_after(now),
+        }
+        .map(|next| self.with_jitter(next));
+
+        info!(
+            "Scheduled job {} ({}), next run at {}",
+            name,
+            schedule,
+            next_run_at.map_or("never".to_string(), |at| at.to_rfc3339())
+        );
+        let entry = Arc::new(JobEntry {
+            name: name.to_string(),
+            schedule,
+            job,
+            state: Mutex::new(JobState {
+                next_run_at,
+                last_run: None,
+            }),
+            running: tokio::sync::Mutex::new(()),
+        });
+        self.save(&entry, last_run_at).await;
+        self.jobs.insert(name.to_string(), entry);
+        self.wake.notify_one();
+    }
+
+    /// Names of the registered jobs, sorted
+    pub fn job_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.jobs.iter().map(|job| job.key().clone()).collect();
+        names.sort();
+        names
+    }
+
+    /// The schedule, next run and last run of the registered jobs, sorted by
+    /// name
+    pub fn jobs(&self) -> Vec<ScheduledJobRecord> {
+        let mut jobs: Vec<ScheduledJobRecord> = self
+            .jobs
+            .iter()
+            .map(|entry| Self::record(&entry, None, self.clock.now()))
+            .collect();
+        jobs.sort_by(|a, b| a.name.cmp(&b.name));
+        jobs
+    }
+
+    /// Run a job now, waiting for a run in progress to finish first
+    ///
+    /// The job's next scheduled run is counted from this one.
+    pub async fn run_now(&self, name: &str) -> Result<JobRun> {
+        let entry = self
+            .jobs
+            .get(name)
+            .map(|entry| entry.clone())
+            .ok_or_else(|| Error::Configuration(format!("No scheduled job named {}", name)))?;
+        info!("Running job {} now", name);
+        Ok(self.execute(&entry).await)
+    }
+
+    /// Run the jobs that are due, one after another
+    ///
+    /// The background task started by [`Scheduler::start`] runs due jobs
+    /// concurrently instead; this is for driving the scheduler by hand, e.g.
+    /// with a mock clock.
+    pub async fn run_due(&self) -> Vec<JobRun> {
+        let mut runs = Vec::new();
+        for entry in self.take_due() {
+            runs.push(self.execute(&entry).await);
+        }
+        runs
+    }
+
+    /// Start the background task running the jobs, unless it is running
+    ///
+    /// The task holds a weak reference to the scheduler and stops once it is
+    /// dropped.
+    pub fn start(self: &Arc<Self>) -> Option<JoinHandle<()>> {
+        if self.started.swap(true, Ordering::SeqCst) {
+            return None;
+        }
+        let scheduler = Arc::downgrade(self);
+        info!("Starting job scheduler");
+
+        Some(tokio::spawn(async move {
+            loop {
+                let Some(current) = scheduler.upgrade() else {
+                    debug!("Scheduler dropped, stopping it");
+                    break;
+                };
+
+                for entry in current.take_due() {
+                    let runner = current.clone();
+                    tokio::spawn(async move {
+                        runner.execute(&entry).await;
+                    });
+                }
+                current.run_requested().await;
+
+                let wait = current
+                    .until_next_run()
+                    .min(current.config.request_poll_interval);
+                tokio::select! {
+                    _ = tokio::time::sleep(wait) => {}
+                    _ = current.wake.notified() => {}
+                }
+            }
+        }))
+    }
+
+    /// Claim the jobs whose next run is due
+    fn take_due(&self) -> Vec<Arc<JobEntry>> {
+        let now = self.clock.now();
+        self.jobs
+            .iter()
+            .filter(|entry| {
+                let mut state = entry.state.lock().unwrap();
+                match state.next_run_at {
+                    Some(next) if next <= now => {
+                        state.next_run_at = None;
+                        true
+                    }
+                    _ => false,
+                }
+            })
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
+    /// Time until the next scheduled run
+    fn until_next_run(&self) -> Duration {
+        let now = self.clock.now();
+        self.jobs
+            .iter()
+            .filter_map(|entry| entry.state.lock().unwrap().next_run_at)
+            .min()
+            .map_or(Duration::MAX, |next| {
+                (next - now).to_std().unwrap_or(Duration::ZERO)
+            })
+    }
+
+    /// Run the jobs other processes requested through storage
+    async fn run_requested(self: &Arc<Self>) {
+        let Some(storage) = self.storage() else {
+            return;
+        };
+        let names = self.job_names();
+        let requested = match storage.take_job_run_requests(&names).await {
+            Ok(requested) => requested,
+            Err(e) => {
+                warn!("Could not check for requested job runs: {}", e);
+                return;
+            }
+        };
+        for name in requested {
+            let scheduler = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = scheduler.run_now(&name).await {
+                    warn!("Could not run requested job {}: {}", name, e);
+                }
+            });
+        }
+    }
+
+    async fn execute(&self, entry: &Arc<JobEntry>) -> JobRun {
+        let _running = entry.running.lock().await;
+        let started_at = self.clock.now();
+        let start = Instant::now();
+        let result = entry.job.run().await;
+        let duration = start.elapsed();
+
+        let (status, error, control) = match result {
+            Ok(control) => (ScheduledJobStatus::Succeeded, None, control),
+            Err(e) => {
+                warn!("Scheduled job {} failed: {}", entry.name, e);
+                (
+                    ScheduledJobStatus::Failed,
+                    Some(e.to_string()),
+                    JobControl::Continue,
+                )
+            }
+        };
+        let next_run_at = match control {
+            JobControl::Continue => entry
+                .schedule
+                .next_after(started_at)
+                .map(|next| self.with_jitter(next)),
+            JobControl::Stop => {
+                debug!("Job {} stopped, unregistering it", entry.name);
+                self.jobs
+                    .remove_if(&entry.name, |_, registered| Arc::ptr_eq(registered, entry));
+                None
+            }
+        };
+        debug!(
+            "Job {} ran in {:?} ({})",
+            entry.name,
+            duration,
+            status.to_string()
+        );
+
+        let run = JobRun {
+            name: entry.name.clone(),
+            started_at,
+            duration,
+            status,
+            error,
+            next_run_at,
+        };
+        {
+            let mut state = entry.state.lock().unwrap();
+            state.next_run_at = next_run_at;
+            state.last_run = Some(run.clone());
+        }
+        self.save(entry, Some(started_at)).await;
+        self.wake.notify_one();
+        run
+    }
+
+    /// Record the state of a job in storage
+    async fn save(&self, entry: &JobEntry, last_run_at: Option<DateTime<Utc>>) {
+        let Some(storage) = self.storage() else {
+            return;
+        };
+        let record = Self::record(entry, last_run_at, self.clock.now());
+        if let Err(e) = storage.save_scheduled_job(&record).await {
+            warn!("Could not record the state of job {}: {}", entry.name, e);
+        }
+    }
+
+    fn record(
+        entry: &JobEntry,
+        last_run_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> ScheduledJobRecord {
+        let state = entry.state.lock().unwrap();
+        let last_run = state.last_run.as_ref();
+        ScheduledJobRecord {
+            name: entry.name.clone(),
+            schedule: entry.schedule.to_string(),
+            last_run_at: last_run
+                .map(|run| run.started_at)
+                .or(last_run_at)
+                .map(|at| at.to_rfc3339()),
+            last_status: last_run.map(|run| run.status),
+            last_error: last_run.and_then(|run| run.error.clone()),
+            last_duration_ms: last_run.map(|run| run.duration.as_millis() as i64),
+            next_run_at: state.next_run_at.map(|at| at.to_rfc3339()),
+            run_requested_at: None,
+            updated_at: now.to_rfc3339(),
+        }
+    }
+
+    /// Delay a run by a random amount up to the configured jitter
+    fn with_jitter(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        let jitter_ms = self.config.jitter.as_millis() as u64;
+        if jitter_ms == 0 {
+            return time;
+        }
+        let random = uuid::Uuid::new_v4().as_u128() as u64;
+        time + ChronoDuration::milliseconds((random % (jitter_ms + 1)) as i64)
+    }
+}
+
+/// What [`TapNode::run_scheduled_job`] did
+#[derive(Debug, Clone)]
+pub enum JobTrigger {
+    /// The job is scheduled by this node and ran
+    Ran(JobRun),
+    /// The job is scheduled by another node sharing the database, which was
+    /// asked to run it
+    Requested,
+}
+
+impl TapNode {
+    /// The node's job scheduler
+    pub fn scheduler(&self) -> &Arc<Scheduler> {
+        &self.scheduler
+    }
+
+    /// Run a scheduled job now
+    ///
+    /// A job scheduled by this node runs right away. A job only recorded in
+    /// the node's storage, by another node using the same database, is
+    /// requested from that node and runs when its scheduler next checks for
+    /// requests.
+    pub async fn run_scheduled_job(&self, name: &str) -> Result<JobTrigger> {
+        if self.scheduler.jobs.contains_key(name) {
+            return Ok(JobTrigger::Ran(self.scheduler.run_now(name).await?));
+        }
+        let requested = match &self.storage {
+            Some(storage) => storage
+                .request_job_run(name)
+                .await
+                .map_err(|e| Error::Storage(e.to_string()))?,
+            None => false,
+        };
+        if !requested {
+            return Err(Error::Configuration(format!(
+                "No scheduled job named {}",
+                name
+            )));
+        }
+        info!("Requested a run of job {}", name);
+        Ok(JobTrigger::Requested)
+    }
+
+    /// The scheduled jobs with their last and next runs
+    ///
+    /// With storage, this includes the jobs of other nodes using the same
+    /// database.
+    pub async fn scheduled_jobs(&self) -> Result<Vec<ScheduledJobRecord>> {
+        match &self.storage {
+            Some(storage) => storage
+                .list_scheduled_jobs()
+                .await
+                .map_err(|e| Error::Storage(e.to_string())),
+            None => Ok(self.scheduler.jobs()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use std::sync::atomic::AtomicUsize;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn next(schedule: &str, after: &str) -> String {
+        schedule
+            .parse::<Schedule>()
+            .unwrap()
+            .next_after(at(after))
+            .unwrap()
+            .to_rfc3339()
+    }
+
+    #[test]
+    fn test_parse_schedules() {
+        assert_eq!(
+            "@every 1h30m".parse::<Schedule>().unwrap(),
+            Schedule::Every(Duration::from_secs(5400))
+        );
+        assert_eq!(
+            "@every 1h30m".parse::<Schedule>().unwrap().to_string(),
+            "@every 90m"
+        );
+        assert_eq!("@daily".parse::<Schedule>().unwrap().to_string(), "@daily");
+
+        for invalid in [
+            "@every",
+            "@every 0s",
+            "@every 5x",
+            "* * * *",
+            "60 * * * *",
+            "* * 0 * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+        ] {
+            assert!(invalid.parse::<Schedule>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        assert_eq!(
+            next("30 2 * * *", "2026-03-01T02:30:00Z"),
+            "2026-03-02T02:30:00+00:00"
+        );
+        assert_eq!(
+            next("*/15 * * * *", "2026-03-01T10:07:42Z"),
+            "2026-03-01T10:15:00+00:00"
+        );
+        // Friday evening to Monday morning
+        assert_eq!(
+            next("0 9 * * 1-5", "2026-03-06T18:00:00Z"),
+            "2026-03-09T09:00:00+00:00"
+        );
+        assert_eq!(
+            next("@yearly", "2026-06-15T00:00:00Z"),
+            "2027-01-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            next("0 0 29 2 *", "2026-03-01T00:00:00Z"),
+            "2028-02-29T00:00:00+00:00"
+        );
+        // Day of month or Sunday (7 is Sunday too)
+        assert_eq!(
+            next("0 0 15 * 7", "2026-03-02T00:00:00Z"),
+            "2026-03-08T00:00:00+00:00"
+        );
+        assert!("0 0 31 2 *"
+            .parse::<Schedule>()
+            .unwrap()
+            .next_after(at("2026-01-01T00:00:00Z"))
+            .is_none());
+        assert_eq!(
+            next("@every 10m", "2026-03-01T10:07:42Z"),
+            "2026-03-01T10:17:42+00:00"
+        );
+    }
+
+    struct Counter {
+        runs: AtomicUsize,
+        stop_after: usize,
+    }
+
+    #[async_trait]
+    impl ScheduledJob for Counter {
+        async fn run(&self) -> Result<JobControl> {
+            let runs = self.runs.fetch_add(1, Ordering::SeqCst) + 1;
+            if runs == 2 {
+                return Err(Error::Processing("second run fails".to_string()));
+            }
+            Ok(if runs >= self.stop_after {
+                JobControl::Stop
+            } else {
+                JobControl::Continue
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_runs_due_jobs() {
+        let clock = Arc::new(MockClock::new(at("2026-03-01T10:00:00Z")));
+        let scheduler = Scheduler::new(SchedulerConfig::default()).with_clock(clock.clone());
+        let job = Arc::new(Counter {
+            runs: AtomicUsize::new(0),
+            stop_after: 3,
+        });
+        scheduler
+            .register(
+                "counter",
+                Schedule::Every(Duration::from_secs(60)),
+                job.clone(),
+            )
+            .await;
+
+        // Interval jobs run right away the first time
+        let runs = scheduler.run_due().await;
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].status, ScheduledJobStatus::Succeeded);
+        assert!(scheduler.run_due().await.is_empty());
+
+        clock.advance(ChronoDuration::seconds(60));
+        let runs = scheduler.run_due().await;
+        assert_eq!(runs[0].status, ScheduledJobStatus::Failed);
+        assert_eq!(
+            runs[0].error.as_deref(),
+            Some("PlainMessage processing error: second run fails")
+        );
+        assert_eq!(runs[0].next_run_at, Some(at("2026-03-01T10:02:00Z")));
+
+        // A job that stops is unregistered
+        let run = scheduler.run_now("counter").await.unwrap();
+        assert_eq!(run.next_run_at, None);
+        assert!(scheduler.job_names().is_empty());
+        assert!(scheduler.run_now("counter").await.is_err());
+        assert_eq!(job.runs.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_resumes_from_last_run() {
+        let storage = Arc::new(Storage::new_in_memory().await.unwrap());
+        let clock = Arc::new(MockClock::new(at("2026-03-01T10:00:00Z")));
+        let config = SchedulerConfig {
+            schedules: HashMap::from([(
+                "counter".to_string(),
+                "@every 1h".parse::<Schedule>().unwrap(),
+            )]),
+            ..Default::default()
+        };
+        let job = || {
+            Arc::new(Counter {
+                runs: AtomicUsize::new(0),
+                stop_after: usize::MAX,
+            })
+        };
+
+        let scheduler = Scheduler::new(config.clone()).with_clock(clock.clone());
+        scheduler.set_storage(&storage);
+        scheduler
+            .register("counter", Schedule::Every(Duration::from_secs(60)), job())
+            .await;
+        assert_eq!(scheduler.run_due().await.len(), 1);
+
+        // After a restart 10 minutes later, the job waits for its hour
+        clock.advance(ChronoDuration::minutes(10));
+        let scheduler = Scheduler::new(config).with_clock(clock.clone());
+        scheduler.set_storage(&storage);
+        scheduler
+            .register("counter", Schedule::Every(Duration::from_secs(60)), job())
+            .await;
+        assert!(scheduler.run_due().await.is_empty());
+        let jobs = scheduler.jobs();
+        assert_eq!(jobs[0].schedule, "@every 1h");
+        assert_eq!(
+            jobs[0].next_run_at.as_deref(),
+            Some("2026-03-01T11:00:00+00:00")
+        );
+
+        let record = storage.get_scheduled_job("counter").await.unwrap().unwrap();
+        assert_eq!(
+            record.last_run_at.as_deref(),
+            Some("2026-03-01T10:00:00+00:00")
+        );
+        assert_eq!(record.last_status, Some(ScheduledJobStatus::Succeeded));
+
+        clock.advance(ChronoDuration::minutes(50));
+        assert_eq!(scheduler.run_due().await.len(), 1);
+    }
+}
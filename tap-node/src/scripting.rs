@@ -0,0 +1,356 @@
+//! Sandboxed scripting hooks
+//!
+//! A [`ScriptHook`] runs a [Rhai](https://rhai.rs) script that lets operators
+//! express routing overrides and simple policy rules without recompiling the
+//! node. A script may define either or both of these functions, each receiving
+//! a map describing the message:
+//!
+//! - `route(message)` returns the DID of a local agent, or an array of DIDs,
+//!   that receives an incoming message instead of its `to` recipients.
+//!   Returning nothing keeps the default routing. Transactions are also stored
+//!   in the databases of the agents they are routed to.
+//! - `policy(message)` returns nothing to pass, or a map with an `action`
+//!   (`"warn"`, `"require_presentation"`, `"manual_review"` or `"reject"`) and a
+//!   `reason`. It is evaluated by adding a
+//!   [`ScriptRule`](crate::policy::ScriptRule) to the policy engine, in which
+//!   case the map also holds the `transaction_id`.
+//!
+//! The message map holds `id`, `type`, `from`, `to`, `thid`, `pthid`,
+//! `created_time`, `expires_time` and `body`. It is a copy, so scripts cannot
+//! change the message.
+//!
+//! ```text
+//! fn route(message) {
+//!     if message.type.ends_with("#Transfer")
+//!         && message.body.asset == "eip155:1/erc20:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"
+//!         && parse_float(message.body.amount) > 10000.0
+//!     {
+//!         return "did:web:compliance.example.com";
+//!     }
+//! }
+//! ```
+//!
+//! Scripts loaded from a file are reloaded when the file changes. If the new
+//! version fails to compile, the error is logged and the previous version
+//! stays in effect.
+//!
+//! Scripts cannot import modules or use `eval`. They have no access to the
+//! file system, the network or the node, and each call is limited in the
+//! number of operations, call depth and data sizes.
+
+use crate::error::{Error, Result};
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Dynamic, Engine, Scope, AST};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+use tap_msg::didcomm::PlainMessage;
+
+/// Maximum number of operations a single script call may perform
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// A compiled script and the modification time of its source file
+struct CompiledScript {
+    ast: AST,
+    modified: Option<SystemTime>,
+}
+
+/// A hot-reloadable script defining `route` and/or `policy` functions
+pub struct ScriptHook {
+    name: String,
+    path: Option<PathBuf>,
+    engine: Engine,
+    compiled: RwLock<CompiledScript>,
+}
+
+impl fmt::Debug for ScriptHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScriptHook")
+            .field("name", &self.name)
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl ScriptHook {
+    /// Load a script from a file, reloading it whenever the file changes
+    pub fn from_file(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let engine = sandboxed_engine();
+        let compiled = compile_file(&engine, &path)?;
+
+        Ok(Self {
+            name: path.display().to_string(),
+            path: Some(path),
+            engine,
+            compiled: RwLock::new(compiled),
+        })
+    }
+
+    /// Compile a script from source
+    pub fn from_source(name: impl Into<String>, source: &str) -> Result<Self> {
+        let name = name.into();
+        let engine = sandboxed_engine();
+        let ast = engine
+            .compile(source)
+            .map_err(|e| Error::Script(format!("Failed to compile {}: {}", name, e)))?;
+
+        Ok(Self {
+            name,
+            path: None,
+            engine,
+            compiled: RwLock::new(CompiledScript {
+                ast,
+                modified: None,
+            }),
+        })
+    }
+
+    /// Name of the script, which is its path when loaded from a file
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Reload the script if its file has changed since it was compiled
+    ///
+    /// Returns whether the script was reloaded. On error the previous version
+    /// stays in effect.
+    pub fn reload(&self) -> Result<bool> {
+        let Some(path) = &self.path else {
+            return Ok(false);
+        };
+
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        if modified == self.compiled.read().unwrap().modified {
+            return Ok(false);
+        }
+
+        let compiled = compile_file(&self.engine, path)?;
+        *self.compiled.write().unwrap() = compiled;
+        log::info!("Reloaded script {}", self.name);
+        Ok(true)
+    }
+
+    /// Local agents an incoming message is routed to, if the script overrides
+    /// its routing
+    pub fn route(&self, message: &PlainMessage) -> Result<Vec<String>> {
+        let Some(result) = self.call("route", message_context(message)?)? else {
+            return Ok(Vec::new());
+        };
+
+        if result.is_unit() {
+            Ok(Vec::new())
+        } else if result.is_string() {
+            Ok(vec![result.into_string().unwrap_or_default()])
+        } else if result.is_array() {
+            result
+                .into_array()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|did| {
+                    did.into_string().map_err(|t| {
+                        Error::Script(format!("route must return DIDs as strings, not {}", t))
+                    })
+                })
+                .collect()
+        } else {
+            Err(Error::Script(format!(
+                "route must return a DID or an array of DIDs, not {}",
+                result.type_name()
+            )))
+        }
+    }
+
+    /// Evaluate the script's `policy` function for a transaction
+    ///
+    /// Returns the map returned by the script as JSON, or None if the script
+    /// does not define `policy` or the transaction passes.
+    pub fn policy(
+        &self,
+        transaction_id: &str,
+        message: &PlainMessage,
+    ) -> Result<Option<serde_json::Value>> {
+        let mut context = message_context(message)?;
+        if let Some(mut map) = context.write_lock::<rhai::Map>() {
+            map.insert("transaction_id".into(), transaction_id.into());
+        }
+
+        match self.call("policy", context)? {
+            Some(result) if !result.is_unit() => rhai::serde::from_dynamic(&result)
+                .map(Some)
+                .map_err(|e| Error::Script(format!("Invalid policy result: {}", e))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Call a function defined by the script, if it exists
+    fn call(&self, function: &str, context: Dynamic) -> Result<Option<Dynamic>> {
+        if let Err(e) = self.reload() {
+            log::error!("Keeping previous version of script {}: {}", self.name, e);
+        }
+
+        let compiled = self.compiled.read().unwrap();
+        let defined = compiled
+            .ast
+            .iter_functions()
+            .any(|f| f.name == function && f.params.len() == 1);
+        if !defined {
+            return Ok(None);
+        }
+
+        self.engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &compiled.ast, function, (context,))
+            .map(Some)
+            .map_err(|e| Error::Script(format!("{} in {} failed: {}", function, self.name, e)))
+    }
+}
+
+/// Engine limited to pure computation on the values passed in
+fn sandboxed_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(64 * 1024);
+    engine.set_max_array_size(10_000);
+    engine.set_max_map_size(10_000);
+    engine.on_print(|text| log::info!("script: {}", text));
+    engine.on_debug(|text, _, _| log::debug!("script: {}", text));
+    engine
+}
+
+fn compile_file(engine: &Engine, path: &Path) -> Result<CompiledScript> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let source = std::fs::read_to_string(path)
+        .map_err(|e| Error::Script(format!("Failed to read {}: {}", path.display(), e)))?;
+    let ast = engine
+        .compile(source)
+        .map_err(|e| Error::Script(format!("Failed to compile {}: {}", path.display(), e)))?;
+
+    Ok(CompiledScript { ast, modified })
+}
+
+/// The message metadata exposed to scripts
+fn message_context(message: &PlainMessage) -> Result<Dynamic> {
+    let context = serde_json::json!({
+        "id": message.id,
+        "type": message.type_,
+        "from": message.from,
+        "to": message.to,
+        "thid": message.thid,
+        "pthid": message.pthid,
+        "created_time": message.created_time,
+        "expires_time": message.expires_time,
+        "body": message.body,
+    });
+    rhai::serde::to_dynamic(context).map_err(|e| Error::Script(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn transfer(amount: &str) -> PlainMessage {
+        PlainMessage {
+            id: "msg-1".to_string(),
+            typ: "application/didcomm-plain+json".to_string(),
+            type_: "https://tap.rsvp/schema/1.0#Transfer".to_string(),
+            body: serde_json::json!({ "asset": "eip155:1/slip44:60", "amount": amount }),
+            from: "did:example:originator".to_string(),
+            to: vec!["did:example:beneficiary".to_string()],
+            thid: None,
+            pthid: None,
+            extra_headers: Default::default(),
+            attachments: None,
+            created_time: None,
+            expires_time: None,
+            from_prior: None,
+        }
+    }
+
+    #[test]
+    fn test_route_by_amount() {
+        let hook = ScriptHook::from_source(
+            "route",
+            r##"
+            fn route(message) {
+                if message.type.ends_with("#Transfer") && parse_float(message.body.amount) > 10000.0 {
+                    return ["did:example:compliance", message.to[0]];
+                }
+            }
+            "##,
+        )
+        .unwrap();
+
+        assert!(hook.route(&transfer("100")).unwrap().is_empty());
+        assert_eq!(
+            hook.route(&transfer("20000")).unwrap(),
+            vec!["did:example:compliance", "did:example:beneficiary"]
+        );
+        assert!(hook.policy("tx-1", &transfer("20000")).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_policy_sees_transaction_id() {
+        let hook = ScriptHook::from_source(
+            "policy",
+            r#"
+            fn policy(message) {
+                message.body.amount = "0";
+                #{ action: "reject", reason: message.transaction_id }
+            }
+            "#,
+        )
+        .unwrap();
+
+        let message = transfer("5");
+        let result = hook.policy("tx-1", &message).unwrap().unwrap();
+        assert_eq!(result["action"], "reject");
+        assert_eq!(result["reason"], "tx-1");
+        assert_eq!(message.body["amount"], "5");
+    }
+
+    #[test]
+    fn test_reload_keeps_previous_version_on_error() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("route.rhai");
+        let write = |source: &str, age: u64| {
+            std::fs::write(&path, source).unwrap();
+            let modified = SystemTime::now() - std::time::Duration::from_secs(age);
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        };
+
+        write(r#"fn route(message) { "did:example:a" }"#, 30);
+        let hook = ScriptHook::from_file(&path).unwrap();
+        assert_eq!(hook.route(&transfer("1")).unwrap(), vec!["did:example:a"]);
+
+        write(r#"fn route(message) { "did:example:b" }"#, 20);
+        assert_eq!(hook.route(&transfer("1")).unwrap(), vec!["did:example:b"]);
+
+        write("fn route(message) {", 10);
+        assert!(hook.reload().is_err());
+        assert_eq!(hook.route(&transfer("1")).unwrap(), vec!["did:example:b"]);
+    }
+
+    #[test]
+    fn test_scripts_are_sandboxed() {
+        assert!(ScriptHook::from_source("eval", r#"fn route(m) { eval("1") }"#).is_err());
+
+        let hook =
+            ScriptHook::from_source("import", r#"fn route(m) { import "secrets" as s; s::did }"#)
+                .unwrap();
+        assert!(hook.route(&transfer("1")).is_err());
+
+        let hook = ScriptHook::from_source("loop", "fn route(m) { loop {} }").unwrap();
+        assert!(hook.route(&transfer("1")).is_err());
+    }
+}
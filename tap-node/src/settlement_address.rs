@@ -0,0 +1,361 @@
+//! Settlement addresses for Authorize responses
+//!
+//! An agent acting for the beneficiary of a Transfer (or the merchant of a
+//! Payment) tells the originator where to settle in its Authorize message.
+//! With [`NodeConfig::settlement_addresses`](crate::NodeConfig::settlement_addresses)
+//! set, the node asks a [`SettlementAddressProvider`] for an address on the
+//! chain of the asset whenever it auto-authorizes such a transaction, and
+//! sends it as the CAIP-10 `settlement_address` of the Authorize.
+//!
+//! Every address handed out is reserved in storage for its transaction, so
+//! an address is never given to two transactions: authorizing the same
+//! transaction again returns its reserved address, and an address the
+//! provider offers that is already reserved is skipped. Providers get the
+//! number of addresses already reserved on the chain as a derivation index:
+//! [`StaticAddressPool`] hands out the addresses of a pool in order,
+//! [`DerivedAddresses`] derives the address at the index (e.g. from an HD
+//! wallet's extended public key), and `WalletApiAddresses` asks an external
+//! wallet service for a fresh address.
+
+use crate::error::{Error, Result};
+#[cfg(feature = "state-machine")]
+use crate::storage::{SettlementAddressReservation, Storage};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+#[cfg(feature = "state-machine")]
+use tap_caip::AssetId;
+
+/// How many addresses are tried before giving up on finding an unreserved one
+pub const MAX_RESERVATION_ATTEMPTS: u32 = 16;
+
+/// A request for a settlement address
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AddressRequest {
+    /// Our agent that authorizes the transaction
+    pub agent_did: String,
+    /// The transaction being authorized
+    pub transaction_id: String,
+    /// CAIP-19 asset ID of the transaction
+    pub asset: String,
+    /// CAIP-2 chain ID the address must be on
+    pub chain_id: String,
+    /// Derivation index of the address: the number of addresses reserved on
+    /// the chain so far, plus the number of reserved ones already skipped
+    pub index: u32,
+}
+
+/// Source of addresses the node's agents receive settlements at
+#[async_trait]
+pub trait SettlementAddressProvider: Send + Sync + fmt::Debug {
+    /// Address on `request.chain_id` for the transaction, without the chain
+    /// prefix, or None if the provider has no address on that chain
+    async fn address(&self, request: &AddressRequest) -> Result<Option<String>>;
+}
+
+/// Provider handing out a fixed pool of addresses per chain, in order
+#[derive(Debug, Clone, Default)]
+pub struct StaticAddressPool {
+    addresses: HashMap<String, Vec<String>>,
+}
+
+impl StaticAddressPool {
+    /// Create an empty pool
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an address on a CAIP-2 chain to the pool
+    pub fn with_address(mut self, chain_id: impl Into<String>, address: impl Into<String>) -> Self {
+        self.addresses
+            .entry(chain_id.into())
+            .or_default()
+            .push(address.into());
+        self
+    }
+}
+
+#[async_trait]
+impl SettlementAddressProvider for StaticAddressPool {
+    async fn address(&self, request: &AddressRequest) -> Result<Option<String>> {
+        Ok(self
+            .addresses
+            .get(&request.chain_id)
+            .and_then(|addresses| addresses.get(request.index as usize))
+            .cloned())
+    }
+}
+
+/// Derivation of the address at an index, e.g. a BIP-32 child of an
+/// extended public key
+pub type DeriveAddress = dyn Fn(u32) -> Result<String> + Send + Sync;
+
+/// Provider deriving a new address per transaction, e.g. from an HD wallet
+#[derive(Clone, Default)]
+pub struct DerivedAddresses {
+    derivations: HashMap<String, Arc<DeriveAddress>>,
+}
+
+impl DerivedAddresses {
+    /// Create a provider without chains
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Derive the addresses on a CAIP-2 chain with `derive`
+    pub fn with_chain(
+        mut self,
+        chain_id: impl Into<String>,
+        derive: impl Fn(u32) -> Result<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.derivations.insert(chain_id.into(), Arc::new(derive));
+        self
+    }
+}
+
+impl fmt::Debug for DerivedAddresses {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DerivedAddresses")
+            .field("chains", &self.derivations.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl SettlementAddressProvider for DerivedAddresses {
+    async fn address(&self, request: &AddressRequest) -> Result<Option<String>> {
+        self.derivations
+            .get(&request.chain_id)
+            .map(|derive| derive(request.index))
+            .transpose()
+    }
+}
+
+/// Provider asking an external wallet service for addresses
+///
+/// Each request is POSTed as JSON to the service, which answers with
+/// `{"address": "..."}`, or with a null address or 404 if it has none on the
+/// chain.
+#[cfg(feature = "native")]
+#[derive(Debug, Clone)]
+pub struct WalletApiAddresses {
+    url: String,
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "native")]
+impl WalletApiAddresses {
+    /// Ask the wallet service at `url` for addresses
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            client: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(10))
+                .user_agent("TAP-Node/0.1")
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+#[async_trait]
+impl SettlementAddressProvider for WalletApiAddresses {
+    async fn address(&self, request: &AddressRequest) -> Result<Option<String>> {
+        #[derive(Deserialize)]
+        struct AddressResponse {
+            address: Option<String>,
+        }
+
+        let response = self
+            .client
+            .post(&self.url)
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| Error::Dispatch(format!("Wallet API request failed: {}", e)))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response
+            .error_for_status()
+            .map_err(|e| Error::Dispatch(format!("Wallet API request failed: {}", e)))?;
+        let body: AddressResponse = response
+            .json()
+            .await
+            .map_err(|e| Error::Serialization(format!("Invalid wallet API response: {}", e)))?;
+        Ok(body.address)
+    }
+}
+
+/// Reserve a settlement address for our agent's authorization of a
+/// transaction, returning it as a CAIP-10 account ID
+///
+/// Returns the address already reserved for the transaction if there is
+/// one, and None if the provider has no address on the asset's chain.
+#[cfg(feature = "state-machine")]
+pub(crate) async fn reserve(
+    provider: &dyn SettlementAddressProvider,
+    storage: &Storage,
+    agent_did: &str,
+    transaction_id: &str,
+    asset: &AssetId,
+) -> Result<Option<String>> {
+    let chain_id = asset.chain_id().to_string();
+    if let Some(reservation) = storage
+        .get_settlement_address_reservation(transaction_id, agent_did, &chain_id)
+        .await
+        .map_err(|e| Error::Storage(e.to_string()))?
+    {
+        return Ok(Some(reservation.account_id()));
+    }
+
+    let reserved = storage
+        .count_settlement_address_reservations(&chain_id)
+        .await
+        .map_err(|e| Error::Storage(e.to_string()))?;
+    let mut request = AddressRequest {
+        agent_did: agent_did.to_string(),
+        transaction_id: transaction_id.to_string(),
+        asset: asset.to_string(),
+        chain_id: chain_id.clone(),
+        index: reserved,
+    };
+
+    for _ in 0..MAX_RESERVATION_ATTEMPTS {
+        let Some(address) = provider.address(&request).await? else {
+            return Ok(None);
+        };
+        let reservation = SettlementAddressReservation {
+            chain_id: chain_id.clone(),
+            address,
+            agent_did: agent_did.to_string(),
+            transaction_id: transaction_id.to_string(),
+            asset: request.asset.clone(),
+            derivation_index: request.index,
+            created_at: storage.clock().now().to_rfc3339(),
+        };
+        if storage
+            .reserve_settlement_address(&reservation)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?
+        {
+            log::info!(
+                "Reserved settlement address {} for transaction {}",
+                reservation.account_id(),
+                transaction_id
+            );
+            return Ok(Some(reservation.account_id()));
+        }
+        log::debug!(
+            "Settlement address {} is already reserved, trying the next one",
+            reservation.account_id()
+        );
+        request.index += 1;
+    }
+
+    Err(Error::Processing(format!(
+        "No unreserved settlement address on {} after {} attempts",
+        chain_id, MAX_RESERVATION_ATTEMPTS
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(chain_id: &str, index: u32) -> AddressRequest {
+        AddressRequest {
+            agent_did: "did:example:vasp".to_string(),
+            transaction_id: "tx-1".to_string(),
+            asset: format!("{}/slip44:60", chain_id),
+            chain_id: chain_id.to_string(),
+            index,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_static_pool_hands_out_addresses_in_order() {
+        let pool = StaticAddressPool::new()
+            .with_address("eip155:1", "0x01")
+            .with_address("eip155:1", "0x02");
+
+        assert_eq!(
+            pool.address(&request("eip155:1", 1)).await.unwrap(),
+            Some("0x02".to_string())
+        );
+        assert_eq!(pool.address(&request("eip155:1", 2)).await.unwrap(), None);
+        assert_eq!(pool.address(&request("eip155:137", 0)).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_derived_addresses_use_index() {
+        let provider =
+            DerivedAddresses::new().with_chain("eip155:1", |index| Ok(format!("0xa{}", index)));
+
+        assert_eq!(
+            provider.address(&request("eip155:1", 7)).await.unwrap(),
+            Some("0xa7".to_string())
+        );
+        assert_eq!(
+            provider.address(&request("eip155:137", 7)).await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reserved_addresses_are_not_reused() {
+        let storage = Storage::new(Some(":memory:".into())).await.unwrap();
+        let asset: AssetId = "eip155:1/slip44:60".parse().unwrap();
+        let pool = StaticAddressPool::new()
+            .with_address("eip155:1", "0x01")
+            .with_address("eip155:1", "0x02");
+
+        let first = reserve(&pool, &storage, "did:example:vasp", "tx-1", &asset)
+            .await
+            .unwrap();
+        assert_eq!(first, Some("eip155:1:0x01".to_string()));
+        // Authorizing the same transaction again keeps its address
+        let again = reserve(&pool, &storage, "did:example:vasp", "tx-1", &asset)
+            .await
+            .unwrap();
+        assert_eq!(again, first);
+
+        let second = reserve(&pool, &storage, "did:example:vasp", "tx-2", &asset)
+            .await
+            .unwrap();
+        assert_eq!(second, Some("eip155:1:0x02".to_string()));
+        let exhausted = reserve(&pool, &storage, "did:example:vasp", "tx-3", &asset)
+            .await
+            .unwrap();
+        assert_eq!(exhausted, None);
+
+        let reservation = storage
+            .find_settlement_address_reservation("eip155:1", "0x02")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(reservation.transaction_id, "tx-2");
+        assert_eq!(reservation.derivation_index, 1);
+    }
+
+    #[tokio::test]
+    async fn test_addresses_reserved_elsewhere_are_skipped() {
+        let storage = Storage::new(Some(":memory:".into())).await.unwrap();
+        let asset: AssetId = "eip155:1/slip44:60".parse().unwrap();
+        // A wallet that keeps offering the same address until asked again
+        let provider = DerivedAddresses::new()
+            .with_chain("eip155:1", |index| Ok(format!("0x{}", index.max(1))));
+
+        let first = reserve(&provider, &storage, "did:example:vasp", "tx-1", &asset)
+            .await
+            .unwrap();
+        assert_eq!(first, Some("eip155:1:0x1".to_string()));
+        let second = reserve(&provider, &storage, "did:example:vasp", "tx-2", &asset)
+            .await
+            .unwrap();
+        assert_eq!(second, Some("eip155:1:0x2".to_string()));
+    }
+}
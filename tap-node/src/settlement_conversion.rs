@@ -0,0 +1,399 @@
+//! Conversion checks of cross-asset settlements
+//!
+//! A transaction may settle in another asset than it requested, e.g. a
+//! Payment of 100 USD settled with USDC. The Settle message then names the
+//! asset it settled in as `settledAsset`, with the settled `amount` in that
+//! asset. With
+//! [`NodeConfig::settlement_conversion`](crate::NodeConfig::settlement_conversion)
+//! set, the node records each such settlement as a [`SettlementConversion`]:
+//! the requested and settled amounts, the rate between the two assets at the
+//! time of the Settle and the slippage of the settled amount from the
+//! amount the rate gives.
+//!
+//! Rates come from an [`ExchangeRateProvider`], quoted in a pivot currency:
+//! the rate between two assets is the ratio of their rates in the pivot.
+//! A settlement whose slippage, in either direction, exceeds the tolerance
+//! configured for its pair of assets is out of tolerance: the node attaches
+//! a `settlement_slippage` warning to the transaction and publishes a
+//! [`NodeEvent::SettlementOutOfTolerance`](crate::event::NodeEvent::SettlementOutOfTolerance)
+//! event so it can be reviewed. The settlement itself is not undone, since
+//! it already happened on chain. Settlements with an asset that has no rate
+//! are recorded as unpriced.
+
+use crate::error::{Error, Result};
+#[cfg(feature = "state-machine")]
+use crate::event::{EventBus, NodeEvent};
+#[cfg(feature = "state-machine")]
+use crate::storage::Storage;
+use crate::storage::{ConversionStatus, SettlementConversion};
+use crate::valuation::{ExchangeRateProvider, SAME_CURRENCY_SOURCE};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+#[cfg(feature = "state-machine")]
+use tap_msg::message::TapMessage;
+
+/// Name of the warning attached to out-of-tolerance settlements
+pub const SLIPPAGE_WARNING_RULE: &str = "settlement_slippage";
+
+/// Default tolerance of the slippage of settled amounts: 1%
+pub const DEFAULT_MAX_SLIPPAGE: f64 = 0.01;
+
+/// How cross-asset settlements are checked
+#[derive(Debug, Clone)]
+pub struct SettlementConversionConfig {
+    /// Where rates come from
+    pub provider: Arc<dyn ExchangeRateProvider>,
+    /// ISO 4217 code of the currency rates are quoted in
+    pub currency: String,
+    /// Tolerated slippage of settled amounts, as a fraction of the expected
+    /// amount
+    pub max_slippage: f64,
+    /// Tolerated slippage per pair of requested and settled asset
+    pub pair_tolerances: HashMap<(String, String), f64>,
+}
+
+impl SettlementConversionConfig {
+    /// Check settlements at the rates of `provider`, quoted in `currency`,
+    /// with the default tolerance
+    pub fn new(provider: Arc<dyn ExchangeRateProvider>, currency: impl Into<String>) -> Self {
+        Self {
+            provider,
+            currency: currency.into().to_uppercase(),
+            max_slippage: DEFAULT_MAX_SLIPPAGE,
+            pair_tolerances: HashMap::new(),
+        }
+    }
+
+    /// Tolerate a slippage of `max_slippage` (a fraction, 0.01 for 1%)
+    pub fn with_max_slippage(mut self, max_slippage: f64) -> Self {
+        self.max_slippage = max_slippage;
+        self
+    }
+
+    /// Tolerate a slippage of `max_slippage` when settling `requested` in
+    /// `settled`, instead of the default tolerance
+    pub fn with_pair_tolerance(
+        mut self,
+        requested: impl Into<String>,
+        settled: impl Into<String>,
+        max_slippage: f64,
+    ) -> Self {
+        self.pair_tolerances
+            .insert((requested.into(), settled.into()), max_slippage);
+        self
+    }
+
+    /// Tolerated slippage when settling `requested` in `settled`
+    pub fn tolerance(&self, requested: &str, settled: &str) -> f64 {
+        self.pair_tolerances
+            .get(&(requested.to_string(), settled.to_string()))
+            .copied()
+            .unwrap_or(self.max_slippage)
+    }
+
+    /// Check a settlement of `requested_amount` of `requested_asset` with
+    /// `settled_amount` of `settled_asset` at time `at`
+    pub async fn check(
+        &self,
+        transaction_id: &str,
+        requested_asset: &str,
+        requested_amount: &str,
+        settled_asset: &str,
+        settled_amount: &str,
+        at: DateTime<Utc>,
+    ) -> Result<SettlementConversion> {
+        let parse = |amount: &str| {
+            amount
+                .parse::<f64>()
+                .map_err(|e| Error::Validation(format!("Invalid amount '{}': {}", amount, e)))
+        };
+        let requested = parse(requested_amount)?;
+        let settled = parse(settled_amount)?;
+        let max_slippage = self.tolerance(requested_asset, settled_asset);
+        let created_at = at.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+
+        let mut conversion = SettlementConversion {
+            transaction_id: transaction_id.to_string(),
+            requested_asset: requested_asset.to_string(),
+            requested_amount: requested_amount.to_string(),
+            settled_asset: settled_asset.to_string(),
+            settled_amount: settled_amount.to_string(),
+            rate: None,
+            rate_source: None,
+            expected_amount: None,
+            slippage: None,
+            max_slippage,
+            status: ConversionStatus::Unpriced,
+            created_at,
+        };
+
+        let (Some((requested_rate, requested_source)), Some((settled_rate, settled_source))) = (
+            self.pivot_rate(requested_asset, at).await?,
+            self.pivot_rate(settled_asset, at).await?,
+        ) else {
+            return Ok(conversion);
+        };
+        if settled_rate <= 0.0 {
+            return Ok(conversion);
+        }
+
+        // Units of the settled asset per unit of the requested asset
+        let rate = requested_rate / settled_rate;
+        let expected = requested * rate;
+        let slippage = if expected > 0.0 {
+            (settled - expected) / expected
+        } else {
+            0.0
+        };
+        conversion.rate = Some(rate);
+        conversion.rate_source = Some(if requested_source == settled_source {
+            requested_source
+        } else {
+            format!("{}/{}", requested_source, settled_source)
+        });
+        conversion.expected_amount = Some(expected);
+        conversion.slippage = Some(slippage);
+        conversion.status = if slippage.abs() > max_slippage {
+            ConversionStatus::OutOfTolerance
+        } else {
+            ConversionStatus::WithinTolerance
+        };
+        Ok(conversion)
+    }
+
+    /// Rate of an asset in the pivot currency, with its source
+    async fn pivot_rate(&self, asset: &str, at: DateTime<Utc>) -> Result<Option<(f64, String)>> {
+        if asset.eq_ignore_ascii_case(&self.currency) {
+            return Ok(Some((1.0, SAME_CURRENCY_SOURCE.to_string())));
+        }
+        Ok(self
+            .provider
+            .rate(asset, &self.currency, at)
+            .await?
+            .map(|rate| (rate.rate, rate.source)))
+    }
+
+    /// Check a Settle against the transaction it settles, store the
+    /// conversion and flag it if it is out of tolerance, logging failures
+    ///
+    /// Returns the conversion if the Settle names a settled asset other than
+    /// the requested one.
+    #[cfg(feature = "state-machine")]
+    pub(crate) async fn record(
+        &self,
+        storage: &Storage,
+        event_bus: &EventBus,
+        transaction_id: &str,
+        settle: &tap_msg::message::Settle,
+    ) -> Option<SettlementConversion> {
+        let settled_asset = settle.settled_asset.as_deref()?;
+        let transaction = match storage.get_transaction_by_id(transaction_id).await {
+            Ok(Some(transaction)) => transaction,
+            Ok(None) => return None,
+            Err(e) => {
+                log::warn!("Failed to load transaction {}: {}", transaction_id, e);
+                return None;
+            }
+        };
+        let (requested_asset, requested_amount) =
+            match requested_asset_and_amount(&transaction.message_json) {
+                Some(requested) => requested,
+                None => {
+                    log::warn!(
+                        "Transaction {} settled in {} has no requested asset",
+                        transaction_id,
+                        settled_asset
+                    );
+                    return None;
+                }
+            };
+        if requested_asset == settled_asset {
+            return None;
+        }
+        let settled_amount = settle.amount.as_deref().unwrap_or(&requested_amount);
+
+        let conversion = match self
+            .check(
+                transaction_id,
+                &requested_asset,
+                &requested_amount,
+                settled_asset,
+                settled_amount,
+                storage.clock().now(),
+            )
+            .await
+        {
+            Ok(conversion) => conversion,
+            Err(e) => {
+                log::warn!(
+                    "Failed to check conversion of transaction {}: {}",
+                    transaction_id,
+                    e
+                );
+                return None;
+            }
+        };
+        if let Err(e) = storage.record_settlement_conversion(&conversion).await {
+            log::warn!(
+                "Failed to store conversion of transaction {}: {}",
+                transaction_id,
+                e
+            );
+        }
+        if conversion.status == ConversionStatus::OutOfTolerance {
+            Self::flag(storage, event_bus, &conversion).await;
+        }
+        Some(conversion)
+    }
+
+    /// Attach a warning to the transaction of an out-of-tolerance settlement
+    /// and publish it
+    #[cfg(feature = "state-machine")]
+    async fn flag(storage: &Storage, event_bus: &EventBus, conversion: &SettlementConversion) {
+        let slippage = conversion.slippage.unwrap_or_default();
+        let expected_amount = conversion.expected_amount.unwrap_or_default();
+        log::warn!(
+            "Transaction {} settled {} {} where {} was expected ({:+.2}%, tolerance {:.2}%)",
+            conversion.transaction_id,
+            conversion.settled_amount,
+            conversion.settled_asset,
+            expected_amount,
+            slippage * 100.0,
+            conversion.max_slippage * 100.0
+        );
+        let reason = format!(
+            "Settled amount slipped {:+.2}% from the rate, more than the tolerated {:.2}%",
+            slippage * 100.0,
+            conversion.max_slippage * 100.0
+        );
+        let details = serde_json::to_value(conversion).unwrap_or_default();
+        if let Err(e) = storage
+            .insert_transaction_warning(
+                &conversion.transaction_id,
+                SLIPPAGE_WARNING_RULE,
+                "warn",
+                &reason,
+                &details,
+            )
+            .await
+        {
+            log::warn!(
+                "Failed to flag settlement of transaction {}: {}",
+                conversion.transaction_id,
+                e
+            );
+        }
+        event_bus
+            .publish_event(NodeEvent::SettlementOutOfTolerance {
+                transaction_id: conversion.transaction_id.clone(),
+                requested_asset: conversion.requested_asset.clone(),
+                requested_amount: conversion.requested_amount.clone(),
+                settled_asset: conversion.settled_asset.clone(),
+                settled_amount: conversion.settled_amount.clone(),
+                expected_amount,
+                slippage,
+                max_slippage: conversion.max_slippage,
+            })
+            .await;
+    }
+}
+
+/// Requested asset (CAIP-19 asset ID or ISO 4217 currency code) and amount
+/// of a stored Transfer or Payment
+#[cfg(feature = "state-machine")]
+fn requested_asset_and_amount(message_json: &serde_json::Value) -> Option<(String, String)> {
+    let message = serde_json::from_value(message_json.clone()).ok()?;
+    match TapMessage::from_plain_message(&message).ok()? {
+        TapMessage::Transfer(transfer) => Some((transfer.asset.to_string(), transfer.amount)),
+        TapMessage::Payment(payment) => payment
+            .asset
+            .as_ref()
+            .map(|asset| asset.to_string())
+            .or(payment.currency_code)
+            .map(|asset| (asset, payment.amount)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::valuation::StaticRates;
+    use chrono::TimeZone;
+
+    const USDC: &str = "eip155:1/erc20:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const ETH: &str = "eip155:1/slip44:60";
+
+    fn config() -> SettlementConversionConfig {
+        let rates = StaticRates::new("test-feed")
+            .with_rate(USDC, "USD", 0.998)
+            .with_rate(ETH, "USD", 2500.0);
+        SettlementConversionConfig::new(Arc::new(rates), "usd")
+    }
+
+    fn at() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_within_tolerance() {
+        let conversion = config()
+            .check("tx-1", "USD", "100.00", USDC, "100.20", at())
+            .await
+            .unwrap();
+
+        assert_eq!(conversion.status, ConversionStatus::WithinTolerance);
+        let expected = conversion.expected_amount.unwrap();
+        assert!((expected - 100.0 / 0.998).abs() < 1e-9);
+        assert!(conversion.slippage.unwrap().abs() < 0.001);
+        assert_eq!(
+            conversion.rate_source.as_deref(),
+            Some("same_currency/test-feed")
+        );
+        assert_eq!(conversion.created_at, "2026-03-01T12:00:00Z");
+    }
+
+    #[tokio::test]
+    async fn test_out_of_tolerance() {
+        // 1 ETH is worth about 2505 USDC, so 2400 USDC is short by over 4%
+        let conversion = config()
+            .check("tx-1", ETH, "1", USDC, "2400", at())
+            .await
+            .unwrap();
+        assert_eq!(conversion.status, ConversionStatus::OutOfTolerance);
+        assert!(conversion.slippage.unwrap() < -0.04);
+
+        // Overpaying is out of tolerance too
+        let conversion = config()
+            .check("tx-1", ETH, "1", USDC, "2700", at())
+            .await
+            .unwrap();
+        assert_eq!(conversion.status, ConversionStatus::OutOfTolerance);
+
+        // Unless the pair tolerates it
+        let conversion = config()
+            .with_pair_tolerance(ETH, USDC, 0.1)
+            .check("tx-1", ETH, "1", USDC, "2400", at())
+            .await
+            .unwrap();
+        assert_eq!(conversion.status, ConversionStatus::WithinTolerance);
+        assert_eq!(conversion.max_slippage, 0.1);
+    }
+
+    #[tokio::test]
+    async fn test_unpriced_and_invalid_amounts() {
+        let conversion = config()
+            .check("tx-1", "EUR", "100", USDC, "108", at())
+            .await
+            .unwrap();
+        assert_eq!(conversion.status, ConversionStatus::Unpriced);
+        assert!(conversion.rate.is_none());
+        assert!(conversion.slippage.is_none());
+
+        assert!(config()
+            .check("tx-1", "USD", "one hundred", USDC, "100", at())
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,354 @@
+//! Simulated counterparties
+//!
+//! Integrating with TAP means handling every way a counterparty can answer a
+//! Transfer: it may authorize it, reject it, ask for a presentation first or
+//! never answer at all. Testing that without a second deployment is what a
+//! simulated counterparty is for. [`TapNode::add_simulated_counterparty`]
+//! creates an agent with a fresh in-memory `did:key` that is not one of the
+//! node's agents. Messages our agents send to it are handed to it instead of
+//! being delivered over HTTPS, and it answers the Transfers and Payments it
+//! receives with the configured [`CounterpartyBehavior`]: after the
+//! behavior's delay, it signs its answer and the node receives it like any
+//! other message, so signature verification, the state machine and events
+//! all run as they would in production.
+//!
+//! Other messages sent to a simulated counterparty are accepted and ignored.
+//! Simulated counterparties live as long as the node and are not stored.
+
+use crate::error::{Error, Result};
+use crate::storage::SourceType;
+use crate::{IngestOutcome, TapNode};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tap_agent::{Agent, TapAgent};
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::{Authorize, Reject, RequestPresentation};
+use tracing::{debug, info, warn};
+
+/// Presentation definition requested by simulated counterparties unless
+/// configured otherwise
+pub const DEFAULT_PRESENTATION_DEFINITION: &str =
+    "https://tap.rsvp/presentation-definitions/ivms-101/2020";
+
+/// How a simulated counterparty answers the Transfers and Payments it
+/// receives
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CounterpartyBehavior {
+    /// Authorize after the delay
+    Authorize { delay: Duration },
+    /// Reject with the reason after the delay
+    Reject { delay: Duration, reason: String },
+    /// Ask the sender for a presentation after the delay
+    RequestPresentation {
+        delay: Duration,
+        presentation_definition: String,
+    },
+    /// Never answer
+    NeverRespond,
+}
+
+impl CounterpartyBehavior {
+    /// Time the counterparty waits before answering, None if it never does
+    pub fn delay(&self) -> Option<Duration> {
+        match self {
+            CounterpartyBehavior::Authorize { delay }
+            | CounterpartyBehavior::Reject { delay, .. }
+            | CounterpartyBehavior::RequestPresentation { delay, .. } => Some(*delay),
+            CounterpartyBehavior::NeverRespond => None,
+        }
+    }
+}
+
+impl fmt::Display for CounterpartyBehavior {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CounterpartyBehavior::Authorize { delay } => {
+                write!(f, "authorize:{}", delay.as_secs())
+            }
+            CounterpartyBehavior::Reject { delay, reason } => {
+                write!(f, "reject:{}:{}", delay.as_secs(), reason)
+            }
+            CounterpartyBehavior::RequestPresentation { delay, .. } => {
+                write!(f, "request-presentation:{}", delay.as_secs())
+            }
+            CounterpartyBehavior::NeverRespond => write!(f, "never"),
+        }
+    }
+}
+
+impl FromStr for CounterpartyBehavior {
+    type Err = String;
+
+    /// Parse `authorize[:SECONDS]`, `reject[:SECONDS[:REASON]]`,
+    /// `request-presentation[:SECONDS]` or `never`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut parts = s.trim().splitn(3, ':');
+        let kind = parts.next().unwrap_or_default();
+        let delay = match parts.next() {
+            Some(seconds) => Duration::from_secs(
+                seconds
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid delay in counterparty behavior {:?}", s))?,
+            ),
+            None => Duration::ZERO,
+        };
+        let reason = parts.next();
+        match (kind, reason) {
+            ("authorize", None) => Ok(CounterpartyBehavior::Authorize { delay }),
+            ("reject", reason) => Ok(CounterpartyBehavior::Reject {
+                delay,
+                reason: reason
+                    .unwrap_or("Rejected by simulated counterparty")
+                    .to_string(),
+            }),
+            ("request-presentation", None) => Ok(CounterpartyBehavior::RequestPresentation {
+                delay,
+                presentation_definition: DEFAULT_PRESENTATION_DEFINITION.to_string(),
+            }),
+            ("never", None) if delay.is_zero() => Ok(CounterpartyBehavior::NeverRespond),
+            _ => Err(format!(
+                "Invalid counterparty behavior {:?}, expected authorize[:SECONDS], \
+                 reject[:SECONDS[:REASON]], request-presentation[:SECONDS] or never",
+                s
+            )),
+        }
+    }
+}
+
+/// A simulated counterparty with its agent and behavior
+#[derive(Debug, Clone)]
+pub struct SimulatedCounterparty {
+    agent: Arc<TapAgent>,
+    behavior: CounterpartyBehavior,
+}
+
+impl SimulatedCounterparty {
+    /// DID of the counterparty's agent
+    pub fn did(&self) -> &str {
+        self.agent.get_agent_did()
+    }
+
+    /// How the counterparty answers
+    pub fn behavior(&self) -> &CounterpartyBehavior {
+        &self.behavior
+    }
+
+    /// Sign the counterparty's answer to a message, None if it does not
+    /// answer it
+    async fn answer(&self, message: &PlainMessage) -> Result<Option<String>> {
+        let message_type = message.type_.rsplit('#').next().unwrap_or_default();
+        if !matches!(message_type, "Transfer" | "Payment") {
+            return Ok(None);
+        }
+        let transaction_id = message.thid.as_deref().unwrap_or(&message.id);
+        let to = vec![message.from.as_str()];
+
+        let sent = match &self.behavior {
+            CounterpartyBehavior::Authorize { .. } => {
+                let authorize = Authorize {
+                    transaction_id: transaction_id.to_string(),
+                    settlement_address: None,
+                    expiry: None,
+                };
+                self.agent.send_message(&authorize, to, false).await
+            }
+            CounterpartyBehavior::Reject { reason, .. } => {
+                let reject = Reject::new(transaction_id, reason);
+                self.agent.send_message(&reject, to, false).await
+            }
+            CounterpartyBehavior::RequestPresentation {
+                presentation_definition,
+                ..
+            } => {
+                let request = RequestPresentation {
+                    transaction_id: transaction_id.to_string(),
+                    presentation_definition: presentation_definition.clone(),
+                    description: Some("Requested by simulated counterparty".to_string()),
+                    challenge: uuid::Uuid::new_v4().to_string(),
+                    for_originator: Some(true),
+                    for_beneficiary: None,
+                    metadata: Default::default(),
+                };
+                self.agent.send_message(&request, to, false).await
+            }
+            CounterpartyBehavior::NeverRespond => return Ok(None),
+        };
+        let (packed, _) = sent.map_err(|e| Error::Agent(e.to_string()))?;
+        Ok(Some(packed))
+    }
+}
+
+impl TapNode {
+    /// Create a simulated counterparty answering with the given behavior
+    ///
+    /// Returns the DID of its agent, to use as the counterparty's agent in
+    /// the Transfers and Payments our agents send. See the
+    /// [module documentation](crate::simulation).
+    pub async fn add_simulated_counterparty(
+        &self,
+        behavior: CounterpartyBehavior,
+    ) -> Result<String> {
+        let (agent, did) = TapAgent::from_ephemeral_key()
+            .await
+            .map_err(|e| Error::Agent(e.to_string()))?;
+        info!("Simulating counterparty {} ({})", did, behavior);
+        self.simulated_counterparties.insert(
+            did.clone(),
+            SimulatedCounterparty {
+                agent: Arc::new(agent),
+                behavior,
+            },
+        );
+        Ok(did)
+    }
+
+    /// Change how a simulated counterparty answers
+    ///
+    /// Returns whether the DID is a simulated counterparty.
+    pub fn set_simulated_behavior(&self, did: &str, behavior: CounterpartyBehavior) -> bool {
+        match self.simulated_counterparties.get_mut(did) {
+            Some(mut counterparty) => {
+                counterparty.behavior = behavior;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove a simulated counterparty; messages to it are delivered as to
+    /// any other DID from then on
+    ///
+    /// Returns whether the DID was a simulated counterparty.
+    pub fn remove_simulated_counterparty(&self, did: &str) -> bool {
+        self.simulated_counterparties.remove(did).is_some()
+    }
+
+    /// The simulated counterparties of the node
+    pub fn simulated_counterparties(&self) -> Vec<SimulatedCounterparty> {
+        self.simulated_counterparties
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
+    /// Whether a DID is a simulated counterparty
+    pub fn is_simulated_counterparty(&self, did: &str) -> bool {
+        self.simulated_counterparties.contains_key(did)
+    }
+
+    /// Hand a message to a simulated counterparty, which answers it in the
+    /// background once its delay has passed
+    pub(crate) fn deliver_to_simulated_counterparty(
+        &self,
+        recipient_did: &str,
+        message: &PlainMessage,
+    ) {
+        let Some(counterparty) = self
+            .simulated_counterparties
+            .get(recipient_did)
+            .map(|entry| entry.value().clone())
+        else {
+            return;
+        };
+        let Some(delay) = counterparty.behavior.delay() else {
+            debug!(
+                "Simulated counterparty {} does not answer message {}",
+                recipient_did, message.id
+            );
+            return;
+        };
+
+        let node = self.clone();
+        let message = message.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let answer = match counterparty.answer(&message).await {
+                Ok(Some(answer)) => answer,
+                Ok(None) => return,
+                Err(e) => {
+                    warn!(
+                        "Simulated counterparty {} failed to answer message {}: {}",
+                        counterparty.did(),
+                        message.id,
+                        e
+                    );
+                    return;
+                }
+            };
+            let answer = match serde_json::from_str(&answer) {
+                Ok(answer) => answer,
+                Err(e) => {
+                    warn!("Failed to parse simulated answer: {}", e);
+                    return;
+                }
+            };
+            // Received like any answer over HTTPS, under the intake limits
+            match node
+                .receive_message_from_source(answer, SourceType::Https, Some("simulation"))
+                .await
+            {
+                Ok(IngestOutcome::Rejected { code, reason }) => warn!(
+                    "Answer of simulated counterparty {} rejected ({}): {}",
+                    counterparty.did(),
+                    code,
+                    reason
+                ),
+                Ok(_) => {}
+                Err(e) => warn!(
+                    "Failed to receive answer of simulated counterparty {}: {}",
+                    counterparty.did(),
+                    e
+                ),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_behaviors() {
+        assert_eq!(
+            "authorize".parse::<CounterpartyBehavior>().unwrap(),
+            CounterpartyBehavior::Authorize {
+                delay: Duration::ZERO
+            }
+        );
+        assert_eq!(
+            "reject:5:Sanctions screening failed"
+                .parse::<CounterpartyBehavior>()
+                .unwrap(),
+            CounterpartyBehavior::Reject {
+                delay: Duration::from_secs(5),
+                reason: "Sanctions screening failed".to_string(),
+            }
+        );
+        assert_eq!(
+            "request-presentation:2"
+                .parse::<CounterpartyBehavior>()
+                .unwrap()
+                .delay(),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            "never".parse::<CounterpartyBehavior>().unwrap(),
+            CounterpartyBehavior::NeverRespond
+        );
+        assert!("authorize:soon".parse::<CounterpartyBehavior>().is_err());
+        assert!("settle".parse::<CounterpartyBehavior>().is_err());
+
+        let behavior = CounterpartyBehavior::Reject {
+            delay: Duration::from_secs(1),
+            reason: "No".to_string(),
+        };
+        assert_eq!(
+            behavior.to_string().parse::<CounterpartyBehavior>(),
+            Ok(behavior)
+        );
+    }
+}
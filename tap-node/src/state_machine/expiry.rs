@@ -0,0 +1,265 @@
+//! Transaction expiry
+//!
+//! A Transfer or Payment may carry a DIDComm `expires_time`, after which its
+//! sender no longer considers it valid. The [`ExpirySweeper`] finds pending
+//! transactions whose expiry has passed, plus a grace period, and cancels
+//! them through the [`StandardTransactionProcessor`]: one of our agents sends
+//! a Cancel to the counterparty, the transaction moves to cancelled and a
+//! [`NodeEvent::TransactionExpired`](crate::event::NodeEvent::TransactionExpired)
+//! event is published. Without it, a transaction the counterparty never
+//! responds to stays pending forever.
+
+use super::StandardTransactionProcessor;
+use crate::clock::{system_clock, Clock};
+use crate::error::{Error, Result};
+use crate::scheduler::{JobControl, Schedule, ScheduledJob, Scheduler};
+use async_trait::async_trait;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Name of the scheduled job cancelling expired transactions
+pub const EXPIRY_JOB: &str = "transaction_expiry";
+
+/// Reason given in Cancel messages sent for expired transactions
+pub const EXPIRY_CANCEL_REASON: &str = "Transaction expired";
+
+/// Number of expired transactions cancelled per sweep
+const EXPIRY_BATCH_SIZE: u32 = 100;
+
+/// Settings for cancelling expired transactions
+#[derive(Debug, Clone)]
+pub struct ExpiryPolicy {
+    /// Time a transaction may remain pending past its expiry before it is
+    /// cancelled, to allow for clock skew and in-flight responses
+    pub grace_period: Duration,
+    /// How often the background sweeper looks for expired transactions
+    pub check_interval: Duration,
+}
+
+impl Default for ExpiryPolicy {
+    fn default() -> Self {
+        Self {
+            grace_period: Duration::from_secs(60),
+            check_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Cancels transactions whose expiry has passed
+#[derive(Debug, Clone)]
+pub struct ExpirySweeper {
+    policy: ExpiryPolicy,
+    clock: Arc<dyn Clock>,
+}
+
+impl ExpirySweeper {
+    /// Create a new sweeper for the given policy
+    pub fn new(policy: ExpiryPolicy) -> Self {
+        Self {
+            policy,
+            clock: system_clock(),
+        }
+    }
+
+    /// Compare expiries against the given clock instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Get the expiry policy
+    pub fn policy(&self) -> &ExpiryPolicy {
+        &self.policy
+    }
+
+    /// Cancel the pending transactions that expired more than the grace
+    /// period ago
+    ///
+    /// At most [`EXPIRY_BATCH_SIZE`] transactions are cancelled per call.
+    /// Transactions that fail to cancel are logged and skipped.
+    ///
+    /// Returns the IDs of the cancelled transactions.
+    pub async fn sweep(&self, processor: &StandardTransactionProcessor) -> Result<Vec<String>> {
+        let grace = i64::try_from(self.policy.grace_period.as_secs()).unwrap_or(i64::MAX);
+        let cutoff = self.clock.now().timestamp().saturating_sub(grace);
+        let Ok(cutoff) = u64::try_from(cutoff) else {
+            return Ok(Vec::new());
+        };
+
+        let expired = processor
+            .storage
+            .list_expired_transaction_ids(cutoff, EXPIRY_BATCH_SIZE)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+
+        let mut cancelled = Vec::new();
+        for transaction_id in expired {
+            match processor.cancel_expired_transaction(&transaction_id).await {
+                Ok(()) => cancelled.push(transaction_id),
+                Err(e) => warn!(
+                    "Failed to cancel expired transaction {}: {}",
+                    transaction_id, e
+                ),
+            }
+        }
+
+        Ok(cancelled)
+    }
+
+    /// Register the sweeper as the [`EXPIRY_JOB`] of a scheduler, running
+    /// every check interval unless configured otherwise
+    ///
+    /// The job holds a weak reference to the processor and stops once it is
+    /// dropped.
+    pub async fn schedule(
+        self,
+        scheduler: &Scheduler,
+        processor: &Arc<StandardTransactionProcessor>,
+    ) {
+        let schedule = Schedule::Every(self.policy.check_interval);
+        debug!(
+            "Registering transaction expiry sweeper (grace period: {:?})",
+            self.policy.grace_period
+        );
+        let job = ExpiryJob {
+            sweeper: self,
+            processor: Arc::downgrade(processor),
+        };
+        scheduler
+            .register(EXPIRY_JOB, schedule, Arc::new(job))
+            .await;
+    }
+}
+
+struct ExpiryJob {
+    sweeper: ExpirySweeper,
+    processor: Weak<StandardTransactionProcessor>,
+}
+
+#[async_trait]
+impl ScheduledJob for ExpiryJob {
+    async fn run(&self) -> Result<JobControl> {
+        let Some(processor) = self.processor.upgrade() else {
+            debug!("State processor dropped, stopping transaction expiry sweeper");
+            return Ok(JobControl::Stop);
+        };
+
+        let cancelled = self.sweeper.sweep(&processor).await?;
+        if !cancelled.is_empty() {
+            info!("Cancelled {} expired transactions", cancelled.len());
+        }
+        Ok(JobControl::Continue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentRegistry;
+    use crate::clock::MockClock;
+    use crate::event::{EventBus, NodeEvent};
+    use crate::state_machine::fsm::DecisionMode;
+    use crate::state_machine::TransactionStateProcessor;
+    use crate::storage::{Storage, TransactionStatus};
+    use crate::test_fixtures::transfer_body;
+    use tap_agent::TapAgent;
+    use tap_caip::AssetId;
+    use tap_msg::didcomm::PlainMessage;
+    use tap_msg::message::tap_message_trait::TapMessageBody;
+    use tap_msg::message::Transfer;
+
+    fn transfer(id: &str, from: &str, to: &str, expires_time: u64) -> PlainMessage {
+        let transfer = Transfer {
+            asset: "eip155:1/erc20:0x6b175474e89094c44da98b954eedeac495271d0f"
+                .parse::<AssetId>()
+                .unwrap(),
+            transaction_id: Some(id.to_string()),
+            ..transfer_body("did:example:alice", "did:example:bob", "100.0")
+        };
+        let mut message = transfer.to_didcomm(from).unwrap();
+        message.id = id.to_string();
+        message.to = vec![to.to_string()];
+        message.expires_time = Some(expires_time);
+        message
+    }
+
+    #[tokio::test]
+    async fn test_sweep_cancels_expired_transactions() {
+        let clock = Arc::new(MockClock::default());
+        let storage = Arc::new(Storage::new_in_memory().await.unwrap());
+        let event_bus = Arc::new(EventBus::new());
+        let agents = Arc::new(AgentRegistry::new(None));
+        let (agent, agent_did) = TapAgent::from_ephemeral_key().await.unwrap();
+        agents
+            .register_agent(agent_did.clone(), Arc::new(agent))
+            .await
+            .unwrap();
+        let (_, counterparty_did) = TapAgent::from_ephemeral_key().await.unwrap();
+
+        let processor = StandardTransactionProcessor::new(
+            storage.clone(),
+            event_bus.clone(),
+            agents,
+            DecisionMode::EventBus,
+        );
+        let now = clock.now().timestamp() as u64;
+        for (id, expires_time) in [
+            ("tx-expired", now - 120),
+            ("tx-in-grace", now - 30),
+            ("tx-open", now + 3600),
+        ] {
+            processor
+                .process_outgoing_message(&transfer(
+                    id,
+                    &agent_did,
+                    &counterparty_did,
+                    expires_time,
+                ))
+                .await
+                .unwrap();
+        }
+
+        let mut events = event_bus.subscribe_channel();
+        let sweeper = ExpirySweeper::new(ExpiryPolicy::default()).with_clock(clock.clone());
+        assert_eq!(
+            sweeper.sweep(&processor).await.unwrap(),
+            vec!["tx-expired".to_string()]
+        );
+
+        let expired = storage
+            .get_transaction_by_id("tx-expired")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(expired.status, TransactionStatus::Cancelled);
+        for id in ["tx-in-grace", "tx-open"] {
+            let transaction = storage.get_transaction_by_id(id).await.unwrap().unwrap();
+            assert_eq!(transaction.status, TransactionStatus::Pending);
+        }
+
+        let mut expired_event = None;
+        while let Ok(event) = events.try_recv() {
+            if let NodeEvent::TransactionExpired {
+                transaction_id,
+                agent_did,
+                expires_time,
+            } = event.as_ref()
+            {
+                expired_event = Some((transaction_id.clone(), agent_did.clone(), *expires_time));
+            }
+        }
+        assert_eq!(
+            expired_event,
+            Some(("tx-expired".to_string(), agent_did.clone(), now - 120))
+        );
+
+        // Once the grace period has passed the next transaction is cancelled
+        clock.advance(chrono::Duration::seconds(60));
+        assert_eq!(
+            sweeper.sweep(&processor).await.unwrap(),
+            vec!["tx-in-grace".to_string()]
+        );
+        assert!(sweeper.sweep(&processor).await.unwrap().is_empty());
+    }
+}
@@ -298,6 +298,14 @@ pub enum FsmEvent {
         /// DID of the removed agent.
         agent_did: String,
     },
+
+    /// An agent was replaced by another agent (TAIP-5).
+    AgentReplaced {
+        /// DID of the replaced agent.
+        original_did: String,
+        /// DID of the agent replacing it.
+        replacement_did: String,
+    },
 }
 
 impl fmt::Display for FsmEvent {
@@ -323,6 +331,10 @@ impl fmt::Display for FsmEvent {
                 write!(f, "AgentsAdded({})", agent_dids.join(", "))
             }
             FsmEvent::AgentRemoved { agent_did } => write!(f, "AgentRemoved({})", agent_did),
+            FsmEvent::AgentReplaced {
+                original_did,
+                replacement_did,
+            } => write!(f, "AgentReplaced({} -> {})", original_did, replacement_did),
         }
     }
 }
@@ -757,6 +769,32 @@ impl TransactionFsm {
                 })
             }
 
+            (
+                _,
+                FsmEvent::AgentReplaced {
+                    original_did,
+                    replacement_did,
+                },
+            ) => {
+                if let Some(agent_state) = ctx.agents.get_mut(original_did) {
+                    *agent_state = AgentState::Removed;
+                }
+                ctx.agents
+                    .entry(replacement_did.clone())
+                    .or_insert(AgentState::Pending);
+                // The replacement has to authorize in its own right, unless
+                // it already has.
+                if from_state == TransactionState::ReadyToSettle && !ctx.all_agents_authorized() {
+                    ctx.state = TransactionState::PartiallyAuthorized;
+                }
+                Ok(Transition {
+                    from_state,
+                    to_state: ctx.state.clone(),
+                    event,
+                    decision: None,
+                })
+            }
+
             // ----- Invalid transitions -----
             _ => Err(InvalidTransition {
                 current_state: from_state,
@@ -777,6 +815,7 @@ impl TransactionFsm {
                 "PoliciesReceived",
                 "AgentsAdded",
                 "AgentRemoved",
+                "AgentReplaced",
             ],
             TransactionState::PolicyRequired => vec![
                 "PresentationReceived",
@@ -786,6 +825,7 @@ impl TransactionFsm {
                 "CancelReceived",
                 "AgentsAdded",
                 "AgentRemoved",
+                "AgentReplaced",
             ],
             TransactionState::PartiallyAuthorized => vec![
                 "AuthorizeReceived",
@@ -793,6 +833,7 @@ impl TransactionFsm {
                 "CancelReceived",
                 "AgentsAdded",
                 "AgentRemoved",
+                "AgentReplaced",
             ],
             TransactionState::ReadyToSettle => vec![
                 "SettleReceived",
@@ -801,6 +842,7 @@ impl TransactionFsm {
                 "CancelReceived",
                 "AgentsAdded",
                 "AgentRemoved",
+                "AgentReplaced",
             ],
             TransactionState::Settled => vec!["RevertReceived"],
             TransactionState::Rejected
@@ -1211,6 +1253,33 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_replaced_agent_must_be_authorized_by_replacement() {
+        let mut ctx = make_ctx(&["did:example:a"]);
+        TransactionFsm::apply(
+            &mut ctx,
+            FsmEvent::AuthorizeReceived {
+                agent_did: "did:example:a".to_string(),
+                settlement_address: None,
+                expiry: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(ctx.state, TransactionState::ReadyToSettle);
+
+        let t = TransactionFsm::apply(
+            &mut ctx,
+            FsmEvent::AgentReplaced {
+                original_did: "did:example:a".to_string(),
+                replacement_did: "did:example:b".to_string(),
+            },
+        )
+        .unwrap();
+        assert_eq!(t.to_state, TransactionState::PartiallyAuthorized);
+        assert_eq!(ctx.agents["did:example:a"], AgentState::Removed);
+        assert_eq!(ctx.pending_agents(), vec!["did:example:b".to_string()]);
+    }
+
     #[test]
     fn test_no_agents_goes_straight_to_ready() {
         let mut ctx = make_ctx(&[]);
@@ -7,13 +7,22 @@
 //!
 //! - [`fsm`]: Formal finite state machine with explicit states, transitions,
 //!   and decision points for the full transaction lifecycle.
+//! - [`expiry`]: Cancellation of transactions whose expiry passed before
+//!   they were settled.
 
+pub mod expiry;
 pub mod fsm;
 
+use crate::address_reuse::AddressReuseConfig;
 use crate::agent::AgentRegistry;
 use crate::error::{Error, Result};
 use crate::event::EventBus;
-use crate::storage::Storage;
+use crate::policy::{PolicyAction, PolicyContext, PolicyEngine, PolicyOutcome};
+use crate::settlement_address::{self, SettlementAddressProvider};
+use crate::settlement_conversion::SettlementConversionConfig;
+use crate::storage::{ConnectionStatus, DocumentStatus, Storage};
+use crate::validation::connection_limit_validator::ConnectionLimitValidator;
+use crate::valuation::ValuationConfig;
 use async_trait::async_trait;
 use dashmap::DashMap;
 use fsm::{
@@ -23,13 +32,21 @@ use fsm::{
 use std::sync::Arc;
 use tap_agent::Agent;
 use tap_msg::didcomm::PlainMessage;
-use tap_msg::message::TapMessage;
+use tap_msg::message::payment::InvoiceReference;
+use tap_msg::message::{
+    Agent as MessageAgent, AgentRole, Cancel, PartyType, Payment, RejectionCode, TapMessage,
+};
 
 /// Trait for processing transaction state changes
 #[async_trait]
 pub trait TransactionStateProcessor: Send + Sync {
     /// Process an incoming message and update transaction state
     async fn process_message(&self, message: &PlainMessage) -> Result<()>;
+
+    /// Update transaction state for a message one of our agents sent to another node
+    ///
+    /// Raises no decisions, since our agent has already acted.
+    async fn process_outgoing_message(&self, message: &PlainMessage) -> Result<()>;
 }
 
 /// Standard transaction state processor
@@ -46,6 +63,16 @@ pub struct StandardTransactionProcessor {
     decision_handler: Arc<dyn DecisionHandler>,
     /// Whether to auto-act on decisions (send Authorize/Settle messages).
     auto_act: bool,
+    /// Policy rules evaluated when authorization is required.
+    policy_engine: Option<Arc<PolicyEngine>>,
+    /// Fiat valuation of recorded transactions.
+    valuation: Option<ValuationConfig>,
+    /// Source of settlement addresses for auto-authorizations.
+    settlement_addresses: Option<Arc<dyn SettlementAddressProvider>>,
+    /// Checks of settlements made in another asset than requested.
+    settlement_conversion: Option<SettlementConversionConfig>,
+    /// Detection of settlement addresses reused across transactions.
+    address_reuse: Option<AddressReuseConfig>,
 }
 
 impl StandardTransactionProcessor {
@@ -69,27 +96,178 @@ impl StandardTransactionProcessor {
             contexts: DashMap::new(),
             decision_handler,
             auto_act,
+            policy_engine: None,
+            valuation: None,
+            settlement_addresses: None,
+            settlement_conversion: None,
+            address_reuse: None,
         }
     }
 
+    /// Whether decisions are acted on automatically.
+    pub(crate) fn auto_acts(&self) -> bool {
+        self.auto_act
+    }
+
+    /// Evaluate the given policy rules before authorizing new transactions.
+    pub fn with_policy_engine(mut self, policy_engine: Arc<PolicyEngine>) -> Self {
+        self.policy_engine = Some(policy_engine);
+        self
+    }
+
+    /// Tag recorded Transfers and Payments with their fiat value.
+    pub fn with_valuation(mut self, valuation: ValuationConfig) -> Self {
+        self.valuation = Some(valuation);
+        self
+    }
+
+    /// Send settlement addresses of the given provider when auto-authorizing.
+    pub fn with_settlement_addresses(
+        mut self,
+        provider: Arc<dyn SettlementAddressProvider>,
+    ) -> Self {
+        self.settlement_addresses = Some(provider);
+        self
+    }
+
+    /// Record and check settlements made in another asset than requested.
+    pub fn with_settlement_conversion(mut self, conversion: SettlementConversionConfig) -> Self {
+        self.settlement_conversion = Some(conversion);
+        self
+    }
+
+    /// Record settlement addresses and flag those reused across transactions.
+    pub fn with_address_reuse(mut self, address_reuse: AddressReuseConfig) -> Self {
+        self.address_reuse = Some(address_reuse);
+        self
+    }
+
     /// Extract agents from a Transfer or Payment message.
     /// Returns (agent_did, role) pairs for agents only (not primary parties).
     fn extract_agents_from_tap_message(tap_message: &TapMessage) -> Vec<(String, String)> {
-        let agents_list = match tap_message {
+        Self::initial_agents(tap_message)
+            .iter()
+            .map(|a| (a.id.clone(), Self::agent_role(a).to_string()))
+            .collect()
+    }
+
+    /// Agents a Transfer or Payment starts the transaction with
+    fn initial_agents(tap_message: &TapMessage) -> &[MessageAgent] {
+        match tap_message {
             TapMessage::Transfer(t) => &t.agents,
             TapMessage::Payment(p) => &p.agents,
-            _ => return Vec::new(),
+            _ => &[],
+        }
+    }
+
+    /// Role of an agent in the `transaction_agents` table
+    fn agent_role(agent: &MessageAgent) -> &'static str {
+        match &agent.role {
+            Some(AgentRole::Compliance) => "compliance",
+            _ => "other",
+        }
+    }
+
+    /// Record agents added to a transaction by a message
+    async fn add_participants(
+        &self,
+        transaction_id: &str,
+        agents: &[MessageAgent],
+        message: &PlainMessage,
+    ) {
+        for agent in agents {
+            if let Err(e) = self
+                .storage
+                .add_transaction_participant(transaction_id, agent, &message.from, &message.id)
+                .await
+            {
+                log::warn!(
+                    "Failed to record agent {} joining transaction {}: {}",
+                    agent.id,
+                    transaction_id,
+                    e
+                );
+            }
+        }
+    }
+
+    /// Store the documents referenced by the invoice of a Payment,
+    /// verifying embedded ones against their digest
+    async fn store_invoice_documents(
+        &self,
+        transaction_id: &str,
+        payment: &Payment,
+        message: &PlainMessage,
+    ) {
+        let Some(documents) = payment
+            .invoice
+            .as_ref()
+            .and_then(InvoiceReference::as_object)
+            .and_then(|invoice| invoice.additional_document_reference.as_ref())
+        else {
+            return;
         };
-        agents_list
-            .iter()
-            .map(|a| {
-                let role = match a.role.as_deref() {
-                    Some("compliance") => "compliance",
-                    _ => "other",
-                };
-                (a.id.clone(), role.to_string())
-            })
-            .collect()
+        for document in documents {
+            match self
+                .storage
+                .insert_document(transaction_id, &message.id, document)
+                .await
+            {
+                Ok(DocumentStatus::Mismatch) => log::warn!(
+                    "Document {} of transaction {} does not match its hash",
+                    document.id,
+                    transaction_id
+                ),
+                Ok(_) => {}
+                Err(e) => log::warn!(
+                    "Failed to store document {} of transaction {}: {}",
+                    document.id,
+                    transaction_id,
+                    e
+                ),
+            }
+        }
+    }
+
+    /// Take an agent out of a transaction, recording who removed it and,
+    /// for a ReplaceAgent message, the agent replacing it
+    async fn remove_participant(
+        &self,
+        transaction_id: &str,
+        agent_did: &str,
+        message: &PlainMessage,
+        replaced_by: Option<&str>,
+    ) {
+        if let Err(e) = self
+            .storage
+            .remove_transaction_agent(transaction_id, agent_did)
+            .await
+        {
+            log::warn!(
+                "Failed to remove agent {} from transaction {}: {}",
+                agent_did,
+                transaction_id,
+                e
+            );
+        }
+        if let Err(e) = self
+            .storage
+            .remove_transaction_participant(
+                transaction_id,
+                agent_did,
+                &message.from,
+                &message.id,
+                replaced_by,
+            )
+            .await
+        {
+            log::warn!(
+                "Failed to record agent {} leaving transaction {}: {}",
+                agent_did,
+                transaction_id,
+                e
+            );
+        }
     }
 
     /// Get or create the FSM context for a transaction.
@@ -144,6 +322,13 @@ impl StandardTransactionProcessor {
             TapMessage::AddAgents(add) => Some(FsmEvent::AgentsAdded {
                 agent_dids: add.agents.iter().map(|a| a.id.clone()).collect(),
             }),
+            TapMessage::ReplaceAgent(replace) => Some(FsmEvent::AgentReplaced {
+                original_did: replace.original.clone(),
+                replacement_did: replace.replacement.id.clone(),
+            }),
+            TapMessage::RemoveAgent(remove) => Some(FsmEvent::AgentRemoved {
+                agent_did: remove.agent.clone(),
+            }),
             TapMessage::UpdatePolicies(_) => Some(FsmEvent::PoliciesReceived {
                 from_did: plain.from.clone(),
             }),
@@ -164,6 +349,8 @@ impl StandardTransactionProcessor {
             TapMessage::Settle(s) => s.transaction_id.clone(),
             TapMessage::Revert(r) => r.transaction_id.clone(),
             TapMessage::AddAgents(a) => a.transaction_id.clone(),
+            TapMessage::ReplaceAgent(r) => r.transaction_id.clone(),
+            TapMessage::RemoveAgent(r) => r.transaction_id.clone(),
             TapMessage::UpdatePolicies(u) => u.transaction_id.clone(),
             // Presentation uses pthid/thid for threading
             _ => plain.thid.clone().unwrap_or_default(),
@@ -183,6 +370,233 @@ impl StandardTransactionProcessor {
             .await;
     }
 
+    // ---- Policy enforcement ----
+
+    /// Evaluate policy rules for a new transaction and enforce the outcome.
+    ///
+    /// Returns the enforced action, or `None` if no rule tripped.
+    async fn enforce_policies(
+        &self,
+        transaction_id: &str,
+        message: &PlainMessage,
+        tap_message: &TapMessage,
+    ) -> Option<PolicyAction> {
+        let policy_engine = self.policy_engine.as_ref()?;
+
+        let outcomes = policy_engine
+            .evaluate(&PolicyContext {
+                transaction_id,
+                message,
+                tap_message,
+                storage: &self.storage,
+            })
+            .await;
+
+        for outcome in &outcomes {
+            log::info!(
+                "Policy rule {} tripped for transaction {}: {} ({})",
+                outcome.rule,
+                transaction_id,
+                outcome.reason,
+                outcome.action
+            );
+            self.event_bus
+                .publish_policy_triggered(
+                    transaction_id.to_string(),
+                    outcome.rule.clone(),
+                    outcome.action.to_string(),
+                    outcome.reason.clone(),
+                    outcome.details.clone(),
+                )
+                .await;
+            if let Err(e) = self
+                .storage
+                .insert_transaction_warning(
+                    transaction_id,
+                    &outcome.rule,
+                    &outcome.action.to_string(),
+                    &outcome.reason,
+                    &outcome.details,
+                )
+                .await
+            {
+                log::warn!(
+                    "Failed to attach warning of rule {} to transaction {}: {}",
+                    outcome.rule,
+                    transaction_id,
+                    e
+                );
+            }
+        }
+
+        let action = PolicyEngine::strictest_action(&outcomes)?;
+        let outcome = outcomes.iter().find(|o| o.action == action)?;
+
+        let result = match action {
+            PolicyAction::Warn => Ok(()),
+            PolicyAction::Reject => self.send_policy_reject(message, tap_message, outcome).await,
+            PolicyAction::RequirePresentation => {
+                self.send_presentation_request(message, tap_message, outcome)
+                    .await
+            }
+            PolicyAction::ManualReview => {
+                self.queue_for_review(transaction_id, tap_message, outcome, &outcomes)
+                    .await
+            }
+        };
+        if let Err(e) = result {
+            log::warn!(
+                "Failed to enforce policy action {} for transaction {}: {}",
+                action,
+                transaction_id,
+                e
+            );
+        }
+
+        Some(action)
+    }
+
+    /// Stop the response timers of one of our agents that an answer the
+    /// processor sent on its behalf closes, see
+    /// [`response_timers`](crate::response_timers)
+    async fn answer_response_timers(
+        &self,
+        message: &PlainMessage,
+        agent_did: &str,
+        response_type: &str,
+    ) {
+        let thread_id = message.thid.as_deref().unwrap_or(&message.id);
+        if let Err(e) = self
+            .storage
+            .answer_response_timers(thread_id, agent_did, response_type, None)
+            .await
+        {
+            log::warn!(
+                "Failed to stop response timers of {} on thread {}: {}",
+                agent_did,
+                thread_id,
+                e
+            );
+        }
+    }
+
+    /// Our registered agents that participate in a transaction.
+    fn our_transaction_agents(&self, tap_message: &TapMessage) -> Vec<String> {
+        let our_agents = self.agents.get_all_dids();
+        Self::extract_agents_from_tap_message(tap_message)
+            .into_iter()
+            .map(|(did, _)| did)
+            .filter(|did| our_agents.contains(did))
+            .collect()
+    }
+
+    /// Send Reject from our agents because a policy rule tripped.
+    async fn send_policy_reject(
+        &self,
+        message: &PlainMessage,
+        tap_message: &TapMessage,
+        outcome: &PolicyOutcome,
+    ) -> Result<()> {
+        use tap_msg::message::tap_message_trait::Authorizable;
+
+        for agent_did in self.our_transaction_agents(tap_message) {
+            let mut reject_message = match tap_message {
+                TapMessage::Transfer(transfer) => transfer.reject(&agent_did, &outcome.reason),
+                TapMessage::Payment(payment) => payment.reject(&agent_did, &outcome.reason),
+                _ => continue,
+            };
+            reject_message.body.code = Some(RejectionCode::Policy);
+
+            let agent = self
+                .agents
+                .get_agent(&agent_did)
+                .await
+                .map_err(|e| Error::Agent(e.to_string()))?;
+            agent
+                .send_message(&reject_message.body, vec![message.from.as_str()], true)
+                .await
+                .map_err(|e| Error::Agent(e.to_string()))?;
+            self.answer_response_timers(message, &agent_did, "Reject")
+                .await;
+        }
+
+        Ok(())
+    }
+
+    /// Hold the transaction in the review queue for each of our agents.
+    ///
+    /// A reviewer later approves (sending Authorize) or rejects (sending Reject)
+    /// the transaction on behalf of the agent.
+    async fn queue_for_review(
+        &self,
+        transaction_id: &str,
+        tap_message: &TapMessage,
+        outcome: &PolicyOutcome,
+        outcomes: &[PolicyOutcome],
+    ) -> Result<()> {
+        let details = serde_json::json!({ "outcomes": outcomes });
+        for agent_did in self.our_transaction_agents(tap_message) {
+            self.storage
+                .insert_review_item(
+                    transaction_id,
+                    &agent_did,
+                    &outcome.rule,
+                    &outcome.reason,
+                    &details,
+                )
+                .await
+                .map_err(|e| Error::Storage(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Send UpdatePolicies requesting a presentation about the originating party.
+    async fn send_presentation_request(
+        &self,
+        message: &PlainMessage,
+        tap_message: &TapMessage,
+        outcome: &PolicyOutcome,
+    ) -> Result<()> {
+        use tap_msg::message::policy::{Policy, RequirePresentation};
+        use tap_msg::message::tap_message_trait::Transaction;
+
+        let about_party = match tap_message {
+            TapMessage::Transfer(transfer) => transfer.originator.as_ref().map(|p| p.id.clone()),
+            TapMessage::Payment(payment) => payment.customer.as_ref().map(|p| p.id.clone()),
+            _ => None,
+        };
+        let policy = Policy::RequirePresentation(RequirePresentation {
+            about_party,
+            purpose: Some(outcome.reason.clone()),
+            ..Default::default()
+        });
+
+        for agent_did in self.our_transaction_agents(tap_message) {
+            let update_message = match tap_message {
+                TapMessage::Transfer(transfer) => {
+                    transfer.update_policies(&agent_did, vec![policy.clone()])
+                }
+                TapMessage::Payment(payment) => {
+                    payment.update_policies(&agent_did, vec![policy.clone()])
+                }
+                _ => continue,
+            };
+
+            let agent = self
+                .agents
+                .get_agent(&agent_did)
+                .await
+                .map_err(|e| Error::Agent(e.to_string()))?;
+            agent
+                .send_message(&update_message.body, vec![message.from.as_str()], true)
+                .await
+                .map_err(|e| Error::Agent(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
     // ---- Auto-act methods (only called in AutoApprove mode) ----
 
     /// Automatically send Authorize for our registered agents.
@@ -203,11 +617,16 @@ impl StandardTransactionProcessor {
             if our_agents.contains(&agent_did) {
                 if let Ok(agent) = self.agents.get_agent(&agent_did).await {
                     use tap_msg::message::tap_message_trait::Authorizable;
+                    let settlement_address = self
+                        .reserve_settlement_address(&tap_message, &agent_did, &message.id)
+                        .await;
                     let authorize_message = match &tap_message {
                         TapMessage::Transfer(transfer) => {
-                            transfer.authorize(&agent_did, None, None)
+                            transfer.authorize(&agent_did, settlement_address.as_deref(), None)
+                        }
+                        TapMessage::Payment(payment) => {
+                            payment.authorize(&agent_did, settlement_address.as_deref(), None)
                         }
-                        TapMessage::Payment(payment) => payment.authorize(&agent_did, None, None),
                         _ => continue,
                     };
 
@@ -227,6 +646,9 @@ impl StandardTransactionProcessor {
                             agent_did,
                             e
                         );
+                    } else {
+                        self.answer_response_timers(message, &agent_did, "Authorize")
+                            .await;
                     }
                 }
             }
@@ -235,8 +657,61 @@ impl StandardTransactionProcessor {
         Ok(())
     }
 
+    /// Reserve a settlement address for our agent's Authorize, if the agent
+    /// acts for the beneficiary of a Transfer or the merchant of a Payment.
+    ///
+    /// A failure to reserve one is logged and the Authorize is sent without
+    /// a settlement address.
+    async fn reserve_settlement_address(
+        &self,
+        tap_message: &TapMessage,
+        agent_did: &str,
+        transaction_id: &str,
+    ) -> Option<String> {
+        let provider = self.settlement_addresses.as_ref()?;
+        let (asset, agents, receiver) = match tap_message {
+            TapMessage::Transfer(transfer) => (
+                &transfer.asset,
+                &transfer.agents,
+                &transfer.beneficiary.as_ref()?.id,
+            ),
+            TapMessage::Payment(payment) => (
+                payment.asset.as_ref()?,
+                &payment.agents,
+                &payment.merchant.id,
+            ),
+            _ => return None,
+        };
+        if !agents
+            .iter()
+            .any(|agent| agent.id == agent_did && agent.acts_for(receiver))
+        {
+            return None;
+        }
+
+        match settlement_address::reserve(
+            provider.as_ref(),
+            &self.storage,
+            agent_did,
+            transaction_id,
+            asset,
+        )
+        .await
+        {
+            Ok(address) => address,
+            Err(e) => {
+                log::warn!(
+                    "Failed to reserve a settlement address for transaction {}: {}",
+                    transaction_id,
+                    e
+                );
+                None
+            }
+        }
+    }
+
     /// Check if all agents authorized and send Settle if so.
-    async fn check_and_send_settle(&self, transaction_id: &str) -> Result<()> {
+    pub(crate) async fn check_and_send_settle(&self, transaction_id: &str) -> Result<()> {
         let transaction = self
             .storage
             .get_transaction_by_id(transaction_id)
@@ -353,16 +828,183 @@ impl StandardTransactionProcessor {
 
         Ok(())
     }
+
+    /// Cancel a transaction whose expiry passed before it was settled.
+    ///
+    /// The originating agent sends Cancel to the recipients if it is ours,
+    /// otherwise our receiving agent sends it back to the originator. The
+    /// transaction then moves to cancelled and a `TransactionExpired` event
+    /// is published.
+    pub(crate) async fn cancel_expired_transaction(&self, transaction_id: &str) -> Result<()> {
+        let transaction = self
+            .storage
+            .get_transaction_by_id(transaction_id)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?
+            .ok_or_else(|| Error::Storage(format!("Transaction {} not found", transaction_id)))?;
+
+        let transaction_message: PlainMessage =
+            serde_json::from_value(transaction.message_json.clone()).map_err(|e| {
+                Error::Serialization(format!("Failed to parse transaction message: {}", e))
+            })?;
+        let tap_message = TapMessage::from_plain_message(&transaction_message)
+            .map_err(|e| Error::InvalidPlainMessage(e.to_string()))?;
+
+        let our_agents = self.agents.get_all_dids();
+        let (agent_did, recipients, by) = if our_agents.contains(&transaction_message.from) {
+            let by = match tap_message {
+                TapMessage::Payment(_) => PartyType::Merchant,
+                _ => PartyType::Originator,
+            };
+            (
+                transaction_message.from.clone(),
+                transaction_message.to.clone(),
+                by,
+            )
+        } else if let Some(agent_did) = transaction_message
+            .to
+            .iter()
+            .find(|did| our_agents.contains(did))
+        {
+            let by = match tap_message {
+                TapMessage::Payment(_) => PartyType::Customer,
+                _ => PartyType::Beneficiary,
+            };
+            (
+                agent_did.clone(),
+                vec![transaction_message.from.clone()],
+                by,
+            )
+        } else {
+            return Err(Error::Processing(format!(
+                "No registered agent participates in transaction {}",
+                transaction_id
+            )));
+        };
+
+        let cancel = Cancel::with_reason(transaction_id, by.as_str(), expiry::EXPIRY_CANCEL_REASON);
+
+        log::info!(
+            "Transaction {} expired, sending Cancel from agent {}",
+            transaction_id,
+            agent_did
+        );
+
+        let agent = self
+            .agents
+            .get_agent(&agent_did)
+            .await
+            .map_err(|e| Error::Agent(e.to_string()))?;
+        let recipients_list: Vec<&str> = recipients
+            .iter()
+            .map(String::as_str)
+            .filter(|did| *did != agent_did)
+            .collect();
+        if let Err(e) = agent.send_message(&cancel, recipients_list, true).await {
+            log::warn!(
+                "Failed to send Cancel for expired transaction {}: {}",
+                transaction_id,
+                e
+            );
+        }
+
+        let mut cancel_message = PlainMessage::new_typed(cancel, &agent_did);
+        cancel_message.thid = Some(transaction_id.to_string());
+        let cancel_message = cancel_message
+            .to_plain_message()
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+        self.apply_message(&cancel_message, false).await?;
+
+        self.event_bus
+            .publish_transaction_expired(
+                transaction_id.to_string(),
+                agent_did,
+                transaction_message.expires_time.unwrap_or_default(),
+            )
+            .await;
+
+        Ok(())
+    }
 }
 
 #[async_trait]
 impl TransactionStateProcessor for StandardTransactionProcessor {
     async fn process_message(&self, message: &PlainMessage) -> Result<()> {
+        self.apply_message(message, true).await
+    }
+
+    async fn process_outgoing_message(&self, message: &PlainMessage) -> Result<()> {
+        self.apply_message(message, false).await
+    }
+}
+
+impl StandardTransactionProcessor {
+    /// Record a Connect request, or the answer to one
+    ///
+    /// Returns whether the message belongs to a connection rather than a
+    /// transaction.
+    async fn apply_connection_message(
+        &self,
+        tap_message: &TapMessage,
+        message: &PlainMessage,
+        reference_id: &str,
+    ) -> bool {
+        let status = match tap_message {
+            TapMessage::Connect(_) => {
+                if let Err(e) = self.storage.insert_connection(message).await {
+                    log::warn!("Failed to record connection {}: {}", message.id, e);
+                }
+                return true;
+            }
+            TapMessage::Authorize(_) => Some(ConnectionStatus::Authorized),
+            TapMessage::Reject(_) => Some(ConnectionStatus::Rejected),
+            TapMessage::AuthorizationRequired(_) => Some(ConnectionStatus::AuthorizationRequired),
+            TapMessage::UpdatePolicies(_) => None,
+            _ => return false,
+        };
+        if reference_id.is_empty() {
+            return false;
+        }
+        match self.storage.get_connection(reference_id).await {
+            Ok(Some(_)) => {}
+            Ok(None) => return false,
+            Err(e) => {
+                log::warn!("Failed to look up connection {}: {}", reference_id, e);
+                return false;
+            }
+        }
+        if let Some(status) = status {
+            if let Err(e) = self
+                .storage
+                .update_connection_status(reference_id, &status)
+                .await
+            {
+                log::warn!(
+                    "Failed to update status of connection {}: {}",
+                    reference_id,
+                    e
+                );
+            }
+        }
+        true
+    }
+
+    /// Update storage and FSM state for a message, handling decisions only
+    /// for messages from other nodes
+    async fn apply_message(&self, message: &PlainMessage, incoming: bool) -> Result<()> {
         let tap_message = TapMessage::from_plain_message(message)
             .map_err(|e| Error::InvalidPlainMessage(e.to_string()))?;
 
         let transaction_id = Self::transaction_id_for(&tap_message, message);
 
+        // Connect requests are tracked apart from transactions
+        if self
+            .apply_connection_message(&tap_message, message, &transaction_id)
+            .await
+        {
+            return Ok(());
+        }
+
         // Convert message to FSM event
         let fsm_event = Self::to_fsm_event(&tap_message, message);
 
@@ -372,6 +1014,40 @@ impl TransactionStateProcessor for StandardTransactionProcessor {
                 if let Err(e) = self.storage.insert_transaction(message).await {
                     log::warn!("Failed to insert transaction {}: {}", transaction_id, e);
                 }
+                if let TapMessage::Payment(payment) = &tap_message {
+                    self.store_invoice_documents(&transaction_id, payment, message)
+                        .await;
+                    if let (Some(address_reuse), Some(addresses)) =
+                        (&self.address_reuse, &payment.fallback_settlement_addresses)
+                    {
+                        for address in addresses {
+                            address_reuse
+                                .record(
+                                    &self.storage,
+                                    &self.event_bus,
+                                    &transaction_id,
+                                    address.as_str(),
+                                    &message.from,
+                                )
+                                .await;
+                        }
+                    }
+                }
+                if let Some(valuation) = &self.valuation {
+                    valuation
+                        .tag(&self.storage, &transaction_id, &tap_message)
+                        .await;
+                }
+                if let Err(e) = ConnectionLimitValidator::new(self.storage.clone())
+                    .record(message)
+                    .await
+                {
+                    log::warn!(
+                        "Failed to count transaction {} against its connection: {}",
+                        transaction_id,
+                        e
+                    );
+                }
                 let agents = Self::extract_agents_from_tap_message(&tap_message);
                 for (agent_did, role) in &agents {
                     if let Err(e) = self
@@ -387,8 +1063,10 @@ impl TransactionStateProcessor for StandardTransactionProcessor {
                         );
                     }
                 }
+                self.add_participants(&transaction_id, Self::initial_agents(&tap_message), message)
+                    .await;
             }
-            TapMessage::Authorize(_) => {
+            TapMessage::Authorize(authorize) => {
                 if let Err(e) = self
                     .storage
                     .update_transaction_agent_status(&transaction_id, &message.from, "authorized")
@@ -401,8 +1079,21 @@ impl TransactionStateProcessor for StandardTransactionProcessor {
                         e
                     );
                 }
+                if let (Some(address_reuse), Some(address)) =
+                    (&self.address_reuse, &authorize.settlement_address)
+                {
+                    address_reuse
+                        .record(
+                            &self.storage,
+                            &self.event_bus,
+                            &transaction_id,
+                            address,
+                            &message.from,
+                        )
+                        .await;
+                }
             }
-            TapMessage::Reject(_) => {
+            TapMessage::Reject(reject) => {
                 let _ = self
                     .storage
                     .update_transaction_agent_status(&transaction_id, &message.from, "rejected")
@@ -411,6 +1102,21 @@ impl TransactionStateProcessor for StandardTransactionProcessor {
                     .storage
                     .update_transaction_status(&transaction_id, "failed")
                     .await;
+                if let Err(e) = self
+                    .storage
+                    .record_rejection(
+                        &transaction_id,
+                        reject.code.as_ref().map(RejectionCode::as_str),
+                        reject.reason.as_deref(),
+                    )
+                    .await
+                {
+                    log::warn!(
+                        "Failed to record rejection of transaction {}: {}",
+                        transaction_id,
+                        e
+                    );
+                }
             }
             TapMessage::Cancel(_) => {
                 let _ = self
@@ -422,11 +1128,16 @@ impl TransactionStateProcessor for StandardTransactionProcessor {
                     .update_transaction_status(&transaction_id, "cancelled")
                     .await;
             }
-            TapMessage::Settle(_) => {
+            TapMessage::Settle(settle) => {
                 let _ = self
                     .storage
                     .update_transaction_status(&transaction_id, "confirmed")
                     .await;
+                if let Some(conversion) = &self.settlement_conversion {
+                    conversion
+                        .record(&self.storage, &self.event_bus, &transaction_id, settle)
+                        .await;
+                }
             }
             TapMessage::Revert(_) => {
                 let _ = self
@@ -436,15 +1147,41 @@ impl TransactionStateProcessor for StandardTransactionProcessor {
             }
             TapMessage::AddAgents(add) => {
                 for agent in &add.agents {
-                    let role_str = match agent.role.as_deref() {
-                        Some("compliance") => "compliance",
-                        _ => "other",
-                    };
                     let _ = self
                         .storage
-                        .insert_transaction_agent(&transaction_id, &agent.id, role_str)
+                        .insert_transaction_agent(
+                            &transaction_id,
+                            &agent.id,
+                            Self::agent_role(agent),
+                        )
                         .await;
                 }
+                self.add_participants(&transaction_id, &add.agents, message)
+                    .await;
+            }
+            TapMessage::ReplaceAgent(replace) => {
+                let replacement = &replace.replacement;
+                let _ = self
+                    .storage
+                    .insert_transaction_agent(
+                        &transaction_id,
+                        &replacement.id,
+                        Self::agent_role(replacement),
+                    )
+                    .await;
+                self.remove_participant(
+                    &transaction_id,
+                    &replace.original,
+                    message,
+                    Some(&replacement.id),
+                )
+                .await;
+                self.add_participants(&transaction_id, std::slice::from_ref(replacement), message)
+                    .await;
+            }
+            TapMessage::RemoveAgent(remove) => {
+                self.remove_participant(&transaction_id, &remove.agent, message, None)
+                    .await;
             }
             _ => {}
         }
@@ -479,7 +1216,16 @@ impl TransactionStateProcessor for StandardTransactionProcessor {
                     }
 
                     // Handle decision if one was produced
-                    if let Some(ref decision) = transition.decision {
+                    if let Some(decision) = transition.decision.as_ref().filter(|_| incoming) {
+                        // Evaluate policy rules before anything can authorize
+                        let policy_action =
+                            if matches!(decision, Decision::AuthorizationRequired { .. }) {
+                                self.enforce_policies(&transaction_id, message, &tap_message)
+                                    .await
+                            } else {
+                                None
+                            };
+
                         // Always notify the decision handler
                         self.decision_handler.handle_decision(&ctx, decision).await;
 
@@ -490,7 +1236,17 @@ impl TransactionStateProcessor for StandardTransactionProcessor {
                         if self.auto_act {
                             match decision {
                                 Decision::AuthorizationRequired { .. } => {
-                                    if let Err(e) = self.auto_authorize_transaction(message).await {
+                                    if let Some(action) =
+                                        policy_action.filter(|action| *action != PolicyAction::Warn)
+                                    {
+                                        log::info!(
+                                            "Not auto-authorizing transaction {}: policy action {}",
+                                            transaction_id,
+                                            action
+                                        );
+                                    } else if let Err(e) =
+                                        self.auto_authorize_transaction(message).await
+                                    {
                                         log::warn!(
                                             "Failed to auto-authorize transaction {}: {}",
                                             transaction_id,
@@ -1,10 +1,18 @@
 //! Agent-specific storage management
 //!
 //! This module provides the AgentStorageManager that handles per-agent storage instances,
-//! ensuring that each agent's data is isolated in its own SQLite database.
+//! ensuring that each agent's data is isolated in its own SQLite database. Agents can be
+//! grouped into organizations whose databases are queried together through a
+//! [`GroupStorageView`].
 
+use crate::clock::{system_clock, Clock};
 use crate::error::Result as NodeResult;
-use crate::storage::Storage;
+use crate::event::EventBus;
+use crate::storage::{
+    recovery, AgentGroup, AgentStorageUsage, GroupStorageView, MessageCipher, MessageEncryption,
+    ReadOnlyStorage, ReadReplicaConfig, Storage, StorageBackend, StorageError, StorageQuotas,
+    StorageRecovery,
+};
 use dashmap::DashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -18,8 +26,31 @@ use tracing::{debug, error, info};
 pub struct AgentStorageManager {
     /// Cache of agent storage instances (DID -> Storage)
     agent_storages: DashMap<String, Arc<Storage>>,
+    /// Cache of read-only handles on agent databases (DID -> ReadOnlyStorage)
+    agent_readers: DashMap<String, Arc<ReadOnlyStorage>>,
     /// TAP root directory for storage
     tap_root: Option<PathBuf>,
+    /// Quotas applied to agent databases
+    quotas: StorageQuotas,
+    /// Clock used by agent databases for timestamps
+    clock: Arc<dyn Clock>,
+    /// Agent groups by ID
+    groups: Arc<DashMap<String, AgentGroup>>,
+    /// Whether agent databases record mutations in their state event log
+    event_sourcing: bool,
+    /// Where read-only handles on agent databases are opened
+    read_replicas: ReadReplicaConfig,
+    /// How agent databases are checked and recovered when opened
+    recovery: StorageRecovery,
+    /// Where the recovery of corrupt agent databases is reported
+    event_bus: Option<Arc<EventBus>>,
+    /// Message types encrypted at rest in agent databases
+    message_encryption: Option<MessageEncryption>,
+    /// Keys of agent databases for at-rest encryption (DID -> key)
+    encryption_keys: DashMap<String, [u8; 32]>,
+    /// Backend the messages, transactions and deliveries of agent databases
+    /// run on
+    shared_storage: Option<Arc<dyn StorageBackend>>,
 }
 
 impl AgentStorageManager {
@@ -28,10 +59,100 @@ impl AgentStorageManager {
         info!("Creating AgentStorageManager with TAP root: {:?}", tap_root);
         Self {
             agent_storages: DashMap::new(),
+            agent_readers: DashMap::new(),
             tap_root,
+            quotas: StorageQuotas::default(),
+            clock: system_clock(),
+            groups: Arc::new(DashMap::new()),
+            event_sourcing: false,
+            read_replicas: ReadReplicaConfig::default(),
+            recovery: StorageRecovery::default(),
+            event_bus: None,
+            message_encryption: None,
+            encryption_keys: DashMap::new(),
+            shared_storage: None,
         }
     }
 
+    /// Enforce quotas on the agent databases opened by this manager
+    pub fn with_quotas(mut self, quotas: StorageQuotas) -> Self {
+        self.quotas = quotas;
+        self
+    }
+
+    /// Set the clock used by the agent databases opened by this manager
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Record mutations in the state event log of the agent databases opened
+    /// by this manager
+    pub fn with_event_sourcing(mut self, enabled: bool) -> Self {
+        self.event_sourcing = enabled;
+        self
+    }
+
+    /// Set where read-only handles on agent databases are opened
+    pub fn with_read_replicas(mut self, read_replicas: ReadReplicaConfig) -> Self {
+        self.read_replicas = read_replicas;
+        self
+    }
+
+    /// Set how agent databases are checked and recovered when opened
+    pub fn with_recovery(mut self, recovery: StorageRecovery) -> Self {
+        self.recovery = recovery;
+        self
+    }
+
+    /// Publish the recovery of corrupt agent databases on an event bus
+    pub fn with_event_bus(mut self, event_bus: Arc<EventBus>) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// Encrypt messages of the given types at rest in the databases of
+    /// agents whose key is set with [`set_encryption_key`](Self::set_encryption_key)
+    pub fn with_message_encryption(mut self, policy: MessageEncryption) -> Self {
+        self.message_encryption = Some(policy);
+        self
+    }
+
+    /// Run the messages, transactions and deliveries of the agent databases
+    /// opened by this manager on a shared backend, each scoped to its agent
+    /// and timestamped with the manager's clock
+    pub fn with_shared_storage(mut self, backend: Arc<dyn StorageBackend>) -> Self {
+        self.shared_storage = Some(backend);
+        self
+    }
+
+    /// Get the shared backend agent databases run on
+    pub fn shared_storage(&self) -> Option<&Arc<dyn StorageBackend>> {
+        self.shared_storage.as_ref()
+    }
+
+    /// Get the message types encrypted at rest
+    pub fn message_encryption(&self) -> Option<&MessageEncryption> {
+        self.message_encryption.as_ref()
+    }
+
+    /// Set the key an agent's database encrypts messages with
+    ///
+    /// An already opened storage of the agent is dropped from the cache, so
+    /// that the next [`get_agent_storage`](Self::get_agent_storage) opens it
+    /// with the key.
+    pub fn set_encryption_key(&self, agent_did: &str, key: [u8; 32]) {
+        self.encryption_keys.insert(agent_did.to_string(), key);
+        if self.agent_storages.remove(agent_did).is_some() {
+            debug!("Reopening storage for agent {} with encryption", agent_did);
+        }
+    }
+
+    /// Get the quotas applied to agent databases
+    pub fn quotas(&self) -> &StorageQuotas {
+        &self.quotas
+    }
+
     /// Get or create storage for an agent
     ///
     /// This method maintains a cache of storage instances to avoid recreating
@@ -46,14 +167,41 @@ impl AgentStorageManager {
 
         // Create new storage for this agent
         debug!("Creating new storage for agent: {}", agent_did);
-        let storage = Storage::new_with_did(agent_did, self.tap_root.clone())
-            .await
-            .map_err(|e| {
-                crate::Error::Storage(format!(
-                    "Failed to create storage for agent {}: {}",
-                    agent_did, e
-                ))
-            })?;
+        let storage_error = |e: StorageError| {
+            crate::Error::Storage(format!(
+                "Failed to create storage for agent {}: {}",
+                agent_did, e
+            ))
+        };
+        let db_path =
+            Storage::agent_db_location(agent_did, self.tap_root.clone()).map_err(storage_error)?;
+        let (storage, report) = match Storage::open_checked(Some(db_path), &self.recovery).await {
+            Ok(opened) => opened,
+            Err(e) => {
+                if let (StorageError::Corrupted(report), Some(event_bus)) = (&e, &self.event_bus) {
+                    recovery::publish_report(event_bus, report).await;
+                }
+                return Err(storage_error(e));
+            }
+        };
+        if let (Some(report), Some(event_bus)) = (&report, &self.event_bus) {
+            recovery::publish_report(event_bus, report).await;
+        }
+        let mut storage = storage
+            .with_clock(self.clock.clone())
+            .with_event_sourcing(self.event_sourcing);
+        if let Some(quota) = self.quotas.quota_for(agent_did) {
+            storage = storage.with_quota(quota);
+        }
+        if let Some(shared) = &self.shared_storage {
+            storage = storage.with_backend(shared.for_agent(agent_did, self.clock.clone()));
+        }
+        if let (Some(policy), Some(key)) = (
+            &self.message_encryption,
+            self.encryption_keys.get(agent_did),
+        ) {
+            storage = storage.with_message_encryption(MessageCipher::new(&key, policy.clone()));
+        }
 
         let storage_arc = Arc::new(storage);
 
@@ -65,6 +213,60 @@ impl AgentStorageManager {
         Ok(storage_arc)
     }
 
+    /// Get an agent's storage if its database exists, without creating one
+    pub async fn get_existing_agent_storage(
+        &self,
+        agent_did: &str,
+    ) -> NodeResult<Option<Arc<Storage>>> {
+        if let Some(storage) = self.get_cached_agent_storage(agent_did) {
+            return Ok(Some(storage));
+        }
+        let db_path = Storage::agent_db_location(agent_did, self.tap_root.clone())
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+        if !db_path.exists() {
+            return Ok(None);
+        }
+        self.get_agent_storage(agent_did).await.map(Some)
+    }
+
+    /// Get or create a read-only handle on an agent's database
+    ///
+    /// The handle has its own connection pool, on the agent's replica if one
+    /// is configured and present, otherwise on the primary database. Use it
+    /// for list, search and report queries so they do not compete with
+    /// message processing for connections.
+    pub async fn get_agent_reader(&self, agent_did: &str) -> NodeResult<Arc<ReadOnlyStorage>> {
+        if let Some(reader) = self.agent_readers.get(agent_did) {
+            return Ok(reader.clone());
+        }
+
+        // Opening the primary first creates and migrates the database
+        let storage = self.get_agent_storage(agent_did).await?;
+        let replica = self.read_replicas.replica_path(agent_did);
+        let reader = ReadOnlyStorage::for_primary(
+            &storage,
+            replica.as_deref(),
+            self.read_replicas.max_connections,
+        )
+        .await
+        .map_err(|e| {
+            crate::Error::Storage(format!(
+                "Failed to open read-only storage for agent {}: {}",
+                agent_did, e
+            ))
+        })?;
+
+        let reader = Arc::new(reader);
+        self.agent_readers
+            .insert(agent_did.to_string(), reader.clone());
+        debug!(
+            "Created and cached read-only storage for agent: {}",
+            agent_did
+        );
+
+        Ok(reader)
+    }
+
     /// Get storage for an agent if it exists in cache (doesn't create new one)
     pub fn get_cached_agent_storage(&self, agent_did: &str) -> Option<Arc<Storage>> {
         self.agent_storages.get(agent_did).map(|s| s.clone())
@@ -76,11 +278,34 @@ impl AgentStorageManager {
     /// Useful when an agent is unregistered.
     pub fn remove_agent_storage(&self, agent_did: &str) -> Option<Arc<Storage>> {
         debug!("Removing storage cache for agent: {}", agent_did);
+        self.agent_readers.remove(agent_did);
         self.agent_storages
             .remove(agent_did)
             .map(|(_, storage)| storage)
     }
 
+    /// Close an agent's database and delete its directory
+    ///
+    /// Returns whether there was a directory to delete.
+    pub async fn delete_agent_storage(&self, agent_did: &str) -> NodeResult<bool> {
+        if let Some(storage) = self.remove_agent_storage(agent_did) {
+            storage.pool().close().await;
+        }
+        let db_path = Storage::agent_db_location(agent_did, self.tap_root.clone())
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+        let Some(agent_dir) = db_path.parent().filter(|dir| dir.exists()) else {
+            return Ok(false);
+        };
+        std::fs::remove_dir_all(agent_dir).map_err(|e| {
+            crate::Error::Storage(format!(
+                "Failed to delete storage of agent {}: {}",
+                agent_did, e
+            ))
+        })?;
+        info!("Deleted storage of agent: {}", agent_did);
+        Ok(true)
+    }
+
     /// Get count of cached storage instances
     pub fn cached_storage_count(&self) -> usize {
         self.agent_storages.len()
@@ -103,6 +328,80 @@ impl AgentStorageManager {
         self.agent_storages.clear();
     }
 
+    /// Report the disk usage of each agent's database
+    pub async fn usage(&self, agent_dids: &[String]) -> NodeResult<Vec<AgentStorageUsage>> {
+        let mut report = Vec::with_capacity(agent_dids.len());
+        for agent_did in agent_dids {
+            let storage = self.get_agent_storage(agent_did).await?;
+            let usage = storage.usage().await.map_err(|e| {
+                crate::Error::Storage(format!(
+                    "Failed to report storage usage for agent {}: {}",
+                    agent_did, e
+                ))
+            })?;
+            report.push(AgentStorageUsage {
+                agent_did: agent_did.clone(),
+                usage,
+            });
+        }
+        Ok(report)
+    }
+
+    /// Define an agent group, replacing any group with the same ID
+    pub fn define_group(&self, group: AgentGroup) -> NodeResult<()> {
+        if group.id.is_empty() {
+            return Err(crate::Error::Configuration(
+                "Agent group ID cannot be empty".to_string(),
+            ));
+        }
+        info!(
+            "Defining agent group {} with {} members",
+            group.id,
+            group.members.len()
+        );
+        self.groups.insert(group.id.clone(), group);
+        Ok(())
+    }
+
+    /// Remove an agent group
+    ///
+    /// The members' databases are not affected.
+    pub fn remove_group(&self, group_id: &str) -> Option<AgentGroup> {
+        self.groups.remove(group_id).map(|(_, group)| group)
+    }
+
+    /// Get an agent group by ID
+    pub fn group(&self, group_id: &str) -> Option<AgentGroup> {
+        self.groups.get(group_id).map(|group| group.clone())
+    }
+
+    /// List all agent groups, ordered by ID
+    pub fn groups(&self) -> Vec<AgentGroup> {
+        let mut groups: Vec<AgentGroup> = self.groups.iter().map(|g| g.value().clone()).collect();
+        groups.sort_by(|a, b| a.id.cmp(&b.id));
+        groups
+    }
+
+    /// List the groups an agent belongs to, ordered by ID
+    pub fn groups_for_agent(&self, agent_did: &str) -> Vec<AgentGroup> {
+        let mut groups = self.groups();
+        groups.retain(|group| group.contains(agent_did));
+        groups
+    }
+
+    /// Open a view across the databases of a group's members
+    pub async fn group_view(&self, group_id: &str) -> NodeResult<GroupStorageView> {
+        let group = self
+            .group(group_id)
+            .ok_or_else(|| crate::Error::Storage(format!("Unknown agent group: {}", group_id)))?;
+
+        let mut storages = Vec::with_capacity(group.members.len());
+        for member in &group.members {
+            storages.push((member.clone(), self.get_agent_storage(member).await?));
+        }
+        Ok(GroupStorageView::new(group, storages))
+    }
+
     /// Ensure storage exists for an agent (creates if needed but doesn't cache)
     ///
     /// This is useful during agent registration to ensure the storage directory
@@ -138,6 +437,51 @@ mod tests {
         assert!(manager.cached_agent_dids().is_empty());
     }
 
+    #[tokio::test]
+    async fn test_set_encryption_key_reopens_storage() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = AgentStorageManager::new(Some(temp_dir.path().to_path_buf()))
+            .with_message_encryption(MessageEncryption::default());
+        let agent_did = "did:example:test-agent";
+
+        let storage = manager.get_agent_storage(agent_did).await.unwrap();
+        assert!(storage.message_encryption().is_none());
+
+        manager.set_encryption_key(agent_did, [1u8; 32]);
+        let storage = manager.get_agent_storage(agent_did).await.unwrap();
+        assert!(storage.message_encryption().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_get_agent_reader_uses_replica_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let replica_root = temp_dir.path().join("replicas");
+        let manager = AgentStorageManager::new(Some(temp_dir.path().join("primary")))
+            .with_read_replicas(ReadReplicaConfig {
+                replica_root: Some(replica_root.clone()),
+                max_connections: 2,
+            });
+
+        // Without a replica file the primary database is read
+        let agent_did = "did:example:test-agent";
+        let reader = manager.get_agent_reader(agent_did).await.unwrap();
+        let storage = manager.get_agent_storage(agent_did).await.unwrap();
+        assert_eq!(reader.db_path(), storage.db_path());
+        assert!(Arc::ptr_eq(
+            &reader,
+            &manager.get_agent_reader(agent_did).await.unwrap()
+        ));
+
+        let replicated_did = "did:example:replicated";
+        let replica_path = Storage::agent_db_path(&replica_root, replicated_did);
+        Storage::new(Some(replica_path.clone())).await.unwrap();
+        let reader = manager.get_agent_reader(replicated_did).await.unwrap();
+        assert_eq!(reader.db_path(), replica_path);
+
+        manager.remove_agent_storage(replicated_did);
+        assert!(manager.agent_readers.get(replicated_did).is_none());
+    }
+
     #[tokio::test]
     async fn test_get_agent_storage() {
         let temp_dir = TempDir::new().unwrap();
@@ -192,4 +536,81 @@ mod tests {
         assert!(cached_dids.contains(&agent1.to_string()));
         assert!(cached_dids.contains(&agent2.to_string()));
     }
+
+    #[tokio::test]
+    async fn test_group_view() {
+        use tap_msg::didcomm::PlainMessage;
+
+        let temp_dir = TempDir::new().unwrap();
+        let manager = AgentStorageManager::new(Some(temp_dir.path().to_path_buf()));
+
+        let retail = "did:example:retail";
+        let institutional = "did:example:institutional";
+        let outsider = "did:example:outsider";
+        manager
+            .define_group(AgentGroup::new(
+                "acme",
+                "Acme Exchange",
+                [retail, institutional],
+            ))
+            .unwrap();
+        assert_eq!(manager.groups_for_agent(retail).len(), 1);
+        assert!(manager.groups_for_agent(outsider).is_empty());
+
+        let transfer = |id: &str, from: &str, to: &str| {
+            PlainMessage::new(
+                id.to_string(),
+                "https://tap.rsvp/schema/1.0#Transfer".to_string(),
+                serde_json::json!({ "asset": "eip155:1/slip44:60", "amount": "1" }),
+                from.to_string(),
+            )
+            .with_recipient(to)
+        };
+
+        // An internal transfer is stored by both members
+        let internal = transfer("tx-internal", retail, institutional);
+        for agent in [retail, institutional] {
+            let storage = manager.get_agent_storage(agent).await.unwrap();
+            storage.insert_transaction(&internal).await.unwrap();
+        }
+        manager
+            .get_agent_storage(retail)
+            .await
+            .unwrap()
+            .insert_transaction(&transfer("tx-retail", retail, outsider))
+            .await
+            .unwrap();
+        manager
+            .get_agent_storage(outsider)
+            .await
+            .unwrap()
+            .insert_transaction(&transfer("tx-outsider", outsider, "did:example:other"))
+            .await
+            .unwrap();
+
+        let view = manager.group_view("acme").await.unwrap();
+        let transactions = view.list_transactions(10, 0).await.unwrap();
+        let mut ids: Vec<&str> = transactions
+            .iter()
+            .map(|tx| tx.record.reference_id.as_str())
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec!["tx-internal", "tx-retail"]);
+
+        let internal = view
+            .get_transaction_by_id("tx-internal")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(internal.agents, vec![retail, institutional]);
+        assert!(view
+            .get_transaction_by_id("tx-outsider")
+            .await
+            .unwrap()
+            .is_none());
+
+        assert!(manager.group_view("unknown").await.is_err());
+        assert!(manager.remove_group("acme").is_some());
+        assert!(manager.groups().is_empty());
+    }
 }
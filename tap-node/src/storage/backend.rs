@@ -0,0 +1,279 @@
+//! Pluggable persistence of messages, transactions and deliveries
+//!
+//! [`StorageBackend`] is the part of the storage API every persistence
+//! backend provides: the message audit trail, transactions and delivery
+//! tracking. [`Storage`] implements it on SQLite; with the `postgres`
+//! feature, [`PostgresBackend`](super::postgres_backend::PostgresBackend)
+//! implements it on a Postgres database that several nodes can share.
+//!
+//! A [`Storage`] given a backend with [`Storage::with_backend`] runs its
+//! messages, transactions and deliveries on it: the backend is their system
+//! of record. Reads of messages, transactions and the deliveries of a
+//! message are served by the backend. The agent database keeps the rest of
+//! the agent's state (reviews, decisions, customers, ...) and a copy of the
+//! backend's rows, with the same delivery IDs, for the queries that join
+//! them. Messages of types encrypted at rest only stay in the agent
+//! database: looking one up falls back to it, and message listings merge
+//! its messages with the backend's.
+//!
+//! The two stores share no transaction. Each write goes to the backend
+//! first, and a failed backend write fails the call without touching the
+//! agent database. If the backend write succeeds and the agent database
+//! write then fails, the call fails too, but the backend keeps its row:
+//! reads served by the backend see it, while the queries joining the agent
+//! database's copy miss it. Nothing reconciles the two afterwards: logging
+//! a message again writes its missing copy, since the backend ignores a
+//! message logged twice, but a transaction recorded again fails with
+//! [`StorageError::DuplicateTransaction`], and a delivery created again is
+//! a new delivery.
+//!
+//! Given a backend through
+//! [`NodeConfig::shared_storage`](crate::NodeConfig::shared_storage), every
+//! agent database of a node runs on it, scoped to its agent with
+//! [`StorageBackend::for_agent`].
+
+use super::db::Storage;
+use super::error::StorageError;
+use super::models::{
+    Delivery, DeliveryStatus, DeliveryType, Message, MessageDirection, Transaction,
+};
+use crate::clock::Clock;
+use async_trait::async_trait;
+use std::fmt;
+use std::sync::Arc;
+use tap_msg::didcomm::PlainMessage;
+
+/// Persistence of messages, transactions and deliveries
+///
+/// The methods behave as the [`Storage`] methods of the same names: logging
+/// a message twice is not an error, recording a transaction twice is
+/// ([`StorageError::DuplicateTransaction`]), listings are newest first, and
+/// deliveries are identified by the IDs `create_delivery` returns.
+#[async_trait]
+pub trait StorageBackend: Send + Sync + fmt::Debug {
+    /// Name of the backend, e.g. `sqlite`
+    fn name(&self) -> &'static str;
+
+    /// Handle on the records of one agent, timestamping them with `clock`
+    ///
+    /// Backends holding the records of several agents scope the returned
+    /// handle to the agent; a SQLite database already belongs to one and
+    /// has a clock of its own.
+    fn for_agent(&self, agent_did: &str, clock: Arc<dyn Clock>) -> Arc<dyn StorageBackend>;
+
+    /// Log a message in the audit trail
+    async fn log_message(
+        &self,
+        message: &PlainMessage,
+        direction: MessageDirection,
+    ) -> Result<(), StorageError>;
+
+    /// Get a logged message by its ID
+    async fn get_message_by_id(&self, message_id: &str) -> Result<Option<Message>, StorageError>;
+
+    /// List logged messages, optionally of one direction
+    async fn list_messages(
+        &self,
+        limit: u32,
+        offset: u32,
+        direction: Option<MessageDirection>,
+    ) -> Result<Vec<Message>, StorageError>;
+
+    /// Record a Transfer or Payment as a transaction
+    async fn insert_transaction(&self, message: &PlainMessage) -> Result<(), StorageError>;
+
+    /// Update the status of a transaction
+    async fn update_transaction_status(
+        &self,
+        transaction_id: &str,
+        status: &str,
+    ) -> Result<(), StorageError>;
+
+    /// Record the reason of the Reject message that failed a transaction
+    async fn record_rejection(
+        &self,
+        transaction_id: &str,
+        code: Option<&str>,
+        reason: Option<&str>,
+    ) -> Result<(), StorageError>;
+
+    /// Get a transaction by its reference ID
+    async fn get_transaction_by_id(
+        &self,
+        reference_id: &str,
+    ) -> Result<Option<Transaction>, StorageError>;
+
+    /// List transactions
+    async fn list_transactions(
+        &self,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<Transaction>, StorageError>;
+
+    /// Record a pending delivery of a message, returning its ID
+    async fn create_delivery(
+        &self,
+        message_id: &str,
+        message_text: &str,
+        recipient_did: &str,
+        delivery_url: Option<&str>,
+        delivery_type: DeliveryType,
+    ) -> Result<i64, StorageError>;
+
+    /// Update the status of a delivery
+    async fn update_delivery_status(
+        &self,
+        delivery_id: i64,
+        status: DeliveryStatus,
+        http_status_code: Option<i32>,
+        error_message: Option<&str>,
+    ) -> Result<(), StorageError>;
+
+    /// Count a retry of a delivery
+    async fn increment_delivery_retry_count(&self, delivery_id: i64) -> Result<(), StorageError>;
+
+    /// Schedule the next attempt of a delivery, or none
+    async fn schedule_delivery_attempt(
+        &self,
+        delivery_id: i64,
+        next_attempt_at: Option<&str>,
+    ) -> Result<(), StorageError>;
+
+    /// Deliver a message to a different endpoint from its next attempt on
+    async fn reassign_delivery(
+        &self,
+        delivery_id: i64,
+        delivery_url: &str,
+    ) -> Result<(), StorageError>;
+
+    /// Get the deliveries of a message, oldest first
+    async fn get_deliveries_for_message(
+        &self,
+        message_id: &str,
+    ) -> Result<Vec<Delivery>, StorageError>;
+}
+
+#[async_trait]
+impl StorageBackend for Storage {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn for_agent(&self, _agent_did: &str, _clock: Arc<dyn Clock>) -> Arc<dyn StorageBackend> {
+        Arc::new(self.clone())
+    }
+
+    async fn log_message(
+        &self,
+        message: &PlainMessage,
+        direction: MessageDirection,
+    ) -> Result<(), StorageError> {
+        Storage::log_message(self, message, direction).await
+    }
+
+    async fn get_message_by_id(&self, message_id: &str) -> Result<Option<Message>, StorageError> {
+        Storage::get_message_by_id(self, message_id).await
+    }
+
+    async fn list_messages(
+        &self,
+        limit: u32,
+        offset: u32,
+        direction: Option<MessageDirection>,
+    ) -> Result<Vec<Message>, StorageError> {
+        Storage::list_messages(self, limit, offset, direction).await
+    }
+
+    async fn insert_transaction(&self, message: &PlainMessage) -> Result<(), StorageError> {
+        Storage::insert_transaction(self, message).await
+    }
+
+    async fn update_transaction_status(
+        &self,
+        transaction_id: &str,
+        status: &str,
+    ) -> Result<(), StorageError> {
+        Storage::update_transaction_status(self, transaction_id, status).await
+    }
+
+    async fn record_rejection(
+        &self,
+        transaction_id: &str,
+        code: Option<&str>,
+        reason: Option<&str>,
+    ) -> Result<(), StorageError> {
+        Storage::record_rejection(self, transaction_id, code, reason).await
+    }
+
+    async fn get_transaction_by_id(
+        &self,
+        reference_id: &str,
+    ) -> Result<Option<Transaction>, StorageError> {
+        Storage::get_transaction_by_id(self, reference_id).await
+    }
+
+    async fn list_transactions(
+        &self,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<Transaction>, StorageError> {
+        Storage::list_transactions(self, limit, offset).await
+    }
+
+    async fn create_delivery(
+        &self,
+        message_id: &str,
+        message_text: &str,
+        recipient_did: &str,
+        delivery_url: Option<&str>,
+        delivery_type: DeliveryType,
+    ) -> Result<i64, StorageError> {
+        Storage::create_delivery(
+            self,
+            message_id,
+            message_text,
+            recipient_did,
+            delivery_url,
+            delivery_type,
+        )
+        .await
+    }
+
+    async fn update_delivery_status(
+        &self,
+        delivery_id: i64,
+        status: DeliveryStatus,
+        http_status_code: Option<i32>,
+        error_message: Option<&str>,
+    ) -> Result<(), StorageError> {
+        Storage::update_delivery_status(self, delivery_id, status, http_status_code, error_message)
+            .await
+    }
+
+    async fn increment_delivery_retry_count(&self, delivery_id: i64) -> Result<(), StorageError> {
+        Storage::increment_delivery_retry_count(self, delivery_id).await
+    }
+
+    async fn schedule_delivery_attempt(
+        &self,
+        delivery_id: i64,
+        next_attempt_at: Option<&str>,
+    ) -> Result<(), StorageError> {
+        Storage::schedule_delivery_attempt(self, delivery_id, next_attempt_at).await
+    }
+
+    async fn reassign_delivery(
+        &self,
+        delivery_id: i64,
+        delivery_url: &str,
+    ) -> Result<(), StorageError> {
+        Storage::reassign_delivery(self, delivery_id, delivery_url).await
+    }
+
+    async fn get_deliveries_for_message(
+        &self,
+        message_id: &str,
+    ) -> Result<Vec<Delivery>, StorageError> {
+        Storage::get_deliveries_for_message(self, message_id).await
+    }
+}
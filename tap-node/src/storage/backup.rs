@@ -0,0 +1,192 @@
+//! Online backups with the SQLite backup API
+//!
+//! [`Storage::backup_to`] copies a database to a file while it stays in use,
+//! producing a consistent snapshot. [`Storage::restore_from`] copies a backup
+//! back over the database, through the storage's own connection pool, so
+//! handles on the database see the restored contents.
+
+use super::db::Storage;
+use super::error::StorageError;
+use libsqlite3_sys as ffi;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_int;
+use std::path::Path;
+use std::time::Duration;
+use tracing::info;
+
+/// How often a backup step that found the database locked is retried
+const BUSY_RETRIES: u32 = 50;
+
+/// Wait between retries of a locked backup step
+const BUSY_WAIT: Duration = Duration::from_millis(20);
+
+impl Storage {
+    /// Copy the database to `path` with the SQLite online backup API
+    ///
+    /// The copy is a consistent snapshot, taken while the database stays in
+    /// use. An existing file at `path` is overwritten.
+    pub async fn backup_to(&self, path: &Path) -> Result<(), StorageError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        info!("Backing up {:?} to {:?}", self.db_path(), path);
+        self.copy_with_file(path, false).await
+    }
+
+    /// Replace the contents of the database with a backup taken by
+    /// [`Storage::backup_to`]
+    ///
+    /// Migrations are run again afterwards, so backups taken by an older
+    /// version are brought up to the current schema.
+    pub async fn restore_from(&self, path: &Path) -> Result<(), StorageError> {
+        if !path.is_file() {
+            return Err(StorageError::NotFound(format!(
+                "Backup {} does not exist",
+                path.display()
+            )));
+        }
+        info!("Restoring {:?} from {:?}", self.db_path(), path);
+        self.copy_with_file(path, true).await?;
+
+        sqlx::migrate!("./migrations")
+            .run(self.pool())
+            .await
+            .map_err(|e| StorageError::Migration(e.to_string()))?;
+        self.backfill_canonical_hashes().await
+    }
+
+    /// Copy the database to a file, or a file over the database
+    async fn copy_with_file(&self, path: &Path, restore: bool) -> Result<(), StorageError> {
+        let file = CString::new(path.to_string_lossy().as_bytes())
+            .map_err(|e| StorageError::Backup(format!("Invalid backup path: {}", e)))?;
+
+        let mut conn = self.pool().acquire().await?;
+        let mut handle = conn.lock_handle().await?;
+        let db = handle.as_raw_handle().as_ptr();
+
+        // SAFETY: `db` is the connection locked by `handle`, which outlives
+        // every use of it below
+        unsafe {
+            let flags = if restore {
+                ffi::SQLITE_OPEN_READONLY
+            } else {
+                ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE
+            };
+            let mut file_db = std::ptr::null_mut();
+            let rc = ffi::sqlite3_open_v2(file.as_ptr(), &mut file_db, flags, std::ptr::null());
+            if rc != ffi::SQLITE_OK {
+                let error = error_message(file_db);
+                ffi::sqlite3_close(file_db);
+                return Err(StorageError::Backup(format!(
+                    "Could not open {}: {}",
+                    path.display(),
+                    error
+                )));
+            }
+
+            let (dest, source) = if restore {
+                (db, file_db)
+            } else {
+                (file_db, db)
+            };
+            let result = run_backup(dest, source);
+            ffi::sqlite3_close(file_db);
+            result
+        }
+    }
+}
+
+/// Copy all pages of `source` to `dest`, retrying while either is locked
+///
+/// # Safety
+///
+/// Both pointers must be open connections not used elsewhere during the copy.
+unsafe fn run_backup(
+    dest: *mut ffi::sqlite3,
+    source: *mut ffi::sqlite3,
+) -> Result<(), StorageError> {
+    let main = c"main";
+    let backup = ffi::sqlite3_backup_init(dest, main.as_ptr(), source, main.as_ptr());
+    if backup.is_null() {
+        return Err(StorageError::Backup(error_message(dest)));
+    }
+
+    let mut retries = 0;
+    let mut rc: c_int;
+    loop {
+        rc = ffi::sqlite3_backup_step(backup, -1);
+        match rc {
+            ffi::SQLITE_BUSY | ffi::SQLITE_LOCKED if retries < BUSY_RETRIES => {
+                retries += 1;
+                std::thread::sleep(BUSY_WAIT);
+            }
+            _ => break,
+        }
+    }
+
+    let finish = ffi::sqlite3_backup_finish(backup);
+    if rc != ffi::SQLITE_DONE || finish != ffi::SQLITE_OK {
+        return Err(StorageError::Backup(error_message(dest)));
+    }
+    Ok(())
+}
+
+/// The message of the last error on a connection
+///
+/// # Safety
+///
+/// `db` must be null or a connection handle returned by SQLite.
+unsafe fn error_message(db: *mut ffi::sqlite3) -> String {
+    if db.is_null() {
+        return "out of memory".to_string();
+    }
+    CStr::from_ptr(ffi::sqlite3_errmsg(db))
+        .to_string_lossy()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MessageDirection;
+    use tap_msg::didcomm::PlainMessage;
+    use tempfile::TempDir;
+
+    fn message(id: &str) -> PlainMessage {
+        PlainMessage::new(
+            id.to_string(),
+            "https://tap.rsvp/schema/1.0#Reject".to_string(),
+            serde_json::json!({ "reason": "Sanctioned" }),
+            "did:example:sender".to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_backup_and_restore_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let storage = Storage::new(Some(dir.path().join("transactions.db")))
+            .await
+            .unwrap();
+        storage
+            .log_message(&message("msg-1"), MessageDirection::Incoming)
+            .await
+            .unwrap();
+
+        let backup = dir.path().join("backups").join("transactions.db");
+        storage.backup_to(&backup).await.unwrap();
+
+        storage
+            .log_message(&message("msg-2"), MessageDirection::Incoming)
+            .await
+            .unwrap();
+        assert_eq!(storage.list_messages(10, 0, None).await.unwrap().len(), 2);
+
+        storage.restore_from(&backup).await.unwrap();
+        let messages = storage.list_messages(10, 0, None).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].message_id, "msg-1");
+
+        let missing = storage.restore_from(&dir.path().join("missing.db")).await;
+        assert!(matches!(missing, Err(StorageError::NotFound(_))));
+    }
+}
@@ -1,17 +1,53 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sha2::{Digest, Sha256};
 use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
 use sqlx::Row;
+use std::collections::HashSet;
 use std::env;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tap_agent::{JwsVerification, KeyAttestation, PresentationChallenge};
 use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::DocumentReference;
+use tap_msg::utils::canonical_transaction_hash;
 use tracing::{debug, info};
 
+use super::backend::StorageBackend;
+use super::encryption::{self, MessageCipher};
 use super::error::StorageError;
+use super::event_store::{self, RebuildReport, StateEvent, StoredStateEvent, TransactionStateAt};
 use super::models::{
-    Customer, CustomerIdentifier, CustomerRelationship, DecisionLogEntry, DecisionStatus,
-    DecisionType, Delivery, DeliveryStatus, DeliveryType, IdentifierType, Message,
-    MessageDirection, Received, ReceivedStatus, SchemaType, SourceType, Transaction,
-    TransactionStatus, TransactionType,
+    AddressSighting, AuthorizationChallenge, ChallengeStatus, Connection, ConnectionStatus,
+    ConversionStatus, CounterpartyBaseline, CounterpartyProfile, CounterpartyStats, Customer,
+    CustomerErasure, CustomerIdentifier, CustomerMerge, CustomerRelationship, DeadLetter,
+    DecisionLogEntry, DecisionStatus, DecisionType, Delivery, DeliveryStatus, DeliveryType,
+    DocumentStatus, Draft, DraftKind, DraftStatus, ForwardStatus, ForwardedMessage, IdentifierType,
+    KeyAttestationRecord, KeyAttestationStatus, LatencyStats, MailboxMessage, MailboxStatus,
+    MailboxSummary, MergeStatus, Message, MessageDirection, MessageVerification, PiiAccess,
+    PinnedDidDocument, PresentationVerificationRecord, PresentationVerificationStatus, Received,
+    ReceivedFilter, ReceivedStatus, ResponseTimeStats, ResponseTimer, ResponseTimerStatus,
+    ReusedAddress, ReviewItem, ReviewStatus, ScheduledJobRecord, ScheduledJobStatus, SchemaType,
+    SettlementAddressReservation, SettlementConversion, SourceType, TimelineEntry, Transaction,
+    TransactionDocument, TransactionGraph, TransactionGraphNode, TransactionParticipant,
+    TransactionPii, TransactionStatus, TransactionType, TransactionValuation, TransactionWarning,
 };
+use super::quota::{QuotaAction, StorageQuota, StorageUsage, TableUsage};
+use crate::address_reuse::normalize_address;
+use crate::clock::{system_clock, Clock};
+use crate::compat::CompatibilityRules;
+use crate::credentials::VerifiedPresentationSummary;
+use crate::interop::{EnvelopeProfile, EnvelopeSerialization, EnvelopeStats};
+use crate::message::{EnvelopeInfo, EnvelopeKind};
+use crate::response_timers::ResponseTimerRule;
+use crate::timeouts::ProcessingStage;
+
+/// Type prefix of TAP protocol messages
+const TAP_MESSAGE_TYPE_PREFIX: &str = "https://tap.rsvp/schema/";
+
+/// Longest chain of parent threads followed by transaction graph queries,
+/// which also bounds the traversal of cyclic links
+const MAX_TRANSACTION_GRAPH_DEPTH: u32 = 64;
 
 /// Storage backend for TAP transactions and message audit trail
 ///
@@ -54,6 +90,12 @@ use super::models::{
 pub struct Storage {
     pool: SqlitePool,
     db_path: PathBuf,
+    quota: Option<StorageQuota>,
+    clock: Arc<dyn Clock>,
+    event_sourcing: bool,
+    cipher: Option<Arc<MessageCipher>>,
+    /// Backend messages, transactions and deliveries run on
+    backend: Option<Arc<dyn StorageBackend>>,
 }
 
 impl Storage {
@@ -78,6 +120,17 @@ impl Storage {
         agent_did: &str,
         tap_root: Option<PathBuf>,
     ) -> Result<Self, StorageError> {
+        Self::new(Some(Self::agent_db_location(agent_did, tap_root)?)).await
+    }
+
+    /// Database path of an agent, under `tap_root` or the default TAP root
+    ///
+    /// The default root is `TAP_HOME`, `TAP_ROOT`, `$TAP_TEST_DIR/.tap` or
+    /// `~/.tap`, whichever is set first.
+    pub fn agent_db_location(
+        agent_did: &str,
+        tap_root: Option<PathBuf>,
+    ) -> Result<PathBuf, StorageError> {
         let root_dir = match tap_root {
             Some(root) => root,
             None => {
@@ -99,11 +152,32 @@ impl Storage {
             }
         };
 
-        // Sanitize the DID for use as a directory name (prevent path traversal)
-        let sanitized_did = agent_did.replace([':', '/', '\\'], "_").replace("..", "_");
-        let db_path = root_dir.join(&sanitized_did).join("transactions.db");
+        Ok(Self::agent_db_path(&root_dir, agent_did))
+    }
+
+    /// Database path of an agent under a TAP root directory:
+    /// `{root}/{sanitized_did}/transactions.db`
+    pub(super) fn agent_db_path(root: &Path, agent_did: &str) -> PathBuf {
+        root.join(Self::sanitize_did(agent_did))
+            .join("transactions.db")
+    }
+
+    /// A DID made safe for use as a directory name (prevents path traversal)
+    pub fn sanitize_did(agent_did: &str) -> String {
+        agent_did.replace([':', '/', '\\'], "_").replace("..", "_")
+    }
 
-        Self::new(Some(db_path)).await
+    /// Wrap an existing pool, e.g. one of read-only connections
+    pub(super) fn from_pool(pool: SqlitePool, db_path: PathBuf) -> Self {
+        Storage {
+            pool,
+            db_path,
+            quota: None,
+            clock: system_clock(),
+            event_sourcing: false,
+            cipher: None,
+            backend: None,
+        }
     }
 
     /// Create a new in-memory storage instance for testing
@@ -129,6 +203,11 @@ impl Storage {
         Ok(Storage {
             pool,
             db_path: PathBuf::from(":memory:"),
+            quota: None,
+            clock: system_clock(),
+            event_sourcing: false,
+            cipher: None,
+            backend: None,
         })
     }
 
@@ -148,11 +227,7 @@ impl Storage {
     /// - Migrations fail to run
     /// - Connection pool cannot be created
     pub async fn new(path: Option<PathBuf>) -> Result<Self, StorageError> {
-        let db_path = path.unwrap_or_else(|| {
-            env::var("TAP_NODE_DB_PATH")
-                .unwrap_or_else(|_| "tap-node.db".to_string())
-                .into()
-        });
+        let db_path = Self::resolve_db_path(path);
 
         info!("Initializing storage at: {:?}", db_path);
 
@@ -184,7 +259,248 @@ impl Storage {
             .await
             .map_err(|e| StorageError::Migration(e.to_string()))?;
 
-        Ok(Storage { pool, db_path })
+        let storage = Storage {
+            pool,
+            db_path,
+            quota: None,
+            clock: system_clock(),
+            event_sourcing: false,
+            cipher: None,
+            backend: None,
+        };
+        storage.backfill_canonical_hashes().await?;
+        Ok(storage)
+    }
+
+    /// The database path, or `TAP_NODE_DB_PATH`, or `./tap-node.db`
+    pub(super) fn resolve_db_path(path: Option<PathBuf>) -> PathBuf {
+        path.unwrap_or_else(|| {
+            env::var("TAP_NODE_DB_PATH")
+                .unwrap_or_else(|_| "tap-node.db".to_string())
+                .into()
+        })
+    }
+
+    /// The connection pool of this database
+    pub(super) fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    /// Enforce a quota on this database, see [`StorageQuota`]
+    pub fn with_quota(mut self, quota: StorageQuota) -> Self {
+        self.quota = Some(quota);
+        self
+    }
+
+    /// Run the messages, transactions and deliveries of this database on a
+    /// backend, see [`backend`](super::backend)
+    pub fn with_backend(mut self, backend: Arc<dyn StorageBackend>) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Get the backend messages, transactions and deliveries run on
+    pub fn backend(&self) -> Option<&Arc<dyn StorageBackend>> {
+        self.backend.as_ref()
+    }
+
+    /// Set the clock used for timestamps written by this storage
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Record every mutation of the transaction and delivery tables in the
+    /// state event log, see [`event_store`](super::event_store)
+    pub fn with_event_sourcing(mut self, enabled: bool) -> Self {
+        self.event_sourcing = enabled;
+        self
+    }
+
+    /// Whether mutations are recorded in the state event log
+    pub fn event_sourcing(&self) -> bool {
+        self.event_sourcing
+    }
+
+    /// Get the clock used for timestamps written by this storage
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    /// Encrypt the stored copies of messages of the cipher's message types,
+    /// see [`encryption`](super::encryption)
+    pub fn with_message_encryption(mut self, cipher: MessageCipher) -> Self {
+        self.cipher = Some(Arc::new(cipher));
+        self
+    }
+
+    /// Get the cipher encrypting the stored copies of sensitive messages
+    pub fn message_encryption(&self) -> Option<&MessageCipher> {
+        self.cipher.as_deref()
+    }
+
+    /// The cipher, if messages of the given type are encrypted
+    fn cipher_for(&self, message_type: &str) -> Option<&MessageCipher> {
+        self.cipher
+            .as_deref()
+            .filter(|cipher| cipher.applies_to(message_type))
+    }
+
+    /// The cipher, if the envelopes and delivered texts of messages of the
+    /// given type are encrypted: those of the cipher's message types and
+    /// those carrying the personal data of parties
+    fn envelope_cipher_for(&self, message_type: &str) -> Option<&MessageCipher> {
+        self.cipher.as_deref().filter(|cipher| {
+            cipher.applies_to(message_type) || encryption::carries_parties(message_type)
+        })
+    }
+
+    /// The type of a logged message
+    async fn message_type_of(&self, message_id: &str) -> Result<Option<String>, StorageError> {
+        Ok(
+            sqlx::query_scalar("SELECT message_type FROM messages WHERE message_id = ?1")
+                .bind(message_id)
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    /// The cipher and the associated data to bind with, if the delivered
+    /// text of the message with the given ID is encrypted
+    async fn cipher_for_message(
+        &self,
+        message_id: &str,
+    ) -> Result<Option<(&MessageCipher, Vec<u8>)>, StorageError> {
+        if self.cipher.is_none() {
+            return Ok(None);
+        }
+        Ok(self
+            .message_type_of(message_id)
+            .await?
+            .and_then(|message_type| {
+                self.envelope_cipher_for(&message_type).map(|cipher| {
+                    (
+                        cipher,
+                        encryption::message_binding(message_id, &message_type),
+                    )
+                })
+            }))
+    }
+
+    /// Seal the parties of a message under the data key of its transaction
+    ///
+    /// Each party carrying more than its `@id` is encrypted with
+    /// [`Storage::seal_transaction_pii`] and replaced by a reference to the
+    /// sealed data, `{"@id": ..., "piiRef": role}`. Returns None if the
+    /// storage has no encryption key or the message has no personal data.
+    async fn seal_parties(
+        &self,
+        message: &PlainMessage,
+    ) -> Result<Option<PlainMessage>, StorageError> {
+        if self.cipher.is_none() || !encryption::carries_parties(&message.type_) {
+            return Ok(None);
+        }
+        // Only UpdateParty refers to its transaction by thread
+        let transaction_id = match &message.thid {
+            Some(thid) if message.type_.ends_with("#UpdateParty") => thid.clone(),
+            _ => message.id.clone(),
+        };
+
+        let mut redacted = message.clone();
+        let mut sealed = false;
+        for field in encryption::PARTY_FIELDS {
+            let Some(party) = message.body.get(*field).and_then(|p| p.as_object()) else {
+                continue;
+            };
+            if party.keys().all(|key| key == "@id" || key == "piiRef") {
+                continue;
+            }
+            let role = match *field {
+                "party" => message
+                    .body
+                    .get("partyType")
+                    .and_then(|t| t.as_str())
+                    .unwrap_or("party"),
+                field => field,
+            };
+            self.seal_transaction_pii(
+                &transaction_id,
+                role,
+                None,
+                &serde_json::Value::Object(party.clone()),
+            )
+            .await?;
+            redacted.body[*field] = serde_json::json!({
+                "@id": party.get("@id").cloned().unwrap_or_default(),
+                "piiRef": role,
+            });
+            sealed = true;
+        }
+        Ok(sealed.then_some(redacted))
+    }
+
+    /// Decrypt stored message JSON if it is encrypted and the key is held
+    ///
+    /// The JSON only decrypts in the row of the message it was encrypted for.
+    fn open_message_json(
+        &self,
+        message_json: serde_json::Value,
+        message_id: &str,
+        message_type: &str,
+    ) -> Result<serde_json::Value, StorageError> {
+        match (&message_json, &self.cipher) {
+            (serde_json::Value::String(sealed), Some(cipher))
+                if encryption::is_encrypted(sealed) =>
+            {
+                let associated_data = encryption::message_binding(message_id, message_type);
+                Ok(serde_json::from_str(
+                    &cipher.decrypt(sealed, &associated_data)?,
+                )?)
+            }
+            _ => Ok(message_json),
+        }
+    }
+
+    /// Decrypt a stored text if it is encrypted and the key is held
+    fn open_text(&self, text: String, associated_data: &[u8]) -> Result<String, StorageError> {
+        match &self.cipher {
+            Some(cipher) if encryption::is_encrypted(&text) => {
+                cipher.decrypt(&text, associated_data)
+            }
+            _ => Ok(text),
+        }
+    }
+
+    /// Decrypt the text of a delivery of the message with the given ID if it
+    /// is encrypted and the key is held
+    async fn open_delivery_text(
+        &self,
+        message_id: &str,
+        text: String,
+    ) -> Result<String, StorageError> {
+        if self.cipher.is_none() || !encryption::is_encrypted(&text) {
+            return Ok(text);
+        }
+        let message_type = self.message_type_of(message_id).await?.ok_or_else(|| {
+            StorageError::Encryption(format!(
+                "Cannot decrypt the delivery of message {}, the message is not logged",
+                message_id
+            ))
+        })?;
+        self.open_text(
+            text,
+            &encryption::message_binding(message_id, &message_type),
+        )
+    }
+
+    /// Associated data binding an encrypted envelope to its received record
+    fn received_binding(received_id: i64) -> Vec<u8> {
+        encryption::binding(&["received", &received_id.to_string()])
+    }
+
+    /// Current time in the format used by timestamp columns
+    fn now(&self) -> String {
+        self.clock.now().format("%Y-%m-%dT%H:%M:%SZ").to_string()
     }
 
     /// Get the database path
@@ -192,6 +508,171 @@ impl Storage {
         &self.db_path
     }
 
+    /// Whether this is an in-memory database
+    pub(crate) fn is_in_memory(&self) -> bool {
+        self.db_path == Path::new(":memory:")
+    }
+
+    /// Get the quota enforced on this database, if any
+    pub fn quota(&self) -> Option<&StorageQuota> {
+        self.quota.as_ref()
+    }
+
+    /// Size of the database in bytes, as counted against the quota
+    pub async fn size_bytes(&self) -> Result<u64, StorageError> {
+        let page_size: i64 = sqlx::query_scalar("PRAGMA page_size")
+            .fetch_one(&self.pool)
+            .await?;
+        let page_count: i64 = sqlx::query_scalar("PRAGMA page_count")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok((page_size * page_count) as u64)
+    }
+
+    /// Report the disk usage of the database and its tables
+    pub async fn usage(&self) -> Result<StorageUsage, StorageError> {
+        let size_bytes = self.size_bytes().await?;
+        let page_size: i64 = sqlx::query_scalar("PRAGMA page_size")
+            .fetch_one(&self.pool)
+            .await?;
+        let freelist_count: i64 = sqlx::query_scalar("PRAGMA freelist_count")
+            .fetch_one(&self.pool)
+            .await?;
+
+        let mut file_bytes = 0;
+        for suffix in ["", "-wal"] {
+            let mut path = self.db_path.clone().into_os_string();
+            path.push(suffix);
+            if let Ok(metadata) = std::fs::metadata(&path) {
+                file_bytes += metadata.len();
+            }
+        }
+
+        // Index pages are counted towards the table they belong to
+        let table_rows = sqlx::query(
+            r#"
+            SELECT m.tbl_name AS name, SUM(s.pgsize) AS size_bytes
+            FROM dbstat s
+            JOIN sqlite_master m ON m.name = s.name
+            WHERE m.tbl_name NOT LIKE 'sqlite_%'
+            GROUP BY m.tbl_name
+            ORDER BY size_bytes DESC, name ASC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut tables = Vec::with_capacity(table_rows.len());
+        for row in &table_rows {
+            let name: String = row.get("name");
+            let rows: i64 = sqlx::query_scalar(&format!(
+                "SELECT COUNT(*) FROM \"{}\"",
+                name.replace('"', "\"\"")
+            ))
+            .fetch_one(&self.pool)
+            .await?;
+            tables.push(TableUsage {
+                name,
+                rows: rows as u64,
+                size_bytes: row.get::<i64, _>("size_bytes") as u64,
+            });
+        }
+
+        Ok(StorageUsage {
+            db_path: self.db_path.clone(),
+            size_bytes,
+            file_bytes,
+            free_bytes: (freelist_count * page_size) as u64,
+            quota: self.quota,
+            quota_exceeded: self.quota.is_some_and(|q| size_bytes > q.max_bytes),
+            tables,
+        })
+    }
+
+    /// Archive and compact the database
+    ///
+    /// The database is first copied to `archive/transactions-{timestamp}.db`
+    /// next to it. Processed raw received messages and successful delivery
+    /// records are then removed and the database is vacuumed.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(path))` - The path of the archive snapshot
+    /// * `Ok(None)` if there was nothing to remove, or the database is in memory
+    /// * `Err(StorageError)` on database or IO error
+    pub async fn archive(&self) -> Result<Option<PathBuf>, StorageError> {
+        if self.db_path == Path::new(":memory:") {
+            return Ok(None);
+        }
+
+        let removable: i64 = sqlx::query_scalar(
+            r#"
+            SELECT (SELECT COUNT(*) FROM received WHERE status = 'processed')
+                 + (SELECT COUNT(*) FROM deliveries WHERE status = 'success')
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        if removable == 0 {
+            return Ok(None);
+        }
+
+        let archive_dir = self
+            .db_path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join("archive");
+        std::fs::create_dir_all(&archive_dir)?;
+        let archive_path = archive_dir.join(format!(
+            "transactions-{}.db",
+            self.clock.now().format("%Y%m%dT%H%M%S%.3fZ")
+        ));
+        info!("Archiving {:?} to {:?}", self.db_path, archive_path);
+
+        sqlx::query("VACUUM INTO ?1")
+            .bind(archive_path.to_string_lossy().to_string())
+            .execute(&self.pool)
+            .await?;
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM received WHERE status = 'processed'")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM deliveries WHERE status = 'success'")
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        sqlx::query("VACUUM").execute(&self.pool).await?;
+
+        Ok(Some(archive_path))
+    }
+
+    /// Check the quota before a non-essential write
+    async fn check_quota(&self) -> Result<(), StorageError> {
+        let Some(quota) = self.quota else {
+            return Ok(());
+        };
+
+        let size_bytes = self.size_bytes().await?;
+        if size_bytes <= quota.max_bytes {
+            return Ok(());
+        }
+
+        if quota.on_exceeded == QuotaAction::Archive {
+            self.archive().await?;
+            let size_bytes = self.size_bytes().await?;
+            if size_bytes <= quota.max_bytes {
+                return Ok(());
+            }
+        }
+
+        Err(StorageError::QuotaExceeded(format!(
+            "{:?} uses {} of {} bytes",
+            self.db_path, size_bytes, quota.max_bytes
+        )))
+    }
+
     /// Get the default logs directory
     ///
     /// Returns the default directory for log files:
@@ -254,6 +735,142 @@ impl Storage {
         Ok(())
     }
 
+    /// Sum the amounts of outgoing Transfers and Payments created since a point in time
+    ///
+    /// Only transactions whose Transfer or Payment this node sent are
+    /// included; transactions received from other agents are not. Those in
+    /// `failed`, `cancelled` or `reverted` status are excluded. The party is
+    /// the originator (Transfer) or customer (Payment); the counterparty is
+    /// the beneficiary (Transfer) or merchant (Payment).
+    ///
+    /// Amounts are summed as exact decimals, like the decimal strings they
+    /// are stored as.
+    ///
+    /// # Arguments
+    ///
+    /// * `party` - Only include transactions sent by this party
+    /// * `counterparty` - Only include transactions sent to this counterparty
+    /// * `asset` - Only include transactions in this asset or currency code
+    /// * `since` - RFC 3339 timestamp; older transactions are excluded
+    /// * `exclude_reference_id` - Transaction to leave out (e.g. the one being evaluated)
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((total, count))` - Sum of amounts and number of matching transactions
+    /// * `Err(StorageError)` on database error, or if a stored amount is not a decimal number
+    pub async fn sum_transaction_amounts(
+        &self,
+        party: Option<&str>,
+        counterparty: Option<&str>,
+        asset: Option<&str>,
+        since: &str,
+        exclude_reference_id: Option<&str>,
+    ) -> Result<(Decimal, i64), StorageError> {
+        let rows = sqlx::query_as::<_, (String, Option<String>)>(
+            r#"
+            SELECT reference_id, CAST(json_extract(message_json, '$.body.amount') AS TEXT)
+            FROM transactions
+            WHERE status NOT IN ('failed', 'cancelled', 'reverted')
+              AND datetime(created_at) >= datetime(?1)
+              AND EXISTS (
+                    SELECT 1 FROM messages
+                    WHERE messages.message_id = transactions.reference_id
+                      AND messages.direction = 'outgoing')
+              AND (?2 IS NULL OR COALESCE(
+                    json_extract(message_json, '$.body.originator."@id"'),
+                    json_extract(message_json, '$.body.customer."@id"')) = ?2)
+              AND (?3 IS NULL OR COALESCE(
+                    json_extract(message_json, '$.body.beneficiary."@id"'),
+                    json_extract(message_json, '$.body.merchant."@id"')) = ?3)
+              AND (?4 IS NULL OR COALESCE(
+                    json_extract(message_json, '$.body.asset'),
+                    json_extract(message_json, '$.body.currency')) = ?4)
+              AND (?5 IS NULL OR reference_id != ?5)
+            "#,
+        )
+        .bind(since)
+        .bind(party)
+        .bind(counterparty)
+        .bind(asset)
+        .bind(exclude_reference_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut total = Decimal::ZERO;
+        for (reference_id, amount) in &rows {
+            let amount = amount.as_deref().unwrap_or_default();
+            let amount = Decimal::from_str_exact(amount).map_err(|e| {
+                StorageError::Serialization(serde::de::Error::custom(format!(
+                    "Invalid amount '{}' of transaction {}: {}",
+                    amount, reference_id, e
+                )))
+            })?;
+            total = total.checked_add(amount).ok_or_else(|| {
+                StorageError::Serialization(serde::de::Error::custom(format!(
+                    "Sum of amounts overflows at transaction {}",
+                    reference_id
+                )))
+            })?;
+        }
+
+        Ok((total, rows.len() as i64))
+    }
+
+    /// Find earlier Transfers with the same parties, asset and amount
+    ///
+    /// Parties are matched exactly, so a missing originator or beneficiary
+    /// only matches Transfers without one, and amounts are compared as
+    /// numbers. Failed, cancelled and reverted transactions are not
+    /// considered.
+    ///
+    /// # Arguments
+    ///
+    /// * `originator` - Originator DID of the Transfer
+    /// * `beneficiary` - Beneficiary DID of the Transfer
+    /// * `asset` - Asset of the Transfer (CAIP-19)
+    /// * `amount` - Amount of the Transfer
+    /// * `since` - RFC 3339 timestamp; only Transfers created at or after it match
+    /// * `exclude_reference_id` - The Transfer itself, which is not returned
+    ///
+    /// # Returns
+    ///
+    /// The reference IDs of the matching Transfers, oldest first
+    pub async fn find_matching_transfers(
+        &self,
+        originator: Option<&str>,
+        beneficiary: Option<&str>,
+        asset: &str,
+        amount: f64,
+        since: &str,
+        exclude_reference_id: &str,
+    ) -> Result<Vec<String>, StorageError> {
+        let reference_ids = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT reference_id
+            FROM transactions
+            WHERE type = 'transfer'
+              AND status NOT IN ('failed', 'cancelled', 'reverted')
+              AND datetime(created_at) >= datetime(?1)
+              AND json_extract(message_json, '$.body.originator."@id"') IS ?2
+              AND json_extract(message_json, '$.body.beneficiary."@id"') IS ?3
+              AND json_extract(message_json, '$.body.asset') = ?4
+              AND CAST(json_extract(message_json, '$.body.amount') AS REAL) = ?5
+              AND reference_id != ?6
+            ORDER BY id
+            "#,
+        )
+        .bind(since)
+        .bind(originator)
+        .bind(beneficiary)
+        .bind(asset)
+        .bind(amount)
+        .bind(exclude_reference_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(reference_ids)
+    }
+
     /// Update the status of a transaction in the transactions table
     ///
     /// # Arguments
@@ -274,70 +891,349 @@ impl Storage {
             transaction_id, status
         );
 
-        sqlx::query(
-            r#"
-            UPDATE transactions 
-            SET status = ?1 
-            WHERE reference_id = ?2
-            "#,
-        )
-        .bind(status)
-        .bind(transaction_id)
-        .execute(&self.pool)
+        if let Some(backend) = &self.backend {
+            backend
+                .update_transaction_status(transaction_id, status)
+                .await?;
+        }
+        self.commit_event(StateEvent::TransactionStatusUpdated {
+            transaction_id: transaction_id.to_string(),
+            status: status.to_string(),
+        })
         .await?;
 
         Ok(())
     }
 
-    /// Get a transaction by its reference ID
+    /// Record the reason of the Reject message that failed a transaction
     ///
     /// # Arguments
     ///
-    /// * `reference_id` - The reference ID of the transaction
+    /// * `transaction_id` - The reference ID of the transaction
+    /// * `code` - Standardized rejection code, if the Reject message had one
+    /// * `reason` - Free-text rejection reason, if the Reject message had one
+    pub async fn record_rejection(
+        &self,
+        transaction_id: &str,
+        code: Option<&str>,
+        reason: Option<&str>,
+    ) -> Result<(), StorageError> {
+        if let Some(backend) = &self.backend {
+            backend
+                .record_rejection(transaction_id, code, reason)
+                .await?;
+        }
+        self.commit_event(StateEvent::RejectionRecorded {
+            transaction_id: transaction_id.to_string(),
+            code: code.map(String::from),
+            reason: reason.map(String::from),
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Count rejected transactions by rejection code
+    ///
+    /// Failed transactions rejected without a code are counted under `None`.
+    ///
+    /// # Arguments
+    ///
+    /// * `since` - Only count transactions created at or after this time (RFC 3339)
+    /// * `until` - Only count transactions created before this time (RFC 3339)
     ///
     /// # Returns
     ///
-    /// * `Ok(Some(Transaction))` if found
-    /// * `Ok(None)` if not found
-    /// * `Err(StorageError)` on database error
-    pub async fn get_transaction_by_id(
+    /// Pairs of rejection code and count, most frequent first
+    pub async fn count_rejections_by_code(
         &self,
-        reference_id: &str,
-    ) -> Result<Option<Transaction>, StorageError> {
-        let result = sqlx::query_as::<_, (
-            i64,
-            String,
-            String,
-            Option<String>,
-            Option<String>,
-            Option<String>,
-            String,
-            String,
-            serde_json::Value,
-            String,
-            String,
-        )>(
+        since: Option<&str>,
+        until: Option<&str>,
+    ) -> Result<Vec<(Option<String>, i64)>, StorageError> {
+        let rows = sqlx::query_as::<_, (Option<String>, i64)>(
             r#"
-            SELECT id, type, reference_id, from_did, to_did, thread_id, message_type, status, message_json, created_at, updated_at
-            FROM transactions WHERE reference_id = ?1
+            SELECT rejection_code, COUNT(*)
+            FROM transactions
+            WHERE status = 'failed'
+              AND (?1 IS NULL OR datetime(created_at) >= datetime(?1))
+              AND (?2 IS NULL OR datetime(created_at) < datetime(?2))
+            GROUP BY rejection_code
+            ORDER BY COUNT(*) DESC, rejection_code
             "#,
         )
-        .bind(reference_id)
-        .fetch_optional(&self.pool)
+        .bind(since)
+        .bind(until)
+        .fetch_all(&self.pool)
         .await?;
 
-        if let Some((
-            id,
-            tx_type,
-            reference_id,
-            from_did,
-            to_did,
-            thread_id,
-            message_type,
-            status,
-            message_json,
-            created_at,
-            updated_at,
+        Ok(rows)
+    }
+
+    /// Apply a mutation, recording it in the state event log in event
+    /// sourcing mode
+    ///
+    /// Returns the applied event, which carries the IDs assigned by the
+    /// mutation.
+    async fn commit_event(&self, mut event: StateEvent) -> Result<StateEvent, StorageError> {
+        let at = self.clock.now();
+        let mut tx = self.pool.begin().await?;
+        event_store::apply(&mut tx, &mut event, at).await?;
+        if self.event_sourcing {
+            event_store::append(&mut tx, &event, at).await?;
+        }
+        tx.commit().await?;
+        Ok(event)
+    }
+
+    /// List events of the state event log in sequence order
+    ///
+    /// # Arguments
+    ///
+    /// * `transaction_id` - Only list events of this transaction
+    /// * `after` - Only list events after this sequence number
+    /// * `limit` - Maximum number of events to return
+    pub async fn list_state_events(
+        &self,
+        transaction_id: Option<&str>,
+        after: i64,
+        limit: u32,
+    ) -> Result<Vec<StoredStateEvent>, StorageError> {
+        let rows = sqlx::query_as::<_, (i64, Option<String>, String, String)>(
+            r#"
+            SELECT sequence, transaction_id, event_json, recorded_at
+            FROM state_events
+            WHERE sequence > ?1 AND (?2 IS NULL OR transaction_id = ?2)
+            ORDER BY sequence ASC
+            LIMIT ?3
+            "#,
+        )
+        .bind(after)
+        .bind(transaction_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|(sequence, transaction_id, event_json, recorded_at)| {
+                Ok(StoredStateEvent {
+                    sequence,
+                    transaction_id,
+                    event: serde_json::from_str(&event_json)?,
+                    recorded_at,
+                })
+            })
+            .collect()
+    }
+
+    /// Reconstruct a transaction as it stood at `timestamp`
+    ///
+    /// Replays the transaction's events in the state event log that were
+    /// recorded at or before `timestamp`, so the result shows the status,
+    /// rejection and participant set known at that moment. Returns `None` if
+    /// the transaction did not exist yet, and an error if the transaction
+    /// exists but the log does not cover it, as happens when event sourcing
+    /// was disabled when it was created.
+    pub async fn get_transaction_state_at(
+        &self,
+        transaction_id: &str,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<TransactionStateAt>, StorageError> {
+        let events = self
+            .list_state_events(Some(transaction_id), 0, u32::MAX)
+            .await?;
+
+        if events.is_empty() {
+            if self.get_transaction_by_id(transaction_id).await?.is_some() {
+                return Err(StorageError::Replay(format!(
+                    "The event log does not cover transaction {}",
+                    transaction_id
+                )));
+            }
+            return Ok(None);
+        }
+
+        let mut known = Vec::with_capacity(events.len());
+        for event in events {
+            let recorded_at = DateTime::parse_from_rfc3339(&event.recorded_at).map_err(|e| {
+                StorageError::Replay(format!(
+                    "Invalid timestamp of event {}: {}",
+                    event.sequence, e
+                ))
+            })?;
+            if recorded_at > timestamp {
+                break;
+            }
+            known.push(event);
+        }
+
+        TransactionStateAt::replay(&known)
+    }
+
+    /// Reconstruct the transaction and delivery tables from the state event log
+    ///
+    /// The `transactions`, `transaction_agents` and `deliveries` tables are
+    /// cleared and every event in the log is applied again, in a single
+    /// database transaction. The rebuild is rolled back if the log does not
+    /// cover a transaction or delivery that exists before the rebuild, as
+    /// happens when event sourcing is enabled on a database with existing data.
+    pub async fn rebuild_projections(&self) -> Result<RebuildReport, StorageError> {
+        const BATCH_SIZE: i64 = 500;
+
+        info!("Rebuilding projections of {:?}", self.db_path);
+        let mut tx = self.pool.begin().await?;
+
+        let transactions: Vec<String> = sqlx::query_scalar("SELECT reference_id FROM transactions")
+            .fetch_all(&mut *tx)
+            .await?;
+        let deliveries: Vec<i64> = sqlx::query_scalar("SELECT id FROM deliveries")
+            .fetch_all(&mut *tx)
+            .await?;
+
+        for statement in [
+            "DELETE FROM deliveries",
+            "DELETE FROM transaction_agents",
+            "DELETE FROM transactions",
+        ] {
+            sqlx::query(statement).execute(&mut *tx).await?;
+        }
+
+        let mut report = RebuildReport::default();
+        let mut after = 0;
+        loop {
+            let rows = sqlx::query_as::<_, (i64, String, String)>(
+                r#"
+                SELECT sequence, event_json, recorded_at
+                FROM state_events
+                WHERE sequence > ?1
+                ORDER BY sequence ASC
+                LIMIT ?2
+                "#,
+            )
+            .bind(after)
+            .bind(BATCH_SIZE)
+            .fetch_all(&mut *tx)
+            .await?;
+            if rows.is_empty() {
+                break;
+            }
+
+            for (sequence, event_json, recorded_at) in rows {
+                let mut event: StateEvent = serde_json::from_str(&event_json)?;
+                let at = DateTime::parse_from_rfc3339(&recorded_at)
+                    .map_err(|e| {
+                        StorageError::Projection(format!(
+                            "Invalid timestamp of event {}: {}",
+                            sequence, e
+                        ))
+                    })?
+                    .with_timezone(&Utc);
+                event_store::apply(&mut tx, &mut event, at)
+                    .await
+                    .map_err(|e| {
+                        StorageError::Projection(format!(
+                            "Failed to replay event {}: {}",
+                            sequence, e
+                        ))
+                    })?;
+                report.events += 1;
+                after = sequence;
+            }
+        }
+
+        let rebuilt_transactions: Vec<String> =
+            sqlx::query_scalar("SELECT reference_id FROM transactions")
+                .fetch_all(&mut *tx)
+                .await?;
+        let rebuilt_deliveries: Vec<i64> = sqlx::query_scalar("SELECT id FROM deliveries")
+            .fetch_all(&mut *tx)
+            .await?;
+        if let Some(missing) = transactions
+            .iter()
+            .find(|id| !rebuilt_transactions.contains(id))
+        {
+            return Err(StorageError::Projection(format!(
+                "The event log does not cover transaction {}",
+                missing
+            )));
+        }
+        if let Some(missing) = deliveries
+            .iter()
+            .find(|id| !rebuilt_deliveries.contains(id))
+        {
+            return Err(StorageError::Projection(format!(
+                "The event log does not cover delivery {}",
+                missing
+            )));
+        }
+
+        tx.commit().await?;
+        report.transactions = rebuilt_transactions.len() as u64;
+        report.deliveries = rebuilt_deliveries.len() as u64;
+        info!(
+            "Rebuilt {} transactions and {} deliveries from {} events",
+            report.transactions, report.deliveries, report.events
+        );
+        Ok(report)
+    }
+
+    /// Get a transaction by its reference ID
+    ///
+    /// # Arguments
+    ///
+    /// * `reference_id` - The reference ID of the transaction
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(Transaction))` if found
+    /// * `Ok(None)` if not found
+    /// * `Err(StorageError)` on database error
+    pub async fn get_transaction_by_id(
+        &self,
+        reference_id: &str,
+    ) -> Result<Option<Transaction>, StorageError> {
+        if let Some(backend) = &self.backend {
+            return backend.get_transaction_by_id(reference_id).await;
+        }
+        let result = sqlx::query_as::<_, (
+            i64,
+            String,
+            String,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            String,
+            String,
+            serde_json::Value,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            String,
+            String,
+        )>(
+            r#"
+            SELECT id, type, reference_id, from_did, to_did, thread_id, message_type, status, message_json, rejection_code, rejection_reason, canonical_hash, created_at, updated_at
+            FROM transactions WHERE reference_id = ?1
+            "#,
+        )
+        .bind(reference_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if let Some((
+            id,
+            tx_type,
+            reference_id,
+            from_did,
+            to_did,
+            thread_id,
+            message_type,
+            status,
+            message_json,
+            rejection_code,
+            rejection_reason,
+            canonical_hash,
+            created_at,
+            updated_at,
         )) = result
         {
             Ok(Some(Transaction {
@@ -352,6 +1248,9 @@ impl Storage {
                 status: TransactionStatus::try_from(status.as_str())
                     .map_err(StorageError::InvalidTransactionType)?,
                 message_json,
+                rejection_code,
+                rejection_reason,
+                canonical_hash,
                 created_at,
                 updated_at,
             }))
@@ -385,11 +1284,14 @@ impl Storage {
             String,
             String,
             serde_json::Value,
+            Option<String>,
+            Option<String>,
+            Option<String>,
             String,
             String,
         )>(
             r#"
-            SELECT id, type, reference_id, from_did, to_did, thread_id, message_type, status, message_json, created_at, updated_at
+            SELECT id, type, reference_id, from_did, to_did, thread_id, message_type, status, message_json, rejection_code, rejection_reason, canonical_hash, created_at, updated_at
             FROM transactions WHERE thread_id = ?1
             "#,
         )
@@ -407,6 +1309,9 @@ impl Storage {
             message_type,
             status,
             message_json,
+            rejection_code,
+            rejection_reason,
+            canonical_hash,
             created_at,
             updated_at,
         )) = result
@@ -423,6 +1328,9 @@ impl Storage {
                 status: TransactionStatus::try_from(status.as_str())
                     .map_err(StorageError::InvalidTransactionType)?,
                 message_json,
+                rejection_code,
+                rejection_reason,
+                canonical_hash,
                 created_at,
                 updated_at,
             }))
@@ -431,6 +1339,63 @@ impl Storage {
         }
     }
 
+    /// Get a transaction by its canonical hash
+    ///
+    /// Copies of a transaction stored under different message IDs share the
+    /// hash; the first one stored is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `canonical_hash` - The hex-encoded canonical transaction hash
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(Transaction))` if found
+    /// * `Ok(None)` if not found
+    /// * `Err(StorageError)` on database error
+    pub async fn get_transaction_by_canonical_hash(
+        &self,
+        canonical_hash: &str,
+    ) -> Result<Option<Transaction>, StorageError> {
+        let reference_id: Option<String> = sqlx::query_scalar(
+            "SELECT reference_id FROM transactions WHERE canonical_hash = ?1 ORDER BY id LIMIT 1",
+        )
+        .bind(canonical_hash.to_lowercase())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match reference_id {
+            Some(reference_id) => self.get_transaction_by_id(&reference_id).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Hash the transactions stored before canonical hashes were recorded
+    pub(super) async fn backfill_canonical_hashes(&self) -> Result<(), StorageError> {
+        let rows: Vec<(i64, serde_json::Value)> = sqlx::query_as(
+            "SELECT id, message_json FROM transactions WHERE canonical_hash IS NULL",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        info!("Computing canonical hashes of {} transactions", rows.len());
+        for (id, message_json) in rows {
+            let body = message_json.get("body").cloned().unwrap_or_default();
+            let Some(from) = message_json.get("from").and_then(|f| f.as_str()) else {
+                continue;
+            };
+            sqlx::query("UPDATE transactions SET canonical_hash = ?1 WHERE id = ?2")
+                .bind(canonical_transaction_hash(&body, from))
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+
     /// Check if an agent is authorized for a transaction
     ///
     /// This checks the transaction_agents table to see if the given agent
@@ -499,40 +1464,11 @@ impl Storage {
         agent_did: &str,
         agent_role: &str,
     ) -> Result<(), StorageError> {
-        // First get the transaction's internal ID
-        let tx_result = sqlx::query_scalar::<_, i64>(
-            r#"
-            SELECT id FROM transactions WHERE reference_id = ?1
-            "#,
-        )
-        .bind(transaction_id)
-        .fetch_optional(&self.pool)
-        .await?;
-
-        let tx_internal_id = match tx_result {
-            Some(id) => id,
-            None => {
-                return Err(StorageError::NotFound(format!(
-                    "Transaction {} not found",
-                    transaction_id
-                )))
-            }
-        };
-
-        // Insert the agent
-        sqlx::query(
-            r#"
-            INSERT INTO transaction_agents (transaction_id, agent_did, agent_role, status)
-            VALUES (?1, ?2, ?3, 'pending')
-            ON CONFLICT(transaction_id, agent_did) DO UPDATE SET
-                agent_role = excluded.agent_role,
-                updated_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
-            "#,
-        )
-        .bind(tx_internal_id)
-        .bind(agent_did)
-        .bind(agent_role)
-        .execute(&self.pool)
+        self.commit_event(StateEvent::TransactionAgentInserted {
+            transaction_id: transaction_id.to_string(),
+            agent_did: agent_did.to_string(),
+            role: agent_role.to_string(),
+        })
         .await?;
 
         Ok(())
@@ -556,47 +1492,13 @@ impl Storage {
         agent_did: &str,
         status: &str,
     ) -> Result<(), StorageError> {
-        // First get the transaction's internal ID
-        let tx_result = sqlx::query_scalar::<_, i64>(
-            r#"
-            SELECT id FROM transactions WHERE reference_id = ?1
-            "#,
-        )
-        .bind(transaction_id)
-        .fetch_optional(&self.pool)
-        .await?;
-
-        let tx_internal_id = match tx_result {
-            Some(id) => id,
-            None => {
-                return Err(StorageError::NotFound(format!(
-                    "Transaction {} not found",
-                    transaction_id
-                )))
-            }
-        };
-
-        // Update the agent status
-        let result = sqlx::query(
-            r#"
-            UPDATE transaction_agents 
-            SET status = ?1 
-            WHERE transaction_id = ?2 AND agent_did = ?3
-            "#,
-        )
-        .bind(status)
-        .bind(tx_internal_id)
-        .bind(agent_did)
-        .execute(&self.pool)
+        self.commit_event(StateEvent::TransactionAgentStatusUpdated {
+            transaction_id: transaction_id.to_string(),
+            agent_did: agent_did.to_string(),
+            status: status.to_string(),
+        })
         .await?;
 
-        if result.rows_affected() == 0 {
-            return Err(StorageError::NotFound(format!(
-                "Agent {} not found for transaction {}",
-                agent_did, transaction_id
-            )));
-        }
-
         Ok(())
     }
 
@@ -650,41 +1552,257 @@ impl Storage {
         Ok(agents)
     }
 
-    /// Check if all agents have authorized the transaction
-    ///
-    /// # Arguments
-    ///
-    /// * `transaction_id` - The reference ID of the transaction
+    /// Remove an agent from a transaction
     ///
-    /// # Returns
+    /// Used when a RemoveAgent or ReplaceAgent message takes the agent out
+    /// of the transaction, so that it no longer has to authorize it.
+    pub async fn remove_transaction_agent(
+        &self,
+        transaction_id: &str,
+        agent_did: &str,
+    ) -> Result<(), StorageError> {
+        self.commit_event(StateEvent::TransactionAgentRemoved {
+            transaction_id: transaction_id.to_string(),
+            agent_did: agent_did.to_string(),
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record that the message `message_id` from `added_by` added an agent
+    /// to a transaction
     ///
-    /// * `Ok(true)` if all agents have authorized
-    /// * `Ok(false)` if any agent hasn't authorized or has rejected/cancelled
-    /// * `Err(StorageError)` on database error
-    pub async fn are_all_agents_authorized(
+    /// Returns false if the agent is already part of the transaction.
+    pub async fn add_transaction_participant(
         &self,
         transaction_id: &str,
+        agent: &tap_msg::message::Agent,
+        added_by: &str,
+        message_id: &str,
     ) -> Result<bool, StorageError> {
-        // First get the transaction's internal ID
-        let tx_result = sqlx::query_scalar::<_, i64>(
+        let result = sqlx::query(
             r#"
-            SELECT id FROM transactions WHERE reference_id = ?1
+            INSERT OR IGNORE INTO transaction_participants (
+                transaction_id, agent_did, role, for_parties, added_by, added_in, added_at
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
             "#,
         )
         .bind(transaction_id)
-        .fetch_optional(&self.pool)
+        .bind(&agent.id)
+        .bind(agent.role.as_ref().map(|r| r.as_str()))
+        .bind(serde_json::to_string(agent.for_parties())?)
+        .bind(added_by)
+        .bind(message_id)
+        .bind(self.now())
+        .execute(&self.pool)
         .await?;
 
-        let tx_internal_id = match tx_result {
-            Some(id) => id,
-            None => return Ok(false), // Transaction doesn't exist
-        };
+        Ok(result.rows_affected() > 0)
+    }
 
-        // Check if there are any agents not in 'authorized' status
-        let non_authorized_count: i64 = sqlx::query_scalar(
+    /// Record that the message `message_id` from `removed_by` removed an
+    /// agent from a transaction, or replaced it by `replaced_by`
+    ///
+    /// Returns false if the agent is not part of the transaction.
+    pub async fn remove_transaction_participant(
+        &self,
+        transaction_id: &str,
+        agent_did: &str,
+        removed_by: &str,
+        message_id: &str,
+        replaced_by: Option<&str>,
+    ) -> Result<bool, StorageError> {
+        let result = sqlx::query(
             r#"
-            SELECT COUNT(*) FROM transaction_agents 
-            WHERE transaction_id = ?1 AND status != 'authorized'
+            UPDATE transaction_participants
+            SET removed_by = ?3, removed_in = ?4, removed_at = ?5, replaced_by = ?6
+            WHERE transaction_id = ?1 AND agent_did = ?2 AND removed_at IS NULL
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(agent_did)
+        .bind(removed_by)
+        .bind(message_id)
+        .bind(self.now())
+        .bind(replaced_by)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Get the agents of a transaction in the order they were added
+    ///
+    /// Only the current agents are returned unless `include_removed` is set,
+    /// in which case removed and replaced agents are included with who
+    /// removed them and when.
+    pub async fn get_transaction_participants(
+        &self,
+        transaction_id: &str,
+        include_removed: bool,
+    ) -> Result<Vec<TransactionParticipant>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM transaction_participants
+            WHERE transaction_id = ?1 AND (?2 OR removed_at IS NULL)
+            ORDER BY id
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(include_removed)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(Self::transaction_participant_from_row)
+            .collect()
+    }
+
+    /// Get the graph of transactions related to a transaction through the
+    /// parent thread IDs (pthid) of their messages
+    ///
+    /// A transaction's parent is the transaction whose reference ID or thread
+    /// ID is the pthid of its Transfer or Payment message, e.g. the Payment a
+    /// Transfer settles or the Transfer a later Transfer reverts. The graph
+    /// holds the earliest known ancestor of the transaction and all its
+    /// descendants, so every transaction of a chain returns the same graph.
+    ///
+    /// Returns None if the transaction does not exist.
+    pub async fn get_transaction_graph(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Option<TransactionGraph>, StorageError> {
+        let root_id: Option<String> = sqlx::query_scalar(
+            r#"
+            WITH RECURSIVE ancestors(reference_id, parent_thread_id, depth) AS (
+                SELECT reference_id, parent_thread_id, 0
+                FROM transactions WHERE reference_id = ?1
+                UNION
+                SELECT t.reference_id, t.parent_thread_id, a.depth + 1
+                FROM ancestors a
+                JOIN transactions t
+                    ON t.reference_id = a.parent_thread_id OR t.thread_id = a.parent_thread_id
+                WHERE a.depth < ?2
+            )
+            SELECT reference_id FROM ancestors ORDER BY depth DESC LIMIT 1
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(MAX_TRANSACTION_GRAPH_DEPTH)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(root_id) = root_id else {
+            return Ok(None);
+        };
+
+        let rows = sqlx::query(
+            r#"
+            WITH RECURSIVE descendants(reference_id, parent_id, depth) AS (
+                SELECT reference_id, NULL, 0
+                FROM transactions WHERE reference_id = ?1
+                UNION
+                SELECT t.reference_id, d.reference_id, d.depth + 1
+                FROM descendants d
+                JOIN transactions p ON p.reference_id = d.reference_id
+                JOIN transactions t ON t.parent_thread_id IN (p.reference_id, p.thread_id)
+                WHERE d.depth < ?2
+            )
+            SELECT d.reference_id, d.parent_id, d.depth, t.parent_thread_id, t.type,
+                   t.message_type, t.status, t.created_at
+            FROM descendants d
+            JOIN transactions t ON t.reference_id = d.reference_id
+            ORDER BY d.depth, t.created_at, t.id
+            "#,
+        )
+        .bind(&root_id)
+        .bind(MAX_TRANSACTION_GRAPH_DEPTH)
+        .fetch_all(&self.pool)
+        .await?;
+
+        // Rows are ordered by depth, so the first row of a transaction is its
+        // place in the tree; later rows come from cyclic or duplicate links
+        let mut nodes: Vec<TransactionGraphNode> = Vec::new();
+        for row in &rows {
+            let reference_id: String = row.get("reference_id");
+            if nodes.iter().any(|n| n.transaction_id == reference_id) {
+                continue;
+            }
+            nodes.push(TransactionGraphNode {
+                transaction_id: reference_id,
+                parent_id: row.get("parent_id"),
+                parent_thread_id: row.get("parent_thread_id"),
+                transaction_type: TransactionType::try_from(row.get::<String, _>("type").as_str())
+                    .map_err(StorageError::InvalidTransactionType)?,
+                message_type: row.get("message_type"),
+                status: TransactionStatus::try_from(row.get::<String, _>("status").as_str())
+                    .map_err(StorageError::InvalidTransactionType)?,
+                depth: row.get::<i64, _>("depth") as u32,
+                created_at: row.get("created_at"),
+            });
+        }
+
+        // Order depth-first, keeping siblings in creation order
+        let mut ordered = Vec::with_capacity(nodes.len());
+        let mut stack = vec![root_id.clone()];
+        while let Some(id) = stack.pop() {
+            let Some(index) = nodes.iter().position(|n| n.transaction_id == id) else {
+                continue;
+            };
+            let node = nodes.remove(index);
+            stack.extend(
+                nodes
+                    .iter()
+                    .filter(|n| n.parent_id.as_deref() == Some(id.as_str()))
+                    .map(|n| n.transaction_id.clone())
+                    .rev(),
+            );
+            ordered.push(node);
+        }
+
+        Ok(Some(TransactionGraph {
+            root_id,
+            nodes: ordered,
+        }))
+    }
+
+    /// Check if all agents have authorized the transaction
+    ///
+    /// # Arguments
+    ///
+    /// * `transaction_id` - The reference ID of the transaction
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` if all agents have authorized
+    /// * `Ok(false)` if any agent hasn't authorized or has rejected/cancelled
+    /// * `Err(StorageError)` on database error
+    pub async fn are_all_agents_authorized(
+        &self,
+        transaction_id: &str,
+    ) -> Result<bool, StorageError> {
+        // First get the transaction's internal ID
+        let tx_result = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT id FROM transactions WHERE reference_id = ?1
+            "#,
+        )
+        .bind(transaction_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let tx_internal_id = match tx_result {
+            Some(id) => id,
+            None => return Ok(false), // Transaction doesn't exist
+        };
+
+        // Check if there are any agents not in 'authorized' status
+        let non_authorized_count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM transaction_agents 
+            WHERE transaction_id = ?1 AND status != 'authorized'
             "#,
         )
         .bind(tx_internal_id)
@@ -696,6 +1814,49 @@ impl Storage {
         Ok(non_authorized_count == 0)
     }
 
+    /// List pending transactions that have agents, all of which authorized
+    ///
+    /// Unlike [`Storage::are_all_agents_authorized`], transactions without
+    /// agents are left out: they are not known to be authorized by anyone.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - Maximum number of transactions to return
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Transaction>)` - The transactions, oldest first
+    /// * `Err(StorageError)` on database error
+    pub async fn list_authorized_pending_transactions(
+        &self,
+        limit: u32,
+    ) -> Result<Vec<Transaction>, StorageError> {
+        let reference_ids: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT t.reference_id FROM transactions t
+            WHERE t.status = 'pending'
+              AND EXISTS (SELECT 1 FROM transaction_agents a WHERE a.transaction_id = t.id)
+              AND NOT EXISTS (
+                  SELECT 1 FROM transaction_agents a
+                  WHERE a.transaction_id = t.id AND a.status != 'authorized'
+              )
+            ORDER BY t.created_at ASC, t.id ASC
+            LIMIT ?1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut transactions = Vec::with_capacity(reference_ids.len());
+        for reference_id in reference_ids {
+            if let Some(transaction) = self.get_transaction_by_id(&reference_id).await? {
+                transactions.push(transaction);
+            }
+        }
+        Ok(transactions)
+    }
+
     /// Insert a new transaction from a TAP message
     ///
     /// This method extracts transaction details from a Transfer or Payment message
@@ -712,59 +1873,21 @@ impl Storage {
     /// - Database insertion fails
     /// - The transaction already exists (duplicate reference_id)
     pub async fn insert_transaction(&self, message: &PlainMessage) -> Result<(), StorageError> {
-        let message_type = message.type_.clone();
-        let message_json = serde_json::to_value(message)?;
-
-        // Extract transaction type and use message ID as reference
-        let message_type_lower = message.type_.to_lowercase();
-        let tx_type = if message_type_lower.contains("transfer") {
-            TransactionType::Transfer
-        } else if message_type_lower.contains("payment") {
-            TransactionType::Payment
-        } else {
-            return Err(StorageError::InvalidTransactionType(
-                message_type.to_string(),
-            ));
-        };
-
-        // Use the PlainMessage ID as the reference_id since transaction_id is not serialized
-        let reference_id = message.id.clone();
-        let from_did = message.from.clone();
-        let to_did = message.to.first().cloned();
-        let thread_id = message.thid.clone();
-
-        debug!("Inserting transaction: {} ({})", reference_id, tx_type);
-
-        let result = sqlx::query(
-            r#"
-            INSERT INTO transactions (type, reference_id, from_did, to_did, thread_id, message_type, message_json)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
-            "#,
-        )
-        .bind(tx_type.to_string())
-        .bind(&reference_id)
-        .bind(from_did)
-        .bind(to_did)
-        .bind(thread_id)
-        .bind(message_type.to_string())
-        .bind(sqlx::types::Json(message_json))
-        .execute(&self.pool)
-        .await;
-
-        match result {
-            Ok(_) => {
-                debug!("Successfully inserted transaction: {}", reference_id);
-                Ok(())
-            }
-            Err(sqlx::Error::Database(db_err)) => {
-                if db_err.message().contains("UNIQUE") {
-                    Err(StorageError::DuplicateTransaction(reference_id))
-                } else {
-                    Err(StorageError::Database(sqlx::Error::Database(db_err)))
-                }
-            }
-            Err(e) => Err(StorageError::Database(e)),
+        let redacted = self.seal_parties(message).await?;
+        let canonical_hash = redacted
+            .as_ref()
+            .map(|_| canonical_transaction_hash(&message.body, &message.from));
+        let message = redacted.as_ref().unwrap_or(message);
+        if let Some(backend) = &self.backend {
+            backend.insert_transaction(message).await?;
         }
+        self.commit_event(StateEvent::TransactionInserted {
+            message: Box::new(message.clone()),
+            canonical_hash,
+        })
+        .await?;
+
+        Ok(())
     }
 
     /// List transactions with pagination
@@ -784,6 +1907,9 @@ impl Storage {
         limit: u32,
         offset: u32,
     ) -> Result<Vec<Transaction>, StorageError> {
+        if let Some(backend) = &self.backend {
+            return backend.list_transactions(limit, offset).await;
+        }
         let rows = sqlx::query_as::<_, (
             i64,
             String,
@@ -794,11 +1920,14 @@ impl Storage {
             String,
             String,
             serde_json::Value,
+            Option<String>,
+            Option<String>,
+            Option<String>,
             String,
             String,
         )>(
             r#"
-            SELECT id, type, reference_id, from_did, to_did, thread_id, message_type, status, message_json, created_at, updated_at
+            SELECT id, type, reference_id, from_did, to_did, thread_id, message_type, status, message_json, rejection_code, rejection_reason, canonical_hash, created_at, updated_at
             FROM transactions
             ORDER BY created_at DESC
             LIMIT ?1 OFFSET ?2
@@ -820,6 +1949,9 @@ impl Storage {
             message_type,
             status,
             message_json,
+            rejection_code,
+            rejection_reason,
+            canonical_hash,
             created_at,
             updated_at,
         ) in rows
@@ -836,6 +1968,9 @@ impl Storage {
                 status: TransactionStatus::try_from(status.as_str())
                     .map_err(StorageError::InvalidTransactionType)?,
                 message_json,
+                rejection_code,
+                rejection_reason,
+                canonical_hash,
                 created_at,
                 updated_at,
             });
@@ -844,6 +1979,37 @@ impl Storage {
         Ok(transactions)
     }
 
+    /// List pending transactions that expired at or before `cutoff`, oldest
+    /// expiry first
+    ///
+    /// # Arguments
+    ///
+    /// * `cutoff` - Unix time in seconds, compared with the `expires_time`
+    ///   of the Transfer or Payment message
+    /// * `limit` - Maximum number of transaction IDs to return
+    pub async fn list_expired_transaction_ids(
+        &self,
+        cutoff: u64,
+        limit: u32,
+    ) -> Result<Vec<String>, StorageError> {
+        let ids = sqlx::query_scalar(
+            r#"
+            SELECT reference_id
+            FROM transactions
+            WHERE status = 'pending'
+              AND json_extract(message_json, '$.expires_time') <= ?1
+            ORDER BY json_extract(message_json, '$.expires_time') ASC
+            LIMIT ?2
+            "#,
+        )
+        .bind(i64::try_from(cutoff).unwrap_or(i64::MAX))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(ids)
+    }
+
     /// Log an incoming or outgoing message to the audit trail
     ///
     /// This method stores any DIDComm message for audit purposes, regardless of type.
@@ -863,7 +2029,24 @@ impl Storage {
         message: &PlainMessage,
         direction: MessageDirection,
     ) -> Result<(), StorageError> {
-        let message_json = serde_json::to_value(message)?;
+        // Messages from other protocols are not part of the TAP audit trail
+        if !message.type_.starts_with(TAP_MESSAGE_TYPE_PREFIX) {
+            self.check_quota().await?;
+        }
+
+        let redacted = self.seal_parties(message).await?;
+        let message = redacted.as_ref().unwrap_or(message);
+        // Messages encrypted at rest only stay in this database
+        if let (Some(backend), None) = (&self.backend, self.cipher_for(&message.type_)) {
+            backend.log_message(message, direction.clone()).await?;
+        }
+        let mut message_json = serde_json::to_value(message)?;
+        if let Some(cipher) = self.cipher_for(&message.type_) {
+            message_json = serde_json::Value::String(cipher.encrypt(
+                &message_json.to_string(),
+                &encryption::message_binding(&message.id, &message.type_),
+            )?);
+        }
         let message_id = message.id.clone();
         let message_type = message.type_.clone();
         let from_did = message.from.clone();
@@ -878,8 +2061,8 @@ impl Storage {
 
         let result = sqlx::query(
             r#"
-            INSERT INTO messages (message_id, message_type, from_did, to_did, thread_id, parent_thread_id, direction, message_json)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            INSERT INTO messages (message_id, message_type, from_did, to_did, thread_id, parent_thread_id, direction, message_json, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
             "#,
         )
         .bind(&message_id)
@@ -890,25 +2073,24 @@ impl Storage {
         .bind(parent_thread_id)
         .bind(direction.to_string())
         .bind(sqlx::types::Json(message_json))
+        .bind(self.now())
         .execute(&self.pool)
         .await;
 
         match result {
-            Ok(_) => {
-                debug!("Successfully logged message: {}", message_id);
-                Ok(())
-            }
+            Ok(_) => debug!("Successfully logged message: {}", message_id),
             Err(sqlx::Error::Database(db_err)) => {
                 if db_err.message().contains("UNIQUE") {
                     // Message already logged, this is fine
                     debug!("Message already logged: {}", message_id);
-                    Ok(())
                 } else {
-                    Err(StorageError::Database(sqlx::Error::Database(db_err)))
+                    return Err(StorageError::Database(sqlx::Error::Database(db_err)));
                 }
             }
-            Err(e) => Err(StorageError::Database(e)),
+            Err(e) => return Err(StorageError::Database(e)),
         }
+
+        Ok(())
     }
 
     /// Retrieve a message by its ID
@@ -926,6 +2108,12 @@ impl Storage {
         &self,
         message_id: &str,
     ) -> Result<Option<Message>, StorageError> {
+        // Messages encrypted at rest are only found in this database
+        if let Some(backend) = &self.backend {
+            if let Some(message) = backend.get_message_by_id(message_id).await? {
+                return Ok(Some(message));
+            }
+        }
         let result = sqlx::query_as::<_, (
             i64,
             String,
@@ -960,6 +2148,7 @@ impl Storage {
                 message_json,
                 created_at,
             )) => Ok(Some(Message {
+                message_json: self.open_message_json(message_json, &message_id, &message_type)?,
                 id,
                 message_id,
                 message_type,
@@ -969,7 +2158,6 @@ impl Storage {
                 parent_thread_id,
                 direction: MessageDirection::try_from(direction.as_str())
                     .map_err(StorageError::InvalidTransactionType)?,
-                message_json,
                 created_at,
             })),
             None => Ok(None),
@@ -992,6 +2180,36 @@ impl Storage {
         limit: u32,
         offset: u32,
         direction: Option<MessageDirection>,
+    ) -> Result<Vec<Message>, StorageError> {
+        let Some(backend) = &self.backend else {
+            return self.list_local_messages(limit, offset, direction).await;
+        };
+        // Messages encrypted at rest are only found in this database, so the
+        // page is cut from both listings merged, the backend's copy of a
+        // message winning
+        let window = limit.saturating_add(offset);
+        let mut messages = backend.list_messages(window, 0, direction.clone()).await?;
+        let listed: HashSet<String> = messages.iter().map(|m| m.message_id.clone()).collect();
+        messages.extend(
+            self.list_local_messages(window, 0, direction)
+                .await?
+                .into_iter()
+                .filter(|message| !listed.contains(&message.message_id)),
+        );
+        messages.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(messages
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect())
+    }
+
+    /// List the messages of this database, see [`Storage::list_messages`]
+    async fn list_local_messages(
+        &self,
+        limit: u32,
+        offset: u32,
+        direction: Option<MessageDirection>,
     ) -> Result<Vec<Message>, StorageError> {
         let rows = if let Some(dir) = direction {
             sqlx::query_as::<_, (
@@ -1060,6 +2278,7 @@ impl Storage {
         ) in rows
         {
             messages.push(Message {
+                message_json: self.open_message_json(message_json, &message_id, &message_type)?,
                 id,
                 message_id,
                 message_type,
@@ -1069,7 +2288,6 @@ impl Storage {
                 parent_thread_id,
                 direction: MessageDirection::try_from(direction.as_str())
                     .map_err(StorageError::InvalidTransactionType)?,
-                message_json,
                 created_at,
             });
         }
@@ -1099,21 +2317,43 @@ impl Storage {
         delivery_url: Option<&str>,
         delivery_type: DeliveryType,
     ) -> Result<i64, StorageError> {
-        let result = sqlx::query(
-            r#"
-            INSERT INTO deliveries (message_id, message_text, recipient_did, delivery_url, delivery_type, status, retry_count)
-            VALUES (?1, ?2, ?3, ?4, ?5, 'pending', 0)
-            "#,
-        )
-        .bind(message_id)
-        .bind(message_text)
-        .bind(recipient_did)
-        .bind(delivery_url)
-        .bind(delivery_type.to_string())
-        .execute(&self.pool)
-        .await?;
+        let message_text = match self.cipher_for_message(message_id).await? {
+            Some((cipher, associated_data)) => cipher.encrypt(message_text, &associated_data)?,
+            None => message_text.to_string(),
+        };
+        // The backend assigns the ID, which the copy in this database keeps
+        let delivery_id = match &self.backend {
+            Some(backend) => Some(
+                backend
+                    .create_delivery(
+                        message_id,
+                        &message_text,
+                        recipient_did,
+                        delivery_url,
+                        delivery_type.clone(),
+                    )
+                    .await?,
+            ),
+            None => None,
+        };
+        let event = self
+            .commit_event(StateEvent::DeliveryCreated {
+                delivery_id,
+                message_id: message_id.to_string(),
+                message_text,
+                recipient_did: recipient_did.to_string(),
+                delivery_url: delivery_url.map(String::from),
+                delivery_type,
+            })
+            .await?;
 
-        Ok(result.last_insert_rowid())
+        match event {
+            StateEvent::DeliveryCreated {
+                delivery_id: Some(delivery_id),
+                ..
+            } => Ok(delivery_id),
+            _ => unreachable!("created deliveries are assigned an ID"),
+        }
     }
 
     /// Update delivery status
@@ -1136,27 +2376,22 @@ impl Storage {
         http_status_code: Option<i32>,
         error_message: Option<&str>,
     ) -> Result<(), StorageError> {
-        let now = chrono::Utc::now().to_rfc3339();
-        let delivered_at = if status == DeliveryStatus::Success {
-            Some(now.clone())
-        } else {
-            None
-        };
-
-        sqlx::query(
-            r#"
-            UPDATE deliveries 
-            SET status = ?1, last_http_status_code = ?2, error_message = ?3, updated_at = ?4, delivered_at = ?5
-            WHERE id = ?6
-            "#,
-        )
-        .bind(status.to_string())
-        .bind(http_status_code)
-        .bind(error_message)
-        .bind(now)
-        .bind(delivered_at)
-        .bind(delivery_id)
-        .execute(&self.pool)
+        if let Some(backend) = &self.backend {
+            backend
+                .update_delivery_status(
+                    delivery_id,
+                    status.clone(),
+                    http_status_code,
+                    error_message,
+                )
+                .await?;
+        }
+        self.commit_event(StateEvent::DeliveryStatusUpdated {
+            delivery_id,
+            status,
+            http_status_code,
+            error_message: error_message.map(String::from),
+        })
         .await?;
 
         Ok(())
@@ -1176,17 +2411,11 @@ impl Storage {
         &self,
         delivery_id: i64,
     ) -> Result<(), StorageError> {
-        sqlx::query(
-            r#"
-            UPDATE deliveries 
-            SET retry_count = retry_count + 1, updated_at = ?1
-            WHERE id = ?2
-            "#,
-        )
-        .bind(chrono::Utc::now().to_rfc3339())
-        .bind(delivery_id)
-        .execute(&self.pool)
-        .await?;
+        if let Some(backend) = &self.backend {
+            backend.increment_delivery_retry_count(delivery_id).await?;
+        }
+        self.commit_event(StateEvent::DeliveryRetried { delivery_id })
+            .await?;
 
         Ok(())
     }
@@ -1222,11 +2451,12 @@ impl Storage {
                 String,
                 String,
                 Option<String>,
+                Option<String>,
             ),
         >(
             r#"
             SELECT id, message_id, message_text, recipient_did, delivery_url, delivery_type, status, retry_count, 
-                   last_http_status_code, error_message, created_at, updated_at, delivered_at
+                   last_http_status_code, error_message, created_at, updated_at, delivered_at, next_attempt_at
             FROM deliveries WHERE id = ?1
             "#,
         )
@@ -1249,10 +2479,11 @@ impl Storage {
                 created_at,
                 updated_at,
                 delivered_at,
+                next_attempt_at,
             )) => Ok(Some(Delivery {
                 id,
+                message_text: self.open_delivery_text(&message_id, message_text).await?,
                 message_id,
-                message_text,
                 recipient_did,
                 delivery_url,
                 delivery_type: DeliveryType::try_from(delivery_type.as_str())
@@ -1265,6 +2496,7 @@ impl Storage {
                 created_at,
                 updated_at,
                 delivered_at,
+                next_attempt_at,
             })),
             None => Ok(None),
         }
@@ -1284,6 +2516,15 @@ impl Storage {
         &self,
         message_id: &str,
     ) -> Result<Vec<Delivery>, StorageError> {
+        if let Some(backend) = &self.backend {
+            let mut deliveries = backend.get_deliveries_for_message(message_id).await?;
+            for delivery in &mut deliveries {
+                delivery.message_text = self
+                    .open_delivery_text(message_id, std::mem::take(&mut delivery.message_text))
+                    .await?;
+            }
+            return Ok(deliveries);
+        }
         let rows = sqlx::query_as::<
             _,
             (
@@ -1300,11 +2541,12 @@ impl Storage {
                 String,
                 String,
                 Option<String>,
+                Option<String>,
             ),
         >(
             r#"
             SELECT id, message_id, message_text, recipient_did, delivery_url, delivery_type, status, retry_count, 
-                   last_http_status_code, error_message, created_at, updated_at, delivered_at
+                   last_http_status_code, error_message, created_at, updated_at, delivered_at, next_attempt_at
             FROM deliveries WHERE message_id = ?1
             ORDER BY created_at ASC
             "#,
@@ -1328,12 +2570,13 @@ impl Storage {
             created_at,
             updated_at,
             delivered_at,
+            next_attempt_at,
         ) in rows
         {
             deliveries.push(Delivery {
                 id,
+                message_text: self.open_delivery_text(&message_id, message_text).await?,
                 message_id,
-                message_text,
                 recipient_did,
                 delivery_url,
                 delivery_type: DeliveryType::try_from(delivery_type.as_str())
@@ -1346,6 +2589,7 @@ impl Storage {
                 created_at,
                 updated_at,
                 delivered_at,
+                next_attempt_at,
             });
         }
 
@@ -1384,11 +2628,12 @@ impl Storage {
                 String,
                 String,
                 Option<String>,
+                Option<String>,
             ),
         >(
             r#"
             SELECT id, message_id, message_text, recipient_did, delivery_url, delivery_type, status, retry_count, 
-                   last_http_status_code, error_message, created_at, updated_at, delivered_at
+                   last_http_status_code, error_message, created_at, updated_at, delivered_at, next_attempt_at
             FROM deliveries 
             WHERE status = 'pending' AND retry_count < ?1
             ORDER BY created_at ASC
@@ -1415,12 +2660,13 @@ impl Storage {
             created_at,
             updated_at,
             delivered_at,
+            next_attempt_at,
         ) in rows
         {
             deliveries.push(Delivery {
                 id,
+                message_text: self.open_delivery_text(&message_id, message_text).await?,
                 message_id,
-                message_text,
                 recipient_did,
                 delivery_url,
                 delivery_type: DeliveryType::try_from(delivery_type.as_str())
@@ -1433,6 +2679,7 @@ impl Storage {
                 created_at,
                 updated_at,
                 delivered_at,
+                next_attempt_at,
             });
         }
 
@@ -1473,11 +2720,12 @@ impl Storage {
                 String,
                 String,
                 Option<String>,
+                Option<String>,
             ),
         >(
             r#"
             SELECT id, message_id, message_text, recipient_did, delivery_url, delivery_type, status, retry_count, 
-                   last_http_status_code, error_message, created_at, updated_at, delivered_at
+                   last_http_status_code, error_message, created_at, updated_at, delivered_at, next_attempt_at
             FROM deliveries 
             WHERE recipient_did = ?1 AND status = 'failed'
             ORDER BY updated_at DESC
@@ -1505,12 +2753,13 @@ impl Storage {
             created_at,
             updated_at,
             delivered_at,
+            next_attempt_at,
         ) in rows
         {
             deliveries.push(Delivery {
                 id,
+                message_text: self.open_delivery_text(&message_id, message_text).await?,
                 message_id,
-                message_text,
                 recipient_did,
                 delivery_url,
                 delivery_type: DeliveryType::try_from(delivery_type.as_str())
@@ -1523,6 +2772,7 @@ impl Storage {
                 created_at,
                 updated_at,
                 delivered_at,
+                next_attempt_at,
             });
         }
 
@@ -1563,11 +2813,12 @@ impl Storage {
                 String,
                 String,
                 Option<String>,
+                Option<String>,
             ),
         >(
             r#"
             SELECT id, message_id, message_text, recipient_did, delivery_url, delivery_type, status, retry_count, 
-                   last_http_status_code, error_message, created_at, updated_at, delivered_at
+                   last_http_status_code, error_message, created_at, updated_at, delivered_at, next_attempt_at
             FROM deliveries 
             WHERE recipient_did = ?1
             ORDER BY created_at DESC
@@ -1595,12 +2846,13 @@ impl Storage {
             created_at,
             updated_at,
             delivered_at,
+            next_attempt_at,
         ) in rows
         {
             deliveries.push(Delivery {
                 id,
+                message_text: self.open_delivery_text(&message_id, message_text).await?,
                 message_id,
-                message_text,
                 recipient_did,
                 delivery_url,
                 delivery_type: delivery_type
@@ -1615,6 +2867,7 @@ impl Storage {
                 created_at,
                 updated_at,
                 delivered_at,
+                next_attempt_at,
             });
         }
 
@@ -1655,12 +2908,13 @@ impl Storage {
                 String,
                 String,
                 Option<String>,
+                Option<String>,
             ),
         >(
             r#"
             SELECT d.id, d.message_id, d.message_text, d.recipient_did, d.delivery_url, 
                    d.delivery_type, d.status, d.retry_count, d.last_http_status_code, 
-                   d.error_message, d.created_at, d.updated_at, d.delivered_at
+                   d.error_message, d.created_at, d.updated_at, d.delivered_at, d.next_attempt_at
             FROM deliveries d
             INNER JOIN messages m ON d.message_id = m.message_id
             WHERE m.thread_id = ?1
@@ -1689,12 +2943,13 @@ impl Storage {
             created_at,
             updated_at,
             delivered_at,
+            next_attempt_at,
         ) in rows
         {
             deliveries.push(Delivery {
                 id,
+                message_text: self.open_delivery_text(&message_id, message_text).await?,
                 message_id,
-                message_text,
                 recipient_did,
                 delivery_url,
                 delivery_type: delivery_type
@@ -1709,20 +2964,187 @@ impl Storage {
                 created_at,
                 updated_at,
                 delivered_at,
+                next_attempt_at,
             });
         }
 
         Ok(deliveries)
     }
 
-    /// Create a new received message record
+    /// Get the queue of external deliveries: HTTPS deliveries that are
+    /// pending or failed, ordered by their next attempt
     ///
-    /// This records a raw incoming message (JWE, JWS, or plain JSON) before processing.
+    /// Deliveries that are not scheduled for another attempt come last.
     ///
     /// # Arguments
     ///
-    /// * `raw_message` - The raw message content as received
-    /// * `source_type` - The type of source (https, internal, websocket, etc.)
+    /// * `limit` - Maximum number of deliveries to return
+    /// * `offset` - Number of deliveries to skip (for pagination)
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Delivery>)` - The queued deliveries
+    /// * `Err(StorageError)` on database error
+    pub async fn get_delivery_queue(
+        &self,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<Delivery>, StorageError> {
+        let ids: Vec<i64> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM deliveries
+            WHERE delivery_type = 'https' AND status IN ('pending', 'failed')
+            ORDER BY next_attempt_at IS NULL, next_attempt_at ASC, created_at ASC, id ASC
+            LIMIT ?1 OFFSET ?2
+            "#,
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        self.get_deliveries_by_ids(&ids).await
+    }
+
+    /// Get all failed HTTPS deliveries, optionally only those to one recipient
+    ///
+    /// # Arguments
+    ///
+    /// * `recipient_did` - Only return deliveries to this recipient
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Delivery>)` - The failed deliveries, oldest first
+    /// * `Err(StorageError)` on database error
+    pub async fn get_failed_deliveries(
+        &self,
+        recipient_did: Option<&str>,
+    ) -> Result<Vec<Delivery>, StorageError> {
+        let ids: Vec<i64> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM deliveries
+            WHERE delivery_type = 'https' AND status = 'failed'
+              AND (?1 IS NULL OR recipient_did = ?1)
+            ORDER BY created_at ASC, id ASC
+            "#,
+        )
+        .bind(recipient_did)
+        .fetch_all(&self.pool)
+        .await?;
+
+        self.get_deliveries_by_ids(&ids).await
+    }
+
+    /// Get failed HTTPS deliveries whose next attempt is due
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time, as `%Y-%m-%dT%H:%M:%SZ`
+    /// * `limit` - Maximum number of deliveries to return
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Delivery>)` - The due deliveries, longest overdue first
+    /// * `Err(StorageError)` on database error
+    pub async fn get_due_deliveries(
+        &self,
+        now: &str,
+        limit: u32,
+    ) -> Result<Vec<Delivery>, StorageError> {
+        let ids: Vec<i64> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM deliveries
+            WHERE delivery_type = 'https' AND status = 'failed'
+              AND next_attempt_at IS NOT NULL AND next_attempt_at <= ?1
+            ORDER BY next_attempt_at ASC, id ASC
+            LIMIT ?2
+            "#,
+        )
+        .bind(now)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        self.get_deliveries_by_ids(&ids).await
+    }
+
+    async fn get_deliveries_by_ids(&self, ids: &[i64]) -> Result<Vec<Delivery>, StorageError> {
+        let mut deliveries = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(delivery) = self.get_delivery_by_id(*id).await? {
+                deliveries.push(delivery);
+            }
+        }
+        Ok(deliveries)
+    }
+
+    /// Schedule the next attempt of a delivery
+    ///
+    /// # Arguments
+    ///
+    /// * `delivery_id` - The ID of the delivery record
+    /// * `next_attempt_at` - When to attempt the delivery, as
+    ///   `%Y-%m-%dT%H:%M:%SZ`, or None to not attempt it again
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` on success
+    /// * `Err(StorageError)` on database error
+    pub async fn schedule_delivery_attempt(
+        &self,
+        delivery_id: i64,
+        next_attempt_at: Option<&str>,
+    ) -> Result<(), StorageError> {
+        if let Some(backend) = &self.backend {
+            backend
+                .schedule_delivery_attempt(delivery_id, next_attempt_at)
+                .await?;
+        }
+        self.commit_event(StateEvent::DeliveryRescheduled {
+            delivery_id,
+            next_attempt_at: next_attempt_at.map(String::from),
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Deliver a message to a different endpoint from its next attempt on
+    ///
+    /// # Arguments
+    ///
+    /// * `delivery_id` - The ID of the delivery record
+    /// * `delivery_url` - The URL to deliver the message to
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` on success
+    /// * `Err(StorageError)` on database error
+    pub async fn reassign_delivery(
+        &self,
+        delivery_id: i64,
+        delivery_url: &str,
+    ) -> Result<(), StorageError> {
+        if let Some(backend) = &self.backend {
+            backend.reassign_delivery(delivery_id, delivery_url).await?;
+        }
+        self.commit_event(StateEvent::DeliveryReassigned {
+            delivery_id,
+            delivery_url: delivery_url.to_string(),
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Create a new received message record
+    ///
+    /// This records a raw incoming message (JWE, JWS, or plain JSON) before processing.
+    ///
+    /// # Arguments
+    ///
+    /// * `raw_message` - The raw message content as received
+    /// * `source_type` - The type of source (https, internal, websocket, etc.)
     /// * `source_identifier` - Optional identifier for the source (URL, agent DID, etc.)
     ///
     /// # Returns
@@ -1735,6 +3157,8 @@ impl Storage {
         source_type: SourceType,
         source_identifier: Option<&str>,
     ) -> Result<i64, StorageError> {
+        self.check_quota().await?;
+
         // Try to extract message ID from the raw message
         let message_id =
             if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(raw_message) {
@@ -1746,20 +3170,37 @@ impl Storage {
                 None
             };
 
+        let cipher = encryption::envelope_message_type(raw_message)
+            .and_then(|message_type| self.envelope_cipher_for(&message_type));
+
+        // An encrypted envelope is bound to the ID of its record, so the
+        // record is created first and the envelope filled in before commit
+        let mut tx = self.pool.begin().await?;
         let result = sqlx::query(
             r#"
-            INSERT INTO received (message_id, raw_message, source_type, source_identifier)
-            VALUES (?1, ?2, ?3, ?4)
+            INSERT INTO received (message_id, raw_message, source_type, source_identifier, received_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
             "#,
         )
         .bind(message_id)
-        .bind(raw_message)
+        .bind(if cipher.is_some() { "" } else { raw_message })
         .bind(source_type.to_string())
         .bind(source_identifier)
-        .execute(&self.pool)
+        .bind(self.now())
+        .execute(&mut *tx)
         .await?;
+        let received_id = result.last_insert_rowid();
+
+        if let Some(cipher) = cipher {
+            sqlx::query("UPDATE received SET raw_message = ?1 WHERE id = ?2")
+                .bind(cipher.encrypt(raw_message, &Self::received_binding(received_id))?)
+                .bind(received_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
 
-        Ok(result.last_insert_rowid())
+        Ok(received_id)
     }
 
     /// Update the status of a received message
@@ -1782,7 +3223,7 @@ impl Storage {
         processed_message_id: Option<&str>,
         error_message: Option<&str>,
     ) -> Result<(), StorageError> {
-        let now = chrono::Utc::now().to_rfc3339();
+        let now = self.clock.now().to_rfc3339();
 
         sqlx::query(
             r#"
@@ -1857,7 +3298,7 @@ impl Storage {
             )) => Ok(Some(Received {
                 id,
                 message_id,
-                raw_message,
+                raw_message: self.open_text(raw_message, &Self::received_binding(id))?,
                 source_type: SourceType::try_from(source_type.as_str())
                     .map_err(StorageError::InvalidTransactionType)?,
                 source_identifier,
@@ -1928,7 +3369,7 @@ impl Storage {
             received_messages.push(Received {
                 id,
                 message_id,
-                raw_message,
+                raw_message: self.open_text(raw_message, &Self::received_binding(id))?,
                 source_type: SourceType::try_from(source_type.as_str())
                     .map_err(StorageError::InvalidTransactionType)?,
                 source_identifier,
@@ -2023,7 +3464,7 @@ impl Storage {
             received_messages.push(Received {
                 id,
                 message_id,
-                raw_message,
+                raw_message: self.open_text(raw_message, &Self::received_binding(id))?,
                 source_type: SourceType::try_from(source_type.as_str())
                     .map_err(StorageError::InvalidTransactionType)?,
                 source_identifier,
@@ -2039,20 +3480,190 @@ impl Storage {
         Ok(received_messages)
     }
 
+    /// List incoming messages from the message log after a message log ID
+    ///
+    /// # Arguments
+    ///
+    /// * `after_id` - Only messages with a larger ID are returned
+    /// * `filter` - Optional message type, sender and thread filters
+    /// * `limit` - Maximum number of messages to return
+    ///
+    /// # Returns
+    ///
+    /// Incoming messages ordered by ID ascending
+    pub async fn list_incoming_messages(
+        &self,
+        after_id: i64,
+        filter: &ReceivedFilter,
+        limit: u32,
+    ) -> Result<Vec<Message>, StorageError> {
+        let mut query = "SELECT id, message_id, message_type, from_did, to_did, thread_id, parent_thread_id, direction, message_json, created_at FROM messages WHERE direction = 'incoming' AND id > ?".to_string();
+        let mut bind_values: Vec<&str> = Vec::new();
+
+        if let Some(message_type) = &filter.message_type {
+            query.push_str(" AND message_type = ?");
+            bind_values.push(message_type);
+        }
+
+        if let Some(from_did) = &filter.from_did {
+            query.push_str(" AND from_did = ?");
+            bind_values.push(from_did);
+        }
+
+        if let Some(thread_id) = &filter.thread_id {
+            query.push_str(" AND thread_id = ?");
+            bind_values.push(thread_id);
+        }
+
+        query.push_str(" ORDER BY id ASC LIMIT ?");
+
+        let mut sqlx_query = sqlx::query_as::<
+            _,
+            (
+                i64,
+                String,
+                String,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                String,
+                serde_json::Value,
+                String,
+            ),
+        >(&query)
+        .bind(after_id);
+
+        for value in bind_values {
+            sqlx_query = sqlx_query.bind(value);
+        }
+
+        let rows = sqlx_query.bind(limit).fetch_all(&self.pool).await?;
+
+        let mut messages = Vec::new();
+        for (
+            id,
+            message_id,
+            message_type,
+            from_did,
+            to_did,
+            thread_id,
+            parent_thread_id,
+            direction,
+            message_json,
+            created_at,
+        ) in rows
+        {
+            messages.push(Message {
+                message_json: self.open_message_json(message_json, &message_id, &message_type)?,
+                id,
+                message_id,
+                message_type,
+                from_did,
+                to_did,
+                thread_id,
+                parent_thread_id,
+                direction: MessageDirection::try_from(direction.as_str())
+                    .map_err(StorageError::InvalidTransactionType)?,
+                created_at,
+            });
+        }
+
+        Ok(messages)
+    }
+
+    /// Count the incoming messages in the message log after a message log ID
+    pub async fn count_incoming_messages(&self, after_id: i64) -> Result<u64, StorageError> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM messages WHERE direction = 'incoming' AND id > ?1",
+        )
+        .bind(after_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count as u64)
+    }
+
+    /// Get the ID of the last incoming message in the message log, or 0 if
+    /// there is none
+    pub async fn last_incoming_message_id(&self) -> Result<i64, StorageError> {
+        let id: Option<i64> =
+            sqlx::query_scalar("SELECT MAX(id) FROM messages WHERE direction = 'incoming'")
+                .fetch_one(&self.pool)
+                .await?;
+
+        Ok(id.unwrap_or(0))
+    }
+
+    /// Get the read cursor of an agent over its incoming messages
+    ///
+    /// Incoming messages with an ID up to and including the cursor count as
+    /// read. The cursor is 0 until messages are first marked read.
+    pub async fn get_inbox_cursor(&self, agent_did: &str) -> Result<i64, StorageError> {
+        let cursor: Option<i64> =
+            sqlx::query_scalar("SELECT read_up_to FROM inbox_cursors WHERE agent_did = ?1")
+                .bind(agent_did)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(cursor.unwrap_or(0))
+    }
+
+    /// Advance the read cursor of an agent over its incoming messages
+    ///
+    /// The cursor never moves backwards. Returns the cursor after the update.
+    pub async fn advance_inbox_cursor(
+        &self,
+        agent_did: &str,
+        read_up_to: i64,
+    ) -> Result<i64, StorageError> {
+        sqlx::query(
+            r#"
+            INSERT INTO inbox_cursors (agent_did, read_up_to, updated_at)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT(agent_did) DO UPDATE SET
+                read_up_to = MAX(read_up_to, excluded.read_up_to),
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(agent_did)
+        .bind(read_up_to)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        self.get_inbox_cursor(agent_did).await
+    }
+
     // Customer Management Methods
 
     /// Create or update a customer record
+    ///
+    /// A customer whose personal data has been erased is left as it is, so
+    /// that extracting a party of a later transaction does not write the data
+    /// back.
     pub async fn upsert_customer(&self, customer: &Customer) -> Result<(), StorageError> {
-        sqlx::query(
+        Self::upsert_customer_with(&self.pool, customer).await
+    }
+
+    async fn upsert_customer_with<'e, E>(
+        executor: E,
+        customer: &Customer,
+    ) -> Result<(), StorageError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+    {
+        let result = sqlx::query(
             r#"
             INSERT INTO customers (
                 id, agent_did, schema_type, given_name, family_name, display_name,
                 legal_name, lei_code, mcc_code, address_country, address_locality,
                 postal_code, street_address, profile, ivms101_data, verified_at,
                 created_at, updated_at
-            ) VALUES (
-                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18
-            ) ON CONFLICT(id) DO UPDATE SET
+            )
+            SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18
+            WHERE NOT EXISTS (SELECT 1 FROM customer_erasures WHERE customer_id = ?1)
+            ON CONFLICT(id) DO UPDATE SET
                 agent_did = excluded.agent_did,
                 schema_type = excluded.schema_type,
                 given_name = excluded.given_name,
@@ -2095,9 +3706,12 @@ impl Storage {
         .bind(&customer.verified_at)
         .bind(&customer.created_at)
         .bind(&customer.updated_at)
-        .execute(&self.pool)
+        .execute(executor)
         .await?;
 
+        if result.rows_affected() == 0 {
+            debug!("Not updating erased customer {}", customer.id);
+        }
         Ok(())
     }
 
@@ -2225,6 +3839,9 @@ impl Storage {
     }
 
     /// Add an identifier to a customer
+    ///
+    /// A customer whose personal data has been erased only takes DID
+    /// identifiers.
     pub async fn add_customer_identifier(
         &self,
         identifier: &CustomerIdentifier,
@@ -2234,9 +3851,11 @@ impl Storage {
             INSERT INTO customer_identifiers (
                 id, customer_id, identifier_type, verified, verification_method,
                 verified_at, created_at
-            ) VALUES (
-                ?1, ?2, ?3, ?4, ?5, ?6, ?7
-            ) ON CONFLICT(id, customer_id) DO UPDATE SET
+            )
+            SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7
+            WHERE ?3 = 'did'
+               OR NOT EXISTS (SELECT 1 FROM customer_erasures WHERE customer_id = ?2)
+            ON CONFLICT(id, customer_id) DO UPDATE SET
                 verified = excluded.verified,
                 verification_method = excluded.verification_method,
                 verified_at = excluded.verified_at
@@ -2470,1187 +4089,7171 @@ impl Storage {
     }
 
     // -----------------------------------------------------------------------
-    // Decision log operations
+    // Customer retention and erasure
     // -----------------------------------------------------------------------
 
-    /// Insert a new decision into the decision log
+    /// Erase a customer's personal data, leaving a tombstone
     ///
-    /// # Arguments
+    /// Names, addresses, the schema.org profile and cached IVMS101 data are
+    /// redacted and non-DID identifiers are removed. The customer row, its DID
+    /// identifiers and relationships are kept so that transactions referencing
+    /// the customer stay intact. A SHA-256 hash of the original record and the
+    /// customer's name hash are recorded in the `customer_erasures` table.
     ///
-    /// * `transaction_id` - The transaction requiring a decision
-    /// * `agent_did` - The DID of the agent this decision is for
-    /// * `decision_type` - The type of decision required
-    /// * `context_json` - JSON context about the decision (transaction details, etc.)
+    /// Erasing an already erased customer redacts its record again, should
+    /// personal data have been written to it since, and returns the existing
+    /// tombstone.
     ///
     /// # Returns
     ///
-    /// * `Ok(i64)` - The ID of the created decision log entry
+    /// * `Ok(Some(CustomerErasure))` - The tombstone for the erased customer
+    /// * `Ok(None)` if the customer does not exist
     /// * `Err(StorageError)` on database error
-    pub async fn insert_decision(
+    pub async fn erase_customer(
         &self,
-        transaction_id: &str,
-        agent_did: &str,
-        decision_type: DecisionType,
-        context_json: &serde_json::Value,
-    ) -> Result<i64, StorageError> {
-        debug!(
-            "Inserting decision for transaction {} agent {} type {}",
-            transaction_id, agent_did, decision_type
-        );
+        customer_id: &str,
+        reason: Option<&str>,
+    ) -> Result<Option<CustomerErasure>, StorageError> {
+        let existing = self.get_customer_erasure(customer_id).await?;
+        let customer = match self.get_customer(customer_id).await? {
+            Some(customer) => customer,
+            None => return Ok(existing),
+        };
 
-        let context_str = serde_json::to_string(context_json)?;
+        let now = self.clock.now().to_rfc3339();
+        let erasure = match existing {
+            Some(existing) => existing,
+            None => CustomerErasure {
+                customer_id: customer_id.to_string(),
+                agent_did: customer.agent_did.clone(),
+                profile_hash: format!("{:x}", Sha256::digest(serde_json::to_vec(&customer)?)),
+                name_hash: customer
+                    .get_name_hash()
+                    .or_else(|| customer.generate_name_hash()),
+                reason: reason.map(|r| r.to_string()),
+                erased_at: now.clone(),
+            },
+        };
 
-        let result = sqlx::query(
+        let mut redacted_profile = serde_json::json!({
+            "@context": "https://schema.org",
+            "@type": customer.schema_type.to_string(),
+            "identifier": customer.id,
+            "erased": true,
+        });
+        if let Some(ref hash) = erasure.name_hash {
+            redacted_profile["nameHash"] = serde_json::Value::String(hash.clone());
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
             r#"
-            INSERT INTO decision_log (transaction_id, agent_did, decision_type, context_json)
-            VALUES (?1, ?2, ?3, ?4)
+            UPDATE customers SET
+                given_name = NULL,
+                family_name = NULL,
+                display_name = NULL,
+                legal_name = NULL,
+                address_country = NULL,
+                address_locality = NULL,
+                postal_code = NULL,
+                street_address = NULL,
+                profile = ?1,
+                ivms101_data = NULL,
+                verified_at = NULL,
+                updated_at = ?2
+            WHERE id = ?3
             "#,
         )
-        .bind(transaction_id)
-        .bind(agent_did)
-        .bind(decision_type.to_string())
-        .bind(&context_str)
-        .execute(&self.pool)
+        .bind(serde_json::to_string(&redacted_profile)?)
+        .bind(&now)
+        .bind(customer_id)
+        .execute(&mut *tx)
         .await?;
 
-        Ok(result.last_insert_rowid())
-    }
+        sqlx::query(
+            r#"
+            DELETE FROM customer_identifiers
+            WHERE customer_id = ?1 AND identifier_type != 'did'
+            "#,
+        )
+        .bind(customer_id)
+        .execute(&mut *tx)
+        .await?;
 
-    /// Update the status of a decision log entry
-    ///
-    /// # Arguments
-    ///
-    /// * `decision_id` - The ID of the decision to update
-    /// * `status` - The new status
-    /// * `resolution` - Optional resolution action (e.g., "authorize", "reject")
-    /// * `resolution_detail` - Optional JSON detail about the resolution
-    ///
-    /// # Returns
-    ///
-    /// * `Ok(())` on success
-    /// * `Err(StorageError)` on database error
-    pub async fn update_decision_status(
-        &self,
-        decision_id: i64,
-        status: DecisionStatus,
-        resolution: Option<&str>,
-        resolution_detail: Option<&serde_json::Value>,
-    ) -> Result<(), StorageError> {
-        debug!("Updating decision {} status to {}", decision_id, status);
+        // Copies of the customer's data kept with transactions
+        sqlx::query("DELETE FROM transaction_pii WHERE customer_id = ?1")
+            .bind(customer_id)
+            .execute(&mut *tx)
+            .await?;
 
-        let now = chrono::Utc::now().to_rfc3339();
-        let resolution_detail_str = resolution_detail.map(serde_json::to_string).transpose()?;
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO customer_erasures (
+                customer_id, agent_did, profile_hash, name_hash, reason, erased_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+        )
+        .bind(&erasure.customer_id)
+        .bind(&erasure.agent_did)
+        .bind(&erasure.profile_hash)
+        .bind(&erasure.name_hash)
+        .bind(&erasure.reason)
+        .bind(&erasure.erased_at)
+        .execute(&mut *tx)
+        .await?;
 
-        let delivered_at = if status == DecisionStatus::Delivered {
-            Some(now.clone())
-        } else {
-            None
-        };
+        tx.commit().await?;
 
-        let resolved_at = if status == DecisionStatus::Resolved {
-            Some(now)
-        } else {
-            None
-        };
+        debug!("Erased personal data for customer {}", customer_id);
 
-        sqlx::query(
+        Ok(Some(erasure))
+    }
+
+    /// Get the erasure tombstone for a customer, if it has been erased
+    pub async fn get_customer_erasure(
+        &self,
+        customer_id: &str,
+    ) -> Result<Option<CustomerErasure>, StorageError> {
+        let row = sqlx::query_as::<
+            _,
+            (
+                String,
+                String,
+                String,
+                Option<String>,
+                Option<String>,
+                String,
+            ),
+        >(
             r#"
-            UPDATE decision_log
-            SET status = ?1,
-                resolution = COALESCE(?2, resolution),
-                resolution_detail = COALESCE(?3, resolution_detail),
-                delivered_at = COALESCE(?4, delivered_at),
-                resolved_at = COALESCE(?5, resolved_at)
-            WHERE id = ?6
+            SELECT customer_id, agent_did, profile_hash, name_hash, reason, erased_at
+            FROM customer_erasures
+            WHERE customer_id = ?1
             "#,
         )
-        .bind(status.to_string())
-        .bind(resolution)
-        .bind(resolution_detail_str.as_deref())
-        .bind(delivered_at)
-        .bind(resolved_at)
-        .bind(decision_id)
-        .execute(&self.pool)
+        .bind(customer_id)
+        .fetch_optional(&self.pool)
         .await?;
 
-        Ok(())
+        Ok(row.map(
+            |(customer_id, agent_did, profile_hash, name_hash, reason, erased_at)| {
+                CustomerErasure {
+                    customer_id,
+                    agent_did,
+                    profile_hash,
+                    name_hash,
+                    reason,
+                    erased_at,
+                }
+            },
+        ))
     }
 
-    /// List decisions from the decision log with optional filtering
-    ///
-    /// # Arguments
-    ///
-    /// * `agent_did` - Optional filter by agent DID
-    /// * `status` - Optional filter by status
-    /// * `since_id` - Optional minimum decision ID (for pagination/replay)
-    /// * `limit` - Maximum number of entries to return
-    ///
-    /// # Returns
-    ///
-    /// * `Ok(Vec<DecisionLogEntry>)` - Matching decision log entries ordered by id ASC
-    /// * `Err(StorageError)` on database error
-    pub async fn list_decisions(
+    /// List erasure tombstones for an agent, most recent first
+    pub async fn list_customer_erasures(
         &self,
-        agent_did: Option<&str>,
-        status: Option<DecisionStatus>,
-        since_id: Option<i64>,
+        agent_did: &str,
         limit: u32,
-    ) -> Result<Vec<DecisionLogEntry>, StorageError> {
-        let mut query = String::from(
-            "SELECT id, transaction_id, agent_did, decision_type, context_json, \
-             status, resolution, resolution_detail, created_at, delivered_at, resolved_at \
-             FROM decision_log WHERE 1=1",
-        );
-        let mut bind_values: Vec<String> = Vec::new();
-        let mut bind_i64: Option<i64> = None;
-
-        if let Some(did) = agent_did {
-            query.push_str(" AND agent_did = ?");
-            bind_values.push(did.to_string());
-        }
-
-        if let Some(s) = status {
-            query.push_str(" AND status = ?");
-            bind_values.push(s.to_string());
-        }
-
-        if let Some(id) = since_id {
-            query.push_str(" AND id > ?");
-            bind_i64 = Some(id);
-        }
-
-        query.push_str(" ORDER BY id ASC LIMIT ?");
-
-        let mut sqlx_query = sqlx::query(&query);
-
-        for value in &bind_values {
-            sqlx_query = sqlx_query.bind(value);
-        }
-
-        if let Some(id) = bind_i64 {
-            sqlx_query = sqlx_query.bind(id);
-        }
+        offset: u32,
+    ) -> Result<Vec<CustomerErasure>, StorageError> {
+        let rows = sqlx::query_as::<
+            _,
+            (
+                String,
+                String,
+                String,
+                Option<String>,
+                Option<String>,
+                String,
+            ),
+        >(
+            r#"
+            SELECT customer_id, agent_did, profile_hash, name_hash, reason, erased_at
+            FROM customer_erasures
+            WHERE agent_did = ?1
+            ORDER BY erased_at DESC
+            LIMIT ?2 OFFSET ?3
+            "#,
+        )
+        .bind(agent_did)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
 
-        sqlx_query = sqlx_query.bind(limit);
+        Ok(rows
+            .into_iter()
+            .map(
+                |(customer_id, agent_did, profile_hash, name_hash, reason, erased_at)| {
+                    CustomerErasure {
+                        customer_id,
+                        agent_did,
+                        profile_hash,
+                        name_hash,
+                        reason,
+                        erased_at,
+                    }
+                },
+            )
+            .collect())
+    }
 
-        let rows = sqlx_query.fetch_all(&self.pool).await?;
+    /// Encrypt the personal data of a party of a transaction with the data
+    /// key of the transaction, creating the key on first use
+    ///
+    /// The data key is stored wrapped with the storage's encryption key, so
+    /// this needs [`Storage::with_message_encryption`]. Sealing a role again
+    /// replaces its data.
+    pub async fn seal_transaction_pii(
+        &self,
+        transaction_id: &str,
+        role: &str,
+        customer_id: Option<&str>,
+        data: &serde_json::Value,
+    ) -> Result<(), StorageError> {
+        let data_key = match self.transaction_data_key(transaction_id).await? {
+            Some(data_key) => data_key,
+            None => self.create_transaction_data_key(transaction_id).await?,
+        };
+        let ciphertext = MessageCipher::for_data(&data_key).encrypt(
+            &data.to_string(),
+            &encryption::binding(&["pii", transaction_id, role]),
+        )?;
 
-        let mut entries = Vec::new();
-        for row in rows {
-            entries.push(DecisionLogEntry {
-                id: row.get("id"),
-                transaction_id: row.get("transaction_id"),
-                agent_did: row.get("agent_did"),
-                decision_type: DecisionType::try_from(
-                    row.get::<String, _>("decision_type").as_str(),
-                )
-                .map_err(StorageError::InvalidTransactionType)?,
-                context_json: serde_json::from_str(&row.get::<String, _>("context_json"))?,
-                status: DecisionStatus::try_from(row.get::<String, _>("status").as_str())
-                    .map_err(StorageError::InvalidTransactionType)?,
-                resolution: row.get("resolution"),
-                resolution_detail: row
-                    .get::<Option<String>, _>("resolution_detail")
-                    .map(|v| serde_json::from_str(&v))
-                    .transpose()?,
-                created_at: row.get("created_at"),
-                delivered_at: row.get("delivered_at"),
-                resolved_at: row.get("resolved_at"),
-            });
-        }
+        sqlx::query(
+            r#"
+            INSERT INTO transaction_pii (transaction_id, role, customer_id, ciphertext, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT(transaction_id, role) DO UPDATE SET
+                customer_id = COALESCE(excluded.customer_id, transaction_pii.customer_id),
+                ciphertext = excluded.ciphertext,
+                created_at = excluded.created_at
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(role)
+        .bind(customer_id)
+        .bind(ciphertext)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
 
-        Ok(entries)
+        debug!(
+            "Sealed personal data of the {} of transaction {}",
+            role, transaction_id
+        );
+        Ok(())
     }
 
-    /// Expire all pending/delivered decisions for a transaction
-    ///
-    /// Called when a transaction reaches a terminal state (Rejected, Cancelled, Reverted)
-    /// to prevent stale decisions from being acted on.
-    ///
-    /// # Arguments
-    ///
-    /// * `transaction_id` - The transaction whose decisions should be expired
-    ///
-    /// # Returns
+    /// Decrypt the personal data of the parties of a transaction for
+    /// `accessor`, recording who read it, when and for what purpose
     ///
-    /// * `Ok(u64)` - Number of decisions expired
-    /// * `Err(StorageError)` on database error
-    pub async fn expire_decisions_for_transaction(
+    /// Only the participants of the transaction, see
+    /// [`Storage::is_transaction_participant`], and the accessors they
+    /// granted access with [`Storage::grant_transaction_pii_access`] may read
+    /// it; anyone else gets [`StorageError::AccessDenied`] and the data key
+    /// is not unwrapped. The read is recorded whether it is granted or not.
+    /// It is refused once the data key of the transaction was revoked.
+    pub async fn read_transaction_pii(
         &self,
         transaction_id: &str,
-    ) -> Result<u64, StorageError> {
-        debug!(
-            "Expiring pending decisions for transaction {}",
-            transaction_id
-        );
+        accessor: &str,
+        purpose: &str,
+    ) -> Result<Vec<TransactionPii>, StorageError> {
+        let authorized = self
+            .is_transaction_participant(transaction_id, accessor)
+            .await?
+            || self.has_pii_access_grant(transaction_id, accessor).await?;
+        let data_key = if authorized {
+            self.transaction_data_key(transaction_id).await
+        } else {
+            Err(StorageError::AccessDenied(format!(
+                "{} may not read the personal data of transaction {}",
+                accessor, transaction_id
+            )))
+        };
+        let granted = matches!(data_key, Ok(Some(_)));
+        sqlx::query(
+            r#"
+            INSERT INTO pii_access_log (transaction_id, accessor, purpose, granted, accessed_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(accessor)
+        .bind(purpose)
+        .bind(granted)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
 
-        let result = sqlx::query(
+        let Some(data_key) = data_key? else {
+            return Ok(Vec::new());
+        };
+        let cipher = MessageCipher::for_data(&data_key);
+        let rows = sqlx::query_as::<_, (String, Option<String>, String, String)>(
             r#"
-            UPDATE decision_log
-            SET status = 'expired'
+            SELECT role, customer_id, ciphertext, created_at
+            FROM transaction_pii
             WHERE transaction_id = ?1
-            AND status IN ('pending', 'delivered')
+            ORDER BY role
             "#,
         )
         .bind(transaction_id)
-        .execute(&self.pool)
+        .fetch_all(&self.pool)
         .await?;
 
-        Ok(result.rows_affected())
+        rows.into_iter()
+            .map(|(role, customer_id, ciphertext, created_at)| {
+                let data = cipher.decrypt(
+                    &ciphertext,
+                    &encryption::binding(&["pii", transaction_id, &role]),
+                )?;
+                Ok(TransactionPii {
+                    transaction_id: transaction_id.to_string(),
+                    role,
+                    customer_id,
+                    data: serde_json::from_str(&data)?,
+                    created_at,
+                })
+            })
+            .collect()
     }
 
-    /// Resolve pending/delivered decisions for a transaction with a specific action.
-    ///
-    /// Optionally filter by decision type. If `decision_type` is `None`, all
-    /// pending/delivered decisions for the transaction are resolved.
-    ///
-    /// # Arguments
+    /// Revoke the data key of a transaction, so its personal data can no
+    /// longer be read, and delete the ciphertext
     ///
-    /// * `transaction_id` - The transaction whose decisions should be resolved
-    /// * `action` - The resolution action (e.g., "authorize", "reject", "settle")
-    /// * `decision_type` - Optional filter for specific decision types
-    ///
-    /// # Returns
+    /// Only a participant of the transaction may revoke its key. Returns
+    /// false if the transaction has no data key or it was already revoked.
+    pub async fn revoke_transaction_data_key(
+        &self,
+        transaction_id: &str,
+        revoked_by: &str,
+        reason: Option<&str>,
+    ) -> Result<bool, StorageError> {
+        if !self
+            .is_transaction_participant(transaction_id, revoked_by)
+            .await?
+        {
+            return Err(StorageError::AccessDenied(format!(
+                "{} is not a participant of transaction {}",
+                revoked_by, transaction_id
+            )));
+        }
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE transaction_data_keys SET
+                wrapped_key = NULL,
+                revoked_at = ?1,
+                revoked_by = ?2,
+                revocation_reason = ?3
+            WHERE transaction_id = ?4 AND wrapped_key IS NOT NULL
+            "#,
+        )
+        .bind(self.now())
+        .bind(revoked_by)
+        .bind(reason)
+        .bind(transaction_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM transaction_pii WHERE transaction_id = ?1")
+            .bind(transaction_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        if result.rows_affected() > 0 {
+            info!(
+                "Revoked the data key of transaction {} for {}",
+                transaction_id, revoked_by
+            );
+        }
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Whether a DID takes part in a transaction: it sent or received the
+    /// transaction's message, or is one of its agents
+    pub async fn is_transaction_participant(
+        &self,
+        transaction_id: &str,
+        did: &str,
+    ) -> Result<bool, StorageError> {
+        let participant: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM transactions
+                WHERE reference_id = ?1 AND (from_did = ?2 OR to_did = ?2)
+            ) OR EXISTS (
+                SELECT 1 FROM transaction_agents ta
+                JOIN transactions t ON t.id = ta.transaction_id
+                WHERE t.reference_id = ?1 AND ta.agent_did = ?2
+            )
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(did)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(participant)
+    }
+
+    /// Allow `accessor` to read the personal data of a transaction
     ///
-    /// * `Ok(u64)` - Number of decisions resolved
-    /// * `Err(StorageError)` on database error
-    pub async fn resolve_decisions_for_transaction(
+    /// Access is granted by a participant of the transaction. Granting it
+    /// again after it was revoked restores it.
+    pub async fn grant_transaction_pii_access(
         &self,
         transaction_id: &str,
-        action: &str,
-        decision_type: Option<DecisionType>,
-    ) -> Result<u64, StorageError> {
-        debug!(
-            "Resolving decisions for transaction {} with action: {}",
-            transaction_id, action
+        accessor: &str,
+        granted_by: &str,
+    ) -> Result<(), StorageError> {
+        if !self
+            .is_transaction_participant(transaction_id, granted_by)
+            .await?
+        {
+            return Err(StorageError::AccessDenied(format!(
+                "{} is not a participant of transaction {}",
+                granted_by, transaction_id
+            )));
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO pii_access_grants (transaction_id, accessor, granted_by, granted_at)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(transaction_id, accessor) DO UPDATE SET
+                granted_by = excluded.granted_by,
+                granted_at = excluded.granted_at,
+                revoked_at = NULL
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(accessor)
+        .bind(granted_by)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        info!(
+            "{} granted {} access to the personal data of transaction {}",
+            granted_by, accessor, transaction_id
         );
+        Ok(())
+    }
 
-        let result = if let Some(dt) = decision_type {
-            sqlx::query(
-                r#"
-                UPDATE decision_log
-                SET status = 'resolved',
-                    resolution = ?1,
-                    resolved_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
-                WHERE transaction_id = ?2
-                AND decision_type = ?3
-                AND status IN ('pending', 'delivered')
-                "#,
-            )
-            .bind(action)
-            .bind(transaction_id)
-            .bind(dt.to_string())
-            .execute(&self.pool)
+    /// Withdraw access granted with [`Storage::grant_transaction_pii_access`]
+    ///
+    /// Returns false if `accessor` had no access to withdraw.
+    pub async fn revoke_transaction_pii_access(
+        &self,
+        transaction_id: &str,
+        accessor: &str,
+        revoked_by: &str,
+    ) -> Result<bool, StorageError> {
+        if !self
+            .is_transaction_participant(transaction_id, revoked_by)
             .await?
-        } else {
-            sqlx::query(
-                r#"
-                UPDATE decision_log
-                SET status = 'resolved',
-                    resolution = ?1,
-                    resolved_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
-                WHERE transaction_id = ?2
-                AND status IN ('pending', 'delivered')
-                "#,
+        {
+            return Err(StorageError::AccessDenied(format!(
+                "{} is not a participant of transaction {}",
+                revoked_by, transaction_id
+            )));
+        }
+        let result = sqlx::query(
+            r#"
+            UPDATE pii_access_grants SET revoked_at = ?1
+            WHERE transaction_id = ?2 AND accessor = ?3 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(self.now())
+        .bind(transaction_id)
+        .bind(accessor)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Whether `accessor` holds access to the personal data of a transaction
+    async fn has_pii_access_grant(
+        &self,
+        transaction_id: &str,
+        accessor: &str,
+    ) -> Result<bool, StorageError> {
+        let granted: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM pii_access_grants
+                WHERE transaction_id = ?1 AND accessor = ?2 AND revoked_at IS NULL
             )
-            .bind(action)
-            .bind(transaction_id)
-            .execute(&self.pool)
-            .await?
-        };
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(accessor)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(granted)
+    }
 
-        Ok(result.rows_affected())
+    /// Link the sealed personal data of a party of a transaction to the
+    /// customer record of the party
+    pub async fn link_transaction_pii(
+        &self,
+        transaction_id: &str,
+        role: &str,
+        customer_id: &str,
+    ) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            "UPDATE transaction_pii SET customer_id = ?1 WHERE transaction_id = ?2 AND role = ?3",
+        )
+        .bind(customer_id)
+        .bind(transaction_id)
+        .bind(role)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
     }
 
-    /// Get a single decision by ID
+    /// List the reads of the personal data of a transaction, oldest first
+    pub async fn list_pii_access(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Vec<PiiAccess>, StorageError> {
+        let rows = sqlx::query_as::<_, (i64, String, String, bool, String)>(
+            r#"
+            SELECT id, accessor, purpose, granted, accessed_at
+            FROM pii_access_log
+            WHERE transaction_id = ?1
+            ORDER BY id
+            "#,
+        )
+        .bind(transaction_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(id, accessor, purpose, granted, accessed_at)| PiiAccess {
+                id,
+                transaction_id: transaction_id.to_string(),
+                accessor,
+                purpose,
+                granted,
+                accessed_at,
+            })
+            .collect())
+    }
+
+    /// The cipher wrapping the data keys of transactions
+    fn key_encryption(&self) -> Result<&MessageCipher, StorageError> {
+        self.cipher.as_deref().ok_or_else(|| {
+            StorageError::Encryption(
+                "Personal data of transactions needs a storage encryption key".to_string(),
+            )
+        })
+    }
+
+    /// The data key of a transaction, None if it has none, an error if it
+    /// was revoked
+    async fn transaction_data_key(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Option<[u8; 32]>, StorageError> {
+        let key_encryption = self.key_encryption()?;
+        let row = sqlx::query_as::<_, (Option<String>,)>(
+            "SELECT wrapped_key FROM transaction_data_keys WHERE transaction_id = ?1",
+        )
+        .bind(transaction_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match row {
+            None => Ok(None),
+            Some((None,)) => Err(StorageError::Encryption(format!(
+                "The data key of transaction {} was revoked",
+                transaction_id
+            ))),
+            Some((Some(wrapped_key),)) => {
+                let data_key = key_encryption.unwrap_key(&wrapped_key, transaction_id)?;
+                Ok(Some(data_key))
+            }
+        }
+    }
+
+    /// Create the data key of a transaction, or return the key another
+    /// writer created first
+    async fn create_transaction_data_key(
+        &self,
+        transaction_id: &str,
+    ) -> Result<[u8; 32], StorageError> {
+        let data_key = encryption::generate_data_key();
+        let wrapped_key = self.key_encryption()?.wrap_key(&data_key, transaction_id)?;
+        sqlx::query(
+            r#"
+            INSERT INTO transaction_data_keys (transaction_id, wrapped_key, created_at)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT(transaction_id) DO NOTHING
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(wrapped_key)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        self.transaction_data_key(transaction_id)
+            .await?
+            .ok_or_else(|| {
+                StorageError::Encryption(format!(
+                    "Failed to create the data key of transaction {}",
+                    transaction_id
+                ))
+            })
+    }
+
+    /// List IDs of customers not updated since `cutoff` that have not been erased
     ///
     /// # Arguments
     ///
-    /// * `decision_id` - The ID of the decision
+    /// * `cutoff` - RFC 3339 timestamp; customers last updated before it are returned
+    /// * `limit` - Maximum number of IDs to return
+    pub async fn list_customers_updated_before(
+        &self,
+        cutoff: &str,
+        limit: u32,
+    ) -> Result<Vec<String>, StorageError> {
+        let rows = sqlx::query_as::<_, (String,)>(
+            r#"
+            SELECT id
+            FROM customers
+            WHERE datetime(updated_at) < datetime(?1)
+              AND id NOT IN (SELECT customer_id FROM customer_erasures)
+            ORDER BY datetime(updated_at) ASC
+            LIMIT ?2
+            "#,
+        )
+        .bind(cutoff)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+
+    /// Clear cached IVMS101 data for customers not updated since `cutoff`
+    ///
+    /// The customer's `updated_at` is preserved (normalized to UTC seconds) so
+    /// clearing IVMS101 data does not reset the profile's retention age.
     ///
     /// # Returns
     ///
-    /// * `Ok(Some(DecisionLogEntry))` if found
-    /// * `Ok(None)` if not found
+    /// * `Ok(u64)` - The number of customers whose IVMS101 data was cleared
     /// * `Err(StorageError)` on database error
-    pub async fn get_decision_by_id(
+    pub async fn purge_ivms101_data_before(&self, cutoff: &str) -> Result<u64, StorageError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE customers SET
+                ivms101_data = NULL,
+                updated_at = strftime('%Y-%m-%dT%H:%M:%SZ', updated_at)
+            WHERE ivms101_data IS NOT NULL
+              AND datetime(updated_at) < datetime(?1)
+            "#,
+        )
+        .bind(cutoff)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Record a customer merge proposal
+    ///
+    /// A pair of customers is only proposed once: if any proposal for the
+    /// pair exists, in either direction and with any status, nothing is
+    /// recorded. Rejected pairs are therefore not proposed again.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` if the proposal was recorded
+    /// * `Ok(false)` if the pair was already proposed
+    pub async fn insert_customer_merge(&self, merge: &CustomerMerge) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO customer_merges (
+                id, agent_did, primary_customer_id, duplicate_customer_id, confidence,
+                signals, status, duplicate_snapshot, created_at, resolved_at
+            )
+            SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10
+            WHERE NOT EXISTS (
+                SELECT 1 FROM customer_merges
+                WHERE (primary_customer_id = ?3 AND duplicate_customer_id = ?4)
+                   OR (primary_customer_id = ?4 AND duplicate_customer_id = ?3)
+            )
+            "#,
+        )
+        .bind(&merge.id)
+        .bind(&merge.agent_did)
+        .bind(&merge.primary_customer_id)
+        .bind(&merge.duplicate_customer_id)
+        .bind(merge.confidence)
+        .bind(serde_json::to_string(&merge.signals)?)
+        .bind(merge.status.to_string())
+        .bind(
+            merge
+                .duplicate_snapshot
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+        )
+        .bind(&merge.created_at)
+        .bind(&merge.resolved_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Get a customer merge proposal by ID
+    pub async fn get_customer_merge(
         &self,
-        decision_id: i64,
-    ) -> Result<Option<DecisionLogEntry>, StorageError> {
+        merge_id: &str,
+    ) -> Result<Option<CustomerMerge>, StorageError> {
         let row = sqlx::query(
             r#"
-            SELECT id, transaction_id, agent_did, decision_type, context_json,
-                   status, resolution, resolution_detail, created_at, delivered_at, resolved_at
-            FROM decision_log WHERE id = ?1
+            SELECT id, agent_did, primary_customer_id, duplicate_customer_id, confidence,
+                   signals, status, duplicate_snapshot, created_at, resolved_at
+            FROM customer_merges
+            WHERE id = ?1
             "#,
         )
-        .bind(decision_id)
+        .bind(merge_id)
         .fetch_optional(&self.pool)
         .await?;
 
-        match row {
-            Some(row) => Ok(Some(DecisionLogEntry {
-                id: row.get("id"),
-                transaction_id: row.get("transaction_id"),
-                agent_did: row.get("agent_did"),
-                decision_type: DecisionType::try_from(
-                    row.get::<String, _>("decision_type").as_str(),
-                )
-                .map_err(StorageError::InvalidTransactionType)?,
-                context_json: serde_json::from_str(&row.get::<String, _>("context_json"))?,
-                status: DecisionStatus::try_from(row.get::<String, _>("status").as_str())
-                    .map_err(StorageError::InvalidTransactionType)?,
-                resolution: row.get("resolution"),
-                resolution_detail: row
-                    .get::<Option<String>, _>("resolution_detail")
-                    .map(|v| serde_json::from_str(&v))
-                    .transpose()?,
-                created_at: row.get("created_at"),
-                delivered_at: row.get("delivered_at"),
-                resolved_at: row.get("resolved_at"),
-            })),
-            None => Ok(None),
-        }
+        row.map(|row| Self::customer_merge_from_row(&row))
+            .transpose()
+    }
+
+    /// List customer merge proposals for an agent, most confident first
+    ///
+    /// # Arguments
+    ///
+    /// * `agent_did` - The agent whose proposals to list
+    /// * `status` - Only list proposals with this status, or all if `None`
+    /// * `limit` - Maximum number of proposals to return
+    /// * `offset` - Number of proposals to skip
+    pub async fn list_customer_merges(
+        &self,
+        agent_did: &str,
+        status: Option<MergeStatus>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<CustomerMerge>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, agent_did, primary_customer_id, duplicate_customer_id, confidence,
+                   signals, status, duplicate_snapshot, created_at, resolved_at
+            FROM customer_merges
+            WHERE agent_did = ?1 AND (?2 IS NULL OR status = ?2)
+            ORDER BY confidence DESC, created_at ASC
+            LIMIT ?3 OFFSET ?4
+            "#,
+        )
+        .bind(agent_did)
+        .bind(status.map(|s| s.to_string()))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::customer_merge_from_row).collect()
+    }
+
+    /// List the confirmed merges whose surviving customer is `customer_id`,
+    /// oldest first
+    pub async fn list_customer_merges_into(
+        &self,
+        customer_id: &str,
+    ) -> Result<Vec<CustomerMerge>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, agent_did, primary_customer_id, duplicate_customer_id, confidence,
+                   signals, status, duplicate_snapshot, created_at, resolved_at
+            FROM customer_merges
+            WHERE primary_customer_id = ?1 AND status = 'confirmed'
+            ORDER BY resolved_at ASC
+            "#,
+        )
+        .bind(customer_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::customer_merge_from_row).collect()
+    }
+
+    /// Get the customer a merged customer was folded into, if any
+    pub async fn get_customer_merged_into(
+        &self,
+        customer_id: &str,
+    ) -> Result<Option<String>, StorageError> {
+        let primary: Option<String> = sqlx::query_scalar(
+            r#"
+            SELECT primary_customer_id
+            FROM customer_merges
+            WHERE duplicate_customer_id = ?1 AND status = 'confirmed'
+            "#,
+        )
+        .bind(customer_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(primary)
+    }
+
+    /// Confirm a customer merge proposal
+    ///
+    /// In a single transaction, `merged` is stored as the surviving customer,
+    /// the duplicate's identifiers and relationships move to it and the
+    /// duplicate row is removed. `duplicate_snapshot` is kept on the merge
+    /// record for lineage. Other open proposals involving the duplicate are
+    /// dropped, since the duplicate no longer exists.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` if the proposal was confirmed
+    /// * `Ok(false)` if the proposal does not exist or is no longer proposed
+    pub async fn confirm_customer_merge(
+        &self,
+        merge_id: &str,
+        merged: &Customer,
+        duplicate_snapshot: &serde_json::Value,
+    ) -> Result<bool, StorageError> {
+        let merge = match self.get_customer_merge(merge_id).await? {
+            Some(merge) if merge.status == MergeStatus::Proposed => merge,
+            _ => return Ok(false),
+        };
+        let resolved_at = self.now();
+
+        let mut tx = self.pool.begin().await?;
+
+        let updated = sqlx::query(
+            r#"
+            UPDATE customer_merges
+            SET status = 'confirmed', duplicate_snapshot = ?1, resolved_at = ?2
+            WHERE id = ?3 AND status = 'proposed'
+            "#,
+        )
+        .bind(serde_json::to_string(duplicate_snapshot)?)
+        .bind(&resolved_at)
+        .bind(merge_id)
+        .execute(&mut *tx)
+        .await?;
+        if updated.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        Self::upsert_customer_with(&mut *tx, merged).await?;
+
+        // Identifiers and relationships the survivor already has stay behind
+        // and are deleted with the duplicate
+        sqlx::query(
+            "UPDATE OR IGNORE customer_identifiers SET customer_id = ?1 WHERE customer_id = ?2",
+        )
+        .bind(&merge.primary_customer_id)
+        .bind(&merge.duplicate_customer_id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "UPDATE OR IGNORE customer_relationships SET customer_id = ?1 WHERE customer_id = ?2",
+        )
+        .bind(&merge.primary_customer_id)
+        .bind(&merge.duplicate_customer_id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM customer_identifiers WHERE customer_id = ?1")
+            .bind(&merge.duplicate_customer_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM customer_relationships WHERE customer_id = ?1")
+            .bind(&merge.duplicate_customer_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM customers WHERE id = ?1")
+            .bind(&merge.duplicate_customer_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM customer_merges
+            WHERE status = 'proposed'
+              AND (primary_customer_id = ?1 OR duplicate_customer_id = ?1)
+            "#,
+        )
+        .bind(&merge.duplicate_customer_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        debug!(
+            "Merged customer {} into {}",
+            merge.duplicate_customer_id, merge.primary_customer_id
+        );
+
+        Ok(true)
+    }
+
+    /// Reject a customer merge proposal
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` if the proposal was rejected
+    /// * `Ok(false)` if the proposal does not exist or is no longer proposed
+    pub async fn reject_customer_merge(&self, merge_id: &str) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE customer_merges
+            SET status = 'rejected', resolved_at = ?1
+            WHERE id = ?2 AND status = 'proposed'
+            "#,
+        )
+        .bind(self.now())
+        .bind(merge_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // -----------------------------------------------------------------------
+    // Decision log operations
+    // -----------------------------------------------------------------------
+
+    /// Insert a new decision into the decision log
+    ///
+    /// # Arguments
+    ///
+    /// * `transaction_id` - The transaction requiring a decision
+    /// * `agent_did` - The DID of the agent this decision is for
+    /// * `decision_type` - The type of decision required
+    /// * `context_json` - JSON context about the decision (transaction details, etc.)
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(i64)` - The ID of the created decision log entry
+    /// * `Err(StorageError)` on database error
+    pub async fn insert_decision(
+        &self,
+        transaction_id: &str,
+        agent_did: &str,
+        decision_type: DecisionType,
+        context_json: &serde_json::Value,
+    ) -> Result<i64, StorageError> {
+        debug!(
+            "Inserting decision for transaction {} agent {} type {}",
+            transaction_id, agent_did, decision_type
+        );
+
+        let context_str = serde_json::to_string(context_json)?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO decision_log (transaction_id, agent_did, decision_type, context_json, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(agent_did)
+        .bind(decision_type.to_string())
+        .bind(&context_str)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Update the status of a decision log entry
+    ///
+    /// # Arguments
+    ///
+    /// * `decision_id` - The ID of the decision to update
+    /// * `status` - The new status
+    /// * `resolution` - Optional resolution action (e.g., "authorize", "reject")
+    /// * `resolution_detail` - Optional JSON detail about the resolution
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` on success
+    /// * `Err(StorageError)` on database error
+    pub async fn update_decision_status(
+        &self,
+        decision_id: i64,
+        status: DecisionStatus,
+        resolution: Option<&str>,
+        resolution_detail: Option<&serde_json::Value>,
+    ) -> Result<(), StorageError> {
+        debug!("Updating decision {} status to {}", decision_id, status);
+
+        let now = self.clock.now().to_rfc3339();
+        let resolution_detail_str = resolution_detail.map(serde_json::to_string).transpose()?;
+
+        let delivered_at = if status == DecisionStatus::Delivered {
+            Some(now.clone())
+        } else {
+            None
+        };
+
+        let resolved_at = if status == DecisionStatus::Resolved {
+            Some(now)
+        } else {
+            None
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE decision_log
+            SET status = ?1,
+                resolution = COALESCE(?2, resolution),
+                resolution_detail = COALESCE(?3, resolution_detail),
+                delivered_at = COALESCE(?4, delivered_at),
+                resolved_at = COALESCE(?5, resolved_at)
+            WHERE id = ?6
+            "#,
+        )
+        .bind(status.to_string())
+        .bind(resolution)
+        .bind(resolution_detail_str.as_deref())
+        .bind(delivered_at)
+        .bind(resolved_at)
+        .bind(decision_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// List decisions from the decision log with optional filtering
+    ///
+    /// # Arguments
+    ///
+    /// * `agent_did` - Optional filter by agent DID
+    /// * `status` - Optional filter by status
+    /// * `since_id` - Optional minimum decision ID (for pagination/replay)
+    /// * `limit` - Maximum number of entries to return
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<DecisionLogEntry>)` - Matching decision log entries ordered by id ASC
+    /// * `Err(StorageError)` on database error
+    pub async fn list_decisions(
+        &self,
+        agent_did: Option<&str>,
+        status: Option<DecisionStatus>,
+        since_id: Option<i64>,
+        limit: u32,
+    ) -> Result<Vec<DecisionLogEntry>, StorageError> {
+        let mut query = String::from(
+            "SELECT id, transaction_id, agent_did, decision_type, context_json, \
+             status, resolution, resolution_detail, created_at, delivered_at, resolved_at \
+             FROM decision_log WHERE 1=1",
+        );
+        let mut bind_values: Vec<String> = Vec::new();
+        let mut bind_i64: Option<i64> = None;
+
+        if let Some(did) = agent_did {
+            query.push_str(" AND agent_did = ?");
+            bind_values.push(did.to_string());
+        }
+
+        if let Some(s) = status {
+            query.push_str(" AND status = ?");
+            bind_values.push(s.to_string());
+        }
+
+        if let Some(id) = since_id {
+            query.push_str(" AND id > ?");
+            bind_i64 = Some(id);
+        }
+
+        query.push_str(" ORDER BY id ASC LIMIT ?");
+
+        let mut sqlx_query = sqlx::query(&query);
+
+        for value in &bind_values {
+            sqlx_query = sqlx_query.bind(value);
+        }
+
+        if let Some(id) = bind_i64 {
+            sqlx_query = sqlx_query.bind(id);
+        }
+
+        sqlx_query = sqlx_query.bind(limit);
+
+        let rows = sqlx_query.fetch_all(&self.pool).await?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            entries.push(DecisionLogEntry {
+                id: row.get("id"),
+                transaction_id: row.get("transaction_id"),
+                agent_did: row.get("agent_did"),
+                decision_type: DecisionType::try_from(
+                    row.get::<String, _>("decision_type").as_str(),
+                )
+                .map_err(StorageError::InvalidTransactionType)?,
+                context_json: serde_json::from_str(&row.get::<String, _>("context_json"))?,
+                status: DecisionStatus::try_from(row.get::<String, _>("status").as_str())
+                    .map_err(StorageError::InvalidTransactionType)?,
+                resolution: row.get("resolution"),
+                resolution_detail: row
+                    .get::<Option<String>, _>("resolution_detail")
+                    .map(|v| serde_json::from_str(&v))
+                    .transpose()?,
+                created_at: row.get("created_at"),
+                delivered_at: row.get("delivered_at"),
+                resolved_at: row.get("resolved_at"),
+            });
+        }
+
+        Ok(entries)
+    }
+
+    /// Expire all pending/delivered decisions for a transaction
+    ///
+    /// Called when a transaction reaches a terminal state (Rejected, Cancelled, Reverted)
+    /// to prevent stale decisions from being acted on.
+    ///
+    /// # Arguments
+    ///
+    /// * `transaction_id` - The transaction whose decisions should be expired
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of decisions expired
+    /// * `Err(StorageError)` on database error
+    pub async fn expire_decisions_for_transaction(
+        &self,
+        transaction_id: &str,
+    ) -> Result<u64, StorageError> {
+        debug!(
+            "Expiring pending decisions for transaction {}",
+            transaction_id
+        );
+
+        let result = sqlx::query(
+            r#"
+            UPDATE decision_log
+            SET status = 'expired'
+            WHERE transaction_id = ?1
+            AND status IN ('pending', 'delivered')
+            "#,
+        )
+        .bind(transaction_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Resolve pending/delivered decisions for a transaction with a specific action.
+    ///
+    /// Optionally filter by decision type. If `decision_type` is `None`, all
+    /// pending/delivered decisions for the transaction are resolved.
+    ///
+    /// # Arguments
+    ///
+    /// * `transaction_id` - The transaction whose decisions should be resolved
+    /// * `action` - The resolution action (e.g., "authorize", "reject", "settle")
+    /// * `decision_type` - Optional filter for specific decision types
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - Number of decisions resolved
+    /// * `Err(StorageError)` on database error
+    pub async fn resolve_decisions_for_transaction(
+        &self,
+        transaction_id: &str,
+        action: &str,
+        decision_type: Option<DecisionType>,
+    ) -> Result<u64, StorageError> {
+        debug!(
+            "Resolving decisions for transaction {} with action: {}",
+            transaction_id, action
+        );
+
+        let result = if let Some(dt) = decision_type {
+            sqlx::query(
+                r#"
+                UPDATE decision_log
+                SET status = 'resolved',
+                    resolution = ?1,
+                    resolved_at = ?4
+                WHERE transaction_id = ?2
+                AND decision_type = ?3
+                AND status IN ('pending', 'delivered')
+                "#,
+            )
+            .bind(action)
+            .bind(transaction_id)
+            .bind(dt.to_string())
+            .bind(self.now())
+            .execute(&self.pool)
+            .await?
+        } else {
+            sqlx::query(
+                r#"
+                UPDATE decision_log
+                SET status = 'resolved',
+                    resolution = ?1,
+                    resolved_at = ?3
+                WHERE transaction_id = ?2
+                AND status IN ('pending', 'delivered')
+                "#,
+            )
+            .bind(action)
+            .bind(transaction_id)
+            .bind(self.now())
+            .execute(&self.pool)
+            .await?
+        };
+
+        Ok(result.rows_affected())
+    }
+
+    /// Get a single decision by ID
+    ///
+    /// # Arguments
+    ///
+    /// * `decision_id` - The ID of the decision
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(DecisionLogEntry))` if found
+    /// * `Ok(None)` if not found
+    /// * `Err(StorageError)` on database error
+    pub async fn get_decision_by_id(
+        &self,
+        decision_id: i64,
+    ) -> Result<Option<DecisionLogEntry>, StorageError> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, transaction_id, agent_did, decision_type, context_json,
+                   status, resolution, resolution_detail, created_at, delivered_at, resolved_at
+            FROM decision_log WHERE id = ?1
+            "#,
+        )
+        .bind(decision_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match row {
+            Some(row) => Ok(Some(DecisionLogEntry {
+                id: row.get("id"),
+                transaction_id: row.get("transaction_id"),
+                agent_did: row.get("agent_did"),
+                decision_type: DecisionType::try_from(
+                    row.get::<String, _>("decision_type").as_str(),
+                )
+                .map_err(StorageError::InvalidTransactionType)?,
+                context_json: serde_json::from_str(&row.get::<String, _>("context_json"))?,
+                status: DecisionStatus::try_from(row.get::<String, _>("status").as_str())
+                    .map_err(StorageError::InvalidTransactionType)?,
+                resolution: row.get("resolution"),
+                resolution_detail: row
+                    .get::<Option<String>, _>("resolution_detail")
+                    .map(|v| serde_json::from_str(&v))
+                    .transpose()?,
+                created_at: row.get("created_at"),
+                delivered_at: row.get("delivered_at"),
+                resolved_at: row.get("resolved_at"),
+            })),
+            None => Ok(None),
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // Review queue operations
+    // -----------------------------------------------------------------------
+
+    /// Queue a transaction for manual review on behalf of one of our agents
+    ///
+    /// # Arguments
+    ///
+    /// * `transaction_id` - The transaction held for review
+    /// * `agent_did` - Our agent that acts on the transaction once reviewed
+    /// * `rule` - Name of the policy rule that requested the review
+    /// * `reason` - Human-readable reason for the review
+    /// * `details` - Rule-specific details shown to the reviewer
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(i64)` - The ID of the created review item
+    /// * `Err(StorageError)` on database error
+    pub async fn insert_review_item(
+        &self,
+        transaction_id: &str,
+        agent_did: &str,
+        rule: &str,
+        reason: &str,
+        details: &serde_json::Value,
+    ) -> Result<i64, StorageError> {
+        debug!(
+            "Queueing transaction {} for review by agent {} (rule {})",
+            transaction_id, agent_did, rule
+        );
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO review_queue (transaction_id, agent_did, rule, reason, details_json, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(agent_did)
+        .bind(rule)
+        .bind(reason)
+        .bind(serde_json::to_string(details)?)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// List review items, most recent first
+    ///
+    /// # Arguments
+    ///
+    /// * `agent_did` - Optional filter by agent DID
+    /// * `status` - Optional filter by status
+    /// * `limit` - Maximum number of items to return
+    /// * `offset` - Number of items to skip
+    pub async fn list_review_items(
+        &self,
+        agent_did: Option<&str>,
+        status: Option<ReviewStatus>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<ReviewItem>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, transaction_id, agent_did, rule, reason, details_json, status,
+                   reviewer, review_note, message_id, created_at, reviewed_at
+            FROM review_queue
+            WHERE (?1 IS NULL OR agent_did = ?1)
+            AND (?2 IS NULL OR status = ?2)
+            ORDER BY id DESC
+            LIMIT ?3 OFFSET ?4
+            "#,
+        )
+        .bind(agent_did)
+        .bind(status.map(|s| s.to_string()))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::review_item_from_row).collect()
+    }
+
+    /// Get a single review item by ID
+    pub async fn get_review_item(
+        &self,
+        review_id: i64,
+    ) -> Result<Option<ReviewItem>, StorageError> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, transaction_id, agent_did, rule, reason, details_json, status,
+                   reviewer, review_note, message_id, created_at, reviewed_at
+            FROM review_queue WHERE id = ?1
+            "#,
+        )
+        .bind(review_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(Self::review_item_from_row).transpose()
+    }
+
+    /// Record a reviewer's decision on a pending review item
+    ///
+    /// # Arguments
+    ///
+    /// * `review_id` - The review item to complete
+    /// * `status` - `Approved` or `Rejected`
+    /// * `reviewer` - Identity of the reviewer
+    /// * `note` - Optional note from the reviewer
+    /// * `message_id` - ID of the Authorize or Reject message sent
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` if the item was pending and is now completed
+    /// * `Ok(false)` if the item does not exist or was already completed
+    /// * `Err(StorageError)` on database error
+    pub async fn complete_review_item(
+        &self,
+        review_id: i64,
+        status: ReviewStatus,
+        reviewer: &str,
+        note: Option<&str>,
+        message_id: Option<&str>,
+    ) -> Result<bool, StorageError> {
+        debug!(
+            "Completing review {} as {} by {}",
+            review_id, status, reviewer
+        );
+
+        let result = sqlx::query(
+            r#"
+            UPDATE review_queue
+            SET status = ?1,
+                reviewer = ?2,
+                review_note = ?3,
+                message_id = ?4,
+                reviewed_at = ?6
+            WHERE id = ?5
+            AND status = 'pending'
+            "#,
+        )
+        .bind(status.to_string())
+        .bind(reviewer)
+        .bind(note)
+        .bind(message_id)
+        .bind(review_id)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Expire all pending review items for a transaction
+    ///
+    /// Called when a transaction reaches a terminal state before it was reviewed.
+    pub async fn expire_review_items_for_transaction(
+        &self,
+        transaction_id: &str,
+    ) -> Result<u64, StorageError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE review_queue
+            SET status = 'expired'
+            WHERE transaction_id = ?1
+            AND status = 'pending'
+            "#,
+        )
+        .bind(transaction_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    fn review_item_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<ReviewItem, StorageError> {
+        Ok(ReviewItem {
+            id: row.get("id"),
+            transaction_id: row.get("transaction_id"),
+            agent_did: row.get("agent_did"),
+            rule: row.get("rule"),
+            reason: row.get("reason"),
+            details: serde_json::from_str(&row.get::<String, _>("details_json"))?,
+            status: ReviewStatus::try_from(row.get::<String, _>("status").as_str())
+                .map_err(StorageError::InvalidTransactionType)?,
+            reviewer: row.get("reviewer"),
+            review_note: row.get("review_note"),
+            message_id: row.get("message_id"),
+            created_at: row.get("created_at"),
+            reviewed_at: row.get("reviewed_at"),
+        })
+    }
+
+    /// Attach a warning to a transaction
+    ///
+    /// # Arguments
+    ///
+    /// * `transaction_id` - The reference ID of the transaction
+    /// * `rule` - Name of the policy rule that tripped
+    /// * `action` - Action the rule requested
+    /// * `reason` - Human-readable reason
+    /// * `details` - Rule-specific details
+    pub async fn insert_transaction_warning(
+        &self,
+        transaction_id: &str,
+        rule: &str,
+        action: &str,
+        reason: &str,
+        details: &serde_json::Value,
+    ) -> Result<i64, StorageError> {
+        debug!(
+            "Attaching warning of rule {} to transaction {}",
+            rule, transaction_id
+        );
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO transaction_warnings (transaction_id, rule, action, reason, details_json, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(rule)
+        .bind(action)
+        .bind(reason)
+        .bind(serde_json::to_string(details)?)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// List the warnings attached to a transaction, oldest first
+    pub async fn list_transaction_warnings(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Vec<TransactionWarning>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, transaction_id, rule, action, reason, details_json, created_at
+            FROM transaction_warnings
+            WHERE transaction_id = ?1
+            ORDER BY id
+            "#,
+        )
+        .bind(transaction_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(TransactionWarning {
+                    id: row.get("id"),
+                    transaction_id: row.get("transaction_id"),
+                    rule: row.get("rule"),
+                    action: row.get("action"),
+                    reason: row.get("reason"),
+                    details: serde_json::from_str(&row.get::<String, _>("details_json"))?,
+                    created_at: row.get("created_at"),
+                })
+            })
+            .collect()
+    }
+
+    /// Store a document referenced by the invoice of a Payment
+    ///
+    /// Embedded content is decoded and checked against the digest of the
+    /// reference; content that is undecodable or does not match is not
+    /// stored and the document is recorded as a mismatch. Documents hosted
+    /// at a URL are recorded as unverified. A document already stored for
+    /// the transaction is left as it is.
+    ///
+    /// # Arguments
+    ///
+    /// * `transaction_id` - The reference ID of the transaction
+    /// * `message_id` - ID of the message that referenced the document
+    /// * `document` - The document reference from the invoice
+    ///
+    /// # Returns
+    ///
+    /// The outcome of checking the document
+    pub async fn insert_document(
+        &self,
+        transaction_id: &str,
+        message_id: &str,
+        document: &DocumentReference,
+    ) -> Result<DocumentStatus, StorageError> {
+        debug!(
+            "Storing document {} of transaction {}",
+            document.id, transaction_id
+        );
+
+        let (content, status) = match document.embedded_content() {
+            Ok(Some(content)) if document.hash.is_some() => {
+                (Some(content), DocumentStatus::Verified)
+            }
+            Ok(content) => (content, DocumentStatus::Unverified),
+            Err(e) => {
+                debug!("Document {} failed verification: {}", document.id, e);
+                (None, DocumentStatus::Mismatch)
+            }
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO documents (transaction_id, message_id, document_id, document_type, url,
+                                   mime_type, hash, content, status, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+            ON CONFLICT(transaction_id, document_id) DO NOTHING
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(message_id)
+        .bind(&document.id)
+        .bind(&document.document_type)
+        .bind(&document.url)
+        .bind(&document.mime_type)
+        .bind(&document.hash)
+        .bind(content)
+        .bind(status.to_string())
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(status)
+    }
+
+    /// List the documents stored for a transaction, in the order received
+    pub async fn list_documents(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Vec<TransactionDocument>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, transaction_id, message_id, document_id, document_type, url, mime_type,
+                   hash, content, status, created_at
+            FROM documents
+            WHERE transaction_id = ?1
+            ORDER BY id
+            "#,
+        )
+        .bind(transaction_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::document_from_row).collect()
+    }
+
+    /// Get a document stored for a transaction by its ID within the invoice
+    pub async fn get_document(
+        &self,
+        transaction_id: &str,
+        document_id: &str,
+    ) -> Result<Option<TransactionDocument>, StorageError> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, transaction_id, message_id, document_id, document_type, url, mime_type,
+                   hash, content, status, created_at
+            FROM documents
+            WHERE transaction_id = ?1 AND document_id = ?2
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(document_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(Self::document_from_row).transpose()
+    }
+
+    fn document_from_row(
+        row: &sqlx::sqlite::SqliteRow,
+    ) -> Result<TransactionDocument, StorageError> {
+        Ok(TransactionDocument {
+            id: row.get("id"),
+            transaction_id: row.get("transaction_id"),
+            message_id: row.get("message_id"),
+            document_id: row.get("document_id"),
+            document_type: row.get("document_type"),
+            url: row.get("url"),
+            mime_type: row.get("mime_type"),
+            hash: row.get("hash"),
+            content: row.get("content"),
+            status: DocumentStatus::try_from(row.get::<String, _>("status").as_str())
+                .map_err(StorageError::InvalidTransactionType)?,
+            created_at: row.get("created_at"),
+        })
+    }
+
+    /// Record the outcome of verifying the key attestation of a message
+    ///
+    /// # Arguments
+    ///
+    /// * `message_id` - ID of the message the attestation was sent with
+    /// * `agent_did` - DID of the sending agent
+    /// * `attestation` - The verified attestation, or why verification failed
+    pub async fn insert_key_attestation(
+        &self,
+        message_id: &str,
+        agent_did: &str,
+        attestation: Result<&KeyAttestation, &str>,
+    ) -> Result<(), StorageError> {
+        debug!(
+            "Recording key attestation of {} in message {}",
+            agent_did, message_id
+        );
+
+        let (status, reason) = match attestation {
+            Ok(_) => (KeyAttestationStatus::Verified, None),
+            Err(reason) => (KeyAttestationStatus::Invalid, Some(reason)),
+        };
+        let attestation = attestation.ok();
+        sqlx::query(
+            r#"
+            INSERT INTO key_attestations (message_id, agent_did, organization, kid, status, reason,
+                                          did_doc_hash, expires_at, verified_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+            ON CONFLICT(message_id) DO NOTHING
+            "#,
+        )
+        .bind(message_id)
+        .bind(agent_did)
+        .bind(attestation.map(|a| &a.organization))
+        .bind(attestation.and_then(|a| a.kid.as_ref()))
+        .bind(status.to_string())
+        .bind(reason)
+        .bind(attestation.map(|a| &a.verification.did_doc_hash))
+        .bind(attestation.and_then(|a| a.expires_at))
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get the outcome of verifying the key attestation of a message
+    ///
+    /// Returns `None` when the message was sent without one.
+    pub async fn get_key_attestation(
+        &self,
+        message_id: &str,
+    ) -> Result<Option<KeyAttestationRecord>, StorageError> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, message_id, agent_did, organization, kid, status, reason, did_doc_hash,
+                   expires_at, verified_at
+            FROM key_attestations
+            WHERE message_id = ?1
+            "#,
+        )
+        .bind(message_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(Self::key_attestation_from_row).transpose()
+    }
+
+    /// List the key attestations received from an agent, newest first
+    pub async fn list_key_attestations(
+        &self,
+        agent_did: &str,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<KeyAttestationRecord>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, message_id, agent_did, organization, kid, status, reason, did_doc_hash,
+                   expires_at, verified_at
+            FROM key_attestations
+            WHERE agent_did = ?1
+            ORDER BY id DESC
+            LIMIT ?2 OFFSET ?3
+            "#,
+        )
+        .bind(agent_did)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::key_attestation_from_row).collect()
+    }
+
+    fn key_attestation_from_row(
+        row: &sqlx::sqlite::SqliteRow,
+    ) -> Result<KeyAttestationRecord, StorageError> {
+        Ok(KeyAttestationRecord {
+            id: row.get("id"),
+            message_id: row.get("message_id"),
+            agent_did: row.get("agent_did"),
+            organization: row.get("organization"),
+            kid: row.get("kid"),
+            status: KeyAttestationStatus::try_from(row.get::<String, _>("status").as_str())
+                .map_err(StorageError::InvalidTransactionType)?,
+            reason: row.get("reason"),
+            did_doc_hash: row.get("did_doc_hash"),
+            expires_at: row.get("expires_at"),
+            verified_at: row.get("verified_at"),
+        })
+    }
+
+    /// Record the outcome of verifying a presentation sent with a message
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The message the presentation was sent with
+    /// * `index` - Position of the presentation among those of the message
+    /// * `presentation` - The verified presentation, or why verification failed
+    pub async fn insert_presentation_verification(
+        &self,
+        message: &PlainMessage,
+        index: usize,
+        presentation: Result<&VerifiedPresentationSummary, &str>,
+    ) -> Result<(), StorageError> {
+        debug!(
+            "Recording presentation {} of message {} from {}",
+            index, message.id, message.from
+        );
+
+        let (status, reason) = match presentation {
+            Ok(_) => (PresentationVerificationStatus::Verified, None),
+            Err(reason) => (PresentationVerificationStatus::Invalid, Some(reason)),
+        };
+        let presentation = presentation.ok();
+        let credentials = presentation
+            .map(|p| p.credentials.as_slice())
+            .unwrap_or(&[]);
+        sqlx::query(
+            r#"
+            INSERT INTO presentation_verifications (message_id, presentation_index, transaction_id,
+                                                    sender_did, holder, status, reason,
+                                                    credentials_json, verified_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+            ON CONFLICT(message_id, presentation_index) DO NOTHING
+            "#,
+        )
+        .bind(&message.id)
+        .bind(index as i64)
+        .bind(
+            message
+                .thid
+                .as_deref()
+                .or(message.body["transaction_id"].as_str()),
+        )
+        .bind(&message.from)
+        .bind(presentation.and_then(|p| p.holder.as_ref()))
+        .bind(status.to_string())
+        .bind(reason)
+        .bind(serde_json::to_string(credentials)?)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// List the presentations received for a transaction, oldest first
+    pub async fn list_presentation_verifications(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Vec<PresentationVerificationRecord>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, message_id, presentation_index, transaction_id, sender_did, holder, status,
+                   reason, credentials_json, verified_at
+            FROM presentation_verifications
+            WHERE transaction_id = ?1
+            ORDER BY id ASC
+            "#,
+        )
+        .bind(transaction_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(PresentationVerificationRecord {
+                    id: row.get("id"),
+                    message_id: row.get("message_id"),
+                    presentation_index: row.get("presentation_index"),
+                    transaction_id: row.get("transaction_id"),
+                    sender_did: row.get("sender_did"),
+                    holder: row.get("holder"),
+                    status: PresentationVerificationStatus::try_from(
+                        row.get::<String, _>("status").as_str(),
+                    )
+                    .map_err(StorageError::InvalidTransactionType)?,
+                    reason: row.get("reason"),
+                    credentials: serde_json::from_str(&row.get::<String, _>("credentials_json"))?,
+                    verified_at: row.get("verified_at"),
+                })
+            })
+            .collect()
+    }
+
+    /// Record a RequestPresentation sent by one of our agents
+    ///
+    /// One request is recorded per recipient, so that the presentation each
+    /// of them answers with can be checked against its challenge. Messages
+    /// without a challenge are not recorded.
+    pub async fn insert_presentation_request(
+        &self,
+        message: &PlainMessage,
+    ) -> Result<(), StorageError> {
+        let Some(challenge) = message.body["challenge"].as_str() else {
+            return Ok(());
+        };
+        let transaction_id = message
+            .thid
+            .as_deref()
+            .or(message.body["transaction_id"].as_str())
+            .unwrap_or(&message.id);
+        debug!(
+            "Recording presentation request {} of {} on {}",
+            message.id, message.from, transaction_id
+        );
+
+        let requested_at = self.now();
+        for recipient in &message.to {
+            sqlx::query(
+                r#"
+                INSERT INTO presentation_requests (message_id, transaction_id, verifier_did,
+                                                   recipient_did, challenge, requested_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                ON CONFLICT(message_id, recipient_did) DO NOTHING
+                "#,
+            )
+            .bind(&message.id)
+            .bind(transaction_id)
+            .bind(&message.from)
+            .bind(recipient)
+            .bind(challenge)
+            .bind(&requested_at)
+            .execute(&self.pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// The challenge of the latest presentation requested from an agent on a
+    /// transaction, with the requesting agent as its domain
+    pub async fn get_presentation_request(
+        &self,
+        transaction_id: &str,
+        recipient_did: &str,
+    ) -> Result<Option<PresentationChallenge>, StorageError> {
+        let row = sqlx::query(
+            r#"
+            SELECT challenge, verifier_did FROM presentation_requests
+            WHERE transaction_id = ?1 AND recipient_did = ?2
+            ORDER BY id DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(recipient_did)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| PresentationChallenge {
+            challenge: row.get("challenge"),
+            domain: row.get("verifier_did"),
+        }))
+    }
+
+    // -----------------------------------------------------------------------
+    // Response timers
+    // -----------------------------------------------------------------------
+
+    /// Start timing the answer of one of our agents to a message
+    ///
+    /// The deadline and warning are those of the rule, counted from now.
+    /// Returns false if the agent already has a timer for the message.
+    pub async fn start_response_timer(
+        &self,
+        message: &PlainMessage,
+        agent_did: &str,
+        rule: &ResponseTimerRule,
+    ) -> Result<bool, StorageError> {
+        debug!(
+            "Starting response timer of {} for {} {}",
+            agent_did, rule.message_type, message.id
+        );
+
+        let started_at = self.clock.now();
+        let deadline_at = chrono::Duration::from_std(rule.deadline)
+            .ok()
+            .and_then(|deadline| started_at.checked_add_signed(deadline))
+            .unwrap_or(chrono::DateTime::<chrono::Utc>::MAX_UTC);
+        let warn_at = chrono::Duration::from_std(rule.warning)
+            .ok()
+            .and_then(|warning| deadline_at.checked_sub_signed(warning))
+            .unwrap_or(started_at)
+            .max(started_at);
+        let format =
+            |at: chrono::DateTime<chrono::Utc>| at.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+        let result = sqlx::query(
+            r#"
+            INSERT INTO response_timers (message_id, message_type, thread_id, agent_did,
+                                         counterparty_did, responses_json, started_at, warn_at,
+                                         deadline_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+            ON CONFLICT(message_id, agent_did) DO NOTHING
+            "#,
+        )
+        .bind(&message.id)
+        .bind(&rule.message_type)
+        .bind(message.thid.as_deref().unwrap_or(&message.id))
+        .bind(agent_did)
+        .bind((!message.from.is_empty()).then_some(&message.from))
+        .bind(serde_json::to_string(&rule.responses)?)
+        .bind(format(started_at))
+        .bind(format(warn_at))
+        .bind(format(deadline_at))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Stop the open timers of an agent on a thread that a message type
+    /// answers
+    ///
+    /// Returns the number of timers stopped.
+    pub async fn answer_response_timers(
+        &self,
+        thread_id: &str,
+        agent_did: &str,
+        response_type: &str,
+        response_message_id: Option<&str>,
+    ) -> Result<u64, StorageError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE response_timers
+            SET status = 'answered', response_type = ?3, response_message_id = ?4,
+                closed_at = ?5, breached = breached OR deadline_at < ?5
+            WHERE thread_id = ?1 AND agent_did = ?2 AND status = 'open'
+              AND EXISTS (SELECT 1 FROM json_each(response_timers.responses_json)
+                          WHERE json_each.value = ?3 COLLATE NOCASE)
+            "#,
+        )
+        .bind(thread_id)
+        .bind(agent_did)
+        .bind(response_type)
+        .bind(response_message_id)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() > 0 {
+            debug!(
+                "{} answered {} response timers on thread {} with {}",
+                agent_did,
+                result.rows_affected(),
+                thread_id,
+                response_type
+            );
+        }
+        Ok(result.rows_affected())
+    }
+
+    /// Cancel the open timers of an agent on a thread, whose requests the
+    /// counterparty withdrew
+    ///
+    /// Returns the number of timers cancelled.
+    pub async fn cancel_response_timers(
+        &self,
+        thread_id: &str,
+        agent_did: &str,
+    ) -> Result<u64, StorageError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE response_timers SET status = 'cancelled', closed_at = ?3
+            WHERE thread_id = ?1 AND agent_did = ?2 AND status = 'open'
+            "#,
+        )
+        .bind(thread_id)
+        .bind(agent_did)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// List the open timers due a warning or a breach at `now`, earliest
+    /// deadline first
+    pub async fn list_due_response_timers(
+        &self,
+        now: &chrono::DateTime<chrono::Utc>,
+        limit: u32,
+    ) -> Result<Vec<ResponseTimer>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM response_timers
+            WHERE status = 'open'
+              AND ((warned = 0 AND warn_at <= ?1) OR (breached = 0 AND deadline_at <= ?1))
+            ORDER BY deadline_at ASC
+            LIMIT ?2
+            "#,
+        )
+        .bind(now.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::response_timer_from_row).collect()
+    }
+
+    /// Record that the deadline warning of a timer was published
+    ///
+    /// Returns false if the timer was already warned about or closed.
+    pub async fn mark_response_timer_warned(&self, id: i64) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            "UPDATE response_timers SET warned = 1 WHERE id = ?1 AND warned = 0 AND status = 'open'",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Record that the deadline of a timer passed without an answer
+    ///
+    /// Returns false if the timer was already breached or closed.
+    pub async fn mark_response_timer_breached(&self, id: i64) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE response_timers SET warned = 1, breached = 1
+            WHERE id = ?1 AND breached = 0 AND status = 'open'
+            "#,
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// List response timers, optionally of one agent or status, most
+    /// recently started first
+    pub async fn list_response_timers(
+        &self,
+        agent_did: Option<&str>,
+        status: Option<ResponseTimerStatus>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<ResponseTimer>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM response_timers
+            WHERE (?1 IS NULL OR agent_did = ?1) AND (?2 IS NULL OR status = ?2)
+            ORDER BY started_at DESC, id DESC
+            LIMIT ?3 OFFSET ?4
+            "#,
+        )
+        .bind(agent_did)
+        .bind(status.map(|s| s.to_string()))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::response_timer_from_row).collect()
+    }
+
+    /// Time our agents took to answer each message type, for the messages
+    /// that arrived at or after `since` (RFC 3339), by message type
+    pub async fn response_time_report(
+        &self,
+        since: Option<&str>,
+    ) -> Result<Vec<ResponseTimeStats>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT message_type, status, breached, started_at, closed_at
+            FROM response_timers
+            WHERE ?1 IS NULL OR started_at >= ?1
+            "#,
+        )
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut by_type: std::collections::BTreeMap<String, (ResponseTimeStats, Vec<f64>)> =
+            std::collections::BTreeMap::new();
+        for row in &rows {
+            let message_type: String = row.get("message_type");
+            let (stats, durations) = by_type.entry(message_type.clone()).or_insert_with(|| {
+                (
+                    ResponseTimeStats {
+                        message_type,
+                        ..Default::default()
+                    },
+                    Vec::new(),
+                )
+            });
+            let status = ResponseTimerStatus::try_from(row.get::<String, _>("status").as_str())
+                .map_err(StorageError::InvalidTransactionType)?;
+            if row.get::<bool, _>("breached") {
+                stats.breached += 1;
+            }
+            match status {
+                ResponseTimerStatus::Open => stats.open += 1,
+                ResponseTimerStatus::Cancelled => stats.cancelled += 1,
+                ResponseTimerStatus::Answered => {
+                    stats.answered += 1;
+                    let started_at: String = row.get("started_at");
+                    let closed_at: Option<String> = row.get("closed_at");
+                    if let (Ok(started_at), Some(Ok(closed_at))) = (
+                        chrono::DateTime::parse_from_rfc3339(&started_at),
+                        closed_at
+                            .as_deref()
+                            .map(chrono::DateTime::parse_from_rfc3339),
+                    ) {
+                        durations.push((closed_at - started_at).num_milliseconds() as f64 / 1000.0);
+                    }
+                }
+            }
+        }
+
+        Ok(by_type
+            .into_values()
+            .map(|(mut stats, durations)| {
+                stats.response_time = LatencyStats::from_durations(durations);
+                stats
+            })
+            .collect())
+    }
+
+    fn response_timer_from_row(
+        row: &sqlx::sqlite::SqliteRow,
+    ) -> Result<ResponseTimer, StorageError> {
+        Ok(ResponseTimer {
+            id: row.get("id"),
+            message_id: row.get("message_id"),
+            message_type: row.get("message_type"),
+            thread_id: row.get("thread_id"),
+            agent_did: row.get("agent_did"),
+            counterparty_did: row.get("counterparty_did"),
+            responses: serde_json::from_str(&row.get::<String, _>("responses_json"))?,
+            status: ResponseTimerStatus::try_from(row.get::<String, _>("status").as_str())
+                .map_err(StorageError::InvalidTransactionType)?,
+            started_at: row.get("started_at"),
+            warn_at: row.get("warn_at"),
+            deadline_at: row.get("deadline_at"),
+            warned: row.get("warned"),
+            breached: row.get("breached"),
+            response_message_id: row.get("response_message_id"),
+            response_type: row.get("response_type"),
+            closed_at: row.get("closed_at"),
+        })
+    }
+
+    /// Record the schedule, last run and next run of a scheduled job
+    ///
+    /// A record without a last run keeps the last run already stored, and a
+    /// pending run request is left in place.
+    pub async fn save_scheduled_job(&self, job: &ScheduledJobRecord) -> Result<(), StorageError> {
+        debug!("Recording state of scheduled job {}", job.name);
+
+        sqlx::query(
+            r#"
+            INSERT INTO scheduled_jobs (name, schedule, last_run_at, last_status, last_error,
+                                        last_duration_ms, next_run_at, updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            ON CONFLICT(name) DO UPDATE SET
+                schedule = excluded.schedule,
+                last_run_at = COALESCE(excluded.last_run_at, scheduled_jobs.last_run_at),
+                last_status = CASE WHEN excluded.last_status IS NULL
+                              THEN scheduled_jobs.last_status ELSE excluded.last_status END,
+                last_error = CASE WHEN excluded.last_status IS NULL
+                             THEN scheduled_jobs.last_error ELSE excluded.last_error END,
+                last_duration_ms = CASE WHEN excluded.last_status IS NULL
+                                   THEN scheduled_jobs.last_duration_ms
+                                   ELSE excluded.last_duration_ms END,
+                next_run_at = excluded.next_run_at,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(&job.name)
+        .bind(&job.schedule)
+        .bind(&job.last_run_at)
+        .bind(job.last_status.map(|status| status.to_string()))
+        .bind(&job.last_error)
+        .bind(job.last_duration_ms)
+        .bind(&job.next_run_at)
+        .bind(&job.updated_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get a scheduled job by name
+    pub async fn get_scheduled_job(
+        &self,
+        name: &str,
+    ) -> Result<Option<ScheduledJobRecord>, StorageError> {
+        let row = sqlx::query(
+            r#"
+            SELECT name, schedule, last_run_at, last_status, last_error, last_duration_ms,
+                   next_run_at, run_requested_at, updated_at
+            FROM scheduled_jobs
+            WHERE name = ?1
+            "#,
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(Self::scheduled_job_from_row).transpose()
+    }
+
+    /// List the scheduled jobs recorded by the schedulers using this
+    /// database, by name
+    pub async fn list_scheduled_jobs(&self) -> Result<Vec<ScheduledJobRecord>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT name, schedule, last_run_at, last_status, last_error, last_duration_ms,
+                   next_run_at, run_requested_at, updated_at
+            FROM scheduled_jobs
+            ORDER BY name
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::scheduled_job_from_row).collect()
+    }
+
+    /// Ask the scheduler running a job to run it now
+    ///
+    /// Returns false if no scheduler has recorded a job with this name.
+    pub async fn request_job_run(&self, name: &str) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE scheduled_jobs SET run_requested_at = ?1
+            WHERE name = ?2
+            "#,
+        )
+        .bind(self.now())
+        .bind(name)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Take the pending run requests of the given jobs, clearing them
+    ///
+    /// Returns the names of the jobs to run.
+    pub async fn take_job_run_requests(
+        &self,
+        names: &[String],
+    ) -> Result<Vec<String>, StorageError> {
+        let mut requested = Vec::new();
+        for name in names {
+            let result = sqlx::query(
+                r#"
+                UPDATE scheduled_jobs SET run_requested_at = NULL
+                WHERE name = ?1 AND run_requested_at IS NOT NULL
+                "#,
+            )
+            .bind(name)
+            .execute(&self.pool)
+            .await?;
+            if result.rows_affected() > 0 {
+                requested.push(name.clone());
+            }
+        }
+        Ok(requested)
+    }
+
+    fn scheduled_job_from_row(
+        row: &sqlx::sqlite::SqliteRow,
+    ) -> Result<ScheduledJobRecord, StorageError> {
+        Ok(ScheduledJobRecord {
+            name: row.get("name"),
+            schedule: row.get("schedule"),
+            last_run_at: row.get("last_run_at"),
+            last_status: row
+                .get::<Option<String>, _>("last_status")
+                .map(|status| ScheduledJobStatus::try_from(status.as_str()))
+                .transpose()
+                .map_err(StorageError::InvalidTransactionType)?,
+            last_error: row.get("last_error"),
+            last_duration_ms: row.get("last_duration_ms"),
+            next_run_at: row.get("next_run_at"),
+            run_requested_at: row.get("run_requested_at"),
+            updated_at: row.get("updated_at"),
+        })
+    }
+
+    /// Record the signature verification of an incoming signed message
+    ///
+    /// The signer's DID document is pinned under its hash so the verification
+    /// can be reproduced later.
+    ///
+    /// # Arguments
+    ///
+    /// * `message_id` - ID of the verified message
+    /// * `verification` - Key, algorithm and DID document that verified it
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(i64)` - The ID of the created verification record
+    /// * `Err(StorageError)` on database error
+    pub async fn insert_message_verification(
+        &self,
+        message_id: &str,
+        verification: &JwsVerification,
+    ) -> Result<i64, StorageError> {
+        debug!(
+            "Recording verification of message {} with key {}",
+            message_id, verification.kid
+        );
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO did_documents (did_doc_hash, did, document_json)
+            VALUES (?1, ?2, ?3)
+            "#,
+        )
+        .bind(&verification.did_doc_hash)
+        .bind(&verification.did_doc.id)
+        .bind(serde_json::to_string(&verification.did_doc)?)
+        .execute(&mut *tx)
+        .await?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO message_verifications
+                (message_id, signer_did, kid, algorithm, did_doc_hash, verified_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+        )
+        .bind(message_id)
+        .bind(&verification.signer_did)
+        .bind(&verification.kid)
+        .bind(&verification.alg)
+        .bind(&verification.did_doc_hash)
+        .bind(&verification.verified_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Get a pinned DID document by its hash
+    pub async fn get_pinned_did_document(
+        &self,
+        did_doc_hash: &str,
+    ) -> Result<Option<PinnedDidDocument>, StorageError> {
+        let row = sqlx::query(
+            r#"
+            SELECT did_doc_hash, did, document_json, pinned_at
+            FROM did_documents WHERE did_doc_hash = ?1
+            "#,
+        )
+        .bind(did_doc_hash)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref()
+            .map(Self::pinned_did_document_from_row)
+            .transpose()
+    }
+
+    /// Export the DID documents used to verify a transaction's messages
+    ///
+    /// Returns each distinct document once, in the order it was pinned.
+    /// Together with the raw messages these allow every verification in the
+    /// transaction to be reproduced with [`tap_agent::PinnedDIDResolver`].
+    ///
+    /// # Arguments
+    ///
+    /// * `transaction_id` - The transaction ID (ID of the initiating message)
+    pub async fn export_pinned_did_documents(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Vec<PinnedDidDocument>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT d.did_doc_hash, d.did, d.document_json, d.pinned_at
+            FROM did_documents d
+            WHERE d.did_doc_hash IN (
+                SELECT v.did_doc_hash
+                FROM message_verifications v
+                JOIN messages m ON m.message_id = v.message_id
+                WHERE m.message_id = ?1 OR m.thread_id = ?1
+            )
+            ORDER BY d.pinned_at ASC, d.rowid ASC
+            "#,
+        )
+        .bind(transaction_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(Self::pinned_did_document_from_row)
+            .collect()
+    }
+
+    /// Get the signature verifications recorded for a message
+    pub async fn get_message_verifications(
+        &self,
+        message_id: &str,
+    ) -> Result<Vec<MessageVerification>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, message_id, signer_did, kid, algorithm, did_doc_hash, verified_at, created_at
+            FROM message_verifications
+            WHERE message_id = ?1
+            ORDER BY id ASC
+            "#,
+        )
+        .bind(message_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(Self::message_verification_from_row)
+            .collect())
+    }
+
+    /// Get the message history of a transaction in chronological order
+    ///
+    /// Includes the initiating message and every message in its thread,
+    /// each with the signature verifications recorded for it and the agents
+    /// it added to or removed from the transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `transaction_id` - The transaction ID (ID of the initiating message)
+    pub async fn get_transaction_timeline(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Vec<TimelineEntry>, StorageError> {
+        let message_rows = sqlx::query(
+            r#"
+            SELECT id, message_id, message_type, from_did, to_did, thread_id, parent_thread_id,
+                   direction, message_json, created_at
+            FROM messages
+            WHERE message_id = ?1 OR thread_id = ?1
+            ORDER BY created_at ASC, id ASC
+            "#,
+        )
+        .bind(transaction_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let verification_rows = sqlx::query(
+            r#"
+            SELECT v.id, v.message_id, v.signer_did, v.kid, v.algorithm, v.did_doc_hash,
+                   v.verified_at, v.created_at
+            FROM message_verifications v
+            JOIN messages m ON m.message_id = v.message_id
+            WHERE m.message_id = ?1 OR m.thread_id = ?1
+            ORDER BY v.id ASC
+            "#,
+        )
+        .bind(transaction_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut verifications: Vec<MessageVerification> = verification_rows
+            .iter()
+            .map(Self::message_verification_from_row)
+            .collect();
+        let participants = self
+            .get_transaction_participants(transaction_id, true)
+            .await?;
+
+        message_rows
+            .iter()
+            .map(|row| {
+                let message = Message {
+                    id: row.get("id"),
+                    message_id: row.get("message_id"),
+                    message_type: row.get("message_type"),
+                    from_did: row.get("from_did"),
+                    to_did: row.get("to_did"),
+                    thread_id: row.get("thread_id"),
+                    parent_thread_id: row.get("parent_thread_id"),
+                    direction: MessageDirection::try_from(
+                        row.get::<String, _>("direction").as_str(),
+                    )
+                    .map_err(StorageError::InvalidTransactionType)?,
+                    message_json: self.open_message_json(
+                        row.get("message_json"),
+                        row.get("message_id"),
+                        row.get("message_type"),
+                    )?,
+                    created_at: row.get("created_at"),
+                };
+                let (own, rest) = verifications
+                    .drain(..)
+                    .partition(|v| v.message_id == message.message_id);
+                verifications = rest;
+                let participants_added = participants
+                    .iter()
+                    .filter(|p| p.added_in == message.message_id)
+                    .cloned()
+                    .collect();
+                let participants_removed = participants
+                    .iter()
+                    .filter(|p| p.removed_in.as_deref() == Some(message.message_id.as_str()))
+                    .cloned()
+                    .collect();
+
+                Ok(TimelineEntry {
+                    message,
+                    verifications: own,
+                    participants_added,
+                    participants_removed,
+                })
+            })
+            .collect()
+    }
+
+    /// Store the nonce bound to an authorization URL
+    ///
+    /// `expires_at` must use the `%Y-%m-%dT%H:%M:%SZ` format so that it
+    /// compares correctly with SQLite timestamps.
+    pub async fn insert_authorization_challenge(
+        &self,
+        challenge: &AuthorizationChallenge,
+    ) -> Result<(), StorageError> {
+        debug!(
+            "Storing authorization challenge for transaction {} (agent {})",
+            challenge.transaction_id, challenge.agent_did
+        );
+
+        sqlx::query(
+            r#"
+            INSERT INTO authorization_challenges (
+                nonce, transaction_id, agent_did, recipient_did, party_type,
+                authorization_url, status, expires_at, created_at
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+            "#,
+        )
+        .bind(&challenge.nonce)
+        .bind(&challenge.transaction_id)
+        .bind(&challenge.agent_did)
+        .bind(&challenge.recipient_did)
+        .bind(&challenge.party_type)
+        .bind(&challenge.authorization_url)
+        .bind(challenge.status.to_string())
+        .bind(&challenge.expires_at)
+        .bind(&challenge.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get an authorization challenge by its nonce
+    pub async fn get_authorization_challenge(
+        &self,
+        nonce: &str,
+    ) -> Result<Option<AuthorizationChallenge>, StorageError> {
+        let row = sqlx::query(
+            r#"
+            SELECT nonce, transaction_id, agent_did, recipient_did, party_type,
+                   authorization_url, status, message_id, expires_at, created_at, redeemed_at
+            FROM authorization_challenges WHERE nonce = ?1
+            "#,
+        )
+        .bind(nonce)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref()
+            .map(Self::authorization_challenge_from_row)
+            .transpose()
+    }
+
+    /// Mark an authorization challenge as redeemed
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` if the challenge was pending and unexpired and is now redeemed
+    /// * `Ok(false)` if it does not exist, was already used or has expired
+    /// * `Err(StorageError)` on database error
+    pub async fn redeem_authorization_challenge(&self, nonce: &str) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE authorization_challenges
+            SET status = 'redeemed',
+                redeemed_at = ?2
+            WHERE nonce = ?1
+            AND status = 'pending'
+            AND expires_at > ?2
+            "#,
+        )
+        .bind(nonce)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Record the Authorize message sent for a redeemed challenge
+    pub async fn set_authorization_challenge_message(
+        &self,
+        nonce: &str,
+        message_id: &str,
+    ) -> Result<(), StorageError> {
+        sqlx::query(
+            r#"
+            UPDATE authorization_challenges SET message_id = ?1 WHERE nonce = ?2
+            "#,
+        )
+        .bind(message_id)
+        .bind(nonce)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Expire all pending authorization challenges past their expiry
+    pub async fn expire_authorization_challenges(&self) -> Result<u64, StorageError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE authorization_challenges
+            SET status = 'expired'
+            WHERE status = 'pending'
+            AND expires_at <= ?1
+            "#,
+        )
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Hold a message for a remote agent until it fetches and acknowledges it
+    ///
+    /// `expires_at` must use the `%Y-%m-%dT%H:%M:%SZ` format so that it
+    /// compares correctly with SQLite timestamps. Returns the sequence number
+    /// of the message.
+    pub async fn insert_mailbox_message(
+        &self,
+        recipient_did: &str,
+        sender_did: &str,
+        message_id: &str,
+        message_text: &str,
+        delivery_id: Option<i64>,
+        expires_at: &str,
+    ) -> Result<i64, StorageError> {
+        debug!(
+            "Holding message {} from {} in the mailbox of {}",
+            message_id, sender_did, recipient_did
+        );
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO mailbox_messages (
+                recipient_did, sender_did, message_id, message_text, delivery_id,
+                status, expires_at, created_at
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, 'pending', ?6, ?7)
+            "#,
+        )
+        .bind(recipient_did)
+        .bind(sender_did)
+        .bind(message_id)
+        .bind(message_text)
+        .bind(delivery_id)
+        .bind(expires_at)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Fetch the unexpired messages held for a remote agent after a sequence
+    /// number, in sequence order
+    ///
+    /// Fetched messages are returned again until they are acknowledged with
+    /// [`Storage::acknowledge_mailbox_messages`] or expire.
+    pub async fn fetch_mailbox_messages(
+        &self,
+        recipient_did: &str,
+        after: i64,
+        limit: u32,
+    ) -> Result<Vec<MailboxMessage>, StorageError> {
+        let now = self.now();
+        let mut tx = self.pool.begin().await?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT seq, recipient_did, sender_did, message_id, message_text, delivery_id,
+                   status, expires_at, created_at, fetched_at, delivered_at
+            FROM mailbox_messages
+            WHERE recipient_did = ?1
+            AND seq > ?2
+            AND status IN ('pending', 'fetched')
+            AND expires_at > ?3
+            ORDER BY seq ASC
+            LIMIT ?4
+            "#,
+        )
+        .bind(recipient_did)
+        .bind(after)
+        .bind(&now)
+        .bind(limit)
+        .fetch_all(&mut *tx)
+        .await?;
+        let mut messages = rows
+            .iter()
+            .map(Self::mailbox_message_from_row)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(last) = messages.last() {
+            sqlx::query(
+                r#"
+                UPDATE mailbox_messages
+                SET status = 'fetched',
+                    fetched_at = ?4
+                WHERE recipient_did = ?1
+                AND seq > ?2
+                AND seq <= ?3
+                AND status = 'pending'
+                "#,
+            )
+            .bind(recipient_did)
+            .bind(after)
+            .bind(last.seq)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        for message in messages
+            .iter_mut()
+            .filter(|m| m.status == MailboxStatus::Pending)
+        {
+            message.status = MailboxStatus::Fetched;
+            message.fetched_at = Some(now.clone());
+        }
+        Ok(messages)
+    }
+
+    /// Mark the fetched messages of a remote agent up to and including a
+    /// sequence number as delivered
+    ///
+    /// Returns the messages that were marked.
+    pub async fn acknowledge_mailbox_messages(
+        &self,
+        recipient_did: &str,
+        cursor: i64,
+    ) -> Result<Vec<MailboxMessage>, StorageError> {
+        let now = self.now();
+        let mut tx = self.pool.begin().await?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT seq, recipient_did, sender_did, message_id, message_text, delivery_id,
+                   status, expires_at, created_at, fetched_at, delivered_at
+            FROM mailbox_messages
+            WHERE recipient_did = ?1
+            AND seq <= ?2
+            AND status = 'fetched'
+            ORDER BY seq ASC
+            "#,
+        )
+        .bind(recipient_did)
+        .bind(cursor)
+        .fetch_all(&mut *tx)
+        .await?;
+        let mut messages = rows
+            .iter()
+            .map(Self::mailbox_message_from_row)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        sqlx::query(
+            r#"
+            UPDATE mailbox_messages
+            SET status = 'delivered',
+                delivered_at = ?3
+            WHERE recipient_did = ?1
+            AND seq <= ?2
+            AND status = 'fetched'
+            "#,
+        )
+        .bind(recipient_did)
+        .bind(cursor)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        for message in &mut messages {
+            message.status = MailboxStatus::Delivered;
+            message.delivered_at = Some(now.clone());
+        }
+        Ok(messages)
+    }
+
+    /// Expire all pending and fetched mailbox messages past their expiry
+    ///
+    /// Returns the messages that expired.
+    pub async fn expire_mailbox_messages(&self) -> Result<Vec<MailboxMessage>, StorageError> {
+        let now = self.now();
+        let mut tx = self.pool.begin().await?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT seq, recipient_did, sender_did, message_id, message_text, delivery_id,
+                   status, expires_at, created_at, fetched_at, delivered_at
+            FROM mailbox_messages
+            WHERE status IN ('pending', 'fetched')
+            AND expires_at <= ?1
+            ORDER BY seq ASC
+            "#,
+        )
+        .bind(&now)
+        .fetch_all(&mut *tx)
+        .await?;
+        let mut messages = rows
+            .iter()
+            .map(Self::mailbox_message_from_row)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        sqlx::query(
+            r#"
+            UPDATE mailbox_messages
+            SET status = 'expired'
+            WHERE status IN ('pending', 'fetched')
+            AND expires_at <= ?1
+            "#,
+        )
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        for message in &mut messages {
+            message.status = MailboxStatus::Expired;
+        }
+        Ok(messages)
+    }
+
+    /// Mark fetched messages of a remote agent as delivered by message ID
+    ///
+    /// Returns the messages that were marked.
+    pub async fn acknowledge_mailbox_messages_by_id(
+        &self,
+        recipient_did: &str,
+        message_ids: &[String],
+    ) -> Result<Vec<MailboxMessage>, StorageError> {
+        let now = self.now();
+        let mut tx = self.pool.begin().await?;
+        let mut messages = Vec::new();
+
+        for message_id in message_ids {
+            let rows = sqlx::query(
+                r#"
+                SELECT seq, recipient_did, sender_did, message_id, message_text, delivery_id,
+                       status, expires_at, created_at, fetched_at, delivered_at
+                FROM mailbox_messages
+                WHERE recipient_did = ?1
+                AND message_id = ?2
+                AND status = 'fetched'
+                "#,
+            )
+            .bind(recipient_did)
+            .bind(message_id)
+            .fetch_all(&mut *tx)
+            .await?;
+            for row in &rows {
+                messages.push(Self::mailbox_message_from_row(row)?);
+            }
+
+            sqlx::query(
+                r#"
+                UPDATE mailbox_messages
+                SET status = 'delivered',
+                    delivered_at = ?3
+                WHERE recipient_did = ?1
+                AND message_id = ?2
+                AND status = 'fetched'
+                "#,
+            )
+            .bind(recipient_did)
+            .bind(message_id)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        for message in &mut messages {
+            message.status = MailboxStatus::Delivered;
+            message.delivered_at = Some(now.clone());
+        }
+        messages.sort_by_key(|m| m.seq);
+        Ok(messages)
+    }
+
+    /// Summarize the unexpired messages waiting for a remote agent
+    pub async fn mailbox_summary(
+        &self,
+        recipient_did: &str,
+    ) -> Result<MailboxSummary, StorageError> {
+        let row = sqlx::query(
+            r#"
+            SELECT COUNT(*) AS message_count,
+                   COALESCE(SUM(LENGTH(CAST(message_text AS BLOB))), 0) AS total_bytes,
+                   MIN(created_at) AS oldest_created_at,
+                   MAX(created_at) AS newest_created_at
+            FROM mailbox_messages
+            WHERE recipient_did = ?1
+            AND status IN ('pending', 'fetched')
+            AND expires_at > ?2
+            "#,
+        )
+        .bind(recipient_did)
+        .bind(self.now())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(MailboxSummary {
+            message_count: row.get::<i64, _>("message_count") as u64,
+            total_bytes: row.get::<i64, _>("total_bytes") as u64,
+            oldest_created_at: row.get("oldest_created_at"),
+            newest_created_at: row.get("newest_created_at"),
+        })
+    }
+
+    /// Keep a message whose processing timed out in the dead letters
+    ///
+    /// Returns the ID of the dead letter.
+    pub async fn insert_dead_letter(
+        &self,
+        message_id: Option<&str>,
+        raw_message: &str,
+        stage: ProcessingStage,
+        reason: &str,
+    ) -> Result<i64, StorageError> {
+        debug!(
+            "Dead-lettering message {} after its {} timed out",
+            message_id.unwrap_or("unknown"),
+            stage
+        );
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO dead_letters (message_id, raw_message, stage, reason, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            "#,
+        )
+        .bind(message_id)
+        .bind(raw_message)
+        .bind(stage.as_str())
+        .bind(reason)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// List dead letters, newest first
+    pub async fn list_dead_letters(
+        &self,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<DeadLetter>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, message_id, raw_message, stage, reason, created_at
+            FROM dead_letters
+            ORDER BY id DESC
+            LIMIT ?1 OFFSET ?2
+            "#,
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::dead_letter_from_row).collect()
+    }
+
+    /// Get a single dead letter by ID
+    pub async fn get_dead_letter(&self, id: i64) -> Result<Option<DeadLetter>, StorageError> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, message_id, raw_message, stage, reason, created_at
+            FROM dead_letters WHERE id = ?1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(Self::dead_letter_from_row).transpose()
+    }
+
+    /// Remove a dead letter, e.g. once it has been replayed
+    ///
+    /// Returns whether the dead letter existed.
+    pub async fn delete_dead_letter(&self, id: i64) -> Result<bool, StorageError> {
+        let result = sqlx::query("DELETE FROM dead_letters WHERE id = ?1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Record a received message that the node was to forward to its
+    /// upstream node, and what became of it
+    ///
+    /// Returns the ID of the record.
+    pub async fn record_forwarded_message(
+        &self,
+        digest: &str,
+        envelope: &EnvelopeInfo,
+        upstream_url: &str,
+        forwarded_by: &[String],
+        status: ForwardStatus,
+        error: Option<&str>,
+    ) -> Result<i64, StorageError> {
+        debug!(
+            "Recording message {} for {} as {} to {}",
+            envelope.message_id.as_deref().unwrap_or("unknown"),
+            envelope.recipients.join(", "),
+            status,
+            upstream_url
+        );
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO forwarded_messages (
+                digest, envelope_kind, message_id, from_did, recipients, upstream_url,
+                forwarded_by, status, error, created_at
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+            "#,
+        )
+        .bind(digest)
+        .bind(envelope.kind.as_str())
+        .bind(&envelope.message_id)
+        .bind(&envelope.from)
+        .bind(serde_json::to_string(&envelope.recipients)?)
+        .bind(upstream_url)
+        .bind(serde_json::to_string(forwarded_by)?)
+        .bind(status.to_string())
+        .bind(error)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Whether an envelope with the given digest was forwarded upstream
+    ///
+    /// Refused and failed forwards do not count.
+    pub async fn has_forwarded(&self, digest: &str) -> Result<bool, StorageError> {
+        let row = sqlx::query(
+            r#"
+            SELECT 1 FROM forwarded_messages
+            WHERE digest = ?1 AND status = 'forwarded'
+            LIMIT 1
+            "#,
+        )
+        .bind(digest)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.is_some())
+    }
+
+    /// List the messages the node forwarded, refused to forward or failed to
+    /// forward to its upstream node, newest first
+    pub async fn list_forwarded_messages(
+        &self,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<ForwardedMessage>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, digest, envelope_kind, message_id, from_did, recipients, upstream_url,
+                   forwarded_by, status, error, created_at
+            FROM forwarded_messages
+            ORDER BY id DESC
+            LIMIT ?1 OFFSET ?2
+            "#,
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::forwarded_message_from_row).collect()
+    }
+
+    /// Record a Connect request sent or received by one of our agents
+    ///
+    /// A Connect that is already recorded is left as it is.
+    pub async fn insert_connection(&self, message: &PlainMessage) -> Result<(), StorageError> {
+        debug!("Recording connection request {}", message.id);
+
+        let now = self.now();
+        sqlx::query(
+            r#"
+            INSERT INTO connections (reference_id, from_did, to_dids, status, message_json, created_at, updated_at)
+            VALUES (?1, ?2, ?3, 'pending', ?4, ?5, ?5)
+            ON CONFLICT(reference_id) DO NOTHING
+            "#,
+        )
+        .bind(&message.id)
+        .bind(&message.from)
+        .bind(serde_json::to_string(&message.to)?)
+        .bind(serde_json::to_string(message)?)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get a Connect request by the ID of its message
+    pub async fn get_connection(
+        &self,
+        reference_id: &str,
+    ) -> Result<Option<Connection>, StorageError> {
+        let row = sqlx::query(
+            r#"
+            SELECT reference_id, from_did, to_dids, status, message_json, created_at, updated_at
+            FROM connections
+            WHERE reference_id = ?1
+            "#,
+        )
+        .bind(reference_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(Self::connection_from_row).transpose()
+    }
+
+    /// List Connect requests, newest first
+    pub async fn list_connections(
+        &self,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<Connection>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT reference_id, from_did, to_dids, status, message_json, created_at, updated_at
+            FROM connections
+            ORDER BY created_at DESC, rowid DESC
+            LIMIT ?1 OFFSET ?2
+            "#,
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::connection_from_row).collect()
+    }
+
+    /// Record the answer to a Connect request
+    ///
+    /// Returns whether the connection exists.
+    pub async fn update_connection_status(
+        &self,
+        reference_id: &str,
+        status: &ConnectionStatus,
+    ) -> Result<bool, StorageError> {
+        debug!("Connection {} is now {}", reference_id, status);
+
+        let result = sqlx::query(
+            r#"
+            UPDATE connections
+            SET status = ?1, updated_at = ?2
+            WHERE reference_id = ?3
+            "#,
+        )
+        .bind(status.to_string())
+        .bind(self.now())
+        .bind(reference_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Count the amount of a transaction against the limits of a connection
+    ///
+    /// A transaction is counted once; recording it again has no effect.
+    pub async fn record_connection_spending(
+        &self,
+        connection_id: &str,
+        transaction_id: &str,
+        currency: &str,
+        amount: f64,
+    ) -> Result<(), StorageError> {
+        debug!(
+            "Counting {} {} of transaction {} against connection {}",
+            amount, currency, transaction_id, connection_id
+        );
+
+        sqlx::query(
+            r#"
+            INSERT INTO connection_spending (connection_id, transaction_id, currency, amount, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT(connection_id, transaction_id) DO NOTHING
+            "#,
+        )
+        .bind(connection_id)
+        .bind(transaction_id)
+        .bind(currency)
+        .bind(amount)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Total amount counted against a connection in a currency since the
+    /// given time
+    ///
+    /// Failed, cancelled and reverted transactions do not count.
+    pub async fn sum_connection_spending(
+        &self,
+        connection_id: &str,
+        currency: &str,
+        since: &str,
+        exclude_transaction_id: Option<&str>,
+    ) -> Result<f64, StorageError> {
+        let total = sqlx::query_scalar::<_, f64>(
+            r#"
+            SELECT COALESCE(SUM(s.amount), 0.0)
+            FROM connection_spending s
+            LEFT JOIN transactions t ON t.reference_id = s.transaction_id
+            WHERE s.connection_id = ?1
+              AND s.currency = ?2
+              AND datetime(s.created_at) >= datetime(?3)
+              AND (t.status IS NULL OR t.status NOT IN ('failed', 'cancelled', 'reverted'))
+              AND (?4 IS NULL OR s.transaction_id != ?4)
+            "#,
+        )
+        .bind(connection_id)
+        .bind(currency)
+        .bind(since)
+        .bind(exclude_transaction_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(total)
+    }
+
+    /// Save the profile of an onboarded counterparty, replacing any earlier one
+    ///
+    /// The profile keeps its original creation time.
+    pub async fn upsert_counterparty_profile(
+        &self,
+        profile: &CounterpartyProfile,
+    ) -> Result<(), StorageError> {
+        debug!(
+            "Saving profile of counterparty {}",
+            profile.counterparty_did
+        );
+
+        sqlx::query(
+            r#"
+            INSERT INTO counterparty_profiles (
+                counterparty_did, connection_id, connection_status, authorization_url,
+                rejection_reason, features, our_policies, their_policies,
+                endpoint_verified, ping_rtt_ms, created_at, updated_at
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+            ON CONFLICT(counterparty_did) DO UPDATE SET
+                connection_id = excluded.connection_id,
+                connection_status = excluded.connection_status,
+                authorization_url = excluded.authorization_url,
+                rejection_reason = excluded.rejection_reason,
+                features = excluded.features,
+                our_policies = excluded.our_policies,
+                their_policies = excluded.their_policies,
+                endpoint_verified = excluded.endpoint_verified,
+                ping_rtt_ms = excluded.ping_rtt_ms,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(&profile.counterparty_did)
+        .bind(&profile.connection_id)
+        .bind(profile.connection_status.to_string())
+        .bind(&profile.authorization_url)
+        .bind(&profile.rejection_reason)
+        .bind(
+            profile
+                .features
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+        )
+        .bind(serde_json::to_string(&profile.our_policies)?)
+        .bind(serde_json::to_string(&profile.their_policies)?)
+        .bind(profile.endpoint_verified)
+        .bind(profile.ping_rtt_ms.map(|rtt| rtt as i64))
+        .bind(&profile.created_at)
+        .bind(&profile.updated_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get the profile of an onboarded counterparty
+    pub async fn get_counterparty_profile(
+        &self,
+        counterparty_did: &str,
+    ) -> Result<Option<CounterpartyProfile>, StorageError> {
+        let row = sqlx::query(
+            r#"
+            SELECT * FROM counterparty_profiles
+            WHERE counterparty_did = ?1
+            "#,
+        )
+        .bind(counterparty_did)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref()
+            .map(Self::counterparty_profile_from_row)
+            .transpose()
+    }
+
+    /// List the profiles of onboarded counterparties, most recently updated first
+    pub async fn list_counterparty_profiles(
+        &self,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<CounterpartyProfile>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM counterparty_profiles
+            ORDER BY updated_at DESC
+            LIMIT ?1 OFFSET ?2
+            "#,
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(Self::counterparty_profile_from_row)
+            .collect()
+    }
+
+    /// Store the baseline of a counterparty, replacing any earlier one
+    pub async fn upsert_counterparty_baseline(
+        &self,
+        baseline: &CounterpartyBaseline,
+    ) -> Result<(), StorageError> {
+        sqlx::query(
+            r#"
+            INSERT INTO counterparty_baselines (counterparty_did, baseline, updated_at)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT(counterparty_did) DO UPDATE SET
+                baseline = excluded.baseline,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(&baseline.counterparty_did)
+        .bind(serde_json::to_string(baseline)?)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get the baseline of a counterparty
+    pub async fn get_counterparty_baseline(
+        &self,
+        counterparty_did: &str,
+    ) -> Result<Option<CounterpartyBaseline>, StorageError> {
+        let baseline: Option<String> = sqlx::query_scalar(
+            r#"
+            SELECT baseline FROM counterparty_baselines
+            WHERE counterparty_did = ?1
+            "#,
+        )
+        .bind(counterparty_did)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(baseline.as_deref().map(serde_json::from_str).transpose()?)
+    }
+
+    /// Aggregate the activity of a counterparty
+    ///
+    /// A transaction counts for the counterparty if it sent or received the
+    /// transaction's initiating message or is one of its agents. The
+    /// authorization rate and time to authorize cover the transactions the
+    /// counterparty did not initiate, up to its first Authorize; the time to
+    /// settle covers all transactions, up to their first Settle.
+    ///
+    /// # Arguments
+    ///
+    /// * `counterparty_did` - DID of the counterparty
+    /// * `since` - Only count transactions and messages created at or after this time (RFC 3339)
+    /// * `until` - Only count transactions and messages created before this time (RFC 3339)
+    pub async fn get_counterparty_stats(
+        &self,
+        counterparty_did: &str,
+        since: Option<&str>,
+        until: Option<&str>,
+    ) -> Result<CounterpartyStats, StorageError> {
+        let mut stats = CounterpartyStats {
+            counterparty_did: counterparty_did.to_string(),
+            since: since.map(String::from),
+            until: until.map(String::from),
+            ..Default::default()
+        };
+
+        let message_counts = sqlx::query_as::<_, (String, i64)>(
+            r#"
+            SELECT direction, COUNT(*)
+            FROM messages
+            WHERE ((direction = 'incoming' AND from_did = ?1)
+                OR (direction = 'outgoing' AND to_did = ?1))
+              AND (?2 IS NULL OR datetime(created_at) >= datetime(?2))
+              AND (?3 IS NULL OR datetime(created_at) < datetime(?3))
+            GROUP BY direction
+            "#,
+        )
+        .bind(counterparty_did)
+        .bind(since)
+        .bind(until)
+        .fetch_all(&self.pool)
+        .await?;
+        for (direction, count) in message_counts {
+            match direction.as_str() {
+                "incoming" => stats.messages_received = count as u64,
+                _ => stats.messages_sent = count as u64,
+            }
+        }
+
+        let rows = sqlx::query(
+            r#"
+            SELECT t.from_did = ?1 AS initiated, t.status, t.rejection_code, t.message_json,
+                (
+                    SELECT (julianday(MIN(m.created_at)) - julianday(t.created_at)) * 86400.0
+                    FROM messages m
+                    WHERE m.thread_id = t.reference_id
+                      AND m.from_did = ?1
+                      AND m.message_type LIKE '%#Authorize'
+                ) AS authorize_secs,
+                (
+                    SELECT (julianday(MIN(m.created_at)) - julianday(t.created_at)) * 86400.0
+                    FROM messages m
+                    WHERE m.thread_id = t.reference_id
+                      AND m.message_type LIKE '%#Settle'
+                ) AS settle_secs
+            FROM transactions t
+            WHERE (t.from_did = ?1 OR t.to_did = ?1 OR EXISTS (
+                    SELECT 1 FROM transaction_agents a
+                    WHERE a.transaction_id = t.id AND a.agent_did = ?1
+                ))
+              AND (?2 IS NULL OR datetime(t.created_at) >= datetime(?2))
+              AND (?3 IS NULL OR datetime(t.created_at) < datetime(?3))
+            "#,
+        )
+        .bind(counterparty_did)
+        .bind(since)
+        .bind(until)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut authorize_secs = Vec::new();
+        let mut settle_secs = Vec::new();
+        for row in &rows {
+            stats.transactions += 1;
+            let initiated: bool = row.try_get("initiated")?;
+            if initiated {
+                stats.initiated += 1;
+            }
+
+            let status: String = row.try_get("status")?;
+            if status == TransactionStatus::Failed.to_string() {
+                stats.rejected += 1;
+                match row.try_get::<Option<String>, _>("rejection_code")? {
+                    Some(code) => *stats.rejections_by_code.entry(code).or_default() += 1,
+                    None => stats.uncoded_rejections += 1,
+                }
+            }
+            if let Some(secs) = row.try_get::<Option<f64>, _>("authorize_secs")? {
+                if !initiated {
+                    stats.authorized += 1;
+                    authorize_secs.push(secs.max(0.0));
+                }
+            }
+            if let Some(secs) = row.try_get::<Option<f64>, _>("settle_secs")? {
+                stats.settled += 1;
+                settle_secs.push(secs.max(0.0));
+            }
+
+            let message: serde_json::Value = row.try_get("message_json")?;
+            let body = &message["body"];
+            let asset = body["asset"].as_str().or_else(|| body["currency"].as_str());
+            let amount = body["amount"]
+                .as_str()
+                .and_then(|amount| amount.parse::<f64>().ok());
+            if let (Some(asset), Some(amount)) = (asset, amount) {
+                *stats.volumes.entry(asset.to_string()).or_default() += amount;
+            }
+        }
+
+        let to_authorize = stats.transactions - stats.initiated;
+        if to_authorize > 0 {
+            stats.authorization_rate = stats.authorized as f64 / to_authorize as f64;
+        }
+        stats.time_to_authorize = LatencyStats::from_durations(authorize_secs);
+        stats.time_to_settle = LatencyStats::from_durations(settle_secs);
+
+        Ok(stats)
+    }
+
+    /// Store the compatibility rules of a counterparty, replacing any earlier
+    /// ones
+    pub async fn upsert_counterparty_compatibility(
+        &self,
+        counterparty_did: &str,
+        rules: &CompatibilityRules,
+    ) -> Result<(), StorageError> {
+        sqlx::query(
+            r#"
+            INSERT INTO counterparty_compatibility (counterparty_did, rules, updated_at)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT(counterparty_did) DO UPDATE SET
+                rules = excluded.rules,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(counterparty_did)
+        .bind(serde_json::to_string(rules)?)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get the compatibility rules of a counterparty
+    pub async fn get_counterparty_compatibility(
+        &self,
+        counterparty_did: &str,
+    ) -> Result<Option<CompatibilityRules>, StorageError> {
+        let rules: Option<String> = sqlx::query_scalar(
+            r#"
+            SELECT rules FROM counterparty_compatibility
+            WHERE counterparty_did = ?1
+            "#,
+        )
+        .bind(counterparty_did)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(rules.as_deref().map(serde_json::from_str).transpose()?)
+    }
+
+    /// List the counterparties with compatibility rules and their rules
+    pub async fn list_counterparty_compatibility(
+        &self,
+    ) -> Result<Vec<(String, CompatibilityRules)>, StorageError> {
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            r#"
+            SELECT counterparty_did, rules FROM counterparty_compatibility
+            ORDER BY counterparty_did
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|(did, rules)| Ok((did, serde_json::from_str(&rules)?)))
+            .collect()
+    }
+
+    /// Delete the compatibility rules of a counterparty, returning whether it
+    /// had any
+    pub async fn delete_counterparty_compatibility(
+        &self,
+        counterparty_did: &str,
+    ) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM counterparty_compatibility
+            WHERE counterparty_did = ?1
+            "#,
+        )
+        .bind(counterparty_did)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Count a message received from a counterparty in the statistics of
+    /// its envelope profile
+    ///
+    /// # Arguments
+    ///
+    /// * `counterparty_did` - DID the message claims to be from
+    /// * `profile` - Envelope profile of the message
+    /// * `error` - Why the message was rejected or failed to process, if it did
+    pub async fn record_envelope(
+        &self,
+        counterparty_did: &str,
+        profile: &EnvelopeProfile,
+        error: Option<&str>,
+    ) -> Result<(), StorageError> {
+        sqlx::query(
+            r#"
+            INSERT INTO envelope_stats (counterparty_did, kind, serialization, alg, enc,
+                                        media_type, schema_version, messages, failures,
+                                        last_error, first_seen_at, last_seen_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 1, ?8 IS NOT NULL, ?8, ?9, ?9)
+            ON CONFLICT(counterparty_did, kind, serialization, alg, enc, media_type,
+                        schema_version) DO UPDATE SET
+                messages = envelope_stats.messages + 1,
+                failures = envelope_stats.failures + (excluded.last_error IS NOT NULL),
+                last_error = COALESCE(excluded.last_error, envelope_stats.last_error),
+                last_seen_at = excluded.last_seen_at
+            "#,
+        )
+        .bind(counterparty_did)
+        .bind(profile.kind.as_str())
+        .bind(profile.serialization.as_str())
+        // Fields that do not apply are stored empty so they take part in the key
+        .bind(profile.alg.as_deref().unwrap_or_default())
+        .bind(profile.enc.as_deref().unwrap_or_default())
+        .bind(profile.media_type.as_deref().unwrap_or_default())
+        .bind(profile.schema_version.as_deref().unwrap_or_default())
+        .bind(error)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// List the envelope profiles received from a counterparty, or from all
+    /// counterparties, most recently seen first
+    pub async fn list_envelope_stats(
+        &self,
+        counterparty_did: Option<&str>,
+    ) -> Result<Vec<EnvelopeStats>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT counterparty_did, kind, serialization, alg, enc, media_type, schema_version,
+                   messages, failures, last_error, first_seen_at, last_seen_at
+            FROM envelope_stats
+            WHERE ?1 IS NULL OR counterparty_did = ?1
+            ORDER BY last_seen_at DESC, counterparty_did
+            "#,
+        )
+        .bind(counterparty_did)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::envelope_stats_from_row).collect()
+    }
+
+    fn envelope_stats_from_row(
+        row: &sqlx::sqlite::SqliteRow,
+    ) -> Result<EnvelopeStats, StorageError> {
+        let optional = |column: &str| {
+            let value: String = row.get(column);
+            (!value.is_empty()).then_some(value)
+        };
+        let kind: String = row.get("kind");
+        let serialization: String = row.get("serialization");
+        Ok(EnvelopeStats {
+            counterparty_did: row.get("counterparty_did"),
+            profile: EnvelopeProfile {
+                kind: EnvelopeKind::try_from(kind.as_str())
+                    .map_err(StorageError::InvalidTransactionType)?,
+                serialization: EnvelopeSerialization::try_from(serialization.as_str())
+                    .map_err(StorageError::InvalidTransactionType)?,
+                alg: optional("alg"),
+                enc: optional("enc"),
+                media_type: optional("media_type"),
+                schema_version: optional("schema_version"),
+            },
+            messages: row.get::<i64, _>("messages") as u64,
+            failures: row.get::<i64, _>("failures") as u64,
+            last_error: row.get("last_error"),
+            first_seen_at: row.get("first_seen_at"),
+            last_seen_at: row.get("last_seen_at"),
+        })
+    }
+
+    /// Store the fiat value of a transaction
+    ///
+    /// A transaction keeps its first valuation; storing another one for it
+    /// is ignored.
+    pub async fn record_transaction_valuation(
+        &self,
+        valuation: &TransactionValuation,
+    ) -> Result<(), StorageError> {
+        debug!(
+            "Valuing transaction {} at {} {}",
+            valuation.transaction_id, valuation.fiat_amount, valuation.currency
+        );
+
+        sqlx::query(
+            r#"
+            INSERT INTO transaction_valuations (
+                transaction_id, asset, amount, currency, fiat_amount, rate,
+                rate_source, rate_timestamp, created_at
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+            ON CONFLICT(transaction_id) DO NOTHING
+            "#,
+        )
+        .bind(&valuation.transaction_id)
+        .bind(&valuation.asset)
+        .bind(&valuation.amount)
+        .bind(&valuation.currency)
+        .bind(valuation.fiat_amount)
+        .bind(valuation.rate)
+        .bind(&valuation.rate_source)
+        .bind(&valuation.rate_timestamp)
+        .bind(&valuation.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get the fiat value of a transaction
+    pub async fn get_transaction_valuation(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Option<TransactionValuation>, StorageError> {
+        let row = sqlx::query(
+            r#"
+            SELECT v.*, t.canonical_hash FROM transaction_valuations v
+            LEFT JOIN transactions t ON t.reference_id = v.transaction_id
+            WHERE v.transaction_id = ?1
+            "#,
+        )
+        .bind(transaction_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(Self::transaction_valuation_from_row))
+    }
+
+    /// List the valuations of transactions, oldest first
+    ///
+    /// `since` and `until` bound the time the transactions were valued as
+    /// RFC 3339 timestamps; `until` is exclusive.
+    pub async fn list_transaction_valuations(
+        &self,
+        since: Option<&str>,
+        until: Option<&str>,
+    ) -> Result<Vec<TransactionValuation>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT v.*, t.canonical_hash FROM transaction_valuations v
+            LEFT JOIN transactions t ON t.reference_id = v.transaction_id
+            WHERE (?1 IS NULL OR datetime(v.created_at) >= datetime(?1))
+              AND (?2 IS NULL OR datetime(v.created_at) < datetime(?2))
+            ORDER BY v.created_at, v.transaction_id
+            "#,
+        )
+        .bind(since)
+        .bind(until)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(Self::transaction_valuation_from_row)
+            .collect())
+    }
+
+    /// Store the conversion of a cross-asset settlement
+    ///
+    /// A transaction keeps its first conversion; storing another one for it
+    /// is ignored.
+    pub async fn record_settlement_conversion(
+        &self,
+        conversion: &SettlementConversion,
+    ) -> Result<(), StorageError> {
+        debug!(
+            "Recording settlement of transaction {} in {} ({})",
+            conversion.transaction_id, conversion.settled_asset, conversion.status
+        );
+
+        sqlx::query(
+            r#"
+            INSERT INTO settlement_conversions (
+                transaction_id, requested_asset, requested_amount, settled_asset,
+                settled_amount, rate, rate_source, expected_amount, slippage,
+                max_slippage, status, created_at
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+            ON CONFLICT(transaction_id) DO NOTHING
+            "#,
+        )
+        .bind(&conversion.transaction_id)
+        .bind(&conversion.requested_asset)
+        .bind(&conversion.requested_amount)
+        .bind(&conversion.settled_asset)
+        .bind(&conversion.settled_amount)
+        .bind(conversion.rate)
+        .bind(&conversion.rate_source)
+        .bind(conversion.expected_amount)
+        .bind(conversion.slippage)
+        .bind(conversion.max_slippage)
+        .bind(conversion.status.to_string())
+        .bind(&conversion.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get the conversion of the settlement of a transaction
+    pub async fn get_settlement_conversion(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Option<SettlementConversion>, StorageError> {
+        let row = sqlx::query(
+            r#"
+            SELECT * FROM settlement_conversions
+            WHERE transaction_id = ?1
+            "#,
+        )
+        .bind(transaction_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref()
+            .map(Self::settlement_conversion_from_row)
+            .transpose()
+    }
+
+    /// List the conversions of cross-asset settlements, newest first
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - Only list conversions with this status
+    /// * `limit` - Maximum number of conversions to return
+    /// * `offset` - Number of conversions to skip (for pagination)
+    pub async fn list_settlement_conversions(
+        &self,
+        status: Option<ConversionStatus>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<SettlementConversion>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM settlement_conversions
+            WHERE (?1 IS NULL OR status = ?1)
+            ORDER BY created_at DESC, transaction_id
+            LIMIT ?2 OFFSET ?3
+            "#,
+        )
+        .bind(status.map(|status| status.to_string()))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(Self::settlement_conversion_from_row)
+            .collect()
+    }
+
+    /// Record a settlement address seen in a transaction
+    ///
+    /// Returns false, without storing anything, if the address was already
+    /// recorded for the transaction.
+    pub async fn record_address_sighting(
+        &self,
+        sighting: &AddressSighting,
+    ) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO settlement_address_sightings (
+                address, transaction_id, provided_by, originator_did, beneficiary_did, seen_at
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT(address, transaction_id) DO NOTHING
+            "#,
+        )
+        .bind(&sighting.address)
+        .bind(&sighting.transaction_id)
+        .bind(&sighting.provided_by)
+        .bind(&sighting.originator_did)
+        .bind(&sighting.beneficiary_did)
+        .bind(&sighting.seen_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// List the transactions a settlement address was seen in, oldest first
+    ///
+    /// The address is compared as normalized by
+    /// [`normalize_address`](crate::address_reuse::normalize_address).
+    pub async fn list_address_sightings(
+        &self,
+        address: &str,
+    ) -> Result<Vec<AddressSighting>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM settlement_address_sightings
+            WHERE address = ?1
+            ORDER BY seen_at, transaction_id
+            "#,
+        )
+        .bind(normalize_address(address))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(Self::address_sighting_from_row).collect())
+    }
+
+    /// List the settlement addresses seen in a transaction
+    pub async fn list_transaction_addresses(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Vec<AddressSighting>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM settlement_address_sightings
+            WHERE transaction_id = ?1
+            ORDER BY seen_at, address
+            "#,
+        )
+        .bind(transaction_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(Self::address_sighting_from_row).collect())
+    }
+
+    /// List the settlement addresses seen in more than one transaction, those
+    /// given by the most agents and paid by the most originators first
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - Maximum number of addresses to return
+    /// * `offset` - Number of addresses to skip (for pagination)
+    pub async fn list_reused_addresses(
+        &self,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<ReusedAddress>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT address,
+                   COUNT(*) AS transactions,
+                   COUNT(DISTINCT provided_by) AS providers,
+                   COUNT(DISTINCT originator_did) AS originators,
+                   COUNT(DISTINCT beneficiary_did) AS beneficiaries,
+                   MIN(seen_at) AS first_seen_at,
+                   MAX(seen_at) AS last_seen_at
+            FROM settlement_address_sightings
+            GROUP BY address
+            HAVING COUNT(*) > 1
+            ORDER BY providers DESC, beneficiaries DESC, originators DESC, transactions DESC,
+                     address
+            LIMIT ?1 OFFSET ?2
+            "#,
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| ReusedAddress {
+                address: row.get("address"),
+                transactions: row.get::<i64, _>("transactions") as u64,
+                providers: row.get::<i64, _>("providers") as u64,
+                originators: row.get::<i64, _>("originators") as u64,
+                beneficiaries: row.get::<i64, _>("beneficiaries") as u64,
+                first_seen_at: row.get("first_seen_at"),
+                last_seen_at: row.get("last_seen_at"),
+            })
+            .collect())
+    }
+
+    /// Reserve a settlement address for a transaction
+    ///
+    /// Returns false, without storing anything, if the address is already
+    /// reserved or the agent already has an address on the chain for the
+    /// transaction.
+    pub async fn reserve_settlement_address(
+        &self,
+        reservation: &SettlementAddressReservation,
+    ) -> Result<bool, StorageError> {
+        debug!(
+            "Reserving settlement address {} for transaction {}",
+            reservation.account_id(),
+            reservation.transaction_id
+        );
+
+        let result = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO settlement_address_reservations (
+                chain_id, address, agent_did, transaction_id, asset,
+                derivation_index, created_at
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            "#,
+        )
+        .bind(&reservation.chain_id)
+        .bind(&reservation.address)
+        .bind(&reservation.agent_did)
+        .bind(&reservation.transaction_id)
+        .bind(&reservation.asset)
+        .bind(reservation.derivation_index as i64)
+        .bind(&reservation.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Get the settlement address an agent reserved on a chain for a transaction
+    pub async fn get_settlement_address_reservation(
+        &self,
+        transaction_id: &str,
+        agent_did: &str,
+        chain_id: &str,
+    ) -> Result<Option<SettlementAddressReservation>, StorageError> {
+        let row = sqlx::query(
+            r#"
+            SELECT * FROM settlement_address_reservations
+            WHERE transaction_id = ?1 AND agent_did = ?2 AND chain_id = ?3
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(agent_did)
+        .bind(chain_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row
+            .as_ref()
+            .map(Self::settlement_address_reservation_from_row))
+    }
+
+    /// Find the reservation of an address, e.g. to match an incoming settlement
+    pub async fn find_settlement_address_reservation(
+        &self,
+        chain_id: &str,
+        address: &str,
+    ) -> Result<Option<SettlementAddressReservation>, StorageError> {
+        let row = sqlx::query(
+            r#"
+            SELECT * FROM settlement_address_reservations
+            WHERE chain_id = ?1 AND address = ?2
+            "#,
+        )
+        .bind(chain_id)
+        .bind(address)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row
+            .as_ref()
+            .map(Self::settlement_address_reservation_from_row))
+    }
+
+    /// Count the settlement addresses reserved on a chain
+    pub async fn count_settlement_address_reservations(
+        &self,
+        chain_id: &str,
+    ) -> Result<u32, StorageError> {
+        let count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM settlement_address_reservations
+            WHERE chain_id = ?1
+            "#,
+        )
+        .bind(chain_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count as u32)
+    }
+
+    /// Store a new draft
+    pub async fn insert_draft(&self, draft: &Draft) -> Result<(), StorageError> {
+        debug!("Storing {} draft {}", draft.kind, draft.id);
+
+        sqlx::query(
+            r#"
+            INSERT INTO drafts (
+                id, agent_did, kind, body, status, validation_errors,
+                expires_at, message_id, created_at, updated_at
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+            "#,
+        )
+        .bind(&draft.id)
+        .bind(&draft.agent_did)
+        .bind(draft.kind.to_string())
+        .bind(serde_json::to_string(&draft.body)?)
+        .bind(draft.status.to_string())
+        .bind(serde_json::to_string(&draft.validation_errors)?)
+        .bind(&draft.expires_at)
+        .bind(&draft.message_id)
+        .bind(&draft.created_at)
+        .bind(&draft.updated_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Replace the body of a draft and the result of its validation
+    ///
+    /// Returns false if the draft does not exist or was already sent.
+    pub async fn update_draft(
+        &self,
+        id: &str,
+        body: &serde_json::Value,
+        status: DraftStatus,
+        validation_errors: &[String],
+    ) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE drafts
+            SET body = ?2, status = ?3, validation_errors = ?4, updated_at = ?5
+            WHERE id = ?1 AND status != 'sent'
+            "#,
+        )
+        .bind(id)
+        .bind(serde_json::to_string(body)?)
+        .bind(status.to_string())
+        .bind(serde_json::to_string(validation_errors)?)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Get a draft by ID
+    pub async fn get_draft(&self, id: &str) -> Result<Option<Draft>, StorageError> {
+        let row = sqlx::query("SELECT * FROM drafts WHERE id = ?1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.as_ref().map(Self::draft_from_row).transpose()
+    }
+
+    /// List drafts, most recently changed first
+    ///
+    /// Expired drafts that were not sent are left out unless
+    /// `include_expired` is set.
+    pub async fn list_drafts(
+        &self,
+        agent_did: Option<&str>,
+        status: Option<DraftStatus>,
+        include_expired: bool,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<Draft>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM drafts
+            WHERE (?1 IS NULL OR agent_did = ?1)
+              AND (?2 IS NULL OR status = ?2)
+              AND (?3 OR status = 'sent' OR expires_at IS NULL
+                   OR datetime(expires_at) > datetime(?4))
+            ORDER BY updated_at DESC, id
+            LIMIT ?5 OFFSET ?6
+            "#,
+        )
+        .bind(agent_did)
+        .bind(status.map(|status| status.to_string()))
+        .bind(include_expired)
+        .bind(self.now())
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::draft_from_row).collect()
+    }
+
+    /// Mark a valid draft sent as the message `message_id`
+    ///
+    /// Returns false if the draft is not valid, e.g. because it was already
+    /// sent, so that a draft is sent only once.
+    pub async fn mark_draft_sent(&self, id: &str, message_id: &str) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE drafts
+            SET status = 'sent', message_id = ?2, updated_at = ?3
+            WHERE id = ?1 AND status = 'valid'
+            "#,
+        )
+        .bind(id)
+        .bind(message_id)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Make a draft valid again after sending it as `message_id` failed
+    pub async fn reopen_draft(&self, id: &str, message_id: &str) -> Result<(), StorageError> {
+        sqlx::query(
+            r#"
+            UPDATE drafts
+            SET status = 'valid', message_id = NULL, updated_at = ?3
+            WHERE id = ?1 AND message_id = ?2
+            "#,
+        )
+        .bind(id)
+        .bind(message_id)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Delete a draft that was not sent, returning whether it existed
+    pub async fn delete_draft(&self, id: &str) -> Result<bool, StorageError> {
+        let result = sqlx::query("DELETE FROM drafts WHERE id = ?1 AND status != 'sent'")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete the expired drafts that were not sent, returning how many
+    pub async fn delete_expired_drafts(&self) -> Result<u64, StorageError> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM drafts
+            WHERE status != 'sent' AND datetime(expires_at) <= datetime(?1)
+            "#,
+        )
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    fn settlement_address_reservation_from_row(
+        row: &sqlx::sqlite::SqliteRow,
+    ) -> SettlementAddressReservation {
+        SettlementAddressReservation {
+            chain_id: row.get("chain_id"),
+            address: row.get("address"),
+            agent_did: row.get("agent_did"),
+            transaction_id: row.get("transaction_id"),
+            asset: row.get("asset"),
+            derivation_index: row.get::<i64, _>("derivation_index") as u32,
+            created_at: row.get("created_at"),
+        }
+    }
+
+    fn transaction_valuation_from_row(row: &sqlx::sqlite::SqliteRow) -> TransactionValuation {
+        TransactionValuation {
+            transaction_id: row.get("transaction_id"),
+            asset: row.get("asset"),
+            amount: row.get("amount"),
+            currency: row.get("currency"),
+            fiat_amount: row.get("fiat_amount"),
+            rate: row.get("rate"),
+            rate_source: row.get("rate_source"),
+            rate_timestamp: row.get("rate_timestamp"),
+            canonical_hash: row.get("canonical_hash"),
+            created_at: row.get("created_at"),
+        }
+    }
+
+    fn settlement_conversion_from_row(
+        row: &sqlx::sqlite::SqliteRow,
+    ) -> Result<SettlementConversion, StorageError> {
+        let status: String = row.get("status");
+        Ok(SettlementConversion {
+            transaction_id: row.get("transaction_id"),
+            requested_asset: row.get("requested_asset"),
+            requested_amount: row.get("requested_amount"),
+            settled_asset: row.get("settled_asset"),
+            settled_amount: row.get("settled_amount"),
+            rate: row.get("rate"),
+            rate_source: row.get("rate_source"),
+            expected_amount: row.get("expected_amount"),
+            slippage: row.get("slippage"),
+            max_slippage: row.get("max_slippage"),
+            status: ConversionStatus::try_from(status.as_str())
+                .map_err(StorageError::InvalidTransactionType)?,
+            created_at: row.get("created_at"),
+        })
+    }
+
+    fn address_sighting_from_row(row: &sqlx::sqlite::SqliteRow) -> AddressSighting {
+        AddressSighting {
+            address: row.get("address"),
+            transaction_id: row.get("transaction_id"),
+            provided_by: row.get("provided_by"),
+            originator_did: row.get("originator_did"),
+            beneficiary_did: row.get("beneficiary_did"),
+            seen_at: row.get("seen_at"),
+        }
+    }
+
+    fn connection_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<Connection, StorageError> {
+        Ok(Connection {
+            reference_id: row.get("reference_id"),
+            from_did: row.get("from_did"),
+            to_dids: serde_json::from_str(&row.get::<String, _>("to_dids"))?,
+            status: ConnectionStatus::try_from(row.get::<String, _>("status").as_str())
+                .map_err(StorageError::InvalidTransactionType)?,
+            message_json: serde_json::from_str(&row.get::<String, _>("message_json"))?,
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        })
+    }
+
+    fn counterparty_profile_from_row(
+        row: &sqlx::sqlite::SqliteRow,
+    ) -> Result<CounterpartyProfile, StorageError> {
+        Ok(CounterpartyProfile {
+            counterparty_did: row.get("counterparty_did"),
+            connection_id: row.get("connection_id"),
+            connection_status: ConnectionStatus::try_from(
+                row.get::<String, _>("connection_status").as_str(),
+            )
+            .map_err(StorageError::InvalidTransactionType)?,
+            authorization_url: row.get("authorization_url"),
+            rejection_reason: row.get("rejection_reason"),
+            features: row
+                .get::<Option<String>, _>("features")
+                .map(|v| serde_json::from_str(&v))
+                .transpose()?,
+            our_policies: serde_json::from_str(&row.get::<String, _>("our_policies"))?,
+            their_policies: serde_json::from_str(&row.get::<String, _>("their_policies"))?,
+            endpoint_verified: row.get("endpoint_verified"),
+            ping_rtt_ms: row
+                .get::<Option<i64>, _>("ping_rtt_ms")
+                .map(|rtt| rtt as u64),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        })
+    }
+
+    fn dead_letter_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<DeadLetter, StorageError> {
+        Ok(DeadLetter {
+            id: row.get("id"),
+            message_id: row.get("message_id"),
+            raw_message: row.get("raw_message"),
+            stage: ProcessingStage::try_from(row.get::<String, _>("stage").as_str())
+                .map_err(StorageError::InvalidTransactionType)?,
+            reason: row.get("reason"),
+            created_at: row.get("created_at"),
+        })
+    }
+
+    fn forwarded_message_from_row(
+        row: &sqlx::sqlite::SqliteRow,
+    ) -> Result<ForwardedMessage, StorageError> {
+        Ok(ForwardedMessage {
+            id: row.get("id"),
+            digest: row.get("digest"),
+            envelope_kind: row.get("envelope_kind"),
+            message_id: row.get("message_id"),
+            from_did: row.get("from_did"),
+            recipients: serde_json::from_str(&row.get::<String, _>("recipients"))?,
+            upstream_url: row.get("upstream_url"),
+            forwarded_by: serde_json::from_str(&row.get::<String, _>("forwarded_by"))?,
+            status: ForwardStatus::try_from(row.get::<String, _>("status").as_str())
+                .map_err(StorageError::InvalidTransactionType)?,
+            error: row.get("error"),
+            created_at: row.get("created_at"),
+        })
+    }
+
+    fn customer_merge_from_row(
+        row: &sqlx::sqlite::SqliteRow,
+    ) -> Result<CustomerMerge, StorageError> {
+        Ok(CustomerMerge {
+            id: row.get("id"),
+            agent_did: row.get("agent_did"),
+            primary_customer_id: row.get("primary_customer_id"),
+            duplicate_customer_id: row.get("duplicate_customer_id"),
+            confidence: row.get("confidence"),
+            signals: serde_json::from_str(&row.get::<String, _>("signals"))?,
+            status: MergeStatus::try_from(row.get::<String, _>("status").as_str())
+                .map_err(StorageError::InvalidTransactionType)?,
+            duplicate_snapshot: row
+                .get::<Option<String>, _>("duplicate_snapshot")
+                .map(|v| serde_json::from_str(&v))
+                .transpose()?,
+            created_at: row.get("created_at"),
+            resolved_at: row.get("resolved_at"),
+        })
+    }
+
+    fn mailbox_message_from_row(
+        row: &sqlx::sqlite::SqliteRow,
+    ) -> Result<MailboxMessage, StorageError> {
+        Ok(MailboxMessage {
+            seq: row.get("seq"),
+            recipient_did: row.get("recipient_did"),
+            sender_did: row.get("sender_did"),
+            message_id: row.get("message_id"),
+            message_text: row.get("message_text"),
+            delivery_id: row.get("delivery_id"),
+            status: MailboxStatus::try_from(row.get::<String, _>("status").as_str())
+                .map_err(StorageError::InvalidTransactionType)?,
+            expires_at: row.get("expires_at"),
+            created_at: row.get("created_at"),
+            fetched_at: row.get("fetched_at"),
+            delivered_at: row.get("delivered_at"),
+        })
+    }
+
+    fn authorization_challenge_from_row(
+        row: &sqlx::sqlite::SqliteRow,
+    ) -> Result<AuthorizationChallenge, StorageError> {
+        Ok(AuthorizationChallenge {
+            nonce: row.get("nonce"),
+            transaction_id: row.get("transaction_id"),
+            agent_did: row.get("agent_did"),
+            recipient_did: row.get("recipient_did"),
+            party_type: row.get("party_type"),
+            authorization_url: row.get("authorization_url"),
+            status: ChallengeStatus::try_from(row.get::<String, _>("status").as_str())
+                .map_err(StorageError::InvalidTransactionType)?,
+            message_id: row.get("message_id"),
+            expires_at: row.get("expires_at"),
+            created_at: row.get("created_at"),
+            redeemed_at: row.get("redeemed_at"),
+        })
+    }
+
+    fn pinned_did_document_from_row(
+        row: &sqlx::sqlite::SqliteRow,
+    ) -> Result<PinnedDidDocument, StorageError> {
+        Ok(PinnedDidDocument {
+            did_doc_hash: row.get("did_doc_hash"),
+            did: row.get("did"),
+            document: serde_json::from_str(&row.get::<String, _>("document_json"))?,
+            pinned_at: row.get("pinned_at"),
+        })
+    }
+
+    fn message_verification_from_row(row: &sqlx::sqlite::SqliteRow) -> MessageVerification {
+        MessageVerification {
+            id: row.get("id"),
+            message_id: row.get("message_id"),
+            signer_did: row.get("signer_did"),
+            kid: row.get("kid"),
+            algorithm: row.get("algorithm"),
+            did_doc_hash: row.get("did_doc_hash"),
+            verified_at: row.get("verified_at"),
+            created_at: row.get("created_at"),
+        }
+    }
+
+    fn transaction_participant_from_row(
+        row: &sqlx::sqlite::SqliteRow,
+    ) -> Result<TransactionParticipant, StorageError> {
+        Ok(TransactionParticipant {
+            id: row.get("id"),
+            transaction_id: row.get("transaction_id"),
+            agent_did: row.get("agent_did"),
+            role: row.get("role"),
+            for_parties: serde_json::from_str(&row.get::<String, _>("for_parties"))?,
+            added_by: row.get("added_by"),
+            added_in: row.get("added_in"),
+            added_at: row.get("added_at"),
+            removed_by: row.get("removed_by"),
+            removed_in: row.get("removed_in"),
+            removed_at: row.get("removed_at"),
+            replaced_by: row.get("replaced_by"),
+        })
+    }
+
+    fn draft_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<Draft, StorageError> {
+        Ok(Draft {
+            id: row.get("id"),
+            agent_did: row.get("agent_did"),
+            kind: DraftKind::try_from(row.get::<String, _>("kind").as_str())
+                .map_err(StorageError::InvalidTransactionType)?,
+            body: serde_json::from_str(&row.get::<String, _>("body"))?,
+            status: DraftStatus::try_from(row.get::<String, _>("status").as_str())
+                .map_err(StorageError::InvalidTransactionType)?,
+            validation_errors: serde_json::from_str(&row.get::<String, _>("validation_errors"))?,
+            expires_at: row.get("expires_at"),
+            message_id: row.get("message_id"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{transfer_body, transfer_message};
+    use tap_msg::message::transfer::Transfer;
+    use tap_msg::message::Party;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_storage_creation() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+
+        let _storage = Storage::new(Some(db_path)).await.unwrap();
+        // Just verify we can create a storage instance
+    }
+
+    #[tokio::test]
+    async fn test_erasing_again_redacts_data_written_since() {
+        let storage = Storage::new_in_memory().await.unwrap();
+        let now = storage.clock().now().to_rfc3339();
+        let mut customer = Customer {
+            id: "did:example:alice".to_string(),
+            agent_did: "did:example:agent".to_string(),
+            schema_type: SchemaType::Person,
+            given_name: Some("Alice".to_string()),
+            family_name: Some("Smith".to_string()),
+            display_name: Some("Alice Smith".to_string()),
+            legal_name: None,
+            lei_code: None,
+            mcc_code: None,
+            address_country: Some("DE".to_string()),
+            address_locality: None,
+            postal_code: None,
+            street_address: None,
+            profile: serde_json::json!({"@type": "Person", "givenName": "Alice"}),
+            ivms101_data: None,
+            verified_at: None,
+            created_at: now.clone(),
+            updated_at: now,
+        };
+        storage.upsert_customer(&customer).await.unwrap();
+        let erasure = storage
+            .erase_customer(&customer.id, Some("data subject request"))
+            .await
+            .unwrap()
+            .unwrap();
+
+        // Upserting the customer again leaves the tombstone as it is
+        customer.display_name = Some("Alice Jones".to_string());
+        storage.upsert_customer(&customer).await.unwrap();
+        let erased = storage.get_customer(&customer.id).await.unwrap().unwrap();
+        assert!(erased.display_name.is_none());
+        assert_eq!(erased.profile["erased"], true);
+
+        // Data written to the row by other means is redacted again
+        sqlx::query("UPDATE customers SET given_name = 'Alice', profile = '{}' WHERE id = ?1")
+            .bind(&customer.id)
+            .execute(storage.pool())
+            .await
+            .unwrap();
+        let again = storage
+            .erase_customer(&customer.id, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(again.erased_at, erasure.erased_at);
+        assert_eq!(again.reason.as_deref(), Some("data subject request"));
+        let erased = storage.get_customer(&customer.id).await.unwrap().unwrap();
+        assert!(erased.given_name.is_none());
+        assert_eq!(erased.profile["erased"], true);
+        assert_eq!(erased.get_name_hash(), erasure.name_hash);
+    }
+
+    #[tokio::test]
+    async fn test_storage_with_did() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let dir = tempdir().unwrap();
+        let tap_root = dir.path().to_path_buf();
+        let agent_did = "did:web:example.com";
+
+        let storage = Storage::new_with_did(agent_did, Some(tap_root.clone()))
+            .await
+            .unwrap();
+
+        // Verify the database was created in the expected location
+        let expected_path = tap_root.join("did_web_example.com").join("transactions.db");
+        assert!(
+            expected_path.exists(),
+            "Database file not created at expected path"
+        );
+
+        // Test that we can use the storage
+        let messages = storage.list_messages(10, 0, None).await.unwrap();
+        assert_eq!(messages.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_default_logs_dir() {
+        let dir = tempdir().unwrap();
+        let tap_root = dir.path().to_path_buf();
+
+        let logs_dir = Storage::default_logs_dir(Some(tap_root.clone()));
+        assert_eq!(logs_dir, tap_root.join("logs"));
+
+        // Test with no tap_root (should use home dir)
+        let default_logs = Storage::default_logs_dir(None);
+        assert!(default_logs.to_string_lossy().contains(".tap/logs"));
+    }
+
+    #[tokio::test]
+    async fn test_insert_and_retrieve_transaction() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let storage = Storage::new(Some(db_path)).await.unwrap();
+
+        // Create a test transfer message
+        let transfer_body = Transfer {
+            transaction_id: Some("test_transfer_123".to_string()),
+            originator: Some(Party::new("did:example:originator")),
+            beneficiary: Some(Party::new("did:example:beneficiary")),
+            asset: "eip155:1/erc20:0x0000000000000000000000000000000000000000"
+                .parse()
+                .unwrap(),
+            amount: "1000000000000000000".to_string(),
+            agents: vec![],
+            memo: None,
+            settlement_id: None,
+            expiry: None,
+            transaction_value: None,
+            connection_id: None,
+            metadata: Default::default(),
+        };
+
+        let message_id = "test_message_123";
+        let message = PlainMessage {
+            id: message_id.to_string(),
+            typ: "application/didcomm-plain+json".to_string(),
+            type_: "https://tap-protocol.io/messages/transfer/1.0".to_string(),
+            body: serde_json::to_value(&transfer_body).unwrap(),
+            from: "did:example:sender".to_string(),
+            to: vec!["did:example:receiver".to_string()],
+            thid: None,
+            pthid: None,
+            extra_headers: Default::default(),
+            attachments: None,
+            created_time: None,
+            expires_time: None,
+            from_prior: None,
+        };
+
+        // Insert transaction
+        storage.insert_transaction(&message).await.unwrap();
+
+        // Retrieve transaction
+        let retrieved = storage.get_transaction_by_id(message_id).await.unwrap();
+        assert!(retrieved.is_some(), "Transaction not found");
+
+        let tx = retrieved.unwrap();
+        assert_eq!(tx.reference_id, message_id);
+        assert_eq!(tx.transaction_type, TransactionType::Transfer);
+        assert_eq!(tx.status, TransactionStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn test_canonical_hash_identifies_copies() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let storage = Storage::new(Some(db_path.clone())).await.unwrap();
+
+        let body = serde_json::json!({
+            "asset": "eip155:1/slip44:60",
+            "amount": "1.5",
+            "originator": {"@id": "did:example:originator"},
+            "agents": [],
+        });
+        let original = PlainMessage::new(
+            "original".to_string(),
+            "https://tap.rsvp/schema/1.0#Transfer".to_string(),
+            body.clone(),
+            "did:example:sender".to_string(),
+        )
+        .with_recipient("did:example:receiver");
+        let mut copy = original.clone();
+        copy.id = "resent-copy".to_string();
+        storage.insert_transaction(&original).await.unwrap();
+        storage.insert_transaction(&copy).await.unwrap();
+
+        let hash = canonical_transaction_hash(&body, "did:example:sender");
+        let stored = storage
+            .get_transaction_by_id("resent-copy")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.canonical_hash.as_deref(), Some(hash.as_str()));
+        let found = storage
+            .get_transaction_by_canonical_hash(&hash.to_uppercase())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.reference_id, original.id);
+        assert!(storage
+            .get_transaction_by_canonical_hash("00")
+            .await
+            .unwrap()
+            .is_none());
+
+        // Transactions stored before the hash was recorded get it on open
+        sqlx::query("UPDATE transactions SET canonical_hash = NULL")
+            .execute(storage.pool())
+            .await
+            .unwrap();
+        storage.pool().close().await;
+        let storage = Storage::new(Some(db_path)).await.unwrap();
+        let backfilled = storage.list_transactions(10, 0).await.unwrap();
+        assert_eq!(backfilled.len(), 2);
+        assert!(backfilled
+            .iter()
+            .all(|tx| tx.canonical_hash.as_deref() == Some(hash.as_str())));
+    }
+
+    #[tokio::test]
+    async fn test_log_and_retrieve_messages() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let storage = Storage::new(Some(db_path)).await.unwrap();
+
+        // Create test messages of different types
+        let connect_message = PlainMessage {
+            id: "msg_connect_123".to_string(),
+            typ: "application/didcomm-plain+json".to_string(),
+            type_: "https://tap-protocol.io/messages/connect/1.0".to_string(),
+            body: serde_json::json!({"constraints": ["test"]}),
+            from: "did:example:alice".to_string(),
+            to: vec!["did:example:bob".to_string()],
+            thid: Some("thread_123".to_string()),
+            pthid: None,
+            extra_headers: Default::default(),
+            attachments: None,
+            created_time: None,
+            expires_time: None,
+            from_prior: None,
+        };
+
+        let authorize_message = PlainMessage {
+            id: "msg_auth_123".to_string(),
+            typ: "application/didcomm-plain+json".to_string(),
+            type_: "https://tap-protocol.io/messages/authorize/1.0".to_string(),
+            body: serde_json::json!({"transaction_id": "test_transfer_123"}),
+            from: "did:example:bob".to_string(),
+            to: vec!["did:example:alice".to_string()],
+            thid: Some("thread_123".to_string()),
+            pthid: None,
+            extra_headers: Default::default(),
+            attachments: None,
+            created_time: None,
+            expires_time: None,
+            from_prior: None,
+        };
+
+        // Log messages
+        storage
+            .log_message(&connect_message, MessageDirection::Incoming)
+            .await
+            .unwrap();
+        storage
+            .log_message(&authorize_message, MessageDirection::Outgoing)
+            .await
+            .unwrap();
+
+        // Retrieve specific message
+        let retrieved = storage.get_message_by_id("msg_connect_123").await.unwrap();
+        assert!(retrieved.is_some());
+        let msg = retrieved.unwrap();
+        assert_eq!(msg.message_id, "msg_connect_123");
+        assert_eq!(msg.direction, MessageDirection::Incoming);
+
+        // List all messages
+        let all_messages = storage.list_messages(10, 0, None).await.unwrap();
+        assert_eq!(all_messages.len(), 2);
+
+        // List only incoming messages
+        let incoming_messages = storage
+            .list_messages(10, 0, Some(MessageDirection::Incoming))
+            .await
+            .unwrap();
+        assert_eq!(incoming_messages.len(), 1);
+        assert_eq!(incoming_messages[0].message_id, "msg_connect_123");
+
+        // Test duplicate message handling (should not error)
+        storage
+            .log_message(&connect_message, MessageDirection::Incoming)
+            .await
+            .unwrap();
+        let all_messages_after = storage.list_messages(10, 0, None).await.unwrap();
+        assert_eq!(all_messages_after.len(), 2); // Should still be 2, not 3
+    }
+
+    // -----------------------------------------------------------------------
+    // Decision log tests
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn test_insert_decision() {
+        let storage = Storage::new_in_memory().await.unwrap();
+
+        let context = serde_json::json!({
+            "transaction_state": "Received",
+            "pending_agents": ["did:key:z6MkAgent1"],
+            "transaction": {
+                "type": "transfer",
+                "asset": "eip155:1/slip44:60",
+                "amount": "100"
+            }
+        });
+
+        let id = storage
+            .insert_decision(
+                "txn-001",
+                "did:key:z6MkAgent1",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+
+        assert!(id > 0);
+
+        // Verify the entry was created with correct defaults
+        let entry = storage.get_decision_by_id(id).await.unwrap().unwrap();
+        assert_eq!(entry.transaction_id, "txn-001");
+        assert_eq!(entry.agent_did, "did:key:z6MkAgent1");
+        assert_eq!(entry.decision_type, DecisionType::AuthorizationRequired);
+        assert_eq!(entry.status, DecisionStatus::Pending);
+        assert!(entry.resolution.is_none());
+        assert!(entry.resolution_detail.is_none());
+        assert!(entry.delivered_at.is_none());
+        assert!(entry.resolved_at.is_none());
+        assert_eq!(entry.context_json["transaction"]["type"], "transfer");
+    }
+
+    #[tokio::test]
+    async fn test_insert_multiple_decision_types() {
+        let storage = Storage::new_in_memory().await.unwrap();
+
+        let context = serde_json::json!({"transaction_id": "txn-002"});
+
+        let id1 = storage
+            .insert_decision(
+                "txn-002",
+                "did:key:z6MkAgent1",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+
+        let id2 = storage
+            .insert_decision(
+                "txn-002",
+                "did:key:z6MkAgent1",
+                DecisionType::PolicySatisfactionRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+
+        let id3 = storage
+            .insert_decision(
+                "txn-003",
+                "did:key:z6MkAgent1",
+                DecisionType::SettlementRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+
+        assert!(id1 < id2);
+        assert!(id2 < id3);
+
+        let e1 = storage.get_decision_by_id(id1).await.unwrap().unwrap();
+        let e2 = storage.get_decision_by_id(id2).await.unwrap().unwrap();
+        let e3 = storage.get_decision_by_id(id3).await.unwrap().unwrap();
+
+        assert_eq!(e1.decision_type, DecisionType::AuthorizationRequired);
+        assert_eq!(e2.decision_type, DecisionType::PolicySatisfactionRequired);
+        assert_eq!(e3.decision_type, DecisionType::SettlementRequired);
+    }
+
+    #[tokio::test]
+    async fn test_update_decision_status_to_delivered() {
+        let storage = Storage::new_in_memory().await.unwrap();
+
+        let context = serde_json::json!({"info": "test"});
+        let id = storage
+            .insert_decision(
+                "txn-010",
+                "did:key:z6MkAgent1",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+
+        // Mark as delivered
+        storage
+            .update_decision_status(id, DecisionStatus::Delivered, None, None)
+            .await
+            .unwrap();
+
+        let entry = storage.get_decision_by_id(id).await.unwrap().unwrap();
+        assert_eq!(entry.status, DecisionStatus::Delivered);
+        assert!(entry.delivered_at.is_some());
+        assert!(entry.resolved_at.is_none());
+        assert!(entry.resolution.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_update_decision_status_to_resolved() {
+        let storage = Storage::new_in_memory().await.unwrap();
+
+        let context = serde_json::json!({"info": "test"});
+        let id = storage
+            .insert_decision(
+                "txn-011",
+                "did:key:z6MkAgent1",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+
+        // First deliver
+        storage
+            .update_decision_status(id, DecisionStatus::Delivered, None, None)
+            .await
+            .unwrap();
+
+        // Then resolve with action
+        let detail = serde_json::json!({"settlement_address": "eip155:1:0xABC"});
+        storage
+            .update_decision_status(
+                id,
+                DecisionStatus::Resolved,
+                Some("authorize"),
+                Some(&detail),
+            )
+            .await
+            .unwrap();
+
+        let entry = storage.get_decision_by_id(id).await.unwrap().unwrap();
+        assert_eq!(entry.status, DecisionStatus::Resolved);
+        assert_eq!(entry.resolution.as_deref(), Some("authorize"));
+        assert!(entry.resolved_at.is_some());
+        assert!(entry.delivered_at.is_some());
+        assert_eq!(
+            entry.resolution_detail.unwrap()["settlement_address"],
+            "eip155:1:0xABC"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_decisions_all() {
+        let storage = Storage::new_in_memory().await.unwrap();
+
+        let context = serde_json::json!({"info": "test"});
+
+        // Insert 3 decisions
+        storage
+            .insert_decision(
+                "txn-020",
+                "did:key:z6MkAgent1",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+        storage
+            .insert_decision(
+                "txn-021",
+                "did:key:z6MkAgent2",
+                DecisionType::SettlementRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+        storage
+            .insert_decision(
+                "txn-022",
+                "did:key:z6MkAgent1",
+                DecisionType::PolicySatisfactionRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+
+        // List all
+        let all = storage.list_decisions(None, None, None, 100).await.unwrap();
+        assert_eq!(all.len(), 3);
+
+        // Verify ordering by id ASC
+        assert!(all[0].id < all[1].id);
+        assert!(all[1].id < all[2].id);
+    }
+
+    #[tokio::test]
+    async fn test_list_decisions_by_agent() {
+        let storage = Storage::new_in_memory().await.unwrap();
+
+        let context = serde_json::json!({"info": "test"});
+
+        storage
+            .insert_decision(
+                "txn-030",
+                "did:key:z6MkAgent1",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+        storage
+            .insert_decision(
+                "txn-031",
+                "did:key:z6MkAgent2",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+        storage
+            .insert_decision(
+                "txn-032",
+                "did:key:z6MkAgent1",
+                DecisionType::SettlementRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+
+        let agent1 = storage
+            .list_decisions(Some("did:key:z6MkAgent1"), None, None, 100)
+            .await
+            .unwrap();
+        assert_eq!(agent1.len(), 2);
+
+        let agent2 = storage
+            .list_decisions(Some("did:key:z6MkAgent2"), None, None, 100)
+            .await
+            .unwrap();
+        assert_eq!(agent2.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_list_decisions_by_status() {
+        let storage = Storage::new_in_memory().await.unwrap();
+
+        let context = serde_json::json!({"info": "test"});
+
+        let id1 = storage
+            .insert_decision(
+                "txn-040",
+                "did:key:z6MkAgent1",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+        let _id2 = storage
+            .insert_decision(
+                "txn-041",
+                "did:key:z6MkAgent1",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+
+        // Mark first as delivered
+        storage
+            .update_decision_status(id1, DecisionStatus::Delivered, None, None)
+            .await
+            .unwrap();
+
+        let pending = storage
+            .list_decisions(None, Some(DecisionStatus::Pending), None, 100)
+            .await
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].transaction_id, "txn-041");
+
+        let delivered = storage
+            .list_decisions(None, Some(DecisionStatus::Delivered), None, 100)
+            .await
+            .unwrap();
+        assert_eq!(delivered.len(), 1);
+        assert_eq!(delivered[0].transaction_id, "txn-040");
+    }
+
+    #[tokio::test]
+    async fn test_list_decisions_since_id() {
+        let storage = Storage::new_in_memory().await.unwrap();
+
+        let context = serde_json::json!({"info": "test"});
+
+        let id1 = storage
+            .insert_decision(
+                "txn-050",
+                "did:key:z6MkAgent1",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+        let id2 = storage
+            .insert_decision(
+                "txn-051",
+                "did:key:z6MkAgent1",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+        let _id3 = storage
+            .insert_decision(
+                "txn-052",
+                "did:key:z6MkAgent1",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+
+        // Get decisions since id1 (should return id2 and id3)
+        let since = storage
+            .list_decisions(None, None, Some(id1), 100)
+            .await
+            .unwrap();
+        assert_eq!(since.len(), 2);
+        assert_eq!(since[0].id, id2);
+    }
+
+    #[tokio::test]
+    async fn test_list_decisions_with_limit() {
+        let storage = Storage::new_in_memory().await.unwrap();
+
+        let context = serde_json::json!({"info": "test"});
+
+        for i in 0..5 {
+            storage
+                .insert_decision(
+                    &format!("txn-06{}", i),
+                    "did:key:z6MkAgent1",
+                    DecisionType::AuthorizationRequired,
+                    &context,
+                )
+                .await
+                .unwrap();
+        }
+
+        let limited = storage.list_decisions(None, None, None, 3).await.unwrap();
+        assert_eq!(limited.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_expire_decisions_for_transaction() {
+        let storage = Storage::new_in_memory().await.unwrap();
+
+        let context = serde_json::json!({"info": "test"});
+
+        // Insert decisions for two transactions
+        let id1 = storage
+            .insert_decision(
+                "txn-070",
+                "did:key:z6MkAgent1",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+        let id2 = storage
+            .insert_decision(
+                "txn-070",
+                "did:key:z6MkAgent2",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+        let id3 = storage
+            .insert_decision(
+                "txn-071",
+                "did:key:z6MkAgent1",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+
+        // Mark id2 as delivered (should still be expired)
+        storage
+            .update_decision_status(id2, DecisionStatus::Delivered, None, None)
+            .await
+            .unwrap();
+
+        // Expire decisions for txn-070
+        let expired_count = storage
+            .expire_decisions_for_transaction("txn-070")
+            .await
+            .unwrap();
+        assert_eq!(expired_count, 2);
+
+        // Verify statuses
+        let e1 = storage.get_decision_by_id(id1).await.unwrap().unwrap();
+        assert_eq!(e1.status, DecisionStatus::Expired);
+
+        let e2 = storage.get_decision_by_id(id2).await.unwrap().unwrap();
+        assert_eq!(e2.status, DecisionStatus::Expired);
+
+        // txn-071 should not be affected
+        let e3 = storage.get_decision_by_id(id3).await.unwrap().unwrap();
+        assert_eq!(e3.status, DecisionStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn test_expire_does_not_affect_resolved() {
+        let storage = Storage::new_in_memory().await.unwrap();
+
+        let context = serde_json::json!({"info": "test"});
+
+        let id1 = storage
+            .insert_decision(
+                "txn-080",
+                "did:key:z6MkAgent1",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+        let id2 = storage
+            .insert_decision(
+                "txn-080",
+                "did:key:z6MkAgent2",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+
+        // Resolve id1
+        storage
+            .update_decision_status(id1, DecisionStatus::Resolved, Some("authorize"), None)
+            .await
+            .unwrap();
+
+        // Expire txn-080
+        let expired_count = storage
+            .expire_decisions_for_transaction("txn-080")
+            .await
+            .unwrap();
+        assert_eq!(expired_count, 1); // Only id2 should be expired
+
+        let e1 = storage.get_decision_by_id(id1).await.unwrap().unwrap();
+        assert_eq!(e1.status, DecisionStatus::Resolved);
+
+        let e2 = storage.get_decision_by_id(id2).await.unwrap().unwrap();
+        assert_eq!(e2.status, DecisionStatus::Expired);
+    }
+
+    #[tokio::test]
+    async fn test_get_decision_by_id_not_found() {
+        let storage = Storage::new_in_memory().await.unwrap();
+
+        let result = storage.get_decision_by_id(99999).await.unwrap();
+        assert!(result.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_list_decisions_combined_filters() {
+        let storage = Storage::new_in_memory().await.unwrap();
+
+        let context = serde_json::json!({"info": "test"});
+
+        let id1 = storage
+            .insert_decision(
+                "txn-090",
+                "did:key:z6MkAgent1",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+        storage
+            .insert_decision(
+                "txn-091",
+                "did:key:z6MkAgent2",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+        storage
+            .insert_decision(
+                "txn-092",
+                "did:key:z6MkAgent1",
+                DecisionType::SettlementRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+
+        // Mark first as delivered
+        storage
+            .update_decision_status(id1, DecisionStatus::Delivered, None, None)
+            .await
+            .unwrap();
+
+        // Filter by agent + status
+        let results = storage
+            .list_decisions(
+                Some("did:key:z6MkAgent1"),
+                Some(DecisionStatus::Pending),
+                None,
+                100,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].transaction_id, "txn-092");
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tap_msg::message::transfer::Transfer;
-    use tap_msg::message::Party;
-    use tempfile::tempdir;
 
     #[tokio::test]
-    async fn test_storage_creation() {
-        let dir = tempdir().unwrap();
-        let db_path = dir.path().join("test.db");
+    async fn test_resolve_decisions_for_transaction() {
+        let storage = Storage::new_in_memory().await.unwrap();
 
-        let _storage = Storage::new(Some(db_path)).await.unwrap();
-        // Just verify we can create a storage instance
+        let context = serde_json::json!({"info": "test"});
+
+        // Insert auth and settlement decisions for same transaction
+        let id1 = storage
+            .insert_decision(
+                "txn-100",
+                "did:key:z6MkAgent1",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+        let id2 = storage
+            .insert_decision(
+                "txn-100",
+                "did:key:z6MkAgent1",
+                DecisionType::SettlementRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+
+        // Resolve only authorization_required decisions
+        let resolved = storage
+            .resolve_decisions_for_transaction(
+                "txn-100",
+                "authorize",
+                Some(DecisionType::AuthorizationRequired),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resolved, 1);
+
+        let e1 = storage.get_decision_by_id(id1).await.unwrap().unwrap();
+        assert_eq!(e1.status, DecisionStatus::Resolved);
+        assert_eq!(e1.resolution.as_deref(), Some("authorize"));
+
+        // Settlement decision should still be pending
+        let e2 = storage.get_decision_by_id(id2).await.unwrap().unwrap();
+        assert_eq!(e2.status, DecisionStatus::Pending);
     }
 
     #[tokio::test]
-    async fn test_storage_with_did() {
-        let _ = env_logger::builder().is_test(true).try_init();
+    async fn test_resolve_decisions_all_types() {
+        let storage = Storage::new_in_memory().await.unwrap();
 
-        let dir = tempdir().unwrap();
-        let tap_root = dir.path().to_path_buf();
-        let agent_did = "did:web:example.com";
+        let context = serde_json::json!({"info": "test"});
 
-        let storage = Storage::new_with_did(agent_did, Some(tap_root.clone()))
+        let id1 = storage
+            .insert_decision(
+                "txn-101",
+                "did:key:z6MkAgent1",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+        let id2 = storage
+            .insert_decision(
+                "txn-101",
+                "did:key:z6MkAgent1",
+                DecisionType::SettlementRequired,
+                &context,
+            )
             .await
             .unwrap();
 
-        // Verify the database was created in the expected location
-        let expected_path = tap_root.join("did_web_example.com").join("transactions.db");
-        assert!(
-            expected_path.exists(),
-            "Database file not created at expected path"
-        );
+        // Resolve all decisions (no type filter)
+        let resolved = storage
+            .resolve_decisions_for_transaction("txn-101", "reject", None)
+            .await
+            .unwrap();
+        assert_eq!(resolved, 2);
 
-        // Test that we can use the storage
-        let messages = storage.list_messages(10, 0, None).await.unwrap();
-        assert_eq!(messages.len(), 0);
+        let e1 = storage.get_decision_by_id(id1).await.unwrap().unwrap();
+        assert_eq!(e1.status, DecisionStatus::Resolved);
+        assert_eq!(e1.resolution.as_deref(), Some("reject"));
+
+        let e2 = storage.get_decision_by_id(id2).await.unwrap().unwrap();
+        assert_eq!(e2.status, DecisionStatus::Resolved);
+        assert_eq!(e2.resolution.as_deref(), Some("reject"));
     }
 
     #[tokio::test]
-    async fn test_default_logs_dir() {
-        let dir = tempdir().unwrap();
-        let tap_root = dir.path().to_path_buf();
+    async fn test_resolve_does_not_affect_already_resolved() {
+        let storage = Storage::new_in_memory().await.unwrap();
 
-        let logs_dir = Storage::default_logs_dir(Some(tap_root.clone()));
-        assert_eq!(logs_dir, tap_root.join("logs"));
+        let context = serde_json::json!({"info": "test"});
 
-        // Test with no tap_root (should use home dir)
-        let default_logs = Storage::default_logs_dir(None);
-        assert!(default_logs.to_string_lossy().contains(".tap/logs"));
+        let id1 = storage
+            .insert_decision(
+                "txn-102",
+                "did:key:z6MkAgent1",
+                DecisionType::AuthorizationRequired,
+                &context,
+            )
+            .await
+            .unwrap();
+
+        // Resolve manually first
+        storage
+            .update_decision_status(id1, DecisionStatus::Resolved, Some("authorize"), None)
+            .await
+            .unwrap();
+
+        // Try to resolve again via the bulk method
+        let resolved = storage
+            .resolve_decisions_for_transaction("txn-102", "reject", None)
+            .await
+            .unwrap();
+        assert_eq!(resolved, 0); // Already resolved, should not change
+
+        let e1 = storage.get_decision_by_id(id1).await.unwrap().unwrap();
+        assert_eq!(e1.resolution.as_deref(), Some("authorize")); // Original resolution preserved
     }
 
-    #[tokio::test]
-    async fn test_insert_and_retrieve_transaction() {
-        let _ = env_logger::builder().is_test(true).try_init();
+    // -----------------------------------------------------------------------
+    // Review queue tests
+    // -----------------------------------------------------------------------
 
-        let dir = tempdir().unwrap();
-        let db_path = dir.path().join("test.db");
-        let storage = Storage::new(Some(db_path)).await.unwrap();
+    #[tokio::test]
+    async fn test_review_queue_lifecycle() {
+        let storage = Storage::new_in_memory().await.unwrap();
+        let details = serde_json::json!({"aggregate_amount": 1100.0});
 
-        // Create a test transfer message
-        let transfer_body = Transfer {
-            transaction_id: Some("test_transfer_123".to_string()),
-            originator: Some(Party::new("did:example:originator")),
-            beneficiary: Some(Party::new("did:example:beneficiary")),
-            asset: "eip155:1/erc20:0x0000000000000000000000000000000000000000"
-                .parse()
-                .unwrap(),
-            amount: "1000000000000000000".to_string(),
-            agents: vec![],
-            memo: None,
-            settlement_id: None,
-            expiry: None,
-            transaction_value: None,
-            connection_id: None,
-            metadata: Default::default(),
-        };
+        let id1 = storage
+            .insert_review_item(
+                "txn-200",
+                "did:key:z6MkAgent1",
+                "daily-limit",
+                "Velocity limit exceeded",
+                &details,
+            )
+            .await
+            .unwrap();
+        let id2 = storage
+            .insert_review_item(
+                "txn-201",
+                "did:key:z6MkAgent1",
+                "daily-limit",
+                "Velocity limit exceeded",
+                &details,
+            )
+            .await
+            .unwrap();
+        storage
+            .insert_review_item(
+                "txn-201",
+                "did:key:z6MkAgent2",
+                "daily-limit",
+                "Velocity limit exceeded",
+                &details,
+            )
+            .await
+            .unwrap();
 
-        let message_id = "test_message_123";
-        let message = PlainMessage {
-            id: message_id.to_string(),
-            typ: "application/didcomm-plain+json".to_string(),
-            type_: "https://tap-protocol.io/messages/transfer/1.0".to_string(),
-            body: serde_json::to_value(&transfer_body).unwrap(),
-            from: "did:example:sender".to_string(),
-            to: vec!["did:example:receiver".to_string()],
-            thid: None,
-            pthid: None,
-            extra_headers: Default::default(),
-            attachments: None,
-            created_time: None,
-            expires_time: None,
-            from_prior: None,
-        };
+        let pending = storage
+            .list_review_items(
+                Some("did:key:z6MkAgent1"),
+                Some(ReviewStatus::Pending),
+                10,
+                0,
+            )
+            .await
+            .unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].id, id2);
+        assert_eq!(pending[1].details["aggregate_amount"], 1100.0);
+
+        assert!(storage
+            .complete_review_item(
+                id1,
+                ReviewStatus::Approved,
+                "alice@example.com",
+                Some("Known customer"),
+                Some("msg-auth-1"),
+            )
+            .await
+            .unwrap());
 
-        // Insert transaction
-        storage.insert_transaction(&message).await.unwrap();
+        // A completed review cannot be acted on again
+        assert!(!storage
+            .complete_review_item(id1, ReviewStatus::Rejected, "bob@example.com", None, None)
+            .await
+            .unwrap());
 
-        // Retrieve transaction
-        let retrieved = storage.get_transaction_by_id(message_id).await.unwrap();
-        assert!(retrieved.is_some(), "Transaction not found");
+        let item = storage.get_review_item(id1).await.unwrap().unwrap();
+        assert_eq!(item.status, ReviewStatus::Approved);
+        assert_eq!(item.reviewer.as_deref(), Some("alice@example.com"));
+        assert_eq!(item.review_note.as_deref(), Some("Known customer"));
+        assert_eq!(item.message_id.as_deref(), Some("msg-auth-1"));
+        assert!(item.reviewed_at.is_some());
 
-        let tx = retrieved.unwrap();
-        assert_eq!(tx.reference_id, message_id);
-        assert_eq!(tx.transaction_type, TransactionType::Transfer);
-        assert_eq!(tx.status, TransactionStatus::Pending);
+        let expired = storage
+            .expire_review_items_for_transaction("txn-201")
+            .await
+            .unwrap();
+        assert_eq!(expired, 2);
+        assert!(storage
+            .list_review_items(None, Some(ReviewStatus::Pending), 10, 0)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(storage.get_review_item(9999).await.unwrap().is_none());
     }
 
-    #[tokio::test]
-    async fn test_log_and_retrieve_messages() {
-        let _ = env_logger::builder().is_test(true).try_init();
-
-        let dir = tempdir().unwrap();
-        let db_path = dir.path().join("test.db");
-        let storage = Storage::new(Some(db_path)).await.unwrap();
+    // -----------------------------------------------------------------------
+    // Message verification tests
+    // -----------------------------------------------------------------------
 
-        // Create test messages of different types
-        let connect_message = PlainMessage {
-            id: "msg_connect_123".to_string(),
+    fn thread_message(id: &str, thid: Option<&str>) -> PlainMessage {
+        PlainMessage {
+            id: id.to_string(),
             typ: "application/didcomm-plain+json".to_string(),
-            type_: "https://tap-protocol.io/messages/connect/1.0".to_string(),
-            body: serde_json::json!({"constraints": ["test"]}),
+            type_: "https://tap.rsvp/schema/1.0#Authorize".to_string(),
+            body: serde_json::json!({}),
             from: "did:example:alice".to_string(),
             to: vec!["did:example:bob".to_string()],
-            thid: Some("thread_123".to_string()),
+            thid: thid.map(String::from),
             pthid: None,
             extra_headers: Default::default(),
             attachments: None,
             created_time: None,
             expires_time: None,
             from_prior: None,
-        };
+        }
+    }
 
-        let authorize_message = PlainMessage {
-            id: "msg_auth_123".to_string(),
-            typ: "application/didcomm-plain+json".to_string(),
-            type_: "https://tap-protocol.io/messages/authorize/1.0".to_string(),
-            body: serde_json::json!({"transaction_id": "test_transfer_123"}),
-            from: "did:example:bob".to_string(),
-            to: vec!["did:example:alice".to_string()],
-            thid: Some("thread_123".to_string()),
-            pthid: None,
-            extra_headers: Default::default(),
-            attachments: None,
-            created_time: None,
-            expires_time: None,
-            from_prior: None,
+    fn did_document(did: &str) -> tap_agent::did::DIDDoc {
+        tap_agent::did::DIDDoc {
+            id: did.to_string(),
+            verification_method: vec![],
+            authentication: vec![],
+            key_agreement: vec![],
+            assertion_method: vec![],
+            capability_invocation: vec![],
+            capability_delegation: vec![],
+            service: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transaction_timeline_with_verifications() {
+        let storage = Storage::new_in_memory().await.unwrap();
+        let verification = JwsVerification {
+            kid: "did:example:alice#key-1".to_string(),
+            alg: "EdDSA".to_string(),
+            signer_did: "did:example:alice".to_string(),
+            did_doc_hash: "ab".repeat(32),
+            did_doc: did_document("did:example:alice"),
+            verified_at: "2026-01-01T00:00:00Z".to_string(),
         };
 
-        // Log messages
+        for message in [
+            thread_message("tx-1", None),
+            thread_message("auth-1", Some("tx-1")),
+            thread_message("other-1", Some("tx-2")),
+        ] {
+            storage
+                .log_message(&message, MessageDirection::Incoming)
+                .await
+                .unwrap();
+        }
         storage
-            .log_message(&connect_message, MessageDirection::Incoming)
+            .insert_message_verification("auth-1", &verification)
             .await
             .unwrap();
         storage
-            .log_message(&authorize_message, MessageDirection::Outgoing)
+            .insert_message_verification("other-1", &verification)
             .await
             .unwrap();
 
-        // Retrieve specific message
-        let retrieved = storage.get_message_by_id("msg_connect_123").await.unwrap();
-        assert!(retrieved.is_some());
-        let msg = retrieved.unwrap();
-        assert_eq!(msg.message_id, "msg_connect_123");
-        assert_eq!(msg.direction, MessageDirection::Incoming);
+        let recorded = storage.get_message_verifications("auth-1").await.unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].kid, "did:example:alice#key-1");
+        assert_eq!(recorded[0].algorithm, "EdDSA");
+        assert_eq!(recorded[0].verified_at, "2026-01-01T00:00:00Z");
+
+        let timeline = storage.get_transaction_timeline("tx-1").await.unwrap();
+        assert_eq!(timeline.len(), 2);
+        assert_eq!(timeline[0].message.message_id, "tx-1");
+        assert!(timeline[0].verifications.is_empty());
+        assert_eq!(timeline[1].message.message_id, "auth-1");
+        assert_eq!(timeline[1].verifications.len(), 1);
+        assert_eq!(timeline[1].verifications[0].did_doc_hash, "ab".repeat(32));
+
+        assert!(storage
+            .get_transaction_timeline("unknown")
+            .await
+            .unwrap()
+            .is_empty());
+    }
 
-        // List all messages
-        let all_messages = storage.list_messages(10, 0, None).await.unwrap();
-        assert_eq!(all_messages.len(), 2);
+    #[tokio::test]
+    async fn test_timeline_shows_participant_changes() {
+        use tap_msg::message::Agent;
 
-        // List only incoming messages
-        let incoming_messages = storage
-            .list_messages(10, 0, Some(MessageDirection::Incoming))
+        let storage = Storage::new_in_memory().await.unwrap();
+        for message in [
+            thread_message("tx-1", None),
+            thread_message("replace-1", Some("tx-1")),
+        ] {
+            storage
+                .log_message(&message, MessageDirection::Incoming)
+                .await
+                .unwrap();
+        }
+        let original = Agent::new("did:example:wallet1", "SourceAddress", "did:example:alice");
+        let replacement = Agent::new("did:example:wallet2", "SourceAddress", "did:example:alice");
+        assert!(storage
+            .add_transaction_participant("tx-1", &original, "did:example:alice", "tx-1")
+            .await
+            .unwrap());
+        // Processing the same message again does not add the agent twice
+        assert!(!storage
+            .add_transaction_participant("tx-1", &original, "did:example:alice", "tx-1")
+            .await
+            .unwrap());
+        assert!(storage
+            .remove_transaction_participant(
+                "tx-1",
+                "did:example:wallet1",
+                "did:example:alice",
+                "replace-1",
+                Some("did:example:wallet2"),
+            )
+            .await
+            .unwrap());
+        storage
+            .add_transaction_participant("tx-1", &replacement, "did:example:alice", "replace-1")
             .await
             .unwrap();
-        assert_eq!(incoming_messages.len(), 1);
-        assert_eq!(incoming_messages[0].message_id, "msg_connect_123");
 
-        // Test duplicate message handling (should not error)
-        storage
-            .log_message(&connect_message, MessageDirection::Incoming)
+        let timeline = storage.get_transaction_timeline("tx-1").await.unwrap();
+        assert_eq!(timeline.len(), 2);
+        assert_eq!(timeline[0].participants_added.len(), 1);
+        assert_eq!(
+            timeline[0].participants_added[0].agent_did,
+            "did:example:wallet1"
+        );
+        assert!(timeline[0].participants_removed.is_empty());
+        assert_eq!(
+            timeline[1].participants_added[0].agent_did,
+            "did:example:wallet2"
+        );
+        assert_eq!(
+            timeline[1].participants_removed[0].replaced_by.as_deref(),
+            Some("did:example:wallet2")
+        );
+
+        // A removed agent can be added again
+        assert!(storage
+            .add_transaction_participant("tx-1", &original, "did:example:bob", "add-1")
+            .await
+            .unwrap());
+        let current = storage
+            .get_transaction_participants("tx-1", false)
             .await
             .unwrap();
-        let all_messages_after = storage.list_messages(10, 0, None).await.unwrap();
-        assert_eq!(all_messages_after.len(), 2); // Should still be 2, not 3
+        assert_eq!(current.len(), 2);
+        assert!(current.iter().all(|p| p.is_current()));
     }
 
-    // -----------------------------------------------------------------------
-    // Decision log tests
-    // -----------------------------------------------------------------------
-
     #[tokio::test]
-    async fn test_insert_decision() {
+    async fn test_did_documents_pinned_per_transaction() {
         let storage = Storage::new_in_memory().await.unwrap();
+        for message in [
+            thread_message("tx-1", None),
+            thread_message("auth-1", Some("tx-1")),
+            thread_message("settle-1", Some("tx-1")),
+            thread_message("tx-2", None),
+        ] {
+            storage
+                .log_message(&message, MessageDirection::Incoming)
+                .await
+                .unwrap();
+        }
 
-        let context = serde_json::json!({
-            "transaction_state": "Received",
-            "pending_agents": ["did:key:z6MkAgent1"],
-            "transaction": {
-                "type": "transfer",
-                "asset": "eip155:1/slip44:60",
-                "amount": "100"
-            }
-        });
-
-        let id = storage
-            .insert_decision(
-                "txn-001",
-                "did:key:z6MkAgent1",
-                DecisionType::AuthorizationRequired,
-                &context,
+        let verification = |message_id: &str, did: &str, hash: &str| {
+            (
+                message_id.to_string(),
+                JwsVerification {
+                    kid: format!("{}#key-1", did),
+                    alg: "EdDSA".to_string(),
+                    signer_did: did.to_string(),
+                    did_doc_hash: hash.to_string(),
+                    did_doc: did_document(did),
+                    verified_at: "2026-01-01T00:00:00Z".to_string(),
+                },
             )
+        };
+        // The same document verifying two messages is pinned once
+        for (message_id, v) in [
+            verification("tx-1", "did:example:alice", "hash-alice"),
+            verification("settle-1", "did:example:alice", "hash-alice"),
+            verification("auth-1", "did:example:bob", "hash-bob"),
+            verification("tx-2", "did:example:carol", "hash-carol"),
+        ] {
+            storage
+                .insert_message_verification(&message_id, &v)
+                .await
+                .unwrap();
+        }
+
+        let pinned = storage
+            .get_pinned_did_document("hash-bob")
             .await
+            .unwrap()
             .unwrap();
+        assert_eq!(pinned.did, "did:example:bob");
+        assert_eq!(pinned.document["id"], "did:example:bob");
+        assert!(storage
+            .get_pinned_did_document("hash-unknown")
+            .await
+            .unwrap()
+            .is_none());
 
-        assert!(id > 0);
-
-        // Verify the entry was created with correct defaults
-        let entry = storage.get_decision_by_id(id).await.unwrap().unwrap();
-        assert_eq!(entry.transaction_id, "txn-001");
-        assert_eq!(entry.agent_did, "did:key:z6MkAgent1");
-        assert_eq!(entry.decision_type, DecisionType::AuthorizationRequired);
-        assert_eq!(entry.status, DecisionStatus::Pending);
-        assert!(entry.resolution.is_none());
-        assert!(entry.resolution_detail.is_none());
-        assert!(entry.delivered_at.is_none());
-        assert!(entry.resolved_at.is_none());
-        assert_eq!(entry.context_json["transaction"]["type"], "transfer");
+        let exported = storage.export_pinned_did_documents("tx-1").await.unwrap();
+        let hashes: Vec<&str> = exported.iter().map(|d| d.did_doc_hash.as_str()).collect();
+        assert_eq!(hashes, vec!["hash-alice", "hash-bob"]);
     }
 
     #[tokio::test]
-    async fn test_insert_multiple_decision_types() {
+    async fn test_authorization_challenge_redeemed_once() {
         let storage = Storage::new_in_memory().await.unwrap();
+        let challenge = |nonce: &str, expires_at: &str| AuthorizationChallenge {
+            nonce: nonce.to_string(),
+            transaction_id: "tx-1".to_string(),
+            agent_did: "did:example:alice".to_string(),
+            recipient_did: "did:example:bob".to_string(),
+            party_type: Some("customer".to_string()),
+            authorization_url: format!("https://vasp.example/authorize/{}", nonce),
+            status: ChallengeStatus::Pending,
+            message_id: None,
+            expires_at: expires_at.to_string(),
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            redeemed_at: None,
+        };
+        storage
+            .insert_authorization_challenge(&challenge("live", "2999-01-01T00:00:00Z"))
+            .await
+            .unwrap();
+        storage
+            .insert_authorization_challenge(&challenge("stale", "2000-01-01T00:00:00Z"))
+            .await
+            .unwrap();
 
-        let context = serde_json::json!({"transaction_id": "txn-002"});
+        assert!(storage
+            .redeem_authorization_challenge("live")
+            .await
+            .unwrap());
+        assert!(!storage
+            .redeem_authorization_challenge("live")
+            .await
+            .unwrap());
+        assert!(!storage
+            .redeem_authorization_challenge("stale")
+            .await
+            .unwrap());
+        assert!(!storage
+            .redeem_authorization_challenge("unknown")
+            .await
+            .unwrap());
 
-        let id1 = storage
-            .insert_decision(
-                "txn-002",
-                "did:key:z6MkAgent1",
-                DecisionType::AuthorizationRequired,
-                &context,
-            )
+        storage
+            .set_authorization_challenge_message("live", "authorize-1")
             .await
             .unwrap();
-
-        let id2 = storage
-            .insert_decision(
-                "txn-002",
-                "did:key:z6MkAgent1",
-                DecisionType::PolicySatisfactionRequired,
-                &context,
-            )
+        let live = storage
+            .get_authorization_challenge("live")
             .await
+            .unwrap()
             .unwrap();
+        assert_eq!(live.status, ChallengeStatus::Redeemed);
+        assert_eq!(live.message_id.as_deref(), Some("authorize-1"));
+        assert!(live.redeemed_at.is_some());
 
-        let id3 = storage
-            .insert_decision(
-                "txn-003",
-                "did:key:z6MkAgent1",
-                DecisionType::SettlementRequired,
-                &context,
-            )
+        assert_eq!(storage.expire_authorization_challenges().await.unwrap(), 1);
+        let stale = storage
+            .get_authorization_challenge("stale")
             .await
+            .unwrap()
             .unwrap();
-
-        assert!(id1 < id2);
-        assert!(id2 < id3);
-
-        let e1 = storage.get_decision_by_id(id1).await.unwrap().unwrap();
-        let e2 = storage.get_decision_by_id(id2).await.unwrap().unwrap();
-        let e3 = storage.get_decision_by_id(id3).await.unwrap().unwrap();
-
-        assert_eq!(e1.decision_type, DecisionType::AuthorizationRequired);
-        assert_eq!(e2.decision_type, DecisionType::PolicySatisfactionRequired);
-        assert_eq!(e3.decision_type, DecisionType::SettlementRequired);
+        assert_eq!(stale.status, ChallengeStatus::Expired);
     }
 
     #[tokio::test]
-    async fn test_update_decision_status_to_delivered() {
+    async fn test_connections_and_counterparty_profiles() {
         let storage = Storage::new_in_memory().await.unwrap();
-
-        let context = serde_json::json!({"info": "test"});
-        let id = storage
-            .insert_decision(
-                "txn-010",
-                "did:key:z6MkAgent1",
-                DecisionType::AuthorizationRequired,
-                &context,
-            )
+        let mut connect = PlainMessage::new(
+            "connect-1".to_string(),
+            "https://tap.rsvp/schema/1.0#Connect".to_string(),
+            serde_json::json!({"constraints": {"purposes": ["BEXP"]}}),
+            "did:example:alice".to_string(),
+        );
+        connect.to = vec!["did:example:bob".to_string()];
+        storage.insert_connection(&connect).await.unwrap();
+        // Recording it again keeps its answer
+        assert!(storage
+            .update_connection_status("connect-1", &ConnectionStatus::Authorized)
+            .await
+            .unwrap());
+        storage.insert_connection(&connect).await.unwrap();
+
+        let connection = storage.get_connection("connect-1").await.unwrap().unwrap();
+        assert_eq!(connection.status, ConnectionStatus::Authorized);
+        assert!(connection.is_party("did:example:bob"));
+        assert!(!connection.is_party("did:example:mallory"));
+        assert_eq!(storage.list_connections(10, 0).await.unwrap().len(), 1);
+        assert!(!storage
+            .update_connection_status("unknown", &ConnectionStatus::Rejected)
+            .await
+            .unwrap());
+
+        let mut profile = CounterpartyProfile {
+            counterparty_did: "did:example:bob".to_string(),
+            connection_id: "connect-1".to_string(),
+            connection_status: ConnectionStatus::Pending,
+            authorization_url: None,
+            rejection_reason: None,
+            features: None,
+            our_policies: vec![],
+            their_policies: vec![],
+            endpoint_verified: false,
+            ping_rtt_ms: None,
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            updated_at: "2026-01-01T00:00:00Z".to_string(),
+        };
+        storage.upsert_counterparty_profile(&profile).await.unwrap();
+
+        profile.connection_status = ConnectionStatus::Authorized;
+        profile.features = Some(vec![tap_msg::message::Disclosure::protocol(
+            "https://tap.rsvp/schema/1.0",
+        )]);
+        profile.endpoint_verified = true;
+        profile.ping_rtt_ms = Some(12);
+        profile.created_at = "2026-02-01T00:00:00Z".to_string();
+        profile.updated_at = "2026-02-01T00:00:00Z".to_string();
+        storage.upsert_counterparty_profile(&profile).await.unwrap();
+
+        let stored = storage
+            .get_counterparty_profile("did:example:bob")
             .await
+            .unwrap()
             .unwrap();
+        assert_eq!(stored.connection_status, ConnectionStatus::Authorized);
+        assert_eq!(
+            stored.supports_protocol("https://tap.rsvp/schema/1.0"),
+            Some(true)
+        );
+        assert!(stored.endpoint_verified);
+        assert_eq!(stored.ping_rtt_ms, Some(12));
+        assert_eq!(stored.created_at, "2026-01-01T00:00:00Z");
+        assert_eq!(stored.updated_at, "2026-02-01T00:00:00Z");
+        assert_eq!(
+            storage
+                .list_counterparty_profiles(10, 0)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
 
-        // Mark as delivered
+    #[tokio::test]
+    async fn test_mailbox_fetch_acknowledge_and_expire() {
+        use crate::clock::MockClock;
+        use chrono::TimeZone;
+
+        let clock = Arc::new(MockClock::new(
+            chrono::Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap(),
+        ));
+        let storage = Storage::new_in_memory()
+            .await
+            .unwrap()
+            .with_clock(clock.clone());
+        let bob = "did:example:bob";
+        for (id, expires_at) in [
+            ("msg-1", "2026-01-01T13:00:00Z"),
+            ("msg-2", "2026-01-01T13:00:00Z"),
+            ("msg-3", "2026-01-01T12:30:00Z"),
+        ] {
+            storage
+                .insert_mailbox_message(bob, "did:example:alice", id, "{}", None, expires_at)
+                .await
+                .unwrap();
+        }
         storage
-            .update_decision_status(id, DecisionStatus::Delivered, None, None)
+            .insert_mailbox_message(
+                "did:example:carol",
+                "did:example:alice",
+                "msg-4",
+                "{}",
+                None,
+                "2026-01-01T13:00:00Z",
+            )
             .await
             .unwrap();
 
-        let entry = storage.get_decision_by_id(id).await.unwrap().unwrap();
-        assert_eq!(entry.status, DecisionStatus::Delivered);
-        assert!(entry.delivered_at.is_some());
-        assert!(entry.resolved_at.is_none());
-        assert!(entry.resolution.is_none());
+        let fetched = storage.fetch_mailbox_messages(bob, 0, 2).await.unwrap();
+        assert_eq!(
+            fetched.iter().map(|m| m.seq).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert!(fetched.iter().all(|m| m.status == MailboxStatus::Fetched));
+
+        // Only fetched messages are acknowledged
+        let acknowledged = storage.acknowledge_mailbox_messages(bob, 3).await.unwrap();
+        assert_eq!(
+            acknowledged.iter().map(|m| m.seq).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert!(storage
+            .fetch_mailbox_messages(bob, 0, 10)
+            .await
+            .unwrap()
+            .iter()
+            .all(|m| m.seq == 3));
+
+        // Fetched but unacknowledged messages expire as well
+        clock.advance(chrono::Duration::minutes(30));
+        assert!(storage
+            .fetch_mailbox_messages(bob, 0, 10)
+            .await
+            .unwrap()
+            .is_empty());
+        let expired = storage.expire_mailbox_messages().await.unwrap();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].message_id, "msg-3");
+        assert_eq!(expired[0].status, MailboxStatus::Expired);
+        assert!(storage
+            .acknowledge_mailbox_messages(bob, 3)
+            .await
+            .unwrap()
+            .is_empty());
     }
 
     #[tokio::test]
-    async fn test_update_decision_status_to_resolved() {
-        let storage = Storage::new_in_memory().await.unwrap();
+    async fn test_clock_drives_expiry_and_timestamps() {
+        use crate::clock::MockClock;
+        use chrono::TimeZone;
+
+        let clock = Arc::new(MockClock::new(
+            chrono::Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap(),
+        ));
+        let storage = Storage::new_in_memory()
+            .await
+            .unwrap()
+            .with_clock(clock.clone());
+        storage
+            .insert_authorization_challenge(&AuthorizationChallenge {
+                nonce: "n-1".to_string(),
+                transaction_id: "tx-1".to_string(),
+                agent_did: "did:example:alice".to_string(),
+                recipient_did: "did:example:bob".to_string(),
+                party_type: None,
+                authorization_url: "https://vasp.example/authorize/n-1".to_string(),
+                status: ChallengeStatus::Pending,
+                message_id: None,
+                expires_at: "2026-01-01T12:10:00Z".to_string(),
+                created_at: "2026-01-01T12:00:00Z".to_string(),
+                redeemed_at: None,
+            })
+            .await
+            .unwrap();
 
-        let context = serde_json::json!({"info": "test"});
-        let id = storage
+        assert_eq!(storage.expire_authorization_challenges().await.unwrap(), 0);
+        clock.advance(chrono::Duration::minutes(10));
+        assert_eq!(storage.expire_authorization_challenges().await.unwrap(), 1);
+
+        let decision_id = storage
             .insert_decision(
-                "txn-011",
-                "did:key:z6MkAgent1",
+                "tx-1",
+                "did:example:alice",
                 DecisionType::AuthorizationRequired,
-                &context,
+                &serde_json::json!({}),
             )
             .await
             .unwrap();
-
-        // First deliver
-        storage
-            .update_decision_status(id, DecisionStatus::Delivered, None, None)
+        let decision = storage
+            .get_decision_by_id(decision_id)
             .await
+            .unwrap()
             .unwrap();
+        assert_eq!(decision.created_at, "2026-01-01T12:10:00Z");
+    }
 
-        // Then resolve with action
-        let detail = serde_json::json!({"settlement_address": "eip155:1:0xABC"});
+    #[tokio::test]
+    async fn test_quota_rejects_non_essential_writes() {
+        let dir = tempdir().unwrap();
+        let storage = Storage::new(Some(dir.path().join("quota.db")))
+            .await
+            .unwrap()
+            .with_quota(StorageQuota::new(1));
+
+        let result = storage
+            .create_received("{}", SourceType::Internal, None)
+            .await;
+        assert!(matches!(result, Err(StorageError::QuotaExceeded(_))));
+
+        let mut ping = thread_message("ping-1", None);
+        ping.type_ = "https://didcomm.org/trust-ping/2.0/ping".to_string();
+        let result = storage.log_message(&ping, MessageDirection::Incoming).await;
+        assert!(matches!(result, Err(StorageError::QuotaExceeded(_))));
+
+        // TAP messages are part of the audit trail and always stored
         storage
-            .update_decision_status(
-                id,
-                DecisionStatus::Resolved,
-                Some("authorize"),
-                Some(&detail),
+            .log_message(
+                &thread_message("auth-1", Some("tx-1")),
+                MessageDirection::Incoming,
             )
             .await
             .unwrap();
 
-        let entry = storage.get_decision_by_id(id).await.unwrap().unwrap();
-        assert_eq!(entry.status, DecisionStatus::Resolved);
-        assert_eq!(entry.resolution.as_deref(), Some("authorize"));
-        assert!(entry.resolved_at.is_some());
-        assert!(entry.delivered_at.is_some());
-        assert_eq!(
-            entry.resolution_detail.unwrap()["settlement_address"],
-            "eip155:1:0xABC"
-        );
+        let usage = storage.usage().await.unwrap();
+        assert!(usage.quota_exceeded);
+        assert!(usage.file_bytes > 0);
+        let messages = usage.tables.iter().find(|t| t.name == "messages").unwrap();
+        assert_eq!(messages.rows, 1);
+        assert!(usage
+            .tables
+            .windows(2)
+            .all(|w| w[0].size_bytes >= w[1].size_bytes));
     }
 
     #[tokio::test]
-    async fn test_list_decisions_all() {
-        let storage = Storage::new_in_memory().await.unwrap();
-
-        let context = serde_json::json!({"info": "test"});
+    async fn test_archive_snapshots_and_compacts() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("archive.db");
+        let storage = Storage::new(Some(db_path.clone())).await.unwrap();
 
-        // Insert 3 decisions
-        storage
-            .insert_decision(
-                "txn-020",
-                "did:key:z6MkAgent1",
-                DecisionType::AuthorizationRequired,
-                &context,
-            )
-            .await
-            .unwrap();
-        storage
-            .insert_decision(
-                "txn-021",
-                "did:key:z6MkAgent2",
-                DecisionType::SettlementRequired,
-                &context,
-            )
+        let raw = format!("{{\"padding\": \"{}\"}}", "x".repeat(4096));
+        for _ in 0..50 {
+            let id = storage
+                .create_received(&raw, SourceType::Internal, None)
+                .await
+                .unwrap();
+            storage
+                .update_received_status(id, ReceivedStatus::Processed, None, None)
+                .await
+                .unwrap();
+        }
+        let pending_id = storage
+            .create_received(&raw, SourceType::Internal, None)
             .await
             .unwrap();
-        storage
-            .insert_decision(
-                "txn-022",
-                "did:key:z6MkAgent1",
-                DecisionType::PolicySatisfactionRequired,
-                &context,
-            )
+        let size_before = storage.size_bytes().await.unwrap();
+
+        // Exceeding an archive quota archives instead of rejecting the write
+        let storage = storage
+            .with_quota(StorageQuota::new(size_before - 1).with_action(QuotaAction::Archive));
+        storage
+            .create_received("{}", SourceType::Internal, None)
             .await
             .unwrap();
+        assert!(storage.size_bytes().await.unwrap() < size_before);
 
-        // List all
-        let all = storage.list_decisions(None, None, None, 100).await.unwrap();
-        assert_eq!(all.len(), 3);
+        let archives: Vec<_> = std::fs::read_dir(dir.path().join("archive"))
+            .unwrap()
+            .collect();
+        assert_eq!(archives.len(), 1);
 
-        // Verify ordering by id ASC
-        assert!(all[0].id < all[1].id);
-        assert!(all[1].id < all[2].id);
+        let remaining = storage.list_received(100, 0, None, None).await.unwrap();
+        assert_eq!(remaining.len(), 2);
+        assert!(remaining.iter().any(|r| r.id == pending_id));
+
+        // Nothing left to archive, so the next write over quota is rejected
+        assert!(storage.archive().await.unwrap().is_none());
     }
 
     #[tokio::test]
-    async fn test_list_decisions_by_agent() {
+    async fn test_transaction_graph_follows_parent_threads() {
         let storage = Storage::new_in_memory().await.unwrap();
+        let mut payment = transfer_message(
+            "pay-1",
+            &transfer_body("did:example:originator", "did:example:beneficiary", "1.0"),
+            "did:example:sender",
+        );
+        payment.type_ = "https://tap.rsvp/schema/1.0#Payment".to_string();
+        let mut messages = vec![payment];
+        for (id, pthid) in [
+            ("tx-1", "pay-1"),
+            ("revert-1", "tx-1"),
+            ("tx-2", "pay-1"),
+            ("loop-1", "loop-2"),
+            ("loop-2", "loop-1"),
+        ] {
+            let mut message = transfer_message(
+                id,
+                &transfer_body("did:example:originator", "did:example:beneficiary", "1.0"),
+                "did:example:sender",
+            );
+            message.pthid = Some(pthid.to_string());
+            messages.push(message);
+        }
+        messages.push(transfer_message(
+            "unrelated",
+            &transfer_body("did:example:originator", "did:example:beneficiary", "1.0"),
+            "did:example:sender",
+        ));
+        for message in &messages {
+            storage.insert_transaction(message).await.unwrap();
+        }
 
-        let context = serde_json::json!({"info": "test"});
-
-        storage
-            .insert_decision(
-                "txn-030",
-                "did:key:z6MkAgent1",
-                DecisionType::AuthorizationRequired,
-                &context,
-            )
-            .await
-            .unwrap();
-        storage
-            .insert_decision(
-                "txn-031",
-                "did:key:z6MkAgent2",
-                DecisionType::AuthorizationRequired,
-                &context,
-            )
-            .await
-            .unwrap();
-        storage
-            .insert_decision(
-                "txn-032",
-                "did:key:z6MkAgent1",
-                DecisionType::SettlementRequired,
-                &context,
-            )
+        // Every transaction of the chain gives the graph from its root
+        let graph = storage
+            .get_transaction_graph("revert-1")
             .await
+            .unwrap()
             .unwrap();
+        assert_eq!(graph.root_id, "pay-1");
+        let ids: Vec<_> = graph
+            .nodes
+            .iter()
+            .map(|n| (n.transaction_id.as_str(), n.depth))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![("pay-1", 0), ("tx-1", 1), ("revert-1", 2), ("tx-2", 1)]
+        );
+        assert_eq!(graph.nodes[0].transaction_type, TransactionType::Payment);
+        assert_eq!(
+            graph
+                .children("pay-1")
+                .map(|n| n.transaction_id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["tx-1", "tx-2"]
+        );
+        assert_eq!(
+            graph,
+            storage
+                .get_transaction_graph("pay-1")
+                .await
+                .unwrap()
+                .unwrap()
+        );
 
-        let agent1 = storage
-            .list_decisions(Some("did:key:z6MkAgent1"), None, None, 100)
+        // Cyclic links end the traversal
+        let cycle = storage
+            .get_transaction_graph("loop-1")
             .await
+            .unwrap()
             .unwrap();
-        assert_eq!(agent1.len(), 2);
+        assert_eq!(cycle.nodes.len(), 2);
 
-        let agent2 = storage
-            .list_decisions(Some("did:key:z6MkAgent2"), None, None, 100)
+        let single = storage
+            .get_transaction_graph("unrelated")
             .await
+            .unwrap()
             .unwrap();
-        assert_eq!(agent2.len(), 1);
+        assert_eq!(single.nodes.len(), 1);
+        assert!(storage
+            .get_transaction_graph("missing")
+            .await
+            .unwrap()
+            .is_none());
     }
 
     #[tokio::test]
-    async fn test_list_decisions_by_status() {
-        let storage = Storage::new_in_memory().await.unwrap();
-
-        let context = serde_json::json!({"info": "test"});
+    async fn test_rebuild_projections_from_event_log() {
+        let storage = Storage::new_in_memory()
+            .await
+            .unwrap()
+            .with_event_sourcing(true);
 
-        let id1 = storage
-            .insert_decision(
-                "txn-040",
-                "did:key:z6MkAgent1",
-                DecisionType::AuthorizationRequired,
-                &context,
-            )
+        for id in ["tx-1", "tx-2"] {
+            let message = transfer_message(
+                id,
+                &transfer_body("did:example:originator", "did:example:beneficiary", "1.0"),
+                "did:example:sender",
+            );
+            storage
+                .log_message(&message, MessageDirection::Outgoing)
+                .await
+                .unwrap();
+            storage.insert_transaction(&message).await.unwrap();
+        }
+        storage
+            .insert_transaction_agent("tx-1", "did:example:vasp", "sender")
             .await
             .unwrap();
-        let _id2 = storage
-            .insert_decision(
-                "txn-041",
-                "did:key:z6MkAgent1",
-                DecisionType::AuthorizationRequired,
-                &context,
+        storage
+            .update_transaction_agent_status("tx-1", "did:example:vasp", "authorized")
+            .await
+            .unwrap();
+        storage
+            .update_transaction_status("tx-1", "confirmed")
+            .await
+            .unwrap();
+        storage
+            .record_rejection("tx-2", Some("sanctions"), Some("Listed party"))
+            .await
+            .unwrap();
+        let delivery_id = storage
+            .create_delivery(
+                "tx-1",
+                "{}",
+                "did:example:beneficiary",
+                Some("https://vasp.example/didcomm"),
+                DeliveryType::Https,
             )
             .await
             .unwrap();
-
-        // Mark first as delivered
         storage
-            .update_decision_status(id1, DecisionStatus::Delivered, None, None)
+            .increment_delivery_retry_count(delivery_id)
             .await
             .unwrap();
-
-        let pending = storage
-            .list_decisions(None, Some(DecisionStatus::Pending), None, 100)
+        storage
+            .update_delivery_status(delivery_id, DeliveryStatus::Success, Some(200), None)
             .await
             .unwrap();
-        assert_eq!(pending.len(), 1);
-        assert_eq!(pending[0].transaction_id, "txn-041");
 
-        let delivered = storage
-            .list_decisions(None, Some(DecisionStatus::Delivered), None, 100)
+        let events = storage
+            .list_state_events(Some("tx-1"), 0, 50)
             .await
             .unwrap();
-        assert_eq!(delivered.len(), 1);
-        assert_eq!(delivered[0].transaction_id, "txn-040");
-    }
+        assert_eq!(events.len(), 4);
+        assert!(matches!(
+            events[0].event,
+            StateEvent::TransactionInserted { .. }
+        ));
 
-    #[tokio::test]
-    async fn test_list_decisions_since_id() {
-        let storage = Storage::new_in_memory().await.unwrap();
+        let before = (
+            storage
+                .get_transaction_by_id("tx-1")
+                .await
+                .unwrap()
+                .unwrap(),
+            storage
+                .get_transaction_by_id("tx-2")
+                .await
+                .unwrap()
+                .unwrap(),
+            storage
+                .get_delivery_by_id(delivery_id)
+                .await
+                .unwrap()
+                .unwrap(),
+        );
 
-        let context = serde_json::json!({"info": "test"});
+        let report = storage.rebuild_projections().await.unwrap();
+        assert_eq!(report.events, 9);
+        assert_eq!(report.transactions, 2);
+        assert_eq!(report.deliveries, 1);
 
-        let id1 = storage
-            .insert_decision(
-                "txn-050",
-                "did:key:z6MkAgent1",
-                DecisionType::AuthorizationRequired,
-                &context,
-            )
-            .await
-            .unwrap();
-        let id2 = storage
-            .insert_decision(
-                "txn-051",
-                "did:key:z6MkAgent1",
-                DecisionType::AuthorizationRequired,
-                &context,
-            )
+        let tx1 = storage
+            .get_transaction_by_id("tx-1")
             .await
+            .unwrap()
             .unwrap();
-        let _id3 = storage
-            .insert_decision(
-                "txn-052",
-                "did:key:z6MkAgent1",
-                DecisionType::AuthorizationRequired,
-                &context,
-            )
+        assert_eq!(tx1.status, TransactionStatus::Confirmed);
+        assert_eq!(tx1.created_at, before.0.created_at);
+        let tx2 = storage
+            .get_transaction_by_id("tx-2")
             .await
+            .unwrap()
             .unwrap();
-
-        // Get decisions since id1 (should return id2 and id3)
-        let since = storage
-            .list_decisions(None, None, Some(id1), 100)
+        assert_eq!(tx2.rejection_code, before.1.rejection_code);
+        assert_eq!(tx2.rejection_reason.as_deref(), Some("Listed party"));
+        assert_eq!(
+            storage.get_transaction_agents("tx-1").await.unwrap(),
+            vec![(
+                "did:example:vasp".to_string(),
+                "sender".to_string(),
+                "authorized".to_string()
+            )]
+        );
+        let delivery = storage
+            .get_delivery_by_id(delivery_id)
             .await
+            .unwrap()
             .unwrap();
-        assert_eq!(since.len(), 2);
-        assert_eq!(since[0].id, id2);
+        assert_eq!(delivery.status, DeliveryStatus::Success);
+        assert_eq!(delivery.retry_count, before.2.retry_count);
+        assert_eq!(delivery.last_http_status_code, Some(200));
     }
 
     #[tokio::test]
-    async fn test_list_decisions_with_limit() {
+    async fn test_rebuild_requires_complete_event_log() {
         let storage = Storage::new_in_memory().await.unwrap();
+        storage
+            .insert_transaction(&transfer_message(
+                "tx-1",
+                &transfer_body("did:example:originator", "did:example:beneficiary", "1.0"),
+                "did:example:sender",
+            ))
+            .await
+            .unwrap();
 
-        let context = serde_json::json!({"info": "test"});
-
-        for i in 0..5 {
-            storage
-                .insert_decision(
-                    &format!("txn-06{}", i),
-                    "did:key:z6MkAgent1",
-                    DecisionType::AuthorizationRequired,
-                    &context,
-                )
-                .await
-                .unwrap();
-        }
-
-        let limited = storage.list_decisions(None, None, None, 3).await.unwrap();
-        assert_eq!(limited.len(), 3);
+        let storage = storage.with_event_sourcing(true);
+        assert!(matches!(
+            storage.rebuild_projections().await,
+            Err(StorageError::Projection(_))
+        ));
+        assert!(storage
+            .get_transaction_by_id("tx-1")
+            .await
+            .unwrap()
+            .is_some());
     }
 
     #[tokio::test]
-    async fn test_expire_decisions_for_transaction() {
-        let storage = Storage::new_in_memory().await.unwrap();
-
-        let context = serde_json::json!({"info": "test"});
+    async fn test_transaction_state_at_timestamp() {
+        use super::super::event_store::TransactionAgentState;
+        use crate::clock::MockClock;
+        use chrono::TimeZone;
+
+        let start = chrono::Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap();
+        let clock = Arc::new(MockClock::new(start));
+        let storage = Storage::new_in_memory()
+            .await
+            .unwrap()
+            .with_clock(clock.clone())
+            .with_event_sourcing(true);
+        let minute = chrono::Duration::minutes(1);
 
-        // Insert decisions for two transactions
-        let id1 = storage
-            .insert_decision(
-                "txn-070",
-                "did:key:z6MkAgent1",
-                DecisionType::AuthorizationRequired,
-                &context,
-            )
+        storage
+            .insert_transaction(&transfer_message(
+                "tx-1",
+                &transfer_body("did:example:originator", "did:example:beneficiary", "1.0"),
+                "did:example:sender",
+            ))
             .await
             .unwrap();
-        let id2 = storage
-            .insert_decision(
-                "txn-070",
-                "did:key:z6MkAgent2",
-                DecisionType::AuthorizationRequired,
-                &context,
-            )
+        clock.advance(minute);
+        storage
+            .insert_transaction_agent("tx-1", "did:example:vasp", "sender")
             .await
             .unwrap();
-        let id3 = storage
-            .insert_decision(
-                "txn-071",
-                "did:key:z6MkAgent1",
-                DecisionType::AuthorizationRequired,
-                &context,
-            )
+        clock.advance(minute);
+        storage
+            .update_transaction_agent_status("tx-1", "did:example:vasp", "authorized")
             .await
             .unwrap();
-
-        // Mark id2 as delivered (should still be expired)
+        clock.advance(minute);
         storage
-            .update_decision_status(id2, DecisionStatus::Delivered, None, None)
+            .insert_transaction_agent("tx-1", "did:example:compliance", "compliance")
+            .await
+            .unwrap();
+        storage
+            .update_transaction_status("tx-1", "failed")
+            .await
+            .unwrap();
+        storage
+            .record_rejection("tx-1", Some("sanctions"), Some("Listed party"))
             .await
             .unwrap();
 
-        // Expire decisions for txn-070
-        let expired_count = storage
-            .expire_decisions_for_transaction("txn-070")
+        assert!(storage
+            .get_transaction_state_at("tx-1", start - minute)
+            .await
+            .unwrap()
+            .is_none());
+
+        let created = storage
+            .get_transaction_state_at("tx-1", start)
             .await
+            .unwrap()
             .unwrap();
-        assert_eq!(expired_count, 2);
+        assert_eq!(created.status, TransactionStatus::Pending);
+        assert!(created.agents.is_empty());
+        assert_eq!(created.message.id, "tx-1");
 
-        // Verify statuses
-        let e1 = storage.get_decision_by_id(id1).await.unwrap().unwrap();
-        assert_eq!(e1.status, DecisionStatus::Expired);
+        let authorized = storage
+            .get_transaction_state_at("tx-1", start + minute * 2 + chrono::Duration::seconds(30))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(authorized.status, TransactionStatus::Pending);
+        assert_eq!(authorized.rejection_code, None);
+        assert_eq!(
+            authorized.agents,
+            vec![TransactionAgentState {
+                agent_did: "did:example:vasp".to_string(),
+                role: "sender".to_string(),
+                status: "authorized".to_string(),
+            }]
+        );
 
-        let e2 = storage.get_decision_by_id(id2).await.unwrap().unwrap();
-        assert_eq!(e2.status, DecisionStatus::Expired);
+        let current = storage
+            .get_transaction_state_at("tx-1", start + minute * 10)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(current.status, TransactionStatus::Failed);
+        assert_eq!(current.rejection_code.as_deref(), Some("sanctions"));
+        assert_eq!(current.agents.len(), 2);
+        assert!(current.sequence > authorized.sequence);
 
-        // txn-071 should not be affected
-        let e3 = storage.get_decision_by_id(id3).await.unwrap().unwrap();
-        assert_eq!(e3.status, DecisionStatus::Pending);
+        assert!(storage
+            .get_transaction_state_at("tx-unknown", start)
+            .await
+            .unwrap()
+            .is_none());
+
+        let untracked = Storage::new_in_memory().await.unwrap();
+        untracked
+            .insert_transaction(&transfer_message(
+                "tx-2",
+                &transfer_body("did:example:originator", "did:example:beneficiary", "1.0"),
+                "did:example:sender",
+            ))
+            .await
+            .unwrap();
+        assert!(matches!(
+            untracked
+                .get_transaction_state_at("tx-2", chrono::Utc::now())
+                .await,
+            Err(StorageError::Replay(_))
+        ));
     }
 
     #[tokio::test]
-    async fn test_expire_does_not_affect_resolved() {
-        let storage = Storage::new_in_memory().await.unwrap();
-
-        let context = serde_json::json!({"info": "test"});
+    async fn test_drafts_are_listed_sent_once_and_expire() {
+        use crate::clock::MockClock;
+        use chrono::TimeZone;
+
+        let clock = Arc::new(MockClock::new(
+            chrono::Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap(),
+        ));
+        let storage = Storage::new_in_memory()
+            .await
+            .unwrap()
+            .with_clock(clock.clone());
+        for (id, status, expires_at) in [
+            ("draft-1", DraftStatus::Valid, "2026-01-01T13:00:00Z"),
+            ("draft-2", DraftStatus::Invalid, "2026-01-02T12:00:00Z"),
+        ] {
+            storage
+                .insert_draft(&Draft {
+                    id: id.to_string(),
+                    agent_did: "did:example:alice".to_string(),
+                    kind: DraftKind::Transfer,
+                    body: serde_json::json!({"amount": "10"}),
+                    status,
+                    validation_errors: Vec::new(),
+                    expires_at: Some(expires_at.to_string()),
+                    message_id: None,
+                    created_at: "2026-01-01T12:00:00Z".to_string(),
+                    updated_at: "2026-01-01T12:00:00Z".to_string(),
+                })
+                .await
+                .unwrap();
+        }
 
-        let id1 = storage
-            .insert_decision(
-                "txn-080",
-                "did:key:z6MkAgent1",
-                DecisionType::AuthorizationRequired,
-                &context,
-            )
+        let valid = storage
+            .list_drafts(None, Some(DraftStatus::Valid), false, 10, 0)
             .await
             .unwrap();
-        let id2 = storage
-            .insert_decision(
-                "txn-080",
-                "did:key:z6MkAgent2",
-                DecisionType::AuthorizationRequired,
-                &context,
-            )
+        assert_eq!(valid.len(), 1);
+        assert_eq!(valid[0].body["amount"], "10");
+
+        assert!(!storage.mark_draft_sent("draft-2", "msg-2").await.unwrap());
+        assert!(storage.mark_draft_sent("draft-1", "msg-1").await.unwrap());
+        assert!(!storage.mark_draft_sent("draft-1", "msg-1").await.unwrap());
+        assert!(!storage
+            .update_draft("draft-1", &serde_json::json!({}), DraftStatus::Valid, &[])
+            .await
+            .unwrap());
+        assert!(!storage.delete_draft("draft-1").await.unwrap());
+
+        clock.advance(chrono::Duration::days(2));
+        let listed = storage
+            .list_drafts(Some("did:example:alice"), None, false, 10, 0)
             .await
             .unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].message_id.as_deref(), Some("msg-1"));
 
-        // Resolve id1
+        assert_eq!(storage.delete_expired_drafts().await.unwrap(), 1);
+        assert!(storage.get_draft("draft-2").await.unwrap().is_none());
+        assert!(storage.get_draft("draft-1").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_delivery_queue() {
+        let storage = Storage::new_in_memory().await.unwrap();
+        let message = transfer_message(
+            "tx-1",
+            &transfer_body("did:example:originator", "did:example:beneficiary", "1.0"),
+            "did:example:sender",
+        );
         storage
-            .update_decision_status(id1, DecisionStatus::Resolved, Some("authorize"), None)
+            .log_message(&message, MessageDirection::Outgoing)
             .await
             .unwrap();
 
-        // Expire txn-080
-        let expired_count = storage
-            .expire_decisions_for_transaction("txn-080")
+        let mut ids = Vec::new();
+        for url in [
+            "https://a.example",
+            "https://b.example",
+            "https://c.example",
+        ] {
+            ids.push(
+                storage
+                    .create_delivery(
+                        "tx-1",
+                        "{}",
+                        "did:example:beneficiary",
+                        Some(url),
+                        DeliveryType::Https,
+                    )
+                    .await
+                    .unwrap(),
+            );
+        }
+        storage
+            .create_delivery(
+                "tx-1",
+                "{}",
+                "did:example:originator",
+                None,
+                DeliveryType::Internal,
+            )
             .await
             .unwrap();
-        assert_eq!(expired_count, 1); // Only id2 should be expired
 
-        let e1 = storage.get_decision_by_id(id1).await.unwrap().unwrap();
-        assert_eq!(e1.status, DecisionStatus::Resolved);
+        for (id, at) in [
+            (ids[1], "2025-01-01T12:00:00Z"),
+            (ids[2], "2025-01-01T11:00:00Z"),
+        ] {
+            storage
+                .update_delivery_status(id, DeliveryStatus::Failed, Some(503), Some("unavailable"))
+                .await
+                .unwrap();
+            storage
+                .schedule_delivery_attempt(id, Some(at))
+                .await
+                .unwrap();
+        }
+        storage
+            .reassign_delivery(ids[0], "https://d.example")
+            .await
+            .unwrap();
 
-        let e2 = storage.get_decision_by_id(id2).await.unwrap().unwrap();
-        assert_eq!(e2.status, DecisionStatus::Expired);
-    }
+        let queue = storage.get_delivery_queue(10, 0).await.unwrap();
+        let queued: Vec<i64> = queue.iter().map(|d| d.id).collect();
+        assert_eq!(queued, vec![ids[2], ids[1], ids[0]]);
+        assert_eq!(queue[2].delivery_url.as_deref(), Some("https://d.example"));
 
-    #[tokio::test]
-    async fn test_get_decision_by_id_not_found() {
-        let storage = Storage::new_in_memory().await.unwrap();
+        let due = storage
+            .get_due_deliveries("2025-01-01T11:30:00Z", 10)
+            .await
+            .unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id, ids[2]);
 
-        let result = storage.get_decision_by_id(99999).await.unwrap();
-        assert!(result.is_none());
+        // Cancelled deliveries leave the queue and are not attempted again
+        storage
+            .update_delivery_status(ids[2], DeliveryStatus::Cancelled, None, None)
+            .await
+            .unwrap();
+        let cancelled = storage.get_delivery_by_id(ids[2]).await.unwrap().unwrap();
+        assert_eq!(cancelled.status, DeliveryStatus::Cancelled);
+        assert!(cancelled.next_attempt_at.is_none());
+        assert_eq!(storage.get_delivery_queue(10, 0).await.unwrap().len(), 2);
     }
 
     #[tokio::test]
-    async fn test_list_decisions_combined_filters() {
-        let storage = Storage::new_in_memory().await.unwrap();
-
-        let context = serde_json::json!({"info": "test"});
+    async fn test_sensitive_messages_are_encrypted_at_rest() {
+        use crate::storage::{MessageCipher, MessageEncryption};
 
-        let id1 = storage
-            .insert_decision(
-                "txn-090",
-                "did:key:z6MkAgent1",
-                DecisionType::AuthorizationRequired,
-                &context,
-            )
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("agent.db");
+        let storage = Storage::new(Some(db_path.clone()))
+            .await
+            .unwrap()
+            .with_message_encryption(MessageCipher::new(&[3u8; 32], MessageEncryption::default()));
+
+        let presentation = PlainMessage::new(
+            "presentation-1".to_string(),
+            encryption::PRESENTATION_MESSAGE_TYPE.to_string(),
+            serde_json::json!({"name": "Alice"}),
+            "did:example:alice".to_string(),
+        );
+        let raw = serde_json::to_string(&presentation).unwrap();
+        storage
+            .log_message(&presentation, MessageDirection::Outgoing)
             .await
             .unwrap();
         storage
-            .insert_decision(
-                "txn-091",
-                "did:key:z6MkAgent2",
-                DecisionType::AuthorizationRequired,
-                &context,
+            .log_message(
+                &transfer_message(
+                    "tx-1",
+                    &transfer_body("did:example:originator", "did:example:beneficiary", "1.0"),
+                    "did:example:sender",
+                ),
+                MessageDirection::Outgoing,
             )
             .await
             .unwrap();
-        storage
-            .insert_decision(
-                "txn-092",
-                "did:key:z6MkAgent1",
-                DecisionType::SettlementRequired,
-                &context,
+        let delivery_id = storage
+            .create_delivery(
+                "presentation-1",
+                &raw,
+                "did:example:bob",
+                Some("https://bob.example"),
+                DeliveryType::Https,
             )
             .await
             .unwrap();
+        let received_id = storage
+            .create_received(&raw, SourceType::Https, None)
+            .await
+            .unwrap();
 
-        // Mark first as delivered
-        storage
-            .update_decision_status(id1, DecisionStatus::Delivered, None, None)
+        // Reads through the storage holding the key are decrypted
+        let message = storage
+            .get_message_by_id("presentation-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(message.message_json["body"]["name"], "Alice");
+        let delivery = storage
+            .get_delivery_by_id(delivery_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(delivery.message_text, raw);
+        let received = storage
+            .get_received_by_id(received_id)
             .await
+            .unwrap()
             .unwrap();
+        assert_eq!(received.raw_message, raw);
 
-        // Filter by agent + status
-        let results = storage
-            .list_decisions(
-                Some("did:key:z6MkAgent1"),
-                Some(DecisionStatus::Pending),
-                None,
-                100,
-            )
+        // Other readers of the database see ciphertext, except for messages
+        // of types that are not encrypted
+        let keyless = Storage::new(Some(db_path)).await.unwrap();
+        let message = keyless
+            .get_message_by_id("presentation-1")
             .await
+            .unwrap()
             .unwrap();
-        assert_eq!(results.len(), 1);
-        assert_eq!(results[0].transaction_id, "txn-092");
+        assert!(encryption::is_encrypted(
+            message.message_json.as_str().unwrap()
+        ));
+        let delivery = keyless
+            .get_delivery_by_id(delivery_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(encryption::is_encrypted(&delivery.message_text));
+        let received = keyless
+            .get_received_by_id(received_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!received.raw_message.contains("Alice"));
+        let transfer = keyless.get_message_by_id("tx-1").await.unwrap().unwrap();
+        assert!(transfer.message_json.is_object());
     }
 
     #[tokio::test]
-    async fn test_resolve_decisions_for_transaction() {
-        let storage = Storage::new_in_memory().await.unwrap();
+    async fn test_encrypted_message_does_not_open_in_another_row() {
+        use crate::storage::{MessageCipher, MessageEncryption};
 
-        let context = serde_json::json!({"info": "test"});
+        let storage = Storage::new_in_memory()
+            .await
+            .unwrap()
+            .with_message_encryption(MessageCipher::new(&[3u8; 32], MessageEncryption::default()));
+        for (id, name) in [("presentation-1", "Alice"), ("presentation-2", "Bob")] {
+            let presentation = PlainMessage::new(
+                id.to_string(),
+                encryption::PRESENTATION_MESSAGE_TYPE.to_string(),
+                serde_json::json!({ "name": name }),
+                "did:example:alice".to_string(),
+            );
+            storage
+                .log_message(&presentation, MessageDirection::Outgoing)
+                .await
+                .unwrap();
+        }
 
-        // Insert auth and settlement decisions for same transaction
-        let id1 = storage
-            .insert_decision(
-                "txn-100",
-                "did:key:z6MkAgent1",
-                DecisionType::AuthorizationRequired,
-                &context,
-            )
+        // Copy the ciphertext of one message into the row of the other
+        sqlx::query(
+            r#"
+            UPDATE messages SET message_json =
+                (SELECT message_json FROM messages WHERE message_id = 'presentation-1')
+            WHERE message_id = 'presentation-2'
+            "#,
+        )
+        .execute(storage.pool())
+        .await
+        .unwrap();
+
+        let message = storage
+            .get_message_by_id("presentation-1")
             .await
+            .unwrap()
             .unwrap();
-        let id2 = storage
-            .insert_decision(
-                "txn-100",
-                "did:key:z6MkAgent1",
-                DecisionType::SettlementRequired,
-                &context,
-            )
+        assert_eq!(message.message_json["body"]["name"], "Alice");
+        assert!(matches!(
+            storage.get_message_by_id("presentation-2").await,
+            Err(StorageError::Encryption(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_parties_are_sealed_at_rest() {
+        use crate::storage::{MessageCipher, MessageEncryption};
+
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("agent.db");
+        let storage = Storage::new(Some(db_path.clone()))
             .await
-            .unwrap();
+            .unwrap()
+            .with_message_encryption(MessageCipher::new(&[3u8; 32], MessageEncryption::default()));
 
-        // Resolve only authorization_required decisions
-        let resolved = storage
-            .resolve_decisions_for_transaction(
-                "txn-100",
-                "authorize",
-                Some(DecisionType::AuthorizationRequired),
-            )
+        let alice = Party::with_metadata(
+            "did:example:alice",
+            [("name".to_string(), serde_json::json!("Alice Smith"))].into(),
+        );
+        let transfer = Transfer {
+            originator: Some(alice),
+            ..transfer_body("did:example:originator", "did:example:beneficiary", "1.0")
+        };
+        let message = transfer_message("tx-1", &transfer, "did:example:sender")
+            .with_recipient("did:example:vasp");
+        let raw = serde_json::to_string(&message).unwrap();
+        storage.insert_transaction(&message).await.unwrap();
+        storage
+            .log_message(&message, MessageDirection::Incoming)
+            .await
+            .unwrap();
+        storage
+            .create_received(&raw, SourceType::Https, None)
             .await
             .unwrap();
-        assert_eq!(resolved, 1);
 
-        let e1 = storage.get_decision_by_id(id1).await.unwrap().unwrap();
-        assert_eq!(e1.status, DecisionStatus::Resolved);
-        assert_eq!(e1.resolution.as_deref(), Some("authorize"));
+        // The stored messages keep a reference to the sealed party, and the
+        // hash of the message as received
+        let stored = storage
+            .get_transaction_by_id("tx-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            stored.message_json["body"]["originator"],
+            serde_json::json!({"@id": "did:example:alice", "piiRef": "originator"})
+        );
+        assert_eq!(
+            stored.message_json["body"]["beneficiary"],
+            serde_json::json!({"@id": "did:example:beneficiary"})
+        );
+        assert_eq!(
+            stored.canonical_hash,
+            Some(canonical_transaction_hash(&message.body, &message.from))
+        );
+        let logged = storage.get_message_by_id("tx-1").await.unwrap().unwrap();
+        assert!(!logged.message_json.to_string().contains("Alice"));
+        let keyless = Storage::new(Some(db_path)).await.unwrap();
+        let received = keyless.list_received(10, 0, None, None).await.unwrap();
+        assert!(!received[0].raw_message.contains("Alice"));
+
+        // Only participants and the accessors they grant access read it
+        let pii = storage
+            .read_transaction_pii("tx-1", "did:example:vasp", "travel rule review")
+            .await
+            .unwrap();
+        assert_eq!(pii.len(), 1);
+        assert_eq!(pii[0].data["name"], "Alice Smith");
+        for accessor in ["did:example:auditor", "did:example:eve"] {
+            assert!(matches!(
+                storage
+                    .read_transaction_pii("tx-1", accessor, "audit")
+                    .await,
+                Err(StorageError::AccessDenied(_))
+            ));
+        }
+        assert!(matches!(
+            storage
+                .grant_transaction_pii_access("tx-1", "did:example:eve", "did:example:eve")
+                .await,
+            Err(StorageError::AccessDenied(_))
+        ));
+        storage
+            .grant_transaction_pii_access("tx-1", "did:example:auditor", "did:example:sender")
+            .await
+            .unwrap();
+        assert_eq!(
+            storage
+                .read_transaction_pii("tx-1", "did:example:auditor", "audit")
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(storage
+            .revoke_transaction_pii_access("tx-1", "did:example:auditor", "did:example:sender")
+            .await
+            .unwrap());
+        assert!(storage
+            .read_transaction_pii("tx-1", "did:example:auditor", "audit")
+            .await
+            .is_err());
 
-        // Settlement decision should still be pending
-        let e2 = storage.get_decision_by_id(id2).await.unwrap().unwrap();
-        assert_eq!(e2.status, DecisionStatus::Pending);
+        let granted: Vec<_> = storage
+            .list_pii_access("tx-1")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|access| access.granted)
+            .collect();
+        assert_eq!(granted, [true, false, false, true, false]);
     }
 
     #[tokio::test]
-    async fn test_resolve_decisions_all_types() {
-        let storage = Storage::new_in_memory().await.unwrap();
+    async fn test_counterparty_stats() {
+        use crate::clock::MockClock;
+        use chrono::TimeZone;
 
-        let context = serde_json::json!({"info": "test"});
+        let start = chrono::Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap();
+        let clock = Arc::new(MockClock::new(start));
+        let storage = Storage::new_in_memory()
+            .await
+            .unwrap()
+            .with_clock(clock.clone());
+        let vasp = "did:example:vasp";
+        let reply = |id: &str, type_: &str, from: &str, to: &str| {
+            PlainMessage::new(
+                id.to_string(),
+                format!("https://tap.rsvp/schema/1.0#{}", type_),
+                serde_json::json!({}),
+                from.to_string(),
+            )
+            .with_recipient(to)
+            .with_thread_id(Some("tx-1".to_string()))
+        };
 
-        let id1 = storage
-            .insert_decision(
-                "txn-101",
-                "did:key:z6MkAgent1",
-                DecisionType::AuthorizationRequired,
-                &context,
+        // Sent to the counterparty, which authorizes after two minutes; the
+        // transfer settles after five
+        let tx1 = transfer_message(
+            "tx-1",
+            &transfer_body("did:example:originator", "did:example:beneficiary", "1.0"),
+            "did:example:sender",
+        )
+        .with_recipient(vasp);
+        storage.insert_transaction(&tx1).await.unwrap();
+        storage
+            .log_message(&tx1, MessageDirection::Outgoing)
+            .await
+            .unwrap();
+        clock.advance(chrono::Duration::minutes(2));
+        storage
+            .log_message(
+                &reply("auth-1", "Authorize", vasp, "did:example:sender"),
+                MessageDirection::Incoming,
             )
             .await
             .unwrap();
-        let id2 = storage
-            .insert_decision(
-                "txn-101",
-                "did:key:z6MkAgent1",
-                DecisionType::SettlementRequired,
-                &context,
+        clock.advance(chrono::Duration::minutes(3));
+        storage
+            .log_message(
+                &reply("settle-1", "Settle", "did:example:sender", vasp),
+                MessageDirection::Outgoing,
             )
             .await
             .unwrap();
 
-        // Resolve all decisions (no type filter)
-        let resolved = storage
-            .resolve_decisions_for_transaction("txn-101", "reject", None)
+        // Sent to the counterparty and rejected
+        let tx2 = transfer_message(
+            "tx-2",
+            &transfer_body("did:example:originator", "did:example:beneficiary", "1.0"),
+            "did:example:sender",
+        )
+        .with_recipient(vasp);
+        storage.insert_transaction(&tx2).await.unwrap();
+        storage
+            .log_message(&tx2, MessageDirection::Outgoing)
+            .await
+            .unwrap();
+        storage
+            .update_transaction_status("tx-2", "failed")
+            .await
+            .unwrap();
+        storage
+            .record_rejection("tx-2", Some("sanctions"), Some("Listed party"))
             .await
             .unwrap();
-        assert_eq!(resolved, 2);
-
-        let e1 = storage.get_decision_by_id(id1).await.unwrap().unwrap();
-        assert_eq!(e1.status, DecisionStatus::Resolved);
-        assert_eq!(e1.resolution.as_deref(), Some("reject"));
-
-        let e2 = storage.get_decision_by_id(id2).await.unwrap().unwrap();
-        assert_eq!(e2.status, DecisionStatus::Resolved);
-        assert_eq!(e2.resolution.as_deref(), Some("reject"));
-    }
-
-    #[tokio::test]
-    async fn test_resolve_does_not_affect_already_resolved() {
-        let storage = Storage::new_in_memory().await.unwrap();
-
-        let context = serde_json::json!({"info": "test"});
 
-        let id1 = storage
-            .insert_decision(
-                "txn-102",
-                "did:key:z6MkAgent1",
-                DecisionType::AuthorizationRequired,
-                &context,
-            )
+        // Initiated by the counterparty
+        let mut tx3 = transfer_message(
+            "tx-3",
+            &transfer_body("did:example:originator", "did:example:beneficiary", "1.0"),
+            "did:example:sender",
+        )
+        .with_recipient("did:example:sender");
+        tx3.from = vasp.to_string();
+        storage.insert_transaction(&tx3).await.unwrap();
+        storage
+            .log_message(&tx3, MessageDirection::Incoming)
             .await
             .unwrap();
 
-        // Resolve manually first
+        // Not involving the counterparty
         storage
-            .update_decision_status(id1, DecisionStatus::Resolved, Some("authorize"), None)
+            .insert_transaction(
+                &transfer_message(
+                    "tx-4",
+                    &transfer_body("did:example:originator", "did:example:beneficiary", "1.0"),
+                    "did:example:sender",
+                )
+                .with_recipient("did:example:other"),
+            )
             .await
             .unwrap();
 
-        // Try to resolve again via the bulk method
-        let resolved = storage
-            .resolve_decisions_for_transaction("txn-102", "reject", None)
+        let stats = storage
+            .get_counterparty_stats(vasp, None, None)
             .await
             .unwrap();
-        assert_eq!(resolved, 0); // Already resolved, should not change
+        assert_eq!(stats.messages_received, 2);
+        assert_eq!(stats.messages_sent, 3);
+        assert_eq!(stats.transactions, 3);
+        assert_eq!(stats.initiated, 1);
+        assert_eq!(stats.authorized, 1);
+        assert_eq!(stats.authorization_rate, 0.5);
+        assert_eq!(stats.time_to_authorize.count, 1);
+        assert_eq!(
+            stats.time_to_authorize.p50_secs.map(f64::round),
+            Some(120.0)
+        );
+        assert_eq!(stats.settled, 1);
+        assert_eq!(stats.time_to_settle.p99_secs.map(f64::round), Some(300.0));
+        assert_eq!(stats.rejected, 1);
+        assert_eq!(stats.rejections_by_code.get("sanctions"), Some(&1));
+        assert_eq!(stats.uncoded_rejections, 0);
+        assert_eq!(stats.volumes.get("eip155:1/slip44:60"), Some(&3.0));
+
+        // Only the transactions created after the first one
+        let since = (start + chrono::Duration::minutes(1)).to_rfc3339();
+        let stats = storage
+            .get_counterparty_stats(vasp, Some(&since), None)
+            .await
+            .unwrap();
+        assert_eq!(stats.transactions, 2);
+        assert_eq!(stats.authorized, 0);
+        assert_eq!(stats.settled, 0);
+    }
 
-        let e1 = storage.get_decision_by_id(id1).await.unwrap().unwrap();
-        assert_eq!(e1.resolution.as_deref(), Some("authorize")); // Original resolution preserved
+    #[test]
+    fn test_latency_stats() {
+        assert_eq!(
+            LatencyStats::from_durations(Vec::new()),
+            LatencyStats::default()
+        );
+
+        let stats = LatencyStats::from_durations((1..=100).rev().map(f64::from).collect());
+        assert_eq!(stats.count, 100);
+        assert_eq!(stats.average_secs, Some(50.5));
+        assert_eq!(stats.p50_secs, Some(50.0));
+        assert_eq!(stats.p90_secs, Some(90.0));
+        assert_eq!(stats.p99_secs, Some(99.0));
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_job_runs_and_requests() {
+        let storage = Storage::new_in_memory().await.unwrap();
+        assert!(!storage.request_job_run("backup").await.unwrap());
+
+        let mut job = ScheduledJobRecord {
+            name: "backup".to_string(),
+            schedule: "@daily".to_string(),
+            last_run_at: Some("2026-03-01T00:00:00+00:00".to_string()),
+            last_status: Some(ScheduledJobStatus::Failed),
+            last_error: Some("disk full".to_string()),
+            last_duration_ms: Some(1200),
+            next_run_at: Some("2026-03-02T00:00:00+00:00".to_string()),
+            run_requested_at: None,
+            updated_at: "2026-03-01T00:00:01+00:00".to_string(),
+        };
+        storage.save_scheduled_job(&job).await.unwrap();
+
+        // Re-registering the job keeps its last run
+        job.schedule = "@every 12h".to_string();
+        job.last_run_at = None;
+        job.last_status = None;
+        job.last_error = None;
+        job.last_duration_ms = None;
+        storage.save_scheduled_job(&job).await.unwrap();
+        let saved = storage.get_scheduled_job("backup").await.unwrap().unwrap();
+        assert_eq!(saved.schedule, "@every 12h");
+        assert_eq!(
+            saved.last_run_at.as_deref(),
+            Some("2026-03-01T00:00:00+00:00")
+        );
+        assert_eq!(saved.last_status, Some(ScheduledJobStatus::Failed));
+        assert_eq!(saved.last_error.as_deref(), Some("disk full"));
+
+        assert!(storage.request_job_run("backup").await.unwrap());
+        let names = vec!["backup".to_string(), "retention".to_string()];
+        assert_eq!(
+            storage.take_job_run_requests(&names).await.unwrap(),
+            vec!["backup".to_string()]
+        );
+        assert!(storage
+            .take_job_run_requests(&names)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(storage.list_scheduled_jobs().await.unwrap().len(), 1);
     }
 }
@@ -0,0 +1,338 @@
+//! At-rest encryption of sensitive messages
+//!
+//! Some messages carry personal data that should not sit in a database in
+//! the clear, above all Presentations of verifiable credentials. A
+//! [`MessageEncryption`] policy names the message types whose stored copies
+//! are encrypted with AES-256-GCM:
+//!
+//! - the parsed message in `messages.message_json`
+//! - the plain or signed envelope it was received as, in
+//!   `received.raw_message`; encrypted envelopes are kept as received
+//! - the message text sent to its recipients, in `deliveries.message_text`
+//!
+//! Each encrypted value is bound to the row it is stored in through the
+//! AES-GCM associated data, see [`binding`]: a message's copies to its ID and
+//! type, a received envelope to its record and the personal data of a
+//! transaction to its transaction and role. A ciphertext copied into another
+//! row fails to decrypt.
+//!
+//! The key of an agent database is derived from the agent's own key through
+//! its key manager, see [`AgentStorageManager::set_encryption_key`]. Reads
+//! through a [`Storage`] holding the key decrypt transparently; other readers,
+//! such as a [`ReadOnlyStorage`] or the `sqlite3` shell, see the ciphertext.
+//! Encrypted message JSON is stored as a JSON string, so SQL queries that
+//! look into it, such as `json_extract`, do not match encrypted messages.
+//!
+//! The personal data of the parties of a transaction is encrypted with a
+//! data key of its own, which is stored wrapped with the database key, so
+//! revoking access to one transaction's data only takes deleting its key.
+//! With a key, the parties of the [`PARTY_MESSAGE_TYPES`] are sealed before
+//! their messages are stored: the stored message JSON only keeps each
+//! party's `@id` and a `piiRef` naming its role, and their envelopes and
+//! delivered texts are encrypted like those of the policy's message types.
+//! The sealed data is only decrypted for the participants of the
+//! transaction and the accessors they grant access, see
+//! [`Storage::read_transaction_pii`].
+//!
+//! [`AgentStorageManager::set_encryption_key`]: super::AgentStorageManager::set_encryption_key
+//! [`Storage`]: super::Storage
+//! [`Storage::read_transaction_pii`]: super::Storage::read_transaction_pii
+//! [`ReadOnlyStorage`]: super::ReadOnlyStorage
+
+use super::error::StorageError;
+use aes_gcm::aead::{rand_core::RngCore, Aead, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use base64::Engine;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Prefix of encrypted values, followed by the base64 of nonce and ciphertext
+pub const ENCRYPTED_PREFIX: &str = "tapenc:v1:";
+
+/// Context the key of an agent database is derived for
+pub const ENCRYPTION_KEY_CONTEXT: &str = "tap-node/message-encryption/v1";
+
+/// Message type of DIDComm Presentations
+pub const PRESENTATION_MESSAGE_TYPE: &str = "https://didcomm.org/present-proof/3.0/presentation";
+
+/// Message types whose bodies carry the personal data of parties
+pub const PARTY_MESSAGE_TYPES: &[&str] = &[
+    "https://tap.rsvp/schema/1.0#Transfer",
+    "https://tap.rsvp/schema/1.0#Payment",
+    "https://tap.rsvp/schema/1.0#Lock",
+    "https://tap.rsvp/schema/1.0#Escrow",
+    "https://tap.rsvp/schema/1.0#UpdateParty",
+];
+
+/// Fields of the bodies of [`PARTY_MESSAGE_TYPES`] holding a party
+pub(crate) const PARTY_FIELDS: &[&str] =
+    &["originator", "beneficiary", "customer", "merchant", "party"];
+
+/// Whether messages of a type carry the personal data of parties
+pub fn carries_parties(message_type: &str) -> bool {
+    PARTY_MESSAGE_TYPES.contains(&message_type)
+}
+
+/// Message types whose stored copies are encrypted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageEncryption {
+    /// Message type URIs to encrypt
+    pub message_types: HashSet<String>,
+}
+
+impl Default for MessageEncryption {
+    /// Encrypt Presentations
+    fn default() -> Self {
+        Self::for_types([PRESENTATION_MESSAGE_TYPE])
+    }
+}
+
+impl MessageEncryption {
+    /// Encrypt messages of the given types
+    pub fn for_types<I, S>(message_types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            message_types: message_types.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Whether messages of a type are encrypted
+    pub fn applies_to(&self, message_type: &str) -> bool {
+        self.message_types.contains(message_type)
+    }
+}
+
+/// Encrypts and decrypts the stored copies of messages under one key
+#[derive(Clone)]
+pub struct MessageCipher {
+    cipher: Aes256Gcm,
+    policy: MessageEncryption,
+}
+
+impl fmt::Debug for MessageCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MessageCipher")
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MessageCipher {
+    /// Create a cipher for the given key and policy
+    pub fn new(key: &[u8; 32], policy: MessageEncryption) -> Self {
+        Self {
+            cipher: Aes256Gcm::new(key.into()),
+            policy,
+        }
+    }
+
+    /// Create a cipher for values other than messages, such as the personal
+    /// data of a transaction under its data key
+    pub fn for_data(key: &[u8; 32]) -> Self {
+        Self::new(key, MessageEncryption::for_types(Vec::<String>::new()))
+    }
+
+    /// Encrypt the data key of a transaction under this cipher's key
+    pub fn wrap_key(
+        &self,
+        data_key: &[u8; 32],
+        transaction_id: &str,
+    ) -> Result<String, StorageError> {
+        self.encrypt(
+            &base64::engine::general_purpose::STANDARD.encode(data_key),
+            &binding(&["data-key", transaction_id]),
+        )
+    }
+
+    /// Decrypt a data key encrypted with [`MessageCipher::wrap_key`] for the
+    /// same transaction
+    pub fn unwrap_key(
+        &self,
+        wrapped_key: &str,
+        transaction_id: &str,
+    ) -> Result<[u8; 32], StorageError> {
+        if !is_encrypted(wrapped_key) {
+            return Err(StorageError::Encryption(
+                "Data key is not encrypted".to_string(),
+            ));
+        }
+        base64::engine::general_purpose::STANDARD
+            .decode(self.decrypt(wrapped_key, &binding(&["data-key", transaction_id]))?)
+            .ok()
+            .and_then(|data_key| <[u8; 32]>::try_from(data_key).ok())
+            .ok_or_else(|| StorageError::Encryption("Invalid data key".to_string()))
+    }
+
+    /// Get the encryption policy
+    pub fn policy(&self) -> &MessageEncryption {
+        &self.policy
+    }
+
+    /// Whether messages of a type are encrypted
+    pub fn applies_to(&self, message_type: &str) -> bool {
+        self.policy.applies_to(message_type)
+    }
+
+    /// Encrypt a value with a fresh nonce, bound to the given associated
+    /// data
+    pub fn encrypt(&self, plaintext: &str, associated_data: &[u8]) -> Result<String, StorageError> {
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce);
+        let payload = Payload {
+            msg: plaintext.as_bytes(),
+            aad: associated_data,
+        };
+        let ciphertext = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), payload)
+            .map_err(|_| StorageError::Encryption("Failed to encrypt message".to_string()))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(format!(
+            "{}{}",
+            ENCRYPTED_PREFIX,
+            base64::engine::general_purpose::STANDARD.encode(sealed)
+        ))
+    }
+
+    /// Decrypt a value encrypted with the same associated data, returning
+    /// values that are not encrypted as they are
+    pub fn decrypt(&self, stored: &str, associated_data: &[u8]) -> Result<String, StorageError> {
+        let Some(encoded) = stored.strip_prefix(ENCRYPTED_PREFIX) else {
+            return Ok(stored.to_string());
+        };
+        let sealed = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(|e| StorageError::Encryption(format!("Invalid encrypted value: {}", e)))?;
+        if sealed.len() < 12 {
+            return Err(StorageError::Encryption(
+                "Encrypted value is truncated".to_string(),
+            ));
+        }
+        let (nonce, ciphertext) = sealed.split_at(12);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: associated_data,
+        };
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), payload)
+            .map_err(|_| {
+                StorageError::Encryption(
+                    "Failed to decrypt message, the key or the row it belongs to does not match"
+                        .to_string(),
+                )
+            })?;
+        String::from_utf8(plaintext)
+            .map_err(|e| StorageError::Encryption(format!("Decrypted message is not UTF-8: {}", e)))
+    }
+}
+
+/// Associated data binding an encrypted value to the row it is stored in,
+/// such as a message's ID and type
+///
+/// Each part is prefixed with its length, so that no two lists of parts
+/// give the same associated data.
+pub fn binding(parts: &[&str]) -> Vec<u8> {
+    let mut associated_data = Vec::new();
+    for part in parts {
+        associated_data.extend_from_slice(&(part.len() as u64).to_be_bytes());
+        associated_data.extend_from_slice(part.as_bytes());
+    }
+    associated_data
+}
+
+/// Associated data of the stored copies of a message
+pub fn message_binding(message_id: &str, message_type: &str) -> Vec<u8> {
+    binding(&["message", message_id, message_type])
+}
+
+/// Whether a stored value is encrypted
+pub fn is_encrypted(stored: &str) -> bool {
+    stored.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// Generate a random data key
+pub fn generate_data_key() -> [u8; 32] {
+    let mut key = [0u8; 32];
+    OsRng.fill_bytes(&mut key);
+    key
+}
+
+/// Type of the message in a plain or signed envelope
+///
+/// Encrypted envelopes give None: their type is only known once they are
+/// decrypted, and they are not readable without the agent's key anyway.
+pub(crate) fn envelope_message_type(raw_message: &str) -> Option<String> {
+    let envelope: serde_json::Value = serde_json::from_str(raw_message).ok()?;
+    let message = match envelope.get("payload").and_then(|p| p.as_str()) {
+        Some(payload) => {
+            let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD
+                .decode(payload.trim_end_matches('='))
+                .ok()?;
+            serde_json::from_slice(&payload).ok()?
+        }
+        None => envelope,
+    };
+    message
+        .get("type")
+        .and_then(|t| t.as_str())
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_wrong_key() {
+        let cipher = MessageCipher::new(&[7u8; 32], MessageEncryption::default());
+        let aad = message_binding("msg-1", PRESENTATION_MESSAGE_TYPE);
+        let sealed = cipher.encrypt(r#"{"id":"msg-1"}"#, &aad).unwrap();
+        assert!(is_encrypted(&sealed));
+        assert_ne!(sealed, cipher.encrypt(r#"{"id":"msg-1"}"#, &aad).unwrap());
+        assert_eq!(cipher.decrypt(&sealed, &aad).unwrap(), r#"{"id":"msg-1"}"#);
+        assert_eq!(cipher.decrypt("plain", &aad).unwrap(), "plain");
+
+        let other = MessageCipher::new(&[8u8; 32], MessageEncryption::default());
+        assert!(matches!(
+            other.decrypt(&sealed, &aad),
+            Err(StorageError::Encryption(_))
+        ));
+    }
+
+    #[test]
+    fn test_ciphertext_does_not_decrypt_in_another_row() {
+        let cipher = MessageCipher::new(&[7u8; 32], MessageEncryption::default());
+        let sealed = cipher
+            .encrypt("{}", &message_binding("msg-1", PRESENTATION_MESSAGE_TYPE))
+            .unwrap();
+        assert!(cipher
+            .decrypt(
+                &sealed,
+                &message_binding("msg-2", PRESENTATION_MESSAGE_TYPE)
+            )
+            .is_err());
+        assert!(cipher
+            .decrypt(&sealed, &message_binding("msg-1", PARTY_MESSAGE_TYPES[0]))
+            .is_err());
+        assert_ne!(binding(&["ab", "c"]), binding(&["a", "bc"]));
+    }
+
+    #[test]
+    fn test_wrap_data_key() {
+        let cipher = MessageCipher::new(&[7u8; 32], MessageEncryption::default());
+        let data_key = generate_data_key();
+        let wrapped = cipher.wrap_key(&data_key, "tx-1").unwrap();
+        assert_eq!(cipher.unwrap_key(&wrapped, "tx-1").unwrap(), data_key);
+        assert!(cipher.unwrap_key(&wrapped, "tx-2").is_err());
+
+        let other = MessageCipher::new(&[8u8; 32], MessageEncryption::default());
+        assert!(other.unwrap_key(&wrapped, "tx-1").is_err());
+        assert!(cipher.unwrap_key("plain", "tx-1").is_err());
+    }
+}
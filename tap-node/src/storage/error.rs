@@ -22,4 +22,28 @@ pub enum StorageError {
 
     #[error("Duplicate transaction: {0}")]
     DuplicateTransaction(String),
+
+    #[error("Storage quota exceeded: {0}")]
+    QuotaExceeded(String),
+
+    #[error("Projection rebuild failed: {0}")]
+    Projection(String),
+
+    #[error("Event replay failed: {0}")]
+    Replay(String),
+
+    #[error("Backup error: {0}")]
+    Backup(String),
+
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+
+    #[error("Access denied: {0}")]
+    AccessDenied(String),
+
+    #[error("Storage backend error: {0}")]
+    Backend(String),
+
+    #[error("Database {} is corrupt and could not be recovered", .0.db_path.display())]
+    Corrupted(Box<super::recovery::RecoveryReport>),
 }
@@ -0,0 +1,621 @@
+//! Append-only log of transaction and delivery state mutations
+//!
+//! Every mutation of the `transactions`, `transaction_agents` and `deliveries`
+//! tables is expressed as a [`StateEvent`] and applied through this module. In
+//! event sourcing mode ([`Storage::with_event_sourcing`]) the event is also
+//! appended to the `state_events` table, in the same database transaction as
+//! the mutation. Those tables are then projections of the log:
+//! [`Storage::rebuild_projections`] clears them and replays the log to
+//! reconstruct them, e.g. after corruption or to investigate how a
+//! transaction reached its state.
+//!
+//! Replayed mutations are timestamped with the time the event was recorded.
+//! Columns maintained by SQLite triggers, such as the `updated_at` column of
+//! transactions, take the time of the rebuild. Transactions keep their
+//! reference IDs and deliveries their IDs, while the internal row IDs of
+//! transactions and transaction agents are reassigned.
+//!
+//! The log also answers what was known about a transaction at an earlier
+//! point: [`Storage::get_transaction_state_at`] replays the events of one
+//! transaction up to a timestamp into a [`TransactionStateAt`], without
+//! touching the projections.
+//!
+//! [`Storage::with_event_sourcing`]: super::Storage::with_event_sourcing
+//! [`Storage::rebuild_projections`]: super::Storage::rebuild_projections
+//! [`Storage::get_transaction_state_at`]: super::Storage::get_transaction_state_at
+
+use super::error::StorageError;
+use super::models::{DeliveryStatus, DeliveryType, TransactionStatus, TransactionType};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteConnection;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::utils::canonical_transaction_hash;
+use tracing::debug;
+
+/// A mutation of the transaction and delivery tables
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StateEvent {
+    /// A Transfer or Payment message created a transaction
+    TransactionInserted {
+        /// The transaction's message
+        message: Box<PlainMessage>,
+        /// Canonical hash of the message as received, when the stored copy
+        /// has its parties' personal data sealed away
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        canonical_hash: Option<String>,
+    },
+    /// The status of a transaction changed
+    TransactionStatusUpdated {
+        /// Reference ID of the transaction
+        transaction_id: String,
+        /// The new status
+        status: String,
+    },
+    /// An agent was added to a transaction, or its role changed
+    TransactionAgentInserted {
+        /// Reference ID of the transaction
+        transaction_id: String,
+        /// DID of the agent
+        agent_did: String,
+        /// Role of the agent
+        role: String,
+    },
+    /// The status of an agent in a transaction changed
+    TransactionAgentStatusUpdated {
+        /// Reference ID of the transaction
+        transaction_id: String,
+        /// DID of the agent
+        agent_did: String,
+        /// The new status
+        status: String,
+    },
+    /// An agent was removed from a transaction, or replaced
+    TransactionAgentRemoved {
+        /// Reference ID of the transaction
+        transaction_id: String,
+        /// DID of the agent
+        agent_did: String,
+    },
+    /// A Reject message failed a transaction
+    RejectionRecorded {
+        /// Reference ID of the transaction
+        transaction_id: String,
+        /// Standardized rejection code
+        code: Option<String>,
+        /// Free-text rejection reason
+        reason: Option<String>,
+    },
+    /// A message delivery was created
+    DeliveryCreated {
+        /// ID of the delivery record, assigned when the delivery is created
+        delivery_id: Option<i64>,
+        /// ID of the delivered message
+        message_id: String,
+        /// The delivered message text
+        message_text: String,
+        /// DID of the recipient
+        recipient_did: String,
+        /// URL the message is delivered to
+        delivery_url: Option<String>,
+        /// How the message is delivered
+        delivery_type: DeliveryType,
+    },
+    /// A delivery attempt finished
+    DeliveryStatusUpdated {
+        /// ID of the delivery record
+        delivery_id: i64,
+        /// The new status
+        status: DeliveryStatus,
+        /// HTTP status code of the attempt
+        http_status_code: Option<i32>,
+        /// Error of the attempt
+        error_message: Option<String>,
+    },
+    /// A delivery is retried
+    DeliveryRetried {
+        /// ID of the delivery record
+        delivery_id: i64,
+    },
+    /// The next attempt of a delivery was scheduled or unscheduled
+    DeliveryRescheduled {
+        /// ID of the delivery record
+        delivery_id: i64,
+        /// When the delivery is next attempted, None if it is not
+        next_attempt_at: Option<String>,
+    },
+    /// A delivery was moved to a different endpoint
+    DeliveryReassigned {
+        /// ID of the delivery record
+        delivery_id: i64,
+        /// URL the message is delivered to from now on
+        delivery_url: String,
+    },
+}
+
+impl StateEvent {
+    /// Reference ID of the transaction the event belongs to, if any
+    pub fn transaction_id(&self) -> Option<&str> {
+        match self {
+            StateEvent::TransactionInserted { message, .. } => Some(&message.id),
+            StateEvent::TransactionStatusUpdated { transaction_id, .. }
+            | StateEvent::TransactionAgentInserted { transaction_id, .. }
+            | StateEvent::TransactionAgentStatusUpdated { transaction_id, .. }
+            | StateEvent::TransactionAgentRemoved { transaction_id, .. }
+            | StateEvent::RejectionRecorded { transaction_id, .. } => Some(transaction_id),
+            _ => None,
+        }
+    }
+}
+
+/// An event in the state event log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredStateEvent {
+    /// Position of the event in the log
+    pub sequence: i64,
+    /// Reference ID of the transaction the event belongs to, if any
+    pub transaction_id: Option<String>,
+    /// The mutation
+    pub event: StateEvent,
+    /// When the mutation was applied
+    pub recorded_at: String,
+}
+
+/// Outcome of a projection rebuild
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RebuildReport {
+    /// Number of events replayed
+    pub events: u64,
+    /// Number of transactions in the rebuilt projection
+    pub transactions: u64,
+    /// Number of deliveries in the rebuilt projection
+    pub deliveries: u64,
+}
+
+/// An agent's role and status in a transaction at a point in time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionAgentState {
+    /// DID of the agent
+    pub agent_did: String,
+    /// Role of the agent
+    pub role: String,
+    /// Status of the agent, e.g. `authorized`
+    pub status: String,
+}
+
+/// A transaction as it stood at a point in time, replayed from the state event log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionStateAt {
+    /// Reference ID of the transaction
+    pub transaction_id: String,
+    /// The Transfer or Payment message that created the transaction
+    pub message: Box<PlainMessage>,
+    /// Status of the transaction
+    pub status: TransactionStatus,
+    /// Standardized code of the Reject message that failed the transaction
+    pub rejection_code: Option<String>,
+    /// Free-text reason of the Reject message that failed the transaction
+    pub rejection_reason: Option<String>,
+    /// Agents of the transaction in the order they were added
+    pub agents: Vec<TransactionAgentState>,
+    /// When the transaction was created
+    pub created_at: String,
+    /// When the last replayed event was recorded
+    pub updated_at: String,
+    /// Sequence number of the last replayed event
+    pub sequence: i64,
+}
+
+impl TransactionStateAt {
+    /// Fold the events of one transaction, in sequence order, into its state
+    ///
+    /// Returns `None` if the events do not start with the transaction's
+    /// creation.
+    pub(crate) fn replay(events: &[StoredStateEvent]) -> Result<Option<Self>, StorageError> {
+        let mut events = events.iter();
+        let (first, message) = match events.next() {
+            Some(first) => match &first.event {
+                StateEvent::TransactionInserted { message, .. } => (first, message),
+                _ => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+
+        let mut state = TransactionStateAt {
+            transaction_id: message.id.clone(),
+            message: message.clone(),
+            status: TransactionStatus::Pending,
+            rejection_code: None,
+            rejection_reason: None,
+            agents: Vec::new(),
+            created_at: first.recorded_at.clone(),
+            updated_at: first.recorded_at.clone(),
+            sequence: first.sequence,
+        };
+
+        for stored in events {
+            match &stored.event {
+                StateEvent::TransactionStatusUpdated { status, .. } => {
+                    state.status = TransactionStatus::try_from(status.as_str())
+                        .map_err(StorageError::Replay)?;
+                }
+                StateEvent::TransactionAgentInserted {
+                    agent_did, role, ..
+                } => match state.agents.iter_mut().find(|a| &a.agent_did == agent_did) {
+                    Some(agent) => agent.role = role.clone(),
+                    None => state.agents.push(TransactionAgentState {
+                        agent_did: agent_did.clone(),
+                        role: role.clone(),
+                        status: "pending".to_string(),
+                    }),
+                },
+                StateEvent::TransactionAgentStatusUpdated {
+                    agent_did, status, ..
+                } => {
+                    if let Some(agent) = state.agents.iter_mut().find(|a| &a.agent_did == agent_did)
+                    {
+                        agent.status = status.clone();
+                    }
+                }
+                StateEvent::TransactionAgentRemoved { agent_did, .. } => {
+                    state.agents.retain(|a| &a.agent_did != agent_did);
+                }
+                StateEvent::RejectionRecorded { code, reason, .. } => {
+                    state.rejection_code = code.clone();
+                    state.rejection_reason = reason.clone();
+                }
+                _ => continue,
+            }
+            state.updated_at = stored.recorded_at.clone();
+            state.sequence = stored.sequence;
+        }
+
+        Ok(Some(state))
+    }
+}
+
+/// Append an applied event to the log
+pub(crate) async fn append(
+    conn: &mut SqliteConnection,
+    event: &StateEvent,
+    at: DateTime<Utc>,
+) -> Result<(), StorageError> {
+    sqlx::query(
+        r#"
+        INSERT INTO state_events (event_type, transaction_id, event_json, recorded_at)
+        VALUES (?1, ?2, ?3, ?4)
+        "#,
+    )
+    .bind(event_type(event))
+    .bind(event.transaction_id())
+    .bind(serde_json::to_string(event)?)
+    .bind(at.to_rfc3339())
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+fn event_type(event: &StateEvent) -> &'static str {
+    match event {
+        StateEvent::TransactionInserted { .. } => "transaction_inserted",
+        StateEvent::TransactionStatusUpdated { .. } => "transaction_status_updated",
+        StateEvent::TransactionAgentInserted { .. } => "transaction_agent_inserted",
+        StateEvent::TransactionAgentStatusUpdated { .. } => "transaction_agent_status_updated",
+        StateEvent::TransactionAgentRemoved { .. } => "transaction_agent_removed",
+        StateEvent::RejectionRecorded { .. } => "rejection_recorded",
+        StateEvent::DeliveryCreated { .. } => "delivery_created",
+        StateEvent::DeliveryStatusUpdated { .. } => "delivery_status_updated",
+        StateEvent::DeliveryRetried { .. } => "delivery_retried",
+        StateEvent::DeliveryRescheduled { .. } => "delivery_rescheduled",
+        StateEvent::DeliveryReassigned { .. } => "delivery_reassigned",
+    }
+}
+
+/// Apply an event to the projections as of time `at`
+///
+/// A `DeliveryCreated` event without a delivery ID is assigned the ID of the
+/// created record.
+pub(crate) async fn apply(
+    conn: &mut SqliteConnection,
+    event: &mut StateEvent,
+    at: DateTime<Utc>,
+) -> Result<(), StorageError> {
+    let timestamp = at.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+
+    match event {
+        StateEvent::TransactionInserted {
+            message,
+            canonical_hash,
+        } => {
+            let message_type_lower = message.type_.to_lowercase();
+            let tx_type = if message_type_lower.contains("transfer") {
+                TransactionType::Transfer
+            } else if message_type_lower.contains("payment") {
+                TransactionType::Payment
+            } else {
+                return Err(StorageError::InvalidTransactionType(message.type_.clone()));
+            };
+
+            // Use the PlainMessage ID as the reference_id since transaction_id is not serialized
+            let reference_id = message.id.clone();
+            debug!("Inserting transaction: {} ({})", reference_id, tx_type);
+
+            let result = sqlx::query(
+                r#"
+                INSERT INTO transactions (type, reference_id, from_did, to_did, thread_id, parent_thread_id, message_type, message_json, canonical_hash, created_at, updated_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?10)
+                "#,
+            )
+            .bind(tx_type.to_string())
+            .bind(&reference_id)
+            .bind(&message.from)
+            .bind(message.to.first())
+            .bind(&message.thid)
+            .bind(&message.pthid)
+            .bind(&message.type_)
+            .bind(sqlx::types::Json(serde_json::to_value(&*message)?))
+            .bind(
+                canonical_hash
+                    .clone()
+                    .unwrap_or_else(|| canonical_transaction_hash(&message.body, &message.from)),
+            )
+            .bind(timestamp)
+            .execute(conn)
+            .await;
+
+            match result {
+                Ok(_) => {
+                    debug!("Successfully inserted transaction: {}", reference_id);
+                    Ok(())
+                }
+                Err(sqlx::Error::Database(db_err)) => {
+                    if db_err.message().contains("UNIQUE") {
+                        Err(StorageError::DuplicateTransaction(reference_id))
+                    } else {
+                        Err(StorageError::Database(sqlx::Error::Database(db_err)))
+                    }
+                }
+                Err(e) => Err(StorageError::Database(e)),
+            }
+        }
+        StateEvent::TransactionStatusUpdated {
+            transaction_id,
+            status,
+        } => {
+            sqlx::query(
+                r#"
+                UPDATE transactions
+                SET status = ?1
+                WHERE reference_id = ?2
+                "#,
+            )
+            .bind(&*status)
+            .bind(&*transaction_id)
+            .execute(conn)
+            .await?;
+
+            Ok(())
+        }
+        StateEvent::TransactionAgentInserted {
+            transaction_id,
+            agent_did,
+            role,
+        } => {
+            let tx_internal_id = transaction_internal_id(conn, transaction_id).await?;
+            sqlx::query(
+                r#"
+                INSERT INTO transaction_agents (transaction_id, agent_did, agent_role, status, created_at, updated_at)
+                VALUES (?1, ?2, ?3, 'pending', ?4, ?4)
+                ON CONFLICT(transaction_id, agent_did) DO UPDATE SET
+                    agent_role = excluded.agent_role,
+                    updated_at = ?4
+                "#,
+            )
+            .bind(tx_internal_id)
+            .bind(&*agent_did)
+            .bind(&*role)
+            .bind(timestamp)
+            .execute(conn)
+            .await?;
+
+            Ok(())
+        }
+        StateEvent::TransactionAgentStatusUpdated {
+            transaction_id,
+            agent_did,
+            status,
+        } => {
+            let tx_internal_id = transaction_internal_id(conn, transaction_id).await?;
+            let result = sqlx::query(
+                r#"
+                UPDATE transaction_agents
+                SET status = ?1
+                WHERE transaction_id = ?2 AND agent_did = ?3
+                "#,
+            )
+            .bind(&*status)
+            .bind(tx_internal_id)
+            .bind(&*agent_did)
+            .execute(conn)
+            .await?;
+
+            if result.rows_affected() == 0 {
+                return Err(StorageError::NotFound(format!(
+                    "Agent {} not found for transaction {}",
+                    agent_did, transaction_id
+                )));
+            }
+
+            Ok(())
+        }
+        StateEvent::TransactionAgentRemoved {
+            transaction_id,
+            agent_did,
+        } => {
+            let tx_internal_id = transaction_internal_id(conn, transaction_id).await?;
+            sqlx::query(
+                r#"
+                DELETE FROM transaction_agents
+                WHERE transaction_id = ?1 AND agent_did = ?2
+                "#,
+            )
+            .bind(tx_internal_id)
+            .bind(&*agent_did)
+            .execute(conn)
+            .await?;
+
+            Ok(())
+        }
+        StateEvent::RejectionRecorded {
+            transaction_id,
+            code,
+            reason,
+        } => {
+            sqlx::query(
+                r#"
+                UPDATE transactions
+                SET rejection_code = ?1, rejection_reason = ?2
+                WHERE reference_id = ?3
+                "#,
+            )
+            .bind(&*code)
+            .bind(&*reason)
+            .bind(&*transaction_id)
+            .execute(conn)
+            .await?;
+
+            Ok(())
+        }
+        StateEvent::DeliveryCreated {
+            delivery_id,
+            message_id,
+            message_text,
+            recipient_did,
+            delivery_url,
+            delivery_type,
+        } => {
+            let result = sqlx::query(
+                r#"
+                INSERT INTO deliveries (id, message_id, message_text, recipient_did, delivery_url, delivery_type, status, retry_count, created_at, updated_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, 'pending', 0, ?7, ?7)
+                "#,
+            )
+            .bind(*delivery_id)
+            .bind(&*message_id)
+            .bind(&*message_text)
+            .bind(&*recipient_did)
+            .bind(&*delivery_url)
+            .bind(delivery_type.to_string())
+            .bind(at.format("%Y-%m-%d %H:%M:%S").to_string())
+            .execute(conn)
+            .await?;
+
+            *delivery_id = Some(result.last_insert_rowid());
+            Ok(())
+        }
+        StateEvent::DeliveryStatusUpdated {
+            delivery_id,
+            status,
+            http_status_code,
+            error_message,
+        } => {
+            let now = at.to_rfc3339();
+            let delivered_at = if *status == DeliveryStatus::Success {
+                Some(now.clone())
+            } else {
+                None
+            };
+
+            // Only failed deliveries wait for their next attempt
+            sqlx::query(
+                r#"
+                UPDATE deliveries
+                SET status = ?1, last_http_status_code = ?2, error_message = ?3, updated_at = ?4, delivered_at = ?5,
+                    next_attempt_at = CASE WHEN ?1 = 'failed' THEN next_attempt_at END
+                WHERE id = ?6
+                "#,
+            )
+            .bind(status.to_string())
+            .bind(*http_status_code)
+            .bind(&*error_message)
+            .bind(now)
+            .bind(delivered_at)
+            .bind(*delivery_id)
+            .execute(conn)
+            .await?;
+
+            Ok(())
+        }
+        StateEvent::DeliveryRetried { delivery_id } => {
+            sqlx::query(
+                r#"
+                UPDATE deliveries
+                SET retry_count = retry_count + 1, updated_at = ?1
+                WHERE id = ?2
+                "#,
+            )
+            .bind(at.to_rfc3339())
+            .bind(*delivery_id)
+            .execute(conn)
+            .await?;
+
+            Ok(())
+        }
+        StateEvent::DeliveryRescheduled {
+            delivery_id,
+            next_attempt_at,
+        } => {
+            sqlx::query(
+                r#"
+                UPDATE deliveries
+                SET next_attempt_at = ?1, updated_at = ?2
+                WHERE id = ?3
+                "#,
+            )
+            .bind(&*next_attempt_at)
+            .bind(at.to_rfc3339())
+            .bind(*delivery_id)
+            .execute(conn)
+            .await?;
+
+            Ok(())
+        }
+        StateEvent::DeliveryReassigned {
+            delivery_id,
+            delivery_url,
+        } => {
+            sqlx::query(
+                r#"
+                UPDATE deliveries
+                SET delivery_url = ?1, updated_at = ?2
+                WHERE id = ?3
+                "#,
+            )
+            .bind(&*delivery_url)
+            .bind(at.to_rfc3339())
+            .bind(*delivery_id)
+            .execute(conn)
+            .await?;
+
+            Ok(())
+        }
+    }
+}
+
+/// Row ID of a transaction, which transaction agents refer to
+async fn transaction_internal_id(
+    conn: &mut SqliteConnection,
+    transaction_id: &str,
+) -> Result<i64, StorageError> {
+    sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT id FROM transactions WHERE reference_id = ?1
+        "#,
+    )
+    .bind(transaction_id)
+    .fetch_optional(conn)
+    .await?
+    .ok_or_else(|| StorageError::NotFound(format!("Transaction {} not found", transaction_id)))
+}
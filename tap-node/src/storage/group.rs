@@ -0,0 +1,166 @@
+//! Agent groups and organization-wide storage views
+//!
+//! A VASP may run a separate agent DID per business line while still needing
+//! an organization-wide view of its activity. An [`AgentGroup`] names a set of
+//! member agents, and a [`GroupStorageView`] queries all member databases at
+//! once. Each agent keeps its own database; the view only aggregates reads.
+//!
+//! Records present in several member databases, such as a transfer between two
+//! business lines of the same organization, appear once in the view with every
+//! member that holds a copy listed in [`GroupRecord::agents`].
+
+use super::db::Storage;
+use super::error::StorageError;
+use super::models::{Message, MessageDirection, Transaction};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A named set of agents belonging to one organization
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentGroup {
+    /// Unique identifier of the group
+    pub id: String,
+    /// Human-readable name, e.g. the organization's name
+    pub name: String,
+    /// DIDs of the member agents
+    pub members: Vec<String>,
+}
+
+impl AgentGroup {
+    /// Create a group with the given members
+    pub fn new(
+        id: impl Into<String>,
+        name: impl Into<String>,
+        members: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            members: members.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Whether an agent is a member of the group
+    pub fn contains(&self, agent_did: &str) -> bool {
+        self.members.iter().any(|member| member == agent_did)
+    }
+}
+
+/// A record from a group view, with the member agents holding it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupRecord<T> {
+    /// The record
+    #[serde(flatten)]
+    pub record: T,
+    /// DIDs of the member agents whose databases hold the record
+    pub agents: Vec<String>,
+}
+
+/// Read-only view across the databases of a group's members
+#[derive(Clone)]
+pub struct GroupStorageView {
+    group: AgentGroup,
+    storages: Vec<(String, Arc<Storage>)>,
+}
+
+impl GroupStorageView {
+    /// Create a view over the given member storages
+    pub fn new(group: AgentGroup, storages: Vec<(String, Arc<Storage>)>) -> Self {
+        Self { group, storages }
+    }
+
+    /// The group this view covers
+    pub fn group(&self) -> &AgentGroup {
+        &self.group
+    }
+
+    /// Storage of a single member agent
+    pub fn agent_storage(&self, agent_did: &str) -> Option<&Arc<Storage>> {
+        self.storages
+            .iter()
+            .find(|(did, _)| did == agent_did)
+            .map(|(_, storage)| storage)
+    }
+
+    /// List transactions of all members, newest first
+    pub async fn list_transactions(
+        &self,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<GroupRecord<Transaction>>, StorageError> {
+        let mut records = Vec::new();
+        for (agent_did, storage) in &self.storages {
+            let transactions = storage.list_transactions(limit + offset, 0).await?;
+            records.extend(transactions.into_iter().map(|tx| (agent_did.clone(), tx)));
+        }
+
+        let mut merged = merge(records, |tx| tx.reference_id.clone());
+        merged.sort_by(|a, b| b.record.created_at.cmp(&a.record.created_at));
+        Ok(page(merged, limit, offset))
+    }
+
+    /// Find a transaction by its reference ID in any member database
+    pub async fn get_transaction_by_id(
+        &self,
+        reference_id: &str,
+    ) -> Result<Option<GroupRecord<Transaction>>, StorageError> {
+        let mut records = Vec::new();
+        for (agent_did, storage) in &self.storages {
+            if let Some(tx) = storage.get_transaction_by_id(reference_id).await? {
+                records.push((agent_did.clone(), tx));
+            }
+        }
+        Ok(merge(records, |tx| tx.reference_id.clone()).pop())
+    }
+
+    /// List messages of all members, newest first
+    ///
+    /// A message exchanged between two members is listed once per direction.
+    pub async fn list_messages(
+        &self,
+        limit: u32,
+        offset: u32,
+        direction: Option<MessageDirection>,
+    ) -> Result<Vec<GroupRecord<Message>>, StorageError> {
+        let mut records = Vec::new();
+        for (agent_did, storage) in &self.storages {
+            let messages = storage
+                .list_messages(limit + offset, 0, direction.clone())
+                .await?;
+            records.extend(messages.into_iter().map(|m| (agent_did.clone(), m)));
+        }
+
+        let mut merged = merge(records, |m| format!("{}:{}", m.direction, m.message_id));
+        merged.sort_by(|a, b| b.record.created_at.cmp(&a.record.created_at));
+        Ok(page(merged, limit, offset))
+    }
+}
+
+/// Collapse records with the same key, collecting the agents holding them
+fn merge<T>(records: Vec<(String, T)>, key: impl Fn(&T) -> String) -> Vec<GroupRecord<T>> {
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut merged: Vec<GroupRecord<T>> = Vec::new();
+    for (agent_did, record) in records {
+        let key = key(&record);
+        match index.get(&key) {
+            Some(&i) => merged[i].agents.push(agent_did),
+            None => {
+                index.insert(key, merged.len());
+                merged.push(GroupRecord {
+                    record,
+                    agents: vec![agent_did],
+                });
+            }
+        }
+    }
+    merged
+}
+
+fn page<T>(records: Vec<T>, limit: u32, offset: u32) -> Vec<T> {
+    records
+        .into_iter()
+        .skip(offset as usize)
+        .take(limit as usize)
+        .collect()
+}
@@ -19,6 +19,17 @@
 //! - **Idempotent Operations**: Duplicate messages are silently ignored
 //! - **Direction Tracking**: Messages are tagged as incoming or outgoing
 //! - **Thread Tracking**: Full support for DIDComm thread and parent thread IDs
+//! - **Read Handles**: List, search and report queries can run on a separate
+//!   read-only pool or a replica through [`ReadOnlyStorage`]
+//! - **Corruption Recovery**: Databases can be checked for corruption when
+//!   opened and recovered as configured by a [`StorageRecovery`]
+//! - **Online Backups**: [`Storage::backup_to`] snapshots a database while it
+//!   stays in use, and [`Storage::restore_from`] restores one
+//! - **Postgres Migration**: with the `postgres` feature, agent databases can
+//!   be copied into Postgres and the copy verified, see [`postgres`]
+//! - **Storage Backends**: messages, transactions and deliveries can run on
+//!   a [`StorageBackend`] shared by several nodes, e.g. Postgres with the
+//!   `postgres` feature, see [`backend`]
 //!
 //! # Usage
 //!
@@ -64,25 +75,80 @@
 #[cfg(feature = "storage")]
 pub mod agent_storage_manager;
 #[cfg(feature = "storage")]
+pub mod backend;
+#[cfg(feature = "storage")]
+pub mod backup;
+#[cfg(feature = "storage")]
 pub mod db;
 #[cfg(feature = "storage")]
+pub mod encryption;
+#[cfg(feature = "storage")]
 pub mod error;
 #[cfg(feature = "storage")]
+pub mod event_store;
+#[cfg(feature = "storage")]
+pub mod group;
+#[cfg(feature = "storage")]
 pub mod models;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "postgres")]
+pub mod postgres_backend;
+#[cfg(feature = "storage")]
+pub mod quota;
+#[cfg(feature = "storage")]
+pub mod read_only;
+#[cfg(feature = "storage")]
+pub mod recovery;
+pub mod source;
 
 #[cfg(feature = "storage")]
 pub use agent_storage_manager::AgentStorageManager;
 #[cfg(feature = "storage")]
+pub use backend::StorageBackend;
+#[cfg(feature = "storage")]
 pub use db::Storage;
 #[cfg(feature = "storage")]
+pub use encryption::{MessageCipher, MessageEncryption};
+#[cfg(feature = "storage")]
 pub use error::StorageError;
 #[cfg(feature = "storage")]
+pub use event_store::{
+    RebuildReport, StateEvent, StoredStateEvent, TransactionAgentState, TransactionStateAt,
+};
+#[cfg(feature = "storage")]
+pub use group::{AgentGroup, GroupRecord, GroupStorageView};
+#[cfg(feature = "storage")]
 pub use models::{
-    Customer, CustomerIdentifier, CustomerRelationship, DecisionLogEntry, DecisionStatus,
-    DecisionType, Delivery, DeliveryStatus, DeliveryType, IdentifierType, Message,
-    MessageDirection, Received, ReceivedStatus, SchemaType, SourceType, Transaction,
-    TransactionStatus, TransactionType,
+    AddressSighting, AssetBaseline, AuthorizationChallenge, ChallengeStatus, Connection,
+    ConnectionStatus, ConversionStatus, CounterpartyBaseline, CounterpartyProfile,
+    CounterpartyStats, Customer, CustomerErasure, CustomerIdentifier, CustomerMerge,
+    CustomerRelationship, DeadLetter, DecisionLogEntry, DecisionStatus, DecisionType, Delivery,
+    DeliveryStatus, DeliveryType, DocumentStatus, Draft, DraftKind, DraftStatus, ForwardStatus,
+    ForwardedMessage, IdentifierType, KeyAttestationRecord, KeyAttestationStatus, LatencyStats,
+    MailboxMessage, MailboxStatus, MailboxSummary, MatchSignal, MergeStatus, Message,
+    MessageDirection, MessageVerification, PiiAccess, PinnedDidDocument,
+    PresentationVerificationRecord, PresentationVerificationStatus, Received, ReceivedFilter,
+    ReceivedStatus, ResponseTimeStats, ResponseTimer, ResponseTimerStatus, ReusedAddress,
+    ReviewItem, ReviewStatus, ScheduledJobRecord, ScheduledJobStatus, SchemaType,
+    SettlementAddressReservation, SettlementConversion, TimelineEntry, Transaction,
+    TransactionDocument, TransactionGraph, TransactionGraphNode, TransactionParticipant,
+    TransactionPii, TransactionStatus, TransactionType, TransactionValuation, TransactionWarning,
+};
+
+#[cfg(feature = "postgres")]
+pub use postgres_backend::PostgresBackend;
+#[cfg(feature = "storage")]
+pub use quota::{
+    AgentStorageUsage, QuotaAction, StorageQuota, StorageQuotas, StorageUsage, TableUsage,
+};
+#[cfg(feature = "storage")]
+pub use read_only::{ReadOnlyStorage, ReadReplicaConfig};
+#[cfg(feature = "storage")]
+pub use recovery::{
+    IntegrityReport, RecoveryAttempt, RecoveryReport, RecoveryStrategy, StorageRecovery,
 };
+pub use source::SourceType;
 
 #[cfg(not(feature = "storage"))]
 pub use mock::*;
@@ -1,8 +1,11 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::str::FromStr;
 use tap_msg::utils::NameHashable;
 
+pub use super::source::SourceType;
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum TransactionType {
@@ -79,6 +82,14 @@ pub struct Transaction {
     pub message_type: String,
     pub status: TransactionStatus,
     pub message_json: serde_json::Value,
+    /// Standardized code of the Reject message that failed the transaction
+    pub rejection_code: Option<String>,
+    /// Free-text reason of the Reject message that failed the transaction
+    pub rejection_reason: Option<String>,
+    /// Hash of the canonicalized initiating message body and sender DID,
+    /// shared by every copy of the transaction, see
+    /// [`canonical_transaction_hash`](tap_msg::utils::canonical_transaction_hash)
+    pub canonical_hash: Option<String>,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -131,6 +142,8 @@ pub enum DeliveryStatus {
     Pending,
     Success,
     Failed,
+    /// Will not be attempted again
+    Cancelled,
 }
 
 impl fmt::Display for DeliveryStatus {
@@ -139,6 +152,7 @@ impl fmt::Display for DeliveryStatus {
             DeliveryStatus::Pending => write!(f, "pending"),
             DeliveryStatus::Success => write!(f, "success"),
             DeliveryStatus::Failed => write!(f, "failed"),
+            DeliveryStatus::Cancelled => write!(f, "cancelled"),
         }
     }
 }
@@ -151,6 +165,7 @@ impl TryFrom<&str> for DeliveryStatus {
             "pending" => Ok(DeliveryStatus::Pending),
             "success" => Ok(DeliveryStatus::Success),
             "failed" => Ok(DeliveryStatus::Failed),
+            "cancelled" => Ok(DeliveryStatus::Cancelled),
             _ => Err(format!("Invalid delivery status: {}", value)),
         }
     }
@@ -225,56 +240,8 @@ pub struct Delivery {
     pub created_at: String,
     pub updated_at: String,
     pub delivered_at: Option<String>,
-}
-
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum SourceType {
-    /// HTTP/HTTPS delivery from external endpoints
-    Https,
-    /// Internal delivery from agents within the same node
-    Internal,
-    /// WebSocket connection
-    WebSocket,
-    /// Return path delivery
-    ReturnPath,
-    /// Pickup delivery
-    Pickup,
-}
-
-impl fmt::Display for SourceType {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            SourceType::Https => write!(f, "https"),
-            SourceType::Internal => write!(f, "internal"),
-            SourceType::WebSocket => write!(f, "websocket"),
-            SourceType::ReturnPath => write!(f, "return_path"),
-            SourceType::Pickup => write!(f, "pickup"),
-        }
-    }
-}
-
-impl TryFrom<&str> for SourceType {
-    type Error = String;
-
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        match value {
-            "https" => Ok(SourceType::Https),
-            "internal" => Ok(SourceType::Internal),
-            "websocket" => Ok(SourceType::WebSocket),
-            "return_path" => Ok(SourceType::ReturnPath),
-            "pickup" => Ok(SourceType::Pickup),
-            _ => Err(format!("Invalid source type: {}", value)),
-        }
-    }
-}
-
-impl FromStr for SourceType {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Self::try_from(s)
-    }
+    /// When a failed delivery is retried, None if it is not
+    pub next_attempt_at: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -403,6 +370,120 @@ pub struct Customer {
     pub updated_at: String,
 }
 
+/// Tombstone recorded when a customer's personal data is erased
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomerErasure {
+    pub customer_id: String,
+    pub agent_did: String,
+    /// SHA-256 (hex) of the customer record as it was before erasure
+    pub profile_hash: String,
+    /// Name hash of the erased customer, kept for Travel Rule matching
+    pub name_hash: Option<String>,
+    pub reason: Option<String>,
+    pub erased_at: String,
+}
+
+/// Decrypted personal data of a party of a transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionPii {
+    pub transaction_id: String,
+    /// Role of the party in the transaction, e.g. `originator`
+    pub role: String,
+    pub customer_id: Option<String>,
+    /// The party as it appeared in the transaction
+    pub data: serde_json::Value,
+    pub created_at: String,
+}
+
+/// A read of the personal data of a transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PiiAccess {
+    pub id: i64,
+    pub transaction_id: String,
+    pub accessor: String,
+    pub purpose: String,
+    /// Whether the data was returned; false once access was revoked
+    pub granted: bool,
+    pub accessed_at: String,
+}
+
+/// Status of a customer merge proposal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStatus {
+    Proposed,
+    Confirmed,
+    Rejected,
+}
+
+impl fmt::Display for MergeStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeStatus::Proposed => write!(f, "proposed"),
+            MergeStatus::Confirmed => write!(f, "confirmed"),
+            MergeStatus::Rejected => write!(f, "rejected"),
+        }
+    }
+}
+
+impl TryFrom<&str> for MergeStatus {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "proposed" => Ok(MergeStatus::Proposed),
+            "confirmed" => Ok(MergeStatus::Confirmed),
+            "rejected" => Ok(MergeStatus::Rejected),
+            _ => Err(format!("Invalid merge status: {}", value)),
+        }
+    }
+}
+
+impl FromStr for MergeStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s)
+    }
+}
+
+/// Evidence that two customer records describe the same party
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchSignal {
+    /// Normalized names are identical
+    NameExact,
+    /// Normalized names are within the configured similarity
+    NameSimilar,
+    /// Same date of birth
+    BirthDate,
+    /// Same national identifier (passport, tax ID, ...)
+    NationalIdentifier,
+    /// Same LEI
+    LeiCode,
+    /// Same country and postal code or locality
+    Address,
+}
+
+/// Proposal to merge a duplicate customer record into a surviving one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomerMerge {
+    pub id: String,
+    pub agent_did: String,
+    /// Customer that survives the merge
+    pub primary_customer_id: String,
+    /// Customer folded into the primary when the merge is confirmed
+    pub duplicate_customer_id: String,
+    /// Match confidence between 0 and 1
+    pub confidence: f64,
+    pub signals: Vec<MatchSignal>,
+    pub status: MergeStatus,
+    /// The duplicate record as it was when the merge was confirmed
+    pub duplicate_snapshot: Option<serde_json::Value>,
+    pub created_at: String,
+    pub resolved_at: Option<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum IdentifierType {
@@ -571,6 +652,381 @@ pub struct DecisionLogEntry {
     pub resolved_at: Option<String>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewStatus {
+    Pending,
+    Approved,
+    Rejected,
+    Expired,
+}
+
+impl fmt::Display for ReviewStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReviewStatus::Pending => write!(f, "pending"),
+            ReviewStatus::Approved => write!(f, "approved"),
+            ReviewStatus::Rejected => write!(f, "rejected"),
+            ReviewStatus::Expired => write!(f, "expired"),
+        }
+    }
+}
+
+impl TryFrom<&str> for ReviewStatus {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "pending" => Ok(ReviewStatus::Pending),
+            "approved" => Ok(ReviewStatus::Approved),
+            "rejected" => Ok(ReviewStatus::Rejected),
+            "expired" => Ok(ReviewStatus::Expired),
+            _ => Err(format!("Invalid review status: {}", value)),
+        }
+    }
+}
+
+impl FromStr for ReviewStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s)
+    }
+}
+
+/// A transaction held for manual review by the policy engine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewItem {
+    pub id: i64,
+    pub transaction_id: String,
+    /// Our agent that authorizes or rejects the transaction once reviewed
+    pub agent_did: String,
+    pub rule: String,
+    pub reason: String,
+    pub details: serde_json::Value,
+    pub status: ReviewStatus,
+    pub reviewer: Option<String>,
+    pub review_note: Option<String>,
+    /// ID of the Authorize or Reject message sent for the review
+    pub message_id: Option<String>,
+    pub created_at: String,
+    pub reviewed_at: Option<String>,
+}
+
+/// A warning attached to a transaction by a policy rule that tripped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionWarning {
+    pub id: i64,
+    pub transaction_id: String,
+    pub rule: String,
+    /// Action the rule requested, e.g. `warn` or `manual_review`
+    pub action: String,
+    pub reason: String,
+    pub details: serde_json::Value,
+    pub created_at: String,
+}
+
+/// Outcome of checking a received document against its digest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentStatus {
+    /// Embedded content matches the digest of the reference
+    Verified,
+    /// Content or digest is missing, e.g. a document hosted at a URL
+    Unverified,
+    /// Embedded content is undecodable or does not match the digest
+    Mismatch,
+}
+
+impl fmt::Display for DocumentStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DocumentStatus::Verified => write!(f, "verified"),
+            DocumentStatus::Unverified => write!(f, "unverified"),
+            DocumentStatus::Mismatch => write!(f, "mismatch"),
+        }
+    }
+}
+
+impl TryFrom<&str> for DocumentStatus {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "verified" => Ok(DocumentStatus::Verified),
+            "unverified" => Ok(DocumentStatus::Unverified),
+            "mismatch" => Ok(DocumentStatus::Mismatch),
+            _ => Err(format!("Invalid document status: {}", value)),
+        }
+    }
+}
+
+impl FromStr for DocumentStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s)
+    }
+}
+
+/// A document referenced by the invoice of a received Payment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionDocument {
+    pub id: i64,
+    pub transaction_id: String,
+    /// Message that referenced the document
+    pub message_id: String,
+    /// ID of the document within the invoice
+    pub document_id: String,
+    pub document_type: Option<String>,
+    pub url: Option<String>,
+    pub mime_type: Option<String>,
+    /// Hex-encoded SHA-256 digest given by the sender
+    pub hash: Option<String>,
+    /// Decoded content of an embedded document
+    pub content: Option<Vec<u8>>,
+    pub status: DocumentStatus,
+    pub created_at: String,
+}
+
+/// Outcome of verifying a key attestation sent with a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyAttestationStatus {
+    Verified,
+    Invalid,
+}
+
+impl fmt::Display for KeyAttestationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyAttestationStatus::Verified => write!(f, "verified"),
+            KeyAttestationStatus::Invalid => write!(f, "invalid"),
+        }
+    }
+}
+
+impl TryFrom<&str> for KeyAttestationStatus {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "verified" => Ok(KeyAttestationStatus::Verified),
+            "invalid" => Ok(KeyAttestationStatus::Invalid),
+            _ => Err(format!("Invalid key attestation status: {}", value)),
+        }
+    }
+}
+
+impl FromStr for KeyAttestationStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s)
+    }
+}
+
+/// Key attestation sent with an incoming message, as verified on receipt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyAttestationRecord {
+    pub id: i64,
+    pub message_id: String,
+    /// The sending agent
+    pub agent_did: String,
+    /// Organization that issued the attestation, if it verified
+    pub organization: Option<String>,
+    /// Key of the agent the attestation is limited to
+    pub kid: Option<String>,
+    pub status: KeyAttestationStatus,
+    /// Why verification failed
+    pub reason: Option<String>,
+    /// Hash of the organization's DID document the signature verified against
+    pub did_doc_hash: Option<String>,
+    /// Unix time in seconds after which the attestation is no longer valid
+    pub expires_at: Option<i64>,
+    pub verified_at: String,
+}
+
+/// Outcome of verifying a verifiable presentation sent with a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresentationVerificationStatus {
+    Verified,
+    Invalid,
+}
+
+impl fmt::Display for PresentationVerificationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PresentationVerificationStatus::Verified => write!(f, "verified"),
+            PresentationVerificationStatus::Invalid => write!(f, "invalid"),
+        }
+    }
+}
+
+impl TryFrom<&str> for PresentationVerificationStatus {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "verified" => Ok(PresentationVerificationStatus::Verified),
+            "invalid" => Ok(PresentationVerificationStatus::Invalid),
+            _ => Err(format!(
+                "Invalid presentation verification status: {}",
+                value
+            )),
+        }
+    }
+}
+
+impl FromStr for PresentationVerificationStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s)
+    }
+}
+
+/// Verifiable presentation sent with an incoming message, as verified on
+/// receipt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresentationVerificationRecord {
+    pub id: i64,
+    pub message_id: String,
+    /// Position of the presentation among those of the message
+    pub presentation_index: i64,
+    /// Thread of the message
+    pub transaction_id: Option<String>,
+    pub sender_did: String,
+    /// Holder of the presentation, if it verified
+    pub holder: Option<String>,
+    pub status: PresentationVerificationStatus,
+    /// Why verification failed
+    pub reason: Option<String>,
+    /// Credentials held by the presentation, if it verified
+    pub credentials: Vec<crate::credentials::PresentedCredential>,
+    pub verified_at: String,
+}
+
+/// Signature verification recorded for an incoming signed message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageVerification {
+    pub id: i64,
+    pub message_id: String,
+    pub signer_did: String,
+    /// Verification method that validated the signature
+    pub kid: String,
+    pub algorithm: String,
+    /// SHA-256 hex digest of the signer's DID document used for verification
+    pub did_doc_hash: String,
+    pub verified_at: String,
+    pub created_at: String,
+}
+
+/// A DID document pinned when it was used to verify a message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PinnedDidDocument {
+    /// SHA-256 hex digest of the document, as recorded on verifications
+    pub did_doc_hash: String,
+    pub did: String,
+    pub document: serde_json::Value,
+    pub pinned_at: String,
+}
+
+/// A message in a transaction's history with its signature verifications
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineEntry {
+    pub message: Message,
+    pub verifications: Vec<MessageVerification>,
+    /// Agents the message added to the transaction
+    pub participants_added: Vec<TransactionParticipant>,
+    /// Agents the message removed from the transaction or replaced
+    pub participants_removed: Vec<TransactionParticipant>,
+}
+
+/// An agent's membership in a transaction, from the message that added it
+/// to the message that removed or replaced it, if any
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionParticipant {
+    pub id: i64,
+    /// Reference ID of the transaction
+    pub transaction_id: String,
+    pub agent_did: String,
+    /// Role of the agent in the message that added it
+    pub role: Option<String>,
+    /// Parties the agent acts for
+    pub for_parties: Vec<String>,
+    /// DID of the sender of the message that added the agent
+    pub added_by: String,
+    /// ID of the message that added the agent
+    pub added_in: String,
+    pub added_at: String,
+    /// DID of the sender of the message that removed the agent
+    pub removed_by: Option<String>,
+    /// ID of the message that removed the agent
+    pub removed_in: Option<String>,
+    pub removed_at: Option<String>,
+    /// DID of the agent that replaced this one
+    pub replaced_by: Option<String>,
+}
+
+impl TransactionParticipant {
+    /// Whether the agent is still part of the transaction
+    pub fn is_current(&self) -> bool {
+        self.removed_at.is_none()
+    }
+}
+
+/// A transaction in a [`TransactionGraph`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionGraphNode {
+    /// Reference ID of the transaction
+    pub transaction_id: String,
+    /// Reference ID of the transaction this one was started from, if it is
+    /// in the graph
+    pub parent_id: Option<String>,
+    /// Parent thread ID (pthid) of the transaction's message, which may name
+    /// a thread without a stored transaction, e.g. a Connect
+    pub parent_thread_id: Option<String>,
+    pub transaction_type: TransactionType,
+    pub message_type: String,
+    pub status: TransactionStatus,
+    /// Distance from the root of the graph
+    pub depth: u32,
+    pub created_at: String,
+}
+
+/// Transactions linked through the parent thread IDs of their messages
+///
+/// The graph is a tree rooted at the earliest known ancestor of the
+/// transaction it was requested for, with nodes ordered depth-first, each
+/// node's children by creation time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionGraph {
+    /// Reference ID of the root transaction
+    pub root_id: String,
+    pub nodes: Vec<TransactionGraphNode>,
+}
+
+impl TransactionGraph {
+    /// Get a transaction of the graph
+    pub fn get(&self, transaction_id: &str) -> Option<&TransactionGraphNode> {
+        self.nodes
+            .iter()
+            .find(|n| n.transaction_id == transaction_id)
+    }
+
+    /// Transactions started from a transaction of the graph
+    pub fn children<'a>(
+        &'a self,
+        transaction_id: &'a str,
+    ) -> impl Iterator<Item = &'a TransactionGraphNode> + 'a {
+        self.nodes
+            .iter()
+            .filter(move |n| n.parent_id.as_deref() == Some(transaction_id))
+    }
+}
+
 // Implement NameHashable for Customer
 impl NameHashable for Customer {}
 
@@ -616,3 +1072,798 @@ impl Customer {
         }
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChallengeStatus {
+    Pending,
+    Redeemed,
+    Expired,
+}
+
+impl fmt::Display for ChallengeStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChallengeStatus::Pending => write!(f, "pending"),
+            ChallengeStatus::Redeemed => write!(f, "redeemed"),
+            ChallengeStatus::Expired => write!(f, "expired"),
+        }
+    }
+}
+
+impl TryFrom<&str> for ChallengeStatus {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "pending" => Ok(ChallengeStatus::Pending),
+            "redeemed" => Ok(ChallengeStatus::Redeemed),
+            "expired" => Ok(ChallengeStatus::Expired),
+            _ => Err(format!("Invalid challenge status: {}", value)),
+        }
+    }
+}
+
+/// A nonce bound to an authorization URL sent in an AuthorizationRequired message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorizationChallenge {
+    pub nonce: String,
+    pub transaction_id: String,
+    /// Our agent that authorizes the transaction once the nonce is redeemed
+    pub agent_did: String,
+    /// Agent the AuthorizationRequired message was sent to
+    pub recipient_did: String,
+    /// Party type required to open the URL, e.g. "customer"
+    pub party_type: Option<String>,
+    pub authorization_url: String,
+    pub status: ChallengeStatus,
+    /// ID of the Authorize message sent when the nonce was redeemed
+    pub message_id: Option<String>,
+    pub expires_at: String,
+    pub created_at: String,
+    pub redeemed_at: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MailboxStatus {
+    Pending,
+    Fetched,
+    Delivered,
+    Expired,
+}
+
+impl fmt::Display for MailboxStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MailboxStatus::Pending => write!(f, "pending"),
+            MailboxStatus::Fetched => write!(f, "fetched"),
+            MailboxStatus::Delivered => write!(f, "delivered"),
+            MailboxStatus::Expired => write!(f, "expired"),
+        }
+    }
+}
+
+impl TryFrom<&str> for MailboxStatus {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "pending" => Ok(MailboxStatus::Pending),
+            "fetched" => Ok(MailboxStatus::Fetched),
+            "delivered" => Ok(MailboxStatus::Delivered),
+            "expired" => Ok(MailboxStatus::Expired),
+            _ => Err(format!("Invalid mailbox status: {}", value)),
+        }
+    }
+}
+
+/// A message held for a remote agent that polls the node for its messages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MailboxMessage {
+    /// Sequence number the remote agent uses as its cursor
+    pub seq: i64,
+    /// Remote agent the message is addressed to
+    pub recipient_did: String,
+    /// Our agent that sent the message
+    pub sender_did: String,
+    pub message_id: String,
+    /// The signed or encrypted message
+    pub message_text: String,
+    /// Delivery record in the sender's database
+    pub delivery_id: Option<i64>,
+    pub status: MailboxStatus,
+    pub expires_at: String,
+    pub created_at: String,
+    pub fetched_at: Option<String>,
+    pub delivered_at: Option<String>,
+}
+
+/// A message set aside after a stage of its processing timed out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub id: i64,
+    /// ID of the message, if known before the timeout
+    pub message_id: Option<String>,
+    /// The message as it was received or was about to be sent
+    pub raw_message: String,
+    /// The stage that timed out
+    pub stage: crate::timeouts::ProcessingStage,
+    /// Why the message was set aside
+    pub reason: String,
+    pub created_at: String,
+}
+
+/// What became of a message a node was to forward to its upstream node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForwardStatus {
+    /// The upstream node accepted the message
+    Forwarded,
+    /// The message was not forwarded to break a forwarding loop
+    Refused,
+    /// The upstream node was unreachable or turned the message down
+    Failed,
+}
+
+impl fmt::Display for ForwardStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ForwardStatus::Forwarded => write!(f, "forwarded"),
+            ForwardStatus::Refused => write!(f, "refused"),
+            ForwardStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+impl TryFrom<&str> for ForwardStatus {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "forwarded" => Ok(ForwardStatus::Forwarded),
+            "refused" => Ok(ForwardStatus::Refused),
+            "failed" => Ok(ForwardStatus::Failed),
+            _ => Err(format!("Invalid forward status: {}", value)),
+        }
+    }
+}
+
+/// A received message for none of the node's agents that it was to forward
+/// to its upstream node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForwardedMessage {
+    pub id: i64,
+    /// SHA-256 digest of the envelope
+    pub digest: String,
+    /// `signed` or `encrypted`
+    pub envelope_kind: String,
+    /// ID of the message (not known for a JWE)
+    pub message_id: Option<String>,
+    /// DID the message claims to be from
+    pub from_did: Option<String>,
+    /// DIDs the message is addressed to
+    pub recipients: Vec<String>,
+    pub upstream_url: String,
+    /// Nodes that forwarded the message to this one, in order
+    pub forwarded_by: Vec<String>,
+    pub status: ForwardStatus,
+    /// Why the message was refused or could not be forwarded
+    pub error: Option<String>,
+    pub created_at: String,
+}
+
+/// The messages waiting in the mailbox of a remote agent
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MailboxSummary {
+    /// Number of unexpired messages that were not acknowledged
+    pub message_count: u64,
+    /// Combined size of the waiting messages in bytes
+    pub total_bytes: u64,
+    /// When the oldest waiting message was held
+    pub oldest_created_at: Option<String>,
+    /// When the newest waiting message was held
+    pub newest_created_at: Option<String>,
+}
+
+/// Filter for the incoming messages of an agent's inbox
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReceivedFilter {
+    /// Only messages of this type
+    pub message_type: Option<String>,
+    /// Only messages from this DID
+    pub from_did: Option<String>,
+    /// Only messages in this thread
+    pub thread_id: Option<String>,
+    /// Only messages after the agent's read cursor
+    pub unread_only: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionStatus {
+    Pending,
+    AuthorizationRequired,
+    Authorized,
+    Rejected,
+}
+
+impl fmt::Display for ConnectionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionStatus::Pending => write!(f, "pending"),
+            ConnectionStatus::AuthorizationRequired => write!(f, "authorization_required"),
+            ConnectionStatus::Authorized => write!(f, "authorized"),
+            ConnectionStatus::Rejected => write!(f, "rejected"),
+        }
+    }
+}
+
+impl TryFrom<&str> for ConnectionStatus {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "pending" => Ok(ConnectionStatus::Pending),
+            "authorization_required" => Ok(ConnectionStatus::AuthorizationRequired),
+            "authorized" => Ok(ConnectionStatus::Authorized),
+            "rejected" => Ok(ConnectionStatus::Rejected),
+            _ => Err(format!("Invalid connection status: {}", value)),
+        }
+    }
+}
+
+/// A Connect request (TAIP-15) and the answer it received
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Connection {
+    /// ID of the Connect message, referenced by the replies
+    pub reference_id: String,
+    /// Agent requesting the connection
+    pub from_did: String,
+    /// Agents the request was sent to
+    pub to_dids: Vec<String>,
+    pub status: ConnectionStatus,
+    pub message_json: serde_json::Value,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl Connection {
+    /// Whether `did` sent or received the Connect request
+    pub fn is_party(&self, did: &str) -> bool {
+        self.from_did == did || self.to_dids.iter().any(|to| to == did)
+    }
+}
+
+/// What an agent learned about a counterparty during an onboarding handshake
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CounterpartyProfile {
+    pub counterparty_did: String,
+    /// ID of the Connect message of the handshake
+    pub connection_id: String,
+    /// Answer to the Connect request, pending if none arrived in time
+    pub connection_status: ConnectionStatus,
+    /// Where to complete the authorization, if it is required
+    pub authorization_url: Option<String>,
+    /// Why the connection was rejected
+    pub rejection_reason: Option<String>,
+    /// Features the counterparty disclosed (None if it did not answer)
+    pub features: Option<Vec<tap_msg::message::Disclosure>>,
+    /// Policies we sent the counterparty
+    pub our_policies: Vec<tap_msg::message::Policy>,
+    /// Policies the counterparty sent us
+    pub their_policies: Vec<tap_msg::message::Policy>,
+    /// Whether the counterparty answered a trust ping
+    pub endpoint_verified: bool,
+    /// Round trip of the trust ping in milliseconds
+    pub ping_rtt_ms: Option<u64>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl CounterpartyProfile {
+    /// Whether the counterparty supports a protocol, e.g.
+    /// `https://tap.rsvp/schema/1.0`; unknown if it disclosed no features
+    pub fn supports_protocol(&self, protocol: &str) -> Option<bool> {
+        self.features.as_ref().map(|features| {
+            features.iter().any(|feature| {
+                feature.feature_type == tap_msg::message::discover_features::PROTOCOL_FEATURE
+                    && feature.id == protocol
+            })
+        })
+    }
+}
+
+/// Usual activity of a counterparty, learned from the transactions it sent
+///
+/// Kept by the [`AnomalyDetector`](crate::anomaly::AnomalyDetector), which
+/// compares every new transaction of the counterparty with it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CounterpartyBaseline {
+    pub counterparty_did: String,
+    /// Transactions seen from the counterparty
+    pub transactions: u64,
+    /// Start of the current rate window, in Unix seconds
+    pub window_start: i64,
+    /// Transactions in the current rate window
+    pub window_count: u64,
+    /// Moving average of transactions per rate window
+    pub mean_window_count: f64,
+    /// Transactions by hour of the day (UTC)
+    pub hours: Vec<u64>,
+    /// Transactions and mean amount by asset (CAIP-19) or currency code
+    pub assets: HashMap<String, AssetBaseline>,
+    pub first_seen: String,
+    pub last_seen: String,
+}
+
+/// Amounts a counterparty sent in one asset
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AssetBaseline {
+    /// Transactions in the asset
+    pub transactions: u64,
+    /// Mean amount of those transactions
+    pub mean_amount: f64,
+}
+
+/// Fiat value of a transaction at the time it was recorded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionValuation {
+    /// Reference ID of the transaction
+    pub transaction_id: String,
+    /// CAIP-19 asset ID or ISO 4217 currency code of the transaction
+    pub asset: String,
+    /// Amount of the transaction in its asset
+    pub amount: String,
+    /// ISO 4217 code of the fiat currency
+    pub currency: String,
+    /// Value of the amount in the fiat currency
+    pub fiat_amount: f64,
+    /// Fiat amount per unit of the asset
+    pub rate: f64,
+    /// Where the rate comes from
+    pub rate_source: String,
+    /// When the rate was observed
+    pub rate_timestamp: String,
+    /// Canonical hash of the transaction, for referencing it in external
+    /// systems; not stored with the valuation
+    #[serde(default)]
+    pub canonical_hash: Option<String>,
+    pub created_at: String,
+}
+
+/// Outcome of checking a cross-asset settlement against its rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversionStatus {
+    /// The settled amount is within the tolerated slippage
+    WithinTolerance,
+    /// The settled amount slipped more than tolerated
+    OutOfTolerance,
+    /// No rate was available for one of the assets
+    Unpriced,
+}
+
+impl fmt::Display for ConversionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionStatus::WithinTolerance => write!(f, "within_tolerance"),
+            ConversionStatus::OutOfTolerance => write!(f, "out_of_tolerance"),
+            ConversionStatus::Unpriced => write!(f, "unpriced"),
+        }
+    }
+}
+
+impl TryFrom<&str> for ConversionStatus {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "within_tolerance" => Ok(ConversionStatus::WithinTolerance),
+            "out_of_tolerance" => Ok(ConversionStatus::OutOfTolerance),
+            "unpriced" => Ok(ConversionStatus::Unpriced),
+            _ => Err(format!("Invalid conversion status: {}", s)),
+        }
+    }
+}
+
+/// A settlement made in another asset than its transaction requested
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SettlementConversion {
+    /// Reference ID of the transaction
+    pub transaction_id: String,
+    /// CAIP-19 asset ID or ISO 4217 currency code the transaction requested
+    pub requested_asset: String,
+    pub requested_amount: String,
+    /// CAIP-19 asset ID or ISO 4217 currency code the transaction settled in
+    pub settled_asset: String,
+    pub settled_amount: String,
+    /// Units of the settled asset per unit of the requested asset
+    pub rate: Option<f64>,
+    /// Where the rate comes from
+    pub rate_source: Option<String>,
+    /// Settled amount the rate gives for the requested amount
+    pub expected_amount: Option<f64>,
+    /// Deviation of the settled amount from the expected amount, as a
+    /// fraction of the expected amount
+    pub slippage: Option<f64>,
+    /// Tolerated slippage, in either direction
+    pub max_slippage: f64,
+    pub status: ConversionStatus,
+    pub created_at: String,
+}
+
+/// A settlement address seen in a transaction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AddressSighting {
+    /// CAIP-10 account ID or PayTo URI, lowercased on EVM chains
+    pub address: String,
+    /// Reference ID of the transaction
+    pub transaction_id: String,
+    /// DID of the agent that gave the address
+    pub provided_by: String,
+    /// Originator of a Transfer or customer of a Payment
+    pub originator_did: Option<String>,
+    /// Beneficiary of a Transfer or merchant of a Payment
+    pub beneficiary_did: Option<String>,
+    pub seen_at: String,
+}
+
+/// A settlement address seen in more than one transaction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReusedAddress {
+    pub address: String,
+    /// Number of transactions the address was seen in
+    pub transactions: u64,
+    /// Number of distinct agents that gave the address
+    pub providers: u64,
+    /// Number of distinct originators of the transactions
+    pub originators: u64,
+    /// Number of distinct beneficiaries of the transactions
+    pub beneficiaries: u64,
+    pub first_seen_at: String,
+    pub last_seen_at: String,
+}
+
+/// A settlement address reserved for a transaction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SettlementAddressReservation {
+    /// CAIP-2 chain ID of the address
+    pub chain_id: String,
+    /// Address on the chain, without the chain prefix
+    pub address: String,
+    /// Our agent that authorized the transaction
+    pub agent_did: String,
+    /// Reference ID of the transaction
+    pub transaction_id: String,
+    /// CAIP-19 asset ID of the transaction
+    pub asset: String,
+    /// Index the provider derived the address at
+    pub derivation_index: u32,
+    pub created_at: String,
+}
+
+impl SettlementAddressReservation {
+    /// The address as a CAIP-10 account ID
+    pub fn account_id(&self) -> String {
+        format!("{}:{}", self.chain_id, self.address)
+    }
+}
+
+/// Kind of message a draft is sent as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DraftKind {
+    Transfer,
+    Payment,
+}
+
+impl fmt::Display for DraftKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DraftKind::Transfer => write!(f, "transfer"),
+            DraftKind::Payment => write!(f, "payment"),
+        }
+    }
+}
+
+impl TryFrom<&str> for DraftKind {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "transfer" => Ok(DraftKind::Transfer),
+            "payment" => Ok(DraftKind::Payment),
+            _ => Err(format!("Invalid draft kind: {}", value)),
+        }
+    }
+}
+
+impl FromStr for DraftKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DraftStatus {
+    /// The body cannot be sent yet
+    Invalid,
+    /// The body can be sent
+    Valid,
+    Sent,
+}
+
+impl fmt::Display for DraftStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DraftStatus::Invalid => write!(f, "invalid"),
+            DraftStatus::Valid => write!(f, "valid"),
+            DraftStatus::Sent => write!(f, "sent"),
+        }
+    }
+}
+
+impl TryFrom<&str> for DraftStatus {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "invalid" => Ok(DraftStatus::Invalid),
+            "valid" => Ok(DraftStatus::Valid),
+            "sent" => Ok(DraftStatus::Sent),
+            _ => Err(format!("Invalid draft status: {}", value)),
+        }
+    }
+}
+
+impl FromStr for DraftStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s)
+    }
+}
+
+/// A Transfer or Payment built up before it is sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Draft {
+    pub id: String,
+    /// Our agent that will send the draft
+    pub agent_did: String,
+    pub kind: DraftKind,
+    /// Message body as edited so far
+    pub body: serde_json::Value,
+    pub status: DraftStatus,
+    /// Why the body cannot be sent, empty if it can
+    pub validation_errors: Vec<String>,
+    /// When the draft can no longer be sent
+    pub expires_at: Option<String>,
+    /// ID of the message the draft was sent as
+    pub message_id: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl Draft {
+    /// Whether the draft can no longer be sent at `now`
+    pub fn is_expired(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.expires_at
+            .as_deref()
+            .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+            .is_some_and(|at| at <= now)
+    }
+}
+
+/// Activity of a counterparty over a period, for evaluating the relationship
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CounterpartyStats {
+    pub counterparty_did: String,
+    /// Start of the period (RFC 3339), if bounded
+    pub since: Option<String>,
+    /// End of the period (RFC 3339, exclusive), if bounded
+    pub until: Option<String>,
+    /// Messages received from the counterparty
+    pub messages_received: u64,
+    /// Messages sent to the counterparty
+    pub messages_sent: u64,
+    /// Transactions the counterparty took part in
+    pub transactions: u64,
+    /// Transactions the counterparty initiated
+    pub initiated: u64,
+    /// Transactions initiated by others that the counterparty authorized
+    pub authorized: u64,
+    /// Transactions that were rejected
+    pub rejected: u64,
+    /// Transactions that were settled
+    pub settled: u64,
+    /// Share of the transactions initiated by others that the counterparty
+    /// authorized
+    pub authorization_rate: f64,
+    /// Time from the creation of a transaction to the counterparty's
+    /// Authorize
+    pub time_to_authorize: LatencyStats,
+    /// Time from the creation of a transaction to its Settle
+    pub time_to_settle: LatencyStats,
+    /// Rejected transactions per rejection code
+    pub rejections_by_code: BTreeMap<String, u64>,
+    /// Transactions rejected without a code
+    pub uncoded_rejections: u64,
+    /// Total amount per CAIP-19 asset ID or ISO 4217 currency code
+    pub volumes: BTreeMap<String, f64>,
+}
+
+/// Distribution of a duration, in seconds
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencyStats {
+    /// Number of measured transactions
+    pub count: u64,
+    pub average_secs: Option<f64>,
+    pub p50_secs: Option<f64>,
+    pub p90_secs: Option<f64>,
+    pub p99_secs: Option<f64>,
+}
+
+impl LatencyStats {
+    /// Compute the distribution of a set of durations in seconds
+    pub fn from_durations(mut durations: Vec<f64>) -> Self {
+        if durations.is_empty() {
+            return Self::default();
+        }
+        durations.sort_by(|a, b| a.total_cmp(b));
+        // Nearest-rank percentile
+        let percentile = |p: f64| {
+            let rank = ((p / 100.0) * durations.len() as f64).ceil() as usize;
+            durations[rank.clamp(1, durations.len()) - 1]
+        };
+        Self {
+            count: durations.len() as u64,
+            average_secs: Some(durations.iter().sum::<f64>() / durations.len() as f64),
+            p50_secs: Some(percentile(50.0)),
+            p90_secs: Some(percentile(90.0)),
+            p99_secs: Some(percentile(99.0)),
+        }
+    }
+}
+
+/// Status of a response timer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseTimerStatus {
+    /// Awaiting an answer
+    Open,
+    /// Answered by our agent
+    Answered,
+    /// Withdrawn by the counterparty before it was answered
+    Cancelled,
+}
+
+impl fmt::Display for ResponseTimerStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResponseTimerStatus::Open => write!(f, "open"),
+            ResponseTimerStatus::Answered => write!(f, "answered"),
+            ResponseTimerStatus::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl TryFrom<&str> for ResponseTimerStatus {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "open" => Ok(ResponseTimerStatus::Open),
+            "answered" => Ok(ResponseTimerStatus::Answered),
+            "cancelled" => Ok(ResponseTimerStatus::Cancelled),
+            _ => Err(format!("Invalid response timer status: {}", value)),
+        }
+    }
+}
+
+impl FromStr for ResponseTimerStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s)
+    }
+}
+
+/// Time one of our agents is taking, or took, to answer a message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseTimer {
+    pub id: i64,
+    /// The message awaiting an answer
+    pub message_id: String,
+    /// Short name of its type, e.g. `Transfer`
+    pub message_type: String,
+    pub thread_id: String,
+    /// Our agent expected to answer
+    pub agent_did: String,
+    /// Sender of the message
+    pub counterparty_did: Option<String>,
+    /// Short names of the message types that answer it
+    pub responses: Vec<String>,
+    pub status: ResponseTimerStatus,
+    pub started_at: String,
+    /// When a warning of the approaching deadline is due
+    pub warn_at: String,
+    pub deadline_at: String,
+    /// Whether the warning was published
+    pub warned: bool,
+    /// Whether the deadline passed before an answer
+    pub breached: bool,
+    pub response_message_id: Option<String>,
+    /// Short name of the type of the answer, e.g. `Authorize`
+    pub response_type: Option<String>,
+    /// When the timer was answered or cancelled
+    pub closed_at: Option<String>,
+}
+
+/// Time our agents took to answer the messages of one type
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResponseTimeStats {
+    /// Short name of the message type, e.g. `Transfer`
+    pub message_type: String,
+    /// Messages answered
+    pub answered: u64,
+    /// Messages still awaiting an answer
+    pub open: u64,
+    /// Messages withdrawn by the counterparty before they were answered
+    pub cancelled: u64,
+    /// Messages whose deadline passed before they were answered, if they were
+    pub breached: u64,
+    /// Time from the arrival of a message to its answer
+    pub response_time: LatencyStats,
+}
+
+/// Outcome of the last run of a scheduled job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduledJobStatus {
+    Succeeded,
+    Failed,
+}
+
+impl fmt::Display for ScheduledJobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduledJobStatus::Succeeded => write!(f, "succeeded"),
+            ScheduledJobStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+impl TryFrom<&str> for ScheduledJobStatus {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "succeeded" => Ok(ScheduledJobStatus::Succeeded),
+            "failed" => Ok(ScheduledJobStatus::Failed),
+            _ => Err(format!("Invalid scheduled job status: {}", value)),
+        }
+    }
+}
+
+/// A job of the node's scheduler, with its last and next run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledJobRecord {
+    pub name: String,
+    /// Schedule the job runs on, e.g. `@every 1h` or `30 2 * * *`
+    pub schedule: String,
+    pub last_run_at: Option<String>,
+    pub last_status: Option<ScheduledJobStatus>,
+    /// Why the last run failed
+    pub last_error: Option<String>,
+    pub last_duration_ms: Option<i64>,
+    /// When the job runs next (None while it runs or once it stopped)
+    pub next_run_at: Option<String>,
+    /// When a run was requested through storage, until the scheduler picks
+    /// it up
+    pub run_requested_at: Option<String>,
+    pub updated_at: String,
+}
@@ -0,0 +1,818 @@
+//! Migration of agent databases to Postgres
+//!
+//! [`PostgresMigration`] copies the tables of an agent's SQLite database
+//! into a schema of its own in a Postgres database, named `tap_` followed by
+//! a hash of the agent DID ([`PostgresMigration::schema_name`]) and recorded
+//! with the DID in `public.tap_agent_schemas`. Each table is created with the
+//! columns, `NOT NULL` constraints and primary key of the SQLite table, its
+//! declared types mapped to Postgres types by SQLite's affinity rules (see
+//! [`ColumnType::from_sqlite`]).
+//!
+//! Rows are copied in batches in rowid order. Each batch is written in one
+//! Postgres transaction together with the last rowid copied, kept in the
+//! `_tap_migration` table of the schema, so an interrupted migration resumes
+//! after its last complete batch and running it again copies the rows added
+//! since.
+//!
+//! Every table is then verified: the number of rows and a checksum of their
+//! values must be the same on both sides ([`TableChecksum`]). Rows updated or
+//! deleted after they were copied make a table fail verification; such a
+//! table is emptied and copied again, once, in the same run. The source
+//! database is only read, so the node can keep running; to get a consistent
+//! copy, pause its intake of messages
+//! ([`TapNode::pause_intake`](crate::TapNode::pause_intake)) for the final
+//! run.
+//!
+//! The [`PostgresBackend`](super::postgres_backend::PostgresBackend) of an
+//! agent runs on the same schema, so a node switched to it after the final
+//! run reads the migrated messages, transactions and deliveries.
+
+use super::db::Storage;
+use super::error::StorageError;
+use futures::TryStreamExt;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::postgres::{PgConnection, PgPool, PgPoolOptions, Postgres};
+use sqlx::{QueryBuilder, Row, TypeInfo, ValueRef};
+use std::fmt;
+use tracing::{info, warn};
+
+/// Rows copied per batch by default
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// Tables of the SQLite database that are not copied
+const SKIPPED_TABLES: &[&str] = &["_sqlx_migrations"];
+
+/// Postgres type of a copied column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    BigInt,
+    Double,
+    Boolean,
+    Bytea,
+    Text,
+}
+
+impl ColumnType {
+    /// Type of a column declared as `declared` in SQLite
+    ///
+    /// Follows SQLite's type affinity rules, except that `BOOLEAN` columns
+    /// become Postgres booleans and columns of numeric or no affinity, such
+    /// as dates, become text.
+    pub fn from_sqlite(declared: &str) -> Self {
+        let declared = declared.to_ascii_uppercase();
+        let contains_any = |names: &[&str]| names.iter().any(|name| declared.contains(name));
+        if declared.contains("BOOL") {
+            ColumnType::Boolean
+        } else if declared.contains("INT") {
+            ColumnType::BigInt
+        } else if contains_any(&["CHAR", "CLOB", "TEXT"]) {
+            ColumnType::Text
+        } else if declared.contains("BLOB") {
+            ColumnType::Bytea
+        } else if contains_any(&["REAL", "FLOA", "DOUB"]) {
+            ColumnType::Double
+        } else {
+            ColumnType::Text
+        }
+    }
+
+    /// The Postgres type name
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            ColumnType::BigInt => "BIGINT",
+            ColumnType::Double => "DOUBLE PRECISION",
+            ColumnType::Boolean => "BOOLEAN",
+            ColumnType::Bytea => "BYTEA",
+            ColumnType::Text => "TEXT",
+        }
+    }
+}
+
+/// A column of a copied table
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Column {
+    name: String,
+    column_type: ColumnType,
+    not_null: bool,
+    /// Position in the primary key, starting at 1 (0 if not part of it)
+    primary_key: i64,
+}
+
+/// A table of the SQLite database
+#[derive(Debug, Clone)]
+struct Table {
+    name: String,
+    columns: Vec<Column>,
+}
+
+impl Table {
+    fn create_sql(&self, schema: &str) -> String {
+        let mut definitions: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| {
+                format!(
+                    "{} {}{}",
+                    quote(&column.name),
+                    column.column_type.as_sql(),
+                    if column.not_null { " NOT NULL" } else { "" }
+                )
+            })
+            .collect();
+        let mut key: Vec<&Column> = self.columns.iter().filter(|c| c.primary_key > 0).collect();
+        key.sort_by_key(|c| c.primary_key);
+        if !key.is_empty() {
+            let key: Vec<String> = key.iter().map(|c| quote(&c.name)).collect();
+            definitions.push(format!("PRIMARY KEY ({})", key.join(", ")));
+        }
+        format!(
+            "CREATE TABLE IF NOT EXISTS {}.{} ({})",
+            quote(schema),
+            quote(&self.name),
+            definitions.join(", ")
+        )
+    }
+
+    fn column_list(&self) -> String {
+        self.columns
+            .iter()
+            .map(|c| quote(&c.name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// A value of a copied row
+#[derive(Debug, Clone, PartialEq)]
+enum Cell {
+    Null,
+    Int(i64),
+    Real(f64),
+    Bool(bool),
+    Bytes(Vec<u8>),
+    Text(String),
+}
+
+impl Cell {
+    /// Read the value of column `index` of a SQLite row, as stored
+    fn from_sqlite(row: &sqlx::sqlite::SqliteRow, index: usize) -> Result<Self, sqlx::Error> {
+        let raw = row.try_get_raw(index)?;
+        if raw.is_null() {
+            return Ok(Cell::Null);
+        }
+        let storage_class = raw.type_info().name().to_string();
+        Ok(match storage_class.as_str() {
+            "INTEGER" => Cell::Int(row.try_get_unchecked(index)?),
+            "REAL" => Cell::Real(row.try_get_unchecked(index)?),
+            "BLOB" => Cell::Bytes(row.try_get_unchecked(index)?),
+            _ => Cell::Text(row.try_get_unchecked(index)?),
+        })
+    }
+
+    /// Read the value of column `index` of a Postgres row
+    fn from_postgres(
+        row: &sqlx::postgres::PgRow,
+        index: usize,
+        column_type: ColumnType,
+    ) -> Result<Self, sqlx::Error> {
+        let cell = match column_type {
+            ColumnType::BigInt => row.try_get::<Option<i64>, _>(index)?.map(Cell::Int),
+            ColumnType::Double => row.try_get::<Option<f64>, _>(index)?.map(Cell::Real),
+            ColumnType::Boolean => row.try_get::<Option<bool>, _>(index)?.map(Cell::Bool),
+            ColumnType::Bytea => row.try_get::<Option<Vec<u8>>, _>(index)?.map(Cell::Bytes),
+            ColumnType::Text => row.try_get::<Option<String>, _>(index)?.map(Cell::Text),
+        };
+        Ok(cell.unwrap_or(Cell::Null))
+    }
+
+    /// Convert a SQLite value to the type of its Postgres column
+    fn coerce(self, column_type: ColumnType) -> Result<Self, String> {
+        let coerced = match (self, column_type) {
+            (Cell::Null, _) => Some(Cell::Null),
+            (Cell::Int(value), ColumnType::BigInt) => Some(Cell::Int(value)),
+            (Cell::Real(value), ColumnType::BigInt) if value.fract() == 0.0 => {
+                Some(Cell::Int(value as i64))
+            }
+            (Cell::Text(value), ColumnType::BigInt) => value.trim().parse().ok().map(Cell::Int),
+            (Cell::Int(value), ColumnType::Double) => Some(Cell::Real(value as f64)),
+            (Cell::Real(value), ColumnType::Double) => Some(Cell::Real(value)),
+            (Cell::Text(value), ColumnType::Double) => value.trim().parse().ok().map(Cell::Real),
+            (Cell::Int(value), ColumnType::Boolean) => Some(Cell::Bool(value != 0)),
+            (Cell::Text(value), ColumnType::Boolean) => match value.as_str() {
+                "1" | "true" | "TRUE" => Some(Cell::Bool(true)),
+                "0" | "false" | "FALSE" => Some(Cell::Bool(false)),
+                _ => None,
+            },
+            (Cell::Bytes(value), ColumnType::Bytea) => Some(Cell::Bytes(value)),
+            (Cell::Text(value), ColumnType::Bytea) => Some(Cell::Bytes(value.into_bytes())),
+            (Cell::Text(value), ColumnType::Text) => Some(Cell::Text(value)),
+            (Cell::Int(value), ColumnType::Text) => Some(Cell::Text(value.to_string())),
+            (Cell::Real(value), ColumnType::Text) => Some(Cell::Text(value.to_string())),
+            (Cell::Bytes(value), ColumnType::Text) => String::from_utf8(value).ok().map(Cell::Text),
+            (cell, column_type) => {
+                return Err(format!("{:?} is not a {}", cell, column_type.as_sql()));
+            }
+        };
+        coerced.ok_or_else(|| format!("Value is not a {}", column_type.as_sql()))
+    }
+
+    fn hash(&self, hasher: &mut Sha256) {
+        match self {
+            Cell::Null => hasher.update([0]),
+            Cell::Int(value) => {
+                hasher.update([1]);
+                hasher.update(value.to_le_bytes());
+            }
+            Cell::Real(value) => {
+                hasher.update([2]);
+                hasher.update(value.to_bits().to_le_bytes());
+            }
+            Cell::Bool(value) => hasher.update([3, u8::from(*value)]),
+            Cell::Bytes(value) => {
+                hasher.update([4]);
+                hasher.update((value.len() as u64).to_le_bytes());
+                hasher.update(value);
+            }
+            Cell::Text(value) => {
+                hasher.update([5]);
+                hasher.update((value.len() as u64).to_le_bytes());
+                hasher.update(value.as_bytes());
+            }
+        }
+    }
+
+    fn bind(
+        self,
+        column_type: ColumnType,
+        values: &mut sqlx::query_builder::Separated<'_, '_, Postgres, &'static str>,
+    ) {
+        match (self, column_type) {
+            (Cell::Int(value), _) => values.push_bind(value),
+            (Cell::Real(value), _) => values.push_bind(value),
+            (Cell::Bool(value), _) => values.push_bind(value),
+            (Cell::Bytes(value), _) => values.push_bind(value),
+            (Cell::Text(value), _) => values.push_bind(value),
+            (Cell::Null, ColumnType::BigInt) => values.push_bind(None::<i64>),
+            (Cell::Null, ColumnType::Double) => values.push_bind(None::<f64>),
+            (Cell::Null, ColumnType::Boolean) => values.push_bind(None::<bool>),
+            (Cell::Null, ColumnType::Bytea) => values.push_bind(None::<Vec<u8>>),
+            (Cell::Null, ColumnType::Text) => values.push_bind(None::<String>),
+        };
+    }
+}
+
+/// Order-independent checksum of the rows of a table
+///
+/// The sum, modulo 2^128, of the first 16 bytes of the SHA-256 of each row's
+/// values, so the rows of both databases can be read in any order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TableChecksum {
+    /// Number of rows
+    pub rows: u64,
+    sum: u128,
+}
+
+impl TableChecksum {
+    fn add(&mut self, row: &[Cell]) {
+        let mut hasher = Sha256::new();
+        for cell in row {
+            cell.hash(&mut hasher);
+        }
+        let digest = hasher.finalize();
+        let mut prefix = [0u8; 16];
+        prefix.copy_from_slice(&digest[..16]);
+        self.rows += 1;
+        self.sum = self.sum.wrapping_add(u128::from_le_bytes(prefix));
+    }
+}
+
+impl fmt::Display for TableChecksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:032x}", self.sum)
+    }
+}
+
+/// Outcome of the migration of a table
+#[derive(Debug, Clone, Serialize)]
+pub struct TableMigration {
+    pub table: String,
+    /// Rows copied by this run
+    pub rows_copied: u64,
+    pub source_rows: u64,
+    pub target_rows: u64,
+    pub source_checksum: String,
+    pub target_checksum: String,
+    /// Whether the table failed verification and was copied again
+    pub recopied: bool,
+    /// Whether both copies have the same rows
+    pub verified: bool,
+}
+
+/// Outcome of the migration of an agent database
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentMigration {
+    pub agent_did: String,
+    /// Postgres schema holding the agent's tables
+    pub schema: String,
+    pub tables: Vec<TableMigration>,
+    /// Whether every table was verified
+    pub verified: bool,
+}
+
+/// Copies agent databases into a Postgres database
+#[derive(Debug, Clone)]
+pub struct PostgresMigration {
+    pool: PgPool,
+    batch_size: usize,
+}
+
+impl PostgresMigration {
+    /// Connect to the Postgres database at `url`
+    pub async fn connect(url: &str) -> Result<Self, StorageError> {
+        let pool = PgPoolOptions::new().max_connections(4).connect(url).await?;
+        Ok(Self::new(pool))
+    }
+
+    /// Migrate into the database of a Postgres pool
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+
+    /// Copy `batch_size` rows per batch
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Postgres schema of an agent's tables
+    pub fn schema_name(agent_did: &str) -> String {
+        let digest = format!("{:x}", Sha256::digest(agent_did.as_bytes()));
+        format!("tap_{}", &digest[..16])
+    }
+
+    /// Copy an agent's database and verify the copy
+    pub async fn migrate(
+        &self,
+        agent_did: &str,
+        source: &Storage,
+    ) -> Result<AgentMigration, StorageError> {
+        let schema = self.prepare_schema(agent_did, source).await?;
+        let mut tables = Vec::new();
+        for table in source_tables(source).await? {
+            sqlx::query(&table.create_sql(&schema))
+                .execute(&self.pool)
+                .await?;
+            let mut rows_copied = self.copy_table(&schema, &table, source).await?;
+            let (mut source_checksum, mut target_checksum) =
+                self.checksums(&schema, &table, source).await?;
+
+            let recopied = source_checksum != target_checksum;
+            if recopied {
+                warn!(
+                    "Table {} of {} changed since it was copied, copying it again",
+                    table.name, agent_did
+                );
+                self.reset_table(&schema, &table).await?;
+                rows_copied = self.copy_table(&schema, &table, source).await?;
+                (source_checksum, target_checksum) =
+                    self.checksums(&schema, &table, source).await?;
+            }
+            tables.push(table_migration(
+                &table,
+                rows_copied,
+                source_checksum,
+                target_checksum,
+                recopied,
+            ));
+        }
+        let migration = AgentMigration {
+            agent_did: agent_did.to_string(),
+            verified: tables.iter().all(|t| t.verified),
+            schema,
+            tables,
+        };
+        info!(
+            "Migrated the database of {} to schema {} (verified: {})",
+            agent_did, migration.schema, migration.verified
+        );
+        Ok(migration)
+    }
+
+    /// Verify the copy of an agent's database without copying rows
+    pub async fn verify(
+        &self,
+        agent_did: &str,
+        source: &Storage,
+    ) -> Result<AgentMigration, StorageError> {
+        let schema = Self::schema_name(agent_did);
+        let mut tables = Vec::new();
+        for table in source_tables(source).await? {
+            let (source_checksum, target_checksum) =
+                self.checksums(&schema, &table, source).await?;
+            tables.push(table_migration(
+                &table,
+                0,
+                source_checksum,
+                target_checksum,
+                false,
+            ));
+        }
+        Ok(AgentMigration {
+            agent_did: agent_did.to_string(),
+            verified: tables.iter().all(|t| t.verified),
+            schema,
+            tables,
+        })
+    }
+
+    /// Create the schema of an agent and its progress table
+    async fn prepare_schema(
+        &self,
+        agent_did: &str,
+        source: &Storage,
+    ) -> Result<String, StorageError> {
+        let migrated_at = source.clock().now().to_rfc3339();
+        let schema = {
+            let mut conn = self.pool.acquire().await?;
+            register_agent_schema(&mut conn, agent_did, &migrated_at).await?
+        };
+        sqlx::query(&format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {}._tap_migration (
+                table_name TEXT PRIMARY KEY,
+                last_rowid BIGINT NOT NULL,
+                rows_copied BIGINT NOT NULL,
+                updated_at TEXT NOT NULL
+            )
+            "#,
+            quote(&schema)
+        ))
+        .execute(&self.pool)
+        .await?;
+        sqlx::query("UPDATE public.tap_agent_schemas SET migrated_at = $2 WHERE agent_did = $1")
+            .bind(agent_did)
+            .bind(&migrated_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(schema)
+    }
+
+    /// Copy the rows of a table added since the last copied batch,
+    /// returning how many were copied
+    async fn copy_table(
+        &self,
+        schema: &str,
+        table: &Table,
+        source: &Storage,
+    ) -> Result<u64, StorageError> {
+        let mut last_rowid: i64 = sqlx::query_scalar(&format!(
+            "SELECT last_rowid FROM {}._tap_migration WHERE table_name = $1",
+            quote(schema)
+        ))
+        .bind(&table.name)
+        .fetch_optional(&self.pool)
+        .await?
+        .unwrap_or(0);
+
+        let select = format!(
+            "SELECT rowid, {} FROM {} WHERE rowid > ?1 ORDER BY rowid LIMIT ?2",
+            table.column_list(),
+            quote(&table.name)
+        );
+        let mut copied = 0u64;
+        loop {
+            let rows = sqlx::query(&select)
+                .bind(last_rowid)
+                .bind(self.batch_size as i64)
+                .fetch_all(source.pool())
+                .await?;
+            let Some(last) = rows.last() else {
+                break;
+            };
+            last_rowid = last.try_get(0)?;
+
+            let mut batch = Vec::with_capacity(rows.len());
+            for row in &rows {
+                batch.push(source_row(table, row, 1)?);
+            }
+            let batch_rows = batch.len() as u64;
+
+            let mut insert = QueryBuilder::<Postgres>::new(format!(
+                "INSERT INTO {}.{} ({}) ",
+                quote(schema),
+                quote(&table.name),
+                table.column_list()
+            ));
+            insert.push_values(batch, |mut values, row| {
+                for (cell, column) in row.into_iter().zip(&table.columns) {
+                    cell.bind(column.column_type, &mut values);
+                }
+            });
+            insert.push(" ON CONFLICT DO NOTHING");
+
+            let mut tx = self.pool.begin().await?;
+            insert.build().execute(&mut *tx).await?;
+            sqlx::query(&format!(
+                r#"
+                INSERT INTO {schema}._tap_migration (table_name, last_rowid, rows_copied, updated_at)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (table_name) DO UPDATE SET
+                    last_rowid = EXCLUDED.last_rowid,
+                    rows_copied = {schema}._tap_migration.rows_copied + EXCLUDED.rows_copied,
+                    updated_at = EXCLUDED.updated_at
+                "#,
+                schema = quote(schema)
+            ))
+            .bind(&table.name)
+            .bind(last_rowid)
+            .bind(batch_rows as i64)
+            .bind(source.clock().now().to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+
+            copied += batch_rows;
+        }
+        Ok(copied)
+    }
+
+    /// Empty the copy of a table so it is copied again from the start
+    async fn reset_table(&self, schema: &str, table: &Table) -> Result<(), StorageError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(&format!(
+            "DELETE FROM {}.{}",
+            quote(schema),
+            quote(&table.name)
+        ))
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(&format!(
+            "DELETE FROM {}._tap_migration WHERE table_name = $1",
+            quote(schema)
+        ))
+        .bind(&table.name)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Checksums of a table in the source database and in Postgres
+    async fn checksums(
+        &self,
+        schema: &str,
+        table: &Table,
+        source: &Storage,
+    ) -> Result<(TableChecksum, TableChecksum), StorageError> {
+        let mut source_checksum = TableChecksum::default();
+        let select = format!("SELECT {} FROM {}", table.column_list(), quote(&table.name));
+        let mut rows = sqlx::query(&select).fetch(source.pool());
+        while let Some(row) = rows.try_next().await? {
+            source_checksum.add(&source_row(table, &row, 0)?);
+        }
+
+        let mut target_checksum = TableChecksum::default();
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_schema = $1 AND table_name = $2)",
+        )
+        .bind(schema)
+        .bind(&table.name)
+        .fetch_one(&self.pool)
+        .await?;
+        if exists {
+            let select = format!(
+                "SELECT {} FROM {}.{}",
+                table.column_list(),
+                quote(schema),
+                quote(&table.name)
+            );
+            let mut rows = sqlx::query(&select).fetch(&self.pool);
+            while let Some(row) = rows.try_next().await? {
+                let mut cells = Vec::with_capacity(table.columns.len());
+                for (index, column) in table.columns.iter().enumerate() {
+                    cells.push(Cell::from_postgres(&row, index, column.column_type)?);
+                }
+                target_checksum.add(&cells);
+            }
+        }
+        Ok((source_checksum, target_checksum))
+    }
+}
+
+/// Create the schema of an agent's tables, named by
+/// [`PostgresMigration::schema_name`], and record it in
+/// `public.tap_agent_schemas` if it is not yet
+pub(super) async fn register_agent_schema(
+    conn: &mut PgConnection,
+    agent_did: &str,
+    registered_at: &str,
+) -> Result<String, StorageError> {
+    let schema = PostgresMigration::schema_name(agent_did);
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS public.tap_agent_schemas (
+            agent_did TEXT PRIMARY KEY,
+            schema_name TEXT NOT NULL,
+            migrated_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query(&format!("CREATE SCHEMA IF NOT EXISTS {}", quote(&schema)))
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO public.tap_agent_schemas (agent_did, schema_name, migrated_at)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (agent_did) DO NOTHING
+        "#,
+    )
+    .bind(agent_did)
+    .bind(&schema)
+    .bind(registered_at)
+    .execute(&mut *conn)
+    .await?;
+    Ok(schema)
+}
+
+/// Create tables of agent databases in `schema` as a migration creates them,
+/// if they do not exist yet
+pub(super) async fn create_agent_tables(
+    conn: &mut PgConnection,
+    schema: &str,
+    names: &[&str],
+) -> Result<(), StorageError> {
+    let layout = Storage::new_in_memory().await?;
+    for table in source_tables(&layout).await? {
+        if names.contains(&table.name.as_str()) {
+            sqlx::query(&table.create_sql(schema))
+                .execute(&mut *conn)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// The tables of a SQLite database with their columns
+async fn source_tables(source: &Storage) -> Result<Vec<Table>, StorageError> {
+    let names: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )
+    .fetch_all(source.pool())
+    .await?;
+
+    let mut tables = Vec::new();
+    for name in names {
+        if SKIPPED_TABLES.contains(&name.as_str()) {
+            continue;
+        }
+        let columns = sqlx::query(&format!("PRAGMA table_info({})", quote(&name)))
+            .fetch_all(source.pool())
+            .await?
+            .iter()
+            .map(|row| {
+                Ok(Column {
+                    name: row.try_get("name")?,
+                    column_type: ColumnType::from_sqlite(&row.try_get::<String, _>("type")?),
+                    not_null: row.try_get::<i64, _>("notnull")? != 0,
+                    primary_key: row.try_get("pk")?,
+                })
+            })
+            .collect::<Result<Vec<_>, sqlx::Error>>()?;
+        tables.push(Table { name, columns });
+    }
+    Ok(tables)
+}
+
+/// The values of a SQLite row whose columns start at `offset`, converted to
+/// the types of the Postgres columns
+fn source_row(
+    table: &Table,
+    row: &sqlx::sqlite::SqliteRow,
+    offset: usize,
+) -> Result<Vec<Cell>, StorageError> {
+    table
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            Cell::from_sqlite(row, offset + index)?
+                .coerce(column.column_type)
+                .map_err(|e| {
+                    StorageError::Migration(format!(
+                        "Cannot copy column {} of table {}: {}",
+                        column.name, table.name, e
+                    ))
+                })
+        })
+        .collect()
+}
+
+fn table_migration(
+    table: &Table,
+    rows_copied: u64,
+    source_checksum: TableChecksum,
+    target_checksum: TableChecksum,
+    recopied: bool,
+) -> TableMigration {
+    TableMigration {
+        table: table.name.clone(),
+        rows_copied,
+        source_rows: source_checksum.rows,
+        target_rows: target_checksum.rows,
+        source_checksum: source_checksum.to_string(),
+        target_checksum: target_checksum.to_string(),
+        recopied,
+        verified: source_checksum == target_checksum,
+    }
+}
+
+/// Quote an SQL identifier
+pub(super) fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_column_types_follow_sqlite_affinity() {
+        assert_eq!(ColumnType::from_sqlite("INTEGER"), ColumnType::BigInt);
+        assert_eq!(ColumnType::from_sqlite("BIGINT"), ColumnType::BigInt);
+        assert_eq!(ColumnType::from_sqlite("BOOLEAN"), ColumnType::Boolean);
+        assert_eq!(ColumnType::from_sqlite("varchar(64)"), ColumnType::Text);
+        assert_eq!(ColumnType::from_sqlite("BLOB"), ColumnType::Bytea);
+        assert_eq!(ColumnType::from_sqlite("REAL"), ColumnType::Double);
+        assert_eq!(ColumnType::from_sqlite("DATETIME"), ColumnType::Text);
+        assert_eq!(ColumnType::from_sqlite(""), ColumnType::Text);
+    }
+
+    #[test]
+    fn test_values_are_coerced_to_column_types() {
+        assert_eq!(
+            Cell::Text("42".to_string()).coerce(ColumnType::BigInt),
+            Ok(Cell::Int(42))
+        );
+        assert_eq!(
+            Cell::Int(1).coerce(ColumnType::Boolean),
+            Ok(Cell::Bool(true))
+        );
+        assert_eq!(
+            Cell::Int(7).coerce(ColumnType::Text),
+            Ok(Cell::Text("7".to_string()))
+        );
+        assert_eq!(Cell::Null.coerce(ColumnType::Double), Ok(Cell::Null));
+        assert!(Cell::Text("many".to_string())
+            .coerce(ColumnType::BigInt)
+            .is_err());
+    }
+
+    #[test]
+    fn test_checksum_ignores_row_order() {
+        let first = vec![Cell::Int(1), Cell::Text("a".to_string())];
+        let second = vec![Cell::Int(2), Cell::Null];
+
+        let mut forward = TableChecksum::default();
+        forward.add(&first);
+        forward.add(&second);
+        let mut backward = TableChecksum::default();
+        backward.add(&second);
+        backward.add(&first);
+        assert_eq!(forward, backward);
+        assert_eq!(forward.rows, 2);
+
+        let mut changed = TableChecksum::default();
+        changed.add(&first);
+        changed.add(&[Cell::Int(2), Cell::Text(String::new())]);
+        assert_ne!(forward, changed);
+    }
+
+    #[tokio::test]
+    async fn test_schema_of_agent_tables() {
+        let dir = tempdir().unwrap();
+        let storage = Storage::new(Some(dir.path().join("transactions.db")))
+            .await
+            .unwrap();
+        let tables = source_tables(&storage).await.unwrap();
+        assert!(!tables.iter().any(|t| t.name == "_sqlx_migrations"));
+
+        let transactions = tables.iter().find(|t| t.name == "transactions").unwrap();
+        let create = transactions.create_sql("tap_0123");
+        assert!(create.starts_with(r#"CREATE TABLE IF NOT EXISTS "tap_0123"."transactions" ("#));
+        assert!(create.contains(r#""id" BIGINT"#));
+        assert!(create.contains(r#"PRIMARY KEY ("id")"#));
+
+        let schema = PostgresMigration::schema_name("did:example:alice");
+        assert_eq!(schema.len(), 20);
+        assert_eq!(schema, PostgresMigration::schema_name("did:example:alice"));
+        assert_ne!(schema, PostgresMigration::schema_name("did:example:bob"));
+    }
+}
@@ -0,0 +1,668 @@
+//! Postgres backend shared by several nodes
+//!
+//! [`PostgresBackend`] keeps the messages, transactions and deliveries of an
+//! agent in the schema [`PostgresMigration`] copies its database into, named
+//! by [`PostgresMigration::schema_name`] and recorded in
+//! `public.tap_agent_schemas`. A node can thus run on the data it migrated,
+//! and several nodes can share one database, e.g. as the
+//! [`NodeConfig::shared_storage`](crate::NodeConfig::shared_storage) of
+//! every node of a deployment.
+//!
+//! The handle returned by [`PostgresBackend::connect`] only holds the
+//! connection pool; [`StorageBackend::for_agent`] scopes it to the schema of
+//! an agent, which is created with the `messages`, `transactions` and
+//! `deliveries` tables of a migration on first use, if it does not exist
+//! yet. Row IDs are drawn from a sequence per table that starts after the
+//! highest ID migrated.
+//!
+//! Timestamps are taken from the [`Clock`] of the agent and formatted as in
+//! SQLite databases (`%Y-%m-%dT%H:%M:%SZ`), so records read from either
+//! compare alike.
+
+use super::backend::StorageBackend;
+use super::error::StorageError;
+use super::models::{
+    Delivery, DeliveryStatus, DeliveryType, Message, MessageDirection, Transaction,
+    TransactionStatus, TransactionType,
+};
+use super::postgres::{create_agent_tables, quote, register_agent_schema, PostgresMigration};
+use crate::clock::{system_clock, Clock};
+use async_trait::async_trait;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use std::fmt;
+use std::sync::Arc;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::utils::canonical_transaction_hash;
+use tokio::sync::OnceCell;
+use tracing::debug;
+
+/// Tables of an agent schema the backend runs on
+const TABLES: [&str; 3] = ["messages", "transactions", "deliveries"];
+
+/// Messages, transactions and deliveries of agents in Postgres, see the
+/// [module documentation](self)
+#[derive(Clone)]
+pub struct PostgresBackend {
+    pool: PgPool,
+    clock: Arc<dyn Clock>,
+    /// Agent whose schema this handle reads and writes, None until scoped
+    agent: Option<Arc<AgentSchema>>,
+}
+
+/// Schema of one agent, created on first use
+#[derive(Debug)]
+struct AgentSchema {
+    did: String,
+    name: String,
+    ready: OnceCell<()>,
+}
+
+impl fmt::Debug for PostgresBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PostgresBackend")
+            .field("pool", &self.pool)
+            .field("agent", &self.agent)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Columns of a transaction row
+type TransactionRow = (
+    i64,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    String,
+    String,
+);
+
+/// Columns of a message row
+type MessageRow = (
+    i64,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    String,
+    String,
+    String,
+);
+
+/// Columns of a delivery row
+type DeliveryRow = (
+    i64,
+    String,
+    String,
+    String,
+    Option<String>,
+    String,
+    String,
+    i64,
+    Option<i64>,
+    Option<String>,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+);
+
+const TRANSACTION_COLUMNS: &str = "id, type, reference_id, from_did, to_did, thread_id, message_type, status, message_json, rejection_code, rejection_reason, canonical_hash, created_at, updated_at";
+
+const MESSAGE_COLUMNS: &str = "id, message_id, message_type, from_did, to_did, thread_id, parent_thread_id, direction, message_json, created_at";
+
+const DELIVERY_COLUMNS: &str = "id, message_id, message_text, recipient_did, delivery_url, delivery_type, status, retry_count, last_http_status_code, error_message, created_at, updated_at, delivered_at, next_attempt_at";
+
+impl PostgresBackend {
+    /// Connect to the Postgres database at `url`
+    pub async fn connect(url: &str) -> Result<Self, StorageError> {
+        let pool = PgPoolOptions::new().max_connections(8).connect(url).await?;
+        Ok(Self::new(pool))
+    }
+
+    /// Run on the database of a Postgres pool
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            clock: system_clock(),
+            agent: None,
+        }
+    }
+
+    /// Timestamp records with `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Agent whose schema this handle reads and writes, None if unscoped
+    pub fn agent_did(&self) -> Option<&str> {
+        self.agent.as_ref().map(|agent| agent.did.as_str())
+    }
+
+    /// Schema this handle reads and writes, None if unscoped
+    pub fn schema(&self) -> Option<&str> {
+        self.agent.as_ref().map(|agent| agent.name.as_str())
+    }
+
+    /// Quoted schema of the agent, created with its tables if needed
+    async fn tables(&self) -> Result<String, StorageError> {
+        let agent = self.agent.as_ref().ok_or_else(|| {
+            StorageError::Backend(
+                "Postgres backend is not scoped to an agent, see StorageBackend::for_agent"
+                    .to_string(),
+            )
+        })?;
+        agent
+            .ready
+            .get_or_try_init(|| self.prepare_schema(agent))
+            .await?;
+        Ok(quote(&agent.name))
+    }
+
+    /// Create the schema and tables of an agent unless they exist, and the
+    /// sequences of their IDs
+    async fn prepare_schema(&self, agent: &AgentSchema) -> Result<(), StorageError> {
+        let mut tx = self.pool.begin().await?;
+        // Nodes of the same agent prepare its schema one at a time
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(&agent.name)
+            .execute(&mut *tx)
+            .await?;
+        register_agent_schema(&mut tx, &agent.did, &self.now()).await?;
+        create_agent_tables(&mut tx, &agent.name, &TABLES).await?;
+
+        let schema = quote(&agent.name);
+        let mut statements = vec![
+            format!("CREATE UNIQUE INDEX IF NOT EXISTS messages_message_id ON {schema}.messages (message_id)"),
+            format!("CREATE UNIQUE INDEX IF NOT EXISTS transactions_reference_id ON {schema}.transactions (reference_id)"),
+            format!("CREATE INDEX IF NOT EXISTS deliveries_message_id ON {schema}.deliveries (message_id)"),
+        ];
+        for table in TABLES {
+            let sequence = format!("{schema}.{}", quote(&format!("{table}_id_seq")));
+            statements.push(format!("CREATE SEQUENCE IF NOT EXISTS {sequence}"));
+            statements.push(format!(
+                "ALTER TABLE {schema}.{table} ALTER COLUMN id SET DEFAULT nextval('{sequence}')"
+            ));
+            // Continue after rows migrated since the sequence was last used
+            statements.push(format!(
+                r#"
+                SELECT setval('{sequence}', ids.max_id + 1, false)
+                FROM (SELECT COALESCE(MAX(id), 0) AS max_id FROM {schema}.{table}) AS ids, {sequence} AS seq
+                WHERE ids.max_id >= seq.last_value
+                "#
+            ));
+        }
+        for statement in statements {
+            sqlx::query(&statement).execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        debug!("Prepared Postgres schema {} of {}", agent.name, agent.did);
+        Ok(())
+    }
+
+    fn now(&self) -> String {
+        self.clock.now().format("%Y-%m-%dT%H:%M:%SZ").to_string()
+    }
+}
+
+fn transaction_from_row(row: TransactionRow) -> Result<Transaction, StorageError> {
+    let (
+        id,
+        tx_type,
+        reference_id,
+        from_did,
+        to_did,
+        thread_id,
+        message_type,
+        status,
+        message_json,
+        rejection_code,
+        rejection_reason,
+        canonical_hash,
+        created_at,
+        updated_at,
+    ) = row;
+    Ok(Transaction {
+        id,
+        transaction_type: TransactionType::try_from(tx_type.as_str())
+            .map_err(StorageError::InvalidTransactionType)?,
+        reference_id,
+        from_did,
+        to_did,
+        thread_id,
+        message_type,
+        status: TransactionStatus::try_from(status.as_str())
+            .map_err(StorageError::InvalidTransactionType)?,
+        message_json: serde_json::from_str(&message_json)?,
+        rejection_code,
+        rejection_reason,
+        canonical_hash,
+        created_at,
+        updated_at,
+    })
+}
+
+fn message_from_row(row: MessageRow) -> Result<Message, StorageError> {
+    let (
+        id,
+        message_id,
+        message_type,
+        from_did,
+        to_did,
+        thread_id,
+        parent_thread_id,
+        direction,
+        message_json,
+        created_at,
+    ) = row;
+    Ok(Message {
+        id,
+        message_id,
+        message_type,
+        from_did,
+        to_did,
+        thread_id,
+        parent_thread_id,
+        direction: MessageDirection::try_from(direction.as_str())
+            .map_err(StorageError::InvalidTransactionType)?,
+        message_json: serde_json::from_str(&message_json)?,
+        created_at,
+    })
+}
+
+fn delivery_from_row(row: DeliveryRow) -> Result<Delivery, StorageError> {
+    let (
+        id,
+        message_id,
+        message_text,
+        recipient_did,
+        delivery_url,
+        delivery_type,
+        status,
+        retry_count,
+        last_http_status_code,
+        error_message,
+        created_at,
+        updated_at,
+        delivered_at,
+        next_attempt_at,
+    ) = row;
+    let out_of_range = |column: &str| {
+        StorageError::Backend(format!("Delivery {} has an out of range {}", id, column))
+    };
+    Ok(Delivery {
+        id,
+        message_id,
+        message_text,
+        recipient_did,
+        delivery_url,
+        delivery_type: DeliveryType::try_from(delivery_type.as_str())
+            .map_err(StorageError::InvalidTransactionType)?,
+        status: DeliveryStatus::try_from(status.as_str())
+            .map_err(StorageError::InvalidTransactionType)?,
+        retry_count: i32::try_from(retry_count).map_err(|_| out_of_range("retry_count"))?,
+        last_http_status_code: last_http_status_code
+            .map(i32::try_from)
+            .transpose()
+            .map_err(|_| out_of_range("last_http_status_code"))?,
+        error_message,
+        created_at,
+        updated_at,
+        delivered_at,
+        next_attempt_at,
+    })
+}
+
+#[async_trait]
+impl StorageBackend for PostgresBackend {
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+
+    fn for_agent(&self, agent_did: &str, clock: Arc<dyn Clock>) -> Arc<dyn StorageBackend> {
+        Arc::new(Self {
+            pool: self.pool.clone(),
+            clock,
+            agent: Some(Arc::new(AgentSchema {
+                did: agent_did.to_string(),
+                name: PostgresMigration::schema_name(agent_did),
+                ready: OnceCell::new(),
+            })),
+        })
+    }
+
+    async fn log_message(
+        &self,
+        message: &PlainMessage,
+        direction: MessageDirection,
+    ) -> Result<(), StorageError> {
+        let schema = self.tables().await?;
+        debug!("Logging {} message {} in Postgres", direction, message.id);
+        sqlx::query(&format!(
+            r#"
+            INSERT INTO {schema}.messages (message_id, message_type, from_did, to_did, thread_id, parent_thread_id, direction, message_json, status, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, 'pending', $9)
+            ON CONFLICT (message_id) DO NOTHING
+            "#
+        ))
+        .bind(&message.id)
+        .bind(&message.type_)
+        .bind(&message.from)
+        .bind(message.to.first())
+        .bind(&message.thid)
+        .bind(&message.pthid)
+        .bind(direction.to_string())
+        .bind(serde_json::to_string(message)?)
+        .bind(self.now())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_message_by_id(&self, message_id: &str) -> Result<Option<Message>, StorageError> {
+        let schema = self.tables().await?;
+        sqlx::query_as::<_, MessageRow>(&format!(
+            "SELECT {MESSAGE_COLUMNS} FROM {schema}.messages WHERE message_id = $1"
+        ))
+        .bind(message_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .map(message_from_row)
+        .transpose()
+    }
+
+    async fn list_messages(
+        &self,
+        limit: u32,
+        offset: u32,
+        direction: Option<MessageDirection>,
+    ) -> Result<Vec<Message>, StorageError> {
+        let schema = self.tables().await?;
+        sqlx::query_as::<_, MessageRow>(&format!(
+            r#"
+            SELECT {MESSAGE_COLUMNS} FROM {schema}.messages
+            WHERE $1::TEXT IS NULL OR direction = $1
+            ORDER BY created_at DESC, id DESC
+            LIMIT $2 OFFSET $3
+            "#
+        ))
+        .bind(direction.map(|direction| direction.to_string()))
+        .bind(i64::from(limit))
+        .bind(i64::from(offset))
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(message_from_row)
+        .collect()
+    }
+
+    async fn insert_transaction(&self, message: &PlainMessage) -> Result<(), StorageError> {
+        let message_type_lower = message.type_.to_lowercase();
+        let tx_type = if message_type_lower.contains("transfer") {
+            TransactionType::Transfer
+        } else if message_type_lower.contains("payment") {
+            TransactionType::Payment
+        } else {
+            return Err(StorageError::InvalidTransactionType(message.type_.clone()));
+        };
+
+        let schema = self.tables().await?;
+        let result = sqlx::query(&format!(
+            r#"
+            INSERT INTO {schema}.transactions (type, reference_id, from_did, to_did, thread_id, parent_thread_id, message_type, status, message_json, canonical_hash, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, 'pending', $8, $9, $10, $10)
+            "#
+        ))
+        .bind(tx_type.to_string())
+        .bind(&message.id)
+        .bind(&message.from)
+        .bind(message.to.first())
+        .bind(&message.thid)
+        .bind(&message.pthid)
+        .bind(&message.type_)
+        .bind(serde_json::to_string(message)?)
+        .bind(canonical_transaction_hash(&message.body, &message.from))
+        .bind(self.now())
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
+                Err(StorageError::DuplicateTransaction(message.id.clone()))
+            }
+            Err(e) => Err(StorageError::Database(e)),
+        }
+    }
+
+    async fn update_transaction_status(
+        &self,
+        transaction_id: &str,
+        status: &str,
+    ) -> Result<(), StorageError> {
+        let schema = self.tables().await?;
+        sqlx::query(&format!(
+            "UPDATE {schema}.transactions SET status = $1, updated_at = $2 WHERE reference_id = $3"
+        ))
+        .bind(status)
+        .bind(self.now())
+        .bind(transaction_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn record_rejection(
+        &self,
+        transaction_id: &str,
+        code: Option<&str>,
+        reason: Option<&str>,
+    ) -> Result<(), StorageError> {
+        let schema = self.tables().await?;
+        sqlx::query(&format!(
+            r#"
+            UPDATE {schema}.transactions
+            SET rejection_code = $1, rejection_reason = $2, updated_at = $3
+            WHERE reference_id = $4
+            "#
+        ))
+        .bind(code)
+        .bind(reason)
+        .bind(self.now())
+        .bind(transaction_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_transaction_by_id(
+        &self,
+        reference_id: &str,
+    ) -> Result<Option<Transaction>, StorageError> {
+        let schema = self.tables().await?;
+        sqlx::query_as::<_, TransactionRow>(&format!(
+            "SELECT {TRANSACTION_COLUMNS} FROM {schema}.transactions WHERE reference_id = $1"
+        ))
+        .bind(reference_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .map(transaction_from_row)
+        .transpose()
+    }
+
+    async fn list_transactions(
+        &self,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<Transaction>, StorageError> {
+        let schema = self.tables().await?;
+        sqlx::query_as::<_, TransactionRow>(&format!(
+            r#"
+            SELECT {TRANSACTION_COLUMNS} FROM {schema}.transactions
+            ORDER BY created_at DESC, id DESC
+            LIMIT $1 OFFSET $2
+            "#
+        ))
+        .bind(i64::from(limit))
+        .bind(i64::from(offset))
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(transaction_from_row)
+        .collect()
+    }
+
+    async fn create_delivery(
+        &self,
+        message_id: &str,
+        message_text: &str,
+        recipient_did: &str,
+        delivery_url: Option<&str>,
+        delivery_type: DeliveryType,
+    ) -> Result<i64, StorageError> {
+        let schema = self.tables().await?;
+        let id = sqlx::query_scalar::<_, i64>(&format!(
+            r#"
+            INSERT INTO {schema}.deliveries (message_id, message_text, recipient_did, delivery_url, delivery_type, status, retry_count, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, 'pending', 0, $6, $6)
+            RETURNING id
+            "#
+        ))
+        .bind(message_id)
+        .bind(message_text)
+        .bind(recipient_did)
+        .bind(delivery_url)
+        .bind(delivery_type.to_string())
+        .bind(self.now())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn update_delivery_status(
+        &self,
+        delivery_id: i64,
+        status: DeliveryStatus,
+        http_status_code: Option<i32>,
+        error_message: Option<&str>,
+    ) -> Result<(), StorageError> {
+        let schema = self.tables().await?;
+        let now = self.now();
+        let delivered_at = (status == DeliveryStatus::Success).then(|| now.clone());
+        // Only failed deliveries wait for their next attempt
+        sqlx::query(&format!(
+            r#"
+            UPDATE {schema}.deliveries
+            SET status = $1, last_http_status_code = $2, error_message = $3, updated_at = $4, delivered_at = $5,
+                next_attempt_at = CASE WHEN $1 = 'failed' THEN next_attempt_at END
+            WHERE id = $6
+            "#
+        ))
+        .bind(status.to_string())
+        .bind(http_status_code.map(i64::from))
+        .bind(error_message)
+        .bind(now)
+        .bind(delivered_at)
+        .bind(delivery_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn increment_delivery_retry_count(&self, delivery_id: i64) -> Result<(), StorageError> {
+        let schema = self.tables().await?;
+        sqlx::query(&format!(
+            "UPDATE {schema}.deliveries SET retry_count = retry_count + 1, updated_at = $1 WHERE id = $2"
+        ))
+        .bind(self.now())
+        .bind(delivery_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn schedule_delivery_attempt(
+        &self,
+        delivery_id: i64,
+        next_attempt_at: Option<&str>,
+    ) -> Result<(), StorageError> {
+        let schema = self.tables().await?;
+        sqlx::query(&format!(
+            "UPDATE {schema}.deliveries SET next_attempt_at = $1, updated_at = $2 WHERE id = $3"
+        ))
+        .bind(next_attempt_at)
+        .bind(self.now())
+        .bind(delivery_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn reassign_delivery(
+        &self,
+        delivery_id: i64,
+        delivery_url: &str,
+    ) -> Result<(), StorageError> {
+        let schema = self.tables().await?;
+        sqlx::query(&format!(
+            "UPDATE {schema}.deliveries SET delivery_url = $1, updated_at = $2 WHERE id = $3"
+        ))
+        .bind(delivery_url)
+        .bind(self.now())
+        .bind(delivery_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_deliveries_for_message(
+        &self,
+        message_id: &str,
+    ) -> Result<Vec<Delivery>, StorageError> {
+        let schema = self.tables().await?;
+        sqlx::query_as::<_, DeliveryRow>(&format!(
+            r#"
+            SELECT {DELIVERY_COLUMNS} FROM {schema}.deliveries
+            WHERE message_id = $1
+            ORDER BY created_at ASC, id ASC
+            "#
+        ))
+        .bind(message_id)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(delivery_from_row)
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unscoped_handle_refuses_records() {
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/tap")
+            .unwrap();
+        let backend = PostgresBackend::new(pool);
+        assert_eq!(backend.schema(), None);
+        assert!(matches!(
+            backend.list_transactions(10, 0).await,
+            Err(StorageError::Backend(_))
+        ));
+
+        let agent = backend.for_agent("did:key:alice", system_clock());
+        assert!(format!("{:?}", agent).contains(&PostgresMigration::schema_name("did:key:alice")));
+    }
+}
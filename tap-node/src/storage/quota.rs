@@ -0,0 +1,107 @@
+//! Storage quotas and disk usage reporting
+//!
+//! A [`StorageQuota`] caps the size of an agent's database. Once the database
+//! grows past the quota, non-essential writes are either rejected or preceded
+//! by archival, depending on the [`QuotaAction`]. Writes that make up the TAP
+//! audit trail (transactions, TAP messages, decisions and deliveries) are never
+//! rejected.
+//!
+//! Non-essential writes are raw copies of received messages and logs of
+//! messages from protocols other than TAP, such as trust pings.
+//!
+//! Archival snapshots the whole database into an `archive` directory next to
+//! it, then removes processed raw received messages and successful delivery
+//! records before compacting the database.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// What happens to non-essential writes once a quota is exceeded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaAction {
+    /// Reject the write with [`StorageError::QuotaExceeded`](super::StorageError::QuotaExceeded)
+    #[default]
+    Reject,
+    /// Archive and compact the database, rejecting the write only if it is
+    /// still over quota afterwards
+    Archive,
+}
+
+/// Maximum size of an agent's database
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageQuota {
+    /// Maximum database size in bytes
+    pub max_bytes: u64,
+    /// Action taken when the database exceeds `max_bytes`
+    pub on_exceeded: QuotaAction,
+}
+
+impl StorageQuota {
+    /// Create a quota that rejects non-essential writes beyond `max_bytes`
+    pub fn new(max_bytes: u64) -> Self {
+        Self {
+            max_bytes,
+            on_exceeded: QuotaAction::Reject,
+        }
+    }
+
+    /// Set the action taken when the quota is exceeded
+    pub fn with_action(mut self, on_exceeded: QuotaAction) -> Self {
+        self.on_exceeded = on_exceeded;
+        self
+    }
+}
+
+/// Quotas applied to agent databases
+#[derive(Debug, Clone, Default)]
+pub struct StorageQuotas {
+    /// Quota for agents without their own entry (None means unlimited)
+    pub default_quota: Option<StorageQuota>,
+    /// Quotas for individual agents, keyed by DID
+    pub agent_quotas: HashMap<String, StorageQuota>,
+}
+
+impl StorageQuotas {
+    /// Quota that applies to an agent's database
+    pub fn quota_for(&self, agent_did: &str) -> Option<StorageQuota> {
+        self.agent_quotas
+            .get(agent_did)
+            .copied()
+            .or(self.default_quota)
+    }
+}
+
+/// Size of a table and its indexes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableUsage {
+    pub name: String,
+    pub rows: u64,
+    /// Bytes used by the table and its indexes
+    pub size_bytes: u64,
+}
+
+/// Disk usage of a database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageUsage {
+    pub db_path: PathBuf,
+    /// Size of the database counted against the quota
+    pub size_bytes: u64,
+    /// Bytes on disk, including the write-ahead log
+    pub file_bytes: u64,
+    /// Bytes in free pages that compaction would reclaim
+    pub free_bytes: u64,
+    pub quota: Option<StorageQuota>,
+    pub quota_exceeded: bool,
+    /// Tables ordered from largest to smallest
+    pub tables: Vec<TableUsage>,
+}
+
+/// Disk usage of an agent's database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentStorageUsage {
+    pub agent_did: String,
+    #[serde(flatten)]
+    pub usage: StorageUsage,
+}
@@ -0,0 +1,475 @@
+//! Read-only storage handles for query load
+//!
+//! Dashboards and reports page through transactions, messages and customers
+//! far more often than messages arrive. Served from the primary connection
+//! pool, those queries hold connections that message processing needs. A
+//! [`ReadOnlyStorage`] has its own pool of read-only connections, opened
+//! either on the primary database file or on a replica of it, and only
+//! exposes the list, search and report queries.
+//!
+//! Reads through a replica may lag the primary. Code that reads its own
+//! writes, such as decision replay or customer deduplication, keeps using
+//! [`Storage`].
+
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing::{info, warn};
+
+use super::db::Storage;
+use super::error::StorageError;
+use super::models::{
+    AddressSighting, ConversionStatus, CounterpartyStats, Customer, DecisionLogEntry,
+    DecisionStatus, Draft, DraftStatus, KeyAttestationRecord, Message, MessageDirection,
+    PresentationVerificationRecord, Received, ReceivedStatus, ReusedAddress, ReviewItem,
+    ReviewStatus, ScheduledJobRecord, SettlementConversion, SourceType, Transaction,
+    TransactionDocument, TransactionGraph, TransactionValuation, TransactionWarning,
+};
+
+/// Default number of connections in a read-only pool
+pub const DEFAULT_READ_CONNECTIONS: u32 = 10;
+
+/// Where read-only handles of agent databases are opened
+#[derive(Debug, Clone)]
+pub struct ReadReplicaConfig {
+    /// Root of replica databases laid out like the TAP root, i.e.
+    /// `{replica_root}/{sanitized_did}/transactions.db` (None opens the
+    /// primary databases read-only)
+    pub replica_root: Option<PathBuf>,
+    /// Connections in each read-only pool
+    pub max_connections: u32,
+}
+
+impl Default for ReadReplicaConfig {
+    fn default() -> Self {
+        Self {
+            replica_root: None,
+            max_connections: DEFAULT_READ_CONNECTIONS,
+        }
+    }
+}
+
+impl ReadReplicaConfig {
+    /// Replica database of an agent, if a replica root is configured
+    pub fn replica_path(&self, agent_did: &str) -> Option<PathBuf> {
+        self.replica_root
+            .as_ref()
+            .map(|root| Storage::agent_db_path(root, agent_did))
+    }
+}
+
+/// Query-only handle on a TAP database
+///
+/// # Example
+///
+/// ```no_run
+/// use tap_node::storage::ReadOnlyStorage;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let replica = ReadOnlyStorage::open("/replicas/tap-node.db", 20).await?;
+/// let transactions = replica.list_transactions(50, 0).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ReadOnlyStorage {
+    storage: Storage,
+}
+
+impl ReadOnlyStorage {
+    /// Open a read-only pool on an existing database file
+    ///
+    /// The file is not created or migrated; it must be a TAP database, such
+    /// as a replica of a primary database.
+    pub async fn open(path: impl AsRef<Path>, max_connections: u32) -> Result<Self, StorageError> {
+        let path = path.as_ref();
+        info!("Opening read-only storage at: {:?}", path);
+
+        let options = SqliteConnectOptions::from_str(&format!("sqlite://{}", path.display()))?
+            .read_only(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(max_connections.max(1))
+            .connect_with(options)
+            .await?;
+
+        Ok(Self {
+            storage: Storage::from_pool(pool, path.to_path_buf()),
+        })
+    }
+
+    /// Open a read-only handle for `primary`
+    ///
+    /// Uses the replica at `replica` if given and present, otherwise the
+    /// primary database file. In-memory databases cannot be reopened, so
+    /// their handle shares the primary pool.
+    pub async fn for_primary(
+        primary: &Storage,
+        replica: Option<&Path>,
+        max_connections: u32,
+    ) -> Result<Self, StorageError> {
+        if let Some(replica) = replica {
+            if replica.exists() {
+                return Self::open(replica, max_connections).await;
+            }
+            warn!(
+                "Read replica {:?} does not exist, reading from primary {:?}",
+                replica,
+                primary.db_path()
+            );
+        }
+
+        if primary.is_in_memory() {
+            return Ok(Self {
+                storage: primary.clone(),
+            });
+        }
+        Self::open(primary.db_path(), max_connections).await
+    }
+
+    /// Path of the database this handle reads
+    pub fn db_path(&self) -> &Path {
+        self.storage.db_path()
+    }
+
+    /// See [`Storage::list_transactions`]
+    pub async fn list_transactions(
+        &self,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<Transaction>, StorageError> {
+        self.storage.list_transactions(limit, offset).await
+    }
+
+    /// See [`Storage::get_transaction_by_canonical_hash`]
+    pub async fn get_transaction_by_canonical_hash(
+        &self,
+        canonical_hash: &str,
+    ) -> Result<Option<Transaction>, StorageError> {
+        self.storage
+            .get_transaction_by_canonical_hash(canonical_hash)
+            .await
+    }
+
+    /// See [`Storage::get_transaction_graph`]
+    pub async fn get_transaction_graph(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Option<TransactionGraph>, StorageError> {
+        self.storage.get_transaction_graph(transaction_id).await
+    }
+
+    /// See [`Storage::list_messages`]
+    pub async fn list_messages(
+        &self,
+        limit: u32,
+        offset: u32,
+        direction: Option<MessageDirection>,
+    ) -> Result<Vec<Message>, StorageError> {
+        self.storage.list_messages(limit, offset, direction).await
+    }
+
+    /// See [`Storage::list_received`]
+    pub async fn list_received(
+        &self,
+        limit: u32,
+        offset: u32,
+        source_type: Option<SourceType>,
+        status: Option<ReceivedStatus>,
+    ) -> Result<Vec<Received>, StorageError> {
+        self.storage
+            .list_received(limit, offset, source_type, status)
+            .await
+    }
+
+    /// See [`Storage::list_customers`]
+    pub async fn list_customers(
+        &self,
+        agent_did: &str,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<Customer>, StorageError> {
+        self.storage.list_customers(agent_did, limit, offset).await
+    }
+
+    /// See [`Storage::search_customers`]
+    pub async fn search_customers(
+        &self,
+        agent_did: &str,
+        query: &str,
+        limit: u32,
+    ) -> Result<Vec<Customer>, StorageError> {
+        self.storage.search_customers(agent_did, query, limit).await
+    }
+
+    /// See [`Storage::list_decisions`]
+    pub async fn list_decisions(
+        &self,
+        agent_did: Option<&str>,
+        status: Option<DecisionStatus>,
+        since_id: Option<i64>,
+        limit: u32,
+    ) -> Result<Vec<DecisionLogEntry>, StorageError> {
+        self.storage
+            .list_decisions(agent_did, status, since_id, limit)
+            .await
+    }
+
+    /// See [`Storage::list_review_items`]
+    pub async fn list_review_items(
+        &self,
+        agent_did: Option<&str>,
+        status: Option<ReviewStatus>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<ReviewItem>, StorageError> {
+        self.storage
+            .list_review_items(agent_did, status, limit, offset)
+            .await
+    }
+
+    /// See [`Storage::list_transaction_warnings`]
+    pub async fn list_transaction_warnings(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Vec<TransactionWarning>, StorageError> {
+        self.storage.list_transaction_warnings(transaction_id).await
+    }
+
+    /// See [`Storage::get_counterparty_stats`]
+    pub async fn get_counterparty_stats(
+        &self,
+        counterparty_did: &str,
+        since: Option<&str>,
+        until: Option<&str>,
+    ) -> Result<CounterpartyStats, StorageError> {
+        self.storage
+            .get_counterparty_stats(counterparty_did, since, until)
+            .await
+    }
+
+    /// See [`Storage::list_documents`]
+    pub async fn list_documents(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Vec<TransactionDocument>, StorageError> {
+        self.storage.list_documents(transaction_id).await
+    }
+
+    /// See [`Storage::get_document`]
+    pub async fn get_document(
+        &self,
+        transaction_id: &str,
+        document_id: &str,
+    ) -> Result<Option<TransactionDocument>, StorageError> {
+        self.storage.get_document(transaction_id, document_id).await
+    }
+
+    /// See [`Storage::list_key_attestations`]
+    pub async fn list_key_attestations(
+        &self,
+        agent_did: &str,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<KeyAttestationRecord>, StorageError> {
+        self.storage
+            .list_key_attestations(agent_did, limit, offset)
+            .await
+    }
+
+    /// See [`Storage::list_presentation_verifications`]
+    pub async fn list_presentation_verifications(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Vec<PresentationVerificationRecord>, StorageError> {
+        self.storage
+            .list_presentation_verifications(transaction_id)
+            .await
+    }
+
+    /// See [`Storage::list_scheduled_jobs`]
+    pub async fn list_scheduled_jobs(&self) -> Result<Vec<ScheduledJobRecord>, StorageError> {
+        self.storage.list_scheduled_jobs().await
+    }
+
+    /// See [`Storage::list_pii_access`]
+    pub async fn list_pii_access(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Vec<super::PiiAccess>, StorageError> {
+        self.storage.list_pii_access(transaction_id).await
+    }
+
+    /// See [`Storage::list_envelope_stats`]
+    pub async fn list_envelope_stats(
+        &self,
+        counterparty_did: Option<&str>,
+    ) -> Result<Vec<crate::interop::EnvelopeStats>, StorageError> {
+        self.storage.list_envelope_stats(counterparty_did).await
+    }
+
+    /// See [`Storage::list_drafts`]
+    pub async fn list_drafts(
+        &self,
+        agent_did: Option<&str>,
+        status: Option<DraftStatus>,
+        include_expired: bool,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<Draft>, StorageError> {
+        self.storage
+            .list_drafts(agent_did, status, include_expired, limit, offset)
+            .await
+    }
+
+    /// See [`Storage::count_rejections_by_code`]
+    pub async fn count_rejections_by_code(
+        &self,
+        since: Option<&str>,
+        until: Option<&str>,
+    ) -> Result<Vec<(Option<String>, i64)>, StorageError> {
+        self.storage.count_rejections_by_code(since, until).await
+    }
+
+    /// See [`Storage::list_transaction_valuations`]
+    pub async fn list_transaction_valuations(
+        &self,
+        since: Option<&str>,
+        until: Option<&str>,
+    ) -> Result<Vec<TransactionValuation>, StorageError> {
+        self.storage.list_transaction_valuations(since, until).await
+    }
+
+    /// See [`Storage::get_settlement_conversion`]
+    pub async fn get_settlement_conversion(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Option<SettlementConversion>, StorageError> {
+        self.storage.get_settlement_conversion(transaction_id).await
+    }
+
+    /// See [`Storage::list_settlement_conversions`]
+    pub async fn list_settlement_conversions(
+        &self,
+        status: Option<ConversionStatus>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<SettlementConversion>, StorageError> {
+        self.storage
+            .list_settlement_conversions(status, limit, offset)
+            .await
+    }
+
+    /// See [`Storage::list_address_sightings`]
+    pub async fn list_address_sightings(
+        &self,
+        address: &str,
+    ) -> Result<Vec<AddressSighting>, StorageError> {
+        self.storage.list_address_sightings(address).await
+    }
+
+    /// See [`Storage::list_transaction_addresses`]
+    pub async fn list_transaction_addresses(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Vec<AddressSighting>, StorageError> {
+        self.storage
+            .list_transaction_addresses(transaction_id)
+            .await
+    }
+
+    /// See [`Storage::list_reused_addresses`]
+    pub async fn list_reused_addresses(
+        &self,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<ReusedAddress>, StorageError> {
+        self.storage.list_reused_addresses(limit, offset).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tap_msg::didcomm::PlainMessage;
+    use tempfile::TempDir;
+
+    fn message(id: &str) -> PlainMessage {
+        PlainMessage::new(
+            id.to_string(),
+            "https://didcomm.org/basicmessage/2.0/message".to_string(),
+            serde_json::json!({"content": "hello"}),
+            "did:example:alice".to_string(),
+        )
+        .with_recipient("did:example:bob")
+    }
+
+    #[tokio::test]
+    async fn test_reader_sees_primary_writes() {
+        let dir = TempDir::new().unwrap();
+        let primary = Storage::new(Some(dir.path().join("primary.db")))
+            .await
+            .unwrap();
+        let reader = ReadOnlyStorage::for_primary(&primary, None, 2)
+            .await
+            .unwrap();
+        assert_eq!(reader.db_path(), primary.db_path());
+
+        primary
+            .log_message(&message("msg-1"), MessageDirection::Incoming)
+            .await
+            .unwrap();
+        let messages = reader.list_messages(10, 0, None).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].message_id, "msg-1");
+
+        // The handle's connections cannot write
+        let result = reader
+            .storage
+            .log_message(&message("msg-2"), MessageDirection::Incoming)
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reader_uses_replica_when_present() {
+        let dir = TempDir::new().unwrap();
+        let primary = Storage::new(Some(dir.path().join("primary.db")))
+            .await
+            .unwrap();
+        let replica = Storage::new(Some(dir.path().join("replica.db")))
+            .await
+            .unwrap();
+        replica
+            .log_message(&message("replicated"), MessageDirection::Incoming)
+            .await
+            .unwrap();
+
+        let reader = ReadOnlyStorage::for_primary(&primary, Some(replica.db_path()), 2)
+            .await
+            .unwrap();
+        let messages = reader.list_messages(10, 0, None).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].message_id, "replicated");
+
+        // A missing replica falls back to the primary
+        let missing = dir.path().join("missing.db");
+        let reader = ReadOnlyStorage::for_primary(&primary, Some(&missing), 2)
+            .await
+            .unwrap();
+        assert_eq!(reader.db_path(), primary.db_path());
+        assert!(reader.list_messages(10, 0, None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_reader_shares_pool() {
+        let primary = Storage::new_in_memory().await.unwrap();
+        let reader = ReadOnlyStorage::for_primary(&primary, None, 2)
+            .await
+            .unwrap();
+        primary
+            .log_message(&message("msg-1"), MessageDirection::Outgoing)
+            .await
+            .unwrap();
+        assert_eq!(reader.list_messages(10, 0, None).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,678 @@
+//! Integrity checks and recovery of corrupt databases
+//!
+//! [`Storage::open_checked`] opens a database like [`Storage::new`], then
+//! runs `PRAGMA integrity_check` on it. A database that fails the check, or
+//! that SQLite refuses to open as malformed, is corrupt: it is moved to a
+//! `quarantine` directory next to it and the [`RecoveryStrategy`]s of the
+//! [`StorageRecovery`] are tried in order until one yields a usable database.
+//!
+//! - [`RecoveryStrategy::RestoreSnapshot`] restores the most recent snapshot
+//!   in the `archive` directory next to the database that passes the check
+//! - [`RecoveryStrategy::RebuildFromMessages`] starts a new database, copies
+//!   every readable row of the message log out of the quarantined one and
+//!   rebuilds the transactions from the logged Transfers and Payments
+//! - [`RecoveryStrategy::Quarantine`] starts a new, empty database
+//!
+//! The quarantined database is never deleted. The outcome is described by a
+//! [`RecoveryReport`], which is also carried by
+//! [`StorageError::Corrupted`] when no strategy succeeds.
+
+use super::db::Storage;
+use super::error::StorageError;
+use crate::event::{EventBus, NodeEvent};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use std::path::{Path, PathBuf};
+use tap_msg::didcomm::PlainMessage;
+use tracing::{error, info, warn};
+
+/// Rows copied per statement when salvaging the message log
+const SALVAGE_BATCH_SIZE: i64 = 500;
+
+/// A way to get a usable database back after corruption
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryStrategy {
+    /// Restore the most recent intact snapshot from the `archive` directory
+    RestoreSnapshot,
+    /// Start a new database and rebuild it from the readable part of the
+    /// corrupt database's message log
+    RebuildFromMessages,
+    /// Start a new, empty database
+    Quarantine,
+}
+
+impl std::fmt::Display for RecoveryStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecoveryStrategy::RestoreSnapshot => write!(f, "restore_snapshot"),
+            RecoveryStrategy::RebuildFromMessages => write!(f, "rebuild_from_messages"),
+            RecoveryStrategy::Quarantine => write!(f, "quarantine"),
+        }
+    }
+}
+
+/// How databases are checked when they are opened, and recovered when corrupt
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageRecovery {
+    /// Run `PRAGMA quick_check` instead of the slower `PRAGMA integrity_check`,
+    /// which also verifies that indexes match their tables
+    pub quick_check: bool,
+    /// Strategies tried in order on a corrupt database (empty fails the
+    /// opening with [`StorageError::Corrupted`])
+    pub strategies: Vec<RecoveryStrategy>,
+}
+
+impl StorageRecovery {
+    /// Try `strategies` in order on corrupt databases
+    pub fn new(strategies: Vec<RecoveryStrategy>) -> Self {
+        Self {
+            quick_check: false,
+            strategies,
+        }
+    }
+
+    /// Run the quicker `PRAGMA quick_check` when opening databases
+    pub fn with_quick_check(mut self, quick_check: bool) -> Self {
+        self.quick_check = quick_check;
+        self
+    }
+}
+
+/// Result of an integrity check
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    /// Problems reported by SQLite, empty if the database is intact
+    pub problems: Vec<String>,
+}
+
+impl IntegrityReport {
+    /// Whether the database passed the check
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// A recovery strategy that was tried and failed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecoveryAttempt {
+    /// The strategy
+    pub strategy: RecoveryStrategy,
+    /// Why it failed
+    pub error: String,
+}
+
+/// What was found in a corrupt database and how it was recovered
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecoveryReport {
+    /// The corrupt database
+    pub db_path: PathBuf,
+    /// Problems found by the integrity check, or the error opening the database
+    pub problems: Vec<String>,
+    /// Where the corrupt database was moved
+    pub quarantined_path: Option<PathBuf>,
+    /// Strategies that were tried and failed
+    pub failed_attempts: Vec<RecoveryAttempt>,
+    /// The strategy that recovered the database, None if none did
+    pub recovered_by: Option<RecoveryStrategy>,
+    /// The snapshot the database was restored from
+    pub restored_from: Option<PathBuf>,
+    /// Messages copied from the corrupt database's message log
+    pub salvaged_messages: u64,
+    /// Transactions rebuilt from the salvaged messages
+    pub rebuilt_transactions: u64,
+}
+
+impl RecoveryReport {
+    /// Whether a usable database was recovered
+    pub fn is_recovered(&self) -> bool {
+        self.recovered_by.is_some()
+    }
+}
+
+impl Storage {
+    /// Check the database for corruption
+    ///
+    /// Runs `PRAGMA quick_check` if `quick` is set, `PRAGMA integrity_check`
+    /// otherwise.
+    pub async fn check_integrity(&self, quick: bool) -> Result<IntegrityReport, StorageError> {
+        let pragma = if quick {
+            "PRAGMA quick_check"
+        } else {
+            "PRAGMA integrity_check"
+        };
+        let rows = sqlx::query(pragma).fetch_all(self.pool()).await?;
+        let problems = rows
+            .iter()
+            .map(|row| row.get::<String, _>(0))
+            .filter(|line| line != "ok")
+            .collect();
+        Ok(IntegrityReport { problems })
+    }
+
+    /// Open a database, checking its integrity and recovering it if corrupt
+    ///
+    /// Returns the storage, with a report if the database was corrupt and
+    /// recovered. Fails with [`StorageError::Corrupted`] if the database is
+    /// corrupt and none of the strategies of `recovery` succeeds.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the database, resolved like in [`Storage::new`]
+    /// * `recovery` - How the database is checked and recovered
+    pub async fn open_checked(
+        path: Option<PathBuf>,
+        recovery: &StorageRecovery,
+    ) -> Result<(Self, Option<RecoveryReport>), StorageError> {
+        let db_path = Self::resolve_db_path(path);
+
+        let problems = match Self::new(Some(db_path.clone())).await {
+            Ok(storage) => {
+                if storage.is_in_memory() {
+                    return Ok((storage, None));
+                }
+                let integrity = match storage.check_integrity(recovery.quick_check).await {
+                    Ok(integrity) => integrity,
+                    Err(e) if is_corruption(&e) => IntegrityReport {
+                        problems: vec![e.to_string()],
+                    },
+                    Err(e) => return Err(e),
+                };
+                if integrity.is_ok() {
+                    return Ok((storage, None));
+                }
+                storage.pool().close().await;
+                integrity.problems
+            }
+            Err(e) if is_corruption(&e) => vec![e.to_string()],
+            Err(e) => return Err(e),
+        };
+
+        error!("Database {:?} is corrupt: {}", db_path, problems.join("; "));
+        let (storage, report) = recover(&db_path, problems, recovery).await?;
+        match storage {
+            Some(storage) => Ok((storage, Some(report))),
+            None => Err(StorageError::Corrupted(Box::new(report))),
+        }
+    }
+}
+
+/// Whether an error means the database file is malformed
+pub(super) fn is_corruption(error: &StorageError) -> bool {
+    match error {
+        StorageError::Database(sqlx::Error::Database(e)) => e
+            .code()
+            .and_then(|code| code.parse::<i32>().ok())
+            // SQLITE_CORRUPT and SQLITE_NOTADB, with their extended codes
+            .is_some_and(|code| matches!(code & 0xff, 11 | 26)),
+        StorageError::Migration(message) => {
+            message.contains("malformed") || message.contains("not a database")
+        }
+        _ => false,
+    }
+}
+
+/// Quarantine a corrupt database and try the recovery strategies in order
+async fn recover(
+    db_path: &Path,
+    problems: Vec<String>,
+    recovery: &StorageRecovery,
+) -> Result<(Option<Storage>, RecoveryReport), StorageError> {
+    let mut report = RecoveryReport {
+        db_path: db_path.to_path_buf(),
+        problems,
+        ..Default::default()
+    };
+    if recovery.strategies.is_empty() {
+        return Ok((None, report));
+    }
+
+    let quarantined = quarantine(db_path)?;
+    warn!("Moved corrupt database {:?} to {:?}", db_path, quarantined);
+    report.quarantined_path = Some(quarantined.clone());
+
+    for strategy in &recovery.strategies {
+        let result = match strategy {
+            RecoveryStrategy::RestoreSnapshot => restore_snapshot(db_path, &mut report).await,
+            RecoveryStrategy::RebuildFromMessages => {
+                rebuild_from_messages(db_path, &quarantined, &mut report).await
+            }
+            RecoveryStrategy::Quarantine => Storage::new(Some(db_path.to_path_buf())).await,
+        };
+        match result {
+            Ok(storage) => {
+                info!("Recovered database {:?} with {}", db_path, strategy);
+                report.recovered_by = Some(*strategy);
+                return Ok((Some(storage), report));
+            }
+            Err(e) => {
+                warn!(
+                    "Could not recover database {:?} with {}: {}",
+                    db_path, strategy, e
+                );
+                remove_database(db_path)?;
+                report.failed_attempts.push(RecoveryAttempt {
+                    strategy: *strategy,
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+
+    Ok((None, report))
+}
+
+/// Files making up a database: the database itself, its WAL and shared memory
+fn database_files(db_path: &Path) -> Vec<(PathBuf, &'static str)> {
+    ["", "-wal", "-shm"]
+        .into_iter()
+        .map(|suffix| {
+            let mut path = db_path.as_os_str().to_owned();
+            path.push(suffix);
+            (PathBuf::from(path), suffix)
+        })
+        .collect()
+}
+
+/// Move a database and its WAL to `quarantine/` next to it
+fn quarantine(db_path: &Path) -> Result<PathBuf, StorageError> {
+    let dir = db_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join("quarantine");
+    std::fs::create_dir_all(&dir)?;
+    let stem = db_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "transactions".to_string());
+    let quarantined = dir.join(format!(
+        "{}-{}.db",
+        stem,
+        chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ")
+    ));
+
+    for (path, suffix) in database_files(db_path) {
+        if path.exists() {
+            let mut target = quarantined.as_os_str().to_owned();
+            target.push(suffix);
+            std::fs::rename(&path, PathBuf::from(target))?;
+        }
+    }
+    Ok(quarantined)
+}
+
+/// Remove what a failed strategy left at the database path
+fn remove_database(db_path: &Path) -> Result<(), StorageError> {
+    for (path, _) in database_files(db_path) {
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Restore the most recent intact snapshot from `archive/` next to the database
+async fn restore_snapshot(
+    db_path: &Path,
+    report: &mut RecoveryReport,
+) -> Result<Storage, StorageError> {
+    let archive_dir = db_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join("archive");
+    let mut snapshots: Vec<PathBuf> = match std::fs::read_dir(&archive_dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "db"))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    // Snapshot names end in their timestamp, so the newest sorts last
+    snapshots.sort();
+
+    for snapshot in snapshots.iter().rev() {
+        std::fs::copy(snapshot, db_path)?;
+        let storage = match Storage::new(Some(db_path.to_path_buf())).await {
+            Ok(storage) => storage,
+            Err(e) => {
+                warn!("Skipping unusable snapshot {:?}: {}", snapshot, e);
+                remove_database(db_path)?;
+                continue;
+            }
+        };
+        if storage.check_integrity(false).await?.is_ok() {
+            report.restored_from = Some(snapshot.clone());
+            return Ok(storage);
+        }
+        warn!("Skipping corrupt snapshot {:?}", snapshot);
+        storage.pool().close().await;
+        remove_database(db_path)?;
+    }
+
+    Err(StorageError::NotFound(format!(
+        "No intact snapshot in {:?}",
+        archive_dir
+    )))
+}
+
+/// Start a new database from the readable part of the quarantined message log
+async fn rebuild_from_messages(
+    db_path: &Path,
+    quarantined: &Path,
+    report: &mut RecoveryReport,
+) -> Result<Storage, StorageError> {
+    let storage = Storage::new(Some(db_path.to_path_buf())).await?;
+    report.salvaged_messages = salvage_messages(&storage, quarantined).await?;
+    report.rebuilt_transactions = rebuild_transactions(&storage).await?;
+    Ok(storage)
+}
+
+/// Copy the message log of a corrupt database, in batches, up to the first
+/// batch that cannot be read
+async fn salvage_messages(storage: &Storage, corrupt: &Path) -> Result<u64, StorageError> {
+    let mut conn = storage.pool().acquire().await?;
+    sqlx::query("ATTACH DATABASE ?1 AS salvage")
+        .bind(corrupt.to_string_lossy().to_string())
+        .execute(&mut *conn)
+        .await?;
+
+    let mut salvaged = 0;
+    let mut after = 0;
+    loop {
+        let result = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO main.messages
+                (id, message_id, message_type, from_did, to_did, thread_id,
+                 parent_thread_id, direction, message_json, status, created_at)
+            SELECT id, message_id, message_type, from_did, to_did, thread_id,
+                   parent_thread_id, direction, message_json, status, created_at
+            FROM salvage.messages
+            WHERE id > ?1
+            ORDER BY id ASC
+            LIMIT ?2
+            "#,
+        )
+        .bind(after)
+        .bind(SALVAGE_BATCH_SIZE)
+        .execute(&mut *conn)
+        .await;
+        match result {
+            Ok(result) if result.rows_affected() > 0 => {
+                salvaged += result.rows_affected();
+                after = sqlx::query_scalar("SELECT MAX(id) FROM main.messages")
+                    .fetch_one(&mut *conn)
+                    .await?;
+            }
+            Ok(_) => break,
+            Err(e) => {
+                warn!(
+                    "Stopped salvaging messages of {:?} after id {}: {}",
+                    corrupt, after, e
+                );
+                break;
+            }
+        }
+    }
+
+    sqlx::query("DETACH DATABASE salvage")
+        .execute(&mut *conn)
+        .await?;
+    Ok(salvaged)
+}
+
+/// Insert the logged Transfers and Payments as transactions, and apply the
+/// status changes of the Reject, Cancel, Settle and Revert messages in their
+/// threads
+async fn rebuild_transactions(storage: &Storage) -> Result<u64, StorageError> {
+    let mut rebuilt = 0;
+    let mut after = 0;
+    loop {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, message_type, thread_id, message_json
+            FROM messages
+            WHERE id > ?1
+            ORDER BY id ASC
+            LIMIT ?2
+            "#,
+        )
+        .bind(after)
+        .bind(SALVAGE_BATCH_SIZE)
+        .fetch_all(storage.pool())
+        .await?;
+        if rows.is_empty() {
+            break;
+        }
+
+        for row in rows {
+            after = row.get("id");
+            let message_type: String = row.get("message_type");
+            let status = match message_type.rsplit('#').next().unwrap_or_default() {
+                "Transfer" | "Payment" => {
+                    let message: PlainMessage =
+                        serde_json::from_value(row.get::<serde_json::Value, _>("message_json"))?;
+                    match storage.insert_transaction(&message).await {
+                        Ok(()) => rebuilt += 1,
+                        Err(e) => warn!("Could not rebuild transaction {}: {}", message.id, e),
+                    }
+                    continue;
+                }
+                "Reject" => "failed",
+                "Cancel" => "cancelled",
+                "Settle" => "confirmed",
+                "Revert" => "reverted",
+                _ => continue,
+            };
+            if let Some(thread_id) = row.get::<Option<String>, _>("thread_id") {
+                // Threads without a stored transaction are skipped
+                let _ = storage.update_transaction_status(&thread_id, status).await;
+            }
+        }
+    }
+    Ok(rebuilt)
+}
+
+/// Publish the events describing the recovery of a corrupt database
+pub(crate) async fn publish_report(event_bus: &EventBus, report: &RecoveryReport) {
+    let db_path = report.db_path.display().to_string();
+    event_bus
+        .publish_event(NodeEvent::StorageCorruptionDetected {
+            db_path: db_path.clone(),
+            problems: report.problems.clone(),
+        })
+        .await;
+
+    let event = match report.recovered_by {
+        Some(strategy) => NodeEvent::StorageRecovered {
+            db_path,
+            strategy: strategy.to_string(),
+            quarantined_path: report
+                .quarantined_path
+                .as_ref()
+                .map(|path| path.display().to_string()),
+            restored_from: report
+                .restored_from
+                .as_ref()
+                .map(|path| path.display().to_string()),
+            salvaged_messages: report.salvaged_messages,
+            rebuilt_transactions: report.rebuilt_transactions,
+        },
+        None => NodeEvent::StorageRecoveryFailed {
+            db_path,
+            errors: report
+                .failed_attempts
+                .iter()
+                .map(|attempt| format!("{}: {}", attempt.strategy, attempt.error))
+                .collect(),
+        },
+    };
+    event_bus.publish_event(event).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MessageDirection;
+    use crate::test_fixtures::{transfer_body, transfer_message};
+    use tempfile::TempDir;
+
+    fn reject_message(id: &str, transaction_id: &str) -> PlainMessage {
+        let mut message = PlainMessage::new(
+            id.to_string(),
+            "https://tap.rsvp/schema/1.0#Reject".to_string(),
+            serde_json::json!({ "reason": "Sanctioned" }),
+            "did:example:beneficiary".to_string(),
+        );
+        message.thid = Some(transaction_id.to_string());
+        message
+    }
+
+    /// Overwrite a closed database with bytes SQLite cannot open
+    fn corrupt(db_path: &Path) {
+        remove_database(db_path).unwrap();
+        std::fs::write(db_path, vec![0x42; 8192]).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_intact_database_opens_without_report() {
+        let dir = TempDir::new().unwrap();
+        let db_path = dir.path().join("transactions.db");
+
+        let (_storage, report) = Storage::open_checked(Some(db_path), &StorageRecovery::default())
+            .await
+            .unwrap();
+        assert!(report.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_database_is_quarantined() {
+        let dir = TempDir::new().unwrap();
+        let db_path = dir.path().join("transactions.db");
+        corrupt(&db_path);
+
+        // Without strategies the corruption is reported, not repaired
+        let result =
+            Storage::open_checked(Some(db_path.clone()), &StorageRecovery::default()).await;
+        match result {
+            Err(StorageError::Corrupted(report)) => {
+                assert!(!report.problems.is_empty());
+                assert!(report.quarantined_path.is_none());
+            }
+            other => panic!("Expected a corruption error, got {:?}", other.map(|_| ())),
+        }
+
+        let recovery = StorageRecovery::new(vec![
+            RecoveryStrategy::RestoreSnapshot,
+            RecoveryStrategy::Quarantine,
+        ]);
+        let (storage, report) = Storage::open_checked(Some(db_path.clone()), &recovery)
+            .await
+            .unwrap();
+        let report = report.unwrap();
+        assert_eq!(report.recovered_by, Some(RecoveryStrategy::Quarantine));
+        // There is no snapshot to restore
+        assert_eq!(report.failed_attempts.len(), 1);
+        assert!(report.quarantined_path.unwrap().exists());
+        assert!(storage.list_messages(10, 0, None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_restore_latest_intact_snapshot() {
+        let dir = TempDir::new().unwrap();
+        let db_path = dir.path().join("transactions.db");
+        let archive_dir = dir.path().join("archive");
+        std::fs::create_dir_all(&archive_dir).unwrap();
+
+        let storage = Storage::new(Some(db_path.clone())).await.unwrap();
+        storage
+            .log_message(
+                &transfer_message(
+                    "tx-1",
+                    &transfer_body("did:example:originator", "did:example:beneficiary", "1.0"),
+                    "did:example:sender",
+                ),
+                MessageDirection::Incoming,
+            )
+            .await
+            .unwrap();
+        let snapshot = archive_dir.join("transactions-20240101T000000.000Z.db");
+        sqlx::query("VACUUM INTO ?1")
+            .bind(snapshot.to_string_lossy().to_string())
+            .execute(storage.pool())
+            .await
+            .unwrap();
+        storage.pool().close().await;
+        // A newer snapshot that is itself corrupt is skipped
+        std::fs::write(
+            archive_dir.join("transactions-20240102T000000.000Z.db"),
+            vec![0x42; 8192],
+        )
+        .unwrap();
+        corrupt(&db_path);
+
+        let recovery = StorageRecovery::new(vec![RecoveryStrategy::RestoreSnapshot]);
+        let (storage, report) = Storage::open_checked(Some(db_path), &recovery)
+            .await
+            .unwrap();
+        let report = report.unwrap();
+        assert_eq!(report.recovered_by, Some(RecoveryStrategy::RestoreSnapshot));
+        assert_eq!(report.restored_from, Some(snapshot));
+        let messages = storage.list_messages(10, 0, None).await.unwrap();
+        assert_eq!(messages.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_from_message_log() {
+        let dir = TempDir::new().unwrap();
+        let db_path = dir.path().join("transactions.db");
+
+        let storage = Storage::new(Some(db_path.clone())).await.unwrap();
+        for (message, direction) in [
+            (
+                transfer_message(
+                    "tx-1",
+                    &transfer_body("did:example:originator", "did:example:beneficiary", "1.0"),
+                    "did:example:sender",
+                ),
+                MessageDirection::Outgoing,
+            ),
+            (
+                transfer_message(
+                    "tx-2",
+                    &transfer_body("did:example:originator", "did:example:beneficiary", "1.0"),
+                    "did:example:sender",
+                ),
+                MessageDirection::Outgoing,
+            ),
+            (
+                reject_message("reject-1", "tx-1"),
+                MessageDirection::Incoming,
+            ),
+        ] {
+            storage.log_message(&message, direction).await.unwrap();
+        }
+        storage.pool().close().await;
+
+        let recovery = StorageRecovery::new(vec![RecoveryStrategy::RebuildFromMessages]);
+        let (storage, report) = recover(&db_path, vec!["page 3 is never used".into()], &recovery)
+            .await
+            .unwrap();
+        let storage = storage.unwrap();
+        assert_eq!(report.salvaged_messages, 3);
+        assert_eq!(report.rebuilt_transactions, 2);
+
+        let rejected = storage
+            .get_transaction_by_id("tx-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(rejected.status.to_string(), "failed");
+        let pending = storage
+            .get_transaction_by_id("tx-2")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(pending.status.to_string(), "pending");
+        assert_eq!(storage.list_messages(10, 0, None).await.unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,58 @@
+//! Where received messages came from
+//!
+//! Kept outside the storage models so that nodes built without the
+//! `storage` feature still tell their receive paths apart.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceType {
+    /// HTTP/HTTPS delivery from external endpoints
+    Https,
+    /// Internal delivery from agents within the same node
+    Internal,
+    /// WebSocket connection
+    WebSocket,
+    /// Return path delivery
+    ReturnPath,
+    /// Pickup delivery
+    Pickup,
+}
+
+impl fmt::Display for SourceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceType::Https => write!(f, "https"),
+            SourceType::Internal => write!(f, "internal"),
+            SourceType::WebSocket => write!(f, "websocket"),
+            SourceType::ReturnPath => write!(f, "return_path"),
+            SourceType::Pickup => write!(f, "pickup"),
+        }
+    }
+}
+
+impl TryFrom<&str> for SourceType {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "https" => Ok(SourceType::Https),
+            "internal" => Ok(SourceType::Internal),
+            "websocket" => Ok(SourceType::WebSocket),
+            "return_path" => Ok(SourceType::ReturnPath),
+            "pickup" => Ok(SourceType::Pickup),
+            _ => Err(format!("Invalid source type: {}", value)),
+        }
+    }
+}
+
+impl FromStr for SourceType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s)
+    }
+}
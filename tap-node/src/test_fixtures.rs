@@ -0,0 +1,32 @@
+//! Message fixtures shared by the unit tests
+
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::{Party, Transfer};
+
+/// A Transfer of `amount` ETH from `originator` to `beneficiary`
+pub(crate) fn transfer_body(originator: &str, beneficiary: &str, amount: &str) -> Transfer {
+    Transfer {
+        transaction_id: None,
+        originator: Some(Party::new(originator)),
+        beneficiary: Some(Party::new(beneficiary)),
+        asset: "eip155:1/slip44:60".parse().unwrap(),
+        amount: amount.to_string(),
+        agents: vec![],
+        memo: None,
+        settlement_id: None,
+        expiry: None,
+        transaction_value: None,
+        connection_id: None,
+        metadata: Default::default(),
+    }
+}
+
+/// The Transfer message `id` carrying `transfer`, sent by `from`
+pub(crate) fn transfer_message(id: &str, transfer: &Transfer, from: &str) -> PlainMessage {
+    PlainMessage::new(
+        id.to_string(),
+        "https://tap.rsvp/schema/1.0#Transfer".to_string(),
+        serde_json::to_value(transfer).unwrap(),
+        from.to_string(),
+    )
+}
@@ -0,0 +1,312 @@
+//! Harness for running TAP nodes against each other in one process
+//!
+//! A [`TestNode`] is a [`TapNode`] with its own temporary storage and a
+//! [`LoopbackServer`] accepting DIDComm messages over HTTP on `127.0.0.1`.
+//! Connecting two test nodes points the deliveries of each node to the
+//! other's agents at the other's loopback endpoint, so messages take the same
+//! signed HTTP path they take between two separate deployments.
+//!
+//! Test nodes process incoming IVMS101 presentations with the
+//! [`TravelRuleProcessor`], which publishes a
+//! [`NodeEvent::TravelRuleDataValidated`] event for each of them.
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use tap_node::testing::TestNode;
+//! use tap_node::{NodeConfig, NodeEvent};
+//!
+//! # async fn example() -> tap_node::Result<()> {
+//! let mut originator = TestNode::start("originator", NodeConfig::default()).await?;
+//! let mut beneficiary = TestNode::start("beneficiary", NodeConfig::default()).await?;
+//! let _originating_vasp = originator.add_agent().await?;
+//! let _beneficiary_vasp = beneficiary.add_agent().await?;
+//! originator.connect(&beneficiary);
+//!
+//! // ... send a Transfer from the originating VASP ...
+//!
+//! beneficiary
+//!     .wait_for_event(Duration::from_secs(5), |event| {
+//!         matches!(event, NodeEvent::TransactionCreated { .. })
+//!     })
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! This module requires the `test-harness` feature.
+
+use crate::customer::CustomerManager;
+use crate::error::{Error, Result};
+use crate::event::NodeEvent;
+use crate::federation::{parse_forwarded_by, FORWARDED_BY_HEADER};
+use crate::intake::{IngestOutcome, RejectionCode};
+use crate::message::{PlainMessageProcessorType, TravelRuleProcessor};
+use crate::storage::SourceType;
+use crate::{NodeConfig, TapNode};
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tap_agent::TapAgent;
+use tokio::net::TcpListener;
+use tokio::sync::{broadcast, Mutex};
+use tokio::task::JoinHandle;
+
+/// HTTP server on `127.0.0.1` that feeds received messages into a node
+///
+/// Every `POST` body is passed to [`TapNode::receive_forwarded_message`]
+/// with the nodes named in its `TAP-Forwarded-By` header.
+/// The server stops when dropped.
+pub struct LoopbackServer {
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl LoopbackServer {
+    /// Start a server for the node on an ephemeral port
+    pub async fn start(node: Arc<TapNode>) -> Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .map_err(|e| Error::Configuration(format!("Failed to bind loopback server: {}", e)))?;
+        let addr = listener
+            .local_addr()
+            .map_err(|e| Error::Configuration(format!("Failed to bind loopback server: {}", e)))?;
+
+        let task = tokio::spawn(async move {
+            loop {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        log::warn!("Loopback server failed to accept a connection: {}", e);
+                        continue;
+                    }
+                };
+
+                let node = node.clone();
+                tokio::spawn(async move {
+                    let service = service_fn(move |request| handle(node.clone(), peer, request));
+                    if let Err(e) = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await
+                    {
+                        log::debug!("Loopback connection from {} failed: {}", peer, e);
+                    }
+                });
+            }
+        });
+
+        Ok(Self { addr, task })
+    }
+
+    /// Address the server listens on
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// URL to deliver messages to
+    pub fn endpoint(&self) -> String {
+        format!("http://{}/didcomm", self.addr)
+    }
+}
+
+impl Drop for LoopbackServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn handle(
+    node: Arc<TapNode>,
+    peer: SocketAddr,
+    request: Request<Incoming>,
+) -> std::result::Result<Response<Full<Bytes>>, Infallible> {
+    if request.method() != Method::POST {
+        return Ok(respond(StatusCode::METHOD_NOT_ALLOWED, String::new()));
+    }
+
+    let forwarded_by = request
+        .headers()
+        .get(FORWARDED_BY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(parse_forwarded_by)
+        .unwrap_or_default();
+    let body = match request.into_body().collect().await {
+        Ok(body) => body.to_bytes(),
+        Err(e) => return Ok(respond(StatusCode::BAD_REQUEST, e.to_string())),
+    };
+    let message = match serde_json::from_slice(&body) {
+        Ok(message) => message,
+        Err(e) => return Ok(respond(StatusCode::BAD_REQUEST, e.to_string())),
+    };
+
+    let source = format!("http://{}", peer);
+    match node
+        .receive_forwarded_message(message, SourceType::Https, Some(&source), forwarded_by)
+        .await
+    {
+        Ok(IngestOutcome::Rejected { code, reason }) => {
+            let status = match code {
+                RejectionCode::UpstreamUnavailable => StatusCode::BAD_GATEWAY,
+                code if code.is_retryable() => StatusCode::TOO_MANY_REQUESTS,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            Ok(respond(status, reason))
+        }
+        Ok(_) => Ok(respond(StatusCode::ACCEPTED, String::new())),
+        Err(e) => {
+            log::warn!("Loopback server failed to process message: {}", e);
+            Ok(respond(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+fn respond(status: StatusCode, body: String) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(body)));
+    *response.status_mut() = status;
+    response
+}
+
+/// A node with temporary storage, reachable over loopback HTTP
+///
+/// The storage directory is removed when the test node is dropped.
+pub struct TestNode {
+    name: String,
+    node: Arc<TapNode>,
+    server: LoopbackServer,
+    agents: Vec<String>,
+    events: Mutex<broadcast::Receiver<Arc<NodeEvent>>>,
+    root: PathBuf,
+}
+
+impl TestNode {
+    /// Start a node with the given configuration
+    ///
+    /// The storage locations of the configuration are replaced by a fresh
+    /// temporary directory.
+    pub async fn start(name: impl Into<String>, mut config: NodeConfig) -> Result<Self> {
+        let name = name.into();
+        let root =
+            std::env::temp_dir().join(format!("tap-test-node-{}-{}", name, uuid::Uuid::new_v4()));
+        config.storage_path = Some(root.join("node.db"));
+        config.tap_root = Some(root.clone());
+
+        let mut node = TapNode::new(config);
+        node.init_storage().await?;
+        if let Some(storage) = node.storage().cloned() {
+            let travel_rule = TravelRuleProcessor::new(Arc::new(CustomerManager::new(storage)))
+                .with_event_bus(node.event_bus().clone());
+            node.add_incoming_processor(PlainMessageProcessorType::TravelRule(travel_rule));
+        }
+
+        let events = Mutex::new(node.event_bus().subscribe_channel());
+        let node = Arc::new(node);
+        let server = LoopbackServer::start(node.clone()).await?;
+        log::info!("Test node {} listening at {}", name, server.endpoint());
+
+        Ok(Self {
+            name,
+            node,
+            server,
+            agents: Vec::new(),
+            events,
+            root,
+        })
+    }
+
+    /// Name given to the node
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The underlying node
+    pub fn node(&self) -> &Arc<TapNode> {
+        &self.node
+    }
+
+    /// URL the node receives messages at
+    pub fn endpoint(&self) -> String {
+        self.server.endpoint()
+    }
+
+    /// DIDs of the agents added with [`TestNode::add_agent`]
+    pub fn agents(&self) -> &[String] {
+        &self.agents
+    }
+
+    /// Register a new agent with an ephemeral `did:key`
+    ///
+    /// Add agents before connecting the node to its peers; agents added
+    /// later are unknown to peers connected earlier.
+    pub async fn add_agent(&mut self) -> Result<String> {
+        let (agent, did) = TapAgent::from_ephemeral_key().await?;
+        self.node.register_agent(Arc::new(agent)).await?;
+        log::info!("Test node {} registered agent {}", self.name, did);
+        self.agents.push(did.clone());
+        Ok(did)
+    }
+
+    /// Route messages between the agents of this node and those of `peer`
+    pub fn connect(&self, peer: &TestNode) {
+        for did in &peer.agents {
+            self.node.set_service_endpoint(did.clone(), peer.endpoint());
+        }
+        for did in &self.agents {
+            peer.node.set_service_endpoint(did.clone(), self.endpoint());
+        }
+    }
+
+    /// Wait for an event matching `predicate`
+    ///
+    /// Events are consumed in the order the node published them since it
+    /// started, so events skipped while waiting are not seen by later calls.
+    pub async fn wait_for_event<F>(&self, timeout: Duration, predicate: F) -> Result<Arc<NodeEvent>>
+    where
+        F: Fn(&NodeEvent) -> bool,
+    {
+        let mut events = self.events.lock().await;
+        let wait = async {
+            loop {
+                match events.recv().await {
+                    Ok(event) if predicate(&event) => return Ok(event),
+                    Ok(_) => continue,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        log::warn!("Test node {} missed {} events", self.name, skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        return Err(Error::Processing(format!(
+                            "Event bus of test node {} closed",
+                            self.name
+                        )));
+                    }
+                }
+            }
+        };
+
+        tokio::time::timeout(timeout, wait).await.map_err(|_| {
+            Error::Processing(format!(
+                "Timed out waiting for an event on test node {}",
+                self.name
+            ))
+        })?
+    }
+}
+
+impl Drop for TestNode {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.root) {
+            log::debug!(
+                "Failed to remove storage of test node {} at {}: {}",
+                self.name,
+                self.root.display(),
+                e
+            );
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Per-message processing timeouts
+//!
+//! A message whose processing hangs, e.g. a JWS signed by a DID that
+//! resolves through an endpoint that never answers, would otherwise hold the
+//! task processing it indefinitely. [`ProcessingTimeouts`] bounds the stages
+//! of processing that call out to resolvers, validators and hooks. When a
+//! stage exceeds its budget, its future is dropped, cancelling the work in
+//! progress, a
+//! [`NodeEvent::MessageProcessingTimedOut`](crate::event::NodeEvent::MessageProcessingTimedOut)
+//! is published and, if `dead_letter` is set, the message is kept in the
+//! node's dead letters (see `Storage::list_dead_letters`).
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+
+/// Time budgets for the stages of message processing
+///
+/// Stages without a budget run to completion.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessingTimeouts {
+    /// Verifying the signature of an incoming message, including resolving
+    /// the signer's DID
+    pub verification: Option<Duration>,
+    /// Validating an incoming message
+    pub validation: Option<Duration>,
+    /// Running the enrichment hooks on an outgoing message
+    pub hooks: Option<Duration>,
+    /// Keep messages that time out in the dead letters of the node's storage
+    pub dead_letter: bool,
+}
+
+impl ProcessingTimeouts {
+    /// Apply the same budget to every stage
+    pub fn uniform(timeout: Duration) -> Self {
+        Self {
+            verification: Some(timeout),
+            validation: Some(timeout),
+            hooks: Some(timeout),
+            dead_letter: false,
+        }
+    }
+
+    /// Dead-letter messages that time out
+    pub fn with_dead_letter(mut self) -> Self {
+        self.dead_letter = true;
+        self
+    }
+
+    /// The budget of a stage
+    pub fn budget(&self, stage: ProcessingStage) -> Option<Duration> {
+        match stage {
+            ProcessingStage::Verification => self.verification,
+            ProcessingStage::Validation => self.validation,
+            ProcessingStage::Hook => self.hooks,
+        }
+    }
+}
+
+/// A stage of message processing that can time out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessingStage {
+    Verification,
+    Validation,
+    Hook,
+}
+
+impl ProcessingStage {
+    /// Snake-case name of the stage, e.g. `verification`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProcessingStage::Verification => "verification",
+            ProcessingStage::Validation => "validation",
+            ProcessingStage::Hook => "hook",
+        }
+    }
+}
+
+impl fmt::Display for ProcessingStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl TryFrom<&str> for ProcessingStage {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "verification" => Ok(ProcessingStage::Verification),
+            "validation" => Ok(ProcessingStage::Validation),
+            "hook" => Ok(ProcessingStage::Hook),
+            _ => Err(format!("Invalid processing stage: {}", value)),
+        }
+    }
+}
+
+/// Run `future` within `budget`, returning `None` if it timed out
+///
+/// Without a budget the future runs to completion.
+pub(crate) async fn run_within<T>(
+    budget: Option<Duration>,
+    future: impl Future<Output = T>,
+) -> Option<T> {
+    match budget {
+        Some(budget) => tokio::time::timeout(budget, future).await.ok(),
+        None => Some(future.await),
+    }
+}
@@ -1,4 +1,7 @@
 //! Agent authorization validation for transaction responses
+//!
+//! Responses to a Connect request, which is tracked as a connection rather
+//! than a transaction, are accepted from the parties to the request.
 
 use super::{MessageValidator, ValidationResult};
 use crate::storage::Storage;
@@ -58,6 +61,10 @@ impl AgentAuthorizationValidator {
                 TapMessage::Reject(reject) => Some(reject.transaction_id),
                 TapMessage::Settle(settle) => Some(settle.transaction_id),
                 TapMessage::Revert(revert) => Some(revert.transaction_id),
+                TapMessage::AddAgents(add) => Some(add.transaction_id),
+                TapMessage::RemoveAgent(remove) => Some(remove.transaction_id),
+                TapMessage::ReplaceAgent(replace) => Some(replace.transaction_id),
+                TapMessage::UpdatePolicies(update) => Some(update.transaction_id),
                 _ => None,
             }
         } else {
@@ -85,6 +92,27 @@ impl MessageValidator for AgentAuthorizationValidator {
             }
         };
 
+        // Replies to a Connect request may come from any of its parties
+        match self.storage.get_connection(&transaction_id).await {
+            Ok(Some(connection)) => {
+                return if connection.is_party(&message.from) {
+                    ValidationResult::Accept
+                } else {
+                    ValidationResult::Reject(format!(
+                        "Agent {} is not a party to connection {}",
+                        message.from, transaction_id
+                    ))
+                };
+            }
+            Ok(None) => {}
+            Err(e) => {
+                return ValidationResult::Reject(format!(
+                    "Unable to verify agent authorization: {}",
+                    e
+                ))
+            }
+        }
+
         // Check if the sender is authorized for this transaction
         match self
             .storage
@@ -167,4 +195,49 @@ mod tests {
             }
         }
     }
+
+    #[tokio::test]
+    async fn test_connection_reply_from_party_accepted() {
+        let dir = tempdir().unwrap();
+        let storage = Arc::new(
+            Storage::new(Some(dir.path().join("test.db")))
+                .await
+                .unwrap(),
+        );
+        let connect = PlainMessage::new(
+            "connect_1".to_string(),
+            "https://tap.rsvp/schema/1.0#Connect".to_string(),
+            serde_json::json!({}),
+            "did:example:requester".to_string(),
+        )
+        .with_recipient("did:example:receiver");
+        storage.insert_connection(&connect).await.unwrap();
+        let validator = AgentAuthorizationValidator::new(storage);
+
+        let authorize = Authorize {
+            transaction_id: "connect_1".to_string(),
+            settlement_address: None,
+            expiry: None,
+        };
+        let reply = |from: &str| {
+            PlainMessage::new(
+                "test_msg_3".to_string(),
+                "https://tap.rsvp/schema/1.0#Authorize".to_string(),
+                serde_json::to_value(&authorize).unwrap(),
+                from.to_string(),
+            )
+            .with_recipient("did:example:requester")
+        };
+
+        assert!(matches!(
+            validator.validate(&reply("did:example:receiver")).await,
+            ValidationResult::Accept
+        ));
+        match validator.validate(&reply("did:example:mallory")).await {
+            ValidationResult::Accept => panic!("Expected reject, got accept"),
+            ValidationResult::Reject(reason) => {
+                assert!(reason.contains("not a party"));
+            }
+        }
+    }
 }
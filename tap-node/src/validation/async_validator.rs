@@ -0,0 +1,230 @@
+//! Asynchronous validation of message bodies
+//!
+//! Some checks of a message body need lookups in storage or over the network,
+//! such as whether the transaction a message refers to exists. Body types
+//! deriving `TapMessage` with `#[tap(async_validation)]` implement
+//! [`AsyncValidate`] for such checks. Registered in [`AsyncValidators`], their
+//! received messages are decoded and their validation awaited by the standard
+//! validator, with lookups answered from the node's storage.
+
+use super::{MessageValidator, ValidationResult};
+use crate::storage::Storage;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::{AsyncValidate, ValidationContext};
+
+/// Decodes the body of a message and awaits its validation
+#[async_trait]
+trait BodyValidator: Send + Sync {
+    async fn validate(
+        &self,
+        message: &PlainMessage,
+        context: &dyn ValidationContext,
+    ) -> Result<(), String>;
+}
+
+/// [`BodyValidator`] of the body type `T`
+struct TypedBodyValidator<T>(PhantomData<fn() -> T>);
+
+#[async_trait]
+impl<T: AsyncValidate + 'static> BodyValidator for TypedBodyValidator<T> {
+    async fn validate(
+        &self,
+        message: &PlainMessage,
+        context: &dyn ValidationContext,
+    ) -> Result<(), String> {
+        let body: T = serde_json::from_value(message.body.clone())
+            .map_err(|e| format!("Invalid {} body: {}", message.type_, e))?;
+        body.validate_async(context)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Body types validated asynchronously, by message type
+#[derive(Clone, Default)]
+pub struct AsyncValidators {
+    validators: HashMap<&'static str, Arc<dyn BodyValidator>>,
+}
+
+impl AsyncValidators {
+    /// Create an empty set of validators
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Validate received messages of `T`'s message type with its
+    /// [`AsyncValidate`] implementation, replacing an earlier registration
+    /// for that type
+    pub fn register<T: AsyncValidate + 'static>(&mut self) {
+        self.validators.insert(
+            T::message_type(),
+            Arc::new(TypedBodyValidator::<T>(PhantomData)),
+        );
+    }
+
+    /// Whether messages of a type are validated asynchronously
+    pub fn contains(&self, message_type: &str) -> bool {
+        self.validators.contains_key(message_type)
+    }
+
+    /// Message types of the registered body types
+    pub fn message_types(&self) -> impl Iterator<Item = &str> {
+        self.validators.keys().copied()
+    }
+}
+
+impl fmt::Debug for AsyncValidators {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.validators.keys()).finish()
+    }
+}
+
+/// Answers the lookups of asynchronous validation from a node's storage
+pub struct StorageValidationContext {
+    storage: Arc<Storage>,
+}
+
+impl StorageValidationContext {
+    pub fn new(storage: Arc<Storage>) -> Self {
+        Self { storage }
+    }
+}
+
+#[async_trait]
+impl ValidationContext for StorageValidationContext {
+    async fn transaction_exists(&self, transaction_id: &str) -> tap_msg::Result<bool> {
+        self.storage
+            .get_transaction_by_id(transaction_id)
+            .await
+            .map(|transaction| transaction.is_some())
+            .map_err(|e| tap_msg::Error::Validation(format!("Transaction lookup failed: {}", e)))
+    }
+}
+
+/// Validator awaiting the asynchronous validation of registered body types
+///
+/// Messages of other types are accepted.
+pub struct AsyncBodyValidator {
+    validators: Arc<AsyncValidators>,
+    context: Arc<dyn ValidationContext>,
+}
+
+impl AsyncBodyValidator {
+    /// Validate with the given validators, answering lookups from `context`
+    pub fn new(validators: Arc<AsyncValidators>, context: Arc<dyn ValidationContext>) -> Self {
+        Self {
+            validators,
+            context,
+        }
+    }
+}
+
+#[async_trait]
+impl MessageValidator for AsyncBodyValidator {
+    async fn validate(&self, message: &PlainMessage) -> ValidationResult {
+        let Some(validator) = self.validators.validators.get(message.type_.as_str()) else {
+            return ValidationResult::Accept;
+        };
+        match validator.validate(message, self.context.as_ref()).await {
+            Ok(()) => ValidationResult::Accept,
+            Err(reason) => ValidationResult::Reject(reason),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+    use std::collections::HashSet;
+    use tap_msg::TapMessage;
+
+    const RECEIPT: &str = "https://example.com/protocols/receipt/1.0/receipt";
+
+    #[derive(Debug, Clone, Serialize, Deserialize, TapMessage)]
+    #[tap(
+        message_type = "https://example.com/protocols/receipt/1.0/receipt",
+        async_validation
+    )]
+    struct Receipt {
+        #[tap(thread_id)]
+        transaction_id: String,
+    }
+
+    impl Receipt {
+        async fn validate_receipt_async(
+            &self,
+            context: &dyn ValidationContext,
+        ) -> tap_msg::Result<()> {
+            if !context.transaction_exists(&self.transaction_id).await? {
+                return Err(tap_msg::Error::Validation(format!(
+                    "Unknown transaction {}",
+                    self.transaction_id
+                )));
+            }
+            Ok(())
+        }
+    }
+
+    struct KnownTransactions(HashSet<String>);
+
+    #[async_trait]
+    impl ValidationContext for KnownTransactions {
+        async fn transaction_exists(&self, transaction_id: &str) -> tap_msg::Result<bool> {
+            Ok(self.0.contains(transaction_id))
+        }
+    }
+
+    fn message(type_: &str, body: serde_json::Value) -> PlainMessage {
+        PlainMessage::new(
+            "receipt-1".to_string(),
+            type_.to_string(),
+            body,
+            "did:example:sender".to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_registered_bodies_are_validated() {
+        let mut validators = AsyncValidators::new();
+        validators.register::<Receipt>();
+        assert!(validators.contains(RECEIPT));
+        let validator = AsyncBodyValidator::new(
+            Arc::new(validators),
+            Arc::new(KnownTransactions(HashSet::from(["tx-1".to_string()]))),
+        );
+
+        let outcome = |body| {
+            let message = message(RECEIPT, body);
+            let validator = &validator;
+            async move { validator.validate(&message).await }
+        };
+        assert!(matches!(
+            outcome(json!({"transaction_id": "tx-1"})).await,
+            ValidationResult::Accept
+        ));
+        match outcome(json!({"transaction_id": "tx-2"})).await {
+            ValidationResult::Reject(reason) => {
+                assert!(reason.contains("Unknown transaction tx-2"))
+            }
+            ValidationResult::Accept => panic!("Unknown transaction was accepted"),
+        }
+        match outcome(json!({})).await {
+            ValidationResult::Reject(reason) => assert!(reason.starts_with("Invalid")),
+            ValidationResult::Accept => panic!("Undecodable body was accepted"),
+        }
+
+        // Messages of other types are left to the other validators
+        let other = message("https://example.com/protocols/other/1.0/other", json!({}));
+        assert!(matches!(
+            validator.validate(&other).await,
+            ValidationResult::Accept
+        ));
+    }
+}
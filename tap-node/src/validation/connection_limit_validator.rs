@@ -0,0 +1,487 @@
+//! Connection limit validation (TAIP-15)
+//!
+//! A Connect request can limit the amount of each transaction made under the
+//! connection, and the total per day, week, month and year. Transfers and
+//! Payments naming the connection in their `connection_id` are counted in a
+//! running total per connection, and rejected when they would exceed one of
+//! its limits. Periods are rolling windows of 1, 7, 30 and 365 days.
+//!
+//! Limits are expressed in the limit currency of the connection. A
+//! transaction counts in that currency when it is denominated in it or when
+//! its fiat transaction value is; other transactions cannot be checked and
+//! are rejected. Without a limit currency, totals are kept per asset.
+//! Failed, cancelled and reverted transactions do not count.
+
+use super::{MessageValidator, ValidationResult};
+use crate::error::{Error, Result};
+use crate::storage::{Connection, Storage};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::{TapMessage, TransactionLimits};
+
+/// Period over which a connection limit applies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitPeriod {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl LimitPeriod {
+    /// Length of the rolling window
+    pub fn duration(&self) -> chrono::Duration {
+        match self {
+            LimitPeriod::Day => chrono::Duration::days(1),
+            LimitPeriod::Week => chrono::Duration::days(7),
+            LimitPeriod::Month => chrono::Duration::days(30),
+            LimitPeriod::Year => chrono::Duration::days(365),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            LimitPeriod::Day => "daily",
+            LimitPeriod::Week => "weekly",
+            LimitPeriod::Month => "monthly",
+            LimitPeriod::Year => "yearly",
+        }
+    }
+}
+
+/// Limit of a connection over one period, and how much of it is left
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeriodAllowance {
+    pub period: LimitPeriod,
+    pub limit: f64,
+    /// Amount of the transactions counted in the current window
+    pub spent: f64,
+    pub remaining: f64,
+}
+
+/// What can still be spent under a connection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionAllowance {
+    pub connection_id: String,
+    /// Currency (or asset) of the amounts
+    pub currency: String,
+    /// Maximum amount of a single transaction
+    pub per_transaction: Option<f64>,
+    pub periods: Vec<PeriodAllowance>,
+}
+
+impl ConnectionAllowance {
+    /// Largest amount a transaction can have now, None if unlimited
+    pub fn available(&self) -> Option<f64> {
+        self.periods
+            .iter()
+            .map(|period| period.remaining)
+            .chain(self.per_transaction)
+            .reduce(f64::min)
+    }
+}
+
+/// Limits of a connection, parsed from its Connect request
+struct Limits {
+    per_transaction: Option<f64>,
+    periods: Vec<(LimitPeriod, f64)>,
+    currency: Option<String>,
+}
+
+impl Limits {
+    /// Limits of the connection, None if it has none
+    fn of(connection: &Connection) -> std::result::Result<Option<Self>, String> {
+        let limits = &connection.message_json["body"]["constraints"]["limits"];
+        if limits.is_null() {
+            return Ok(None);
+        }
+        let limits: TransactionLimits = serde_json::from_value(limits.clone()).map_err(|e| {
+            format!(
+                "Invalid limits of connection {}: {}",
+                connection.reference_id, e
+            )
+        })?;
+        let parse = |limit: &Option<String>| {
+            limit
+                .as_deref()
+                .map(|limit| {
+                    limit.parse::<f64>().map_err(|_| {
+                        format!(
+                            "Invalid limit '{}' of connection {}",
+                            limit, connection.reference_id
+                        )
+                    })
+                })
+                .transpose()
+        };
+
+        let mut periods = Vec::new();
+        for (period, limit) in [
+            (LimitPeriod::Day, &limits.per_day),
+            (LimitPeriod::Week, &limits.per_week),
+            (LimitPeriod::Month, &limits.per_month),
+            (LimitPeriod::Year, &limits.per_year),
+        ] {
+            if let Some(limit) = parse(limit)? {
+                periods.push((period, limit));
+            }
+        }
+        let per_transaction = parse(&limits.per_transaction)?;
+        if per_transaction.is_none() && periods.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            per_transaction,
+            periods,
+            currency: limits.currency,
+        }))
+    }
+}
+
+/// A transaction made under a connection with limits
+struct Spending {
+    connection_id: String,
+    limits: Limits,
+    /// Currency the amount is counted in
+    currency: String,
+    amount: f64,
+}
+
+/// Validator enforcing the transaction limits of connections
+///
+/// It also records the transactions it accepts, and reports the allowance
+/// left on a connection.
+pub struct ConnectionLimitValidator {
+    storage: Arc<Storage>,
+}
+
+impl ConnectionLimitValidator {
+    /// Create a new connection limit validator
+    pub fn new(storage: Arc<Storage>) -> Self {
+        Self { storage }
+    }
+
+    /// The allowance left on a connection, None if the connection is not
+    /// known or has no limits
+    ///
+    /// When the connection has no limit currency, `asset` names the asset
+    /// (CAIP-19) or currency code of the totals.
+    pub async fn allowance(
+        &self,
+        connection_id: &str,
+        asset: Option<&str>,
+    ) -> Result<Option<ConnectionAllowance>> {
+        let Some(connection) = self
+            .storage
+            .get_connection(connection_id)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?
+        else {
+            return Ok(None);
+        };
+        let Some(limits) = Limits::of(&connection).map_err(Error::Validation)? else {
+            return Ok(None);
+        };
+        let currency = limits
+            .currency
+            .clone()
+            .or_else(|| asset.map(String::from))
+            .ok_or_else(|| {
+                Error::Validation(format!(
+                    "Limits of connection {} have no currency, an asset is required",
+                    connection_id
+                ))
+            })?;
+
+        let mut periods = Vec::new();
+        for (period, limit) in &limits.periods {
+            let spent = self
+                .spent(connection_id, &currency, *period, None)
+                .await
+                .map_err(Error::Storage)?;
+            periods.push(PeriodAllowance {
+                period: *period,
+                limit: *limit,
+                spent,
+                remaining: (limit - spent).max(0.0),
+            });
+        }
+
+        Ok(Some(ConnectionAllowance {
+            connection_id: connection_id.to_string(),
+            currency,
+            per_transaction: limits.per_transaction,
+            periods,
+        }))
+    }
+
+    /// Count a Transfer or Payment against the limits of its connection
+    ///
+    /// Messages that are not made under a connection with limits are
+    /// ignored.
+    pub async fn record(&self, message: &PlainMessage) -> Result<()> {
+        let Some(spending) = self.spending(message).await.map_err(Error::Validation)? else {
+            return Ok(());
+        };
+        self.storage
+            .record_connection_spending(
+                &spending.connection_id,
+                &message.id,
+                &spending.currency,
+                spending.amount,
+            )
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    /// Connection, currency and amount a message is counted with
+    async fn spending(
+        &self,
+        message: &PlainMessage,
+    ) -> std::result::Result<Option<Spending>, String> {
+        let (connection_id, asset, amount, value) = match TapMessage::from_plain_message(message) {
+            Ok(TapMessage::Transfer(transfer)) => (
+                transfer.connection_id,
+                Some(transfer.asset.to_string()),
+                transfer.amount,
+                transfer.transaction_value,
+            ),
+            Ok(TapMessage::Payment(payment)) => (
+                payment.connection_id,
+                payment
+                    .asset
+                    .map(|asset| asset.to_string())
+                    .or(payment.currency_code),
+                payment.amount,
+                None,
+            ),
+            _ => return Ok(None),
+        };
+        let Some(connection_id) = connection_id else {
+            return Ok(None);
+        };
+        let connection = match self.storage.get_connection(&connection_id).await {
+            Ok(Some(connection)) => connection,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                return Err(format!(
+                    "Unable to look up connection {}: {}",
+                    connection_id, e
+                ))
+            }
+        };
+        let Some(limits) = Limits::of(&connection)? else {
+            return Ok(None);
+        };
+
+        let (currency, amount) = match (&limits.currency, asset) {
+            (Some(currency), Some(asset)) if asset.eq_ignore_ascii_case(currency) => {
+                (currency.clone(), amount)
+            }
+            (Some(currency), _) => match value.filter(|v| v.currency.eq_ignore_ascii_case(currency))
+            {
+                Some(value) => (currency.clone(), value.amount),
+                None => {
+                    return Err(format!(
+                        "Transaction cannot be checked against the limits of connection {}: it is not valued in {}",
+                        connection_id, currency
+                    ))
+                }
+            },
+            (None, Some(asset)) => (asset, amount),
+            (None, None) => return Ok(None),
+        };
+        let amount = amount
+            .parse::<f64>()
+            .map_err(|_| format!("Invalid amount '{}'", amount))?;
+
+        Ok(Some(Spending {
+            connection_id,
+            limits,
+            currency,
+            amount,
+        }))
+    }
+
+    /// Total counted against a connection in the current window of a period
+    async fn spent(
+        &self,
+        connection_id: &str,
+        currency: &str,
+        period: LimitPeriod,
+        exclude_transaction_id: Option<&str>,
+    ) -> std::result::Result<f64, String> {
+        let since = (self.storage.clock().now() - period.duration()).to_rfc3339();
+        self.storage
+            .sum_connection_spending(connection_id, currency, &since, exclude_transaction_id)
+            .await
+            .map_err(|e| {
+                format!(
+                    "Unable to total the transactions of connection {}: {}",
+                    connection_id, e
+                )
+            })
+    }
+
+    async fn check(&self, message: &PlainMessage) -> std::result::Result<(), String> {
+        let Some(spending) = self.spending(message).await? else {
+            return Ok(());
+        };
+
+        if let Some(limit) = spending.limits.per_transaction {
+            if spending.amount > limit {
+                return Err(format!(
+                    "Amount {} {} exceeds the per-transaction limit of {} of connection {}",
+                    spending.amount, spending.currency, limit, spending.connection_id
+                ));
+            }
+        }
+        for (period, limit) in &spending.limits.periods {
+            let spent = self
+                .spent(
+                    &spending.connection_id,
+                    &spending.currency,
+                    *period,
+                    Some(&message.id),
+                )
+                .await?;
+            if spent + spending.amount > *limit {
+                return Err(format!(
+                    "Amount {} {} exceeds the {} limit of {} of connection {}, {} remaining",
+                    spending.amount,
+                    spending.currency,
+                    period.as_str(),
+                    limit,
+                    spending.connection_id,
+                    (limit - spent).max(0.0)
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MessageValidator for ConnectionLimitValidator {
+    async fn validate(&self, message: &PlainMessage) -> ValidationResult {
+        match self.check(message).await {
+            Ok(()) => ValidationResult::Accept,
+            Err(reason) => ValidationResult::Reject(reason),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ASSET: &str = "eip155:1/erc20:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+
+    fn connect() -> PlainMessage {
+        PlainMessage::new(
+            "connect-1".to_string(),
+            "https://tap.rsvp/schema/1.0#Connect".to_string(),
+            json!({
+                "constraints": {
+                    "limits": {
+                        "per_transaction": "2000",
+                        "per_day": "5000",
+                        "currency": "USD"
+                    }
+                }
+            }),
+            "did:web:wallet.example".to_string(),
+        )
+        .with_recipient("did:web:vasp.example")
+    }
+
+    fn transfer(id: &str, amount: &str, value: Option<&str>) -> PlainMessage {
+        let mut body = json!({
+            "asset": ASSET,
+            "amount": amount,
+            "originator": { "@id": "did:example:alice" },
+            "agents": [],
+            "connection_id": "connect-1"
+        });
+        if let Some(value) = value {
+            body["transactionValue"] = json!({ "amount": value, "currency": "USD" });
+        }
+        PlainMessage::new(
+            id.to_string(),
+            "https://tap.rsvp/schema/1.0#Transfer".to_string(),
+            body,
+            "did:web:wallet.example".to_string(),
+        )
+        .with_recipient("did:web:vasp.example")
+    }
+
+    async fn assert_rejected(validator: &ConnectionLimitValidator, message: &PlainMessage) {
+        match validator.validate(message).await {
+            ValidationResult::Reject(_) => {}
+            ValidationResult::Accept => panic!("Expected {} to be rejected", message.id),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_limits_and_allowance() {
+        let storage = Arc::new(Storage::new_in_memory().await.unwrap());
+        storage.insert_connection(&connect()).await.unwrap();
+        let validator = ConnectionLimitValidator::new(storage.clone());
+
+        // Amounts are counted by their value in the limit currency
+        assert_rejected(&validator, &transfer("transfer-0", "100", None)).await;
+        assert_rejected(&validator, &transfer("transfer-0", "3000", Some("3000"))).await;
+
+        for (id, value) in [("transfer-1", "2000"), ("transfer-2", "2000")] {
+            let message = transfer(id, value, Some(value));
+            assert!(matches!(
+                validator.validate(&message).await,
+                ValidationResult::Accept
+            ));
+            storage.insert_transaction(&message).await.unwrap();
+            validator.record(&message).await.unwrap();
+        }
+        // Recording a transaction again does not count it twice
+        validator
+            .record(&transfer("transfer-2", "2000", Some("2000")))
+            .await
+            .unwrap();
+
+        let allowance = validator
+            .allowance("connect-1", None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(allowance.currency, "USD");
+        assert_eq!(allowance.periods[0].spent, 4000.0);
+        assert_eq!(allowance.periods[0].remaining, 1000.0);
+        assert_eq!(allowance.available(), Some(1000.0));
+
+        let over = transfer("transfer-3", "1500", Some("1500"));
+        match validator.validate(&over).await {
+            ValidationResult::Reject(reason) => assert!(reason.contains("daily limit")),
+            ValidationResult::Accept => panic!("Expected the daily limit to be exceeded"),
+        }
+
+        // Cancelled transactions give their amount back
+        storage
+            .update_transaction_status("transfer-1", "cancelled")
+            .await
+            .unwrap();
+        assert!(matches!(
+            validator.validate(&over).await,
+            ValidationResult::Accept
+        ));
+        assert!(validator
+            .allowance("connect-2", None)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,66 @@
+//! Verification of credentials presented by agents
+
+use super::{MessageValidator, ValidationResult};
+use crate::credentials::CredentialVerifier;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::Agent;
+
+/// Validator that verifies the credentials of the agents in a message
+///
+/// Every agent listed in the `agents` of a message body (Transfer, Payment,
+/// etc.) that carries credentials has them verified. Credentials that fail
+/// verification are logged, and the message is rejected if `reject_invalid`
+/// is set. Agents without credentials are accepted; policy rules decide
+/// whether a credential is required.
+pub struct CredentialValidator {
+    verifier: Arc<CredentialVerifier>,
+    reject_invalid: bool,
+}
+
+impl CredentialValidator {
+    /// Create a new credential validator
+    pub fn new(verifier: Arc<CredentialVerifier>, reject_invalid: bool) -> Self {
+        Self {
+            verifier,
+            reject_invalid,
+        }
+    }
+}
+
+#[async_trait]
+impl MessageValidator for CredentialValidator {
+    async fn validate(&self, message: &PlainMessage) -> ValidationResult {
+        let agents = message.body["agents"].as_array().into_iter().flatten();
+        for agent in agents {
+            let Ok(agent) = serde_json::from_value::<Agent>(agent.clone()) else {
+                continue;
+            };
+            if agent.credentials().is_empty() {
+                continue;
+            }
+
+            let result = self.verifier.verify_agent(&agent).await;
+            for failure in &result.failures {
+                log::warn!(
+                    "Credential {} of agent {} in message {} failed verification: {}",
+                    failure.index,
+                    agent.id,
+                    message.id,
+                    failure.reason
+                );
+            }
+            if self.reject_invalid {
+                if let Some(failure) = result.failures.first() {
+                    return ValidationResult::Reject(format!(
+                        "Invalid credential for agent {}: {}",
+                        agent.id, failure.reason
+                    ));
+                }
+            }
+        }
+
+        ValidationResult::Accept
+    }
+}
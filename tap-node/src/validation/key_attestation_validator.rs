@@ -0,0 +1,104 @@
+//! Verification of key attestations sent with messages
+
+use super::{MessageValidator, ValidationResult};
+use crate::clock::Clock;
+use crate::storage::Storage;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tap_agent::{verify_key_attestation, SyncDIDResolver};
+use tap_msg::didcomm::PlainMessage;
+
+/// Validator that verifies the key attestation a message was sent with
+///
+/// The key that verified the message's signature is looked up in storage,
+/// where the node records it on receipt, so an attestation limited to a key
+/// only holds for messages signed with that key. The outcome is recorded in
+/// storage, where [`KeyAttestationRule`](crate::policy::KeyAttestationRule)s
+/// pick it up.
+/// Invalid attestations are logged, and the message is rejected if
+/// `reject_invalid` is set. Messages without an attestation are accepted;
+/// policy rules decide whether one is required.
+pub struct KeyAttestationValidator {
+    storage: Arc<Storage>,
+    resolver: Arc<dyn SyncDIDResolver>,
+    clock: Arc<dyn Clock>,
+    reject_invalid: bool,
+}
+
+impl KeyAttestationValidator {
+    /// Create a new key attestation validator
+    pub fn new(
+        storage: Arc<Storage>,
+        resolver: Arc<dyn SyncDIDResolver>,
+        clock: Arc<dyn Clock>,
+        reject_invalid: bool,
+    ) -> Self {
+        Self {
+            storage,
+            resolver,
+            clock,
+            reject_invalid,
+        }
+    }
+}
+
+#[async_trait]
+impl MessageValidator for KeyAttestationValidator {
+    async fn validate(&self, message: &PlainMessage) -> ValidationResult {
+        let now = self.clock.now().timestamp();
+        let signer_kid = match self.storage.get_message_verifications(&message.id).await {
+            Ok(verifications) => verifications.into_iter().last().map(|v| v.kid),
+            Err(e) => {
+                log::warn!(
+                    "Failed to look up the signer of message {}: {}",
+                    message.id,
+                    e
+                );
+                None
+            }
+        };
+        let outcome = match verify_key_attestation(
+            message,
+            signer_kid.as_deref(),
+            self.resolver.as_ref(),
+            now,
+        )
+        .await
+        {
+            Ok(None) => return ValidationResult::Accept,
+            Ok(Some(attestation)) => Ok(attestation),
+            Err(e) => {
+                log::warn!(
+                    "Key attestation of {} in message {} failed verification: {}",
+                    message.from,
+                    message.id,
+                    e
+                );
+                Err(e.to_string())
+            }
+        };
+
+        if let Err(e) = self
+            .storage
+            .insert_key_attestation(
+                &message.id,
+                &message.from,
+                outcome.as_ref().map_err(String::as_str),
+            )
+            .await
+        {
+            log::warn!(
+                "Failed to record key attestation of message {}: {}",
+                message.id,
+                e
+            );
+        }
+
+        match outcome {
+            Err(reason) if self.reject_invalid => {
+                ValidationResult::Reject(format!("Invalid key attestation: {}", reason))
+            }
+            _ => ValidationResult::Accept,
+        }
+    }
+}
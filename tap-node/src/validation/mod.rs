@@ -6,13 +6,27 @@
 //! - Timestamp validation (messages not too far in future/past)
 //! - Agent authorization (only authorized agents can respond to transactions)
 //! - Message expiry validation
-
+//! - Rejection codes of Reject messages
+//! - Credentials presented by agents
+//! - Key attestations binding the sending agent to an organization
+//! - Verifiable presentations of Presentation messages
+//! - Transaction limits of connections (TAIP-15)
+//! - Asynchronous checks of registered message body types
+
+use crate::clock::Clock;
 use crate::storage::Storage;
 use async_trait::async_trait;
 use std::sync::Arc;
 use tap_msg::didcomm::PlainMessage;
 
 pub mod agent_validator;
+pub mod async_validator;
+pub mod connection_limit_validator;
+pub mod credential_validator;
+pub mod key_attestation_validator;
+pub mod presentation_validator;
+pub mod rejection_code_validator;
+pub mod settlement_address_validator;
 pub mod timestamp_validator;
 pub mod uniqueness_validator;
 
@@ -62,10 +76,34 @@ impl MessageValidator for CompositeValidator {
 
 /// Standard validator configuration
 pub struct StandardValidatorConfig {
-    /// Maximum allowed timestamp drift in seconds
-    pub max_timestamp_drift_secs: i64,
+    /// Allowed timestamp drift, overall and per counterparty
+    pub timestamp_drift: timestamp_validator::DriftAllowances,
+    /// Tracker recording the clock skew of each counterparty (None skips
+    /// recording)
+    pub skew_tracker: Option<Arc<timestamp_validator::ClockSkewTracker>>,
     /// Storage for uniqueness and agent checks
     pub storage: Arc<Storage>,
+    /// Clock that timestamps are checked against
+    pub clock: Arc<dyn Clock>,
+    /// How settlement addresses on the wrong chain are handled
+    pub settlement_address_strictness: settlement_address_validator::AddressStrictness,
+    /// Verifier of credentials presented by agents (None skips the check)
+    pub credential_verifier: Option<Arc<crate::credentials::CredentialVerifier>>,
+    /// Whether messages with invalid credentials are rejected
+    pub reject_invalid_credentials: bool,
+    /// Resolver of the organizations signing key attestations (None skips
+    /// verifying them)
+    pub key_attestation_resolver: Option<Arc<dyn tap_agent::SyncDIDResolver>>,
+    /// Whether messages with invalid key attestations are rejected
+    pub reject_invalid_key_attestations: bool,
+    /// Verifier of the presentations of Presentation messages (None skips
+    /// verifying them)
+    pub presentation_verifier: Option<Arc<crate::credentials::PresentationVerifier>>,
+    /// Whether messages with invalid presentations are rejected
+    pub reject_invalid_presentations: bool,
+    /// Body types whose asynchronous validation messages must pass (None
+    /// skips it)
+    pub async_validators: Option<Arc<async_validator::AsyncValidators>>,
 }
 
 // Note: StandardValidatorConfig doesn't have a Default implementation
@@ -73,17 +111,64 @@ pub struct StandardValidatorConfig {
 
 /// Create a standard validator with all recommended validators
 pub async fn create_standard_validator(config: StandardValidatorConfig) -> CompositeValidator {
-    let validators: Vec<Box<dyn MessageValidator>> = vec![
-        Box::new(timestamp_validator::TimestampValidator::new(
-            config.max_timestamp_drift_secs,
-        )),
+    let mut timestamps =
+        timestamp_validator::TimestampValidator::with_allowances(config.timestamp_drift)
+            .with_clock(config.clock.clone());
+    if let Some(tracker) = config.skew_tracker {
+        timestamps = timestamps.with_skew_tracker(tracker);
+    }
+    let mut validators: Vec<Box<dyn MessageValidator>> = vec![
+        Box::new(timestamps),
         Box::new(uniqueness_validator::UniquenessValidator::new(
             config.storage.clone(),
         )),
         Box::new(agent_validator::AgentAuthorizationValidator::new(
             config.storage.clone(),
         )),
+        Box::new(
+            settlement_address_validator::SettlementAddressValidator::new(
+                config.settlement_address_strictness,
+            )
+            .with_storage(config.storage.clone()),
+        ),
+        Box::new(rejection_code_validator::RejectionCodeValidator),
+        Box::new(connection_limit_validator::ConnectionLimitValidator::new(
+            config.storage.clone(),
+        )),
     ];
+    if let Some(verifier) = config.credential_verifier {
+        validators.push(Box::new(credential_validator::CredentialValidator::new(
+            verifier,
+            config.reject_invalid_credentials,
+        )));
+    }
+    if let Some(resolver) = config.key_attestation_resolver {
+        validators.push(Box::new(
+            key_attestation_validator::KeyAttestationValidator::new(
+                config.storage.clone(),
+                resolver,
+                config.clock,
+                config.reject_invalid_key_attestations,
+            ),
+        ));
+    }
+    if let Some(verifier) = config.presentation_verifier {
+        validators.push(Box::new(
+            presentation_validator::PresentationValidator::new(
+                config.storage.clone(),
+                verifier,
+                config.reject_invalid_presentations,
+            ),
+        ));
+    }
+    if let Some(async_validators) = config.async_validators {
+        validators.push(Box::new(async_validator::AsyncBodyValidator::new(
+            async_validators,
+            Arc::new(async_validator::StorageValidationContext::new(
+                config.storage.clone(),
+            )),
+        )));
+    }
 
     CompositeValidator::new(validators)
 }
@@ -0,0 +1,192 @@
+//! Verification of the verifiable presentations of Presentation messages
+
+use super::{MessageValidator, ValidationResult};
+use crate::credentials::PresentationVerifier;
+use crate::storage::Storage;
+use async_trait::async_trait;
+use base64::Engine;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tap_msg::didcomm::{Attachment, AttachmentData, PlainMessage};
+
+/// Validator that verifies the presentations carried by Presentation
+/// messages
+///
+/// Each presentation must answer a RequestPresentation one of our agents
+/// sent the sender on the same thread: its holder proof must sign the
+/// challenge of the latest such request and name the requesting agent as its
+/// domain. Unsolicited presentations fail verification.
+///
+/// The outcome of each presentation is recorded in storage, where
+/// [`PresentationRule`](crate::policy::PresentationRule)s pick it up.
+/// Presentations that fail verification are logged, and the message is
+/// rejected if `reject_invalid` is set.
+pub struct PresentationValidator {
+    storage: Arc<Storage>,
+    verifier: Arc<PresentationVerifier>,
+    reject_invalid: bool,
+}
+
+impl PresentationValidator {
+    /// Create a new presentation validator
+    pub fn new(
+        storage: Arc<Storage>,
+        verifier: Arc<PresentationVerifier>,
+        reject_invalid: bool,
+    ) -> Self {
+        Self {
+            storage,
+            verifier,
+            reject_invalid,
+        }
+    }
+}
+
+#[async_trait]
+impl MessageValidator for PresentationValidator {
+    async fn validate(&self, message: &PlainMessage) -> ValidationResult {
+        let presentations = presentations_of(message);
+        if presentations.is_empty() {
+            return ValidationResult::Accept;
+        }
+
+        let transaction_id = message
+            .thid
+            .as_deref()
+            .or(message.body["transaction_id"].as_str())
+            .unwrap_or(&message.id);
+        let request = match self
+            .storage
+            .get_presentation_request(transaction_id, &message.from)
+            .await
+        {
+            Ok(Some(request)) => Ok(request),
+            Ok(None) => Err(format!(
+                "No presentation was requested from {} on {}",
+                message.from, transaction_id
+            )),
+            Err(e) => Err(format!("Could not look up the presentation request: {}", e)),
+        };
+
+        let mut failure = None;
+        for (index, presentation) in presentations.iter().enumerate() {
+            let outcome = match &request {
+                Ok(request) => self.verifier.verify(presentation, request).await,
+                Err(reason) => Err(reason.clone()),
+            };
+            if let Err(reason) = &outcome {
+                log::warn!(
+                    "Presentation {} of message {} from {} failed verification: {}",
+                    index,
+                    message.id,
+                    message.from,
+                    reason
+                );
+                failure.get_or_insert_with(|| reason.clone());
+            }
+
+            if let Err(e) = self
+                .storage
+                .insert_presentation_verification(
+                    message,
+                    index,
+                    outcome.as_ref().map_err(String::as_str),
+                )
+                .await
+            {
+                log::warn!(
+                    "Failed to record presentation {} of message {}: {}",
+                    index,
+                    message.id,
+                    e
+                );
+            }
+        }
+
+        match failure {
+            Some(reason) if self.reject_invalid => {
+                ValidationResult::Reject(format!("Invalid presentation: {}", reason))
+            }
+            _ => ValidationResult::Accept,
+        }
+    }
+}
+
+/// The verifiable presentations carried by a Presentation message
+///
+/// Presentations come in JSON or base64 attachments, of the message or, for
+/// DIDComm present-proof messages, of its body. The `credentials` of a TAP
+/// Presentation body are presentations themselves, or credentials presented
+/// together without a holder proof, which fail verification.
+fn presentations_of(message: &PlainMessage) -> Vec<Value> {
+    if !message.type_.contains("Presentation") && !message.type_.contains("present-proof") {
+        return Vec::new();
+    }
+
+    let body_attachments: Vec<Attachment> = message
+        .body
+        .get("attachments")
+        .and_then(|a| serde_json::from_value(a.clone()).ok())
+        .unwrap_or_default();
+    let mut presentations: Vec<Value> = message
+        .attachments
+        .iter()
+        .flatten()
+        .chain(&body_attachments)
+        .filter_map(|attachment| match &attachment.data {
+            AttachmentData::Json { value } => Some(value.json.clone()),
+            AttachmentData::Base64 { value } => {
+                let data = base64::engine::general_purpose::STANDARD
+                    .decode(&value.base64)
+                    .ok()?;
+                serde_json::from_slice(&data)
+                    .ok()
+                    .or_else(|| String::from_utf8(data).ok().map(Value::String))
+            }
+            AttachmentData::Links { .. } => None,
+        })
+        .filter(is_presentation)
+        .collect();
+
+    let (held, loose): (Vec<&Value>, Vec<&Value>) = message
+        .body
+        .get("credentials")
+        .and_then(|c| c.as_array())
+        .into_iter()
+        .flatten()
+        .partition(|credential| is_presentation(credential));
+    presentations.extend(held.into_iter().cloned());
+    if !loose.is_empty() {
+        presentations.push(json!({ "verifiableCredential": loose }));
+    }
+    presentations
+}
+
+/// Whether a value is a presentation: a JSON-LD document of type
+/// `VerifiablePresentation` or holding credentials, or a JWT with a `vp`
+/// claim
+fn is_presentation(value: &Value) -> bool {
+    match value {
+        Value::String(jwt) => jwt
+            .split('.')
+            .nth(1)
+            .and_then(|payload| {
+                base64::engine::general_purpose::URL_SAFE_NO_PAD
+                    .decode(payload)
+                    .ok()
+            })
+            .and_then(|payload| serde_json::from_slice::<Value>(&payload).ok())
+            .is_some_and(|claims| claims.get("vp").is_some()),
+        Value::Object(document) => {
+            document.contains_key("verifiableCredential")
+                || match &value["type"] {
+                    Value::String(t) => t == "VerifiablePresentation",
+                    Value::Array(types) => types
+                        .iter()
+                        .any(|t| t.as_str() == Some("VerifiablePresentation")),
+                    _ => false,
+                }
+        }
+        _ => false,
+    }
+}
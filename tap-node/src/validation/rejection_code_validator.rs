@@ -0,0 +1,78 @@
+//! Rejection code validation for Reject messages
+
+use super::{MessageValidator, ValidationResult};
+use async_trait::async_trait;
+use std::str::FromStr;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::RejectionCode;
+
+/// Validator that checks the reason code of Reject messages
+///
+/// A Reject message may omit its code, but a code it carries must be one of
+/// the standardized [`RejectionCode`]s so that rejections can be acted on and
+/// aggregated reliably.
+pub struct RejectionCodeValidator;
+
+#[async_trait]
+impl MessageValidator for RejectionCodeValidator {
+    async fn validate(&self, message: &PlainMessage) -> ValidationResult {
+        if message.type_ != "https://tap.rsvp/schema/1.0#Reject" {
+            return ValidationResult::Accept;
+        }
+
+        match message.body.get("code") {
+            None | Some(serde_json::Value::Null) => ValidationResult::Accept,
+            Some(serde_json::Value::String(code)) => match RejectionCode::from_str(code) {
+                Ok(_) => ValidationResult::Accept,
+                Err(e) => ValidationResult::Reject(e.to_string()),
+            },
+            Some(code) => ValidationResult::Reject(format!("Invalid rejection code: {}", code)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn reject(body: serde_json::Value) -> PlainMessage {
+        PlainMessage::new(
+            "reject-1".to_string(),
+            "https://tap.rsvp/schema/1.0#Reject".to_string(),
+            body,
+            "did:example:beneficiary".to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_rejection_codes() {
+        let validator = RejectionCodeValidator;
+        for body in [
+            json!({ "transaction_id": "tx-1", "reason": "Declined" }),
+            json!({ "transaction_id": "tx-1", "code": "sanctions" }),
+            json!({ "transaction_id": "tx-1", "code": "missing_travel_rule_data" }),
+        ] {
+            assert!(matches!(
+                validator.validate(&reject(body)).await,
+                ValidationResult::Accept
+            ));
+        }
+
+        match validator
+            .validate(&reject(
+                json!({ "transaction_id": "tx-1", "code": "bored" }),
+            ))
+            .await
+        {
+            ValidationResult::Reject(reason) => assert!(reason.contains("bored")),
+            ValidationResult::Accept => panic!("Expected unknown code to be rejected"),
+        }
+        assert!(matches!(
+            validator
+                .validate(&reject(json!({ "transaction_id": "tx-1", "code": 7 })))
+                .await,
+            ValidationResult::Reject(_)
+        ));
+    }
+}
@@ -0,0 +1,252 @@
+//! Settlement address validation against the asset's chain
+
+use super::{MessageValidator, ValidationResult};
+use crate::storage::Storage;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+use tap_caip::AssetId;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::agent::roles;
+use tap_msg::settlement_address::SettlementAddress;
+
+/// How settlement addresses that do not match the asset's chain are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressStrictness {
+    /// Do not check settlement addresses
+    Off,
+    /// Log mismatches and accept the message
+    #[default]
+    Warn,
+    /// Reject messages with mismatched addresses
+    Reject,
+}
+
+/// Validator that checks settlement addresses against the asset's chain
+///
+/// This validator checks that:
+/// - Settlement and source address agents of a Transfer or Payment are
+///   accounts on the chain of its asset
+/// - The settlement address of an Authorize, Capture or Revert is an account
+///   on the chain of the stored transaction's asset
+///
+/// Addresses are checked with the tap-caip validators, so e.g. an eip155
+/// address supplied for a Solana asset, or an Ethereum-style address on the
+/// Solana chain, are mismatches. PayTo URIs and non-`did:pkh` agents are not
+/// checked.
+pub struct SettlementAddressValidator {
+    strictness: AddressStrictness,
+    storage: Option<Arc<Storage>>,
+}
+
+impl SettlementAddressValidator {
+    /// Create a new settlement address validator
+    pub fn new(strictness: AddressStrictness) -> Self {
+        Self {
+            strictness,
+            storage: None,
+        }
+    }
+
+    /// Look up the asset of stored transactions when checking responses to them
+    pub fn with_storage(mut self, storage: Arc<Storage>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Describe every settlement address in the message that cannot hold the
+    /// asset
+    pub async fn mismatches(&self, message: &PlainMessage) -> Vec<String> {
+        let body = &message.body;
+        let (asset, addresses) = match message.type_.rsplit('#').next() {
+            Some("Transfer") | Some("Payment") => {
+                let mut addresses: Vec<String> = body["agents"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter(|agent| {
+                        matches!(
+                            agent["role"].as_str(),
+                            Some(roles::SETTLEMENT_ADDRESS) | Some(roles::SOURCE_ADDRESS)
+                        )
+                    })
+                    .filter_map(|agent| agent["@id"].as_str().map(String::from))
+                    .collect();
+                addresses.extend(
+                    body["fallbackSettlementAddresses"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|address| address.as_str().map(String::from)),
+                );
+                (body["asset"].as_str().map(String::from), addresses)
+            }
+            Some("Authorize") | Some("Capture") | Some("Revert") => {
+                let Some(address) = body["settlementAddress"].as_str() else {
+                    return Vec::new();
+                };
+                (
+                    self.transaction_asset(message).await,
+                    vec![address.to_string()],
+                )
+            }
+            _ => return Vec::new(),
+        };
+
+        let Some(asset) = asset.and_then(|asset| AssetId::from_str(&asset).ok()) else {
+            return Vec::new();
+        };
+
+        addresses
+            .iter()
+            .filter_map(|id| SettlementAddress::from_agent_id(id))
+            .filter_map(|address| address.check_asset(&asset).err())
+            .map(|e| e.to_string())
+            .collect()
+    }
+
+    /// Asset of the transaction a message responds to
+    async fn transaction_asset(&self, message: &PlainMessage) -> Option<String> {
+        let storage = self.storage.as_ref()?;
+        let mut transaction = None;
+        if let Some(thread_id) = &message.thid {
+            transaction = storage
+                .get_transaction_by_thread_id(thread_id)
+                .await
+                .ok()
+                .flatten();
+        }
+        if transaction.is_none() {
+            // Responses are threaded on the transaction's message ID
+            let transaction_id = message
+                .thid
+                .as_deref()
+                .or_else(|| message.body["transaction_id"].as_str())
+                .or_else(|| message.body["transactionId"].as_str())?;
+            transaction = storage
+                .get_transaction_by_id(transaction_id)
+                .await
+                .ok()
+                .flatten();
+        }
+
+        transaction?.message_json["body"]["asset"]
+            .as_str()
+            .map(String::from)
+    }
+}
+
+#[async_trait]
+impl MessageValidator for SettlementAddressValidator {
+    async fn validate(&self, message: &PlainMessage) -> ValidationResult {
+        if self.strictness == AddressStrictness::Off {
+            return ValidationResult::Accept;
+        }
+
+        let mismatches = self.mismatches(message).await;
+        if mismatches.is_empty() {
+            return ValidationResult::Accept;
+        }
+
+        match self.strictness {
+            AddressStrictness::Reject => ValidationResult::Reject(mismatches.join("; ")),
+            _ => {
+                for mismatch in &mismatches {
+                    log::warn!("Message {}: {}", message.id, mismatch);
+                }
+                ValidationResult::Accept
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SOL_USDC: &str =
+        "solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp/token:EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const ETH_ADDRESS: &str = "did:pkh:eip155:1:0x4b20993Bc481177ec7E8f571ceCaE8A9e22C02db";
+    const SOL_ADDRESS: &str =
+        "did:pkh:solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp:7S3P4HxJpyyigGzodYwHtCxZyUQe9JiBMHyRWXArAaKv";
+
+    fn transfer(settlement_address: &str) -> PlainMessage {
+        PlainMessage::new(
+            "transfer-1".to_string(),
+            "https://tap.rsvp/schema/1.0#Transfer".to_string(),
+            json!({
+                "asset": SOL_USDC,
+                "amount": "100",
+                "agents": [
+                    { "@id": "did:web:originator.vasp", "for": "did:example:alice" },
+                    { "@id": settlement_address, "role": "SettlementAddress", "for": "did:example:bob" }
+                ]
+            }),
+            "did:web:originator.vasp".to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_strictness() {
+        let mismatched = transfer(ETH_ADDRESS);
+
+        let reject = SettlementAddressValidator::new(AddressStrictness::Reject);
+        assert!(matches!(
+            reject.validate(&transfer(SOL_ADDRESS)).await,
+            ValidationResult::Accept
+        ));
+        match reject.validate(&mismatched).await {
+            ValidationResult::Reject(reason) => assert!(reason.contains("eip155:1")),
+            ValidationResult::Accept => panic!("Expected mismatch to be rejected"),
+        }
+
+        for strictness in [AddressStrictness::Warn, AddressStrictness::Off] {
+            let validator = SettlementAddressValidator::new(strictness);
+            assert!(matches!(
+                validator.validate(&mismatched).await,
+                ValidationResult::Accept
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_authorize_checked_against_stored_transaction() {
+        let storage = Arc::new(Storage::new_in_memory().await.unwrap());
+        let transfer = transfer(SOL_ADDRESS).with_recipient("did:web:beneficiary.vasp");
+        storage.insert_transaction(&transfer).await.unwrap();
+
+        let authorize = |address: &str| {
+            let mut message = PlainMessage::new(
+                "authorize-1".to_string(),
+                "https://tap.rsvp/schema/1.0#Authorize".to_string(),
+                json!({ "settlementAddress": address }),
+                "did:web:beneficiary.vasp".to_string(),
+            );
+            message.thid = Some("transfer-1".to_string());
+            message
+        };
+
+        let validator =
+            SettlementAddressValidator::new(AddressStrictness::Reject).with_storage(storage);
+        assert_eq!(
+            validator
+                .mismatches(&authorize(
+                    "eip155:1:0x4b20993Bc481177ec7E8f571ceCaE8A9e22C02db"
+                ))
+                .await
+                .len(),
+            1
+        );
+        assert!(validator
+            .mismatches(&authorize(SOL_ADDRESS.trim_start_matches("did:pkh:")))
+            .await
+            .is_empty());
+        assert!(validator
+            .mismatches(&authorize("payto://iban/DE75512108001245126199"))
+            .await
+            .is_empty());
+    }
+}
@@ -1,18 +1,163 @@
 //! Timestamp validation for TAP messages
 
 use super::{MessageValidator, ValidationResult};
+use crate::clock::{system_clock, Clock};
 use async_trait::async_trait;
 use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
 use tap_msg::didcomm::PlainMessage;
 
+/// How far in the future message timestamps may be
+///
+/// Counterparties whose clocks are known to run ahead can be given a larger
+/// allowance than the default, without loosening the check for everyone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriftAllowances {
+    /// Maximum seconds a message's `created_time` may be in the future
+    pub max_future_drift_secs: i64,
+    /// Allowances replacing the default for messages from these DIDs
+    pub counterparties: HashMap<String, i64>,
+}
+
+impl Default for DriftAllowances {
+    fn default() -> Self {
+        Self {
+            max_future_drift_secs: 60,
+            counterparties: HashMap::new(),
+        }
+    }
+}
+
+impl DriftAllowances {
+    /// Allowance for messages from a counterparty
+    pub fn for_counterparty(&self, did: &str) -> i64 {
+        self.counterparties
+            .get(did)
+            .copied()
+            .unwrap_or(self.max_future_drift_secs)
+    }
+}
+
+/// Clock skew observed in the messages of one counterparty
+///
+/// Skew is the message's `created_time` minus the local time it was
+/// validated at, so it also includes transit time. A positive skew means the
+/// counterparty's clock runs ahead of ours.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CounterpartySkew {
+    /// DID of the sender
+    pub counterparty: String,
+    /// Messages with a `created_time` seen from the sender
+    pub samples: u64,
+    /// Skew of the latest message, in seconds
+    pub last_skew_secs: i64,
+    /// Smallest skew seen, in seconds
+    pub min_skew_secs: i64,
+    /// Largest skew seen, in seconds
+    pub max_skew_secs: i64,
+    /// Mean skew, in seconds
+    pub mean_skew_secs: f64,
+    /// Messages rejected for being too far in the future
+    pub rejected: u64,
+    /// When the latest message was seen
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Per-counterparty clock skew, recorded by [`TimestampValidator`]
+#[derive(Debug, Default)]
+pub struct ClockSkewTracker {
+    counterparties: DashMap<String, SkewStats>,
+}
+
+#[derive(Debug, Clone)]
+struct SkewStats {
+    samples: u64,
+    last: i64,
+    min: i64,
+    max: i64,
+    sum: i64,
+    rejected: u64,
+    last_seen: DateTime<Utc>,
+}
+
+impl ClockSkewTracker {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the skew of a message from a counterparty
+    pub fn record(&self, counterparty: &str, skew_secs: i64, rejected: bool, at: DateTime<Utc>) {
+        let mut stats = self
+            .counterparties
+            .entry(counterparty.to_string())
+            .or_insert_with(|| SkewStats {
+                samples: 0,
+                last: skew_secs,
+                min: skew_secs,
+                max: skew_secs,
+                sum: 0,
+                rejected: 0,
+                last_seen: at,
+            });
+        stats.samples += 1;
+        stats.last = skew_secs;
+        stats.min = stats.min.min(skew_secs);
+        stats.max = stats.max.max(skew_secs);
+        stats.sum = stats.sum.saturating_add(skew_secs);
+        stats.rejected += u64::from(rejected);
+        stats.last_seen = at;
+    }
+
+    /// Skew observed for a counterparty
+    pub fn get(&self, counterparty: &str) -> Option<CounterpartySkew> {
+        self.counterparties
+            .get(counterparty)
+            .map(|stats| Self::summarize(counterparty, &stats))
+    }
+
+    /// Skew observed for every counterparty, ordered by DID
+    pub fn snapshot(&self) -> Vec<CounterpartySkew> {
+        let mut skews: Vec<_> = self
+            .counterparties
+            .iter()
+            .map(|entry| Self::summarize(entry.key(), entry.value()))
+            .collect();
+        skews.sort_by(|a, b| a.counterparty.cmp(&b.counterparty));
+        skews
+    }
+
+    fn summarize(counterparty: &str, stats: &SkewStats) -> CounterpartySkew {
+        CounterpartySkew {
+            counterparty: counterparty.to_string(),
+            samples: stats.samples,
+            last_skew_secs: stats.last,
+            min_skew_secs: stats.min,
+            max_skew_secs: stats.max,
+            mean_skew_secs: stats.sum as f64 / stats.samples as f64,
+            rejected: stats.rejected,
+            last_seen: stats.last_seen,
+        }
+    }
+}
+
 /// Validator that checks message timestamps
 ///
 /// This validator ensures that:
-/// - Messages are not too far in the future (prevents clock drift issues)
+/// - Messages are not too far in the future (prevents clock drift issues),
+///   with per-counterparty allowances
 /// - Messages have not expired
 /// - Timestamps are valid and parseable
+///
+/// With a [`ClockSkewTracker`] it also records the skew of every message, so
+/// spurious rejections can be traced to a counterparty's clock.
 pub struct TimestampValidator {
-    max_future_drift_secs: i64,
+    allowances: DriftAllowances,
+    clock: Arc<dyn Clock>,
+    skew_tracker: Option<Arc<ClockSkewTracker>>,
 }
 
 impl TimestampValidator {
@@ -21,11 +166,39 @@ impl TimestampValidator {
     /// # Arguments
     /// * `max_future_drift_secs` - Maximum allowed seconds a message can be from the future
     pub fn new(max_future_drift_secs: i64) -> Self {
-        Self {
+        Self::with_allowances(DriftAllowances {
             max_future_drift_secs,
+            counterparties: HashMap::new(),
+        })
+    }
+
+    /// Create a timestamp validator with per-counterparty allowances
+    pub fn with_allowances(allowances: DriftAllowances) -> Self {
+        Self {
+            allowances,
+            clock: system_clock(),
+            skew_tracker: None,
         }
     }
 
+    /// Allow messages from a counterparty to be further in the future
+    pub fn with_counterparty_drift(mut self, did: impl Into<String>, secs: i64) -> Self {
+        self.allowances.counterparties.insert(did.into(), secs);
+        self
+    }
+
+    /// Record the skew of validated messages in the given tracker
+    pub fn with_skew_tracker(mut self, tracker: Arc<ClockSkewTracker>) -> Self {
+        self.skew_tracker = Some(tracker);
+        self
+    }
+
+    /// Check timestamps against the given clock instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// Convert a Unix timestamp to DateTime
     fn timestamp_to_datetime(timestamp: u64) -> DateTime<Utc> {
         // Detect if timestamp is in seconds or milliseconds
@@ -54,15 +227,21 @@ impl TimestampValidator {
 #[async_trait]
 impl MessageValidator for TimestampValidator {
     async fn validate(&self, message: &PlainMessage) -> ValidationResult {
-        let now = Utc::now();
+        let now = self.clock.now();
 
         // Check created_time
         if let Some(created_time) = message.created_time {
             let created_dt = Self::timestamp_to_datetime(created_time);
 
             // Check if message is too far in the future
-            let max_future = now + Duration::seconds(self.max_future_drift_secs);
-            if created_dt > max_future {
+            let drift = self.allowances.for_counterparty(&message.from);
+            let max_future = now + Duration::seconds(drift);
+            let too_far = created_dt > max_future;
+            if let Some(tracker) = &self.skew_tracker {
+                let skew = created_dt.timestamp() - now.timestamp();
+                tracker.record(&message.from, skew, too_far, now);
+            }
+            if too_far {
                 return ValidationResult::Reject(format!(
                     "Message created_time is too far in the future: {} (max allowed: {})",
                     created_dt, max_future
@@ -244,4 +423,82 @@ mod tests {
             }
         }
     }
+
+    #[tokio::test]
+    async fn test_expiry_follows_clock() {
+        use crate::clock::MockClock;
+
+        let clock = Arc::new(MockClock::default());
+        let validator = TimestampValidator::new(60).with_clock(clock.clone());
+        let mut message = PlainMessage::new(
+            "test_msg_5".to_string(),
+            "test_type".to_string(),
+            serde_json::json!({}),
+            "did:example:sender".to_string(),
+        )
+        .with_recipient("did:example:receiver");
+        message.expires_time = Some((clock.now() + Duration::seconds(30)).timestamp() as u64);
+
+        assert!(matches!(
+            validator.validate(&message).await,
+            ValidationResult::Accept
+        ));
+
+        clock.advance(Duration::seconds(31));
+        match validator.validate(&message).await {
+            ValidationResult::Accept => panic!("Expected reject, got accept"),
+            ValidationResult::Reject(reason) => assert!(reason.contains("expired")),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_counterparty_drift_and_skew_tracking() {
+        use crate::clock::MockClock;
+
+        let clock = Arc::new(MockClock::default());
+        let tracker = Arc::new(ClockSkewTracker::new());
+        let validator = TimestampValidator::new(60)
+            .with_counterparty_drift("did:example:fast", 300)
+            .with_clock(clock.clone())
+            .with_skew_tracker(tracker.clone());
+        let message = |from: &str, ahead: i64| {
+            let mut message = PlainMessage::new(
+                "test_msg_6".to_string(),
+                "test_type".to_string(),
+                serde_json::json!({}),
+                from.to_string(),
+            )
+            .with_recipient("did:example:receiver");
+            message.created_time =
+                Some((clock.now() + Duration::seconds(ahead)).timestamp() as u64);
+            message
+        };
+
+        assert!(matches!(
+            validator.validate(&message("did:example:fast", 120)).await,
+            ValidationResult::Accept
+        ));
+        assert!(matches!(
+            validator.validate(&message("did:example:fast", 240)).await,
+            ValidationResult::Accept
+        ));
+        assert!(matches!(
+            validator
+                .validate(&message("did:example:sender", 120))
+                .await,
+            ValidationResult::Reject(_)
+        ));
+
+        let fast = tracker.get("did:example:fast").unwrap();
+        assert_eq!(fast.samples, 2);
+        assert_eq!(fast.last_skew_secs, 240);
+        assert_eq!(fast.min_skew_secs, 120);
+        assert_eq!(fast.mean_skew_secs, 180.0);
+        assert_eq!(fast.rejected, 0);
+
+        let snapshot = tracker.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[1].counterparty, "did:example:sender");
+        assert_eq!(snapshot[1].rejected, 1);
+    }
 }
@@ -0,0 +1,245 @@
+//! Fiat valuation of transactions for reporting
+//!
+//! With [`NodeConfig::valuation`](crate::NodeConfig::valuation) set, every
+//! Transfer and Payment the node records is tagged with its value in a fiat
+//! currency, at the rate an [`ExchangeRateProvider`] gives for the time the
+//! transaction was recorded. The value is stored with the rate, its source
+//! and its timestamp as a [`TransactionValuation`], so that reports such as
+//! [`ValuationReport`](crate::reporting::ValuationReport) use the rate of the
+//! transaction rather than the rate when the report is run.
+//!
+//! A transaction is valued once: recording it again keeps its first
+//! valuation. Transactions whose asset has no rate are left untagged, and a
+//! provider error is logged without failing message processing. Providers
+//! are asked while the message is processed, so those backed by a remote
+//! service should cache their rates.
+
+use crate::error::{Error, Result};
+#[cfg(feature = "state-machine")]
+use crate::storage::Storage;
+use crate::storage::TransactionValuation;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use tap_msg::message::TapMessage;
+
+/// Rate source of amounts already in the reporting currency
+pub const SAME_CURRENCY_SOURCE: &str = "same_currency";
+
+/// Price of one unit of an asset in a fiat currency
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExchangeRate {
+    /// Fiat amount per unit of the asset
+    pub rate: f64,
+    /// Where the rate comes from, e.g. the name of a price feed
+    pub source: String,
+    /// When the rate was observed, as an RFC 3339 timestamp
+    pub timestamp: String,
+}
+
+/// Source of exchange rates between assets and fiat currencies
+#[async_trait]
+pub trait ExchangeRateProvider: Send + Sync + fmt::Debug {
+    /// Rate of `asset` (a CAIP-19 asset ID or an ISO 4217 currency code) in
+    /// `currency` at time `at`, or None if the provider has no rate for it
+    async fn rate(
+        &self,
+        asset: &str,
+        currency: &str,
+        at: DateTime<Utc>,
+    ) -> Result<Option<ExchangeRate>>;
+}
+
+/// Provider of fixed rates, e.g. for tests or assets pegged to a currency
+#[derive(Debug, Clone)]
+pub struct StaticRates {
+    source: String,
+    rates: HashMap<(String, String), f64>,
+}
+
+impl StaticRates {
+    /// Create a provider without rates, reporting them as from `source`
+    pub fn new(source: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            rates: HashMap::new(),
+        }
+    }
+
+    /// Add the rate of an asset in a currency
+    pub fn with_rate(
+        mut self,
+        asset: impl Into<String>,
+        currency: impl Into<String>,
+        rate: f64,
+    ) -> Self {
+        self.rates
+            .insert((asset.into(), currency.into().to_uppercase()), rate);
+        self
+    }
+}
+
+#[async_trait]
+impl ExchangeRateProvider for StaticRates {
+    async fn rate(
+        &self,
+        asset: &str,
+        currency: &str,
+        at: DateTime<Utc>,
+    ) -> Result<Option<ExchangeRate>> {
+        Ok(self
+            .rates
+            .get(&(asset.to_string(), currency.to_uppercase()))
+            .map(|rate| ExchangeRate {
+                rate: *rate,
+                source: self.source.clone(),
+                timestamp: at.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            }))
+    }
+}
+
+/// How recorded transactions are valued
+#[derive(Debug, Clone)]
+pub struct ValuationConfig {
+    /// Where rates come from
+    pub provider: Arc<dyn ExchangeRateProvider>,
+    /// ISO 4217 code of the currency transactions are valued in
+    pub currency: String,
+}
+
+impl ValuationConfig {
+    /// Value transactions in `currency` at the rates of `provider`
+    pub fn new(provider: Arc<dyn ExchangeRateProvider>, currency: impl Into<String>) -> Self {
+        Self {
+            provider,
+            currency: currency.into().to_uppercase(),
+        }
+    }
+
+    /// Value a Transfer or Payment at time `at`
+    ///
+    /// Returns None for other messages and for assets without a rate.
+    pub async fn value(
+        &self,
+        transaction_id: &str,
+        tap_message: &TapMessage,
+        at: DateTime<Utc>,
+    ) -> Result<Option<TransactionValuation>> {
+        let (asset, amount) = match tap_message {
+            TapMessage::Transfer(transfer) => (transfer.asset.to_string(), &transfer.amount),
+            TapMessage::Payment(payment) => match payment
+                .asset
+                .as_ref()
+                .map(|asset| asset.to_string())
+                .or_else(|| payment.currency_code.clone())
+            {
+                Some(asset) => (asset, &payment.amount),
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        let parsed_amount = amount
+            .parse::<f64>()
+            .map_err(|e| Error::Validation(format!("Invalid amount '{}': {}", amount, e)))?;
+
+        let rate = if asset.eq_ignore_ascii_case(&self.currency) {
+            ExchangeRate {
+                rate: 1.0,
+                source: SAME_CURRENCY_SOURCE.to_string(),
+                timestamp: at.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            }
+        } else {
+            match self.provider.rate(&asset, &self.currency, at).await? {
+                Some(rate) => rate,
+                None => return Ok(None),
+            }
+        };
+
+        Ok(Some(TransactionValuation {
+            transaction_id: transaction_id.to_string(),
+            asset,
+            amount: amount.clone(),
+            currency: self.currency.clone(),
+            fiat_amount: parsed_amount * rate.rate,
+            rate: rate.rate,
+            rate_source: rate.source,
+            rate_timestamp: rate.timestamp,
+            canonical_hash: None,
+            created_at: at.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        }))
+    }
+
+    /// Value a transaction and store the valuation, logging failures
+    #[cfg(feature = "state-machine")]
+    pub(crate) async fn tag(
+        &self,
+        storage: &Storage,
+        transaction_id: &str,
+        tap_message: &TapMessage,
+    ) {
+        let at = storage.clock().now();
+        match self.value(transaction_id, tap_message, at).await {
+            Ok(Some(valuation)) => {
+                if let Err(e) = storage.record_transaction_valuation(&valuation).await {
+                    log::warn!(
+                        "Failed to store valuation of transaction {}: {}",
+                        transaction_id,
+                        e
+                    );
+                }
+            }
+            Ok(None) => log::debug!(
+                "No {} rate to value transaction {}",
+                self.currency,
+                transaction_id
+            ),
+            Err(e) => log::warn!("Failed to value transaction {}: {}", transaction_id, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tap_msg::didcomm::PlainMessage;
+
+    fn transfer(asset: &str, amount: &str) -> TapMessage {
+        let message = PlainMessage::new(
+            "tx-1".to_string(),
+            "https://tap.rsvp/schema/1.0#Transfer".to_string(),
+            serde_json::json!({ "asset": asset, "amount": amount, "agents": [] }),
+            "did:example:originator".to_string(),
+        );
+        TapMessage::from_plain_message(&message).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_value_transfer_at_recorded_rate() {
+        let provider = StaticRates::new("test-feed").with_rate("eip155:1/slip44:60", "usd", 2500.0);
+        let config = ValuationConfig::new(Arc::new(provider), "usd");
+        let at = "2026-03-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        let valuation = config
+            .value("tx-1", &transfer("eip155:1/slip44:60", "1.5"), at)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(valuation.currency, "USD");
+        assert_eq!(valuation.fiat_amount, 3750.0);
+        assert_eq!(valuation.rate, 2500.0);
+        assert_eq!(valuation.rate_source, "test-feed");
+        assert_eq!(valuation.rate_timestamp, "2026-03-01T12:00:00Z");
+
+        let unknown = config
+            .value("tx-1", &transfer("eip155:137/slip44:966", "1"), at)
+            .await
+            .unwrap();
+        assert!(unknown.is_none());
+        assert!(config
+            .value("tx-1", &transfer("eip155:1/slip44:60", "lots"), at)
+            .await
+            .is_err());
+    }
+}
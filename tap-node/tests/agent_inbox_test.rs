@@ -0,0 +1,151 @@
+//! Test polling the messages an agent received through its inbox
+
+use serde_json::json;
+use std::sync::Arc;
+use tap_agent::TapAgent;
+use tap_msg::didcomm::PlainMessage;
+use tap_node::storage::{MessageDirection, ReceivedFilter};
+use tap_node::{NodeConfig, TapNode};
+use tempfile::TempDir;
+
+fn plain_message(id: &str, type_: &str, from: &str, to: &str, thid: &str) -> PlainMessage {
+    serde_json::from_value(json!({
+        "id": id,
+        "typ": "application/didcomm-plain+json",
+        "type": type_,
+        "from": from,
+        "to": [to],
+        "thid": thid,
+        "body": {},
+    }))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_list_received_with_read_cursor() {
+    let temp_dir = TempDir::new().unwrap();
+    let config = NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        ..Default::default()
+    };
+    let node = TapNode::new(config);
+
+    let (agent, agent_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(agent)).await.unwrap();
+    let storage = node
+        .agent_storage_manager()
+        .unwrap()
+        .get_agent_storage(&agent_did)
+        .await
+        .unwrap();
+
+    let transfer = "https://tap.rsvp/schema/1.0#Transfer";
+    let authorize = "https://tap.rsvp/schema/1.0#Authorize";
+    for (id, type_, from, thid) in [
+        ("msg-1", transfer, "did:example:alice", "thread-1"),
+        ("msg-2", authorize, "did:example:bob", "thread-1"),
+        ("msg-3", transfer, "did:example:bob", "thread-2"),
+    ] {
+        storage
+            .log_message(
+                &plain_message(id, type_, from, &agent_did, thid),
+                MessageDirection::Incoming,
+            )
+            .await
+            .unwrap();
+    }
+    storage
+        .log_message(
+            &plain_message(
+                "msg-4",
+                authorize,
+                &agent_did,
+                "did:example:bob",
+                "thread-2",
+            ),
+            MessageDirection::Outgoing,
+        )
+        .await
+        .unwrap();
+
+    // Everything received, oldest first, without the outgoing message
+    let page = node
+        .list_received(&agent_did, None, &ReceivedFilter::default(), None)
+        .await
+        .unwrap();
+    let ids: Vec<&str> = page
+        .messages
+        .iter()
+        .map(|m| m.message_id.as_str())
+        .collect();
+    assert_eq!(ids, ["msg-1", "msg-2", "msg-3"]);
+    assert_eq!(page.read_cursor, 0);
+    assert_eq!(page.unread_count, 3);
+    assert!(!page.is_read(&page.messages[0]));
+
+    // Continue after a cursor
+    let first = node
+        .list_received(&agent_did, None, &ReceivedFilter::default(), Some(1))
+        .await
+        .unwrap();
+    assert_eq!(first.messages.len(), 1);
+    let rest = node
+        .list_received(
+            &agent_did,
+            Some(first.next_cursor),
+            &ReceivedFilter::default(),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(rest.messages.len(), 2);
+    assert_eq!(rest.messages[0].message_id, "msg-2");
+
+    // Filters
+    let filter = ReceivedFilter {
+        message_type: Some(transfer.to_string()),
+        from_did: Some("did:example:bob".to_string()),
+        ..Default::default()
+    };
+    let page = node
+        .list_received(&agent_did, None, &filter, None)
+        .await
+        .unwrap();
+    assert_eq!(page.messages.len(), 1);
+    assert_eq!(page.messages[0].message_id, "msg-3");
+
+    // Mark the first two read
+    let read_cursor = node
+        .mark_received_read(&agent_did, Some(rest.messages[0].id))
+        .await
+        .unwrap();
+    let unread = ReceivedFilter {
+        unread_only: true,
+        ..Default::default()
+    };
+    let page = node
+        .list_received(&agent_did, None, &unread, None)
+        .await
+        .unwrap();
+    assert_eq!(page.read_cursor, read_cursor);
+    assert_eq!(page.unread_count, 1);
+    assert_eq!(page.messages.len(), 1);
+    assert_eq!(page.messages[0].message_id, "msg-3");
+
+    // The cursor never moves backwards
+    let cursor = node
+        .mark_received_read(&agent_did, Some(first.messages[0].id))
+        .await
+        .unwrap();
+    assert_eq!(cursor, read_cursor);
+
+    // Mark everything read
+    node.mark_received_read(&agent_did, None).await.unwrap();
+    let page = node
+        .list_received(&agent_did, None, &unread, None)
+        .await
+        .unwrap();
+    assert!(page.messages.is_empty());
+    assert_eq!(page.unread_count, 0);
+    assert_eq!(page.next_cursor, page.read_cursor);
+}
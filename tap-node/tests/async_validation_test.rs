@@ -0,0 +1,100 @@
+//! Tests for the asynchronous validation of received message bodies
+
+mod common;
+
+use common::transfer;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tap_agent::TapAgent;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::ValidationContext;
+use tap_msg::TapMessage;
+use tap_node::message::{CustomMessageType, MessageTypeRegistry};
+use tap_node::validation::async_validator::AsyncValidators;
+use tap_node::{IngestOutcome, NodeConfig, RejectionCode, TapNode};
+use tempfile::TempDir;
+
+const RECEIPT: &str = "https://example.com/protocols/receipt/1.0/receipt";
+
+/// Receipt for a payment made under a transaction the node knows
+#[derive(Debug, Clone, Serialize, Deserialize, TapMessage)]
+#[tap(
+    message_type = "https://example.com/protocols/receipt/1.0/receipt",
+    async_validation
+)]
+struct Receipt {
+    #[tap(thread_id)]
+    transaction_id: String,
+}
+
+impl Receipt {
+    async fn validate_receipt_async(&self, context: &dyn ValidationContext) -> tap_msg::Result<()> {
+        if !context.transaction_exists(&self.transaction_id).await? {
+            return Err(tap_msg::Error::Validation(format!(
+                "Unknown transaction {}",
+                self.transaction_id
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn receipt(id: &str, transaction_id: &str, from: &str, to: &str) -> serde_json::Value {
+    let message = PlainMessage::new(
+        id.to_string(),
+        RECEIPT.to_string(),
+        serde_json::to_value(Receipt {
+            transaction_id: transaction_id.to_string(),
+        })
+        .unwrap(),
+        from.to_string(),
+    )
+    .with_recipient(to);
+    serde_json::to_value(message).unwrap()
+}
+
+#[tokio::test]
+async fn test_received_bodies_are_validated_against_storage() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut message_types = MessageTypeRegistry::new();
+    message_types
+        .register(CustomMessageType::new(RECEIPT))
+        .unwrap();
+    let mut async_validators = AsyncValidators::new();
+    async_validators.register::<Receipt>();
+    let mut node = TapNode::new(NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("node.db")),
+        message_types: Some(Arc::new(message_types)),
+        async_validators: Some(Arc::new(async_validators)),
+        ..Default::default()
+    });
+    node.init_storage().await.unwrap();
+    let (alice, alice_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (bob, bob_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(alice)).await.unwrap();
+    node.register_agent(Arc::new(bob)).await.unwrap();
+    node.storage()
+        .unwrap()
+        .insert_transaction(&transfer("transfer-1", &alice_did, &bob_did))
+        .await
+        .unwrap();
+
+    let outcome = node
+        .receive_message(receipt("receipt-1", "transfer-1", &alice_did, &bob_did))
+        .await
+        .unwrap();
+    assert!(outcome.is_accepted());
+
+    let outcome = node
+        .receive_message(receipt("receipt-2", "transfer-2", &alice_did, &bob_did))
+        .await
+        .unwrap();
+    match outcome {
+        IngestOutcome::Rejected { code, reason } => {
+            assert_eq!(code, RejectionCode::Invalid);
+            assert!(reason.contains("Unknown transaction transfer-2"));
+        }
+        other => panic!("Unexpected outcome {:?}", other),
+    }
+}
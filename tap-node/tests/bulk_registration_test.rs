@@ -0,0 +1,85 @@
+//! Tests for registering many agents at once
+
+use std::sync::Arc;
+use tap_agent::TapAgent;
+use tap_node::{NodeConfig, NodeEvent, TapNode};
+use tempfile::TempDir;
+
+#[tokio::test]
+async fn test_register_agents_initializes_storages() {
+    let dir = TempDir::new().unwrap();
+    let mut node = TapNode::new(NodeConfig {
+        tap_root: Some(dir.path().to_path_buf()),
+        storage_path: Some(dir.path().join("node.db")),
+        ..Default::default()
+    });
+    node.init_storage().await.unwrap();
+
+    let mut agents = Vec::new();
+    let mut dids = Vec::new();
+    for _ in 0..40 {
+        let (agent, did) = TapAgent::from_ephemeral_key().await.unwrap();
+        agents.push(Arc::new(agent));
+        dids.push(did);
+    }
+    node.register_agent(agents[0].clone()).await.unwrap();
+    let mut events = node.event_bus().subscribe_channel();
+
+    // Registered and repeated agents are skipped
+    let mut batch = agents.clone();
+    batch.push(agents[1].clone());
+    let registered = node.register_agents(batch).await.unwrap();
+    assert_eq!(registered, dids[1..]);
+    assert_eq!(node.list_agents().len(), 40);
+
+    let storage_manager = node.agent_storage_manager().unwrap();
+    for did in &dids {
+        assert!(storage_manager.get_cached_agent_storage(did).is_some());
+    }
+
+    let mut announced = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let NodeEvent::AgentRegistered { did } = event.as_ref() {
+            announced.push(did.clone());
+        }
+    }
+    assert_eq!(announced, registered);
+
+    assert!(node.register_agents(agents).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_agent_without_encryption_key_is_refused() {
+    let dir = TempDir::new().unwrap();
+    let mut node = TapNode::new(NodeConfig {
+        tap_root: Some(dir.path().to_path_buf()),
+        storage_path: Some(dir.path().join("node.db")),
+        message_encryption: Some(tap_node::storage::MessageEncryption::default()),
+        ..Default::default()
+    });
+    node.init_storage().await.unwrap();
+
+    // The agent's key manager does not hold its key
+    let keyless = Arc::new(TapAgent::new(
+        tap_agent::AgentConfig::new("did:key:z6MkKeyless".to_string()),
+        Arc::new(tap_agent::AgentKeyManager::new()),
+    ));
+    let (agent, did) = TapAgent::from_ephemeral_key().await.unwrap();
+
+    assert!(node.register_agent(keyless.clone()).await.is_err());
+    let registered = node
+        .register_agents(vec![keyless, Arc::new(agent)])
+        .await
+        .unwrap();
+    assert_eq!(registered, vec![did.clone()]);
+
+    let storage_manager = node.agent_storage_manager().unwrap();
+    assert!(storage_manager
+        .get_cached_agent_storage("did:key:z6MkKeyless")
+        .is_none());
+    assert!(storage_manager
+        .get_cached_agent_storage(&did)
+        .unwrap()
+        .message_encryption()
+        .is_some());
+}
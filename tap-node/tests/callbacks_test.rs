@@ -0,0 +1,107 @@
+//! Tests for callbacks registered for received message types
+
+mod common;
+
+use common::{transfer_body, transfer_message};
+use std::sync::Arc;
+use std::time::Duration;
+use tap_agent::TapAgent;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::Transfer;
+use tap_node::callbacks::ReceivedMessage;
+use tap_node::{NodeConfig, TapNode};
+use tempfile::TempDir;
+use tokio::sync::mpsc;
+
+fn transfer(id: &str, from: &str, to: &str, amount: &str) -> PlainMessage {
+    transfer_message(id, &transfer_body(from, to, amount), from, to)
+}
+
+#[tokio::test]
+async fn test_callbacks_receive_delivered_messages_of_their_type() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut node = TapNode::new(NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("node.db")),
+        ..Default::default()
+    });
+    node.init_storage().await.unwrap();
+    let (alice, alice_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (bob, bob_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(alice)).await.unwrap();
+    node.register_agent(Arc::new(bob)).await.unwrap();
+
+    let (typed_sender, mut typed) = mpsc::unbounded_channel();
+    let typed_id = node.on_message(&bob_did, move |received: ReceivedMessage<Transfer>| {
+        let sender = typed_sender.clone();
+        async move {
+            sender
+                .send((
+                    received.agent_did,
+                    received.message.id,
+                    received.body.amount,
+                ))
+                .unwrap();
+            Ok(())
+        }
+    });
+    let (json_sender, mut json) = mpsc::unbounded_channel();
+    node.on_message_type(
+        &alice_did,
+        "https://tap.rsvp/schema/1.0#Transfer",
+        move |received: ReceivedMessage<serde_json::Value>| {
+            let sender = json_sender.clone();
+            async move {
+                sender.send(received.body["amount"].clone()).unwrap();
+                Ok(())
+            }
+        },
+    );
+
+    for message in [
+        transfer("transfer-1", &alice_did, &bob_did, "10"),
+        transfer("transfer-2", &bob_did, &alice_did, "20"),
+        transfer("transfer-3", &alice_did, &bob_did, "30"),
+    ] {
+        let outcome = node
+            .receive_message(serde_json::to_value(message).unwrap())
+            .await
+            .unwrap();
+        assert!(outcome.is_accepted());
+    }
+
+    let mut received = Vec::new();
+    for _ in 0..2 {
+        received.push(
+            tokio::time::timeout(Duration::from_secs(5), typed.recv())
+                .await
+                .unwrap()
+                .unwrap(),
+        );
+    }
+    received.sort();
+    assert_eq!(
+        received,
+        vec![
+            (bob_did.clone(), "transfer-1".to_string(), "10".to_string()),
+            (bob_did.clone(), "transfer-3".to_string(), "30".to_string()),
+        ]
+    );
+    assert_eq!(
+        tokio::time::timeout(Duration::from_secs(5), json.recv())
+            .await
+            .unwrap()
+            .unwrap(),
+        "20"
+    );
+
+    // Removed callbacks receive nothing more
+    assert!(node.remove_callback(&typed_id));
+    node.receive_message(
+        serde_json::to_value(transfer("transfer-4", &alice_did, &bob_did, "40")).unwrap(),
+    )
+    .await
+    .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(typed.try_recv().is_err());
+}
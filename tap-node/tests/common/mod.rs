@@ -0,0 +1,41 @@
+//! Fixtures shared by the integration tests
+//!
+//! Each test crate uses some of them only.
+#![allow(dead_code)]
+
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::{Party, Transfer};
+
+/// A Transfer of `amount` ETH from `originator` to `beneficiary`
+pub fn transfer_body(originator: &str, beneficiary: &str, amount: &str) -> Transfer {
+    Transfer {
+        transaction_id: None,
+        originator: Some(Party::new(originator)),
+        beneficiary: Some(Party::new(beneficiary)),
+        asset: "eip155:1/slip44:60".parse().unwrap(),
+        amount: amount.to_string(),
+        agents: vec![],
+        memo: None,
+        settlement_id: None,
+        expiry: None,
+        transaction_value: None,
+        connection_id: None,
+        metadata: Default::default(),
+    }
+}
+
+/// The Transfer message `id` carrying `transfer`, sent by `from` to `to`
+pub fn transfer_message(id: &str, transfer: &Transfer, from: &str, to: &str) -> PlainMessage {
+    PlainMessage::new(
+        id.to_string(),
+        "https://tap.rsvp/schema/1.0#Transfer".to_string(),
+        serde_json::to_value(transfer).unwrap(),
+        from.to_string(),
+    )
+    .with_recipient(to)
+}
+
+/// A Transfer of 10 ETH from `from` to `to`, sent by `from` to `to`
+pub fn transfer(id: &str, from: &str, to: &str) -> PlainMessage {
+    transfer_message(id, &transfer_body(from, to, "10"), from, to)
+}
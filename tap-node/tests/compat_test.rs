@@ -0,0 +1,194 @@
+//! Tests for the compatibility rules of counterparties
+
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use tap_agent::TapAgent;
+use tap_msg::didcomm::PlainMessage;
+use tap_node::compat::{CompatibilityRule, CompatibilityRules};
+use tap_node::message::{CustomMessageHandler, CustomMessageType, MessageTypeRegistry};
+use tap_node::{NodeConfig, TapNode};
+use tempfile::TempDir;
+
+const NOTE_V1: &str = "https://example.com/protocols/note/1.0/note";
+const NOTE_V2: &str = "https://example.com/protocols/note/2.0/note";
+
+#[derive(Default)]
+struct RecordingHandler {
+    handled: Mutex<Vec<PlainMessage>>,
+}
+
+#[async_trait]
+impl CustomMessageHandler for RecordingHandler {
+    async fn handle(&self, message: &PlainMessage) -> tap_node::Result<()> {
+        self.handled.lock().unwrap().push(message.clone());
+        Ok(())
+    }
+}
+
+async fn node_with_handler(temp_dir: &TempDir, handler: Arc<RecordingHandler>) -> TapNode {
+    let mut registry = MessageTypeRegistry::new();
+    registry
+        .register(CustomMessageType::new(NOTE_V1).with_handler(handler))
+        .unwrap();
+    let config = NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("node.db")),
+        message_types: Some(Arc::new(registry)),
+        ..Default::default()
+    };
+    let mut node = TapNode::new(config);
+    node.init_storage().await.unwrap();
+    node
+}
+
+#[tokio::test]
+async fn test_inbound_rules_normalize_received_messages() {
+    let temp_dir = TempDir::new().unwrap();
+    let handler = Arc::new(RecordingHandler::default());
+    let node = node_with_handler(&temp_dir, handler.clone()).await;
+    let (bob, bob_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(bob)).await.unwrap();
+    let counterparty = "did:example:legacy-vasp";
+
+    node.set_counterparty_compatibility(
+        counterparty,
+        CompatibilityRules {
+            inbound: vec![
+                CompatibilityRule::RenameType {
+                    from: "https://example.com/protocols/note/2.0".to_string(),
+                    to: "https://example.com/protocols/note/1.0".to_string(),
+                },
+                CompatibilityRule::DefaultField {
+                    pointer: "/body/lang".to_string(),
+                    value: serde_json::json!("en"),
+                },
+            ],
+            outbound: Vec::new(),
+        },
+    )
+    .await
+    .unwrap();
+
+    let message = PlainMessage::new(
+        "note-1".to_string(),
+        NOTE_V2.to_string(),
+        serde_json::json!({"text": "hello"}),
+        counterparty.to_string(),
+    )
+    .with_recipient(&bob_did);
+    let outcome = node
+        .receive_message(serde_json::to_value(&message).unwrap())
+        .await
+        .unwrap();
+    assert!(outcome.is_accepted());
+
+    let handled = handler.handled.lock().unwrap().clone();
+    assert_eq!(handled.len(), 1);
+    assert_eq!(handled[0].type_, NOTE_V1);
+    assert_eq!(handled[0].body["lang"], "en");
+
+    // Messages of other senders are left alone
+    let other = PlainMessage::new(
+        "note-2".to_string(),
+        NOTE_V2.to_string(),
+        serde_json::json!({"text": "hello"}),
+        "did:example:other".to_string(),
+    )
+    .with_recipient(&bob_did);
+    node.receive_message(serde_json::to_value(&other).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(handler.handled.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_outbound_rules_format_sent_messages() {
+    let temp_dir = TempDir::new().unwrap();
+    let handler = Arc::new(RecordingHandler::default());
+    let node = node_with_handler(&temp_dir, handler.clone()).await;
+    let (alice, alice_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (bob, bob_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(alice)).await.unwrap();
+    node.register_agent(Arc::new(bob)).await.unwrap();
+
+    node.set_counterparty_compatibility(
+        &bob_did,
+        CompatibilityRules {
+            inbound: Vec::new(),
+            outbound: vec![CompatibilityRule::OmitField {
+                pointer: "/body/internal_ref".to_string(),
+            }],
+        },
+    )
+    .await
+    .unwrap();
+
+    let message = PlainMessage::new(
+        "note-3".to_string(),
+        NOTE_V1.to_string(),
+        serde_json::json!({"text": "hello", "internal_ref": "case-42"}),
+        alice_did.clone(),
+    )
+    .with_recipient(&bob_did);
+    node.send_message(alice_did.clone(), message).await.unwrap();
+
+    let handled = handler.handled.lock().unwrap().clone();
+    assert_eq!(handled.len(), 1);
+    assert_eq!(handled[0].body["text"], "hello");
+    assert!(handled[0].body.get("internal_ref").is_none());
+
+    // The sender keeps the message as it was written
+    let alice_storage = node
+        .agent_storage_manager()
+        .unwrap()
+        .get_agent_storage(&alice_did)
+        .await
+        .unwrap();
+    let stored = alice_storage
+        .get_message_by_id("note-3")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.message_json["body"]["internal_ref"], "case-42");
+}
+
+#[tokio::test]
+async fn test_rules_are_persisted() {
+    let temp_dir = TempDir::new().unwrap();
+    let counterparty = "did:example:legacy-vasp";
+    let rules = CompatibilityRules {
+        inbound: Vec::new(),
+        outbound: vec![CompatibilityRule::MediaType {
+            typ: "application/json".to_string(),
+        }],
+    };
+
+    {
+        let node = node_with_handler(&temp_dir, Arc::default()).await;
+        node.set_counterparty_compatibility(counterparty, rules.clone())
+            .await
+            .unwrap();
+    }
+
+    let node = node_with_handler(&temp_dir, Arc::default()).await;
+    let stored = node
+        .counterparty_compatibility(counterparty)
+        .await
+        .unwrap()
+        .expect("Rules should survive a restart");
+    assert_eq!(*stored, rules);
+
+    assert!(node
+        .remove_counterparty_compatibility(counterparty)
+        .await
+        .unwrap());
+    assert!(node
+        .counterparty_compatibility(counterparty)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(!node
+        .remove_counterparty_compatibility(counterparty)
+        .await
+        .unwrap());
+}
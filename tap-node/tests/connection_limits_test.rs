@@ -0,0 +1,91 @@
+//! Tests for the TAIP-15 transaction limits of connections
+
+mod common;
+
+use common::{transfer_body, transfer_message};
+use std::sync::Arc;
+use tap_agent::TapAgent;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::Transfer;
+use tap_node::{IngestOutcome, NodeConfig, TapNode};
+use tempfile::TempDir;
+
+const USDC: &str = "eip155:1/erc20:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+
+fn transfer(id: &str, amount: &str, from: &str, to: &str) -> serde_json::Value {
+    let transfer = Transfer {
+        asset: USDC.parse().unwrap(),
+        connection_id: Some("connect-1".to_string()),
+        ..transfer_body(from, to, amount)
+    };
+    serde_json::to_value(transfer_message(id, &transfer, from, to)).unwrap()
+}
+
+#[tokio::test]
+async fn test_transfers_limited_per_connection() {
+    let temp_dir = TempDir::new().unwrap();
+    let config = NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("node.db")),
+        ..Default::default()
+    };
+    let mut node = TapNode::new(config);
+    node.init_storage().await.unwrap();
+    let (wallet, wallet_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (vasp, vasp_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(wallet)).await.unwrap();
+    node.register_agent(Arc::new(vasp)).await.unwrap();
+
+    // The wallet may move up to 5000 USDC a day under its connection
+    let connect = PlainMessage::new(
+        "connect-1".to_string(),
+        "https://tap.rsvp/schema/1.0#Connect".to_string(),
+        serde_json::json!({
+            "constraints": { "limits": { "per_day": "5000" } }
+        }),
+        wallet_did.clone(),
+    )
+    .with_recipient(&vasp_did);
+    node.storage()
+        .unwrap()
+        .insert_connection(&connect)
+        .await
+        .unwrap();
+
+    for id in ["transfer-1", "transfer-2"] {
+        let outcome = node
+            .receive_message(transfer(id, "2000", &wallet_did, &vasp_did))
+            .await
+            .unwrap();
+        assert!(outcome.is_accepted());
+    }
+
+    let allowance = node
+        .connection_allowance("connect-1", Some(USDC))
+        .await
+        .unwrap()
+        .expect("Connection has limits");
+    assert_eq!(allowance.periods[0].spent, 4000.0);
+    assert_eq!(allowance.available(), Some(1000.0));
+
+    let outcome = node
+        .receive_message(transfer("transfer-3", "1500", &wallet_did, &vasp_did))
+        .await
+        .unwrap();
+    assert!(matches!(outcome, IngestOutcome::Rejected { .. }));
+
+    // Limits are also enforced on transfers the node sends
+    let over: PlainMessage =
+        serde_json::from_value(transfer("transfer-4", "1500", &wallet_did, &vasp_did)).unwrap();
+    let error = node
+        .send_message(wallet_did.clone(), over)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("daily limit"));
+
+    assert!(node
+        .connection_allowance("connect-2", Some(USDC))
+        .await
+        .unwrap()
+        .is_none());
+}
@@ -0,0 +1,122 @@
+//! Tests for verifying credentials presented by agents in incoming messages
+
+mod common;
+
+use common::{transfer_body, transfer_message};
+use serde_json::json;
+use std::sync::Arc;
+use tap_agent::key_manager::KeyManager;
+use tap_agent::message::JwsProtected;
+use tap_agent::TapAgent;
+use tap_msg::message::{Agent, Transfer};
+use tap_node::credentials::CredentialVerification;
+use tap_node::{IngestOutcome, NodeConfig, RejectionCode, TapNode};
+use tempfile::TempDir;
+
+const SENDER: &str = "did:web:vasp.example";
+
+/// A credential issued by `issuer` about `subject`
+async fn issue(issuer: &TapAgent, issuer_did: &str, subject: &str) -> serde_json::Value {
+    let kid = issuer.get_signing_kid().await.unwrap();
+    let claims = json!({
+        "iss": issuer_did,
+        "sub": subject,
+        "exp": chrono::Utc::now().timestamp() + 3600,
+        "vc": {
+            "type": ["VerifiableCredential", "VASPLicense"],
+            "credentialSubject": {"id": subject, "jurisdiction": "US"}
+        }
+    });
+    let protected = JwsProtected {
+        typ: "JWT".to_string(),
+        alg: String::new(),
+        kid: kid.clone(),
+        zip: None,
+    };
+    let jws = issuer
+        .key_manager()
+        .sign_jws(&kid, &serde_json::to_vec(&claims).unwrap(), Some(protected))
+        .await
+        .unwrap();
+    serde_json::from_str(&jws).unwrap()
+}
+
+fn transfer(id: &str, recipient: &str, credential: serde_json::Value) -> serde_json::Value {
+    let transfer = Transfer {
+        transaction_id: Some(id.to_string()),
+        agents: vec![
+            Agent::new(SENDER, "VASP", "did:example:alice").with_credential(credential),
+            Agent::new(recipient, "VASP", "did:example:bob"),
+        ],
+        ..transfer_body("did:example:alice", "did:example:bob", "10")
+    };
+    serde_json::to_value(transfer_message(id, &transfer, SENDER, recipient)).unwrap()
+}
+
+async fn setup(trusted_issuer: &str, reject_invalid: bool) -> (TempDir, TapNode, String) {
+    let temp_dir = TempDir::new().unwrap();
+    let config = NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("node.db")),
+        credential_verification: Some(CredentialVerification {
+            trusted_issuers: vec![trusted_issuer.to_string()],
+            reject_invalid,
+        }),
+        ..Default::default()
+    };
+    let mut node = TapNode::new(config);
+    node.init_storage().await.unwrap();
+
+    let (receiver, receiver_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(receiver)).await.unwrap();
+
+    (temp_dir, node, receiver_did)
+}
+
+#[tokio::test]
+async fn test_invalid_credentials_rejected() {
+    let (regulator, regulator_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (_temp_dir, node, receiver_did) = setup(&regulator_did, true).await;
+    assert!(node.credential_verifier().is_some());
+
+    // A trusted credential about the sending agent is accepted
+    let valid = issue(&regulator, &regulator_did, SENDER).await;
+    let result = node
+        .receive_message(transfer("tx-valid", &receiver_did, valid))
+        .await
+        .unwrap();
+    assert!(result.is_accepted());
+
+    // A credential about another agent is rejected
+    let borrowed = issue(&regulator, &regulator_did, "did:web:other.example").await;
+    let result = node
+        .receive_message(transfer("tx-borrowed", &receiver_did, borrowed))
+        .await;
+    match result {
+        Ok(IngestOutcome::Rejected {
+            code: RejectionCode::Invalid,
+            reason,
+        }) => {
+            assert!(
+                reason.contains("Invalid credential for agent"),
+                "{}",
+                reason
+            )
+        }
+        other => panic!("Expected rejection, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_invalid_credentials_logged_by_default() {
+    let (regulator, regulator_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (_temp_dir, node, receiver_did) = setup("did:example:other-regulator", false).await;
+
+    // Untrusted, but only logged
+    let untrusted = issue(&regulator, &regulator_did, SENDER).await;
+    let result = node
+        .receive_message(transfer("tx-untrusted", &receiver_did, untrusted))
+        .await
+        .unwrap();
+    assert!(result.is_accepted());
+}
@@ -0,0 +1,121 @@
+//! Tests for custom message types registered with the node
+
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use tap_agent::TapAgent;
+use tap_msg::didcomm::PlainMessage;
+use tap_node::message::{
+    CustomMessageHandler, CustomMessageType, MessageTypeRegistry, RequiredBodyFields,
+};
+use tap_node::{IngestOutcome, NodeConfig, TapNode};
+use tempfile::TempDir;
+
+const INVOICE: &str = "https://example.com/protocols/invoice/1.0/invoice";
+
+#[derive(Default)]
+struct RecordingHandler {
+    handled: Mutex<Vec<String>>,
+}
+
+#[async_trait]
+impl CustomMessageHandler for RecordingHandler {
+    async fn handle(&self, message: &PlainMessage) -> tap_node::Result<()> {
+        self.handled.lock().unwrap().push(message.id.clone());
+        Ok(())
+    }
+}
+
+fn invoice(
+    id: &str,
+    type_uri: &str,
+    body: serde_json::Value,
+    from: &str,
+    to: &str,
+) -> serde_json::Value {
+    let message = PlainMessage::new(id.to_string(), type_uri.to_string(), body, from.to_string())
+        .with_recipient(to);
+    serde_json::to_value(&message).unwrap()
+}
+
+#[tokio::test]
+async fn test_custom_message_type_is_validated_stored_and_handled() {
+    let handler = Arc::new(RecordingHandler::default());
+    let mut registry = MessageTypeRegistry::new();
+    registry
+        .register(
+            CustomMessageType::new(INVOICE)
+                .with_validator(RequiredBodyFields::new(["amount"]))
+                .with_handler(handler.clone()),
+        )
+        .unwrap();
+
+    let temp_dir = TempDir::new().unwrap();
+    let config = NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("node.db")),
+        message_types: Some(Arc::new(registry)),
+        ..Default::default()
+    };
+    let mut node = TapNode::new(config);
+    node.init_storage().await.unwrap();
+    let (alice, alice_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (bob, bob_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(alice)).await.unwrap();
+    node.register_agent(Arc::new(bob)).await.unwrap();
+
+    let outcome = node
+        .receive_message(invoice(
+            "invoice-1",
+            INVOICE,
+            serde_json::json!({"amount": "10.00"}),
+            &alice_did,
+            &bob_did,
+        ))
+        .await
+        .unwrap();
+    assert!(outcome.is_accepted());
+    assert_eq!(*handler.handled.lock().unwrap(), vec!["invoice-1"]);
+
+    let bob_storage = node
+        .agent_storage_manager()
+        .unwrap()
+        .get_agent_storage(&bob_did)
+        .await
+        .unwrap();
+    let stored = bob_storage
+        .get_message_by_id("invoice-1")
+        .await
+        .unwrap()
+        .expect("Invoice should be stored for its recipient");
+    assert_eq!(stored.message_type, INVOICE);
+
+    // Invalid messages are rejected before they are stored or handled
+    let outcome = node
+        .receive_message(invoice(
+            "invoice-2",
+            INVOICE,
+            serde_json::json!({"currency": "USD"}),
+            &alice_did,
+            &bob_did,
+        ))
+        .await
+        .unwrap();
+    assert!(matches!(outcome, IngestOutcome::Rejected { .. }));
+    assert!(bob_storage
+        .get_message_by_id("invoice-2")
+        .await
+        .unwrap()
+        .is_none());
+
+    // Unregistered types of other protocols are not handled
+    node.receive_message(invoice(
+        "receipt-1",
+        "https://example.com/protocols/invoice/1.0/receipt",
+        serde_json::json!({"amount": "10.00"}),
+        &alice_did,
+        &bob_did,
+    ))
+    .await
+    .unwrap();
+    assert_eq!(handler.handled.lock().unwrap().len(), 1);
+}
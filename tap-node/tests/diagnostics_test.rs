@@ -0,0 +1,71 @@
+//! Tests for the diagnostics snapshot of a node
+
+mod common;
+
+use common::transfer;
+use std::sync::Arc;
+use tap_agent::TapAgent;
+use tap_node::diagnostics::ProcessingDirection;
+use tap_node::message::ProcessorPoolConfig;
+use tap_node::{NodeConfig, TapNode};
+use tempfile::TempDir;
+
+#[tokio::test]
+async fn test_diagnostics_report_processing() {
+    let temp_dir = TempDir::new().unwrap();
+    let config = NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("node.db")),
+        ..Default::default()
+    };
+    let mut node = TapNode::new(config);
+    node.init_storage().await.unwrap();
+    let (alice, alice_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (bob, bob_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(alice)).await.unwrap();
+    node.register_agent(Arc::new(bob)).await.unwrap();
+
+    let diagnostics = node.diagnostics().await;
+    assert!(diagnostics.processor_pool.is_none());
+    assert!(diagnostics.processors.is_empty());
+
+    node.send_message(
+        alice_did.clone(),
+        transfer("transfer-1", &alice_did, &bob_did),
+    )
+    .await
+    .unwrap();
+    let outcome = node
+        .receive_message(
+            serde_json::to_value(transfer("transfer-2", &alice_did, &bob_did)).unwrap(),
+        )
+        .await
+        .unwrap();
+    assert!(outcome.is_accepted());
+
+    node.start(ProcessorPoolConfig::default()).await.unwrap();
+    let diagnostics = node.diagnostics().await;
+    let lanes = diagnostics
+        .processor_pool
+        .expect("Node has a processor pool");
+    assert_eq!(lanes.high + lanes.normal + lanes.low, 0);
+    assert_eq!(diagnostics.intake_queued, 0);
+    assert_eq!(diagnostics.incoming_in_flight, 0);
+    assert_eq!(diagnostics.outgoing_in_flight, 0);
+
+    // Processors were timed in both directions
+    for direction in [ProcessingDirection::Incoming, ProcessingDirection::Outgoing] {
+        let validation = diagnostics
+            .processors
+            .iter()
+            .find(|timing| timing.processor == "validation" && timing.direction == direction)
+            .expect("Validation was timed");
+        assert!(validation.calls >= 1);
+        assert!(validation.max_micros >= validation.mean_micros);
+    }
+    assert!(diagnostics.event_bus.published.values().sum::<u64>() > 0);
+
+    // The snapshot serializes for export
+    let json = serde_json::to_value(&diagnostics).unwrap();
+    assert!(json["processors"][0]["direction"].is_string());
+}
@@ -9,7 +9,7 @@ use tap_node::message::processor::{PlainMessageProcessor, ValidationPlainMessage
 
 #[tokio::test]
 async fn test_trust_ping_passes_validation() {
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a Trust Ping message
     let trust_ping = PlainMessage {
@@ -51,7 +51,7 @@ async fn test_trust_ping_passes_validation() {
 
 #[tokio::test]
 async fn test_basic_message_passes_validation() {
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a Basic Message
     let basic_message = PlainMessage {
@@ -93,7 +93,7 @@ async fn test_basic_message_passes_validation() {
 
 #[tokio::test]
 async fn test_unknown_didcomm_message_passes_validation() {
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create an unknown DIDComm message type
     let unknown_message = PlainMessage {
@@ -134,7 +134,7 @@ async fn test_unknown_didcomm_message_passes_validation() {
 
 #[tokio::test]
 async fn test_unknown_protocol_fails_validation() {
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a message with completely unknown protocol
     let unknown_protocol = PlainMessage {
@@ -0,0 +1,104 @@
+//! Tests for agents registered for a single transaction or thread
+
+use std::time::Duration;
+use tap_agent::Agent;
+use tap_node::storage::Storage;
+use tap_node::{NodeConfig, TapNode};
+use tempfile::TempDir;
+
+/// Wait until the node has no agent with the DID
+async fn wait_for_release(node: &TapNode, did: &str) {
+    for _ in 0..50 {
+        if !node.list_agents().iter().any(|agent| agent == did) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("Ephemeral agent {} was not released", did);
+}
+
+#[tokio::test]
+async fn test_ephemeral_agent_released_when_thread_ends() {
+    let temp_dir = TempDir::new().unwrap();
+    let config = NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("node.db")),
+        ..Default::default()
+    };
+    let mut node = TapNode::new(config);
+    node.init_storage().await.unwrap();
+
+    let agent = node
+        .register_ephemeral_agent("transfer-1", Duration::from_secs(3600))
+        .await
+        .unwrap();
+    let did = agent.get_agent_did().to_string();
+    assert!(node.list_agents().contains(&did));
+    assert_eq!(node.ephemeral_agents()[0].thread_id, "transfer-1");
+
+    // No storage is set up until the agent handles a message
+    let db_path = Storage::agent_db_location(&did, Some(temp_dir.path().to_path_buf())).unwrap();
+    assert!(!db_path.exists());
+    node.agent_storage_manager()
+        .unwrap()
+        .get_agent_storage(&did)
+        .await
+        .unwrap();
+    assert!(db_path.exists());
+
+    // Other transactions and intermediate states do not release the agent
+    let event_bus = node.event_bus();
+    event_bus
+        .publish_transaction_state_changed(
+            "transfer-2".to_string(),
+            "received".to_string(),
+            "rejected".to_string(),
+            None,
+        )
+        .await;
+    event_bus
+        .publish_transaction_state_changed(
+            "transfer-1".to_string(),
+            "received".to_string(),
+            "ready_to_settle".to_string(),
+            None,
+        )
+        .await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(node.list_agents().contains(&did));
+
+    event_bus
+        .publish_transaction_state_changed(
+            "transfer-1".to_string(),
+            "ready_to_settle".to_string(),
+            "settled".to_string(),
+            None,
+        )
+        .await;
+    wait_for_release(&node, &did).await;
+    assert!(node.ephemeral_agents().is_empty());
+
+    // The database is deleted after the agent is unregistered
+    for _ in 0..50 {
+        if !db_path.parent().unwrap().exists() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(!db_path.parent().unwrap().exists());
+}
+
+#[tokio::test]
+async fn test_ephemeral_agent_released_after_ttl() {
+    let node = TapNode::new(NodeConfig::default());
+
+    let agent = node
+        .register_ephemeral_agent("session-1", Duration::from_millis(100))
+        .await
+        .unwrap();
+    let did = agent.get_agent_did().to_string();
+    wait_for_release(&node, &did).await;
+
+    // Releasing the agent again has no effect
+    assert!(!node.release_ephemeral_agent(&did).await.unwrap());
+}
@@ -0,0 +1,157 @@
+//! Tests for EventBus routing, subscriber isolation and metrics
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tap_node::event::{DeliveryPolicy, EventBus, EventKind};
+use tap_node::{EventSubscriber, NodeEvent};
+use tokio::sync::Notify;
+
+/// Counts the events it receives, optionally restricted to some kinds
+#[derive(Default)]
+struct CountingSubscriber {
+    count: AtomicUsize,
+    kinds: Option<&'static [EventKind]>,
+}
+
+#[async_trait::async_trait]
+impl EventSubscriber for CountingSubscriber {
+    async fn handle_event(&self, _event: NodeEvent) {
+        self.count.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn event_kinds(&self) -> Option<&'static [EventKind]> {
+        self.kinds
+    }
+}
+
+/// Blocks in its handler until released
+struct BlockedSubscriber {
+    release: Notify,
+    handled: AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl EventSubscriber for BlockedSubscriber {
+    async fn handle_event(&self, _event: NodeEvent) {
+        self.release.notified().await;
+        self.handled.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn name(&self) -> &str {
+        "blocked"
+    }
+}
+
+#[tokio::test]
+async fn test_events_are_routed_by_kind() {
+    let event_bus = EventBus::new();
+    let registrations = Arc::new(CountingSubscriber {
+        kinds: Some(&[EventKind::AgentRegistered]),
+        ..Default::default()
+    });
+    let everything = Arc::new(CountingSubscriber::default());
+    event_bus.subscribe(registrations.clone()).await;
+    event_bus.subscribe(everything.clone()).await;
+
+    event_bus
+        .publish_agent_registered("did:example:alice".to_string())
+        .await;
+    event_bus
+        .publish_did_resolved("did:example:alice".to_string(), true)
+        .await;
+    event_bus
+        .publish_agent_unregistered("did:example:alice".to_string())
+        .await;
+
+    assert_eq!(registrations.count.load(Ordering::SeqCst), 1);
+    assert_eq!(everything.count.load(Ordering::SeqCst), 3);
+
+    let metrics = event_bus.metrics().await;
+    assert_eq!(metrics.total_published(), 3);
+    assert_eq!(metrics.published[&EventKind::AgentRegistered], 1);
+    assert_eq!(metrics.subscribers.len(), 2);
+    assert_eq!(metrics.subscribers[0].delivered, 1);
+    assert_eq!(
+        metrics.subscribers[0].event_kinds,
+        Some(vec![EventKind::AgentRegistered])
+    );
+    assert_eq!(metrics.subscribers[1].delivered, 3);
+
+    // Unsubscribing removes the subscriber from every kind it was routed for
+    let registrations: Arc<dyn EventSubscriber> = registrations;
+    event_bus.unsubscribe(&registrations).await;
+    assert_eq!(event_bus.metrics().await.subscribers.len(), 1);
+}
+
+#[tokio::test]
+async fn test_blocked_isolated_subscriber_does_not_stall_publication() {
+    let event_bus = EventBus::new();
+    let blocked = Arc::new(BlockedSubscriber {
+        release: Notify::new(),
+        handled: AtomicUsize::new(0),
+    });
+    let inline = Arc::new(CountingSubscriber::default());
+    event_bus
+        .subscribe_with_policy(blocked.clone(), DeliveryPolicy::Isolated { capacity: 2 })
+        .await;
+    event_bus.subscribe(inline.clone()).await;
+
+    // The first event is taken by the blocked handler, two more fill its
+    // queue and the rest are dropped
+    let publish = async {
+        for i in 0..10 {
+            event_bus
+                .publish_agent_registered(format!("did:example:{}", i))
+                .await;
+            tokio::task::yield_now().await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(5), publish)
+        .await
+        .expect("publication stalled behind a blocked subscriber");
+    assert_eq!(inline.count.load(Ordering::SeqCst), 10);
+
+    let metrics = event_bus.metrics().await;
+    let blocked_metrics = metrics
+        .subscribers
+        .iter()
+        .find(|s| s.name == "blocked")
+        .unwrap();
+    assert_eq!(
+        blocked_metrics.policy,
+        DeliveryPolicy::Isolated { capacity: 2 }
+    );
+    assert_eq!(blocked_metrics.delivered, 0);
+    assert_eq!(blocked_metrics.queue_depth, 2);
+    assert_eq!(blocked_metrics.dropped, 7);
+    assert_eq!(metrics.total_dropped(), 7);
+
+    // Releasing the handler drains the queued events
+    for _ in 0..3 {
+        blocked.release.notify_one();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(blocked.handled.load(Ordering::SeqCst), 3);
+    assert_eq!(event_bus.metrics().await.subscribers[0].delivered, 3);
+}
+
+#[tokio::test]
+async fn test_channel_receivers_share_events() {
+    let event_bus = EventBus::new();
+    let mut first = event_bus.subscribe_channel();
+    let mut second = event_bus.subscribe_channel();
+
+    event_bus
+        .publish_agent_registered("did:example:alice".to_string())
+        .await;
+
+    let a = first.recv().await.unwrap();
+    let b = second.recv().await.unwrap();
+    assert!(Arc::ptr_eq(&a, &b));
+    assert_eq!(a.kind(), EventKind::AgentRegistered);
+
+    let metrics = event_bus.metrics().await;
+    assert_eq!(metrics.channel_receivers, 2);
+    assert_eq!(metrics.channel_backlog, 0);
+}
@@ -0,0 +1,282 @@
+//! Tests for forwarding messages for unknown recipients to an upstream node
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tap_agent::message_packing::KeyManagerPacking;
+use tap_agent::{PackOptions, Packable, TapAgent};
+use tap_msg::didcomm::PlainMessage;
+use tap_node::federation::FederationConfig;
+use tap_node::storage::{ForwardStatus, SourceType};
+use tap_node::{IngestOutcome, NodeConfig, RejectionCode, TapNode};
+use tempfile::TempDir;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+/// A request received by the upstream endpoint
+struct Forward {
+    headers: HashMap<String, String>,
+    body: Value,
+}
+
+/// Serve an upstream DIDComm endpoint accepting every message
+async fn upstream() -> (String, mpsc::UnboundedReceiver<Forward>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/didcomm", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let mut stream = BufReader::new(stream);
+                loop {
+                    let mut line = String::new();
+                    if stream.read_line(&mut line).await.unwrap_or(0) == 0 {
+                        return;
+                    }
+                    let mut headers = HashMap::new();
+                    loop {
+                        line.clear();
+                        stream.read_line(&mut line).await.unwrap();
+                        if line == "\r\n" {
+                            break;
+                        }
+                        if let Some((name, value)) = line.split_once(':') {
+                            headers.insert(name.to_lowercase(), value.trim().to_string());
+                        }
+                    }
+                    let content_length = headers["content-length"].parse().unwrap();
+                    let mut body = vec![0; content_length];
+                    stream.read_exact(&mut body).await.unwrap();
+                    tx.send(Forward {
+                        headers,
+                        body: serde_json::from_slice(&body).unwrap(),
+                    })
+                    .unwrap();
+                    stream
+                        .get_mut()
+                        .write_all(b"HTTP/1.1 202 Accepted\r\ncontent-length: 0\r\n\r\n")
+                        .await
+                        .unwrap();
+                }
+            });
+        }
+    });
+    (url, rx)
+}
+
+async fn regional_node(temp_dir: &TempDir, federation: FederationConfig) -> TapNode {
+    let mut node = TapNode::new(NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("regional.db")),
+        federation: Some(federation),
+        ..Default::default()
+    });
+    node.init_storage().await.unwrap();
+    node
+}
+
+/// A message from `sender` to `to`, signed by the sender
+async fn signed(sender: &TapAgent, sender_did: &str, to: &str) -> Value {
+    let message = PlainMessage::new(
+        uuid::Uuid::new_v4().to_string(),
+        "https://example.org/test".to_string(),
+        json!({"content": "Hello"}),
+        sender_did.to_string(),
+    )
+    .with_recipient(to);
+    let sender_kid = format!(
+        "{}#{}",
+        sender_did,
+        sender_did.strip_prefix("did:key:").unwrap()
+    );
+    let packed = message
+        .pack(
+            sender.key_manager().as_ref() as &dyn KeyManagerPacking,
+            PackOptions::new().with_sign(&sender_kid),
+        )
+        .await
+        .unwrap();
+    serde_json::from_str(&packed).unwrap()
+}
+
+#[tokio::test]
+async fn test_unknown_recipients_are_forwarded_upstream() {
+    let temp_dir = TempDir::new().unwrap();
+    let (url, mut forwards) = upstream().await;
+    let regional = regional_node(&temp_dir, FederationConfig::new(&url, "regional-eu")).await;
+    let (alice, alice_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (carol, carol_did) = TapAgent::from_ephemeral_key().await.unwrap();
+
+    let message = signed(&alice, &alice_did, &carol_did).await;
+    let outcome = regional.receive_message(message.clone()).await.unwrap();
+    assert!(matches!(
+        outcome,
+        IngestOutcome::Forwarded {
+            message_id: Some(_)
+        }
+    ));
+
+    // The envelope is passed through unchanged
+    let forward = tokio::time::timeout(Duration::from_secs(5), forwards.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(forward.body, message);
+    assert_eq!(forward.headers["tap-forwarded-by"], "regional-eu");
+    assert_eq!(
+        forward.headers["content-type"],
+        "application/didcomm-signed+json"
+    );
+
+    let records = regional.forwarded_messages(10, 0).await.unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].status, ForwardStatus::Forwarded);
+    assert_eq!(records[0].recipients, vec![carol_did.clone()]);
+    assert_eq!(records[0].from_did.as_deref(), Some(alice_did.as_str()));
+    assert_eq!(records[0].upstream_url, url);
+
+    // The message is not forwarded a second time
+    let outcome = regional.receive_message(message.clone()).await.unwrap();
+    match outcome {
+        IngestOutcome::Rejected { code, reason } => {
+            assert_eq!(code, RejectionCode::Invalid);
+            assert!(reason.contains("already forwarded"));
+        }
+        other => panic!("Unexpected outcome {:?}", other),
+    }
+    let records = regional.forwarded_messages(10, 0).await.unwrap();
+    assert_eq!(records[0].status, ForwardStatus::Refused);
+
+    // The upstream node verifies and processes it for its agent
+    let central = TapNode::new(NodeConfig::default());
+    central.register_agent(Arc::new(carol)).await.unwrap();
+    let outcome = central
+        .receive_forwarded_message(
+            forward.body,
+            SourceType::Https,
+            None,
+            vec!["regional-eu".to_string()],
+        )
+        .await
+        .unwrap();
+    assert!(matches!(outcome, IngestOutcome::Accepted { .. }));
+}
+
+#[tokio::test]
+async fn test_messages_for_registered_agents_are_not_forwarded() {
+    let temp_dir = TempDir::new().unwrap();
+    let (url, mut forwards) = upstream().await;
+    let regional = regional_node(&temp_dir, FederationConfig::new(&url, "regional-eu")).await;
+    let (alice, alice_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (bob, bob_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    regional.register_agent(Arc::new(bob)).await.unwrap();
+
+    let outcome = regional
+        .receive_message(signed(&alice, &alice_did, &bob_did).await)
+        .await
+        .unwrap();
+    assert!(matches!(outcome, IngestOutcome::Accepted { .. }));
+    assert!(forwards.try_recv().is_err());
+    assert!(regional.forwarded_messages(10, 0).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_forwarding_loops_are_refused() {
+    let temp_dir = TempDir::new().unwrap();
+    let (url, mut forwards) = upstream().await;
+    let mut federation = FederationConfig::new(&url, "regional-eu");
+    federation.max_hops = 2;
+    let regional = regional_node(&temp_dir, federation).await;
+    let (alice, alice_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (_, carol_did) = TapAgent::from_ephemeral_key().await.unwrap();
+
+    // The message already passed through this node
+    let outcome = regional
+        .receive_forwarded_message(
+            signed(&alice, &alice_did, &carol_did).await,
+            SourceType::Https,
+            None,
+            vec!["regional-eu".to_string(), "central".to_string()],
+        )
+        .await
+        .unwrap();
+    match outcome {
+        IngestOutcome::Rejected { code, reason } => {
+            assert_eq!(code, RejectionCode::Invalid);
+            assert!(reason.contains("Forwarding loop"));
+        }
+        other => panic!("Unexpected outcome {:?}", other),
+    }
+
+    // The message was forwarded as often as allowed
+    let outcome = regional
+        .receive_forwarded_message(
+            signed(&alice, &alice_did, &carol_did).await,
+            SourceType::Https,
+            None,
+            vec!["branch-1".to_string(), "branch-2".to_string()],
+        )
+        .await
+        .unwrap();
+    assert!(matches!(
+        outcome,
+        IngestOutcome::Rejected {
+            code: RejectionCode::Invalid,
+            ..
+        }
+    ));
+
+    assert!(forwards.try_recv().is_err());
+    let records = regional.forwarded_messages(10, 0).await.unwrap();
+    assert_eq!(records.len(), 2);
+    assert!(records
+        .iter()
+        .all(|record| record.status == ForwardStatus::Refused));
+    assert_eq!(records[0].forwarded_by, vec!["branch-1", "branch-2"]);
+}
+
+#[tokio::test]
+async fn test_unreachable_upstream_is_retryable() {
+    let temp_dir = TempDir::new().unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/didcomm", listener.local_addr().unwrap());
+    drop(listener);
+    let regional = regional_node(&temp_dir, FederationConfig::new(url, "regional-eu")).await;
+    let (alice, alice_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (_, carol_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let message = signed(&alice, &alice_did, &carol_did).await;
+
+    // A failed forward does not count as forwarded, so retries are attempted
+    for _ in 0..2 {
+        match regional.receive_message(message.clone()).await.unwrap() {
+            IngestOutcome::Rejected { code, .. } => {
+                assert_eq!(code, RejectionCode::UpstreamUnavailable);
+                assert!(code.is_retryable());
+            }
+            other => panic!("Unexpected outcome {:?}", other),
+        }
+    }
+    let records = regional.forwarded_messages(10, 0).await.unwrap();
+    assert_eq!(records.len(), 2);
+    assert!(records
+        .iter()
+        .all(|record| record.status == ForwardStatus::Failed && record.error.is_some()));
+}
+
+#[tokio::test]
+async fn test_invalid_upstream_fails_setup() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut node = TapNode::new(NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("regional.db")),
+        federation: Some(FederationConfig::new("not a url", "regional-eu")),
+        ..Default::default()
+    });
+    let err = node.init_storage().await.unwrap_err();
+    assert!(err.to_string().contains("Invalid upstream URL"));
+    assert!(node.storage().is_none());
+}
@@ -0,0 +1,128 @@
+//! Tests for the envelope statistics of counterparties
+
+mod common;
+
+use common::transfer;
+use serde_json::Value;
+use std::sync::Arc;
+use tap_agent::message::SecurityMode;
+use tap_agent::message_packing::{PackOptions, Packable};
+use tap_agent::{Jws, TapAgent};
+use tap_node::interop::{EnvelopeSerialization, UNKNOWN_COUNTERPARTY};
+use tap_node::message::EnvelopeKind;
+use tap_node::{NodeConfig, TapNode};
+use tempfile::TempDir;
+
+#[tokio::test]
+async fn test_envelope_profiles_are_counted_per_counterparty() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut node = TapNode::new(NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("node.db")),
+        ..Default::default()
+    });
+    node.init_storage().await.unwrap();
+    let (bob, bob_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(bob)).await.unwrap();
+    let (alice, alice_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let alice_kid = alice.get_signing_kid().await.unwrap();
+
+    // The same counterparty signs one message in JSON and two compactly
+    let mut compact_messages = Vec::new();
+    for id in ["msg-1", "msg-2", "msg-3"] {
+        let signed = transfer(id, &alice_did, &bob_did)
+            .pack(
+                &**alice.key_manager(),
+                PackOptions::new().with_sign(&alice_kid),
+            )
+            .await
+            .unwrap();
+        compact_messages.push(
+            serde_json::from_str::<Jws>(&signed)
+                .unwrap()
+                .to_compact()
+                .unwrap(),
+        );
+        if id == "msg-1" {
+            node.receive_message(serde_json::from_str(&signed).unwrap())
+                .await
+                .unwrap();
+        }
+    }
+    for compact in &compact_messages[1..] {
+        node.receive_message(Value::String(compact.clone()))
+            .await
+            .unwrap();
+    }
+
+    let stats = node.envelope_stats(Some(&alice_did)).await.unwrap();
+    assert_eq!(stats.len(), 2);
+    for profile in &stats {
+        assert_eq!(profile.profile.kind, EnvelopeKind::Signed);
+        assert_eq!(profile.profile.alg.as_deref(), Some("EdDSA"));
+        assert_eq!(
+            profile.profile.schema_version.as_deref(),
+            Some("https://tap.rsvp/schema/1.0")
+        );
+        assert_eq!(profile.failures, 0);
+    }
+    let compact = stats
+        .iter()
+        .find(|profile| profile.profile.serialization == EnvelopeSerialization::Compact)
+        .unwrap();
+    assert_eq!(compact.messages, 2);
+    let flattened = stats
+        .iter()
+        .find(|profile| profile.profile.serialization == EnvelopeSerialization::Flattened)
+        .unwrap();
+    assert_eq!(flattened.messages, 1);
+
+    // Encrypted messages no agent can decrypt are counted as failures with
+    // their algorithms
+    let (carol, carol_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let encrypted = transfer("msg-4", &alice_did, &carol_did)
+        .pack(
+            &**alice.key_manager(),
+            PackOptions {
+                security_mode: SecurityMode::AuthCrypt,
+                sender_kid: Some(alice_kid.clone()),
+                recipient_kid: Some(carol.get_signing_kid().await.unwrap()),
+                content_encryption: None,
+                compression: None,
+            },
+        )
+        .await
+        .unwrap();
+    let _ = node
+        .receive_message(serde_json::from_str(&encrypted).unwrap())
+        .await;
+
+    let stats = node.envelope_stats(Some(&alice_did)).await.unwrap();
+    let encrypted = stats
+        .iter()
+        .find(|profile| profile.profile.kind == EnvelopeKind::Encrypted)
+        .unwrap();
+    assert_eq!(encrypted.messages, 1);
+    assert_eq!(encrypted.failures, 1);
+    assert!(encrypted.profile.enc.is_some());
+    assert_eq!(encrypted.profile.schema_version, None);
+    assert!(encrypted
+        .last_error
+        .as_deref()
+        .unwrap()
+        .contains("No agent could process the encrypted message"));
+
+    // Envelopes without a sender are counted for an unknown counterparty
+    let _ = node
+        .receive_message(serde_json::json!({"id": "msg-5", "body": {}}))
+        .await;
+    let unknown = node
+        .envelope_stats(Some(UNKNOWN_COUNTERPARTY))
+        .await
+        .unwrap();
+    assert_eq!(unknown.len(), 1);
+    assert_eq!(unknown[0].profile.kind, EnvelopeKind::Plain);
+    assert_eq!(unknown[0].failures, 1);
+
+    assert_eq!(node.envelope_stats(None).await.unwrap().len(), 4);
+}
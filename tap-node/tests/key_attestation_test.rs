@@ -0,0 +1,206 @@
+//! Tests for verifying the key attestations sent with incoming messages
+
+mod common;
+
+use common::{transfer_body, transfer_message};
+use std::sync::Arc;
+use tap_agent::agent::Agent;
+use tap_agent::attestation::KEY_ATTESTATION_HEADER;
+use tap_agent::TapAgent;
+use tap_msg::message::Transfer;
+use tap_node::credentials::KeyAttestationVerification;
+use tap_node::storage::KeyAttestationStatus;
+use tap_node::{IngestOutcome, NodeConfig, RejectionCode, TapNode};
+use tempfile::TempDir;
+
+/// A Transfer whose transaction ID is `id`
+fn transfer_with_id(id: &str) -> Transfer {
+    Transfer {
+        transaction_id: Some(id.to_string()),
+        ..transfer_body("did:example:alice", "did:example:bob", "10")
+    }
+}
+
+fn transfer(
+    id: &str,
+    sender: &str,
+    recipient: &str,
+    attestation: Option<serde_json::Value>,
+) -> serde_json::Value {
+    let mut message = transfer_message(id, &transfer_with_id(id), sender, recipient);
+    if let Some(attestation) = attestation {
+        message
+            .extra_headers
+            .insert(KEY_ATTESTATION_HEADER.to_string(), attestation);
+    }
+    serde_json::to_value(&message).unwrap()
+}
+
+/// A Transfer signed by `sender`, carrying its configured key attestation
+async fn signed_transfer(sender: &TapAgent, id: &str, recipient: &str) -> serde_json::Value {
+    let (packed, _) = sender
+        .send_message(&transfer_with_id(id), vec![recipient], false)
+        .await
+        .unwrap();
+    serde_json::from_str(&packed).unwrap()
+}
+
+async fn setup(reject_invalid: bool) -> (TempDir, TapNode, String) {
+    let temp_dir = TempDir::new().unwrap();
+    let config = NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("node.db")),
+        key_attestation_verification: Some(KeyAttestationVerification { reject_invalid }),
+        ..Default::default()
+    };
+    let mut node = TapNode::new(config);
+    node.init_storage().await.unwrap();
+
+    let (receiver, receiver_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(receiver)).await.unwrap();
+
+    (temp_dir, node, receiver_did)
+}
+
+#[tokio::test]
+async fn test_key_attestations_recorded() {
+    let (organization, organization_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (_, sender_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (_temp_dir, node, receiver_did) = setup(false).await;
+    let storage = node.storage().unwrap();
+
+    let attestation = organization
+        .attest_key(&sender_did, None, None)
+        .await
+        .unwrap();
+    let result = node
+        .receive_message(transfer(
+            "tx-attested",
+            &sender_did,
+            &receiver_did,
+            Some(attestation.clone()),
+        ))
+        .await
+        .unwrap();
+    assert!(result.is_accepted());
+    let record = storage
+        .get_key_attestation("tx-attested")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(record.status, KeyAttestationStatus::Verified);
+    assert_eq!(
+        record.organization.as_deref(),
+        Some(organization_did.as_str())
+    );
+    assert_eq!(record.agent_did, sender_did);
+
+    // An attestation sent by another agent is recorded as invalid, but only logged
+    let (_, impostor_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let result = node
+        .receive_message(transfer(
+            "tx-borrowed",
+            &impostor_did,
+            &receiver_did,
+            Some(attestation),
+        ))
+        .await
+        .unwrap();
+    assert!(result.is_accepted());
+    let record = storage
+        .get_key_attestation("tx-borrowed")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(record.status, KeyAttestationStatus::Invalid);
+    assert!(record.reason.unwrap().contains("not the sender"));
+
+    // Nothing is recorded for messages without an attestation
+    node.receive_message(transfer("tx-plain", &sender_did, &receiver_did, None))
+        .await
+        .unwrap();
+    assert!(storage
+        .get_key_attestation("tx-plain")
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        storage
+            .list_key_attestations(&sender_did, 10, 0)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_key_attestations_rejected() {
+    let (organization, _) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (_, sender_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (_temp_dir, node, receiver_did) = setup(true).await;
+
+    let expired = organization
+        .attest_key(&sender_did, None, Some(chrono::Utc::now().timestamp() - 60))
+        .await
+        .unwrap();
+    let result = node
+        .receive_message(transfer(
+            "tx-expired",
+            &sender_did,
+            &receiver_did,
+            Some(expired),
+        ))
+        .await;
+    match result {
+        Ok(IngestOutcome::Rejected {
+            code: RejectionCode::Invalid,
+            reason,
+        }) => assert!(reason.contains("Invalid key attestation"), "{}", reason),
+        other => panic!("Expected rejection, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_key_limited_attestations_require_the_signing_key() {
+    let (organization, _) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (mut sender, sender_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (_temp_dir, node, receiver_did) = setup(false).await;
+    let storage = node.storage().unwrap();
+    let signing_kid = sender.get_signing_kid().await.unwrap();
+
+    // Signed with the attested key
+    let attestation = organization
+        .attest_key(&sender_did, Some(&signing_kid), None)
+        .await
+        .unwrap();
+    sender.config = sender.config.clone().with_key_attestation(attestation);
+    node.receive_message(signed_transfer(&sender, "tx-signed", &receiver_did).await)
+        .await
+        .unwrap();
+    let records = storage
+        .list_key_attestations(&sender_did, 10, 0)
+        .await
+        .unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].status, KeyAttestationStatus::Verified);
+
+    // Signed with a key other than the attested one
+    let other_key = organization
+        .attest_key(&sender_did, Some(&format!("{}#other", sender_did)), None)
+        .await
+        .unwrap();
+    sender.config = sender.config.clone().with_key_attestation(other_key);
+    node.receive_message(signed_transfer(&sender, "tx-other-key", &receiver_did).await)
+        .await
+        .unwrap();
+    let records = storage
+        .list_key_attestations(&sender_did, 10, 0)
+        .await
+        .unwrap();
+    let record = records
+        .iter()
+        .find(|r| r.status == KeyAttestationStatus::Invalid)
+        .expect("invalid attestation recorded");
+    assert!(record.reason.as_deref().unwrap().contains("signed with"));
+}
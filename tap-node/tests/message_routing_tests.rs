@@ -7,7 +7,7 @@ use std::sync::Arc;
 use tap_agent::message_packing::KeyManagerPacking;
 use tap_agent::{PackOptions, Packable, TapAgent};
 use tap_msg::didcomm::PlainMessage;
-use tap_node::{NodeConfig, TapNode};
+use tap_node::{IngestOutcome, NodeConfig, RejectionCode, TapNode};
 
 #[tokio::test]
 async fn test_receive_plain_message() {
@@ -31,8 +31,13 @@ async fn test_receive_plain_message() {
     });
 
     // Node should process the message successfully
-    let result = node.receive_message(message_value).await;
-    assert!(result.is_ok());
+    let result = node.receive_message(message_value).await.unwrap();
+    assert_eq!(
+        result,
+        IngestOutcome::Accepted {
+            message_id: Some("test-plain-123".to_string())
+        }
+    );
 }
 
 #[tokio::test]
@@ -225,8 +230,14 @@ async fn test_receive_message_invalid_json() {
     // Invalid JSON structure
     let invalid_value = json!("not a message object");
 
-    let result = node.receive_message(invalid_value).await;
-    assert!(result.is_err());
+    let result = node.receive_message(invalid_value).await.unwrap();
+    assert!(matches!(
+        result,
+        IngestOutcome::Rejected {
+            code: RejectionCode::Malformed,
+            ..
+        }
+    ));
 }
 
 #[tokio::test]
@@ -335,3 +346,34 @@ async fn test_message_type_detection() {
         .to_string()
         .contains("No agent could process"));
 }
+
+#[tokio::test]
+async fn test_receive_message_rejects_hostile_envelopes() {
+    // Create node with an agent so envelopes reach the unpack path
+    let config = NodeConfig::default();
+    let node = Arc::new(TapNode::new(config));
+    let (agent, _) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(agent)).await.unwrap();
+
+    // Reuse the malformed envelopes from the tap-agent corpus
+    let corpus = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../tap-agent/tests/corpus/envelopes");
+    let mut entries: Vec<_> = std::fs::read_dir(corpus)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        // Entries that are not JSON never reach receive_message
+        let Ok(message) = serde_json::from_slice(&std::fs::read(&path).unwrap()) else {
+            continue;
+        };
+        let result = node.receive_message(message).await;
+        assert!(
+            !matches!(result, Ok(outcome) if outcome.is_accepted()),
+            "Corpus entry {} should be rejected",
+            path.display()
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! End-to-end tests of the onboarding handshake between two nodes
+
+use std::time::Duration;
+use tap_msg::message::{
+    Agent as MessageAgent, Authorize, Connect, Party, Policy, RequireAuthorization, TapMessageBody,
+    UpdatePolicies,
+};
+use tap_node::onboarding::OnboardingRequest;
+use tap_node::storage::{Connection, ConnectionStatus};
+use tap_node::testing::TestNode;
+use tap_node::NodeConfig;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Network {
+    requester: TestNode,
+    counterparty: TestNode,
+    requesting_vasp: String,
+    counterparty_vasp: String,
+}
+
+async fn network() -> Network {
+    let mut requester = TestNode::start("requester", NodeConfig::default())
+        .await
+        .unwrap();
+    let config = NodeConfig {
+        answer_handshakes: true,
+        ..Default::default()
+    };
+    let mut counterparty = TestNode::start("counterparty", config).await.unwrap();
+    let requesting_vasp = requester.add_agent().await.unwrap();
+    let counterparty_vasp = counterparty.add_agent().await.unwrap();
+    requester.connect(&counterparty);
+
+    Network {
+        requester,
+        counterparty,
+        requesting_vasp,
+        counterparty_vasp,
+    }
+}
+
+fn connect(agent_did: &str) -> Connect {
+    Connect::new_v2(
+        Party::new("did:example:merchant"),
+        Party::new("did:example:merchant"),
+        vec![MessageAgent::new(
+            agent_did,
+            "merchant_vasp",
+            "did:example:merchant",
+        )],
+        serde_json::from_value(serde_json::json!({"purposes": ["BEXP"]})).unwrap(),
+    )
+}
+
+fn require_authorization(purpose: &str) -> Policy {
+    Policy::RequireAuthorization(RequireAuthorization {
+        from: None,
+        from_role: None,
+        from_agent: None,
+        purpose: Some(purpose.to_string()),
+    })
+}
+
+/// Wait for the Connect request to reach a node
+async fn received_connection(node: &TestNode) -> Connection {
+    let storage = node.node().storage().unwrap();
+    tokio::time::timeout(TIMEOUT, async {
+        loop {
+            if let Some(connection) = storage.list_connections(1, 0).await.unwrap().pop() {
+                return connection;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("Connect request was not received")
+}
+
+#[tokio::test]
+async fn test_onboarding_handshake_builds_profile() {
+    let network = network().await;
+    let request = OnboardingRequest::new(
+        &network.requesting_vasp,
+        &network.counterparty_vasp,
+        connect(&network.requesting_vasp),
+    )
+    .with_policies(vec![require_authorization("Onboarding")]);
+    let node = network.requester.node().clone();
+    let onboarding = tokio::spawn(async move { node.onboard_counterparty(request).await });
+
+    // The counterparty's agent approves the connection and sends its policies
+    let connection = received_connection(&network.counterparty).await;
+    assert_eq!(connection.from_did, network.requesting_vasp);
+    let counterparty = network.counterparty.node();
+    let policies = UpdatePolicies::new(
+        &connection.reference_id,
+        vec![require_authorization("Travel rule")],
+    )
+    .to_didcomm_with_route(
+        &network.counterparty_vasp,
+        [network.requesting_vasp.as_str()],
+    )
+    .unwrap();
+    counterparty
+        .send_message(network.counterparty_vasp.clone(), policies)
+        .await
+        .unwrap();
+    let authorize = Authorize {
+        transaction_id: connection.reference_id.clone(),
+        settlement_address: None,
+        expiry: None,
+    }
+    .to_didcomm_with_route(
+        &network.counterparty_vasp,
+        [network.requesting_vasp.as_str()],
+    )
+    .unwrap();
+    counterparty
+        .send_message(network.counterparty_vasp.clone(), authorize)
+        .await
+        .unwrap();
+
+    let profile = onboarding.await.unwrap().unwrap();
+    assert_eq!(profile.counterparty_did, network.counterparty_vasp);
+    assert_eq!(profile.connection_id, connection.reference_id);
+    assert_eq!(profile.connection_status, ConnectionStatus::Authorized);
+    assert_eq!(
+        profile.supports_protocol("https://tap.rsvp/schema/1.0"),
+        Some(true)
+    );
+    assert_eq!(
+        profile.our_policies,
+        vec![require_authorization("Onboarding")]
+    );
+    assert_eq!(
+        profile.their_policies,
+        vec![require_authorization("Travel rule")]
+    );
+    assert!(profile.endpoint_verified);
+    assert!(profile.ping_rtt_ms.is_some());
+
+    // The profile is stored for our agent, and the answer with the connection
+    let stored = network
+        .requester
+        .node()
+        .get_counterparty_profile(&network.requesting_vasp, &network.counterparty_vasp)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.connection_status, ConnectionStatus::Authorized);
+    let tracked = network
+        .requester
+        .node()
+        .storage()
+        .unwrap()
+        .get_connection(&connection.reference_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(tracked.status, ConnectionStatus::Authorized);
+}
+
+#[tokio::test]
+async fn test_unanswered_connect_is_recorded_as_pending() {
+    let network = network().await;
+    let request = OnboardingRequest::new(
+        &network.requesting_vasp,
+        &network.counterparty_vasp,
+        connect(&network.requesting_vasp),
+    )
+    .with_step_timeout(Duration::from_millis(500));
+
+    let profile = network
+        .requester
+        .node()
+        .onboard_counterparty(request)
+        .await
+        .unwrap();
+    assert_eq!(profile.connection_status, ConnectionStatus::Pending);
+    assert!(profile.authorization_url.is_none());
+    assert!(profile.our_policies.is_empty());
+    assert!(profile.their_policies.is_empty());
+    // The counterparty's node still answered the queries and the ping
+    assert_eq!(
+        profile.supports_protocol("https://tap.rsvp/schema/1.0"),
+        Some(true)
+    );
+    assert!(profile.endpoint_verified);
+}
@@ -0,0 +1,270 @@
+//! Tests for listing and completing the actions awaiting a person's approval
+
+use serde_json::json;
+use std::sync::Arc;
+use tap_agent::TapAgent;
+use tap_caip::{AssetId, ChainId};
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::tap_message_trait::TapMessageBody;
+use tap_msg::message::{Agent, Party, Transfer};
+use tap_node::mailbox::{MailboxConfig, MailboxRecipient};
+use tap_node::pending_actions::{PendingActionFilter, PendingActionKind, PendingActionResponse};
+use tap_node::storage::{DecisionStatus, DecisionType, MessageDirection, ReviewStatus, Storage};
+use tap_node::timeouts::ProcessingStage;
+use tap_node::{NodeConfig, TapNode};
+use tempfile::TempDir;
+
+async fn setup() -> (TempDir, TapNode, Arc<Storage>, String, String) {
+    // A counterparty on another node, whose messages the node holds
+    let (_, counterparty_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    let config = NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("node.db")),
+        mailbox: MailboxConfig {
+            recipients: vec![MailboxRecipient::new(&counterparty_did, "token")],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut node = TapNode::new(config);
+    node.init_storage().await.unwrap();
+
+    let (agent, agent_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(agent)).await.unwrap();
+    let storage = node.storage().unwrap().clone();
+
+    (temp_dir, node, storage, agent_did, counterparty_did)
+}
+
+/// Store a Transfer the counterparty sent to our agent
+async fn receive_transfer(storage: &Storage, id: &str, counterparty_did: &str, agent_did: &str) {
+    let transfer = Transfer {
+        asset: AssetId::new(
+            ChainId::new("eip155", "1").unwrap(),
+            "erc20",
+            "0x6b175474e89094c44da98b954eedeac495271d0f",
+        )
+        .unwrap(),
+        originator: Some(Party::new("did:example:originator")),
+        beneficiary: Some(Party::new("did:example:beneficiary")),
+        amount: "100".to_string(),
+        agents: vec![
+            Agent::new(
+                counterparty_did,
+                "originator_vasp",
+                "did:example:originator",
+            ),
+            Agent::new(agent_did, "beneficiary_vasp", "did:example:beneficiary"),
+        ],
+        memo: None,
+        settlement_id: None,
+        expiry: None,
+        transaction_value: None,
+        transaction_id: None,
+        connection_id: None,
+        metadata: Default::default(),
+    };
+    let mut message = transfer.to_didcomm(counterparty_did).unwrap();
+    message.id = id.to_string();
+    storage.insert_transaction(&message).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_pending_actions_listed_and_completed() {
+    let (_temp_dir, node, storage, agent_did, counterparty_did) = setup().await;
+    receive_transfer(&storage, "tx-review", &counterparty_did, &agent_did).await;
+    receive_transfer(&storage, "tx-authorize", &counterparty_did, &agent_did).await;
+
+    let review_id = storage
+        .insert_review_item(
+            "tx-review",
+            &agent_did,
+            "large-transfer",
+            "Amount exceeds 50",
+            &json!({}),
+        )
+        .await
+        .unwrap();
+    // The review covers the authorization decision of its transaction
+    for transaction_id in ["tx-review", "tx-authorize"] {
+        storage
+            .insert_decision(
+                transaction_id,
+                &agent_did,
+                DecisionType::AuthorizationRequired,
+                &json!({ "transaction_id": transaction_id }),
+            )
+            .await
+            .unwrap();
+    }
+    let raw = json!({
+        "id": "stuck",
+        "type": "https://tap.rsvp/schema/1.0#Transfer",
+        "from": counterparty_did,
+        "to": [agent_did],
+        "body": {}
+    });
+    let dead_letter_id = storage
+        .insert_dead_letter(
+            Some("stuck"),
+            &raw.to_string(),
+            ProcessingStage::Validation,
+            "validation timed out after 50ms",
+        )
+        .await
+        .unwrap();
+
+    let actions = node
+        .list_pending_actions(&PendingActionFilter::default(), None)
+        .await
+        .unwrap();
+    let kinds: Vec<_> = actions.iter().map(|a| a.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            PendingActionKind::ManualReview,
+            PendingActionKind::Authorization,
+            PendingActionKind::QuarantinedMessage,
+        ]
+    );
+    assert_eq!(actions[0].id, format!("manual_review:{}", review_id));
+    assert_eq!(actions[1].transaction_id.as_deref(), Some("tx-authorize"));
+    assert_eq!(actions[2].agent_did.as_deref(), Some(agent_did.as_str()));
+
+    let filter = PendingActionFilter {
+        agent_did: Some("did:example:other".to_string()),
+        ..Default::default()
+    };
+    assert!(node
+        .list_pending_actions(&filter, None)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Approving the review authorizes its transaction
+    let response = PendingActionResponse::new("alice").with_note("Checked");
+    let outcome = node
+        .approve_pending_action(&actions[0].id, &response)
+        .await
+        .unwrap()
+        .unwrap();
+    let item = storage.get_review_item(review_id).await.unwrap().unwrap();
+    assert_eq!(item.status, ReviewStatus::Approved);
+    assert_eq!(item.reviewer.as_deref(), Some("alice"));
+    assert_eq!(item.message_id, outcome.message_id);
+    assert!(node
+        .approve_pending_action(&actions[0].id, &response)
+        .await
+        .unwrap()
+        .is_none());
+
+    // Rejecting the authorization resolves its decision
+    let outcome = node
+        .reject_pending_action(&actions[1].id, &PendingActionResponse::new("alice"))
+        .await
+        .unwrap()
+        .unwrap();
+    let decisions = storage
+        .list_decisions(Some(&agent_did), Some(DecisionStatus::Resolved), None, 10)
+        .await
+        .unwrap();
+    assert_eq!(decisions.len(), 2);
+    let rejected = &decisions[1];
+    assert_eq!(rejected.resolution.as_deref(), Some("reject"));
+    assert_eq!(
+        rejected.resolution_detail.as_ref().unwrap()["message_id"],
+        json!(outcome.message_id)
+    );
+
+    // Authorize and Reject are held for the counterparty
+    let held = node.mailbox_summary(&counterparty_did).await.unwrap();
+    assert_eq!(held.message_count, 2);
+
+    // Rejecting a quarantined message discards it
+    let outcome = node
+        .reject_pending_action(
+            &format!("quarantined_message:{}", dead_letter_id),
+            &PendingActionResponse::new("alice"),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(outcome.message_id.is_none());
+    assert!(storage.list_dead_letters(10, 0).await.unwrap().is_empty());
+
+    assert!(node
+        .list_pending_actions(&PendingActionFilter::default(), None)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(node
+        .approve_pending_action("unknown:1", &response)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_relationship_confirmation() {
+    let (_temp_dir, node, storage, agent_did, counterparty_did) = setup().await;
+    receive_transfer(&storage, "tx-relationship", &counterparty_did, &agent_did).await;
+
+    let update_policies = PlainMessage::new(
+        "update-policies".to_string(),
+        "https://tap.rsvp/schema/1.0#UpdatePolicies".to_string(),
+        json!({
+            "transactionId": "tx-relationship",
+            "policies": [{
+                "@type": "RequireRelationshipConfirmation",
+                "fromRole": "BeneficiaryVASP"
+            }]
+        }),
+        counterparty_did.clone(),
+    )
+    .with_recipient(&agent_did)
+    .with_thread_id(Some("tx-relationship".to_string()));
+    storage
+        .log_message(&update_policies, MessageDirection::Incoming)
+        .await
+        .unwrap();
+    let decision_id = storage
+        .insert_decision(
+            "tx-relationship",
+            &agent_did,
+            DecisionType::PolicySatisfactionRequired,
+            &json!({ "requested_by": counterparty_did }),
+        )
+        .await
+        .unwrap();
+
+    let filter = PendingActionFilter {
+        kinds: vec![PendingActionKind::RelationshipConfirmation],
+        ..Default::default()
+    };
+    let actions = node.list_pending_actions(&filter, None).await.unwrap();
+    assert_eq!(actions.len(), 1);
+    assert_eq!(
+        actions[0].id,
+        format!("relationship_confirmation:{}", decision_id)
+    );
+    assert_eq!(actions[0].details["requested_by"], json!(counterparty_did));
+
+    let outcome = node
+        .approve_pending_action(&actions[0].id, &PendingActionResponse::new("alice"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(outcome.message_id.is_some());
+    let decision = storage
+        .get_decision_by_id(decision_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(decision.status, DecisionStatus::Resolved);
+    assert_eq!(decision.resolution.as_deref(), Some("confirm_relationship"));
+    assert!(node
+        .list_pending_actions(&filter, None)
+        .await
+        .unwrap()
+        .is_empty());
+}
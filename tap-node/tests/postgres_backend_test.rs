@@ -0,0 +1,448 @@
+//! Tests for the Postgres storage backend
+//!
+//! They run against the database named by `TAP_TEST_POSTGRES_URL` and are
+//! skipped when it is not set, e.g.
+//! `TAP_TEST_POSTGRES_URL=postgres://tap@localhost/tap_test cargo test -p tap-node --features postgres --test postgres_backend_test`
+
+mod common;
+
+use chrono::{TimeZone, Utc};
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use std::sync::Arc;
+use tap_agent::TapAgent;
+use tap_msg::message::tap_message_trait::TapMessageBody;
+use tap_node::clock::{Clock, MockClock};
+use tap_node::storage::postgres::PostgresMigration;
+use tap_node::storage::{
+    DeliveryStatus, DeliveryType, MessageDirection, PostgresBackend, Storage, StorageBackend,
+    StorageError,
+};
+use tap_node::{NodeConfig, TapNode};
+use tempfile::TempDir;
+
+/// Pool on the test database, None to skip the test
+async fn test_pool() -> Option<PgPool> {
+    let Ok(url) = std::env::var("TAP_TEST_POSTGRES_URL") else {
+        eprintln!("TAP_TEST_POSTGRES_URL is not set, skipping");
+        return None;
+    };
+    Some(
+        PgPoolOptions::new()
+            .max_connections(4)
+            .connect(&url)
+            .await
+            .expect("connect to TAP_TEST_POSTGRES_URL"),
+    )
+}
+
+/// A DID no other test run uses
+fn unique_did(name: &str) -> String {
+    format!("did:example:{}-{}", name, uuid::Uuid::new_v4())
+}
+
+/// Drop the schema of an agent and its registration
+async fn drop_agent_schema(pool: &PgPool, agent_did: &str) {
+    let schema = PostgresMigration::schema_name(agent_did);
+    sqlx::query(&format!("DROP SCHEMA IF EXISTS \"{}\" CASCADE", schema))
+        .execute(pool)
+        .await
+        .unwrap();
+    sqlx::query("DELETE FROM public.tap_agent_schemas WHERE agent_did = $1")
+        .bind(agent_did)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_backend_round_trip() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let agent_did = unique_did("round-trip");
+    let clock = Arc::new(MockClock::new(
+        Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap(),
+    ));
+    let backend = PostgresBackend::new(pool.clone()).for_agent(&agent_did, clock.clone());
+
+    let message = common::transfer("pg-transfer-1", &agent_did, "did:key:bob");
+    backend
+        .log_message(&message, MessageDirection::Outgoing)
+        .await
+        .unwrap();
+    // Logging a message twice is not an error
+    backend
+        .log_message(&message, MessageDirection::Outgoing)
+        .await
+        .unwrap();
+    backend.insert_transaction(&message).await.unwrap();
+    assert!(matches!(
+        backend.insert_transaction(&message).await,
+        Err(StorageError::DuplicateTransaction(_))
+    ));
+
+    clock.advance(chrono::Duration::minutes(5));
+    backend
+        .update_transaction_status("pg-transfer-1", "failed")
+        .await
+        .unwrap();
+    backend
+        .record_rejection("pg-transfer-1", Some("compliance"), Some("Sanctioned"))
+        .await
+        .unwrap();
+
+    let logged = backend
+        .get_message_by_id("pg-transfer-1")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(logged.direction, MessageDirection::Outgoing);
+    assert_eq!(logged.message_json["body"]["amount"], "10");
+    assert_eq!(logged.created_at, "2026-03-01T12:00:00Z");
+    assert_eq!(
+        backend
+            .list_messages(10, 0, Some(MessageDirection::Incoming))
+            .await
+            .unwrap()
+            .len(),
+        0
+    );
+
+    let transaction = backend
+        .get_transaction_by_id("pg-transfer-1")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(transaction.status.to_string(), "failed");
+    assert_eq!(transaction.rejection_code.as_deref(), Some("compliance"));
+    assert_eq!(transaction.rejection_reason.as_deref(), Some("Sanctioned"));
+    assert!(transaction.canonical_hash.is_some());
+    assert_eq!(transaction.created_at, "2026-03-01T12:00:00Z");
+    assert_eq!(transaction.updated_at, "2026-03-01T12:05:00Z");
+    assert_eq!(backend.list_transactions(10, 0).await.unwrap().len(), 1);
+
+    let first = backend
+        .create_delivery(
+            "pg-transfer-1",
+            "{}",
+            "did:key:bob",
+            Some("https://bob.example/didcomm"),
+            DeliveryType::Https,
+        )
+        .await
+        .unwrap();
+    let second = backend
+        .create_delivery(
+            "pg-transfer-1",
+            "{}",
+            "did:key:carol",
+            None,
+            DeliveryType::Internal,
+        )
+        .await
+        .unwrap();
+    assert!(second > first);
+    backend
+        .update_delivery_status(
+            first,
+            DeliveryStatus::Failed,
+            Some(503),
+            Some("Unavailable"),
+        )
+        .await
+        .unwrap();
+    backend.increment_delivery_retry_count(first).await.unwrap();
+    backend
+        .schedule_delivery_attempt(first, Some("2026-03-01T12:10:00Z"))
+        .await
+        .unwrap();
+    backend
+        .reassign_delivery(first, "https://bob.example/v2/didcomm")
+        .await
+        .unwrap();
+    backend
+        .update_delivery_status(second, DeliveryStatus::Success, None, None)
+        .await
+        .unwrap();
+
+    let deliveries = backend
+        .get_deliveries_for_message("pg-transfer-1")
+        .await
+        .unwrap();
+    assert_eq!(deliveries.len(), 2);
+    assert_eq!(deliveries[0].id, first);
+    assert_eq!(deliveries[0].status, DeliveryStatus::Failed);
+    assert_eq!(deliveries[0].retry_count, 1);
+    assert_eq!(deliveries[0].last_http_status_code, Some(503));
+    assert_eq!(
+        deliveries[0].next_attempt_at.as_deref(),
+        Some("2026-03-01T12:10:00Z")
+    );
+    assert_eq!(
+        deliveries[0].delivery_url.as_deref(),
+        Some("https://bob.example/v2/didcomm")
+    );
+    assert_eq!(deliveries[1].status, DeliveryStatus::Success);
+    assert_eq!(
+        deliveries[1].delivered_at.as_deref(),
+        Some("2026-03-01T12:05:00Z")
+    );
+
+    // The records are kept in the agent's schema, registered as a migration
+    // registers it
+    let schema: String =
+        sqlx::query_scalar("SELECT schema_name FROM public.tap_agent_schemas WHERE agent_did = $1")
+            .bind(&agent_did)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(schema, PostgresMigration::schema_name(&agent_did));
+
+    // Another agent does not see them
+    let other_did = unique_did("other");
+    let other = PostgresBackend::new(pool.clone()).for_agent(&other_did, clock);
+    assert!(other
+        .get_transaction_by_id("pg-transfer-1")
+        .await
+        .unwrap()
+        .is_none());
+
+    drop_agent_schema(&pool, &agent_did).await;
+    drop_agent_schema(&pool, &other_did).await;
+}
+
+#[tokio::test]
+async fn test_backend_reads_migrated_database() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let agent_did = unique_did("migrated");
+    let storage = Storage::new_in_memory().await.unwrap();
+    let message = common::transfer("pg-migrated-1", &agent_did, "did:key:bob");
+    storage
+        .log_message(&message, MessageDirection::Outgoing)
+        .await
+        .unwrap();
+    storage.insert_transaction(&message).await.unwrap();
+    storage
+        .update_transaction_status("pg-migrated-1", "confirmed")
+        .await
+        .unwrap();
+    let migrated_delivery = storage
+        .create_delivery(
+            "pg-migrated-1",
+            "{}",
+            "did:key:bob",
+            None,
+            DeliveryType::Internal,
+        )
+        .await
+        .unwrap();
+
+    let migration = PostgresMigration::new(pool.clone())
+        .migrate(&agent_did, &storage)
+        .await
+        .unwrap();
+    assert!(migration.verified);
+
+    let backend = PostgresBackend::new(pool.clone()).for_agent(&agent_did, storage.clock().clone());
+    let transaction = backend
+        .get_transaction_by_id("pg-migrated-1")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(transaction.status.to_string(), "confirmed");
+    assert_eq!(transaction.message_json["id"], "pg-migrated-1");
+    let logged = backend
+        .get_message_by_id("pg-migrated-1")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(logged.message_json["id"], "pg-migrated-1");
+    let deliveries = backend
+        .get_deliveries_for_message("pg-migrated-1")
+        .await
+        .unwrap();
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].id, migrated_delivery);
+
+    // New rows continue after the migrated ones, and the migrated rows keep
+    // their uniqueness
+    let delivery = backend
+        .create_delivery(
+            "pg-migrated-1",
+            "{}",
+            "did:key:carol",
+            None,
+            DeliveryType::Internal,
+        )
+        .await
+        .unwrap();
+    assert!(delivery > migrated_delivery);
+    assert!(matches!(
+        backend.insert_transaction(&message).await,
+        Err(StorageError::DuplicateTransaction(_))
+    ));
+    let next = common::transfer("pg-migrated-2", &agent_did, "did:key:bob");
+    backend.insert_transaction(&next).await.unwrap();
+    let next = backend
+        .get_transaction_by_id("pg-migrated-2")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(next.id > transaction.id);
+
+    drop_agent_schema(&pool, &agent_did).await;
+}
+
+#[tokio::test]
+async fn test_storage_runs_on_postgres() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let agent_did = unique_did("storage");
+    let backend = PostgresBackend::new(pool.clone());
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("agent.db");
+    let storage = Storage::new(Some(path.clone())).await.unwrap();
+    let clock = storage.clock().clone();
+    let storage = storage.with_backend(backend.for_agent(&agent_did, clock.clone()));
+
+    let message = common::transfer("pg-storage-1", &agent_did, "did:key:bob");
+    storage
+        .log_message(&message, MessageDirection::Outgoing)
+        .await
+        .unwrap();
+    storage.insert_transaction(&message).await.unwrap();
+    let delivery_id = storage
+        .create_delivery(
+            "pg-storage-1",
+            "{}",
+            "did:key:bob",
+            Some("https://bob.example/didcomm"),
+            DeliveryType::Https,
+        )
+        .await
+        .unwrap();
+    storage
+        .update_delivery_status(delivery_id, DeliveryStatus::Success, Some(200), None)
+        .await
+        .unwrap();
+
+    // Another handle on the agent's schema, e.g. of another node, reads
+    // what this one wrote
+    let shared = PostgresBackend::new(pool.clone()).for_agent(&agent_did, clock);
+    assert!(shared
+        .get_transaction_by_id("pg-storage-1")
+        .await
+        .unwrap()
+        .is_some());
+    let deliveries = shared
+        .get_deliveries_for_message("pg-storage-1")
+        .await
+        .unwrap();
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].id, delivery_id);
+    assert_eq!(deliveries[0].status, DeliveryStatus::Success);
+
+    // Writes of the other handle are read through the storage
+    let other = common::transfer("pg-storage-2", &agent_did, "did:key:carol");
+    shared.insert_transaction(&other).await.unwrap();
+    assert!(storage
+        .get_transaction_by_id("pg-storage-2")
+        .await
+        .unwrap()
+        .is_some());
+    assert_eq!(storage.list_transactions(10, 0).await.unwrap().len(), 2);
+
+    // The agent database keeps a copy of what the storage wrote only
+    let local = Storage::new(Some(path)).await.unwrap();
+    assert!(local
+        .get_transaction_by_id("pg-storage-1")
+        .await
+        .unwrap()
+        .is_some());
+    assert!(local
+        .get_transaction_by_id("pg-storage-2")
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        local
+            .get_delivery_by_id(delivery_id)
+            .await
+            .unwrap()
+            .unwrap()
+            .status,
+        DeliveryStatus::Success
+    );
+
+    drop_agent_schema(&pool, &agent_did).await;
+}
+
+#[tokio::test]
+async fn test_node_runs_on_postgres() {
+    let Some(pool) = test_pool().await else {
+        return;
+    };
+    let temp_dir = TempDir::new().unwrap();
+    let shared = Arc::new(PostgresBackend::new(pool.clone()));
+    let config = NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        shared_storage: Some(shared.clone()),
+        ..Default::default()
+    };
+    let mut node = TapNode::new(config);
+    node.set_storage(Storage::new_in_memory().await.unwrap())
+        .await
+        .unwrap();
+    assert_eq!(
+        node.capabilities().shared_storage.as_deref(),
+        Some("postgres")
+    );
+
+    let (sender_agent, sender_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (recipient_agent, recipient_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(sender_agent)).await.unwrap();
+    node.register_agent(Arc::new(recipient_agent))
+        .await
+        .unwrap();
+
+    let message = common::transfer_body(&sender_did, &recipient_did, "100.00")
+        .to_didcomm(&sender_did)
+        .unwrap();
+    let message_id = message.id.clone();
+    node.send_message(sender_did.clone(), message)
+        .await
+        .unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    // Each agent's records are in its own schema
+    let clock: Arc<dyn Clock> = Arc::new(MockClock::default());
+    for agent_did in [&sender_did, &recipient_did] {
+        let records = shared.for_agent(agent_did, clock.clone());
+        assert!(records
+            .get_message_by_id(&message_id)
+            .await
+            .unwrap()
+            .is_some());
+        let transaction = records
+            .get_transaction_by_id(&message_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(transaction.from_did.as_deref(), Some(sender_did.as_str()));
+    }
+    let deliveries = shared
+        .for_agent(&sender_did, clock)
+        .get_deliveries_for_message(&message_id)
+        .await
+        .unwrap();
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].recipient_did, recipient_did);
+    assert_eq!(deliveries[0].delivery_type, DeliveryType::Internal);
+    assert_eq!(deliveries[0].status, DeliveryStatus::Success);
+
+    drop_agent_schema(&pool, &sender_did).await;
+    drop_agent_schema(&pool, &recipient_did).await;
+}
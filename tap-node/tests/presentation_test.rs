@@ -0,0 +1,288 @@
+//! Tests for verifying the presentations of incoming Presentation messages
+
+use serde_json::json;
+use std::sync::Arc;
+use tap_agent::key_manager::KeyManager;
+use tap_agent::message::JwsProtected;
+use tap_agent::{PresentationChallenge, TapAgent};
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::{Presentation, RequestPresentation};
+use tap_node::credentials::{PresentationVerification, RevocationStatus};
+use tap_node::storage::PresentationVerificationStatus;
+use tap_node::{IngestOutcome, NodeConfig, RejectionCode, TapNode};
+use tempfile::TempDir;
+
+/// Sign `claims` as a compact JWT with the key of `signer`
+async fn sign(signer: &TapAgent, claims: serde_json::Value) -> String {
+    let kid = signer.get_signing_kid().await.unwrap();
+    let protected = JwsProtected {
+        typ: "JWT".to_string(),
+        alg: String::new(),
+        kid: kid.clone(),
+        zip: None,
+    };
+    let jws = signer
+        .key_manager()
+        .sign_jws(&kid, &serde_json::to_vec(&claims).unwrap(), Some(protected))
+        .await
+        .unwrap();
+    let jws: serde_json::Value = serde_json::from_str(&jws).unwrap();
+    format!(
+        "{}.{}.{}",
+        jws["protected"].as_str().unwrap(),
+        jws["payload"].as_str().unwrap(),
+        jws["signature"].as_str().unwrap()
+    )
+}
+
+/// A presentation by `holder` of a travel rule credential issued by `issuer`,
+/// answering `request`
+async fn present(
+    holder: &TapAgent,
+    holder_did: &str,
+    issuer: &TapAgent,
+    issuer_did: &str,
+    expires_in: i64,
+    request: &PresentationChallenge,
+) -> serde_json::Value {
+    let now = chrono::Utc::now().timestamp();
+    let credential = sign(
+        issuer,
+        json!({
+            "iss": issuer_did,
+            "sub": holder_did,
+            "exp": now + expires_in,
+            "vc": {
+                "type": ["VerifiableCredential", "TravelRuleCredential"],
+                "credentialSubject": {"id": holder_did, "beneficiary": {"naturalPerson": {}}},
+                "credentialStatus": {"type": "BitstringStatusListEntry", "statusListIndex": "7"}
+            }
+        }),
+    )
+    .await;
+    json!(
+        sign(
+            holder,
+            json!({
+                "iss": holder_did,
+                "exp": now + 3600,
+                "nonce": request.challenge,
+                "aud": request.domain,
+                "vp": {"type": ["VerifiablePresentation"], "verifiableCredential": [credential]}
+            }),
+        )
+        .await
+    )
+}
+
+fn presentation(
+    id: &str,
+    transaction_id: &str,
+    sender: &str,
+    recipient: &str,
+    presentation: serde_json::Value,
+) -> serde_json::Value {
+    let body = Presentation::new(
+        "challenge".to_string(),
+        vec![presentation],
+        Some(transaction_id.to_string()),
+    );
+    let message = PlainMessage::new(
+        id.to_string(),
+        "https://tap.rsvp/schema/1.0#Presentation".to_string(),
+        serde_json::to_value(&body).unwrap(),
+        sender.to_string(),
+    )
+    .with_recipient(recipient)
+    .with_thread_id(Some(transaction_id.to_string()));
+    serde_json::to_value(&message).unwrap()
+}
+
+/// Have the receiving agent ask `holder_did` for a presentation on a
+/// transaction
+async fn request(
+    node: &TapNode,
+    receiver_did: &str,
+    holder_did: &str,
+    transaction_id: &str,
+) -> PresentationChallenge {
+    let challenge = format!("challenge-{}", transaction_id);
+    let body = RequestPresentation {
+        transaction_id: transaction_id.to_string(),
+        presentation_definition: "https://tap.rsvp/presentation-definitions/ivms-101/eu/tfr"
+            .to_string(),
+        description: None,
+        challenge: challenge.clone(),
+        for_originator: Some(true),
+        for_beneficiary: None,
+        metadata: Default::default(),
+    };
+    let message = PlainMessage::new(
+        format!("request-{}", transaction_id),
+        "https://tap.rsvp/schema/1.0#RequestPresentation".to_string(),
+        serde_json::to_value(&body).unwrap(),
+        receiver_did.to_string(),
+    )
+    .with_recipient(holder_did)
+    .with_thread_id(Some(transaction_id.to_string()));
+    node.send_message(receiver_did.to_string(), message)
+        .await
+        .unwrap();
+
+    PresentationChallenge {
+        challenge,
+        domain: receiver_did.to_string(),
+    }
+}
+
+async fn setup(trusted_issuers: Vec<String>, reject_invalid: bool) -> (TempDir, TapNode, String) {
+    let temp_dir = TempDir::new().unwrap();
+    let config = NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("node.db")),
+        presentation_verification: Some(PresentationVerification {
+            trusted_issuers,
+            status_checker: None,
+            reject_invalid,
+        }),
+        ..Default::default()
+    };
+    let mut node = TapNode::new(config);
+    node.init_storage().await.unwrap();
+
+    let (receiver, receiver_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(receiver)).await.unwrap();
+
+    (temp_dir, node, receiver_did)
+}
+
+#[tokio::test]
+async fn test_presentations_recorded() {
+    let (issuer, issuer_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (holder, holder_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (_temp_dir, node, receiver_did) = setup(vec![], false).await;
+    let storage = node.storage().unwrap();
+    let holder = Arc::new(holder);
+    node.register_agent(holder.clone()).await.unwrap();
+    let asked = request(&node, &receiver_did, &holder_did, "tx-1").await;
+
+    let valid = present(&holder, &holder_did, &issuer, &issuer_did, 3600, &asked).await;
+    let result = node
+        .receive_message(presentation(
+            "p-valid",
+            "tx-1",
+            &holder_did,
+            &receiver_did,
+            valid,
+        ))
+        .await
+        .unwrap();
+    assert!(result.is_accepted());
+
+    // An expired credential is recorded as invalid, but only logged
+    let expired = present(&holder, &holder_did, &issuer, &issuer_did, -60, &asked).await;
+    let result = node
+        .receive_message(presentation(
+            "p-expired",
+            "tx-1",
+            &holder_did,
+            &receiver_did,
+            expired,
+        ))
+        .await
+        .unwrap();
+    assert!(result.is_accepted());
+
+    // A presentation signed for another request
+    let replayed = PresentationChallenge {
+        challenge: "challenge-tx-0".to_string(),
+        ..asked.clone()
+    };
+    let replayed = present(&holder, &holder_did, &issuer, &issuer_did, 3600, &replayed).await;
+    node.receive_message(presentation(
+        "p-replayed",
+        "tx-1",
+        &holder_did,
+        &receiver_did,
+        replayed,
+    ))
+    .await
+    .unwrap();
+
+    // A presentation nobody asked for
+    let unsolicited = present(&holder, &holder_did, &issuer, &issuer_did, 3600, &asked).await;
+    node.receive_message(presentation(
+        "p-unsolicited",
+        "tx-9",
+        &holder_did,
+        &receiver_did,
+        unsolicited,
+    ))
+    .await
+    .unwrap();
+
+    let records = storage
+        .list_presentation_verifications("tx-1")
+        .await
+        .unwrap();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0].message_id, "p-valid");
+    assert_eq!(records[0].status, PresentationVerificationStatus::Verified);
+    assert_eq!(records[0].holder.as_deref(), Some(holder_did.as_str()));
+    let credential = &records[0].credentials[0];
+    assert_eq!(credential.issuer, issuer_did);
+    assert!(credential
+        .types
+        .contains(&"TravelRuleCredential".to_string()));
+    assert_eq!(credential.revocation, RevocationStatus::Unchecked);
+    assert_eq!(records[1].status, PresentationVerificationStatus::Invalid);
+    assert!(records[1].reason.as_ref().unwrap().contains("expired"));
+    assert!(records[1].credentials.is_empty());
+    assert_eq!(records[2].status, PresentationVerificationStatus::Invalid);
+    assert!(records[2]
+        .reason
+        .as_ref()
+        .unwrap()
+        .contains("answers challenge challenge-tx-0"));
+
+    let records = storage
+        .list_presentation_verifications("tx-9")
+        .await
+        .unwrap();
+    assert_eq!(records[0].status, PresentationVerificationStatus::Invalid);
+    assert!(records[0]
+        .reason
+        .as_ref()
+        .unwrap()
+        .contains("No presentation was requested"));
+}
+
+#[tokio::test]
+async fn test_invalid_presentations_rejected() {
+    let (issuer, issuer_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (holder, holder_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (_temp_dir, node, receiver_did) =
+        setup(vec!["did:example:regulator".to_string()], true).await;
+
+    let holder = Arc::new(holder);
+    node.register_agent(holder.clone()).await.unwrap();
+    let asked = request(&node, &receiver_did, &holder_did, "tx-2").await;
+
+    let untrusted = present(&holder, &holder_did, &issuer, &issuer_did, 3600, &asked).await;
+    let result = node
+        .receive_message(presentation(
+            "p-untrusted",
+            "tx-2",
+            &holder_did,
+            &receiver_did,
+            untrusted,
+        ))
+        .await;
+    match result {
+        Ok(IngestOutcome::Rejected {
+            code: RejectionCode::Invalid,
+            reason,
+        }) => assert!(reason.contains("is not trusted"), "{}", reason),
+        other => panic!("Expected rejection, got {:?}", other),
+    }
+}
@@ -0,0 +1,105 @@
+//! Tests for DIDComm problem reports sent when incoming messages fail processing
+
+use std::sync::Arc;
+use std::time::Duration;
+use tap_agent::TapAgent;
+use tap_msg::didcomm::PlainMessage;
+use tap_node::{IngestOutcome, NodeConfig, NodeEvent, RejectionCode, TapNode};
+use tempfile::TempDir;
+
+async fn setup(problem_reports: bool) -> (TempDir, TapNode, String, String) {
+    let temp_dir = TempDir::new().unwrap();
+    let config = NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("node.db")),
+        problem_reports,
+        ..Default::default()
+    };
+    let mut node = TapNode::new(config);
+    node.init_storage().await.unwrap();
+
+    let (alice, alice_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (bob, bob_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(alice)).await.unwrap();
+    node.register_agent(Arc::new(bob)).await.unwrap();
+
+    (temp_dir, node, alice_did, bob_did)
+}
+
+/// An Authorize from alice to bob that expired an hour ago
+fn expired_message(alice_did: &str, bob_did: &str) -> serde_json::Value {
+    let now = chrono::Utc::now().timestamp() as u64;
+    let mut message = PlainMessage::new(
+        "expired-authorize".to_string(),
+        "https://tap.rsvp/schema/1.0#Authorize".to_string(),
+        serde_json::json!({"transaction_id": "tx-expired"}),
+        alice_did.to_string(),
+    )
+    .with_recipient(bob_did)
+    .with_thread_id(Some("tx-expired".to_string()));
+    message.expires_time = Some(now - 3600);
+    serde_json::to_value(&message).unwrap()
+}
+
+#[tokio::test]
+async fn test_rejected_message_is_reported_to_sender() {
+    let (_temp_dir, node, alice_did, bob_did) = setup(true).await;
+    let mut events = node.event_bus().subscribe_channel();
+
+    let result = node
+        .receive_message(expired_message(&alice_did, &bob_did))
+        .await;
+    assert!(matches!(
+        result,
+        Ok(IngestOutcome::Rejected {
+            code: RejectionCode::Invalid,
+            ..
+        })
+    ));
+
+    let event = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let event = events.recv().await.unwrap();
+            if let NodeEvent::ProblemReportReceived { .. } = event.as_ref() {
+                return event;
+            }
+        }
+    })
+    .await
+    .expect("No problem report received");
+
+    match event.as_ref() {
+        NodeEvent::ProblemReportReceived {
+            from,
+            code,
+            comment,
+            pthid,
+            ..
+        } => {
+            assert_eq!(from, &bob_did);
+            assert_eq!(code, "e.m.msg");
+            assert!(comment.as_deref().unwrap().contains("expired"));
+            assert_eq!(pthid.as_deref(), Some("tx-expired"));
+        }
+        _ => unreachable!(),
+    }
+}
+
+#[tokio::test]
+async fn test_problem_reports_disabled_by_default() {
+    let (_temp_dir, node, alice_did, bob_did) = setup(false).await;
+    let mut events = node.event_bus().subscribe_channel();
+
+    let result = node
+        .receive_message(expired_message(&alice_did, &bob_did))
+        .await
+        .unwrap();
+    assert!(!result.is_accepted());
+
+    while let Ok(event) = events.try_recv() {
+        assert!(
+            !matches!(event.as_ref(), NodeEvent::ProblemReportReceived { .. }),
+            "Unexpected problem report"
+        );
+    }
+}
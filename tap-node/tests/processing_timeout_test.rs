@@ -0,0 +1,152 @@
+//! Tests for cancelling message processing that exceeds its timeout
+
+mod common;
+
+use async_trait::async_trait;
+use common::{transfer_body, transfer_message};
+use std::sync::Arc;
+use std::time::Duration;
+use tap_agent::TapAgent;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::Transfer;
+use tap_node::message::{
+    EnrichmentContext, EnrichmentDecision, EnrichmentHook, EnrichmentPipeline, TransactionDraft,
+};
+use tap_node::timeouts::{ProcessingStage, ProcessingTimeouts};
+use tap_node::{NodeConfig, NodeEvent, TapNode};
+use tempfile::TempDir;
+
+/// A hook that never finishes in time, like one calling a hanging endpoint
+#[derive(Debug)]
+struct HangingHook;
+
+#[async_trait]
+impl EnrichmentHook for HangingHook {
+    fn name(&self) -> &str {
+        "hanging"
+    }
+
+    async fn enrich(
+        &self,
+        _ctx: &EnrichmentContext<'_>,
+        _draft: &mut TransactionDraft,
+    ) -> tap_node::Result<EnrichmentDecision> {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        Ok(EnrichmentDecision::Continue)
+    }
+}
+
+async fn setup(dead_letter: bool) -> (TempDir, TapNode, String) {
+    let temp_dir = TempDir::new().unwrap();
+    let config = NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("node.db")),
+        enrichment: Some(Arc::new(
+            EnrichmentPipeline::new().with_hook(Arc::new(HangingHook)),
+        )),
+        processing_timeouts: ProcessingTimeouts {
+            hooks: Some(Duration::from_millis(50)),
+            dead_letter,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut node = TapNode::new(config);
+    node.init_storage().await.unwrap();
+
+    let (alice, alice_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(alice)).await.unwrap();
+
+    (temp_dir, node, alice_did)
+}
+
+fn transfer(from: &str) -> PlainMessage {
+    let transfer = Transfer {
+        transaction_id: Some("tx-hanging".to_string()),
+        ..transfer_body("did:example:alice", "did:example:bob", "10")
+    };
+    transfer_message("tx-hanging", &transfer, from, "did:example:bob-vasp")
+}
+
+async fn timed_out_event(
+    events: &mut tokio::sync::broadcast::Receiver<Arc<NodeEvent>>,
+) -> Arc<NodeEvent> {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let event = events.recv().await.unwrap();
+            if let NodeEvent::MessageProcessingTimedOut { .. } = event.as_ref() {
+                return event;
+            }
+        }
+    })
+    .await
+    .expect("No timeout event published")
+}
+
+#[tokio::test]
+async fn test_hanging_hook_is_cancelled_and_dead_lettered() {
+    let (_temp_dir, node, alice_did) = setup(true).await;
+    let mut events = node.event_bus().subscribe_channel();
+
+    let started = std::time::Instant::now();
+    let result = node
+        .send_message(alice_did.clone(), transfer(&alice_did))
+        .await;
+    assert!(started.elapsed() < Duration::from_secs(5));
+    match result {
+        Err(tap_node::Error::ProcessingTimedOut { stage, timeout_ms }) => {
+            assert_eq!(stage, ProcessingStage::Hook);
+            assert_eq!(timeout_ms, 50);
+        }
+        other => panic!("Expected a timeout, got {:?}", other),
+    }
+
+    match timed_out_event(&mut events).await.as_ref() {
+        NodeEvent::MessageProcessingTimedOut {
+            message_id,
+            stage,
+            dead_lettered,
+            ..
+        } => {
+            assert_eq!(message_id.as_deref(), Some("tx-hanging"));
+            assert_eq!(*stage, ProcessingStage::Hook);
+            assert!(dead_lettered);
+        }
+        _ => unreachable!(),
+    }
+
+    let storage = node.storage().unwrap();
+    let dead_letters = storage.list_dead_letters(10, 0).await.unwrap();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].message_id.as_deref(), Some("tx-hanging"));
+    assert_eq!(dead_letters[0].stage, ProcessingStage::Hook);
+    let raw: PlainMessage = serde_json::from_str(&dead_letters[0].raw_message).unwrap();
+    assert_eq!(raw.id, "tx-hanging");
+
+    assert!(storage
+        .delete_dead_letter(dead_letters[0].id)
+        .await
+        .unwrap());
+    assert!(storage.list_dead_letters(10, 0).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_timed_out_message_not_dead_lettered_by_default() {
+    let (_temp_dir, node, alice_did) = setup(false).await;
+    let mut events = node.event_bus().subscribe_channel();
+
+    let result = node
+        .send_message(alice_did.clone(), transfer(&alice_did))
+        .await;
+    assert!(matches!(
+        result,
+        Err(tap_node::Error::ProcessingTimedOut { .. })
+    ));
+
+    match timed_out_event(&mut events).await.as_ref() {
+        NodeEvent::MessageProcessingTimedOut { dead_lettered, .. } => assert!(!dead_lettered),
+        _ => unreachable!(),
+    }
+    let storage = node.storage().unwrap();
+    assert!(storage.list_dead_letters(10, 0).await.unwrap().is_empty());
+}
@@ -40,7 +40,7 @@ fn create_test_message(
 #[tokio::test]
 async fn test_validation_processor_accepts_valid_messages() {
     // Create a validator
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a valid message with all required fields
     let message = create_test_message(
@@ -62,7 +62,7 @@ async fn test_validation_processor_accepts_valid_messages() {
 #[tokio::test]
 async fn test_validation_processor_rejects_empty_id() {
     // Create a validator
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a message with an empty ID
     let message = create_test_message(
@@ -84,7 +84,7 @@ async fn test_validation_processor_rejects_empty_id() {
 #[tokio::test]
 async fn test_validation_processor_rejects_empty_type() {
     // Create a validator
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a message with an empty type
     let message = create_test_message(
@@ -106,7 +106,7 @@ async fn test_validation_processor_rejects_empty_type() {
 #[tokio::test]
 async fn test_validation_processor_rejects_invalid_from_did() {
     // Create a validator
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a message with an invalid from DID
     let message = create_test_message(
@@ -128,7 +128,7 @@ async fn test_validation_processor_rejects_invalid_from_did() {
 #[tokio::test]
 async fn test_validation_processor_rejects_invalid_to_did() {
     // Create a validator
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a message with an invalid to DID
     let message = create_test_message(
@@ -150,7 +150,7 @@ async fn test_validation_processor_rejects_invalid_to_did() {
 #[tokio::test]
 async fn test_validation_processor_accepts_valid_did_formats() {
     // Create a validator
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a message with valid DID formats
     let message = create_test_message(
@@ -0,0 +1,96 @@
+//! Tests for the response deadlines of our agents
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tap_agent::TapAgent;
+use tap_msg::message::{Agent as MessageAgent, Party, TapMessageBody, Transfer};
+use tap_node::response_timers::ResponseTimerPolicy;
+use tap_node::storage::{ResponseTimer, ResponseTimerStatus};
+use tap_node::{NodeConfig, TapNode};
+use tempfile::TempDir;
+
+/// Wait until the agent has a timer in the given status
+async fn wait_for_timer(
+    node: &TapNode,
+    agent_did: &str,
+    status: ResponseTimerStatus,
+) -> ResponseTimer {
+    for _ in 0..100 {
+        let timers = node
+            .list_response_timers(Some(agent_did), Some(status), 10, 0)
+            .await
+            .unwrap();
+        if let Some(timer) = timers.into_iter().next() {
+            return timer;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("No {} response timer for {}", status, agent_did);
+}
+
+#[tokio::test]
+async fn test_timer_started_on_receipt_and_stopped_by_authorize() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut node = TapNode::new(NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("node.db")),
+        response_timers: Some(ResponseTimerPolicy::default()),
+        ..Default::default()
+    });
+    node.init_storage().await.unwrap();
+
+    let (originator, originator_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (beneficiary, beneficiary_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(originator)).await.unwrap();
+    node.register_agent(Arc::new(beneficiary)).await.unwrap();
+
+    let transfer = Transfer {
+        asset: "eip155:1/slip44:60".parse().unwrap(),
+        originator: Some(Party::new("did:example:alice")),
+        beneficiary: Some(Party::new("did:example:bob")),
+        amount: "1.25".to_string(),
+        agents: vec![
+            MessageAgent::new(&originator_did, "originating_vasp", "did:example:alice"),
+            MessageAgent::new(&beneficiary_did, "beneficiary_vasp", "did:example:bob"),
+        ],
+        memo: None,
+        settlement_id: None,
+        expiry: None,
+        transaction_value: None,
+        transaction_id: None,
+        connection_id: None,
+        metadata: HashMap::new(),
+    };
+    let message = transfer
+        .to_didcomm_with_route(&originator_did, [beneficiary_did.as_str()])
+        .unwrap();
+    let transaction_id = message.id.clone();
+    node.send_message(originator_did.clone(), message)
+        .await
+        .unwrap();
+
+    // The beneficiary's agent is timed on the Transfer it received, and the
+    // Authorize the node sends for it in the default decision mode stops it
+    let timer = wait_for_timer(&node, &beneficiary_did, ResponseTimerStatus::Answered).await;
+    assert_eq!(timer.message_id, transaction_id);
+    assert_eq!(timer.message_type, "Transfer");
+    assert_eq!(
+        timer.counterparty_did.as_deref(),
+        Some(originator_did.as_str())
+    );
+    assert_eq!(timer.response_type.as_deref(), Some("Authorize"));
+    assert!(!timer.breached);
+
+    // The originator's agent sent the Transfer and is not timed
+    assert!(node
+        .list_response_timers(Some(&originator_did), None, 10, 0)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let report = node.response_time_report(None).await.unwrap();
+    assert_eq!(report.len(), 1);
+    assert_eq!(report[0].message_type, "Transfer");
+    assert_eq!(report[0].answered, 1);
+}
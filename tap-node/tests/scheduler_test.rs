@@ -0,0 +1,98 @@
+//! Tests for the node's job scheduler
+
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+use tap_node::clock::MockClock;
+use tap_node::retention::{RetentionPolicy, RETENTION_JOB};
+use tap_node::scheduler::{JobTrigger, SchedulerConfig};
+use tap_node::storage::{ScheduledJobRecord, ScheduledJobStatus};
+use tap_node::{NodeConfig, TapNode};
+use tempfile::TempDir;
+
+async fn start_node(temp_dir: &TempDir, clock: &Arc<MockClock>, retention: bool) -> TapNode {
+    let mut node = TapNode::new(NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("node.db")),
+        clock: Some(clock.clone()),
+        retention_policy: retention.then(RetentionPolicy::default),
+        scheduler: SchedulerConfig {
+            request_poll_interval: Duration::from_millis(50),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    node.init_storage().await.unwrap();
+    node
+}
+
+/// Wait for the recorded state of a job to satisfy a condition
+async fn wait_for_job(
+    node: &TapNode,
+    name: &str,
+    condition: impl Fn(&ScheduledJobRecord) -> bool,
+) -> ScheduledJobRecord {
+    for _ in 0..100 {
+        let jobs = node.scheduled_jobs().await.unwrap();
+        if let Some(job) = jobs.into_iter().find(|job| job.name == name) {
+            if condition(&job) {
+                return job;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("Job {} did not reach the expected state", name);
+}
+
+fn at(s: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_jobs_run_on_request_and_resume_after_restart() {
+    let temp_dir = TempDir::new().unwrap();
+    let clock = Arc::new(MockClock::new(at("2026-03-01T10:00:00Z")));
+
+    // The retention purge runs as soon as the node starts
+    let node = start_node(&temp_dir, &clock, true).await;
+    let job = wait_for_job(&node, RETENTION_JOB, |job| job.last_run_at.is_some()).await;
+    assert_eq!(job.schedule, "@every 1h");
+    assert_eq!(job.last_status, Some(ScheduledJobStatus::Succeeded));
+    assert_eq!(
+        job.next_run_at.as_deref(),
+        Some("2026-03-01T11:00:00+00:00")
+    );
+
+    // A process without the job, like the CLI, requests a run from the node
+    clock.advance(ChronoDuration::minutes(10));
+    let other = start_node(&temp_dir, &clock, false).await;
+    assert!(matches!(
+        other.run_scheduled_job(RETENTION_JOB).await.unwrap(),
+        JobTrigger::Requested
+    ));
+    assert!(other.run_scheduled_job("no_such_job").await.is_err());
+    let job = wait_for_job(&node, RETENTION_JOB, |job| {
+        job.last_run_at.as_deref() == Some("2026-03-01T10:10:00+00:00")
+    })
+    .await;
+    assert!(job.run_requested_at.is_none());
+
+    // The node runs its own jobs directly
+    clock.advance(ChronoDuration::minutes(5));
+    let JobTrigger::Ran(run) = node.run_scheduled_job(RETENTION_JOB).await.unwrap() else {
+        panic!("The job should have run");
+    };
+    assert_eq!(run.status, ScheduledJobStatus::Succeeded);
+    assert_eq!(run.next_run_at, Some(at("2026-03-01T11:15:00Z")));
+    drop(node);
+
+    // After a restart, the job waits for the rest of its interval
+    clock.advance(ChronoDuration::minutes(15));
+    let node = start_node(&temp_dir, &clock, true).await;
+    let jobs = node.scheduler().jobs();
+    let job = jobs.iter().find(|job| job.name == RETENTION_JOB).unwrap();
+    assert_eq!(
+        job.next_run_at.as_deref(),
+        Some("2026-03-01T11:15:00+00:00")
+    );
+}
@@ -102,3 +102,203 @@ async fn test_http_message_sender_wasm_fallback() -> Result<()> {
 
     Ok(())
 }
+
+/// Serve the given raw HTTP responses in order, recording each request
+#[cfg(feature = "reqwest")]
+async fn scripted_server(
+    responses: Vec<String>,
+) -> (
+    String,
+    std::sync::Arc<std::sync::Mutex<Vec<(std::time::Instant, String)>>>,
+) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+
+    let recorded = requests.clone();
+    tokio::spawn(async move {
+        for response in responses {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_lowercase();
+                if let Some(header_end) = text.find("\r\n\r\n") {
+                    let content_length = text
+                        .lines()
+                        .find_map(|l| l.strip_prefix("content-length:"))
+                        .and_then(|v| v.trim().parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if request.len() >= header_end + 4 + content_length {
+                        break;
+                    }
+                }
+                if n == 0 {
+                    break;
+                }
+            }
+            recorded.lock().unwrap().push((
+                std::time::Instant::now(),
+                String::from_utf8_lossy(&request).to_string(),
+            ));
+            stream.write_all(response.as_bytes()).await.unwrap();
+            stream.shutdown().await.ok();
+        }
+    });
+
+    (base_url, requests)
+}
+
+#[cfg(feature = "reqwest")]
+fn http_response(status: &str, headers: &[&str], body: &str) -> String {
+    let mut response = format!("HTTP/1.1 {}\r\nConnection: close\r\n", status);
+    for header in headers {
+        response.push_str(header);
+        response.push_str("\r\n");
+    }
+    response.push_str(&format!("Content-Length: {}\r\n\r\n{}", body.len(), body));
+    response
+}
+
+#[cfg(feature = "reqwest")]
+#[tokio::test]
+async fn test_http_sender_honors_retry_after() -> Result<()> {
+    let (base_url, requests) = scripted_server(vec![
+        http_response("429 Too Many Requests", &["Retry-After: 1"], "slow down"),
+        http_response("200 OK", &[], "{}"),
+    ])
+    .await;
+
+    let sender = HttpPlainMessageSender::with_options(base_url, 5000, 2);
+    let packed = r#"{"id":"msg-retry","type":"https://tap.rsvp/schema/1.0#Transfer"}"#;
+    sender
+        .send(packed.to_string(), vec!["did:example:123".to_string()])
+        .await?;
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[1].0 - requests[0].0 >= std::time::Duration::from_millis(900));
+    for (_, request) in requests.iter() {
+        assert!(request
+            .to_lowercase()
+            .contains("idempotency-key: msg-retry\r\n"));
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "reqwest")]
+#[tokio::test]
+async fn test_http_sender_gives_up_on_long_retry_after() -> Result<()> {
+    let retry_after = format!(
+        "Retry-After: {}",
+        tap_node::message::sender::MAX_RETRY_AFTER.as_secs() + 1
+    );
+    let (base_url, requests) = scripted_server(vec![http_response(
+        "503 Service Unavailable",
+        &[&retry_after],
+        "maintenance",
+    )])
+    .await;
+
+    let sender = HttpPlainMessageSender::with_options(base_url, 5000, 3);
+    let result = sender
+        .send(
+            r#"{"id":"msg-unavailable"}"#.to_string(),
+            vec!["did:example:123".to_string()],
+        )
+        .await;
+
+    assert!(result.unwrap_err().to_string().contains("503"));
+    assert_eq!(requests.lock().unwrap().len(), 1);
+
+    Ok(())
+}
+
+#[cfg(all(feature = "reqwest", feature = "delivery-tracking"))]
+#[tokio::test]
+async fn test_tracking_sender_records_duplicates_and_failures() -> Result<()> {
+    use std::sync::Arc;
+    use tap_node::storage::models::{DeliveryStatus, MessageDirection};
+    use tap_node::{HttpPlainMessageSenderWithTracking, Storage};
+
+    let storage = Arc::new(Storage::new_in_memory().await.unwrap());
+    let long_body = "x".repeat(2000);
+    let (base_url, _requests) = scripted_server(vec![
+        http_response("409 Conflict", &[], "duplicate"),
+        http_response("422 Unprocessable Entity", &[], &long_body),
+    ])
+    .await;
+    let sender =
+        HttpPlainMessageSenderWithTracking::with_options(base_url, 5000, 1, storage.clone());
+
+    // Deliveries reference messages in the audit trail
+    let mut packed = Vec::new();
+    for id in ["msg-duplicate", "msg-rejected"] {
+        let message = serde_json::json!({
+            "id": id,
+            "type": "https://tap.rsvp/schema/1.0#Transfer",
+            "body": {},
+            "from": "did:example:sender",
+            "to": ["did:example:123"],
+        });
+        storage
+            .log_message(
+                &serde_json::from_value(message.clone()).unwrap(),
+                MessageDirection::Outgoing,
+            )
+            .await
+            .unwrap();
+        packed.push(message.to_string());
+    }
+
+    // A 409 means the recipient already has the message
+    sender
+        .send(packed[0].clone(), vec!["did:example:123".to_string()])
+        .await?;
+    let deliveries = storage
+        .get_deliveries_for_message("msg-duplicate")
+        .await
+        .unwrap();
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].status, DeliveryStatus::Success);
+    assert_eq!(deliveries[0].last_http_status_code, Some(409));
+
+    let result = sender
+        .send(packed[1].clone(), vec!["did:example:123".to_string()])
+        .await;
+    assert!(result.is_err());
+    let deliveries = storage
+        .get_deliveries_for_message("msg-rejected")
+        .await
+        .unwrap();
+    assert_eq!(deliveries[0].status, DeliveryStatus::Failed);
+    assert_eq!(deliveries[0].last_http_status_code, Some(422));
+    let error = deliveries[0].error_message.as_deref().unwrap();
+    assert!(error.contains("422"));
+    assert!(error.contains("xxx"));
+    assert!(error.len() < long_body.len());
+
+    Ok(())
+}
+
+#[test]
+fn test_idempotency_key_uses_message_id() {
+    use base64::Engine;
+    use tap_node::message::sender::{idempotency_key, packed_message_id};
+
+    assert_eq!(idempotency_key(r#"{"id":"msg-plain"}"#), "msg-plain");
+
+    let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(r#"{"id":"msg-signed"}"#);
+    let jws = format!(r#"{{"payload":"{}","signatures":[]}}"#, payload);
+    assert_eq!(packed_message_id(&jws).as_deref(), Some("msg-signed"));
+
+    let jwe = r#"{"protected":"e30","ciphertext":"abc"}"#;
+    assert_eq!(packed_message_id(jwe), None);
+    assert_eq!(idempotency_key(jwe).len(), 64);
+    assert_eq!(idempotency_key(jwe), idempotency_key(jwe));
+}
@@ -0,0 +1,329 @@
+//! Tests for running messages, transactions and deliveries on a storage
+//! backend
+
+mod common;
+
+use async_trait::async_trait;
+use std::sync::Arc;
+use tap_msg::didcomm::PlainMessage;
+use tap_node::storage::{
+    encryption, Delivery, DeliveryStatus, DeliveryType, Message, MessageCipher, MessageDirection,
+    MessageEncryption, Storage, StorageBackend, StorageError, Transaction,
+};
+use tempfile::TempDir;
+
+/// A backend whose every call fails, as an unreachable database would
+#[derive(Debug)]
+struct UnavailableBackend;
+
+fn unavailable<T>() -> Result<T, StorageError> {
+    Err(StorageError::Backend("connection refused".to_string()))
+}
+
+#[async_trait]
+impl StorageBackend for UnavailableBackend {
+    fn name(&self) -> &'static str {
+        "unavailable"
+    }
+
+    fn for_agent(
+        &self,
+        _agent_did: &str,
+        _clock: Arc<dyn tap_node::clock::Clock>,
+    ) -> Arc<dyn StorageBackend> {
+        Arc::new(UnavailableBackend)
+    }
+
+    async fn log_message(
+        &self,
+        _message: &PlainMessage,
+        _direction: MessageDirection,
+    ) -> Result<(), StorageError> {
+        unavailable()
+    }
+
+    async fn get_message_by_id(&self, _message_id: &str) -> Result<Option<Message>, StorageError> {
+        unavailable()
+    }
+
+    async fn list_messages(
+        &self,
+        _limit: u32,
+        _offset: u32,
+        _direction: Option<MessageDirection>,
+    ) -> Result<Vec<Message>, StorageError> {
+        unavailable()
+    }
+
+    async fn insert_transaction(&self, _message: &PlainMessage) -> Result<(), StorageError> {
+        unavailable()
+    }
+
+    async fn update_transaction_status(
+        &self,
+        _transaction_id: &str,
+        _status: &str,
+    ) -> Result<(), StorageError> {
+        unavailable()
+    }
+
+    async fn record_rejection(
+        &self,
+        _transaction_id: &str,
+        _code: Option<&str>,
+        _reason: Option<&str>,
+    ) -> Result<(), StorageError> {
+        unavailable()
+    }
+
+    async fn get_transaction_by_id(
+        &self,
+        _reference_id: &str,
+    ) -> Result<Option<Transaction>, StorageError> {
+        unavailable()
+    }
+
+    async fn list_transactions(
+        &self,
+        _limit: u32,
+        _offset: u32,
+    ) -> Result<Vec<Transaction>, StorageError> {
+        unavailable()
+    }
+
+    async fn create_delivery(
+        &self,
+        _message_id: &str,
+        _message_text: &str,
+        _recipient_did: &str,
+        _delivery_url: Option<&str>,
+        _delivery_type: DeliveryType,
+    ) -> Result<i64, StorageError> {
+        unavailable()
+    }
+
+    async fn update_delivery_status(
+        &self,
+        _delivery_id: i64,
+        _status: DeliveryStatus,
+        _http_status_code: Option<i32>,
+        _error_message: Option<&str>,
+    ) -> Result<(), StorageError> {
+        unavailable()
+    }
+
+    async fn increment_delivery_retry_count(&self, _delivery_id: i64) -> Result<(), StorageError> {
+        unavailable()
+    }
+
+    async fn schedule_delivery_attempt(
+        &self,
+        _delivery_id: i64,
+        _next_attempt_at: Option<&str>,
+    ) -> Result<(), StorageError> {
+        unavailable()
+    }
+
+    async fn reassign_delivery(
+        &self,
+        _delivery_id: i64,
+        _delivery_url: &str,
+    ) -> Result<(), StorageError> {
+        unavailable()
+    }
+
+    async fn get_deliveries_for_message(
+        &self,
+        _message_id: &str,
+    ) -> Result<Vec<Delivery>, StorageError> {
+        unavailable()
+    }
+}
+
+#[tokio::test]
+async fn test_storage_runs_on_backend() {
+    let backend = Arc::new(Storage::new_in_memory().await.unwrap());
+    let storage = Storage::new_in_memory()
+        .await
+        .unwrap()
+        .with_backend(backend.clone());
+
+    let message = common::transfer("transfer-1", "did:key:alice", "did:key:bob");
+    storage
+        .log_message(&message, MessageDirection::Outgoing)
+        .await
+        .unwrap();
+    storage.insert_transaction(&message).await.unwrap();
+    storage
+        .update_transaction_status("transfer-1", "confirmed")
+        .await
+        .unwrap();
+    let delivery_id = storage
+        .create_delivery(
+            "transfer-1",
+            "{}",
+            "did:key:bob",
+            Some("https://bob.example/didcomm"),
+            DeliveryType::Https,
+        )
+        .await
+        .unwrap();
+    storage
+        .update_delivery_status(delivery_id, DeliveryStatus::Success, Some(200), None)
+        .await
+        .unwrap();
+
+    // Written to the backend
+    assert!(backend
+        .get_message_by_id("transfer-1")
+        .await
+        .unwrap()
+        .is_some());
+    let transaction = backend
+        .get_transaction_by_id("transfer-1")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(transaction.status.to_string(), "confirmed");
+    let deliveries = backend
+        .get_deliveries_for_message("transfer-1")
+        .await
+        .unwrap();
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].id, delivery_id);
+    assert_eq!(deliveries[0].status, DeliveryStatus::Success);
+
+    // The agent database keeps a copy under the same delivery ID
+    let copy = storage
+        .get_delivery_by_id(delivery_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(copy.status, DeliveryStatus::Success);
+
+    // Reads are served by the backend
+    let other = common::transfer("transfer-2", "did:key:carol", "did:key:bob");
+    backend.insert_transaction(&other).await.unwrap();
+    assert!(storage
+        .get_transaction_by_id("transfer-2")
+        .await
+        .unwrap()
+        .is_some());
+    assert_eq!(storage.list_transactions(10, 0).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_listing_includes_messages_encrypted_at_rest() {
+    let backend = Arc::new(Storage::new_in_memory().await.unwrap());
+    let storage = Storage::new_in_memory()
+        .await
+        .unwrap()
+        .with_message_encryption(MessageCipher::new(&[3u8; 32], MessageEncryption::default()))
+        .with_backend(backend.clone());
+
+    let presentation = PlainMessage::new(
+        "presentation-1".to_string(),
+        encryption::PRESENTATION_MESSAGE_TYPE.to_string(),
+        serde_json::json!({"name": "Alice"}),
+        "did:key:alice".to_string(),
+    );
+    storage
+        .log_message(&presentation, MessageDirection::Outgoing)
+        .await
+        .unwrap();
+    storage
+        .log_message(
+            &common::transfer("transfer-1", "did:key:alice", "did:key:bob"),
+            MessageDirection::Outgoing,
+        )
+        .await
+        .unwrap();
+
+    // Only the transfer reaches the backend
+    assert_eq!(backend.list_messages(10, 0, None).await.unwrap().len(), 1);
+
+    let mut listed: Vec<String> = storage
+        .list_messages(10, 0, None)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|message| message.message_id)
+        .collect();
+    listed.sort();
+    assert_eq!(listed, vec!["presentation-1", "transfer-1"]);
+    let outgoing = storage
+        .list_messages(10, 0, Some(MessageDirection::Outgoing))
+        .await
+        .unwrap();
+    assert_eq!(outgoing.len(), 2);
+    let presentation = outgoing
+        .iter()
+        .find(|message| message.message_id == "presentation-1")
+        .unwrap();
+    assert_eq!(presentation.message_json["body"]["name"], "Alice");
+    assert!(storage
+        .list_messages(10, 0, Some(MessageDirection::Incoming))
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Pages are cut from the merged listing
+    let first = storage.list_messages(1, 0, None).await.unwrap();
+    let second = storage.list_messages(1, 1, None).await.unwrap();
+    assert_eq!(first.len(), 1);
+    assert_eq!(second.len(), 1);
+    assert_ne!(first[0].message_id, second[0].message_id);
+    assert!(storage.list_messages(1, 2, None).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_failed_backend_write_fails_call() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("agent.db");
+    let storage = Storage::new(Some(path.clone()))
+        .await
+        .unwrap()
+        .with_backend(Arc::new(UnavailableBackend));
+
+    let message = common::transfer("transfer-1", "did:key:alice", "did:key:bob");
+    assert!(matches!(
+        storage.insert_transaction(&message).await,
+        Err(StorageError::Backend(_))
+    ));
+    assert!(matches!(
+        storage
+            .log_message(&message, MessageDirection::Incoming)
+            .await,
+        Err(StorageError::Backend(_))
+    ));
+    assert!(matches!(
+        storage
+            .create_delivery(
+                "transfer-1",
+                "{}",
+                "did:key:bob",
+                None,
+                DeliveryType::Internal
+            )
+            .await,
+        Err(StorageError::Backend(_))
+    ));
+
+    // Nothing was written to the agent database either
+    let local = Storage::new(Some(path)).await.unwrap();
+    assert!(local
+        .get_transaction_by_id("transfer-1")
+        .await
+        .unwrap()
+        .is_none());
+    assert!(local
+        .get_message_by_id("transfer-1")
+        .await
+        .unwrap()
+        .is_none());
+    assert!(local
+        .get_deliveries_for_message("transfer-1")
+        .await
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,202 @@
+//! Tests for simulated counterparties
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tap_agent::TapAgent;
+use tap_msg::message::{Agent as MessageAgent, Party, TapMessageBody, Transfer};
+use tap_node::simulation::CounterpartyBehavior;
+use tap_node::storage::Message;
+use tap_node::{NodeConfig, NodeEvent, TapNode};
+use tempfile::TempDir;
+
+/// A node with one agent, and the DID of the agent
+async fn node_with_agent(temp_dir: &TempDir) -> (TapNode, String) {
+    let config = NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("node.db")),
+        ..Default::default()
+    };
+    let mut node = TapNode::new(config);
+    node.init_storage().await.unwrap();
+
+    let (agent, did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(agent)).await.unwrap();
+    (node, did)
+}
+
+/// Send a Transfer from our agent to the counterparty, returning its
+/// transaction ID
+async fn send_transfer(node: &TapNode, our_did: &str, counterparty: &str) -> String {
+    let transfer = Transfer {
+        asset: "eip155:1/slip44:60".parse().unwrap(),
+        originator: Some(Party::new("did:example:alice")),
+        beneficiary: Some(Party::new("did:example:bob")),
+        amount: "1.25".to_string(),
+        agents: vec![
+            MessageAgent::new(our_did, "originating_vasp", "did:example:alice"),
+            MessageAgent::new(counterparty, "beneficiary_vasp", "did:example:bob"),
+        ],
+        memo: None,
+        settlement_id: None,
+        expiry: None,
+        transaction_value: None,
+        transaction_id: None,
+        connection_id: None,
+        metadata: HashMap::new(),
+    };
+    let message = transfer
+        .to_didcomm_with_route(our_did, [counterparty])
+        .unwrap();
+    let transaction_id = message.id.clone();
+    node.send_message(our_did.to_string(), message)
+        .await
+        .unwrap();
+    transaction_id
+}
+
+/// Wait for the node to accept an answer from the counterparty, returning
+/// its ID and type
+async fn wait_for_answer(
+    events: &mut tokio::sync::broadcast::Receiver<Arc<NodeEvent>>,
+    counterparty: &str,
+    timeout: Duration,
+) -> Option<(String, String)> {
+    tokio::time::timeout(timeout, async {
+        loop {
+            if let NodeEvent::MessageAccepted {
+                message_id,
+                message_type,
+                from,
+                ..
+            } = &*events.recv().await.unwrap()
+            {
+                if from == counterparty {
+                    return (message_id.clone(), message_type.clone());
+                }
+            }
+        }
+    })
+    .await
+    .ok()
+}
+
+/// The answer as stored for our agent
+async fn stored_answer(node: &TapNode, our_did: &str, message_id: &str) -> Message {
+    let storage = node
+        .agent_storage_manager()
+        .unwrap()
+        .get_agent_storage(our_did)
+        .await
+        .unwrap();
+    for _ in 0..50 {
+        if let Some(message) = storage.get_message_by_id(message_id).await.unwrap() {
+            return message;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("Answer {} was not stored", message_id);
+}
+
+#[tokio::test]
+async fn test_simulated_counterparty_authorizes() {
+    let temp_dir = TempDir::new().unwrap();
+    let (node, our_did) = node_with_agent(&temp_dir).await;
+    let counterparty = node
+        .add_simulated_counterparty(CounterpartyBehavior::Authorize {
+            delay: Duration::from_millis(100),
+        })
+        .await
+        .unwrap();
+    assert!(node.is_simulated_counterparty(&counterparty));
+    assert!(!node.list_agents().contains(&counterparty));
+
+    let mut events = node.event_bus().subscribe_channel();
+    let transaction_id = send_transfer(&node, &our_did, &counterparty).await;
+
+    let (answer_id, answer_type) =
+        wait_for_answer(&mut events, &counterparty, Duration::from_secs(10))
+            .await
+            .expect("No answer from the simulated counterparty");
+    assert!(answer_type.ends_with("#Authorize"));
+    let answer = stored_answer(&node, &our_did, &answer_id).await;
+    assert_eq!(answer.thread_id.as_deref(), Some(transaction_id.as_str()));
+    assert_eq!(answer.to_did.as_deref(), Some(our_did.as_str()));
+}
+
+#[tokio::test]
+async fn test_simulated_counterparty_rejects_with_reason() {
+    let temp_dir = TempDir::new().unwrap();
+    let (node, our_did) = node_with_agent(&temp_dir).await;
+    let counterparty = node
+        .add_simulated_counterparty("reject:0:Sanctions screening failed".parse().unwrap())
+        .await
+        .unwrap();
+
+    let mut events = node.event_bus().subscribe_channel();
+    send_transfer(&node, &our_did, &counterparty).await;
+
+    let (answer_id, answer_type) =
+        wait_for_answer(&mut events, &counterparty, Duration::from_secs(10))
+            .await
+            .expect("No answer from the simulated counterparty");
+    assert!(answer_type.ends_with("#Reject"));
+    let answer = stored_answer(&node, &our_did, &answer_id).await;
+    assert_eq!(
+        answer.message_json["body"]["reason"],
+        "Sanctions screening failed"
+    );
+}
+
+#[tokio::test]
+async fn test_simulated_counterparty_requests_presentation() {
+    let temp_dir = TempDir::new().unwrap();
+    let (node, our_did) = node_with_agent(&temp_dir).await;
+    let counterparty = node
+        .add_simulated_counterparty("request-presentation".parse().unwrap())
+        .await
+        .unwrap();
+
+    let mut events = node.event_bus().subscribe_channel();
+    send_transfer(&node, &our_did, &counterparty).await;
+
+    let (_, answer_type) = wait_for_answer(&mut events, &counterparty, Duration::from_secs(10))
+        .await
+        .expect("No answer from the simulated counterparty");
+    assert!(answer_type.ends_with("#RequestPresentation"));
+}
+
+#[tokio::test]
+async fn test_simulated_counterparty_never_responds() {
+    let temp_dir = TempDir::new().unwrap();
+    let (node, our_did) = node_with_agent(&temp_dir).await;
+    let counterparty = node
+        .add_simulated_counterparty(CounterpartyBehavior::NeverRespond)
+        .await
+        .unwrap();
+
+    let mut events = node.event_bus().subscribe_channel();
+    send_transfer(&node, &our_did, &counterparty).await;
+    assert!(
+        wait_for_answer(&mut events, &counterparty, Duration::from_millis(500))
+            .await
+            .is_none()
+    );
+
+    // Changing its behavior answers the next Transfer
+    assert!(node.set_simulated_behavior(
+        &counterparty,
+        CounterpartyBehavior::Authorize {
+            delay: Duration::ZERO
+        }
+    ));
+    send_transfer(&node, &our_did, &counterparty).await;
+    assert!(
+        wait_for_answer(&mut events, &counterparty, Duration::from_secs(10))
+            .await
+            .is_some()
+    );
+
+    assert!(node.remove_simulated_counterparty(&counterparty));
+    assert!(node.simulated_counterparties().is_empty());
+}
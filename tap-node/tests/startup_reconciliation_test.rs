@@ -0,0 +1,230 @@
+//! Tests for reconciling processing interrupted by a stop of the node
+
+mod common;
+
+use chrono::Utc;
+use common::{transfer_body, transfer_message};
+use std::sync::Arc;
+use tap_agent::TapAgent;
+use tap_msg::didcomm::PlainMessage;
+use tap_msg::message::{Agent, Transfer};
+use tap_node::clock::MockClock;
+use tap_node::storage::{
+    DeliveryStatus, DeliveryType, MessageDirection, ReceivedStatus, SourceType, TransactionStatus,
+};
+use tap_node::{NodeConfig, NodeEvent, TapNode};
+use tempfile::TempDir;
+
+fn transfer(id: &str, from: &str, to: &str, agents: Vec<Agent>) -> PlainMessage {
+    let transfer = Transfer {
+        agents,
+        ..transfer_body(from, to, "10")
+    };
+    transfer_message(id, &transfer, from, to)
+}
+
+/// A node over the databases in `temp_dir` with both agents registered
+async fn start_node(
+    temp_dir: &TempDir,
+    clock: &Arc<MockClock>,
+    agents: &[Arc<TapAgent>],
+) -> TapNode {
+    let mut node = TapNode::new(NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("node.db")),
+        clock: Some(clock.clone()),
+        ..Default::default()
+    });
+    node.init_storage().await.unwrap();
+    for agent in agents {
+        node.register_agent(agent.clone()).await.unwrap();
+    }
+    node
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_interrupted_deliveries_and_messages_are_redriven() {
+    let temp_dir = TempDir::new().unwrap();
+    // Messages are created at the current time, so the clock starts there
+    let clock = Arc::new(MockClock::new(Utc::now()));
+    let (alice, alice_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let (bob, bob_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let agents = [Arc::new(alice), Arc::new(bob)];
+
+    // The first run stops after recording work it never finished
+    let node = start_node(&temp_dir, &clock, &agents).await;
+    let manager = node.agent_storage_manager().unwrap();
+    let alice_storage = manager.get_agent_storage(&alice_did).await.unwrap();
+    let bob_storage = manager.get_agent_storage(&bob_did).await.unwrap();
+
+    let delivered = transfer("transfer-1", &alice_did, &bob_did, vec![]);
+    bob_storage
+        .log_message(&delivered, MessageDirection::Incoming)
+        .await
+        .unwrap();
+    let internal_id = bob_storage
+        .create_delivery(
+            &delivered.id,
+            &serde_json::to_string(&delivered).unwrap(),
+            &bob_did,
+            None,
+            DeliveryType::Internal,
+        )
+        .await
+        .unwrap();
+
+    alice_storage
+        .log_message(&delivered, MessageDirection::Outgoing)
+        .await
+        .unwrap();
+    let external_id = alice_storage
+        .create_delivery(
+            &delivered.id,
+            "{}",
+            "did:example:carol",
+            Some("http://127.0.0.1:9/didcomm"),
+            DeliveryType::Https,
+        )
+        .await
+        .unwrap();
+
+    let received = transfer("transfer-2", &alice_did, &bob_did, vec![]);
+    let received_id = bob_storage
+        .create_received(
+            &serde_json::to_string(&received).unwrap(),
+            SourceType::Https,
+            None,
+        )
+        .await
+        .unwrap();
+    drop(node);
+
+    clock.advance(chrono::Duration::minutes(1));
+    let node = start_node(&temp_dir, &clock, &agents).await;
+    let mut events = node.event_bus().subscribe_channel();
+    let report = node.reconcile().await.unwrap();
+
+    assert_eq!(report.redelivered_messages, 1);
+    assert_eq!(report.requeued_deliveries, 1);
+    assert_eq!(report.reprocessed_messages, 1);
+    // Without a retry policy, nothing retries the external delivery
+    assert_eq!(report.flagged.len(), 1);
+    assert_eq!(
+        report.flagged[0].agent_did.as_deref(),
+        Some(alice_did.as_str())
+    );
+    assert_eq!(
+        report.flagged[0].record,
+        format!("delivery {}", external_id)
+    );
+
+    let internal = bob_storage
+        .get_delivery_by_id(internal_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(internal.status, DeliveryStatus::Success);
+    let external = alice_storage
+        .get_delivery_by_id(external_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(external.status, DeliveryStatus::Failed);
+    assert_eq!(external.retry_count, 1);
+    let interrupted = bob_storage
+        .get_received_by_id(received_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(interrupted.status, ReceivedStatus::Failed);
+    assert!(node
+        .storage()
+        .unwrap()
+        .get_transaction_by_id("transfer-2")
+        .await
+        .unwrap()
+        .is_some());
+
+    // Reprocessed messages publish their own events first
+    loop {
+        if let NodeEvent::ReconciliationCompleted {
+            redelivered_messages,
+            flagged,
+            ..
+        } = events.recv().await.unwrap().as_ref()
+        {
+            assert_eq!(*redelivered_messages, 1);
+            assert_eq!(flagged.as_array().unwrap().len(), 1);
+            break;
+        }
+    }
+
+    // Records of this run are left alone
+    assert!(node.reconcile().await.unwrap().is_clean());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_settlement_of_authorized_transaction_is_resumed() {
+    let temp_dir = TempDir::new().unwrap();
+    // Messages are created at the current time, so the clock starts there
+    let clock = Arc::new(MockClock::new(Utc::now()));
+    let (alice, alice_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let agents = [Arc::new(alice)];
+
+    let bob_vasp = Agent::new("did:example:bob-vasp", "BeneficiaryVASP", "did:example:bob");
+
+    // The last Authorize was recorded, but the node stopped before settling
+    let node = start_node(&temp_dir, &clock, &agents).await;
+    let storage = node.storage().unwrap();
+    storage
+        .insert_transaction(&transfer(
+            "transfer-1",
+            &alice_did,
+            "did:example:bob",
+            vec![bob_vasp.clone()],
+        ))
+        .await
+        .unwrap();
+    storage
+        .insert_transaction_agent("transfer-1", "did:example:bob-vasp", "receiver")
+        .await
+        .unwrap();
+    storage
+        .update_transaction_agent_status("transfer-1", "did:example:bob-vasp", "authorized")
+        .await
+        .unwrap();
+    // A transaction nobody authorized yet is not settled
+    storage
+        .insert_transaction(&transfer(
+            "transfer-2",
+            &alice_did,
+            "did:example:bob",
+            vec![bob_vasp.clone()],
+        ))
+        .await
+        .unwrap();
+    storage
+        .insert_transaction_agent("transfer-2", "did:example:bob-vasp", "receiver")
+        .await
+        .unwrap();
+    drop(node);
+
+    clock.advance(chrono::Duration::minutes(1));
+    let node = start_node(&temp_dir, &clock, &agents).await;
+    let report = node.reconcile().await.unwrap();
+    assert_eq!(report.resumed_settlements, 1);
+
+    let storage = node.storage().unwrap();
+    let settled = storage
+        .get_transaction_by_id("transfer-1")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(settled.status, TransactionStatus::Confirmed);
+    let waiting = storage
+        .get_transaction_by_id("transfer-2")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(waiting.status, TransactionStatus::Pending);
+}
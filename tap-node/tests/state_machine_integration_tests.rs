@@ -11,7 +11,7 @@ use std::sync::Arc;
 use tap_caip::{AssetId, ChainId};
 use tap_msg::didcomm::PlainMessage;
 use tap_msg::message::tap_message_trait::TapMessageBody;
-use tap_msg::message::{Agent, Party, Payment, Transfer};
+use tap_msg::message::{Agent, Party, Payment, SupportedAsset, Transfer};
 use tap_node::agent::AgentRegistry;
 use tap_node::event::EventBus;
 use tap_node::message::{PlainMessageProcessor, StateMachineIntegrationProcessor};
@@ -205,7 +205,7 @@ async fn test_processing_pipeline_order() {
     // 3. State Machine Integration (update state)
     let mut composite = CompositePlainMessageProcessor::new(vec![]);
     composite.add_processor(PlainMessageProcessorType::Validation(
-        ValidationPlainMessageProcessor,
+        ValidationPlainMessageProcessor::default(),
     ));
     composite.add_processor(PlainMessageProcessorType::Logging(
         LoggingPlainMessageProcessor,
@@ -279,7 +279,7 @@ async fn test_invalid_message_filtering() {
     // Create a composite processor
     let mut composite = CompositePlainMessageProcessor::new(vec![]);
     composite.add_processor(PlainMessageProcessorType::Validation(
-        ValidationPlainMessageProcessor,
+        ValidationPlainMessageProcessor::default(),
     ));
     composite.add_processor(PlainMessageProcessorType::StateMachine(
         StateMachineIntegrationProcessor::new().with_state_processor(state_processor),
@@ -311,3 +311,748 @@ async fn test_invalid_message_filtering() {
     let transactions = storage.list_transactions(10, 0).await.unwrap();
     assert_eq!(transactions.len(), 0);
 }
+
+/// Test that velocity rules trip at authorization time and publish events
+#[tokio::test]
+async fn test_velocity_rule_publishes_policy_event() {
+    use std::time::Duration;
+    use tap_node::event::NodeEvent;
+    use tap_node::policy::{Decimal, PolicyAction, PolicyEngine, VelocityRule, VelocityScope};
+    use tap_node::storage::MessageDirection;
+
+    let storage = Arc::new(Storage::new(Some(":memory:".into())).await.unwrap());
+    let event_bus = Arc::new(EventBus::new());
+    let agents = Arc::new(AgentRegistry::new(None));
+    let mut events = event_bus.subscribe_channel();
+
+    let policy_engine = PolicyEngine::new().with_rule(VelocityRule::new(
+        "originator-daily",
+        VelocityScope::Party,
+        Decimal::from(150),
+        Duration::from_secs(86400),
+        PolicyAction::ManualReview,
+    ));
+    let state_processor = StandardTransactionProcessor::new(
+        storage.clone(),
+        event_bus.clone(),
+        agents.clone(),
+        DecisionMode::AutoApprove,
+    )
+    .with_policy_engine(Arc::new(policy_engine));
+
+    for (id, amount) in [("velocity-tx-1", "100.0"), ("velocity-tx-2", "75.0")] {
+        let transfer = Transfer {
+            asset: test_asset(),
+            originator: Some(test_party("alice")),
+            beneficiary: Some(test_party("bob")),
+            amount: amount.to_string(),
+            agents: vec![test_agent("compliance1", "compliance", "alice")],
+            memo: None,
+            settlement_id: None,
+            expiry: None,
+            transaction_value: None,
+            transaction_id: Some(id.to_string()),
+            connection_id: None,
+            metadata: std::collections::HashMap::new(),
+        };
+        let mut plain_message = transfer.to_didcomm(&test_agent_did("alice")).unwrap();
+        plain_message.id = id.to_string();
+        if id == "velocity-tx-1" {
+            // Sent earlier by the node, so it counts towards the limit
+            storage
+                .log_message(&plain_message, MessageDirection::Outgoing)
+                .await
+                .unwrap();
+            state_processor
+                .process_outgoing_message(&plain_message)
+                .await
+                .unwrap();
+        } else {
+            state_processor
+                .process_message(&plain_message)
+                .await
+                .unwrap();
+        }
+    }
+
+    let mut triggered = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let NodeEvent::PolicyTriggered {
+            transaction_id,
+            rule,
+            action,
+            details,
+            ..
+        } = event.as_ref()
+        {
+            triggered.push((
+                transaction_id.clone(),
+                rule.clone(),
+                action.clone(),
+                details.clone(),
+            ));
+        }
+    }
+
+    assert_eq!(triggered.len(), 1);
+    let (transaction_id, rule, action, details) = &triggered[0];
+    assert_eq!(transaction_id, "velocity-tx-2");
+    assert_eq!(rule, "originator-daily");
+    assert_eq!(action, "manual_review");
+    assert_eq!(details["aggregate_amount"], "175.0");
+}
+
+/// Test that likely duplicate Transfers get a warning and are still authorized
+#[tokio::test]
+async fn test_duplicate_rule_attaches_warning() {
+    use std::time::Duration;
+    use tap_node::event::NodeEvent;
+    use tap_node::policy::{DuplicateRule, PolicyEngine};
+
+    let storage = Arc::new(Storage::new(Some(":memory:".into())).await.unwrap());
+    let event_bus = Arc::new(EventBus::new());
+    let agents = Arc::new(AgentRegistry::new(None));
+    let mut events = event_bus.subscribe_channel();
+
+    let policy_engine =
+        PolicyEngine::new().with_rule(DuplicateRule::new("duplicates", Duration::from_secs(300)));
+    let state_processor = StandardTransactionProcessor::new(
+        storage.clone(),
+        event_bus.clone(),
+        agents.clone(),
+        DecisionMode::AutoApprove,
+    )
+    .with_policy_engine(Arc::new(policy_engine));
+
+    for id in ["duplicate-tx-1", "duplicate-tx-2"] {
+        let transfer = Transfer {
+            asset: test_asset(),
+            originator: Some(test_party("alice")),
+            beneficiary: Some(test_party("bob")),
+            amount: "250.0".to_string(),
+            agents: vec![test_agent("compliance1", "compliance", "alice")],
+            memo: None,
+            settlement_id: None,
+            expiry: None,
+            transaction_value: None,
+            transaction_id: Some(id.to_string()),
+            connection_id: None,
+            metadata: std::collections::HashMap::new(),
+        };
+        let mut plain_message = transfer.to_didcomm(&test_agent_did("alice")).unwrap();
+        plain_message.id = id.to_string();
+        state_processor
+            .process_message(&plain_message)
+            .await
+            .unwrap();
+    }
+
+    assert!(storage
+        .list_transaction_warnings("duplicate-tx-1")
+        .await
+        .unwrap()
+        .is_empty());
+    let warnings = storage
+        .list_transaction_warnings("duplicate-tx-2")
+        .await
+        .unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].rule, "duplicates");
+    assert_eq!(warnings[0].action, "warn");
+    assert_eq!(
+        warnings[0].details["duplicate_of"],
+        serde_json::json!(["duplicate-tx-1"])
+    );
+
+    let mut triggered = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let NodeEvent::PolicyTriggered {
+            transaction_id,
+            action,
+            ..
+        } = event.as_ref()
+        {
+            triggered.push((transaction_id.clone(), action.clone()));
+        }
+    }
+    assert_eq!(
+        triggered,
+        vec![("duplicate-tx-2".to_string(), "warn".to_string())]
+    );
+
+    // Warnings do not hold the transaction for review
+    assert!(storage
+        .list_review_items(None, None, 10, 0)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+/// Test that script rules trip at authorization time and publish events
+#[cfg(feature = "scripting")]
+#[tokio::test]
+async fn test_script_rule_publishes_policy_event() {
+    use tap_node::event::NodeEvent;
+    use tap_node::policy::{PolicyEngine, ScriptRule};
+    use tap_node::scripting::ScriptHook;
+
+    let storage = Arc::new(Storage::new(Some(":memory:".into())).await.unwrap());
+    let event_bus = Arc::new(EventBus::new());
+    let agents = Arc::new(AgentRegistry::new(None));
+    let mut events = event_bus.subscribe_channel();
+
+    let hook = ScriptHook::from_source(
+        "large-transfers",
+        r#"
+        fn policy(message) {
+            let amount = parse_float(message.body.amount);
+            if amount > 10000.0 {
+                return #{ action: "manual_review", reason: "Large transfer", amount: amount };
+            }
+        }
+        "#,
+    )
+    .unwrap();
+    let policy_engine = PolicyEngine::new().with_rule(ScriptRule::new(Arc::new(hook)));
+    let state_processor = StandardTransactionProcessor::new(
+        storage.clone(),
+        event_bus.clone(),
+        agents.clone(),
+        DecisionMode::AutoApprove,
+    )
+    .with_policy_engine(Arc::new(policy_engine));
+
+    for (id, amount) in [("script-tx-1", "100.0"), ("script-tx-2", "25000.0")] {
+        let transfer = Transfer {
+            asset: test_asset(),
+            originator: Some(test_party("alice")),
+            beneficiary: Some(test_party("bob")),
+            amount: amount.to_string(),
+            agents: vec![test_agent("compliance1", "compliance", "alice")],
+            memo: None,
+            settlement_id: None,
+            expiry: None,
+            transaction_value: None,
+            transaction_id: Some(id.to_string()),
+            connection_id: None,
+            metadata: std::collections::HashMap::new(),
+        };
+        let mut plain_message = transfer.to_didcomm(&test_agent_did("alice")).unwrap();
+        plain_message.id = id.to_string();
+        state_processor
+            .process_message(&plain_message)
+            .await
+            .unwrap();
+    }
+
+    let mut triggered = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let NodeEvent::PolicyTriggered {
+            transaction_id,
+            rule,
+            action,
+            details,
+            ..
+        } = event.as_ref()
+        {
+            triggered.push((
+                transaction_id.clone(),
+                rule.clone(),
+                action.clone(),
+                details.clone(),
+            ));
+        }
+    }
+
+    assert_eq!(triggered.len(), 1);
+    let (transaction_id, rule, action, details) = &triggered[0];
+    assert_eq!(transaction_id, "script-tx-2");
+    assert_eq!(rule, "script:large-transfers");
+    assert_eq!(action, "manual_review");
+    assert_eq!(details["amount"], 25000.0);
+}
+
+/// Test that recorded transactions are tagged with their fiat value once
+#[tokio::test]
+async fn test_recorded_transfer_is_valued() {
+    use tap_node::valuation::{StaticRates, ValuationConfig};
+
+    let storage = Arc::new(Storage::new(Some(":memory:".into())).await.unwrap());
+    let rates = StaticRates::new("test-feed").with_rate(test_asset().to_string(), "USD", 0.999);
+    let state_processor = StandardTransactionProcessor::new(
+        storage.clone(),
+        Arc::new(EventBus::new()),
+        Arc::new(AgentRegistry::new(None)),
+        DecisionMode::EventBus,
+    )
+    .with_valuation(ValuationConfig::new(Arc::new(rates), "usd"));
+
+    let transfer = Transfer {
+        asset: test_asset(),
+        originator: Some(test_party("alice")),
+        beneficiary: Some(test_party("bob")),
+        amount: "200".to_string(),
+        agents: vec![test_agent("compliance1", "compliance", "alice")],
+        memo: None,
+        settlement_id: None,
+        expiry: None,
+        transaction_value: None,
+        transaction_id: Some("valued-tx".to_string()),
+        connection_id: None,
+        metadata: std::collections::HashMap::new(),
+    };
+    let mut plain_message = transfer.to_didcomm(&test_agent_did("alice")).unwrap();
+    plain_message.id = "valued-tx".to_string();
+    state_processor
+        .process_message(&plain_message)
+        .await
+        .unwrap();
+
+    let valuation = storage
+        .get_transaction_valuation("valued-tx")
+        .await
+        .unwrap()
+        .expect("transaction should be valued");
+    assert_eq!(valuation.currency, "USD");
+    assert_eq!(valuation.amount, "200");
+    assert_eq!(valuation.rate_source, "test-feed");
+    assert!((valuation.fiat_amount - 199.8).abs() < 1e-9);
+}
+
+/// Test that agent changes are materialized into the participants of a transaction
+#[tokio::test]
+async fn test_agent_changes_are_recorded_as_participants() {
+    use tap_msg::message::{RemoveAgent, ReplaceAgent};
+
+    let storage = Arc::new(Storage::new(Some(":memory:".into())).await.unwrap());
+    let state_processor = StandardTransactionProcessor::new(
+        storage.clone(),
+        Arc::new(EventBus::new()),
+        Arc::new(AgentRegistry::new(None)),
+        DecisionMode::EventBus,
+    );
+
+    let transfer = Transfer {
+        asset: test_asset(),
+        originator: Some(test_party("alice")),
+        beneficiary: Some(test_party("bob")),
+        amount: "50".to_string(),
+        agents: vec![
+            test_agent("compliance1", "compliance", "alice"),
+            test_agent("wallet1", "SourceAddress", "alice"),
+        ],
+        memo: None,
+        settlement_id: None,
+        expiry: None,
+        transaction_value: None,
+        transaction_id: Some("participants-tx".to_string()),
+        connection_id: None,
+        metadata: std::collections::HashMap::new(),
+    };
+    let mut plain_message = transfer.to_didcomm(&test_agent_did("alice")).unwrap();
+    plain_message.id = "participants-tx".to_string();
+    state_processor
+        .process_message(&plain_message)
+        .await
+        .unwrap();
+
+    let replace = ReplaceAgent::new(
+        "participants-tx",
+        &test_agent_did("wallet1"),
+        test_agent("wallet2", "SourceAddress", "alice"),
+    );
+    let mut replace_message = replace.to_didcomm(&test_agent_did("alice")).unwrap();
+    replace_message.id = "replace-1".to_string();
+    state_processor
+        .process_message(&replace_message)
+        .await
+        .unwrap();
+
+    let remove = RemoveAgent::new("participants-tx", &test_agent_did("compliance1"));
+    let mut remove_message = remove.to_didcomm(&test_agent_did("bob")).unwrap();
+    remove_message.id = "remove-1".to_string();
+    state_processor
+        .process_message(&remove_message)
+        .await
+        .unwrap();
+
+    let current = storage
+        .get_transaction_participants("participants-tx", false)
+        .await
+        .unwrap();
+    assert_eq!(current.len(), 1);
+    assert_eq!(current[0].agent_did, test_agent_did("wallet2"));
+    assert_eq!(current[0].added_in, "replace-1");
+    assert_eq!(current[0].for_parties, vec![test_agent_did("alice")]);
+
+    let history = storage
+        .get_transaction_participants("participants-tx", true)
+        .await
+        .unwrap();
+    assert_eq!(history.len(), 3);
+    let wallet1 = &history[1];
+    assert_eq!(wallet1.agent_did, test_agent_did("wallet1"));
+    assert_eq!(wallet1.removed_in.as_deref(), Some("replace-1"));
+    assert_eq!(wallet1.replaced_by, Some(test_agent_did("wallet2")));
+    let compliance = &history[0];
+    assert_eq!(compliance.removed_by, Some(test_agent_did("bob")));
+    assert!(compliance.replaced_by.is_none());
+
+    // Removed agents no longer have to authorize the transaction
+    let agents = storage
+        .get_transaction_agents("participants-tx")
+        .await
+        .unwrap();
+    assert_eq!(agents.len(), 1);
+    assert_eq!(agents[0].0, test_agent_did("wallet2"));
+}
+
+#[tokio::test]
+async fn test_payment_invoice_documents_are_stored() {
+    use tap_msg::message::invoice::{document_digest, DocumentReference, Invoice, LineItem};
+    use tap_msg::message::payment::InvoiceReference;
+    use tap_node::storage::DocumentStatus;
+
+    let storage = Arc::new(Storage::new(Some(":memory:".into())).await.unwrap());
+    let event_bus = Arc::new(EventBus::new());
+    let agents = Arc::new(AgentRegistry::new(None));
+    let state_processor = StandardTransactionProcessor::new(
+        storage.clone(),
+        event_bus,
+        agents,
+        DecisionMode::AutoApprove,
+    );
+
+    let pdf = b"%PDF-1.7 invoice INV-42".to_vec();
+    let mut tampered = DocumentReference::embedded("receipt", "application/pdf", b"receipt");
+    tampered.hash = Some(document_digest(b"another receipt"));
+    let mut invoice = Invoice::new(
+        "INV-42".to_string(),
+        "2025-04-20".to_string(),
+        "USD".to_string(),
+        vec![LineItem {
+            id: "1".to_string(),
+            description: "Consulting".to_string(),
+            quantity: 1.0,
+            unit_code: None,
+            unit_price: 50.0,
+            line_total: 50.0,
+            tax_category: None,
+            name: None,
+            image: None,
+            url: None,
+        }],
+        50.0,
+    );
+    invoice.additional_document_reference = Some(vec![
+        DocumentReference::embedded("invoice-pdf", "application/pdf", &pdf),
+        tampered,
+        DocumentReference::external("contract", "https://example.com/contract.pdf", b"terms")
+            .with_document_type("Contract"),
+    ]);
+
+    let payment = Payment {
+        asset: Some(test_asset()),
+        amount: "50.0".to_string(),
+        currency_code: None,
+        supported_assets: None,
+        customer: Some(test_party("customer1")),
+        merchant: test_party("merchant1"),
+        transaction_id: Some("invoice-payment-001".to_string()),
+        memo: None,
+        expiry: None,
+        invoice: Some(InvoiceReference::Object(Box::new(invoice))),
+        agents: vec![test_agent("merchant_agent", "merchant", "merchant1")],
+        connection_id: None,
+        fallback_settlement_addresses: None,
+        metadata: std::collections::HashMap::new(),
+    };
+    let mut plain_message = payment
+        .to_didcomm(&test_agent_did("merchant_agent"))
+        .unwrap();
+    plain_message.id = "invoice-payment-001".to_string();
+    state_processor
+        .process_message(&plain_message)
+        .await
+        .unwrap();
+
+    let documents = storage.list_documents("invoice-payment-001").await.unwrap();
+    let statuses: Vec<_> = documents
+        .iter()
+        .map(|d| (d.document_id.as_str(), d.status))
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![
+            ("invoice-pdf", DocumentStatus::Verified),
+            ("receipt", DocumentStatus::Mismatch),
+            ("contract", DocumentStatus::Unverified),
+        ]
+    );
+    assert!(documents[1].content.is_none());
+
+    let stored = storage
+        .get_document("invoice-payment-001", "invoice-pdf")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.content, Some(pdf));
+    assert_eq!(stored.mime_type.as_deref(), Some("application/pdf"));
+    assert_eq!(stored.message_id, "invoice-payment-001");
+
+    let contract = storage
+        .get_document("invoice-payment-001", "contract")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        contract.url.as_deref(),
+        Some("https://example.com/contract.pdf")
+    );
+    assert_eq!(contract.hash, Some(document_digest(b"terms")));
+    assert!(storage
+        .get_document("invoice-payment-001", "missing")
+        .await
+        .unwrap()
+        .is_none());
+
+    // Processing the Payment again does not duplicate its documents
+    state_processor
+        .process_message(&plain_message)
+        .await
+        .unwrap();
+    assert_eq!(
+        storage
+            .list_documents("invoice-payment-001")
+            .await
+            .unwrap()
+            .len(),
+        3
+    );
+}
+
+/// Test that settlements in another asset are checked against the rate
+#[tokio::test]
+async fn test_cross_asset_settlement_is_checked() {
+    use tap_msg::message::Settle;
+    use tap_node::event::NodeEvent;
+    use tap_node::settlement_conversion::SettlementConversionConfig;
+    use tap_node::storage::ConversionStatus;
+    use tap_node::valuation::StaticRates;
+
+    let storage = Arc::new(Storage::new(Some(":memory:".into())).await.unwrap());
+    let event_bus = Arc::new(EventBus::new());
+    let mut events = event_bus.subscribe_channel();
+    let usdc = test_asset().to_string();
+    let rates = StaticRates::new("test-feed").with_rate(usdc.clone(), "USD", 0.998);
+    let state_processor = StandardTransactionProcessor::new(
+        storage.clone(),
+        event_bus.clone(),
+        Arc::new(AgentRegistry::new(None)),
+        DecisionMode::EventBus,
+    )
+    .with_settlement_conversion(
+        SettlementConversionConfig::new(Arc::new(rates), "USD").with_max_slippage(0.005),
+    );
+
+    for (id, settled_amount) in [("fx-payment-1", "100.10"), ("fx-payment-2", "97.00")] {
+        let payment = Payment {
+            asset: None,
+            amount: "100.00".to_string(),
+            currency_code: Some("USD".to_string()),
+            supported_assets: Some(vec![SupportedAsset::Simple(test_asset())]),
+            customer: Some(test_party("customer1")),
+            merchant: test_party("merchant1"),
+            transaction_id: Some(id.to_string()),
+            memo: None,
+            expiry: None,
+            invoice: None,
+            agents: vec![test_agent("merchant_agent", "merchant", "merchant1")],
+            connection_id: None,
+            fallback_settlement_addresses: None,
+            metadata: std::collections::HashMap::new(),
+        };
+        let mut plain_message = payment
+            .to_didcomm(&test_agent_did("merchant_agent"))
+            .unwrap();
+        plain_message.id = id.to_string();
+        state_processor
+            .process_message(&plain_message)
+            .await
+            .unwrap();
+
+        let settle = Settle::new(
+            id,
+            "eip155:1:tx/0x3edb98c24d46d148eb926c714f4fbaa117c47b0c0821f38bfce9763604457c33",
+        )
+        .with_settled_asset(&usdc, settled_amount);
+        let settle_message = settle
+            .to_didcomm(&test_agent_did("customer_agent"))
+            .unwrap();
+        state_processor
+            .process_message(&settle_message)
+            .await
+            .unwrap();
+    }
+
+    let within = storage
+        .get_settlement_conversion("fx-payment-1")
+        .await
+        .unwrap()
+        .expect("conversion should be recorded");
+    assert_eq!(within.requested_asset, "USD");
+    assert_eq!(within.settled_asset, usdc);
+    assert_eq!(within.settled_amount, "100.10");
+    assert_eq!(within.status, ConversionStatus::WithinTolerance);
+    assert!(storage
+        .list_transaction_warnings("fx-payment-1")
+        .await
+        .unwrap()
+        .is_empty());
+
+    let out = storage
+        .get_settlement_conversion("fx-payment-2")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(out.status, ConversionStatus::OutOfTolerance);
+    assert!(out.slippage.unwrap() < -0.02);
+    let warnings = storage
+        .list_transaction_warnings("fx-payment-2")
+        .await
+        .unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].rule, "settlement_slippage");
+    assert_eq!(warnings[0].action, "warn");
+
+    let flagged = storage
+        .list_settlement_conversions(Some(ConversionStatus::OutOfTolerance), 10, 0)
+        .await
+        .unwrap();
+    assert_eq!(flagged.len(), 1);
+    assert_eq!(flagged[0].transaction_id, "fx-payment-2");
+
+    let mut out_of_tolerance = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let NodeEvent::SettlementOutOfTolerance {
+            transaction_id,
+            settled_amount,
+            ..
+        } = event.as_ref()
+        {
+            out_of_tolerance.push((transaction_id.clone(), settled_amount.clone()));
+        }
+    }
+    assert_eq!(
+        out_of_tolerance,
+        vec![("fx-payment-2".to_string(), "97.00".to_string())]
+    );
+}
+
+/// Test that settlement addresses reused across transactions are flagged
+#[tokio::test]
+async fn test_reused_settlement_address_is_flagged() {
+    use tap_msg::message::Authorize;
+    use tap_node::address_reuse::AddressReuseConfig;
+    use tap_node::event::NodeEvent;
+
+    let storage = Arc::new(Storage::new(Some(":memory:".into())).await.unwrap());
+    let event_bus = Arc::new(EventBus::new());
+    let mut events = event_bus.subscribe_channel();
+    let state_processor = StandardTransactionProcessor::new(
+        storage.clone(),
+        event_bus.clone(),
+        Arc::new(AgentRegistry::new(None)),
+        DecisionMode::EventBus,
+    )
+    .with_address_reuse(AddressReuseConfig::new());
+
+    // The beneficiary's agent gives the same address twice for bob, then
+    // another agent gives it for erin
+    let address = "eip155:1:0x742d35Cc6634C0532925a3b844Bc454e4438f44e";
+    for (id, beneficiary, beneficiary_agent) in [
+        ("reuse-tx-1", "bob", "vasp_b"),
+        ("reuse-tx-2", "bob", "vasp_b"),
+        ("reuse-tx-3", "erin", "vasp_c"),
+    ] {
+        let transfer = Transfer {
+            asset: test_asset(),
+            originator: Some(test_party("alice")),
+            beneficiary: Some(test_party(beneficiary)),
+            amount: "100.0".to_string(),
+            agents: vec![test_agent("vasp_a", "originator", "alice")],
+            memo: None,
+            settlement_id: None,
+            expiry: None,
+            transaction_value: None,
+            transaction_id: Some(id.to_string()),
+            connection_id: None,
+            metadata: std::collections::HashMap::new(),
+        };
+        let mut plain_message = transfer.to_didcomm(&test_agent_did("vasp_a")).unwrap();
+        plain_message.id = id.to_string();
+        state_processor
+            .process_message(&plain_message)
+            .await
+            .unwrap();
+
+        let authorize = Authorize::with_settlement_address(id, address)
+            .to_didcomm(&test_agent_did(beneficiary_agent))
+            .unwrap();
+        state_processor.process_message(&authorize).await.unwrap();
+    }
+
+    for id in ["reuse-tx-1", "reuse-tx-2"] {
+        assert!(storage
+            .list_transaction_warnings(id)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+    let warnings = storage
+        .list_transaction_warnings("reuse-tx-3")
+        .await
+        .unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].rule, "address_reuse");
+
+    let sightings = storage.list_address_sightings(address).await.unwrap();
+    assert_eq!(sightings.len(), 3);
+    assert_eq!(sightings[0].address, address.to_lowercase());
+    assert_eq!(sightings[0].provided_by, test_agent_did("vasp_b"));
+    assert_eq!(
+        sightings[2].beneficiary_did.as_deref(),
+        Some(test_agent_did("erin").as_str())
+    );
+    assert_eq!(
+        storage
+            .list_transaction_addresses("reuse-tx-3")
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+
+    let reused = storage.list_reused_addresses(10, 0).await.unwrap();
+    assert_eq!(reused.len(), 1);
+    assert_eq!(reused[0].transactions, 3);
+    assert_eq!(reused[0].providers, 2);
+    assert_eq!(reused[0].originators, 1);
+    assert_eq!(reused[0].beneficiaries, 2);
+
+    let mut detected = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let NodeEvent::AddressReuseDetected {
+            transaction_id,
+            kind,
+            ..
+        } = event.as_ref()
+        {
+            detected.push((transaction_id.clone(), kind.clone()));
+        }
+    }
+    assert_eq!(
+        detected,
+        vec![("reuse-tx-3".to_string(), "shared_address".to_string())]
+    );
+}
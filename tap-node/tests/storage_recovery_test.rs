@@ -0,0 +1,67 @@
+//! Tests for detecting and recovering corrupt databases at startup
+
+use tap_node::storage::{RecoveryStrategy, StorageRecovery};
+use tap_node::{NodeConfig, NodeEvent, TapNode};
+use tempfile::TempDir;
+
+fn corrupt_node(recovery: StorageRecovery) -> (TempDir, TapNode) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("node.db");
+    std::fs::write(&db_path, vec![0x42; 8192]).unwrap();
+
+    let config = NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(db_path),
+        storage_recovery: recovery,
+        ..Default::default()
+    };
+    (temp_dir, TapNode::new(config))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_corrupt_database_fails_startup_by_default() {
+    let (_temp_dir, mut node) = corrupt_node(StorageRecovery::default());
+    let mut events = node.event_bus().subscribe_channel();
+
+    let result = node.init_storage().await;
+    assert!(result.unwrap_err().to_string().contains("corrupt"));
+    assert!(node.storage().is_none());
+
+    assert!(matches!(
+        events.recv().await.unwrap().as_ref(),
+        NodeEvent::StorageCorruptionDetected { .. }
+    ));
+    match events.recv().await.unwrap().as_ref() {
+        NodeEvent::StorageRecoveryFailed { errors, .. } => assert!(errors.is_empty()),
+        other => panic!("Unexpected event {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_corrupt_database_is_quarantined_at_startup() {
+    let (temp_dir, mut node) =
+        corrupt_node(StorageRecovery::new(vec![RecoveryStrategy::Quarantine]));
+    let mut events = node.event_bus().subscribe_channel();
+
+    node.init_storage().await.unwrap();
+    let storage = node.storage().unwrap();
+    assert!(storage.list_transactions(10, 0).await.unwrap().is_empty());
+
+    assert!(matches!(
+        events.recv().await.unwrap().as_ref(),
+        NodeEvent::StorageCorruptionDetected { .. }
+    ));
+    match events.recv().await.unwrap().as_ref() {
+        NodeEvent::StorageRecovered {
+            strategy,
+            quarantined_path,
+            ..
+        } => {
+            assert_eq!(strategy, "quarantine");
+            let quarantined = quarantined_path.as_deref().unwrap();
+            assert!(quarantined.starts_with(&*temp_dir.path().join("quarantine").to_string_lossy()));
+            assert_eq!(std::fs::read(quarantined).unwrap(), vec![0x42; 8192]);
+        }
+        other => panic!("Unexpected event {:?}", other),
+    }
+}
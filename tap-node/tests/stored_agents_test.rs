@@ -0,0 +1,78 @@
+//! Tests for registering agents from key storage at startup
+
+use std::sync::Arc;
+use tap_agent::did::DIDGenerationOptions;
+use tap_agent::{AgentKeyManager, KeyStorage, TapAgent};
+use tap_node::{NodeConfig, NodeEvent, TapNode};
+use tempfile::TempDir;
+
+/// Write a key storage with `count` keys to `dir/keys.json`
+fn store_keys(dir: &TempDir, count: usize) -> Vec<String> {
+    let key_manager = AgentKeyManager::new();
+    let mut storage = KeyStorage::new();
+    for _ in 0..count {
+        let key = key_manager
+            .generate_key_without_save(DIDGenerationOptions::default())
+            .unwrap();
+        storage.add_key(KeyStorage::from_generated_key(&key));
+    }
+    storage.save_to_path(&dir.path().join("keys.json")).unwrap();
+
+    let mut dids: Vec<String> = storage.keys.keys().cloned().collect();
+    dids.sort();
+    dids
+}
+
+fn config(dir: &TempDir, auto_register_stored_agents: bool) -> NodeConfig {
+    NodeConfig {
+        tap_root: Some(dir.path().to_path_buf()),
+        storage_path: Some(dir.path().join("node.db")),
+        key_storage_path: Some(dir.path().to_path_buf()),
+        auto_register_stored_agents,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_stored_agents_registered_on_init() {
+    let dir = TempDir::new().unwrap();
+    let dids = store_keys(&dir, 2);
+
+    let mut node = TapNode::new(config(&dir, true));
+    let mut events = node.event_bus().subscribe_channel();
+    node.init_storage().await.unwrap();
+
+    let mut registered = node.list_agents();
+    registered.sort();
+    assert_eq!(registered, dids);
+
+    let mut announced = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let NodeEvent::AgentRegistered { did } = event.as_ref() {
+            announced.push(did.clone());
+        }
+    }
+    announced.sort();
+    assert_eq!(announced, dids);
+}
+
+#[tokio::test]
+async fn test_register_stored_agents_skips_registered() {
+    let dir = TempDir::new().unwrap();
+    let dids = store_keys(&dir, 2);
+
+    let mut node = TapNode::new(config(&dir, false));
+    node.init_storage().await.unwrap();
+    assert!(node.list_agents().is_empty());
+
+    let (ephemeral, ephemeral_did) = TapAgent::from_ephemeral_key().await.unwrap();
+    node.register_agent(Arc::new(ephemeral)).await.unwrap();
+
+    let registered = node.register_stored_agents().await.unwrap();
+    assert_eq!(registered, dids);
+    assert_eq!(node.list_agents().len(), 3);
+    assert!(node.agents().has_agent(&ephemeral_did));
+
+    // A second scan finds nothing new
+    assert!(node.register_stored_agents().await.unwrap().is_empty());
+}
@@ -0,0 +1,53 @@
+//! Tests for retiring the thread keys of ended transactions
+
+use std::sync::Arc;
+use std::time::Duration;
+use tap_agent::TapAgent;
+use tap_node::{NodeConfig, TapNode};
+use tempfile::TempDir;
+
+#[tokio::test]
+async fn test_thread_keys_retired_when_transaction_ends() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut node = TapNode::new(NodeConfig {
+        tap_root: Some(temp_dir.path().to_path_buf()),
+        storage_path: Some(temp_dir.path().join("node.db")),
+        ..Default::default()
+    });
+    node.init_storage().await.unwrap();
+
+    let (agent, did) = TapAgent::from_ephemeral_key().await.unwrap();
+    let key_manager = agent.key_manager().clone();
+    node.register_agent(Arc::new(agent)).await.unwrap();
+    key_manager.thread_key(&did, "transfer-1").unwrap();
+    key_manager.thread_key(&did, "transfer-2").unwrap();
+
+    // Intermediate states keep the keys
+    let event_bus = node.event_bus();
+    event_bus
+        .publish_transaction_state_changed(
+            "transfer-1".to_string(),
+            "received".to_string(),
+            "ready_to_settle".to_string(),
+            None,
+        )
+        .await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(key_manager.thread_ids().len(), 2);
+
+    event_bus
+        .publish_transaction_state_changed(
+            "transfer-1".to_string(),
+            "ready_to_settle".to_string(),
+            "settled".to_string(),
+            None,
+        )
+        .await;
+    for _ in 0..50 {
+        if key_manager.thread_ids().len() == 1 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(key_manager.thread_ids(), vec!["transfer-2".to_string()]);
+}
@@ -6,7 +6,7 @@ use tap_node::message::processor::{PlainMessageProcessor, ValidationPlainMessage
 
 #[tokio::test]
 async fn test_timestamp_in_seconds_passes_validation() {
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a message with timestamp in seconds (common in some systems)
     let message = PlainMessage {
@@ -37,7 +37,7 @@ async fn test_timestamp_in_seconds_passes_validation() {
 
 #[tokio::test]
 async fn test_timestamp_in_milliseconds_passes_validation() {
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a message with timestamp in milliseconds (DIDComm standard)
     let message = PlainMessage {
@@ -68,7 +68,7 @@ async fn test_timestamp_in_milliseconds_passes_validation() {
 
 #[tokio::test]
 async fn test_future_timestamp_seconds_fails_validation() {
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a message with future timestamp in seconds
     let message = PlainMessage {
@@ -99,7 +99,7 @@ async fn test_future_timestamp_seconds_fails_validation() {
 
 #[tokio::test]
 async fn test_future_timestamp_milliseconds_fails_validation() {
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a message with future timestamp in milliseconds
     let message = PlainMessage {
@@ -130,7 +130,7 @@ async fn test_future_timestamp_milliseconds_fails_validation() {
 
 #[tokio::test]
 async fn test_slightly_future_timestamp_within_tolerance() {
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a message with timestamp 1 minute in the future (within 5 minute tolerance)
     let message_seconds = PlainMessage {
@@ -10,7 +10,7 @@ use tap_node::message::processor::{PlainMessageProcessor, ValidationPlainMessage
 #[tokio::test]
 async fn test_valid_message_passes_validation() {
     // Create a processor
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a valid message
     let message = PlainMessage {
@@ -48,7 +48,7 @@ async fn test_valid_message_passes_validation() {
 #[tokio::test]
 async fn test_missing_id_fails_validation() {
     // Create a processor
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a message missing the required ID field
     let message = PlainMessage {
@@ -81,7 +81,7 @@ async fn test_missing_id_fails_validation() {
 #[tokio::test]
 async fn test_missing_type_fails_validation() {
     // Create a processor
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a message missing the type field
     let message = PlainMessage {
@@ -114,7 +114,7 @@ async fn test_missing_type_fails_validation() {
 #[tokio::test]
 async fn test_invalid_from_did_fails_validation() {
     // Create a processor
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a message with an invalid FROM DID
     let message = PlainMessage {
@@ -147,7 +147,7 @@ async fn test_invalid_from_did_fails_validation() {
 #[tokio::test]
 async fn test_invalid_to_did_fails_validation() {
     // Create a processor
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a message with an invalid TO DID
     let message = PlainMessage {
@@ -180,7 +180,7 @@ async fn test_invalid_to_did_fails_validation() {
 #[tokio::test]
 async fn test_future_timestamp_fails_validation() {
     // Create a processor
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a message with a timestamp too far in the future
     let message = PlainMessage {
@@ -213,7 +213,7 @@ async fn test_future_timestamp_fails_validation() {
 #[tokio::test]
 async fn test_unknown_message_type_fails_validation() {
     // Create a processor
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a message with an unknown type
     let message = PlainMessage {
@@ -246,7 +246,7 @@ async fn test_unknown_message_type_fails_validation() {
 #[tokio::test]
 async fn test_missing_body_fails_validation() {
     // Create a processor
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a TAP message missing a required body
     let message = PlainMessage {
@@ -275,7 +275,7 @@ async fn test_missing_body_fails_validation() {
 #[tokio::test]
 async fn test_invalid_body_format_fails_validation() {
     // Create a processor
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a DIDComm message with an invalid body format
     let message = PlainMessage {
@@ -304,7 +304,7 @@ async fn test_invalid_body_format_fails_validation() {
 #[tokio::test]
 async fn test_empty_pthid_fails_validation() {
     // Create a processor
-    let processor = ValidationPlainMessageProcessor;
+    let processor = ValidationPlainMessageProcessor::default();
 
     // Create a message with an empty pthid
     let message = PlainMessage {
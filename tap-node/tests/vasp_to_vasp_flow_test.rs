@@ -0,0 +1,283 @@
+//! End-to-end tests of two nodes exchanging a transfer over loopback HTTP
+
+use std::collections::HashMap;
+use std::time::Duration;
+use tap_ivms101::builder::{NaturalPersonBuilder, NaturalPersonNameBuilder};
+use tap_ivms101::Person;
+use tap_msg::didcomm::Attachment;
+use tap_msg::message::{
+    Agent as MessageAgent, Authorize, DIDCommPresentation, Party, Settle, TapMessageBody, Transfer,
+};
+use tap_node::state_machine::fsm::DecisionMode;
+use tap_node::testing::TestNode;
+use tap_node::{NodeConfig, NodeEvent};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Network {
+    originator: TestNode,
+    beneficiary: TestNode,
+    originating_vasp: String,
+    beneficiary_vasp: String,
+}
+
+async fn network() -> Network {
+    let config = NodeConfig {
+        decision_mode: DecisionMode::EventBus,
+        ..Default::default()
+    };
+    let mut originator = TestNode::start("originator", config.clone()).await.unwrap();
+    let mut beneficiary = TestNode::start("beneficiary", config).await.unwrap();
+    let originating_vasp = originator.add_agent().await.unwrap();
+    let beneficiary_vasp = beneficiary.add_agent().await.unwrap();
+    originator.connect(&beneficiary);
+
+    Network {
+        originator,
+        beneficiary,
+        originating_vasp,
+        beneficiary_vasp,
+    }
+}
+
+impl Network {
+    async fn send_transfer(&self) -> String {
+        let transfer = Transfer {
+            asset: "eip155:1/slip44:60".parse().unwrap(),
+            originator: Some(Party::new("did:example:alice")),
+            beneficiary: Some(Party::new("did:example:bob")),
+            amount: "1.25".to_string(),
+            agents: vec![
+                MessageAgent::new(
+                    &self.originating_vasp,
+                    "originating_vasp",
+                    "did:example:alice",
+                ),
+                MessageAgent::new(
+                    &self.beneficiary_vasp,
+                    "beneficiary_vasp",
+                    "did:example:bob",
+                ),
+            ],
+            memo: None,
+            settlement_id: None,
+            expiry: None,
+            transaction_value: None,
+            transaction_id: None,
+            connection_id: None,
+            metadata: HashMap::new(),
+        };
+        let message = transfer
+            .to_didcomm_with_route(&self.originating_vasp, [self.beneficiary_vasp.as_str()])
+            .unwrap();
+        let transaction_id = message.id.clone();
+        self.originator
+            .node()
+            .send_message(self.originating_vasp.clone(), message)
+            .await
+            .unwrap();
+        transaction_id
+    }
+
+    async fn present(&self, transaction_id: &str, originator: serde_json::Value) {
+        let credentials = serde_json::json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1", "https://intervasp.org/ivms101"],
+            "type": ["VerifiablePresentation"],
+            "verifiableCredential": [{
+                "type": ["VerifiableCredential", "TravelRuleCredential"],
+                "credentialSubject": { "originator": originator }
+            }]
+        });
+        let attachment = Attachment::json(credentials)
+            .media_type("application/json".to_string())
+            .format("dif/presentation-exchange/submission@v1.0".to_string())
+            .finalize();
+        let message = DIDCommPresentation::new(
+            vec!["dif/presentation-exchange/submission@v1.0".to_string()],
+            vec![attachment],
+            Some(transaction_id.to_string()),
+        )
+        .to_didcomm_with_route(&self.originating_vasp, [self.beneficiary_vasp.as_str()])
+        .unwrap();
+        self.originator
+            .node()
+            .send_message(self.originating_vasp.clone(), message)
+            .await
+            .unwrap();
+    }
+
+    async fn status(&self, node: &TestNode, transaction_id: &str) -> String {
+        node.node()
+            .storage()
+            .unwrap()
+            .get_transaction_by_id(transaction_id)
+            .await
+            .unwrap()
+            .unwrap()
+            .status
+            .to_string()
+    }
+}
+
+fn alice() -> serde_json::Value {
+    let person = NaturalPersonBuilder::new()
+        .name(
+            NaturalPersonNameBuilder::new()
+                .legal_name("Smith", "Alice")
+                .build()
+                .unwrap(),
+        )
+        .country_of_residence("US")
+        .build()
+        .unwrap();
+    serde_json::to_value(Person::NaturalPerson(person)).unwrap()
+}
+
+#[tokio::test]
+async fn test_transfer_authorize_settle_between_nodes() {
+    let network = network().await;
+    let transaction_id = network.send_transfer().await;
+
+    let event = network
+        .beneficiary
+        .wait_for_event(TIMEOUT, |event| {
+            matches!(event, NodeEvent::DecisionRequired { .. })
+        })
+        .await
+        .unwrap();
+    match event.as_ref() {
+        NodeEvent::DecisionRequired {
+            transaction_id: id,
+            pending_agents,
+            ..
+        } => {
+            assert_eq!(id, &transaction_id);
+            assert_eq!(pending_agents.len(), 2);
+        }
+        other => panic!("Unexpected event: {:?}", other),
+    }
+
+    network.present(&transaction_id, alice()).await;
+    let event = network
+        .beneficiary
+        .wait_for_event(TIMEOUT, |event| {
+            matches!(event, NodeEvent::TravelRuleDataValidated { .. })
+        })
+        .await
+        .unwrap();
+    match event.as_ref() {
+        NodeEvent::TravelRuleDataValidated {
+            sender,
+            role,
+            report,
+            ..
+        } => {
+            assert_eq!(sender, &network.originating_vasp);
+            assert_eq!(role.as_deref(), Some("originator"));
+            assert!(report.is_valid(), "{:?}", report);
+        }
+        other => panic!("Unexpected event: {:?}", other),
+    }
+
+    network
+        .originator
+        .node()
+        .send_message(
+            network.originating_vasp.clone(),
+            Authorize::new(&transaction_id)
+                .to_didcomm_with_route(
+                    &network.originating_vasp,
+                    [network.beneficiary_vasp.as_str()],
+                )
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    network
+        .beneficiary
+        .node()
+        .send_message(
+            network.beneficiary_vasp.clone(),
+            Authorize::with_settlement_address(
+                &transaction_id,
+                "eip155:1:0x1234567890123456789012345678901234567890",
+            )
+            .to_didcomm_with_route(
+                &network.beneficiary_vasp,
+                [network.originating_vasp.as_str()],
+            )
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    network
+        .originator
+        .wait_for_event(TIMEOUT, |event| {
+            matches!(
+                event,
+                NodeEvent::DecisionRequired { transaction_state, .. }
+                    if transaction_state == "ready_to_settle"
+            )
+        })
+        .await
+        .unwrap();
+
+    network
+        .originator
+        .node()
+        .send_message(
+            network.originating_vasp.clone(),
+            Settle::with_amount(&transaction_id, "eip155:1:tx/0xabc123", "1.25")
+                .to_didcomm_with_route(
+                    &network.originating_vasp,
+                    [network.beneficiary_vasp.as_str()],
+                )
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    for node in [&network.originator, &network.beneficiary] {
+        node.wait_for_event(TIMEOUT, |event| {
+            matches!(
+                event,
+                NodeEvent::TransactionStateChanged { new_state, .. } if new_state == "settled"
+            )
+        })
+        .await
+        .unwrap();
+        assert_eq!(network.status(node, &transaction_id).await, "confirmed");
+    }
+}
+
+#[tokio::test]
+async fn test_invalid_travel_rule_data_is_reported() {
+    let network = network().await;
+    let transaction_id = network.send_transfer().await;
+
+    let mut originator = alice();
+    originator["naturalPerson"]["countryOfResidence"] = "USA".into();
+    network.present(&transaction_id, originator).await;
+
+    let event = network
+        .beneficiary
+        .wait_for_event(TIMEOUT, |event| {
+            matches!(event, NodeEvent::TravelRuleDataValidated { .. })
+        })
+        .await
+        .unwrap();
+    match event.as_ref() {
+        NodeEvent::TravelRuleDataValidated { report, .. } => {
+            assert!(!report.is_valid());
+            assert!(report
+                .errors()
+                .any(|violation| violation.path == "/naturalPerson/countryOfResidence"));
+        }
+        other => panic!("Unexpected event: {:?}", other),
+    }
+    assert_eq!(
+        network.status(&network.beneficiary, &transaction_id).await,
+        "pending"
+    );
+}
@@ -0,0 +1,209 @@
+//! Tests for webhooks scoped to a transaction, counterparty or connection
+
+use serde_json::Value;
+use std::time::Duration;
+use tap_msg::didcomm::PlainMessage;
+use tap_node::event::webhook::{Webhook, WebhookScope};
+use tap_node::{NodeConfig, NodeEvent, TapNode};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+/// Serve a webhook endpoint, forwarding the body of each POST
+async fn endpoint() -> (String, mpsc::UnboundedReceiver<Value>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let mut stream = BufReader::new(stream);
+                loop {
+                    let mut content_length = 0;
+                    let mut line = String::new();
+                    if stream.read_line(&mut line).await.unwrap_or(0) == 0 {
+                        return;
+                    }
+                    loop {
+                        line.clear();
+                        stream.read_line(&mut line).await.unwrap();
+                        if line == "\r\n" {
+                            break;
+                        }
+                        if let Some((name, value)) = line.split_once(':') {
+                            if name.eq_ignore_ascii_case("content-length") {
+                                content_length = value.trim().parse().unwrap();
+                            }
+                        }
+                    }
+                    let mut body = vec![0; content_length];
+                    stream.read_exact(&mut body).await.unwrap();
+                    tx.send(serde_json::from_slice(&body).unwrap()).unwrap();
+                    stream
+                        .get_mut()
+                        .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                        .await
+                        .unwrap();
+                }
+            });
+        }
+    });
+    (url, rx)
+}
+
+async fn next_delivery(deliveries: &mut mpsc::UnboundedReceiver<Value>) -> Value {
+    tokio::time::timeout(Duration::from_secs(5), deliveries.recv())
+        .await
+        .expect("No delivery")
+        .unwrap()
+}
+
+/// Wait until the registry has handled everything published so far
+async fn settle(node: &TapNode) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let metrics = node.event_bus().metrics().await;
+            if metrics
+                .subscribers
+                .iter()
+                .filter(|s| s.name == "webhooks")
+                .all(|s| s.queue_depth == 0)
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+}
+
+fn message(id: &str, pthid: Option<&str>, from: &str, to: &str) -> PlainMessage {
+    let mut message = PlainMessage::new(
+        id.to_string(),
+        "https://tap.rsvp/schema/1.0#Authorize".to_string(),
+        serde_json::json!({}),
+        from.to_string(),
+    )
+    .with_recipient(to);
+    message.pthid = pthid.map(String::from);
+    message
+}
+
+async fn state_changed(node: &TapNode, transaction_id: &str, new_state: &str) {
+    node.event_bus()
+        .publish_transaction_state_changed(
+            transaction_id.to_string(),
+            "received".to_string(),
+            new_state.to_string(),
+            None,
+        )
+        .await;
+}
+
+#[tokio::test]
+async fn test_transaction_webhook_receives_its_events_and_expires() {
+    let node = TapNode::new(NodeConfig::default());
+    let (url, mut deliveries) = endpoint().await;
+
+    let webhook = Webhook::new(url, WebhookScope::Transaction("tx-1".to_string()));
+    assert!(webhook.expire_on_completion);
+    let webhook_id = node.register_webhook(webhook).await.unwrap();
+
+    state_changed(&node, "tx-other", "partially_authorized").await;
+    state_changed(&node, "tx-1", "partially_authorized").await;
+    let delivery = next_delivery(&mut deliveries).await;
+    assert_eq!(delivery["webhook_id"], webhook_id);
+    assert_eq!(delivery["event_type"], "transaction_state_changed");
+    assert_eq!(delivery["data"]["transaction_id"], "tx-1");
+
+    state_changed(&node, "tx-1", "settled").await;
+    let delivery = next_delivery(&mut deliveries).await;
+    assert_eq!(delivery["data"]["new_state"], "settled");
+
+    settle(&node).await;
+    assert!(node.webhooks().is_empty());
+    state_changed(&node, "tx-1", "reverted").await;
+    settle(&node).await;
+    assert!(deliveries.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_counterparty_and_connection_webhooks() {
+    let node = TapNode::new(NodeConfig::default());
+    let (counterparty_url, mut counterparty_deliveries) = endpoint().await;
+    let (connection_url, mut connection_deliveries) = endpoint().await;
+
+    let counterparty = Webhook::new(
+        counterparty_url,
+        WebhookScope::Counterparty("did:example:bob".to_string()),
+    );
+    assert!(!counterparty.expire_on_completion);
+    node.register_webhook(counterparty).await.unwrap();
+    let connection = Webhook::new(
+        connection_url,
+        WebhookScope::Connection("connect-1".to_string()),
+    )
+    .expiring();
+    let connection_id = node.register_webhook(connection).await.unwrap();
+    assert_eq!(node.webhooks().len(), 2);
+
+    // A transfer from bob, started in the Connect session
+    node.event_bus()
+        .publish_event(NodeEvent::MessageReceived {
+            message: message(
+                "tx-2",
+                Some("connect-1"),
+                "did:example:bob",
+                "did:example:alice",
+            ),
+            source: "did:example:bob".to_string(),
+        })
+        .await;
+    // A transfer from carol, unrelated to either webhook
+    node.event_bus()
+        .publish_event(NodeEvent::MessageReceived {
+            message: message("tx-3", None, "did:example:carol", "did:example:alice"),
+            source: "did:example:carol".to_string(),
+        })
+        .await;
+    state_changed(&node, "tx-3", "settled").await;
+    state_changed(&node, "tx-2", "settled").await;
+
+    for deliveries in [&mut counterparty_deliveries, &mut connection_deliveries] {
+        let delivery = next_delivery(deliveries).await;
+        assert_eq!(delivery["event_type"], "message_received_new");
+        assert_eq!(delivery["data"]["message"]["id"], "tx-2");
+        let delivery = next_delivery(deliveries).await;
+        assert_eq!(delivery["event_type"], "transaction_state_changed");
+        assert_eq!(delivery["data"]["transaction_id"], "tx-2");
+    }
+
+    // The connection webhook expired with its transaction
+    settle(&node).await;
+    let remaining = node.webhooks();
+    assert_eq!(remaining.len(), 1);
+    assert_ne!(remaining[0].id, connection_id);
+    assert!(counterparty_deliveries.try_recv().is_err());
+    assert!(connection_deliveries.try_recv().is_err());
+
+    assert!(node.unregister_webhook(&remaining[0].id));
+    assert!(node.webhooks().is_empty());
+}
+
+#[tokio::test]
+async fn test_webhook_url_must_be_http() {
+    let node = TapNode::new(NodeConfig::default());
+    assert!(node
+        .register_webhook(Webhook::new("ftp://example.com/hook", WebhookScope::All))
+        .await
+        .is_err());
+    assert!(node
+        .register_webhook(Webhook::new("not a url", WebhookScope::All))
+        .await
+        .is_err());
+    assert!(node.webhooks().is_empty());
+}
@@ -82,6 +82,7 @@ fn bench_message_conversion(c: &mut Criterion) {
 
     let reject_body = Reject {
         transaction_id: "test-transfer-id".to_string(),
+        code: None,
         reason: Some(
             "COMPLIANCE_FAILURE: Unable to comply with transfer requirements. Rejected for testing."
                 .to_string(),
@@ -6,6 +6,7 @@
 
 mod util;
 mod wasm_agent;
+mod webcrypto;
 
 use tap_agent::did::KeyType as TapKeyType;
 use wasm_bindgen::prelude::*;
@@ -88,3 +89,20 @@ pub fn generate_private_key(key_type_str: String) -> Result<String, JsValue> {
 pub fn generate_uuid() -> String {
     generate_uuid_v4()
 }
+
+/// Generates an ID with the configured ID strategy, as used for message IDs
+#[wasm_bindgen(js_name = generateId)]
+pub fn generate_id() -> String {
+    tap_msg::id::new_id()
+}
+
+/// Sets how message and transaction IDs are generated: "uuid-v4" (the
+/// default), "uuid-v7" or "ulid"
+#[wasm_bindgen(js_name = setIdStrategy)]
+pub fn set_id_strategy(strategy: String) -> Result<(), JsValue> {
+    let strategy = strategy
+        .parse::<tap_msg::id::IdStrategy>()
+        .map_err(|e| JsValue::from_str(&e))?;
+    tap_msg::id::set_id_generator(strategy.generator());
+    Ok(())
+}
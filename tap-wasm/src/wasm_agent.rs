@@ -1,4 +1,5 @@
 use crate::util::js_to_tap_message;
+use crate::webcrypto::{WebCryptoAlgorithm, WebCryptoKey};
 use js_sys::{Array, Object, Promise, Reflect};
 use std::sync::Arc;
 use tap_agent::agent::TapAgent;
@@ -6,7 +7,7 @@ use tap_agent::{
     did::DIDGenerationOptions,
     message::SecurityMode,
     message_packing::{PackOptions, UnpackOptions},
-    AgentConfig, AgentKeyManager, AgentKeyManagerBuilder, KeyType, Packable, Unpackable,
+    AgentConfig, AgentKey, AgentKeyManager, AgentKeyManagerBuilder, KeyType, Packable, Unpackable,
 };
 
 // Extension trait for TapAgent in WASM context
@@ -38,6 +39,8 @@ pub struct WasmTapAgent {
     debug: bool,
     /// Store the private key directly for export (temporary fix)
     private_key_hex: Option<String>,
+    /// Non-extractable WebCrypto key the agent signs with, if any
+    web_crypto_key: Option<Arc<WebCryptoKey>>,
 }
 
 #[wasm_bindgen]
@@ -95,6 +98,7 @@ impl WasmTapAgent {
                 nickname: None,
                 debug: false,
                 private_key_hex: Some(private_key_hex.clone()),
+                web_crypto_key: None,
             })
         }
 
@@ -182,9 +186,110 @@ impl WasmTapAgent {
             nickname,
             debug,
             private_key_hex: None,
+            web_crypto_key: None,
         })
     }
 
+    /// Creates an agent that signs with a non-extractable WebCrypto key
+    ///
+    /// Options are `keyType` (`Ed25519` or `P256`, default `Ed25519`),
+    /// `keyName` (the IndexedDB entry of the key, default `default`),
+    /// `nickname` and `debug`. The key stored under `keyName` is reused;
+    /// otherwise a key is generated and stored there. If the browser does not
+    /// support WebCrypto keys of that type, a software key is generated
+    /// instead, which is not persisted; `usesWebCrypto()` tells which.
+    #[wasm_bindgen(js_name = withWebCrypto)]
+    pub async fn with_web_crypto(options: JsValue) -> Result<WasmTapAgent, JsValue> {
+        let option = |name: &str| Reflect::get(&options, &JsValue::from_str(name)).ok();
+        let nickname = option("nickname").and_then(|value| value.as_string());
+        let debug = option("debug").is_some_and(|value| value.is_truthy());
+        let key_type = option("keyType")
+            .and_then(|value| value.as_string())
+            .unwrap_or_else(|| "Ed25519".to_string());
+        let key_name = option("keyName")
+            .and_then(|value| value.as_string())
+            .unwrap_or_else(|| "default".to_string());
+        let algorithm = WebCryptoAlgorithm::from_key_type(&key_type).ok_or_else(|| {
+            JsValue::from_str(&format!(
+                "Key type {} is not supported by WebCrypto",
+                key_type
+            ))
+        })?;
+
+        let key = match WebCryptoKey::load(&key_name).await {
+            Ok(Some(key)) if key.algorithm() == algorithm => Some(key),
+            Ok(Some(_)) => {
+                return Err(JsValue::from_str(&format!(
+                    "Key {} stored in IndexedDB is not a {} key",
+                    key_name, key_type
+                )))
+            }
+            Ok(None) | Err(_) => match WebCryptoKey::generate(algorithm).await {
+                Ok(key) => {
+                    if let Err(e) = key.save(&key_name).await {
+                        console::warn_1(&JsValue::from_str(&format!(
+                            "WebCrypto key {} is not persisted: {}",
+                            key_name, e
+                        )));
+                    }
+                    Some(key)
+                }
+                Err(e) => {
+                    console::warn_1(&JsValue::from_str(&format!(
+                        "Falling back to a software {} key: {}",
+                        key_type, e
+                    )));
+                    None
+                }
+            },
+        };
+
+        let Some(key) = key else {
+            let private_key = crate::generate_private_key(key_type.clone())?;
+            let mut agent = WasmTapAgent::from_private_key(private_key, key_type).await?;
+            agent.nickname = nickname;
+            agent.debug = debug;
+            return Ok(agent);
+        };
+
+        let key = Arc::new(key);
+        let key_manager = AgentKeyManagerBuilder::new()
+            .add_signing_key(key.clone())
+            .build()
+            .map_err(|e| JsValue::from_str(&format!("Failed to build key manager: {}", e)))?;
+        let agent_config = AgentConfig::new(key.did().to_string()).with_debug(debug);
+        let agent = TapAgent::new(agent_config, Arc::new(key_manager));
+
+        if debug {
+            console::log_1(&JsValue::from_str(&format!(
+                "Created WASM TAP Agent with WebCrypto key {} and DID: {}",
+                key_name, agent.config.agent_did
+            )));
+        }
+
+        Ok(WasmTapAgent {
+            agent,
+            nickname,
+            debug,
+            private_key_hex: None,
+            web_crypto_key: Some(key),
+        })
+    }
+
+    /// Deletes a WebCrypto key stored in IndexedDB
+    #[wasm_bindgen(js_name = deleteWebCryptoKey)]
+    pub async fn delete_web_crypto_key(key_name: String) -> Result<(), JsValue> {
+        WebCryptoKey::delete(&key_name)
+            .await
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Whether the agent signs with a non-extractable WebCrypto key
+    #[wasm_bindgen(js_name = usesWebCrypto)]
+    pub fn uses_web_crypto(&self) -> bool {
+        self.web_crypto_key.is_some()
+    }
+
     /// Gets the agent's DID
     pub fn get_did(&self) -> String {
         self.agent.config.agent_did.clone()
@@ -198,6 +303,12 @@ impl WasmTapAgent {
     /// Export the agent's private key as a hex string
     #[wasm_bindgen(js_name = exportPrivateKey)]
     pub fn export_private_key(&self) -> Result<String, JsValue> {
+        if self.web_crypto_key.is_some() {
+            return Err(JsValue::from_str(
+                "The agent's WebCrypto key is not extractable",
+            ));
+        }
+
         // If we have a stored private key (from from_private_key method), use it directly
         if let Some(stored_key) = &self.private_key_hex {
             return Ok(stored_key.clone());
@@ -217,6 +328,10 @@ impl WasmTapAgent {
     /// Export the agent's public key as a hex string
     #[wasm_bindgen(js_name = exportPublicKey)]
     pub fn export_public_key(&self) -> Result<String, JsValue> {
+        if let Some(key) = &self.web_crypto_key {
+            return Ok(hex::encode(key.public_key()));
+        }
+
         // Get the key manager from the agent
         let key_manager = self.agent.agent_key_manager();
 
@@ -287,6 +402,8 @@ impl WasmTapAgent {
                 security_mode,
                 sender_kid,
                 recipient_kid,
+                content_encryption: None,
+                compression: None,
             };
 
             // Pack the message
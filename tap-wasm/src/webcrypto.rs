@@ -0,0 +1,423 @@
+//! WebCrypto-backed agent keys
+//!
+//! Keys generated here are non-extractable `CryptoKey`s: the browser signs
+//! with them through `SubtleCrypto.sign` and never exposes the private key to
+//! JavaScript or WASM memory. The key pair handles are structured-cloneable,
+//! so they are persisted as is in IndexedDB and survive page reloads.
+//!
+//! Ed25519 and P-256 (ECDSA) keys are supported, where the browser supports
+//! them. A WebCrypto key can only sign; messages packed by an agent using one
+//! are signed, not encrypted.
+
+use async_trait::async_trait;
+use base64::Engine;
+use js_sys::{Array, Object, Promise, Reflect, Uint8Array};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tap_agent::agent_key::{AgentKey, JwsAlgorithm, SigningKey};
+use tap_agent::error::{Error, Result};
+use tap_agent::message::{Jws, JwsProtected, JwsSignature, DIDCOMM_SIGNED};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    CryptoKey, IdbDatabase, IdbFactory, IdbOpenDbRequest, IdbRequest, IdbTransactionMode,
+    SubtleCrypto,
+};
+
+/// IndexedDB database holding the key handles
+const KEY_DATABASE: &str = "tap-wasm-keys";
+/// Object store of the key handles, keyed by key name
+const KEY_STORE: &str = "keys";
+
+/// Wrapper marking JS handles and futures as `Send` and `Sync`
+///
+/// The agent traits require thread-safe keys, but JS values are bound to
+/// the thread that created them. A WASM module runs on a single thread, so
+/// the values never cross threads.
+struct SingleThreaded<T>(T);
+
+unsafe impl<T> Send for SingleThreaded<T> {}
+unsafe impl<T> Sync for SingleThreaded<T> {}
+
+impl<F: Future> Future for SingleThreaded<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: the inner future is never moved out of the wrapper
+        unsafe { self.map_unchecked_mut(|wrapper| &mut wrapper.0) }.poll(cx)
+    }
+}
+
+/// Signature algorithm of a WebCrypto key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebCryptoAlgorithm {
+    Ed25519,
+    P256,
+}
+
+impl WebCryptoAlgorithm {
+    /// Parse a key type as accepted by `generatePrivateKey`
+    pub fn from_key_type(key_type: &str) -> Option<Self> {
+        match key_type {
+            "Ed25519" => Some(Self::Ed25519),
+            "P256" => Some(Self::P256),
+            _ => None,
+        }
+    }
+
+    /// Key type as accepted by `generatePrivateKey`
+    pub fn key_type(&self) -> &'static str {
+        match self {
+            Self::Ed25519 => "Ed25519",
+            Self::P256 => "P256",
+        }
+    }
+
+    /// Parameters of `generateKey`
+    fn generate_params(&self) -> Result<Object> {
+        let params = Object::new();
+        match self {
+            Self::Ed25519 => set(&params, "name", &"Ed25519".into())?,
+            Self::P256 => {
+                set(&params, "name", &"ECDSA".into())?;
+                set(&params, "namedCurve", &"P-256".into())?;
+            }
+        }
+        Ok(params)
+    }
+
+    /// Parameters of `sign`
+    fn sign_params(&self) -> Result<Object> {
+        let params = Object::new();
+        match self {
+            Self::Ed25519 => set(&params, "name", &"Ed25519".into())?,
+            Self::P256 => {
+                set(&params, "name", &"ECDSA".into())?;
+                set(&params, "hash", &"SHA-256".into())?;
+            }
+        }
+        Ok(params)
+    }
+
+    /// Multicodec prefix of the public key in a did:key
+    fn multicodec_prefix(&self) -> [u8; 2] {
+        match self {
+            Self::Ed25519 => [0xed, 0x01],
+            Self::P256 => [0x12, 0x00],
+        }
+    }
+}
+
+/// A non-extractable signing key held by the browser
+#[derive(Debug)]
+pub struct WebCryptoKey {
+    did: String,
+    kid: String,
+    algorithm: WebCryptoAlgorithm,
+    /// Raw public key: 32 bytes for Ed25519, the uncompressed point for P-256
+    public_key: Vec<u8>,
+    private_key: SingleThreaded<CryptoKey>,
+}
+
+impl std::fmt::Debug for SingleThreaded<CryptoKey> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CryptoKey")
+    }
+}
+
+impl WebCryptoKey {
+    fn new(algorithm: WebCryptoAlgorithm, private_key: CryptoKey, public_key: Vec<u8>) -> Self {
+        let mut prefixed_key = algorithm.multicodec_prefix().to_vec();
+        prefixed_key.extend_from_slice(&public_key);
+        let multibase = multibase::encode(multibase::Base::Base58Btc, &prefixed_key);
+        let did = format!("did:key:{}", multibase);
+        Self {
+            kid: format!("{}#{}", did, multibase),
+            did,
+            algorithm,
+            public_key,
+            private_key: SingleThreaded(private_key),
+        }
+    }
+
+    /// Generate a non-extractable key pair
+    ///
+    /// Fails if the browser does not support the algorithm.
+    pub async fn generate(algorithm: WebCryptoAlgorithm) -> Result<Self> {
+        let subtle = subtle_crypto()?;
+        let usages = Array::of1(&"sign".into());
+        let pair = await_promise(
+            subtle
+                .generate_key_with_object(&algorithm.generate_params()?, false, &usages)
+                .map_err(js_error)?,
+        )
+        .await?;
+        let private_key: CryptoKey = get(&pair, "privateKey")?.unchecked_into();
+        let public_key: CryptoKey = get(&pair, "publicKey")?.unchecked_into();
+
+        // Public keys are always extractable
+        let raw = await_promise(subtle.export_key("raw", &public_key).map_err(js_error)?).await?;
+        Ok(Self::new(
+            algorithm,
+            private_key,
+            Uint8Array::new(&raw).to_vec(),
+        ))
+    }
+
+    /// Load the key stored under `name`, if any
+    pub async fn load(name: &str) -> Result<Option<Self>> {
+        let db = open_key_database().await?;
+        let store = db
+            .transaction_with_str_and_mode(KEY_STORE, IdbTransactionMode::Readonly)
+            .and_then(|tx| tx.object_store(KEY_STORE))
+            .map_err(js_error)?;
+        let record = await_request(&store.get(&name.into()).map_err(js_error)?).await?;
+        db.close();
+        if record.is_undefined() {
+            return Ok(None);
+        }
+
+        let algorithm = get(&record, "algorithm")?
+            .as_string()
+            .and_then(|key_type| WebCryptoAlgorithm::from_key_type(&key_type))
+            .ok_or_else(|| Error::Storage(format!("Invalid algorithm of stored key {}", name)))?;
+        let private_key: CryptoKey = get(&record, "privateKey")?.unchecked_into();
+        let public_key = Uint8Array::new(&get(&record, "publicKey")?).to_vec();
+        Ok(Some(Self::new(algorithm, private_key, public_key)))
+    }
+
+    /// Store the key handle under `name`, replacing any key stored there
+    pub async fn save(&self, name: &str) -> Result<()> {
+        let record = Object::new();
+        set(&record, "did", &self.did.as_str().into())?;
+        set(&record, "algorithm", &self.algorithm.key_type().into())?;
+        set(&record, "privateKey", &self.private_key.0)?;
+        set(
+            &record,
+            "publicKey",
+            &Uint8Array::from(self.public_key.as_slice()),
+        )?;
+
+        let db = open_key_database().await?;
+        let store = db
+            .transaction_with_str_and_mode(KEY_STORE, IdbTransactionMode::Readwrite)
+            .and_then(|tx| tx.object_store(KEY_STORE))
+            .map_err(js_error)?;
+        await_request(
+            &store
+                .put_with_key(&record, &name.into())
+                .map_err(js_error)?,
+        )
+        .await?;
+        db.close();
+        Ok(())
+    }
+
+    /// Delete the key stored under `name`
+    pub async fn delete(name: &str) -> Result<()> {
+        let db = open_key_database().await?;
+        let store = db
+            .transaction_with_str_and_mode(KEY_STORE, IdbTransactionMode::Readwrite)
+            .and_then(|tx| tx.object_store(KEY_STORE))
+            .map_err(js_error)?;
+        await_request(&store.delete(&name.into()).map_err(js_error)?).await?;
+        db.close();
+        Ok(())
+    }
+
+    /// Raw public key bytes
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    /// Signature algorithm of the key
+    pub fn algorithm(&self) -> WebCryptoAlgorithm {
+        self.algorithm
+    }
+}
+
+#[async_trait]
+impl AgentKey for WebCryptoKey {
+    fn key_id(&self) -> &str {
+        &self.kid
+    }
+
+    fn public_key_jwk(&self) -> Result<serde_json::Value> {
+        let b64 = |bytes: &[u8]| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+        Ok(match self.algorithm {
+            WebCryptoAlgorithm::Ed25519 => serde_json::json!({
+                "kty": "OKP",
+                "crv": "Ed25519",
+                "x": b64(&self.public_key),
+                "kid": self.kid,
+            }),
+            WebCryptoAlgorithm::P256 => {
+                if self.public_key.len() != 65 {
+                    return Err(Error::Cryptography(
+                        "Invalid P-256 public key length".to_string(),
+                    ));
+                }
+                serde_json::json!({
+                    "kty": "EC",
+                    "crv": "P-256",
+                    "x": b64(&self.public_key[1..33]),
+                    "y": b64(&self.public_key[33..]),
+                    "kid": self.kid,
+                })
+            }
+        })
+    }
+
+    fn did(&self) -> &str {
+        &self.did
+    }
+
+    fn key_type(&self) -> &str {
+        match self.algorithm {
+            WebCryptoAlgorithm::Ed25519 => "Ed25519",
+            WebCryptoAlgorithm::P256 => "P-256",
+        }
+    }
+}
+
+#[async_trait]
+impl SigningKey for WebCryptoKey {
+    async fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        SingleThreaded(async {
+            let subtle = subtle_crypto()?;
+            let promise = subtle
+                .sign_with_object_and_u8_array(
+                    &self.algorithm.sign_params()?,
+                    &self.private_key.0,
+                    data,
+                )
+                .map_err(js_error)?;
+            // ECDSA signatures come as r || s, as JWS expects them
+            let signature = await_promise(promise).await?;
+            Ok(Uint8Array::new(&signature).to_vec())
+        })
+        .await
+    }
+
+    fn recommended_jws_alg(&self) -> JwsAlgorithm {
+        match self.algorithm {
+            WebCryptoAlgorithm::Ed25519 => JwsAlgorithm::EdDSA,
+            WebCryptoAlgorithm::P256 => JwsAlgorithm::ES256,
+        }
+    }
+
+    async fn create_jws(
+        &self,
+        payload: &[u8],
+        protected_header: Option<JwsProtected>,
+    ) -> Result<Jws> {
+        let b64 = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let mut protected = protected_header.unwrap_or_else(|| JwsProtected {
+            typ: DIDCOMM_SIGNED.to_string(),
+            alg: String::new(),
+            kid: String::new(),
+            zip: None,
+        });
+        protected.alg = self.recommended_jws_alg().as_str().to_string();
+        if protected.kid.is_empty() {
+            protected.kid = self.kid.clone();
+        }
+
+        let protected_json = serde_json::to_string(&protected).map_err(|e| {
+            Error::Serialization(format!("Failed to serialize protected header: {}", e))
+        })?;
+        let protected_b64 = b64.encode(protected_json);
+        let payload_b64 = b64.encode(payload);
+        let signature = self
+            .sign(format!("{}.{}", protected_b64, payload_b64).as_bytes())
+            .await?;
+
+        Ok(Jws {
+            payload: payload_b64,
+            signatures: vec![JwsSignature {
+                protected: protected_b64,
+                signature: b64.encode(signature),
+            }],
+        })
+    }
+}
+
+fn js_error(value: JsValue) -> Error {
+    let message = value
+        .dyn_ref::<js_sys::Error>()
+        .map(|e| String::from(e.message()))
+        .or_else(|| value.as_string())
+        .unwrap_or_else(|| format!("{:?}", value));
+    Error::Cryptography(format!("WebCrypto error: {}", message))
+}
+
+fn get(target: &JsValue, key: &str) -> Result<JsValue> {
+    Reflect::get(target, &key.into()).map_err(js_error)
+}
+
+fn set(target: &Object, key: &str, value: &JsValue) -> Result<()> {
+    Reflect::set(target, &key.into(), value)
+        .map(|_| ())
+        .map_err(js_error)
+}
+
+/// `crypto.subtle` of the window or worker
+fn subtle_crypto() -> Result<SubtleCrypto> {
+    let crypto = get(&js_sys::global(), "crypto")?;
+    if crypto.is_undefined() {
+        return Err(Error::Cryptography(
+            "WebCrypto is not available".to_string(),
+        ));
+    }
+    Ok(crypto.unchecked_into::<web_sys::Crypto>().subtle())
+}
+
+async fn await_promise(promise: Promise) -> Result<JsValue> {
+    JsFuture::from(promise).await.map_err(js_error)
+}
+
+/// Wait for an IndexedDB request to succeed
+async fn await_request(request: &IdbRequest) -> Result<JsValue> {
+    let done = Promise::new(&mut |resolve, reject| {
+        let on_success = Closure::once_into_js(move |event: JsValue| {
+            let result = get(&event, "target")
+                .and_then(|target| get(&target, "result"))
+                .unwrap_or(JsValue::UNDEFINED);
+            let _ = resolve.call1(&JsValue::NULL, &result);
+        });
+        let on_error = Closure::once_into_js(move |event: JsValue| {
+            let error = get(&event, "target")
+                .and_then(|target| get(&target, "error"))
+                .unwrap_or(event);
+            let _ = reject.call1(&JsValue::NULL, &error);
+        });
+        request.set_onsuccess(Some(on_success.unchecked_ref()));
+        request.set_onerror(Some(on_error.unchecked_ref()));
+    });
+    await_promise(done)
+        .await
+        .map_err(|e| Error::Storage(format!("IndexedDB request failed: {}", e)))
+}
+
+/// Open the key database, creating its object store on first use
+async fn open_key_database() -> Result<IdbDatabase> {
+    let factory = get(&js_sys::global(), "indexedDB")?;
+    if factory.is_undefined() || factory.is_null() {
+        return Err(Error::Storage("IndexedDB is not available".to_string()));
+    }
+    let request: IdbOpenDbRequest = factory
+        .unchecked_into::<IdbFactory>()
+        .open_with_u32(KEY_DATABASE, 1)
+        .map_err(js_error)?;
+    let on_upgrade = Closure::once_into_js(move |event: JsValue| {
+        if let Ok(db) = get(&event, "target").and_then(|target| get(&target, "result")) {
+            let _ = db
+                .unchecked_into::<IdbDatabase>()
+                .create_object_store(KEY_STORE);
+        }
+    });
+    request.set_onupgradeneeded(Some(on_upgrade.unchecked_ref()));
+    Ok(await_request(&request).await?.unchecked_into())
+}
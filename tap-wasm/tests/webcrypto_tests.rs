@@ -0,0 +1,186 @@
+#![allow(dead_code)] // wasm_bindgen_test functions are not detected as tests by clippy
+
+use js_sys::{Function, Object, Reflect, JSON};
+use tap_wasm::WasmTapAgent;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// Options of `withWebCrypto`
+fn web_crypto_options(key_type: &str, key_name: &str) -> JsValue {
+    JSON::parse(&serde_json::json!({ "keyType": key_type, "keyName": key_name }).to_string())
+        .unwrap()
+}
+
+async fn web_crypto_agent(key_type: &str, key_name: &str) -> WasmTapAgent {
+    WasmTapAgent::with_web_crypto(web_crypto_options(key_type, key_name))
+        .await
+        .expect("Failed to create WebCrypto agent")
+}
+
+/// Sign a Transfer from `sender` to `recipient`, returning the JWS
+async fn sign_transfer(sender: &WasmTapAgent, recipient: &str) -> String {
+    let message = JSON::parse(
+        &serde_json::json!({
+            "id": "webcrypto-transfer",
+            "type": "https://tap.rsvp/schema/1.0#Transfer",
+            "from": sender.get_did(),
+            "to": [recipient],
+            "body": {
+                "amount": "100.0",
+                "asset": "eip155:1/erc20:0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+                "originator": { "@id": sender.get_did() },
+                "beneficiary": { "@id": recipient },
+                "agents": []
+            }
+        })
+        .to_string(),
+    )
+    .unwrap();
+    let packed = JsFuture::from(sender.pack_message(message))
+        .await
+        .expect("Packing should succeed");
+    Reflect::get(&packed, &JsValue::from_str("message"))
+        .unwrap()
+        .as_string()
+        .expect("Should have message string")
+}
+
+/// Unpack a signed message, verifying its signature against the sender's did:key
+async fn verify(recipient: &WasmTapAgent, jws: &str) -> Result<JsValue, JsValue> {
+    JsFuture::from(recipient.unpack_message(jws, None)).await
+}
+
+/// Replace the signature of a general JWS with another valid-looking one
+fn tamper(jws: &str) -> String {
+    let mut jws: serde_json::Value = serde_json::from_str(jws).unwrap();
+    let signature = jws["signatures"][0]["signature"].as_str().unwrap();
+    let flipped = if signature.starts_with('A') { "B" } else { "A" };
+    jws["signatures"][0]["signature"] = format!("{}{}", flipped, &signature[1..]).into();
+    jws.to_string()
+}
+
+/// Test that messages signed with WebCrypto keys verify, and tampered ones do not
+#[wasm_bindgen_test]
+async fn test_web_crypto_sign_verify_round_trip() {
+    let recipient = WasmTapAgent::new(Object::new().into()).unwrap();
+
+    // Browsers without Ed25519 in SubtleCrypto fall back to a software key,
+    // which must verify all the same
+    for key_type in ["P256", "Ed25519"] {
+        let key_name = format!("webcrypto-test-round-trip-{}", key_type);
+        WasmTapAgent::delete_web_crypto_key(key_name.clone())
+            .await
+            .unwrap();
+        let sender = web_crypto_agent(key_type, &key_name).await;
+        if key_type == "P256" {
+            assert!(sender.uses_web_crypto(), "P-256 is supported by WebCrypto");
+        }
+
+        let jws = sign_transfer(&sender, &recipient.get_did()).await;
+        let unpacked = verify(&recipient, &jws)
+            .await
+            .expect("Signature of the WebCrypto key should verify");
+        assert_eq!(
+            Reflect::get(&unpacked, &JsValue::from_str("from"))
+                .unwrap()
+                .as_string(),
+            Some(sender.get_did())
+        );
+        assert!(
+            verify(&recipient, &tamper(&jws)).await.is_err(),
+            "Tampered signature should not verify"
+        );
+
+        WasmTapAgent::delete_web_crypto_key(key_name).await.unwrap();
+    }
+}
+
+/// Test that a key handle stored in IndexedDB is reused by later agents
+#[wasm_bindgen_test]
+async fn test_web_crypto_key_survives_reload() {
+    let key_name = "webcrypto-test-reload";
+    WasmTapAgent::delete_web_crypto_key(key_name.to_string())
+        .await
+        .unwrap();
+    let recipient = WasmTapAgent::new(Object::new().into()).unwrap();
+
+    let first = web_crypto_agent("P256", key_name).await;
+    assert!(first.uses_web_crypto());
+    assert!(
+        first.export_private_key().is_err(),
+        "WebCrypto keys are not extractable"
+    );
+
+    // A new agent opens the database again and loads the same key handle,
+    // which still signs
+    let reloaded = web_crypto_agent("P256", key_name).await;
+    assert!(reloaded.uses_web_crypto());
+    assert_eq!(reloaded.get_did(), first.get_did());
+    let jws = sign_transfer(&reloaded, &recipient.get_did()).await;
+    assert!(verify(&recipient, &jws).await.is_ok());
+
+    // The stored key has another type than the one asked for
+    assert!(
+        WasmTapAgent::with_web_crypto(web_crypto_options("Ed25519", key_name))
+            .await
+            .is_err()
+    );
+
+    // Once deleted, a new key is generated
+    WasmTapAgent::delete_web_crypto_key(key_name.to_string())
+        .await
+        .unwrap();
+    let regenerated = web_crypto_agent("P256", key_name).await;
+    assert_ne!(regenerated.get_did(), first.get_did());
+    WasmTapAgent::delete_web_crypto_key(key_name.to_string())
+        .await
+        .unwrap();
+}
+
+/// Test the software key used when SubtleCrypto does not support Ed25519
+#[wasm_bindgen_test]
+async fn test_software_fallback_without_ed25519() {
+    let key_name = "webcrypto-test-fallback";
+    WasmTapAgent::delete_web_crypto_key(key_name.to_string())
+        .await
+        .unwrap();
+    let recipient = WasmTapAgent::new(Object::new().into()).unwrap();
+
+    // Make SubtleCrypto refuse Ed25519 the way browsers without it do
+    let crypto = Reflect::get(&js_sys::global(), &JsValue::from_str("crypto")).unwrap();
+    let subtle = Reflect::get(&crypto, &JsValue::from_str("subtle")).unwrap();
+    let generate_key = Function::new_with_args(
+        "algorithm, extractable, usages",
+        "if ((algorithm.name || algorithm) === 'Ed25519') { \
+            return Promise.reject(new DOMException('Unrecognized name.', 'NotSupportedError')); \
+        } \
+        return SubtleCrypto.prototype.generateKey.call(this, algorithm, extractable, usages);",
+    );
+    Reflect::set(&subtle, &JsValue::from_str("generateKey"), &generate_key).unwrap();
+    let fallback = WasmTapAgent::with_web_crypto(web_crypto_options("Ed25519", key_name)).await;
+    let again = WasmTapAgent::with_web_crypto(web_crypto_options("Ed25519", key_name)).await;
+    Reflect::delete_property(
+        subtle.unchecked_ref::<Object>(),
+        &JsValue::from_str("generateKey"),
+    )
+    .unwrap();
+
+    let fallback = fallback.expect("Should fall back to a software key");
+    assert!(!fallback.uses_web_crypto());
+    assert!(fallback.get_did().starts_with("did:key:z6Mk"));
+    assert_eq!(
+        fallback.export_private_key().unwrap().len(),
+        64,
+        "Software Ed25519 keys are exportable"
+    );
+
+    // Software keys are not persisted, so each agent gets its own
+    let again = again.expect("Should fall back to a software key");
+    assert_ne!(again.get_did(), fallback.get_did());
+
+    let jws = sign_transfer(&fallback, &recipient.get_did()).await;
+    assert!(verify(&recipient, &jws).await.is_ok());
+}